@@ -0,0 +1,527 @@
+//! HTTP authentication of requests to protected endpoints
+//!
+//! An [`AuthScheme`] adds the credentials a host asks for to every request
+//! sent to it: Basic credentials, a Bearer token, or an AWS Signature V4
+//! signature for S3-backed or API Gateway endpoints. Credentials are never
+//! written into the scheme itself but referenced by name and looked up from
+//! the [`SecretProvider`] installed with [`set_secret_provider`]; the default
+//! [`EnvSecrets`] reads `env:NAME` from the environment and `file:PATH` from a
+//! file.
+//!
+//! Bearer tokens can be static or refreshed: by running a command that
+//! prints one (e.g. `gcloud auth print-access-token`), or by a
+//! [`TokenHook`] registered with [`register_token_hook`]. Refreshed tokens are
+//! shared by every request of the process until they expire or the server
+//! answers 401.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Write},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// How long a refreshed Bearer token is used unless the scheme sets it
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(50 * 60);
+
+static SECRETS: Lazy<RwLock<Arc<dyn SecretProvider>>> =
+    Lazy::new(|| RwLock::new(Arc::new(EnvSecrets)));
+static TOKEN_HOOKS: Lazy<RwLock<HashMap<String, Arc<dyn TokenHook>>>> = Lazy::new(Default::default);
+static TOKENS: Lazy<Mutex<HashMap<AuthScheme, (String, Instant)>>> = Lazy::new(Default::default);
+
+/// Where the secrets an [`AuthScheme`] names come from
+pub trait SecretProvider: Send + Sync {
+    /// The secret `reference` names
+    fn secret(&self, reference: &str) -> Result<String>;
+}
+
+/// Secrets from the environment (`env:NAME`) and files (`file:PATH`); other
+/// references are taken as the secret itself
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvSecrets;
+
+impl SecretProvider for EnvSecrets {
+    fn secret(&self, reference: &str) -> Result<String> {
+        if let Some(name) = reference.strip_prefix("env:") {
+            return std::env::var(name).with_context(|| format!("{} is not set", name));
+        }
+        if let Some(path) = reference.strip_prefix("file:") {
+            let secret = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path))?;
+            return Ok(secret.trim_end().to_string());
+        }
+        Ok(reference.to_string())
+    }
+}
+
+/// Look secrets up in `provider` from now on, e.g. a secrets manager
+pub fn set_secret_provider(provider: Arc<dyn SecretProvider>) {
+    *SECRETS.write().unwrap() = provider;
+}
+
+fn secret(reference: &str) -> Result<String> {
+    let provider = SECRETS.read().unwrap().clone();
+    provider.secret(reference)
+}
+
+/// Fetches a fresh Bearer token, e.g. through an OAuth client credentials grant
+pub trait TokenHook: Send + Sync {
+    fn token(&self) -> Pin<Box<dyn Future<Output = Result<String>> + Send + '_>>;
+}
+
+/// Make `hook` available to Bearer schemes as `refresh_hook = "<name>"`
+pub fn register_token_hook(name: &str, hook: Arc<dyn TokenHook>) {
+    TOKEN_HOOKS.write().unwrap().insert(name.to_string(), hook);
+}
+
+/// Credentials added to every request sent to a host
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum AuthScheme {
+    Basic {
+        username: String,
+        /// Secret reference of the password
+        password: String,
+    },
+    Bearer {
+        /// Secret reference of a static token
+        #[serde(default)]
+        token: Option<String>,
+        /// Command printing a fresh token, run without a shell
+        #[serde(default)]
+        refresh_command: Option<Vec<String>>,
+        /// Name of a registered [`TokenHook`]
+        #[serde(default)]
+        refresh_hook: Option<String>,
+        /// Seconds a refreshed token is used, 50 minutes by default
+        #[serde(default)]
+        refresh_secs: Option<u64>,
+    },
+    /// AWS Signature V4
+    Sigv4 {
+        /// Secret reference of the access key ID
+        access_key_id: String,
+        /// Secret reference of the secret access key
+        secret_access_key: String,
+        /// Secret reference of the session token of temporary credentials
+        #[serde(default)]
+        session_token: Option<String>,
+        region: String,
+        /// e.g. `s3` or `execute-api`
+        service: String,
+    },
+}
+
+impl fmt::Debug for AuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // References may be secrets themselves
+        match self {
+            AuthScheme::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            AuthScheme::Bearer { .. } => f.debug_struct("Bearer").finish_non_exhaustive(),
+            AuthScheme::Sigv4 {
+                region, service, ..
+            } => f
+                .debug_struct("Sigv4")
+                .field("region", region)
+                .field("service", service)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl AuthScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthScheme::Basic { .. } => "basic",
+            AuthScheme::Bearer { .. } => "bearer",
+            AuthScheme::Sigv4 { .. } => "sigv4",
+        }
+    }
+
+    /// Check that the scheme is complete, without looking its secrets up
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            AuthScheme::Basic { .. } => {}
+            AuthScheme::Bearer {
+                token,
+                refresh_command,
+                refresh_hook,
+                ..
+            } => match (token, refresh_command, refresh_hook) {
+                (_, Some(_), Some(_)) => {
+                    bail!("bearer auth takes a refresh_command or a refresh_hook, not both")
+                }
+                (_, Some(command), _) if command.is_empty() => {
+                    bail!("bearer auth: refresh_command is empty")
+                }
+                (None, None, None) => {
+                    bail!("bearer auth needs a token, a refresh_command or a refresh_hook")
+                }
+                _ => {}
+            },
+            AuthScheme::Sigv4 {
+                region, service, ..
+            } => {
+                if region.is_empty() || service.is_empty() {
+                    bail!("sigv4 auth needs a region and a service");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a rejected token can be replaced by a fresh one
+    pub fn refreshable(&self) -> bool {
+        matches!(
+            self,
+            AuthScheme::Bearer { refresh_command, refresh_hook, .. }
+                if refresh_command.is_some() || refresh_hook.is_some()
+        )
+    }
+
+    /// Drop the refreshed token, e.g. after the server rejected it
+    pub fn invalidate(&self) {
+        TOKENS.lock().unwrap().remove(self);
+    }
+
+    /// Headers authenticating a `method` request to `url` with `payload`
+    pub async fn headers(
+        &self,
+        method: &str,
+        url: &Url,
+        payload: &[u8],
+    ) -> Result<Vec<(String, String)>> {
+        match self {
+            AuthScheme::Basic { username, password } => {
+                let credentials = format!("{}:{}", username, secret(password)?);
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                Ok(vec![(
+                    "authorization".to_string(),
+                    format!("Basic {}", encoded),
+                )])
+            }
+            AuthScheme::Bearer { .. } => Ok(vec![(
+                "authorization".to_string(),
+                format!("Bearer {}", self.bearer_token().await?),
+            )]),
+            AuthScheme::Sigv4 {
+                access_key_id,
+                secret_access_key,
+                session_token,
+                region,
+                service,
+            } => {
+                let credentials = SigningCredentials {
+                    access_key_id: secret(access_key_id)?,
+                    secret_access_key: secret(secret_access_key)?,
+                    region,
+                    service,
+                };
+                let payload_hash = format!("{:x}", Sha256::digest(payload));
+                let mut headers = BTreeMap::new();
+                headers.insert("host".to_string(), host(url));
+                // S3 wants the payload hash in a header, other services don't mind it
+                headers.insert("x-amz-content-sha256".to_string(), payload_hash.clone());
+                if let Some(session_token) = session_token {
+                    headers.insert("x-amz-security-token".to_string(), secret(session_token)?);
+                }
+                let authorization = sign(
+                    &credentials,
+                    method,
+                    url,
+                    &mut headers,
+                    &payload_hash,
+                    Utc::now(),
+                );
+                headers.remove("host");
+                headers.insert("authorization".to_string(), authorization);
+                Ok(headers.into_iter().collect())
+            }
+        }
+    }
+
+    async fn bearer_token(&self) -> Result<String> {
+        let AuthScheme::Bearer {
+            token,
+            refresh_command,
+            refresh_hook,
+            refresh_secs,
+        } = self
+        else {
+            unreachable!("only called for bearer schemes")
+        };
+        if !self.refreshable() {
+            let token = token.as_deref().context("bearer auth without a token")?;
+            return secret(token);
+        }
+
+        let ttl = refresh_secs.map_or(DEFAULT_TOKEN_TTL, Duration::from_secs);
+        let cached = TOKENS
+            .lock()
+            .unwrap()
+            .get(self)
+            .filter(|(_, refreshed_at)| refreshed_at.elapsed() < ttl)
+            .map(|(token, _)| token.clone());
+        if let Some(token) = cached {
+            return Ok(token);
+        }
+        let fresh = match (refresh_command, refresh_hook) {
+            (Some(command), _) => run_refresh_command(command).await?,
+            (None, Some(name)) => {
+                let hook = TOKEN_HOOKS
+                    .read()
+                    .unwrap()
+                    .get(name)
+                    .cloned()
+                    .with_context(|| format!("No token hook named '{}' is registered", name))?;
+                hook.token().await?
+            }
+            (None, None) => unreachable!("refreshable schemes have a command or a hook"),
+        };
+        TOKENS
+            .lock()
+            .unwrap()
+            .insert(self.clone(), (fresh.clone(), Instant::now()));
+        Ok(fresh)
+    }
+}
+
+async fn run_refresh_command(command: &[String]) -> Result<String> {
+    let (program, args) = command.split_first().context("empty refresh_command")?;
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!("{} exited with {}", program, output.status);
+    }
+    let token = String::from_utf8(output.stdout)
+        .with_context(|| format!("{} printed a token that isn't UTF-8", program))?;
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("{} printed no token", program);
+    }
+    Ok(token.to_string())
+}
+
+struct SigningCredentials<'a> {
+    access_key_id: String,
+    secret_access_key: String,
+    region: &'a str,
+    service: &'a str,
+}
+
+/// `Host` header value of `url`, with the port unless it is the default
+fn host(url: &Url) -> String {
+    match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    }
+}
+
+/// `Authorization` header signing `headers` (lowercase names, including
+/// `host`), to which `x-amz-date` is added
+fn sign(
+    credentials: &SigningCredentials,
+    method: &str,
+    url: &Url,
+    headers: &mut BTreeMap<String, String>,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    headers.insert("x-amz-date".to_string(), amz_date.clone());
+
+    // S3 paths are encoded once, every other service's twice
+    let path = match credentials.service {
+        "s3" => url.path().to_string(),
+        _ => uri_encode(url.path(), false),
+    };
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name, true), uri_encode(&value, true)))
+        .collect();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut canonical_headers = String::new();
+    for (name, value) in headers.iter() {
+        let _ = writeln!(canonical_headers, "{}:{}", name, value.trim());
+    }
+    let signed_headers = headers.keys().cloned().collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+
+    let scope = format!(
+        "{}/{}/{}/aws4_request",
+        date, credentials.region, credentials.service
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        amz_date,
+        scope,
+        Sha256::digest(canonical_request.as_bytes())
+    );
+    let key = [credentials.region, credentials.service, "aws4_request"]
+        .iter()
+        .fold(
+            hmac_sha256(
+                format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+                date.as_bytes(),
+            ),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+    let signature = format!("{:x}", hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> sha2::digest::Output<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes()
+}
+
+/// Percent-encoding of everything but unreserved characters (and `/` unless
+/// `encode_slash`), as Signature V4 wants it
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigv4_matches_aws_example() {
+        // Example from the AWS Signature V4 documentation
+        let credentials = SigningCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            region: "us-east-1",
+            service: "iam",
+        };
+        let url =
+            Url::parse("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08").unwrap();
+        let mut headers = BTreeMap::from([
+            (
+                "content-type".to_string(),
+                "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+            ),
+            ("host".to_string(), host(&url)),
+        ]);
+        let now = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let payload_hash = format!("{:x}", Sha256::digest(b""));
+
+        let authorization = sign(&credentials, "GET", &url, &mut headers, &payload_hash, now);
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+        assert_eq!(headers["x-amz-date"], "20150830T123600Z");
+    }
+
+    #[tokio::test]
+    async fn test_basic_and_bearer_headers() {
+        std::env::set_var("SWOOP_TEST_AUTH_PASSWORD", "open sesame");
+        let url = Url::parse("https://partner.example/api").unwrap();
+        let basic = AuthScheme::Basic {
+            username: "Aladdin".to_string(),
+            password: "env:SWOOP_TEST_AUTH_PASSWORD".to_string(),
+        };
+        assert_eq!(
+            basic.headers("GET", &url, b"").await.unwrap(),
+            vec![(
+                "authorization".to_string(),
+                "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_string()
+            )]
+        );
+        assert!(!format!("{:?}", basic).contains("SWOOP_TEST_AUTH_PASSWORD"));
+
+        let bearer: AuthScheme = serde_json::from_str(
+            r#"{"scheme": "bearer", "refresh_command": ["echo", "fresh-token"]}"#,
+        )
+        .unwrap();
+        bearer.validate().unwrap();
+        assert!(bearer.refreshable());
+        let headers = bearer.headers("GET", &url, b"").await.unwrap();
+        assert_eq!(headers[0].1, "Bearer fresh-token");
+        bearer.invalidate();
+
+        let incomplete: AuthScheme = serde_json::from_str(r#"{"scheme": "bearer"}"#).unwrap();
+        assert!(incomplete.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_hooks_refresh_bearer_tokens() {
+        struct Counter(Mutex<u32>);
+        impl TokenHook for Counter {
+            fn token(&self) -> Pin<Box<dyn Future<Output = Result<String>> + Send + '_>> {
+                Box::pin(async move {
+                    let mut count = self.0.lock().unwrap();
+                    *count += 1;
+                    Ok(format!("token-{}", *count))
+                })
+            }
+        }
+        register_token_hook("counter", Arc::new(Counter(Mutex::new(0))));
+        let bearer = AuthScheme::Bearer {
+            token: None,
+            refresh_command: None,
+            refresh_hook: Some("counter".to_string()),
+            refresh_secs: None,
+        };
+        let url = Url::parse("https://api.example/").unwrap();
+
+        let token = |headers: Vec<(String, String)>| headers[0].1.clone();
+        assert_eq!(
+            token(bearer.headers("GET", &url, b"").await.unwrap()),
+            "Bearer token-1"
+        );
+        assert_eq!(
+            token(bearer.headers("GET", &url, b"").await.unwrap()),
+            "Bearer token-1"
+        );
+        bearer.invalidate();
+        assert_eq!(
+            token(bearer.headers("GET", &url, b"").await.unwrap()),
+            "Bearer token-2"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Record-and-replay HTTP cassettes
+//!
+//! A [`Cassette`] is a JSON file of recorded request/response pairs. Tests
+//! fetch through it instead of [`crate::fetch_url`]: in replay mode responses
+//! come from the file and nothing touches the network; in record mode requests
+//! go out for real and the responses are saved for later runs.
+//!
+//! The mode comes from the `SWOOP_CASSETTE_MODE` environment variable
+//! (`replay`, `record` or `new`) and defaults to `replay`, so CI never hits
+//! live sites. To refresh a cassette, run the test once with
+//! `SWOOP_CASSETTE_MODE=record`.
+
+use crate::{security::UrlValidator, CLIENT};
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Environment variable selecting the [`CassetteMode`]
+pub const MODE_ENV: &str = "SWOOP_CASSETTE_MODE";
+
+/// Response headers never written to a cassette
+const REDACTED_HEADERS: &[&str] = &[
+    "set-cookie",
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Serve recorded responses only; unknown requests fail
+    Replay,
+    /// Discard the existing recording and fetch everything live
+    Record,
+    /// Replay known requests and record new ones
+    NewEpisodes,
+}
+
+impl CassetteMode {
+    /// Read the mode from `SWOOP_CASSETTE_MODE`, defaulting to replay
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(MODE_ENV).as_deref() {
+            Err(_) | Ok("") | Ok("replay") => Ok(Self::Replay),
+            Ok("record") => Ok(Self::Record),
+            Ok("new") => Ok(Self::NewEpisodes),
+            Ok(other) => bail!(
+                "Invalid {} '{}', expected replay, record or new",
+                MODE_ENV,
+                other
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// Final URL after redirects
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    /// Decoded body; non-UTF-8 bytes are replaced
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// Recorded HTTP interactions backed by a JSON file
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    validator: UrlValidator,
+    interactions: Mutex<Vec<Interaction>>,
+    /// How many times each request has been replayed, so repeated requests
+    /// get their recorded responses in order
+    replayed: Mutex<HashMap<RecordedRequest, usize>>,
+}
+
+impl Cassette {
+    /// Open the cassette at `path` in the mode given by `SWOOP_CASSETTE_MODE`
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::with_mode(path, CassetteMode::from_env()?)
+    }
+
+    pub fn with_mode(path: impl Into<PathBuf>, mode: CassetteMode) -> Result<Self> {
+        let path = path.into();
+        let file = match mode {
+            CassetteMode::Record => CassetteFile::default(),
+            CassetteMode::NewEpisodes if !path.exists() => CassetteFile::default(),
+            CassetteMode::Replay | CassetteMode::NewEpisodes => {
+                let json = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+                serde_json::from_slice(&json)
+                    .with_context(|| format!("Invalid cassette {}", path.display()))?
+            }
+        };
+
+        Ok(Self {
+            path,
+            mode,
+            validator: UrlValidator::default(),
+            interactions: Mutex::new(file.interactions),
+            replayed: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Validator applied to live requests while recording
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions.lock().unwrap().clone()
+    }
+
+    /// Drop-in replacement for [`crate::fetch_url`] that returns the body only
+    pub async fn fetch(&self, url: &str, request_timeout: Duration) -> Result<Bytes> {
+        let response = self.get(url, request_timeout).await?;
+        Ok(Bytes::from(response.body))
+    }
+
+    /// GET `url`, replaying or recording the full response
+    pub async fn get(&self, url: &str, request_timeout: Duration) -> Result<RecordedResponse> {
+        let request = RecordedRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+        };
+
+        if self.mode != CassetteMode::Record {
+            if let Some(response) = self.replay(&request) {
+                return Ok(response);
+            }
+            if self.mode == CassetteMode::Replay {
+                bail!(
+                    "No recorded response for {} {} in {}; re-record with {}=record",
+                    request.method,
+                    request.url,
+                    self.path.display(),
+                    MODE_ENV
+                );
+            }
+        }
+
+        let response = self.record(url, request_timeout).await?;
+        self.interactions.lock().unwrap().push(Interaction {
+            request,
+            response: response.clone(),
+        });
+        Ok(response)
+    }
+
+    fn replay(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let interactions = self.interactions.lock().unwrap();
+        let matching: Vec<&Interaction> = interactions
+            .iter()
+            .filter(|interaction| &interaction.request == request)
+            .collect();
+        let last = matching.last()?;
+
+        let mut replayed = self.replayed.lock().unwrap();
+        let count = replayed.entry(request.clone()).or_default();
+        // Once the recorded responses run out, keep serving the last one
+        let interaction = matching.get(*count).unwrap_or(last);
+        *count += 1;
+        Some(interaction.response.clone())
+    }
+
+    async fn record(&self, url: &str, request_timeout: Duration) -> Result<RecordedResponse> {
+        self.validator.validate_url(url)?;
+
+        let response = CLIENT
+            .client()
+            .get(url)
+            .timeout(request_timeout)
+            .send()
+            .await?;
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !REDACTED_HEADERS.contains(&name.as_str()))
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let body = String::from_utf8_lossy(&response.bytes().await?).into_owned();
+
+        Ok(RecordedResponse {
+            status,
+            url: final_url,
+            headers,
+            body,
+        })
+    }
+
+    /// Write recorded interactions back to the cassette file
+    ///
+    /// Does nothing in replay mode, so tests can call it unconditionally.
+    pub fn save(&self) -> Result<()> {
+        if self.mode == CassetteMode::Replay {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = CassetteFile {
+            interactions: self.interactions(),
+        };
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&file)?)
+            .with_context(|| format!("Failed to write cassette {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{pages, FixtureServer, RATE_LIMITED_RESPONSES};
+
+    #[tokio::test]
+    async fn test_record_then_replay_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassettes/fixture.json");
+        let timeout = Duration::from_secs(5);
+
+        let server = FixtureServer::start().await;
+        let (article, limited) = (server.url("/article"), server.url("/rate-limited"));
+        let recorder = Cassette::with_mode(&path, CassetteMode::Record)
+            .unwrap()
+            .with_validator(server.validator());
+        recorder.fetch(&article, timeout).await.unwrap();
+        for _ in 0..=RATE_LIMITED_RESPONSES {
+            recorder.get(&limited, timeout).await.unwrap();
+        }
+        recorder.save().unwrap();
+        drop(server);
+
+        let replay = Cassette::with_mode(&path, CassetteMode::Replay).unwrap();
+        assert_eq!(
+            replay.fetch(&article, timeout).await.unwrap(),
+            pages::ARTICLE.as_bytes()
+        );
+        let statuses: Vec<u16> = replay_statuses(&replay, &limited, 4).await;
+        assert_eq!(statuses, vec![429, 429, 200, 200]);
+
+        let missing = replay.fetch("https://example.com/unknown", timeout).await;
+        assert!(missing.unwrap_err().to_string().contains(MODE_ENV));
+    }
+
+    async fn replay_statuses(cassette: &Cassette, url: &str, count: usize) -> Vec<u16> {
+        let mut statuses = Vec::new();
+        for _ in 0..count {
+            let response = cassette.get(url, Duration::from_secs(5)).await.unwrap();
+            statuses.push(response.status);
+        }
+        statuses
+    }
+
+    #[tokio::test]
+    async fn test_recording_redacts_cookies() {
+        let server = FixtureServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/session"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200).insert_header("set-cookie", "sid=secret"),
+            )
+            .mount(server.mock_server())
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Cassette::with_mode(dir.path().join("c.json"), CassetteMode::NewEpisodes)
+            .unwrap()
+            .with_validator(server.validator());
+        let response = recorder
+            .get(&server.url("/session"), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(!response.headers.contains_key("set-cookie"));
+    }
+}
@@ -0,0 +1,605 @@
+//! Segmented, resumable downloads for large files
+//!
+//! [`Downloader`] fetches a file into `<dest>.part`. When the server accepts
+//! byte ranges, the file is split into segments fetched in parallel, and a
+//! `<dest>.part.json` sidecar records how far each segment got, so a download
+//! that was interrupted picks up where it stopped. The finished file is checked
+//! against an expected SHA-256 when one is given, and an optional rate limit
+//! caps the bandwidth of all segments together.
+
+use crate::{
+    client::{self, ClientKey, PooledClient},
+    security::UrlValidator,
+};
+use anyhow::{Context, Result};
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH, ETAG, RANGE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Progress is saved after this many bytes of a segment
+const SAVE_EVERY_BYTES: u64 = 1024 * 1024;
+
+/// How a download is split, verified and paced
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Segments fetched in parallel when the server accepts ranges
+    pub segments: usize,
+    /// Files are not split into segments smaller than this
+    pub min_segment_bytes: u64,
+    /// Bandwidth cap across all segments
+    pub max_bytes_per_sec: Option<u64>,
+    /// Expected SHA-256 of the file, hex encoded
+    pub sha256: Option<String>,
+    /// Retries of a failed segment, each resuming where it stopped
+    pub retries: u32,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            segments: 4,
+            min_segment_bytes: 4 * 1024 * 1024,
+            max_bytes_per_sec: None,
+            sha256: None,
+            retries: 3,
+        }
+    }
+}
+
+/// A finished download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadReport {
+    pub url: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// SHA-256 of the file, hex encoded
+    pub sha256: String,
+    /// Segments the file was fetched in; 1 when the server doesn't accept ranges
+    pub segments: usize,
+    /// Bytes kept from an earlier, interrupted run
+    pub resumed_bytes: u64,
+    pub elapsed: Duration,
+}
+
+/// Progress of a segmented download, saved next to the `.part` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PartState {
+    url: String,
+    total: u64,
+    etag: Option<String>,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Segment {
+    start: u64,
+    /// Inclusive, as in a `Range` header
+    end: u64,
+    /// Bytes of the segment already written
+    done: u64,
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    fn is_complete(&self) -> bool {
+        self.done >= self.len()
+    }
+}
+
+impl PartState {
+    fn new(url: &str, total: u64, etag: Option<String>, segments: usize) -> Self {
+        let size = total.div_ceil(segments as u64).max(1);
+        let segments = (0..total)
+            .step_by(size as usize)
+            .map(|start| Segment {
+                start,
+                end: (start + size).min(total) - 1,
+                done: 0,
+            })
+            .collect();
+        Self {
+            url: url.to_string(),
+            total,
+            etag,
+            segments,
+        }
+    }
+
+    fn done(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.done).sum()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Caps the combined transfer rate of every segment
+struct Throttle {
+    bytes_per_sec: Option<u64>,
+    started: Instant,
+    transferred: AtomicU64,
+}
+
+impl Throttle {
+    async fn consume(&self, bytes: u64) {
+        let Some(rate) = self.bytes_per_sec.filter(|rate| *rate > 0) else {
+            return;
+        };
+        let transferred = self.transferred.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let due = Duration::from_secs_f64(transferred as f64 / rate as f64);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// What a `HEAD` request says about the file
+struct Probe {
+    total: Option<u64>,
+    ranges: bool,
+    etag: Option<String>,
+}
+
+/// Downloads large files, see the [module docs](self)
+#[derive(Clone)]
+pub struct Downloader {
+    client: PooledClient,
+    validator: UrlValidator,
+    options: DownloadOptions,
+}
+
+impl Downloader {
+    pub fn new(options: DownloadOptions) -> Result<Self> {
+        // No overall timeout: large files legitimately take long
+        let client = client::pool().client(&ClientKey::default())?;
+        Ok(Self {
+            client,
+            validator: UrlValidator::default(),
+            options,
+        })
+    }
+
+    /// Validate URLs with `validator` instead of the default SSRF rules
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Download `url` to `dest`, resuming an earlier partial download of it
+    pub async fn download(&self, url: &str, dest: &Path) -> Result<DownloadReport> {
+        self.validator.validate_url(url)?;
+        let started = Instant::now();
+        let part_path = with_suffix(dest, ".part");
+        let state_path = with_suffix(dest, ".part.json");
+        let throttle = Arc::new(Throttle {
+            bytes_per_sec: self.options.max_bytes_per_sec,
+            started,
+            transferred: AtomicU64::new(0),
+        });
+
+        let probe = self.probe(url).await;
+        let (segments, resumed_bytes) = match probe {
+            Probe {
+                total: Some(total),
+                ranges: true,
+                etag,
+            } if total > 0 => {
+                let state = self
+                    .resume_or_start(url, total, etag, &part_path, &state_path)
+                    .await?;
+                let segments = state.segments.len();
+                let resumed_bytes = state.done();
+                self.fetch_segments(state, &part_path, &state_path, throttle)
+                    .await?;
+                (segments, resumed_bytes)
+            }
+            _ => {
+                self.fetch_whole(url, &part_path, throttle).await?;
+                (1, 0)
+            }
+        };
+
+        let (bytes, sha256) = file_sha256(&part_path).await?;
+        if let Some(expected) = &self.options.sha256 {
+            if !expected.eq_ignore_ascii_case(&sha256) {
+                let _ = tokio::fs::remove_file(&part_path).await;
+                let _ = tokio::fs::remove_file(&state_path).await;
+                anyhow::bail!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    url,
+                    expected,
+                    sha256
+                );
+            }
+        }
+        tokio::fs::rename(&part_path, dest)
+            .await
+            .with_context(|| format!("Failed to move download to {}", dest.display()))?;
+        let _ = tokio::fs::remove_file(&state_path).await;
+
+        Ok(DownloadReport {
+            url: url.to_string(),
+            path: dest.to_path_buf(),
+            bytes,
+            sha256,
+            segments,
+            resumed_bytes,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    async fn probe(&self, url: &str) -> Probe {
+        let unknown = Probe {
+            total: None,
+            ranges: false,
+            etag: None,
+        };
+        let Ok(response) = self.client.client().head(url).send().await else {
+            return unknown;
+        };
+        if !response.status().is_success() {
+            return unknown;
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Probe {
+            // Not `content_length()`: a HEAD response has no body to measure
+            total: header(CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+            ranges: header(ACCEPT_RANGES).is_some_and(|ranges| ranges.contains("bytes")),
+            etag: header(ETAG),
+        }
+    }
+
+    /// Progress saved by an earlier run of the same file, or a fresh split
+    async fn resume_or_start(
+        &self,
+        url: &str,
+        total: u64,
+        etag: Option<String>,
+        part_path: &Path,
+        state_path: &Path,
+    ) -> Result<PartState> {
+        let saved = tokio::fs::read(state_path)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PartState>(&bytes).ok())
+            .filter(|saved| saved.url == url && saved.total == total && saved.etag == etag);
+        if let Some(saved) = saved {
+            if tokio::fs::metadata(part_path)
+                .await
+                .is_ok_and(|metadata| metadata.len() == total)
+            {
+                return Ok(saved);
+            }
+        }
+
+        let segments = self.options.segments.clamp(
+            1,
+            total.div_ceil(self.options.min_segment_bytes.max(1)).max(1) as usize,
+        );
+        let state = PartState::new(url, total, etag, segments);
+        let file = tokio::fs::File::create(part_path)
+            .await
+            .with_context(|| format!("Failed to create {}", part_path.display()))?;
+        file.set_len(total).await?;
+        state.save(state_path)?;
+        Ok(state)
+    }
+
+    async fn fetch_segments(
+        &self,
+        state: PartState,
+        part_path: &Path,
+        state_path: &Path,
+        throttle: Arc<Throttle>,
+    ) -> Result<()> {
+        let url = state.url.clone();
+        let pending: Vec<usize> = (0..state.segments.len())
+            .filter(|index| !state.segments[*index].is_complete())
+            .collect();
+        let state = Arc::new(Mutex::new(state));
+        let mut tasks = tokio::task::JoinSet::new();
+        for index in pending {
+            let downloader = self.clone();
+            let url = url.clone();
+            let state = state.clone();
+            let part_path = part_path.to_path_buf();
+            let state_path = state_path.to_path_buf();
+            let throttle = throttle.clone();
+            tasks.spawn(async move {
+                let mut attempt = 0;
+                loop {
+                    let result = downloader
+                        .fetch_segment(&url, index, &state, &part_path, &state_path, &throttle)
+                        .await;
+                    match result {
+                        Ok(()) => return Ok(()),
+                        Err(e) if attempt < downloader.options.retries => {
+                            attempt += 1;
+                            tracing::debug!("Segment {} of {} failed, retrying: {}", index, url, e);
+                            tokio::time::sleep(Duration::from_millis(500 * u64::from(attempt)))
+                                .await;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            });
+        }
+
+        let mut failure = None;
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result.context("Download segment panicked").and_then(|r| r) {
+                failure.get_or_insert(e);
+            }
+        }
+        // Keep what was fetched so the next run resumes from it
+        state.lock().unwrap().save(state_path)?;
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    async fn fetch_segment(
+        &self,
+        url: &str,
+        index: usize,
+        state: &Mutex<PartState>,
+        part_path: &Path,
+        state_path: &Path,
+        throttle: &Throttle,
+    ) -> Result<()> {
+        let segment = state.lock().unwrap().segments[index];
+        if segment.is_complete() {
+            return Ok(());
+        }
+        let offset = segment.start + segment.done;
+        let _in_flight = self.client.begin();
+        let mut response = self
+            .client
+            .client()
+            .get(url)
+            .header(RANGE, format!("bytes={}-{}", offset, segment.end))
+            .send()
+            .await?;
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            anyhow::bail!("Expected 206 Partial Content, got {}", response.status());
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(part_path)
+            .await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut unsaved = 0;
+        let mut remaining = segment.len() - segment.done;
+        while let Some(chunk) = response.chunk().await? {
+            // Never write past the segment, even if the server sends more
+            let chunk = &chunk[..chunk.len().min(remaining as usize)];
+            file.write_all(chunk).await?;
+            remaining -= chunk.len() as u64;
+            unsaved += chunk.len() as u64;
+            state.lock().unwrap().segments[index].done += chunk.len() as u64;
+            if unsaved >= SAVE_EVERY_BYTES {
+                file.flush().await?;
+                state.lock().unwrap().save(state_path)?;
+                unsaved = 0;
+            }
+            throttle.consume(chunk.len() as u64).await;
+            if remaining == 0 {
+                break;
+            }
+        }
+        file.flush().await?;
+        if remaining > 0 {
+            anyhow::bail!("Segment ended {} bytes early", remaining);
+        }
+        Ok(())
+    }
+
+    /// Fetch the file in one request, for servers that don't accept ranges
+    async fn fetch_whole(
+        &self,
+        url: &str,
+        part_path: &Path,
+        throttle: Arc<Throttle>,
+    ) -> Result<()> {
+        let _in_flight = self.client.begin();
+        let mut response = self.client.client().get(url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {} for {}", response.status(), url);
+        }
+        let mut file = tokio::fs::File::create(part_path)
+            .await
+            .with_context(|| format!("Failed to create {}", part_path.display()))?;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            throttle.consume(chunk.len() as u64).await;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// File name for a download of `url`: its last path segment, or `download`
+pub fn file_name(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+        })
+        // `..` and the like would leave the target directory
+        .filter(|name| Path::new(name).file_name().is_some_and(|file| file == name.as_str()))
+        .unwrap_or_else(|| "download".to_string())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Size and SHA-256 of the file at `path`
+async fn file_sha256(path: &Path) -> Result<(u64, String)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut bytes = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        bytes += read as u64;
+    }
+    Ok((bytes, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, Request, Respond, ResponseTemplate,
+    };
+
+    /// Serves `body`, honouring `Range: bytes=a-b`
+    struct RangedFile(Vec<u8>);
+
+    impl Respond for RangedFile {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let range = request
+                .headers
+                .get("range")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("bytes="))
+                .and_then(|value| value.split_once('-'))
+                .and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)));
+            match range {
+                Some((start, end)) => {
+                    let end = usize::min(end, self.0.len() - 1);
+                    ResponseTemplate::new(206).set_body_bytes(self.0[start..=end].to_vec())
+                }
+                None => ResponseTemplate::new(200).set_body_bytes(self.0.clone()),
+            }
+        }
+    }
+
+    async fn serve(body: &[u8]) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/video.mp4"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("accept-ranges", "bytes")
+                    .insert_header("content-length", body.len().to_string().as_str()),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/video.mp4"))
+            .respond_with(RangedFile(body.to_vec()))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn downloader(server: &MockServer, options: DownloadOptions) -> Downloader {
+        Downloader::new(options).unwrap().with_validator(
+            UrlValidator::default().with_allowed_host(&server.address().ip().to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_segmented_download_resumes_and_verifies() {
+        let body: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let sha256 = format!("{:x}", Sha256::digest(&body));
+        let server = serve(&body).await;
+        let url = format!("{}/video.mp4", server.uri());
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("video.mp4");
+        let options = DownloadOptions {
+            segments: 4,
+            min_segment_bytes: 10_000,
+            sha256: Some(sha256.clone()),
+            ..DownloadOptions::default()
+        };
+
+        // An earlier run finished the first segment and part of the second
+        let mut state = PartState::new(&url, body.len() as u64, None, 4);
+        state.segments[0].done = state.segments[0].len();
+        state.segments[1].done = 1_000;
+        let mut partial = vec![0; body.len()];
+        let kept = (state.segments[1].start + 1_000) as usize;
+        partial[..kept].copy_from_slice(&body[..kept]);
+        std::fs::write(with_suffix(&dest, ".part"), partial).unwrap();
+        state.save(&with_suffix(&dest, ".part.json")).unwrap();
+
+        let report = downloader(&server, options.clone())
+            .download(&url, &dest)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert_eq!(report.sha256, sha256);
+        assert_eq!(report.segments, 4);
+        assert_eq!(report.resumed_bytes, kept as u64);
+        assert!(!with_suffix(&dest, ".part.json").exists());
+
+        let ranges: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|request| request.headers.get("range"))
+            .map(|range| range.to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ranges.len(), 3);
+        assert!(ranges.contains(&format!("bytes={}-49999", kept)));
+        assert!(!ranges.iter().any(|range| range.starts_with("bytes=0-")));
+
+        let mismatch = downloader(
+            &server,
+            DownloadOptions {
+                sha256: Some("00".repeat(32)),
+                ..options
+            },
+        )
+        .download(&url, &dir.path().join("other.mp4"))
+        .await;
+        assert!(mismatch
+            .unwrap_err()
+            .to_string()
+            .contains("Checksum mismatch"));
+        assert!(!dir.path().join("other.mp4.part").exists());
+
+        assert_eq!(
+            file_name("https://cdn.example/v/clip.mp4?sig=1"),
+            "clip.mp4"
+        );
+        assert_eq!(file_name("https://cdn.example/v/"), "download");
+        assert_eq!(file_name("https://cdn.example/v/%2E%2E"), "download");
+    }
+}
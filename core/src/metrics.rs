@@ -0,0 +1,207 @@
+//! Fetch latency and size histograms
+//!
+//! [`FetchHistograms`] keeps HDR histograms of total fetch time, time to first
+//! byte and body size, so dashboards can show real percentiles instead of
+//! rolling averages, and renders them in the Prometheus text format.
+
+use crate::trace::FetchTrace;
+use hdrhistogram::Histogram;
+use std::{fmt::Write, time::Duration};
+
+/// Longest fetch time tracked, in milliseconds; slower fetches are clamped
+const MAX_LATENCY_MS: u64 = 60 * 60 * 1000;
+/// Largest body size tracked, in bytes; larger bodies are clamped
+const MAX_BODY_BYTES: u64 = 1 << 32;
+/// Two significant digits keep each histogram small enough to clone per frame
+const SIGNIFICANT_DIGITS: u8 = 2;
+
+/// Percentiles of one histogram
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quantiles {
+    pub count: u64,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+    pub mean: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Series {
+    histogram: Histogram<u64>,
+    /// Exact sum of recorded values, which the histogram only approximates
+    sum: u64,
+}
+
+impl Series {
+    fn new(max: u64) -> Self {
+        Self {
+            histogram: Histogram::new_with_bounds(1, max, SIGNIFICANT_DIGITS)
+                .expect("histogram bounds are valid"),
+            sum: 0,
+        }
+    }
+
+    fn record(&mut self, value: u64) {
+        self.histogram.saturating_record(value.max(1));
+        self.sum = self.sum.saturating_add(value);
+    }
+
+    fn quantiles(&self) -> Quantiles {
+        let histogram = &self.histogram;
+        if histogram.is_empty() {
+            return Quantiles::default();
+        }
+        Quantiles {
+            count: histogram.len(),
+            min: histogram.min(),
+            p50: histogram.value_at_quantile(0.5),
+            p90: histogram.value_at_quantile(0.9),
+            p99: histogram.value_at_quantile(0.99),
+            max: histogram.max(),
+            mean: histogram.mean(),
+        }
+    }
+
+    /// Write a Prometheus summary, dividing values by `scale` (e.g. ms to seconds)
+    fn write_prometheus(&self, out: &mut String, name: &str, help: &str, scale: f64) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} summary", name);
+        for quantile in [0.5, 0.9, 0.99] {
+            let value = if self.histogram.is_empty() {
+                0.0
+            } else {
+                self.histogram.value_at_quantile(quantile) as f64 / scale
+            };
+            let _ = writeln!(out, "{}{{quantile=\"{}\"}} {}", name, quantile, value);
+        }
+        let _ = writeln!(out, "{}_sum {}", name, self.sum as f64 / scale);
+        let _ = writeln!(out, "{}_count {}", name, self.histogram.len());
+    }
+}
+
+/// Histograms of total fetch time, time to first byte and body size
+#[derive(Debug, Clone)]
+pub struct FetchHistograms {
+    total_ms: Series,
+    ttfb_ms: Series,
+    body_bytes: Series,
+}
+
+impl Default for FetchHistograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FetchHistograms {
+    pub fn new() -> Self {
+        Self {
+            total_ms: Series::new(MAX_LATENCY_MS),
+            ttfb_ms: Series::new(MAX_LATENCY_MS),
+            body_bytes: Series::new(MAX_BODY_BYTES),
+        }
+    }
+
+    /// Record one fetch; `ttfb` is optional because not every source reports it
+    pub fn record(&mut self, total: Duration, ttfb: Option<Duration>, body_bytes: usize) {
+        self.total_ms.record(total.as_millis() as u64);
+        if let Some(ttfb) = ttfb {
+            self.ttfb_ms.record(ttfb.as_millis() as u64);
+        }
+        self.body_bytes.record(body_bytes as u64);
+    }
+
+    /// Record the timings of a [`FetchTrace`] together with the body size
+    pub fn record_trace(&mut self, trace: &FetchTrace, body_bytes: usize) {
+        self.record(
+            Duration::from_millis(trace.timing.total_ms),
+            trace.timing.ttfb_ms.map(Duration::from_millis),
+            body_bytes,
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_ms.histogram.is_empty()
+    }
+
+    /// Total fetch time in milliseconds
+    pub fn latency(&self) -> Quantiles {
+        self.total_ms.quantiles()
+    }
+
+    /// Time to first byte in milliseconds
+    pub fn ttfb(&self) -> Quantiles {
+        self.ttfb_ms.quantiles()
+    }
+
+    /// Response body size in bytes
+    pub fn body_size(&self) -> Quantiles {
+        self.body_bytes.quantiles()
+    }
+
+    /// Prometheus text exposition, with metric names starting with `prefix`
+    pub fn render_prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        self.total_ms.write_prometheus(
+            &mut out,
+            &format!("{}_fetch_duration_seconds", prefix),
+            "Total fetch time including redirects and body download",
+            1000.0,
+        );
+        self.ttfb_ms.write_prometheus(
+            &mut out,
+            &format!("{}_fetch_ttfb_seconds", prefix),
+            "Time until the response headers arrived",
+            1000.0,
+        );
+        self.body_bytes.write_prometheus(
+            &mut out,
+            &format!("{}_response_body_bytes", prefix),
+            "Response body size",
+            1.0,
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles() {
+        let mut histograms = FetchHistograms::new();
+        assert!(histograms.is_empty());
+        for ms in 1..=100 {
+            histograms.record(
+                Duration::from_millis(ms),
+                (ms % 2 == 0).then(|| Duration::from_millis(ms / 2)),
+                1024,
+            );
+        }
+
+        let latency = histograms.latency();
+        assert_eq!(latency.count, 100);
+        assert_eq!(latency.p50, 50);
+        assert_eq!(latency.p99, 99);
+        assert_eq!(latency.max, 100);
+        assert_eq!(histograms.ttfb().count, 50);
+        // Values above 256 are bucketed to two significant digits
+        assert!(histograms.body_size().p90.abs_diff(1024) <= 1024 / 100);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let mut histograms = FetchHistograms::new();
+        histograms.record(Duration::from_millis(250), None, 2048);
+
+        let text = histograms.render_prometheus("swoop");
+        assert!(text.contains("# TYPE swoop_fetch_duration_seconds summary"));
+        assert!(text.contains("swoop_fetch_duration_seconds{quantile=\"0.5\"} 0.25"));
+        assert!(text.contains("swoop_fetch_duration_seconds_count 1"));
+        assert!(text.contains("swoop_fetch_ttfb_seconds_count 0"));
+        assert!(text.contains("swoop_response_body_bytes_sum 2048"));
+    }
+}
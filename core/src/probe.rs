@@ -0,0 +1,285 @@
+//! Metadata-only URL scans
+//!
+//! Checking a large list of URLs doesn't need their bodies. [`Prober`] sends
+//! each URL a `HEAD` request and keeps the status, headers, content type and
+//! size of the answer. Servers that refuse `HEAD` with a 405 or 501 get a
+//! `GET` for the first byte only (`Range: bytes=0-0`), whose `Content-Range`
+//! still gives the full size. Bodies are never read, so validation runs
+//! check many times more URLs a second than full fetches would.
+
+use crate::{
+    client::{self, ClientKey, PooledClient},
+    security::UrlValidator,
+};
+use anyhow::Result;
+use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE},
+    Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// How a [`Prober`] asks for a URL's metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeOptions {
+    /// Time each request gets, redirects included
+    pub timeout: Duration,
+    /// Retry with a one-byte ranged `GET` when the server refuses `HEAD`
+    pub ranged_fallback: bool,
+    pub user_agent: Option<String>,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            ranged_fallback: true,
+            user_agent: None,
+        }
+    }
+}
+
+/// Request that got a URL's metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeMethod {
+    Head,
+    /// `GET` with `Range: bytes=0-0`
+    RangedGet,
+}
+
+impl ProbeMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProbeMethod::Head => "head",
+            ProbeMethod::RangedGet => "ranged_get",
+        }
+    }
+}
+
+/// What a URL answered, without its body
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeReport {
+    pub url: String,
+    pub method: Option<ProbeMethod>,
+    pub status: Option<u16>,
+    /// URL after redirects
+    pub final_url: Option<String>,
+    /// `Content-Type` without parameters such as the charset
+    pub content_type: Option<String>,
+    /// Size of the whole body, from `Content-Length` or `Content-Range`
+    pub content_length: Option<u64>,
+    pub last_modified: Option<String>,
+    pub etag: Option<String>,
+    /// Lowercased names; repeated headers are joined with `, `
+    pub headers: BTreeMap<String, String>,
+    pub elapsed_ms: u64,
+    /// Why no answer came, e.g. a DNS failure or timeout
+    pub error: Option<String>,
+}
+
+impl ProbeReport {
+    /// Whether the URL answered with a 2xx status
+    pub fn is_success(&self) -> bool {
+        self.status
+            .is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// Collects URL metadata with `HEAD` requests; see the [module docs](self)
+pub struct Prober {
+    client: PooledClient,
+    validator: UrlValidator,
+    options: ProbeOptions,
+}
+
+impl Prober {
+    pub fn new(options: ProbeOptions) -> Result<Self> {
+        let client = client::pool().client(&ClientKey {
+            user_agent: options.user_agent.clone(),
+            timeout: Some(options.timeout),
+            ..ClientKey::default()
+        })?;
+        Ok(Self {
+            client,
+            validator: UrlValidator::default(),
+            options,
+        })
+    }
+
+    /// Validate URLs with `validator` instead of the default SSRF rules
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Status, headers and size of `url`; failures are reported, not returned
+    pub async fn probe(&self, url: &str) -> ProbeReport {
+        let started = Instant::now();
+        let mut report = ProbeReport {
+            url: url.to_string(),
+            ..ProbeReport::default()
+        };
+        if let Err(e) = self.validator.validate_url(url) {
+            report.error = Some(e.to_string());
+            return report;
+        }
+
+        let _in_flight = self.client.begin();
+        let mut answer = self
+            .client
+            .client()
+            .head(url)
+            .send()
+            .await
+            .map(|response| (ProbeMethod::Head, response));
+        let refused = matches!(
+            &answer,
+            Ok((_, response)) if matches!(
+                response.status(),
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+            )
+        );
+        if refused && self.options.ranged_fallback {
+            answer = self
+                .client
+                .client()
+                .get(url)
+                .header(RANGE, "bytes=0-0")
+                .send()
+                .await
+                .map(|response| (ProbeMethod::RangedGet, response));
+        }
+        match answer {
+            // Dropped without reading the body
+            Ok((method, response)) => report.record(method, &response),
+            Err(e) => report.error = Some(format!("{:#}", anyhow::Error::from(e))),
+        }
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        report
+    }
+}
+
+impl ProbeReport {
+    fn record(&mut self, method: ProbeMethod, response: &Response) {
+        let headers = client::collect_headers(response.headers());
+        let header = |name: &str| headers.get(name).cloned();
+        self.content_length = match response.status() {
+            // `bytes 0-0/12345`; the total may be `*` when unknown
+            StatusCode::PARTIAL_CONTENT => header(CONTENT_RANGE.as_str())
+                .and_then(|range| range.rsplit('/').next()?.trim().parse().ok()),
+            // Not `content_length()`: a HEAD response has no body to measure
+            _ => header(CONTENT_LENGTH.as_str()).and_then(|length| length.trim().parse().ok()),
+        };
+        self.method = Some(method);
+        self.status = Some(response.status().as_u16());
+        self.final_url = Some(response.url().to_string());
+        self.content_type = header("content-type")
+            .and_then(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .map(|value| value.trim().to_string())
+            })
+            .filter(|value| !value.is_empty());
+        self.last_modified = header("last-modified");
+        self.etag = header("etag");
+        self.headers = headers;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FixtureServer;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, ResponseTemplate,
+    };
+
+    fn prober(server: &FixtureServer, options: ProbeOptions) -> Prober {
+        Prober::new(options)
+            .unwrap()
+            .with_validator(server.validator())
+    }
+
+    #[tokio::test]
+    async fn test_probe_reads_headers_without_bodies() {
+        let server = FixtureServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/moved"))
+            .respond_with(ResponseTemplate::new(301).insert_header("location", "/report.pdf"))
+            .mount(server.mock_server())
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/report.pdf"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/pdf")
+                    .insert_header("etag", "\"v2\""),
+            )
+            .mount(server.mock_server())
+            .await;
+
+        let prober = prober(&server, ProbeOptions::default());
+        let report = prober.probe(&server.url("/moved")).await;
+        assert!(report.is_success(), "{:?}", report);
+        assert_eq!(report.method, Some(ProbeMethod::Head));
+        assert_eq!(report.final_url, Some(server.url("/report.pdf")));
+        assert_eq!(report.content_type.as_deref(), Some("application/pdf"));
+        assert_eq!(report.etag.as_deref(), Some("\"v2\""));
+
+        let missing = prober.probe(&server.url("/not-found")).await;
+        assert_eq!((missing.status, missing.error), (Some(404), None));
+        let blocked = Prober::new(ProbeOptions::default())
+            .unwrap()
+            .probe(&server.url("/moved"))
+            .await;
+        assert!(blocked.error.is_some() && blocked.status.is_none());
+
+        let requests = server.mock_server().received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .all(|request| request.method.as_str() == "HEAD"));
+    }
+
+    #[tokio::test]
+    async fn test_refused_head_falls_back_to_a_ranged_get() {
+        let server = FixtureServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/video.mp4"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(server.mock_server())
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/video.mp4"))
+            .and(header("range", "bytes=0-0"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 0-0/73400320")
+                    .set_body_raw(vec![0u8], "video/mp4"),
+            )
+            .mount(server.mock_server())
+            .await;
+
+        let report = prober(&server, ProbeOptions::default())
+            .probe(&server.url("/video.mp4"))
+            .await;
+        assert_eq!(report.method, Some(ProbeMethod::RangedGet));
+        assert_eq!(report.status, Some(206));
+        assert_eq!(report.content_length, Some(73_400_320));
+        assert_eq!(report.content_type.as_deref(), Some("video/mp4"));
+
+        let options = ProbeOptions {
+            ranged_fallback: false,
+            ..ProbeOptions::default()
+        };
+        let without_fallback = prober(&server, options)
+            .probe(&server.url("/video.mp4"))
+            .await;
+        assert_eq!(without_fallback.status, Some(405));
+    }
+}
@@ -1,3 +1,12 @@
+//! URL validation against SSRF
+//!
+//! [`UrlValidator`] only lets http(s) URLs through whose host isn't a blocked
+//! domain or a private, loopback, link-local or otherwise internal address.
+//! IPv6 hosts are checked as thoroughly as IPv4 ones: unique local
+//! (`fc00::/7`) and link-local (`fe80::/10`) addresses are private, and IPv4
+//! addresses embedded in IPv6 ones (`::ffff:127.0.0.1`, NAT64 or 6to4) are
+//! judged by the IPv4 they reach.
+
 use hyper::http::Uri;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
@@ -22,9 +31,11 @@ pub enum SecurityError {
     MalformedUrl { details: String },
 }
 
+#[derive(Clone)]
 pub struct UrlValidator {
     allowed_schemes: Vec<String>,
     blocked_domains: Vec<String>,
+    allowed_hosts: Vec<String>,
     allow_private_ips: bool,
 }
 
@@ -38,6 +49,7 @@ impl Default for UrlValidator {
                 "0.0.0.0".to_string(),
                 "169.254.169.254".to_string(), // AWS metadata
             ],
+            allowed_hosts: Vec::new(),
             allow_private_ips: false,
         }
     }
@@ -51,6 +63,13 @@ impl UrlValidator {
         }
     }
 
+    /// Exempt an exact host from the blocked-domain and private IP checks,
+    /// e.g. a local fixture server in tests
+    pub fn with_allowed_host(mut self, host: &str) -> Self {
+        self.allowed_hosts.push(host.to_string());
+        self
+    }
+
     pub fn validate_url(&self, url: &str) -> Result<Uri, SecurityError> {
         let uri: Uri = url.parse().map_err(|e| SecurityError::ValidationFailed {
             reason: format!("Parse error: {}", e),
@@ -65,7 +84,10 @@ impl UrlValidator {
         }
 
         // Validate host
-        if let Some(host) = uri.host() {
+        if let Some(host) = uri
+            .host()
+            .filter(|host| !self.allowed_hosts.iter().any(|h| h == host))
+        {
             // Check blocked domains
             if self
                 .blocked_domains
@@ -89,8 +111,12 @@ impl UrlValidator {
     }
 
     fn is_private_ip(&self, host: &str) -> Result<bool, SecurityError> {
-        // Try to parse as IP address
-        if let Ok(ip) = host.parse::<IpAddr>() {
+        // Try to parse as IP address; IPv6 hosts come in brackets
+        let literal = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        if let Ok(ip) = literal.parse::<IpAddr>() {
             match ip {
                 IpAddr::V4(ipv4) => Ok(self.is_private_ipv4(ipv4)),
                 IpAddr::V6(ipv6) => Ok(self.is_private_ipv6(ipv6)),
@@ -104,6 +130,7 @@ impl UrlValidator {
     fn is_private_ipv4(&self, ip: Ipv4Addr) -> bool {
         ip.is_private()
             || ip.is_loopback()
+            || ip.is_unspecified()
             || ip.is_link_local()
             || ip.is_broadcast()
             || ip.is_documentation()
@@ -113,7 +140,40 @@ impl UrlValidator {
     }
 
     fn is_private_ipv6(&self, ip: Ipv6Addr) -> bool {
-        ip.is_loopback() || ip.is_multicast() || ip.is_unspecified()
+        if let Some(ipv4) = embedded_ipv4(ip) {
+            return self.is_private_ipv4(ipv4);
+        }
+        let segments = ip.segments();
+        ip.is_loopback()
+            || ip.is_multicast()
+            || ip.is_unspecified()
+            // Unique local fc00::/7, which includes AWS metadata at fd00:ec2::254
+            || segments[0] & 0xfe00 == 0xfc00
+            // Link-local fe80::/10 and the deprecated site-local fec0::/10
+            || segments[0] & 0xffc0 == 0xfe80
+            || segments[0] & 0xffc0 == 0xfec0
+            // Documentation 2001:db8::/32
+            || segments[..2] == [0x2001, 0x0db8]
+    }
+}
+
+/// IPv4 address an IPv6 address reaches: IPv4-mapped `::ffff:0:0/96`, the
+/// deprecated IPv4-compatible `::/96`, NAT64 `64:ff9b::/96` and 6to4 `2002::/16`
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let [.., a, b, c, d] = ip.octets();
+    match segments {
+        [0, 0, 0, 0, 0, 0xffff, _, _] | [0x64, 0xff9b, 0, 0, 0, 0, _, _] => {
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        // `::` and `::1` are unspecified and loopback, not IPv4
+        [0, 0, 0, 0, 0, 0, high, _] if high != 0 => Some(Ipv4Addr::new(a, b, c, d)),
+        [0x2002, high, low, ..] => {
+            let [a, b] = high.to_be_bytes();
+            let [c, d] = low.to_be_bytes();
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        _ => None,
     }
 }
 
@@ -156,6 +216,52 @@ mod tests {
         assert!(matches!(result, Err(SecurityError::BlockedDomain { .. })));
     }
 
+    #[test]
+    fn test_allowed_host_bypasses_checks() {
+        let validator = UrlValidator::default().with_allowed_host("127.0.0.1");
+        assert!(validator.validate_url("http://127.0.0.1:8080/page").is_ok());
+        assert!(validator.validate_url("http://localhost:8080").is_err());
+    }
+
+    #[test]
+    fn test_private_ipv6_blocked() {
+        let validator = UrlValidator::default();
+        for url in [
+            "http://[::1]:8080/",
+            "http://[fd00:ec2::254]/latest/meta-data/",
+            "http://[fc00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:7f00:1]/",
+            "http://[::ffff:a9fe:a9fe]/",
+            "http://[::ffff:192.168.1.1]/",
+            "http://[64:ff9b::10.0.0.1]/",
+            "http://[2002:c0a8:0101::1]/",
+            "http://[2001:db8::1]/",
+        ] {
+            assert!(
+                matches!(
+                    validator.validate_url(url),
+                    Err(SecurityError::PrivateIP { .. })
+                ),
+                "{} should be blocked",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_ipv6_allowed() {
+        let validator = UrlValidator::default();
+        assert!(validator.validate_url("https://[2606:4700::1111]/").is_ok());
+        assert!(validator.validate_url("https://[::ffff:1.1.1.1]/").is_ok());
+        assert!(validator
+            .validate_url("https://[2002:0101:0101::1]/")
+            .is_ok());
+        assert!(UrlValidator::new(true)
+            .validate_url("http://[fd00::1]/")
+            .is_ok());
+    }
+
     #[test]
     fn test_allow_private_ips() {
         let validator = UrlValidator::new(true);
@@ -0,0 +1,267 @@
+//! Deterministic fixture server for end-to-end tests
+//!
+//! [`FixtureServer`] starts a local HTTP server with canned pages, redirect
+//! chains, rate limiting, gzip bodies and a fake Cloudflare challenge, so the
+//! fetch, anti-bot and extraction pipeline can be tested without touching the
+//! network. Enabled in dependent crates through the `test-support` feature.
+
+use crate::{fetch_url_with, security::UrlValidator};
+use anyhow::Result;
+use bytes::Bytes;
+use flate2::{write::GzEncoder, Compression};
+use std::{io::Write, time::Duration};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// HTML served by the fixture routes
+pub mod pages {
+    pub const ARTICLE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <title>Fixture Article</title>
+  <meta name="description" content="A canned article for integration tests">
+  <meta property="og:type" content="article">
+</head>
+<body>
+  <article>
+    <h1>Fixture Article</h1>
+    <p class="byline">By Test Author</p>
+    <p>Swoop fetches pages, detects bot protection and extracts structured content.
+    This article exists so the whole pipeline can run against a local server with
+    predictable output, which keeps the tests fast and deterministic.</p>
+    <p>A second paragraph adds enough text for readability heuristics to treat the
+    page as an article rather than a thin navigation page.</p>
+    <a href="/product">Related product</a>
+  </article>
+</body>
+</html>"#;
+
+    pub const PRODUCT: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <title>Fixture Widget</title>
+  <meta property="og:type" content="product">
+  <meta property="product:price:amount" content="19.99">
+  <meta property="product:price:currency" content="USD">
+  <script type="application/ld+json">
+  {"@context": "https://schema.org", "@type": "Product", "name": "Fixture Widget",
+   "offers": {"@type": "Offer", "price": "19.99", "priceCurrency": "USD"}}
+  </script>
+</head>
+<body>
+  <h1>Fixture Widget</h1>
+  <span class="price">$19.99</span>
+  <button>Add to cart</button>
+</body>
+</html>"#;
+
+    pub const LOGIN: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head><title>Sign in</title></head>
+<body>
+  <h1>Sign in to continue</h1>
+  <form action="/session" method="post">
+    <input type="email" name="email">
+    <input type="password" name="password">
+    <button type="submit">Log in</button>
+  </form>
+  <a href="/forgot">Forgot password?</a>
+</body>
+</html>"#;
+
+    pub const CLOUDFLARE_CHALLENGE: &str = r#"<!DOCTYPE html>
+<html lang="en-US">
+<head><title>Just a moment...</title></head>
+<body>
+  <div id="challenge-running">Checking your browser before accessing the site.</div>
+  <div class="cf-browser-verification">Please enable JavaScript and cookies to continue</div>
+  <script src="/cdn-cgi/challenge-platform/h/b/orchestrate/jsch/v1"></script>
+  <p>Performance &amp; security by Cloudflare</p>
+</body>
+</html>"#;
+}
+
+/// Number of `429 Too Many Requests` responses `/rate-limited` sends before succeeding
+pub const RATE_LIMITED_RESPONSES: u64 = 2;
+
+/// How long `/slow` takes to respond
+pub const SLOW_RESPONSE_DELAY: Duration = Duration::from_secs(3);
+
+/// Local server with canned routes:
+///
+/// - `/article`, `/product`, `/login`: HTML pages from [`pages`]
+/// - `/redirect/1` → `/redirect/2` → `/redirect/3` → `/article`
+/// - `/rate-limited`: 429 with `Retry-After: 1` for the first
+///   [`RATE_LIMITED_RESPONSES`] requests, then the article
+/// - `/gzip`: the article gzip-encoded with `Content-Encoding: gzip`
+/// - `/cloudflare`: 403 Cloudflare JS challenge
+/// - `/slow`: the article after [`SLOW_RESPONSE_DELAY`]
+/// - `/not-found` and any unknown path: 404
+pub struct FixtureServer {
+    server: MockServer,
+}
+
+fn html(status: u16, body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status)
+        .insert_header("content-type", "text/html; charset=utf-8")
+        .set_body_string(body)
+}
+
+fn gzip(body: &str) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(body.as_bytes())
+        .expect("writing to a Vec cannot fail");
+    encoder.finish().expect("writing to a Vec cannot fail")
+}
+
+impl FixtureServer {
+    /// Start the server on a random local port with all fixture routes mounted
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+
+        for (route, body) in [
+            ("/article", pages::ARTICLE),
+            ("/product", pages::PRODUCT),
+            ("/login", pages::LOGIN),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .respond_with(html(200, body))
+                .mount(&server)
+                .await;
+        }
+
+        for (from, to) in [
+            ("/redirect/1", "/redirect/2"),
+            ("/redirect/2", "/redirect/3"),
+            ("/redirect/3", "/article"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(from))
+                .respond_with(ResponseTemplate::new(302).insert_header("location", to))
+                .mount(&server)
+                .await;
+        }
+
+        Mock::given(method("GET"))
+            .and(path("/rate-limited"))
+            .respond_with(
+                html(429, "<html><body>Too Many Requests</body></html>")
+                    .insert_header("retry-after", "1"),
+            )
+            .up_to_n_times(RATE_LIMITED_RESPONSES)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/rate-limited"))
+            .respond_with(html(200, pages::ARTICLE))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/gzip"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/html; charset=utf-8")
+                    .insert_header("content-encoding", "gzip")
+                    .set_body_bytes(gzip(pages::ARTICLE)),
+            )
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/cloudflare"))
+            .respond_with(
+                html(403, pages::CLOUDFLARE_CHALLENGE)
+                    .insert_header("server", "cloudflare")
+                    .insert_header("cf-ray", "8a1b2c3d4e5f6789-AMS")
+                    .insert_header("cf-mitigated", "challenge"),
+            )
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(html(200, pages::ARTICLE).set_delay(SLOW_RESPONSE_DELAY))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/not-found"))
+            .respond_with(html(404, "<html><body>Not Found</body></html>"))
+            .mount(&server)
+            .await;
+
+        Self { server }
+    }
+
+    /// Base URI, e.g. `http://127.0.0.1:54321`
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// Absolute URL of a fixture route
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.server.uri(), path)
+    }
+
+    /// Default validator with the fixture server's host allowed
+    pub fn validator(&self) -> UrlValidator {
+        UrlValidator::default().with_allowed_host(&self.server.address().ip().to_string())
+    }
+
+    /// Fetch a fixture route through [`crate::fetch_url_with`]
+    pub async fn fetch(&self, path: &str, timeout: Duration) -> Result<Bytes> {
+        fetch_url_with(&self.validator(), &self.url(path), timeout).await
+    }
+
+    /// The underlying mock server, for mounting extra routes or inspecting requests
+    pub fn mock_server(&self) -> &MockServer {
+        &self.server
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixture_routes() {
+        let server = FixtureServer::start().await;
+        let client = reqwest::Client::new();
+
+        let redirected = client.get(server.url("/redirect/1")).send().await.unwrap();
+        assert_eq!(redirected.url().path(), "/article");
+        assert_eq!(redirected.text().await.unwrap(), pages::ARTICLE);
+
+        let gzipped = server.fetch("/gzip", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(gzipped, pages::ARTICLE.as_bytes());
+
+        for _ in 0..RATE_LIMITED_RESPONSES {
+            let limited = client
+                .get(server.url("/rate-limited"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(limited.status(), 429);
+            assert_eq!(limited.headers()["retry-after"], "1");
+        }
+        let recovered = client
+            .get(server.url("/rate-limited"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(recovered.status(), 200);
+
+        let challenge = client.get(server.url("/cloudflare")).send().await.unwrap();
+        assert_eq!(challenge.status(), 403);
+        assert_eq!(challenge.headers()["cf-mitigated"], "challenge");
+
+        let missing = client.get(server.url("/missing")).send().await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+}
@@ -0,0 +1,217 @@
+//! Text that shares the buffer of a fetched body
+//!
+//! A [`SharedStr`] is a UTF-8 view into a [`Bytes`] buffer. Extractors hand
+//! out slices of the page they were given, such as its title or a trimmed
+//! plain-text body, as views into the page's own buffer instead of copies, and
+//! cloning one only bumps a reference count. Text that had to be rewritten,
+//! e.g. decoded entities, gets a buffer of its own. Owned `String`s are only
+//! made where the text leaves the pipeline, e.g. for storage.
+
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{borrow::Borrow, borrow::Cow, fmt, hash, ops::Deref};
+
+/// Cheaply cloned UTF-8 text, usually a slice of a fetched body
+#[derive(Clone, Default)]
+pub struct SharedStr(Bytes);
+
+impl SharedStr {
+    /// Text of a response body, decoded lossily if it isn't valid UTF-8
+    ///
+    /// A valid body is shared, not copied.
+    pub fn from_body(body: Bytes) -> Self {
+        match std::str::from_utf8(&body) {
+            Ok(_) => Self(body),
+            Err(_) => Self::from(String::from_utf8_lossy(&body).into_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the buffer is only ever built from UTF-8, see the constructors
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+
+    /// `part` of this text, sharing its buffer if `part` was sliced from it
+    /// and copied otherwise
+    pub fn share(&self, part: &str) -> Self {
+        let range = self.0.as_ptr_range();
+        let part_range = part.as_bytes().as_ptr_range();
+        let inside =
+            !part.is_empty() && range.start <= part_range.start && part_range.end <= range.end;
+        if inside {
+            Self(self.0.slice_ref(part.as_bytes()))
+        } else {
+            Self::from(part)
+        }
+    }
+
+    /// Like [`Self::share`], keeping text an extractor had to rewrite as it is
+    pub fn share_cow(&self, part: Cow<'_, str>) -> Self {
+        match part {
+            Cow::Borrowed(part) => self.share(part),
+            Cow::Owned(part) => Self::from(part),
+        }
+    }
+
+    /// The underlying bytes, sharing the buffer
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// An owned `String`, reusing the buffer when nothing else shares it
+    pub fn into_string(self) -> String {
+        String::from_utf8(Vec::from(self.0)).expect("SharedStr holds UTF-8")
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(text: String) -> Self {
+        Self(Bytes::from(text.into_bytes()))
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(text: &str) -> Self {
+        Self(Bytes::copy_from_slice(text.as_bytes()))
+    }
+}
+
+impl From<SharedStr> for String {
+    fn from(text: SharedStr) -> Self {
+        text.into_string()
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for SharedStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for SharedStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for SharedStr {}
+
+impl PartialEq<str> for SharedStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SharedStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for SharedStr {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialOrd for SharedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedStr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl hash::Hash for SharedStr {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Serialize for SharedStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_share_the_body() {
+        let body = SharedStr::from_body(Bytes::from_static(b"  <title>Hello</title>  "));
+        let title = body.share(&body[9..14]);
+        assert_eq!(title, "Hello");
+        assert_eq!(title.as_bytes().as_ptr(), body.as_bytes()[9..].as_ptr());
+
+        // Text from elsewhere is copied
+        let other = String::from("Hello");
+        let copied = body.share(&other);
+        assert_eq!(copied, "Hello");
+        assert_ne!(copied.as_bytes().as_ptr(), other.as_ptr());
+        assert_eq!(
+            body.share_cow(Cow::Owned("rewritten".to_string())),
+            "rewritten"
+        );
+    }
+
+    #[test]
+    fn decodes_invalid_bodies_lossily() {
+        let text = SharedStr::from_body(Bytes::from_static(b"caf\xe9"));
+        assert_eq!(text, "caf\u{fffd}");
+    }
+
+    #[test]
+    fn reuses_unshared_buffers_as_strings() {
+        let text = SharedStr::from(String::from("page"));
+        let pointer = text.as_ptr();
+        let owned = text.into_string();
+        assert_eq!(owned, "page");
+        assert_eq!(owned.as_ptr(), pointer);
+    }
+
+    #[test]
+    fn serializes_as_a_string() {
+        let text = SharedStr::from("quoted \"text\"");
+        let json = serde_json::to_string(&text).unwrap();
+        assert_eq!(json, r#""quoted \"text\"""#);
+        assert_eq!(serde_json::from_str::<SharedStr>(&json).unwrap(), text);
+    }
+}
@@ -0,0 +1,131 @@
+//! Concurrency limits that can change while work is running
+//!
+//! A [`Throttle`] is a semaphore with a fixed maximum whose current limit can
+//! be lowered and raised again, e.g. to shed load while the host is short of
+//! memory. Lowering takes effect as permits are returned: permits still in use
+//! are retired by [`Throttle::settle`] once they come back, which
+//! [`Throttle::acquire`] and every later limit change call.
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+/// A semaphore whose number of permits can be adjusted between 1 and a maximum
+#[derive(Debug)]
+pub struct Throttle {
+    semaphore: Arc<Semaphore>,
+    max: usize,
+    state: Mutex<ThrottleState>,
+}
+
+#[derive(Debug)]
+struct ThrottleState {
+    limit: usize,
+    /// Permits to retire once they are returned
+    owed: usize,
+}
+
+impl Throttle {
+    /// A throttle at its maximum of `max` permits, at least 1
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            max,
+            state: Mutex::new(ThrottleState {
+                limit: max,
+                owed: 0,
+            }),
+        }
+    }
+
+    /// The underlying semaphore, for callers that acquire permits themselves
+    pub fn semaphore(&self) -> Arc<Semaphore> {
+        self.semaphore.clone()
+    }
+
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, AcquireError> {
+        self.settle();
+        self.semaphore.clone().acquire_owned().await
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Permits allowed at the moment
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Allow `limit` permits, clamped between 1 and the maximum; returns the
+    /// new limit
+    pub fn set_limit(&self, limit: usize) -> usize {
+        let limit = limit.clamp(1, self.max);
+        let mut state = self.state.lock().unwrap();
+        if limit < state.limit {
+            state.owed += state.limit - limit;
+        } else {
+            let raise = limit - state.limit;
+            // Permits not retired yet cover the raise first
+            let kept = raise.min(state.owed);
+            state.owed -= kept;
+            self.semaphore.add_permits(raise - kept);
+        }
+        state.limit = limit;
+        state.owed -= self.semaphore.forget_permits(state.owed);
+        limit
+    }
+
+    /// Retire the permits a lowered limit still owes, as far as they have
+    /// been returned
+    pub fn settle(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.owed > 0 {
+            state.owed -= self.semaphore.forget_permits(state.owed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lowers_as_permits_are_returned_and_raises_again() {
+        let throttle = Throttle::new(4);
+        let held: Vec<_> = acquire_permits(&throttle, 3).await;
+        assert_eq!(throttle.semaphore().available_permits(), 1);
+
+        // One permit is free now, two more are retired when returned
+        assert_eq!(throttle.set_limit(1), 1);
+        assert_eq!(throttle.semaphore().available_permits(), 0);
+        drop(held);
+        throttle.settle();
+        assert_eq!(throttle.semaphore().available_permits(), 1);
+
+        assert_eq!(throttle.set_limit(10), 4);
+        assert_eq!(throttle.semaphore().available_permits(), 4);
+    }
+
+    #[tokio::test]
+    async fn raising_before_permits_return_cancels_the_debt() {
+        let throttle = Throttle::new(3);
+        let held = acquire_permits(&throttle, 3).await;
+
+        throttle.set_limit(1);
+        throttle.set_limit(2);
+        drop(held);
+        throttle.settle();
+        assert_eq!(throttle.limit(), 2);
+        assert_eq!(throttle.semaphore().available_permits(), 2);
+        assert_eq!(throttle.set_limit(0), 1);
+    }
+
+    async fn acquire_permits(throttle: &Throttle, count: usize) -> Vec<OwnedSemaphorePermit> {
+        let mut permits = Vec::new();
+        for _ in 0..count {
+            permits.push(throttle.acquire().await.unwrap());
+        }
+        permits
+    }
+}
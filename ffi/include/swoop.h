@@ -0,0 +1,62 @@
+/*
+ * Swoop C API
+ *
+ * Ownership:
+ *   - swoop_engine_new returns an engine owned by the caller; release it with
+ *     swoop_engine_shutdown exactly once.
+ *   - Input strings are only borrowed for the duration of the call.
+ *   - Strings returned by swoop_poll_results are owned by the caller and must be
+ *     released with swoop_string_free.
+ *   - swoop_last_error returns a thread-local string owned by the library, valid
+ *     until the next Swoop call on the same thread.
+ *
+ * All functions except swoop_engine_shutdown are safe to call concurrently on
+ * the same engine.
+ */
+
+#ifndef SWOOP_H
+#define SWOOP_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SWOOP_OK 0
+#define SWOOP_ERR_NULL_POINTER -1
+#define SWOOP_ERR_INVALID_UTF8 -2
+#define SWOOP_ERR_SHUTDOWN -3
+#define SWOOP_ERR_INTERNAL -4
+
+typedef struct SwoopEngine SwoopEngine;
+
+/* Create an engine. Zero selects the default concurrency (16) / timeout (30s).
+ * Returns NULL on failure. */
+SwoopEngine *swoop_engine_new(uint32_t concurrency, uint64_t timeout_ms);
+
+/* Queue a URL. Writes the request ID to out_id (may be NULL). */
+int32_t swoop_submit(const SwoopEngine *engine, const char *url, uint64_t *out_id);
+
+/* Number of submitted URLs whose results are not yet available. */
+uint64_t swoop_pending(const SwoopEngine *engine);
+
+/* Take up to max finished results (all when max is 0) as a JSON array of
+ * {id, url, success, content_length, response_time_ms, fetched_at, body, error}.
+ * Free the result with swoop_string_free. Returns NULL on error. */
+char *swoop_poll_results(const SwoopEngine *engine, uint32_t max);
+
+/* Free a string returned by swoop_poll_results. NULL is ignored. */
+void swoop_string_free(char *value);
+
+/* Stop accepting work, wait up to grace_ms for in-flight fetches and free the engine. */
+void swoop_engine_shutdown(SwoopEngine *engine, uint64_t grace_ms);
+
+/* Last error message on this thread, or NULL. */
+const char *swoop_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SWOOP_H */
@@ -0,0 +1,418 @@
+//! Stable C API for embedding Swoop
+//!
+//! Exposes a small engine handle that services written in Go, Node, Java or
+//! anything else with a C FFI can drive without linking Rust directly. The
+//! matching header lives in `include/swoop.h`.
+//!
+//! Ownership rules:
+//! - `swoop_engine_new` returns an engine owned by the caller; release it with
+//!   `swoop_engine_shutdown` exactly once.
+//! - Strings passed in (`const char *`) are only borrowed for the duration of the call.
+//! - Strings returned by `swoop_poll_results` are owned by the caller and must be
+//!   released with `swoop_string_free`.
+//! - `swoop_last_error` returns a thread-local string owned by the library; it stays
+//!   valid until the next Swoop call on the same thread.
+//!
+//! Every function except `swoop_engine_shutdown` may be called concurrently on the
+//! same engine from multiple threads.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{runtime::Runtime, sync::Semaphore};
+
+/// Call succeeded
+pub const SWOOP_OK: i32 = 0;
+/// A required pointer argument was null
+pub const SWOOP_ERR_NULL_POINTER: i32 = -1;
+/// A string argument was not valid UTF-8
+pub const SWOOP_ERR_INVALID_UTF8: i32 = -2;
+/// The engine is shutting down and no longer accepts work
+pub const SWOOP_ERR_SHUTDOWN: i32 = -3;
+/// An unexpected internal error occurred; see `swoop_last_error`
+pub const SWOOP_ERR_INTERNAL: i32 = -4;
+
+const DEFAULT_CONCURRENCY: usize = 16;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// Outcome of a single submitted URL, serialized to JSON for `swoop_poll_results`
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchResult {
+    pub id: u64,
+    pub url: String,
+    pub success: bool,
+    pub content_length: usize,
+    pub response_time_ms: u64,
+    pub fetched_at: DateTime<Utc>,
+    pub body: Option<String>,
+    pub error: Option<String>,
+}
+
+/// One submitted URL counted in `SwoopEngine::pending` until dropped
+struct Pending(Arc<AtomicU64>);
+
+impl Pending {
+    fn start(pending: &Arc<AtomicU64>) -> Self {
+        pending.fetch_add(1, Ordering::SeqCst);
+        Self(pending.clone())
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Opaque engine handle
+pub struct SwoopEngine {
+    runtime: Runtime,
+    semaphore: Arc<Semaphore>,
+    results: Arc<Mutex<VecDeque<FetchResult>>>,
+    pending: Arc<AtomicU64>,
+    next_id: AtomicU64,
+    shutting_down: AtomicBool,
+    timeout: Duration,
+}
+
+impl SwoopEngine {
+    fn new(concurrency: usize, timeout: Duration) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("swoop-ffi")
+            .build()?;
+
+        Ok(Self {
+            runtime,
+            semaphore: Arc::new(Semaphore::new(concurrency)),
+            results: Arc::new(Mutex::new(VecDeque::new())),
+            pending: Arc::new(AtomicU64::new(0)),
+            next_id: AtomicU64::new(1),
+            shutting_down: AtomicBool::new(false),
+            timeout,
+        })
+    }
+
+    fn submit(&self, url: String) -> Option<u64> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let semaphore = self.semaphore.clone();
+        let results = self.results.clone();
+        let pending = Pending::start(&self.pending);
+        let timeout = self.timeout;
+
+        self.runtime.spawn(async move {
+            // Owned by the task, so a panic or a task dropped unfinished
+            // still counts it as done
+            let _pending = pending;
+            let _permit = semaphore.acquire_owned().await;
+            let started = Instant::now();
+            let outcome = swoop_core::fetch_url(&url, timeout).await;
+            let response_time_ms = started.elapsed().as_millis() as u64;
+
+            let result = match outcome {
+                Ok(body) => FetchResult {
+                    id,
+                    url,
+                    success: true,
+                    content_length: body.len(),
+                    response_time_ms,
+                    fetched_at: Utc::now(),
+                    body: Some(String::from_utf8_lossy(&body).into_owned()),
+                    error: None,
+                },
+                Err(e) => FetchResult {
+                    id,
+                    url,
+                    success: false,
+                    content_length: 0,
+                    response_time_ms,
+                    fetched_at: Utc::now(),
+                    body: None,
+                    error: Some(e.to_string()),
+                },
+            };
+
+            results.lock().unwrap().push_back(result);
+        });
+
+        Some(id)
+    }
+
+    fn drain(&self, max: usize) -> Vec<FetchResult> {
+        let mut results = self.results.lock().unwrap();
+        let count = if max == 0 {
+            results.len()
+        } else {
+            max.min(results.len())
+        };
+        results.drain(..count).collect()
+    }
+}
+
+/// Create an engine running up to `concurrency` fetches at once with a per-request
+/// timeout of `timeout_ms`. Zero selects the defaults. Returns null on failure.
+#[no_mangle]
+pub extern "C" fn swoop_engine_new(concurrency: u32, timeout_ms: u64) -> *mut SwoopEngine {
+    clear_last_error();
+    let concurrency = match concurrency {
+        0 => DEFAULT_CONCURRENCY,
+        n => n as usize,
+    };
+    let timeout = match timeout_ms {
+        0 => DEFAULT_TIMEOUT,
+        ms => Duration::from_millis(ms),
+    };
+
+    match catch_unwind(|| SwoopEngine::new(concurrency, timeout)) {
+        Ok(Ok(engine)) => Box::into_raw(Box::new(engine)),
+        Ok(Err(e)) => {
+            set_last_error(format!("Failed to start runtime: {}", e));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("Panic while creating engine");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Queue `url` for fetching. On success the request ID is written to `out_id`
+/// (which may be null) and `SWOOP_OK` is returned.
+///
+/// # Safety
+/// `engine` must be a live pointer from `swoop_engine_new` and `url` a valid
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn swoop_submit(
+    engine: *const SwoopEngine,
+    url: *const c_char,
+    out_id: *mut u64,
+) -> i32 {
+    clear_last_error();
+    if engine.is_null() || url.is_null() {
+        set_last_error("engine and url must not be null");
+        return SWOOP_ERR_NULL_POINTER;
+    }
+    let engine = &*engine;
+    let url = match CStr::from_ptr(url).to_str() {
+        Ok(url) => url.to_string(),
+        Err(e) => {
+            set_last_error(format!("url is not valid UTF-8: {}", e));
+            return SWOOP_ERR_INVALID_UTF8;
+        }
+    };
+
+    match catch_unwind(AssertUnwindSafe(|| engine.submit(url))) {
+        Ok(Some(id)) => {
+            if !out_id.is_null() {
+                *out_id = id;
+            }
+            SWOOP_OK
+        }
+        Ok(None) => {
+            set_last_error("engine is shutting down");
+            SWOOP_ERR_SHUTDOWN
+        }
+        Err(_) => {
+            set_last_error("Panic while submitting URL");
+            SWOOP_ERR_INTERNAL
+        }
+    }
+}
+
+/// Number of submitted URLs whose results are not yet available
+///
+/// # Safety
+/// `engine` must be null or a live pointer from `swoop_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn swoop_pending(engine: *const SwoopEngine) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    (*engine).pending.load(Ordering::SeqCst)
+}
+
+/// Take up to `max` finished results (all of them when `max` is 0) as a JSON array.
+/// Returns an empty array when nothing is ready and null on error. The caller
+/// owns the returned string and must free it with `swoop_string_free`.
+///
+/// # Safety
+/// `engine` must be a live pointer from `swoop_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn swoop_poll_results(engine: *const SwoopEngine, max: u32) -> *mut c_char {
+    clear_last_error();
+    if engine.is_null() {
+        set_last_error("engine must not be null");
+        return ptr::null_mut();
+    }
+    let engine = &*engine;
+
+    let json = catch_unwind(AssertUnwindSafe(|| {
+        serde_json::to_string(&engine.drain(max as usize))
+    }));
+    match json {
+        // serde_json escapes control characters, so the output never contains NUL
+        Ok(Ok(json)) => CString::new(json).map_or(ptr::null_mut(), CString::into_raw),
+        Ok(Err(e)) => {
+            set_last_error(format!("Failed to serialize results: {}", e));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("Panic while polling results");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a string returned by `swoop_poll_results`. Null is ignored.
+///
+/// # Safety
+/// `value` must be null or a string returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn swoop_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Stop accepting work, wait up to `grace_ms` for in-flight fetches, then free
+/// the engine. Unpolled results are discarded. Null is ignored.
+///
+/// # Safety
+/// `engine` must be null or a pointer from `swoop_engine_new` that has not been
+/// shut down, and no other thread may be using it.
+#[no_mangle]
+pub unsafe extern "C" fn swoop_engine_shutdown(engine: *mut SwoopEngine, grace_ms: u64) {
+    if engine.is_null() {
+        return;
+    }
+    let engine = Box::from_raw(engine);
+    engine.shutting_down.store(true, Ordering::SeqCst);
+
+    let deadline = Instant::now() + Duration::from_millis(grace_ms);
+    while engine.pending.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    engine.runtime.shutdown_timeout(Duration::from_millis(100));
+}
+
+/// Message describing the last failed call on this thread, or null. The string
+/// is owned by the library and valid until the next Swoop call on this thread.
+#[no_mangle]
+pub extern "C" fn swoop_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn poll_until_done(engine: *const SwoopEngine) -> serde_json::Value {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while swoop_pending(engine) > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let raw = swoop_poll_results(engine, 0);
+        assert!(!raw.is_null());
+        let value = serde_json::from_str(CStr::from_ptr(raw).to_str().unwrap()).unwrap();
+        swoop_string_free(raw);
+        value
+    }
+
+    #[test]
+    fn test_submit_and_poll_roundtrip() {
+        unsafe {
+            let engine = swoop_engine_new(2, 1_000);
+            assert!(!engine.is_null());
+
+            // Loopback targets are rejected by the SSRF guard, so this completes offline
+            let url = CString::new("http://127.0.0.1/blocked").unwrap();
+            let mut id = 0;
+            assert_eq!(swoop_submit(engine, url.as_ptr(), &mut id), SWOOP_OK);
+            assert_eq!(id, 1);
+
+            let results = poll_until_done(engine);
+            let results = results.as_array().unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0]["id"], 1);
+            assert_eq!(results[0]["success"], false);
+            assert!(results[0]["error"].is_string());
+
+            // Results are handed out once
+            assert_eq!(poll_until_done(engine), serde_json::json!([]));
+
+            swoop_engine_shutdown(engine, 1_000);
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments_set_last_error() {
+        unsafe {
+            assert_eq!(
+                swoop_submit(ptr::null(), ptr::null(), ptr::null_mut()),
+                SWOOP_ERR_NULL_POINTER
+            );
+            assert!(!swoop_last_error().is_null());
+
+            let engine = swoop_engine_new(0, 0);
+            let invalid = [0xff_u8, 0];
+            assert_eq!(
+                swoop_submit(engine, invalid.as_ptr() as *const c_char, ptr::null_mut()),
+                SWOOP_ERR_INVALID_UTF8
+            );
+
+            (*engine).shutting_down.store(true, Ordering::SeqCst);
+            let url = CString::new("https://example.com").unwrap();
+            assert_eq!(
+                swoop_submit(engine, url.as_ptr(), ptr::null_mut()),
+                SWOOP_ERR_SHUTDOWN
+            );
+
+            swoop_engine_shutdown(engine, 0);
+            swoop_engine_shutdown(ptr::null_mut(), 0);
+            swoop_string_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_pending_counts_down_when_a_task_panics() {
+        let engine = SwoopEngine::new(1, Duration::from_secs(1)).unwrap();
+        let pending = Pending::start(&engine.pending);
+        assert_eq!(engine.pending.load(Ordering::SeqCst), 1);
+
+        let task = engine.runtime.spawn(async move {
+            let _pending = pending;
+            panic!("fetch task panicked");
+        });
+        assert!(engine.runtime.block_on(task).is_err());
+        assert_eq!(engine.pending.load(Ordering::SeqCst), 0);
+    }
+}
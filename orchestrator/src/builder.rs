@@ -0,0 +1,319 @@
+//! Builder-style entry point
+//!
+//! [`Swoop`] is a configured [`Pipeline`], put together with
+//! [`Swoop::builder`] instead of filling in a [`PipelineConfig`]:
+//!
+//! ```ignore
+//! let swoop = Swoop::builder()
+//!     .with_storage(Arc::new(LocalStore::new("./data").await?))
+//!     .with_policies(Arc::new(PolicyResolver::from_file("policies.toml")?))
+//!     .with_antibot(AntiBotSettings { warm_up: true, ..AntiBotSettings::default() })
+//!     .build();
+//! let results = swoop.run(["https://example.com"]);
+//! ```
+
+use futures::{Stream, StreamExt};
+use scrapers::{
+    browser::BrowserPool,
+    platforms::ScraperRegistry,
+    policy::{FetchTier, PolicyResolver},
+    rate_limiter::RateProfile,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use storage::{browser_profiles::ProfileStore, FingerprintStore, QuarantineStore, StorageBackend};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    fallback::TierStats,
+    workflow::{Workflow, WorkflowError, WorkflowReport},
+    AntiBotSettings, Pipeline, PipelineConfig, PipelineResult,
+};
+
+/// A scraping engine ready to run
+#[derive(Clone)]
+pub struct Swoop {
+    config: PipelineConfig,
+}
+
+impl Swoop {
+    pub fn builder() -> SwoopBuilder {
+        SwoopBuilder::default()
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Run every seed through the pipeline; see [`Pipeline::run`]
+    pub fn run<I>(&self, seeds: I) -> impl Stream<Item = PipelineResult>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Pipeline::run(seeds, self.config.clone())
+    }
+
+    /// Run seeds in the background, with at most `capacity` results waiting
+    /// to be received; see [`Pipeline::spawn`]
+    pub fn spawn<S>(&self, seeds: S, capacity: usize) -> Receiver<PipelineResult>
+    where
+        S: Stream<Item = String> + Send + 'static,
+    {
+        Pipeline::spawn(seeds, self.config.clone(), capacity)
+    }
+
+    /// Run the steps of `workflow` with this engine; see [`Workflow::run`]
+    pub async fn run_workflow(&self, workflow: &Workflow) -> Result<WorkflowReport, WorkflowError> {
+        workflow.run(&self.config).await
+    }
+
+    /// What happened at each fetch tier so far, per host; see [`crate::fallback`]
+    pub fn tier_stats(&self) -> BTreeMap<String, BTreeMap<FetchTier, TierStats>> {
+        self.config.tiers.stats()
+    }
+
+    /// Run a single URL through the pipeline
+    pub async fn scrape(&self, url: &str) -> PipelineResult {
+        let mut results = Box::pin(self.run([url]));
+        results
+            .next()
+            .await
+            .expect("the pipeline yields one result per seed")
+    }
+}
+
+/// Puts a [`Swoop`] together
+#[derive(Default)]
+pub struct SwoopBuilder {
+    config: PipelineConfig,
+    registry: Option<ScraperRegistry>,
+}
+
+impl SwoopBuilder {
+    /// Store extracted pages in `storage`
+    pub fn with_storage(mut self, storage: Arc<dyn StorageBackend>) -> Self {
+        self.config.storage = Some(storage);
+        self
+    }
+
+    /// Apply per-domain policies to fetching and extraction
+    pub fn with_policies(mut self, policies: Arc<PolicyResolver>) -> Self {
+        self.config.policies = Some(policies);
+        self
+    }
+
+    /// Keep body fingerprints in `store`, so pages that haven't changed since
+    /// their last fetch are neither extracted nor stored again
+    pub fn with_fingerprints(mut self, store: Arc<dyn FingerprintStore>) -> Self {
+        self.config.fingerprints = Some(store);
+        self
+    }
+
+    /// Render the pages of domains with a `browser_profile` in persistent
+    /// profiles from `store`
+    pub fn with_browser_profiles(mut self, store: Arc<ProfileStore>) -> Self {
+        self.config.browser_profiles = Some(store);
+        self
+    }
+
+    /// Keep the bodies the safety filters quarantine in `store`
+    pub fn with_quarantine(mut self, store: Arc<dyn QuarantineStore>) -> Self {
+        self.config.quarantine = Some(store);
+        self
+    }
+
+    /// Anti-bot settings of domains whose policy leaves them unset
+    pub fn with_antibot(mut self, anti_bot: AntiBotSettings) -> Self {
+        self.config.anti_bot = anti_bot;
+        self
+    }
+
+    /// Render browser-only, walled and still-blocked pages in `pool`
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.config.browser = Some(pool);
+        self
+    }
+
+    /// Also run `pool`, for the pages of domains whose `browser_engine` is
+    /// the one it runs
+    pub fn with_engine(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.config.engines.push(pool);
+        self
+    }
+
+    /// Pages in flight at once, 10 by default
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency;
+        self
+    }
+
+    /// Rate profile of URLs whose policy has none
+    pub fn with_rate(mut self, profile: RateProfile) -> Self {
+        self.config.rate = Some(profile);
+        self
+    }
+
+    /// Send `name: value` with every request
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Send `headers` with every request
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.config.headers.extend(headers);
+        self
+    }
+
+    /// Extract with `registry` instead of the built-in scrapers
+    pub fn with_registry(mut self, registry: ScraperRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn build(self) -> Swoop {
+        let mut config = self.config;
+        let mut registry = self.registry.unwrap_or_default();
+        if let Some(policies) = &config.policies {
+            registry = registry.with_policies(policies.clone());
+        }
+        config.registry = Arc::new(registry);
+        Swoop { config }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stage;
+    use scrapers::safety::{Hazard, SafetyAction};
+    use storage::local_store::LocalStore;
+    use swoop_core::test_support::FixtureServer;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_built_engine_scrapes_and_stores() {
+        let server = FixtureServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(LocalStore::new(dir.path()).await.unwrap());
+        let swoop = Swoop::builder()
+            .with_storage(store.clone())
+            .with_concurrency(2)
+            .with_header("Accept-Language", "en-GB")
+            .build();
+
+        let result = swoop.scrape(&server.url("/article")).await;
+        assert!(result.is_success(), "{:?}", result.error);
+        let stored = store
+            .get_content(&result.stored_id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.title.as_deref(), Some("Fixture Article"));
+
+        let requests = server.mock_server().received_requests().await.unwrap();
+        assert_eq!(requests[0].headers["accept-language"], "en-GB");
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_pages_skip_extraction_and_storage() {
+        let server = FixtureServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(LocalStore::new(dir.path()).await.unwrap());
+        let swoop = Swoop::builder()
+            .with_storage(store.clone())
+            .with_fingerprints(store.clone())
+            .build();
+
+        let url = server.url("/article");
+        let first = swoop.scrape(&url).await;
+        assert!(first.is_success() && !first.unchanged);
+        let again = swoop.scrape(&url).await;
+        assert!(again.is_success(), "{:?}", again.error);
+        assert!(again.unchanged);
+        assert!(again.content.is_none());
+        assert_eq!(again.stored_id, first.stored_id);
+        assert_eq!(store.list_content().await.unwrap().len(), 1);
+
+        let fingerprint = store.get_fingerprint(&url).await.unwrap().unwrap();
+        assert_eq!(fingerprint.unchanged, 1);
+        assert_eq!(fingerprint.stored_id, first.stored_id);
+    }
+
+    #[tokio::test]
+    async fn test_unsafe_bodies_are_rejected_or_quarantined() {
+        let server = FixtureServer::start().await;
+        // DOS stub pointing at a PE header
+        let mut executable = vec![0u8; 0x48];
+        executable[..2].copy_from_slice(b"MZ");
+        executable[0x3c] = 0x40;
+        executable[0x40..0x44].copy_from_slice(b"PE\0\0");
+        for (route, content_type) in [
+            ("/setup.exe", "application/x-msdownload"),
+            ("/photo.jpg", "image/jpeg"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(executable.clone(), content_type),
+                )
+                .mount(server.mock_server())
+                .await;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(LocalStore::new(dir.path()).await.unwrap());
+        let swoop = Swoop::builder()
+            .with_storage(store.clone())
+            .with_quarantine(store.clone())
+            .build();
+
+        let installer = swoop.scrape(&server.url("/setup.exe")).await;
+        assert_eq!(installer.error.unwrap().stage, Stage::Fetch);
+        let verdict = installer.safety.unwrap();
+        assert_eq!(verdict.hazard, Hazard::Executable);
+        assert_eq!(verdict.action, SafetyAction::Reject);
+
+        let disguised = swoop.scrape(&server.url("/photo.jpg")).await;
+        assert!(disguised.error.unwrap().message.contains("kept at"));
+        assert_eq!(disguised.safety.unwrap().hazard, Hazard::Malware);
+        let quarantined = store.list_quarantined().await.unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].url, server.url("/photo.jpg"));
+        assert!(store.list_content().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_policies_reach_the_registry() {
+        let server = FixtureServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/noindex"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<html><head><meta name="robots" content="noindex"></head></html>"#,
+            ))
+            .mount(server.mock_server())
+            .await;
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [default]
+            respect_robots_meta = true
+            "#,
+        )
+        .unwrap();
+        let swoop = Swoop::builder().with_policies(Arc::new(policies)).build();
+
+        let error = swoop.scrape(&server.url("/noindex")).await.error.unwrap();
+        assert_eq!(error.stage, Stage::Extract);
+        assert!(error.message.contains("noindex"));
+        assert!(Swoop::builder()
+            .build()
+            .scrape(&server.url("/noindex"))
+            .await
+            .is_success());
+    }
+}
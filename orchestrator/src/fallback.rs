@@ -0,0 +1,173 @@
+//! Fetch tier budgets and statistics
+//!
+//! Pages escalate through the [`FetchTier`]s of their policy's fallback
+//! chain (see [`ResolvedPolicy::fetch_chain`]) until one of them serves the
+//! page. The [`TierLedger`] a pipeline shares keeps each host within the
+//! [`TierLimits`] of every tier and counts what happened at each one, so a
+//! job can tell which domains plain HTTP still serves and which need the
+//! browser.
+//!
+//! [`ResolvedPolicy::fetch_chain`]: scrapers::policy::ResolvedPolicy::fetch_chain
+
+use scrapers::policy::{FetchTier, TierLimits};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// What happened to a host's pages at one tier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TierStats {
+    /// Pages the tier served
+    pub served: u64,
+    /// Pages blocked or walled at the tier that went on to the next one
+    pub escalated: u64,
+    /// Pages that failed at the tier, with no tier after it to try
+    pub failed: u64,
+    /// Pages that skipped the tier because the host's budget for it was spent
+    pub over_budget: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TierOutcome {
+    Served,
+    Escalated,
+    Failed,
+    OverBudget,
+}
+
+#[derive(Debug, Default)]
+struct HostUsage {
+    requests: u64,
+    /// Earliest time the next request may go out
+    next_slot: Option<Instant>,
+}
+
+/// Budgets, pacing and statistics of the fetch tiers, per host
+#[derive(Debug, Default)]
+pub struct TierLedger {
+    usage: Mutex<HashMap<(String, FetchTier), HostUsage>>,
+    stats: Mutex<BTreeMap<String, BTreeMap<FetchTier, TierStats>>>,
+}
+
+impl TierLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one of `host`'s requests at `tier`, waiting for its turn under
+    /// `limits`; false when the host's budget for the tier is spent
+    pub(crate) async fn acquire(&self, host: &str, tier: FetchTier, limits: &TierLimits) -> bool {
+        let wait = {
+            let mut usage = self.usage.lock().unwrap();
+            let usage = usage.entry((host.to_string(), tier)).or_default();
+            if limits.max_requests.is_some_and(|max| usage.requests >= max) {
+                return false;
+            }
+            usage.requests += 1;
+            match limits.requests_per_minute.filter(|rpm| *rpm > 0) {
+                Some(rpm) => {
+                    let now = Instant::now();
+                    let slot = usage.next_slot.map_or(now, |next| next.max(now));
+                    usage.next_slot = Some(slot + Duration::from_secs(60) / rpm);
+                    slot - now
+                }
+                None => Duration::ZERO,
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        true
+    }
+
+    pub(crate) fn record(&self, host: &str, tier: FetchTier, outcome: TierOutcome) {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats
+            .entry(host.to_string())
+            .or_default()
+            .entry(tier)
+            .or_default();
+        match outcome {
+            TierOutcome::Served => stats.served += 1,
+            TierOutcome::Escalated => stats.escalated += 1,
+            TierOutcome::Failed => stats.failed += 1,
+            TierOutcome::OverBudget => stats.over_budget += 1,
+        }
+    }
+
+    /// What happened at each tier, per host
+    pub fn stats(&self) -> BTreeMap<String, BTreeMap<FetchTier, TierStats>> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// The tier that served most of `host`'s pages
+    pub fn serving_tier(&self, host: &str) -> Option<FetchTier> {
+        let stats = self.stats.lock().unwrap();
+        stats
+            .get(host)?
+            .iter()
+            .filter(|(_, stats)| stats.served > 0)
+            .max_by_key(|(tier, stats)| (stats.served, std::cmp::Reverse(**tier)))
+            .map(|(tier, _)| *tier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_budgets_and_pacing_are_per_host() {
+        let ledger = TierLedger::new();
+        let limits = TierLimits {
+            max_requests: Some(2),
+            requests_per_minute: Some(600),
+        };
+
+        let started = Instant::now();
+        for _ in 0..2 {
+            assert!(
+                ledger
+                    .acquire("a.example", FetchTier::Browser, &limits)
+                    .await
+            );
+        }
+        // The second request waited for its slot, 100ms after the first
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert!(
+            !ledger
+                .acquire("a.example", FetchTier::Browser, &limits)
+                .await
+        );
+        assert!(
+            ledger
+                .acquire("b.example", FetchTier::Browser, &limits)
+                .await
+        );
+        assert!(
+            ledger
+                .acquire("a.example", FetchTier::Http, &TierLimits::default())
+                .await
+        );
+    }
+
+    #[test]
+    fn test_stats_tell_which_tier_serves_a_host() {
+        let ledger = TierLedger::new();
+        ledger.record("shop.example", FetchTier::Http, TierOutcome::Escalated);
+        ledger.record("shop.example", FetchTier::Browser, TierOutcome::Served);
+        ledger.record("shop.example", FetchTier::Http, TierOutcome::Served);
+        ledger.record("shop.example", FetchTier::Browser, TierOutcome::Served);
+
+        let stats = ledger.stats();
+        let http = stats["shop.example"][&FetchTier::Http];
+        assert_eq!((http.served, http.escalated), (1, 1));
+        assert_eq!(
+            ledger.serving_tier("shop.example"),
+            Some(FetchTier::Browser)
+        );
+        assert_eq!(ledger.serving_tier("other.example"), None);
+    }
+}
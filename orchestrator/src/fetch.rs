@@ -0,0 +1,331 @@
+//! Fetching past bot protection
+//!
+//! [`fetch_past_bot_protection`] fetches a page the way a domain's
+//! [`ResolvedPolicy`] asks for: with its pinned user agent, proxy, retries,
+//! warm-up visits and referrer chain. When a bot protection vendor blocks
+//! the page, it is fetched again with the vendor's [`VendorStrategy`].
+//! [`fetch_plain`] and [`fetch_stealthily`] are the two steps on their own,
+//! as the HTTP tiers of the pipeline's fallback chain. Proxies of domains
+//! with `verify_exit` are checked by the policy's
+//! [`ExitChecks`](scrapers::anti_bot::exit_checks::ExitChecks) before any
+//! request goes through them.
+
+use bytes::Bytes;
+use scrapers::{
+    anti_bot::{
+        vendors::{detect_vendor, BotVendor, FingerprintKind, VendorStrategy},
+        warm_up::WarmUpPage,
+    },
+    policy::ResolvedPolicy,
+    utils::is_bot_protected,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use swoop_core::trace::{FetchTrace, FetchTracer, TracedFetch};
+use tracing::{debug, info};
+
+/// HTTP fetch function with retry logic, returning the trace of the last attempt
+///
+/// Retries, the proxy, the IP version, TLS trust and authentication follow
+/// `policy`; without a rate profile a failed fetch is retried once after
+/// 200ms. Unless `headers` set a User-Agent, the host's pinned desktop user
+/// agent and its client hints are sent. With `verify_exit`, nothing is sent
+/// through a proxy whose exit fails verification.
+pub async fn fetch_url_traced(
+    url: &str,
+    headers: &HashMap<String, String>,
+    policy: &ResolvedPolicy,
+) -> (
+    Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    FetchTrace,
+) {
+    info!("Fetching URL: {}", url);
+    let mut headers = headers.clone();
+    let user_agent = match headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+    {
+        Some((_, user_agent)) => user_agent.clone(),
+        None => {
+            let entry = policy.user_agent(url, FingerprintKind::Desktop);
+            entry.apply(&mut headers);
+            entry.user_agent
+        }
+    };
+    let tracer = FetchTracer::new()
+        .with_timeout(Duration::from_secs(30))
+        .and_then(|tracer| tracer.with_user_agent(&user_agent))
+        .and_then(|tracer| tracer.with_address_family(policy.address_family))
+        .and_then(|tracer| match &policy.proxy {
+            Some(proxy) => tracer.with_proxy(proxy),
+            None => Ok(tracer),
+        })
+        .and_then(|tracer| match &policy.tls {
+            Some(tls) => tracer.with_tls(tls.clone()),
+            None => Ok(tracer),
+        });
+    let tracer = match tracer {
+        Ok(tracer) => match &policy.auth {
+            Some(auth) => tracer.with_auth(auth.clone()),
+            None => tracer,
+        },
+        Err(e) => return (Err(e.into()), FetchTrace::new(url)),
+    };
+    if let Some(proxy) = policy.proxy.as_deref().filter(|_| policy.verify_exit) {
+        if let Err(e) = verify_exit(proxy, policy).await {
+            return (Err(e.into()), FetchTrace::new(url));
+        }
+    }
+    let profile = policy.rate.as_ref();
+
+    let max_attempts = profile.map_or(2, |profile| profile.max_attempts.max(1));
+    let proxy_tier = policy.proxy_tier.map(|tier| tier.as_str().to_string());
+    let mut failure = None;
+    let mut bytes_received = 0;
+    for attempt in 1..=max_attempts {
+        let TracedFetch { body, mut trace } = tracer.fetch(url, &headers).await;
+        trace.attempts = attempt;
+        trace.proxy_tier = proxy_tier.clone();
+        // Failed attempts use the proxy's bandwidth too
+        bytes_received += trace.bytes_received;
+        trace.bytes_received = bytes_received;
+        let error = match (body, trace.status) {
+            (Ok(bytes), Some(status)) if (200..300).contains(&status) => {
+                info!("Finished fetching URL: {} (attempt {})", url, attempt);
+                return (Ok(bytes), trace);
+            }
+            (Ok(_), status) => format!("HTTP {}", status.unwrap_or_default()),
+            (Err(e), _) => e.to_string(),
+        };
+        failure = Some((error, trace));
+        if attempt < max_attempts {
+            let delay = profile.map_or(Duration::from_millis(200), |profile| {
+                profile.retry_delay(attempt)
+            });
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    let (error, trace) = failure.expect("at least one attempt was made");
+    (Err(error.into()), trace)
+}
+
+/// Check where `proxy` exits before the policy's requests go through it
+async fn verify_exit(proxy: &str, policy: &ResolvedPolicy) -> Result<(), String> {
+    let fetch = |endpoint: String| async move {
+        let tracer = FetchTracer::new()
+            .with_timeout(Duration::from_secs(10))
+            .and_then(|tracer| tracer.with_address_family(policy.address_family))
+            .and_then(|tracer| tracer.with_proxy(proxy))
+            .map_err(|e| e.to_string())?;
+        let TracedFetch { body, trace } = tracer.fetch(&endpoint, &HashMap::new()).await;
+        match (body, trace.status) {
+            (Ok(bytes), Some(200..=299)) => Ok(swoop_core::body_to_string(bytes)),
+            (Ok(_), status) => Err(format!("HTTP {}", status.unwrap_or_default())),
+            (Err(e), _) => Err(e.to_string()),
+        }
+    };
+    let country = policy.locale.as_ref().map(|locale| locale.country.as_str());
+    let verification = policy.exit_checks.verify(proxy, country, fetch).await;
+    if verification.is_usable() {
+        return Ok(());
+    }
+    Err(format!(
+        "Proxy {} refused: {}",
+        verification.proxy,
+        verification
+            .reason
+            .as_deref()
+            .unwrap_or(verification.status.as_str())
+    ))
+}
+
+/// Vendor whose bot protection blocked a fetch
+///
+/// 403 and 503 responses are attributed by their headers, successful ones by
+/// a challenge page in the body. 429s are rate limits, not blocks.
+pub fn blocked_vendor(
+    result: &Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    trace: &FetchTrace,
+) -> Option<BotVendor> {
+    match result {
+        Ok(body) => {
+            let body = String::from_utf8_lossy(body);
+            if is_bot_protected(&body) {
+                detect_vendor(&BTreeMap::new(), &body)
+            } else {
+                None
+            }
+        }
+        Err(_) if matches!(trace.status, Some(403 | 503)) => {
+            detect_vendor(&trace.response_headers, "")
+        }
+        Err(_) => None,
+    }
+}
+
+/// Fetch `url`, retrying with the vendor's strategy when bot protection blocks
+/// it; domains whose policy names a vendor use its strategy from the start
+pub async fn fetch_past_bot_protection(
+    url: &str,
+    headers: &HashMap<String, String>,
+    policy: &ResolvedPolicy,
+) -> (
+    Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    FetchTrace,
+    Option<BotVendor>,
+) {
+    if let Some(vendor) = policy.anti_bot {
+        let (result, trace) =
+            fetch_with_strategy(url, headers, policy, &policy.strategy(vendor)).await;
+        return (result, trace, Some(vendor));
+    }
+    let (result, trace) = fetch_plain(url, headers, policy).await;
+    match blocked_vendor(&result, &trace) {
+        Some(vendor) => {
+            info!(
+                "🛡️  {} blocked by {}, retrying with its strategy",
+                url,
+                vendor.as_str()
+            );
+            forget_session(url, policy);
+            let (result, trace) = fetch_stealthily(url, headers, policy, Some(vendor)).await;
+            (result, trace, Some(vendor))
+        }
+        None => (result, trace, None),
+    }
+}
+
+/// Drop the warm session with `url`'s host after it got blocked, so the
+/// next request warms up anew without the cookies that got it blocked
+pub fn forget_session(url: &str, policy: &ResolvedPolicy) {
+    if let Some(host) = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+    {
+        policy.warm_ups.reset(&host);
+    }
+}
+
+/// Fetch `url` with the domain's own user agent, warming the session up
+/// first when the policy asks for it
+pub async fn fetch_plain(
+    url: &str,
+    headers: &HashMap<String, String>,
+    policy: &ResolvedPolicy,
+) -> (
+    Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    FetchTrace,
+) {
+    let mut request_headers = headers.clone();
+    if policy.warm_up == Some(true) {
+        warm_up(url, &mut request_headers, policy, FingerprintKind::Desktop).await;
+    }
+    follow_referrer_chain(url, headers, &mut request_headers, policy);
+    fetch_url_traced(url, &request_headers, policy).await
+}
+
+/// Fetch `url` the way `vendor`'s strategy gets past its bot protection;
+/// without a known vendor, through a warmed-up session
+pub async fn fetch_stealthily(
+    url: &str,
+    headers: &HashMap<String, String>,
+    policy: &ResolvedPolicy,
+    vendor: Option<BotVendor>,
+) -> (
+    Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    FetchTrace,
+) {
+    match vendor {
+        Some(vendor) => fetch_with_strategy(url, headers, policy, &policy.strategy(vendor)).await,
+        None => {
+            let mut request_headers = headers.clone();
+            warm_up(url, &mut request_headers, policy, FingerprintKind::Desktop).await;
+            follow_referrer_chain(url, headers, &mut request_headers, policy);
+            fetch_url_traced(url, &request_headers, policy).await
+        }
+    }
+}
+
+/// Fetch with a vendor strategy's fingerprint and proxy, after warming the
+/// session up if the strategy asks for it
+async fn fetch_with_strategy(
+    url: &str,
+    headers: &HashMap<String, String>,
+    policy: &ResolvedPolicy,
+    strategy: &VendorStrategy,
+) -> (
+    Result<Bytes, Box<dyn std::error::Error + Send + Sync>>,
+    FetchTrace,
+) {
+    let mut policy = policy.clone();
+    if strategy.proxy.is_some() {
+        policy.proxy = strategy.proxy.clone();
+        policy.proxy_tier = strategy.proxy_tier;
+    }
+    let mut headers = headers.clone();
+    policy
+        .user_agent(url, strategy.fingerprint)
+        .apply(&mut headers);
+    let configured = headers.clone();
+    if strategy.warm_up {
+        warm_up(url, &mut headers, &policy, strategy.fingerprint).await;
+    }
+    follow_referrer_chain(url, &configured, &mut headers, &policy);
+    fetch_url_traced(url, &headers, &policy).await
+}
+
+/// Send the `Referer` the policy's referrer chain picks for `url`, unless the
+/// `configured` headers already set one
+fn follow_referrer_chain(
+    url: &str,
+    configured: &HashMap<String, String>,
+    headers: &mut HashMap<String, String>,
+    policy: &ResolvedPolicy,
+) {
+    let Some(referrers) = &policy.referrers else {
+        return;
+    };
+    if configured
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("referer"))
+    {
+        return;
+    }
+    headers.retain(|name, _| !name.eq_ignore_ascii_case("referer"));
+    if let Some(referer) = referrers.navigate(url) {
+        headers.insert("Referer".to_string(), referer);
+    }
+}
+
+/// Browse `url`'s home page and category pages first unless this session
+/// with its host already did, and send the cookies and referrer picked up
+/// with `headers`
+async fn warm_up(
+    url: &str,
+    headers: &mut HashMap<String, String>,
+    policy: &ResolvedPolicy,
+    device: FingerprintKind,
+) {
+    let fetch = |page: String, headers: HashMap<String, String>| async move {
+        let (result, trace) = fetch_url_traced(&page, &headers, policy).await;
+        debug!(
+            "🔥 Warm-up visit to {}: {}",
+            page,
+            trace
+                .status
+                .map_or("failed".to_string(), |status| status.to_string())
+        );
+        WarmUpPage {
+            body: result.map(swoop_core::body_to_string).unwrap_or_default(),
+            set_cookie: trace.response_headers.get("set-cookie").cloned(),
+        }
+    };
+    let session = policy.warm_ups.session(url, device, headers, fetch).await;
+    if let Some(referrers) = &policy.referrers {
+        referrers.record_visits(&session.visited);
+    }
+    session.apply(headers);
+}
@@ -0,0 +1,1004 @@
+//! Orchestrator crate - The whole scraping engine behind one call
+//!
+//! [`Pipeline::run`] takes seed URLs through the stages `swoop scrape` runs
+//! them through: fetching past bot protection (see [`fetch`]), rendering
+//! browser-only, walled and still-blocked pages in a browser, extracting them
+//! with the platform scrapers and storing what was extracted. Pages escalate
+//! from plain HTTP to the browser along their domain's fallback chain, within
+//! per-tier budgets (see [`fallback`]). Pages whose body is the same as at
+//! their last fetch skip extraction and storage when the pipeline keeps body
+//! fingerprints. Executables, oversized binaries and suspected malware are
+//! stopped before extraction by the policy's
+//! [`SafetyFilter`](scrapers::safety::SafetyFilter), and quarantined ones are
+//! kept in a [`QuarantineStore`] for review. Chromium and Firefox pools can
+//! run side by side, each rendering the domains whose `browser_engine` it
+//! runs. Domains naming a `browser_profile` render in a persistent profile
+//! from a [`ProfileStore`], so a site sees a returning visitor. Results
+//! stream back as pages finish, so programmatic users get the full engine
+//! without going through the CLI. [`Swoop::builder`] puts a pipeline
+//! together, and [`prelude`] has the types it takes from the other crates.
+//! Jobs of several steps, such as discovering seeds, crawling them and
+//! exporting what fits a rule set, are declared as a YAML [`workflow`].
+
+use futures::{stream, Stream, StreamExt};
+use scrapers::{
+    anti_bot::{
+        exit_checks::{browser_leak_risks, LeakRisk},
+        referrers::ReferrerChain,
+        user_agents::UserAgentPool,
+        vendors::BotVendor,
+    },
+    browser::{select_pool, BrowserPool},
+    extractors::{extract_images, extract_links},
+    locale::LocaleProfile,
+    metadata,
+    platforms::ScraperRegistry,
+    policy::{FetchTier, PolicyResolver, ResolvedPolicy},
+    rate_limiter::RateProfile,
+    safety::{SafetyAction, SafetyVerdict},
+    utils::extract_domain,
+    walls::WallDetector,
+    ExtractedContent,
+};
+use std::{collections::HashMap, fmt, sync::Arc};
+use storage::{
+    browser_profiles::ProfileStore,
+    fingerprints::{self, Sighting},
+    models::{PageFingerprint, QuarantinedResponse, StoredContent},
+    FingerprintStore, QuarantineStore, StorageBackend,
+};
+use swoop_core::{text::SharedStr, trace::FetchTrace};
+use tokio::sync::mpsc::{self, Receiver};
+use tracing::{debug, info, warn};
+
+pub mod builder;
+pub mod fallback;
+pub mod fetch;
+pub mod prelude;
+pub mod workflow;
+
+pub use builder::{Swoop, SwoopBuilder};
+use fallback::{TierLedger, TierOutcome};
+use fetch::{blocked_vendor, fetch_plain, fetch_stealthily, forget_session};
+
+/// Anti-bot settings of URLs whose domain policy leaves them unset
+#[derive(Debug, Clone, Default)]
+pub struct AntiBotSettings {
+    /// Warm sessions up before their first request
+    pub warm_up: bool,
+    /// Send `Referer` headers following the crawl's navigation graph
+    pub referrers: Option<Arc<ReferrerChain>>,
+    /// Market requests emulate
+    pub locale: Option<LocaleProfile>,
+}
+
+/// Configuration of a pipeline run
+#[derive(Clone)]
+pub struct PipelineConfig {
+    /// Pages in flight at once
+    pub concurrency: usize,
+    /// Headers sent with every request, over the ones a domain policy sets
+    pub headers: HashMap<String, String>,
+    /// Per-domain policies: rate profiles, proxies and bot protection strategies
+    ///
+    /// The registry applies its own policies to extraction; see
+    /// [`ScraperRegistry::with_policies`].
+    pub policies: Option<Arc<PolicyResolver>>,
+    /// Rate profile of URLs whose policy has none
+    pub rate: Option<RateProfile>,
+    pub anti_bot: AntiBotSettings,
+    /// Scrapers extracting the fetched pages
+    pub registry: Arc<ScraperRegistry>,
+    /// Browser for browser-only domains, walls and blocks a vendor strategy
+    /// renders; without one those pages fail
+    pub browser: Option<Arc<BrowserPool>>,
+    /// Pools of further engines, running side by side with `browser` for
+    /// domains whose `browser_engine` it doesn't run
+    pub engines: Vec<Arc<BrowserPool>>,
+    /// Persistent profiles the pages of domains with a `browser_profile` are
+    /// rendered in; without them those pages render in pooled sessions
+    pub browser_profiles: Option<Arc<ProfileStore>>,
+    /// Backend extracted pages are stored in; without one they are only
+    /// returned
+    pub storage: Option<Arc<dyn StorageBackend>>,
+    /// Body fingerprints of the pages fetched before; with them, pages whose
+    /// body hasn't changed since are neither extracted nor stored again
+    pub fingerprints: Option<Arc<dyn FingerprintStore>>,
+    /// Where bodies the safety filters quarantine are kept; without one they
+    /// are dropped like rejected ones
+    pub quarantine: Option<Arc<dyn QuarantineStore>>,
+    /// Fetch tier budgets and statistics, shared by clones of the config
+    pub tiers: Arc<TierLedger>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 10,
+            headers: HashMap::new(),
+            policies: None,
+            rate: None,
+            anti_bot: AntiBotSettings::default(),
+            registry: Arc::new(ScraperRegistry::default()),
+            browser: None,
+            engines: Vec::new(),
+            browser_profiles: None,
+            storage: None,
+            fingerprints: None,
+            quarantine: None,
+            tiers: Arc::new(TierLedger::new()),
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Effective policy for `url`: its domain policy, if any, with the
+    /// pipeline's rate profile and anti-bot settings filling in the rest
+    fn policy(&self, url: &str) -> ResolvedPolicy {
+        let mut policy = match &self.policies {
+            Some(policies) => policies.resolve(url, self.rate.as_ref()),
+            None => ResolvedPolicy {
+                rate: self.rate.clone(),
+                user_agents: UserAgentPool::shared(),
+                ..ResolvedPolicy::default()
+            },
+        };
+        if let (None, Some(locale)) = (&policy.locale, &self.anti_bot.locale) {
+            policy.localize(locale.clone());
+        }
+        policy.warm_up.get_or_insert(self.anti_bot.warm_up);
+        if policy.referrers.is_none() {
+            policy.referrers = self.anti_bot.referrers.clone();
+        }
+        policy
+    }
+
+    /// Browser pool for pages of `policy`: the one running its domain's
+    /// `browser_engine`, or `browser` when the domain names none
+    fn browser_for(&self, policy: &ResolvedPolicy) -> Option<&Arc<BrowserPool>> {
+        select_pool(
+            self.browser.iter().chain(&self.engines),
+            policy.browser_engine,
+        )
+    }
+}
+
+/// Stage of the pipeline a page failed at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Fetch,
+    Extract,
+    Store,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Fetch => "fetch",
+            Stage::Extract => "extract",
+            Stage::Store => "store",
+        }
+    }
+}
+
+/// Why a page didn't make it through the pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineError {
+    pub stage: Stage,
+    pub message: String,
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.stage.as_str(), self.message)
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// What became of one seed URL
+#[derive(Debug)]
+pub struct PipelineResult {
+    pub url: String,
+    /// The extracted page; kept when only storing it failed
+    pub content: Option<ExtractedContent>,
+    /// ID the storage backend gave the page; for unchanged pages, the one it
+    /// was last stored under
+    pub stored_id: Option<String>,
+    /// Trace of the last HTTP fetch; none for pages only rendered in a browser
+    pub trace: Option<FetchTrace>,
+    /// Bot protection vendor that blocked the page at some point
+    pub vendor: Option<BotVendor>,
+    /// Whether the page extracted was rendered in a browser
+    pub rendered: bool,
+    /// Fetch tier that served the page
+    pub tier: Option<FetchTier>,
+    /// Whether the body was the same as at the last fetch, so the page was
+    /// neither extracted nor stored again
+    pub unchanged: bool,
+    /// Why the safety filters stopped the fetched body
+    pub safety: Option<SafetyVerdict>,
+    pub error: Option<PipelineError>,
+}
+
+impl PipelineResult {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            content: None,
+            stored_id: None,
+            trace: None,
+            vendor: None,
+            rendered: false,
+            tier: None,
+            unchanged: false,
+            safety: None,
+            error: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn failed(mut self, stage: Stage, message: String) -> Self {
+        debug!("❌ {} failed at {}: {}", self.url, stage.as_str(), message);
+        self.error = Some(PipelineError { stage, message });
+        self
+    }
+}
+
+/// Fetch → anti-bot → extract → store, for many URLs at once
+pub struct Pipeline;
+
+/// What one fetch tier got for a page
+enum Attempt {
+    Served(String),
+    /// Blocked by bot protection, and why
+    Blocked(String),
+    /// The walled page's HTML
+    Walled(String),
+    /// A body the safety filters stopped
+    Unsafe(SafetyVerdict, bytes::Bytes),
+    Failed(String),
+}
+
+impl Pipeline {
+    /// Run every seed through the pipeline, `config.concurrency` at a time
+    ///
+    /// Results come in the order pages finish, one per seed.
+    pub fn run<I>(seeds: I, config: PipelineConfig) -> impl Stream<Item = PipelineResult>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let seeds: Vec<String> = seeds.into_iter().map(Into::into).collect();
+        Self::run_stream(stream::iter(seeds), config)
+    }
+
+    /// Run seeds as `seeds` yields them, for jobs whose seeds aren't known
+    /// up front, such as ones fed from a queue
+    ///
+    /// Seeds are only taken while fewer than `config.concurrency` pages are
+    /// in flight, and pages only start while their results are consumed.
+    pub fn run_stream<S>(seeds: S, config: PipelineConfig) -> impl Stream<Item = PipelineResult>
+    where
+        S: Stream<Item = String>,
+    {
+        let concurrency = config.concurrency.max(1);
+        let config = Arc::new(config);
+        seeds
+            .map(move |url| {
+                let config = config.clone();
+                async move { Self::process(&config, url).await }
+            })
+            .buffer_unordered(concurrency)
+    }
+
+    /// Run `seeds` in the background, handing results over a channel that
+    /// holds at most `capacity` of them
+    ///
+    /// The pipeline waits while the channel is full, so a slow consumer,
+    /// such as an exporter, holds the crawl back instead of results piling up
+    /// in memory. Dropping the receiver stops the run.
+    pub fn spawn<S>(seeds: S, config: PipelineConfig, capacity: usize) -> Receiver<PipelineResult>
+    where
+        S: Stream<Item = String> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        tokio::spawn(async move {
+            let results = Self::run_stream(seeds, config);
+            futures::pin_mut!(results);
+            while let Some(result) = results.next().await {
+                if sender.send(result).await.is_err() {
+                    debug!("🛑 Results are no longer received, stopping the pipeline");
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    async fn process(config: &PipelineConfig, url: String) -> PipelineResult {
+        let mut result = PipelineResult::new(url);
+        let html = match Self::fetch(config, &mut result).await {
+            Ok(html) => html,
+            Err(message) => return result.failed(Stage::Fetch, message),
+        };
+        let fingerprint = match &config.fingerprints {
+            Some(store) => match Self::sight(store.as_ref(), &mut result, &html).await {
+                Some(fingerprint) => Some((store, fingerprint)),
+                None => return result,
+            },
+            None => None,
+        };
+
+        let (mut result, html) = Self::extract(config, result, html).await;
+        if let (Some(html), Some(content), Some(storage)) =
+            (&html, &result.content, &config.storage)
+        {
+            let stored = Self::stored_content(config, &result.url, html, content);
+            match storage.store_content(&stored).await {
+                Ok(id) => result.stored_id = Some(id),
+                Err(e) => return result.failed(Stage::Store, format!("{:#}", e)),
+            }
+        }
+        if result.is_success() {
+            if let Some((store, fingerprint)) = fingerprint {
+                Self::save_fingerprint(store.as_ref(), &result, fingerprint).await;
+            }
+            info!("✅ Successfully scraped: {}", result.url);
+        }
+        result
+    }
+
+    /// Compare the fetched body with the URL's fingerprint
+    ///
+    /// An unchanged body is recorded as seen and marks the result unchanged,
+    /// with `None` for the caller to stop there. A new or changed body gives
+    /// the fingerprint to save once the page is through. Fingerprints that
+    /// can't be read count as none: the page is then extracted as usual.
+    async fn sight(
+        store: &dyn FingerprintStore,
+        result: &mut PipelineResult,
+        html: &str,
+    ) -> Option<PageFingerprint> {
+        let previous = store
+            .get_fingerprint(&result.url)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "⚠️  Failed to read the fingerprint of {}: {:#}",
+                    result.url, e
+                );
+                None
+            });
+        let (fingerprint, sighting) =
+            fingerprints::observe(previous, &result.url, html.as_bytes(), chrono::Utc::now());
+        if sighting != Sighting::Unchanged {
+            return Some(fingerprint);
+        }
+        debug!(
+            "⏭️  {} unchanged since {}, skipping extraction",
+            result.url, fingerprint.last_changed
+        );
+        if let Err(e) = store.save_fingerprint(&fingerprint).await {
+            warn!(
+                "⚠️  Failed to save the fingerprint of {}: {:#}",
+                result.url, e
+            );
+        }
+        result.unchanged = true;
+        result.stored_id = fingerprint.stored_id;
+        None
+    }
+
+    /// Fetch and extract `url`, without storing it; the page's HTML comes
+    /// with the result unless a stage failed
+    ///
+    /// With body fingerprints, an unchanged page is marked so and comes with
+    /// its HTML but isn't extracted, and an extracted page has its
+    /// fingerprint saved.
+    pub(crate) async fn fetch_and_extract(
+        config: &PipelineConfig,
+        url: String,
+    ) -> (PipelineResult, Option<SharedStr>) {
+        let mut result = PipelineResult::new(url);
+        let html = match Self::fetch(config, &mut result).await {
+            Ok(html) => html,
+            Err(message) => return (result.failed(Stage::Fetch, message), None),
+        };
+        let fingerprint = match &config.fingerprints {
+            Some(store) => match Self::sight(store.as_ref(), &mut result, &html).await {
+                Some(fingerprint) => Some((store, fingerprint)),
+                None => return (result, Some(html.into())),
+            },
+            None => None,
+        };
+        let (result, html) = Self::extract(config, result, html).await;
+        if let (true, Some((store, fingerprint))) = (result.is_success(), fingerprint) {
+            Self::save_fingerprint(store.as_ref(), &result, fingerprint).await;
+        }
+        (result, html)
+    }
+
+    /// Save the fingerprint of a page that made it through, with the ID it
+    /// was stored under
+    async fn save_fingerprint(
+        store: &dyn FingerprintStore,
+        result: &PipelineResult,
+        mut fingerprint: PageFingerprint,
+    ) {
+        fingerprint.stored_id = result.stored_id.clone().or(fingerprint.stored_id);
+        if let Err(e) = store.save_fingerprint(&fingerprint).await {
+            warn!(
+                "⚠️  Failed to save the fingerprint of {}: {:#}",
+                result.url, e
+            );
+        }
+    }
+
+    /// Extract the fetched `html`, which the extracted title and text share
+    async fn extract(
+        config: &PipelineConfig,
+        mut result: PipelineResult,
+        html: String,
+    ) -> (PipelineResult, Option<SharedStr>) {
+        let html = SharedStr::from(html);
+        let mut content = match config.registry.reprocess(&result.url, &html).await {
+            Ok(content) => content,
+            Err(e) => return (result.failed(Stage::Extract, format!("{:#}", e)), None),
+        };
+        if result.rendered {
+            content
+                .metadata
+                .insert("fetched_via".into(), "browser".to_string());
+        }
+        result.content = Some(content);
+        (result, Some(html))
+    }
+
+    /// HTML of the result's URL, from the first tier of its policy's
+    /// fallback chain that gets the page
+    ///
+    /// Pages a bot protection vendor blocks go on to the next tier and walled
+    /// pages on to the browser, which another HTTP fingerprint won't get past
+    /// a wall. Other failures, such as a 404, and bodies the safety filters
+    /// stop end the chain. Tiers whose
+    /// budget the host has spent are skipped, and so is the browser when none
+    /// is configured or the blocking vendor's strategy rules it out; a walled
+    /// page no later tier could render is used as it is.
+    async fn fetch(config: &PipelineConfig, result: &mut PipelineResult) -> Result<String, String> {
+        let policy = config.policy(&result.url);
+        let host = url::Url::parse(&result.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let mut headers = policy.headers.clone();
+        headers.extend(config.headers.clone());
+
+        // Blocked or walled page of the last tier, until a later one does better
+        let mut pending: Option<(FetchTier, Attempt)> = None;
+        let mut failure = None;
+        for tier in policy.fetch_chain() {
+            let skip = match tier {
+                // Known to block plain requests
+                FetchTier::Http => policy.anti_bot.is_some(),
+                FetchTier::StealthHttp => matches!(pending, Some((_, Attempt::Walled(_)))),
+                FetchTier::Browser => match (config.browser_for(&policy), result.vendor) {
+                    (None, _) => {
+                        let browser = match policy.browser_engine {
+                            Some(engine) => format!("a {} browser", engine.as_str()),
+                            None => "a browser".to_string(),
+                        };
+                        failure.get_or_insert_with(|| {
+                            format!("{} needs {} but none is configured", result.url, browser)
+                        });
+                        true
+                    }
+                    (Some(_), Some(vendor)) => !policy.strategy(vendor).browser,
+                    (Some(_), None) => false,
+                },
+            };
+            if skip {
+                continue;
+            }
+            if !config
+                .tiers
+                .acquire(&host, tier, &policy.tier_limits(tier))
+                .await
+            {
+                debug!("💸 {} budget of {} is spent", tier.as_str(), host);
+                config.tiers.record(&host, tier, TierOutcome::OverBudget);
+                failure.get_or_insert_with(|| {
+                    format!("{} budget of {} is spent", tier.as_str(), host)
+                });
+                continue;
+            }
+            let after_block = match pending.take() {
+                Some((from, attempt)) => {
+                    debug!(
+                        "⤴️  Escalating {} from {} to {}",
+                        result.url,
+                        from.as_str(),
+                        tier.as_str()
+                    );
+                    config.tiers.record(&host, from, TierOutcome::Escalated);
+                    matches!(attempt, Attempt::Blocked(_))
+                }
+                None => false,
+            };
+
+            let attempt = match tier {
+                FetchTier::Http => {
+                    let (fetched, trace) = fetch_plain(&result.url, &headers, &policy).await;
+                    Self::judge(config, &policy, result, fetched, trace)
+                }
+                FetchTier::StealthHttp => {
+                    if after_block {
+                        forget_session(&result.url, &policy);
+                    }
+                    result.vendor = result.vendor.or(policy.anti_bot);
+                    let (fetched, trace) =
+                        fetch_stealthily(&result.url, &headers, &policy, result.vendor).await;
+                    Self::judge(config, &policy, result, fetched, trace)
+                }
+                FetchTier::Browser => {
+                    let browser = config
+                        .browser_for(&policy)
+                        .expect("skipped without a browser");
+                    let rendered = match Self::check_browser_leaks(browser, &policy) {
+                        Ok(()) => Self::render(config, browser, &policy, result).await,
+                        Err(message) => Err(message),
+                    };
+                    match rendered {
+                        Ok(html) => Attempt::Served(html),
+                        Err(message) => Attempt::Failed(message),
+                    }
+                }
+            };
+            match attempt {
+                Attempt::Served(html) => {
+                    config.tiers.record(&host, tier, TierOutcome::Served);
+                    result.tier = Some(tier);
+                    return Ok(html);
+                }
+                Attempt::Unsafe(verdict, body) => {
+                    config.tiers.record(&host, tier, TierOutcome::Served);
+                    return Err(Self::stop_unsafe(config, result, verdict, body).await);
+                }
+                Attempt::Failed(message) => {
+                    config.tiers.record(&host, tier, TierOutcome::Failed);
+                    return Err(message);
+                }
+                attempt => pending = Some((tier, attempt)),
+            }
+        }
+
+        match pending {
+            Some((tier, Attempt::Walled(html))) => {
+                config.tiers.record(&host, tier, TierOutcome::Served);
+                result.tier = Some(tier);
+                Ok(html)
+            }
+            Some((tier, Attempt::Blocked(message))) => {
+                config.tiers.record(&host, tier, TierOutcome::Failed);
+                Err(message)
+            }
+            _ => Err(failure.unwrap_or_else(|| format!("No fetch tier left for {}", result.url))),
+        }
+    }
+
+    /// What an HTTP tier's response means for the fallback chain
+    fn judge(
+        config: &PipelineConfig,
+        policy: &ResolvedPolicy,
+        result: &mut PipelineResult,
+        fetched: Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>>,
+        trace: FetchTrace,
+    ) -> Attempt {
+        let blocked = blocked_vendor(&fetched, &trace);
+        result.trace = Some(trace);
+        if let Some(vendor) = blocked {
+            debug!("🛡️  {} blocked by {}", result.url, vendor.as_str());
+            result.vendor = Some(vendor);
+            let why = match &fetched {
+                Ok(_) => "challenge page".to_string(),
+                Err(e) => e.to_string(),
+            };
+            return Attempt::Blocked(format!("{} (blocked by {})", why, vendor.as_str()));
+        }
+        let body = match (fetched, result.vendor) {
+            (Ok(body), _) => body,
+            (Err(e), Some(vendor)) => {
+                return Attempt::Failed(format!("{} (blocked by {})", e, vendor.as_str()))
+            }
+            (Err(e), None) => return Attempt::Failed(e.to_string()),
+        };
+        let content_type = result
+            .trace
+            .as_ref()
+            .and_then(|trace| trace.response_headers.get("content-type"));
+        if let Some(verdict) = policy.safety.check(content_type.map(String::as_str), &body) {
+            return Attempt::Unsafe(verdict, body);
+        }
+
+        let html = swoop_core::body_to_string(body);
+        // Walled pages are useless as-is
+        if config.browser_for(policy).is_some()
+            && WallDetector::new()
+                .detect(&html, Some(&result.url))
+                .is_some()
+        {
+            debug!("🧱 Wall detected: {}", result.url);
+            return Attempt::Walled(html);
+        }
+        Attempt::Served(html)
+    }
+
+    /// Keep a body the safety filters stopped away from extraction, in the
+    /// quarantine store when the verdict asks for it, and say why the page failed
+    async fn stop_unsafe(
+        config: &PipelineConfig,
+        result: &mut PipelineResult,
+        verdict: SafetyVerdict,
+        body: bytes::Bytes,
+    ) -> String {
+        warn!(
+            "☣️  {} stopped as {}: {}",
+            result.url,
+            verdict.hazard.as_str(),
+            verdict.reason
+        );
+        let mut message = format!(
+            "{}: {} ({})",
+            verdict.action.as_str(),
+            verdict.reason,
+            verdict.hazard.as_str()
+        );
+        if verdict.action == SafetyAction::Quarantine {
+            match &config.quarantine {
+                Some(store) => {
+                    let content_type = result
+                        .trace
+                        .as_ref()
+                        .and_then(|trace| trace.response_headers.get("content-type").cloned());
+                    let record = QuarantinedResponse::new(
+                        result.url.clone(),
+                        content_type,
+                        verdict.hazard.as_str().to_string(),
+                        verdict.reason.clone(),
+                        &body,
+                    );
+                    match store.quarantine(&record, &body).await {
+                        Ok(location) => message.push_str(&format!(", kept at {}", location)),
+                        Err(e) => {
+                            warn!("⚠️  Failed to quarantine {}: {:#}", result.url, e);
+                            message.push_str(", dropped as it couldn't be kept");
+                        }
+                    }
+                }
+                None => message.push_str(", dropped without a quarantine store"),
+            }
+        }
+        result.safety = Some(verdict);
+        message
+    }
+
+    /// Refuse to render a `verify_exit` page in a browser that would leak
+    /// around its proxy, or warn when `[exit_check]` lets leaks through
+    fn check_browser_leaks(browser: &BrowserPool, policy: &ResolvedPolicy) -> Result<(), String> {
+        if !policy.verify_exit {
+            return Ok(());
+        }
+        let mut leaks = browser_leak_risks(&browser.config().capabilities);
+        // Without a proxy for the domain, HTTP tiers go out directly too
+        if policy.proxy.is_none() {
+            leaks.retain(|leak| *leak != LeakRisk::Direct);
+        }
+        if leaks.is_empty() {
+            return Ok(());
+        }
+        let leaks: Vec<&str> = leaks.iter().map(LeakRisk::as_str).collect();
+        let message = format!("browser leaks around its proxy: {}", leaks.join(", "));
+        if policy.exit_checks.config().refuse_leaks {
+            return Err(message);
+        }
+        warn!("🛰️  {}", message);
+        Ok(())
+    }
+
+    /// Render the page in a pooled session, or in the persistent profile its
+    /// policy names, which is sealed again once the session is closed
+    async fn render(
+        config: &PipelineConfig,
+        browser: &BrowserPool,
+        policy: &ResolvedPolicy,
+        result: &mut PipelineResult,
+    ) -> Result<String, String> {
+        let profiles = config.browser_profiles.as_ref();
+        let Some((store, name)) = profiles.zip(policy.browser_profile.as_deref()) else {
+            let instance = browser
+                .get_browser()
+                .await
+                .map_err(|e| format!("{:#}", e))?;
+            let page = instance
+                .scrape_page(&result.url)
+                .await
+                .map_err(|e| format!("browser fetch failed: {:#}", e))?;
+            result.rendered = true;
+            return Ok(page.html);
+        };
+
+        let lease = store
+            .checkout(name)
+            .await
+            .map_err(|e| format!("browser profile unavailable: {:#}", e))?;
+        let instance = browser
+            .get_browser_in(lease.dir())
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        let page = instance.scrape_page(&result.url).await;
+        // The browser must be gone before its profile is trimmed and sealed
+        if let Err(e) = instance.close().await {
+            warn!(
+                "⚠️  Failed to close the session of profile {}: {:#}",
+                name, e
+            );
+        }
+        match lease.release().await {
+            Ok(bytes) => debug!("Browser profile {} kept ({} bytes)", name, bytes),
+            Err(e) => warn!("⚠️  Failed to keep browser profile {}: {:#}", name, e),
+        }
+        let page = page.map_err(|e| format!("browser fetch failed: {:#}", e))?;
+        result.rendered = true;
+        Ok(page.html)
+    }
+
+    /// Owned copy of extracted `content` for storage
+    pub(crate) fn stored_content(
+        config: &PipelineConfig,
+        url: &str,
+        html: &str,
+        content: &ExtractedContent,
+    ) -> StoredContent {
+        let platform = config
+            .registry
+            .find_scraper(url)
+            .map_or("generic", |scraper| scraper.platform_name());
+        let mut stored = StoredContent::new(
+            url.to_string(),
+            extract_domain(url).unwrap_or_default(),
+            platform.to_string(),
+            content.title.as_deref().map(str::to_string),
+            content.text.as_deref().map(str::to_string),
+            Some(html.to_string()),
+            metadata::into_owned(content.metadata.clone()),
+        );
+        stored.scraped_at = content.extracted_at;
+        stored.links = extract_links(html).unwrap_or_default();
+        stored.images = extract_images(html).unwrap_or_default();
+        stored.tags = content.tags.clone();
+        stored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrapers::browser::BrowserConfig;
+    use storage::local_store::LocalStore;
+    use swoop_core::test_support::FixtureServer;
+
+    async fn run(seeds: Vec<String>, config: PipelineConfig) -> HashMap<String, PipelineResult> {
+        Pipeline::run(seeds, config)
+            .map(|result| (result.url.clone(), result))
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_extracts_and_stores_pages() {
+        let server = FixtureServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(LocalStore::new(dir.path()).await.unwrap());
+        let config = PipelineConfig {
+            storage: Some(store.clone()),
+            ..PipelineConfig::default()
+        };
+
+        let article = server.url("/article");
+        let mut results = run(vec![article.clone(), server.url("/redirect/1")], config).await;
+        assert_eq!(results.len(), 2);
+
+        let result = results.remove(&article).unwrap();
+        assert!(result.is_success(), "{:?}", result.error);
+        assert_eq!(result.trace.unwrap().status, Some(200));
+        let content = result.content.unwrap();
+        assert_eq!(content.title.as_deref(), Some("Fixture Article"));
+
+        let stored = store
+            .get_content(&result.stored_id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.url, article);
+        assert_eq!(stored.title.as_deref(), Some("Fixture Article"));
+        assert!(stored.links.iter().any(|link| link.ends_with("/product")));
+        assert!(results.values().all(PipelineResult::is_success));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_reports_the_failing_stage() {
+        let server = FixtureServer::start().await;
+        let missing = server.url("/not-found");
+        let blocked = server.url("/cloudflare");
+        let mut results = run(
+            vec![missing.clone(), blocked.clone()],
+            PipelineConfig::default(),
+        )
+        .await;
+
+        let missing = results.remove(&missing).unwrap();
+        let error = missing.error.unwrap();
+        assert_eq!(error.stage, Stage::Fetch);
+        assert_eq!(error.message, "HTTP 404");
+        assert!(missing.content.is_none());
+
+        let blocked = results.remove(&blocked).unwrap();
+        assert_eq!(blocked.vendor, Some(BotVendor::Cloudflare));
+        assert!(blocked
+            .error
+            .unwrap()
+            .message
+            .contains("blocked by cloudflare"));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_chain_budgets_and_stats() {
+        let server = FixtureServer::start().await;
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."127.0.0.1"]
+            fallback = ["http"]
+
+            [tiers.http]
+            max_requests = 1
+            "#,
+        )
+        .unwrap();
+        let config = PipelineConfig {
+            policies: Some(Arc::new(policies)),
+            ..PipelineConfig::default()
+        };
+        let results = run(
+            vec![server.url("/article"), server.url("/product")],
+            config.clone(),
+        )
+        .await;
+        let (served, spent): (Vec<_>, Vec<_>) =
+            results.into_values().partition(PipelineResult::is_success);
+        assert_eq!(served[0].tier, Some(FetchTier::Http));
+        assert!(spent[0].error.as_ref().unwrap().message.contains("budget"));
+        let stats = config.tiers.stats()["127.0.0.1"][&FetchTier::Http];
+        assert_eq!((stats.served, stats.over_budget), (1, 1));
+
+        // Blocked pages skip a tier whose budget is spent
+        let policies = PolicyResolver::from_toml("[tiers.stealth_http]\nmax_requests = 0").unwrap();
+        let config = PipelineConfig {
+            policies: Some(Arc::new(policies)),
+            ..PipelineConfig::default()
+        };
+        let mut results = run(vec![server.url("/cloudflare")], config.clone()).await;
+        let (_, blocked) = results.drain().next().unwrap();
+        let attempts = blocked.trace.as_ref().unwrap().attempts;
+        assert!(blocked
+            .error
+            .unwrap()
+            .message
+            .contains("blocked by cloudflare"));
+        let stats = &config.tiers.stats()["127.0.0.1"];
+        assert_eq!(stats[&FetchTier::Http].failed, 1);
+        assert_eq!(stats[&FetchTier::StealthHttp].over_budget, 1);
+        // Only the plain tier's attempts reached the server
+        let requests = server.mock_server().received_requests().await.unwrap();
+        let cloudflare = requests
+            .iter()
+            .filter(|request| request.url.path() == "/cloudflare")
+            .count();
+        assert_eq!(cloudflare, attempts as usize);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_pipeline_waits_for_the_consumer() {
+        let server = FixtureServer::start().await;
+        let seeds: Vec<String> = (0..6).map(|_| server.url("/article")).collect();
+        let config = PipelineConfig {
+            concurrency: 2,
+            ..PipelineConfig::default()
+        };
+
+        let mut results = Pipeline::spawn(stream::iter(seeds), config, 1);
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        // One result in the channel and two pages held by full slots at most
+        let fetched = server.mock_server().received_requests().await.unwrap();
+        assert!(fetched.len() <= 4, "fetched {} pages ahead", fetched.len());
+
+        let mut received = 0;
+        while let Some(result) = results.recv().await {
+            assert!(result.is_success(), "{:?}", result.error);
+            received += 1;
+        }
+        assert_eq!(received, 6);
+    }
+
+    #[test]
+    fn test_anti_bot_settings_fill_in_unset_policies() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."cold.example"]
+            warm_up = false
+            "#,
+        )
+        .unwrap();
+        let config = PipelineConfig {
+            policies: Some(Arc::new(policies)),
+            anti_bot: AntiBotSettings {
+                warm_up: true,
+                ..AntiBotSettings::default()
+            },
+            ..PipelineConfig::default()
+        };
+
+        assert_eq!(config.policy("https://cold.example/").warm_up, Some(false));
+        assert_eq!(config.policy("https://warm.example/").warm_up, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_browser_required_pages_fail_without_a_browser() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."127.0.0.1"]
+            browser_required = true
+            "#,
+        )
+        .unwrap();
+        let server = FixtureServer::start().await;
+        let config = PipelineConfig {
+            policies: Some(Arc::new(policies)),
+            ..PipelineConfig::default()
+        };
+
+        let mut results = run(vec![server.url("/article")], config).await;
+        let (_, result) = results.drain().next().unwrap();
+        let error = result.error.unwrap();
+        assert_eq!(error.stage, Stage::Fetch);
+        assert!(error.message.contains("needs a browser"));
+        assert!(result.trace.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pages_need_a_pool_of_their_engine() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."127.0.0.1"]
+            browser_required = true
+            browser_engine = "firefox"
+            "#,
+        )
+        .unwrap();
+        let server = FixtureServer::start().await;
+        let config = PipelineConfig {
+            policies: Some(Arc::new(policies)),
+            browser: Some(Arc::new(BrowserPool::new(BrowserConfig::default()))),
+            ..PipelineConfig::default()
+        };
+        let policy = config.policy(&server.url("/article"));
+        assert!(config.browser_for(&policy).is_none());
+
+        let mut results = run(vec![server.url("/article")], config).await;
+        let (_, result) = results.drain().next().unwrap();
+        let error = result.error.unwrap();
+        assert!(error.message.contains("needs a firefox browser"));
+    }
+}
@@ -0,0 +1,31 @@
+//! The types most programs embedding swoop need, from every crate
+//!
+//! ```ignore
+//! use swoop_orchestrator::prelude::*;
+//! ```
+
+pub use crate::{
+    fallback::TierStats,
+    workflow::{Workflow, WorkflowError, WorkflowReport},
+    AntiBotSettings, Pipeline, PipelineConfig, PipelineError, PipelineResult, Stage, Swoop,
+    SwoopBuilder,
+};
+pub use futures::StreamExt;
+pub use scrapers::{
+    anti_bot::{
+        referrers::{ReferrerChain, ReferrerConfig},
+        vendors::BotVendor,
+    },
+    browser::{BrowserConfig, BrowserEngine, BrowserPool},
+    locale::LocaleProfile,
+    platforms::ScraperRegistry,
+    policy::{FetchTier, PolicyResolver},
+    rate_limiter::RateProfile,
+    safety::{SafetyAction, SafetyFilter, SafetyVerdict},
+    ExtractedContent, PlatformScraper,
+};
+pub use storage::{
+    browser_profiles::ProfileStore, local_store::LocalStore, models::StoredContent,
+    FingerprintStore, QuarantineStore, StorageBackend,
+};
+pub use swoop_core::trace::FetchTrace;
@@ -0,0 +1,947 @@
+//! Multi-step workflows declared in YAML
+//!
+//! A [`Workflow`] declares a recurring job once instead of scripting it
+//! around the CLI:
+//!
+//! ```yaml
+//! name: weekly-news
+//! steps:
+//!   - id: seeds
+//!     discover:
+//!       sitemap: https://news.example/sitemap.xml
+//!       limit: 200
+//!   - id: pages
+//!     needs: [seeds]
+//!     crawl:
+//!       depth: 1
+//!       scope: domain
+//!       max_pages: 1000
+//!   - id: articles
+//!     needs: [pages]
+//!     extract:
+//!       rule_set: article
+//!   - id: save
+//!     needs: [articles]
+//!     store: {}
+//!   - id: feed
+//!     needs: [articles]
+//!     export:
+//!       path: articles.jsonl
+//! ```
+//!
+//! Every step takes and yields one kind of [`Data`]: `discover` yields URLs
+//! from a sitemap, a Google search or a list, `crawl` fetches URLs and the
+//! links below them into pages, `extract` keeps the pages that fit a rule set,
+//! and `store` and `export` write pages out and pass them on. A step takes
+//! the outputs of the steps it `needs` together. [`Workflow::parse`] checks
+//! that each step only needs steps yielding what it takes and that needs
+//! don't go in circles before anything runs; [`Workflow::run`] then runs the
+//! steps in dependency order with a [`PipelineConfig`].
+
+use futures::{stream, StreamExt};
+use scrapers::{
+    discovery::{self, parse_sitemap},
+    platforms::serp::{SerpQuery, SerpScraper},
+    schemas::SchemaRegistry,
+    utils::{extract_domain, normalize_url},
+    ExtractedContent, ScraperConfig,
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use storage::{local_store::LocalStore, StorageBackend};
+use swoop_core::text::SharedStr;
+use tracing::{info, warn};
+
+use crate::{fetch::fetch_url_traced, Pipeline, PipelineConfig};
+
+/// Organic results on one Google result page
+const SERP_PAGE_SIZE: usize = 10;
+
+/// Most Google result pages fetched for one search
+const MAX_SERP_PAGES: usize = 50;
+
+/// Most sitemap files read for one `discover` step, indexes included
+const MAX_SITEMAPS: usize = 50;
+
+/// Steps run in dependency order, see the [module docs](self)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    pub name: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// One step of a [`Workflow`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    pub id: String,
+    /// Steps whose outputs this one takes
+    #[serde(default)]
+    pub needs: Vec<String>,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// What a step does
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Discover(Discover),
+    Crawl(Crawl),
+    Extract(Extract),
+    Store(Store),
+    Export(Export),
+}
+
+/// Find seed URLs, adding them to the URLs of the steps needed
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Discover {
+    /// Seed URLs as they are
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Sitemap or sitemap index listing the pages
+    pub sitemap: Option<String>,
+    /// Google query whose organic results are seeds
+    pub search: Option<String>,
+    /// Most URLs taken from the sitemap and the search each
+    #[serde(default = "default_discover_limit")]
+    pub limit: usize,
+}
+
+fn default_discover_limit() -> usize {
+    100
+}
+
+/// Fetch and extract URLs, following links below them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Crawl {
+    /// Link levels followed below the seeds; 0 fetches the seeds only
+    #[serde(default)]
+    pub depth: u32,
+    #[serde(default)]
+    pub scope: CrawlScope,
+    /// Most pages fetched, seeds included
+    pub max_pages: Option<usize>,
+}
+
+/// Links a crawl follows, relative to the seed a page was found from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlScope {
+    /// Links to the seed's host
+    #[default]
+    Host,
+    /// Links to the seed's domain and its subdomains
+    Domain,
+    /// Any link
+    Any,
+}
+
+impl CrawlScope {
+    /// Whether `link` is in scope for a crawl from `seed`
+    pub fn allows(&self, seed: &str, link: &str) -> bool {
+        let (Ok(seed), Ok(link)) = (extract_domain(seed), extract_domain(link)) else {
+            return *self == CrawlScope::Any;
+        };
+        if seed.is_empty() || link.is_empty() {
+            return *self == CrawlScope::Any;
+        }
+        match self {
+            CrawlScope::Host => seed == link,
+            CrawlScope::Domain => {
+                let domain = seed.strip_prefix("www.").unwrap_or(&seed);
+                link == domain
+                    || link
+                        .strip_suffix(domain)
+                        .is_some_and(|sub| sub.ends_with('.'))
+            }
+            CrawlScope::Any => true,
+        }
+    }
+}
+
+/// Keep the pages that fit an extraction rule set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Extract {
+    /// Built-in rule set: `product`, `post`, `video`, `article` or `job_posting`
+    pub rule_set: String,
+}
+
+/// Store pages
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Store {
+    /// Local store to write to instead of the pipeline's storage backend
+    pub path: Option<PathBuf>,
+}
+
+/// Write pages to a file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Export {
+    pub path: PathBuf,
+    /// Taken from the path's extension when not given
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl Export {
+    fn format(&self) -> Option<ExportFormat> {
+        self.format.or_else(
+            || match self.path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => Some(ExportFormat::Json),
+                Some("jsonl") => Some(ExportFormat::Jsonl),
+                _ => None,
+            },
+        )
+    }
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Discover(_) => "discover",
+            Action::Crawl(_) => "crawl",
+            Action::Extract(_) => "extract",
+            Action::Store(_) => "store",
+            Action::Export(_) => "export",
+        }
+    }
+
+    /// Kind of data the step takes from the steps it needs
+    fn takes(&self) -> DataKind {
+        match self {
+            Action::Discover(_) | Action::Crawl(_) => DataKind::Urls,
+            Action::Extract(_) | Action::Store(_) | Action::Export(_) => DataKind::Pages,
+        }
+    }
+
+    fn yields(&self) -> DataKind {
+        match self {
+            Action::Discover(_) => DataKind::Urls,
+            _ => DataKind::Pages,
+        }
+    }
+}
+
+/// A fetched and extracted page
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub url: String,
+    pub html: SharedStr,
+    pub content: ExtractedContent,
+    /// Link levels below its seed the page was found at
+    pub depth: u32,
+}
+
+/// What a step passes on to the steps needing it
+#[derive(Debug, Clone)]
+pub enum Data {
+    Urls(Vec<String>),
+    Pages(Vec<Page>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataKind {
+    Urls,
+    Pages,
+}
+
+impl DataKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DataKind::Urls => "URLs",
+            DataKind::Pages => "pages",
+        }
+    }
+}
+
+impl Data {
+    fn len(&self) -> usize {
+        match self {
+            Data::Urls(urls) => urls.len(),
+            Data::Pages(pages) => pages.len(),
+        }
+    }
+}
+
+/// Why a workflow can't be run, or stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowError {
+    /// Not a workflow in YAML
+    Parse(String),
+    /// The steps don't fit together
+    Invalid(String),
+    /// A step failed as a whole, so the steps after it can't run
+    Step { id: String, message: String },
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkflowError::Parse(message) => write!(f, "Invalid workflow file: {}", message),
+            WorkflowError::Invalid(message) => write!(f, "Invalid workflow: {}", message),
+            WorkflowError::Step { id, message } => write!(f, "Step '{}' failed: {}", id, message),
+        }
+    }
+}
+
+impl std::error::Error for WorkflowError {}
+
+/// How one step went
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    pub id: String,
+    pub action: &'static str,
+    /// URLs or pages the step passed on
+    pub output: usize,
+    /// URLs and pages that failed, with the reason
+    pub failures: Vec<String>,
+}
+
+/// How a workflow run went, step by step in the order they ran
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowReport {
+    pub steps: Vec<StepReport>,
+}
+
+impl WorkflowReport {
+    pub fn failures(&self) -> usize {
+        self.steps.iter().map(|step| step.failures.len()).sum()
+    }
+}
+
+impl Workflow {
+    /// Parse and validate a workflow
+    pub fn parse(yaml: &str) -> Result<Self, WorkflowError> {
+        let workflow: Workflow =
+            serde_yaml::from_str(yaml).map_err(|e| WorkflowError::Parse(e.to_string()))?;
+        workflow.order()?;
+        Ok(workflow)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, WorkflowError> {
+        let path = path.as_ref();
+        let yaml = fs::read_to_string(path)
+            .map_err(|e| WorkflowError::Parse(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&yaml)
+    }
+
+    /// Indices of the steps in an order that runs every step after the ones
+    /// it needs, checking that the steps fit together
+    fn order(&self) -> Result<Vec<usize>, WorkflowError> {
+        let invalid = |message: String| Err(WorkflowError::Invalid(message));
+        if self.steps.is_empty() {
+            return invalid("no steps".to_string());
+        }
+        let mut index = HashMap::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if index.insert(step.id.as_str(), i).is_some() {
+                return invalid(format!("two steps are called '{}'", step.id));
+            }
+        }
+        let mut dependents = vec![Vec::new(); self.steps.len()];
+        let mut pending = vec![0; self.steps.len()];
+        for (i, step) in self.steps.iter().enumerate() {
+            step.check()?;
+            let takes = step.action.takes();
+            if step.needs.is_empty() && !matches!(step.action, Action::Discover(_)) {
+                return invalid(format!(
+                    "'{}' needs a step yielding {}",
+                    step.id,
+                    takes.as_str()
+                ));
+            }
+            for need in &step.needs {
+                let Some(&needed) = index.get(need.as_str()) else {
+                    return invalid(format!("'{}' needs unknown step '{}'", step.id, need));
+                };
+                let yields = self.steps[needed].action.yields();
+                if yields != takes {
+                    return invalid(format!(
+                        "'{}' takes {} but '{}' yields {}",
+                        step.id,
+                        takes.as_str(),
+                        need,
+                        yields.as_str()
+                    ));
+                }
+                dependents[needed].push(i);
+                pending[i] += 1;
+            }
+        }
+
+        let mut ready: VecDeque<usize> =
+            (0..self.steps.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.steps.len());
+        while let Some(i) = ready.pop_front() {
+            order.push(i);
+            for &dependent in &dependents[i] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.push_back(dependent);
+                }
+            }
+        }
+        if order.len() < self.steps.len() {
+            let circular: Vec<&str> = (0..self.steps.len())
+                .filter(|&i| pending[i] > 0)
+                .map(|i| self.steps[i].id.as_str())
+                .collect();
+            return invalid(format!("steps need each other: {}", circular.join(", ")));
+        }
+        Ok(order)
+    }
+
+    /// Run the steps in dependency order, one at a time
+    ///
+    /// URLs and pages failing in a step are reported and left out of what it
+    /// passes on; a step failing as a whole, such as an export that can't be
+    /// written, stops the run.
+    pub async fn run(&self, config: &PipelineConfig) -> Result<WorkflowReport, WorkflowError> {
+        let order = self.order()?;
+        // Outputs are dropped once the last step needing them has taken them
+        let mut consumers: HashMap<&str, usize> = HashMap::new();
+        for step in &self.steps {
+            for need in &step.needs {
+                *consumers.entry(need.as_str()).or_default() += 1;
+            }
+        }
+        let mut outputs: HashMap<&str, Data> = HashMap::new();
+        let mut report = WorkflowReport::default();
+        for i in order {
+            let step = &self.steps[i];
+            let mut inputs = Vec::with_capacity(step.needs.len());
+            for need in &step.needs {
+                let consumers = consumers.get_mut(need.as_str()).expect("needs are counted");
+                *consumers -= 1;
+                let data = if *consumers == 0 {
+                    outputs.remove(need.as_str())
+                } else {
+                    outputs.get(need.as_str()).cloned()
+                };
+                inputs.push(data.expect("needed steps run first"));
+            }
+
+            info!("▶️  Running step '{}' ({})", step.id, step.action.as_str());
+            let mut step_report = StepReport {
+                id: step.id.clone(),
+                action: step.action.as_str(),
+                output: 0,
+                failures: Vec::new(),
+            };
+            let output = step
+                .run(config, inputs, &mut step_report.failures)
+                .await
+                .map_err(|message| WorkflowError::Step {
+                    id: step.id.clone(),
+                    message,
+                })?;
+            step_report.output = output.len();
+            info!(
+                "✅ Step '{}' passed on {} item(s), {} failed",
+                step.id,
+                step_report.output,
+                step_report.failures.len()
+            );
+            report.steps.push(step_report);
+            if consumers.contains_key(step.id.as_str()) {
+                outputs.insert(step.id.as_str(), output);
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl Step {
+    /// Settings that are wrong whatever the step needs
+    fn check(&self) -> Result<(), WorkflowError> {
+        let invalid =
+            |message: &str| Err(WorkflowError::Invalid(format!("'{}' {}", self.id, message)));
+        match &self.action {
+            Action::Discover(discover) => {
+                if discover.urls.is_empty()
+                    && discover.sitemap.is_none()
+                    && discover.search.is_none()
+                {
+                    return invalid("discovers nothing; give urls, a sitemap or a search");
+                }
+            }
+            Action::Extract(extract) => {
+                let rule_sets = SchemaRegistry::new().with_builtin_rule_sets();
+                if rule_sets.schema_for(&extract.rule_set).is_none() {
+                    return invalid(&format!("uses unknown rule set '{}'", extract.rule_set));
+                }
+            }
+            Action::Export(export) if export.format().is_none() => {
+                return invalid("needs an export format: json or jsonl");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        config: &PipelineConfig,
+        inputs: Vec<Data>,
+        failures: &mut Vec<String>,
+    ) -> Result<Data, String> {
+        let mut urls = Vec::new();
+        let mut pages = Vec::new();
+        for input in inputs {
+            match input {
+                Data::Urls(more) => urls.extend(more),
+                Data::Pages(more) => pages.extend(more),
+            }
+        }
+        match &self.action {
+            Action::Discover(discover) => {
+                urls.extend(discover.run(config, failures).await);
+                Ok(Data::Urls(dedupe(urls)))
+            }
+            Action::Crawl(crawl) => {
+                Ok(Data::Pages(crawl.run(config, dedupe(urls), failures).await))
+            }
+            Action::Extract(extract) => Ok(Data::Pages(extract.run(pages, failures))),
+            Action::Store(store) => store.run(config, pages, failures).await.map(Data::Pages),
+            Action::Export(export) => export.run(&pages).map(|()| Data::Pages(pages)),
+        }
+    }
+}
+
+impl Discover {
+    async fn run(&self, config: &PipelineConfig, failures: &mut Vec<String>) -> Vec<String> {
+        let mut urls = self.urls.clone();
+        if let Some(sitemap) = &self.sitemap {
+            urls.extend(read_sitemap(config, sitemap, self.limit, failures).await);
+        }
+        if let Some(search) = &self.search {
+            let pages = self.limit.div_ceil(SERP_PAGE_SIZE).clamp(1, MAX_SERP_PAGES);
+            let mut scraper = SerpScraper::new(ScraperConfig::default());
+            if let Some(browser) = &config.browser {
+                scraper = scraper.with_browser(browser.clone());
+            }
+            match scraper.search(&SerpQuery::new(search), pages as u32).await {
+                Ok(results) => {
+                    if let Some(block) = &results.blocked {
+                        failures.push(format!("search '{}': {}", search, block));
+                    }
+                    let mut seeds = discovery::from_serp(&results);
+                    seeds.truncate(self.limit);
+                    urls.extend(seeds.into_iter().map(|seed| seed.url));
+                }
+                Err(e) => failures.push(format!("search '{}': {:#}", search, e)),
+            }
+        }
+        urls
+    }
+}
+
+/// Up to `limit` page URLs of the sitemap at `url`, following sitemap indexes
+async fn read_sitemap(
+    config: &PipelineConfig,
+    url: &str,
+    limit: usize,
+    failures: &mut Vec<String>,
+) -> Vec<String> {
+    let mut pending = VecDeque::from([url.to_string()]);
+    let mut visited = HashSet::new();
+    let mut urls = Vec::new();
+    while let Some(sitemap) = pending.pop_front() {
+        if urls.len() >= limit || visited.len() >= MAX_SITEMAPS {
+            break;
+        }
+        if !visited.insert(sitemap.clone()) {
+            continue;
+        }
+        let policy = config.policy(&sitemap);
+        let mut headers = policy.headers.clone();
+        headers.extend(config.headers.clone());
+        match fetch_url_traced(&sitemap, &headers, &policy).await {
+            (Ok(body), _) => {
+                let parsed = parse_sitemap(&swoop_core::body_to_string(body));
+                pending.extend(parsed.sitemaps);
+                urls.extend(parsed.urls);
+            }
+            (Err(e), _) => failures.push(format!("{}: {}", sitemap, e)),
+        }
+    }
+    urls.truncate(limit);
+    urls
+}
+
+impl Crawl {
+    /// Crawl level by level, `config.concurrency` pages at a time
+    ///
+    /// With body fingerprints, pages unchanged since their last fetch aren't
+    /// passed on, but their links are still followed.
+    async fn run(
+        &self,
+        config: &PipelineConfig,
+        seeds: Vec<String>,
+        failures: &mut Vec<String>,
+    ) -> Vec<Page> {
+        let mut remaining = self.max_pages.unwrap_or(usize::MAX);
+        let mut seen: HashSet<String> = seeds.iter().map(|url| normalize_url(url)).collect();
+        // Every URL with the seed it was found from
+        let mut level: Vec<(String, String)> =
+            seeds.into_iter().map(|url| (url.clone(), url)).collect();
+        let mut pages = Vec::new();
+        let mut unchanged = 0;
+        for depth in 0..=self.depth {
+            level.truncate(remaining);
+            if level.is_empty() {
+                break;
+            }
+            remaining -= level.len();
+            let fetched: Vec<_> = stream::iter(level)
+                .map(|(url, seed)| async move {
+                    let (result, html) = Pipeline::fetch_and_extract(config, url).await;
+                    (result, html, seed)
+                })
+                .buffer_unordered(config.concurrency.max(1))
+                .collect()
+                .await;
+
+            let mut next = Vec::new();
+            for (result, html, seed) in fetched {
+                let (content, Some(html)) = (result.content, html) else {
+                    let error = result.error.map_or_else(String::new, |e| e.to_string());
+                    failures.push(format!("{}: {}", result.url, error));
+                    continue;
+                };
+                if depth < self.depth {
+                    match config
+                        .registry
+                        .links_to_follow(&result.url, depth + 1, &html)
+                    {
+                        Ok(links) => next.extend(
+                            links
+                                .into_iter()
+                                .filter(|link| self.scope.allows(&seed, link))
+                                .filter(|link| seen.insert(normalize_url(link)))
+                                .map(|link| (link, seed.clone())),
+                        ),
+                        Err(e) => warn!("⚠️  Not following links of {}: {:#}", result.url, e),
+                    }
+                }
+                match content {
+                    Some(content) => pages.push(Page {
+                        url: result.url,
+                        html,
+                        content,
+                        depth,
+                    }),
+                    None => unchanged += 1,
+                }
+            }
+            level = next;
+        }
+        if unchanged > 0 {
+            info!("⏭️  {} page(s) unchanged since their last fetch", unchanged);
+        }
+        pages
+    }
+}
+
+impl Extract {
+    fn run(&self, pages: Vec<Page>, failures: &mut Vec<String>) -> Vec<Page> {
+        let rule_sets = SchemaRegistry::new().with_builtin_rule_sets();
+        pages
+            .into_iter()
+            .filter_map(
+                |mut page| match rule_sets.apply(&self.rule_set, page.content) {
+                    Ok(content) => {
+                        page.content = content;
+                        Some(page)
+                    }
+                    Err(violation) => {
+                        failures.push(violation.to_string());
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+}
+
+impl Store {
+    async fn run(
+        &self,
+        config: &PipelineConfig,
+        pages: Vec<Page>,
+        failures: &mut Vec<String>,
+    ) -> Result<Vec<Page>, String> {
+        let storage: Arc<dyn StorageBackend> = match (&self.path, &config.storage) {
+            (Some(path), _) => Arc::new(
+                LocalStore::new(path)
+                    .await
+                    .map_err(|e| format!("{}: {:#}", path.display(), e))?,
+            ),
+            (None, Some(storage)) => storage.clone(),
+            (None, None) => return Err("no storage backend to store pages in".to_string()),
+        };
+        let mut stored = Vec::with_capacity(pages.len());
+        for page in pages {
+            let content = Pipeline::stored_content(config, &page.url, &page.html, &page.content);
+            match storage.store_content(&content).await {
+                Ok(_) => stored.push(page),
+                Err(e) => failures.push(format!("{}: {:#}", page.url, e)),
+            }
+        }
+        Ok(stored)
+    }
+}
+
+impl Export {
+    fn run(&self, pages: &[Page]) -> Result<(), String> {
+        let error = |e: &dyn fmt::Display| format!("{}: {}", self.path.display(), e);
+        let file = fs::File::create(&self.path).map_err(|e| error(&e))?;
+        let mut writer = BufWriter::new(file);
+        let contents = pages.iter().map(|page| &page.content);
+        match self.format() {
+            Some(ExportFormat::Jsonl) => {
+                for content in contents {
+                    serde_json::to_writer(&mut writer, content).map_err(|e| error(&e))?;
+                    writer.write_all(b"\n").map_err(|e| error(&e))?;
+                }
+            }
+            _ => {
+                let contents: Vec<&ExtractedContent> = contents.collect();
+                serde_json::to_writer_pretty(&mut writer, &contents).map_err(|e| error(&e))?;
+            }
+        }
+        writer.flush().map_err(|e| error(&e))?;
+        info!(
+            "📄 Exported {} page(s) to {}",
+            pages.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+/// Drop repeated URLs, compared after [`normalize_url`], keeping the first
+fn dedupe(urls: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    urls.into_iter()
+        .filter(|url| seen.insert(normalize_url(url)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swoop_core::test_support::FixtureServer;
+
+    #[test]
+    fn test_parse_checks_that_steps_fit_together() {
+        let workflow = Workflow::parse(
+            r#"
+            name: nightly
+            steps:
+              - id: feed
+                needs: [pages]
+                export: { path: out.jsonl }
+              - id: pages
+                needs: [seeds]
+                crawl: { depth: 2, scope: domain }
+              - id: seeds
+                discover: { urls: ["https://a.example/"] }
+            "#,
+        )
+        .unwrap();
+        let order: Vec<&str> = workflow
+            .order()
+            .unwrap()
+            .into_iter()
+            .map(|i| workflow.steps[i].id.as_str())
+            .collect();
+        assert_eq!(order, vec!["seeds", "pages", "feed"]);
+
+        let invalid = |yaml: &str| match Workflow::parse(yaml) {
+            Err(WorkflowError::Invalid(message)) => message,
+            other => panic!("expected an invalid workflow, got {:?}", other),
+        };
+        assert!(invalid(
+            r#"
+            steps:
+              - id: seeds
+                discover: { urls: ["https://a.example/"] }
+              - id: save
+                needs: [seeds]
+                store: {}
+            "#
+        )
+        .contains("'save' takes pages but 'seeds' yields URLs"));
+        assert!(invalid(
+            r#"
+            steps:
+              - id: a
+                needs: [b]
+                extract: { rule_set: article }
+              - id: b
+                needs: [a]
+                extract: { rule_set: article }
+            "#
+        )
+        .contains("need each other"));
+        assert!(invalid(
+            r#"
+            steps:
+              - id: seeds
+                discover: { urls: ["https://a.example/"] }
+              - id: pages
+                needs: [seeds]
+                crawl: {}
+              - id: typed
+                needs: [pages]
+                extract: { rule_set: recipe }
+            "#
+        )
+        .contains("unknown rule set 'recipe'"));
+        assert!(matches!(
+            Workflow::parse("steps:\n  - id: x\n    crawl: { depth: deep }\n"),
+            Err(WorkflowError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_crawl_scope() {
+        let seed = "https://www.shop.example/";
+        assert!(CrawlScope::Host.allows(seed, "https://www.shop.example/a"));
+        assert!(!CrawlScope::Host.allows(seed, "https://blog.shop.example/a"));
+        assert!(CrawlScope::Domain.allows(seed, "https://blog.shop.example/a"));
+        assert!(CrawlScope::Domain.allows(seed, "https://shop.example/a"));
+        assert!(!CrawlScope::Domain.allows(seed, "https://othershop.example/a"));
+        assert!(CrawlScope::Any.allows(seed, "https://other.example/"));
+    }
+
+    #[tokio::test]
+    async fn test_workflow_crawls_extracts_stores_and_exports() {
+        let server = FixtureServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let export = dir.path().join("products.jsonl");
+        let yaml = format!(
+            r#"
+            steps:
+              - id: seeds
+                discover: {{ urls: ["{seed}"] }}
+              - id: pages
+                needs: [seeds]
+                crawl: {{ depth: 1 }}
+              - id: products
+                needs: [pages]
+                extract: {{ rule_set: product }}
+              - id: save
+                needs: [products]
+                store: {{ path: "{store}" }}
+              - id: feed
+                needs: [products]
+                export: {{ path: "{export}" }}
+            "#,
+            seed = server.url("/article"),
+            store = dir.path().join("store").display(),
+            export = export.display(),
+        );
+        let workflow = Workflow::parse(&yaml).unwrap();
+
+        let report = workflow.run(&PipelineConfig::default()).await.unwrap();
+        let steps: HashMap<&str, &StepReport> = report
+            .steps
+            .iter()
+            .map(|step| (step.id.as_str(), step))
+            .collect();
+        // The article links to the product page; only the latter has a price
+        assert_eq!(steps["pages"].output, 2);
+        assert_eq!(steps["products"].output, 1);
+        assert!(steps["products"].failures[0].contains("missing price"));
+        assert_eq!(steps["save"].output, 1);
+
+        let store = LocalStore::new(dir.path().join("store")).await.unwrap();
+        let stored = store.list_content().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].url, server.url("/product"));
+        let exported = fs::read_to_string(&export).unwrap();
+        assert_eq!(exported.lines().count(), 1);
+        assert!(exported.contains("Fixture Widget"));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_passes_on_changed_pages_only() {
+        let server = FixtureServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let yaml = format!(
+            r#"
+            steps:
+              - id: seeds
+                discover: {{ urls: ["{}"] }}
+              - id: pages
+                needs: [seeds]
+                crawl: {{ depth: 1 }}
+            "#,
+            server.url("/article")
+        );
+        let workflow = Workflow::parse(&yaml).unwrap();
+        let config = PipelineConfig {
+            fingerprints: Some(Arc::new(LocalStore::new(dir.path()).await.unwrap())),
+            ..PipelineConfig::default()
+        };
+
+        let first = workflow.run(&config).await.unwrap();
+        assert_eq!(first.steps[1].output, 2);
+        // Links of the unchanged article still lead to the product page
+        let again = workflow.run(&config).await.unwrap();
+        assert_eq!(again.steps[1].output, 0);
+        assert!(again.steps[1].failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_without_a_backend_stops_the_run() {
+        let server = FixtureServer::start().await;
+        let yaml = format!(
+            r#"
+            steps:
+              - id: seeds
+                discover: {{ urls: ["{}"] }}
+              - id: pages
+                needs: [seeds]
+                crawl: {{}}
+              - id: save
+                needs: [pages]
+                store: {{}}
+            "#,
+            server.url("/article")
+        );
+        let error = Workflow::parse(&yaml)
+            .unwrap()
+            .run(&PipelineConfig::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            WorkflowError::Step {
+                id: "save".to_string(),
+                message: "no storage backend to store pages in".to_string(),
+            }
+        );
+    }
+}
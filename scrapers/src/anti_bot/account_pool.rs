@@ -0,0 +1,370 @@
+//! Account scheduling for authenticated platforms
+//!
+//! What [`ProxyRotator`](super::proxy_rotator::ProxyRotator) does for
+//! addresses, [`AccountPool`] does for logged-in identities. It spreads tasks
+//! over a platform's accounts from a [`CredentialVault`], handing each task
+//! the healthy account that was used least recently. An account is healthy
+//! when all of these hold:
+//! - it has `daily_requests` left for the current UTC day
+//! - it isn't cooling down after a soft block (rate limit, challenge,
+//!   CAPTCHA), which lasts `cooldown_secs` and doubles with every block in a
+//!   row, up to `max_cooldown_secs`
+//! - its health score, which successes raise and soft blocks lower, is at
+//!   least `min_health`
+//! - it hasn't been locked, i.e. suspended or refused at login, which only
+//!   [`AccountPool::reset`] undoes
+//!
+//! When no account is healthy, [`NoAccountAvailable`] says how long until
+//! one will be, so callers can wait instead of burning an account.
+
+use crate::api::{Account, CredentialVault};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How accounts are budgeted and rested; see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountPoolConfig {
+    /// Tasks an account takes per UTC day
+    pub daily_requests: u32,
+    /// Rest after a soft block, doubled for each further block in a row
+    pub cooldown_secs: u64,
+    pub max_cooldown_secs: u64,
+    /// Health score, from 0 to 1, below which an account is left alone
+    pub min_health: f64,
+}
+
+impl Default for AccountPoolConfig {
+    fn default() -> Self {
+        Self {
+            daily_requests: 250,
+            cooldown_secs: 900,
+            max_cooldown_secs: 24 * 3600,
+            min_health: 0.3,
+        }
+    }
+}
+
+/// How a task went for the account it was given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountOutcome {
+    Success,
+    /// Rate limited or challenged: the account rests, then is used again
+    SoftBlock,
+    /// Suspended, or its login refused: the account isn't used again
+    Locked,
+}
+
+/// No account of a platform can take a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoAccountAvailable {
+    pub platform: String,
+    /// Until the first account cools down or gets a new daily budget;
+    /// `None` when every account is locked or unhealthy, or there are none
+    pub retry_in: Option<Duration>,
+}
+
+impl fmt::Display for NoAccountAvailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.retry_in {
+            Some(wait) => write!(
+                f,
+                "No {} account available for {}s",
+                self.platform,
+                wait.as_secs()
+            ),
+            None => write!(f, "No usable {} account", self.platform),
+        }
+    }
+}
+
+impl std::error::Error for NoAccountAvailable {}
+
+/// Where an account stands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountStats {
+    pub username: String,
+    pub health: f64,
+    /// Tasks taken today
+    pub requests_today: u32,
+    /// Seconds of cooldown left
+    pub cooldown_secs: Option<u64>,
+    /// Soft blocks in a row
+    pub strikes: u32,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone)]
+struct AccountState {
+    health: f64,
+    day: NaiveDate,
+    requests_today: u32,
+    last_used: Option<Instant>,
+    cooldown_until: Option<Instant>,
+    strikes: u32,
+    locked: bool,
+}
+
+impl AccountState {
+    fn new(today: NaiveDate) -> Self {
+        Self {
+            health: 1.0,
+            day: today,
+            requests_today: 0,
+            last_used: None,
+            cooldown_until: None,
+            strikes: 0,
+            locked: false,
+        }
+    }
+
+    fn cooldown_left(&self, now: Instant) -> Option<Duration> {
+        self.cooldown_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+}
+
+/// Hands out a platform's accounts; see the [module docs](self)
+pub struct AccountPool {
+    vault: Arc<CredentialVault>,
+    config: AccountPoolConfig,
+    /// By platform and username
+    states: Mutex<HashMap<(String, String), AccountState>>,
+}
+
+impl AccountPool {
+    pub fn new(vault: Arc<CredentialVault>, config: AccountPoolConfig) -> Self {
+        Self {
+            vault,
+            config,
+            states: Mutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &AccountPoolConfig {
+        &self.config
+    }
+
+    /// The least recently used healthy account of `platform`, charged one
+    /// task of its daily budget
+    pub fn acquire(&self, platform: &str) -> Result<Account, NoAccountAvailable> {
+        let accounts = self.vault.accounts(platform);
+        let now = Instant::now();
+        let today = Utc::now().date_naive();
+        let mut states = self.states.lock().unwrap();
+        let mut retry_in: Option<Duration> = None;
+        let mut chosen: Option<(usize, Option<Instant>)> = None;
+        for (index, account) in accounts.iter().enumerate() {
+            let state = states
+                .entry((platform.to_string(), account.username.clone()))
+                .or_insert_with(|| AccountState::new(today));
+            if state.day != today {
+                state.day = today;
+                state.requests_today = 0;
+            }
+            if state.locked || state.health < self.config.min_health {
+                continue;
+            }
+            let wait = if let Some(cooldown) = state.cooldown_left(now) {
+                Some(cooldown)
+            } else if state.requests_today >= self.config.daily_requests {
+                Some(until_next_day())
+            } else {
+                None
+            };
+            if let Some(wait) = wait {
+                retry_in = Some(retry_in.map_or(wait, |shortest| shortest.min(wait)));
+                continue;
+            }
+            // Never-used accounts come first, then the longest unused
+            if !chosen.is_some_and(|(_, used)| used <= state.last_used) {
+                chosen = Some((index, state.last_used));
+            }
+        }
+
+        let Some((index, _)) = chosen else {
+            return Err(NoAccountAvailable {
+                platform: platform.to_string(),
+                retry_in,
+            });
+        };
+        let account = accounts[index].clone();
+        let state = states
+            .get_mut(&(platform.to_string(), account.username.clone()))
+            .expect("state was created above");
+        state.requests_today += 1;
+        state.last_used = Some(now);
+        Ok(account)
+    }
+
+    /// Record how `username`'s last task on `platform` went
+    pub fn report(&self, platform: &str, username: &str, outcome: AccountOutcome) {
+        let mut states = self.states.lock().unwrap();
+        let state = states
+            .entry((platform.to_string(), username.to_string()))
+            .or_insert_with(|| AccountState::new(Utc::now().date_naive()));
+        match outcome {
+            AccountOutcome::Success => {
+                state.health = (state.health * 0.9 + 0.1).min(1.0);
+                state.strikes = 0;
+            }
+            AccountOutcome::SoftBlock => {
+                state.health *= 0.7;
+                state.strikes += 1;
+                let cooldown = self
+                    .config
+                    .cooldown_secs
+                    .saturating_mul(1 << (state.strikes - 1).min(16))
+                    .min(self.config.max_cooldown_secs);
+                state.cooldown_until = Some(Instant::now() + Duration::from_secs(cooldown));
+                tracing::warn!(
+                    "🔐 {} account {} soft-blocked; resting it for {}s",
+                    platform,
+                    username,
+                    cooldown
+                );
+            }
+            AccountOutcome::Locked => {
+                state.locked = true;
+                tracing::warn!("🔐 {} account {} is locked", platform, username);
+            }
+        }
+    }
+
+    /// Forget `username`'s blocks, lock and health, e.g. once it was
+    /// unlocked by hand; today's budget stays spent
+    pub fn reset(&self, platform: &str, username: &str) {
+        if let Some(state) = self
+            .states
+            .lock()
+            .unwrap()
+            .get_mut(&(platform.to_string(), username.to_string()))
+        {
+            *state = AccountState {
+                requests_today: state.requests_today,
+                last_used: state.last_used,
+                ..AccountState::new(state.day)
+            };
+        }
+    }
+
+    /// Standing of each of `platform`'s accounts, in vault order
+    pub fn stats(&self, platform: &str) -> Vec<AccountStats> {
+        let now = Instant::now();
+        let today = Utc::now().date_naive();
+        let states = self.states.lock().unwrap();
+        self.vault
+            .accounts(platform)
+            .into_iter()
+            .map(|account| {
+                let state = states
+                    .get(&(platform.to_string(), account.username.clone()))
+                    .cloned()
+                    .unwrap_or_else(|| AccountState::new(today));
+                AccountStats {
+                    health: state.health,
+                    requests_today: if state.day == today {
+                        state.requests_today
+                    } else {
+                        0
+                    },
+                    cooldown_secs: state.cooldown_left(now).map(|left| left.as_secs()),
+                    strikes: state.strikes,
+                    locked: state.locked,
+                    username: account.username,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Time until the next UTC midnight, when daily budgets renew
+fn until_next_day() -> Duration {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight exists")
+        .and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linkedin_pool(config: AccountPoolConfig) -> AccountPool {
+        let vault = CredentialVault::in_memory()
+            .with_account("linkedin", Account::new("a@example.com", "a"))
+            .with_account("linkedin", Account::new("b@example.com", "b"))
+            .with_account("linkedin", Account::new("c@example.com", "c"));
+        AccountPool::new(Arc::new(vault), config)
+    }
+
+    fn take(pool: &AccountPool) -> String {
+        pool.acquire("linkedin").unwrap().username
+    }
+
+    #[test]
+    fn test_least_recently_used_healthy_account() {
+        let pool = linkedin_pool(AccountPoolConfig::default());
+        assert_eq!(take(&pool), "a@example.com");
+        assert_eq!(take(&pool), "b@example.com");
+        assert_eq!(take(&pool), "c@example.com");
+        assert_eq!(take(&pool), "a@example.com");
+
+        // b rests after a soft block, and c is gone for good
+        pool.report("linkedin", "b@example.com", AccountOutcome::SoftBlock);
+        pool.report("linkedin", "c@example.com", AccountOutcome::Locked);
+        assert_eq!(take(&pool), "a@example.com");
+        let stats = pool.stats("linkedin");
+        assert_eq!(stats[0].requests_today, 3);
+        assert_eq!(stats[1].strikes, 1);
+        let left = stats[1].cooldown_secs.unwrap();
+        assert!((899..=900).contains(&left));
+        assert!(stats[1].health < 1.0 && stats[2].locked);
+
+        // A second block in a row rests it twice as long
+        pool.report("linkedin", "b@example.com", AccountOutcome::SoftBlock);
+        let left = pool.stats("linkedin")[1].cooldown_secs.unwrap();
+        assert!((1799..=1800).contains(&left));
+        pool.reset("linkedin", "b@example.com");
+        pool.reset("linkedin", "c@example.com");
+        assert_eq!(take(&pool), "b@example.com");
+        assert_eq!(take(&pool), "c@example.com");
+        assert!(pool.acquire("x").unwrap_err().retry_in.is_none());
+    }
+
+    #[test]
+    fn test_budgets_and_health_exhaust_accounts() {
+        let pool = linkedin_pool(AccountPoolConfig {
+            daily_requests: 1,
+            ..AccountPoolConfig::default()
+        });
+        for _ in 0..3 {
+            take(&pool);
+        }
+        let spent = pool.acquire("linkedin").unwrap_err();
+        let retry_in = spent.retry_in.unwrap();
+        assert!(retry_in > Duration::ZERO && retry_in <= Duration::from_secs(24 * 3600));
+
+        let pool = linkedin_pool(AccountPoolConfig {
+            cooldown_secs: 0,
+            ..AccountPoolConfig::default()
+        });
+        // Blocks that keep coming wear its health down until it is left alone
+        for _ in 0..4 {
+            pool.report("linkedin", "a@example.com", AccountOutcome::SoftBlock);
+        }
+        assert_eq!(take(&pool), "b@example.com");
+        assert_eq!(take(&pool), "c@example.com");
+        assert_eq!(take(&pool), "b@example.com");
+    }
+}
@@ -6,8 +6,12 @@
 //! - Content-aware scroll behavior and timing
 //! - Timing variation engine with statistical variance
 //! - Session & navigation simulation
+//! - Diurnal request pacing with session breaks for long crawls
 
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, FixedOffset, Timelike, Utc};
 use rand::{Rng, thread_rng};
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
@@ -19,6 +23,7 @@ pub struct BehaviorEngine {
     scroll_simulator: ScrollSimulator,
     timing_engine: TimingEngine,
     navigation_simulator: NavigationSimulator,
+    pacing: Option<Arc<PacingPlanner>>,
 }
 
 impl BehaviorEngine {
@@ -30,9 +35,23 @@ impl BehaviorEngine {
             scroll_simulator: ScrollSimulator::new(),
             timing_engine: TimingEngine::new(),
             navigation_simulator: NavigationSimulator::new(),
+            pacing: None,
         })
     }
 
+    /// Pace requests over the day with `planner`
+    pub fn with_pacing(mut self, planner: Arc<PacingPlanner>) -> Self {
+        self.pacing = Some(planner);
+        self
+    }
+
+    /// Wait for `host`'s next slot in the pacing plan, if there is one
+    pub async fn apply_pacing(&self, host: &str) {
+        if let Some(pacing) = &self.pacing {
+            pacing.pace(host).await;
+        }
+    }
+
     /// Apply human-like timing delay
     pub async fn apply_timing_delay(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let delay = self.timing_engine.calculate_natural_delay().await;
@@ -603,3 +622,344 @@ pub struct TimingCharacteristics {
     pub variance_factor: f64,
     pub context_aware: bool,
 }
+
+// Diurnal pacing
+
+/// Activity below which an hour counts as quiet and nothing is fetched
+const QUIET_ACTIVITY: f64 = 0.01;
+
+/// Slowest a low-activity hour stretches the gap between requests
+const MAX_SLOWDOWN: f64 = 20.0;
+
+/// Relative request volume for each hour of the target's local day, `0.0`
+/// (quiet, nothing fetched) to `1.0` (busiest)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DiurnalCurve(pub [f64; 24]);
+
+impl DiurnalCurve {
+    /// Built-in curves
+    pub const NAMES: &'static [&'static str] = &["human", "office", "evening", "flat"];
+
+    /// Browsing at home: quiet nights, busy lunch breaks and evenings
+    pub fn human() -> Self {
+        Self([
+            0.15, 0.08, 0.04, 0.0, 0.0, 0.03, 0.1, 0.3, 0.5, 0.6, 0.65, 0.7, 0.8, 0.7, 0.6, 0.6,
+            0.65, 0.75, 0.85, 0.95, 1.0, 0.9, 0.6, 0.3,
+        ])
+    }
+
+    /// Working hours only, with a dip at lunch
+    pub fn office() -> Self {
+        Self([
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.2, 0.6, 1.0, 1.0, 0.9, 0.5, 0.8, 1.0, 1.0, 0.9,
+            0.5, 0.2, 0.05, 0.0, 0.0, 0.0, 0.0,
+        ])
+    }
+
+    /// Mostly after work and late at night
+    pub fn evening() -> Self {
+        Self([
+            0.6, 0.4, 0.15, 0.0, 0.0, 0.0, 0.0, 0.05, 0.1, 0.1, 0.1, 0.15, 0.25, 0.15, 0.1, 0.15,
+            0.25, 0.4, 0.6, 0.8, 1.0, 1.0, 0.95, 0.8,
+        ])
+    }
+
+    /// The same volume around the clock; only session breaks apply
+    pub fn flat() -> Self {
+        Self([1.0; 24])
+    }
+
+    /// A built-in curve by name, or 24 comma-separated hourly weights
+    /// starting at midnight, scaled so the busiest hour is `1.0`
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "human" => return Ok(Self::human()),
+            "office" => return Ok(Self::office()),
+            "evening" => return Ok(Self::evening()),
+            "flat" => return Ok(Self::flat()),
+            _ => {}
+        }
+        let weights = spec
+            .split(',')
+            .map(|weight| weight.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|weights| {
+                weights
+                    .iter()
+                    .all(|weight| weight.is_finite() && *weight >= 0.0)
+            });
+        let weights: [f64; 24] = match weights.map(<[f64; 24]>::try_from) {
+            Some(Ok(weights)) => weights,
+            _ => {
+                return Err(format!(
+                    "Unknown pacing curve '{}', expected one of {} or 24 comma-separated weights",
+                    spec,
+                    Self::NAMES.join(", ")
+                )
+                .into())
+            }
+        };
+        let busiest = weights.iter().cloned().fold(0.0, f64::max);
+        if busiest == 0.0 {
+            return Err("A pacing curve needs at least one active hour".into());
+        }
+        Ok(Self(weights.map(|weight| weight / busiest)))
+    }
+
+    /// Activity during `hour` (0-23)
+    pub fn activity(&self, hour: u32) -> f64 {
+        self.0[hour as usize % 24]
+    }
+}
+
+/// The curve's name if it is a built-in one, otherwise its weights as
+/// [`DiurnalCurve::parse`] reads them
+impl std::fmt::Display for DiurnalCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let named = Self::NAMES
+            .iter()
+            .find(|name| Self::parse(name).is_ok_and(|curve| curve == *self));
+        match named {
+            Some(name) => f.write_str(name),
+            None => {
+                let weights: Vec<String> = self.0.iter().map(f64::to_string).collect();
+                f.write_str(&weights.join(","))
+            }
+        }
+    }
+}
+
+impl Default for DiurnalCurve {
+    fn default() -> Self {
+        Self::human()
+    }
+}
+
+/// How a [`PacingPlanner`] spreads requests over the day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PacingConfig {
+    pub curve: DiurnalCurve,
+    /// Offset of the target market's local time from UTC, in minutes
+    pub utc_offset_minutes: i32,
+    /// Gap between a host's requests in its busiest hour, before jitter
+    pub base_interval_ms: u64,
+    /// Range a session with one host lasts before a break
+    pub session_minutes: (u64, u64),
+    /// Range a break between sessions lasts
+    pub break_minutes: (u64, u64),
+}
+
+impl Default for PacingConfig {
+    fn default() -> Self {
+        Self {
+            curve: DiurnalCurve::default(),
+            utc_offset_minutes: 0,
+            base_interval_ms: 3000,
+            session_minutes: (20, 45),
+            break_minutes: (5, 20),
+        }
+    }
+}
+
+/// Where one host's current session stands
+#[derive(Debug, Clone, Copy)]
+struct PacingSession {
+    ends: Instant,
+    /// Earliest the host's next request may go out
+    next: Instant,
+}
+
+/// Shapes request volume over long crawls like a person's browsing: fewer
+/// requests in the target market's quiet hours, none at all in hours the
+/// curve marks quiet, and sessions with each host broken up by minutes of
+/// inactivity
+///
+/// Applied on top of the rate limiter, which still caps bursts: each request
+/// reserves the host's next slot, so concurrent requests to a host queue up
+/// behind each other instead of sharing one delay.
+#[derive(Debug)]
+pub struct PacingPlanner {
+    config: PacingConfig,
+    offset: FixedOffset,
+    sessions: Mutex<HashMap<String, PacingSession>>,
+}
+
+impl PacingPlanner {
+    pub fn new(config: PacingConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let offset = FixedOffset::east_opt(config.utc_offset_minutes * 60).ok_or_else(|| {
+            format!(
+                "Invalid UTC offset of {} minutes",
+                config.utc_offset_minutes
+            )
+        })?;
+        let ranges = [config.session_minutes, config.break_minutes];
+        if ranges.iter().any(|(min, max)| min > max) || config.session_minutes.0 == 0 {
+            return Err(
+                "Pacing session and break ranges need min <= max and sessions of at least a minute"
+                    .into(),
+            );
+        }
+        Ok(Self {
+            config,
+            offset,
+            sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn config(&self) -> &PacingConfig {
+        &self.config
+    }
+
+    /// Wait for `host`'s next slot
+    pub async fn pace(&self, host: &str) {
+        let delay = self.reserve(host, Utc::now(), Instant::now());
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+
+    /// Reserve `host`'s next slot and return how long until it, given the
+    /// wall clock and monotonic clock read at the same moment
+    fn reserve(&self, host: &str, wall: DateTime<Utc>, now: Instant) -> Duration {
+        let mut rng = thread_rng();
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .entry(host.to_ascii_lowercase())
+            .or_insert_with(|| PacingSession {
+                ends: now + self.session_length(),
+                next: now,
+            });
+
+        let mut slot = session.next.max(now);
+        if slot >= session.ends {
+            // A host left alone for longer than a break needs no extra one
+            let resume = session.ends
+                + minutes(rng.gen_range(self.config.break_minutes.0..=self.config.break_minutes.1));
+            slot = slot.max(resume);
+            session.ends = slot + self.session_length();
+        }
+
+        let local = wall.with_timezone(&self.offset) + (slot - now);
+        let mut activity = self.config.curve.activity(local.hour());
+        if activity < QUIET_ACTIVITY {
+            // Wait for the next active hour, arriving some minutes into it
+            let quiet_hours = (1..=24)
+                .find(|ahead| self.config.curve.activity(local.hour() + ahead) >= QUIET_ACTIVITY)
+                .unwrap_or(1);
+            let into_hour = Duration::from_secs(u64::from(local.minute() * 60 + local.second()));
+            slot = slot + Duration::from_secs(u64::from(quiet_hours) * 3600) - into_hour
+                + minutes(rng.gen_range(0..=10));
+            session.ends = slot + self.session_length();
+            activity = self.config.curve.activity(local.hour() + quiet_hours);
+        }
+
+        let gap = self.config.base_interval_ms as f64
+            * rng.gen_range(0.5..1.5)
+            * (1.0 / activity).min(MAX_SLOWDOWN);
+        session.next = slot + Duration::from_millis(gap as u64);
+        slot - now
+    }
+
+    fn session_length(&self) -> Duration {
+        let (min, max) = self.config.session_minutes;
+        minutes(thread_rng().gen_range(min..=max))
+    }
+}
+
+fn minutes(minutes: u64) -> Duration {
+    Duration::from_secs(minutes * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn planner(curve: DiurnalCurve) -> PacingPlanner {
+        PacingPlanner::new(PacingConfig {
+            curve,
+            base_interval_ms: 1000,
+            session_minutes: (10, 10),
+            break_minutes: (5, 5),
+            ..PacingConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_curves() {
+        assert_eq!(
+            DiurnalCurve::parse("Office").unwrap(),
+            DiurnalCurve::office()
+        );
+        let custom = DiurnalCurve::parse(&["2"; 24].join(",")).unwrap();
+        assert_eq!(custom, DiurnalCurve::flat());
+        assert_eq!(custom.to_string(), "flat");
+        let mut weights = [0.5; 24];
+        weights[20] = 1.0;
+        let spec = DiurnalCurve(weights).to_string();
+        assert_eq!(DiurnalCurve::parse(&spec).unwrap(), DiurnalCurve(weights));
+        assert!(DiurnalCurve::parse("0,1,2").is_err());
+        assert!(DiurnalCurve::parse(&["0"; 24].join(",")).is_err());
+        assert!(DiurnalCurve::parse("nocturnal").is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_slow_and_pause() {
+        let now = Instant::now();
+        let noon = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+
+        // Requests to one host queue behind each other, spaced further
+        // apart the quieter the hour
+        let busy = planner(DiurnalCurve::flat());
+        assert_eq!(busy.reserve("shop.test", noon, now), Duration::ZERO);
+        let second = busy.reserve("shop.test", noon, now);
+        assert!(second >= Duration::from_millis(500) && second < Duration::from_millis(1500));
+        assert_eq!(busy.reserve("other.test", noon, now), Duration::ZERO);
+
+        let mut weights = [1.0; 24];
+        weights[12] = 0.1;
+        let slow = planner(DiurnalCurve(weights));
+        slow.reserve("shop.test", noon, now);
+        assert!(slow.reserve("shop.test", noon, now) >= Duration::from_secs(5));
+
+        // Nothing goes out before the office opens, in the market's time
+        let office = PacingPlanner::new(PacingConfig {
+            curve: DiurnalCurve::office(),
+            utc_offset_minutes: -5 * 60,
+            ..PacingConfig::default()
+        })
+        .unwrap();
+        // 10:00 UTC is 05:00 in the market, two hours before it opens
+        let delay = office.reserve("shop.test", noon - chrono::Duration::hours(2), now);
+        assert!(
+            delay >= Duration::from_secs(2 * 3600) && delay <= Duration::from_secs(2 * 3600 + 600)
+        );
+    }
+
+    #[test]
+    fn test_sessions_end_in_breaks() {
+        let now = Instant::now();
+        let noon = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let planner = planner(DiurnalCurve::flat());
+        planner.reserve("shop.test", noon, now);
+
+        // Past the session's ten minutes the host rests for five
+        let later = now + Duration::from_secs(11 * 60);
+        let wall = noon + chrono::Duration::minutes(11);
+        assert_eq!(
+            planner.reserve("shop.test", wall, later),
+            Duration::from_secs(4 * 60)
+        );
+
+        // A host idle for longer than a break picks up right away
+        let much_later = now + Duration::from_secs(3600);
+        let wall = noon + chrono::Duration::hours(1);
+        assert_eq!(
+            planner.reserve("shop.test", wall, much_later),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,595 @@
+//! Block page corpus and learned signatures
+//!
+//! The built-in checks ([`is_bot_protected`](crate::utils::is_bot_protected)
+//! and [`detect_vendor`](super::vendors::detect_vendor)) only know generic
+//! phrases and the big vendors' markup. A [`BlockCorpus`] collects labeled
+//! samples of the block and captcha pages a crawl actually runs into, next to
+//! clean pages of the same sites, and [`BlockSignatures::train`] turns it into
+//! a signature set: text trigrams, element ids and classes, and script or form
+//! sources that occur on block pages but never on clean ones. Installing the
+//! set with [`install`] extends the built-in checks with it.
+
+use super::vendors::BotVendor;
+use crate::extractors::{extract_text_secure, html_tags};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+const INDEX_FILE: &str = "index.json";
+
+/// Signatures each block sample should be recognized by
+const PER_SAMPLE: usize = 3;
+
+/// Signatures a page must contain to match a label with at least that many
+const MIN_MATCHES: usize = 2;
+
+/// Upper bound on the size of a trained set
+const MAX_SIGNATURES: usize = 500;
+
+/// Signature set consulted by the built-in block checks
+static INSTALLED: Lazy<RwLock<Arc<BlockSignatures>>> =
+    Lazy::new(|| RwLock::new(Arc::new(BlockSignatures::default())));
+
+/// What a sampled page is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockLabel {
+    /// Access denied or challenge page
+    Block,
+    Captcha,
+    /// A real page of the site, which signatures must never match
+    Clean,
+}
+
+impl BlockLabel {
+    pub const NAMES: &'static [&'static str] = &["block", "captcha", "clean"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Self::Block),
+            "captcha" => Some(Self::Captcha),
+            "clean" => Some(Self::Clean),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Captcha => "captcha",
+            Self::Clean => "clean",
+        }
+    }
+
+    /// Label for a page the built-in checks flagged, before anyone looked at it
+    pub fn guess(html: &str) -> Self {
+        if html.to_ascii_lowercase().contains("captcha") {
+            Self::Captcha
+        } else {
+            Self::Block
+        }
+    }
+}
+
+/// One page of the corpus; the page itself is kept next to the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSample {
+    /// Start of the page's SHA-256, so the same page is only kept once
+    pub id: String,
+    pub label: BlockLabel,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    pub vendor: Option<BotVendor>,
+    /// Whether the label was given by a person rather than guessed
+    pub reviewed: bool,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Labeled block, captcha and clean pages kept in a directory
+#[derive(Debug)]
+pub struct BlockCorpus {
+    dir: PathBuf,
+    /// Serializes read-modify-write cycles of the index
+    lock: Mutex<()>,
+}
+
+impl BlockCorpus {
+    /// Corpus in `dir`, created if missing
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Samples, oldest first
+    pub fn samples(&self) -> Result<Vec<BlockSample>> {
+        let path = self.dir.join(INDEX_FILE);
+        match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .with_context(|| format!("Unreadable corpus index {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Page stored for `sample`
+    pub fn html(&self, sample: &BlockSample) -> Result<String> {
+        let path = self.page_path(&sample.id);
+        fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))
+    }
+
+    /// Add a page; a page already in the corpus keeps its entry, and is only
+    /// relabeled when `sample` is reviewed. Returns the stored entry and
+    /// whether it is new.
+    pub fn add(&self, html: &str, mut sample: BlockSample) -> Result<(BlockSample, bool)> {
+        let _guard = self.lock.lock().unwrap();
+        sample.id = format!("{:x}", Sha256::digest(html.as_bytes()))[..12].to_string();
+        let mut samples = self.samples()?;
+        if let Some(existing) = samples.iter_mut().find(|existing| existing.id == sample.id) {
+            if sample.reviewed {
+                existing.label = sample.label;
+                existing.reviewed = true;
+            }
+            let existing = existing.clone();
+            self.write_index(&samples)?;
+            return Ok((existing, false));
+        }
+        fs::write(self.page_path(&sample.id), html)?;
+        samples.push(sample.clone());
+        self.write_index(&samples)?;
+        Ok((sample, true))
+    }
+
+    /// Keep a fraction `rate` of the pages offered, labeled by a guess until
+    /// reviewed; returns the sample when the page was kept
+    pub fn sample(
+        &self,
+        rate: f64,
+        html: &str,
+        url: &str,
+        status: Option<u16>,
+        vendor: Option<BotVendor>,
+    ) -> Result<Option<BlockSample>> {
+        if !rand::thread_rng().gen_bool(rate.clamp(0.0, 1.0)) {
+            return Ok(None);
+        }
+        let sample = BlockSample {
+            id: String::new(),
+            label: BlockLabel::guess(html),
+            url: Some(url.to_string()),
+            status,
+            vendor,
+            reviewed: false,
+            captured_at: Utc::now(),
+        };
+        let (sample, new) = self.add(html, sample)?;
+        Ok(new.then_some(sample))
+    }
+
+    /// Give the sample whose id starts with `id` a reviewed label
+    pub fn relabel(&self, id: &str, label: BlockLabel) -> Result<BlockSample> {
+        let _guard = self.lock.lock().unwrap();
+        let mut samples = self.samples()?;
+        let index = self.find(&samples, id)?;
+        samples[index].label = label;
+        samples[index].reviewed = true;
+        self.write_index(&samples)?;
+        Ok(samples[index].clone())
+    }
+
+    /// Drop the sample whose id starts with `id`
+    pub fn remove(&self, id: &str) -> Result<BlockSample> {
+        let _guard = self.lock.lock().unwrap();
+        let mut samples = self.samples()?;
+        let sample = samples.remove(self.find(&samples, id)?);
+        self.write_index(&samples)?;
+        let _ = fs::remove_file(self.page_path(&sample.id));
+        Ok(sample)
+    }
+
+    /// Every sample with its page, skipping pages that went missing
+    pub fn load(&self) -> Result<Vec<(BlockSample, String)>> {
+        Ok(self
+            .samples()?
+            .into_iter()
+            .filter_map(|sample| {
+                let html = self.html(&sample).ok()?;
+                Some((sample, html))
+            })
+            .collect())
+    }
+
+    fn find(&self, samples: &[BlockSample], id: &str) -> Result<usize> {
+        let matches: Vec<usize> = samples
+            .iter()
+            .enumerate()
+            .filter(|(_, sample)| !id.is_empty() && sample.id.starts_with(id))
+            .map(|(index, _)| index)
+            .collect();
+        match matches[..] {
+            [index] => Ok(index),
+            [] => bail!("No sample '{}' in the corpus", id),
+            _ => bail!("Sample id '{}' is ambiguous", id),
+        }
+    }
+
+    fn page_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.html", id))
+    }
+
+    fn write_index(&self, samples: &[BlockSample]) -> Result<()> {
+        fs::write(
+            self.dir.join(INDEX_FILE),
+            serde_json::to_vec_pretty(samples)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// A feature of block pages that clean pages lack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub label: BlockLabel,
+    /// `text:` trigram, `id:`, `class:` or `src:` feature
+    pub feature: String,
+    /// Vendor of every sample the feature was found on, if they agree
+    pub vendor: Option<BotVendor>,
+    /// Samples of the label the feature was found on
+    pub support: usize,
+}
+
+/// A page recognized by learned signatures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockMatch {
+    pub label: BlockLabel,
+    pub vendor: Option<BotVendor>,
+    /// Features of the signatures found on the page
+    pub features: Vec<String>,
+}
+
+/// Signatures learned from a corpus
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSignatures {
+    pub signatures: Vec<Signature>,
+    /// Samples per label the set was trained on
+    pub samples: BTreeMap<BlockLabel, usize>,
+    pub trained_at: Option<DateTime<Utc>>,
+}
+
+impl BlockSignatures {
+    /// Pick, per block label, the features covering each sample of the label
+    /// best while occurring on no clean sample
+    pub fn train(corpus: &[(BlockSample, String)]) -> Self {
+        let featured: Vec<(&BlockSample, HashSet<String>)> = corpus
+            .iter()
+            .map(|(sample, html)| (sample, page_features(html)))
+            .collect();
+        let clean: HashSet<&String> = featured
+            .iter()
+            .filter(|(sample, _)| sample.label == BlockLabel::Clean)
+            .flat_map(|(_, features)| features)
+            .collect();
+
+        let mut signatures = Vec::new();
+        for label in [BlockLabel::Block, BlockLabel::Captcha] {
+            let positives: Vec<&(&BlockSample, HashSet<String>)> = featured
+                .iter()
+                .filter(|(sample, _)| sample.label == label)
+                .collect();
+            let mut found_on: HashMap<&String, Vec<usize>> = HashMap::new();
+            for (index, (_, features)) in positives.iter().enumerate() {
+                for feature in features.iter().filter(|feature| !clean.contains(feature)) {
+                    found_on.entry(feature).or_default().push(index);
+                }
+            }
+            // Widest support first, then the kinds that generalize best
+            let mut candidates: Vec<(&String, Vec<usize>)> = found_on.into_iter().collect();
+            candidates.sort_by(|(a, on_a), (b, on_b)| {
+                on_b.len()
+                    .cmp(&on_a.len())
+                    .then(feature_rank(a).cmp(&feature_rank(b)))
+                    .then(a.cmp(b))
+            });
+
+            let mut coverage = vec![0usize; positives.len()];
+            for (feature, on) in candidates {
+                if signatures.len() == MAX_SIGNATURES || coverage.iter().all(|n| *n >= PER_SAMPLE) {
+                    break;
+                }
+                if on.iter().all(|index| coverage[*index] >= PER_SAMPLE) {
+                    continue;
+                }
+                for index in &on {
+                    coverage[*index] += 1;
+                }
+                let mut vendors = on.iter().map(|index| positives[*index].0.vendor);
+                let first = vendors.next().flatten();
+                signatures.push(Signature {
+                    label,
+                    feature: feature.clone(),
+                    vendor: vendors
+                        .all(|vendor| vendor == first)
+                        .then_some(first)
+                        .flatten(),
+                    support: on.len(),
+                });
+            }
+        }
+
+        let mut samples = BTreeMap::new();
+        for (sample, _) in corpus {
+            *samples.entry(sample.label).or_insert(0) += 1;
+        }
+        Self {
+            signatures,
+            samples,
+            trained_at: Some(Utc::now()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Block label whose signatures `html` matches most
+    pub fn classify(&self, html: &str) -> Option<BlockMatch> {
+        if self.signatures.is_empty() {
+            return None;
+        }
+        let features = page_features(html);
+        [BlockLabel::Block, BlockLabel::Captcha]
+            .into_iter()
+            .filter_map(|label| {
+                let of_label: Vec<&Signature> = self
+                    .signatures
+                    .iter()
+                    .filter(|signature| signature.label == label)
+                    .collect();
+                let matched: Vec<&Signature> = of_label
+                    .iter()
+                    .copied()
+                    .filter(|signature| features.contains(&signature.feature))
+                    .collect();
+                let needed = MIN_MATCHES.min(of_label.len());
+                (needed > 0 && matched.len() >= needed).then_some((label, matched))
+            })
+            .max_by_key(|(_, matched)| matched.len())
+            .map(|(label, matched)| {
+                let mut vendors: BTreeMap<BotVendor, usize> = BTreeMap::new();
+                for vendor in matched.iter().filter_map(|signature| signature.vendor) {
+                    *vendors.entry(vendor).or_insert(0) += 1;
+                }
+                BlockMatch {
+                    label,
+                    vendor: vendors
+                        .into_iter()
+                        .max_by_key(|(_, count)| *count)
+                        .map(|(vendor, _)| vendor),
+                    features: matched
+                        .iter()
+                        .map(|signature| signature.feature.clone())
+                        .collect(),
+                }
+            })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| anyhow!("Unreadable signature set {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Make `signatures` extend the built-in block checks process-wide
+pub fn install(signatures: BlockSignatures) {
+    *INSTALLED.write().unwrap() = Arc::new(signatures);
+}
+
+/// Signature set the built-in block checks use, empty until [`install`]ed
+pub fn installed() -> Arc<BlockSignatures> {
+    INSTALLED.read().unwrap().clone()
+}
+
+/// Signature kinds in order of preference: sources and ids are what vendors
+/// rarely change, text and classes vary more between pages
+fn feature_rank(feature: &str) -> usize {
+    ["src:", "id:", "text:", "class:"]
+        .iter()
+        .position(|kind| feature.starts_with(kind))
+        .unwrap_or(usize::MAX)
+}
+
+/// Word trigrams of a page's text, and the ids, classes and script, frame
+/// and form sources of its markup
+fn page_features(html: &str) -> HashSet<String> {
+    let mut features = HashSet::new();
+    let text = extract_text_secure(html).unwrap_or_default().to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    for trigram in words.windows(3) {
+        features.insert(format!("text:{}", trigram.join(" ")));
+    }
+
+    for tag in html_tags(html) {
+        if let Some(id) = tag.attributes.get("id").filter(|id| id.len() > 3) {
+            features.insert(format!("id:{}", id.to_ascii_lowercase()));
+        }
+        if let Some(classes) = tag.attributes.get("class") {
+            for class in classes.split_whitespace().filter(|class| class.len() > 3) {
+                features.insert(format!("class:{}", class.to_ascii_lowercase()));
+            }
+        }
+        let source = match tag.name.as_str() {
+            "script" | "iframe" | "img" => tag.attributes.get("src"),
+            "form" => tag.attributes.get("action"),
+            _ => None,
+        };
+        if let Some(source) = source.and_then(|source| source_feature(source)) {
+            features.insert(source);
+        }
+    }
+    features
+}
+
+/// Host and first path segment of a source, e.g.
+/// `src:geo.captcha-delivery.com/captcha`
+fn source_feature(source: &str) -> Option<String> {
+    let base = url::Url::parse("https://relative.invalid/").unwrap();
+    let url = base.join(source.trim()).ok()?;
+    let host = url.host_str().filter(|host| *host != "relative.invalid");
+    let segment = url
+        .path_segments()
+        .and_then(|mut segments| segments.next())
+        .filter(|segment| !segment.is_empty());
+    match (host, segment) {
+        (None, None) => None,
+        (host, segment) => Some(format!(
+            "src:{}/{}",
+            host.unwrap_or_default().to_ascii_lowercase(),
+            segment.unwrap_or_default().to_ascii_lowercase()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(label: BlockLabel, vendor: Option<BotVendor>, html: &str) -> (BlockSample, String) {
+        let sample = BlockSample {
+            id: format!("{:x}", Sha256::digest(html.as_bytes()))[..12].to_string(),
+            label,
+            url: None,
+            status: Some(200),
+            vendor,
+            reviewed: true,
+            captured_at: Utc::now(),
+        };
+        (sample, html.to_string())
+    }
+
+    fn corpus() -> Vec<(BlockSample, String)> {
+        let shield = |ray: &str| {
+            format!(
+                r#"<html><body><div id="shield-wall"><h1>Our shield stopped this visit</h1>
+                <p>Reference {}. Your visit looked automated to us.</p>
+                <script src="https://shield.example.net/wall/v2.js"></script></div></body></html>"#,
+                ray
+            )
+        };
+        vec![
+            page(BlockLabel::Block, Some(BotVendor::Imperva), &shield("a81f")),
+            page(BlockLabel::Block, Some(BotVendor::Imperva), &shield("c2d0")),
+            page(
+                BlockLabel::Captcha,
+                None,
+                r#"<html><body><form action="/verify-human"><p>Type the letters you see to continue shopping.</p>
+                <img src="/verify-human/image.png"></form></body></html>"#,
+            ),
+            page(
+                BlockLabel::Clean,
+                None,
+                r#"<html><body><div class="wall-art"><h1>Canvas prints</h1>
+                <p>Your visit looked great on our wall art. Continue shopping for frames.</p></div></body></html>"#,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_train_and_classify() {
+        let signatures = BlockSignatures::train(&corpus());
+        assert_eq!(signatures.samples[&BlockLabel::Clean], 1);
+        assert!(signatures
+            .signatures
+            .iter()
+            .any(
+                |signature| signature.feature == "src:shield.example.net/wall"
+                    && signature.support == 2
+                    && signature.vendor == Some(BotVendor::Imperva)
+            ));
+        // Phrases a clean page shares never become signatures
+        assert!(!signatures
+            .signatures
+            .iter()
+            .any(|signature| signature.feature == "text:your visit looked"));
+
+        let blocked = signatures
+            .classify(
+                r#"<div id="shield-wall"><p>Reference 99ee. Your visit looked automated to us.</p></div>"#,
+            )
+            .unwrap();
+        assert_eq!(blocked.label, BlockLabel::Block);
+        assert_eq!(blocked.vendor, Some(BotVendor::Imperva));
+        let captcha = signatures
+            .classify(r#"<form action="/verify-human?step=2"><p>Type the letters you see to continue</p></form>"#)
+            .unwrap();
+        assert_eq!(captcha.label, BlockLabel::Captcha);
+
+        // One stray match is not enough
+        assert_eq!(
+            signatures.classify(r#"<div id="shield-wall">Welcome</div>"#),
+            None
+        );
+        assert_eq!(signatures.classify(&corpus()[3].1), None);
+        assert_eq!(BlockSignatures::default().classify(&corpus()[0].1), None);
+    }
+
+    #[test]
+    fn test_corpus_keeps_labeled_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let corpus = BlockCorpus::open(dir.path().join("blocks")).unwrap();
+        let html = "<p>Access to this page has been denied</p>";
+        let first = corpus
+            .sample(1.0, html, "https://shop.test/", Some(200), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.label, BlockLabel::Block);
+        assert!(!first.reviewed);
+        // The same page is kept once
+        assert_eq!(
+            corpus
+                .sample(1.0, html, "https://shop.test/p/1", Some(200), None)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            corpus
+                .sample(0.0, "<p>other</p>", "", Some(200), None)
+                .unwrap(),
+            None
+        );
+
+        let relabeled = corpus.relabel(&first.id[..6], BlockLabel::Clean).unwrap();
+        assert!(relabeled.reviewed);
+        let loaded = corpus.load().unwrap();
+        assert_eq!(loaded, vec![(relabeled.clone(), html.to_string())]);
+        assert!(corpus.relabel("", BlockLabel::Block).is_err());
+
+        corpus.remove(&relabeled.id).unwrap();
+        assert!(corpus.samples().unwrap().is_empty());
+        assert!(!corpus.dir().join(format!("{}.html", relabeled.id)).exists());
+    }
+}
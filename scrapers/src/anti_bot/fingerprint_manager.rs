@@ -6,12 +6,143 @@
 //! - AudioContext fingerprinting evasion
 //! - TLS/HTTP2 signature randomization (JA3/JA4)
 //! - Screen/viewport randomization
+//!
+//! Browser sessions get a [`SessionFingerprint`] drawn from a per-session
+//! seed. Its [`injection_script`](SessionFingerprint::injection_script)
+//! overrides the canvas, WebGL, audio, screen and hardware APIs, and has to
+//! run before any page script does, e.g. through CDP's
+//! `Page.addScriptToEvaluateOnNewDocument`. Canvas and audio noise is derived
+//! from the seed and the data read, so a page reading the same canvas twice
+//! gets the same answer, as it would from a real browser, while another
+//! session answers differently.
+//!
+//! Detectors look for devices that can't exist, like an Apple GPU on
+//! Windows or a budget laptop with 16 cores, so a session's values are never
+//! picked independently: they all come from one [`HardwareProfile`], a named
+//! device whose GPU, screens, cores, memory and audio hardware belong
+//! together, running the operating system the user agent claims.
 
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use rand::{Rng, thread_rng};
+use rand::{rngs::StdRng, Rng, SeedableRng, thread_rng};
 use serde::{Deserialize, Serialize};
 
+/// JavaScript applying a [`SessionFingerprint`], which replaces `__FINGERPRINT__`
+const INJECTION_SCRIPT: &str = r#"(() => {
+  const fingerprint = __FINGERPRINT__;
+  // Deterministic noise in [0, 1) for position `n`, from the session's seed
+  const noise = (n) => {
+    let t = (fingerprint.seed ^ Math.imul(n, 0x9e3779b1)) >>> 0;
+    t = Math.imul(t ^ (t >>> 15), t | 1);
+    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+
+  // Overridden functions still print as native code
+  const natives = new WeakMap();
+  const toString = Function.prototype.toString;
+  const patch = (object, name, replacement) => {
+    natives.set(replacement, toString.call(object[name]));
+    object[name] = replacement;
+  };
+  patch(Function.prototype, 'toString', function () {
+    return natives.get(this) ?? toString.call(this);
+  });
+  const define = (object, name, value) =>
+    Object.defineProperty(object, name, { get: () => value, configurable: true });
+
+  // Canvas: flip the low bit of a seeded tenth of the pixels
+  const shift = (data) => {
+    for (let i = 0; i < data.length; i += 4) {
+      if (noise(i) < 0.1) {
+        data[i] ^= 1;
+      }
+    }
+  };
+  const getImageData = CanvasRenderingContext2D.prototype.getImageData;
+  patch(CanvasRenderingContext2D.prototype, 'getImageData', function (...args) {
+    const image = getImageData.apply(this, args);
+    shift(image.data);
+    return image;
+  });
+  // Exports read a noisy copy, leaving the page's canvas as drawn
+  const noisyCopy = (canvas) => {
+    const copy = document.createElement('canvas');
+    copy.width = canvas.width;
+    copy.height = canvas.height;
+    const context = copy.getContext('2d');
+    if (canvas.width && canvas.height) {
+      context.drawImage(canvas, 0, 0);
+      const image = getImageData.call(context, 0, 0, canvas.width, canvas.height);
+      shift(image.data);
+      context.putImageData(image, 0, 0);
+    }
+    return copy;
+  };
+  for (const name of ['toDataURL', 'toBlob']) {
+    const original = HTMLCanvasElement.prototype[name];
+    patch(HTMLCanvasElement.prototype, name, function (...args) {
+      return original.apply(noisyCopy(this), args);
+    });
+  }
+
+  // WebGL: the unmasked vendor and renderer of the session's GPU
+  for (const context of [window.WebGLRenderingContext, window.WebGL2RenderingContext]) {
+    if (!context) {
+      continue;
+    }
+    const getParameter = context.prototype.getParameter;
+    patch(context.prototype, 'getParameter', function (parameter) {
+      if (parameter === 0x9245) {
+        return fingerprint.webgl_vendor;
+      }
+      if (parameter === 0x9246) {
+        return fingerprint.webgl_renderer;
+      }
+      return getParameter.call(this, parameter);
+    });
+  }
+
+  // Audio: seeded noise far below what can be heard, added once per buffer
+  if (window.AudioBuffer) {
+    const getChannelData = AudioBuffer.prototype.getChannelData;
+    const noisy = new WeakSet();
+    patch(AudioBuffer.prototype, 'getChannelData', function (...args) {
+      const data = getChannelData.apply(this, args);
+      if (!noisy.has(data)) {
+        noisy.add(data);
+        for (let i = 0; i < data.length; i += 100) {
+          data[i] += (noise(i) - 0.5) * 1e-7;
+        }
+      }
+      return data;
+    });
+  }
+
+  // The rate of the device's audio output; offline contexts keep the rate
+  // the page asks for
+  if (window.AudioContext) {
+    define(AudioContext.prototype, 'sampleRate', fingerprint.audio_sample_rate);
+  }
+
+  // Screen: the session's display, with a taskbar's height unavailable
+  const screen = fingerprint.screen;
+  define(Screen.prototype, 'width', screen.width);
+  define(Screen.prototype, 'height', screen.height);
+  define(Screen.prototype, 'availWidth', screen.width);
+  define(Screen.prototype, 'availHeight', screen.height - 40);
+  define(Screen.prototype, 'colorDepth', screen.color_depth);
+  define(Screen.prototype, 'pixelDepth', screen.color_depth);
+
+  // Hardware
+  define(Navigator.prototype, 'platform', fingerprint.navigator_platform);
+  define(Navigator.prototype, 'hardwareConcurrency', fingerprint.hardware_concurrency);
+  if ('deviceMemory' in Navigator.prototype) {
+    define(Navigator.prototype, 'deviceMemory', fingerprint.device_memory);
+  }
+})();
+"#;
+
 /// Browser fingerprint manager for advanced evasion
 pub struct FingerprintManager {
     canvas_spoofing: CanvasSpoofing,
@@ -19,9 +150,19 @@ pub struct FingerprintManager {
     audio_spoofing: AudioSpoofing,
     tls_spoofing: TLSSpoofing,
     viewport_spoofing: ViewportSpoofing,
+    /// Devices sessions are drawn from
+    hardware_profiles: Vec<HardwareProfile>,
     request_count: Arc<RwLock<u64>>,
 }
 
+impl std::fmt::Debug for FingerprintManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FingerprintManager")
+            .field("hardware_profiles", &self.hardware_profiles.len())
+            .finish_non_exhaustive()
+    }
+}
+
 impl FingerprintManager {
     /// Create a new fingerprint manager
     pub async fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
@@ -31,10 +172,25 @@ impl FingerprintManager {
             audio_spoofing: AudioSpoofing::new(),
             tls_spoofing: TLSSpoofing::new(),
             viewport_spoofing: ViewportSpoofing::new(),
+            hardware_profiles: HardwareProfile::builtin(),
             request_count: Arc::new(RwLock::new(0)),
         })
     }
 
+    /// Draw sessions from `profiles` instead of the
+    /// [built-in devices](HardwareProfile::builtin); an empty list keeps them
+    pub fn with_hardware_profiles(mut self, profiles: Vec<HardwareProfile>) -> Self {
+        if !profiles.is_empty() {
+            self.hardware_profiles = profiles;
+        }
+        self
+    }
+
+    /// Devices sessions are drawn from
+    pub fn hardware_profiles(&self) -> &[HardwareProfile] {
+        &self.hardware_profiles
+    }
+
     /// Apply comprehensive fingerprint spoofing to HTTP request
     pub async fn apply_spoofing(
         &self,
@@ -55,6 +211,28 @@ impl FingerprintManager {
         Ok(())
     }
 
+    /// Apply a session's `headers` to `request`, on top of the browser headers
+    /// every request carries, instead of headers drawn for this request
+    /// alone
+    pub async fn apply_session_spoofing(
+        &self,
+        request: &mut http::Request<hyper::body::Bytes>,
+        session_headers: &[(String, String)],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        {
+            let mut count = self.request_count.write().await;
+            *count += 1;
+        }
+        self.tls_spoofing.apply_to_request(request).await?;
+
+        let headers = request.headers_mut();
+        self.apply_realistic_browser_headers(headers).await?;
+        for (name, value) in session_headers {
+            headers.insert(http::HeaderName::from_bytes(name.as_bytes())?, value.parse()?);
+        }
+        Ok(())
+    }
+
     /// Apply spoofed headers based on current fingerprint profile
     async fn apply_spoofed_headers(
         &self,
@@ -124,6 +302,46 @@ impl FingerprintManager {
             tls_signature: self.tls_spoofing.generate_signature().await,
         }
     }
+
+    /// Fingerprint of a browser session, drawn from `seed` among the devices
+    /// running the operating system of `user_agent`: the same seed always
+    /// gives the same fingerprint
+    pub fn session_fingerprint(&self, seed: u32, user_agent: Option<&str>) -> SessionFingerprint {
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let platform = user_agent.and_then(platform_of_user_agent);
+        let mut devices: Vec<&HardwareProfile> = self
+            .hardware_profiles
+            .iter()
+            .filter(|device| platform.is_none() || platform == Some(device.platform.as_str()))
+            .collect();
+        if devices.is_empty() {
+            // None run it; a device that doesn't match beats no spoofing
+            devices = self.hardware_profiles.iter().collect();
+        }
+        let device = devices[rng.gen_range(0..devices.len())];
+        let (width, height) = match device.screens.len() {
+            0 => (1920, 1080),
+            screens => device.screens[rng.gen_range(0..screens)],
+        };
+        let viewport = &self.viewport_spoofing;
+        let timezone = viewport.timezones[rng.gen_range(0..viewport.timezones.len())].clone();
+        SessionFingerprint {
+            seed,
+            hardware: device.name.clone(),
+            navigator_platform: navigator_platform(&device.platform).to_string(),
+            webgl_vendor: device.webgl_vendor.clone(),
+            webgl_renderer: device.webgl_renderer.clone(),
+            screen: ViewportData {
+                width,
+                height,
+                color_depth: device.color_depth,
+                timezone,
+            },
+            hardware_concurrency: device.hardware_concurrency,
+            device_memory: device.device_memory,
+            audio_sample_rate: device.audio_sample_rate,
+        }
+    }
 }
 
 /// Canvas fingerprinting evasion
@@ -436,8 +654,214 @@ pub struct BrowserFingerprintProfile {
     pub tls_signature: String,
 }
 
+/// A device sessions can claim to be, with values that belong together
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareProfile {
+    /// e.g. `windows-laptop`
+    pub name: String,
+    /// Operating system, as `Sec-CH-UA-Platform` names it: `Windows`,
+    /// `macOS` or `Linux`
+    pub platform: String,
+    /// `UNMASKED_VENDOR_WEBGL`, as Chrome reports the GPU
+    pub webgl_vendor: String,
+    /// `UNMASKED_RENDERER_WEBGL`
+    pub webgl_renderer: String,
+    /// Screens the device has, or is commonly plugged into
+    pub screens: Vec<(u32, u32)>,
+    pub color_depth: u32,
+    /// Logical cores
+    pub hardware_concurrency: u32,
+    /// Memory in GiB, as `navigator.deviceMemory` rounds it (8 at most)
+    pub device_memory: u32,
+    /// Output rate of the audio hardware
+    pub audio_sample_rate: u32,
+}
+
+impl HardwareProfile {
+    /// Common devices, each as Chrome on it reports itself
+    pub fn builtin() -> Vec<Self> {
+        let device = |name: &str,
+                      platform: &str,
+                      gpu: (&str, &str),
+                      screens: &[(u32, u32)],
+                      color_depth,
+                      hardware_concurrency,
+                      device_memory,
+                      audio_sample_rate| HardwareProfile {
+            name: name.to_string(),
+            platform: platform.to_string(),
+            webgl_vendor: gpu.0.to_string(),
+            webgl_renderer: gpu.1.to_string(),
+            screens: screens.to_vec(),
+            color_depth,
+            hardware_concurrency,
+            device_memory,
+            audio_sample_rate,
+        };
+        vec![
+            device(
+                "windows-laptop",
+                "Windows",
+                (
+                    "Google Inc. (Intel)",
+                    "ANGLE (Intel, Intel(R) UHD Graphics 620 Direct3D11 vs_5_0 ps_5_0, D3D11)",
+                ),
+                &[(1536, 864), (1920, 1080)],
+                24,
+                8,
+                8,
+                48000,
+            ),
+            device(
+                "windows-laptop-budget",
+                "Windows",
+                (
+                    "Google Inc. (Intel)",
+                    "ANGLE (Intel, Intel(R) HD Graphics 520 Direct3D11 vs_5_0 ps_5_0, D3D11)",
+                ),
+                &[(1366, 768)],
+                24,
+                4,
+                4,
+                44100,
+            ),
+            device(
+                "windows-gaming-desktop",
+                "Windows",
+                (
+                    "Google Inc. (NVIDIA)",
+                    "ANGLE (NVIDIA, NVIDIA GeForce GTX 1060 6GB Direct3D11 vs_5_0 ps_5_0, D3D11)",
+                ),
+                &[(1920, 1080), (2560, 1440)],
+                24,
+                12,
+                8,
+                48000,
+            ),
+            device(
+                "windows-amd-desktop",
+                "Windows",
+                (
+                    "Google Inc. (AMD)",
+                    "ANGLE (AMD, Radeon RX 580 Series Direct3D11 vs_5_0 ps_5_0, D3D11)",
+                ),
+                &[(1920, 1080)],
+                24,
+                12,
+                8,
+                48000,
+            ),
+            device(
+                "macbook-air-m1",
+                "macOS",
+                (
+                    "Google Inc. (Apple)",
+                    "ANGLE (Apple, ANGLE Metal Renderer: Apple M1, Unspecified Version)",
+                ),
+                &[(1440, 900)],
+                30,
+                8,
+                8,
+                44100,
+            ),
+            device(
+                "imac-intel",
+                "macOS",
+                (
+                    "Google Inc. (Intel Inc.)",
+                    "ANGLE (Intel Inc., Intel(R) UHD Graphics 630, OpenGL 4.1)",
+                ),
+                &[(2560, 1440)],
+                30,
+                12,
+                8,
+                44100,
+            ),
+            device(
+                "linux-desktop",
+                "Linux",
+                (
+                    "Google Inc. (Intel)",
+                    "ANGLE (Intel, Mesa Intel(R) UHD Graphics 630 (CFL GT2), OpenGL 4.6)",
+                ),
+                &[(1920, 1080)],
+                24,
+                8,
+                8,
+                48000,
+            ),
+        ]
+    }
+
+    /// The built-in device called `name`
+    pub fn named(name: &str) -> Option<Self> {
+        Self::builtin()
+            .into_iter()
+            .find(|device| device.name == name)
+    }
+}
+
+/// Operating system `user_agent` claims, as `Sec-CH-UA-Platform` names it
+fn platform_of_user_agent(user_agent: &str) -> Option<&'static str> {
+    if user_agent.contains("Windows") {
+        Some("Windows")
+    } else if user_agent.contains("Macintosh") {
+        Some("macOS")
+    } else if user_agent.contains("Linux") && !user_agent.contains("Android") {
+        Some("Linux")
+    } else {
+        None
+    }
+}
+
+/// `navigator.platform` on `platform`
+fn navigator_platform(platform: &str) -> &'static str {
+    match platform {
+        "macOS" => "MacIntel",
+        "Linux" => "Linux x86_64",
+        _ => "Win32",
+    }
+}
+
+/// What one browser session presents to fingerprinting scripts, fixed for
+/// the session's lifetime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionFingerprint {
+    /// Seed of the session's canvas and audio noise
+    pub seed: u32,
+    /// Name of the [`HardwareProfile`] the values come from
+    pub hardware: String,
+    /// `navigator.platform`
+    pub navigator_platform: String,
+    /// `UNMASKED_VENDOR_WEBGL`
+    pub webgl_vendor: String,
+    /// `UNMASKED_RENDERER_WEBGL`
+    pub webgl_renderer: String,
+    pub screen: ViewportData,
+    pub hardware_concurrency: u32,
+    pub device_memory: u32,
+    pub audio_sample_rate: u32,
+}
+
+impl SessionFingerprint {
+    /// Operating system of the device, as `Sec-CH-UA-Platform` names it
+    pub fn platform(&self) -> &'static str {
+        match self.navigator_platform.as_str() {
+            "MacIntel" => "macOS",
+            "Linux x86_64" => "Linux",
+            _ => "Windows",
+        }
+    }
+
+    /// JavaScript applying the fingerprint; see the [module docs](self)
+    pub fn injection_script(&self) -> String {
+        let fingerprint = serde_json::to_string(self).expect("fingerprints serialize");
+        INJECTION_SCRIPT.replace("__FINGERPRINT__", &fingerprint)
+    }
+}
+
 /// Viewport and screen data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ViewportData {
     pub width: u32,
     pub height: u32,
@@ -3,15 +3,26 @@
 //! This module provides comprehensive anti-bot detection capabilities including:
 //! - Browser fingerprint spoofing (Canvas, WebGL, AudioContext)
 //! - TLS/HTTP2 signature randomization (JA3/JA4)
-//! - Residential proxy management
+//! - Residential proxy management and exit verification
 //! - Human behavioral simulation
 //! - Advanced browser automation with stealth mode
+//! - Per-vendor retry strategies for blocked responses
+//! - Identity rotation, replacing session, proxy and fingerprint together
+//! - Account scheduling by budget, cooldown and health for logged-in platforms
 
+pub mod account_pool;
 pub mod fingerprint_manager;
 pub mod proxy_rotator;
+pub mod referrers;
 pub mod behavior_engine;
+pub mod block_pages;
+pub mod exit_checks;
+pub mod rotation;
 pub mod stealth_browser;
 pub mod session_manager;
+pub mod user_agents;
+pub mod warm_up;
+pub mod vendors;
 
 use std::collections::HashMap;
 use tokio::sync::RwLock;
@@ -32,6 +43,8 @@ pub struct AntiBotConfig {
     pub behavior_simulation_level: u8,
     /// Platform-specific evasion settings
     pub platform_settings: HashMap<String, PlatformConfig>,
+    /// When a slot's session, proxy and fingerprint are replaced
+    pub rotation: rotation::RotationPolicy,
 }
 
 /// Platform-specific configuration
@@ -67,6 +80,7 @@ impl Default for AntiBotConfig {
             proxy_rotation_interval: 300, // 5 minutes
             behavior_simulation_level: 7,
             platform_settings: HashMap::new(),
+            rotation: rotation::RotationPolicy::default(),
         }
     }
 }
@@ -74,21 +88,29 @@ impl Default for AntiBotConfig {
 /// Main anti-bot evasion coordinator
 pub struct AntiBotManager {
     config: Arc<RwLock<AntiBotConfig>>,
-    fingerprint_manager: fingerprint_manager::FingerprintManager,
-    proxy_rotator: proxy_rotator::ProxyRotator,
+    fingerprint_manager: Arc<fingerprint_manager::FingerprintManager>,
+    proxy_rotator: Arc<proxy_rotator::ProxyRotator>,
     behavior_engine: behavior_engine::BehaviorEngine,
-    session_manager: session_manager::SessionManager,
+    session_manager: Arc<session_manager::SessionManager>,
+    rotation: rotation::RotationEngine,
 }
 
 impl AntiBotManager {
     /// Create a new anti-bot manager with the given configuration
     pub async fn new(config: AntiBotConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let rotation_policy = config.rotation.clone();
         let config_arc = Arc::new(RwLock::new(config));
         
-        let fingerprint_manager = fingerprint_manager::FingerprintManager::new().await?;
-        let proxy_rotator = proxy_rotator::ProxyRotator::new().await?;
+        let fingerprint_manager = Arc::new(fingerprint_manager::FingerprintManager::new().await?);
+        let proxy_rotator = Arc::new(proxy_rotator::ProxyRotator::new().await?);
         let behavior_engine = behavior_engine::BehaviorEngine::new().await?;
-        let session_manager = session_manager::SessionManager::new().await?;
+        let session_manager = Arc::new(session_manager::SessionManager::new().await?);
+        let rotation = rotation::RotationEngine::new(
+            rotation_policy,
+            session_manager.clone(),
+            proxy_rotator.clone(),
+            fingerprint_manager.clone(),
+        );
 
         Ok(Self {
             config: config_arc,
@@ -96,21 +118,25 @@ impl AntiBotManager {
             proxy_rotator,
             behavior_engine,
             session_manager,
+            rotation,
         })
     }
 
-    /// Apply anti-bot evasion to an HTTP request
+    /// Apply anti-bot evasion to an HTTP request that `slot`, e.g. a worker
+    /// or task, makes to `platform`
     pub async fn apply_evasion(
         &self,
         request: &mut http::Request<hyper::body::Bytes>,
+        slot: &str,
         platform: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Apply fingerprint spoofing
-        self.fingerprint_manager.apply_spoofing(request).await?;
-        
-        // Rotate proxy if needed
-        if let Some(proxy) = self.proxy_rotator.get_current_proxy(platform).await? {
-            self.apply_proxy_settings(request, &proxy).await?;
+        // Use the slot's identity, rotated as the policy says
+        let identity = self.rotation.identity(slot, platform).await?;
+        self.fingerprint_manager
+            .apply_session_spoofing(request, &identity.headers())
+            .await?;
+        if let Some(proxy) = &identity.proxy {
+            self.apply_proxy_settings(request, proxy).await?;
         }
         
         // Apply behavioral timing
@@ -129,8 +155,15 @@ impl AntiBotManager {
         Ok(())
     }
 
+    /// Record how `slot`'s last request went, so a blocked identity is
+    /// rotated
+    pub async fn report_outcome(&self, slot: &str, outcome: rotation::RequestOutcome) {
+        self.rotation.report(slot, outcome).await;
+    }
+
     /// Update configuration at runtime
     pub async fn update_config(&self, new_config: AntiBotConfig) {
+        self.rotation.set_policy(new_config.rotation.clone());
         let mut config = self.config.write().await;
         *config = new_config;
     }
@@ -149,6 +182,10 @@ impl AntiBotManager {
         &self.session_manager
     }
 
+    pub fn get_rotation_engine(&self) -> &rotation::RotationEngine {
+        &self.rotation
+    }
+
     async fn get_detection_count(&self) -> u64 {
         // Placeholder - will implement detection tracking
         0
@@ -44,6 +44,10 @@ impl ProxyRotator {
     }
 
     /// Get current proxy for a platform with sticky session support
+    ///
+    /// The platform keeps its proxy while it is healthy, until
+    /// [`Self::rotate_proxy`] moves it on, e.g. together with its session and
+    /// fingerprint (see [`super::rotation`]).
     pub async fn get_current_proxy(&self, platform: &str) -> Result<Option<ProxyInfo>, Box<dyn std::error::Error + Send + Sync>> {
         let session_key = format!("session_{}", platform);
         
@@ -51,7 +55,7 @@ impl ProxyRotator {
         {
             let sessions = self.active_sessions.read().await;
             if let Some(session) = sessions.get(&session_key) {
-                if !session.is_expired() && session.proxy.is_healthy().await {
+                if session.proxy.is_healthy().await {
                     return Ok(Some(session.proxy.clone()));
                 }
             }
@@ -61,6 +65,12 @@ impl ProxyRotator {
         self.rotate_proxy_for_platform(platform).await
     }
 
+    /// Move the platform to the next healthy proxy, however young its
+    /// session, e.g. when its identity is rotated
+    pub async fn rotate_proxy(&self, platform: &str) -> Result<Option<ProxyInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        self.rotate_proxy_for_platform(platform).await
+    }
+
     /// Rotate proxy for a specific platform
     async fn rotate_proxy_for_platform(&self, platform: &str) -> Result<Option<ProxyInfo>, Box<dyn std::error::Error + Send + Sync>> {
         let region = self.determine_optimal_region(platform).await;
@@ -384,13 +394,6 @@ pub struct ProxySession {
     pub platform: String,
 }
 
-impl ProxySession {
-    /// Check if session has expired
-    fn is_expired(&self) -> bool {
-        self.created_at.elapsed() > Duration::from_secs(300) // 5 minutes
-    }
-}
-
 /// Health monitor for proxy infrastructure
 pub struct HealthMonitor {
     health_checks: Arc<RwLock<HashMap<String, Instant>>>,
@@ -418,13 +421,24 @@ impl HealthMonitor {
 }
 
 /// Proxy type enumeration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ProxyType {
     Residential,
     Datacenter,
     Mobile,
 }
 
+impl ProxyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Residential => "residential",
+            Self::Datacenter => "datacenter",
+            Self::Mobile => "mobile",
+        }
+    }
+}
+
 /// Proxy credentials
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyCredentials {
@@ -0,0 +1,315 @@
+//! Referrer chains
+//!
+//! A [`ReferrerChain`] sends each request the `Referer` a visitor who got to
+//! the URL by clicking around would send. It tracks the navigation graph of
+//! a crawl: links found on fetched pages point back to the page they were
+//! found on, and each host keeps a trail of the pages visited on it. A URL
+//! is referred by, in order of preference:
+//!
+//! - the page it was linked from
+//! - the deepest visited page above it on the same host, so a detail page
+//!   comes from its listing
+//! - the page last visited on the host
+//! - for the first page of a host, a search engine, a social site or nothing,
+//!   per [`EntryReferrer`]
+//!
+//! Like browsers under the default `strict-origin-when-cross-origin` policy,
+//! referrers from another site are cut down to their origin.
+
+use crate::utils::{extract_domain, normalize_url};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// Pages remembered per host
+const TRAIL_LEN: usize = 50;
+
+/// Where the first page of a host appears to come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryReferrer {
+    /// Mostly search, some direct visits and the odd social link
+    #[default]
+    Mixed,
+    Search,
+    Social,
+    /// Typed in or bookmarked: no `Referer`
+    Direct,
+}
+
+impl EntryReferrer {
+    pub const NAMES: &'static [&'static str] = &["mixed", "search", "social", "direct"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "mixed" => Some(Self::Mixed),
+            "search" => Some(Self::Search),
+            "social" => Some(Self::Social),
+            "direct" => Some(Self::Direct),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mixed => "mixed",
+            Self::Search => "search",
+            Self::Social => "social",
+            Self::Direct => "direct",
+        }
+    }
+}
+
+/// Referrer settings, as configured in the policy file's `[referrers]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReferrerConfig {
+    pub entry: EntryReferrer,
+    /// Origins search visits come from
+    pub search_engines: Vec<String>,
+    /// Origins social visits come from
+    pub social_sites: Vec<String>,
+}
+
+impl Default for ReferrerConfig {
+    fn default() -> Self {
+        let origins = |origins: &[&str]| origins.iter().map(|origin| origin.to_string()).collect();
+        Self {
+            entry: EntryReferrer::default(),
+            search_engines: origins(&[
+                "https://www.google.com/",
+                "https://www.bing.com/",
+                "https://duckduckgo.com/",
+            ]),
+            social_sites: origins(&[
+                "https://www.facebook.com/",
+                "https://t.co/",
+                "https://www.linkedin.com/",
+            ]),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Graph {
+    /// Page each normalized URL was first linked from
+    parents: HashMap<String, String>,
+    /// Pages visited on each host, oldest first
+    trails: HashMap<String, VecDeque<String>>,
+}
+
+/// Navigation graph of a crawl, handing out referrers that follow it
+#[derive(Debug, Default)]
+pub struct ReferrerChain {
+    config: ReferrerConfig,
+    graph: Mutex<Graph>,
+}
+
+impl PartialEq for ReferrerChain {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl ReferrerChain {
+    pub fn new(config: ReferrerConfig) -> Self {
+        Self {
+            config,
+            graph: Mutex::new(Graph::default()),
+        }
+    }
+
+    pub fn config(&self) -> &ReferrerConfig {
+        &self.config
+    }
+
+    /// Remember that `links` were found on the page at `from`; a link found
+    /// on several pages keeps the first
+    pub fn record_links(&self, from: &str, links: &[String]) {
+        let mut graph = self.graph.lock().unwrap();
+        for link in links {
+            graph
+                .parents
+                .entry(normalize_url(link))
+                .or_insert_with(|| from.to_string());
+        }
+    }
+
+    /// Add pages visited outside the chain, such as a warm-up's, to their
+    /// hosts' trails; pages already on a trail keep their place
+    pub fn record_visits(&self, pages: &[String]) {
+        let mut graph = self.graph.lock().unwrap();
+        for page in pages {
+            let host = match extract_domain(page) {
+                Ok(host) if !host.is_empty() => host,
+                _ => continue,
+            };
+            let trail = graph.trails.entry(host).or_default();
+            if !trail.contains(page) {
+                push_capped(trail, page.clone());
+            }
+        }
+    }
+
+    /// Referrer for a request to `url`, which then counts as the latest page
+    /// visited on its host
+    pub fn navigate(&self, url: &str) -> Option<String> {
+        let Ok(target) = url::Url::parse(url) else {
+            return None;
+        };
+        let host = target.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut graph = self.graph.lock().unwrap();
+        let parent = graph.parents.get(&normalize_url(url)).cloned();
+        let trail = graph.trails.entry(host).or_default();
+        let referer = match parent {
+            Some(parent) => Some(parent),
+            None => {
+                let mut visited = trail.iter().filter(|page| *page != url);
+                let listing = visited
+                    .clone()
+                    .filter_map(|page| Some((ancestor_depth(page, &target)?, page)))
+                    .filter(|(depth, _)| *depth > 0)
+                    // Deepest first, then the most recent
+                    .max_by_key(|(depth, _)| *depth)
+                    .map(|(_, page)| page.clone());
+                listing
+                    .or_else(|| visited.next_back().cloned())
+                    .or_else(|| self.entry())
+            }
+        };
+        trail.retain(|page| page != url);
+        push_capped(trail, url.to_string());
+        referer.map(|referer| trim_cross_origin(&referer, &target))
+    }
+
+    /// Referrer of a host's first page
+    fn entry(&self) -> Option<String> {
+        let mut rng = rand::thread_rng();
+        let entry = match self.config.entry {
+            EntryReferrer::Mixed => match rng.gen_range(0..10) {
+                0..=5 => EntryReferrer::Search,
+                6..=8 => EntryReferrer::Direct,
+                _ => EntryReferrer::Social,
+            },
+            entry => entry,
+        };
+        let origins = match entry {
+            EntryReferrer::Search => &self.config.search_engines,
+            EntryReferrer::Social => &self.config.social_sites,
+            _ => return None,
+        };
+        origins.choose(&mut rng).cloned()
+    }
+}
+
+fn push_capped(trail: &mut VecDeque<String>, page: String) {
+    if trail.len() == TRAIL_LEN {
+        trail.pop_front();
+    }
+    trail.push_back(page);
+}
+
+/// Path segments of `page` if it lies above `target` on the same origin
+fn ancestor_depth(page: &str, target: &url::Url) -> Option<usize> {
+    let page = url::Url::parse(page).ok()?;
+    if page.origin() != target.origin() || page.query().is_some() {
+        return None;
+    }
+    let segments = |url: &url::Url| -> Vec<String> {
+        url.path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (above, below) = (segments(&page), segments(target));
+    (above.len() < below.len() && below.starts_with(&above)).then_some(above.len())
+}
+
+/// `referer` as sent to `target`: whole from the same origin, only its
+/// origin from another
+fn trim_cross_origin(referer: &str, target: &url::Url) -> String {
+    match url::Url::parse(referer) {
+        Ok(parsed) if parsed.origin() != target.origin() => {
+            format!("{}/", parsed.origin().ascii_serialization())
+        }
+        _ => referer.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entering_from(entry: EntryReferrer) -> ReferrerChain {
+        ReferrerChain::new(ReferrerConfig {
+            entry,
+            search_engines: vec!["https://www.google.com/".to_string()],
+            ..ReferrerConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_search_listing_detail() {
+        let chain = entering_from(EntryReferrer::Search);
+        assert_eq!(
+            chain.navigate("https://shop.test/c/shoes").as_deref(),
+            Some("https://www.google.com/")
+        );
+        chain.navigate("https://shop.test/sale");
+        // A detail page comes from its listing, not the page visited last
+        assert_eq!(
+            chain
+                .navigate("https://shop.test/c/shoes/blue-42")
+                .as_deref(),
+            Some("https://shop.test/c/shoes")
+        );
+        assert_eq!(
+            chain.navigate("https://shop.test/p/7").as_deref(),
+            Some("https://shop.test/c/shoes/blue-42")
+        );
+
+        // Warm-up pages join the trail without moving pages already on it
+        chain.record_visits(&[
+            "https://new.test/".to_string(),
+            "https://new.test/c/bags".to_string(),
+        ]);
+        assert_eq!(
+            chain.navigate("https://new.test/p/1").as_deref(),
+            Some("https://new.test/c/bags")
+        );
+        assert_eq!(
+            entering_from(EntryReferrer::Direct).navigate("https://shop.test/"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_links_point_back_to_their_page() {
+        let chain = entering_from(EntryReferrer::Direct);
+        chain.record_links(
+            "https://blog.test/post?id=1",
+            &[
+                "https://shop.test/p/1#reviews".to_string(),
+                "https://blog.test/about".to_string(),
+            ],
+        );
+        chain.record_links(
+            "https://blog.test/other",
+            &["https://blog.test/about".to_string()],
+        );
+        // Cross-site referrers only carry the origin
+        assert_eq!(
+            chain.navigate("https://shop.test/p/1").as_deref(),
+            Some("https://blog.test/")
+        );
+        assert_eq!(
+            chain.navigate("https://blog.test/about").as_deref(),
+            Some("https://blog.test/post?id=1")
+        );
+    }
+}
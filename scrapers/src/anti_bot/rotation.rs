@@ -0,0 +1,386 @@
+//! Coordinated identity rotation
+//!
+//! What a site sees of a scraper is an identity: the session (cookies, user
+//! agent, headers) from [`SessionManager`], the proxy from [`ProxyRotator`]
+//! and the browser fingerprint from [`FingerprintManager`]. Left to their
+//! own timers, each part expires on its own, so old cookies arrive from a
+//! new address or a known fingerprint keeps its session across a proxy
+//! change, which is exactly the link between identities rotating is meant to
+//! break. [`RotationEngine`] evaluates one [`RotationPolicy`] per identity
+//! slot, e.g. a worker, and replaces all three parts together:
+//! - after `max_requests` requests
+//! - once the identity is `max_age_secs` old
+//! - after a request was blocked, with `rotate_on_block`
+//! - when the slot moves on to another platform, with
+//!   `rotate_on_platform_change`, so no identity is seen on two platforms
+//!
+//! Identities are handed out as snapshots, and a slot's identity is replaced
+//! under the engine's lock, so no caller sees a new session with the old
+//! proxy. [`Identity::headers`] are what a request made with one carries.
+
+use super::{
+    fingerprint_manager::{FingerprintManager, SessionFingerprint},
+    proxy_rotator::{ProxyInfo, ProxyRotator},
+    session_manager::{BrowserSession, SessionManager},
+    user_agents::BrowserFamily,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// When an identity is replaced; see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationPolicy {
+    /// Requests an identity serves before it is replaced
+    pub max_requests: Option<u64>,
+    /// Seconds an identity is used for before it is replaced
+    pub max_age_secs: Option<u64>,
+    /// Replace an identity once a request made with it was blocked
+    pub rotate_on_block: bool,
+    /// Replace a slot's identity when it is asked for another platform
+    pub rotate_on_platform_change: bool,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_requests: Some(100),
+            max_age_secs: Some(300),
+            rotate_on_block: true,
+            rotate_on_platform_change: true,
+        }
+    }
+}
+
+impl RotationPolicy {
+    /// Why `identity` has to be replaced before serving a request for
+    /// `platform`, if it does
+    pub fn rotation_reason(&self, identity: &Identity, platform: &str) -> Option<RotationReason> {
+        if self.rotate_on_block && identity.blocked {
+            Some(RotationReason::Blocked)
+        } else if self.rotate_on_platform_change && identity.platform != platform {
+            Some(RotationReason::PlatformChange)
+        } else if self
+            .max_requests
+            .is_some_and(|max| identity.requests >= max)
+        {
+            Some(RotationReason::MaxRequests)
+        } else if self
+            .max_age_secs
+            .is_some_and(|secs| identity.created_at.elapsed() >= Duration::from_secs(secs))
+        {
+            Some(RotationReason::MaxAge)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why an identity was replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationReason {
+    MaxRequests,
+    MaxAge,
+    Blocked,
+    PlatformChange,
+}
+
+impl RotationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RotationReason::MaxRequests => "max_requests",
+            RotationReason::MaxAge => "max_age",
+            RotationReason::Blocked => "blocked",
+            RotationReason::PlatformChange => "platform_change",
+        }
+    }
+}
+
+/// How a request made with an identity went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Success,
+    Failure,
+    /// The site recognized the request as a bot's
+    Blocked,
+}
+
+/// Session, proxy and fingerprint used together
+#[derive(Debug, Clone)]
+pub struct Identity {
+    /// Sequence number of the identity among those the engine issued
+    pub generation: u64,
+    pub platform: String,
+    pub session: BrowserSession,
+    /// `None` when the platform's proxy pool has no healthy proxy
+    pub proxy: Option<ProxyInfo>,
+    /// Drawn for the session's user agent
+    pub fingerprint: SessionFingerprint,
+    pub created_at: Instant,
+    /// Requests the identity was handed out for
+    pub requests: u64,
+    /// Whether a request made with it was blocked
+    pub blocked: bool,
+    /// Why the slot's previous identity was replaced; `None` for its first
+    pub replaced: Option<RotationReason>,
+}
+
+impl Identity {
+    /// Headers of a request made with the identity: the session's headers,
+    /// user agent and cookies, and for browsers that send client hints the
+    /// platform of the fingerprint, so the two never disagree
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let session = &self.session;
+        let mut headers: Vec<(String, String)> = session
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        headers.push(("User-Agent".to_string(), session.user_agent.clone()));
+        if BrowserFamily::of_user_agent(&session.user_agent)
+            .is_some_and(|family| family.sends_client_hints())
+        {
+            let platform = format!("\"{}\"", self.fingerprint.platform());
+            headers.push(("Sec-CH-UA-Platform".to_string(), platform));
+        }
+        let cookies: Vec<String> = session
+            .cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired())
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        if !cookies.is_empty() {
+            headers.push(("Cookie".to_string(), cookies.join("; ")));
+        }
+        headers
+    }
+}
+
+/// Rotates sessions, proxies and fingerprints together; see the
+/// [module docs](self)
+pub struct RotationEngine {
+    policy: Mutex<RotationPolicy>,
+    sessions: Arc<SessionManager>,
+    proxies: Arc<ProxyRotator>,
+    fingerprints: Arc<FingerprintManager>,
+    /// Current identity of each slot
+    slots: tokio::sync::Mutex<HashMap<String, Identity>>,
+    generation: Mutex<u64>,
+    rotations: Mutex<HashMap<RotationReason, u64>>,
+}
+
+impl RotationEngine {
+    pub fn new(
+        policy: RotationPolicy,
+        sessions: Arc<SessionManager>,
+        proxies: Arc<ProxyRotator>,
+        fingerprints: Arc<FingerprintManager>,
+    ) -> Self {
+        Self {
+            policy: Mutex::new(policy),
+            sessions,
+            proxies,
+            fingerprints,
+            slots: tokio::sync::Mutex::default(),
+            generation: Mutex::new(0),
+            rotations: Mutex::default(),
+        }
+    }
+
+    pub fn policy(&self) -> RotationPolicy {
+        self.policy.lock().unwrap().clone()
+    }
+
+    /// Apply `policy` from the next request on
+    pub fn set_policy(&self, policy: RotationPolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+
+    /// Identity for `slot`'s next request to `platform`, replacing the
+    /// current one when the policy says so; counts as one request
+    pub async fn identity(
+        &self,
+        slot: &str,
+        platform: &str,
+    ) -> Result<Identity, Box<dyn std::error::Error + Send + Sync>> {
+        let mut slots = self.slots.lock().await;
+        let policy = self.policy();
+        let replaced = match slots.get_mut(slot) {
+            Some(current) => match policy.rotation_reason(current, platform) {
+                None => {
+                    current.requests += 1;
+                    return Ok(current.clone());
+                }
+                reason => reason,
+            },
+            None => None,
+        };
+
+        let mut identity = self.issue(platform, replaced).await?;
+        identity.requests = 1;
+        slots.insert(slot.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    /// Record how `slot`'s last request went
+    pub async fn report(&self, slot: &str, outcome: RequestOutcome) {
+        let mut slots = self.slots.lock().await;
+        let Some(identity) = slots.get_mut(slot) else {
+            return;
+        };
+        let session = &mut identity.session;
+        session.request_count += 1;
+        session.last_activity = Instant::now();
+        match outcome {
+            RequestOutcome::Success => session.success_count += 1,
+            RequestOutcome::Failure => {}
+            RequestOutcome::Blocked => identity.blocked = true,
+        }
+    }
+
+    /// Identities replaced so far, by reason
+    pub fn rotations(&self) -> HashMap<RotationReason, u64> {
+        self.rotations.lock().unwrap().clone()
+    }
+
+    /// A new identity for `platform`, replacing one for `reason`
+    async fn issue(
+        &self,
+        platform: &str,
+        reason: Option<RotationReason>,
+    ) -> Result<Identity, Box<dyn std::error::Error + Send + Sync>> {
+        let session = self.sessions.rotate_session(platform).await?;
+        let proxy = self.proxies.rotate_proxy(platform).await?;
+        let fingerprint = self
+            .fingerprints
+            .session_fingerprint(rand::random(), Some(&session.user_agent));
+        let generation = {
+            let mut generation = self.generation.lock().unwrap();
+            *generation += 1;
+            *generation
+        };
+        if let Some(reason) = reason {
+            *self.rotations.lock().unwrap().entry(reason).or_default() += 1;
+            tracing::debug!(
+                "🔄 Rotated to identity {} for {} ({})",
+                generation,
+                platform,
+                reason.as_str()
+            );
+        }
+        Ok(Identity {
+            generation,
+            platform: platform.to_string(),
+            session,
+            proxy,
+            fingerprint,
+            created_at: Instant::now(),
+            requests: 0,
+            blocked: false,
+            replaced: reason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anti_bot::session_manager::Cookie;
+
+    async fn engine(policy: RotationPolicy) -> RotationEngine {
+        RotationEngine::new(
+            policy,
+            Arc::new(SessionManager::new().await.unwrap()),
+            Arc::new(ProxyRotator::new().await.unwrap()),
+            Arc::new(FingerprintManager::new().await.unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_identities_rotate_together() {
+        let policy = RotationPolicy {
+            max_requests: Some(2),
+            ..RotationPolicy::default()
+        };
+        let engine = engine(policy).await;
+
+        let first = engine.identity("worker-1", "shop").await.unwrap();
+        let again = engine.identity("worker-1", "shop").await.unwrap();
+        assert_eq!(again.generation, first.generation);
+        assert_eq!(again.requests, 2);
+        // Other slots get identities of their own
+        let other = engine.identity("worker-2", "shop").await.unwrap();
+        assert_ne!(other.generation, first.generation);
+
+        let next = engine.identity("worker-1", "shop").await.unwrap();
+        assert_eq!(next.replaced, Some(RotationReason::MaxRequests));
+        assert_ne!(next.session.session_id, first.session.session_id);
+        let host = |identity: &Identity| identity.proxy.as_ref().unwrap().host.clone();
+        assert_ne!(host(&next), host(&first));
+        assert_eq!(engine.rotations()[&RotationReason::MaxRequests], 1);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_and_platform_changes_rotate() {
+        let engine = engine(RotationPolicy::default()).await;
+        let first = engine.identity("worker-1", "shop").await.unwrap();
+        engine.report("worker-1", RequestOutcome::Success).await;
+        let same = engine.identity("worker-1", "shop").await.unwrap();
+        assert_eq!(same.generation, first.generation);
+        assert_eq!(same.session.success_count, 1);
+
+        engine.report("worker-1", RequestOutcome::Blocked).await;
+        let unblocked = engine.identity("worker-1", "shop").await.unwrap();
+        assert_eq!(unblocked.replaced, Some(RotationReason::Blocked));
+
+        let moved = engine.identity("worker-1", "forum").await.unwrap();
+        assert_eq!(moved.replaced, Some(RotationReason::PlatformChange));
+        assert_eq!(moved.platform, "forum");
+
+        engine.set_policy(RotationPolicy {
+            max_age_secs: Some(0),
+            rotate_on_platform_change: false,
+            ..RotationPolicy::default()
+        });
+        let aged = engine.identity("worker-1", "shop").await.unwrap();
+        assert_eq!(aged.replaced, Some(RotationReason::MaxAge));
+
+        engine.set_policy(RotationPolicy {
+            max_requests: None,
+            max_age_secs: None,
+            rotate_on_block: false,
+            rotate_on_platform_change: false,
+        });
+        engine.report("worker-1", RequestOutcome::Blocked).await;
+        let kept = engine.identity("worker-1", "forum").await.unwrap();
+        assert_eq!(kept.generation, aged.generation);
+    }
+
+    #[tokio::test]
+    async fn test_headers_follow_the_identity() {
+        let engine = engine(RotationPolicy::default()).await;
+        let mut identity = engine.identity("worker-1", "facebook").await.unwrap();
+        identity.session.cookies.push(Cookie {
+            name: "sid".to_string(),
+            value: "abc".to_string(),
+            domain: "facebook.com".to_string(),
+            path: "/".to_string(),
+            expires: None,
+            secure: true,
+            http_only: true,
+            same_site: None,
+        });
+        let headers: HashMap<String, String> = identity.headers().into_iter().collect();
+        // A Chrome on a Mac, claiming a Mac's platform
+        assert_eq!(headers["User-Agent"], identity.session.user_agent);
+        assert_eq!(identity.fingerprint.navigator_platform, "MacIntel");
+        assert_eq!(headers["Sec-CH-UA-Platform"], "\"macOS\"");
+        assert_eq!(headers["Cookie"], "sid=abc");
+        assert_eq!(headers["Accept-Language"], "en-US,en;q=0.5");
+    }
+}
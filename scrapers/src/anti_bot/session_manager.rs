@@ -39,16 +39,18 @@ impl SessionManager {
     }
 
     /// Create or retrieve a session for a platform
+    ///
+    /// A session doesn't expire on its own: it lasts until
+    /// [`Self::rotate_session`] replaces it, e.g. together with its proxy and
+    /// fingerprint (see [`super::rotation`]).
     pub async fn get_session(&self, platform: &str) -> Result<BrowserSession, Box<dyn std::error::Error + Send + Sync>> {
         let session_key = format!("session_{}", platform);
         
-        // Check if session exists and is valid
+        // Check if session exists
         {
             let sessions = self.sessions.read().await;
             if let Some(session) = sessions.get(&session_key) {
-                if !session.is_expired() {
-                    return Ok(session.clone());
-                }
+                return Ok(session.clone());
             }
         }
 
@@ -56,6 +58,12 @@ impl SessionManager {
         self.create_new_session(platform).await
     }
 
+    /// Replace the platform's session with a new one, however young, e.g.
+    /// when its identity is rotated
+    pub async fn rotate_session(&self, platform: &str) -> Result<BrowserSession, Box<dyn std::error::Error + Send + Sync>> {
+        self.create_new_session(platform).await
+    }
+
     /// Create a new browser session
     async fn create_new_session(&self, platform: &str) -> Result<BrowserSession, Box<dyn std::error::Error + Send + Sync>> {
         let session = BrowserSession {
@@ -299,11 +307,6 @@ pub struct BrowserSession {
 }
 
 impl BrowserSession {
-    /// Check if session has expired
-    pub fn is_expired(&self) -> bool {
-        self.last_activity.elapsed() > Duration::from_secs(1800) // 30 minutes
-    }
-
     /// Check if session has expired using config timeout
     pub fn is_expired_with_config(&self, config: &SessionConfig) -> bool {
         self.last_activity.elapsed() > config.session_timeout
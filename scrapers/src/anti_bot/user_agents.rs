@@ -0,0 +1,418 @@
+//! User agent pools
+//!
+//! A [`UserAgentPool`] holds user agents grouped by browser family and
+//! version tier, picked at random by weight. Each host is pinned to the user
+//! agent it first gets, per device kind, so a site sees one browser for the
+//! whole session. The client hints sent with a user agent are derived from
+//! the same entry: Chromium browsers send matching `Sec-CH-UA*` headers,
+//! Firefox and Safari send none, and mobile fingerprints only ever get
+//! mobile user agents.
+
+use super::vendors::FingerprintKind;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Browser a user agent claims to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserFamily {
+    Chrome,
+    Edge,
+    Firefox,
+    Safari,
+}
+
+impl BrowserFamily {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrowserFamily::Chrome => "chrome",
+            BrowserFamily::Edge => "edge",
+            BrowserFamily::Firefox => "firefox",
+            BrowserFamily::Safari => "safari",
+        }
+    }
+
+    /// Browser `user_agent` claims to be, if it is one of the families
+    pub fn of_user_agent(user_agent: &str) -> Option<Self> {
+        if user_agent.contains("Edg/") || user_agent.contains("EdgA/") {
+            Some(BrowserFamily::Edge)
+        } else if user_agent.contains("Firefox/") || user_agent.contains("FxiOS/") {
+            Some(BrowserFamily::Firefox)
+        } else if user_agent.contains("Chrome/") || user_agent.contains("CriOS/") {
+            Some(BrowserFamily::Chrome)
+        } else if user_agent.contains("Safari/") {
+            Some(BrowserFamily::Safari)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the browser sends `Sec-CH-UA` client hints
+    pub fn sends_client_hints(&self) -> bool {
+        matches!(self, BrowserFamily::Chrome | BrowserFamily::Edge)
+    }
+}
+
+/// How far a user agent's version is behind the browser's current release
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionTier {
+    #[default]
+    Latest,
+    Previous,
+    /// Older releases still seen in the wild, such as extended support ones
+    Legacy,
+}
+
+/// One user agent of a pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserAgentEntry {
+    pub user_agent: String,
+    pub family: BrowserFamily,
+    /// Major browser version, used for the `Sec-CH-UA` brands
+    pub version: u32,
+    #[serde(default)]
+    pub tier: VersionTier,
+    #[serde(default)]
+    pub device: FingerprintKind,
+    /// Operating system for `Sec-CH-UA-Platform`, e.g. `Windows` or `Android`
+    pub platform: String,
+    /// Relative share of picks
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl UserAgentEntry {
+    /// `User-Agent` and the client hints that go with it
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("User-Agent", self.user_agent.clone())];
+        if self.family.sends_client_hints() {
+            let brand = match self.family {
+                BrowserFamily::Edge => "Microsoft Edge",
+                _ => "Google Chrome",
+            };
+            headers.push((
+                "Sec-CH-UA",
+                format!(
+                    "\"Chromium\";v=\"{v}\", \"{}\";v=\"{v}\", \"Not-A.Brand\";v=\"99\"",
+                    brand,
+                    v = self.version
+                ),
+            ));
+            let mobile = if self.device == FingerprintKind::Mobile {
+                "?1"
+            } else {
+                "?0"
+            };
+            headers.push(("Sec-CH-UA-Mobile", mobile.to_string()));
+            headers.push(("Sec-CH-UA-Platform", format!("\"{}\"", self.platform)));
+        }
+        headers
+    }
+
+    /// Set this user agent on `headers`, dropping client hints left over from
+    /// another browser
+    pub fn apply(&self, headers: &mut HashMap<String, String>) {
+        headers.retain(|name, _| {
+            !name.eq_ignore_ascii_case("user-agent")
+                && !name.to_ascii_lowercase().starts_with("sec-ch-ua")
+        });
+        for (name, value) in self.headers() {
+            headers.insert(name.to_string(), value);
+        }
+    }
+}
+
+/// Which entries of the pool are used, as configured in the policy file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserAgentConfig {
+    /// Families to use; empty uses all
+    #[serde(default)]
+    pub families: Vec<BrowserFamily>,
+    /// Version tiers to use; empty uses all
+    #[serde(default)]
+    pub tiers: Vec<VersionTier>,
+    /// Keep one user agent per host instead of rotating on every request
+    #[serde(default = "default_pin")]
+    pub pin: bool,
+    /// Entries replacing the built-in pool
+    #[serde(default)]
+    pub pool: Vec<UserAgentEntry>,
+}
+
+fn default_pin() -> bool {
+    true
+}
+
+impl Default for UserAgentConfig {
+    fn default() -> Self {
+        Self {
+            families: Vec::new(),
+            tiers: Vec::new(),
+            pin: true,
+            pool: Vec::new(),
+        }
+    }
+}
+
+/// Built-in pool, weighted roughly by browser market share
+static BUILTIN: Lazy<Vec<UserAgentEntry>> = Lazy::new(|| {
+    use BrowserFamily::*;
+    use FingerprintKind::{Desktop, Mobile};
+    use VersionTier::*;
+    let entry =
+        |user_agent: &str, family, version, tier, device, platform: &str, weight| UserAgentEntry {
+            user_agent: user_agent.to_string(),
+            family,
+            version,
+            tier,
+            device,
+            platform: platform.to_string(),
+            weight,
+        };
+    vec![
+        entry("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36", Chrome, 124, Latest, Desktop, "Windows", 30),
+        entry("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36", Chrome, 124, Latest, Desktop, "macOS", 15),
+        entry("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36", Chrome, 124, Latest, Desktop, "Linux", 5),
+        entry("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36", Chrome, 123, Previous, Desktop, "Windows", 10),
+        entry("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0", Edge, 124, Latest, Desktop, "Windows", 8),
+        entry("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0", Firefox, 125, Latest, Desktop, "Windows", 8),
+        entry("Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0", Firefox, 125, Latest, Desktop, "Linux", 3),
+        entry("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:115.0) Gecko/20100101 Firefox/115.0", Firefox, 115, Legacy, Desktop, "Windows", 2),
+        entry("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15", Safari, 17, Latest, Desktop, "macOS", 8),
+        entry("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36", Chrome, 124, Latest, Mobile, "Android", 30),
+        entry("Mozilla/5.0 (Linux; Android 13; SM-S911B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Mobile Safari/537.36", Chrome, 123, Previous, Mobile, "Android", 10),
+        entry("Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1", Safari, 17, Latest, Mobile, "iOS", 30),
+        entry("Mozilla/5.0 (Android 14; Mobile; rv:125.0) Gecko/125.0 Firefox/125.0", Firefox, 125, Latest, Mobile, "Android", 3),
+    ]
+});
+
+/// Pool shared by everything that runs without a policy file
+static SHARED: Lazy<Arc<UserAgentPool>> = Lazy::new(|| Arc::new(UserAgentPool::default()));
+
+/// Weighted user agent rotation with per-host pinning
+#[derive(Debug)]
+pub struct UserAgentPool {
+    entries: Vec<UserAgentEntry>,
+    pin: bool,
+    /// Index into `entries` each host and device kind is pinned to
+    pinned: Mutex<HashMap<(String, FingerprintKind), usize>>,
+}
+
+impl Default for UserAgentPool {
+    fn default() -> Self {
+        Self {
+            entries: BUILTIN.clone(),
+            pin: true,
+            pinned: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl PartialEq for UserAgentPool {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries && self.pin == other.pin
+    }
+}
+
+impl UserAgentPool {
+    /// Pool with the built-in entries or `config.pool`, narrowed to the
+    /// configured families and tiers
+    pub fn new(config: &UserAgentConfig) -> Result<Self> {
+        let source = if config.pool.is_empty() {
+            BUILTIN.as_slice()
+        } else {
+            config.pool.as_slice()
+        };
+        let entries: Vec<UserAgentEntry> = source
+            .iter()
+            .filter(|entry| config.families.is_empty() || config.families.contains(&entry.family))
+            .filter(|entry| config.tiers.is_empty() || config.tiers.contains(&entry.tier))
+            .filter(|entry| entry.weight > 0)
+            .cloned()
+            .collect();
+        for device in [FingerprintKind::Desktop, FingerprintKind::Mobile] {
+            if !entries.iter().any(|entry| entry.device == device) {
+                bail!(
+                    "User agent pool has no {:?} entries for the configured families and tiers",
+                    device
+                );
+            }
+        }
+        Ok(Self {
+            entries,
+            pin: config.pin,
+            pinned: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Built-in pool shared by every caller, so pins hold process-wide
+    pub fn shared() -> Arc<Self> {
+        SHARED.clone()
+    }
+
+    pub fn entries(&self) -> &[UserAgentEntry] {
+        &self.entries
+    }
+
+    /// Weighted random entry for `device` among `families` (empty for any)
+    pub fn pick(&self, device: FingerprintKind, families: &[BrowserFamily]) -> &UserAgentEntry {
+        self.choose(device, families).1
+    }
+
+    fn choose(
+        &self,
+        device: FingerprintKind,
+        families: &[BrowserFamily],
+    ) -> (usize, &UserAgentEntry) {
+        let candidates: Vec<(usize, &UserAgentEntry)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.device == device)
+            .collect();
+        // A family filter that leaves nothing falls back to the whole device
+        let filtered: Vec<(usize, &UserAgentEntry)> = candidates
+            .iter()
+            .filter(|(_, entry)| families.is_empty() || families.contains(&entry.family))
+            .copied()
+            .collect();
+        let candidates = if filtered.is_empty() {
+            candidates
+        } else {
+            filtered
+        };
+        *candidates
+            .choose_weighted(&mut rand::thread_rng(), |(_, entry)| entry.weight)
+            .expect("pools have entries for every device kind")
+    }
+
+    /// User agent for requests to `host`: the one pinned to it for `device`,
+    /// or a new pick that is pinned from now on
+    pub fn for_host(
+        &self,
+        host: &str,
+        device: FingerprintKind,
+        families: &[BrowserFamily],
+    ) -> UserAgentEntry {
+        if !self.pin {
+            return self.pick(device, families).clone();
+        }
+        let key = (host.to_ascii_lowercase(), device);
+        let mut pinned = self.pinned.lock().unwrap();
+        if let Some(entry) = pinned.get(&key).map(|index| &self.entries[*index]) {
+            if families.is_empty() || families.contains(&entry.family) {
+                return entry.clone();
+            }
+        }
+        let (index, entry) = self.choose(device, families);
+        pinned.insert(key, index);
+        entry.clone()
+    }
+
+    /// Forget `host`'s pins, so its next request starts a new identity
+    pub fn release(&self, host: &str) {
+        let host = host.to_ascii_lowercase();
+        self.pinned
+            .lock()
+            .unwrap()
+            .retain(|(pinned, _), _| *pinned != host);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts_keep_their_user_agent() {
+        let pool = UserAgentPool::default();
+        let first = pool.for_host("Shop.example", FingerprintKind::Desktop, &[]);
+        for _ in 0..20 {
+            assert_eq!(
+                pool.for_host("shop.example", FingerprintKind::Desktop, &[]),
+                first
+            );
+        }
+        let mobile = pool.for_host("shop.example", FingerprintKind::Mobile, &[]);
+        assert_eq!(mobile.device, FingerprintKind::Mobile);
+
+        // A domain limited to Firefox gets re-pinned to a Firefox entry
+        let firefox = pool.for_host(
+            "shop.example",
+            FingerprintKind::Desktop,
+            &[BrowserFamily::Firefox],
+        );
+        assert_eq!(firefox.family, BrowserFamily::Firefox);
+        assert_eq!(
+            pool.for_host("shop.example", FingerprintKind::Desktop, &[]),
+            firefox
+        );
+    }
+
+    #[test]
+    fn test_headers_match_the_browser() {
+        let pool = UserAgentPool::default();
+        let chrome = pool.pick(FingerprintKind::Mobile, &[BrowserFamily::Chrome]);
+        let mut headers =
+            HashMap::from([("sec-ch-ua-platform".to_string(), "\"Windows\"".to_string())]);
+        chrome.apply(&mut headers);
+        assert_eq!(headers["Sec-CH-UA-Mobile"], "?1");
+        assert_eq!(headers["Sec-CH-UA-Platform"], "\"Android\"");
+        assert!(
+            headers["Sec-CH-UA"].contains(&format!("\"Google Chrome\";v=\"{}\"", chrome.version))
+        );
+        assert_eq!(headers.len(), 4);
+
+        // Firefox sends no client hints, so Chrome's are dropped
+        let firefox = pool.pick(FingerprintKind::Desktop, &[BrowserFamily::Firefox]);
+        firefox.apply(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers["User-Agent"].contains("Firefox/"));
+    }
+
+    #[test]
+    fn test_families_of_user_agents() {
+        for entry in UserAgentPool::default().entries() {
+            assert_eq!(
+                BrowserFamily::of_user_agent(&entry.user_agent),
+                Some(entry.family),
+                "{}",
+                entry.user_agent
+            );
+        }
+        assert_eq!(BrowserFamily::of_user_agent("curl/8.5.0"), None);
+    }
+
+    #[test]
+    fn test_configured_pools() {
+        let config = UserAgentConfig {
+            families: vec![BrowserFamily::Safari],
+            ..UserAgentConfig::default()
+        };
+        let pool = UserAgentPool::new(&config).unwrap();
+        assert!(pool
+            .entries()
+            .iter()
+            .all(|entry| entry.family == BrowserFamily::Safari));
+
+        // Every fingerprint needs a user agent to go with it
+        let config = UserAgentConfig {
+            tiers: vec![VersionTier::Legacy],
+            ..UserAgentConfig::default()
+        };
+        assert!(UserAgentPool::new(&config).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Per-vendor anti-bot strategies
+//!
+//! Blocked responses usually say which bot-protection vendor sent them: a
+//! `cf-ray` header, a `datadome` cookie, an `_abck` sensor cookie. Each vendor
+//! responds to different countermeasures, so [`detect_vendor`] names the
+//! vendor and [`VendorStrategy::preset`] picks what to retry with. Policy files
+//! override the presets with [`StrategyOverrides`].
+
+use super::proxy_rotator::ProxyType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Bot-protection vendors recognized in responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BotVendor {
+    Cloudflare,
+    DataDome,
+    Akamai,
+    PerimeterX,
+    Imperva,
+    Kasada,
+    #[serde(rename = "aws_waf")]
+    AwsWaf,
+}
+
+impl BotVendor {
+    pub const ALL: [BotVendor; 7] = [
+        BotVendor::Cloudflare,
+        BotVendor::DataDome,
+        BotVendor::Akamai,
+        BotVendor::PerimeterX,
+        BotVendor::Imperva,
+        BotVendor::Kasada,
+        BotVendor::AwsWaf,
+    ];
+
+    /// Name used in policy files and tags
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotVendor::Cloudflare => "cloudflare",
+            BotVendor::DataDome => "datadome",
+            BotVendor::Akamai => "akamai",
+            BotVendor::PerimeterX => "perimeterx",
+            BotVendor::Imperva => "imperva",
+            BotVendor::Kasada => "kasada",
+            BotVendor::AwsWaf => "aws_waf",
+        }
+    }
+}
+
+/// Response header signatures: (vendor, header, substring of its value; empty matches any value)
+const HEADER_SIGNATURES: &[(BotVendor, &str, &str)] = &[
+    (BotVendor::DataDome, "x-datadome", ""),
+    (BotVendor::DataDome, "set-cookie", "datadome="),
+    (BotVendor::Akamai, "set-cookie", "_abck="),
+    (BotVendor::Akamai, "set-cookie", "bm_sz="),
+    (BotVendor::Akamai, "server", "akamaighost"),
+    (BotVendor::PerimeterX, "set-cookie", "_px3="),
+    (BotVendor::PerimeterX, "set-cookie", "_pxhd="),
+    (BotVendor::Imperva, "x-iinfo", ""),
+    (BotVendor::Imperva, "x-cdn", "incapsula"),
+    (BotVendor::Imperva, "set-cookie", "incap_ses_"),
+    (BotVendor::Kasada, "x-kpsdk-ct", ""),
+    (BotVendor::AwsWaf, "x-amzn-waf-action", ""),
+    (BotVendor::Cloudflare, "cf-mitigated", ""),
+    (BotVendor::Cloudflare, "cf-ray", ""),
+    (BotVendor::Cloudflare, "server", "cloudflare"),
+];
+
+/// Block page signatures: (vendor, lowercase substring of the body)
+const BODY_SIGNATURES: &[(BotVendor, &str)] = &[
+    (BotVendor::DataDome, "captcha-delivery.com"),
+    (BotVendor::PerimeterX, "px-captcha"),
+    (BotVendor::PerimeterX, "perimeterx"),
+    (BotVendor::Imperva, "_incapsula_resource"),
+    (BotVendor::Kasada, "kpsdk"),
+    (BotVendor::AwsWaf, "awswafintegration"),
+    (BotVendor::Akamai, "_abck"),
+    (BotVendor::Cloudflare, "challenges.cloudflare.com"),
+    (BotVendor::Cloudflare, "cf-browser-verification"),
+    (BotVendor::Cloudflare, "__cf_chl_"),
+];
+
+/// Vendor that sent a blocked response, from its headers (lowercased names)
+/// and body
+///
+/// Dedicated bot managers are checked before CDNs, since a DataDome or Akamai
+/// block is often served through Cloudflare.
+pub fn detect_vendor(headers: &BTreeMap<String, String>, body: &str) -> Option<BotVendor> {
+    let body = body.to_ascii_lowercase();
+    let from_headers = HEADER_SIGNATURES.iter().find(|(_, header, needle)| {
+        headers
+            .get(*header)
+            .is_some_and(|value| value.to_ascii_lowercase().contains(needle))
+    });
+    let from_body = BODY_SIGNATURES
+        .iter()
+        .find(|(_, needle)| body.contains(needle));
+    // Cloudflare headers are the weakest evidence
+    match (from_headers, from_body) {
+        (Some((BotVendor::Cloudflare, ..)), Some((vendor, _))) => Some(*vendor),
+        (Some((vendor, ..)), _) => Some(*vendor),
+        (None, Some((vendor, _))) => Some(*vendor),
+        // Vendors of block pages learned from the corpus
+        (None, None) if !body.is_empty() => super::block_pages::installed()
+            .classify(&body)
+            .and_then(|found| found.vendor),
+        (None, None) => None,
+    }
+}
+
+/// Device a request claims to come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FingerprintKind {
+    #[default]
+    Desktop,
+    Mobile,
+}
+
+impl FingerprintKind {
+    /// User agent and client hints for a request with this fingerprint
+    pub fn headers(&self) -> [(&'static str, &'static str); 3] {
+        match self {
+            FingerprintKind::Desktop => [
+                ("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"),
+                ("Sec-CH-UA-Mobile", "?0"),
+                ("Sec-CH-UA-Platform", "\"Windows\""),
+            ],
+            FingerprintKind::Mobile => [
+                ("User-Agent", "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36"),
+                ("Sec-CH-UA-Mobile", "?1"),
+                ("Sec-CH-UA-Platform", "\"Android\""),
+            ],
+        }
+    }
+}
+
+/// Policy file overrides for one vendor's preset; unset fields keep the preset's
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyOverrides {
+    pub fingerprint: Option<FingerprintKind>,
+    pub proxy_tier: Option<ProxyType>,
+    pub browser: Option<bool>,
+    pub warm_up: Option<bool>,
+}
+
+/// How to retry a URL blocked by one vendor
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorStrategy {
+    pub vendor: BotVendor,
+    pub fingerprint: FingerprintKind,
+    pub proxy_tier: Option<ProxyType>,
+    /// Proxy URL configured for `proxy_tier`, when there is one
+    pub proxy: Option<String>,
+    /// Render the page in a browser when plain HTTP is still blocked
+    pub browser: bool,
+    /// Visit the site's home page first and send the cookies it sets (such as
+    /// Akamai's `_abck`/`bm_sz` sensor cookies) with the retry
+    pub warm_up: bool,
+}
+
+impl VendorStrategy {
+    /// Built-in strategy for `vendor`
+    pub fn preset(vendor: BotVendor) -> Self {
+        let strategy = |fingerprint, proxy_tier, browser, warm_up| Self {
+            vendor,
+            fingerprint,
+            proxy_tier,
+            proxy: None,
+            browser,
+            warm_up,
+        };
+        use FingerprintKind::{Desktop, Mobile};
+        use ProxyType::Residential;
+        match vendor {
+            BotVendor::Cloudflare => strategy(Desktop, None, true, false),
+            BotVendor::DataDome => strategy(Mobile, Some(Residential), true, false),
+            BotVendor::Akamai => strategy(Desktop, None, false, true),
+            BotVendor::PerimeterX => strategy(Desktop, Some(Residential), true, false),
+            BotVendor::Imperva => strategy(Desktop, None, false, true),
+            BotVendor::Kasada => strategy(Desktop, Some(Residential), true, false),
+            BotVendor::AwsWaf => strategy(Desktop, None, true, true),
+        }
+    }
+
+    pub fn with_overrides(mut self, overrides: &StrategyOverrides) -> Self {
+        if let Some(fingerprint) = overrides.fingerprint {
+            self.fingerprint = fingerprint;
+        }
+        if let Some(tier) = overrides.proxy_tier {
+            self.proxy_tier = Some(tier);
+        }
+        if let Some(browser) = overrides.browser {
+            self.browser = browser;
+        }
+        if let Some(warm_up) = overrides.warm_up {
+            self.warm_up = warm_up;
+        }
+        self
+    }
+}
+
+/// `Cookie` header value replaying the cookies of a `Set-Cookie` header,
+/// where repeated headers were joined with `, `
+pub fn cookie_header(set_cookie: &str) -> Option<String> {
+    let cookies: Vec<&str> = set_cookie
+        .split(',')
+        .filter_map(|part| {
+            // Commas inside `Expires=Wed, 21 Oct ...` leave fragments without a name
+            let pair = part.split(';').next()?.trim();
+            let (name, _) = pair.split_once('=')?;
+            (!name.is_empty() && !name.contains(' ')).then_some(pair)
+        })
+        .collect();
+    (!cookies.is_empty()).then(|| cookies.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_vendor() {
+        let datadome = headers(&[
+            ("server", "cloudflare"),
+            ("set-cookie", "datadome=abc; Path=/"),
+        ]);
+        assert_eq!(detect_vendor(&datadome, ""), Some(BotVendor::DataDome));
+        let akamai = headers(&[("set-cookie", "bm_sz=1; Path=/, _abck=2; Path=/")]);
+        assert_eq!(detect_vendor(&akamai, ""), Some(BotVendor::Akamai));
+        // Body evidence beats a bare Cloudflare header
+        let behind_cdn = headers(&[("cf-ray", "8a1b")]);
+        assert_eq!(
+            detect_vendor(&behind_cdn, "<div id=\"px-captcha\"></div>"),
+            Some(BotVendor::PerimeterX)
+        );
+        assert_eq!(
+            detect_vendor(&behind_cdn, "<p>Blocked</p>"),
+            Some(BotVendor::Cloudflare)
+        );
+        assert_eq!(detect_vendor(&BTreeMap::new(), "<p>Blocked</p>"), None);
+    }
+
+    #[test]
+    fn test_presets_and_overrides() {
+        let datadome = VendorStrategy::preset(BotVendor::DataDome);
+        assert_eq!(datadome.fingerprint, FingerprintKind::Mobile);
+        assert_eq!(datadome.proxy_tier, Some(ProxyType::Residential));
+        assert!(datadome.browser);
+        assert!(VendorStrategy::preset(BotVendor::Akamai).warm_up);
+
+        let overridden = datadome.with_overrides(&StrategyOverrides {
+            proxy_tier: Some(ProxyType::Mobile),
+            browser: Some(false),
+            ..StrategyOverrides::default()
+        });
+        assert_eq!(overridden.proxy_tier, Some(ProxyType::Mobile));
+        assert!(!overridden.browser);
+        assert_eq!(overridden.fingerprint, FingerprintKind::Mobile);
+    }
+
+    #[test]
+    fn test_cookie_header() {
+        let set_cookie =
+            "_abck=XYZ~0~; Expires=Wed, 21 Oct 2026 07:28:00 GMT; Path=/, bm_sz=123; HttpOnly";
+        assert_eq!(
+            cookie_header(set_cookie).as_deref(),
+            Some("_abck=XYZ~0~; bm_sz=123")
+        );
+        assert_eq!(cookie_header(""), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Session warm-up
+//!
+//! Visitors rarely land straight on a product page with no cookies and no
+//! referrer. Before the first request of a session with a host, [`WarmUps`]
+//! browses the site the way a visitor arriving at it would: the home page,
+//! then a couple of category pages linked from it, with human-like pauses in
+//! between. The cookies those pages set, and the last of them as `Referer`,
+//! go with the requests for the actual target URLs. A session lasts
+//! `session_minutes`; the host's next request after that warms up again.
+
+use super::vendors::{cookie_header, FingerprintKind};
+use crate::traps::page_links;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Path words of pages a visitor doesn't browse on the way to content
+const SKIPPED_PATH_WORDS: &[&str] = &[
+    "login", "signin", "sign-in", "logout", "register", "signup", "account", "cart", "basket",
+    "checkout", "search", "privacy", "terms", "legal", "cookie", "help",
+];
+
+/// Links on the home page considered for category pages
+const CANDIDATE_LINKS: usize = 30;
+
+/// Warm-up settings, as configured in the policy file's `[warm_up]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarmUpConfig {
+    /// Category pages visited after the home page
+    pub pages: usize,
+    /// Pause before each page after the home page, and before the target
+    pub min_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Paths visited as category pages instead of ones linked from the home
+    /// page, e.g. `/c/shoes`
+    pub paths: Vec<String>,
+    /// How long the cookies and referrer of one warm-up are used
+    pub session_minutes: u64,
+}
+
+impl Default for WarmUpConfig {
+    fn default() -> Self {
+        Self {
+            pages: 2,
+            min_delay_ms: 1500,
+            max_delay_ms: 4000,
+            paths: Vec::new(),
+            session_minutes: 30,
+        }
+    }
+}
+
+/// What a warm-up fetch returned
+#[derive(Debug, Clone, Default)]
+pub struct WarmUpPage {
+    /// Response body; empty when the fetch failed
+    pub body: String,
+    /// `Set-Cookie` headers, joined with `, `
+    pub set_cookie: Option<String>,
+}
+
+/// Cookies and referrer picked up by one warm-up
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarmUpSession {
+    /// Cookie names and values, in the order they were first set
+    pub cookies: Vec<(String, String)>,
+    /// Last page visited
+    pub referer: Option<String>,
+    /// Pages visited, in order
+    pub visited: Vec<String>,
+}
+
+impl WarmUpSession {
+    /// Keep the cookies of a `Set-Cookie` header, replacing earlier values
+    pub fn absorb(&mut self, set_cookie: &str) {
+        let Some(header) = cookie_header(set_cookie) else {
+            return;
+        };
+        for pair in header.split("; ") {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+            match self
+                .cookies
+                .iter_mut()
+                .find(|(existing, _)| existing == name)
+            {
+                Some((_, existing)) => *existing = value.to_string(),
+                None => self.cookies.push((name.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    /// `Cookie` header value replaying the session's cookies
+    pub fn cookie_header(&self) -> Option<String> {
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join("; "))
+    }
+
+    /// Send the session's cookies and referrer with `headers`; cookies and a
+    /// `Referer` already set there win
+    pub fn apply(&self, headers: &mut HashMap<String, String>) {
+        let existing = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+            .map(|(name, value)| (name.clone(), value.clone()));
+        let mut cookies: Vec<String> = Vec::new();
+        if let Some((name, value)) = &existing {
+            headers.remove(name);
+            cookies.push(value.clone());
+        }
+        let set: Vec<&str> = existing
+            .iter()
+            .flat_map(|(_, value)| value.split(';'))
+            .filter_map(|pair| pair.split_once('=').map(|(name, _)| name.trim()))
+            .collect();
+        cookies.extend(
+            self.cookies
+                .iter()
+                .filter(|(name, _)| !set.contains(&name.as_str()))
+                .map(|(name, value)| format!("{}={}", name, value)),
+        );
+        if !cookies.is_empty() {
+            headers.insert("Cookie".to_string(), cookies.join("; "));
+        }
+        let has_referer = headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("referer"));
+        if let (Some(referer), false) = (&self.referer, has_referer) {
+            headers.insert("Referer".to_string(), referer.clone());
+        }
+    }
+}
+
+/// Same-site links on a home page that look like category pages: visible,
+/// followable, one or two path segments deep, without a query, and not an
+/// account, cart or legal page
+pub fn category_links(home: &str, html: &str) -> Vec<String> {
+    let Some(host) = url::Url::parse(home)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+    else {
+        return Vec::new();
+    };
+    let mut links: Vec<String> = Vec::new();
+    for link in page_links(html, home) {
+        if link.hidden || link.is_nofollow() || links.contains(&link.url) {
+            continue;
+        }
+        let Ok(url) = url::Url::parse(&link.url) else {
+            continue;
+        };
+        let path = url.path().to_ascii_lowercase();
+        let segments = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .count();
+        let is_file = path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.') && !name.ends_with(".html"));
+        if url.host_str() != Some(host.as_str())
+            || url.query().is_some()
+            || !(1..=2).contains(&segments)
+            || is_file
+            || SKIPPED_PATH_WORDS.iter().any(|word| path.contains(word))
+        {
+            continue;
+        }
+        links.push(link.url);
+    }
+    links
+}
+
+/// One host's warm-up state; a warm-up in progress holds the lock, so
+/// concurrent requests to the host wait for it instead of starting their own
+type SessionSlot = Arc<tokio::sync::Mutex<Option<(Instant, WarmUpSession)>>>;
+
+/// Warms up each host once per session
+#[derive(Debug, Default)]
+pub struct WarmUps {
+    config: WarmUpConfig,
+    sessions: Mutex<HashMap<(String, FingerprintKind), SessionSlot>>,
+}
+
+impl PartialEq for WarmUps {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl WarmUps {
+    pub fn new(config: WarmUpConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &WarmUpConfig {
+        &self.config
+    }
+
+    /// Session for requests to `url`'s host as `device`, warming up first if
+    /// there is none or it has run out
+    ///
+    /// `fetch` requests a warm-up page with the given headers: `headers`
+    /// plus the cookies and referrer picked up so far.
+    pub async fn session<F, Fut>(
+        &self,
+        url: &str,
+        device: FingerprintKind,
+        headers: &HashMap<String, String>,
+        fetch: F,
+    ) -> WarmUpSession
+    where
+        F: Fn(String, HashMap<String, String>) -> Fut,
+        Fut: Future<Output = WarmUpPage>,
+    {
+        let Ok(target) = url::Url::parse(url) else {
+            return WarmUpSession::default();
+        };
+        let host = target.host_str().unwrap_or_default().to_ascii_lowercase();
+        let slot = self
+            .sessions
+            .lock()
+            .unwrap()
+            .entry((host, device))
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().await;
+        let lifetime = Duration::from_secs(self.config.session_minutes * 60);
+        if let Some((started, session)) = slot.as_ref() {
+            if started.elapsed() < lifetime {
+                return session.clone();
+            }
+        }
+
+        let home = format!("{}/", target.origin().ascii_serialization());
+        let mut session = WarmUpSession::default();
+        let page = self.visit(&mut session, &home, headers, &fetch).await;
+        let mut pages: Vec<String> = if self.config.paths.is_empty() {
+            let mut links = category_links(&home, &page.body);
+            links.truncate(CANDIDATE_LINKS);
+            links.shuffle(&mut rand::thread_rng());
+            links
+        } else {
+            self.config
+                .paths
+                .iter()
+                .filter_map(|path| url::Url::parse(&home).ok()?.join(path).ok())
+                .map(|url| url.to_string())
+                .collect()
+        };
+        // The target itself is fetched for real right after
+        pages.retain(|page| page != target.as_str() && *page != home);
+        for page in pages.into_iter().take(self.config.pages) {
+            tokio::time::sleep(self.pause()).await;
+            self.visit(&mut session, &page, headers, &fetch).await;
+        }
+        tokio::time::sleep(self.pause()).await;
+
+        *slot = Some((Instant::now(), session.clone()));
+        session
+    }
+
+    /// Fetch `page` as part of `session`, keeping its cookies and making it
+    /// the referrer of the next page
+    async fn visit<F, Fut>(
+        &self,
+        session: &mut WarmUpSession,
+        page: &str,
+        headers: &HashMap<String, String>,
+        fetch: &F,
+    ) -> WarmUpPage
+    where
+        F: Fn(String, HashMap<String, String>) -> Fut,
+        Fut: Future<Output = WarmUpPage>,
+    {
+        let mut headers = headers.clone();
+        session.apply(&mut headers);
+        let response = fetch(page.to_string(), headers).await;
+        if let Some(set_cookie) = &response.set_cookie {
+            session.absorb(set_cookie);
+        }
+        session.referer = Some(page.to_string());
+        session.visited.push(page.to_string());
+        response
+    }
+
+    fn pause(&self) -> Duration {
+        let (min, max) = (self.config.min_delay_ms, self.config.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(min..=max.max(min)))
+    }
+
+    /// Forget `host`'s sessions, so its next request warms up again
+    pub fn reset(&self, host: &str) {
+        let host = host.to_ascii_lowercase();
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|(warmed, _), _| *warmed != host);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOME: &str = r#"<html><body><nav>
+        <a href="/c/shoes">Shoes</a>
+        <a href="/c/bags?sort=new">Bags, sorted</a>
+        <a href="/account/login">Sign in</a>
+        <a href="https://other.test/c/hats">Hats elsewhere</a>
+        <a href="/c/coats" style="display:none">Hidden</a>
+        <a href="/brochure.pdf">Brochure</a>
+        <a href="/p/shoes/runners/blue-42">Deep product</a>
+        <a href="/cart">Cart</a>
+        <a href="/c/shoes">Shoes again</a>
+        </nav></body></html>"#;
+
+    #[test]
+    fn test_category_links() {
+        assert_eq!(
+            category_links("https://shop.test/", HOME),
+            vec!["https://shop.test/c/shoes".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_once_per_session() {
+        let warm_ups = WarmUps::new(WarmUpConfig {
+            pages: 2,
+            min_delay_ms: 0,
+            max_delay_ms: 0,
+            paths: vec!["/c/shoes".to_string(), "sale".to_string()],
+            ..WarmUpConfig::default()
+        });
+        let requests = Mutex::new(Vec::new());
+        let fetch = |page: String, headers: HashMap<String, String>| {
+            requests.lock().unwrap().push((page.clone(), headers));
+            let set_cookie = match page.as_str() {
+                "https://shop.test/" => {
+                    "bm_sz=1; Path=/, _abck=a; Expires=Wed, 21 Oct 2026 07:28:00 GMT"
+                }
+                _ => "_abck=b; Path=/",
+            };
+            async move {
+                WarmUpPage {
+                    body: String::new(),
+                    set_cookie: Some(set_cookie.to_string()),
+                }
+            }
+        };
+        let headers = HashMap::from([("Cookie".to_string(), "consent=yes".to_string())]);
+        let url = "https://Shop.test/p/1";
+        let session = warm_ups
+            .session(url, FingerprintKind::Desktop, &headers, &fetch)
+            .await;
+        assert_eq!(
+            session.visited,
+            vec![
+                "https://shop.test/",
+                "https://shop.test/c/shoes",
+                "https://shop.test/sale"
+            ]
+        );
+        assert_eq!(session.cookie_header().as_deref(), Some("bm_sz=1; _abck=b"));
+
+        // Each page is requested with the cookies so far and the page before as referrer
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests[0].1.get("Referer"), None);
+            assert_eq!(requests[1].1["Referer"], "https://shop.test/");
+            assert_eq!(requests[1].1["Cookie"], "consent=yes; bm_sz=1; _abck=a");
+        }
+
+        let mut target = headers.clone();
+        session.apply(&mut target);
+        assert_eq!(target["Cookie"], "consent=yes; bm_sz=1; _abck=b");
+        assert_eq!(target["Referer"], "https://shop.test/sale");
+
+        // The rest of the session reuses the warm-up
+        let again = warm_ups
+            .session(url, FingerprintKind::Desktop, &headers, &fetch)
+            .await;
+        assert_eq!(again, session);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        warm_ups.reset("shop.test");
+        warm_ups
+            .session(url, FingerprintKind::Desktop, &headers, &fetch)
+            .await;
+        assert_eq!(requests.lock().unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,725 @@
+//! GraphQL and JSON API requests for platform scrapers
+//!
+//! Many platforms serve their data through GraphQL or JSON endpoints rather
+//! than HTML. A scraper describes such a call as an [`ApiRequest`]: either a
+//! GraphQL document with variables or a JSON body template with `{{name}}`
+//! placeholders. [`ApiClient`] encodes it (GraphQL over GET goes into the query
+//! string, everything else into a JSON body), adds the platform's persisted
+//! auth headers from an [`AuthStore`] and follows cursor pagination, returning
+//! every response page. Platforms that need a logged-in session take their
+//! login credentials from a [`CredentialVault`]. Requests keep within the rate
+//! limits an API publishes in its `X-RateLimit-*` headers.
+
+use crate::rate_limiter::ApiRateLimiter;
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::Duration,
+};
+use swoop_core::security::UrlValidator;
+
+/// Default auth header file name
+pub const AUTH_FILE: &str = "auth_headers.json";
+/// Default credential vault file name
+pub const CREDENTIALS_FILE: &str = "credentials.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiMethod {
+    Get,
+    #[default]
+    Post,
+}
+
+/// What an [`ApiRequest`] sends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ApiBody {
+    /// GraphQL operation; `query` may be omitted for persisted queries that
+    /// are identified through `extensions` (or the URL) instead
+    Graphql {
+        query: Option<String>,
+        operation_name: Option<String>,
+        extensions: Option<Value>,
+    },
+    /// JSON body in which `"{{name}}"` strings are replaced by variables
+    Template { template: Value },
+}
+
+/// Cursor pagination over successive API responses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorPagination {
+    /// Variable that carries the cursor, e.g. `after`
+    pub cursor_variable: String,
+    /// JSON pointer to the next cursor, e.g. `/data/user/posts/pageInfo/endCursor`
+    pub cursor_path: String,
+    /// JSON pointer to a has-more flag such as `hasNextPage`; without it paging
+    /// stops once the cursor is missing or repeats
+    pub has_next_path: Option<String>,
+    pub max_pages: usize,
+}
+
+impl CursorPagination {
+    /// GraphQL connection paging with the usual `pageInfo { hasNextPage endCursor }`
+    pub fn relay(connection_path: &str, max_pages: usize) -> Self {
+        let connection = connection_path.trim_end_matches('/');
+        Self {
+            cursor_variable: "after".to_string(),
+            cursor_path: format!("{}/pageInfo/endCursor", connection),
+            has_next_path: Some(format!("{}/pageInfo/hasNextPage", connection)),
+            max_pages,
+        }
+    }
+
+    /// Cursor of the page after `response`, if there is one
+    fn next_cursor(&self, response: &Value) -> Option<String> {
+        if let Some(path) = &self.has_next_path {
+            if response.pointer(path) != Some(&Value::Bool(true)) {
+                return None;
+            }
+        }
+        match response.pointer(&self.cursor_path)? {
+            Value::String(cursor) if !cursor.is_empty() => Some(cursor.clone()),
+            Value::Number(cursor) => Some(cursor.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// A GraphQL or JSON API call a scraper needs to fetch a page's data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiRequest {
+    pub url: String,
+    #[serde(default)]
+    pub method: ApiMethod,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: ApiBody,
+    #[serde(default)]
+    pub variables: Map<String, Value>,
+    pub pagination: Option<CursorPagination>,
+}
+
+/// Request as it goes over the wire
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedRequest {
+    pub method: ApiMethod,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<Bytes>,
+}
+
+impl ApiRequest {
+    pub fn graphql(url: &str, query: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            method: ApiMethod::Post,
+            headers: HashMap::new(),
+            body: ApiBody::Graphql {
+                query: Some(query.to_string()),
+                operation_name: None,
+                extensions: None,
+            },
+            variables: Map::new(),
+            pagination: None,
+        }
+    }
+
+    /// JSON POST whose body is `template` with `{{name}}` placeholders filled in
+    pub fn template(url: &str, template: Value) -> Self {
+        Self {
+            body: ApiBody::Template { template },
+            ..Self::graphql(url, "")
+        }
+    }
+
+    pub fn with_method(mut self, method: ApiMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_variable(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.variables.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: CursorPagination) -> Self {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    /// Encode the request, passing `cursor` in the pagination variable
+    pub fn encode(&self, cursor: Option<&str>) -> Result<EncodedRequest> {
+        let mut variables = self.variables.clone();
+        if let (Some(cursor), Some(pagination)) = (cursor, &self.pagination) {
+            variables.insert(pagination.cursor_variable.clone(), cursor.into());
+        }
+
+        let mut headers = self.headers.clone();
+        let mut url = self.url.clone();
+        let payload = match &self.body {
+            ApiBody::Graphql {
+                query,
+                operation_name,
+                extensions,
+            } => {
+                let mut payload = Map::new();
+                if let Some(query) = query.as_ref().filter(|query| !query.is_empty()) {
+                    payload.insert("query".to_string(), query.as_str().into());
+                }
+                if let Some(operation_name) = operation_name {
+                    payload.insert("operationName".to_string(), operation_name.as_str().into());
+                }
+                payload.insert("variables".to_string(), Value::Object(variables));
+                if let Some(extensions) = extensions {
+                    payload.insert("extensions".to_string(), extensions.clone());
+                }
+                Value::Object(payload)
+            }
+            ApiBody::Template { template } => fill_template(template, &variables),
+        };
+
+        let body = match self.method {
+            ApiMethod::Post => {
+                if !headers
+                    .keys()
+                    .any(|name| name.eq_ignore_ascii_case("content-type"))
+                {
+                    headers.insert("Content-Type".to_string(), "application/json".to_string());
+                }
+                Some(Bytes::from(serde_json::to_vec(&payload)?))
+            }
+            ApiMethod::Get => {
+                let mut parsed = url::Url::parse(&url)
+                    .with_context(|| format!("Invalid API URL {}", self.url))?;
+                let Value::Object(params) = payload else {
+                    bail!("GET API requests need an object body to encode as query parameters");
+                };
+                {
+                    let mut query = parsed.query_pairs_mut();
+                    for (name, value) in params {
+                        match value {
+                            Value::String(value) => query.append_pair(&name, &value),
+                            value => query.append_pair(&name, &value.to_string()),
+                        };
+                    }
+                }
+                url = parsed.to_string();
+                None
+            }
+        };
+
+        Ok(EncodedRequest {
+            method: self.method,
+            url,
+            headers,
+            body,
+        })
+    }
+
+    fn is_graphql(&self) -> bool {
+        matches!(self.body, ApiBody::Graphql { .. })
+    }
+}
+
+/// Replace `"{{name}}"` strings with the variable's JSON value, and
+/// placeholders inside longer strings with its text
+fn fill_template(template: &Value, variables: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => {
+            if let Some(value) = text
+                .strip_prefix("{{")
+                .and_then(|rest| rest.strip_suffix("}}"))
+                .and_then(|name| variables.get(name.trim()))
+            {
+                return value.clone();
+            }
+            let mut text = text.clone();
+            for (name, value) in variables {
+                let placeholder = format!("{{{{{}}}}}", name);
+                if text.contains(&placeholder) {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    };
+                    text = text.replace(&placeholder, &value);
+                }
+            }
+            Value::String(text)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| fill_template(item, variables))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), fill_template(value, variables)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Auth headers (tokens, CSRF headers, session cookies) per platform, kept
+/// in a JSON file so they survive between runs
+#[derive(Debug, Default)]
+pub struct AuthStore {
+    path: Option<PathBuf>,
+    platforms: RwLock<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+impl AuthStore {
+    /// Store that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load the headers saved at `path`, starting empty if the file doesn't exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let platforms = match std::fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .with_context(|| format!("Invalid auth header file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            platforms: RwLock::new(platforms),
+        })
+    }
+
+    pub fn headers(&self, platform: &str) -> HashMap<String, String> {
+        self.platforms
+            .read()
+            .unwrap()
+            .get(platform)
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, platform: &str, name: &str, value: &str) {
+        self.platforms
+            .write()
+            .unwrap()
+            .entry(platform.to_string())
+            .or_default()
+            .insert(name.to_string(), value.to_string());
+    }
+
+    pub fn remove(&self, platform: &str, name: &str) {
+        if let Some(headers) = self.platforms.write().unwrap().get_mut(platform) {
+            headers.remove(name);
+        }
+    }
+
+    /// Write the headers back to the file they were loaded from
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&*self.platforms.read().unwrap())?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Login credentials for one platform account
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub username: String,
+    pub password: String,
+}
+
+impl Account {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Login credentials per platform, kept in a JSON file that only its owner
+/// can read
+///
+/// Scrapers log in with these on demand and keep the resulting session in
+/// their [`AuthStore`], so the password is only sent when a session is missing
+/// or has expired.
+#[derive(Debug, Default)]
+pub struct CredentialVault {
+    path: Option<PathBuf>,
+    platforms: RwLock<BTreeMap<String, Vec<Account>>>,
+}
+
+impl CredentialVault {
+    /// Vault that is never written to disk
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load the accounts saved at `path`, starting empty if the file doesn't exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let platforms = match std::fs::read(path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .with_context(|| format!("Invalid credential vault {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            platforms: RwLock::new(platforms),
+        })
+    }
+
+    pub fn with_account(self, platform: &str, account: Account) -> Self {
+        self.add(platform, account);
+        self
+    }
+
+    pub fn accounts(&self, platform: &str) -> Vec<Account> {
+        self.platforms
+            .read()
+            .unwrap()
+            .get(platform)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Add `account`, replacing a saved account with the same username
+    pub fn add(&self, platform: &str, account: Account) {
+        let mut platforms = self.platforms.write().unwrap();
+        let accounts = platforms.entry(platform.to_string()).or_default();
+        accounts.retain(|saved| saved.username != account.username);
+        accounts.push(account);
+    }
+
+    /// Write the accounts back to the file they were loaded from
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&*self.platforms.read().unwrap())?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs [`ApiRequest`]s through the SSRF-checked core client
+///
+/// Requests are paced by the rate limits APIs publish in their response
+/// headers, through the process-wide [`ApiRateLimiter`] unless given another.
+pub struct ApiClient {
+    validator: UrlValidator,
+    timeout: Duration,
+    auth: Option<std::sync::Arc<AuthStore>>,
+    rate_limits: Option<std::sync::Arc<ApiRateLimiter>>,
+}
+
+impl std::fmt::Debug for ApiClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiClient")
+            .field("timeout", &self.timeout)
+            .field("auth", &self.auth)
+            .field("rate_limits", &self.rate_limits.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl ApiClient {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            validator: UrlValidator::default(),
+            timeout,
+            auth: None,
+            rate_limits: Some(ApiRateLimiter::shared()),
+        }
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Send each platform's persisted auth headers with its requests
+    pub fn with_auth(mut self, auth: std::sync::Arc<AuthStore>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Pace requests by `limiter` instead of the shared one; `None` ignores
+    /// published rate limits
+    pub fn with_rate_limiter(mut self, limiter: Option<std::sync::Arc<ApiRateLimiter>>) -> Self {
+        self.rate_limits = limiter;
+        self
+    }
+
+    /// Send `request` for `platform` and return every page of responses
+    ///
+    /// Headers set on the request win over persisted auth headers.
+    pub async fn fetch_pages(&self, platform: &str, request: &ApiRequest) -> Result<Vec<Value>> {
+        let max_pages = request
+            .pagination
+            .as_ref()
+            .map_or(1, |pagination| pagination.max_pages.max(1));
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut encoded = request.encode(cursor.as_deref())?;
+            if let Some(auth) = &self.auth {
+                let mut headers = auth.headers(platform);
+                headers.extend(encoded.headers);
+                encoded.headers = headers;
+            }
+            let method = match encoded.method {
+                ApiMethod::Get => reqwest::Method::GET,
+                ApiMethod::Post => reqwest::Method::POST,
+            };
+            if let Some(limiter) = &self.rate_limits {
+                limiter.acquire(&encoded.url).await;
+            }
+            let sent = swoop_core::send_request_response_with(
+                &self.validator,
+                method,
+                &encoded.url,
+                &encoded.headers,
+                encoded.body,
+                self.timeout,
+            )
+            .await;
+            if let (Some(limiter), Some(meta)) = (&self.rate_limits, &sent.meta) {
+                limiter.record(&encoded.url, &meta.headers, meta.status);
+            }
+            let body = sent.body?;
+            let page: Value = serde_json::from_slice(&body)
+                .with_context(|| format!("API response from {} is not JSON", request.url))?;
+            if request.is_graphql() {
+                check_graphql_errors(&page)?;
+            }
+
+            let next = request
+                .pagination
+                .as_ref()
+                .and_then(|pagination| pagination.next_cursor(&page));
+            pages.push(page);
+            match next {
+                Some(next) if pages.len() < max_pages && cursor.as_deref() != Some(&next) => {
+                    cursor = Some(next)
+                }
+                _ => break,
+            }
+        }
+        Ok(pages)
+    }
+
+    /// Fetch all pages of every request in order
+    pub async fn fetch_all(&self, platform: &str, requests: &[ApiRequest]) -> Result<Vec<Value>> {
+        let mut pages = Vec::new();
+        for request in requests {
+            pages.extend(self.fetch_pages(platform, request).await?);
+        }
+        Ok(pages)
+    }
+}
+
+/// Fail on GraphQL responses that carry errors and no data
+fn check_graphql_errors(page: &Value) -> Result<()> {
+    let has_data = page.get("data").is_some_and(|data| !data.is_null());
+    match page.get("errors").and_then(Value::as_array) {
+        Some(errors) if !errors.is_empty() && !has_data => {
+            let messages: Vec<&str> = errors
+                .iter()
+                .filter_map(|error| error.get("message").and_then(Value::as_str))
+                .collect();
+            bail!("GraphQL errors: {}", messages.join("; "))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_encode_graphql_and_templates() {
+        let request = ApiRequest::graphql("https://api.example.com/graphql", "query Q { a }")
+            .with_variable("id", 7);
+        let encoded = request.encode(None).unwrap();
+        assert_eq!(encoded.headers["Content-Type"], "application/json");
+        let body: Value = serde_json::from_slice(&encoded.body.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({ "query": "query Q { a }", "variables": { "id": 7 } })
+        );
+
+        let get = request.with_method(ApiMethod::Get).encode(None).unwrap();
+        assert!(get.body.is_none());
+        let url = url::Url::parse(&get.url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["variables"], r#"{"id":7}"#);
+
+        let template = ApiRequest::template(
+            "https://api.example.com/search",
+            json!({ "filter": { "user": "{{user}}", "limit": "{{limit}}" }, "q": "from:{{user}}" }),
+        )
+        .with_variable("user", "rustlang")
+        .with_variable("limit", 20);
+        let body: Value =
+            serde_json::from_slice(&template.encode(None).unwrap().body.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({ "filter": { "user": "rustlang", "limit": 20 }, "q": "from:rustlang" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pages_follows_cursors() {
+        let server = MockServer::start().await;
+        let page = |cursor: &str, has_next: bool| {
+            json!({ "data": { "feed": {
+                "edges": [cursor],
+                "pageInfo": { "endCursor": cursor, "hasNextPage": has_next }
+            } } })
+        };
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(header("x-csrf-token", "secret"))
+            .and(body_partial_json(json!({ "variables": { "after": "c1" } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page("c2", false)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(header("x-csrf-token", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page("c1", true)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/graphql"))
+            .and(query_param("query", "{ broken }"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "data": null, "errors": [{ "message": "nope" }] })),
+            )
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let auth_path = dir.path().join(AUTH_FILE);
+        let auth = AuthStore::load(&auth_path).unwrap();
+        auth.set("example", "X-Csrf-Token", "secret");
+        auth.save().unwrap();
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let client = ApiClient::default()
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_auth(std::sync::Arc::new(AuthStore::load(&auth_path).unwrap()));
+        let endpoint = format!("{}/graphql", server.uri());
+
+        let request = ApiRequest::graphql(&endpoint, "query Feed($after: String) { feed }")
+            .with_pagination(CursorPagination::relay("/data/feed", 5));
+        let pages = client.fetch_pages("example", &request).await.unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1]["data"]["feed"]["edges"][0], "c2");
+
+        let broken = ApiRequest::graphql(&endpoint, "{ broken }").with_method(ApiMethod::Get);
+        let error = client.fetch_pages("example", &broken).await.unwrap_err();
+        assert!(error.to_string().contains("nope"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pages_waits_out_published_limits() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/items"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-RateLimit-Remaining", "0")
+                    .insert_header("X-RateLimit-Reset", "1")
+                    .set_body_json(json!({ "items": [] })),
+            )
+            .mount(&server)
+            .await;
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let client = ApiClient::default()
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_rate_limiter(Some(std::sync::Arc::new(ApiRateLimiter::new())));
+        let request = ApiRequest::template(&format!("{}/v1/items", server.uri()), json!({}))
+            .with_method(ApiMethod::Get);
+
+        client.fetch_pages("example", &request).await.unwrap();
+        let start = std::time::Instant::now();
+        client.fetch_pages("example", &request).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_credential_vault_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CREDENTIALS_FILE);
+        let vault = CredentialVault::load(&path).unwrap();
+        vault.add("linkedin", Account::new("a@example.com", "old"));
+        vault.add("linkedin", Account::new("a@example.com", "new"));
+        vault.save().unwrap();
+
+        let loaded = CredentialVault::load(&path).unwrap();
+        assert_eq!(
+            loaded.accounts("linkedin"),
+            vec![Account::new("a@example.com", "new")]
+        );
+        assert!(loaded.accounts("x").is_empty());
+        assert!(!format!("{:?}", loaded.accounts("linkedin")).contains("new"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
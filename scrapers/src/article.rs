@@ -0,0 +1,494 @@
+//! News and blog article extraction
+//!
+//! [`ArticleExtractor`] runs a readability pass that keeps the prose of the
+//! page's main content (the `<article>` or `<main>` element when present) and
+//! drops navigation, asides, footers and scripts. It layers the headline,
+//! byline, publish and update dates from JSON-LD, meta tags and `<time>`
+//! elements on top, along with the canonical URL and AMP variant, and records
+//! them as the metadata the [`Article`](crate::schemas::Article) schema reads.
+
+use crate::{
+    extractors::{
+        decode_entities, extract_canonical, extract_json_ld, find_json_ld_node, html_tags,
+        json_ld_images, json_ld_text,
+    },
+    metadata::Metadata,
+    platforms::CANONICAL_URL_KEY,
+    schemas::{ARTICLE_AMP_URL_KEY, ARTICLE_AUTHORS_KEY, ARTICLE_HEADLINE_KEY},
+    utils::normalize_url,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// JSON-LD types treated as articles
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "ReportageNewsArticle",
+    "AnalysisNewsArticle",
+    "OpinionNewsArticle",
+    "BlogPosting",
+    "LiveBlogPosting",
+    "TechArticle",
+    "Report",
+];
+/// Paragraphs shorter than this are captions, buttons or bylines rather than prose
+const MIN_PARAGRAPH_WORDS: usize = 5;
+
+/// Elements whose content is never part of the article body
+const BOILERPLATE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "template",
+];
+
+static BOILERPLATE_REGEXES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    BOILERPLATE_TAGS
+        .iter()
+        .map(|tag| Regex::new(&format!(r"(?is)<{tag}\b[^>]*>.*?</{tag}>")).unwrap())
+        .collect()
+});
+static ARTICLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<article\b[^>]*>(.*)</article>").unwrap());
+static MAIN_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<main\b[^>]*>(.*)</main>").unwrap());
+static PARAGRAPH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<(p|blockquote|li)\b([^>]*)>(.*?)</(?:p|blockquote|li)>").unwrap()
+});
+static HEADING_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<h1\b[^>]*>(.*?)</h1>").unwrap());
+static BYLINE_ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:class|rel|itemprop)\s*=\s*["'][^"']*\b(?:byline|author)\b"#).unwrap()
+});
+static BYLINE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<([a-z][a-z0-9]*)\b[^>]*(?:class|rel|itemprop)\s*=\s*["'][^"']*\b(?:byline|author)\b[^"']*["'][^>]*>(.*?)</[a-z][a-z0-9]*>"#,
+    )
+    .unwrap()
+});
+static STRIP_TAGS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static AMP_HTML_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<html\b[^>]*(?:\samp\b|⚡)").unwrap());
+
+/// Article data found on a page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedArticle {
+    pub headline: Option<String>,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub modified_at: Option<DateTime<Utc>>,
+    /// Main content, one paragraph per line pair
+    pub text: String,
+    pub word_count: usize,
+    pub canonical_url: Option<String>,
+    /// Where the AMP variant of the page lives, from `<link rel="amphtml">`
+    pub amp_url: Option<String>,
+    /// Whether this page is itself an AMP page
+    pub is_amp: bool,
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl ExtractedArticle {
+    /// Record the article as the metadata the `article` schema reads
+    pub fn record(&self, metadata: &mut Metadata) {
+        let mut set = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                metadata.insert(key.into(), value);
+            }
+        };
+        set(ARTICLE_HEADLINE_KEY, self.headline.clone());
+        set(
+            ARTICLE_AUTHORS_KEY,
+            (!self.authors.is_empty()).then(|| self.authors.join("\n")),
+        );
+        set(
+            "article:published_time",
+            self.published_at.map(|date| date.to_rfc3339()),
+        );
+        set(
+            "article:modified_time",
+            self.modified_at.map(|date| date.to_rfc3339()),
+        );
+        set(CANONICAL_URL_KEY, self.canonical_url.clone());
+        set(ARTICLE_AMP_URL_KEY, self.amp_url.clone());
+        set("og:site_name", self.site_name.clone());
+        set("og:image", self.image.clone());
+        set("description", self.description.clone());
+    }
+}
+
+/// Extracts articles from news sites and blogs
+#[derive(Debug, Clone, Default)]
+pub struct ArticleExtractor;
+
+impl ArticleExtractor {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Article on the page at `url`, if the page is marked up as one and has prose
+    pub fn extract(&self, html: &str, url: &str) -> Option<ExtractedArticle> {
+        let json_ld = extract_json_ld(html);
+        let node = json_ld
+            .iter()
+            .find_map(|document| find_json_ld_node(document, ARTICLE_TYPES));
+        let meta = meta_tags(html);
+        let meta_value = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).cloned());
+        let is_article = node.is_some()
+            || meta
+                .get("og:type")
+                .is_some_and(|kind| kind.eq_ignore_ascii_case("article"))
+            || meta.contains_key("article:published_time")
+            || ARTICLE_REGEX.is_match(html);
+        if !is_article {
+            return None;
+        }
+
+        let paragraphs = readable_paragraphs(html);
+        if paragraphs.is_empty() {
+            return None;
+        }
+        let text = paragraphs.join("\n\n");
+        let field = |key: &str| node.and_then(|node| node.get(key)).and_then(json_ld_text);
+
+        let published_at = field("datePublished")
+            .or_else(|| {
+                meta_value(&[
+                    "article:published_time",
+                    "og:published_time",
+                    "datepublished",
+                    "pubdate",
+                    "publishdate",
+                    "date",
+                    "dc.date",
+                    "dc.date.issued",
+                    "parsely-pub-date",
+                    "sailthru.date",
+                ])
+            })
+            .or_else(|| time_element(html))
+            .and_then(|raw| parse_date(&raw));
+        let modified_at = field("dateModified")
+            .or_else(|| meta_value(&["article:modified_time", "og:updated_time", "datemodified"]))
+            .and_then(|raw| parse_date(&raw));
+
+        let base = url::Url::parse(url).ok();
+        let absolute = |link: String| match &base {
+            Some(base) => base.join(&link).map(|link| link.to_string()).ok(),
+            None => Some(link),
+        };
+
+        Some(ExtractedArticle {
+            headline: field("headline")
+                .or_else(|| meta_value(&["og:title", "twitter:title"]))
+                .or_else(|| {
+                    HEADING_REGEX
+                        .captures(html)
+                        .and_then(|captures| clean_text(&captures[1]))
+                }),
+            description: field("description")
+                .or_else(|| meta_value(&["og:description", "description"])),
+            authors: authors(node, &meta, html),
+            published_at,
+            modified_at,
+            word_count: text.split_whitespace().count(),
+            text,
+            canonical_url: extract_canonical(html, url).map(|canonical| normalize_url(&canonical)),
+            amp_url: link_href(html, "amphtml").and_then(absolute),
+            is_amp: AMP_HTML_REGEX.is_match(html),
+            image: node
+                .and_then(|node| node.get("image"))
+                .map(json_ld_images)
+                .and_then(|images| images.into_iter().next())
+                .or_else(|| meta_value(&["og:image", "twitter:image"]))
+                .and_then(absolute),
+            site_name: node
+                .and_then(|node| node.get("publisher"))
+                .and_then(json_ld_text)
+                .or_else(|| meta_value(&["og:site_name"])),
+        })
+    }
+}
+
+fn clean_text(html: &str) -> Option<String> {
+    let text = decode_entities(&STRIP_TAGS_REGEX.replace_all(html, " "));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// `<meta>` values by lowercased `property`, `name` or `itemprop`, first one winning
+fn meta_tags(html: &str) -> HashMap<String, String> {
+    let mut meta = HashMap::new();
+    for tag in html_tags(html).filter(|tag| tag.name == "meta") {
+        let attrs = &tag.attributes;
+        let key = attrs
+            .get("property")
+            .or_else(|| attrs.get("name"))
+            .or_else(|| attrs.get("itemprop"));
+        if let (Some(key), Some(content)) = (key, attrs.get("content")) {
+            let content = decode_entities(content.trim());
+            if !content.is_empty() {
+                meta.entry(key.to_ascii_lowercase()).or_insert(content);
+            }
+        }
+    }
+    meta
+}
+
+fn link_href(html: &str, rel: &str) -> Option<String> {
+    html_tags(html)
+        .filter(|tag| tag.name == "link")
+        .find_map(|tag| {
+            let rels = tag.attributes.get("rel")?;
+            rels.split_whitespace()
+                .any(|value| value.eq_ignore_ascii_case(rel))
+                .then(|| tag.attributes.get("href").cloned())
+                .flatten()
+        })
+}
+
+/// `datetime` of the first `<time>` element, preferring one marked as the publish date
+fn time_element(html: &str) -> Option<String> {
+    let times: Vec<_> = html_tags(html)
+        .filter(|tag| tag.name == "time")
+        .filter_map(|tag| {
+            let published = tag
+                .attributes
+                .get("itemprop")
+                .is_some_and(|prop| prop == "datePublished");
+            Some((published, tag.attributes.get("datetime")?.clone()))
+        })
+        .collect();
+    times
+        .iter()
+        .find(|(published, _)| *published)
+        .or_else(|| times.first())
+        .map(|(_, datetime)| datetime.clone())
+}
+
+/// Parse the date formats sites put in markup, assuming UTC when no offset is given
+pub fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(raw) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc2822(raw) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%z",
+        "%Y-%m-%dT%H:%M:%S%.f%z",
+        "%Y-%m-%d %H:%M:%S%z",
+    ] {
+        if let Ok(date) = DateTime::parse_from_str(raw, format) {
+            return Some(date.with_timezone(&Utc));
+        }
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(date.and_utc());
+        }
+    }
+    for format in [
+        "%Y-%m-%d",
+        "%Y/%m/%d",
+        "%B %d, %Y",
+        "%b %d, %Y",
+        "%d %B %Y",
+        "%d %b %Y",
+    ] {
+        if let Ok(date) = NaiveDate::parse_from_str(raw, format) {
+            return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+        }
+    }
+    None
+}
+
+/// Split a byline like "By Jane Doe and John Roe" into names
+fn byline_names(byline: &str) -> Vec<String> {
+    let byline = byline.trim();
+    let byline = ["By ", "by ", "BY "]
+        .iter()
+        .find_map(|prefix| byline.strip_prefix(prefix))
+        .unwrap_or(byline);
+    byline
+        .split([',', '|'])
+        .flat_map(|part| part.split(" and "))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && name.split_whitespace().count() <= 5)
+        .collect()
+}
+
+fn authors(node: Option<&Value>, meta: &HashMap<String, String>, html: &str) -> Vec<String> {
+    let mut authors: Vec<String> = match node.and_then(|node| node.get("author")) {
+        Some(Value::Array(authors)) => authors.iter().filter_map(json_ld_text).collect(),
+        Some(author) => json_ld_text(author).into_iter().collect(),
+        None => Vec::new(),
+    };
+    if authors.is_empty() {
+        // `article:author` is often a profile URL rather than a name
+        authors = [
+            "author",
+            "article:author",
+            "parsely-author",
+            "sailthru.author",
+        ]
+        .iter()
+        .filter_map(|key| meta.get(*key))
+        .find(|author| !author.starts_with("http"))
+        .map(|author| byline_names(author))
+        .unwrap_or_default();
+    }
+    if authors.is_empty() {
+        authors = BYLINE_REGEX
+            .captures_iter(html)
+            .filter_map(|captures| clean_text(&captures[2]))
+            .map(|byline| byline_names(&byline))
+            .find(|names| !names.is_empty())
+            .unwrap_or_default();
+    }
+    authors.dedup();
+    authors
+}
+
+/// Prose paragraphs of the page's main content
+fn readable_paragraphs(html: &str) -> Vec<String> {
+    let mut html = html.to_string();
+    for boilerplate in BOILERPLATE_REGEXES.iter() {
+        html = boilerplate.replace_all(&html, " ").into_owned();
+    }
+    let scope = ARTICLE_REGEX
+        .captures(&html)
+        .or_else(|| MAIN_REGEX.captures(&html))
+        .map(|captures| captures[1].to_string())
+        .unwrap_or(html);
+
+    PARAGRAPH_REGEX
+        .captures_iter(&scope)
+        .filter(|captures| !BYLINE_ATTRIBUTE_REGEX.is_match(&captures[2]))
+        .filter_map(|captures| clean_text(&captures[3]))
+        .filter(|paragraph| paragraph.split_whitespace().count() >= MIN_PARAGRAPH_WORDS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEWS: &str = r#"<!DOCTYPE html>
+<html lang="en"><head>
+  <title>Harbour reopens after storm | Coast Times</title>
+  <link rel="canonical" href="https://news.example/2024/harbour-reopens?utm_source=x">
+  <link rel="amphtml" href="/amp/2024/harbour-reopens">
+  <meta property="og:site_name" content="Coast Times">
+  <script type="application/ld+json">
+  {"@context": "https://schema.org", "@type": "NewsArticle",
+   "headline": "Harbour reopens after storm",
+   "author": [{"@type": "Person", "name": "Jane Doe"}, {"@type": "Person", "name": "John Roe"}],
+   "datePublished": "2024-03-05T08:30:00+01:00",
+   "dateModified": "2024-03-05 12:00:00",
+   "image": {"@type": "ImageObject", "url": "/img/harbour.jpg"}}
+  </script>
+</head><body>
+  <nav><p>Home News Sport Weather and more links here</p></nav>
+  <article>
+    <h1>Harbour reopens after storm</h1>
+    <p class="byline">By Jane Doe and John Roe</p>
+    <p>The harbour reopened on Tuesday after three days of closures caused by the storm.</p>
+    <figure><figcaption>Boats</figcaption></figure>
+    <p>Fishing crews said the damage was less severe than feared, though repairs continue.</p>
+    <aside><p>Sign up for our newsletter to get the latest updates daily.</p></aside>
+  </article>
+  <footer><p>Copyright Coast Times, all rights reserved worldwide.</p></footer>
+</body></html>"#;
+
+    #[test]
+    fn test_extracts_news_article() {
+        let article = ArticleExtractor::new()
+            .extract(NEWS, "https://news.example/2024/harbour-reopens")
+            .unwrap();
+        assert_eq!(
+            article.headline.as_deref(),
+            Some("Harbour reopens after storm")
+        );
+        assert_eq!(article.authors, vec!["Jane Doe", "John Roe"]);
+        assert_eq!(
+            article.published_at.unwrap().to_rfc3339(),
+            "2024-03-05T07:30:00+00:00"
+        );
+        assert_eq!(
+            article.modified_at.unwrap().to_rfc3339(),
+            "2024-03-05T12:00:00+00:00"
+        );
+        assert_eq!(
+            article.text,
+            "The harbour reopened on Tuesday after three days of closures caused by the storm.\n\n\
+             Fishing crews said the damage was less severe than feared, though repairs continue."
+        );
+        assert_eq!(article.word_count, 27);
+        assert_eq!(
+            article.canonical_url.as_deref(),
+            Some("https://news.example/2024/harbour-reopens")
+        );
+        assert_eq!(
+            article.amp_url.as_deref(),
+            Some("https://news.example/amp/2024/harbour-reopens")
+        );
+        assert!(!article.is_amp);
+        assert_eq!(
+            article.image.as_deref(),
+            Some("https://news.example/img/harbour.jpg")
+        );
+        assert_eq!(article.site_name.as_deref(), Some("Coast Times"));
+    }
+
+    #[test]
+    fn test_falls_back_to_meta_tags_and_bylines() {
+        let html = r#"<html amp><head>
+            <meta property="og:type" content="article">
+            <meta property="og:title" content="Ten tips for sourdough">
+            <meta property="article:author" content="https://facebook.com/someone">
+        </head><body><main>
+            <span class="author-name">by Sam Baker</span>
+            <time datetime="2023-11-02">November 2</time>
+            <p>Start with a lively starter and feed it the night before you bake.</p>
+        </main></body></html>"#;
+
+        let article = ArticleExtractor::new()
+            .extract(html, "https://blog.example/sourdough")
+            .unwrap();
+        assert_eq!(article.headline.as_deref(), Some("Ten tips for sourdough"));
+        assert_eq!(article.authors, vec!["Sam Baker"]);
+        assert_eq!(
+            article.published_at.unwrap().to_rfc3339(),
+            "2023-11-02T00:00:00+00:00"
+        );
+        assert!(article.is_amp);
+
+        assert!(ArticleExtractor::new()
+            .extract(
+                "<html><body><p>Just a page with enough words here.</p></body></html>",
+                "https://a.example/"
+            )
+            .is_none());
+        assert_eq!(
+            parse_date("March 5, 2024").unwrap().to_rfc3339(),
+            "2024-03-05T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("Tue, 05 Mar 2024 10:00:00 GMT")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-03-05T10:00:00+00:00"
+        );
+    }
+}
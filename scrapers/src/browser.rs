@@ -1,20 +1,100 @@
-use anyhow::Result;
-use fantoccini::{Client, ClientBuilder, Locator};
+use crate::anti_bot::fingerprint_manager::{FingerprintManager, SessionFingerprint};
+use crate::anti_bot::user_agents::BrowserFamily;
+use crate::consent::ConsentHandler;
+use crate::recipes::{fill, Recipe, RecipeFailure, RecipeTrace, TraceOptions, TraceStep};
+use anyhow::{Context, Result};
+use fantoccini::{elements::Element, Client, ClientBuilder, Locator};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Semaphore;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use swoop_core::throttle::Throttle;
+use tokio::{sync::OwnedSemaphorePermit, task::JoinHandle};
 use url::Url;
 
+/// Time between two autoscaling passes
+const AUTOSCALE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Browser engine a pool's sessions run, behind the WebDriver server for it
+///
+/// Bot detection tells the engines apart by far more than the user agent
+/// (JavaScript engine quirks, TLS and HTTP/2 fingerprints), so a session
+/// only ever claims to be a browser its engine really is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserEngine {
+    /// Chrome or Edge, through chromedriver
+    #[default]
+    #[serde(alias = "chrome")]
+    Chromium,
+    /// Firefox, through geckodriver
+    Firefox,
+}
+
+impl BrowserEngine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrowserEngine::Chromium => "chromium",
+            BrowserEngine::Firefox => "firefox",
+        }
+    }
+
+    /// Browser families whose user agents the engine passes for
+    pub fn families(&self) -> &'static [BrowserFamily] {
+        match self {
+            BrowserEngine::Chromium => &[BrowserFamily::Chrome, BrowserFamily::Edge],
+            BrowserEngine::Firefox => &[BrowserFamily::Firefox],
+        }
+    }
+
+    /// The families of `families` the engine passes for, or all of its own
+    /// when that leaves none
+    pub fn narrow(&self, families: &[BrowserFamily]) -> Vec<BrowserFamily> {
+        let narrowed: Vec<BrowserFamily> = families
+            .iter()
+            .copied()
+            .filter(|family| self.families().contains(family))
+            .collect();
+        if narrowed.is_empty() {
+            self.families().to_vec()
+        } else {
+            narrowed
+        }
+    }
+
+    fn default_user_agent(&self) -> &'static str {
+        match self {
+            BrowserEngine::Chromium => "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            BrowserEngine::Firefox => {
+                "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0"
+            }
+        }
+    }
+}
+
 /// Configuration for browser automation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BrowserConfig {
     /// Maximum number of concurrent browser instances
     pub max_instances: usize,
+    /// Browser sessions kept open even while there is nothing to render
+    #[serde(default)]
+    pub min_instances: usize,
+    /// Idle sessions kept ready on top of the current demand
+    #[serde(default)]
+    pub warm_standby: usize,
+    /// Seconds a session above the demand stays idle before it is closed
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
     /// Page load timeout in seconds
     pub page_timeout_secs: u64,
     /// WebDriver server URL (e.g., http://localhost:4444)
     pub webdriver_url: String,
+    /// Engine the WebDriver server runs, which `capabilities` are written for
+    #[serde(default)]
+    pub engine: BrowserEngine,
     /// Whether to run in headless mode
     pub headless: bool,
     /// Custom user agent string
@@ -23,6 +103,17 @@ pub struct BrowserConfig {
     pub window_size: (u32, u32),
     /// Additional browser capabilities
     pub capabilities: serde_json::Value,
+    /// Dismiss cookie banners and consent popups after each page load
+    #[serde(default = "default_dismiss_consent")]
+    pub dismiss_consent: bool,
+}
+
+fn default_dismiss_consent() -> bool {
+    true
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    60
 }
 
 impl Default for BrowserConfig {
@@ -59,73 +150,702 @@ impl Default for BrowserConfig {
 
         Self {
             max_instances: 5,
+            min_instances: 0,
+            warm_standby: 0,
+            idle_timeout_secs: default_idle_timeout_secs(),
             page_timeout_secs: 30,
             webdriver_url: "http://localhost:4444".to_string(),
+            engine: BrowserEngine::Chromium,
             headless: true,
-            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()),
+            user_agent: Some(BrowserEngine::Chromium.default_user_agent().to_string()),
             window_size: (1920, 1080),
             capabilities: serde_json::Value::Object(caps),
+            dismiss_consent: true,
+        }
+    }
+}
+
+impl BrowserConfig {
+    /// Default settings for `engine`: its capabilities and a user agent of
+    /// a browser it runs
+    pub fn for_engine(engine: BrowserEngine) -> Self {
+        match engine {
+            BrowserEngine::Chromium => Self::default(),
+            BrowserEngine::Firefox => Self {
+                engine,
+                user_agent: Some(engine.default_user_agent().to_string()),
+                capabilities: serde_json::json!({
+                    "browserName": "firefox",
+                    "moz:firefoxOptions": {
+                        "args": ["-headless"],
+                        "prefs": { "dom.webdriver.enabled": false }
+                    }
+                }),
+                ..Self::default()
+            },
         }
     }
 }
 
 /// Browser pool for managing multiple browser instances
+///
+/// Sessions are reused: a [`BrowserInstance`] hands its session back when it
+/// is dropped, and the next caller gets it with its cookies cleared. With
+/// [`BrowserPool::start_autoscaling`], the pool keeps as many sessions open
+/// as there are pages being rendered, callers waiting and browser-required
+/// URLs queued (see [`BrowserPool::queue_page`]), plus `warm_standby`, within
+/// `min_instances` and the current limit of [`BrowserPool::throttle`]. Idle
+/// sessions beyond that are closed after `idle_timeout_secs`, the least used
+/// first.
+#[derive(Debug)]
 pub struct BrowserPool {
     config: BrowserConfig,
-    semaphore: Arc<Semaphore>,
+    throttle: Arc<Throttle>,
+    consent: Arc<ConsentHandler>,
+    /// Draws each session's spoofed fingerprint, when set
+    fingerprints: Option<Arc<FingerprintManager>>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    /// Sessions ready for the next caller, the most recently used last
+    idle: Vec<IdleSession>,
+    /// Sessions open or being opened, idle or checked out
+    open: usize,
+    /// Sessions checked out
+    busy: usize,
+    /// Callers waiting for a slot
+    waiting: usize,
+    /// Browser-required URLs queued
+    queued: usize,
+    next_id: u64,
+    usage: HashMap<u64, SessionUsage>,
+}
+
+#[derive(Debug)]
+struct IdleSession {
+    id: u64,
+    client: Client,
+    since: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SessionUsage {
+    opened: Instant,
+    busy: Duration,
+    uses: u64,
+}
+
+impl SessionUsage {
+    fn new() -> Self {
+        Self {
+            opened: Instant::now(),
+            busy: Duration::ZERO,
+            uses: 0,
+        }
+    }
+
+    /// Share of its lifetime the session spent checked out
+    fn utilization(&self) -> f64 {
+        let age = self.opened.elapsed().as_secs_f64();
+        if age > 0.0 {
+            (self.busy.as_secs_f64() / age).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl PoolState {
+    fn demand(&self) -> usize {
+        self.busy + self.waiting + self.queued
+    }
+
+    fn close(&mut self, id: u64) {
+        self.open = self.open.saturating_sub(1);
+        self.usage.remove(&id);
+    }
+}
+
+/// Sessions the pool should have open for `demand` pages, when at most
+/// `limit` may be rendered at once
+fn target_sessions(config: &BrowserConfig, demand: usize, limit: usize) -> usize {
+    (demand + config.warm_standby)
+        .min(limit)
+        .max(config.min_instances.min(limit))
+}
+
+/// A caller waiting for a browser slot, counted until it gets one
+struct Waiting<'a>(&'a Mutex<PoolState>);
+
+impl<'a> Waiting<'a> {
+    fn new(state: &'a Mutex<PoolState>) -> Self {
+        state.lock().unwrap().waiting += 1;
+        Self(state)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().waiting -= 1;
+    }
+}
+
+/// A URL that will need a browser, counted in the pool's demand while it
+/// is held
+#[derive(Debug)]
+pub struct QueuedPage {
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl Drop for QueuedPage {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().queued -= 1;
+    }
+}
+
+/// Sessions of a pool and how much they are used
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PoolStats {
+    pub open: usize,
+    pub busy: usize,
+    pub idle: usize,
+    pub waiting: usize,
+    pub queued: usize,
+    /// Sessions the autoscaler is working towards
+    pub target: usize,
+    pub instances: Vec<InstanceStats>,
+}
+
+/// Use of one browser session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstanceStats {
+    pub id: u64,
+    /// Pages rendered, or times the session was checked out
+    pub uses: u64,
+    /// Share of the session's lifetime spent checked out, from 0 to 1
+    pub utilization: f64,
 }
 
 impl BrowserPool {
     pub fn new(config: BrowserConfig) -> Self {
-        let semaphore = Arc::new(Semaphore::new(config.max_instances));
-        Self { config, semaphore }
+        let family = config
+            .user_agent
+            .as_deref()
+            .and_then(BrowserFamily::of_user_agent);
+        if let Some(family) = family.filter(|family| !config.engine.families().contains(family)) {
+            tracing::warn!(
+                "🌐 A {} browser claiming to be {} is easy to tell apart; use a {} user agent",
+                config.engine.as_str(),
+                family.as_str(),
+                config.engine.families()[0].as_str()
+            );
+        }
+        let throttle = Arc::new(Throttle::new(config.max_instances));
+        Self {
+            config,
+            throttle,
+            consent: Arc::new(ConsentHandler::default()),
+            fingerprints: None,
+            state: Arc::new(Mutex::new(PoolState::default())),
+        }
     }
 
-    /// Get a browser instance from the pool
-    pub async fn get_browser(&self) -> Result<BrowserInstance> {
-        let _permit = self.semaphore.acquire().await?;
+    /// Replace the default consent banner rules
+    pub fn with_consent_handler(mut self, handler: ConsentHandler) -> Self {
+        self.consent = Arc::new(handler);
+        self
+    }
 
-        let mut client_builder = ClientBuilder::native();
+    /// Give every new session its own [`SessionFingerprint`] from `manager`,
+    /// applied before any page script runs. Needs Chrome DevTools, so only
+    /// Chromium sessions are spoofed.
+    pub fn with_fingerprints(mut self, manager: Arc<FingerprintManager>) -> Self {
+        if self.config.engine != BrowserEngine::Chromium {
+            tracing::warn!(
+                "🎭 {} sessions can't be given spoofed fingerprints; they need Chrome DevTools",
+                self.config.engine.as_str()
+            );
+        }
+        self.fingerprints = Some(manager);
+        self
+    }
 
-        // Set capabilities
-        if let serde_json::Value::Object(caps) = &self.config.capabilities {
-            client_builder.capabilities(caps.clone());
+    /// Browser instances that can be started without waiting
+    pub fn available(&self) -> usize {
+        self.throttle.semaphore().available_permits()
+    }
+
+    pub fn max_instances(&self) -> usize {
+        self.config.max_instances
+    }
+
+    /// Settings instances are started with
+    pub fn config(&self) -> &BrowserConfig {
+        &self.config
+    }
+
+    pub fn engine(&self) -> BrowserEngine {
+        self.config.engine
+    }
+
+    /// Limit on running instances, which can be lowered below
+    /// `max_instances` while the host is short of resources
+    pub fn throttle(&self) -> Arc<Throttle> {
+        self.throttle.clone()
+    }
+
+    /// Count a URL that will need a browser in the demand the pool scales
+    /// to, until the returned guard is dropped
+    pub fn queue_page(&self) -> QueuedPage {
+        self.state.lock().unwrap().queued += 1;
+        QueuedPage {
+            state: self.state.clone(),
         }
+    }
 
-        let client = client_builder.connect(&self.config.webdriver_url).await?;
+    pub fn stats(&self) -> PoolStats {
+        let state = self.state.lock().unwrap();
+        let mut instances: Vec<InstanceStats> = state
+            .usage
+            .iter()
+            .map(|(id, usage)| InstanceStats {
+                id: *id,
+                uses: usage.uses,
+                utilization: usage.utilization(),
+            })
+            .collect();
+        instances.sort_by_key(|instance| instance.id);
+        PoolStats {
+            open: state.open,
+            busy: state.busy,
+            idle: state.idle.len(),
+            waiting: state.waiting,
+            queued: state.queued,
+            target: target_sessions(&self.config, state.demand(), self.throttle.limit()),
+            instances,
+        }
+    }
 
-        // Set window size
-        client
-            .set_window_size(self.config.window_size.0, self.config.window_size.1)
+    /// Ask the WebDriver server whether it can start new sessions
+    pub async fn status(&self) -> Result<WebDriverStatus> {
+        let url = format!("{}/status", self.config.webdriver_url.trim_end_matches('/'));
+        let response: StatusResponse = reqwest::Client::new()
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
             .await?;
+        Ok(response.value)
+    }
 
-        // Set user agent if specified
-        if let Some(user_agent) = &self.config.user_agent {
-            client.execute(
-                &format!(r#"Object.defineProperty(navigator, 'userAgent', {{get: function(){{return '{}'}}}});"#, user_agent),
-                vec![]
-            ).await?;
-        }
+    /// Get a browser instance from the pool
+    pub async fn get_browser(&self) -> Result<BrowserInstance> {
+        let waiting = Waiting::new(&self.state);
+        let permit = self.throttle.acquire().await?;
+        drop(waiting);
+
+        let (id, client) = loop {
+            let idle = self.state.lock().unwrap().idle.pop();
+            match idle {
+                // A session that can't clear its cookies has died meanwhile
+                Some(session) => match session.client.delete_all_cookies().await {
+                    Ok(()) => break (session.id, session.client),
+                    Err(_) => {
+                        self.state.lock().unwrap().close(session.id);
+                        let _ = session.client.close().await;
+                    }
+                },
+                None => {
+                    let id = self.reserve_session();
+                    match self.connect(&self.config.capabilities, None).await {
+                        Ok(client) => break (id, client),
+                        Err(e) => {
+                            self.state.lock().unwrap().close(id);
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        };
+        self.state.lock().unwrap().busy += 1;
 
-        // Don't drop the permit, keep it alive with the instance
-        std::mem::forget(_permit);
+        Ok(BrowserInstance {
+            client: Arc::new(client),
+            config: self.config.clone(),
+            consent: self.consent.clone(),
+            session: id,
+            checked_out: Instant::now(),
+            pool: self.state.clone(),
+            dedicated: false,
+            closed: false,
+            _permit: permit,
+        })
+    }
+
+    /// Start a session of its own with `dir` as the browser's profile
+    /// directory, e.g. a persistent profile from `storage::browser_profiles`
+    ///
+    /// The session counts against the pool's limit but isn't handed to
+    /// other callers. [`BrowserInstance::close`] ends it, which makes the
+    /// browser write its state to `dir`.
+    pub async fn get_browser_in(&self, dir: &Path) -> Result<BrowserInstance> {
+        let waiting = Waiting::new(&self.state);
+        let permit = self.throttle.acquire().await?;
+        drop(waiting);
+
+        let id = self.reserve_session();
+        let capabilities = with_profile_dir(&self.config.capabilities, dir);
+        // The profile's returning visitor keeps the device it came back on
+        let client = match self.connect(&capabilities, Some(profile_seed(dir))).await {
+            Ok(client) => client,
+            Err(e) => {
+                self.state.lock().unwrap().close(id);
+                return Err(e);
+            }
+        };
+        self.state.lock().unwrap().busy += 1;
 
         Ok(BrowserInstance {
             client: Arc::new(client),
             config: self.config.clone(),
-            _semaphore: self.semaphore.clone(),
+            consent: self.consent.clone(),
+            session: id,
+            checked_out: Instant::now(),
+            pool: self.state.clone(),
+            dedicated: true,
+            closed: false,
+            _permit: permit,
         })
     }
+
+    /// Open and close sessions in the background to follow the demand, for
+    /// as long as the pool exists
+    pub fn start_autoscaling(self: &Arc<Self>) -> JoinHandle<()> {
+        let pool: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(AUTOSCALE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    return;
+                };
+                pool.autoscale().await;
+            }
+        })
+    }
+
+    /// One autoscaling pass: open sessions up to the target, and close idle
+    /// ones above it that have timed out, or straight away when the throttle
+    /// was lowered below them
+    pub async fn autoscale(&self) {
+        let limit = self.throttle.limit();
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_secs);
+        let (to_open, to_close) = {
+            let mut state = self.state.lock().unwrap();
+            let target = target_sessions(&self.config, state.demand(), limit);
+            let to_open = target.saturating_sub(state.open);
+
+            let excess = state.open.saturating_sub(target);
+            let over_limit = state.open.saturating_sub(limit);
+            let mut candidates: Vec<(u64, f64, bool)> = state
+                .idle
+                .iter()
+                .map(|session| {
+                    let utilization = state
+                        .usage
+                        .get(&session.id)
+                        .map_or(0.0, SessionUsage::utilization);
+                    (
+                        session.id,
+                        utilization,
+                        session.since.elapsed() >= idle_timeout,
+                    )
+                })
+                .collect();
+            candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let closing: Vec<u64> = candidates
+                .iter()
+                .enumerate()
+                .filter(|(rank, (_, _, timed_out))| *rank < over_limit || *timed_out)
+                .map(|(_, (id, _, _))| *id)
+                .take(excess)
+                .collect();
+            let mut to_close = Vec::new();
+            state.idle.retain(|session| {
+                if closing.contains(&session.id) {
+                    to_close.push(session.client.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            for id in &closing {
+                state.close(*id);
+            }
+            (to_open, to_close)
+        };
+
+        if to_open > 0 || !to_close.is_empty() {
+            tracing::debug!(
+                "🌐 Browser pool: opening {}, closing {} sessions",
+                to_open,
+                to_close.len()
+            );
+        }
+        for client in to_close {
+            let _ = client.close().await;
+        }
+        for _ in 0..to_open {
+            let id = self.reserve_session();
+            match self.connect(&self.config.capabilities, None).await {
+                Ok(client) => self.state.lock().unwrap().idle.push(IdleSession {
+                    id,
+                    client,
+                    since: Instant::now(),
+                }),
+                Err(e) => {
+                    self.state.lock().unwrap().close(id);
+                    tracing::warn!("🌐 Failed to open a standby browser session: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Close every idle session, e.g. at the end of a run
+    pub async fn close_idle(&self) {
+        let idle: Vec<IdleSession> = {
+            let mut state = self.state.lock().unwrap();
+            let idle: Vec<IdleSession> = state.idle.drain(..).collect();
+            for session in &idle {
+                state.close(session.id);
+            }
+            idle
+        };
+        for session in idle {
+            let _ = session.client.close().await;
+        }
+    }
+
+    /// Count a session about to be opened, returning its id
+    fn reserve_session(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.open += 1;
+        state.next_id += 1;
+        let id = state.next_id;
+        state.usage.insert(id, SessionUsage::new());
+        id
+    }
+
+    /// Start a session; a spoofed fingerprint is drawn from `seed`, or at
+    /// random
+    async fn connect(&self, capabilities: &serde_json::Value, seed: Option<u32>) -> Result<Client> {
+        let mut client_builder = ClientBuilder::native();
+
+        // Set capabilities, with the user agent set at launch so every
+        // page and worker sees it
+        let capabilities = match &self.config.user_agent {
+            Some(user_agent) => with_user_agent(capabilities, user_agent),
+            None => capabilities.clone(),
+        };
+        if let serde_json::Value::Object(caps) = capabilities {
+            client_builder.capabilities(caps);
+        }
+
+        let client = client_builder.connect(&self.config.webdriver_url).await?;
+
+        let (mut width, mut height) = self.config.window_size;
+        let fingerprints = self
+            .fingerprints
+            .as_ref()
+            .filter(|_| self.config.engine == BrowserEngine::Chromium);
+        if let Some(manager) = fingerprints {
+            let fingerprint = manager.session_fingerprint(
+                seed.unwrap_or_else(rand::random),
+                self.config.user_agent.as_deref(),
+            );
+            // A window never outgrows the screen it claims to be on
+            width = width.min(fingerprint.screen.width);
+            height = height.min(fingerprint.screen.height);
+            if let Err(e) = self.inject_fingerprint(&client, &fingerprint).await {
+                let _ = client.close().await;
+                return Err(e.context("Failed to inject the session's fingerprint"));
+            }
+        }
+
+        // Set window size
+        client.set_window_size(width, height).await?;
+        Ok(client)
+    }
+
+    /// Have every document of `client`'s session run `fingerprint`'s
+    /// overrides before its own scripts, through chromedriver's DevTools
+    /// passthrough
+    async fn inject_fingerprint(
+        &self,
+        client: &Client,
+        fingerprint: &SessionFingerprint,
+    ) -> Result<()> {
+        let session = client
+            .session_id()
+            .await?
+            .context("WebDriver session has no id")?;
+        let url = format!(
+            "{}/session/{}/goog/cdp/execute",
+            self.config.webdriver_url.trim_end_matches('/'),
+            session
+        );
+        let command = serde_json::json!({
+            "cmd": "Page.addScriptToEvaluateOnNewDocument",
+            "params": { "source": fingerprint.injection_script() }
+        });
+        reqwest::Client::new()
+            .post(&url)
+            .json(&command)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The pool of `pools` running `engine`, or the first one when any engine
+/// will do
+pub fn select_pool<'a>(
+    pools: impl IntoIterator<Item = &'a Arc<BrowserPool>>,
+    engine: Option<BrowserEngine>,
+) -> Option<&'a Arc<BrowserPool>> {
+    let mut pools = pools.into_iter();
+    match engine {
+        Some(engine) => pools.find(|pool| pool.engine() == engine),
+        None => pools.next(),
+    }
+}
+
+/// Fingerprint seed of the profile in `dir`, the same in every run
+fn profile_seed(dir: &Path) -> u32 {
+    // FNV-1a over the profile's name
+    let name = dir.file_name().unwrap_or(dir.as_os_str()).to_string_lossy();
+    name.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// `capabilities` launching the browser with `user_agent`, unless they
+/// already set one
+fn with_user_agent(capabilities: &serde_json::Value, user_agent: &str) -> serde_json::Value {
+    let mut capabilities = capabilities.clone();
+    if let Some(options) = capabilities
+        .get_mut("goog:chromeOptions")
+        .and_then(|options| options.as_object_mut())
+    {
+        let args = options
+            .entry("args")
+            .or_insert_with(|| serde_json::json!([]));
+        if let Some(args) = args.as_array_mut() {
+            let set = args.iter().any(|arg| {
+                arg.as_str()
+                    .is_some_and(|arg| arg.starts_with("--user-agent="))
+            });
+            if !set {
+                args.push(format!("--user-agent={}", user_agent).into());
+            }
+        }
+    } else if let Some(options) = capabilities
+        .get_mut("moz:firefoxOptions")
+        .and_then(|options| options.as_object_mut())
+    {
+        let prefs = options
+            .entry("prefs")
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(prefs) = prefs.as_object_mut() {
+            prefs
+                .entry("general.useragent.override")
+                .or_insert_with(|| user_agent.into());
+        }
+    }
+    capabilities
+}
+
+/// `capabilities` with `dir` as the browser's profile directory
+fn with_profile_dir(capabilities: &serde_json::Value, dir: &Path) -> serde_json::Value {
+    let mut capabilities = capabilities.clone();
+    if let Some(caps) = capabilities.as_object_mut() {
+        let firefox = caps.contains_key("moz:firefoxOptions")
+            || caps.get("browserName").and_then(|name| name.as_str()) == Some("firefox");
+        let (options, args) = if firefox {
+            (
+                "moz:firefoxOptions",
+                vec!["-profile".to_string(), dir.display().to_string()],
+            )
+        } else {
+            (
+                "goog:chromeOptions",
+                vec![format!("--user-data-dir={}", dir.display())],
+            )
+        };
+        let options = caps.entry(options).or_insert_with(|| serde_json::json!({}));
+        if let Some(existing) = options
+            .as_object_mut()
+            .map(|options| {
+                options
+                    .entry("args")
+                    .or_insert_with(|| serde_json::json!([]))
+            })
+            .and_then(|existing| existing.as_array_mut())
+        {
+            existing.extend(args.into_iter().map(serde_json::Value::String));
+        }
+    }
+    capabilities
+}
+
+/// Readiness reported by a WebDriver server's `/status` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebDriverStatus {
+    pub ready: bool,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusResponse {
+    value: WebDriverStatus,
 }
 
 /// Wrapper around a browser instance with automatic cleanup
 pub struct BrowserInstance {
     client: Arc<Client>,
     config: BrowserConfig,
-    _semaphore: Arc<Semaphore>,
+    consent: Arc<ConsentHandler>,
+    /// Session id within the pool, which gets the session back on drop
+    session: u64,
+    checked_out: Instant,
+    pool: Arc<Mutex<PoolState>>,
+    /// Started for this caller alone, and ended instead of going back to
+    /// the pool
+    dedicated: bool,
+    closed: bool,
+    /// Frees the pool slot when the instance is dropped
+    _permit: OwnedSemaphorePermit,
 }
 
 impl BrowserInstance {
+    /// End a session started with [`BrowserPool::get_browser_in`], letting
+    /// the browser write its profile; other sessions just go back to the pool
+    pub async fn close(mut self) -> Result<()> {
+        if self.dedicated {
+            self.closed = true;
+            (*self.client).clone().close().await?;
+        }
+        Ok(())
+    }
+
     /// Navigate to a URL and extract content
     pub async fn scrape_page(&self, url: &str) -> Result<ScrapedContent> {
         let _parsed_url = Url::parse(url)?;
@@ -135,6 +855,7 @@ impl BrowserInstance {
 
         // Wait for page to load
         tokio::time::sleep(Duration::from_secs(2)).await;
+        self.dismiss_consent().await;
 
         // Extract content
         let html = self.client.source().await?;
@@ -157,59 +878,88 @@ impl BrowserInstance {
         })
     }
 
+    /// Clear cookie banners so they don't cover screenshots or the extracted DOM
+    async fn dismiss_consent(&self) {
+        if !self.config.dismiss_consent {
+            return;
+        }
+        // A banner we can't dismiss shouldn't fail the whole scrape
+        let _ = self.consent.dismiss(&self.client).await;
+    }
+
     /// Execute JavaScript on the page
     pub async fn execute_script(&self, script: &str) -> Result<serde_json::Value> {
         let result = self.client.execute(script, vec![]).await?;
         Ok(result)
     }
 
-    /// Interact with page elements (click, type, etc.)
+    /// Open `url`, run `actions` on it and return the page they lead to
     pub async fn interact_with_page(
         &self,
         url: &str,
         actions: Vec<PageAction>,
     ) -> Result<ScrapedContent> {
-        // Navigate to the page
-        self.client.goto(url).await?;
+        self.open(url).await?;
+        let mut run = RecipeRun::new(BTreeMap::new(), TraceOptions::default());
+        self.perform(&actions, "", &mut run).await?;
+        self.capture().await
+    }
 
-        // Wait for page to load
-        tokio::time::sleep(Duration::from_secs(2)).await;
+    /// Run `recipe` from its start page; see [`crate::recipes`]
+    pub async fn run_recipe(&self, recipe: &Recipe) -> Result<RecipeOutcome, RecipeFailure> {
+        self.run_recipe_traced(recipe, TraceOptions::default())
+            .await
+    }
 
-        // Execute actions
-        for action in actions {
-            match action {
-                PageAction::Click { selector } => {
-                    if let Ok(element) = self.client.find(Locator::Css(&selector)).await {
-                        element.click().await?;
-                        tokio::time::sleep(Duration::from_millis(500)).await;
-                    }
-                }
-                PageAction::Type { selector, text } => {
-                    if let Ok(element) = self.client.find(Locator::Css(&selector)).await {
-                        element.clear().await?;
-                        element.send_keys(&text).await?;
-                        tokio::time::sleep(Duration::from_millis(200)).await;
-                    }
-                }
-                PageAction::Wait { duration_ms } => {
-                    tokio::time::sleep(Duration::from_millis(duration_ms)).await;
-                }
-                PageAction::ScrollTo { selector } => {
-                    let script = format!(
-                        "document.querySelector('{}').scrollIntoView();",
-                        selector.replace("'", "\\'")
-                    );
-                    let _ = self.client.execute(&script, vec![]).await;
-                    tokio::time::sleep(Duration::from_millis(300)).await;
-                }
-            }
+    /// Run `recipe`, keeping what `options` asks for after each step in the
+    /// trace of its outcome, or of the [`RecipeFailure`] when a step fails
+    pub async fn run_recipe_traced(
+        &self,
+        recipe: &Recipe,
+        options: TraceOptions,
+    ) -> Result<RecipeOutcome, RecipeFailure> {
+        let mut run = RecipeRun::new(recipe.params.clone(), options);
+        match self.run_steps(recipe, &mut run).await {
+            Ok(()) => Ok(run.outcome),
+            Err(error) => Err(RecipeFailure {
+                error,
+                outcome: run.outcome,
+            }),
         }
+    }
+
+    async fn run_steps(&self, recipe: &Recipe, run: &mut RecipeRun) -> Result<()> {
+        let url = fill(&recipe.url, &run.outcome.values);
+        let index = run.begin("0", "open");
+        let opened = self
+            .open(&url)
+            .await
+            .with_context(|| format!("Failed to open {}", url));
+        self.finish(run, index, &opened).await;
+        opened?;
+
+        self.perform(&recipe.steps, "", run).await?;
+        if run.outcome.pages.is_empty() {
+            let page = self.capture().await?;
+            run.outcome.pages.push(page);
+        }
+        Ok(())
+    }
+
+    async fn open(&self, url: &str) -> Result<()> {
+        Url::parse(url)?;
+        self.client.goto(url).await?;
+        // Wait for page to load
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        self.dismiss_consent().await;
+        Ok(())
+    }
 
-        // Extract final content
+    /// The page as it is now
+    async fn capture(&self) -> Result<ScrapedContent> {
         let html = self.client.source().await?;
         let title = self.client.title().await.unwrap_or_default();
         let current_url = self.client.current_url().await?.to_string();
-
         Ok(ScrapedContent {
             url: current_url,
             title,
@@ -218,22 +968,357 @@ impl BrowserInstance {
             timestamp: chrono::Utc::now(),
         })
     }
+
+    async fn find(&self, selector: &str) -> Result<Element> {
+        self.client
+            .find(Locator::Css(selector))
+            .await
+            .with_context(|| format!("No element matches `{}`", selector))
+    }
+
+    async fn is_present(&self, selector: &str) -> bool {
+        self.client.find(Locator::Css(selector)).await.is_ok()
+    }
+
+    /// Run `actions` in order, keeping captured pages, extracted values and
+    /// the trace of each step in `run`; `prefix` numbers nested steps
+    fn perform<'a>(
+        &'a self,
+        actions: &'a [PageAction],
+        prefix: &'a str,
+        run: &'a mut RecipeRun,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for (i, action) in actions.iter().enumerate() {
+                let step = match prefix {
+                    "" => (i + 1).to_string(),
+                    prefix => format!("{}.{}", prefix, i + 1),
+                };
+                let index = run.begin(&step, action.name());
+                let mut result = self.perform_step(action, &step, run).await;
+                // Nested steps name themselves in their errors
+                if !matches!(
+                    action,
+                    PageAction::IfPresent { .. } | PageAction::Repeat { .. }
+                ) {
+                    result = result.with_context(|| format!("Step {} ({})", step, action.name()));
+                }
+                self.finish(run, index, &result).await;
+                result?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn perform_step(
+        &self,
+        action: &PageAction,
+        step: &str,
+        run: &mut RecipeRun,
+    ) -> Result<()> {
+        match action {
+            PageAction::Click { selector } => {
+                self.find(selector).await?.click().await?;
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            PageAction::Type { selector, text } => {
+                let element = self.find(selector).await?;
+                element.clear().await?;
+                element.send_keys(&fill(text, &run.outcome.values)).await?;
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            PageAction::Wait { duration_ms } => {
+                tokio::time::sleep(Duration::from_millis(*duration_ms)).await;
+            }
+            PageAction::ScrollTo { selector } => {
+                let script = format!(
+                    "document.querySelector('{}').scrollIntoView();",
+                    selector.replace("'", "\\'")
+                );
+                let _ = self.client.execute(&script, vec![]).await;
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            }
+            PageAction::Select { selector, value } => {
+                self.find(selector)
+                    .await?
+                    .select_by_value(&fill(value, &run.outcome.values))
+                    .await
+                    .with_context(|| format!("`{}` has no option {}", selector, value))?;
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            PageAction::Check { selector, checked } => {
+                let element = self.find(selector).await?;
+                if element.is_selected().await? != *checked {
+                    element.click().await?;
+                }
+            }
+            PageAction::Upload { selector, path } => {
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Can't upload {}", path.display()))?;
+                self.find(selector)
+                    .await?
+                    .send_keys(&path.to_string_lossy())
+                    .await?;
+            }
+            PageAction::WaitFor {
+                selector,
+                timeout_ms,
+            } => {
+                self.client
+                    .wait()
+                    .at_most(Duration::from_millis(*timeout_ms))
+                    .for_element(Locator::Css(selector))
+                    .await
+                    .with_context(|| {
+                        format!("`{}` didn't appear within {}ms", selector, timeout_ms)
+                    })?;
+            }
+            PageAction::Extract {
+                name,
+                selector,
+                attribute,
+            } => {
+                let element = self.find(selector).await?;
+                let value = match attribute {
+                    Some(attribute) => element.attr(attribute).await?.unwrap_or_default(),
+                    None => element.text().await?,
+                };
+                run.outcome
+                    .values
+                    .insert(name.clone(), value.trim().to_string());
+            }
+            PageAction::Capture => {
+                let page = self.capture().await?;
+                run.outcome.pages.push(page);
+            }
+            PageAction::IfPresent {
+                selector,
+                then,
+                otherwise,
+            } => {
+                let branch = if self.is_present(selector).await {
+                    then
+                } else {
+                    otherwise
+                };
+                self.perform(branch, step, run).await?;
+            }
+            PageAction::Repeat {
+                times,
+                while_present,
+                steps,
+            } => {
+                for round in 1..=*times {
+                    if let Some(selector) = while_present {
+                        if !self.is_present(selector).await {
+                            break;
+                        }
+                    }
+                    self.perform(steps, &format!("{}[{}]", step, round), run)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete the trace of step `index` now that it ended with `result`
+    async fn finish(&self, run: &mut RecipeRun, index: usize, result: &Result<()>) {
+        let ended_ms = run.elapsed_ms();
+        let url = self
+            .client
+            .current_url()
+            .await
+            .ok()
+            .map(|url| url.to_string());
+        let screenshot = match run.options.screenshots {
+            true => self.client.screenshot().await.ok(),
+            false => None,
+        };
+        let dom = match run.options.dom {
+            true => self.client.source().await.ok(),
+            false => None,
+        };
+
+        let step = &mut run.outcome.trace.steps[index];
+        step.duration_ms = ended_ms.saturating_sub(step.started_ms);
+        step.url = url;
+        step.error = result.as_ref().err().map(|e| format!("{:#}", e));
+        step.screenshot = screenshot;
+        step.dom = dom;
+    }
 }
 
 impl Drop for BrowserInstance {
     fn drop(&mut self) {
-        // Browser cleanup is handled by fantoccini automatically
-        // when the client goes out of scope
+        // The session goes back to the pool for the next caller; the
+        // autoscaler closes it once it is no longer needed
+        let mut pool = self.pool.lock().unwrap();
+        pool.busy -= 1;
+        if let Some(usage) = pool.usage.get_mut(&self.session) {
+            usage.busy += self.checked_out.elapsed();
+            usage.uses += 1;
+        }
+        if self.dedicated {
+            pool.close(self.session);
+            if !self.closed {
+                let client = (*self.client).clone();
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    runtime.spawn(async move {
+                        let _ = client.close().await;
+                    });
+                }
+            }
+            return;
+        }
+        pool.idle.push(IdleSession {
+            id: self.session,
+            client: (*self.client).clone(),
+            since: Instant::now(),
+        });
     }
 }
 
 /// Actions that can be performed on a web page
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Actions on an element fail when no element matches their selector; wrap
+/// ones on optional elements in [`PageAction::IfPresent`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
 pub enum PageAction {
-    Click { selector: String },
-    Type { selector: String, text: String },
-    Wait { duration_ms: u64 },
-    ScrollTo { selector: String },
+    Click {
+        selector: String,
+    },
+    /// Type `text` into an input, replacing what it holds
+    Type {
+        selector: String,
+        text: String,
+    },
+    Wait {
+        duration_ms: u64,
+    },
+    ScrollTo {
+        selector: String,
+    },
+    /// Pick the option of a `<select>` whose value is `value`
+    Select {
+        selector: String,
+        value: String,
+    },
+    /// Tick a checkbox or radio button, or untick it with `checked = false`
+    Check {
+        selector: String,
+        #[serde(default = "default_checked")]
+        checked: bool,
+    },
+    /// Attach a file to a file input; the WebDriver server has to be able to
+    /// read `path`
+    Upload {
+        selector: String,
+        path: PathBuf,
+    },
+    /// Wait until an element matching `selector` is on the page
+    WaitFor {
+        selector: String,
+        #[serde(default = "default_wait_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// Keep the text of the first element matching `selector`, or its
+    /// `attribute`, as the value `name`
+    Extract {
+        name: String,
+        selector: String,
+        attribute: Option<String>,
+    },
+    /// Keep the page as it is now as a result page
+    Capture,
+    /// Run `then` when an element matches `selector`, `otherwise` when none does
+    IfPresent {
+        selector: String,
+        #[serde(default)]
+        then: Vec<PageAction>,
+        #[serde(default)]
+        otherwise: Vec<PageAction>,
+    },
+    /// Run `steps` up to `times` times, stopping early once no element
+    /// matches `while_present`
+    Repeat {
+        times: u32,
+        while_present: Option<String>,
+        steps: Vec<PageAction>,
+    },
+}
+
+impl PageAction {
+    /// The `action` name of the step in recipes
+    pub fn name(&self) -> &'static str {
+        match self {
+            PageAction::Click { .. } => "click",
+            PageAction::Type { .. } => "type",
+            PageAction::Wait { .. } => "wait",
+            PageAction::ScrollTo { .. } => "scroll_to",
+            PageAction::Select { .. } => "select",
+            PageAction::Check { .. } => "check",
+            PageAction::Upload { .. } => "upload",
+            PageAction::WaitFor { .. } => "wait_for",
+            PageAction::Extract { .. } => "extract",
+            PageAction::Capture => "capture",
+            PageAction::IfPresent { .. } => "if_present",
+            PageAction::Repeat { .. } => "repeat",
+        }
+    }
+}
+
+fn default_checked() -> bool {
+    true
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    10_000
+}
+
+/// What running a [`Recipe`] produced
+#[derive(Debug, Clone, Default)]
+pub struct RecipeOutcome {
+    /// Pages kept by `capture` steps, or the last page when there were none
+    pub pages: Vec<ScrapedContent>,
+    /// Values kept by `extract` steps, along with the recipe's parameters
+    pub values: BTreeMap<String, String>,
+    /// What happened at each step
+    pub trace: RecipeTrace,
+}
+
+/// A recipe being run
+struct RecipeRun {
+    outcome: RecipeOutcome,
+    options: TraceOptions,
+    started: Instant,
+}
+
+impl RecipeRun {
+    fn new(values: BTreeMap<String, String>, options: TraceOptions) -> Self {
+        Self {
+            outcome: RecipeOutcome {
+                values,
+                ..RecipeOutcome::default()
+            },
+            options,
+            started: Instant::now(),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Start the trace of `step`, returning its index in the trace
+    fn begin(&mut self, step: &str, action: &str) -> usize {
+        let started_ms = self.elapsed_ms();
+        let steps = &mut self.outcome.trace.steps;
+        steps.push(TraceStep::new(step, action, started_ms));
+        steps.len() - 1
+    }
 }
 
 /// Content extracted from a web page using browser automation
@@ -259,6 +1344,80 @@ mod tests {
         assert!(!config.capabilities.is_null());
     }
 
+    #[test]
+    fn test_engine_configs() {
+        let chromium = BrowserConfig::for_engine(BrowserEngine::Chromium);
+        let chrome = with_user_agent(&chromium.capabilities, "Chrome/120");
+        let args = chrome["goog:chromeOptions"]["args"].as_array().unwrap();
+        assert_eq!(args.last().unwrap(), "--user-agent=Chrome/120");
+        assert_eq!(with_user_agent(&chrome, "Chrome/121"), chrome);
+
+        let config = BrowserConfig::for_engine(BrowserEngine::Firefox);
+        assert_eq!(config.capabilities["browserName"], "firefox");
+        let user_agent = config.user_agent.as_deref().unwrap();
+        assert_eq!(
+            BrowserFamily::of_user_agent(user_agent),
+            Some(BrowserFamily::Firefox)
+        );
+        let firefox = with_user_agent(&config.capabilities, user_agent);
+        assert_eq!(
+            firefox["moz:firefoxOptions"]["prefs"]["general.useragent.override"],
+            user_agent
+        );
+
+        let pools = [
+            Arc::new(BrowserPool::new(chromium)),
+            Arc::new(BrowserPool::new(config)),
+        ];
+        let firefox = select_pool(&pools, Some(BrowserEngine::Firefox)).unwrap();
+        assert_eq!(firefox.engine(), BrowserEngine::Firefox);
+        let any = select_pool(&pools, None).unwrap();
+        assert_eq!(any.engine(), BrowserEngine::Chromium);
+        assert!(select_pool(&pools[..1], Some(BrowserEngine::Firefox)).is_none());
+
+        let families = [BrowserFamily::Safari, BrowserFamily::Edge];
+        assert_eq!(
+            BrowserEngine::Chromium.narrow(&families),
+            vec![BrowserFamily::Edge]
+        );
+        assert_eq!(
+            BrowserEngine::Firefox.narrow(&families),
+            vec![BrowserFamily::Firefox]
+        );
+    }
+
+    #[test]
+    fn test_profiles_keep_their_fingerprint_seed() {
+        // Encrypted profiles are unpacked under `.open/` while in use
+        let seed = profile_seed(Path::new("/state/browser_profiles/shop-de"));
+        assert_eq!(
+            profile_seed(Path::new("/state/browser_profiles/.open/shop-de")),
+            seed
+        );
+        assert_ne!(
+            profile_seed(Path::new("/state/browser_profiles/bank")),
+            seed
+        );
+    }
+
+    #[test]
+    fn test_profile_dir_capabilities() {
+        let dir = Path::new("/var/lib/swoop/browser_profiles/shop");
+        let chrome = with_profile_dir(&BrowserConfig::default().capabilities, dir);
+        let args = chrome["goog:chromeOptions"]["args"].as_array().unwrap();
+        assert!(args.contains(&"--headless=new".into()));
+        assert_eq!(
+            args.last().unwrap(),
+            "--user-data-dir=/var/lib/swoop/browser_profiles/shop"
+        );
+
+        let firefox = with_profile_dir(&serde_json::json!({"browserName": "firefox"}), dir);
+        assert_eq!(
+            firefox["moz:firefoxOptions"]["args"],
+            serde_json::json!(["-profile", "/var/lib/swoop/browser_profiles/shop"])
+        );
+    }
+
     #[test]
     fn test_page_action_serialization() {
         let action = PageAction::Click {
@@ -283,6 +1442,78 @@ mod tests {
         };
 
         let pool = BrowserPool::new(config);
-        assert_eq!(pool.semaphore.available_permits(), 2);
+        assert_eq!(pool.available(), 2);
+
+        pool.throttle().set_limit(1);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn test_target_sessions_follow_demand() {
+        let config = BrowserConfig {
+            max_instances: 4,
+            min_instances: 1,
+            warm_standby: 1,
+            ..Default::default()
+        };
+        assert_eq!(target_sessions(&config, 0, 4), 1);
+        assert_eq!(target_sessions(&config, 2, 4), 3);
+        assert_eq!(target_sessions(&config, 10, 4), 4);
+        // A lowered throttle caps the minimum too
+        assert_eq!(target_sessions(&config, 10, 2), 2);
+        let config = BrowserConfig {
+            min_instances: 3,
+            ..config
+        };
+        assert_eq!(target_sessions(&config, 0, 2), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queued_pages_count_towards_the_target() {
+        let pool = BrowserPool::new(BrowserConfig {
+            max_instances: 3,
+            ..Default::default()
+        });
+        assert_eq!(pool.stats().target, 0);
+
+        let queued: Vec<_> = (0..5).map(|_| pool.queue_page()).collect();
+        let stats = pool.stats();
+        assert_eq!(stats.queued, 5);
+        assert_eq!(stats.target, 3);
+
+        drop(queued);
+        assert_eq!(pool.stats().queued, 0);
+        // Nothing to open or close while there is no demand
+        pool.autoscale().await;
+        assert_eq!(pool.stats().open, 0);
+    }
+
+    #[test]
+    fn test_browser_config_autoscaling_defaults() {
+        let config: BrowserConfig = serde_json::from_value(serde_json::json!({
+            "max_instances": 2,
+            "page_timeout_secs": 30,
+            "webdriver_url": "http://localhost:4444",
+            "headless": true,
+            "user_agent": null,
+            "window_size": [800, 600],
+            "capabilities": {}
+        }))
+        .unwrap();
+        assert_eq!(config.min_instances, 0);
+        assert_eq!(config.warm_standby, 0);
+        assert_eq!(config.idle_timeout_secs, 60);
+    }
+
+    #[test]
+    fn test_webdriver_status_parsing() {
+        let body = r#"{"value":{"ready":false,"message":"Session already started","build":{"version":"120"}}}"#;
+        let response: StatusResponse = serde_json::from_str(body).unwrap();
+        assert!(!response.value.ready);
+        assert_eq!(response.value.message, "Session already started");
+
+        // Some drivers leave the message out
+        let response: StatusResponse = serde_json::from_str(r#"{"value":{"ready":true}}"#).unwrap();
+        assert!(response.value.ready);
     }
 }
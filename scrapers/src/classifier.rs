@@ -0,0 +1,449 @@
+//! Page classification
+//!
+//! Assigns coarse page types (article, product, listing, login wall, error page)
+//! so results can be tagged, filtered downstream and junk pages skipped in
+//! recurring crawls. Classification is rule-based on cheap [`PageSignals`]; an
+//! optional [`ClassificationModel`] can contribute additional scores.
+
+use crate::{
+    enrichment::{ProcessFuture, Processor},
+    ExtractedContent,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{collections::HashMap, sync::Arc};
+
+static PASSWORD_INPUT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)<input[^>]*type\s*=\s*["']?password"#).unwrap());
+static FORM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<form[\s>]").unwrap());
+static LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<a\s[^>]*href").unwrap());
+static PARAGRAPH_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<p[\s>]").unwrap());
+static ARTICLE_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<article[\s>]").unwrap());
+static LIST_ITEM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<li[\s>]").unwrap());
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+static OG_TYPE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)<meta[^>]*property\s*=\s*["']og:type["'][^>]*content\s*=\s*["']([^"']+)"#)
+        .unwrap()
+});
+static JSON_LD_TYPE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#""@type"\s*:\s*"([A-Za-z]+)""#).unwrap());
+static PRICE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:[$€£¥]\s?\d+(?:[.,]\d{2})?|\d+(?:[.,]\d{2})?\s?(?:USD|EUR|GBP))").unwrap()
+});
+
+/// Matches any of [`CART_PHRASES`] without lowercasing the whole text
+static CART_PHRASE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    let phrases: Vec<String> = CART_PHRASES.iter().map(|p| regex::escape(p)).collect();
+    Regex::new(&format!("(?i){}", phrases.join("|"))).unwrap()
+});
+
+const ERROR_PHRASES: &[&str] = &[
+    "page not found",
+    "404 not found",
+    "not found",
+    "access denied",
+    "forbidden",
+    "internal server error",
+    "service unavailable",
+    "something went wrong",
+    "this page isn't available",
+    "no longer available",
+];
+
+const LOGIN_PHRASES: &[&str] = &[
+    "sign in",
+    "log in",
+    "login",
+    "forgot password",
+    "create an account",
+    "sign in to continue",
+    "log in to continue",
+];
+
+const CART_PHRASES: &[&str] = &["add to cart", "add to basket", "buy now", "in stock"];
+
+/// Coarse page type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PageClass {
+    Article,
+    Product,
+    Listing,
+    LoginWall,
+    ErrorPage,
+}
+
+impl PageClass {
+    /// Tag stored on content, e.g. `login-wall`
+    pub fn as_tag(&self) -> &'static str {
+        match self {
+            PageClass::Article => "article",
+            PageClass::Product => "product",
+            PageClass::Listing => "listing",
+            PageClass::LoginWall => "login-wall",
+            PageClass::ErrorPage => "error-page",
+        }
+    }
+
+    /// Pages that are not worth storing or re-crawling
+    pub fn is_junk(&self) -> bool {
+        matches!(self, PageClass::LoginWall | PageClass::ErrorPage)
+    }
+}
+
+/// Cheap features the rules operate on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageSignals {
+    pub status_code: Option<u16>,
+    pub title: String,
+    pub word_count: usize,
+    pub link_count: usize,
+    pub paragraph_count: usize,
+    pub list_item_count: usize,
+    pub form_count: usize,
+    pub has_password_input: bool,
+    pub has_article_tag: bool,
+    pub price_mentions: usize,
+    pub has_cart_phrase: bool,
+    pub og_type: Option<String>,
+    pub schema_types: Vec<String>,
+}
+
+impl PageSignals {
+    /// Collect signals from raw HTML
+    pub fn from_html(html: &str, status_code: Option<u16>) -> Self {
+        let text = crate::extractors::extract_text_secure(html).unwrap_or_default();
+
+        Self {
+            status_code,
+            title: TITLE_REGEX
+                .captures(html)
+                .map(|c| c[1].trim().to_lowercase())
+                .unwrap_or_default(),
+            word_count: text.split_whitespace().count(),
+            link_count: LINK_REGEX.find_iter(html).count(),
+            paragraph_count: PARAGRAPH_REGEX.find_iter(html).count(),
+            list_item_count: LIST_ITEM_REGEX.find_iter(html).count(),
+            form_count: FORM_REGEX.find_iter(html).count(),
+            has_password_input: PASSWORD_INPUT_REGEX.is_match(html),
+            has_article_tag: ARTICLE_TAG_REGEX.is_match(html),
+            price_mentions: PRICE_REGEX.find_iter(&text).count(),
+            has_cart_phrase: CART_PHRASE_REGEX.is_match(&text),
+            og_type: OG_TYPE_REGEX.captures(html).map(|c| c[1].to_lowercase()),
+            schema_types: JSON_LD_TYPE_REGEX
+                .captures_iter(html)
+                .map(|c| c[1].to_string())
+                .collect(),
+        }
+    }
+
+    /// Collect the subset of signals available after extraction
+    pub fn from_content(content: &ExtractedContent) -> Self {
+        let text = content.text.as_deref().unwrap_or_default();
+
+        Self {
+            status_code: None,
+            title: content.title.as_deref().unwrap_or_default().to_lowercase(),
+            word_count: text.split_whitespace().count(),
+            price_mentions: PRICE_REGEX.find_iter(text).count(),
+            has_cart_phrase: CART_PHRASE_REGEX.is_match(text),
+            og_type: content.metadata.get("og:type").map(|t| t.to_lowercase()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Optional learned model whose scores are combined with the rules
+pub trait ClassificationModel: Send + Sync {
+    /// Scores in `[0, 1]` per class
+    fn predict(&self, signals: &PageSignals) -> HashMap<PageClass, f32>;
+}
+
+/// Outcome of classifying a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    /// Classes whose score reached the threshold, best first
+    pub classes: Vec<(PageClass, f32)>,
+}
+
+impl Classification {
+    pub fn primary(&self) -> Option<PageClass> {
+        self.classes.first().map(|(class, _)| *class)
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.classes
+            .iter()
+            .map(|(class, _)| class.as_tag().to_string())
+            .collect()
+    }
+
+    pub fn is_junk(&self) -> bool {
+        self.classes.iter().any(|(class, _)| class.is_junk())
+    }
+}
+
+/// Rule-based page classifier
+#[derive(Clone)]
+pub struct ContentClassifier {
+    threshold: f32,
+    model: Option<Arc<dyn ClassificationModel>>,
+}
+
+impl ContentClassifier {
+    pub fn new() -> Self {
+        Self {
+            threshold: 0.5,
+            model: None,
+        }
+    }
+
+    /// Minimum score for a class to be assigned
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Combine rule scores with `model` (the higher score wins per class)
+    pub fn with_model(mut self, model: Arc<dyn ClassificationModel>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn classify_html(&self, html: &str, status_code: Option<u16>) -> Classification {
+        self.classify(&PageSignals::from_html(html, status_code))
+    }
+
+    pub fn classify(&self, signals: &PageSignals) -> Classification {
+        let mut scores = rule_scores(signals);
+        if let Some(model) = &self.model {
+            for (class, score) in model.predict(signals) {
+                let entry = scores.entry(class).or_insert(0.0);
+                *entry = entry.max(score);
+            }
+        }
+
+        let mut classes: Vec<(PageClass, f32)> = scores
+            .into_iter()
+            .filter(|(_, score)| *score >= self.threshold)
+            .collect();
+        classes.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        Classification { classes }
+    }
+}
+
+impl Default for ContentClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn rule_scores(signals: &PageSignals) -> HashMap<PageClass, f32> {
+    let mut scores = HashMap::new();
+    let schema_has = |types: &[&str]| {
+        signals
+            .schema_types
+            .iter()
+            .any(|t| types.contains(&t.as_str()))
+    };
+    let og_is = |value: &str| signals.og_type.as_deref() == Some(value);
+
+    // Error pages: HTTP status or a short page whose title/text says so
+    let mut error: f32 = 0.0;
+    if signals.status_code.is_some_and(|status| status >= 400) {
+        error += 0.8;
+    }
+    if ERROR_PHRASES
+        .iter()
+        .any(|phrase| signals.title.contains(phrase))
+    {
+        error += 0.6;
+    }
+    if signals.word_count < 150 && error > 0.0 {
+        error += 0.1;
+    }
+    scores.insert(PageClass::ErrorPage, error.min(1.0));
+
+    // Login walls: a password field on a page with little other content
+    let mut login: f32 = 0.0;
+    if signals.has_password_input {
+        login += 0.6;
+    }
+    if LOGIN_PHRASES
+        .iter()
+        .any(|phrase| signals.title.contains(phrase))
+    {
+        login += 0.3;
+    }
+    if signals.has_password_input && signals.word_count < 300 {
+        login += 0.2;
+    }
+    scores.insert(PageClass::LoginWall, login.min(1.0));
+
+    // Products: structured data, prices and cart buttons
+    let mut product: f32 = 0.0;
+    if schema_has(&["Product", "Offer"]) || og_is("product") {
+        product += 0.6;
+    }
+    if signals.has_cart_phrase {
+        product += 0.3;
+    }
+    if (1..=5).contains(&signals.price_mentions) {
+        product += 0.2;
+    }
+    scores.insert(PageClass::Product, product.min(1.0));
+
+    // Listings: many links or list items relative to prose, or many prices
+    let mut listing: f32 = 0.0;
+    if schema_has(&["ItemList", "CollectionPage", "SearchResultsPage"]) {
+        listing += 0.6;
+    }
+    if signals.link_count >= 40 && signals.paragraph_count < signals.link_count / 8 {
+        listing += 0.4;
+    }
+    if signals.list_item_count >= 20 {
+        listing += 0.2;
+    }
+    if signals.price_mentions > 5 {
+        listing += 0.3;
+    }
+    scores.insert(PageClass::Listing, listing.min(1.0));
+
+    // Articles: long-form prose
+    let mut article: f32 = 0.0;
+    if schema_has(&["Article", "NewsArticle", "BlogPosting", "Report"]) || og_is("article") {
+        article += 0.6;
+    }
+    if signals.has_article_tag {
+        article += 0.2;
+    }
+    let prose = signals.word_count >= 300 && signals.paragraph_count >= 5;
+    let link_sparse = signals.word_count >= 600 && signals.link_count < signals.word_count / 20;
+    if prose || link_sparse {
+        article += 0.3;
+    }
+    scores.insert(PageClass::Article, article.min(1.0));
+
+    scores
+}
+
+impl Processor for ContentClassifier {
+    fn name(&self) -> &'static str {
+        "classifier"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let classification = self.classify(&PageSignals::from_content(content));
+            if let Some(primary) = classification.primary() {
+                content
+                    .metadata
+                    .insert("page_class".into(), primary.as_tag().to_string());
+            }
+            for tag in classification.tags() {
+                if !content.tags.contains(&tag) {
+                    content.tags.push(tag);
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_wall_and_error_page_are_junk() {
+        let classifier = ContentClassifier::new();
+
+        let login = classifier.classify_html(
+            r#"<html><head><title>Sign in to continue</title></head><body>
+               <form><input name="user"><input type="password" name="pw"></form></body></html>"#,
+            Some(200),
+        );
+        assert_eq!(login.primary(), Some(PageClass::LoginWall));
+        assert!(login.is_junk());
+
+        let missing = classifier.classify_html(
+            "<html><head><title>Page Not Found</title></head><body>Oops</body></html>",
+            Some(404),
+        );
+        assert_eq!(missing.primary(), Some(PageClass::ErrorPage));
+        assert_eq!(missing.tags(), vec!["error-page".to_string()]);
+    }
+
+    #[test]
+    fn test_product_and_article_pages() {
+        let classifier = ContentClassifier::new();
+
+        let product = classifier.classify_html(
+            r#"<html><head><script type="application/ld+json">{"@type": "Product"}</script></head>
+               <body><h1>Kettle</h1><p>$29.99</p><button>Add to cart</button></body></html>"#,
+            Some(200),
+        );
+        assert_eq!(product.primary(), Some(PageClass::Product));
+        assert!(!product.is_junk());
+
+        let paragraph = format!("<p>{}</p>", "word ".repeat(80));
+        let article = classifier.classify_html(
+            &format!(
+                r#"<html><head><meta property="og:type" content="article"></head>
+                   <body><article>{}</article></body></html>"#,
+                paragraph.repeat(6)
+            ),
+            Some(200),
+        );
+        assert_eq!(article.primary(), Some(PageClass::Article));
+    }
+
+    #[test]
+    fn test_listing_page() {
+        let items = (0..50)
+            .map(|i| format!(r#"<li><a href="/item/{}">Item {}</a></li>"#, i, i))
+            .collect::<Vec<_>>()
+            .concat();
+        let listing = ContentClassifier::new().classify_html(
+            &format!("<html><body><ul>{}</ul></body></html>", items),
+            None,
+        );
+        assert_eq!(listing.primary(), Some(PageClass::Listing));
+    }
+
+    #[test]
+    fn test_model_scores_are_combined() {
+        struct AlwaysArticle;
+        impl ClassificationModel for AlwaysArticle {
+            fn predict(&self, _signals: &PageSignals) -> HashMap<PageClass, f32> {
+                HashMap::from([(PageClass::Article, 0.9)])
+            }
+        }
+
+        let classifier = ContentClassifier::new().with_model(Arc::new(AlwaysArticle));
+        let classification = classifier.classify(&PageSignals::default());
+        assert_eq!(classification.primary(), Some(PageClass::Article));
+    }
+
+    #[tokio::test]
+    async fn test_processor_tags_extracted_content() {
+        let mut content = ExtractedContent {
+            url: "https://shop.example.com/kettle".to_string(),
+            title: Some("Kettle".into()),
+            text: Some("Blue kettle for $19.99. Add to cart today.".into()),
+            metadata: HashMap::from([("og:type".into(), "product".to_string())]),
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        };
+        ContentClassifier::new()
+            .process(&mut content)
+            .await
+            .unwrap();
+
+        assert_eq!(content.tags, vec!["product".to_string()]);
+        assert_eq!(
+            content.metadata.get("page_class").map(String::as_str),
+            Some("product")
+        );
+    }
+}
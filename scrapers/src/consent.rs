@@ -0,0 +1,273 @@
+//! Cookie banner and GDPR popup dismissal for browser scrapes
+//!
+//! Known consent managers are handled by [`ConsentRule`]s that click their
+//! accept button; anything else falls back to a generic script that looks for
+//! an accept-style button inside cookie/consent containers. Leftover overlays
+//! are removed afterwards so screenshots and the extracted DOM show the page.
+
+use anyhow::Result;
+use fantoccini::{Client, Locator};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Button labels the generic clicker treats as "accept"
+const ACCEPT_LABELS: &[&str] = &[
+    "accept all",
+    "accept all cookies",
+    "accept cookies",
+    "allow all",
+    "allow all cookies",
+    "i accept",
+    "i agree",
+    "agree",
+    "accept",
+    "got it",
+    "ok",
+    "alle akzeptieren",
+    "tout accepter",
+    "aceptar todo",
+    "accetta tutto",
+];
+
+/// Elements the generic clicker searches for accept buttons
+const CONSENT_CONTAINERS: &str = "[id*='cookie' i], [class*='cookie' i], [id*='consent' i], \
+     [class*='consent' i], [id*='gdpr' i], [class*='gdpr' i], [aria-label*='cookie' i], \
+     [role='dialog']";
+
+/// Clickable elements considered inside a consent container
+const ACCEPT_BUTTONS: &str = "button, a[role='button'], input[type='button'], input[type='submit']";
+
+/// Selectors for consent containers removed after dismissal
+const OVERLAY_SELECTORS: &[&str] = &[
+    "#onetrust-consent-sdk",
+    "#CybotCookiebotDialog",
+    ".qc-cmp2-container",
+    "#didomi-host",
+    "[id^='sp_message_container']",
+    "#truste-consent-track",
+    "#usercentrics-root",
+    ".fc-consent-root",
+];
+
+/// How to dismiss one consent manager
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsentRule {
+    pub name: String,
+    /// Selector present when the banner is shown
+    pub container: String,
+    /// Accept buttons, tried in order
+    pub accept: Vec<String>,
+}
+
+impl ConsentRule {
+    pub fn new(name: &str, container: &str, accept: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            container: container.to_string(),
+            accept: accept.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Rules for widely deployed consent managers
+pub fn default_rules() -> Vec<ConsentRule> {
+    vec![
+        ConsentRule::new(
+            "onetrust",
+            "#onetrust-banner-sdk",
+            &[
+                "#onetrust-accept-btn-handler",
+                ".onetrust-close-btn-handler",
+            ],
+        ),
+        ConsentRule::new(
+            "cookiebot",
+            "#CybotCookiebotDialog",
+            &[
+                "#CybotCookiebotDialogBodyLevelButtonLevelOptinAllowAll",
+                "#CybotCookiebotDialogBodyButtonAccept",
+            ],
+        ),
+        ConsentRule::new(
+            "quantcast",
+            ".qc-cmp2-container",
+            &[".qc-cmp2-summary-buttons button[mode='primary']"],
+        ),
+        ConsentRule::new("didomi", "#didomi-host", &["#didomi-notice-agree-button"]),
+        ConsentRule::new(
+            "trustarc",
+            "#truste-consent-track",
+            &["#truste-consent-button"],
+        ),
+        ConsentRule::new(
+            "usercentrics",
+            "#usercentrics-root",
+            &["[data-testid='uc-accept-all-button']"],
+        ),
+        ConsentRule::new(
+            "funding-choices",
+            ".fc-consent-root",
+            &[".fc-cta-consent", ".fc-button.fc-cta-consent"],
+        ),
+        ConsentRule::new(
+            "google",
+            "form[action*='consent.google']",
+            &[
+                "button[aria-label*='Accept']",
+                "form[action*='consent'] button",
+            ],
+        ),
+    ]
+}
+
+/// Dismisses consent banners on a loaded page
+#[derive(Debug, Clone)]
+pub struct ConsentHandler {
+    rules: Vec<ConsentRule>,
+    generic: bool,
+    settle: Duration,
+}
+
+impl Default for ConsentHandler {
+    fn default() -> Self {
+        Self {
+            rules: default_rules(),
+            generic: true,
+            settle: Duration::from_millis(500),
+        }
+    }
+}
+
+impl ConsentHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule; custom rules are tried before the built-in ones
+    pub fn with_rule(mut self, rule: ConsentRule) -> Self {
+        self.rules.insert(0, rule);
+        self
+    }
+
+    /// Enable or disable the generic accept-button heuristic
+    pub fn with_generic(mut self, generic: bool) -> Self {
+        self.generic = generic;
+        self
+    }
+
+    /// Time to wait after clicking for the banner to animate away
+    pub fn with_settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    pub fn rules(&self) -> &[ConsentRule] {
+        &self.rules
+    }
+
+    /// Dismiss a banner if one is shown, returning the rule that handled it
+    pub async fn dismiss(&self, client: &Client) -> Result<Option<String>> {
+        let mut handled = None;
+
+        for rule in &self.rules {
+            if client.find(Locator::Css(&rule.container)).await.is_err() {
+                continue;
+            }
+            for selector in &rule.accept {
+                if let Ok(button) = client.find(Locator::Css(selector)).await {
+                    if button.click().await.is_ok() {
+                        handled = Some(rule.name.clone());
+                        break;
+                    }
+                }
+            }
+            if handled.is_some() {
+                break;
+            }
+        }
+
+        if handled.is_none() && self.generic {
+            let clicked = client.execute(&generic_accept_script(), vec![]).await?;
+            if clicked.as_bool().unwrap_or(false) {
+                handled = Some("generic".to_string());
+            }
+        }
+
+        if handled.is_some() {
+            tokio::time::sleep(self.settle).await;
+        }
+        client.execute(&remove_overlays_script(), vec![]).await?;
+        Ok(handled)
+    }
+}
+
+fn js_string_array(values: &[&str]) -> String {
+    serde_json::to_string(values).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Script that clicks the first accept-style button inside a consent container
+pub fn generic_accept_script() -> String {
+    format!(
+        r#"
+        const labels = {labels};
+        const containers = Array.from(document.querySelectorAll({containers}));
+        for (const container of containers) {{
+            const buttons = container.querySelectorAll({buttons});
+            for (const button of buttons) {{
+                const label = (button.innerText || button.value || "").trim().toLowerCase();
+                if (labels.includes(label) && button.offsetParent !== null) {{
+                    button.click();
+                    return true;
+                }}
+            }}
+        }}
+        return false;
+        "#,
+        labels = js_string_array(ACCEPT_LABELS),
+        containers = serde_json::Value::from(CONSENT_CONTAINERS),
+        buttons = serde_json::Value::from(ACCEPT_BUTTONS)
+    )
+}
+
+/// Script that removes known consent containers and unlocks page scrolling
+pub fn remove_overlays_script() -> String {
+    format!(
+        r#"
+        for (const selector of {selectors}) {{
+            document.querySelectorAll(selector).forEach((el) => el.remove());
+        }}
+        for (const el of [document.documentElement, document.body]) {{
+            if (el) {{ el.style.overflow = ""; el.classList.remove("modal-open", "no-scroll"); }}
+        }}
+        return true;
+        "#,
+        selectors = js_string_array(OVERLAY_SELECTORS)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_rules_take_precedence() {
+        let handler = ConsentHandler::new().with_rule(ConsentRule::new(
+            "site",
+            "#cookie-wall",
+            &["#cookie-wall .yes"],
+        ));
+        assert_eq!(handler.rules()[0].name, "site");
+        assert!(handler.rules().iter().any(|rule| rule.name == "onetrust"));
+    }
+
+    #[test]
+    fn test_scripts_embed_labels_and_selectors() {
+        let accept = generic_accept_script();
+        assert!(accept.contains("\"accept all\""));
+        assert!(accept.contains("return false;"));
+
+        let cleanup = remove_overlays_script();
+        assert!(cleanup.contains("#onetrust-consent-sdk"));
+        assert!(cleanup.contains("[id^='sp_message_container']"));
+    }
+}
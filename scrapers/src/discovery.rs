@@ -0,0 +1,307 @@
+//! Seed discovery from search results and sitemaps
+//!
+//! Builds a seed URL list for a topic from a search engine: Google result
+//! pages through [`SerpScraper`](crate::platforms::serp::SerpScraper) via
+//! [`from_serp`], or a JSON search API through [`SearchApi`], with the Brave
+//! Search API preset. [`parse_sitemap`] reads a site's own URL list from its
+//! sitemap. [`dedupe`] drops repeats and URLs that are already known,
+//! comparing normalized URLs.
+
+use crate::{extractors::decode_entities, platforms::serp::SerpResults, utils::normalize_url};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashSet, sync::OnceLock, time::Duration};
+use swoop_core::security::UrlValidator;
+
+/// Brave Search web endpoint
+pub const BRAVE_SEARCH_URL: &str =
+    "https://api.search.brave.com/res/v1/web/search?q={query}&count={count}&offset={page}";
+
+/// A discovered seed URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Seed {
+    pub url: String,
+    pub title: Option<String>,
+    /// 1-based rank in the search results
+    pub rank: usize,
+}
+
+/// Organic results of `results` as seeds, in rank order
+pub fn from_serp(results: &SerpResults) -> Vec<Seed> {
+    results
+        .organic()
+        .map(|result| Seed {
+            url: result.url.clone(),
+            title: Some(result.title.clone()),
+            rank: result.position as usize,
+        })
+        .collect()
+}
+
+/// Drop seeds that repeat an earlier one or appear in `known`, compared after
+/// [`normalize_url`]; returns the kept seeds and how many were dropped
+pub fn dedupe(seeds: Vec<Seed>, known: &HashSet<String>) -> (Vec<Seed>, usize) {
+    let total = seeds.len();
+    let mut seen = HashSet::new();
+    let kept: Vec<Seed> = seeds
+        .into_iter()
+        .filter(|seed| {
+            let normalized = normalize_url(&seed.url);
+            !known.contains(&normalized) && seen.insert(normalized)
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// A paged JSON search API
+///
+/// `endpoint` is a URL template: `{query}` is replaced with the encoded
+/// query, `{count}` with the page size and `{page}` with the page number
+/// counting from 0. Results are read from the array at `results_pointer`.
+#[derive(Clone)]
+pub struct SearchApi {
+    pub endpoint: String,
+    /// Header carrying the API key, and the key
+    pub auth_header: Option<(String, String)>,
+    /// JSON pointer to the result array, e.g. `/web/results`
+    pub results_pointer: String,
+    pub url_field: String,
+    pub title_field: String,
+    pub page_size: usize,
+    /// Most pages the API serves for one query
+    pub max_pages: usize,
+    pub timeout: Duration,
+    validator: UrlValidator,
+}
+
+impl SearchApi {
+    pub fn new(endpoint: &str, results_pointer: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            auth_header: None,
+            results_pointer: results_pointer.to_string(),
+            url_field: "url".to_string(),
+            title_field: "title".to_string(),
+            page_size: 20,
+            max_pages: 10,
+            timeout: Duration::from_secs(30),
+            validator: UrlValidator::default(),
+        }
+    }
+
+    /// The Brave Search API, authenticated with `api_key`
+    pub fn brave(api_key: &str) -> Self {
+        Self::new(BRAVE_SEARCH_URL, "/web/results").with_auth("X-Subscription-Token", api_key)
+    }
+
+    pub fn with_auth(mut self, header: &str, key: &str) -> Self {
+        self.auth_header = Some((header.to_string(), key.to_string()));
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    fn page_url(&self, query: &str, page: usize) -> String {
+        let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        self.endpoint
+            .replace("{query}", &encoded)
+            .replace("{count}", &self.page_size.to_string())
+            .replace("{page}", &page.to_string())
+    }
+
+    /// Up to `limit` results for `query`, fetching pages until one comes back
+    /// short or the API's page limit is reached
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<Seed>> {
+        if !self.endpoint.contains("{query}") {
+            bail!("Search API URL has no {{query}} placeholder");
+        }
+        let mut headers = std::collections::HashMap::new();
+        if let Some((name, key)) = &self.auth_header {
+            headers.insert(name.clone(), key.clone());
+        }
+        headers.insert("Accept".to_string(), "application/json".to_string());
+
+        let mut seeds = Vec::new();
+        for page in 0..self.max_pages {
+            if seeds.len() >= limit {
+                break;
+            }
+            let url = self.page_url(query, page);
+            let body = swoop_core::send_request_with(
+                &self.validator,
+                reqwest::Method::GET,
+                &url,
+                &headers,
+                None,
+                self.timeout,
+            )
+            .await
+            .with_context(|| format!("Search API request for page {} failed", page + 1))?;
+            let response: Value =
+                serde_json::from_slice(&body).context("Search API response is not JSON")?;
+            let results = response
+                .pointer(&self.results_pointer)
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for result in results {
+                let Some(url) = result.get(&self.url_field).and_then(Value::as_str) else {
+                    continue;
+                };
+                seeds.push(Seed {
+                    url: url.to_string(),
+                    title: result
+                        .get(&self.title_field)
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    rank: seeds.len() + 1,
+                });
+            }
+            // Pages without a `{page}` placeholder are all the same page
+            if results.len() < self.page_size || !self.endpoint.contains("{page}") {
+                break;
+            }
+        }
+        seeds.truncate(limit);
+        Ok(seeds)
+    }
+}
+
+/// `<loc>` entries of one sitemap file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sitemap {
+    /// Page URLs of a `<urlset>`
+    pub urls: Vec<String>,
+    /// Nested sitemaps of a `<sitemapindex>`
+    pub sitemaps: Vec<String>,
+}
+
+/// Read the page and nested sitemap URLs of a sitemap or sitemap index
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    let entry = ENTRY.get_or_init(|| {
+        Regex::new(
+            r"(?is)<(url|sitemap)\b[^>]*>.*?<loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</loc>",
+        )
+        .unwrap()
+    });
+    let mut sitemap = Sitemap::default();
+    for captures in entry.captures_iter(xml) {
+        let loc = decode_entities(captures[2].trim());
+        if loc.is_empty() {
+            continue;
+        }
+        if captures[1].eq_ignore_ascii_case("sitemap") {
+            sitemap.sitemaps.push(loc);
+        } else {
+            sitemap.urls.push(loc);
+        }
+    }
+    sitemap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_dedupe_against_known() {
+        let seed = |url: &str, rank| Seed {
+            url: url.to_string(),
+            title: None,
+            rank,
+        };
+        let known = HashSet::from([normalize_url("https://a.test/old")]);
+        let (kept, dropped) = dedupe(
+            vec![
+                seed("https://a.test/new?utm_source=x", 1),
+                seed("https://a.test/old#top", 2),
+                seed("https://a.test/new", 3),
+                seed("https://b.test/", 4),
+            ],
+            &known,
+        );
+        let urls: Vec<&str> = kept.iter().map(|seed| seed.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://a.test/new?utm_source=x", "https://b.test/"]
+        );
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn test_parse_sitemap_and_index() {
+        let urlset = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+                    xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+              <url><loc>https://a.test/one?x=1&amp;y=2</loc><lastmod>2024-01-01</lastmod></url>
+              <url>
+                <loc><![CDATA[ https://a.test/two ]]></loc>
+                <image:image><image:loc>https://a.test/two.png</image:loc></image:image>
+              </url>
+            </urlset>"#;
+        let sitemap = parse_sitemap(urlset);
+        assert_eq!(
+            sitemap.urls,
+            vec!["https://a.test/one?x=1&y=2", "https://a.test/two"]
+        );
+        assert!(sitemap.sitemaps.is_empty());
+
+        let index = r#"<sitemapindex><sitemap><loc>https://a.test/posts.xml</loc></sitemap></sitemapindex>"#;
+        assert_eq!(
+            parse_sitemap(index).sitemaps,
+            vec!["https://a.test/posts.xml"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_api_pages_until_short_page() {
+        let server = MockServer::start().await;
+        let results = |from: usize, count: usize| {
+            let results: Vec<Value> = (from..from + count)
+                .map(|i| json!({ "url": format!("https://r{}.test/", i), "title": format!("R{}", i) }))
+                .collect();
+            json!({ "web": { "results": results } })
+        };
+        for (page, count) in [(0, 2), (1, 1)] {
+            Mock::given(method("GET"))
+                .and(path("/search"))
+                .and(query_param("q", "rust crawlers"))
+                .and(query_param("offset", page.to_string()))
+                .and(header("X-Subscription-Token", "secret"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(results(page * 2, count)))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let mut api = SearchApi::brave("secret")
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()));
+        api.endpoint = format!(
+            "{}/search?q={{query}}&count={{count}}&offset={{page}}",
+            server.uri()
+        );
+        api.page_size = 2;
+
+        let seeds = api.search("rust crawlers", 10).await.unwrap();
+        let urls: Vec<&str> = seeds.iter().map(|seed| seed.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://r0.test/", "https://r1.test/", "https://r2.test/"]
+        );
+        assert_eq!(seeds[2].rank, 3);
+        assert_eq!(seeds[0].title.as_deref(), Some("R0"));
+    }
+}
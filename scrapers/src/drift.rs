@@ -0,0 +1,345 @@
+//! Extraction drift detection
+//!
+//! [`DriftMonitor`] tracks how often each extraction rule (a field a platform
+//! is expected to produce) matches. When a rule that historically matched most
+//! pages suddenly stops matching, it raises a `selector_broken` alert through
+//! the [`NotificationHub`] and tags affected results until the rule recovers.
+
+use crate::{
+    notify::{Alert, NotificationHub, Severity},
+    ExtractedContent,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+type RuleCheck = Arc<dyn Fn(&ExtractedContent) -> bool + Send + Sync>;
+
+/// Thresholds for declaring a rule broken
+#[derive(Debug, Clone)]
+pub struct DriftConfig {
+    /// Number of recent observations compared against history
+    pub window: usize,
+    /// Observations required in the history before alerting
+    pub min_history: u64,
+    /// Historical match rate above which a rule counts as healthy
+    pub healthy_rate: f64,
+    /// Drop in match rate that marks a healthy rule as broken
+    pub max_drop: f64,
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            window: 50,
+            min_history: 100,
+            healthy_rate: 0.9,
+            max_drop: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RuleStats {
+    history_total: u64,
+    history_matched: u64,
+    recent: VecDeque<bool>,
+    broken: bool,
+}
+
+impl RuleStats {
+    fn historical_rate(&self) -> Option<f64> {
+        (self.history_total > 0).then(|| self.history_matched as f64 / self.history_total as f64)
+    }
+
+    fn recent_rate(&self) -> Option<f64> {
+        let matched = self.recent.iter().filter(|matched| **matched).count();
+        (!self.recent.is_empty()).then(|| matched as f64 / self.recent.len() as f64)
+    }
+}
+
+/// Current health of one rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleHealth {
+    pub platform: String,
+    pub rule: String,
+    pub historical_rate: Option<f64>,
+    pub recent_rate: Option<f64>,
+    pub samples: u64,
+    pub broken: bool,
+}
+
+/// Tracks per-rule match rates and alerts on sudden drops
+pub struct DriftMonitor {
+    config: DriftConfig,
+    rules: HashMap<String, Vec<(String, RuleCheck)>>,
+    stats: Mutex<HashMap<String, HashMap<String, RuleStats>>>,
+    notifications: NotificationHub,
+}
+
+impl DriftMonitor {
+    pub fn new(config: DriftConfig) -> Self {
+        Self {
+            config,
+            rules: HashMap::new(),
+            stats: Mutex::new(HashMap::new()),
+            notifications: NotificationHub::new(),
+        }
+    }
+
+    pub fn with_notifications(mut self, notifications: NotificationHub) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    /// Track a rule evaluated against every result from `platform`
+    pub fn with_rule(
+        mut self,
+        platform: &str,
+        rule: &str,
+        check: impl Fn(&ExtractedContent) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.rules
+            .entry(platform.to_string())
+            .or_default()
+            .push((rule.to_string(), Arc::new(check)));
+        self
+    }
+
+    /// Track whether results from `platform` carry a non-empty metadata `key`
+    pub fn with_metadata_rule(self, platform: &str, key: &str) -> Self {
+        let key = key.to_string();
+        self.with_rule(platform, &key.clone(), move |content| {
+            content
+                .metadata
+                .get(key.as_str())
+                .is_some_and(|value| !value.trim().is_empty())
+        })
+    }
+
+    /// Record one observation, returning an alert if the rule just broke
+    pub fn record(&self, platform: &str, rule: &str, matched: bool) -> Option<Alert> {
+        let mut stats = self.stats.lock().unwrap();
+        let rule_stats = stats
+            .entry(platform.to_string())
+            .or_default()
+            .entry(rule.to_string())
+            .or_default();
+
+        rule_stats.recent.push_back(matched);
+        if rule_stats.recent.len() > self.config.window {
+            if let Some(oldest) = rule_stats.recent.pop_front() {
+                rule_stats.history_total += 1;
+                rule_stats.history_matched += u64::from(oldest);
+            }
+        }
+
+        let (Some(historical), Some(recent)) =
+            (rule_stats.historical_rate(), rule_stats.recent_rate())
+        else {
+            return None;
+        };
+        let drop = historical - recent;
+
+        if rule_stats.broken {
+            // Recover once the recent rate is back within half the allowed drop
+            if drop < self.config.max_drop / 2.0 {
+                rule_stats.broken = false;
+            }
+            return None;
+        }
+
+        let enough_data = rule_stats.history_total >= self.config.min_history
+            && rule_stats.recent.len() >= self.config.window;
+        if enough_data && historical >= self.config.healthy_rate && drop >= self.config.max_drop {
+            rule_stats.broken = true;
+            return Some(
+                Alert::new(
+                    "selector_broken",
+                    Severity::Warning,
+                    format!(
+                        "{} rule '{}' dropped from {:.0}% to {:.0}% of pages",
+                        platform,
+                        rule,
+                        historical * 100.0,
+                        recent * 100.0
+                    ),
+                )
+                .with_detail("platform", platform)
+                .with_detail("rule", rule)
+                .with_detail("historical_rate", format!("{:.3}", historical))
+                .with_detail("recent_rate", format!("{:.3}", recent)),
+            );
+        }
+        None
+    }
+
+    /// Evaluate the platform's rules on `content`, send alerts for newly broken
+    /// rules and tag the content with every rule currently broken
+    pub async fn observe(&self, platform: &str, content: &mut ExtractedContent) {
+        let alerts: Vec<Alert> = self
+            .rules
+            .get(platform)
+            .into_iter()
+            .flatten()
+            .filter_map(|(rule, check)| self.record(platform, rule, check(content)))
+            .collect();
+        for alert in &alerts {
+            self.notify(alert).await;
+        }
+
+        self.mark_broken(platform, content);
+    }
+
+    /// Send `alert` to the configured notifiers
+    pub async fn notify(&self, alert: &Alert) {
+        // Delivery problems must not fail the extraction
+        let _ = self.notifications.send(alert).await;
+    }
+
+    /// Tag `content` with `drift:<rule>` for each broken rule of `platform`
+    pub fn mark_broken(&self, platform: &str, content: &mut ExtractedContent) {
+        let stats = self.stats.lock().unwrap();
+        let Some(rules) = stats.get(platform) else {
+            return;
+        };
+
+        let mut broken: Vec<&str> = rules
+            .iter()
+            .filter(|(_, stats)| stats.broken)
+            .map(|(rule, _)| rule.as_str())
+            .collect();
+        if broken.is_empty() {
+            return;
+        }
+        broken.sort_unstable();
+        content
+            .tags
+            .extend(broken.iter().map(|rule| format!("drift:{}", rule)));
+        content
+            .metadata
+            .insert("drift_rules".into(), broken.join(","));
+    }
+
+    /// Health of every rule observed so far, sorted by platform and rule
+    pub fn health(&self) -> Vec<RuleHealth> {
+        let stats = self.stats.lock().unwrap();
+        let mut health: Vec<RuleHealth> = stats
+            .iter()
+            .flat_map(|(platform, rules)| {
+                rules.iter().map(move |(rule, stats)| RuleHealth {
+                    platform: platform.clone(),
+                    rule: rule.clone(),
+                    historical_rate: stats.historical_rate(),
+                    recent_rate: stats.recent_rate(),
+                    samples: stats.history_total + stats.recent.len() as u64,
+                    broken: stats.broken,
+                })
+            })
+            .collect();
+        health.sort_by(|a, b| (&a.platform, &a.rule).cmp(&(&b.platform, &b.rule)));
+        health
+    }
+
+    /// Restore match-rate history saved by [`DriftMonitor::save_state`]
+    pub fn load_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(());
+        }
+        let state = serde_json::from_slice(&std::fs::read(path)?)?;
+        *self.stats.lock().unwrap() = state;
+        Ok(())
+    }
+
+    /// Persist match-rate history so drift is tracked across runs
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(&*self.stats.lock().unwrap())?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+impl Default for DriftMonitor {
+    fn default() -> Self {
+        Self::new(DriftConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::CallbackNotifier;
+
+    fn content(price: Option<&str>) -> ExtractedContent {
+        ExtractedContent {
+            url: "https://shop.example.com/item".to_string(),
+            title: None,
+            text: None,
+            metadata: price
+                .map(|price| HashMap::from([("price".into(), price.to_string())]))
+                .unwrap_or_default(),
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        }
+    }
+
+    fn monitor(alerts: Arc<Mutex<Vec<Alert>>>) -> DriftMonitor {
+        DriftMonitor::new(DriftConfig {
+            window: 10,
+            min_history: 20,
+            ..DriftConfig::default()
+        })
+        .with_notifications(NotificationHub::new().with_notifier(CallbackNotifier::new(
+            move |alert: &Alert| alerts.lock().unwrap().push(alert.clone()),
+        )))
+        .with_metadata_rule("shop", "price")
+    }
+
+    #[tokio::test]
+    async fn test_alerts_once_when_rule_breaks_and_recovers() {
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let monitor = monitor(alerts.clone());
+
+        for _ in 0..30 {
+            monitor.observe("shop", &mut content(Some("9.99"))).await;
+        }
+        assert!(alerts.lock().unwrap().is_empty());
+
+        let mut last = content(None);
+        for _ in 0..10 {
+            last = content(None);
+            monitor.observe("shop", &mut last).await;
+        }
+        assert_eq!(alerts.lock().unwrap().len(), 1);
+        assert_eq!(alerts.lock().unwrap()[0].kind, "selector_broken");
+        assert!(last.tags.contains(&"drift:price".to_string()));
+        assert!(monitor.health()[0].broken);
+
+        for _ in 0..10 {
+            monitor.observe("shop", &mut content(Some("9.99"))).await;
+        }
+        assert!(!monitor.health()[0].broken);
+        assert_eq!(alerts.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("drift.json");
+
+        let monitor = DriftMonitor::default();
+        for _ in 0..5 {
+            monitor.record("shop", "price", true);
+        }
+        monitor.save_state(&path).unwrap();
+
+        let restored = DriftMonitor::default();
+        restored.load_state(&path).unwrap();
+        assert_eq!(restored.health(), monitor.health());
+    }
+}
@@ -0,0 +1,548 @@
+//! Content enrichment pipeline
+//!
+//! After extraction, an [`EnrichmentPipeline`] runs a sequence of [`Processor`]s
+//! over each [`ExtractedContent`]. Processors append to the content's metadata
+//! and tags before it is stored. Built-in processors:
+//! - [`KeywordExtractor`] - frequent non-stopword terms (`keywords`)
+//! - [`SummaryHook`] - pluggable summarizer, lead sentences by default (`summary`)
+//! - [`EntityExtractor`] - named entities from an external HTTP API (`entities`)
+//! - [`SentimentTagger`] - lexicon-based sentiment (`sentiment_score`, `sentiment:*` tag)
+//!
+//! Each platform can use its own processor chain; platforms without one fall
+//! back to the default chain.
+
+use crate::ExtractedContent;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Boxed future returned by [`Processor::process`]
+pub type ProcessFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// A single enrichment step
+pub trait Processor: Send + Sync {
+    /// Name used in error metadata
+    fn name(&self) -> &'static str;
+
+    /// Enrich `content` in place
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a>;
+}
+
+fn add_tag(content: &mut ExtractedContent, tag: String) {
+    if !content.tags.contains(&tag) {
+        content.tags.push(tag);
+    }
+}
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has", "have", "he",
+    "her", "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "more", "most", "my",
+    "no", "not", "of", "on", "one", "or", "our", "out", "over", "she", "so", "some", "than",
+    "that", "the", "their", "them", "then", "there", "these", "they", "this", "to", "up", "us",
+    "was", "we", "were", "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// Extracts the most frequent non-stopword terms
+pub struct KeywordExtractor {
+    max_keywords: usize,
+    min_length: usize,
+}
+
+impl KeywordExtractor {
+    pub fn new(max_keywords: usize) -> Self {
+        Self {
+            max_keywords,
+            min_length: 3,
+        }
+    }
+
+    /// Ignore terms shorter than `min_length` characters
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    fn keywords(&self, text: &str) -> Vec<String> {
+        let stopwords: HashSet<&str> = STOPWORDS.iter().copied().collect();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .filter(|word| word.chars().count() >= self.min_length)
+            .filter(|word| !stopwords.contains(word.as_str()))
+            .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+        {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(self.max_keywords)
+            .map(|(word, _)| word)
+            .collect()
+    }
+}
+
+impl Default for KeywordExtractor {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl Processor for KeywordExtractor {
+    fn name(&self) -> &'static str {
+        "keywords"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let text = format!(
+                "{} {}",
+                content.title.as_deref().unwrap_or_default(),
+                content.text.as_deref().unwrap_or_default()
+            );
+            let keywords = self.keywords(&text);
+            if !keywords.is_empty() {
+                content
+                    .metadata
+                    .insert("keywords".into(), keywords.join(","));
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Summarizer callback used by [`SummaryHook`]
+pub type SummarizeFn = dyn Fn(&ExtractedContent) -> Option<String> + Send + Sync;
+
+/// Stores a summary produced by a pluggable summarizer
+pub struct SummaryHook {
+    summarize: Arc<SummarizeFn>,
+}
+
+impl SummaryHook {
+    /// Use a custom summarizer, e.g. a call into an ML model
+    pub fn new(
+        summarize: impl Fn(&ExtractedContent) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            summarize: Arc::new(summarize),
+        }
+    }
+
+    /// Summarize as the first `count` sentences of the text
+    pub fn lead_sentences(count: usize) -> Self {
+        Self::new(move |content| {
+            let text = content.text.as_deref()?.trim();
+            let mut end = 0;
+            for (sentence, (index, c)) in text
+                .char_indices()
+                .filter(|(_, c)| matches!(c, '.' | '!' | '?'))
+                .enumerate()
+            {
+                end = index + c.len_utf8();
+                if sentence + 1 >= count {
+                    break;
+                }
+            }
+            let summary = if end == 0 { text } else { &text[..end] };
+            (!summary.is_empty()).then(|| summary.to_string())
+        })
+    }
+}
+
+impl Default for SummaryHook {
+    fn default() -> Self {
+        Self::lead_sentences(2)
+    }
+}
+
+impl Processor for SummaryHook {
+    fn name(&self) -> &'static str {
+        "summary"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            if let Some(summary) = (self.summarize)(content) {
+                content.metadata.insert("summary".into(), summary);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Entity returned by the external extraction API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub text: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EntityRequest<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityResponse {
+    entities: Vec<Entity>,
+}
+
+/// Extracts named entities through an external HTTP API
+///
+/// The endpoint receives `POST {"text": "..."}` and must answer with
+/// `{"entities": [{"text": "...", "type": "..."}]}`. Entities are stored as JSON
+/// under `entities` and each entity type is added as an `entity:<type>` tag.
+pub struct EntityExtractor {
+    endpoint: String,
+    api_key: Option<String>,
+    max_chars: usize,
+    client: reqwest::Client,
+}
+
+impl EntityExtractor {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api_key: None,
+            max_chars: 20_000,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Send `key` as a bearer token
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Only send the first `max_chars` characters of the text
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Request timeout for the API call
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        self
+    }
+}
+
+impl Processor for EntityExtractor {
+    fn name(&self) -> &'static str {
+        "entities"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let Some(text) = content.text.as_deref().filter(|text| !text.is_empty()) else {
+                return Ok(());
+            };
+            let text: String = text.chars().take(self.max_chars).collect();
+
+            let mut request = self
+                .client
+                .post(&self.endpoint)
+                .json(&EntityRequest { text: &text });
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response: EntityResponse = request.send().await?.error_for_status()?.json().await?;
+
+            for entity in &response.entities {
+                add_tag(content, format!("entity:{}", entity.kind.to_lowercase()));
+            }
+            content.metadata.insert(
+                "entities".into(),
+                serde_json::to_string(&response.entities)?,
+            );
+            Ok(())
+        })
+    }
+}
+
+const POSITIVE_WORDS: &[&str] = &[
+    "amazing",
+    "awesome",
+    "best",
+    "better",
+    "excellent",
+    "fantastic",
+    "favorite",
+    "good",
+    "great",
+    "happy",
+    "helpful",
+    "impressive",
+    "love",
+    "loved",
+    "nice",
+    "perfect",
+    "pleased",
+    "recommend",
+    "success",
+    "wonderful",
+];
+
+const NEGATIVE_WORDS: &[&str] = &[
+    "angry",
+    "awful",
+    "bad",
+    "broken",
+    "disappointed",
+    "disappointing",
+    "fail",
+    "failed",
+    "failure",
+    "hate",
+    "horrible",
+    "poor",
+    "problem",
+    "sad",
+    "scam",
+    "terrible",
+    "useless",
+    "waste",
+    "worse",
+    "worst",
+];
+
+/// Tags content as positive, negative or neutral using a word lexicon
+pub struct SentimentTagger {
+    threshold: f64,
+}
+
+impl SentimentTagger {
+    /// Scores at or beyond `±threshold` are tagged positive/negative
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold }
+    }
+
+    /// Score in `[-1, 1]`: (positive - negative) / (positive + negative)
+    pub fn score(text: &str) -> f64 {
+        let (mut positive, mut negative) = (0usize, 0usize);
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+        {
+            if POSITIVE_WORDS.contains(&word.as_str()) {
+                positive += 1;
+            } else if NEGATIVE_WORDS.contains(&word.as_str()) {
+                negative += 1;
+            }
+        }
+
+        match positive + negative {
+            0 => 0.0,
+            total => (positive as f64 - negative as f64) / total as f64,
+        }
+    }
+}
+
+impl Default for SentimentTagger {
+    fn default() -> Self {
+        Self::new(0.2)
+    }
+}
+
+impl Processor for SentimentTagger {
+    fn name(&self) -> &'static str {
+        "sentiment"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let Some(text) = content.text.as_deref() else {
+                return Ok(());
+            };
+            let score = Self::score(text);
+            let label = if score >= self.threshold {
+                "positive"
+            } else if score <= -self.threshold {
+                "negative"
+            } else {
+                "neutral"
+            };
+
+            content
+                .metadata
+                .insert("sentiment_score".into(), format!("{:.2}", score));
+            add_tag(content, format!("sentiment:{}", label));
+            Ok(())
+        })
+    }
+}
+
+/// Ordered processor chains, optionally specialized per platform
+#[derive(Clone, Default)]
+pub struct EnrichmentPipeline {
+    default: Vec<Arc<dyn Processor>>,
+    platforms: HashMap<String, Vec<Arc<dyn Processor>>>,
+}
+
+impl EnrichmentPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a processor to the default chain
+    pub fn with_processor(mut self, processor: impl Processor + 'static) -> Self {
+        self.default.push(Arc::new(processor));
+        self
+    }
+
+    /// Append a processor to the chain for `platform`, which replaces the default chain
+    pub fn with_platform_processor(
+        mut self,
+        platform: impl Into<String>,
+        processor: impl Processor + 'static,
+    ) -> Self {
+        self.platforms
+            .entry(platform.into())
+            .or_default()
+            .push(Arc::new(processor));
+        self
+    }
+
+    /// Processors that run for `platform`
+    pub fn processors_for(&self, platform: &str) -> &[Arc<dyn Processor>] {
+        self.platforms.get(platform).unwrap_or(&self.default)
+    }
+
+    /// Run the chain for `platform` over `content`
+    ///
+    /// A failing processor does not stop the chain; its error is recorded under
+    /// `enrichment_error.<name>` in the metadata.
+    pub async fn run(&self, platform: &str, content: &mut ExtractedContent) {
+        for processor in self.processors_for(platform) {
+            if let Err(e) = processor.process(content).await {
+                content.metadata.insert(
+                    format!("enrichment_error.{}", processor.name()).into(),
+                    e.to_string(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn content(text: &str) -> ExtractedContent {
+        ExtractedContent {
+            url: "https://example.com/post".to_string(),
+            title: Some("Rust crawler release".into()),
+            text: Some(text.into()),
+            metadata: HashMap::new(),
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_chain_enriches_metadata_and_tags() {
+        let pipeline = EnrichmentPipeline::new()
+            .with_processor(KeywordExtractor::new(2))
+            .with_processor(SummaryHook::lead_sentences(1))
+            .with_processor(SentimentTagger::default());
+
+        let mut content =
+            content("The crawler is great. The crawler handles retries and the crawler is fast!");
+        pipeline.run("generic", &mut content).await;
+
+        assert_eq!(
+            content.metadata.get("keywords").map(String::as_str),
+            Some("crawler,fast")
+        );
+        assert_eq!(
+            content.metadata.get("summary").map(String::as_str),
+            Some("The crawler is great.")
+        );
+        assert_eq!(
+            content.metadata.get("sentiment_score").map(String::as_str),
+            Some("1.00")
+        );
+        assert!(content.tags.contains(&"sentiment:positive".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_platform_chain_replaces_default() {
+        let pipeline = EnrichmentPipeline::new()
+            .with_processor(KeywordExtractor::default())
+            .with_platform_processor("linkedin", SentimentTagger::default());
+
+        let mut content = content("A terrible, awful experience.");
+        pipeline.run("linkedin", &mut content).await;
+
+        assert!(!content.metadata.contains_key("keywords"));
+        assert!(content.tags.contains(&"sentiment:negative".to_string()));
+        assert_eq!(pipeline.processors_for("facebook").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_entity_extractor_calls_external_api() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entities": [
+                    { "text": "Mozilla", "type": "ORG" },
+                    { "text": "Berlin", "type": "LOC" }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let pipeline = EnrichmentPipeline::new()
+            .with_processor(EntityExtractor::new(server.uri()).with_api_key("secret"));
+        let mut content = content("Mozilla opened an office in Berlin.");
+        pipeline.run("generic", &mut content).await;
+
+        assert!(content.tags.contains(&"entity:org".to_string()));
+        assert!(content.tags.contains(&"entity:loc".to_string()));
+        let entities: Vec<Entity> =
+            serde_json::from_str(content.metadata.get("entities").unwrap()).unwrap();
+        assert_eq!(entities.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failing_processor_is_recorded_and_chain_continues() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let pipeline = EnrichmentPipeline::new()
+            .with_processor(EntityExtractor::new(server.uri()))
+            .with_processor(SentimentTagger::default());
+        let mut content = content("Nothing special here.");
+        pipeline.run("generic", &mut content).await;
+
+        assert!(content.metadata.contains_key("enrichment_error.entities"));
+        assert!(content.tags.contains(&"sentiment:neutral".to_string()));
+    }
+}
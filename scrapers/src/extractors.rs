@@ -3,10 +3,12 @@
 //! This module provides utilities for extracting specific types of content
 //! from web pages, including text, metadata, and structured data.
 
+use crate::metadata::Metadata;
 use ammonia::{clean, Builder};
 use anyhow::Result;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde_json::Value;
 use std::collections::HashMap;
 
 // Pre-compiled regexes for performance
@@ -17,6 +19,9 @@ static HTML_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap(
 
 static WHITESPACE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
 
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
 /// Extract text content from HTML with comprehensive security measures
 pub fn extract_text_secure(html: &str) -> Result<String> {
     // Step 1: Basic HTML sanitization using ammonia
@@ -26,15 +31,15 @@ pub fn extract_text_secure(html: &str) -> Result<String> {
         .clean_content_tags(std::collections::HashSet::new())
         .strip_comments(true);
 
-    let sanitized = builder.clean(html).to_string();
-
-    // Step 2: Remove any remaining script/style content
-    let no_scripts = SCRIPT_STYLE_REGEX.replace_all(&sanitized, "");
+    // Script and style contents go first: with no content tags configured,
+    // ammonia keeps them as text once their tags are removed
+    let no_scripts = SCRIPT_STYLE_REGEX.replace_all(html, "");
+    let sanitized = builder.clean(&no_scripts).to_string();
 
-    // Step 3: Remove HTML tags (defensive measure)
-    let no_tags = HTML_TAG_REGEX.replace_all(&no_scripts, " ");
+    // Step 2: Remove HTML tags (defensive measure)
+    let no_tags = HTML_TAG_REGEX.replace_all(&sanitized, " ");
 
-    // Step 4: Normalize whitespace
+    // Step 3: Normalize whitespace
     let normalized = WHITESPACE_REGEX.replace_all(&no_tags, " ");
 
     Ok(normalized.trim().to_string())
@@ -42,24 +47,18 @@ pub fn extract_text_secure(html: &str) -> Result<String> {
 
 /// Extract the page title from HTML
 pub fn extract_title(html: &str) -> Result<Option<String>> {
-    if let Some(captures) = regex::Regex::new(r"(?i)<title[^>]*>(.*?)</title>")
-        .unwrap()
-        .captures(html)
-    {
-        if let Some(title) = captures.get(1) {
-            let title = title.as_str().trim();
-            if !title.is_empty() {
-                return Ok(Some(title.to_string()));
-            }
-        }
-    }
+    Ok(find_title(html).map(str::to_string))
+}
 
-    Ok(None)
+/// The page title, as a slice of `html`
+pub fn find_title(html: &str) -> Option<&str> {
+    let title = TITLE_REGEX.captures(html)?.get(1)?.as_str().trim();
+    (!title.is_empty()).then_some(title)
 }
 
 /// Extract meta tags from HTML with proper sanitization
-pub fn extract_metadata_secure(html: &str) -> Result<HashMap<String, String>> {
-    let mut metadata = HashMap::new();
+pub fn extract_metadata_secure(html: &str) -> Result<Metadata> {
+    let mut metadata = Metadata::new();
 
     // Safe regex patterns for meta tags - work on original HTML first
     let name_regex = Regex::new(
@@ -72,18 +71,18 @@ pub fn extract_metadata_secure(html: &str) -> Result<HashMap<String, String>> {
     for captures in name_regex.captures_iter(html) {
         if let (Some(name), Some(content)) = (captures.get(1), captures.get(2)) {
             let name_str = name.as_str().to_lowercase();
-            let content_str = content.as_str().to_string(); // Keep original content
+            let content_str = content.as_str(); // Keep original content
 
             // Validate metadata keys (only allow safe characters)
             if is_safe_metadata_key(&name_str) {
                 // Clean the content but preserve it
-                let cleaned_content = clean(&content_str);
+                let cleaned_content = clean(content_str);
                 if !cleaned_content.is_empty() {
-                    metadata.insert(name_str, cleaned_content);
+                    metadata.insert(name_str.into(), cleaned_content);
                 } else {
                     // If cleaning removes everything, use original but validate it's safe
-                    if is_safe_content(&content_str) {
-                        metadata.insert(name_str, content_str);
+                    if is_safe_content(content_str) {
+                        metadata.insert(name_str.into(), content_str.to_string());
                     }
                 }
             }
@@ -93,18 +92,18 @@ pub fn extract_metadata_secure(html: &str) -> Result<HashMap<String, String>> {
     for captures in property_regex.captures_iter(html) {
         if let (Some(property), Some(content)) = (captures.get(1), captures.get(2)) {
             let property_str = property.as_str().to_lowercase();
-            let content_str = content.as_str().to_string(); // Keep original content
+            let content_str = content.as_str(); // Keep original content
 
             // Validate metadata keys (only allow safe characters)
             if is_safe_metadata_key(&property_str) {
                 // Clean the content but preserve it
-                let cleaned_content = clean(&content_str);
+                let cleaned_content = clean(content_str);
                 if !cleaned_content.is_empty() {
-                    metadata.insert(property_str, cleaned_content);
+                    metadata.insert(property_str.into(), cleaned_content);
                 } else {
                     // If cleaning removes everything, use original but validate it's safe
-                    if is_safe_content(&content_str) {
-                        metadata.insert(property_str, content_str);
+                    if is_safe_content(content_str) {
+                        metadata.insert(property_str.into(), content_str.to_string());
                     }
                 }
             }
@@ -114,13 +113,13 @@ pub fn extract_metadata_secure(html: &str) -> Result<HashMap<String, String>> {
     Ok(metadata)
 }
 
-fn is_safe_metadata_key(key: &str) -> bool {
+pub(crate) fn is_safe_metadata_key(key: &str) -> bool {
     // Only allow alphanumeric, dash, underscore, colon
     key.chars()
         .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ':')
 }
 
-fn is_safe_content(content: &str) -> bool {
+pub(crate) fn is_safe_content(content: &str) -> bool {
     // Basic safety check for content - no script tags or dangerous patterns
     let content_lower = content.to_lowercase();
     !content_lower.contains("<script") 
@@ -130,6 +129,222 @@ fn is_safe_content(content: &str) -> bool {
         && content.len() < 1000 // Reasonable length limit
 }
 
+static LINK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+
+static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap()
+});
+
+/// Extract the `<link rel="canonical">` URL, resolved against `base_url`
+///
+/// Only http(s) targets are returned.
+pub fn extract_canonical(html: &str, base_url: &str) -> Option<String> {
+    let base = url::Url::parse(base_url).ok()?;
+    LINK_TAG_REGEX.find_iter(html).find_map(|tag| {
+        let mut rel = None;
+        let mut href = None;
+        for captures in ATTRIBUTE_REGEX.captures_iter(tag.as_str()) {
+            let value = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .or_else(|| captures.get(4))
+                .map(|value| value.as_str().trim());
+            match captures[1].to_ascii_lowercase().as_str() {
+                "rel" => rel = value,
+                "href" => href = value,
+                _ => {}
+            }
+        }
+        let is_canonical = rel?
+            .split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("canonical"));
+        let canonical = base.join(href.filter(|href| !href.is_empty())?).ok()?;
+        (is_canonical && matches!(canonical.scheme(), "http" | "https"))
+            .then(|| canonical.to_string())
+    })
+}
+
+static OPEN_TAG_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<([a-z][a-z0-9]*)\b([^>]*)>").unwrap());
+
+static TAG_BOUNDARY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<(/?)([a-z][a-z0-9]*)\b[^>]*?(/?)>").unwrap());
+
+static JSON_LD_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<script\b[^>]*type\s*=\s*["']application/ld\+json["'][^>]*>(.*?)</script>"#)
+        .unwrap()
+});
+
+/// An opening tag with its lowercased attribute names
+pub(crate) struct HtmlTag {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    /// Byte offsets of the tag itself
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Every opening tag in `html`, in document order
+pub(crate) fn html_tags(html: &str) -> impl Iterator<Item = HtmlTag> + '_ {
+    OPEN_TAG_REGEX.captures_iter(html).map(|captures| {
+        let whole = captures.get(0).unwrap();
+        let attributes = ATTRIBUTE_REGEX
+            .captures_iter(&captures[2])
+            .filter_map(|attribute| {
+                let value = attribute
+                    .get(2)
+                    .or_else(|| attribute.get(3))
+                    .or_else(|| attribute.get(4))?;
+                Some((attribute[1].to_ascii_lowercase(), value.as_str().to_string()))
+            })
+            .collect();
+        HtmlTag {
+            name: captures[1].to_ascii_lowercase(),
+            attributes,
+            start: whole.start(),
+            end: whole.end(),
+        }
+    })
+}
+
+/// Inner HTML of the element opened by `tag`, up to its matching closing tag
+/// (or the end of the document when it is never closed)
+pub(crate) fn element_inner_html<'a>(html: &'a str, tag: &HtmlTag) -> &'a str {
+    let rest = &html[tag.end..];
+    let mut depth = 0usize;
+    for boundary in TAG_BOUNDARY_REGEX.captures_iter(rest) {
+        if !boundary[2].eq_ignore_ascii_case(&tag.name) || !boundary[3].is_empty() {
+            continue;
+        }
+        if boundary[1].is_empty() {
+            depth += 1;
+        } else if depth == 0 {
+            return &rest[..boundary.get(0).unwrap().start()];
+        } else {
+            depth -= 1;
+        }
+    }
+    rest
+}
+
+/// Decode the handful of entities common in attribute values and text
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+/// Plain text of an HTML fragment, keeping paragraph, list item and `<br>` breaks
+pub(crate) fn html_to_text(html: &str) -> String {
+    let html = html
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("</p>", "\n")
+        .replace("</li>", "\n");
+    let text = decode_entities(&HTML_TAG_REGEX.replace_all(&html, " "));
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse every `<script type="application/ld+json">` block, skipping invalid ones
+pub fn extract_json_ld(html: &str) -> Vec<Value> {
+    JSON_LD_REGEX
+        .captures_iter(html)
+        .filter_map(|captures| serde_json::from_str(captures[1].trim()).ok())
+        .collect()
+}
+
+/// First JSON-LD node whose `@type` is one of `types`, looking through arrays and `@graph`
+pub fn find_json_ld_node<'a>(
+    document: &'a Value,
+    types: &[&str],
+) -> Option<&'a Value> {
+    let has_type = |kind: &Value| {
+        kind.as_str()
+            .and_then(|kind| kind.rsplit('/').next())
+            .is_some_and(|kind| types.contains(&kind))
+    };
+    match document {
+        Value::Array(values) => values
+            .iter()
+            .find_map(|value| find_json_ld_node(value, types)),
+        Value::Object(object) => {
+            let matches = match object.get("@type") {
+                Some(Value::Array(kinds)) => kinds.iter().any(has_type),
+                Some(kind) => has_type(kind),
+                None => false,
+            };
+            if matches {
+                Some(document)
+            } else {
+                object
+                    .get("@graph")
+                    .and_then(|graph| find_json_ld_node(graph, types))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// First value of `key` anywhere under `value`, searching depth-first
+pub fn find_json_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(object) => object
+            .get(key)
+            .or_else(|| object.values().find_map(|value| find_json_key(value, key))),
+        Value::Array(values) => values.iter().find_map(|value| find_json_key(value, key)),
+        _ => None,
+    }
+}
+
+/// Text of a JSON-LD value: a string, a number, the first usable array item or an
+/// object's `name`
+pub fn json_ld_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => {
+            let text = decode_entities(text.trim());
+            (!text.is_empty()).then_some(text)
+        }
+        Value::Number(number) => Some(number.to_string()),
+        Value::Array(values) => values.iter().find_map(json_ld_text),
+        Value::Object(object) => object.get("name").and_then(json_ld_text),
+        _ => None,
+    }
+}
+
+/// Image URLs of a JSON-LD `image` value: URLs, lists or `ImageObject`s
+pub fn json_ld_images(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(_) => json_ld_text(value).into_iter().collect(),
+        Value::Array(values) => values.iter().flat_map(json_ld_images).collect(),
+        Value::Object(object) => object
+            .get("url")
+            .or_else(|| object.get("contentUrl"))
+            .map(json_ld_images)
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// `locality, region, country` of a JSON-LD `PostalAddress` (or the first of a list)
+pub fn json_ld_address(address: Option<&Value>) -> Option<String> {
+    let address = match address? {
+        Value::Array(addresses) => addresses.first()?,
+        address => address,
+    };
+    let parts: Vec<String> = ["addressLocality", "addressRegion", "addressCountry"]
+        .iter()
+        .filter_map(|key| address.get(*key).and_then(json_ld_text))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
 /// Extract links from HTML
 pub fn extract_links(html: &str) -> Result<Vec<String>> {
     let mut links = Vec::new();
@@ -158,10 +373,128 @@ pub fn extract_images(html: &str) -> Result<Vec<String>> {
     Ok(images)
 }
 
+/// Characters kept in a [`PageSummary`] excerpt
+pub const EXCERPT_CHARS: usize = 280;
+
+/// Quick facts about a fetched body, for engines that keep the raw page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageSummary {
+    pub title: Option<String>,
+    /// `<link rel="canonical">`, resolved against the page URL
+    pub canonical_url: Option<String>,
+    /// Declared media type without parameters, or sniffed from the body
+    pub content_type: String,
+    /// Start of the text, cut at a word boundary
+    pub excerpt: Option<String>,
+}
+
+/// Run the cheap extractors over a fetched `body`
+///
+/// `declared_type` is the response's `Content-Type`, if any. Title and
+/// canonical URL are only looked for in HTML; an excerpt is kept for any text.
+pub fn summarize_page(body: &str, url: &str, declared_type: Option<&str>) -> PageSummary {
+    let content_type = detect_content_type(body, declared_type);
+    let is_html = matches!(content_type.as_str(), "text/html" | "application/xhtml+xml");
+    let text = if is_html {
+        decode_entities(&extract_text_secure(body).unwrap_or_default())
+    } else if content_type.starts_with("text/") {
+        WHITESPACE_REGEX.replace_all(body.trim(), " ").into_owned()
+    } else {
+        String::new()
+    };
+
+    PageSummary {
+        title: is_html
+            .then(|| extract_title(body).ok().flatten())
+            .flatten()
+            .map(|title| WHITESPACE_REGEX.replace_all(&decode_entities(&title), " ").trim().to_string())
+            .filter(|title| !title.is_empty()),
+        canonical_url: is_html.then(|| extract_canonical(body, url)).flatten(),
+        content_type,
+        excerpt: excerpt(&text, EXCERPT_CHARS),
+    }
+}
+
+/// Media type of a body: the declared one without parameters unless it is
+/// missing or generic, otherwise sniffed from the first bytes
+pub fn detect_content_type(body: &str, declared_type: Option<&str>) -> String {
+    let declared = declared_type
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty() && value != "application/octet-stream");
+    if let Some(declared) = declared {
+        return declared;
+    }
+
+    let start = body.trim_start_matches('\u{feff}').trim_start();
+    let head: String = start.chars().take(1024).collect::<String>().to_ascii_lowercase();
+    let sniffed = if head.starts_with("%pdf-") {
+        "application/pdf"
+    } else if head.contains('\0') {
+        "application/octet-stream"
+    } else if head.starts_with("<?xml") {
+        if head.contains("<rss") {
+            "application/rss+xml"
+        } else if head.contains("<feed") {
+            "application/atom+xml"
+        } else if head.contains("<html") {
+            "application/xhtml+xml"
+        } else {
+            "application/xml"
+        }
+    } else if head.starts_with("<!doctype html") || head.starts_with("<html") || head.contains("<body") {
+        "text/html"
+    } else if (head.starts_with('{') || head.starts_with('['))
+        && serde_json::from_str::<serde::de::IgnoredAny>(start).is_ok()
+    {
+        "application/json"
+    } else {
+        "text/plain"
+    };
+    sniffed.to_string()
+}
+
+/// The first `max_chars` characters of `text`, cut back to a word boundary
+fn excerpt(text: &str, max_chars: usize) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return Some(text.to_string());
+    };
+    let head = &text[..cut];
+    let head = head.rfind(char::is_whitespace).map_or(head, |space| &head[..space]);
+    Some(format!("{}…", head.trim_end()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_summarize_page() {
+        let html = r#"<!DOCTYPE html><html><head><title> Fish &amp; Chips
+            </title><link rel="canonical" href="/menu"></head>
+            <body><script>var x;</script><p>Crispy fish, thick chips.</p></body></html>"#;
+        let summary = summarize_page(html, "https://example.com/menu?ref=1", None);
+        assert_eq!(summary.title.as_deref(), Some("Fish & Chips"));
+        assert_eq!(summary.canonical_url.as_deref(), Some("https://example.com/menu"));
+        assert_eq!(summary.content_type, "text/html");
+        assert_eq!(summary.excerpt.as_deref(), Some("Fish & Chips Crispy fish, thick chips."));
+
+        let long = "word ".repeat(100);
+        let summary = summarize_page(&long, "https://example.com/a.txt", Some("text/plain; charset=utf-8"));
+        assert_eq!(summary.title, None);
+        let excerpt = summary.excerpt.unwrap();
+        assert!(excerpt.ends_with("word…") && excerpt.chars().count() <= EXCERPT_CHARS + 1);
+
+        assert_eq!(detect_content_type(r#"{"a": [1, 2]}"#, None), "application/json");
+        assert_eq!(detect_content_type("<?xml version=\"1.0\"?><rss>", None), "application/rss+xml");
+        assert_eq!(detect_content_type("%PDF-1.7", Some("application/octet-stream")), "application/pdf");
+        assert_eq!(detect_content_type("{not json", None), "text/plain");
+    }
+
     #[test]
     fn test_extract_title() {
         let html = r#"<html><head><title>Test Title</title></head><body></body></html>"#;
@@ -188,6 +521,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_extract_canonical() {
+        let html = r#"<head>
+            <link rel="stylesheet" href="/style.css">
+            <link href='/articles/42' rel='canonical'>
+        </head>"#;
+        assert_eq!(
+            extract_canonical(html, "https://example.com/a?utm_source=x").as_deref(),
+            Some("https://example.com/articles/42")
+        );
+        assert_eq!(
+            extract_canonical("<link rel=canonical href=javascript:alert(1)>", "https://e.com/"),
+            None
+        );
+        assert_eq!(extract_canonical("<p>no head</p>", "https://example.com/"), None);
+    }
+
     #[test]
     fn test_extract_links() {
         let html = r#"<html><body><a href="https://example.com">Link</a><a href="/relative">Relative</a></body></html>"#;
@@ -0,0 +1,355 @@
+//! Redirect- and canonical-aware URL deduplication
+//!
+//! [`Frontier`] decides whether a URL still needs fetching. URLs are compared
+//! after [`normalize_url`], and once a page has been fetched its redirect
+//! target and `<link rel="canonical">` are recorded as aliases of one
+//! canonical URL, so mirrors and tracking-redirect variants of a page that was
+//! already crawled are skipped instead of fetched again.
+//!
+//! The frontier also enforces per-host [`HostBudget`]s: once a host has used
+//! up its pages, bytes or time, its remaining URLs are refused with the
+//! budget that ran out, so one enormous site can't take over a crawl.
+
+use crate::utils::{extract_domain, normalize_url};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Limits on how much of a crawl one host may take; `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostBudget {
+    pub max_pages: Option<usize>,
+    /// Response bytes fetched from the host
+    pub max_bytes: Option<u64>,
+    /// Time since the host's first URL was claimed
+    pub max_duration: Option<Duration>,
+}
+
+impl HostBudget {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_pages.is_none() && self.max_bytes.is_none() && self.max_duration.is_none()
+    }
+
+    /// This budget, with limits it leaves unset taken from `fallback`
+    pub fn or(self, fallback: &HostBudget) -> HostBudget {
+        HostBudget {
+            max_pages: self.max_pages.or(fallback.max_pages),
+            max_bytes: self.max_bytes.or(fallback.max_bytes),
+            max_duration: self.max_duration.or(fallback.max_duration),
+        }
+    }
+}
+
+/// Which part of a [`HostBudget`] ran out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetExhausted {
+    Pages,
+    Bytes,
+    Duration,
+}
+
+impl BudgetExhausted {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pages => "pages",
+            Self::Bytes => "bytes",
+            Self::Duration => "duration",
+        }
+    }
+}
+
+impl fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Host budget exhausted ({})", self.as_str())
+    }
+}
+
+impl std::error::Error for BudgetExhausted {}
+
+/// Outcome of [`Frontier::claim`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// The URL is new and should be fetched
+    New,
+    /// The same page was already claimed
+    Duplicate,
+    /// The URL's host has used up its budget
+    OverBudget(BudgetExhausted),
+}
+
+/// What one host used of its budget
+#[derive(Debug, Clone)]
+struct HostUsage {
+    pages: usize,
+    bytes: u64,
+    started: Instant,
+    exhausted: Option<BudgetExhausted>,
+    /// URLs refused once the budget ran out
+    refused: usize,
+}
+
+impl HostUsage {
+    fn new() -> Self {
+        Self {
+            pages: 0,
+            bytes: 0,
+            started: Instant::now(),
+            exhausted: None,
+            refused: 0,
+        }
+    }
+
+    /// Part of `budget` that is used up; stays set once reached
+    fn check(&mut self, budget: &HostBudget) -> Option<BudgetExhausted> {
+        if self.exhausted.is_none() {
+            self.exhausted = if budget.max_pages.is_some_and(|max| self.pages >= max) {
+                Some(BudgetExhausted::Pages)
+            } else if budget.max_bytes.is_some_and(|max| self.bytes >= max) {
+                Some(BudgetExhausted::Bytes)
+            } else if budget
+                .max_duration
+                .is_some_and(|max| self.started.elapsed() >= max)
+            {
+                Some(BudgetExhausted::Duration)
+            } else {
+                None
+            };
+        }
+        self.exhausted
+    }
+}
+
+/// A host whose budget ran out, as listed by [`Frontier::exhausted_hosts`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExhaustedHost {
+    pub host: String,
+    pub reason: BudgetExhausted,
+    pub pages: usize,
+    pub bytes: u64,
+    /// URLs refused once the budget ran out
+    pub refused: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Frontier {
+    /// Canonical URL of every normalized URL seen so far
+    canonical: HashMap<String, String>,
+    /// Canonical URLs already claimed, with the URL that claimed them
+    crawled: HashMap<String, String>,
+    /// Budget use per host
+    hosts: HashMap<String, HostUsage>,
+}
+
+impl Frontier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Canonical URL of `url` as far as is known
+    pub fn canonical_of(&self, url: &str) -> String {
+        let normalized = normalize_url(url);
+        self.canonical
+            .get(&normalized)
+            .cloned()
+            .unwrap_or(normalized)
+    }
+
+    /// Claim `url` for fetching
+    ///
+    /// Returns false if the same page, or a page it is known to redirect or
+    /// canonicalize to, has already been claimed.
+    pub fn insert(&mut self, url: &str) -> bool {
+        self.claim(url, &HostBudget::default()) == Claim::New
+    }
+
+    /// Claim `url` for fetching, charging a page to its host's `budget`
+    ///
+    /// Duplicates are reported before the budget is checked, so they never
+    /// count as refused.
+    pub fn claim(&mut self, url: &str, budget: &HostBudget) -> Claim {
+        let canonical = self.canonical_of(url);
+        if self.crawled.contains_key(&canonical) {
+            return Claim::Duplicate;
+        }
+        let usage = self
+            .hosts
+            .entry(extract_domain(url).unwrap_or_default())
+            .or_insert_with(HostUsage::new);
+        if let Some(reason) = usage.check(budget) {
+            usage.refused += 1;
+            return Claim::OverBudget(reason);
+        }
+        usage.pages += 1;
+
+        self.canonical
+            .entry(normalize_url(url))
+            .or_insert_with(|| canonical.clone());
+        self.crawled.insert(canonical, url.to_string());
+        Claim::New
+    }
+
+    /// Charge `bytes` fetched for `url` to its host
+    pub fn charge(&mut self, url: &str, bytes: u64) {
+        if let Some(usage) = self.hosts.get_mut(&extract_domain(url).unwrap_or_default()) {
+            usage.bytes += bytes;
+        }
+    }
+
+    /// Hosts whose budget ran out, by host name
+    pub fn exhausted_hosts(&self) -> Vec<ExhaustedHost> {
+        let mut hosts: Vec<ExhaustedHost> = self
+            .hosts
+            .iter()
+            .filter_map(|(host, usage)| {
+                Some(ExhaustedHost {
+                    host: host.clone(),
+                    reason: usage.exhausted?,
+                    pages: usage.pages,
+                    bytes: usage.bytes,
+                    refused: usage.refused,
+                })
+            })
+            .collect();
+        hosts.sort_by(|a, b| a.host.cmp(&b.host));
+        hosts
+    }
+
+    /// Record where a fetch of `url` ended up and the canonical URL it declared
+    ///
+    /// Returns the URL that already crawled the same canonical page, if this
+    /// fetch turned out to be a duplicate.
+    pub fn record(
+        &mut self,
+        url: &str,
+        final_url: Option<&str>,
+        canonical: Option<&str>,
+    ) -> Option<String> {
+        let target = self.canonical_of(canonical.or(final_url).unwrap_or(url));
+        for alias in [Some(url), final_url, canonical].into_iter().flatten() {
+            self.canonical.insert(normalize_url(alias), target.clone());
+        }
+
+        match self.crawled.get(&target) {
+            Some(first) if normalize_url(first) != normalize_url(url) => Some(first.clone()),
+            _ => {
+                self.crawled.insert(target, url.to_string());
+                None
+            }
+        }
+    }
+
+    /// URLs that map to a different canonical URL, for storing the mapping
+    pub fn aliases(&self) -> BTreeMap<String, String> {
+        self.canonical
+            .iter()
+            .filter(|(url, canonical)| url != canonical)
+            .map(|(url, canonical)| (url.clone(), canonical.clone()))
+            .collect()
+    }
+
+    /// Number of distinct pages claimed
+    pub fn len(&self) -> usize {
+        self.crawled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crawled.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_variants_are_claimed_once() {
+        let mut frontier = Frontier::new();
+        assert!(frontier.insert("https://example.com/a"));
+        assert!(!frontier.insert("https://EXAMPLE.com/a#comments"));
+        assert!(!frontier.insert("https://example.com/a?utm_source=feed"));
+        assert!(frontier.insert("https://example.com/b"));
+        assert_eq!(frontier.len(), 2);
+    }
+
+    #[test]
+    fn test_redirects_and_canonicals_become_aliases() {
+        let mut frontier = Frontier::new();
+        assert!(frontier.insert("https://t.co/xyz"));
+        let duplicate = frontier.record(
+            "https://t.co/xyz",
+            Some("https://example.com/post?ref=tw"),
+            Some("https://example.com/post"),
+        );
+        assert_eq!(duplicate, None);
+
+        // The redirect target and canonical are now known as crawled
+        assert!(!frontier.insert("https://example.com/post"));
+        assert!(!frontier.insert("https://example.com/post?ref=tw"));
+        assert_eq!(
+            frontier.canonical_of("https://t.co/xyz"),
+            "https://example.com/post"
+        );
+
+        // A mirror only reveals its canonical after it was fetched
+        assert!(frontier.insert("https://mirror.example.org/post"));
+        let duplicate = frontier.record(
+            "https://mirror.example.org/post",
+            None,
+            Some("https://example.com/post"),
+        );
+        assert_eq!(duplicate.as_deref(), Some("https://t.co/xyz"));
+        assert_eq!(frontier.aliases().len(), 3);
+    }
+
+    #[test]
+    fn test_host_budgets() {
+        let mut frontier = Frontier::new();
+        let budget = HostBudget {
+            max_pages: Some(2),
+            max_bytes: Some(1000),
+            ..HostBudget::default()
+        };
+        assert_eq!(frontier.claim("https://big.test/1", &budget), Claim::New);
+        assert_eq!(frontier.claim("https://big.test/2", &budget), Claim::New);
+        assert_eq!(
+            frontier.claim("https://big.test/3", &budget),
+            Claim::OverBudget(BudgetExhausted::Pages)
+        );
+        // Duplicates aren't refusals, and other hosts have their own budget
+        assert_eq!(
+            frontier.claim("https://big.test/1#x", &budget),
+            Claim::Duplicate
+        );
+        assert_eq!(frontier.claim("https://small.test/1", &budget), Claim::New);
+        frontier.charge("https://small.test/1", 1500);
+        assert_eq!(
+            frontier.claim("https://small.test/2", &budget),
+            Claim::OverBudget(BudgetExhausted::Bytes)
+        );
+
+        let exhausted = frontier.exhausted_hosts();
+        assert_eq!(exhausted.len(), 2);
+        assert_eq!(
+            (
+                exhausted[0].host.as_str(),
+                exhausted[0].reason,
+                exhausted[0].refused
+            ),
+            ("big.test", BudgetExhausted::Pages, 1)
+        );
+        assert_eq!(exhausted[1].bytes, 1500);
+
+        let timed = HostBudget {
+            max_duration: Some(Duration::ZERO),
+            ..HostBudget::default()
+        };
+        assert_eq!(
+            frontier.claim("https://slow.test/", &timed.or(&budget)),
+            Claim::OverBudget(BudgetExhausted::Duration)
+        );
+    }
+}
@@ -4,15 +4,48 @@
 //! and websites, implementing anti-bot evasion and rate limiting strategies.
 
 use anyhow::Result;
+use metadata::Metadata;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use swoop_core::text::SharedStr;
 
 pub mod anti_bot;
+pub mod api;
+pub mod article;
 pub mod browser;
+pub mod classifier;
+pub mod consent;
+pub mod discovery;
+pub mod drift;
+pub mod enrichment;
 pub mod extractors;
+pub mod frontier;
+pub mod llm;
+pub mod locale;
+pub mod metadata;
+pub mod mime;
+pub mod notify;
+pub mod offline;
+pub mod pii;
 pub mod platforms;
+pub mod plugins;
+pub mod policy;
+pub mod product;
+pub mod quality;
 pub mod rate_limiter;
+pub mod recipes;
+pub mod router;
+pub mod safety;
+pub mod sanitize;
+pub mod schemas;
+pub mod scripting;
+pub mod site_infra;
+pub mod site_profile;
+pub mod streaming;
+pub mod traps;
 pub mod utils;
+pub mod walls;
+pub mod wayback;
 
 /// Configuration for scraping operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,7 +56,8 @@ pub struct ScraperConfig {
     pub timeout_secs: u64,
     /// Rate limiting: requests per second
     pub rate_limit: f64,
-    /// User agent string to use
+    /// User agent string for requests that don't rotate through a
+    /// [`UserAgentPool`](anti_bot::user_agents::UserAgentPool)
     pub user_agent: String,
     /// Headers to include in requests
     pub headers: HashMap<String, String>,
@@ -44,8 +78,7 @@ impl Default for ScraperConfig {
             max_concurrent: 10,
             timeout_secs: 30,
             rate_limit: 1.0, // 1 request per second by default
-            user_agent: "Mozilla/5.0 (X11; Linux x86_64; rv:91.0) Gecko/20100101 Firefox/91.0"
-                .to_string(),
+            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36".to_string(),
             headers,
         }
     }
@@ -56,16 +89,71 @@ impl Default for ScraperConfig {
 pub struct ExtractedContent {
     /// URL where the content was found
     pub url: String,
-    /// Title of the content
-    pub title: Option<String>,
+    /// Title of the content, often a slice of the fetched page
+    pub title: Option<SharedStr>,
     /// Main text content
-    pub text: Option<String>,
+    pub text: Option<SharedStr>,
     /// Metadata about the content
-    pub metadata: HashMap<String, String>,
+    pub metadata: Metadata,
+    /// Tags for categorization, e.g. added by enrichment processors
+    #[serde(default)]
+    pub tags: Vec<String>,
     /// Timestamp when content was extracted
     pub extracted_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl ExtractedContent {
+    /// Media URLs a platform scraper recorded under [`schemas::POST_MEDIA_KEY`]
+    pub fn media_urls(&self) -> impl Iterator<Item = &str> {
+        self.metadata
+            .get(schemas::POST_MEDIA_KEY)
+            .into_iter()
+            .flat_map(|media| media.lines())
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+    }
+
+    /// Download every media URL into `dir`, one result per URL
+    pub async fn download_media(
+        &self,
+        downloader: &swoop_core::download::Downloader,
+        dir: &std::path::Path,
+    ) -> Vec<Result<swoop_core::download::DownloadReport>> {
+        let mut reports = Vec::new();
+        for url in self.media_urls() {
+            let dest = dir.join(swoop_core::download::file_name(url));
+            reports.push(downloader.download(url, &dest).await);
+        }
+        reports
+    }
+}
+
+/// How much a scraper depends on a resource
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Need {
+    #[default]
+    No,
+    /// Used when available, e.g. a browser fallback for blocked pages
+    Optional,
+    Required,
+}
+
+/// Resources a scraper needs for a URL, declared before it runs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScraperCapabilities {
+    pub browser: Need,
+    /// A logged-in session, cookies or stored credentials
+    pub auth: Need,
+    /// Proxy tier requests should go through, e.g. residential for sites
+    /// that block datacenter addresses
+    pub proxy_tier: Option<anti_bot::proxy_rotator::ProxyType>,
+    /// Country proxy exits should be in, as an ISO 3166-1 alpha-2 code
+    pub proxy_country: Option<String>,
+    /// Whether the scraper sends POST requests, e.g. logins or GraphQL
+    pub post: bool,
+}
+
 /// Trait for platform-specific scrapers
 pub trait PlatformScraper {
     /// Extract content from a URL
@@ -79,6 +167,38 @@ pub trait PlatformScraper {
 
     /// Get the platform name
     fn platform_name(&self) -> &'static str;
+
+    /// Resources extracting `url` needs, so callers can provide them up
+    /// front instead of the extraction failing halfway
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        ScraperCapabilities::default()
+    }
+
+    /// Extract content from already fetched HTML, if the scraper supports it
+    ///
+    /// `html` is the fetched page; fields sliced from it can share its buffer
+    /// through [`SharedStr::share`].
+    fn extract_from_html(&self, _url: &str, _html: &SharedStr) -> Option<Result<ExtractedContent>> {
+        None
+    }
+
+    /// GraphQL or JSON API calls that fetch the data behind `url`
+    ///
+    /// When this returns requests, [`platforms::ScraperRegistry`] runs them
+    /// and hands the responses to [`Self::extract_from_api`] instead of
+    /// calling [`Self::extract`].
+    fn api_requests(&self, _url: &str) -> Vec<api::ApiRequest> {
+        Vec::new()
+    }
+
+    /// Build content from the response pages of [`Self::api_requests`]
+    fn extract_from_api(
+        &self,
+        _url: &str,
+        _responses: &[serde_json::Value],
+    ) -> Option<Result<ExtractedContent>> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -99,13 +219,14 @@ mod tests {
     fn test_extracted_content_creation() {
         let content = ExtractedContent {
             url: "https://example.com".to_string(),
-            title: Some("Test Title".to_string()),
-            text: Some("Test content".to_string()),
+            title: Some("Test Title".into()),
+            text: Some("Test content".into()),
             metadata: HashMap::new(),
+            tags: Vec::new(),
             extracted_at: chrono::Utc::now(),
         };
 
         assert_eq!(content.url, "https://example.com");
-        assert_eq!(content.title, Some("Test Title".to_string()));
+        assert_eq!(content.title.as_deref(), Some("Test Title"));
     }
 }
@@ -0,0 +1,510 @@
+//! LLM-assisted extraction
+//!
+//! [`LlmExtractor`] is an enrichment [`Processor`] for sites where writing
+//! selectors isn't practical. It sends the cleaned page text together with a
+//! user-provided JSON schema to an OpenAI-compatible chat completions endpoint and
+//! stores the returned fields as `llm.<field>` metadata (plus the full object
+//! under `llm_extraction`).
+//!
+//! Calls are guarded by:
+//! - a cache keyed by a hash of model, schema and text, so unchanged pages are free
+//! - a shared [`TokenBudget`]; once spent, pages are skipped (`llm_skipped = budget`)
+//! - a strict per-request timeout and an input size cap
+
+use crate::{
+    enrichment::{ProcessFuture, Processor},
+    ExtractedContent,
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Rough characters-per-token ratio used for budgeting before the API reports usage
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Shared token allowance across all LLM calls
+#[derive(Debug)]
+pub struct TokenBudget {
+    limit: u64,
+    used: AtomicU64,
+}
+
+impl TokenBudget {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: AtomicU64::new(0),
+        }
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used())
+    }
+
+    fn charge(&self, tokens: u64) {
+        self.used.fetch_add(tokens, Ordering::Relaxed);
+    }
+}
+
+/// Connection and prompt settings for [`LlmExtractor`]
+#[derive(Debug, Clone)]
+pub struct LlmConfig {
+    /// Base URL of an OpenAI-compatible API, e.g. `https://api.openai.com/v1`
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    /// JSON schema describing the fields to extract
+    pub schema: Value,
+    /// Hard timeout for a single request
+    pub timeout: Duration,
+    /// Page text is truncated to roughly this many tokens
+    pub max_input_tokens: usize,
+    /// Upper bound on tokens generated per response
+    pub max_output_tokens: u32,
+}
+
+impl LlmConfig {
+    pub fn new(endpoint: impl Into<String>, model: impl Into<String>, schema: Value) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api_key: None,
+            model: model.into(),
+            schema,
+            timeout: Duration::from_secs(20),
+            max_input_tokens: 3_000,
+            max_output_tokens: 512,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_input_tokens(mut self, max_input_tokens: usize) -> Self {
+        self.max_input_tokens = max_input_tokens;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatUsage {
+    total_tokens: u64,
+}
+
+/// Structured field extraction through an LLM
+pub struct LlmExtractor {
+    config: LlmConfig,
+    client: reqwest::Client,
+    budget: Option<Arc<TokenBudget>>,
+    cache: Mutex<HashMap<String, Map<String, Value>>>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl LlmExtractor {
+    pub fn new(config: LlmConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to build LLM HTTP client")?;
+
+        Ok(Self {
+            config,
+            client,
+            budget: None,
+            cache: Mutex::new(HashMap::new()),
+            cache_dir: None,
+        })
+    }
+
+    /// Stop calling the API once `budget` is spent
+    pub fn with_budget(mut self, budget: Arc<TokenBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Persist cached responses as JSON files in `dir`, so they survive restarts
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Extract fields from `text`; returns `None` when the token budget is exhausted
+    pub async fn extract_fields(&self, text: &str) -> Result<Option<Map<String, Value>>> {
+        let text = self.truncate(text);
+        let key = self.cache_key(&text);
+        if let Some(fields) = self.cached(&key) {
+            return Ok(Some(fields));
+        }
+
+        let estimated =
+            (text.len() / CHARS_PER_TOKEN) as u64 + self.config.max_output_tokens as u64;
+        if let Some(budget) = &self.budget {
+            if budget.remaining() < estimated {
+                return Ok(None);
+            }
+        }
+
+        let response = self.request(&text).await?;
+        if let Some(budget) = &self.budget {
+            budget.charge(
+                response
+                    .usage
+                    .as_ref()
+                    .map_or(estimated, |usage| usage.total_tokens),
+            );
+        }
+
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| anyhow::anyhow!("LLM response has no content"))?;
+        let fields = self.parse_fields(&content)?;
+
+        self.store(&key, &fields);
+        Ok(Some(fields))
+    }
+
+    fn truncate(&self, text: &str) -> String {
+        text.chars()
+            .take(self.config.max_input_tokens * CHARS_PER_TOKEN)
+            .collect()
+    }
+
+    fn cache_key(&self, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.config.model.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.config.schema.to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn cached(&self, key: &str) -> Option<Map<String, Value>> {
+        if let Some(fields) = self.cache.lock().unwrap().get(key) {
+            return Some(fields.clone());
+        }
+
+        let path = self.cache_dir.as_ref()?.join(format!("{}.json", key));
+        let fields: Map<String, Value> = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), fields.clone());
+        Some(fields)
+    }
+
+    fn store(&self, key: &str, fields: &Map<String, Value>) {
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), fields.clone());
+
+        if let Some(dir) = &self.cache_dir {
+            // The cache is an optimization; failing to persist it is not an extraction error
+            let _ = std::fs::create_dir_all(dir).and_then(|_| {
+                std::fs::write(
+                    dir.join(format!("{}.json", key)),
+                    serde_json::to_vec(fields).unwrap_or_default(),
+                )
+            });
+        }
+    }
+
+    async fn request(&self, text: &str) -> Result<ChatResponse> {
+        let body = json!({
+            "model": self.config.model,
+            "temperature": 0,
+            "max_tokens": self.config.max_output_tokens,
+            "response_format": { "type": "json_object" },
+            "messages": [
+                {
+                    "role": "system",
+                    "content": format!(
+                        "Extract data from the web page text provided by the user. \
+                         Respond with a single JSON object that matches this JSON schema. \
+                         Use null for fields that are not present.\n\n{}",
+                        self.config.schema
+                    ),
+                },
+                { "role": "user", "content": text },
+            ],
+        });
+
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/chat/completions",
+                self.config.endpoint.trim_end_matches('/')
+            ))
+            .json(&body);
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                anyhow::anyhow!("LLM request timed out after {:?}", self.config.timeout)
+            } else {
+                e.into()
+            }
+        })?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    fn parse_fields(&self, content: &str) -> Result<Map<String, Value>> {
+        // Some models wrap JSON in a markdown code fence despite the response format
+        let content = content
+            .trim()
+            .trim_start_matches("```json")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+        let value: Value = serde_json::from_str(content).context("LLM returned invalid JSON")?;
+        let Value::Object(fields) = value else {
+            anyhow::bail!("LLM returned JSON that is not an object");
+        };
+
+        if let Some(required) = self.config.schema.get("required").and_then(Value::as_array) {
+            let missing: Vec<&str> = required
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|field| !fields.contains_key(*field))
+                .collect();
+            if !missing.is_empty() {
+                anyhow::bail!(
+                    "LLM response is missing required fields: {}",
+                    missing.join(", ")
+                );
+            }
+        }
+        Ok(fields)
+    }
+}
+
+impl Processor for LlmExtractor {
+    fn name(&self) -> &'static str {
+        "llm"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let Some(text) = content
+                .text
+                .as_deref()
+                .filter(|text| !text.trim().is_empty())
+            else {
+                return Ok(());
+            };
+
+            let Some(fields) = self.extract_fields(text).await? else {
+                content
+                    .metadata
+                    .insert("llm_skipped".into(), "budget".to_string());
+                return Ok(());
+            };
+
+            for (field, value) in &fields {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Null => continue,
+                    other => other.to_string(),
+                };
+                content
+                    .metadata
+                    .insert(format!("llm.{}", field).into(), value);
+            }
+            content
+                .metadata
+                .insert("llm_extraction".into(), Value::Object(fields).to_string());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment::EnrichmentPipeline;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "price": { "type": "number" },
+                "currency": { "type": "string" }
+            },
+            "required": ["price"]
+        })
+    }
+
+    fn completion(content: &str, tokens: u64) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": content } }],
+            "usage": { "total_tokens": tokens }
+        }))
+    }
+
+    fn page(text: &str) -> ExtractedContent {
+        ExtractedContent {
+            url: "https://shop.example.com/item".to_string(),
+            title: None,
+            text: Some(text.into()),
+            metadata: HashMap::new(),
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fields_stored_and_cached_by_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer key"))
+            .respond_with(completion(r#"{"price": 19.99, "currency": "EUR"}"#, 120))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let budget = Arc::new(TokenBudget::new(10_000));
+        let extractor = LlmExtractor::new(
+            LlmConfig::new(server.uri(), "test-model", schema()).with_api_key("key"),
+        )
+        .unwrap()
+        .with_budget(budget.clone());
+        let pipeline = EnrichmentPipeline::new().with_processor(extractor);
+
+        let mut first = page("Blue kettle, now only 19.99 EUR");
+        pipeline.run("generic", &mut first).await;
+        assert_eq!(
+            first.metadata.get("llm.price").map(String::as_str),
+            Some("19.99")
+        );
+        assert_eq!(
+            first.metadata.get("llm.currency").map(String::as_str),
+            Some("EUR")
+        );
+        assert_eq!(budget.used(), 120);
+
+        // Same text hits the cache; the mock verifies only one request was made
+        let mut second = page("Blue kettle, now only 19.99 EUR");
+        pipeline.run("generic", &mut second).await;
+        assert_eq!(
+            second.metadata.get("llm.price").map(String::as_str),
+            Some("19.99")
+        );
+        assert_eq!(budget.used(), 120);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_budget_skips_call() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(completion(r#"{"price": 1}"#, 10))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let extractor = LlmExtractor::new(LlmConfig::new(server.uri(), "test-model", schema()))
+            .unwrap()
+            .with_budget(Arc::new(TokenBudget::new(10)));
+        let mut content = page("Some product page text");
+        extractor.process(&mut content).await.unwrap();
+
+        assert_eq!(
+            content.metadata.get("llm_skipped").map(String::as_str),
+            Some("budget")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_invalid_output_are_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(completion(r#"{"price": 1}"#, 10).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+        let slow = LlmExtractor::new(
+            LlmConfig::new(server.uri(), "test-model", schema())
+                .with_timeout(Duration::from_millis(200)),
+        )
+        .unwrap();
+        let error = slow.extract_fields("text").await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+
+        let extractor =
+            LlmExtractor::new(LlmConfig::new("http://unused", "test-model", schema())).unwrap();
+        assert!(extractor
+            .parse_fields("```json\n{\"price\": 5}\n```")
+            .is_ok());
+        assert!(extractor.parse_fields(r#"{"currency": "EUR"}"#).is_err());
+        assert!(extractor.parse_fields("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_survives_new_extractor() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(completion(r#"{"price": 3}"#, 10))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+
+        for _ in 0..2 {
+            let extractor = LlmExtractor::new(LlmConfig::new(server.uri(), "test-model", schema()))
+                .unwrap()
+                .with_cache_dir(dir.path());
+            let fields = extractor
+                .extract_fields("cached text")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(fields.get("price"), Some(&json!(3)));
+        }
+    }
+}
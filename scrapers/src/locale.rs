@@ -0,0 +1,245 @@
+//! Locale emulation per target market
+//!
+//! A [`LocaleProfile`] describes the market a request should look like it
+//! comes from: the `Accept-Language` header it sends, the country a geo proxy
+//! is picked for, and how that market writes numbers and dates, so localized
+//! variants of a site (amazon.de rather than amazon.com) are requested and
+//! read consistently. [`served_language`] reports the language a page was
+//! actually served in, to catch sites that ignored the request.
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+static HTML_LANG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)<html\b[^>]*?\blang\s*=\s*["']?([a-z]{2,3}(?:[-_][a-z0-9]+)*)"#).unwrap()
+});
+
+/// Order of day, month and year in a market's numeric dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// 31.12.2024
+    Dmy,
+    /// 12/31/2024
+    Mdy,
+    /// 2024/12/31
+    Ymd,
+}
+
+/// How requests for one market are made and its pages read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocaleProfile {
+    /// BCP 47 tag, e.g. `de-DE`
+    pub tag: String,
+    pub accept_language: String,
+    /// ISO 3166 country code, lowercase, that geo proxies are looked up by
+    pub country: String,
+    /// ISO 4217 code prices are expected in
+    pub currency: Option<String>,
+    pub decimal_separator: char,
+    pub grouping_separator: char,
+    pub date_order: DateOrder,
+}
+
+impl LocaleProfile {
+    /// Tags with built-in profiles
+    pub const NAMES: &'static [&'static str] = &[
+        "en-US", "en-GB", "de-DE", "de-AT", "de-CH", "fr-FR", "es-ES", "es-MX", "it-IT", "nl-NL",
+        "pl-PL", "pt-BR", "sv-SE", "ja-JP", "en-IN", "en-AU", "en-CA", "fr-CA",
+    ];
+
+    /// Profile for a BCP 47 tag such as `de-DE`
+    ///
+    /// Known markets come with their currency and formats; other
+    /// `language-COUNTRY` tags get the language's common formats.
+    pub fn named(tag: &str) -> Result<Self> {
+        let valid =
+            |part: &str, len| part.len() == len && part.chars().all(|c| c.is_ascii_alphabetic());
+        let parts = tag.split_once(['-', '_']).filter(|(language, country)| {
+            (valid(language, 2) || valid(language, 3)) && valid(country, 2)
+        });
+        let Some((language, country)) = parts else {
+            bail!(
+                "Invalid locale '{}', expected language-COUNTRY such as de-DE",
+                tag
+            );
+        };
+        let language = language.to_ascii_lowercase();
+        let country = country.to_ascii_lowercase();
+        let tag = format!("{}-{}", language, country.to_ascii_uppercase());
+
+        let currency = match country.as_str() {
+            "us" => "USD",
+            "gb" => "GBP",
+            "de" | "at" | "fr" | "es" | "it" | "nl" | "ie" | "be" | "fi" | "pt" => "EUR",
+            "ch" => "CHF",
+            "mx" => "MXN",
+            "pl" => "PLN",
+            "br" => "BRL",
+            "se" => "SEK",
+            "jp" => "JPY",
+            "in" => "INR",
+            "au" => "AUD",
+            "ca" => "CAD",
+            _ => "",
+        };
+        let (decimal_separator, grouping_separator) = match (language.as_str(), country.as_str()) {
+            (_, "ch") => ('.', '\''),
+            ("en" | "ja" | "zh" | "ko" | "he" | "th", _) | ("es", "mx") => ('.', ','),
+            ("fr" | "sv" | "pl" | "fi" | "nb" | "cs" | "ru", _) => (',', ' '),
+            _ => (',', '.'),
+        };
+        let date_order = match (language.as_str(), country.as_str()) {
+            (_, "us") => DateOrder::Mdy,
+            ("ja" | "zh" | "ko" | "sv" | "hu" | "lt", _) | (_, "ca") => DateOrder::Ymd,
+            _ => DateOrder::Dmy,
+        };
+        let accept_language = if language == "en" {
+            format!("{},en;q=0.9", tag)
+        } else {
+            format!("{},{};q=0.9,en;q=0.5", tag, language)
+        };
+
+        Ok(Self {
+            tag,
+            accept_language,
+            country,
+            currency: (!currency.is_empty()).then(|| currency.to_string()),
+            decimal_separator,
+            grouping_separator,
+            date_order,
+        })
+    }
+
+    /// Language subtag, e.g. `de`
+    pub fn language(&self) -> &str {
+        self.tag.split('-').next().unwrap_or_default()
+    }
+
+    /// Set this market's `Accept-Language` on `headers`
+    pub fn apply(&self, headers: &mut HashMap<String, String>) {
+        headers.retain(|name, _| !name.eq_ignore_ascii_case("accept-language"));
+        headers.insert("Accept-Language".to_string(), self.accept_language.clone());
+    }
+
+    /// Whether a page served in `language` is in this market's language
+    pub fn matches_language(&self, language: &str) -> bool {
+        language
+            .split(['-', '_'])
+            .next()
+            .is_some_and(|served| served.eq_ignore_ascii_case(self.language()))
+    }
+
+    /// Parse a number written the market's way, e.g. `1.234,56` for de-DE;
+    /// currency symbols and other text around it are ignored
+    pub fn parse_number(&self, raw: &str) -> Option<f64> {
+        let normalized: String = raw
+            .chars()
+            // Narrow and non-breaking spaces group digits in several markets
+            .filter(|c| {
+                !(*c == self.grouping_separator
+                    || (self.grouping_separator == ' ' && c.is_whitespace()))
+            })
+            .filter_map(|c| match c {
+                c if c == self.decimal_separator => Some('.'),
+                '0'..='9' | '-' => Some(c),
+                _ => None,
+            })
+            .collect();
+        normalized
+            .trim_matches('.')
+            .parse()
+            .ok()
+            .filter(|number: &f64| number.is_finite())
+    }
+
+    /// Parse a numeric date in the market's order, e.g. `31.12.2024` for
+    /// de-DE; ISO dates are accepted in every market
+    pub fn parse_date(&self, raw: &str) -> Option<NaiveDate> {
+        let raw = raw.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+            return Some(date);
+        }
+        let parts: Vec<u32> = raw
+            .split(['.', '/', '-', ' '])
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        let [a, b, c] = parts[..] else {
+            return None;
+        };
+        let (year, month, day) = match self.date_order {
+            DateOrder::Dmy => (c, b, a),
+            DateOrder::Mdy => (c, a, b),
+            DateOrder::Ymd => (a, b, c),
+        };
+        // Two-digit years are this century's
+        let year = if year < 100 { year + 2000 } else { year };
+        NaiveDate::from_ymd_opt(year as i32, month, day)
+    }
+}
+
+/// Language a page declares in `<html lang>`
+pub fn served_language(html: &str) -> Option<String> {
+    HTML_LANG_REGEX
+        .captures(html)
+        .map(|captures| captures[1].replace('_', "-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_profiles() {
+        let de = LocaleProfile::named("de_de").unwrap();
+        assert_eq!(de.tag, "de-DE");
+        assert_eq!(de.accept_language, "de-DE,de;q=0.9,en;q=0.5");
+        assert_eq!(de.currency.as_deref(), Some("EUR"));
+        assert_eq!(de.parse_number("1.234,56 €"), Some(1234.56));
+        assert_eq!(
+            de.parse_date("31.12.2024"),
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+        );
+
+        let us = LocaleProfile::named("en-US").unwrap();
+        assert_eq!(us.parse_number("$1,234.56"), Some(1234.56));
+        assert_eq!(
+            us.parse_date("12/31/24"),
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+        );
+        assert_eq!(us.parse_date("31/12/24"), None);
+
+        let fr = LocaleProfile::named("fr-FR").unwrap();
+        assert_eq!(fr.parse_number("1\u{202f}234,5"), Some(1234.5));
+        assert_eq!(
+            LocaleProfile::named("ja-JP")
+                .unwrap()
+                .parse_date("2024/12/31"),
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+        );
+        assert!(LocaleProfile::NAMES
+            .iter()
+            .all(|name| LocaleProfile::named(name).unwrap().currency.is_some()));
+        assert!(LocaleProfile::named("german").is_err());
+    }
+
+    #[test]
+    fn test_served_language() {
+        let de = LocaleProfile::named("de-DE").unwrap();
+        let html = r#"<!doctype html><html class="no-js" lang="en_US"><head>"#;
+        assert_eq!(served_language(html).as_deref(), Some("en-US"));
+        assert!(!de.matches_language("en-US"));
+        assert!(de.matches_language("de"));
+        assert_eq!(served_language("<html><body>"), None);
+
+        let mut headers = HashMap::from([("accept-language".to_string(), "en".to_string())]);
+        de.apply(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["Accept-Language"], de.accept_language);
+    }
+}
@@ -0,0 +1,173 @@
+//! Metadata keys of extracted content
+//!
+//! Every page of a crawl records much the same metadata keys, e.g.
+//! `description`, `og:title` or `canonical_url`. A [`MetaKey`] is interned:
+//! all keys with the same name share one allocation, so a result's
+//! [`Metadata`] only allocates its values. Keys turn back into `String`s at
+//! the storage boundary, through [`into_owned`].
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Deref,
+    sync::{Arc, RwLock},
+};
+
+/// Metadata of extracted content, by interned key
+pub type Metadata = HashMap<MetaKey, String>;
+
+/// Distinct keys interned; keys past this, e.g. from pages making up meta
+/// names, get an allocation of their own
+const MAX_INTERNED_KEYS: usize = 4096;
+
+static INTERNED: Lazy<RwLock<HashSet<Arc<str>>>> = Lazy::new(Default::default);
+
+/// Name of a metadata entry, sharing its allocation with every equal key
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MetaKey(Arc<str>);
+
+impl MetaKey {
+    pub fn new(name: &str) -> Self {
+        if let Some(key) = INTERNED.read().unwrap().get(name) {
+            return Self(key.clone());
+        }
+        let mut interned = INTERNED.write().unwrap();
+        if let Some(key) = interned.get(name) {
+            return Self(key.clone());
+        }
+        let key: Arc<str> = Arc::from(name);
+        if interned.len() < MAX_INTERNED_KEYS {
+            interned.insert(key.clone());
+        }
+        Self(key)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Metadata with owned keys, as storage keeps it
+pub fn into_owned(metadata: Metadata) -> HashMap<String, String> {
+    metadata
+        .into_iter()
+        .map(|(key, value)| (key.as_str().to_string(), value))
+        .collect()
+}
+
+/// Metadata with interned keys, e.g. for stored content extracted again
+pub fn from_owned(metadata: HashMap<String, String>) -> Metadata {
+    metadata
+        .into_iter()
+        .map(|(key, value)| (MetaKey::new(&key), value))
+        .collect()
+}
+
+impl From<&str> for MetaKey {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for MetaKey {
+    fn from(name: String) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl From<&String> for MetaKey {
+    fn from(name: &String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl Deref for MetaKey {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for MetaKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for MetaKey {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for MetaKey {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for MetaKey {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Debug for MetaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for MetaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for MetaKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for MetaKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_keys_share_an_allocation() {
+        let a = MetaKey::from("og:title");
+        let b = MetaKey::from(String::from("og:title"));
+        assert_eq!(a, b);
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert_ne!(a, MetaKey::from("og:image"));
+    }
+
+    #[test]
+    fn looks_up_and_converts_by_name() {
+        let mut metadata = Metadata::new();
+        metadata.insert("description".into(), "A page".to_string());
+        assert_eq!(
+            metadata.get("description").map(String::as_str),
+            Some("A page")
+        );
+
+        let owned = into_owned(metadata.clone());
+        assert_eq!(owned["description"], "A page");
+        assert_eq!(from_owned(owned), metadata);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(json, r#"{"description":"A page"}"#);
+        assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), metadata);
+    }
+}
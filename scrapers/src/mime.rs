@@ -0,0 +1,380 @@
+//! Content-type routing
+//!
+//! Not every URL is an HTML page. [`MimeRouter`] picks a handler by the
+//! response's media type: HTML goes to the regular extractors, JSON is parsed
+//! and kept pretty-printed, images, PDFs and other binary files are described
+//! by metadata only and handed to an [`ArtifactSink`] when one is configured,
+//! and anything else goes to a configurable fallback, plain text by default.
+
+use crate::{metadata::Metadata, ExtractedContent};
+use anyhow::Result;
+use bytes::Bytes;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{future::Future, pin::Pin, sync::Arc};
+use swoop_core::text::SharedStr;
+
+/// Metadata key naming the route a response took, e.g. `json`
+pub const CONTENT_ROUTE_KEY: &str = "content_route";
+
+/// Top-level JSON keys listed in `json:keys`
+const MAX_JSON_KEYS: usize = 50;
+
+pub type HandlerFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Custom handling for responses of some media type
+pub trait ContentHandler: Send + Sync {
+    fn handle<'a>(
+        &'a self,
+        url: &'a str,
+        content_type: &'a str,
+        body: Bytes,
+    ) -> HandlerFuture<'a, ExtractedContent>;
+}
+
+/// Destination for binary responses, such as an artifact store
+pub trait ArtifactSink: Send + Sync {
+    /// Keep `body` and return where it was stored
+    fn store<'a>(
+        &'a self,
+        url: &'a str,
+        content_type: &'a str,
+        body: Bytes,
+    ) -> HandlerFuture<'a, String>;
+}
+
+/// How responses of one media type are handled
+#[derive(Clone)]
+pub enum Route {
+    /// Regular HTML extraction
+    Html,
+    /// Parsed JSON, pretty-printed as the text
+    Json,
+    /// Metadata only; the body goes to the router's [`ArtifactSink`], if any
+    Artifact,
+    /// The body as text
+    Text,
+    Custom(Arc<dyn ContentHandler>),
+}
+
+impl Route {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Route::Html => "html",
+            Route::Json => "json",
+            Route::Artifact => "artifact",
+            Route::Text => "text",
+            Route::Custom(_) => "custom",
+        }
+    }
+}
+
+impl std::fmt::Debug for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Picks a [`Route`] by media type, see the [module docs](self)
+///
+/// Patterns are exact media types (`application/pdf`), whole top-level types
+/// (`image/*`) or structured syntax suffixes (`*+json`). Routes added with
+/// [`MimeRouter::with_route`] are tried before the defaults.
+#[derive(Clone)]
+pub struct MimeRouter {
+    routes: Vec<(String, Route)>,
+    fallback: Route,
+    artifacts: Option<Arc<dyn ArtifactSink>>,
+}
+
+impl Default for MimeRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MimeRouter {
+    pub fn new() -> Self {
+        let routes = [
+            ("text/html", Route::Html),
+            ("application/xhtml+xml", Route::Html),
+            ("application/json", Route::Json),
+            ("*+json", Route::Json),
+            ("image/*", Route::Artifact),
+            ("audio/*", Route::Artifact),
+            ("video/*", Route::Artifact),
+            ("font/*", Route::Artifact),
+            ("application/pdf", Route::Artifact),
+            ("application/octet-stream", Route::Artifact),
+            ("application/zip", Route::Artifact),
+            ("application/gzip", Route::Artifact),
+            ("application/x-tar", Route::Artifact),
+            ("application/x-7z-compressed", Route::Artifact),
+        ];
+        Self {
+            routes: routes
+                .into_iter()
+                .map(|(pattern, route)| (pattern.to_string(), route))
+                .collect(),
+            fallback: Route::Text,
+            artifacts: None,
+        }
+    }
+
+    /// Handle media types matching `pattern` with `route`
+    pub fn with_route(mut self, pattern: &str, route: Route) -> Self {
+        self.routes.insert(0, (pattern.to_ascii_lowercase(), route));
+        self
+    }
+
+    /// Route for media types no pattern matches
+    pub fn with_fallback(mut self, route: Route) -> Self {
+        self.fallback = route;
+        self
+    }
+
+    /// Store the bodies of [`Route::Artifact`] responses in `sink`
+    pub fn with_artifact_sink(mut self, sink: Arc<dyn ArtifactSink>) -> Self {
+        self.artifacts = Some(sink);
+        self
+    }
+
+    /// The route for `content_type`, a media type without parameters
+    pub fn route(&self, content_type: &str) -> &Route {
+        let content_type = content_type.to_ascii_lowercase();
+        self.routes
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, &content_type))
+            .map_or(&self.fallback, |(_, route)| route)
+    }
+
+    /// Extract `body`, fetched from `url`, along the route for `content_type`
+    pub async fn dispatch(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: Bytes,
+    ) -> Result<ExtractedContent> {
+        let route = self.route(content_type);
+        let mut content = match route {
+            Route::Html => {
+                crate::platforms::content_from_html(url.to_string(), &SharedStr::from_body(body))
+            }
+            Route::Json => json_content(url, body),
+            Route::Artifact => self.artifact_content(url, content_type, body).await?,
+            Route::Text => text_content(url, body),
+            Route::Custom(handler) => handler.handle(url, content_type, body).await?,
+        };
+        content
+            .metadata
+            .insert(CONTENT_ROUTE_KEY.into(), route.name().to_string());
+        Ok(content)
+    }
+
+    async fn artifact_content(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: Bytes,
+    ) -> Result<ExtractedContent> {
+        let filename = url::Url::parse(url).ok().and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+                .filter(|name| !name.is_empty())
+        });
+        let mut metadata = Metadata::from([
+            ("artifact:content_type".into(), content_type.to_string()),
+            ("artifact:bytes".into(), body.len().to_string()),
+            (
+                "artifact:sha256".into(),
+                format!("{:x}", Sha256::digest(&body)),
+            ),
+        ]);
+        if let Some(filename) = &filename {
+            metadata.insert("artifact:filename".into(), filename.clone());
+        }
+        if let Some(sink) = &self.artifacts {
+            let location = sink.store(url, content_type, body).await?;
+            metadata.insert("artifact:location".into(), location);
+        }
+        Ok(ExtractedContent {
+            url: url.to_string(),
+            title: filename.map(SharedStr::from),
+            text: None,
+            metadata,
+            tags: vec!["artifact".to_string()],
+            extracted_at: chrono::Utc::now(),
+        })
+    }
+}
+
+fn matches_pattern(pattern: &str, content_type: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        content_type.ends_with(suffix)
+    } else if let Some(top_level) = pattern.strip_suffix("/*") {
+        content_type
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind == top_level)
+    } else {
+        pattern == content_type
+    }
+}
+
+/// Pretty-printed JSON with its shape in `json:*` metadata; invalid JSON is kept as text
+fn json_content(url: &str, body: Bytes) -> ExtractedContent {
+    let Ok(value) = serde_json::from_slice::<Value>(&body) else {
+        let mut content = text_content(url, body);
+        content
+            .metadata
+            .insert("json:invalid".into(), "true".to_string());
+        return content;
+    };
+
+    let mut metadata = Metadata::new();
+    let title = match &value {
+        Value::Object(map) => {
+            metadata.insert("json:type".into(), "object".to_string());
+            let keys: Vec<&str> = map.keys().take(MAX_JSON_KEYS).map(String::as_str).collect();
+            metadata.insert("json:keys".into(), keys.join(","));
+            ["title", "name", "headline"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(Value::as_str))
+                .map(str::to_string)
+        }
+        Value::Array(items) => {
+            metadata.insert("json:type".into(), "array".to_string());
+            metadata.insert("json:items".into(), items.len().to_string());
+            None
+        }
+        _ => {
+            metadata.insert("json:type".into(), "scalar".to_string());
+            None
+        }
+    };
+    ExtractedContent {
+        url: url.to_string(),
+        title: title.map(SharedStr::from),
+        text: serde_json::to_string_pretty(&value)
+            .ok()
+            .map(SharedStr::from),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: chrono::Utc::now(),
+    }
+}
+
+/// Plain text, sharing the body's buffer
+fn text_content(url: &str, body: Bytes) -> ExtractedContent {
+    let body = SharedStr::from_body(body);
+    let text = body.trim();
+    ExtractedContent {
+        url: url.to_string(),
+        title: None,
+        text: (!text.is_empty()).then(|| body.share(text)),
+        metadata: Metadata::new(),
+        tags: Vec::new(),
+        extracted_at: chrono::Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<(String, usize)>>);
+
+    impl ArtifactSink for MemorySink {
+        fn store<'a>(
+            &'a self,
+            url: &'a str,
+            _content_type: &'a str,
+            body: Bytes,
+        ) -> HandlerFuture<'a, String> {
+            Box::pin(async move {
+                self.0.lock().unwrap().push((url.to_string(), body.len()));
+                Ok(format!("memory://{}", body.len()))
+            })
+        }
+    }
+
+    struct Upper;
+
+    impl ContentHandler for Upper {
+        fn handle<'a>(
+            &'a self,
+            url: &'a str,
+            _content_type: &'a str,
+            body: Bytes,
+        ) -> HandlerFuture<'a, ExtractedContent> {
+            Box::pin(async move {
+                let mut content = text_content(url, body);
+                content.text = content.text.map(|text| text.to_uppercase().into());
+                Ok(content)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_content_type() {
+        let sink = Arc::new(MemorySink::default());
+        let router = MimeRouter::new()
+            .with_route("text/csv", Route::Custom(Arc::new(Upper)))
+            .with_artifact_sink(sink.clone());
+
+        assert_eq!(router.route("application/ld+json").name(), "json");
+        assert_eq!(router.route("IMAGE/PNG").name(), "artifact");
+        assert_eq!(router.route("text/markdown").name(), "text");
+
+        let json = router
+            .dispatch(
+                "https://api.example.com/item",
+                "application/json",
+                Bytes::from_static(br#"{"name":"Widget","price":3}"#),
+            )
+            .await
+            .unwrap();
+        assert_eq!(json.title.as_deref(), Some("Widget"));
+        assert_eq!(json.metadata["json:keys"], "name,price");
+        assert!(json.text.unwrap().contains("\n  \"price\": 3"));
+        assert_eq!(json.metadata[CONTENT_ROUTE_KEY], "json");
+
+        let image = router
+            .dispatch(
+                "https://cdn.example.com/img/logo.png",
+                "image/png",
+                Bytes::from_static(b"\x89PNG...."),
+            )
+            .await
+            .unwrap();
+        assert_eq!(image.text, None);
+        assert_eq!(image.title.as_deref(), Some("logo.png"));
+        assert_eq!(image.metadata["artifact:bytes"], "8");
+        assert_eq!(image.metadata["artifact:location"], "memory://8");
+        assert_eq!(image.metadata["artifact:sha256"].len(), 64);
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+
+        let csv = router
+            .dispatch(
+                "https://example.com/a.csv",
+                "text/csv",
+                Bytes::from_static(b"a,b\n"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(csv.text.as_deref(), Some("A,B"));
+        assert_eq!(csv.metadata[CONTENT_ROUTE_KEY], "custom");
+
+        let broken = router
+            .dispatch(
+                "https://example.com/x",
+                "application/json",
+                Bytes::from_static(b"{oops"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(broken.metadata["json:invalid"], "true");
+        assert_eq!(broken.text.as_deref(), Some("{oops"));
+    }
+}
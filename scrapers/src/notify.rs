@@ -0,0 +1,175 @@
+//! Alert notifications
+//!
+//! Components that detect problems during a crawl (such as extraction drift)
+//! raise an [`Alert`] through a [`NotificationHub`], which fans it out to every
+//! configured [`Notifier`].
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+/// Future returned by [`Notifier::notify`]
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Something a user should know about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    /// Machine-readable alert type, e.g. `selector_broken`
+    pub kind: String,
+    pub severity: Severity,
+    pub message: String,
+    pub details: HashMap<String, String>,
+    pub raised_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Alert {
+    pub fn new(kind: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            severity,
+            message: message.into(),
+            details: HashMap::new(),
+            raised_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn with_detail(mut self, key: &str, value: impl ToString) -> Self {
+        self.details.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Delivers alerts somewhere
+pub trait Notifier: Send + Sync {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> NotifyFuture<'a>;
+}
+
+/// POSTs alerts as JSON to a webhook URL
+pub struct WebhookNotifier {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            self.client
+                .post(&self.url)
+                .json(alert)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// Calls a closure for every alert, e.g. to log or collect them
+pub struct CallbackNotifier<F> {
+    callback: F,
+}
+
+impl<F: Fn(&Alert) + Send + Sync> CallbackNotifier<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: Fn(&Alert) + Send + Sync> Notifier for CallbackNotifier<F> {
+    fn notify<'a>(&'a self, alert: &'a Alert) -> NotifyFuture<'a> {
+        (self.callback)(alert);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Fans alerts out to every registered notifier
+#[derive(Default, Clone)]
+pub struct NotificationHub {
+    notifiers: Vec<Arc<dyn Notifier>>,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_notifier(mut self, notifier: impl Notifier + 'static) -> Self {
+        self.notifiers.push(Arc::new(notifier));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifiers.is_empty()
+    }
+
+    /// Deliver `alert` to all notifiers; one failing doesn't stop the others
+    pub async fn send(&self, alert: &Alert) -> Result<()> {
+        let mut last_error = None;
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.notify(alert).await {
+                last_error = Some(e);
+            }
+        }
+        last_error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use wiremock::{
+        matchers::{body_partial_json, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_hub_delivers_to_all_notifiers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"kind": "selector_broken"}),
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let collected = seen.clone();
+        let hub = NotificationHub::new()
+            .with_notifier(WebhookNotifier::new(server.uri()))
+            .with_notifier(CallbackNotifier::new(move |alert: &Alert| {
+                collected.lock().unwrap().push(alert.kind.clone());
+            }));
+
+        let alert = Alert::new(
+            "selector_broken",
+            Severity::Warning,
+            "price stopped matching",
+        )
+        .with_detail("rule", "price");
+        hub.send(&alert).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["selector_broken".to_string()]);
+    }
+}
@@ -0,0 +1,737 @@
+//! Self-contained offline page snapshots
+//!
+//! A stored page still loads its stylesheets and images from the site, so it
+//! only looks right while the site serves them unchanged. [`PageArchiver`]
+//! fetches them at capture time and packs the page into a single file that
+//! opens offline as captured:
+//! - [`SnapshotFormat::Html`] inlines stylesheets, images and icons as
+//!   `data:` URIs, including the images and `@import`s the stylesheets use
+//! - [`SnapshotFormat::Mhtml`] writes a `multipart/related` archive with one
+//!   part per resource, as browsers' "save as single file" does
+//!
+//! Pages are sanitized first (see [`crate::sanitize`]), so snapshots run no
+//! scripts and load nothing from the network. Lazy-loaded images are pinned to
+//! their `data-src`, and `srcset`s and `<picture>` sources are dropped in
+//! favour of the inlined `src`. Resources the [`SafetyFilter`] stops, such as
+//! an executable served as an image, are left out.
+
+use crate::{safety::SafetyFilter, sanitize::SnapshotSanitizer};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use lol_html::{element, html_content::ContentType, rewrite_str, text, RewriteStrSettings};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+    time::Duration,
+};
+use swoop_core::{client::ResponseMeta, security::UrlValidator};
+use url::Url;
+
+static CSS_URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^)'"\s]*))\s*\)|@import\s+(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+});
+
+/// `@import`s followed into imported stylesheets
+const MAX_IMPORT_DEPTH: usize = 3;
+
+/// How a snapshot is packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnapshotFormat {
+    /// One HTML file with its resources inlined as `data:` URIs
+    Html,
+    /// A MIME `multipart/related` archive
+    Mhtml,
+}
+
+impl SnapshotFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Mhtml => "mhtml",
+        }
+    }
+
+    /// Media type of the snapshot file
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Html => "text/html",
+            Self::Mhtml => "multipart/related",
+        }
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "mhtml" | "mht" => Ok(Self::Mhtml),
+            _ => bail!(
+                "Unknown snapshot format '{}', expected html or mhtml",
+                value
+            ),
+        }
+    }
+}
+
+/// A stylesheet, image or icon fetched for a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    /// Media type, without parameters
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl Resource {
+    fn is_css(&self) -> bool {
+        self.content_type == "text/css"
+    }
+
+    fn data_uri(&self) -> String {
+        data_uri(&self.content_type, &self.body)
+    }
+}
+
+/// Fetched resources by absolute URL
+pub type Resources = HashMap<String, Resource>;
+
+/// A page packed into one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfflineSnapshot {
+    pub format: SnapshotFormat,
+    pub body: Vec<u8>,
+    /// Resources packed into the snapshot
+    pub resources: usize,
+    /// Resources that couldn't be fetched or didn't fit
+    pub missing: Vec<String>,
+}
+
+/// Packs pages and their resources into [`OfflineSnapshot`]s
+#[derive(Clone)]
+pub struct PageArchiver {
+    validator: UrlValidator,
+    timeout: Duration,
+    max_resources: usize,
+    max_resource_bytes: usize,
+    /// Resource bytes per snapshot, before `data:` URI or MIME encoding
+    max_total_bytes: usize,
+    safety: SafetyFilter,
+}
+
+impl Default for PageArchiver {
+    fn default() -> Self {
+        Self {
+            validator: UrlValidator::default(),
+            timeout: Duration::from_secs(30),
+            max_resources: 200,
+            max_resource_bytes: 5 * 1024 * 1024,
+            max_total_bytes: 15 * 1024 * 1024,
+            safety: SafetyFilter::default(),
+        }
+    }
+}
+
+impl PageArchiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Leave out the resources `safety` stops
+    pub fn with_safety(mut self, safety: SafetyFilter) -> Self {
+        self.safety = safety;
+        self
+    }
+
+    /// Pack at most `count` resources, and skip any larger than `bytes`
+    pub fn with_limits(mut self, count: usize, bytes: usize, total_bytes: usize) -> Self {
+        self.max_resources = count;
+        self.max_resource_bytes = bytes;
+        self.max_total_bytes = total_bytes;
+        self
+    }
+
+    /// Snapshot `html`, fetched from `page_url`, with its resources
+    pub async fn archive(
+        &self,
+        html: &str,
+        page_url: &str,
+        format: SnapshotFormat,
+    ) -> Result<OfflineSnapshot> {
+        let base =
+            Url::parse(page_url).with_context(|| format!("Invalid page URL {}", page_url))?;
+        let html = SnapshotSanitizer::new().sanitize(html)?.html;
+        let (resources, missing) = self.fetch_resources(page_resources(&html, &base)?).await;
+        let body = match format {
+            SnapshotFormat::Html => inline_html(&html, &base, &resources)?.into_bytes(),
+            SnapshotFormat::Mhtml => to_mhtml(&html, &base, &resources, Utc::now())?.into_bytes(),
+        };
+        Ok(OfflineSnapshot {
+            format,
+            body,
+            resources: resources.len(),
+            missing,
+        })
+    }
+
+    /// Fetch `urls`, and the resources of the stylesheets among them
+    async fn fetch_resources(&self, urls: Vec<String>) -> (Resources, Vec<String>) {
+        let mut seen: HashSet<String> = urls.iter().cloned().collect();
+        let mut queue: VecDeque<String> = urls.into();
+        let mut resources = Resources::new();
+        let mut missing = Vec::new();
+        let mut total_bytes = 0;
+
+        while !queue.is_empty() {
+            let room = self.max_resources.saturating_sub(resources.len());
+            if room == 0 {
+                missing.extend(queue.drain(..));
+                break;
+            }
+            let batch: Vec<String> = queue.drain(..queue.len().min(room)).collect();
+            let fetched: Vec<(String, Result<Resource>)> = futures::stream::iter(batch)
+                .map(|url| async move {
+                    let resource = self.fetch(&url).await;
+                    (url, resource)
+                })
+                .buffered(4)
+                .collect()
+                .await;
+            for (url, resource) in fetched {
+                match resource {
+                    Ok(resource) if total_bytes + resource.body.len() <= self.max_total_bytes => {
+                        total_bytes += resource.body.len();
+                        if resource.is_css() {
+                            let css = String::from_utf8_lossy(&resource.body);
+                            if let Ok(css_base) = Url::parse(&url) {
+                                queue.extend(
+                                    css_resources(&css, &css_base)
+                                        .into_iter()
+                                        .filter(|url| seen.insert(url.clone())),
+                                );
+                            }
+                        }
+                        resources.insert(url, resource);
+                    }
+                    Ok(_) => missing.push(url),
+                    Err(e) => {
+                        tracing::debug!("📦 Left {} out of the snapshot: {}", url, e);
+                        missing.push(url);
+                    }
+                }
+            }
+        }
+        (resources, missing)
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Resource> {
+        let started = std::time::Instant::now();
+        let mut response = swoop_core::open_url_with(&self.validator, url, self.timeout).await?;
+        let meta = ResponseMeta::from_response(&response, started);
+        if meta.status >= 400 {
+            bail!("HTTP {}", meta.status);
+        }
+        // Don't read past the limit, whatever size the server claims
+        let limit = self.max_resource_bytes;
+        if let Some(length) = response
+            .content_length()
+            .filter(|length| *length > limit as u64)
+        {
+            bail!("{} bytes, over the {} byte limit", length, limit);
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > limit {
+                bail!("Over the {} byte limit", limit);
+            }
+        }
+        if let Some(verdict) = self.safety.check(meta.content_type(), &body) {
+            bail!("{} ({})", verdict.reason, verdict.hazard.as_str());
+        }
+        Ok(Resource {
+            content_type: meta
+                .content_type()
+                .map(str::to_ascii_lowercase)
+                .unwrap_or_else(|| guess_content_type(url).to_string()),
+            body,
+        })
+    }
+}
+
+/// Absolute URLs of the stylesheets, images and icons `html` uses, in
+/// document order
+pub fn page_resources(html: &str, base: &Url) -> Result<Vec<String>> {
+    let urls = RefCell::new(Vec::new());
+    let css = RefCell::new(String::new());
+    let push = |value: &str| {
+        if let Some(url) = resolve(base, value) {
+            urls.borrow_mut().push(url);
+        }
+    };
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("link[href]", |element| {
+                    if is_packed_link(element.get_attribute("rel")) {
+                        push(&element.get_attribute("href").unwrap_or_default());
+                    }
+                    Ok(())
+                }),
+                element!("img", |element| {
+                    let src = element.get_attribute("src");
+                    if let Some(source) = image_source(src, element.get_attribute("data-src")) {
+                        push(&source);
+                    }
+                    Ok(())
+                }),
+                element!("input[src], video[poster]", |element| {
+                    let source = element
+                        .get_attribute("src")
+                        .or_else(|| element.get_attribute("poster"));
+                    push(&source.unwrap_or_default());
+                    Ok(())
+                }),
+                element!("[style]", |element| {
+                    let style = element.get_attribute("style").unwrap_or_default();
+                    urls.borrow_mut().extend(css_resources(&style, base));
+                    Ok(())
+                }),
+                text!("style", |chunk| {
+                    css.borrow_mut().push_str(chunk.as_str());
+                    if chunk.last_in_text_node() {
+                        urls.borrow_mut().extend(css_resources(&css.take(), base));
+                    }
+                    Ok(())
+                }),
+            ],
+            ..RewriteStrSettings::default()
+        },
+    )
+    .map_err(|e| anyhow!("Failed to read page resources: {}", e))?;
+
+    let mut seen = HashSet::new();
+    let mut urls = urls.into_inner();
+    urls.retain(|url| seen.insert(url.clone()));
+    Ok(urls)
+}
+
+/// Absolute URLs of the images, fonts and `@import`s stylesheet `css` uses
+pub fn css_resources(css: &str, base: &Url) -> Vec<String> {
+    CSS_URL_REGEX
+        .captures_iter(css)
+        .filter_map(|captures| css_reference(&captures))
+        .filter_map(|reference| resolve(base, reference))
+        .collect()
+}
+
+/// `html` with every resource found in `resources` inlined as a `data:` URI
+pub fn inline_html(html: &str, base: &Url, resources: &Resources) -> Result<String> {
+    rewrite_page(html, base, Some(resources))
+}
+
+/// `html` and `resources` as an MHTML archive of the page at `base`, saved
+/// at `date`
+pub fn to_mhtml(
+    html: &str,
+    base: &Url,
+    resources: &Resources,
+    date: DateTime<Utc>,
+) -> Result<String> {
+    let html = rewrite_page(html, base, None)?;
+    let boundary = format!(
+        "----MultipartBoundary--swoop-{}----",
+        date.timestamp_millis()
+    );
+    let mut mhtml = format!(
+        "From: <Saved by swoop>\r\nSnapshot-Content-Location: {}\r\nDate: {}\r\n\
+         MIME-Version: 1.0\r\nContent-Type: multipart/related;\r\n\ttype=\"text/html\";\r\n\
+         \tboundary=\"{}\"\r\n\r\n",
+        base,
+        date.to_rfc2822(),
+        boundary
+    );
+    let mut part = |content_type: &str, location: &str, body: &[u8]| {
+        mhtml.push_str(&format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Transfer-Encoding: base64\r\n\
+             Content-Location: {}\r\n\r\n",
+            boundary, content_type, location
+        ));
+        // MIME caps lines at 76 characters
+        let encoded = STANDARD.encode(body);
+        for line in encoded.as_bytes().chunks(76) {
+            mhtml.push_str(std::str::from_utf8(line).unwrap_or_default());
+            mhtml.push_str("\r\n");
+        }
+        mhtml.push_str("\r\n");
+    };
+    part("text/html; charset=utf-8", base.as_str(), html.as_bytes());
+    let mut urls: Vec<&String> = resources.keys().collect();
+    urls.sort();
+    for url in urls {
+        let resource = &resources[url];
+        part(&resource.content_type, url, &resource.body);
+    }
+    mhtml.push_str(&format!("--{}--\r\n", boundary));
+    Ok(mhtml)
+}
+
+/// Rewrite a sanitized page to be read as UTF-8, inlining `resources` if given
+fn rewrite_page(html: &str, base: &Url, resources: Option<&Resources>) -> Result<String> {
+    let css = RefCell::new(String::new());
+    let empty = Resources::new();
+    let packed = resources.unwrap_or(&empty);
+    let inline = |value: &str| -> Option<String> {
+        let url = resolve(base, value)?;
+        let resource = packed.get(&url)?;
+        Some(match resource.is_css() {
+            true => inline_stylesheet(resource, &url, packed, 0),
+            false => resource.data_uri(),
+        })
+    };
+
+    let mut handlers = vec![
+        // Snapshots are written as UTF-8, whatever the page declared
+        element!("meta[charset]", |element| {
+            element.set_attribute("charset", "utf-8")?;
+            Ok(())
+        }),
+        element!("meta[http-equiv]", |element| {
+            let is_content_type = element
+                .get_attribute("http-equiv")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("content-type"));
+            if is_content_type {
+                element.set_attribute("content", "text/html; charset=utf-8")?;
+            }
+            Ok(())
+        }),
+    ];
+    if resources.is_some() {
+        handlers.extend([
+            element!("link[href]", |element| {
+                if is_packed_link(element.get_attribute("rel")) {
+                    if let Some(uri) = inline(&element.get_attribute("href").unwrap_or_default()) {
+                        element.set_attribute("href", &uri)?;
+                    }
+                }
+                Ok(())
+            }),
+            element!("img", |element| {
+                let source = image_source(
+                    element.get_attribute("src"),
+                    element.get_attribute("data-src"),
+                );
+                if let Some(uri) = source.and_then(|source| inline(&source)) {
+                    element.set_attribute("src", &uri)?;
+                }
+                element.remove_attribute("srcset");
+                element.remove_attribute("sizes");
+                element.remove_attribute("loading");
+                Ok(())
+            }),
+            element!("picture > source", |element| {
+                element.remove();
+                Ok(())
+            }),
+            element!("input[src]", |element| {
+                if let Some(uri) = inline(&element.get_attribute("src").unwrap_or_default()) {
+                    element.set_attribute("src", &uri)?;
+                }
+                Ok(())
+            }),
+            element!("video[poster]", |element| {
+                if let Some(uri) = inline(&element.get_attribute("poster").unwrap_or_default()) {
+                    element.set_attribute("poster", &uri)?;
+                }
+                Ok(())
+            }),
+            element!("[style]", |element| {
+                let style = element.get_attribute("style").unwrap_or_default();
+                element.set_attribute("style", &inline_css(&style, base, packed, 0))?;
+                Ok(())
+            }),
+            text!("style", |chunk| {
+                css.borrow_mut().push_str(chunk.as_str());
+                if chunk.last_in_text_node() {
+                    chunk.replace(&inline_css(&css.take(), base, packed, 0), ContentType::Html);
+                } else {
+                    chunk.remove();
+                }
+                Ok(())
+            }),
+        ]);
+    }
+
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: handlers,
+            ..RewriteStrSettings::default()
+        },
+    )
+    .map_err(|e| anyhow!("Failed to pack snapshot: {}", e))
+}
+
+/// A stylesheet as a `data:` URI, with its own resources inlined
+fn inline_stylesheet(
+    stylesheet: &Resource,
+    url: &str,
+    resources: &Resources,
+    depth: usize,
+) -> String {
+    let css = String::from_utf8_lossy(&stylesheet.body);
+    match Url::parse(url) {
+        Ok(css_base) => {
+            let css = inline_css(&css, &css_base, resources, depth);
+            data_uri("text/css", css.as_bytes())
+        }
+        Err(_) => stylesheet.data_uri(),
+    }
+}
+
+/// `css` with its `url()`s and `@import`s pointing at `data:` URIs
+fn inline_css(css: &str, base: &Url, resources: &Resources, depth: usize) -> String {
+    CSS_URL_REGEX
+        .replace_all(css, |captures: &Captures| {
+            let found = css_reference(captures)
+                .and_then(|reference| resolve(base, reference))
+                .and_then(|url| resources.get(&url).map(|resource| (url, resource)));
+            let uri = match found {
+                Some((url, resource)) if resource.is_css() => {
+                    if depth >= MAX_IMPORT_DEPTH {
+                        return captures[0].to_string();
+                    }
+                    inline_stylesheet(resource, &url, resources, depth + 1)
+                }
+                Some((_, resource)) => resource.data_uri(),
+                None => return captures[0].to_string(),
+            };
+            match captures[0].get(..1) {
+                Some("@") => format!("@import url({})", uri),
+                _ => format!("url({})", uri),
+            }
+        })
+        .into_owned()
+}
+
+/// The reference in a `url()` or `@import` match
+fn css_reference<'c>(captures: &Captures<'c>) -> Option<&'c str> {
+    (1..=5)
+        .find_map(|group| captures.get(group))
+        .map(|reference| reference.as_str().trim())
+        .filter(|reference| !reference.is_empty())
+}
+
+/// `value` resolved against `base`, if it names something to fetch
+fn resolve(base: &Url, value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value.starts_with('#') {
+        return None;
+    }
+    let mut url = base.join(&value.replace("&amp;", "&")).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url.into())
+}
+
+/// The image an `<img>` shows once loaded, preferring a lazy loader's
+/// `data-src` over a placeholder
+fn image_source(src: Option<String>, data_src: Option<String>) -> Option<String> {
+    match (src, data_src) {
+        (Some(src), _) if !src.trim().is_empty() && !src.trim_start().starts_with("data:") => {
+            Some(src)
+        }
+        (_, Some(data_src)) => Some(data_src),
+        (src, None) => src,
+    }
+}
+
+/// Whether a `<link>` is a stylesheet or an icon
+fn is_packed_link(rel: Option<String>) -> bool {
+    rel.unwrap_or_default()
+        .to_ascii_lowercase()
+        .split_whitespace()
+        .any(|rel| matches!(rel, "stylesheet" | "icon" | "apple-touch-icon"))
+}
+
+fn data_uri(content_type: &str, body: &[u8]) -> String {
+    // Written unquoted into attributes and CSS, so only plain media types pass
+    let content_type: String = content_type
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '+' | '.' | '-'))
+        .collect();
+    format!("data:{};base64,{}", content_type, STANDARD.encode(body))
+}
+
+/// Media type of a resource served without one, from its extension
+fn guess_content_type(url: &str) -> &'static str {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("css") => "text/css",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        Some("woff") => "font/woff",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn resources() -> Resources {
+        let mut resources = Resources::new();
+        let resource = |content_type: &str, body: &[u8]| Resource {
+            content_type: content_type.to_string(),
+            body: body.to_vec(),
+        };
+        resources.insert(
+            "https://shop.example/css/site.css".to_string(),
+            resource(
+                "text/css",
+                b"@import 'print.css'; .hero { background: url(../img/bg.png) }",
+            ),
+        );
+        resources.insert(
+            "https://shop.example/css/print.css".to_string(),
+            resource("text/css", b"body { color: black }"),
+        );
+        resources.insert(
+            "https://shop.example/img/bg.png".to_string(),
+            resource("image/png", b"bg"),
+        );
+        resources.insert(
+            "https://shop.example/shoe.jpg".to_string(),
+            resource("image/jpeg", b"shoe"),
+        );
+        resources
+    }
+
+    #[test]
+    fn test_finds_page_resources() {
+        let base = Url::parse("https://shop.example/products/1").unwrap();
+        let html = r#"<html><head><link rel="stylesheet" href="/css/site.css">
+            <link rel="icon" href="/favicon.ico"><link rel="canonical" href="/p/1">
+            <style>.logo { background: url("/img/logo.svg") }</style></head>
+            <body><img src="data:image/gif;base64,R0lGOD" data-src="../shoe.jpg#zoom">
+            <div style="background-image: url('/img/bg.png')"></div><img src="/css/site.css"></body></html>"#;
+
+        assert_eq!(
+            page_resources(html, &base).unwrap(),
+            vec![
+                "https://shop.example/css/site.css",
+                "https://shop.example/favicon.ico",
+                "https://shop.example/img/logo.svg",
+                "https://shop.example/shoe.jpg",
+                "https://shop.example/img/bg.png",
+            ]
+        );
+        let css_base = Url::parse("https://shop.example/css/site.css").unwrap();
+        assert_eq!(
+            css_resources(
+                "@import 'print.css'; a { background: url(../img/bg.png) }",
+                &css_base
+            ),
+            vec![
+                "https://shop.example/css/print.css",
+                "https://shop.example/img/bg.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inlines_resources() {
+        let base = Url::parse("https://shop.example/products/1").unwrap();
+        let html = r#"<html><head><meta charset="iso-8859-1"><link rel="stylesheet" href="/css/site.css"></head>
+            <body><picture><source srcset="/shoe.webp"><img src="/shoe.jpg" srcset="/shoe-2x.jpg 2x"></picture>
+            <img src="/missing.png"></body></html>"#;
+
+        let out = inline_html(html, &base, &resources()).unwrap();
+        assert!(out.contains(r#"<meta charset="utf-8">"#), "{}", out);
+        assert!(out.contains(&format!(
+            r#"<img src="{}">"#,
+            data_uri("image/jpeg", b"shoe")
+        )));
+        assert!(!out.contains("srcset") && !out.contains("<source"));
+        assert!(out.contains(r#"<img src="/missing.png">"#));
+
+        let stylesheet = out
+            .split(r#"href="data:text/css;base64,"#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+        let css = String::from_utf8(STANDARD.decode(stylesheet).unwrap()).unwrap();
+        assert!(
+            css.starts_with("@import url(data:text/css;base64,"),
+            "{}",
+            css
+        );
+        assert!(css.contains(&format!("url({})", data_uri("image/png", b"bg"))));
+    }
+
+    #[test]
+    fn test_writes_mhtml() {
+        let base = Url::parse("https://shop.example/products/1").unwrap();
+        let date = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mhtml = to_mhtml("<html><body>Shoe</body></html>", &base, &resources(), date).unwrap();
+
+        assert!(mhtml.starts_with("From: <Saved by swoop>\r\n"));
+        assert!(mhtml.contains("Snapshot-Content-Location: https://shop.example/products/1\r\n"));
+        assert!(
+            mhtml.contains("Content-Location: https://shop.example/shoe.jpg\r\n\r\nc2hvZQ==\r\n")
+        );
+        let boundary = format!(
+            "----MultipartBoundary--swoop-{}----",
+            date.timestamp_millis()
+        );
+        assert_eq!(mhtml.matches(&format!("--{}\r\n", boundary)).count(), 5);
+        assert!(mhtml.ends_with(&format!("--{}--\r\n", boundary)));
+        assert!(mhtml.lines().all(|line| line.len() <= 100));
+    }
+
+    #[test]
+    fn test_parses_formats() {
+        assert_eq!(
+            "MHTML".parse::<SnapshotFormat>().unwrap(),
+            SnapshotFormat::Mhtml
+        );
+        assert_eq!(
+            "html".parse::<SnapshotFormat>().unwrap().content_type(),
+            "text/html"
+        );
+        assert!("pdf".parse::<SnapshotFormat>().is_err());
+    }
+}
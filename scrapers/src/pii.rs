@@ -0,0 +1,452 @@
+//! PII scrubbing before storage and export
+//!
+//! Public pages still carry personal data: contact emails, phone numbers,
+//! national ID numbers, people's names. A [`PiiScrubber`] finds them in
+//! extracted text and masks them (`[email]`) or drops them before anything is
+//! stored. Every redaction can be appended to a [`PiiAuditLog`], which records
+//! where data was removed and a digest of it, never the data itself, so a data
+//! subject's request can be checked against the log.
+//!
+//! The scrubber is also a [`Processor`], so it can run as the first step of an
+//! [`EnrichmentPipeline`](crate::enrichment::EnrichmentPipeline).
+
+use crate::{
+    enrichment::{ProcessFuture, Processor},
+    ExtractedContent,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use swoop_core::text::SharedStr;
+
+static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b").unwrap()
+});
+
+/// Candidates only; [`is_phone_number`] rejects dates, IPs and short numbers
+static PHONE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s-]?)?(?:\(\d{1,4}\)[\s-]?)?\b\d{2,4}(?:[\s.-]\d{2,5}){1,4}\b")
+        .unwrap()
+});
+
+/// US social security numbers and UK national insurance numbers
+static NATIONAL_ID_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\b\d{3}-\d{2}-\d{4}\b|\b[A-CEGHJ-PR-TW-Z][A-CEGHJ-NPR-TW-Z] ?\d{2} ?\d{2} ?\d{2} ?[A-D]\b",
+    )
+    .unwrap()
+});
+
+static DATE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{4}[-./]\d{1,2}[-./]\d{1,2}$|^\d{1,2}[-./]\d{1,2}[-./]\d{4}$").unwrap()
+});
+
+/// Kinds of personal data the scrubber looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    NationalId,
+    /// Names from the scrubber's dictionary
+    Name,
+}
+
+impl PiiKind {
+    /// In the order overlapping matches are claimed
+    pub const ALL: [PiiKind; 4] = [
+        PiiKind::NationalId,
+        PiiKind::Email,
+        PiiKind::Phone,
+        PiiKind::Name,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::NationalId => "national-id",
+            PiiKind::Name => "name",
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// What happens to matched data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    /// Replace it with its kind, e.g. `[email]`
+    #[default]
+    Mask,
+    /// Remove it
+    Drop,
+}
+
+/// One piece of personal data removed from a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    pub url: String,
+    /// Field it was found in, e.g. `text` or `metadata.author`
+    pub field: String,
+    pub kind: PiiKind,
+    /// First 16 hex digits of the SHA-256 of the lowercased value
+    pub digest: String,
+    pub redacted_at: DateTime<Utc>,
+}
+
+/// Digest recorded for a redacted `value`
+pub fn redaction_digest(value: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(value.trim().to_lowercase()));
+    digest[..16].to_string()
+}
+
+/// Append-only JSON Lines log of [`Redaction`]s
+#[derive(Debug)]
+pub struct PiiAuditLog {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl PiiAuditLog {
+    /// Open `path` for appending, creating it and its directory if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open PII audit log {}", path.display()))?;
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn record(&self, redactions: &[Redaction]) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for redaction in redactions {
+            serde_json::to_writer(&mut *writer, redaction)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Finds and removes personal data, see the [module docs](self)
+#[derive(Debug)]
+pub struct PiiScrubber {
+    kinds: Vec<PiiKind>,
+    mode: RedactionMode,
+    names: Option<Regex>,
+    audit: Option<PiiAuditLog>,
+    redacted: AtomicUsize,
+}
+
+impl PiiScrubber {
+    /// Scrub `kinds`; [`PiiKind::Name`] also needs [`Self::with_names`]
+    pub fn new(kinds: impl IntoIterator<Item = PiiKind>) -> Self {
+        let kinds: Vec<PiiKind> = kinds.into_iter().collect();
+        Self {
+            // Claim order, whatever order they were given in
+            kinds: PiiKind::ALL
+                .into_iter()
+                .filter(|kind| kinds.contains(kind))
+                .collect(),
+            mode: RedactionMode::default(),
+            names: None,
+            audit: None,
+            redacted: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: RedactionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Dictionary of names (or full names) to scrub; matching ignores case
+    /// but only capitalized occurrences count, so `Rose` the person is
+    /// scrubbed and `rose` the flower is not
+    pub fn with_names<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> Result<Self> {
+        let mut names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().trim().to_string())
+            .filter(|name| !name.is_empty() && !name.starts_with('#'))
+            .map(|name| regex::escape(&name))
+            .collect();
+        if names.is_empty() {
+            return Ok(self);
+        }
+        // Longest first, so full names win over the first names they start with
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let pattern = format!(r"\b(?:{})\b", names.join("|"));
+        self.names = Some(
+            RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .size_limit(64 * 1024 * 1024)
+                .build()
+                .context("Name dictionary is too large")?,
+        );
+        if !self.kinds.contains(&PiiKind::Name) {
+            self.kinds.push(PiiKind::Name);
+        }
+        Ok(self)
+    }
+
+    /// Name dictionary from a file with one name per line; `#` starts a comment line
+    pub fn with_names_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let names = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read name dictionary {}", path.display()))?;
+        self.with_names(names.lines())
+    }
+
+    pub fn with_audit_log(mut self, log: PiiAuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    pub fn kinds(&self) -> &[PiiKind] {
+        &self.kinds
+    }
+
+    /// Redactions made so far
+    pub fn redacted(&self) -> usize {
+        self.redacted.load(Ordering::Relaxed)
+    }
+
+    /// Scrub `text` in place, returning what was removed from it
+    ///
+    /// `field` names where `text` came from in the returned redactions.
+    pub fn scrub_text(&self, url: &str, field: &str, text: &mut String) -> Vec<Redaction> {
+        match self.scrub(url, field, text) {
+            Some((scrubbed, redactions)) => {
+                *text = scrubbed;
+                redactions
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Like [`Self::scrub_text`] for shared text, which is only copied when
+    /// something in it is removed
+    pub fn scrub_shared(&self, url: &str, field: &str, text: &mut SharedStr) -> Vec<Redaction> {
+        match self.scrub(url, field, text) {
+            Some((scrubbed, redactions)) => {
+                *text = scrubbed.into();
+                redactions
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn scrub(&self, url: &str, field: &str, text: &str) -> Option<(String, Vec<Redaction>)> {
+        let mut claimed: Vec<(usize, usize, PiiKind)> = Vec::new();
+        for kind in &self.kinds {
+            for (start, end) in self.find(*kind, text) {
+                if claimed.iter().all(|(s, e, _)| end <= *s || start >= *e) {
+                    claimed.push((start, end, *kind));
+                }
+            }
+        }
+        if claimed.is_empty() {
+            return None;
+        }
+        claimed.sort_by_key(|(start, ..)| *start);
+
+        let now = Utc::now();
+        let mut scrubbed = String::with_capacity(text.len());
+        let mut last = 0;
+        let mut redactions = Vec::with_capacity(claimed.len());
+        for (start, end, kind) in claimed {
+            scrubbed.push_str(&text[last..start]);
+            if self.mode == RedactionMode::Mask {
+                scrubbed.push('[');
+                scrubbed.push_str(kind.as_str());
+                scrubbed.push(']');
+            }
+            redactions.push(Redaction {
+                url: url.to_string(),
+                field: field.to_string(),
+                kind,
+                digest: redaction_digest(&text[start..end]),
+                redacted_at: now,
+            });
+            last = end;
+        }
+        scrubbed.push_str(&text[last..]);
+        self.redacted.fetch_add(redactions.len(), Ordering::Relaxed);
+        Some((scrubbed, redactions))
+    }
+
+    /// Scrub the title, text and metadata values of `content`
+    pub fn scrub_content(&self, content: &mut ExtractedContent) -> Vec<Redaction> {
+        let url = content.url.clone();
+        let mut redactions = Vec::new();
+        if let Some(title) = &mut content.title {
+            redactions.extend(self.scrub_shared(&url, "title", title));
+        }
+        if let Some(text) = &mut content.text {
+            redactions.extend(self.scrub_shared(&url, "text", text));
+        }
+        for (key, value) in content.metadata.iter_mut() {
+            redactions.extend(self.scrub_text(&url, &format!("metadata.{}", key), value));
+        }
+        redactions
+    }
+
+    /// Append `redactions` to the audit log, if there is one
+    pub fn audit(&self, redactions: &[Redaction]) -> Result<()> {
+        match &self.audit {
+            Some(log) if !redactions.is_empty() => log.record(redactions),
+            _ => Ok(()),
+        }
+    }
+
+    fn find(&self, kind: PiiKind, text: &str) -> Vec<(usize, usize)> {
+        let regex = match kind {
+            PiiKind::Email => &*EMAIL_REGEX,
+            PiiKind::Phone => &*PHONE_REGEX,
+            PiiKind::NationalId => &*NATIONAL_ID_REGEX,
+            PiiKind::Name => match &self.names {
+                Some(names) => names,
+                None => return Vec::new(),
+            },
+        };
+        regex
+            .find_iter(text)
+            .filter(|found| match kind {
+                PiiKind::Phone => is_phone_number(found.as_str()),
+                PiiKind::Name => found.as_str().starts_with(|c: char| c.is_uppercase()),
+                _ => true,
+            })
+            .map(|found| (found.start(), found.end()))
+            .collect()
+    }
+}
+
+/// Whether a [`PHONE_REGEX`] candidate is a phone number rather than a date,
+/// an IP address or some other number
+fn is_phone_number(candidate: &str) -> bool {
+    let digits = candidate.chars().filter(char::is_ascii_digit).count();
+    if !(9..=15).contains(&digits) || DATE_REGEX.is_match(candidate) {
+        return false;
+    }
+    let dotted_quad = candidate.split('.').count() == 4
+        && candidate
+            .split('.')
+            .all(|part| part.len() <= 3 && part.chars().all(|c| c.is_ascii_digit()));
+    !dotted_quad
+}
+
+impl Processor for PiiScrubber {
+    fn name(&self) -> &'static str {
+        "pii"
+    }
+
+    fn process<'a>(&'a self, content: &'a mut ExtractedContent) -> ProcessFuture<'a> {
+        Box::pin(async move {
+            let redactions = self.scrub_content(content);
+            if !redactions.is_empty() {
+                content
+                    .metadata
+                    .insert("pii_redactions".into(), redactions.len().to_string());
+                if !content.tags.iter().any(|tag| tag == "pii:redacted") {
+                    content.tags.push("pii:redacted".to_string());
+                }
+            }
+            self.audit(&redactions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_masks_emails_phones_and_ids() {
+        let scrubber = PiiScrubber::new(PiiKind::ALL);
+        let mut text = "Contact jane.doe@example.co.uk or +44 20 7946 0958, SSN 078-05-1120. \
+                        Released 2024-06-01 from 192.168.10.200, order 12345."
+            .to_string();
+        let redactions = scrubber.scrub_text("https://example.com/", "text", &mut text);
+        assert_eq!(
+            text,
+            "Contact [email] or [phone], SSN [national-id]. \
+             Released 2024-06-01 from 192.168.10.200, order 12345."
+        );
+        let kinds: Vec<PiiKind> = redactions.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![PiiKind::Email, PiiKind::Phone, PiiKind::NationalId]
+        );
+        assert_eq!(
+            redactions[0].digest,
+            redaction_digest("Jane.Doe@example.co.uk")
+        );
+        assert_eq!(scrubber.redacted(), 3);
+    }
+
+    #[test]
+    fn test_drops_dictionary_names() {
+        let scrubber = PiiScrubber::new([PiiKind::Email])
+            .with_mode(RedactionMode::Drop)
+            .with_names(["Rose", "Rose Tyler", "# comment"])
+            .unwrap();
+        let mut text = "Rose Tyler grows a rose. Ask Rose at rose@example.com.".to_string();
+        scrubber.scrub_text("https://example.com/", "text", &mut text);
+        assert_eq!(text, " grows a rose. Ask  at .");
+    }
+
+    #[tokio::test]
+    async fn test_processor_scrubs_content_and_audits() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("audit").join("pii.jsonl");
+        let scrubber = PiiScrubber::new([PiiKind::Email])
+            .with_audit_log(PiiAuditLog::open(&log_path).unwrap());
+        let mut content = ExtractedContent {
+            url: "https://example.com/team".to_string(),
+            title: Some("Team".into()),
+            text: Some("Write to press@example.com".into()),
+            metadata: HashMap::from([("author".into(), "ed@example.com".to_string())]),
+            tags: Vec::new(),
+            extracted_at: Utc::now(),
+        };
+        scrubber.process(&mut content).await.unwrap();
+
+        assert_eq!(content.text.as_deref(), Some("Write to [email]"));
+        assert_eq!(content.metadata["author"], "[email]");
+        assert_eq!(content.metadata["pii_redactions"], "2");
+        assert!(content.tags.contains(&"pii:redacted".to_string()));
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        let first: Redaction = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(first.digest, redaction_digest("press@example.com"));
+        // The log never holds the data it redacted
+        assert!(!log.contains("@example.com"));
+    }
+}
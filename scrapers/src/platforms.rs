@@ -3,22 +3,187 @@
 //! This module contains scrapers for different social media platforms
 //! and websites, each implementing the PlatformScraper trait.
 
-use crate::{ExtractedContent, PlatformScraper, ScraperConfig};
+use crate::{
+    api::ApiClient,
+    article::ArticleExtractor,
+    browser::BrowserPool,
+    drift::DriftMonitor,
+    enrichment::EnrichmentPipeline,
+    plugins::{self, PluginLimits},
+    policy::{PolicyResolver, ResolvedPolicy},
+    product::ProductExtractor,
+    schemas::SchemaRegistry,
+    scripting::{HookRequest, ScriptHooks},
+    streaming::{self, StreamedPage, StreamingLimits, STREAMING_THRESHOLD},
+    traps::{self, LinkGuard},
+    utils::{normalize_url, RobotsDirectives},
+    walls::WallDetector,
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
 use anyhow::Result;
-use std::collections::HashMap;
+use std::{path::Path, sync::Arc};
+use swoop_core::text::SharedStr;
+
+pub mod ebay;
+pub mod facebook;
+pub mod instagram;
+pub mod jobs;
+pub mod linkedin;
+pub mod serp;
+pub mod wiki;
+pub mod x;
+
+pub use ebay::EbayScraper;
+pub use facebook::FacebookScraper;
+pub use instagram::InstagramScraper;
+pub use jobs::JobBoardScraper;
+pub use linkedin::LinkedInScraper;
+pub use serp::SerpScraper;
+pub use wiki::WikiScraper;
+pub use x::XScraper;
+
+/// Metadata key holding the page's normalized `<link rel="canonical">` URL
+pub const CANONICAL_URL_KEY: &str = "canonical_url";
+
+/// Metadata key listing the robots meta/`X-Robots-Tag` directives a page
+/// carries, e.g. `noindex,nofollow`
+pub const ROBOTS_DIRECTIVES_KEY: &str = "robots_directives";
+
+/// A scraper for a platform that requires accounts was used without a session
+///
+/// Raised by scrapers that need a logged-in session before they send requests,
+/// unless logged-out access was enabled explicitly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRequired {
+    pub platform: &'static str,
+}
+
+impl std::fmt::Display for SessionRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} scraping needs a logged-in session: add a `cookie` header for `{}` to the scraper's AuthStore, or enable logged-out requests with `with_anonymous_access`",
+            self.platform, self.platform
+        )
+    }
+}
+
+impl std::error::Error for SessionRequired {}
 
 /// Generic web scraper for standard websites
 pub struct GenericScraper {
     config: ScraperConfig,
+    streaming_threshold: usize,
+    streaming_limits: StreamingLimits,
+    router: MimeRouter,
+    /// Largest non-HTML body read before giving up on the response
+    max_binary_bytes: u64,
 }
 
 impl GenericScraper {
     pub fn new(config: ScraperConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            streaming_threshold: STREAMING_THRESHOLD,
+            streaming_limits: StreamingLimits::default(),
+            router: MimeRouter::new(),
+            max_binary_bytes: DEFAULT_MAX_BINARY_BYTES,
+        }
+    }
+
+    /// Route non-HTML responses by content type with `router`
+    pub fn with_router(mut self, router: MimeRouter) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// Extract pages larger than `bytes` while streaming, with `limits` on
+    /// what is kept of them
+    pub fn with_streaming(mut self, bytes: usize, limits: StreamingLimits) -> Self {
+        self.streaming_threshold = bytes;
+        self.streaming_limits = limits;
+        self
+    }
+
+    /// Stop reading non-HTML responses larger than `bytes`, by default the
+    /// safety filters' `max_binary_bytes`
+    pub fn with_max_binary_bytes(mut self, bytes: u64) -> Self {
+        self.max_binary_bytes = bytes;
+        self
+    }
+}
+
+use crate::mime::{MimeRouter, Route};
+use crate::safety::DEFAULT_MAX_BINARY_BYTES;
+use std::time::{Duration, Instant};
+use swoop_core::client::ResponseMeta;
+
+/// Run the generic extractors over `html`, flagging login/consent/paywall pages
+/// and recording product and article data
+///
+/// The title is a slice of `html`, sharing its buffer.
+pub(crate) fn content_from_html(url: String, html: &SharedStr) -> ExtractedContent {
+    let title = crate::extractors::find_title(html).map(|title| html.share(title));
+    let text = crate::extractors::extract_text_secure(html)
+        .ok()
+        .map(SharedStr::from);
+    let mut metadata = crate::extractors::extract_metadata_secure(html).unwrap_or_default();
+    if let Some(canonical) = crate::extractors::extract_canonical(html, &url) {
+        metadata.insert(CANONICAL_URL_KEY.into(), normalize_url(&canonical));
+    }
+    if let Some(wall) = WallDetector::new().detect(html, Some(&url)) {
+        wall.record(&mut metadata);
+    }
+    let mut tags = Vec::new();
+    if let Some(product) = ProductExtractor::new().extract(html, &url) {
+        product.record(&mut metadata);
+        tags.push("product".to_string());
+    }
+    // Articles keep only their readable body rather than the whole page's text
+    let text = match ArticleExtractor::new().extract(html, &url) {
+        Some(article) => {
+            article.record(&mut metadata);
+            tags.push("article".to_string());
+            Some(article.text.into())
+        }
+        None => text,
+    };
+
+    ExtractedContent {
+        url,
+        title,
+        text,
+        metadata,
+        tags,
+        extracted_at: chrono::Utc::now(),
     }
 }
 
-use std::time::Duration;
+/// Content of a page extracted by the streaming pass, which skips the
+/// whole-document extractors (walls, products, articles)
+fn content_from_stream(url: String, page: StreamedPage) -> ExtractedContent {
+    let mut metadata = page.metadata;
+    if let Some(canonical) = &page.canonical_url {
+        metadata.insert(CANONICAL_URL_KEY.into(), normalize_url(canonical));
+    }
+    if let Some(language) = page.language {
+        metadata.insert("language".into(), language);
+    }
+    metadata.insert("fetched_via".into(), "stream".to_string());
+    metadata.insert("content_length".into(), page.bytes.to_string());
+    metadata.insert("links".into(), page.links.len().to_string());
+    if page.text_truncated {
+        metadata.insert("text_truncated".into(), "true".to_string());
+    }
+    ExtractedContent {
+        url,
+        title: page.title.map(SharedStr::from),
+        text: Some(page.text.into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: chrono::Utc::now(),
+    }
+}
 
 impl PlatformScraper for GenericScraper {
     fn extract(
@@ -30,21 +195,68 @@ impl PlatformScraper for GenericScraper {
         let timeout = self.config.timeout_secs;
         Box::pin(async move {
             // Use the core HTTP client to fetch the page
-            let html_bytes = swoop_core::fetch_url(&url, Duration::from_secs(timeout)).await?;
-            let html = String::from_utf8_lossy(&html_bytes);
-
-            // Extract content using our extractors
-            let title = crate::extractors::extract_title(&html).unwrap_or(None);
-            let text = crate::extractors::extract_text_secure(&html).ok();
-            let metadata = crate::extractors::extract_metadata_secure(&html).unwrap_or_default();
-
-            Ok(ExtractedContent {
-                url,
-                title,
-                text,
-                metadata,
-                extracted_at: chrono::Utc::now(),
-            })
+            let started = Instant::now();
+            let mut response = swoop_core::open_url(&url, Duration::from_secs(timeout)).await?;
+            let mut meta = ResponseMeta::from_response(&response, started);
+            let mut html = response.chunk().await?.map(Vec::from).unwrap_or_default();
+            // Without a declared type, sniff the first chunk
+            let content_type = crate::extractors::detect_content_type(
+                &String::from_utf8_lossy(&html[..html.len().min(1024)]),
+                meta.content_type(),
+            );
+            if !matches!(self.router.route(&content_type), Route::Html) {
+                // Binaries can be any size, so don't read past the limit
+                let limit = self.max_binary_bytes;
+                if let Some(length) = response.content_length().filter(|length| *length > limit) {
+                    anyhow::bail!(
+                        "{} body of {} bytes, over the {}-byte limit",
+                        content_type,
+                        length,
+                        limit
+                    );
+                }
+                loop {
+                    if html.len() as u64 > limit {
+                        anyhow::bail!("{} body over the {}-byte limit", content_type, limit);
+                    }
+                    let Some(chunk) = response.chunk().await? else {
+                        break;
+                    };
+                    html.extend_from_slice(&chunk);
+                }
+                let mut content = self
+                    .router
+                    .dispatch(&url, &content_type, html.into())
+                    .await?;
+                meta.finish(started);
+                meta.record_metadata(&mut content.metadata);
+                return Ok(content);
+            }
+
+            let threshold = self.streaming_threshold;
+            let mut stream = html.len() > threshold
+                || response
+                    .content_length()
+                    .is_some_and(|length| length > threshold as u64);
+            // Buffer until the page turns out to be too large to hold
+            while !stream {
+                let Some(chunk) = response.chunk().await? else {
+                    break;
+                };
+                html.extend_from_slice(&chunk);
+                stream = html.len() > threshold;
+            }
+            let mut content = if stream {
+                let page =
+                    streaming::extract_response(&url, html, response, self.streaming_limits)
+                        .await?;
+                content_from_stream(url, page)
+            } else {
+                content_from_html(url, &SharedStr::from_body(html.into()))
+            };
+            meta.finish(started);
+            meta.record_metadata(&mut content.metadata);
+            Ok(content)
         })
     }
 
@@ -56,159 +268,332 @@ impl PlatformScraper for GenericScraper {
     fn platform_name(&self) -> &'static str {
         "generic"
     }
+
+    fn extract_from_html(&self, url: &str, html: &SharedStr) -> Option<Result<ExtractedContent>> {
+        Some(Ok(content_from_html(url.to_string(), html)))
+    }
 }
 
-/// Placeholder for Facebook scraper
-pub struct FacebookScraper {
-    _config: ScraperConfig,
+/// Scraper registry for managing multiple platform scrapers
+pub struct ScraperRegistry {
+    scrapers: Vec<Box<dyn PlatformScraper + Send + Sync>>,
+    hooks: Option<Arc<ScriptHooks>>,
+    enrichment: Option<Arc<EnrichmentPipeline>>,
+    browser: Option<Arc<BrowserPool>>,
+    schemas: Option<Arc<SchemaRegistry>>,
+    drift: Option<Arc<DriftMonitor>>,
+    policies: Option<Arc<PolicyResolver>>,
+    link_guard: Arc<LinkGuard>,
+    api: ApiClient,
 }
 
-impl FacebookScraper {
-    pub fn new(config: ScraperConfig) -> Self {
-        Self { _config: config }
+impl ScraperRegistry {
+    pub fn new() -> Self {
+        Self {
+            scrapers: Vec::new(),
+            hooks: None,
+            enrichment: None,
+            browser: None,
+            schemas: None,
+            drift: None,
+            policies: None,
+            link_guard: Arc::new(LinkGuard::default()),
+            api: ApiClient::default(),
+        }
     }
-}
 
-impl PlatformScraper for FacebookScraper {
-    fn extract(
-        &self,
-        url: &str,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
-    {
-        let url = url.to_string();
-        Box::pin(async move {
-            // TODO: Implement Facebook-specific scraping logic
-            // This would include handling login, cookies, rate limiting, etc.
-
-            // For now, return a placeholder
-            Ok(ExtractedContent {
-                url,
-                title: Some("Facebook Content".to_string()),
-                text: Some("Facebook scraping not yet implemented".to_string()),
-                metadata: HashMap::new(),
-                extracted_at: chrono::Utc::now(),
-            })
-        })
+    /// Run user script hooks around every extraction
+    pub fn with_hooks(mut self, hooks: Arc<ScriptHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
     }
 
-    fn can_handle(&self, url: &str) -> bool {
-        url.contains("facebook.com") || url.contains("fb.com")
+    /// Enrich every extracted document before the `post_extract` hook runs
+    pub fn with_enrichment(mut self, pipeline: Arc<EnrichmentPipeline>) -> Self {
+        self.enrichment = Some(pipeline);
+        self
     }
 
-    fn platform_name(&self) -> &'static str {
-        "facebook"
+    /// Replace the default honeypot and crawler-trap checks on followed links
+    pub fn with_link_guard(mut self, guard: Arc<LinkGuard>) -> Self {
+        self.link_guard = guard;
+        self
     }
-}
 
-/// Placeholder for Instagram scraper
-pub struct InstagramScraper {
-    _config: ScraperConfig,
-}
+    /// Re-fetch pages flagged as login, consent or paywall walls through a browser
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
 
-impl InstagramScraper {
-    pub fn new(config: ScraperConfig) -> Self {
-        Self { _config: config }
+    /// Validate extracted content against per-platform schemas
+    ///
+    /// Content that doesn't match fails with a [`crate::schemas::SchemaViolation`].
+    pub fn with_schemas(mut self, schemas: Arc<SchemaRegistry>) -> Self {
+        self.schemas = Some(schemas);
+        self
     }
-}
 
-impl PlatformScraper for InstagramScraper {
-    fn extract(
-        &self,
-        url: &str,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
-    {
-        let url = url.to_string();
-        Box::pin(async move {
-            // TODO: Implement Instagram-specific scraping logic
+    /// Track extraction rule match rates and alert when a rule stops matching
+    pub fn with_drift_monitor(mut self, monitor: Arc<DriftMonitor>) -> Self {
+        self.drift = Some(monitor);
+        self
+    }
 
-            Ok(ExtractedContent {
-                url,
-                title: Some("Instagram Content".to_string()),
-                text: Some("Instagram scraping not yet implemented".to_string()),
-                metadata: HashMap::new(),
-                extracted_at: chrono::Utc::now(),
-            })
-        })
+    /// Apply per-domain policies: browser-only domains, extraction rule sets
+    /// and crawl depth limits
+    pub fn with_policies(mut self, policies: Arc<PolicyResolver>) -> Self {
+        self.policies = Some(policies);
+        self
     }
 
-    fn can_handle(&self, url: &str) -> bool {
-        url.contains("instagram.com")
+    /// Client for scrapers that fetch through GraphQL or JSON APIs, e.g. one
+    /// carrying persisted auth headers
+    pub fn with_api_client(mut self, client: ApiClient) -> Self {
+        self.api = client;
+        self
     }
 
-    fn platform_name(&self) -> &'static str {
-        "instagram"
+    fn policy(&self, url: &str) -> Option<ResolvedPolicy> {
+        self.policies.as_ref().map(|policies| policies.resolve(url, None))
     }
-}
 
-/// Placeholder for LinkedIn scraper
-pub struct LinkedInScraper {
-    _config: ScraperConfig,
-}
+    pub fn register<T: PlatformScraper + Send + Sync + 'static>(&mut self, scraper: T) {
+        self.scrapers.push(Box::new(scraper));
+    }
 
-impl LinkedInScraper {
-    pub fn new(config: ScraperConfig) -> Self {
-        Self { _config: config }
+    /// Load WASM plugins from `dir`; plugins take precedence over built-in scrapers
+    pub fn load_plugins(&mut self, dir: impl AsRef<Path>, limits: &PluginLimits) -> Result<usize> {
+        let loaded = plugins::load_plugins(dir, limits)?;
+        let count = loaded.len();
+        for (index, plugin) in loaded.into_iter().enumerate() {
+            self.scrapers.insert(index, Box::new(plugin));
+        }
+        Ok(count)
     }
-}
 
-impl PlatformScraper for LinkedInScraper {
-    fn extract(
-        &self,
-        url: &str,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
-    {
-        let url = url.to_string();
-        Box::pin(async move {
-            // TODO: Implement LinkedIn-specific scraping logic
+    /// Scraper for `url`: the one a policy route names, otherwise the first
+    /// registered scraper that can handle it
+    pub fn find_scraper(&self, url: &str) -> Option<&(dyn PlatformScraper + Send + Sync)> {
+        let routed = self
+            .policy(url)
+            .and_then(|policy| policy.platform)
+            .and_then(|platform| {
+                self.scrapers
+                    .iter()
+                    .find(|scraper| scraper.platform_name() == platform)
+            });
+        routed
+            .or_else(|| self.scrapers.iter().find(|scraper| scraper.can_handle(url)))
+            .map(|scraper| scraper.as_ref())
+    }
 
-            Ok(ExtractedContent {
-                url,
-                title: Some("LinkedIn Content".to_string()),
-                text: Some("LinkedIn scraping not yet implemented".to_string()),
-                metadata: HashMap::new(),
-                extracted_at: chrono::Utc::now(),
-            })
-        })
+    /// What extracting `url` needs: its scraper's declaration, raised by the
+    /// domain policy's `browser_required` and `proxy_tier`
+    pub fn capabilities(&self, url: &str) -> Option<ScraperCapabilities> {
+        let mut capabilities = self.find_scraper(url)?.capabilities(url);
+        if let Some(policy) = self.policy(url) {
+            if policy.browser_required {
+                capabilities.browser = Need::Required;
+            }
+            capabilities.proxy_tier = policy.proxy_tier.or(capabilities.proxy_tier);
+        }
+        Some(capabilities)
     }
 
-    fn can_handle(&self, url: &str) -> bool {
-        url.contains("linkedin.com")
+    pub async fn extract(&self, url: &str) -> Result<ExtractedContent> {
+        let url = match &self.hooks {
+            Some(hooks) => match hooks.pre_request(HookRequest::new(url))? {
+                Some(request) => request.url,
+                None => anyhow::bail!("Skipped by pre_request hook: {}", url),
+            },
+            None => url.to_string(),
+        };
+
+        let Some(scraper) = self.find_scraper(&url) else {
+            anyhow::bail!("No scraper found for URL: {}", url)
+        };
+        let browser_required = self
+            .capabilities(&url)
+            .is_some_and(|capabilities| capabilities.browser == Need::Required);
+        let content = match &self.browser {
+            Some(pool) if browser_required => Self::extract_with_browser(pool, &url).await?,
+            None if browser_required => anyhow::bail!(
+                "{} requires a browser for {} but none is configured",
+                scraper.platform_name(),
+                url
+            ),
+            _ => {
+                let content = self.extract_with_scraper(scraper, &url).await?;
+                match &self.browser {
+                    Some(pool) if content.metadata.contains_key("wall") => {
+                        Self::extract_with_browser(pool, &url).await?
+                    }
+                    _ => content,
+                }
+            }
+        };
+        self.finish(scraper.platform_name(), &url, content).await
     }
 
-    fn platform_name(&self) -> &'static str {
-        "linkedin"
+    /// Extract through the scraper's API requests if it has any, otherwise
+    /// through [`PlatformScraper::extract`]
+    async fn extract_with_scraper(
+        &self,
+        scraper: &(dyn PlatformScraper + Send + Sync),
+        url: &str,
+    ) -> Result<ExtractedContent> {
+        let requests = scraper.api_requests(url);
+        if !requests.is_empty() {
+            let responses = self.api.fetch_all(scraper.platform_name(), &requests).await?;
+            if let Some(content) = scraper.extract_from_api(url, &responses) {
+                return content;
+            }
+        }
+        scraper.extract(url).await
     }
-}
 
-/// Scraper registry for managing multiple platform scrapers
-pub struct ScraperRegistry {
-    scrapers: Vec<Box<dyn PlatformScraper + Send + Sync>>,
-}
+    /// Run the extractors over previously fetched HTML, e.g. stored content,
+    /// followed by the same validation, enrichment and hooks as [`Self::extract`]
+    pub async fn reprocess(&self, url: &str, html: &SharedStr) -> Result<ExtractedContent> {
+        let Some(scraper) = self.find_scraper(url) else {
+            anyhow::bail!("No scraper found for URL: {}", url)
+        };
+        let content = match scraper.extract_from_html(url, html) {
+            Some(content) => content?,
+            None => content_from_html(url.to_string(), html),
+        };
+        self.finish(scraper.platform_name(), url, content).await
+    }
 
-impl ScraperRegistry {
-    pub fn new() -> Self {
-        Self {
-            scrapers: Vec::new(),
+    async fn finish(
+        &self,
+        platform: &str,
+        url: &str,
+        mut content: ExtractedContent,
+    ) -> Result<ExtractedContent> {
+        let policy = self.policy(url);
+        let directives = RobotsDirectives::from_metadata(&content.metadata);
+        if !directives.is_empty() {
+            content
+                .metadata
+                .insert(ROBOTS_DIRECTIVES_KEY.into(), directives.names().join(","));
+            let respect = policy
+                .as_ref()
+                .is_some_and(|policy| policy.respect_robots_meta);
+            if directives.noindex && respect {
+                anyhow::bail!("Not kept, page is marked noindex: {}", url);
+            }
+        }
+        // A policy's extraction rule set replaces the platform's schema
+        let extraction = policy.and_then(|policy| policy.extraction);
+        let platform = extraction.as_deref().unwrap_or(platform);
+        if let Some(schemas) = &self.schemas {
+            let validated = schemas.apply(platform, content);
+            if let (Some(drift), Some(schema)) = (&self.drift, schemas.schema_for(platform)) {
+                let rule = format!("schema:{}", schema);
+                if let Some(alert) = drift.record(platform, &rule, validated.is_ok()) {
+                    drift.notify(&alert).await;
+                }
+            }
+            content = validated?;
+        }
+        if let Some(drift) = &self.drift {
+            drift.observe(platform, &mut content).await;
+        }
+        if let Some(pipeline) = &self.enrichment {
+            pipeline.run(platform, &mut content).await;
+        }
+
+        match &self.hooks {
+            Some(hooks) => hooks
+                .post_extract(content)?
+                .ok_or_else(|| anyhow::anyhow!("Dropped by post_extract hook: {}", url)),
+            None => Ok(content),
         }
     }
 
-    pub fn register<T: PlatformScraper + Send + Sync + 'static>(&mut self, scraper: T) {
-        self.scrapers.push(Box::new(scraper));
+    async fn extract_with_browser(pool: &BrowserPool, url: &str) -> Result<ExtractedContent> {
+        let page = pool.get_browser().await?.scrape_page(url).await?;
+        let mut content = content_from_html(page.url, &page.html.into());
+        content
+            .metadata
+            .insert("fetched_via".into(), "browser".to_string());
+        Ok(content)
     }
 
-    pub fn find_scraper(&self, url: &str) -> Option<&(dyn PlatformScraper + Send + Sync)> {
-        self.scrapers
-            .iter()
-            .find(|scraper| scraper.can_handle(url))
-            .map(|scraper| scraper.as_ref())
+    /// Keep only the links the `should_follow_link` hook accepts
+    pub fn filter_links(&self, from: &str, links: Vec<String>) -> Result<Vec<String>> {
+        self.filter_links_at_depth(from, 1, links)
     }
 
-    pub async fn extract(&self, url: &str) -> Result<ExtractedContent> {
-        if let Some(scraper) = self.find_scraper(url) {
-            scraper.extract(url).await
-        } else {
-            anyhow::bail!("No scraper found for URL: {}", url)
+    /// Like [`Self::filter_links`] for links `depth` levels below a seed,
+    /// also dropping links beyond their domain policy's `max_depth`
+    pub fn filter_links_at_depth(
+        &self,
+        from: &str,
+        depth: u32,
+        links: Vec<String>,
+    ) -> Result<Vec<String>> {
+        let links: Vec<String> = match &self.policies {
+            Some(policies) => links
+                .into_iter()
+                .filter(|link| policies.resolve(link, None).allows_depth(depth))
+                .collect(),
+            None => links,
+        };
+        let links = match &self.hooks {
+            Some(hooks) => {
+                let mut followed = Vec::with_capacity(links.len());
+                for link in links {
+                    if hooks.should_follow_link(&link, from)? {
+                        followed.push(link);
+                    }
+                }
+                followed
+            }
+            None => links,
+        };
+        // Last, so links dropped above don't count towards template caps
+        Ok(links
+            .into_iter()
+            .filter(|link| self.link_guard.check_url(link).is_ok())
+            .collect())
+    }
+
+    /// Links on the page at `from` worth following, `depth` levels below a
+    /// seed: hidden links and suspected honeypots are skipped before
+    /// [`Self::filter_links_at_depth`] runs
+    ///
+    /// Under a policy with `respect_robots_meta`, a page marked `nofollow`
+    /// yields no links and `rel="nofollow"` links are skipped.
+    /// With `[referrers]` in the policy file, the links returned are recorded
+    /// as linked from `from`.
+    pub fn links_to_follow(&self, from: &str, depth: u32, html: &str) -> Result<Vec<String>> {
+        let respect_nofollow = self
+            .policy(from)
+            .is_some_and(|policy| policy.respect_robots_meta);
+        if respect_nofollow && RobotsDirectives::from_page(html, None).nofollow {
+            return Ok(Vec::new());
+        }
+        let mut seen = std::collections::HashSet::new();
+        let links: Vec<String> = traps::page_links(html, from)
+            .into_iter()
+            .filter(|link| self.link_guard.check_link(from, link).is_ok())
+            .filter(|link| !(respect_nofollow && link.is_nofollow()))
+            .map(|link| link.url)
+            .filter(|url| seen.insert(url.clone()))
+            .collect();
+        let links = self.filter_links_at_depth(from, depth, links)?;
+        if let Some(referrers) = self.policies.as_ref().and_then(|p| p.referrers()) {
+            referrers.record_links(from, &links);
         }
+        Ok(links)
+    }
+
+    /// Links skipped as suspected honeypots so far
+    pub fn honeypots(&self) -> Vec<traps::Honeypot> {
+        self.link_guard.honeypots()
     }
 }
 
@@ -221,6 +606,11 @@ impl Default for ScraperRegistry {
         registry.register(FacebookScraper::new(config.clone()));
         registry.register(InstagramScraper::new(config.clone()));
         registry.register(LinkedInScraper::new(config.clone()));
+        registry.register(XScraper::new(config.clone()));
+        registry.register(SerpScraper::new(config.clone()));
+        registry.register(EbayScraper::new(config.clone()));
+        registry.register(WikiScraper::new(config.clone()));
+        registry.register(JobBoardScraper::new(config.clone()));
         registry.register(GenericScraper::new(config));
 
         registry
@@ -231,6 +621,266 @@ impl Default for ScraperRegistry {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_registry_filters_links_with_hooks() {
+        let hooks = ScriptHooks::from_source(
+            r#"fn should_follow_link(url, from) { !url.ends_with(".pdf") }"#,
+        )
+        .unwrap();
+        let registry = ScraperRegistry::default().with_hooks(Arc::new(hooks));
+
+        let links = vec![
+            "https://example.com/a".to_string(),
+            "https://example.com/b.pdf".to_string(),
+        ];
+        assert_eq!(
+            registry.filter_links("https://example.com", links).unwrap(),
+            vec!["https://example.com/a".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_registry_skips_trap_links() {
+        let registry = ScraperRegistry::default()
+            .with_link_guard(Arc::new(LinkGuard::new().with_max_per_template(2)));
+        let html = r#"
+            <a href="/tag/1">One</a><a href="/tag/2">Two</a><a href="/tag/3">Three</a>
+            <div style="display:none"><a href="/wp-login" rel="nofollow">Login</a></div>
+            <a href="/spider-trap">Sitemap</a>
+        "#;
+        assert_eq!(
+            registry
+                .links_to_follow("https://example.com/", 1, html)
+                .unwrap(),
+            vec!["https://example.com/tag/1", "https://example.com/tag/2"]
+        );
+        assert_eq!(registry.honeypots().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_registry_honors_robots_meta() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."strict.example"]
+            respect_robots_meta = true
+            "#,
+        )
+        .unwrap();
+        let registry = ScraperRegistry::default().with_policies(Arc::new(policies));
+        let html = SharedStr::from(
+            r#"<html><head><meta name="robots" content="noindex, nofollow"></head>
+            <body><a href="/next">Next</a></body></html>"#,
+        );
+
+        // Directives are recorded everywhere, enforced where the policy asks
+        let content = registry
+            .reprocess("https://other.example/", &html)
+            .await
+            .unwrap();
+        assert_eq!(content.metadata[ROBOTS_DIRECTIVES_KEY], "noindex,nofollow");
+        assert!(registry
+            .reprocess("https://strict.example/", &html)
+            .await
+            .is_err());
+
+        assert!(registry
+            .links_to_follow("https://strict.example/", 1, &html)
+            .unwrap()
+            .is_empty());
+        let sponsored = r#"<a href="/ad" rel="nofollow sponsored">Ad</a><a href="/b">B</a>"#;
+        assert_eq!(
+            registry
+                .links_to_follow("https://strict.example/", 1, sponsored)
+                .unwrap(),
+            vec!["https://strict.example/b"]
+        );
+        assert_eq!(
+            registry
+                .links_to_follow("https://other.example/", 1, &html)
+                .unwrap(),
+            vec!["https://other.example/next"]
+        );
+    }
+
+    #[test]
+    fn test_registry_records_link_referrers() {
+        let policies = PolicyResolver::from_toml("[referrers]\nentry = \"direct\"").unwrap();
+        let registry = ScraperRegistry::default().with_policies(Arc::new(policies));
+        let html = r#"<a href="/c/shoes">Shoes</a>"#;
+        registry
+            .links_to_follow("https://shop.example/sale", 1, html)
+            .unwrap();
+
+        let referrers = registry.policies.as_ref().unwrap().referrers().unwrap();
+        let referer = referrers.navigate("https://shop.example/c/shoes");
+        assert_eq!(referer.as_deref(), Some("https://shop.example/sale"));
+    }
+
+    #[tokio::test]
+    async fn test_registry_applies_domain_policies() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [domains."shop.example.com"]
+            extraction = "product"
+            max_depth = 1
+            "#,
+        )
+        .unwrap();
+        let schemas = SchemaRegistry::new().with_schema::<crate::schemas::Product>("product");
+        let registry = ScraperRegistry::default()
+            .with_schemas(Arc::new(schemas))
+            .with_policies(Arc::new(policies));
+
+        // Only the shop's pages are held to the product rule set
+        let html =
+            SharedStr::from("<html><head><title>About us</title></head><body>Hello</body></html>");
+        assert!(registry
+            .reprocess("https://shop.example.com/about", &html)
+            .await
+            .is_err());
+        assert!(registry
+            .reprocess("https://blog.example.com/about", &html)
+            .await
+            .is_ok());
+
+        let links = vec![
+            "https://shop.example.com/item/1".to_string(),
+            "https://other.org/page".to_string(),
+        ];
+        assert_eq!(
+            registry
+                .filter_links_at_depth("https://other.org/", 2, links)
+                .unwrap(),
+            vec!["https://other.org/page"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_reports_capabilities() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [proxies]
+            residential = "http://proxy.example:8080"
+
+            [domains."app.example.com"]
+            browser_required = true
+            proxy_tier = "residential"
+            "#,
+        )
+        .unwrap();
+        let registry = ScraperRegistry::default().with_policies(Arc::new(policies));
+
+        let serp = registry
+            .capabilities("https://www.google.com/search?q=rust&gl=de")
+            .unwrap();
+        assert_eq!(serp.browser, Need::Optional);
+        assert_eq!(serp.proxy_country.as_deref(), Some("de"));
+        let linkedin = registry
+            .capabilities("https://www.linkedin.com/in/someone")
+            .unwrap();
+        assert!(linkedin.post);
+        assert_eq!(
+            registry.capabilities("https://example.com/"),
+            Some(ScraperCapabilities::default())
+        );
+
+        // A browser-only domain fails before anything is fetched
+        let app = registry.capabilities("https://app.example.com/").unwrap();
+        assert_eq!(app.browser, Need::Required);
+        assert_eq!(
+            app.proxy_tier,
+            Some(crate::anti_bot::proxy_rotator::ProxyType::Residential)
+        );
+        let error = registry
+            .extract("https://app.example.com/")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("requires a browser"));
+    }
+
+    /// Scraper that reads a profile from a GraphQL endpoint
+    struct ApiScraper {
+        endpoint: String,
+    }
+
+    impl PlatformScraper for ApiScraper {
+        fn extract(
+            &self,
+            _url: &str,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>,
+        > {
+            Box::pin(async { anyhow::bail!("HTML extraction should not be used") })
+        }
+
+        fn can_handle(&self, url: &str) -> bool {
+            url.starts_with("https://social.example/")
+        }
+
+        fn platform_name(&self) -> &'static str {
+            "social"
+        }
+
+        fn api_requests(&self, url: &str) -> Vec<crate::api::ApiRequest> {
+            let user = url.rsplit('/').next().unwrap_or_default();
+            vec![crate::api::ApiRequest::graphql(
+                &self.endpoint,
+                "query User($login: String!) { user(login: $login) { name } }",
+            )
+            .with_variable("login", user)]
+        }
+
+        fn extract_from_api(
+            &self,
+            url: &str,
+            responses: &[serde_json::Value],
+        ) -> Option<Result<ExtractedContent>> {
+            let name = responses.first()?.pointer("/data/user/name")?.as_str()?;
+            Some(Ok(ExtractedContent {
+                url: url.to_string(),
+                title: Some(name.into()),
+                text: None,
+                metadata: std::collections::HashMap::new(),
+                tags: Vec::new(),
+                extracted_at: chrono::Utc::now(),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_extracts_through_api_requests() {
+        use wiremock::{
+            matchers::{body_partial_json, method},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({ "variables": { "login": "ferris" } }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({ "data": { "user": { "name": "Ferris" } } }),
+            ))
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let validator = swoop_core::security::UrlValidator::default()
+            .with_allowed_host(host.host_str().unwrap());
+        let mut registry =
+            ScraperRegistry::new().with_api_client(ApiClient::default().with_validator(validator));
+        registry.register(ApiScraper {
+            endpoint: format!("{}/graphql", server.uri()),
+        });
+
+        let content = registry
+            .extract("https://social.example/ferris")
+            .await
+            .unwrap();
+        assert_eq!(content.title.as_deref(), Some("Ferris"));
+    }
+
     #[test]
     fn test_generic_scraper_can_handle() {
         let scraper = GenericScraper::new(ScraperConfig::default());
@@ -261,4 +911,28 @@ mod tests {
         assert!(generic_scraper.is_some());
         assert_eq!(generic_scraper.unwrap().platform_name(), "generic");
     }
+
+    #[test]
+    fn test_registry_follows_policy_routes() {
+        let policies = PolicyResolver::from_toml(
+            r#"
+            [[routes]]
+            pattern = "www.facebook.com/help/**"
+            platform = "generic"
+
+            [[routes]]
+            pattern = "*.example.com"
+            platform = "no-such-scraper"
+            "#,
+        )
+        .unwrap();
+        let registry = ScraperRegistry::default().with_policies(Arc::new(policies));
+
+        let name = |url| registry.find_scraper(url).unwrap().platform_name();
+        assert_eq!(name("https://www.facebook.com/help/123"), "generic");
+        assert_eq!(name("https://www.facebook.com/ferris"), "facebook");
+        assert_eq!(name("https://www.linkedin.com/in/someone"), "linkedin");
+        // Unknown names fall back to the scrapers' own checks
+        assert_eq!(name("https://www.example.com/"), "generic");
+    }
 }
@@ -0,0 +1,784 @@
+//! eBay scraper
+//!
+//! Parses listing pages (`/itm/<id>`) for price, condition, seller, shipping
+//! and bids, and search result pages (`/sch/i.html?_nkw=...`) with pagination.
+//! Both emit the same [`ExtractedProduct`] data as the generic product
+//! extractor, recorded as `product:*` metadata, so eBay results validate
+//! against the `product` schema and compare directly with other shops.
+//! eBay-specific fields go in `ebay:*` metadata. Requests are paced per host
+//! like [`super::SerpScraper`]'s, and eBay's bot check page either fails the
+//! request with [`EbayBlocked`] or, with a browser configured, is rendered
+//! there instead.
+
+use crate::{
+    browser::BrowserPool,
+    extractors::{decode_entities, element_inner_html, html_tags, HtmlTag},
+    metadata::Metadata,
+    product::{
+        currency_code, normalize_availability, parse_price, ExtractedProduct, ProductExtractor,
+        ProductSource,
+    },
+    rate_limiter::{HostThrottle, RateProfile},
+    router::UrlRouter,
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+use swoop_core::{text::SharedStr, trace::FetchTracer};
+
+const PLATFORM: &str = "ebay";
+const SITE_URL: &str = "https://www.ebay.com";
+const USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0";
+
+/// eBay sites on any country domain
+static HOSTS: Lazy<UrlRouter<()>> =
+    Lazy::new(|| UrlRouter::from_patterns(&["ebay.*", "www.ebay.*", "m.ebay.*"], ()).unwrap());
+static STRIP_TAGS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static COUNT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d[\d,.]*)").unwrap());
+static FEEDBACK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d{1,3}(?:\.\d+)?)%\s*positive").unwrap());
+static ITEM_ID_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/itm/(?:[^/?#]+/)?(\d{9,})").unwrap());
+
+/// eBay answered with its bot check instead of the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EbayBlocked;
+
+impl fmt::Display for EbayBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "eBay answered with its bot check page")
+    }
+}
+
+impl std::error::Error for EbayBlocked {}
+
+/// Whether a response is eBay's bot check rather than the requested page
+pub fn is_blocked(final_url: Option<&str>, status: Option<u16>, html: &str) -> bool {
+    final_url.is_some_and(|url| url.contains("/splashui/captcha"))
+        || status == Some(429)
+        || html.contains("Pardon Our Interruption")
+}
+
+/// A listing page on eBay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EbayListing {
+    pub url: String,
+    pub item_id: Option<String>,
+    pub title: Option<String>,
+    pub price: Option<f64>,
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: Option<String>,
+    /// eBay's condition label, e.g. `New` or `Used`
+    pub condition: Option<String>,
+    pub seller: Option<String>,
+    /// Positive feedback percentage
+    pub seller_feedback: Option<f64>,
+    /// Shipping as shown, e.g. `Free Standard Shipping`
+    pub shipping: Option<String>,
+    /// Shipping cost in the listing's currency; `0` for free shipping
+    pub shipping_cost: Option<f64>,
+    /// Bids on an auction; `None` for fixed-price listings
+    pub bids: Option<u32>,
+    pub images: Vec<String>,
+    /// The product extracted from the page's structured data and eBay markup
+    #[serde(skip)]
+    pub product: ExtractedProduct,
+}
+
+impl EbayListing {
+    /// Record the listing as `product:*` and `ebay:*` metadata
+    pub fn record(&self, metadata: &mut Metadata) {
+        self.product.record(metadata);
+        let fields = [
+            ("ebay:item_id", self.item_id.clone()),
+            ("ebay:condition", self.condition.clone()),
+            ("ebay:seller", self.seller.clone()),
+            (
+                "ebay:seller_feedback",
+                self.seller_feedback.map(|feedback| feedback.to_string()),
+            ),
+            ("ebay:shipping", self.shipping.clone()),
+            (
+                "ebay:shipping_cost",
+                self.shipping_cost.map(|cost| cost.to_string()),
+            ),
+            ("ebay:bids", self.bids.map(|bids| bids.to_string())),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                metadata.insert(key.into(), value);
+            }
+        }
+    }
+}
+
+/// One result on a search page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EbaySearchItem {
+    pub item_id: Option<String>,
+    pub url: String,
+    pub title: String,
+    /// Lowest price for listings with a price range
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub condition: Option<String>,
+    pub shipping: Option<String>,
+    pub shipping_cost: Option<f64>,
+    pub bids: Option<u32>,
+    pub seller: Option<String>,
+    pub image: Option<String>,
+}
+
+impl EbaySearchItem {
+    /// The item as generic product data
+    pub fn to_product(&self) -> ExtractedProduct {
+        ExtractedProduct {
+            name: Some(self.title.clone()),
+            price: self.price,
+            currency: self.currency.clone(),
+            sku: self.item_id.clone(),
+            images: self.image.iter().cloned().collect(),
+            sources: vec![ProductSource::Platform],
+            ..ExtractedProduct::default()
+        }
+    }
+}
+
+/// One parsed search result page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EbaySearchPage {
+    pub query: String,
+    /// 1-based, as in eBay's `_pgn` parameter
+    pub page: u32,
+    pub items: Vec<EbaySearchItem>,
+    pub has_next: bool,
+}
+
+/// Pages fetched by [`EbayScraper::search`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EbaySearchResults {
+    pub pages: Vec<EbaySearchPage>,
+    /// Set when eBay blocked a page; `pages` holds what was fetched before
+    pub blocked: bool,
+}
+
+impl EbaySearchResults {
+    pub fn items(&self) -> impl Iterator<Item = &EbaySearchItem> {
+        self.pages.iter().flat_map(|page| page.items.iter())
+    }
+}
+
+/// A page on eBay the scraper knows how to extract
+#[derive(Debug, Clone, PartialEq)]
+enum EbayTarget {
+    Listing,
+    Search { query: String, page: u32 },
+}
+
+fn parse_target(url: &str) -> Option<EbayTarget> {
+    let parsed = url::Url::parse(url).ok()?;
+    if parsed.path().starts_with("/itm/") {
+        return Some(EbayTarget::Listing);
+    }
+    if parsed.path().starts_with("/sch/") {
+        let param = |name: &str| {
+            parsed
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        return Some(EbayTarget::Search {
+            query: param("_nkw")?,
+            page: param("_pgn")
+                .and_then(|page| page.parse().ok())
+                .unwrap_or(1)
+                .max(1),
+        });
+    }
+    None
+}
+
+/// URL of result page `page` (1-based) for `query` on the eBay site at `site`
+pub fn search_url(site: &str, query: &str, page: u32) -> Result<String> {
+    let mut url = url::Url::parse(&format!("{}/sch/i.html", site.trim_end_matches('/')))
+        .with_context(|| format!("Invalid eBay site URL {}", site))?;
+    url.query_pairs_mut().append_pair("_nkw", query);
+    if page > 1 {
+        url.query_pairs_mut().append_pair("_pgn", &page.to_string());
+    }
+    Ok(url.to_string())
+}
+
+fn clean_text(html: &str) -> Option<String> {
+    let text = decode_entities(&STRIP_TAGS_REGEX.replace_all(html, " "));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn has_class(tag: &HtmlTag, class: &str) -> bool {
+    tag.attributes
+        .get("class")
+        .is_some_and(|classes| classes.split_whitespace().any(|name| name == class))
+}
+
+/// Text of the first element in `html` with `class`
+fn class_text(html: &str, class: &str) -> Option<String> {
+    html_tags(html)
+        .find(|tag| has_class(tag, class))
+        .and_then(|tag| clean_text(element_inner_html(html, &tag)))
+}
+
+/// Price and currency from eBay's price text, e.g. `US $24.99`, `C $10.00`,
+/// `£12.50` or `EUR 9,99`; the lower bound of `$10.00 to $20.00`
+fn parse_ebay_price(text: &str) -> Option<(f64, Option<String>)> {
+    let text = text.split(" to ").next()?.trim();
+    let currency = if text.starts_with("US $") || text.starts_with("US$") {
+        Some("USD".to_string())
+    } else if text.starts_with("C $") || text.starts_with("C$") {
+        Some("CAD".to_string())
+    } else if text.starts_with("AU $") || text.starts_with("AU$") {
+        Some("AUD".to_string())
+    } else {
+        text.split_whitespace().next().and_then(|prefix| {
+            currency_code(prefix)
+                .or_else(|| currency_code(&prefix.chars().take(1).collect::<String>()))
+        })
+    };
+    let amount = text.trim_start_matches(|c: char| !c.is_ascii_digit());
+    Some((parse_price(amount.split_whitespace().next()?)?, currency))
+}
+
+/// Shipping cost: `0` for free shipping, otherwise the first price in the text
+fn shipping_cost(shipping: &str) -> Option<f64> {
+    if shipping.to_ascii_lowercase().contains("free") {
+        return Some(0.0);
+    }
+    parse_ebay_price(shipping.trim_start_matches('+')).map(|(cost, _)| cost)
+}
+
+fn parse_count(text: &str) -> Option<u32> {
+    let digits: String = COUNT_REGEX
+        .captures(text)?
+        .get(1)?
+        .as_str()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+fn item_id(url: &str) -> Option<String> {
+    ITEM_ID_REGEX
+        .captures(url)
+        .map(|captures| captures[1].to_string())
+}
+
+/// Shipping text without the row's label and trailing links
+fn shipping_text(html: &str) -> Option<String> {
+    let text = class_text(html, "ux-labels-values--shipping")?;
+    let text = text
+        .trim_start_matches("Shipping:")
+        .split(" See details")
+        .next()?
+        .trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Parse a listing page, starting from its structured product data
+pub fn parse_listing(html: &str, url: &str) -> EbayListing {
+    let mut product = ProductExtractor::new()
+        .extract(html, url)
+        .unwrap_or_default();
+
+    let price = class_text(html, "x-price-primary")
+        .or_else(|| class_text(html, "x-bin-price__content"))
+        .and_then(|text| parse_ebay_price(&text));
+    let images: Vec<String> = html_tags(html)
+        .filter(|tag| tag.name == "img")
+        .filter_map(|tag| {
+            tag.attributes
+                .get("data-zoom-src")
+                .or_else(|| tag.attributes.get("src"))
+                .filter(|src| src.contains("ebayimg.com") && src.contains("/images/g/"))
+                .cloned()
+        })
+        .fold(Vec::new(), |mut images, src| {
+            if !images.contains(&src) {
+                images.push(src);
+            }
+            images
+        });
+    product.merge(
+        ExtractedProduct {
+            name: class_text(html, "x-item-title__mainTitle"),
+            price: price.as_ref().map(|(price, _)| *price),
+            currency: price.and_then(|(_, currency)| currency),
+            availability: class_text(html, "d-quantity__availability")
+                .filter(|text| text.to_ascii_lowercase().contains("sold out"))
+                .and_then(|_| normalize_availability("OutOfStock")),
+            images,
+            ..ExtractedProduct::default()
+        },
+        ProductSource::Platform,
+    );
+    let item_id = item_id(url).or_else(|| product.sku.clone());
+    if product.sku.is_none() {
+        product.sku = item_id.clone();
+    }
+
+    let seller_card = html_tags(html)
+        .find(|tag| has_class(tag, "x-sellercard-atf"))
+        .map(|tag| element_inner_html(html, &tag));
+    let shipping = shipping_text(html);
+    EbayListing {
+        url: url.to_string(),
+        item_id,
+        title: product.name.clone(),
+        price: product.price,
+        currency: product.currency.clone(),
+        condition: class_text(html, "x-item-condition-text")
+            .or_else(|| class_text(html, "x-item-condition-value"))
+            .map(|condition| {
+                condition
+                    .split(" See all condition definitions")
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches("Condition:")
+                    .trim()
+                    .to_string()
+            }),
+        seller: seller_card
+            .and_then(|card| class_text(card, "x-sellercard-atf__info__about-seller")),
+        seller_feedback: seller_card
+            .and_then(clean_text)
+            .and_then(|text| FEEDBACK_REGEX.captures(&text)?[1].parse().ok()),
+        shipping_cost: shipping.as_deref().and_then(shipping_cost),
+        shipping,
+        bids: class_text(html, "x-bid-count").and_then(|bids| parse_count(&bids)),
+        images: product.images.clone(),
+        product,
+    }
+}
+
+/// Parse a search result page
+pub fn parse_search(html: &str, query: &str, page: u32) -> EbaySearchPage {
+    let mut items = Vec::new();
+    for tag in html_tags(html).filter(|tag| tag.name == "li" && has_class(tag, "s-item")) {
+        let item = element_inner_html(html, &tag);
+        let Some(url) = html_tags(item)
+            .find(|tag| has_class(tag, "s-item__link"))
+            .and_then(|tag| tag.attributes.get("href").map(|href| decode_entities(href)))
+        else {
+            continue;
+        };
+        let Some(title) = class_text(item, "s-item__title")
+            .map(|title| title.trim_start_matches("New Listing").trim().to_string())
+        else {
+            continue;
+        };
+        // eBay pads result lists with a placeholder card
+        if title == "Shop on eBay" {
+            continue;
+        }
+        let price = class_text(item, "s-item__price").and_then(|text| parse_ebay_price(&text));
+        let shipping = class_text(item, "s-item__shipping");
+        items.push(EbaySearchItem {
+            item_id: item_id(&url),
+            title,
+            price: price.as_ref().map(|(price, _)| *price),
+            currency: price.and_then(|(_, currency)| currency),
+            condition: class_text(item, "SECONDARY_INFO"),
+            shipping_cost: shipping.as_deref().and_then(shipping_cost),
+            shipping,
+            bids: class_text(item, "s-item__bids").and_then(|bids| parse_count(&bids)),
+            seller: class_text(item, "s-item__seller-info-text")
+                .and_then(|seller| seller.split_whitespace().next().map(str::to_string)),
+            image: html_tags(item)
+                .find(|tag| tag.name == "img")
+                .and_then(|tag| {
+                    tag.attributes
+                        .get("data-defer-load")
+                        .or_else(|| tag.attributes.get("src"))
+                        .cloned()
+                }),
+            url: url.split('?').next().unwrap_or(&url).to_string(),
+        });
+    }
+    let has_next = html_tags(html).any(|tag| {
+        has_class(&tag, "pagination__next")
+            && tag.attributes.get("aria-disabled").map(String::as_str) != Some("true")
+            && tag.attributes.contains_key("href")
+    });
+    EbaySearchPage {
+        query: query.to_string(),
+        page,
+        items,
+        has_next,
+    }
+}
+
+fn listing_content(listing: &EbayListing) -> ExtractedContent {
+    let mut metadata = Metadata::new();
+    listing.record(&mut metadata);
+    let text = [
+        listing
+            .condition
+            .as_ref()
+            .map(|condition| format!("Condition: {}", condition)),
+        listing
+            .seller
+            .as_ref()
+            .map(|seller| format!("Seller: {}", seller)),
+        listing
+            .shipping
+            .as_ref()
+            .map(|shipping| format!("Shipping: {}", shipping)),
+        listing.bids.map(|bids| format!("Bids: {}", bids)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n");
+    ExtractedContent {
+        url: listing.url.clone(),
+        title: listing.title.as_deref().map(SharedStr::from),
+        text: Some(text.into()),
+        metadata,
+        tags: vec!["product".to_string()],
+        extracted_at: chrono::Utc::now(),
+    }
+}
+
+fn search_content(url: &str, page: &EbaySearchPage) -> Result<ExtractedContent> {
+    let text = page
+        .items
+        .iter()
+        .map(|item| match (item.price, &item.currency) {
+            (Some(price), Some(currency)) => {
+                format!("{}\n{} {:.2}\n{}", item.title, currency, price, item.url)
+            }
+            _ => format!("{}\n{}", item.title, item.url),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let products: Vec<ExtractedProduct> =
+        page.items.iter().map(EbaySearchItem::to_product).collect();
+    let metadata = Metadata::from([
+        ("ebay:query".into(), page.query.clone()),
+        ("ebay:page".into(), page.page.to_string()),
+        ("ebay:results".into(), serde_json::to_string(&page.items)?),
+        ("ebay:products".into(), serde_json::to_string(&products)?),
+    ]);
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(format!("{} | eBay", page.query).into()),
+        text: Some(text.into()),
+        metadata,
+        tags: vec!["ebay_search".to_string()],
+        extracted_at: chrono::Utc::now(),
+    })
+}
+
+/// Scraper for eBay listings and search results
+pub struct EbayScraper {
+    config: ScraperConfig,
+    site_url: String,
+    throttle: Arc<HostThrottle>,
+    proxy: Option<String>,
+    browser: Option<Arc<BrowserPool>>,
+}
+
+impl EbayScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            site_url: SITE_URL.to_string(),
+            throttle: Arc::new(
+                HostThrottle::new(RateProfile::gentle()).expect("preset profile is valid"),
+            ),
+            proxy: None,
+            browser: None,
+        }
+    }
+
+    /// Use another eBay site, e.g. `https://www.ebay.co.uk` or a mock server
+    pub fn with_site_url(mut self, url: &str) -> Self {
+        self.site_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Pace requests with `profile` instead of the gentle default
+    pub fn with_profile(mut self, profile: RateProfile) -> Result<Self> {
+        self.throttle = Arc::new(HostThrottle::new(profile)?);
+        Ok(self)
+    }
+
+    /// Send every request through `proxy`
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Render pages eBay blocks in a browser instead of giving up
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
+
+    /// Fetch `url`'s path and query from the configured site
+    async fn fetch(&self, url: &str) -> Result<String> {
+        let parsed = url::Url::parse(url)?;
+        let mut url = format!("{}{}", self.site_url, parsed.path());
+        if let Some(query) = parsed.query() {
+            url.push('?');
+            url.push_str(query);
+        }
+        let host = url::Url::parse(&url)?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        let _permit = self.throttle.acquire(&host).await;
+
+        let mut tracer = FetchTracer::new()
+            .with_timeout(Duration::from_secs(self.config.timeout_secs))?
+            .with_user_agent(USER_AGENT)?;
+        if let Some(proxy) = &self.proxy {
+            tracer = tracer.with_proxy(proxy)?;
+        }
+        let headers = HashMap::from([
+            (
+                "Accept".to_string(),
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+            ),
+            ("Accept-Language".to_string(), "en-US,en;q=0.9".to_string()),
+        ]);
+        let fetched = tracer.fetch(&url, &headers).await;
+        let body = fetched.body?;
+        let html = String::from_utf8_lossy(&body).into_owned();
+        if !is_blocked(
+            fetched.trace.final_url.as_deref(),
+            fetched.trace.status,
+            &html,
+        ) {
+            return Ok(html);
+        }
+        let Some(pool) = &self.browser else {
+            return Err(EbayBlocked.into());
+        };
+        let html = pool.get_browser().await?.scrape_page(&url).await?.html;
+        if is_blocked(None, None, &html) {
+            return Err(EbayBlocked.into());
+        }
+        Ok(html)
+    }
+
+    /// Fetch and parse a listing page
+    pub async fn listing(&self, url: &str) -> Result<EbayListing> {
+        let html = self.fetch(url).await?;
+        Ok(parse_listing(&html, url))
+    }
+
+    /// Fetch and parse search result page `page` (1-based)
+    pub async fn search_page(&self, query: &str, page: u32) -> Result<EbaySearchPage> {
+        let html = self
+            .fetch(&search_url(&self.site_url, query, page)?)
+            .await?;
+        Ok(parse_search(&html, query, page))
+    }
+
+    /// Fetch up to `pages` search result pages, stopping early at the last page or a block
+    pub async fn search(&self, query: &str, pages: u32) -> Result<EbaySearchResults> {
+        let mut results = EbaySearchResults::default();
+        for page in 1..=pages.max(1) {
+            match self.search_page(query, page).await {
+                Ok(parsed) => {
+                    let has_next = parsed.has_next && !parsed.items.is_empty();
+                    results.pages.push(parsed);
+                    if !has_next {
+                        break;
+                    }
+                }
+                Err(e) if e.is::<EbayBlocked>() && !results.pages.is_empty() => {
+                    results.blocked = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl PlatformScraper for EbayScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            match parse_target(&url).context("Not an eBay listing or search URL")? {
+                EbayTarget::Listing => Ok(listing_content(&self.listing(&url).await?)),
+                EbayTarget::Search { query, page } => {
+                    search_content(&url, &self.search_page(&query, page).await?)
+                }
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        HOSTS.is_match(url) && parse_target(url).is_some()
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        ScraperCapabilities {
+            browser: Need::Optional,
+            ..ScraperCapabilities::default()
+        }
+    }
+
+    fn extract_from_html(&self, url: &str, html: &SharedStr) -> Option<Result<ExtractedContent>> {
+        Some(match parse_target(url)? {
+            EbayTarget::Listing => Ok(listing_content(&parse_listing(html, url))),
+            EbayTarget::Search { query, page } => {
+                search_content(url, &parse_search(html, &query, page))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::{Product, Schema};
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const LISTING: &str = r#"<html><head><title>Vintage Camera | eBay</title></head><body>
+        <h1 class="x-item-title__mainTitle"><span class="ux-textspans ux-textspans--BOLD">Vintage Film Camera 35mm</span></h1>
+        <div class="x-item-condition-text"><div class="ux-icon-text"><span class="ux-textspans">Used</span></div></div>
+        <div class="x-price-primary" data-testid="x-price-primary"><span class="ux-textspans">US $124.50</span></div>
+        <div class="x-bid-count"><span class="ux-textspans">7 bids</span></div>
+        <div class="ux-labels-values ux-labels-values--shipping">
+          <div class="ux-labels-values__labels"><span class="ux-textspans">Shipping:</span></div>
+          <div class="ux-labels-values__values"><div><span class="ux-textspans ux-textspans--BOLD">US $12.00</span>
+            <span class="ux-textspans">Standard Shipping</span> <a><span>See details</span></a></div></div>
+        </div>
+        <div class="x-sellercard-atf">
+          <div class="x-sellercard-atf__info__about-seller"><a href="/str/camerashop"><span class="ux-textspans ux-textspans--BOLD">camerashop</span></a></div>
+          <ul><li class="x-sellercard-atf__data-item"><span class="ux-textspans">99.6% positive</span></li></ul>
+        </div>
+        <img src="https://i.ebayimg.com/images/g/abc/s-l1600.jpg">
+        <img src="https://i.ebayimg.com/images/g/abc/s-l1600.jpg">
+        <img src="https://ir.ebaystatic.com/logo.png">
+    </body></html>"#;
+
+    const SEARCH: &str = r#"<ul class="srp-results">
+        <li class="s-item"><div class="s-item__title"><span>Shop on eBay</span></div>
+          <a class="s-item__link" href="https://ebay.com/itm/123456"></a></li>
+        <li class="s-item s-item__pl-on-bottom" data-viewport="x">
+          <div class="s-item__image-wrapper"><img src="https://i.ebayimg.com/images/g/x/s-l225.jpg"></div>
+          <a class="s-item__link" href="https://www.ebay.com/itm/256123456789?hash=item3b&amp;amdata=x">
+            <div class="s-item__title"><span role="heading"><span class="LIGHT_HIGHLIGHT">New Listing</span>Film Camera</span></div></a>
+          <div class="s-item__subtitle"><span class="SECONDARY_INFO">Pre-Owned</span></div>
+          <span class="s-item__price">$40.00 to $55.00</span>
+          <span class="s-item__shipping s-item__logisticsCost">Free shipping</span>
+          <span class="s-item__seller-info-text">lenslover (1,204) 100%</span>
+        </li>
+        <li class="s-item">
+          <a class="s-item__link" href="https://www.ebay.com/itm/256999999999"><div class="s-item__title"><span>Camera Strap</span></div></a>
+          <span class="s-item__price">$9.99</span>
+          <span class="s-item__shipping s-item__logisticsCost">+$3.50 shipping</span>
+          <span class="s-item__bids s-item__bidCount">3 bids</span>
+        </li>
+      </ul>
+      <a class="pagination__next icon-link" href="/sch/i.html?_nkw=film+camera&amp;_pgn=2">Next</a>"#;
+
+    #[test]
+    fn test_parse_listing_as_product() {
+        let url = "https://www.ebay.com/itm/vintage-film-camera/256123456789";
+        let listing = parse_listing(LISTING, url);
+        assert_eq!(listing.item_id.as_deref(), Some("256123456789"));
+        assert_eq!(listing.condition.as_deref(), Some("Used"));
+        assert_eq!(listing.seller.as_deref(), Some("camerashop"));
+        assert_eq!(listing.seller_feedback, Some(99.6));
+        assert_eq!(
+            listing.shipping.as_deref(),
+            Some("US $12.00 Standard Shipping")
+        );
+        assert_eq!(listing.shipping_cost, Some(12.0));
+        assert_eq!(listing.bids, Some(7));
+        assert_eq!(listing.images.len(), 1);
+
+        let content = listing_content(&listing);
+        let product = Product::from_content(&content).unwrap();
+        assert_eq!(product.name, "Vintage Film Camera 35mm");
+        assert_eq!(product.price, 124.5);
+        assert_eq!(product.currency.as_deref(), Some("USD"));
+        assert_eq!(product.sku.as_deref(), Some("256123456789"));
+    }
+
+    #[test]
+    fn test_parse_search_page() {
+        let page = parse_search(SEARCH, "film camera", 1);
+        assert!(page.has_next);
+        assert_eq!(page.items.len(), 2);
+        let camera = &page.items[0];
+        assert_eq!(camera.title, "Film Camera");
+        assert_eq!(camera.url, "https://www.ebay.com/itm/256123456789");
+        assert_eq!(camera.price, Some(40.0));
+        assert_eq!(camera.condition.as_deref(), Some("Pre-Owned"));
+        assert_eq!(camera.shipping_cost, Some(0.0));
+        assert_eq!(camera.seller.as_deref(), Some("lenslover"));
+        let strap = &page.items[1];
+        assert_eq!(strap.shipping_cost, Some(3.5));
+        assert_eq!(strap.bids, Some(3));
+        assert_eq!(strap.to_product().sku.as_deref(), Some("256999999999"));
+
+        assert_eq!(
+            parse_target("https://www.ebay.com/sch/i.html?_nkw=film+camera&_pgn=3"),
+            Some(EbayTarget::Search {
+                query: "film camera".to_string(),
+                page: 3
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_until_blocked() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/sch/i.html"))
+            .and(query_param("_pgn", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("<title>Pardon Our Interruption...</title>"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/sch/i.html"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(SEARCH))
+            .mount(&server)
+            .await;
+
+        let scraper = EbayScraper::new(ScraperConfig::default())
+            .with_site_url(&server.uri())
+            .with_profile(RateProfile::aggressive())
+            .unwrap();
+        let results = scraper.search("film camera", 3).await.unwrap();
+        assert_eq!(results.pages.len(), 1);
+        assert!(results.blocked);
+        assert_eq!(results.items().count(), 2);
+    }
+}
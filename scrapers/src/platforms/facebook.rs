@@ -0,0 +1,667 @@
+//! Facebook scraper
+//!
+//! Extracts posts, photos and videos (`/<page>/posts/<id>`, `permalink.php`,
+//! `/watch?v=`, `/reel/<id>`, ...) and pages or profiles from the JSON data
+//! facebook.com embeds in its pages for the web client, producing
+//! [`Post`]-shaped content: message, author, reaction/comment/share counts and
+//! media URLs. Pages carry follower counts and the posts on the page as
+//! `facebook:posts`.
+//!
+//! Facebook shows little to logged-out visitors, so the scraper refuses to send
+//! requests until a session is configured: a `cookie` header (with `c_user` and
+//! `xs`) for the `facebook` platform in its [`AuthStore`].
+//! [`FacebookScraper::with_anonymous_access`] opts in to logged-out requests
+//! instead. Pages that come back as a login wall are rendered in the browser
+//! given with [`FacebookScraper::with_browser`].
+
+use super::SessionRequired;
+use crate::{
+    anti_bot::proxy_rotator::ProxyType,
+    api::AuthStore,
+    browser::BrowserPool,
+    extractors::extract_metadata_secure,
+    metadata::Metadata,
+    router::UrlRouter,
+    schemas::{Post, POST_MEDIA_KEY, POST_METRIC_PREFIX},
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt, sync::Arc, time::Duration};
+use swoop_core::security::UrlValidator;
+
+const PLATFORM: &str = "facebook";
+
+/// Hosts the scraper handles
+static HOSTS: Lazy<UrlRouter<()>> = Lazy::new(|| {
+    UrlRouter::from_patterns(
+        &[
+            "facebook.com",
+            "*.facebook.com",
+            "fb.com",
+            "www.fb.com",
+            "fb.watch",
+        ],
+        (),
+    )
+    .unwrap()
+});
+
+/// Path segments that are facebook.com pages rather than profiles
+const RESERVED_PATHS: &[&str] = &[
+    "login",
+    "watch",
+    "groups",
+    "events",
+    "marketplace",
+    "gaming",
+    "help",
+    "policies",
+    "privacy",
+    "settings",
+    "hashtag",
+    "search",
+    "share",
+    "reel",
+    "photo",
+    "photo.php",
+    "story.php",
+    "permalink.php",
+];
+
+static PAGE_DATA_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<script[^>]*type\s*=\s*"application/json"[^>]*>(.*?)</script>"#).unwrap()
+});
+
+/// Where the scraper sends requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacebookEndpoints {
+    pub base_url: String,
+}
+
+impl Default for FacebookEndpoints {
+    fn default() -> Self {
+        Self::at("https://www.facebook.com")
+    }
+}
+
+impl FacebookEndpoints {
+    /// Endpoints served from `base`, e.g. a mock server
+    pub fn at(base: &str) -> Self {
+        Self {
+            base_url: base.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+/// Facebook answered with its login form instead of the page
+#[derive(Debug)]
+pub struct LoginWall;
+
+impl fmt::Display for LoginWall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Facebook answered with a login wall")
+    }
+}
+
+impl std::error::Error for LoginWall {}
+
+/// A page on facebook.com the scraper knows how to extract
+#[derive(Debug, Clone, PartialEq)]
+enum FacebookTarget {
+    Post { id: Option<String> },
+    Page { name: String },
+}
+
+fn parse_target(url: &str) -> Option<FacebookTarget> {
+    let url = url::Url::parse(url).ok()?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if url.host_str() == Some("fb.watch") {
+        return Some(FacebookTarget::Post { id: None });
+    }
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["permalink.php" | "story.php"] => Some(FacebookTarget::Post {
+            id: param("story_fbid"),
+        }),
+        ["photo" | "photo.php"] => Some(FacebookTarget::Post { id: param("fbid") }),
+        ["watch", ..] => Some(FacebookTarget::Post { id: param("v") }),
+        ["reel", id, ..] | ["share", "p" | "v" | "r", id, ..] => Some(FacebookTarget::Post {
+            id: Some(id.to_string()),
+        }),
+        [_, "posts" | "videos" | "photos", .., id]
+        | ["groups", _, "posts" | "permalink", id, ..] => Some(FacebookTarget::Post {
+            id: Some(id.to_string()),
+        }),
+        ["profile.php"] => param("id").map(|name| FacebookTarget::Page { name }),
+        [name] if !RESERVED_PATHS.contains(&name.to_ascii_lowercase().as_str()) => {
+            Some(FacebookTarget::Page {
+                name: name.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Every JSON document embedded in the page for the web client
+fn page_data(html: &str) -> Value {
+    Value::Array(
+        PAGE_DATA_REGEX
+            .captures_iter(html)
+            .filter_map(|captures| serde_json::from_str(&captures[1]).ok())
+            .collect(),
+    )
+}
+
+/// Every value of `key` under `value`, in document order
+fn values_of<'a>(value: &'a Value, key: &str, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(value) = object.get(key) {
+                found.push(value);
+            }
+            object
+                .values()
+                .for_each(|value| values_of(value, key, found));
+        }
+        Value::Array(values) => values.iter().for_each(|value| values_of(value, key, found)),
+        _ => {}
+    }
+}
+
+/// The first value of one of `keys` under `value` that `pick` accepts
+fn first_of<T>(value: &Value, keys: &[&str], pick: impl Fn(&Value) -> Option<T>) -> Option<T> {
+    keys.iter().find_map(|key| {
+        let mut found = Vec::new();
+        values_of(value, key, &mut found);
+        found.into_iter().find_map(&pick)
+    })
+}
+
+/// Objects under `value` that have `key`, outermost first
+fn objects_with<'a>(value: &'a Value, key: &str, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(object) => {
+            if object.contains_key(key) {
+                found.push(value);
+            }
+            object
+                .values()
+                .for_each(|value| objects_with(value, key, found));
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| objects_with(value, key, found)),
+        _ => {}
+    }
+}
+
+/// A post found in page data
+#[derive(Debug, Clone, PartialEq)]
+struct Story {
+    id: Option<String>,
+    url: Option<String>,
+    author: Option<String>,
+    text: String,
+    created_at: Option<DateTime<Utc>>,
+    metrics: BTreeMap<String, u64>,
+    media: Vec<String>,
+}
+
+impl Story {
+    fn to_post(&self, fallback_url: &str) -> Post {
+        Post {
+            url: self.url.clone().unwrap_or_else(|| fallback_url.to_string()),
+            author: self.author.clone(),
+            text: self.text.clone(),
+            published_at: self.created_at,
+            image: self.media.first().cloned(),
+            metrics: self.metrics.clone(),
+            media: self.media.clone(),
+        }
+    }
+}
+
+fn count(value: &Value, fields: &[&str]) -> Option<u64> {
+    value.as_u64().or_else(|| {
+        fields
+            .iter()
+            .find_map(|field| value.get(field).and_then(Value::as_u64))
+    })
+}
+
+/// Parse the first story under `root`: its message, author, counts and media
+fn parse_story(root: &Value) -> Option<Story> {
+    let text = first_of(root, &["message"], |message| {
+        message
+            .get("text")
+            .and_then(Value::as_str)
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string)
+    })?;
+
+    let mut metrics = BTreeMap::new();
+    for (name, keys, fields) in [
+        (
+            "reactions",
+            &["reaction_count", "reactors"][..],
+            &["count"][..],
+        ),
+        (
+            "comments",
+            &["comment_count", "comments", "total_comment_count"][..],
+            &["total_count", "count"][..],
+        ),
+        ("shares", &["share_count"][..], &["count"][..]),
+        (
+            "views",
+            &["video_view_count", "play_count"][..],
+            &["count"][..],
+        ),
+    ] {
+        if let Some(count) = first_of(root, keys, |value| count(value, fields)) {
+            metrics.insert(name.to_string(), count);
+        }
+    }
+
+    let mut media: Vec<String> = Vec::new();
+    let mut found = Vec::new();
+    for key in ["playable_url_quality_hd", "photo_image", "full_image"] {
+        values_of(root, key, &mut found);
+    }
+    for value in found {
+        let url = value
+            .as_str()
+            .or_else(|| value.get("uri").and_then(Value::as_str));
+        if let Some(url) = url {
+            if !media.iter().any(|seen| seen == url) {
+                media.push(url.to_string());
+            }
+        }
+    }
+
+    Some(Story {
+        id: first_of(root, &["post_id"], |id| id.as_str().map(str::to_string)),
+        url: first_of(root, &["wwwURL", "permalink_url"], |url| {
+            url.as_str().map(str::to_string)
+        }),
+        author: first_of(root, &["actors", "owning_profile"], |actor| {
+            let actor = actor.get(0).unwrap_or(actor);
+            actor
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        }),
+        text,
+        created_at: first_of(root, &["creation_time", "publish_time"], Value::as_i64)
+            .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        metrics,
+        media,
+    })
+}
+
+fn story_content(url: &str, story: &Story) -> ExtractedContent {
+    let mut metadata = Metadata::from([("fetched_via".into(), "page_data".to_string())]);
+    if let Some(id) = &story.id {
+        metadata.insert("facebook:post_id".into(), id.clone());
+    }
+    if let Some(author) = &story.author {
+        metadata.insert("author".into(), author.clone());
+    }
+    if let Some(created_at) = story.created_at {
+        metadata.insert("article:published_time".into(), created_at.to_rfc3339());
+    }
+    for (name, count) in &story.metrics {
+        metadata.insert(
+            format!("{}{}", POST_METRIC_PREFIX, name).into(),
+            count.to_string(),
+        );
+    }
+    if !story.media.is_empty() {
+        metadata.insert(POST_MEDIA_KEY.into(), story.media.join("\n"));
+    }
+
+    ExtractedContent {
+        url: url.to_string(),
+        title: story
+            .author
+            .as_ref()
+            .map(|author| format!("{} on Facebook", author).into()),
+        text: Some(story.text.as_str().into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    }
+}
+
+fn page_content(url: &str, html: &str, data: &Value) -> Result<ExtractedContent> {
+    let tags = extract_metadata_secure(html).unwrap_or_default();
+    let name = tags
+        .get("og:title")
+        .cloned()
+        .or_else(|| crate::extractors::extract_title(html).ok().flatten())
+        .context("Facebook page has no name")?;
+    let mut metadata = Metadata::from([
+        ("author".into(), name.clone()),
+        ("fetched_via".into(), "page_data".to_string()),
+    ]);
+    if let Some(image) = tags.get("og:image") {
+        metadata.insert("og:image".into(), image.clone());
+    }
+    for (key, keys) in [
+        ("facebook:followers", &["follower_count"][..]),
+        (
+            "facebook:likes",
+            &["global_likers_count", "page_likers"][..],
+        ),
+    ] {
+        if let Some(count) = first_of(data, keys, |value| count(value, &["global_likers_count"])) {
+            metadata.insert(key.into(), count.to_string());
+        }
+    }
+
+    let mut containers = Vec::new();
+    objects_with(data, "post_id", &mut containers);
+    let mut stories: Vec<Story> = Vec::new();
+    for story in containers.into_iter().filter_map(parse_story) {
+        if !stories.iter().any(|seen| seen.id == story.id) {
+            stories.push(story);
+        }
+    }
+    let posts: Vec<Post> = stories.iter().map(|story| story.to_post(url)).collect();
+    metadata.insert("facebook:posts".into(), serde_json::to_string(&posts)?);
+
+    let description = tags.get("og:description").cloned().unwrap_or_default();
+    let text: Vec<&str> = std::iter::once(description.as_str())
+        .chain(stories.iter().map(|story| story.text.as_str()))
+        .filter(|text| !text.is_empty())
+        .collect();
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(name.into()),
+        text: Some(text.join("\n\n").into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    })
+}
+
+fn is_login_form(html: &str) -> bool {
+    html.contains("id=\"login_form\"") || html.contains("name=\"login_source\"")
+}
+
+/// Whether Facebook refused the request: a login wall, an expired session or
+/// rate limiting
+fn is_refused(error: &anyhow::Error) -> bool {
+    error.is::<LoginWall>()
+        || error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status)
+            .is_some_and(|status| matches!(status.as_u16(), 401 | 403 | 429))
+}
+
+/// Scraper for facebook.com posts, pages and profiles
+pub struct FacebookScraper {
+    config: ScraperConfig,
+    endpoints: FacebookEndpoints,
+    validator: UrlValidator,
+    auth: Arc<AuthStore>,
+    browser: Option<Arc<BrowserPool>>,
+    anonymous: bool,
+}
+
+impl FacebookScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            endpoints: FacebookEndpoints::default(),
+            validator: UrlValidator::default(),
+            auth: Arc::new(AuthStore::in_memory()),
+            browser: None,
+            anonymous: false,
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: FacebookEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Read session cookies from `auth`, e.g. a store loaded from disk
+    pub fn with_auth(mut self, auth: Arc<AuthStore>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Render pages in a browser when Facebook answers with a login wall
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
+
+    /// Send requests without a session; Facebook shows only some public pages
+    pub fn with_anonymous_access(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    fn logged_in(&self) -> bool {
+        self.auth
+            .headers(PLATFORM)
+            .iter()
+            .any(|(name, value)| name.eq_ignore_ascii_case("cookie") && value.contains("c_user="))
+    }
+
+    /// Fetch the page at `url`'s path and query from the configured endpoint
+    async fn fetch_page(&self, url: &str) -> Result<String> {
+        let parsed = url::Url::parse(url)?;
+        let mut page_url = format!("{}{}", self.endpoints.base_url, parsed.path());
+        if let Some(query) = parsed.query() {
+            page_url.push('?');
+            page_url.push_str(query);
+        }
+        let mut headers = self.auth.headers(PLATFORM);
+        headers.insert("User-Agent".to_string(), self.config.user_agent.clone());
+        headers.insert("Accept".to_string(), "text/html".to_string());
+        headers.insert("Accept-Language".to_string(), "en-US,en;q=0.9".to_string());
+        let body = swoop_core::send_request_with(
+            &self.validator,
+            reqwest::Method::GET,
+            &page_url,
+            &headers,
+            None,
+            Duration::from_secs(self.config.timeout_secs),
+        )
+        .await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn extract_from_page(&self, url: &str) -> Result<ExtractedContent> {
+        let target = parse_target(url)
+            .with_context(|| format!("Not a Facebook post, page or profile URL: {}", url))?;
+        let html = self.fetch_page(url).await?;
+        let data = page_data(&html);
+        match target {
+            FacebookTarget::Post { .. } => match parse_story(&data) {
+                Some(story) => Ok(story_content(url, &story)),
+                None if is_login_form(&html) => Err(LoginWall.into()),
+                None => anyhow::bail!("No post data in Facebook page {}", url),
+            },
+            FacebookTarget::Page { name } => {
+                if is_login_form(&html) && !html.contains("og:title") {
+                    return Err(LoginWall.into());
+                }
+                page_content(url, &html, &data)
+                    .with_context(|| format!("Facebook page {} is unavailable", name))
+            }
+        }
+    }
+}
+
+impl PlatformScraper for FacebookScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            if !self.logged_in() && !self.anonymous {
+                return Err(SessionRequired { platform: PLATFORM }.into());
+            }
+            match self.extract_from_page(&url).await {
+                Err(e) if is_refused(&e) => {
+                    let Some(pool) = &self.browser else {
+                        return Err(e.context("Facebook refused the request"));
+                    };
+                    let page = pool.get_browser().await?.scrape_page(&url).await?;
+                    let data = page_data(&page.html);
+                    let mut content = match parse_story(&data) {
+                        Some(story) => story_content(&page.url, &story),
+                        None => super::content_from_html(page.url, &page.html.into()),
+                    };
+                    content
+                        .metadata
+                        .insert("fetched_via".into(), "browser".to_string());
+                    Ok(content)
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        HOSTS.is_match(url)
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        ScraperCapabilities {
+            browser: Need::Optional,
+            auth: if self.anonymous {
+                Need::Optional
+            } else {
+                Need::Required
+            },
+            proxy_tier: Some(ProxyType::Residential),
+            ..ScraperCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::Schema;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const POST_PAGE: &str = r#"<html><head><title>Facebook</title></head><body>
+        <script type="application/json" data-sjs>{"require": [["ScheduledServerJS", {"__bbox": {
+          "result": {"data": {"node": {
+            "post_id": "123", "wwwURL": "https://www.facebook.com/rustlang/posts/123",
+            "actors": [{"__typename": "Page", "name": "Rust"}],
+            "comet_sections": {
+              "content": {"story": {"message": {"text": "Rust 1.80 is out!"}}},
+              "context_layout": {"story": {"creation_time": 1722470400}},
+              "feedback": {"reaction_count": {"count": 950}, "comment_count": {"total_count": 42},
+                           "share_count": {"count": 17}}
+            },
+            "attachments": [{"media": {"photo_image": {"uri": "https://scontent.example/1.jpg"}}}]
+          }}}
+        }}]]}</script></body></html>"#;
+
+    #[test]
+    fn test_parse_targets_and_stories() {
+        assert_eq!(
+            parse_target("https://www.facebook.com/permalink.php?story_fbid=42&id=7"),
+            Some(FacebookTarget::Post {
+                id: Some("42".to_string())
+            })
+        );
+        assert_eq!(
+            parse_target("https://m.facebook.com/rustlang/posts/pfbid0abc"),
+            Some(FacebookTarget::Post {
+                id: Some("pfbid0abc".to_string())
+            })
+        );
+        assert_eq!(
+            parse_target("https://www.facebook.com/profile.php?id=100"),
+            Some(FacebookTarget::Page {
+                name: "100".to_string()
+            })
+        );
+        assert_eq!(parse_target("https://www.facebook.com/marketplace"), None);
+
+        let story = parse_story(&page_data(POST_PAGE)).unwrap();
+        let content = story_content("https://www.facebook.com/rustlang/posts/123", &story);
+        assert_eq!(content.title.as_deref(), Some("Rust on Facebook"));
+        assert_eq!(content.metadata["facebook:post_id"], "123");
+        let post = Post::from_content(&content).unwrap();
+        assert_eq!(post.text, "Rust 1.80 is out!");
+        assert_eq!(post.metrics["reactions"], 950);
+        assert_eq!(post.metrics["comments"], 42);
+        assert_eq!(post.metrics["shares"], 17);
+        assert_eq!(post.media, vec!["https://scontent.example/1.jpg"]);
+        assert!(post.published_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_session_cookies_and_login_wall() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/rustlang/posts/123"))
+            .and(header("cookie", "c_user=1; xs=secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(POST_PAGE))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/rustlang/posts/123"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<form id="login_form" action="/login/"><input name="email"></form>"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let auth = Arc::new(AuthStore::in_memory());
+        let scraper = FacebookScraper::new(ScraperConfig::default())
+            .with_endpoints(FacebookEndpoints::at(&server.uri()))
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_auth(auth.clone());
+        let url = "https://www.facebook.com/rustlang/posts/123";
+
+        let error = scraper.extract(url).await.unwrap_err();
+        assert!(error.is::<SessionRequired>());
+
+        // Logged out, Facebook shows its login form and there's no browser to fall back to
+        let anonymous = FacebookScraper::new(ScraperConfig::default())
+            .with_endpoints(FacebookEndpoints::at(&server.uri()))
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_anonymous_access();
+        let error = anonymous.extract(url).await.unwrap_err();
+        assert!(error.is::<LoginWall>());
+
+        auth.set(PLATFORM, "cookie", "c_user=1; xs=secret");
+        let content = scraper.extract(url).await.unwrap();
+        assert_eq!(content.text.as_deref(), Some("Rust 1.80 is out!"));
+        assert_eq!(content.metadata["author"], "Rust");
+    }
+}
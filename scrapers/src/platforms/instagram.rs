@@ -0,0 +1,591 @@
+//! Instagram scraper
+//!
+//! Fetches posts and reels (`/p/<code>`, `/reel/<code>`, `/tv/<code>`) and
+//! profiles through the JSON endpoints the instagram.com web client uses,
+//! producing [`Post`]-shaped content: caption, author, like/comment/view
+//! counts and the URLs of every photo and video. Profiles carry follower counts
+//! and their latest posts as `instagram:posts`.
+//!
+//! Instagram only serves these endpoints reliably to logged-in accounts, so the
+//! scraper refuses to send requests until a session is configured: a `cookie`
+//! header (with `sessionid` and `csrftoken`) for the `instagram` platform in its
+//! [`AuthStore`]. [`InstagramScraper::with_anonymous_access`] opts in to
+//! logged-out requests instead. When Instagram refuses a request, the page is
+//! rendered in the browser given with [`InstagramScraper::with_browser`].
+
+use super::SessionRequired;
+use crate::{
+    anti_bot::proxy_rotator::ProxyType,
+    api::{ApiClient, ApiMethod, ApiRequest, AuthStore},
+    browser::BrowserPool,
+    metadata::Metadata,
+    router::UrlRouter,
+    schemas::{Post, POST_MEDIA_KEY, POST_METRIC_PREFIX},
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use swoop_core::security::UrlValidator;
+
+const PLATFORM: &str = "instagram";
+
+/// Hosts the scraper handles
+static HOSTS: Lazy<UrlRouter<()>> =
+    Lazy::new(|| UrlRouter::from_patterns(&["instagram.com", "*.instagram.com"], ()).unwrap());
+
+/// App ID the instagram.com web client sends with its API requests
+const WEB_APP_ID: &str = "936619743392459";
+/// Path segments that are instagram.com pages rather than profiles
+const RESERVED_PATHS: &[&str] = &[
+    "explore",
+    "accounts",
+    "stories",
+    "direct",
+    "reels",
+    "about",
+    "developer",
+    "legal",
+    "web",
+    "api",
+];
+
+/// Where the scraper sends requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstagramEndpoints {
+    pub base_url: String,
+}
+
+impl Default for InstagramEndpoints {
+    fn default() -> Self {
+        Self::at("https://www.instagram.com")
+    }
+}
+
+impl InstagramEndpoints {
+    /// Endpoints served from `base`, e.g. a mock server
+    pub fn at(base: &str) -> Self {
+        Self {
+            base_url: base.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+/// A page on instagram.com the scraper knows how to fetch
+#[derive(Debug, Clone, PartialEq)]
+enum InstagramTarget {
+    Post { shortcode: String },
+    Profile { username: String },
+}
+
+fn parse_target(url: &str) -> Option<InstagramTarget> {
+    let url = url::Url::parse(url).ok()?;
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["p" | "reel" | "reels" | "tv", shortcode, ..] | [_, "p" | "reel", shortcode, ..] => {
+            Some(InstagramTarget::Post {
+                shortcode: shortcode.to_string(),
+            })
+        }
+        [name] if !RESERVED_PATHS.contains(&name.to_ascii_lowercase().as_str()) => {
+            Some(InstagramTarget::Profile {
+                username: name.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// One post parsed from either of Instagram's media formats
+#[derive(Debug, Clone, PartialEq)]
+struct Media {
+    shortcode: String,
+    username: Option<String>,
+    caption: String,
+    taken_at: Option<DateTime<Utc>>,
+    metrics: BTreeMap<String, u64>,
+    media: Vec<String>,
+}
+
+impl Media {
+    fn url(&self) -> String {
+        format!("https://www.instagram.com/p/{}/", self.shortcode)
+    }
+
+    fn to_post(&self) -> Post {
+        Post {
+            url: self.url(),
+            author: self.username.as_ref().map(|name| format!("@{}", name)),
+            text: self.caption.clone(),
+            published_at: self.taken_at,
+            image: self.media.first().cloned(),
+            metrics: self.metrics.clone(),
+            media: self.media.clone(),
+        }
+    }
+}
+
+fn str_at<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer).and_then(Value::as_str)
+}
+
+fn count_at(value: &Value, pointers: &[&str]) -> Option<u64> {
+    pointers
+        .iter()
+        .find_map(|pointer| value.pointer(pointer).and_then(Value::as_u64))
+}
+
+/// The video URL of a media item, or its largest image
+fn media_url(item: &Value) -> Option<String> {
+    str_at(item, "/video_versions/0/url")
+        .or_else(|| str_at(item, "/image_versions2/candidates/0/url"))
+        .or_else(|| str_at(item, "/video_url"))
+        .or_else(|| str_at(item, "/display_url"))
+        .map(str::to_string)
+}
+
+/// Parse a media item from the v1 API (`items[]`) or the GraphQL format
+/// (`shortcode_media`, timeline `node`s)
+fn parse_media(item: &Value) -> Option<Media> {
+    let shortcode = str_at(item, "/code").or_else(|| str_at(item, "/shortcode"))?;
+    let children: Vec<&Value> = match item
+        .get("carousel_media")
+        .or_else(|| item.pointer("/edge_sidecar_to_children/edges"))
+        .and_then(Value::as_array)
+    {
+        Some(children) => children
+            .iter()
+            .map(|child| child.get("node").unwrap_or(child))
+            .collect(),
+        None => vec![item],
+    };
+
+    let mut metrics = BTreeMap::new();
+    for (name, pointers) in [
+        (
+            "likes",
+            &[
+                "/like_count",
+                "/edge_media_preview_like/count",
+                "/edge_liked_by/count",
+            ][..],
+        ),
+        (
+            "comments",
+            &[
+                "/comment_count",
+                "/edge_media_to_comment/count",
+                "/edge_media_to_parent_comment/count",
+            ][..],
+        ),
+        (
+            "views",
+            &["/play_count", "/view_count", "/video_view_count"][..],
+        ),
+    ] {
+        if let Some(count) = count_at(item, pointers) {
+            metrics.insert(name.to_string(), count);
+        }
+    }
+
+    Some(Media {
+        shortcode: shortcode.to_string(),
+        username: str_at(item, "/user/username")
+            .or_else(|| str_at(item, "/owner/username"))
+            .map(str::to_string),
+        caption: str_at(item, "/caption/text")
+            .or_else(|| str_at(item, "/edge_media_to_caption/edges/0/node/text"))
+            .unwrap_or_default()
+            .to_string(),
+        taken_at: count_at(item, &["/taken_at", "/taken_at_timestamp"])
+            .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)),
+        metrics,
+        media: children.into_iter().filter_map(media_url).collect(),
+    })
+}
+
+fn post_content(url: &str, media: &Media) -> ExtractedContent {
+    let mut metadata = Metadata::from([
+        ("instagram:shortcode".into(), media.shortcode.clone()),
+        ("fetched_via".into(), "api".to_string()),
+    ]);
+    if let Some(name) = &media.username {
+        metadata.insert("author".into(), format!("@{}", name));
+    }
+    if let Some(taken_at) = media.taken_at {
+        metadata.insert("article:published_time".into(), taken_at.to_rfc3339());
+    }
+    for (name, count) in &media.metrics {
+        metadata.insert(
+            format!("{}{}", POST_METRIC_PREFIX, name).into(),
+            count.to_string(),
+        );
+    }
+    if !media.media.is_empty() {
+        metadata.insert(POST_MEDIA_KEY.into(), media.media.join("\n"));
+    }
+
+    ExtractedContent {
+        url: url.to_string(),
+        title: media
+            .username
+            .as_ref()
+            .map(|name| format!("@{} on Instagram", name).into()),
+        text: Some(media.caption.as_str().into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    }
+}
+
+fn profile_content(url: &str, user: &Value) -> Result<ExtractedContent> {
+    let username = str_at(user, "/username").context("Instagram profile response has no user")?;
+    let mut metadata = Metadata::from([
+        ("author".into(), format!("@{}", username)),
+        ("fetched_via".into(), "api".to_string()),
+    ]);
+    for (key, pointer) in [
+        ("instagram:followers", "/edge_followed_by/count"),
+        ("instagram:following", "/edge_follow/count"),
+        (
+            "instagram:post_count",
+            "/edge_owner_to_timeline_media/count",
+        ),
+    ] {
+        if let Some(count) = count_at(user, &[pointer]) {
+            metadata.insert(key.into(), count.to_string());
+        }
+    }
+    if let Some(image) =
+        str_at(user, "/profile_pic_url_hd").or_else(|| str_at(user, "/profile_pic_url"))
+    {
+        metadata.insert("og:image".into(), image.to_string());
+    }
+    if let Some(website) = str_at(user, "/external_url") {
+        metadata.insert("instagram:website".into(), website.to_string());
+    }
+    if user.get("is_private").and_then(Value::as_bool) == Some(true) {
+        metadata.insert("instagram:private".into(), "true".to_string());
+    }
+    let posts: Vec<Media> = user
+        .pointer("/edge_owner_to_timeline_media/edges")
+        .and_then(Value::as_array)
+        .map(|edges| {
+            edges
+                .iter()
+                .filter_map(|edge| edge.get("node").and_then(parse_media))
+                .collect()
+        })
+        .unwrap_or_default();
+    let as_posts: Vec<Post> = posts.iter().map(Media::to_post).collect();
+    metadata.insert("instagram:posts".into(), serde_json::to_string(&as_posts)?);
+
+    let biography = str_at(user, "/biography").unwrap_or_default();
+    let text: Vec<&str> = std::iter::once(biography)
+        .chain(posts.iter().map(|post| post.caption.as_str()))
+        .filter(|text| !text.is_empty())
+        .collect();
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(
+            match str_at(user, "/full_name").filter(|name| !name.is_empty()) {
+                Some(name) => format!("{} (@{})", name, username).into(),
+                None => format!("@{}", username).into(),
+            },
+        ),
+        text: Some(text.join("\n\n").into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    })
+}
+
+/// Whether Instagram refused the request: a login redirect (HTML instead of
+/// JSON), an expired session or rate limiting
+fn is_refused(error: &anyhow::Error) -> bool {
+    error.is::<serde_json::Error>()
+        || error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status)
+            .is_some_and(|status| matches!(status.as_u16(), 401 | 403 | 429))
+}
+
+/// Scraper for instagram.com posts, reels and profiles
+pub struct InstagramScraper {
+    config: ScraperConfig,
+    endpoints: InstagramEndpoints,
+    validator: UrlValidator,
+    auth: Arc<AuthStore>,
+    browser: Option<Arc<BrowserPool>>,
+    anonymous: bool,
+}
+
+impl InstagramScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            endpoints: InstagramEndpoints::default(),
+            validator: UrlValidator::default(),
+            auth: Arc::new(AuthStore::in_memory()),
+            browser: None,
+            anonymous: false,
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: InstagramEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Read session cookies from `auth`, e.g. a store loaded from disk
+    pub fn with_auth(mut self, auth: Arc<AuthStore>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Render pages in a browser when Instagram refuses API requests
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
+
+    /// Send requests without a session; Instagram answers only some of them
+    pub fn with_anonymous_access(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    fn logged_in(&self) -> bool {
+        self.auth.headers(PLATFORM).iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("cookie") && value.contains("sessionid=")
+        })
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        let mut request =
+            ApiRequest::template(&format!("{}{}", self.endpoints.base_url, path), json!({}))
+                .with_method(ApiMethod::Get)
+                .with_header("x-ig-app-id", WEB_APP_ID)
+                .with_header("x-requested-with", "XMLHttpRequest")
+                .with_header("user-agent", &self.config.user_agent);
+        // Instagram checks the CSRF header against the session's csrftoken cookie
+        if let Some(csrf) = self
+            .auth
+            .headers(PLATFORM)
+            .get("cookie")
+            .and_then(|cookie| {
+                cookie
+                    .split(';')
+                    .find_map(|pair| pair.trim().strip_prefix("csrftoken="))
+                    .map(str::to_string)
+            })
+        {
+            request = request.with_header("x-csrftoken", &csrf);
+        }
+        let client = ApiClient::new(Duration::from_secs(self.config.timeout_secs))
+            .with_validator(self.validator.clone())
+            .with_auth(self.auth.clone());
+        Ok(client.fetch_pages(PLATFORM, &request).await?.remove(0))
+    }
+
+    async fn extract_post(&self, url: &str, shortcode: &str) -> Result<ExtractedContent> {
+        let response = self
+            .get(&format!("/p/{}/?__a=1&__d=dis", shortcode))
+            .await?;
+        let item = response
+            .pointer("/items/0")
+            .or_else(|| response.pointer("/graphql/shortcode_media"))
+            .with_context(|| format!("Instagram post {} is unavailable", shortcode))?;
+        let media = parse_media(item).context("Instagram post response has no media")?;
+        Ok(post_content(url, &media))
+    }
+
+    async fn extract_profile(&self, url: &str, username: &str) -> Result<ExtractedContent> {
+        let response = self
+            .get(&format!(
+                "/api/v1/users/web_profile_info/?username={}",
+                username
+            ))
+            .await?;
+        let user = response
+            .pointer("/data/user")
+            .filter(|user| !user.is_null())
+            .with_context(|| format!("Instagram user @{} not found", username))?;
+        profile_content(url, user)
+    }
+
+    async fn extract_via_api(&self, url: &str) -> Result<ExtractedContent> {
+        match parse_target(url) {
+            Some(InstagramTarget::Post { shortcode }) => self.extract_post(url, &shortcode).await,
+            Some(InstagramTarget::Profile { username }) => {
+                self.extract_profile(url, &username).await
+            }
+            None => anyhow::bail!("Not an Instagram post or profile URL: {}", url),
+        }
+    }
+}
+
+impl PlatformScraper for InstagramScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            if !self.logged_in() && !self.anonymous {
+                return Err(SessionRequired { platform: PLATFORM }.into());
+            }
+            match self.extract_via_api(&url).await {
+                Err(e) if is_refused(&e) => {
+                    let Some(pool) = &self.browser else {
+                        return Err(e.context("Instagram refused the request"));
+                    };
+                    let page = pool.get_browser().await?.scrape_page(&url).await?;
+                    let mut content = super::content_from_html(page.url, &page.html.into());
+                    content
+                        .metadata
+                        .insert("fetched_via".into(), "browser".to_string());
+                    Ok(content)
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        HOSTS.is_match(url)
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        ScraperCapabilities {
+            browser: Need::Optional,
+            auth: if self.anonymous {
+                Need::Optional
+            } else {
+                Need::Required
+            },
+            proxy_tier: Some(ProxyType::Residential),
+            ..ScraperCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::Schema;
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_parse_targets_and_media_formats() {
+        assert_eq!(
+            parse_target("https://www.instagram.com/reel/C1a2b3/?igsh=x"),
+            Some(InstagramTarget::Post {
+                shortcode: "C1a2b3".to_string()
+            })
+        );
+        assert_eq!(
+            parse_target("https://instagram.com/ferris/"),
+            Some(InstagramTarget::Profile {
+                username: "ferris".to_string()
+            })
+        );
+        assert_eq!(parse_target("https://www.instagram.com/explore/"), None);
+
+        // v1 API item with a carousel
+        let item = json!({
+            "code": "C1a2b3", "taken_at": 1_700_000_000,
+            "user": { "username": "ferris" },
+            "caption": { "text": "Crab rave" },
+            "like_count": 120, "comment_count": 4,
+            "carousel_media": [
+                { "image_versions2": { "candidates": [{ "url": "https://cdn.example/1.jpg" }] } },
+                { "video_versions": [{ "url": "https://cdn.example/2.mp4" }],
+                  "image_versions2": { "candidates": [{ "url": "https://cdn.example/2.jpg" }] } }
+            ]
+        });
+        let media = parse_media(&item).unwrap();
+        assert_eq!(
+            media.media,
+            vec!["https://cdn.example/1.jpg", "https://cdn.example/2.mp4"]
+        );
+        let post = Post::from_content(&post_content(&media.url(), &media)).unwrap();
+        assert_eq!(post.author.as_deref(), Some("@ferris"));
+        assert_eq!(post.metrics["likes"], 120);
+        assert!(post.published_at.is_some());
+
+        // GraphQL node from a profile timeline
+        let node = json!({
+            "shortcode": "Z9", "taken_at_timestamp": 1_700_000_000,
+            "owner": { "username": "ferris" },
+            "edge_media_to_caption": { "edges": [{ "node": { "text": "Hello" } }] },
+            "edge_liked_by": { "count": 7 }, "video_view_count": 99,
+            "display_url": "https://cdn.example/z9.jpg"
+        });
+        let media = parse_media(&node).unwrap();
+        assert_eq!(media.caption, "Hello");
+        assert_eq!(media.metrics["views"], 99);
+        assert_eq!(media.media, vec!["https://cdn.example/z9.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn test_requires_session_and_sends_cookies() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users/web_profile_info/"))
+            .and(query_param("username", "ferris"))
+            .and(header("x-ig-app-id", WEB_APP_ID))
+            .and(header("x-csrftoken", "tok"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "data": { "user": {
+                "username": "ferris", "full_name": "Ferris", "biography": "Crab",
+                "edge_followed_by": { "count": 1000 }, "edge_follow": { "count": 5 },
+                "edge_owner_to_timeline_media": { "count": 1, "edges": [{ "node": {
+                    "shortcode": "Z9", "display_url": "https://cdn.example/z9.jpg",
+                    "edge_media_to_caption": { "edges": [{ "node": { "text": "Hello" } }] }
+                } }] }
+            } } })),
+            )
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let auth = Arc::new(AuthStore::in_memory());
+        let scraper = InstagramScraper::new(ScraperConfig::default())
+            .with_endpoints(InstagramEndpoints::at(&server.uri()))
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_auth(auth.clone());
+
+        let error = scraper
+            .extract("https://www.instagram.com/ferris/")
+            .await
+            .unwrap_err();
+        assert!(error.is::<SessionRequired>());
+
+        auth.set(PLATFORM, "cookie", "sessionid=abc; csrftoken=tok");
+        let content = scraper
+            .extract("https://www.instagram.com/ferris/")
+            .await
+            .unwrap();
+        assert_eq!(content.title.as_deref(), Some("Ferris (@ferris)"));
+        assert_eq!(content.metadata["instagram:followers"], "1000");
+        assert_eq!(content.text.as_deref(), Some("Crab\n\nHello"));
+        let posts: Vec<Post> = serde_json::from_str(&content.metadata["instagram:posts"]).unwrap();
+        assert_eq!(posts[0].url, "https://www.instagram.com/p/Z9/");
+    }
+}
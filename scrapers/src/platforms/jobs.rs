@@ -0,0 +1,842 @@
+//! Job board scraper
+//!
+//! [`JobBoardScraper`] reads postings from Greenhouse and Lever through their
+//! public job board APIs and from Indeed's pages, and normalizes all of them
+//! into the [`JobPosting`] schema LinkedIn jobs use. A board URL
+//! (`boards.greenhouse.io/<company>`, `jobs.lever.co/<company>`,
+//! `indeed.com/cmp/<company>/jobs`) lists every open posting of the company,
+//! following pagination; a posting URL yields that one posting. Company
+//! careers pages that embed a Greenhouse or Lever board are recognized by the
+//! embed and read from the board.
+
+use crate::{
+    api::{ApiClient, ApiMethod, ApiRequest},
+    extractors::{
+        decode_entities, extract_json_ld, find_json_key, find_json_ld_node, html_to_text,
+        json_ld_address, json_ld_text,
+    },
+    metadata::Metadata,
+    schemas::{JobPosting, Schema},
+    ExtractedContent, PlatformScraper, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use swoop_core::security::UrlValidator;
+
+const PLATFORM: &str = "jobs";
+/// Postings per Lever API page
+const LEVER_PAGE_SIZE: usize = 100;
+
+static GREENHOUSE_EMBED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:job-)?boards(?:\.eu)?\.greenhouse\.io/embed/job_(?:board|app)(?:/js)?\?(?:[^'\x22\s]*&(?:amp;)?)?for=([A-Za-z0-9_-]+)").unwrap()
+});
+static LEVER_EMBED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:jobs|api)(?:\.eu)?\.lever\.co/(?:v0/postings/)?([A-Za-z0-9_.-]+)").unwrap()
+});
+static INDEED_CARDS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"providerData\["mosaic-provider-jobcards"\]\s*=\s*"#).unwrap());
+
+/// A supported job board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobBoard {
+    Greenhouse,
+    Lever,
+    Indeed,
+}
+
+impl JobBoard {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Greenhouse => "greenhouse",
+            Self::Lever => "lever",
+            Self::Indeed => "indeed",
+        }
+    }
+}
+
+/// What a job board URL points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobTarget {
+    /// Every open posting of a company
+    Company { board: JobBoard, company: String },
+    /// A single posting; Indeed posting URLs don't name the company
+    Posting {
+        board: JobBoard,
+        company: Option<String>,
+        id: String,
+    },
+}
+
+impl JobTarget {
+    pub fn board(&self) -> JobBoard {
+        match self {
+            Self::Company { board, .. } | Self::Posting { board, .. } => *board,
+        }
+    }
+}
+
+/// The job board target of `url`, if it is a Greenhouse, Lever or Indeed URL
+pub fn detect(url: &str) -> Option<JobTarget> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    let query = |name: &str| {
+        parsed
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    if host.ends_with("greenhouse.io") {
+        let (company, id) = match segments.as_slice() {
+            ["embed", ..] => (query("for")?, query("token")),
+            ["v1", "boards", company, "jobs", id, ..] => {
+                (company.to_string(), Some(id.to_string()))
+            }
+            ["v1", "boards", company, ..] => (company.to_string(), None),
+            [company, "jobs", id, ..] => (company.to_string(), Some(id.to_string())),
+            [company, ..] => (company.to_string(), query("gh_jid")),
+            [] => return None,
+        };
+        return Some(match id {
+            Some(id) => JobTarget::Posting {
+                board: JobBoard::Greenhouse,
+                company: Some(company),
+                id,
+            },
+            None => JobTarget::Company {
+                board: JobBoard::Greenhouse,
+                company,
+            },
+        });
+    }
+    if host.ends_with("lever.co") {
+        let segments = match segments.as_slice() {
+            ["v0", "postings", rest @ ..] => rest,
+            rest => rest,
+        };
+        return match segments {
+            [company] => Some(JobTarget::Company {
+                board: JobBoard::Lever,
+                company: company.to_string(),
+            }),
+            [company, id, ..] => Some(JobTarget::Posting {
+                board: JobBoard::Lever,
+                company: Some(company.to_string()),
+                id: id.to_string(),
+            }),
+            [] => None,
+        };
+    }
+    if host == "indeed.com" || host.ends_with(".indeed.com") || host.contains(".indeed.") {
+        if let Some(id) = query("jk").or_else(|| query("vjk")) {
+            return Some(JobTarget::Posting {
+                board: JobBoard::Indeed,
+                company: None,
+                id,
+            });
+        }
+        if let ["cmp", company, ..] = segments.as_slice() {
+            return Some(JobTarget::Company {
+                board: JobBoard::Indeed,
+                company: company.to_string(),
+            });
+        }
+    }
+    None
+}
+
+/// A Greenhouse or Lever board embedded in a company's careers page. A
+/// Greenhouse `gh_jid` in `url` selects one posting of the embedded board
+pub fn detect_embed(url: &str, html: &str) -> Option<JobTarget> {
+    if let Some(captures) = GREENHOUSE_EMBED_REGEX.captures(html) {
+        let company = captures[1].to_string();
+        let posting = url::Url::parse(url).ok().and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "gh_jid")
+                .map(|(_, id)| id.into_owned())
+        });
+        return Some(match posting {
+            Some(id) => JobTarget::Posting {
+                board: JobBoard::Greenhouse,
+                company: Some(company),
+                id,
+            },
+            None => JobTarget::Company {
+                board: JobBoard::Greenhouse,
+                company,
+            },
+        });
+    }
+    LEVER_EMBED_REGEX
+        .captures(html)
+        .map(|captures| JobTarget::Company {
+            board: JobBoard::Lever,
+            company: captures[1].to_string(),
+        })
+}
+
+/// Posting of a JSON-LD `JobPosting` node
+pub(crate) fn json_ld_job(url: &str, posting: &Value) -> Option<JobPosting> {
+    let text = |key: &str| posting.get(key).and_then(json_ld_text);
+    let location = match posting.get("jobLocation") {
+        Some(Value::Array(locations)) => locations.first(),
+        location => location,
+    };
+    Some(JobPosting {
+        url: url.to_string(),
+        title: text("title")?,
+        company: text("hiringOrganization"),
+        location: location.and_then(|location| json_ld_address(location.get("address"))),
+        employment_type: text("employmentType"),
+        seniority: None,
+        posted_at: text("datePosted").and_then(|date| crate::article::parse_date(&date)),
+        salary: json_ld_salary(posting.get("baseSalary")),
+        applicants: None,
+        description: text("description")
+            .map(|description| html_to_text(&description))
+            .unwrap_or_default(),
+    })
+}
+
+/// Salary of a JSON-LD `MonetaryAmount`, e.g. `USD 120000-150000/YEAR`
+pub(crate) fn json_ld_salary(base_salary: Option<&Value>) -> Option<String> {
+    let base_salary = base_salary?;
+    let value = base_salary.get("value")?;
+    let amount = |key: &str| value.get(key).and_then(json_ld_text);
+    let range = match (amount("minValue"), amount("maxValue"), amount("value")) {
+        (Some(min), Some(max), _) => salary_range(min, max),
+        (Some(min), _, _) => min,
+        (_, _, Some(single)) => single,
+        _ => return None,
+    };
+    Some(format_salary(
+        base_salary.get("currency").and_then(json_ld_text),
+        range,
+        value.get("unitText").and_then(json_ld_text),
+    ))
+}
+
+fn salary_range(min: String, max: String) -> String {
+    if min == max {
+        min
+    } else {
+        format!("{}-{}", min, max)
+    }
+}
+
+fn format_salary(currency: Option<String>, range: String, unit: Option<String>) -> String {
+    format!(
+        "{}{}{}",
+        currency
+            .map(|currency| format!("{} ", currency))
+            .unwrap_or_default(),
+        range,
+        unit.map(|unit| format!("/{}", unit)).unwrap_or_default()
+    )
+}
+
+/// Document for one posting, recorded as `job:*` metadata for the `job_posting` schema
+pub(crate) fn job_content(job: &JobPosting) -> Result<ExtractedContent> {
+    let mut metadata = Metadata::new();
+    let fields = [
+        ("job:title", Some(job.title.clone())),
+        ("job:company", job.company.clone()),
+        ("job:location", job.location.clone()),
+        ("job:employment_type", job.employment_type.clone()),
+        ("job:seniority", job.seniority.clone()),
+        ("job:posted_at", job.posted_at.map(|date| date.to_rfc3339())),
+        ("job:salary", job.salary.clone()),
+        (
+            "job:applicants",
+            job.applicants.map(|count| count.to_string()),
+        ),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            metadata.insert(key.into(), value);
+        }
+    }
+    metadata.insert("schema".into(), JobPosting::NAME.to_string());
+    metadata.insert("schema_record".into(), serde_json::to_string(job)?);
+    Ok(ExtractedContent {
+        url: job.url.clone(),
+        title: Some(job.title.as_str().into()),
+        text: Some(job.description.as_str().into()),
+        metadata,
+        tags: vec![JobPosting::NAME.to_string()],
+        extracted_at: Utc::now(),
+    })
+}
+
+/// Document listing a company's open postings
+fn listing_content(
+    url: &str,
+    board: JobBoard,
+    company: &str,
+    jobs: &[JobPosting],
+) -> Result<ExtractedContent> {
+    let lines: Vec<String> = jobs
+        .iter()
+        .map(|job| match &job.location {
+            Some(location) => format!("{} ({})", job.title, location),
+            None => job.title.clone(),
+        })
+        .collect();
+    let metadata = Metadata::from([
+        ("jobs:board".into(), board.name().to_string()),
+        ("jobs:company".into(), company.to_string()),
+        ("jobs:count".into(), jobs.len().to_string()),
+        ("jobs:postings".into(), serde_json::to_string(jobs)?),
+    ]);
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(format!("Jobs at {}", company).into()),
+        text: Some(lines.join("\n").into()),
+        metadata,
+        tags: vec!["job_listing".to_string()],
+        extracted_at: Utc::now(),
+    })
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn greenhouse_job(board: &str, job: &Value) -> Option<JobPosting> {
+    let id = job.get("id").map(|id| match id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    })?;
+    let salary = job
+        .get("pay_input_ranges")
+        .and_then(Value::as_array)
+        .and_then(|ranges| ranges.first())
+        .and_then(|range| {
+            let cents = |key: &str| range.get(key).and_then(Value::as_u64);
+            let range_text = match (cents("min_cents"), cents("max_cents")) {
+                (Some(min), Some(max)) => {
+                    salary_range((min / 100).to_string(), (max / 100).to_string())
+                }
+                (Some(amount), None) | (None, Some(amount)) => (amount / 100).to_string(),
+                (None, None) => return None,
+            };
+            Some(format_salary(
+                str_field(range, "currency_type"),
+                range_text,
+                None,
+            ))
+        });
+    Some(JobPosting {
+        url: str_field(job, "absolute_url")
+            .unwrap_or_else(|| format!("https://boards.greenhouse.io/{}/jobs/{}", board, id)),
+        title: str_field(job, "title")?,
+        company: str_field(job, "company_name").or_else(|| Some(board.to_string())),
+        location: job.pointer("/location/name").and_then(json_ld_text),
+        employment_type: None,
+        seniority: None,
+        posted_at: str_field(job, "first_published")
+            .or_else(|| str_field(job, "updated_at"))
+            .and_then(|date| crate::article::parse_date(&date)),
+        salary,
+        applicants: None,
+        // The API returns the description HTML entity-escaped
+        description: str_field(job, "content")
+            .map(|content| html_to_text(&decode_entities(&content)))
+            .unwrap_or_default(),
+    })
+}
+
+fn lever_job(company: &str, job: &Value) -> Option<JobPosting> {
+    let mut description: Vec<String> = str_field(job, "descriptionPlain").into_iter().collect();
+    for list in job
+        .get("lists")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        description.extend(str_field(list, "text"));
+        description.extend(str_field(list, "content").map(|content| html_to_text(&content)));
+    }
+    description.extend(str_field(job, "additionalPlain"));
+    let salary = job.get("salaryRange").and_then(|range| {
+        let amount = |key: &str| {
+            range
+                .get(key)
+                .and_then(Value::as_u64)
+                .map(|value| value.to_string())
+        };
+        let range_text = match (amount("min"), amount("max")) {
+            (Some(min), Some(max)) => salary_range(min, max),
+            (Some(amount), None) | (None, Some(amount)) => amount,
+            (None, None) => return None,
+        };
+        // Intervals look like `per-year-salary`
+        let unit = str_field(range, "interval").map(|interval| {
+            interval
+                .trim_start_matches("per-")
+                .trim_end_matches("-salary")
+                .to_ascii_uppercase()
+        });
+        Some(format_salary(
+            str_field(range, "currency"),
+            range_text,
+            unit,
+        ))
+    });
+    Some(JobPosting {
+        url: str_field(job, "hostedUrl").or_else(|| {
+            str_field(job, "id").map(|id| format!("https://jobs.lever.co/{}/{}", company, id))
+        })?,
+        title: str_field(job, "text")?,
+        company: Some(company.to_string()),
+        location: job.pointer("/categories/location").and_then(json_ld_text),
+        employment_type: job.pointer("/categories/commitment").and_then(json_ld_text),
+        seniority: None,
+        posted_at: job
+            .get("createdAt")
+            .and_then(Value::as_i64)
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+        salary,
+        applicants: None,
+        description: description.join("\n"),
+    })
+}
+
+/// Posting cards of an Indeed listing page
+pub fn parse_indeed_listing(origin: &str, html: &str) -> Vec<JobPosting> {
+    let Some(start) = INDEED_CARDS_REGEX.find(html).map(|found| found.end()) else {
+        return Vec::new();
+    };
+    let Some(Ok(data)) = serde_json::Deserializer::from_str(&html[start..])
+        .into_iter::<Value>()
+        .next()
+    else {
+        return Vec::new();
+    };
+    find_json_key(&data, "results")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|card| {
+            let id = str_field(card, "jobkey")?;
+            Some(JobPosting {
+                url: format!("{}/viewjob?jk={}", origin, id),
+                title: str_field(card, "displayTitle").or_else(|| str_field(card, "title"))?,
+                company: str_field(card, "company"),
+                location: str_field(card, "formattedLocation"),
+                employment_type: card.get("jobTypes").and_then(json_ld_text),
+                seniority: None,
+                posted_at: card
+                    .get("pubDate")
+                    .and_then(Value::as_i64)
+                    .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+                salary: card.pointer("/salarySnippet/text").and_then(json_ld_text),
+                applicants: None,
+                description: str_field(card, "snippet")
+                    .map(|snippet| html_to_text(&snippet))
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Where the scraper sends requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobBoardEndpoints {
+    pub greenhouse_api: String,
+    pub lever_api: String,
+    /// Indeed site to read from; by default the one in the scraped URL
+    pub indeed_url: Option<String>,
+}
+
+impl Default for JobBoardEndpoints {
+    fn default() -> Self {
+        Self {
+            greenhouse_api: "https://boards-api.greenhouse.io".to_string(),
+            lever_api: "https://api.lever.co".to_string(),
+            indeed_url: None,
+        }
+    }
+}
+
+impl JobBoardEndpoints {
+    /// Endpoints served from `base`, e.g. a mock server
+    pub fn at(base: &str) -> Self {
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            greenhouse_api: base.clone(),
+            lever_api: base.clone(),
+            indeed_url: Some(base),
+        }
+    }
+}
+
+/// Scraper for Greenhouse, Lever and Indeed job postings
+pub struct JobBoardScraper {
+    config: ScraperConfig,
+    endpoints: JobBoardEndpoints,
+    validator: UrlValidator,
+    max_pages: usize,
+}
+
+impl JobBoardScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            endpoints: JobBoardEndpoints::default(),
+            validator: UrlValidator::default(),
+            max_pages: 20,
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: JobBoardEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Stop following a company listing's pagination after `pages` pages
+    pub fn with_max_pages(mut self, pages: usize) -> Self {
+        self.max_pages = pages.max(1);
+        self
+    }
+
+    async fn api(&self, url: &str, params: Value) -> Result<Value> {
+        let request = ApiRequest::template(url, params)
+            .with_method(ApiMethod::Get)
+            .with_header("User-Agent", &self.config.user_agent);
+        Ok(
+            ApiClient::new(Duration::from_secs(self.config.timeout_secs))
+                .with_validator(self.validator.clone())
+                .fetch_pages(PLATFORM, &request)
+                .await?
+                .remove(0),
+        )
+    }
+
+    async fn fetch_html(&self, url: &str) -> Result<String> {
+        let mut headers = self.config.headers.clone();
+        headers.insert("User-Agent".to_string(), self.config.user_agent.clone());
+        let body = swoop_core::send_request_with(
+            &self.validator,
+            reqwest::Method::GET,
+            url,
+            &headers,
+            None,
+            Duration::from_secs(self.config.timeout_secs),
+        )
+        .await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    fn indeed_origin(&self, url: &str) -> Result<String> {
+        match &self.endpoints.indeed_url {
+            Some(base) => Ok(base.clone()),
+            None => Ok(url::Url::parse(url)?.origin().ascii_serialization()),
+        }
+    }
+
+    /// Every open posting of `company` on `board`, following pagination.
+    /// `url` is the board URL, used to pick the Indeed site
+    pub async fn company_jobs(
+        &self,
+        board: JobBoard,
+        company: &str,
+        url: &str,
+    ) -> Result<Vec<JobPosting>> {
+        match board {
+            JobBoard::Greenhouse => {
+                let response = self
+                    .api(
+                        &format!(
+                            "{}/v1/boards/{}/jobs",
+                            self.endpoints.greenhouse_api, company
+                        ),
+                        json!({ "content": "true" }),
+                    )
+                    .await?;
+                Ok(response
+                    .get("jobs")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|job| greenhouse_job(company, job))
+                    .collect())
+            }
+            JobBoard::Lever => {
+                let mut jobs = Vec::new();
+                for page in 0..self.max_pages {
+                    let response = self
+                        .api(
+                            &format!("{}/v0/postings/{}", self.endpoints.lever_api, company),
+                            json!({
+                                "mode": "json",
+                                "skip": (page * LEVER_PAGE_SIZE).to_string(),
+                                "limit": LEVER_PAGE_SIZE.to_string(),
+                            }),
+                        )
+                        .await?;
+                    let postings = response.as_array().cloned().unwrap_or_default();
+                    jobs.extend(postings.iter().filter_map(|job| lever_job(company, job)));
+                    if postings.len() < LEVER_PAGE_SIZE {
+                        break;
+                    }
+                }
+                Ok(jobs)
+            }
+            JobBoard::Indeed => {
+                let origin = self.indeed_origin(url)?;
+                let mut jobs: Vec<JobPosting> = Vec::new();
+                for _ in 0..self.max_pages {
+                    let page_url = format!("{}/cmp/{}/jobs?start={}", origin, company, jobs.len());
+                    let cards = parse_indeed_listing(&origin, &self.fetch_html(&page_url).await?);
+                    let before = jobs.len();
+                    for card in cards {
+                        if !jobs.iter().any(|job| job.url == card.url) {
+                            jobs.push(card);
+                        }
+                    }
+                    if jobs.len() == before {
+                        break;
+                    }
+                }
+                Ok(jobs)
+            }
+        }
+    }
+
+    /// Posting `id`; Greenhouse and Lever postings need the `company` board
+    pub async fn posting(
+        &self,
+        board: JobBoard,
+        company: Option<&str>,
+        id: &str,
+        url: &str,
+    ) -> Result<JobPosting> {
+        let company_of =
+            || company.with_context(|| format!("{} postings need the company board", board.name()));
+        let job = match board {
+            JobBoard::Greenhouse => {
+                let company = company_of()?;
+                let response = self
+                    .api(
+                        &format!(
+                            "{}/v1/boards/{}/jobs/{}",
+                            self.endpoints.greenhouse_api, company, id
+                        ),
+                        json!({ "pay_transparency": "true" }),
+                    )
+                    .await?;
+                greenhouse_job(company, &response)
+            }
+            JobBoard::Lever => {
+                let company = company_of()?;
+                let response = self
+                    .api(
+                        &format!(
+                            "{}/v0/postings/{}/{}",
+                            self.endpoints.lever_api, company, id
+                        ),
+                        json!({ "mode": "json" }),
+                    )
+                    .await?;
+                lever_job(company, &response)
+            }
+            JobBoard::Indeed => {
+                let page_url = format!("{}/viewjob?jk={}", self.indeed_origin(url)?, id);
+                let html = self.fetch_html(&page_url).await?;
+                extract_json_ld(&html)
+                    .iter()
+                    .find_map(|document| find_json_ld_node(document, &["JobPosting"]))
+                    .and_then(|posting| json_ld_job(&page_url, posting))
+            }
+        };
+        job.with_context(|| format!("No {} job posting {}", board.name(), id))
+    }
+
+    async fn target_content(&self, url: &str, target: JobTarget) -> Result<ExtractedContent> {
+        match target {
+            JobTarget::Company { board, company } => {
+                let jobs = self.company_jobs(board, &company, url).await?;
+                listing_content(url, board, &company, &jobs)
+            }
+            JobTarget::Posting { board, company, id } => {
+                let job = self.posting(board, company.as_deref(), &id, url).await?;
+                let mut content = job_content(&job)?;
+                content
+                    .metadata
+                    .insert("jobs:board".into(), board.name().to_string());
+                Ok(content)
+            }
+        }
+    }
+}
+
+impl PlatformScraper for JobBoardScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            if let Some(target) = detect(&url) {
+                return self.target_content(&url, target).await;
+            }
+            // A company careers page, possibly with an embedded board
+            let html = self.fetch_html(&url).await?;
+            match detect_embed(&url, &html) {
+                Some(target) => self.target_content(&url, target).await,
+                None => Ok(super::content_from_html(url, &html.into())),
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        detect(url).is_some()
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_detects_boards_and_embeds() {
+        assert_eq!(
+            detect("https://boards.greenhouse.io/acme/jobs/4012345"),
+            Some(JobTarget::Posting {
+                board: JobBoard::Greenhouse,
+                company: Some("acme".to_string()),
+                id: "4012345".to_string()
+            })
+        );
+        assert_eq!(
+            detect("https://jobs.lever.co/acme"),
+            Some(JobTarget::Company {
+                board: JobBoard::Lever,
+                company: "acme".to_string()
+            })
+        );
+        assert_eq!(
+            detect("https://uk.indeed.com/viewjob?jk=abc123&from=serp")
+                .map(|target| target.board()),
+            Some(JobBoard::Indeed)
+        );
+        assert_eq!(detect("https://www.indeed.com/career-advice"), None);
+
+        let careers = r#"<div id="grnhse_app"></div>
+            <script src="https://boards.greenhouse.io/embed/job_board/js?for=acme"></script>"#;
+        assert_eq!(
+            detect_embed("https://acme.example/careers?gh_jid=77", careers),
+            Some(JobTarget::Posting {
+                board: JobBoard::Greenhouse,
+                company: Some("acme".to_string()),
+                id: "77".to_string()
+            })
+        );
+
+        let listing = r#"<script>window.mosaic.providerData["mosaic-provider-jobcards"]={"metaData":{"mosaicProviderJobCardsModel":{"results":[
+            {"jobkey":"abc123","displayTitle":"Data Analyst","company":"Acme","formattedLocation":"Austin, TX",
+             "salarySnippet":{"text":"$70,000 - $85,000 a year"},"snippet":"<ul><li>SQL</li></ul>","pubDate":1711929600000}
+        ]}}};</script>"#;
+        let jobs = parse_indeed_listing("https://www.indeed.com", listing);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].url, "https://www.indeed.com/viewjob?jk=abc123");
+        assert_eq!(jobs[0].salary.as_deref(), Some("$70,000 - $85,000 a year"));
+        assert_eq!(jobs[0].description, "SQL");
+    }
+
+    #[tokio::test]
+    async fn test_greenhouse_posting_and_lever_pagination() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/boards/acme/jobs/4012345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 4012345, "title": "Site Reliability Engineer",
+                "absolute_url": "https://boards.greenhouse.io/acme/jobs/4012345",
+                "company_name": "Acme Corp", "location": { "name": "Remote - US" },
+                "first_published": "2024-04-01T09:00:00-04:00",
+                "content": "&lt;p&gt;Keep &amp;amp; scale our systems.&lt;/p&gt;",
+                "pay_input_ranges": [{ "min_cents": 15000000, "max_cents": 18000000, "currency_type": "USD" }]
+            })))
+            .mount(&server)
+            .await;
+        let postings: Vec<Value> = (0..LEVER_PAGE_SIZE + 1)
+            .map(|i| {
+                json!({
+                    "id": format!("p{}", i), "text": format!("Role {}", i),
+                    "hostedUrl": format!("https://jobs.lever.co/acme/p{}", i),
+                    "categories": { "location": "Berlin", "commitment": "Full-time" },
+                    "createdAt": 1711929600000i64, "descriptionPlain": "About the role",
+                    "salaryRange": { "min": 60000, "max": 80000, "currency": "EUR", "interval": "per-year-salary" }
+                })
+            })
+            .collect();
+        for (skip, page) in [
+            (0, &postings[..LEVER_PAGE_SIZE]),
+            (LEVER_PAGE_SIZE, &postings[LEVER_PAGE_SIZE..]),
+        ] {
+            Mock::given(method("GET"))
+                .and(path("/v0/postings/acme"))
+                .and(query_param("skip", skip.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!(page)))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let scraper = JobBoardScraper::new(ScraperConfig::default())
+            .with_endpoints(JobBoardEndpoints::at(&server.uri()))
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()));
+
+        let content = scraper
+            .extract("https://boards.greenhouse.io/acme/jobs/4012345")
+            .await
+            .unwrap();
+        let job = JobPosting::from_content(&content).unwrap();
+        assert_eq!(job.company.as_deref(), Some("Acme Corp"));
+        assert_eq!(job.salary.as_deref(), Some("USD 150000-180000"));
+        assert_eq!(job.description, "Keep & scale our systems.");
+        assert_eq!(
+            job.posted_at.unwrap().to_rfc3339(),
+            "2024-04-01T13:00:00+00:00"
+        );
+
+        let content = scraper.extract("https://jobs.lever.co/acme").await.unwrap();
+        assert_eq!(content.metadata["jobs:count"], "101");
+        let jobs: Vec<JobPosting> =
+            serde_json::from_str(&content.metadata["jobs:postings"]).unwrap();
+        assert_eq!(jobs[100].title, "Role 100");
+        assert_eq!(jobs[0].salary.as_deref(), Some("EUR 60000-80000/YEAR"));
+        assert_eq!(jobs[0].employment_type.as_deref(), Some("Full-time"));
+    }
+}
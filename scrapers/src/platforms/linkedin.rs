@@ -0,0 +1,1082 @@
+//! LinkedIn scraper
+//!
+//! Extracts public profiles (`/in/<slug>`), company pages (`/company/<slug>`)
+//! and job postings (`/jobs/view/<id>`) as typed [`LinkedInProfile`],
+//! [`LinkedInCompany`] and [`JobPosting`] records. Without a session, the
+//! JSON-LD LinkedIn embeds in public pages is used. With an account in the
+//! scraper's [`CredentialVault`], the scraper logs in on demand, keeps the
+//! session cookies in its [`AuthStore`] and reads the Voyager API the web
+//! client uses, which adds full experience, education and skills. A
+//! session that LinkedIn rejects is dropped and the account logs in again once.
+//!
+//! LinkedIn restricts accounts that browse too fast, so every request is paced
+//! per account (and for anonymous requests) by [`AccountLimits`]: a minimum
+//! interval between requests and a daily cap, after which requests fail with
+//! [`AccountLimitReached`] instead of risking the account.
+
+use super::jobs::{job_content, json_ld_job};
+use crate::{
+    anti_bot::proxy_rotator::ProxyType,
+    api::{Account, ApiClient, ApiMethod, ApiRequest, AuthStore, CredentialVault},
+    extractors::{
+        extract_json_ld, find_json_key, find_json_ld_node, html_to_text, json_ld_address,
+        json_ld_text,
+    },
+    metadata::Metadata,
+    router::UrlRouter,
+    schemas::JobPosting,
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::header;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use swoop_core::{client::ClientKey, security::UrlValidator};
+
+const PLATFORM: &str = "linkedin";
+
+/// Hosts the scraper handles
+static HOSTS: Lazy<UrlRouter<()>> =
+    Lazy::new(|| UrlRouter::from_patterns(&["linkedin.com", "*.linkedin.com"], ()).unwrap());
+
+/// Rate-limit key for requests made without a session
+const GUEST: &str = "guest";
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+static LOGIN_CSRF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)name\s*=\s*"loginCsrfParam"\s+value\s*=\s*"([^"]+)""#).unwrap());
+static JOB_CRITERIA_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<h3[^>]*job-criteria-subheader[^>]*>\s*(.*?)\s*</h3>\s*<span[^>]*job-criteria-text[^>]*>\s*(.*?)\s*</span>"#,
+    )
+    .unwrap()
+});
+static APPLICANTS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)num-applicants__caption[^>]*>\s*([^<]*?)\s*<"#).unwrap());
+
+/// Where the scraper sends requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedInEndpoints {
+    pub base_url: String,
+}
+
+impl Default for LinkedInEndpoints {
+    fn default() -> Self {
+        Self::at("https://www.linkedin.com")
+    }
+}
+
+impl LinkedInEndpoints {
+    /// Endpoints served from `base`, e.g. a mock server
+    pub fn at(base: &str) -> Self {
+        Self {
+            base_url: base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+/// Request pacing applied to each account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccountLimits {
+    /// Minimum time between two requests of the same account
+    pub min_interval: Duration,
+    /// Requests per account per UTC day
+    pub daily_requests: u32,
+}
+
+impl Default for AccountLimits {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(5),
+            daily_requests: 250,
+        }
+    }
+}
+
+/// An account used up its daily request budget
+#[derive(Debug)]
+pub struct AccountLimitReached {
+    pub account: String,
+}
+
+impl fmt::Display for AccountLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LinkedIn daily request limit reached for {}",
+            self.account
+        )
+    }
+}
+
+impl std::error::Error for AccountLimitReached {}
+
+/// LinkedIn refused a login or a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkedInAuthError {
+    /// The page is behind the login wall and no account is configured
+    LoginRequired,
+    /// LinkedIn asked for a verification step that needs a human
+    Challenge,
+    /// Username or password were rejected
+    Rejected,
+}
+
+impl fmt::Display for LinkedInAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoginRequired => write!(
+                f,
+                "LinkedIn requires a logged-in session for this page; add an account to the credential vault"
+            ),
+            Self::Challenge => write!(
+                f,
+                "LinkedIn asked for a security verification; sign in once in a browser"
+            ),
+            Self::Rejected => write!(f, "LinkedIn rejected the login credentials"),
+        }
+    }
+}
+
+impl std::error::Error for LinkedInAuthError {}
+
+/// A position in a profile's experience section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub title: Option<String>,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    /// `YYYY` or `YYYY-MM`
+    pub start: Option<String>,
+    /// `YYYY` or `YYYY-MM`; `None` for current positions
+    pub end: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Education {
+    pub school: String,
+    pub degree: Option<String>,
+    pub field_of_study: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// LinkedIn member profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedInProfile {
+    pub url: String,
+    pub name: String,
+    pub headline: Option<String>,
+    pub location: Option<String>,
+    pub about: Option<String>,
+    pub experience: Vec<Experience>,
+    pub education: Vec<Education>,
+    /// Only available with a logged-in session
+    pub skills: Vec<String>,
+}
+
+impl LinkedInProfile {
+    pub const SCHEMA: &'static str = "linkedin_profile";
+}
+
+/// LinkedIn company page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedInCompany {
+    pub url: String,
+    pub name: String,
+    pub tagline: Option<String>,
+    pub description: Option<String>,
+    pub industry: Option<String>,
+    pub employee_count: Option<u64>,
+    pub headquarters: Option<String>,
+    pub website: Option<String>,
+    pub followers: Option<u64>,
+}
+
+impl LinkedInCompany {
+    pub const SCHEMA: &'static str = "linkedin_company";
+}
+
+/// A page on linkedin.com the scraper knows how to extract
+#[derive(Debug, Clone, PartialEq)]
+enum LinkedInTarget {
+    Profile { slug: String },
+    Company { slug: String },
+    Job { id: String },
+}
+
+fn parse_target(url: &str) -> Option<LinkedInTarget> {
+    let url = url::Url::parse(url).ok()?;
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["in", slug, ..] => Some(LinkedInTarget::Profile {
+            slug: slug.to_string(),
+        }),
+        ["company", slug, ..] => Some(LinkedInTarget::Company {
+            slug: slug.to_string(),
+        }),
+        // Job slugs look like `backend-engineer-at-acme-3912345678`
+        ["jobs", "view", slug, ..] => {
+            let id = slug.rsplit('-').next()?;
+            id.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| LinkedInTarget::Job { id: id.to_string() })
+        }
+        ["jobs", ..] => url
+            .query_pairs()
+            .find(|(key, _)| key == "currentJobId")
+            .map(|(_, id)| LinkedInTarget::Job {
+                id: id.into_owned(),
+            }),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+struct Usage {
+    day: NaiveDate,
+    requests: u32,
+    next_slot: Instant,
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    value.and_then(json_ld_text)
+}
+
+/// `YYYY-MM` from a Voyager `{ "year": 2021, "month": 3 }` date
+fn voyager_date(date: Option<&Value>) -> Option<String> {
+    let date = date?;
+    let year = date.get("year")?.as_u64()?;
+    Some(match date.get("month").and_then(Value::as_u64) {
+        Some(month) => format!("{}-{:02}", year, month),
+        None => year.to_string(),
+    })
+}
+
+/// `YYYY-MM` (or `YYYY`) from a JSON-LD date
+fn json_ld_date(date: Option<&Value>) -> Option<String> {
+    let date = text(date)?;
+    Some(
+        date.chars()
+            .take(7)
+            .collect::<String>()
+            .trim_end_matches('-')
+            .to_string(),
+    )
+}
+
+fn join_location(parts: &[Option<String>]) -> Option<String> {
+    let parts: Vec<&str> = parts.iter().flatten().map(String::as_str).collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+fn as_array(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(values)) => values.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(value) => vec![value],
+    }
+}
+
+fn public_profile(url: &str, html: &str) -> Option<LinkedInProfile> {
+    let documents = extract_json_ld(html);
+    let person = documents
+        .iter()
+        .find_map(|document| find_json_ld_node(document, &["Person"]))?;
+    let experience = as_array(person.get("worksFor"))
+        .into_iter()
+        .filter_map(|organization| {
+            let member = organization.get("member");
+            Some(Experience {
+                title: text(member.and_then(|member| member.get("roleName"))),
+                company: Some(text(organization.get("name"))?),
+                location: text(organization.get("location")),
+                start: json_ld_date(member.and_then(|member| member.get("startDate"))),
+                end: json_ld_date(member.and_then(|member| member.get("endDate"))),
+                description: text(member.and_then(|member| member.get("description"))),
+            })
+        })
+        .collect();
+    let education = as_array(person.get("alumniOf"))
+        .into_iter()
+        .filter(|organization| {
+            organization
+                .get("@type")
+                .and_then(Value::as_str)
+                .is_none_or(|kind| kind != "Organization")
+        })
+        .filter_map(|school| {
+            let member = school.get("member");
+            Some(Education {
+                school: text(school.get("name"))?,
+                degree: None,
+                field_of_study: None,
+                start: json_ld_date(member.and_then(|member| member.get("startDate"))),
+                end: json_ld_date(member.and_then(|member| member.get("endDate"))),
+            })
+        })
+        .collect();
+
+    Some(LinkedInProfile {
+        url: url.to_string(),
+        name: text(person.get("name"))?,
+        headline: text(person.get("jobTitle")),
+        location: json_ld_address(person.get("address")),
+        about: text(person.get("description")),
+        experience,
+        education,
+        skills: Vec::new(),
+    })
+}
+
+fn public_company(url: &str, html: &str) -> Option<LinkedInCompany> {
+    let documents = extract_json_ld(html);
+    let organization = documents.iter().find_map(|document| {
+        find_json_ld_node(
+            document,
+            &["Organization", "Corporation", "EducationalOrganization"],
+        )
+    })?;
+    Some(LinkedInCompany {
+        url: url.to_string(),
+        name: text(organization.get("name"))?,
+        tagline: text(organization.get("slogan")),
+        description: text(organization.get("description")),
+        industry: None,
+        employee_count: organization
+            .pointer("/numberOfEmployees/value")
+            .and_then(Value::as_u64),
+        headquarters: json_ld_address(organization.get("address")),
+        website: text(organization.get("sameAs")),
+        followers: None,
+    })
+}
+
+fn public_job(url: &str, html: &str) -> Option<JobPosting> {
+    let documents = extract_json_ld(html);
+    let posting = documents
+        .iter()
+        .find_map(|document| find_json_ld_node(document, &["JobPosting"]))?;
+    let criteria: HashMap<String, String> = JOB_CRITERIA_REGEX
+        .captures_iter(html)
+        .map(|captures| {
+            (
+                html_to_text(&captures[1]).to_ascii_lowercase(),
+                html_to_text(&captures[2]),
+            )
+        })
+        .collect();
+    let applicants = APPLICANTS_REGEX.captures(html).and_then(|captures| {
+        let digits: String = captures[1].chars().filter(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    });
+
+    let mut job = json_ld_job(url, posting)?;
+    job.employment_type = job
+        .employment_type
+        .or_else(|| criteria.get("employment type").cloned());
+    job.seniority = criteria.get("seniority level").cloned();
+    job.applicants = applicants;
+    Some(job)
+}
+
+fn voyager_profile(url: &str, view: &Value, skills: &Value) -> Option<LinkedInProfile> {
+    let profile = view.get("profile")?;
+    let name = join_location(&[
+        text(profile.get("firstName")),
+        text(profile.get("lastName")),
+    ])?
+    .replace(", ", " ");
+    let experience = as_array(view.pointer("/positionView/elements"))
+        .into_iter()
+        .map(|position| Experience {
+            title: text(position.get("title")),
+            company: text(position.get("companyName")),
+            location: text(position.get("locationName")),
+            start: voyager_date(position.pointer("/timePeriod/startDate")),
+            end: voyager_date(position.pointer("/timePeriod/endDate")),
+            description: text(position.get("description")),
+        })
+        .collect();
+    let education = as_array(view.pointer("/educationView/elements"))
+        .into_iter()
+        .filter_map(|school| {
+            Some(Education {
+                school: text(school.get("schoolName"))?,
+                degree: text(school.get("degreeName")),
+                field_of_study: text(school.get("fieldOfStudy")),
+                start: voyager_date(school.pointer("/timePeriod/startDate")),
+                end: voyager_date(school.pointer("/timePeriod/endDate")),
+            })
+        })
+        .collect();
+    let skills = as_array(skills.get("elements"))
+        .into_iter()
+        .filter_map(|skill| text(skill.get("name")))
+        .collect();
+
+    Some(LinkedInProfile {
+        url: url.to_string(),
+        name,
+        headline: text(profile.get("headline")),
+        location: text(profile.get("locationName")),
+        about: text(profile.get("summary")),
+        experience,
+        education,
+        skills,
+    })
+}
+
+fn voyager_company(url: &str, response: &Value) -> Option<LinkedInCompany> {
+    let company = as_array(response.get("elements")).into_iter().next()?;
+    Some(LinkedInCompany {
+        url: url.to_string(),
+        name: text(company.get("name"))?,
+        tagline: text(company.get("tagline")),
+        description: text(company.get("description")),
+        industry: company
+            .pointer("/companyIndustries/0/localizedName")
+            .and_then(json_ld_text),
+        employee_count: company.get("staffCount").and_then(Value::as_u64),
+        headquarters: company.get("headquarter").and_then(|hq| {
+            join_location(&[
+                text(hq.get("city")),
+                text(hq.get("geographicArea")),
+                text(hq.get("country")),
+            ])
+        }),
+        website: text(company.get("companyPageUrl")),
+        followers: company
+            .pointer("/followingInfo/followerCount")
+            .and_then(Value::as_u64),
+    })
+}
+
+fn voyager_job(url: &str, posting: &Value) -> Option<JobPosting> {
+    let company = posting.get("companyDetails").and_then(|details| {
+        find_json_key(details, "companyResolutionResult")
+            .and_then(|company| text(company.get("name")))
+            .or_else(|| text(find_json_key(details, "companyName")))
+    });
+    Some(JobPosting {
+        url: url.to_string(),
+        title: text(posting.get("title"))?,
+        company,
+        location: text(posting.get("formattedLocation")),
+        employment_type: text(posting.get("formattedEmploymentStatus")),
+        seniority: text(posting.get("formattedExperienceLevel")),
+        posted_at: posting
+            .get("listedAt")
+            .and_then(Value::as_i64)
+            .and_then(DateTime::<Utc>::from_timestamp_millis),
+        salary: None,
+        applicants: posting.get("applies").and_then(Value::as_u64),
+        description: text(posting.pointer("/description/text")).unwrap_or_default(),
+    })
+}
+
+/// Content carrying a typed record as `schema`/`schema_record` metadata
+fn typed_content<T: Serialize>(
+    url: &str,
+    title: &str,
+    text: String,
+    schema: &str,
+    record: &T,
+    mut metadata: Metadata,
+) -> Result<ExtractedContent> {
+    metadata.insert("schema".into(), schema.to_string());
+    metadata.insert("schema_record".into(), serde_json::to_string(record)?);
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(title.into()),
+        text: Some(text.into()),
+        metadata,
+        tags: vec![schema.to_string()],
+        extracted_at: Utc::now(),
+    })
+}
+
+fn profile_content(profile: &LinkedInProfile) -> Result<ExtractedContent> {
+    let mut lines = vec![profile.name.clone()];
+    lines.extend(profile.headline.clone());
+    lines.extend(profile.about.clone());
+    for position in &profile.experience {
+        lines.push(format!(
+            "{} at {} ({} - {})",
+            position.title.as_deref().unwrap_or("Position"),
+            position.company.as_deref().unwrap_or("unknown company"),
+            position.start.as_deref().unwrap_or("?"),
+            position.end.as_deref().unwrap_or("present")
+        ));
+    }
+    if !profile.skills.is_empty() {
+        lines.push(format!("Skills: {}", profile.skills.join(", ")));
+    }
+    typed_content(
+        &profile.url,
+        &profile.name,
+        lines.join("\n"),
+        LinkedInProfile::SCHEMA,
+        profile,
+        Metadata::new(),
+    )
+}
+
+fn company_content(company: &LinkedInCompany) -> Result<ExtractedContent> {
+    let text = [&company.tagline, &company.description]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    typed_content(
+        &company.url,
+        &company.name,
+        text,
+        LinkedInCompany::SCHEMA,
+        company,
+        Metadata::new(),
+    )
+}
+
+/// `name=value` pairs from a response's `Set-Cookie` headers
+fn response_cookies(response: &reqwest::Response, cookies: &mut BTreeMap<String, String>) {
+    for value in response.headers().get_all(header::SET_COOKIE) {
+        let Some((name, value)) = value
+            .to_str()
+            .ok()
+            .and_then(|cookie| cookie.split(';').next())
+            .and_then(|pair| pair.split_once('='))
+        else {
+            continue;
+        };
+        cookies.insert(name.trim().to_string(), value.trim().to_string());
+    }
+}
+
+fn cookie_header(cookies: &BTreeMap<String, String>) -> String {
+    cookies
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn is_session_rejected(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| matches!(status.as_u16(), 401 | 403))
+}
+
+/// Scraper for linkedin.com profiles, company pages and job postings
+pub struct LinkedInScraper {
+    config: ScraperConfig,
+    endpoints: LinkedInEndpoints,
+    validator: UrlValidator,
+    auth: Arc<AuthStore>,
+    vault: Arc<CredentialVault>,
+    limits: AccountLimits,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl LinkedInScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            endpoints: LinkedInEndpoints::default(),
+            validator: UrlValidator::default(),
+            auth: Arc::new(AuthStore::in_memory()),
+            vault: Arc::new(CredentialVault::in_memory()),
+            limits: AccountLimits::default(),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: LinkedInEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Keep session cookies in `auth`, e.g. a store loaded from disk
+    pub fn with_auth(mut self, auth: Arc<AuthStore>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Log in with the first `linkedin` account in `vault` when a session is needed
+    pub fn with_vault(mut self, vault: Arc<CredentialVault>) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: AccountLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn account(&self) -> Option<Account> {
+        self.vault.accounts(PLATFORM).into_iter().next()
+    }
+
+    fn has_session(&self) -> bool {
+        self.auth
+            .headers(PLATFORM)
+            .get("cookie")
+            .is_some_and(|cookie| cookie.contains("li_at="))
+    }
+
+    /// Wait for the account's next request slot, failing once its daily budget is spent
+    async fn pace(&self, account: &str) -> Result<()> {
+        let wait = {
+            let mut usage = self.usage.lock().unwrap();
+            let now = Instant::now();
+            let today = Utc::now().date_naive();
+            let usage = usage.entry(account.to_string()).or_insert(Usage {
+                day: today,
+                requests: 0,
+                next_slot: now,
+            });
+            if usage.day != today {
+                usage.day = today;
+                usage.requests = 0;
+            }
+            if usage.requests >= self.limits.daily_requests {
+                return Err(AccountLimitReached {
+                    account: account.to_string(),
+                }
+                .into());
+            }
+            usage.requests += 1;
+            let slot = usage.next_slot.max(now);
+            usage.next_slot = slot + self.limits.min_interval;
+            slot - now
+        };
+        tokio::time::sleep(wait).await;
+        Ok(())
+    }
+
+    fn http_client(&self) -> Result<reqwest::Client> {
+        let key = ClientKey {
+            user_agent: Some(USER_AGENT.to_string()),
+            timeout: Some(Duration::from_secs(self.config.timeout_secs)),
+            manual_redirects: true,
+            ..ClientKey::default()
+        };
+        Ok(swoop_core::client::pool().client(&key)?.client().clone())
+    }
+
+    /// Sign in with `account` and keep the session cookies in the auth store
+    async fn login(&self, account: &Account) -> Result<()> {
+        self.pace(&account.username).await?;
+        let client = self.http_client()?;
+        let login_url = self.endpoints.url("/login");
+        self.validator.validate_url(&login_url)?;
+
+        let mut cookies = BTreeMap::new();
+        let page = client.get(&login_url).send().await?.error_for_status()?;
+        response_cookies(&page, &mut cookies);
+        let html = page.text().await?;
+        let csrf = LOGIN_CSRF_REGEX
+            .captures(&html)
+            .map(|captures| captures[1].to_string())
+            .context("LinkedIn login page has no CSRF token")?;
+
+        let submit = client
+            .post(self.endpoints.url("/checkpoint/lg/login-submit"))
+            .header(header::COOKIE, cookie_header(&cookies))
+            .form(&[
+                ("session_key", account.username.as_str()),
+                ("session_password", account.password.as_str()),
+                ("loginCsrfParam", csrf.as_str()),
+            ])
+            .send()
+            .await?;
+        response_cookies(&submit, &mut cookies);
+        let location = submit
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .unwrap_or_default();
+
+        let (Some(li_at), Some(jsessionid)) = (cookies.get("li_at"), cookies.get("JSESSIONID"))
+        else {
+            return Err(if location.contains("/checkpoint/challenge") {
+                LinkedInAuthError::Challenge
+            } else {
+                LinkedInAuthError::Rejected
+            }
+            .into());
+        };
+        self.auth.set(
+            PLATFORM,
+            "cookie",
+            &format!("li_at={}; JSESSIONID={}", li_at, jsessionid),
+        );
+        // The Voyager API expects the session ID, without quotes, as its CSRF token
+        self.auth
+            .set(PLATFORM, "csrf-token", jsessionid.trim_matches('"'));
+        self.auth.save()
+    }
+
+    fn drop_session(&self) {
+        self.auth.remove(PLATFORM, "cookie");
+        self.auth.remove(PLATFORM, "csrf-token");
+    }
+
+    /// GET a Voyager API path with the session, logging in again once if it was rejected
+    async fn voyager(&self, path: &str) -> Result<Value> {
+        let account = self.account();
+        let key = account
+            .as_ref()
+            .map_or("session".to_string(), |account| account.username.clone());
+        let request = ApiRequest::template(
+            &self.endpoints.url(&format!("/voyager/api{}", path)),
+            json!({}),
+        )
+        .with_method(ApiMethod::Get)
+        .with_header("x-restli-protocol-version", "2.0.0")
+        .with_header("accept", "application/json");
+        let client = ApiClient::new(Duration::from_secs(self.config.timeout_secs))
+            .with_validator(self.validator.clone())
+            .with_auth(self.auth.clone());
+
+        let mut relogged = false;
+        loop {
+            if !self.has_session() {
+                let account = account.as_ref().ok_or(LinkedInAuthError::LoginRequired)?;
+                self.login(account).await?;
+                relogged = true;
+            }
+            self.pace(&key).await?;
+            match client.fetch_pages(PLATFORM, &request).await {
+                Ok(mut pages) => return Ok(pages.remove(0)),
+                Err(e) if is_session_rejected(&e) && !relogged && account.is_some() => {
+                    self.drop_session();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Fetch a public page anonymously, failing on LinkedIn's login wall
+    async fn public_page(&self, url: &str) -> Result<String> {
+        self.pace(GUEST).await?;
+        self.validator.validate_url(url)?;
+        let response = self
+            .http_client()?
+            .get(url)
+            .header(header::ACCEPT_LANGUAGE, "en-US,en;q=0.9")
+            .send()
+            .await?;
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .unwrap_or_default();
+        // LinkedIn answers blocked anonymous requests with a 999 or an authwall redirect
+        if response.status().as_u16() == 999
+            || location.contains("/authwall")
+            || location.contains("/login")
+        {
+            return Err(LinkedInAuthError::LoginRequired.into());
+        }
+        Ok(response.error_for_status()?.text().await?)
+    }
+
+    async fn extract_authenticated(
+        &self,
+        url: &str,
+        target: &LinkedInTarget,
+    ) -> Result<ExtractedContent> {
+        match target {
+            LinkedInTarget::Profile { slug } => {
+                let view = self
+                    .voyager(&format!("/identity/profiles/{}/profileView", slug))
+                    .await?;
+                let skills = self
+                    .voyager(&format!("/identity/profiles/{}/skills?count=100", slug))
+                    .await
+                    .unwrap_or(Value::Null);
+                let profile = voyager_profile(url, &view, &skills)
+                    .with_context(|| format!("LinkedIn profile {} not found", slug))?;
+                profile_content(&profile)
+            }
+            LinkedInTarget::Company { slug } => {
+                let response = self
+                    .voyager(&format!(
+                        "/organization/companies?q=universalName&universalName={}",
+                        slug
+                    ))
+                    .await?;
+                let company = voyager_company(url, &response)
+                    .with_context(|| format!("LinkedIn company {} not found", slug))?;
+                company_content(&company)
+            }
+            LinkedInTarget::Job { id } => {
+                let posting = self.voyager(&format!("/jobs/jobPostings/{}", id)).await?;
+                let job = voyager_job(url, &posting)
+                    .with_context(|| format!("LinkedIn job {} not found", id))?;
+                job_content(&job)
+            }
+        }
+    }
+
+    async fn extract_public(&self, url: &str, target: &LinkedInTarget) -> Result<ExtractedContent> {
+        let page_url = match target {
+            LinkedInTarget::Profile { slug } => self.endpoints.url(&format!("/in/{}", slug)),
+            LinkedInTarget::Company { slug } => self.endpoints.url(&format!("/company/{}", slug)),
+            LinkedInTarget::Job { id } => self.endpoints.url(&format!("/jobs/view/{}", id)),
+        };
+        let html = self.public_page(&page_url).await?;
+        let missing = || anyhow::anyhow!("No structured data in LinkedIn page {}", url);
+        match target {
+            LinkedInTarget::Profile { .. } => {
+                profile_content(&public_profile(url, &html).ok_or_else(missing)?)
+            }
+            LinkedInTarget::Company { .. } => {
+                company_content(&public_company(url, &html).ok_or_else(missing)?)
+            }
+            LinkedInTarget::Job { .. } => job_content(&public_job(url, &html).ok_or_else(missing)?),
+        }
+    }
+}
+
+impl PlatformScraper for LinkedInScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            let target = parse_target(&url)
+                .with_context(|| format!("Not a LinkedIn profile, company or job URL: {}", url))?;
+            if self.has_session() || self.account().is_some() {
+                self.extract_authenticated(&url, &target).await
+            } else {
+                self.extract_public(&url, &target).await
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        HOSTS.is_match(url)
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        // Guests get public pages; logins are posted with vault accounts
+        ScraperCapabilities {
+            auth: Need::Optional,
+            proxy_tier: Some(ProxyType::Residential),
+            post: true,
+            ..ScraperCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::Schema;
+    use wiremock::{
+        matchers::{body_string_contains, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn scraper(server: &MockServer) -> LinkedInScraper {
+        let host = url::Url::parse(&server.uri()).unwrap();
+        LinkedInScraper::new(ScraperConfig::default())
+            .with_endpoints(LinkedInEndpoints::at(&server.uri()))
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_limits(AccountLimits {
+                min_interval: Duration::ZERO,
+                daily_requests: 10,
+            })
+    }
+
+    #[test]
+    fn test_parse_targets_and_public_pages() {
+        assert_eq!(
+            parse_target("https://www.linkedin.com/in/jane-doe-123/"),
+            Some(LinkedInTarget::Profile {
+                slug: "jane-doe-123".to_string()
+            })
+        );
+        assert_eq!(
+            parse_target("https://www.linkedin.com/jobs/view/backend-engineer-at-acme-3912345678"),
+            Some(LinkedInTarget::Job {
+                id: "3912345678".to_string()
+            })
+        );
+        assert_eq!(
+            parse_target("https://www.linkedin.com/jobs/search/?currentJobId=42"),
+            Some(LinkedInTarget::Job {
+                id: "42".to_string()
+            })
+        );
+        assert_eq!(parse_target("https://www.linkedin.com/feed/"), None);
+
+        let profile_html = r#"<script type="application/ld+json">{"@context": "http://schema.org",
+            "@graph": [{"@type": "Person", "name": "Jane Doe", "jobTitle": ["Staff Engineer"],
+              "address": {"@type": "PostalAddress", "addressLocality": "Berlin", "addressCountry": "DE"},
+              "worksFor": [{"@type": "Organization", "name": "Acme",
+                "member": {"@type": "OrganizationRole", "startDate": "2021-03"}}],
+              "alumniOf": [{"@type": "EducationalOrganization", "name": "TU Berlin",
+                "member": {"@type": "OrganizationRole", "startDate": 2012, "endDate": 2016}}]}]}
+            </script>"#;
+        let profile = public_profile("https://www.linkedin.com/in/jane", profile_html).unwrap();
+        assert_eq!(profile.headline.as_deref(), Some("Staff Engineer"));
+        assert_eq!(profile.location.as_deref(), Some("Berlin, DE"));
+        assert_eq!(profile.experience[0].company.as_deref(), Some("Acme"));
+        assert_eq!(profile.experience[0].start.as_deref(), Some("2021-03"));
+        assert_eq!(profile.education[0].end.as_deref(), Some("2016"));
+
+        let job_html = r#"<script type="application/ld+json">{"@type": "JobPosting",
+            "title": "Backend Engineer", "datePosted": "2024-04-01",
+            "description": "&lt;p&gt;Build the crawler.&lt;/p&gt;",
+            "hiringOrganization": {"@type": "Organization", "name": "Acme"},
+            "jobLocation": {"@type": "Place", "address": {"addressLocality": "Remote"}},
+            "baseSalary": {"currency": "EUR", "value": {"minValue": 70000, "maxValue": 90000, "unitText": "YEAR"}}}
+            </script>
+            <h3 class="description__job-criteria-subheader">Seniority level</h3>
+            <span class="description__job-criteria-text description__job-criteria-text--criteria">Mid-Senior level</span>
+            <figcaption class="num-applicants__caption">Over 200 applicants</figcaption>"#;
+        let job = public_job("https://www.linkedin.com/jobs/view/1", job_html).unwrap();
+        assert_eq!(job.company.as_deref(), Some("Acme"));
+        assert_eq!(job.seniority.as_deref(), Some("Mid-Senior level"));
+        assert_eq!(job.salary.as_deref(), Some("EUR 70000-90000/YEAR"));
+        assert_eq!(job.applicants, Some(200));
+        assert_eq!(job.description, "Build the crawler.");
+
+        let content = job_content(&job).unwrap();
+        assert_eq!(
+            JobPosting::from_content(&content).unwrap().title,
+            "Backend Engineer"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logs_in_from_vault_and_reads_voyager() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/login"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("set-cookie", "JSESSIONID=\"ajax:123\"; Path=/")
+                    .set_body_string(
+                        r#"<form><input type="hidden" name="loginCsrfParam" value="csrf-1"></form>"#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/checkpoint/lg/login-submit"))
+            .and(body_string_contains("session_password=hunter2"))
+            .and(body_string_contains("loginCsrfParam=csrf-1"))
+            .respond_with(
+                ResponseTemplate::new(303)
+                    .insert_header("set-cookie", "li_at=session-token; Path=/; HttpOnly")
+                    .insert_header("location", "/feed/"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/voyager/api/identity/profiles/jane/profileView"))
+            .and(header("csrf-token", "ajax:123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "profile": { "firstName": "Jane", "lastName": "Doe", "headline": "Staff Engineer" },
+                "positionView": { "elements": [{
+                    "title": "Staff Engineer", "companyName": "Acme",
+                    "timePeriod": { "startDate": { "year": 2021, "month": 3 } }
+                }] },
+                "educationView": { "elements": [{ "schoolName": "TU Berlin", "degreeName": "MSc" }] }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/voyager/api/identity/profiles/jane/skills"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "elements": [{ "name": "Rust" }, { "name": "Distributed Systems" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let vault = CredentialVault::in_memory()
+            .with_account(PLATFORM, Account::new("jane@example.com", "hunter2"));
+        let auth = Arc::new(AuthStore::in_memory());
+        let scraper = scraper(&server)
+            .with_vault(Arc::new(vault))
+            .with_auth(auth.clone());
+        let content = scraper
+            .extract("https://www.linkedin.com/in/jane")
+            .await
+            .unwrap();
+        assert_eq!(content.title.as_deref(), Some("Jane Doe"));
+        assert_eq!(content.metadata["schema"], LinkedInProfile::SCHEMA);
+        let profile: LinkedInProfile =
+            serde_json::from_str(&content.metadata["schema_record"]).unwrap();
+        assert_eq!(profile.experience[0].start.as_deref(), Some("2021-03"));
+        assert_eq!(profile.education[0].degree.as_deref(), Some("MSc"));
+        assert_eq!(profile.skills, vec!["Rust", "Distributed Systems"]);
+        assert_eq!(
+            auth.headers(PLATFORM)["cookie"],
+            "li_at=session-token; JSESSIONID=\"ajax:123\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_guest_limits_and_login_wall() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/company/acme"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/authwall?trk=x"))
+            .mount(&server)
+            .await;
+
+        let scraper = scraper(&server).with_limits(AccountLimits {
+            min_interval: Duration::ZERO,
+            daily_requests: 1,
+        });
+        let error = scraper
+            .extract("https://www.linkedin.com/company/acme")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<LinkedInAuthError>(),
+            Some(&LinkedInAuthError::LoginRequired)
+        );
+
+        let error = scraper
+            .extract("https://www.linkedin.com/company/acme")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<AccountLimitReached>().unwrap().account,
+            GUEST
+        );
+    }
+}
@@ -0,0 +1,617 @@
+//! Google search results scraper
+//!
+//! [`SerpScraper::search`] fetches result pages for a [`SerpQuery`] and parses
+//! organic results, ads and "People also ask" questions with [`parse_serp`].
+//! Google blocks automated traffic aggressively, so every request goes through
+//! a [`HostThrottle`] running the gentle profile by default, rotates between
+//! current desktop user agents, carries consent cookies that skip the EU
+//! interstitial and can be sent through a proxy. CAPTCHA and consent pages are
+//! detected and reported as a [`SerpBlock`]; with a browser configured, blocked
+//! pages are rendered there instead.
+
+use crate::{
+    anti_bot::proxy_rotator::ProxyType,
+    browser::BrowserPool,
+    metadata::Metadata,
+    rate_limiter::{HostThrottle, RateProfile},
+    router::UrlRouter,
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+use swoop_core::trace::FetchTracer;
+
+const SEARCH_URL: &str = "https://www.google.com/search";
+const RESULTS_PER_PAGE: u32 = 10;
+/// Pre-accepted consent, so EU traffic isn't redirected to consent.google.com
+const CONSENT_COOKIE: &str = "CONSENT=YES+cb; SOCS=CAESEwgDEgk0ODE3Nzk3MjQaAmVuIAEaBgiA_LyaBg";
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+];
+
+/// Google result pages on any country domain
+static SEARCH_PAGES: Lazy<UrlRouter<()>> = Lazy::new(|| {
+    UrlRouter::from_patterns(&["google.*/search", "www.google.*/search"], ()).unwrap()
+});
+static ANCHOR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)<a\b([^>]*)>(.*?)</a>"#).unwrap());
+static HREF_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)\bhref\s*=\s*"([^"]*)""#).unwrap());
+static HEADING_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<h3\b[^>]*>(.*?)</h3>|<div\b[^>]*role="heading"[^>]*>(.*?)</div>"#).unwrap()
+});
+static SNIPPET_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<(?:div|span)\b[^>]*class="[^"]*\b(?:VwiC3b|IsZvec|s3v9rd|MUxGbd|yDYNvb)\b[^"]*"[^>]*>(.*?)</(?:div|span)>"#,
+    )
+    .unwrap()
+});
+static QUESTION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bdata-q\s*=\s*"([^"]+)""#).unwrap());
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+/// A search and where to run it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerpQuery {
+    pub query: String,
+    /// Interface language (`hl`), e.g. `en`
+    pub language: Option<String>,
+    /// Result country (`gl`), e.g. `us`
+    pub country: Option<String>,
+}
+
+impl SerpQuery {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            language: None,
+            country: None,
+        }
+    }
+
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    pub fn with_country(mut self, country: &str) -> Self {
+        self.country = Some(country.to_string());
+        self
+    }
+
+    /// URL of result page `page`, counting from 0
+    pub fn page_url(&self, base: &str, page: u32) -> String {
+        let mut url = url::Url::parse(base).expect("search URL is valid");
+        {
+            let mut params = url.query_pairs_mut();
+            params.append_pair("q", &self.query);
+            if page > 0 {
+                params.append_pair("start", &(page * RESULTS_PER_PAGE).to_string());
+            }
+            if let Some(language) = &self.language {
+                params.append_pair("hl", language);
+            }
+            if let Some(country) = &self.country {
+                params.append_pair("gl", country);
+            }
+        }
+        url.to_string()
+    }
+
+    /// Query and page of a Google search URL
+    pub fn from_url(url: &str) -> Option<(Self, u32)> {
+        let url = url::Url::parse(url).ok()?;
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        let query = Self {
+            query: params.get("q")?.clone(),
+            language: params.get("hl").cloned(),
+            country: params.get("gl").cloned(),
+        };
+        let start: u32 = params
+            .get("start")
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(0);
+        Some((query, start / RESULTS_PER_PAGE))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrganicResult {
+    /// 1-based rank across all fetched pages
+    pub position: u32,
+    pub title: String,
+    pub url: String,
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdResult {
+    /// 1-based rank among the page's ads
+    pub position: u32,
+    pub title: String,
+    pub url: String,
+    pub snippet: Option<String>,
+}
+
+/// One parsed result page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerpPage {
+    pub query: String,
+    /// Page number, counting from 0
+    pub page: u32,
+    pub organic: Vec<OrganicResult>,
+    pub ads: Vec<AdResult>,
+    pub people_also_ask: Vec<String>,
+    pub has_next: bool,
+}
+
+/// Why Google refused to serve results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SerpBlock {
+    /// The "unusual traffic" CAPTCHA or a 429
+    Captcha,
+    /// The cookie consent interstitial
+    Consent,
+}
+
+impl fmt::Display for SerpBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerpBlock::Captcha => write!(f, "Google served a CAPTCHA (unusual traffic)"),
+            SerpBlock::Consent => write!(f, "Google served its consent page"),
+        }
+    }
+}
+
+impl std::error::Error for SerpBlock {}
+
+/// Classify a response as a block page rather than results
+pub fn detect_block(final_url: Option<&str>, status: Option<u16>, html: &str) -> Option<SerpBlock> {
+    let url = final_url.unwrap_or_default();
+    if status == Some(429)
+        || url.contains("/sorry/")
+        || html.contains("id=\"captcha-form\"")
+        || html.contains("unusual traffic from your computer network")
+    {
+        return Some(SerpBlock::Captcha);
+    }
+    let consent_host = url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.host_str()
+                .map(|host| host.starts_with("consent.google."))
+        })
+        .unwrap_or(false);
+    if consent_host || html.contains("action=\"https://consent.google.") {
+        return Some(SerpBlock::Consent);
+    }
+    None
+}
+
+fn clean_text(html: &str) -> String {
+    let text = TAG_REGEX.replace_all(html, " ");
+    let text = text
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Absolute target of a result link, unwrapping `/url?q=` redirects
+fn result_url(href: &str) -> Option<String> {
+    let href = href.replace("&amp;", "&");
+    let url = if href.starts_with("/url?") || href.starts_with("/aclk?") {
+        let parsed = url::Url::parse(&format!("https://www.google.com{}", href)).ok()?;
+        parsed
+            .query_pairs()
+            .find(|(key, _)| key == "q" || key == "url" || key == "adurl")?
+            .1
+            .into_owned()
+    } else {
+        href
+    };
+    let parsed = url::Url::parse(&url).ok()?;
+    let host = parsed.host_str()?;
+    let internal = host.ends_with("google.com") && !parsed.path().starts_with("/aclk");
+    (matches!(parsed.scheme(), "http" | "https") && !internal).then_some(url)
+}
+
+/// Byte range of the element with `id`, up to where `end_ids` or the document ends
+fn section(html: &str, id: &str, end_ids: &[&str]) -> Option<(usize, usize)> {
+    let start = html.find(&format!("id=\"{}\"", id))?;
+    let end = end_ids
+        .iter()
+        .filter_map(|end| html[start..].find(&format!("id=\"{}\"", end)))
+        .min()
+        .map_or(html.len(), |offset| start + offset);
+    Some((start, end))
+}
+
+/// Parse a Google result page
+pub fn parse_serp(html: &str, query: &str, page: u32) -> SerpPage {
+    let ad_sections: Vec<(usize, usize)> = [
+        section(html, "tads", &["rso", "search", "res"]),
+        section(html, "tadsb", &["botstuff", "foot"]),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let in_ads = |offset: usize| {
+        ad_sections
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&offset))
+    };
+
+    let anchors: Vec<(usize, usize, String, String)> = ANCHOR_REGEX
+        .captures_iter(html)
+        .filter_map(|captures| {
+            let whole = captures.get(0)?;
+            let href = HREF_REGEX
+                .captures(captures.get(1)?.as_str())?
+                .get(1)?
+                .as_str();
+            let heading = HEADING_REGEX.captures(captures.get(2)?.as_str())?;
+            let title = clean_text(heading.get(1).or_else(|| heading.get(2))?.as_str());
+            let url = result_url(href)?;
+            (!title.is_empty()).then_some((whole.start(), whole.end(), url, title))
+        })
+        .collect();
+
+    let mut result = SerpPage {
+        query: query.to_string(),
+        page,
+        has_next: html.contains("id=\"pnnext\""),
+        ..SerpPage::default()
+    };
+    for (index, (start, end, url, title)) in anchors.iter().enumerate() {
+        // The snippet sits between this result's link and the next one
+        let next = anchors.get(index + 1).map_or(html.len(), |next| next.0);
+        let snippet = SNIPPET_REGEX
+            .captures(&html[*end..next])
+            .and_then(|captures| captures.get(1))
+            .map(|snippet| clean_text(snippet.as_str()))
+            .filter(|snippet| !snippet.is_empty());
+
+        if in_ads(*start) {
+            result.ads.push(AdResult {
+                position: result.ads.len() as u32 + 1,
+                title: title.clone(),
+                url: url.clone(),
+                snippet,
+            });
+        } else if !result.organic.iter().any(|seen| &seen.url == url) {
+            result.organic.push(OrganicResult {
+                position: page * RESULTS_PER_PAGE + result.organic.len() as u32 + 1,
+                title: title.clone(),
+                url: url.clone(),
+                snippet,
+            });
+        }
+    }
+
+    for captures in QUESTION_REGEX.captures_iter(html) {
+        let question = clean_text(&captures[1]);
+        if !question.is_empty() && !result.people_also_ask.contains(&question) {
+            result.people_also_ask.push(question);
+        }
+    }
+    result
+}
+
+/// Pages fetched by [`SerpScraper::search`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerpResults {
+    pub pages: Vec<SerpPage>,
+    /// Set when Google blocked a page; `pages` holds what was fetched before
+    pub blocked: Option<SerpBlock>,
+}
+
+impl SerpResults {
+    pub fn organic(&self) -> impl Iterator<Item = &OrganicResult> {
+        self.pages.iter().flat_map(|page| page.organic.iter())
+    }
+}
+
+/// Scraper for Google web search
+pub struct SerpScraper {
+    config: ScraperConfig,
+    search_url: String,
+    throttle: Arc<HostThrottle>,
+    proxy: Option<String>,
+    browser: Option<Arc<BrowserPool>>,
+}
+
+impl SerpScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            search_url: SEARCH_URL.to_string(),
+            throttle: Arc::new(
+                HostThrottle::new(RateProfile::gentle()).expect("preset profile is valid"),
+            ),
+            proxy: None,
+            browser: None,
+        }
+    }
+
+    /// Search somewhere other than google.com, e.g. a country domain or a mock server
+    pub fn with_search_url(mut self, url: &str) -> Self {
+        self.search_url = url.to_string();
+        self
+    }
+
+    /// Pace requests with `profile` instead of the gentle default
+    pub fn with_profile(mut self, profile: RateProfile) -> Result<Self> {
+        self.throttle = Arc::new(HostThrottle::new(profile)?);
+        Ok(self)
+    }
+
+    /// Send every request through `proxy`, e.g. a residential proxy
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Render blocked pages in a browser instead of giving up
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
+
+    fn headers(&self, query: &SerpQuery) -> HashMap<String, String> {
+        let language = query.language.as_deref().unwrap_or("en");
+        HashMap::from([
+            (
+                "Accept".to_string(),
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+            ),
+            (
+                "Accept-Language".to_string(),
+                format!("{},en;q=0.8", language),
+            ),
+            ("Cookie".to_string(), CONSENT_COOKIE.to_string()),
+        ])
+    }
+
+    /// Fetch and parse one result page
+    pub async fn fetch_page(&self, query: &SerpQuery, page: u32) -> Result<SerpPage> {
+        let url = query.page_url(&self.search_url, page);
+        let host = url::Url::parse(&url)?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        let _permit = self.throttle.acquire(&host).await;
+
+        let user_agent = USER_AGENTS
+            .choose(&mut rand::thread_rng())
+            .expect("user agent pool is not empty");
+        let mut tracer = FetchTracer::new()
+            .with_timeout(Duration::from_secs(self.config.timeout_secs))?
+            .with_user_agent(user_agent)?;
+        if let Some(proxy) = &self.proxy {
+            tracer = tracer.with_proxy(proxy)?;
+        }
+        let fetched = tracer.fetch(&url, &self.headers(query)).await;
+        let body = fetched.body?;
+        let html = String::from_utf8_lossy(&body);
+
+        let block = detect_block(
+            fetched.trace.final_url.as_deref(),
+            fetched.trace.status,
+            &html,
+        );
+        let html = match (block, &self.browser) {
+            (None, _) => html.into_owned(),
+            (Some(_), Some(pool)) => pool.get_browser().await?.scrape_page(&url).await?.html,
+            (Some(block), None) => return Err(block.into()),
+        };
+        if let Some(block) = detect_block(None, None, &html) {
+            return Err(block.into());
+        }
+        Ok(parse_serp(&html, &query.query, page))
+    }
+
+    /// Fetch up to `pages` result pages, stopping early at the last page or a block
+    pub async fn search(&self, query: &SerpQuery, pages: u32) -> Result<SerpResults> {
+        let mut results = SerpResults::default();
+        for page in 0..pages.max(1) {
+            match self.fetch_page(query, page).await {
+                Ok(parsed) => {
+                    let has_next = parsed.has_next && !parsed.organic.is_empty();
+                    results.pages.push(parsed);
+                    if !has_next {
+                        break;
+                    }
+                }
+                Err(e) => match e.downcast_ref::<SerpBlock>() {
+                    Some(block) if !results.pages.is_empty() => {
+                        results.blocked = Some(*block);
+                        break;
+                    }
+                    _ => return Err(e),
+                },
+            }
+        }
+        Ok(results)
+    }
+}
+
+impl PlatformScraper for SerpScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            let (query, page) = SerpQuery::from_url(&url).context("Not a Google search URL")?;
+            let parsed = self.fetch_page(&query, page).await?;
+            let text = parsed
+                .organic
+                .iter()
+                .map(|result| match &result.snippet {
+                    Some(snippet) => format!("{}\n{}\n{}", result.title, result.url, snippet),
+                    None => format!("{}\n{}", result.title, result.url),
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let metadata = Metadata::from([
+                ("serp:query".into(), parsed.query.clone()),
+                ("serp:page".into(), parsed.page.to_string()),
+                (
+                    "serp:organic".into(),
+                    serde_json::to_string(&parsed.organic)?,
+                ),
+                ("serp:ads".into(), serde_json::to_string(&parsed.ads)?),
+                (
+                    "serp:people_also_ask".into(),
+                    serde_json::to_string(&parsed.people_also_ask)?,
+                ),
+            ]);
+            Ok(ExtractedContent {
+                url,
+                title: Some(format!("{} - Google Search", parsed.query).into()),
+                text: Some(text.into()),
+                metadata,
+                tags: vec!["serp".to_string()],
+                extracted_at: chrono::Utc::now(),
+            })
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        SEARCH_PAGES.is_match(url)
+            && url::Url::parse(url).is_ok_and(|url| url.query_pairs().any(|(key, _)| key == "q"))
+    }
+
+    fn platform_name(&self) -> &'static str {
+        "serp"
+    }
+
+    fn capabilities(&self, url: &str) -> ScraperCapabilities {
+        ScraperCapabilities {
+            browser: Need::Optional,
+            proxy_tier: Some(ProxyType::Residential),
+            proxy_country: SerpQuery::from_url(url).and_then(|(query, _)| query.country),
+            ..ScraperCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const PAGE: &str = r#"<html><body>
+        <div id="tads"><div>
+          <a href="https://www.googleadservices.com/pagead/aclk?adurl=https://ads.example/shoes" data-rw="x">
+            <div role="heading" aria-level="3"><span>Cheap Running Shoes</span></div></a>
+          <div class="MUxGbd yDYNvb">Free shipping on all orders</div>
+        </div></div>
+        <div id="rso">
+          <div class="g"><a href="https://runners.example/guide" data-ved="1"><br><h3 class="LC20lb">Running Shoe Guide &amp; Reviews</h3></a>
+            <div class="VwiC3b yXK7lf">How to pick <em>running shoes</em> for your gait.</div></div>
+          <div class="g"><a href="/url?q=https://shop.example/shoes&amp;sa=U"><h3>Shoe Shop</h3></a>
+            <span class="VwiC3b">All the shoes.</span></div>
+          <div class="related-question-pairs">
+            <div jsname="yEVEwb" data-q="How often should you replace running shoes?"></div>
+            <div jsname="yEVEwb" data-q="Are expensive running shoes worth it?"></div>
+          </div>
+          <a href="https://maps.google.com/x"><h3>Maps</h3></a>
+        </div>
+        <a id="pnnext" href="/search?q=running+shoes&amp;start=10">Next</a>
+    </body></html>"#;
+
+    #[test]
+    fn test_parse_serp() {
+        let page = parse_serp(PAGE, "running shoes", 0);
+        assert_eq!(page.ads.len(), 1);
+        assert_eq!(page.ads[0].title, "Cheap Running Shoes");
+        assert_eq!(
+            page.ads[0].snippet.as_deref(),
+            Some("Free shipping on all orders")
+        );
+
+        assert_eq!(page.organic.len(), 2);
+        assert_eq!(page.organic[0].title, "Running Shoe Guide & Reviews");
+        assert_eq!(
+            page.organic[0].snippet.as_deref(),
+            Some("How to pick running shoes for your gait.")
+        );
+        assert_eq!(page.organic[1].url, "https://shop.example/shoes");
+        assert_eq!(page.organic[1].position, 2);
+        assert_eq!(page.people_also_ask.len(), 2);
+        assert!(page.has_next);
+
+        assert_eq!(parse_serp(PAGE, "running shoes", 2).organic[0].position, 21);
+    }
+
+    #[test]
+    fn test_query_urls_and_blocks() {
+        let query = SerpQuery::new("rust lang").with_language("de");
+        let url = query.page_url(SEARCH_URL, 2);
+        assert_eq!(
+            url,
+            "https://www.google.com/search?q=rust+lang&start=20&hl=de"
+        );
+        assert_eq!(SerpQuery::from_url(&url), Some((query, 2)));
+
+        let scraper = SerpScraper::new(ScraperConfig::default());
+        assert!(scraper.can_handle("https://www.google.de/search?q=rust"));
+        assert!(!scraper.can_handle("https://www.google.com/maps?q=rust"));
+
+        let sorry = "https://www.google.com/sorry/index?continue=x";
+        assert_eq!(
+            detect_block(Some(sorry), Some(200), ""),
+            Some(SerpBlock::Captcha)
+        );
+        assert_eq!(
+            detect_block(Some("https://consent.google.com/ml"), Some(200), ""),
+            Some(SerpBlock::Consent)
+        );
+        assert_eq!(detect_block(None, Some(200), PAGE), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_until_blocked() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("start", "10"))
+            .respond_with(ResponseTemplate::new(429).set_body_string("unusual traffic"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PAGE))
+            .mount(&server)
+            .await;
+
+        let scraper = SerpScraper::new(ScraperConfig::default())
+            .with_search_url(&format!("{}/search", server.uri()))
+            .with_profile(RateProfile::aggressive())
+            .unwrap();
+        let results = scraper
+            .search(&SerpQuery::new("running shoes"), 5)
+            .await
+            .unwrap();
+        assert_eq!(results.pages.len(), 1);
+        assert_eq!(results.blocked, Some(SerpBlock::Captcha));
+        assert_eq!(results.organic().count(), 2);
+    }
+}
@@ -0,0 +1,736 @@
+//! MediaWiki scraper
+//!
+//! [`WikiScraper`] reads pages through the MediaWiki action API instead of
+//! scraping rendered HTML: the parsed page HTML and its wikitext, categories,
+//! the current revision (ID, timestamp, author, comment) and the page's
+//! infobox, parsed from the wikitext into a key-value map. It works with any
+//! MediaWiki site, not just Wikipedia: the API endpoint is found from the
+//! page's `EditURI` link (or the usual `/w/api.php` and `/api.php` paths),
+//! confirmed with a `siteinfo` query and remembered per site. Pages on sites
+//! that turn out not to run MediaWiki are extracted like any other page.
+
+use crate::{
+    api::{ApiClient, ApiMethod, ApiRequest},
+    extractors::{extract_text_secure, html_tags},
+    metadata::Metadata,
+    ExtractedContent, PlatformScraper, ScraperConfig,
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::Duration,
+};
+use swoop_core::{security::UrlValidator, text::SharedStr};
+
+const PLATFORM: &str = "wiki";
+/// Wiki farms whose API location is known without fetching the page
+const WIKIMEDIA_HOSTS: &[&str] = &[
+    "wikipedia.org",
+    "wikimedia.org",
+    "wiktionary.org",
+    "wikibooks.org",
+    "wikiquote.org",
+    "wikisource.org",
+    "wikivoyage.org",
+    "wikinews.org",
+    "wikiversity.org",
+    "mediawiki.org",
+];
+const FANDOM_HOST: &str = "fandom.com";
+
+/// A MediaWiki site, as reported by its `siteinfo`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WikiSite {
+    pub api_url: String,
+    pub name: String,
+    /// Article URL pattern, e.g. `/wiki/$1`
+    pub article_path: String,
+    pub language: Option<String>,
+    /// MediaWiki version string, e.g. `MediaWiki 1.42.0`
+    pub generator: Option<String>,
+}
+
+impl WikiSite {
+    /// Page title addressed by `url`: from the article path or a `title` parameter
+    pub fn title_from_url(&self, url: &str) -> Option<String> {
+        let parsed = url::Url::parse(url).ok()?;
+        if let Some((_, title)) = parsed.query_pairs().find(|(key, _)| key == "title") {
+            return Some(title.replace('_', " "));
+        }
+        let (prefix, suffix) = self.article_path.split_once("$1")?;
+        let title = parsed.path().strip_prefix(prefix)?;
+        let title = title.strip_suffix(suffix).unwrap_or(title);
+        let title = percent_decode(title).replace('_', " ");
+        (!title.is_empty()).then_some(title)
+    }
+}
+
+/// The revision a page was read at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WikiRevision {
+    pub id: u64,
+    pub parent_id: Option<u64>,
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub user: Option<String>,
+    pub comment: Option<String>,
+    /// Page size in bytes
+    pub size: Option<u64>,
+}
+
+/// A page read through the MediaWiki API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WikiPage {
+    pub url: String,
+    pub site: WikiSite,
+    pub title: String,
+    pub page_id: Option<u64>,
+    pub revision: Option<WikiRevision>,
+    /// Visible categories, without the `Category:` prefix
+    pub categories: Vec<String>,
+    /// Parameters of the page's first infobox, with markup removed
+    pub infobox: BTreeMap<String, String>,
+    pub wikitext: String,
+    /// Rendered page body
+    pub html: String,
+}
+
+impl WikiPage {
+    pub fn to_content(&self) -> Result<ExtractedContent> {
+        let mut metadata = Metadata::from([
+            ("wiki:site".into(), self.site.name.clone()),
+            ("wiki:api_url".into(), self.site.api_url.clone()),
+            ("wiki:wikitext".into(), self.wikitext.clone()),
+            ("wiki:html".into(), self.html.clone()),
+            ("wiki:categories".into(), self.categories.join("\n")),
+            ("wiki:infobox".into(), serde_json::to_string(&self.infobox)?),
+        ]);
+        if let Some(page_id) = self.page_id {
+            metadata.insert("wiki:page_id".into(), page_id.to_string());
+        }
+        if let Some(language) = &self.site.language {
+            metadata.insert("language".into(), language.clone());
+        }
+        if let Some(revision) = &self.revision {
+            metadata.insert("wiki:revision_id".into(), revision.id.to_string());
+            if let Some(user) = &revision.user {
+                metadata.insert("wiki:revision_user".into(), user.clone());
+            }
+            if let Some(comment) = &revision.comment {
+                metadata.insert("wiki:revision_comment".into(), comment.clone());
+            }
+            if let Some(timestamp) = revision.timestamp {
+                metadata.insert("article:modified_time".into(), timestamp.to_rfc3339());
+            }
+        }
+
+        Ok(ExtractedContent {
+            url: self.url.clone(),
+            title: Some(self.title.as_str().into()),
+            text: extract_text_secure(&self.html).ok().map(SharedStr::from),
+            metadata,
+            tags: vec![PLATFORM.to_string()],
+            extracted_at: chrono::Utc::now(),
+        })
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The `{{ ... }}` template starting at `start`, as a slice including its braces
+fn template_at(wikitext: &str, start: usize) -> Option<&str> {
+    let bytes = wikitext.as_bytes();
+    let mut depth = 0usize;
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"{{" => {
+                depth += 1;
+                i += 2;
+            }
+            b"}}" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(&wikitext[start..i]);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Split a template's body on `|` outside nested templates and links
+fn split_params(body: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut braces, mut brackets, mut last) = (0i32, 0i32, 0usize);
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let pair = bytes.get(i..i + 2).unwrap_or_default();
+        match pair {
+            b"{{" => braces += 1,
+            b"}}" => braces -= 1,
+            b"[[" => brackets += 1,
+            b"]]" => brackets -= 1,
+            _ if bytes[i] == b'|' && braces == 0 && brackets == 0 => {
+                params.push(&body[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+        i += if matches!(pair, b"{{" | b"}}" | b"[[" | b"]]") {
+            2
+        } else {
+            1
+        };
+    }
+    params.push(&body[last..]);
+    params
+}
+
+/// Plain text of a wikitext value: links become their label, nested templates
+/// their positional parameters, list items a comma-separated list
+pub fn strip_wikitext(value: &str) -> String {
+    let mut text = value.to_string();
+    for pattern in [
+        r"(?s)<!--.*?-->",
+        r"(?is)<ref[^>]*/>",
+        r"(?is)<ref[^>]*>.*?</ref>",
+    ] {
+        text = regex::Regex::new(pattern)
+            .expect("pattern is valid")
+            .replace_all(&text, "")
+            .into_owned();
+    }
+
+    // Nested templates, innermost first
+    while let Some(start) = text.find("{{") {
+        let Some(template) = template_at(&text, start) else {
+            break;
+        };
+        let params = split_params(&template[2..template.len() - 2]);
+        let positional: Vec<String> = params
+            .iter()
+            .skip(1)
+            .filter(|param| !param.contains('='))
+            .map(|param| strip_wikitext(param))
+            .filter(|param| !param.is_empty())
+            .collect();
+        let replacement = positional.join(" ");
+        text.replace_range(start..start + template.len(), &replacement);
+    }
+
+    // [[target|label]] and [[target]] links, [url label] external links
+    let link = regex::Regex::new(r"\[\[(?:[^\]|]*\|)?([^\]]*)\]\]").expect("pattern is valid");
+    text = link.replace_all(&text, "$1").into_owned();
+    let external =
+        regex::Regex::new(r"\[https?://[^\s\]]+\s*([^\]]*)\]").expect("pattern is valid");
+    text = external.replace_all(&text, "$1").into_owned();
+    let tags = regex::Regex::new(r"(?i)<br\s*/?>").expect("pattern is valid");
+    text = tags.replace_all(&text, "\n").into_owned();
+    let html = regex::Regex::new(r"(?s)<[^>]+>").expect("pattern is valid");
+    text = html.replace_all(&text, "").into_owned();
+    text = text
+        .replace("'''", "")
+        .replace("''", "")
+        .replace("&nbsp;", " ");
+
+    text.lines()
+        .map(|line| line.trim().trim_start_matches(['*', '#']).trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parameters of the first `{{Infobox ...}}` template in `wikitext`
+pub fn parse_infobox(wikitext: &str) -> BTreeMap<String, String> {
+    let lower = wikitext.to_ascii_lowercase();
+    let Some(template) = lower
+        .match_indices("{{")
+        .map(|(start, _)| start)
+        .find(|&start| lower[start + 2..].trim_start().starts_with("infobox"))
+        .and_then(|start| template_at(wikitext, start))
+    else {
+        return BTreeMap::new();
+    };
+    split_params(&template[2..template.len() - 2])
+        .into_iter()
+        .skip(1)
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            let key = key.trim().to_string();
+            let value = strip_wikitext(value);
+            (!key.is_empty() && !value.is_empty()).then_some((key, value))
+        })
+        .collect()
+}
+
+/// `EditURI` API link in a page's `<head>`, without its query
+fn edit_uri(html: &str, page_url: &str) -> Option<String> {
+    let href = html_tags(html).find_map(|tag| {
+        (tag.name == "link"
+            && tag
+                .attributes
+                .get("rel")
+                .is_some_and(|rel| rel.eq_ignore_ascii_case("EditURI")))
+        .then(|| tag.attributes.get("href").cloned())
+        .flatten()
+    })?;
+    let mut url = url::Url::parse(page_url).ok()?.join(&href).ok()?;
+    url.set_query(None);
+    Some(url.to_string())
+}
+
+fn origin(url: &url::Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+fn api_error(response: &Value) -> Result<()> {
+    if let Some(error) = response.get("error") {
+        bail!(
+            "MediaWiki API error {}: {}",
+            error
+                .get("code")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown"),
+            error
+                .get("info")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// Scraper for MediaWiki sites such as Wikipedia
+pub struct WikiScraper {
+    config: ScraperConfig,
+    validator: UrlValidator,
+    extra_hosts: Vec<String>,
+    /// Sites by origin; `None` for origins that don't run MediaWiki
+    sites: Mutex<HashMap<String, Option<WikiSite>>>,
+}
+
+impl WikiScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            validator: UrlValidator::default(),
+            extra_hosts: Vec::new(),
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Handle URLs on `host` too, e.g. a company wiki without `/wiki/` paths
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.extra_hosts.push(host.to_ascii_lowercase());
+        self
+    }
+
+    fn client(&self) -> ApiClient {
+        ApiClient::new(Duration::from_secs(self.config.timeout_secs))
+            .with_validator(self.validator.clone())
+    }
+
+    async fn api(&self, api_url: &str, params: Value) -> Result<Value> {
+        let mut params = params;
+        params["format"] = json!("json");
+        params["formatversion"] = json!("2");
+        let request = ApiRequest::template(api_url, params)
+            .with_method(ApiMethod::Get)
+            .with_header("User-Agent", &self.config.user_agent);
+        let response = self
+            .client()
+            .fetch_pages(PLATFORM, &request)
+            .await?
+            .remove(0);
+        api_error(&response)?;
+        Ok(response)
+    }
+
+    /// Site info from `api_url`, if it is a MediaWiki API
+    async fn probe(&self, api_url: &str) -> Option<WikiSite> {
+        let response = self
+            .api(
+                api_url,
+                json!({ "action": "query", "meta": "siteinfo", "siprop": "general" }),
+            )
+            .await
+            .ok()?;
+        let general = response.pointer("/query/general")?;
+        let field = |name: &str| {
+            general
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        Some(WikiSite {
+            api_url: api_url.to_string(),
+            name: field("sitename")?,
+            article_path: field("articlepath").unwrap_or_else(|| "/wiki/$1".to_string()),
+            language: field("lang"),
+            generator: field("generator"),
+        })
+    }
+
+    async fn fetch_html(&self, url: &str) -> Result<String> {
+        let headers = HashMap::from([("User-Agent".to_string(), self.config.user_agent.clone())]);
+        let body = swoop_core::send_request_with(
+            &self.validator,
+            reqwest::Method::GET,
+            url,
+            &headers,
+            None,
+            Duration::from_secs(self.config.timeout_secs),
+        )
+        .await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Find the MediaWiki site serving `url`; `Err` carries the fetched HTML
+    /// when the site turns out not to be a wiki
+    async fn discover(&self, url: &str) -> Result<std::result::Result<WikiSite, String>> {
+        let parsed = url::Url::parse(url)?;
+        let origin = origin(&parsed);
+        let cached = self.sites.lock().unwrap().get(&origin).cloned();
+        if let Some(site) = cached {
+            return Ok(match site {
+                Some(site) => Ok(site),
+                None => Err(self.fetch_html(url).await?),
+            });
+        }
+
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        let known = if WIKIMEDIA_HOSTS.iter().any(|farm| host.ends_with(farm)) {
+            Some(format!("{}/w/api.php", origin))
+        } else if host.ends_with(FANDOM_HOST) {
+            Some(format!("{}/api.php", origin))
+        } else {
+            None
+        };
+        let mut html = None;
+        let mut candidates = Vec::new();
+        match known {
+            Some(api_url) => candidates.push(api_url),
+            None => {
+                let page = self.fetch_html(url).await?;
+                candidates.extend(edit_uri(&page, url));
+                html = Some(page);
+                for path in ["/w/api.php", "/api.php"] {
+                    let candidate = format!("{}{}", origin, path);
+                    if !candidates.contains(&candidate) {
+                        candidates.push(candidate);
+                    }
+                }
+            }
+        }
+
+        for candidate in candidates {
+            if let Some(site) = self.probe(&candidate).await {
+                self.sites
+                    .lock()
+                    .unwrap()
+                    .insert(origin, Some(site.clone()));
+                return Ok(Ok(site));
+            }
+        }
+        self.sites.lock().unwrap().insert(origin, None);
+        match html {
+            Some(html) => Ok(Err(html)),
+            None => bail!("No MediaWiki API found for {}", url),
+        }
+    }
+
+    /// Read `title` from `site`
+    pub async fn page(&self, site: &WikiSite, title: &str, url: &str) -> Result<WikiPage> {
+        let response = self
+            .api(
+                &site.api_url,
+                json!({
+                    "action": "parse",
+                    "page": title,
+                    "prop": "text|wikitext|categories|displaytitle",
+                    "redirects": "1",
+                    "disableeditsection": "1",
+                    "disabletoc": "1",
+                }),
+            )
+            .await?;
+        let parsed = response
+            .get("parse")
+            .with_context(|| format!("MediaWiki page {} not found", title))?;
+        let text = |name: &str| {
+            parsed
+                .get(name)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let categories = parsed
+            .get("categories")
+            .and_then(Value::as_array)
+            .map(|categories| {
+                categories
+                    .iter()
+                    .filter(|category| category.get("hidden").is_none())
+                    .filter_map(|category| category.get("category").and_then(Value::as_str))
+                    .map(|category| category.replace('_', " "))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let wikitext = text("wikitext");
+
+        let revision = match parsed.get("revid").and_then(Value::as_u64) {
+            Some(revid) => self.revision(site, revid).await?,
+            None => None,
+        };
+        Ok(WikiPage {
+            url: url.to_string(),
+            site: site.clone(),
+            title: parsed
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or(title)
+                .to_string(),
+            page_id: parsed.get("pageid").and_then(Value::as_u64),
+            revision,
+            categories,
+            infobox: parse_infobox(&wikitext),
+            wikitext,
+            html: text("text"),
+        })
+    }
+
+    async fn revision(&self, site: &WikiSite, revid: u64) -> Result<Option<WikiRevision>> {
+        let response = self
+            .api(
+                &site.api_url,
+                json!({
+                    "action": "query",
+                    "prop": "revisions",
+                    "revids": revid.to_string(),
+                    "rvprop": "ids|timestamp|user|comment|size",
+                }),
+            )
+            .await?;
+        let Some(revision) = response.pointer("/query/pages/0/revisions/0") else {
+            return Ok(None);
+        };
+        let field = |name: &str| {
+            revision
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        Ok(Some(WikiRevision {
+            id: revision
+                .get("revid")
+                .and_then(Value::as_u64)
+                .unwrap_or(revid),
+            parent_id: revision.get("parentid").and_then(Value::as_u64),
+            timestamp: field("timestamp")
+                .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(&timestamp).ok())
+                .map(|timestamp| timestamp.with_timezone(&chrono::Utc)),
+            user: field("user"),
+            comment: field("comment").filter(|comment| !comment.is_empty()),
+            size: revision.get("size").and_then(Value::as_u64),
+        }))
+    }
+}
+
+impl PlatformScraper for WikiScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            let site = match self.discover(&url).await? {
+                Ok(site) => site,
+                Err(html) => return Ok(super::content_from_html(url, &html.into())),
+            };
+            let title = site
+                .title_from_url(&url)
+                .with_context(|| format!("No page title in {}", url))?;
+            self.page(&site, &title, &url).await?.to_content()
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        let Ok(parsed) = url::Url::parse(url) else {
+            return false;
+        };
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        let known_host = WIKIMEDIA_HOSTS
+            .iter()
+            .chain(std::iter::once(&FANDOM_HOST))
+            .any(|farm| host == *farm || host.ends_with(&format!(".{}", farm)))
+            || self.extra_hosts.contains(&host);
+        let wiki_path = parsed.path().starts_with("/wiki/")
+            || (parsed.path().ends_with("/index.php")
+                && parsed.query_pairs().any(|(key, _)| key == "title"));
+        known_host || wiki_path
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const WIKITEXT: &str = r#"{{Short description|English mathematician}}
+{{Infobox person
+| name        = Ada Lovelace
+| image       = Ada Lovelace portrait.jpg
+| birth_date  = {{birth date|1815|12|10|df=y}}
+| birth_place = [[London]], England
+| known_for   = {{plainlist|
+* [[Analytical Engine|Mathematics of the Analytical Engine]]
+* First computer program}}
+| spouse      = William King<ref>Some source</ref> <!-- m. 1835 -->
+}}
+'''Augusta Ada King, Countess of Lovelace''' was an English [[mathematician]]."#;
+
+    #[test]
+    fn test_parse_infobox() {
+        let infobox = parse_infobox(WIKITEXT);
+        assert_eq!(infobox["name"], "Ada Lovelace");
+        assert_eq!(infobox["birth_date"], "1815 12 10");
+        assert_eq!(infobox["birth_place"], "London, England");
+        assert_eq!(
+            infobox["known_for"],
+            "Mathematics of the Analytical Engine, First computer program"
+        );
+        assert_eq!(infobox["spouse"], "William King");
+
+        let site = WikiSite {
+            api_url: "https://wiki.example/w/api.php".to_string(),
+            name: "Example".to_string(),
+            article_path: "/wiki/$1".to_string(),
+            language: None,
+            generator: None,
+        };
+        assert_eq!(
+            site.title_from_url("https://wiki.example/wiki/C%2B%2B_(language)")
+                .as_deref(),
+            Some("C++ (language)")
+        );
+        assert_eq!(
+            site.title_from_url("https://wiki.example/w/index.php?title=Ada_Lovelace&oldid=1")
+                .as_deref(),
+            Some("Ada Lovelace")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discovers_api_and_reads_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/wiki/Ada_Lovelace"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<html><head><link rel="EditURI" type="application/rsd+xml" href="/mw/api.php?action=rsd"></head></html>"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/mw/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "query": { "general": {
+                "sitename": "History Wiki", "articlepath": "/wiki/$1", "lang": "en",
+                "generator": "MediaWiki 1.41.1"
+            } } })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/mw/api.php"))
+            .and(query_param("action", "parse"))
+            .and(query_param("page", "Ada Lovelace"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "parse": {
+                "title": "Ada Lovelace", "pageid": 42, "revid": 1001,
+                "text": "<div class=\"mw-parser-output\"><p>Augusta Ada King was an English mathematician.</p></div>",
+                "wikitext": WIKITEXT,
+                "categories": [
+                    { "sortkey": "", "category": "English_mathematicians" },
+                    { "sortkey": "", "category": "Articles_with_hCards", "hidden": true }
+                ]
+            } })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/mw/api.php"))
+            .and(query_param("revids", "1001"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "query": { "pages": [{
+                "pageid": 42, "revisions": [{
+                    "revid": 1001, "parentid": 1000, "user": "Historian",
+                    "timestamp": "2024-05-01T12:00:00Z", "comment": "copyedit", "size": 5120
+                }]
+            }] } })),
+            )
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let scraper = WikiScraper::new(ScraperConfig::default())
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()));
+        let url = format!("{}/wiki/Ada_Lovelace", server.uri());
+        assert!(scraper.can_handle(&url));
+
+        let content = scraper.extract(&url).await.unwrap();
+        assert_eq!(content.title.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(content.metadata["wiki:site"], "History Wiki");
+        assert_eq!(
+            content.metadata["wiki:categories"],
+            "English mathematicians"
+        );
+        assert_eq!(content.metadata["wiki:revision_user"], "Historian");
+        assert!(content.metadata["article:modified_time"].starts_with("2024-05-01"));
+        let infobox: BTreeMap<String, String> =
+            serde_json::from_str(&content.metadata["wiki:infobox"]).unwrap();
+        assert_eq!(infobox["birth_place"], "London, England");
+        assert!(content
+            .text
+            .unwrap()
+            .contains("Augusta Ada King was an English mathematician."));
+    }
+}
@@ -0,0 +1,894 @@
+//! X (Twitter) scraper
+//!
+//! Fetches tweets, threads and profile timelines through the GraphQL endpoints
+//! the x.com web client uses, producing [`Post`]-shaped content (text, author,
+//! engagement metrics, media URLs). Anonymous requests carry a guest token that
+//! is activated on demand and kept in the scraper's [`AuthStore`]; a
+//! rate-limited token is dropped and replaced. Once
+//! [`XScraper::with_max_guest_tokens`] tokens have been used up within X's
+//! 15-minute rate-limit window, pages are rendered in the browser instead, if
+//! one is configured. Logged-in sessions work by putting `cookie` and
+//! `x-csrf-token` headers for the `x` platform into the [`AuthStore`].
+
+use crate::{
+    api::{ApiClient, ApiMethod, ApiRequest, AuthStore},
+    browser::BrowserPool,
+    metadata::Metadata,
+    router::UrlRouter,
+    schemas::{Post, POST_MEDIA_KEY, POST_METRIC_PREFIX},
+    ExtractedContent, Need, PlatformScraper, ScraperCapabilities, ScraperConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use swoop_core::security::UrlValidator;
+
+const PLATFORM: &str = "x";
+
+/// Hosts the scraper handles
+static HOSTS: Lazy<UrlRouter<()>> = Lazy::new(|| {
+    UrlRouter::from_patterns(
+        &[
+            "x.com",
+            "www.x.com",
+            "mobile.x.com",
+            "twitter.com",
+            "www.twitter.com",
+            "mobile.twitter.com",
+        ],
+        (),
+    )
+    .unwrap()
+});
+
+const GUEST_TOKEN_HEADER: &str = "x-guest-token";
+/// Bearer token embedded in the public x.com web client
+const WEB_BEARER: &str = "AAAAAAAAAAAAAAAAAAAAANRILgAAAAAAnNwIzUejRCOuH5E6I8xnZz4puTs%3D1Zv7ttfk8LF81IUq16cHjhLTvJu4FA33AGWWjCpTnA";
+/// X resets its rate limits every 15 minutes
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Path segments that are x.com pages rather than profiles
+const RESERVED_PATHS: &[&str] = &[
+    "home",
+    "explore",
+    "search",
+    "i",
+    "settings",
+    "notifications",
+    "messages",
+    "hashtag",
+    "login",
+    "signup",
+    "tos",
+    "privacy",
+];
+
+/// GraphQL operations used by the scraper
+///
+/// Operation paths are `<query id>/<name>`; query IDs change with web client
+/// deployments, so they can be overridden.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XEndpoints {
+    pub graphql_url: String,
+    pub activate_url: String,
+    pub tweet_detail: String,
+    pub user_by_screen_name: String,
+    pub user_tweets: String,
+}
+
+impl Default for XEndpoints {
+    fn default() -> Self {
+        Self {
+            graphql_url: "https://x.com/i/api/graphql".to_string(),
+            activate_url: "https://api.x.com/1.1/guest/activate.json".to_string(),
+            tweet_detail: "nBS-WpgA6ZG0CyNHD517JQ/TweetDetail".to_string(),
+            user_by_screen_name: "G3KGOASz96M-Qu0nwmGXNg/UserByScreenName".to_string(),
+            user_tweets: "E3opETHurmVJflFsUBVuUQ/UserTweets".to_string(),
+        }
+    }
+}
+
+impl XEndpoints {
+    /// Default operations served from `base`, e.g. a mock server
+    pub fn at(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            graphql_url: format!("{}/i/api/graphql", base),
+            activate_url: format!("{}/1.1/guest/activate.json", base),
+            ..Self::default()
+        }
+    }
+}
+
+/// Every guest token allowed in the current rate-limit window was rate limited
+#[derive(Debug)]
+pub struct GuestTokensExhausted;
+
+impl fmt::Display for GuestTokensExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "X guest tokens exhausted for this rate-limit window")
+    }
+}
+
+impl std::error::Error for GuestTokensExhausted {}
+
+#[derive(Debug, Default)]
+struct GuestBudget {
+    window_start: Option<Instant>,
+    activations: u32,
+}
+
+/// A page on x.com the scraper knows how to fetch
+#[derive(Debug, Clone, PartialEq)]
+enum XTarget {
+    Tweet { id: String },
+    Profile { screen_name: String },
+}
+
+fn parse_target(url: &str) -> Option<XTarget> {
+    let url = url::Url::parse(url).ok()?;
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        [_, "status", id, ..] if id.chars().all(|c| c.is_ascii_digit()) => {
+            Some(XTarget::Tweet { id: id.to_string() })
+        }
+        [name] if !RESERVED_PATHS.contains(&name.to_ascii_lowercase().as_str()) => {
+            Some(XTarget::Profile {
+                screen_name: name.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// One tweet parsed from a GraphQL response
+#[derive(Debug, Clone, PartialEq)]
+struct Tweet {
+    id: String,
+    conversation_id: Option<String>,
+    screen_name: Option<String>,
+    author_name: Option<String>,
+    text: String,
+    created_at: Option<DateTime<Utc>>,
+    metrics: BTreeMap<String, u64>,
+    media: Vec<String>,
+}
+
+impl Tweet {
+    fn url(&self) -> String {
+        format!(
+            "https://x.com/{}/status/{}",
+            self.screen_name.as_deref().unwrap_or("i"),
+            self.id
+        )
+    }
+
+    fn to_post(&self) -> Post {
+        Post {
+            url: self.url(),
+            author: self.screen_name.as_ref().map(|name| format!("@{}", name)),
+            text: self.text.clone(),
+            published_at: self.created_at,
+            image: self.media.first().cloned(),
+            metrics: self.metrics.clone(),
+            media: self.media.clone(),
+        }
+    }
+}
+
+/// Unwrap a `tweet_results.result`, skipping tombstones and unavailable tweets
+fn tweet_object(result: &Value) -> Option<&Value> {
+    match result.get("__typename").and_then(Value::as_str) {
+        Some("TweetWithVisibilityResults") => result.get("tweet"),
+        Some("Tweet") | None => result.get("legacy").map(|_| result),
+        _ => None,
+    }
+}
+
+fn parse_tweet(result: &Value) -> Option<Tweet> {
+    let tweet = tweet_object(result)?;
+    let legacy = tweet.get("legacy")?;
+    let id = tweet
+        .get("rest_id")
+        .or_else(|| legacy.get("id_str"))?
+        .as_str()?
+        .to_string();
+    let user = tweet.pointer("/core/user_results/result");
+    let user_field = |field: &str| {
+        user.and_then(|user| {
+            user.pointer(&format!("/legacy/{}", field))
+                .or_else(|| user.pointer(&format!("/core/{}", field)))
+        })
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    };
+    // Long tweets carry their full text in a note
+    let text = tweet
+        .pointer("/note_tweet/note_tweet_results/result/text")
+        .or_else(|| legacy.get("full_text"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut metrics = BTreeMap::new();
+    for (name, field) in [
+        ("likes", "favorite_count"),
+        ("reposts", "retweet_count"),
+        ("replies", "reply_count"),
+        ("quotes", "quote_count"),
+        ("bookmarks", "bookmark_count"),
+    ] {
+        if let Some(count) = legacy.get(field).and_then(Value::as_u64) {
+            metrics.insert(name.to_string(), count);
+        }
+    }
+    if let Some(views) = tweet
+        .pointer("/views/count")
+        .and_then(Value::as_str)
+        .and_then(|views| views.parse().ok())
+    {
+        metrics.insert("views".to_string(), views);
+    }
+
+    Some(Tweet {
+        id,
+        conversation_id: legacy
+            .get("conversation_id_str")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        screen_name: user_field("screen_name"),
+        author_name: user_field("name"),
+        text,
+        created_at: legacy
+            .get("created_at")
+            .and_then(Value::as_str)
+            .and_then(|raw| DateTime::parse_from_str(raw, "%a %b %d %H:%M:%S %z %Y").ok())
+            .map(|time| time.with_timezone(&Utc)),
+        metrics,
+        media: parse_media(legacy),
+    })
+}
+
+/// Photo URLs, and the highest-bitrate MP4 of videos and GIFs
+fn parse_media(legacy: &Value) -> Vec<String> {
+    let Some(media) = legacy
+        .pointer("/extended_entities/media")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+    media
+        .iter()
+        .filter_map(|item| {
+            let best_video = item
+                .pointer("/video_info/variants")
+                .and_then(Value::as_array)
+                .and_then(|variants| {
+                    variants
+                        .iter()
+                        .filter(|v| {
+                            v.get("content_type").and_then(Value::as_str) == Some("video/mp4")
+                        })
+                        .max_by_key(|v| v.get("bitrate").and_then(Value::as_u64).unwrap_or(0))
+                })
+                .and_then(|variant| variant.get("url"));
+            best_video
+                .or_else(|| item.get("media_url_https"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Every tweet in a response, in document order, without duplicates
+fn collect_tweets(response: &Value) -> Vec<Tweet> {
+    fn walk(value: &Value, tweets: &mut Vec<Tweet>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(tweet) = fields
+                    .get("tweet_results")
+                    .and_then(|results| results.get("result"))
+                    .and_then(parse_tweet)
+                {
+                    if !tweets.iter().any(|seen| seen.id == tweet.id) {
+                        tweets.push(tweet);
+                    }
+                }
+                fields.values().for_each(|value| walk(value, tweets));
+            }
+            Value::Array(items) => items.iter().for_each(|value| walk(value, tweets)),
+            _ => {}
+        }
+    }
+    let mut tweets = Vec::new();
+    walk(response, &mut tweets);
+    tweets
+}
+
+/// Cursor of the next (older) page of a timeline
+fn bottom_cursor(response: &Value) -> Option<String> {
+    match response {
+        Value::Object(fields) => {
+            let is_bottom = fields
+                .get("entryId")
+                .and_then(Value::as_str)
+                .is_some_and(|id| id.starts_with("cursor-bottom"));
+            if is_bottom {
+                let content = fields.get("content")?;
+                return content
+                    .get("value")
+                    .or_else(|| content.pointer("/itemContent/value"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+            }
+            fields.values().find_map(bottom_cursor)
+        }
+        Value::Array(items) => items.iter().find_map(bottom_cursor),
+        _ => None,
+    }
+}
+
+/// The focal tweet and the author's own replies in the same conversation
+fn self_thread<'a>(tweets: &'a [Tweet], focal: &'a Tweet) -> Vec<&'a Tweet> {
+    let mut thread: Vec<&Tweet> = tweets
+        .iter()
+        .filter(|tweet| {
+            tweet.id == focal.id
+                || (tweet.conversation_id.is_some()
+                    && tweet.conversation_id == focal.conversation_id
+                    && tweet.screen_name == focal.screen_name)
+        })
+        .collect();
+    // Tweet IDs are snowflakes, so ordering them orders by time
+    thread.sort_by(|a, b| a.id.len().cmp(&b.id.len()).then_with(|| a.id.cmp(&b.id)));
+    thread
+}
+
+fn tweet_content(url: &str, focal: &Tweet, thread: &[&Tweet]) -> ExtractedContent {
+    let mut metadata = Metadata::from([
+        ("x:tweet_id".into(), focal.id.clone()),
+        ("fetched_via".into(), "api".to_string()),
+    ]);
+    if let Some(name) = &focal.screen_name {
+        metadata.insert("author".into(), format!("@{}", name));
+    }
+    if let Some(conversation) = &focal.conversation_id {
+        metadata.insert("x:conversation_id".into(), conversation.clone());
+    }
+    if let Some(created_at) = focal.created_at {
+        metadata.insert("article:published_time".into(), created_at.to_rfc3339());
+    }
+    for (name, count) in &focal.metrics {
+        metadata.insert(
+            format!("{}{}", POST_METRIC_PREFIX, name).into(),
+            count.to_string(),
+        );
+    }
+    let media: Vec<&str> = thread
+        .iter()
+        .flat_map(|tweet| tweet.media.iter().map(String::as_str))
+        .collect();
+    if !media.is_empty() {
+        metadata.insert(POST_MEDIA_KEY.into(), media.join("\n"));
+    }
+    if thread.len() > 1 {
+        metadata.insert("x:thread_length".into(), thread.len().to_string());
+    }
+
+    let author = focal
+        .author_name
+        .clone()
+        .or_else(|| focal.screen_name.as_ref().map(|name| format!("@{}", name)));
+    ExtractedContent {
+        url: url.to_string(),
+        title: author.map(|author| format!("{} on X", author).into()),
+        text: Some(
+            thread
+                .iter()
+                .map(|tweet| tweet.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+                .into(),
+        ),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    }
+}
+
+fn profile_content(url: &str, user: &Value, tweets: &[Tweet]) -> Result<ExtractedContent> {
+    let legacy = user
+        .get("legacy")
+        .context("X profile response has no user")?;
+    let field = |name: &str| {
+        legacy
+            .get(name)
+            .or_else(|| user.pointer(&format!("/core/{}", name)))
+            .and_then(Value::as_str)
+    };
+    let screen_name = field("screen_name").unwrap_or_default();
+    let mut metadata = Metadata::from([
+        ("author".into(), format!("@{}", screen_name)),
+        ("fetched_via".into(), "api".to_string()),
+    ]);
+    for (key, count) in [
+        ("x:followers", "followers_count"),
+        ("x:following", "friends_count"),
+        ("x:tweet_count", "statuses_count"),
+    ] {
+        if let Some(count) = legacy.get(count).and_then(Value::as_u64) {
+            metadata.insert(key.into(), count.to_string());
+        }
+    }
+    if let Some(image) = legacy
+        .get("profile_image_url_https")
+        .or_else(|| user.pointer("/avatar/image_url"))
+        .and_then(Value::as_str)
+    {
+        metadata.insert("og:image".into(), image.to_string());
+    }
+    let posts: Vec<Post> = tweets.iter().map(Tweet::to_post).collect();
+    metadata.insert("x:posts".into(), serde_json::to_string(&posts)?);
+
+    let description = legacy
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let text: Vec<&str> = std::iter::once(description)
+        .chain(tweets.iter().map(|tweet| tweet.text.as_str()))
+        .filter(|text| !text.is_empty())
+        .collect();
+    Ok(ExtractedContent {
+        url: url.to_string(),
+        title: Some(match field("name") {
+            Some(name) => format!("{} (@{})", name, screen_name).into(),
+            None => format!("@{}", screen_name).into(),
+        }),
+        text: Some(text.join("\n\n").into()),
+        metadata,
+        tags: Vec::new(),
+        extracted_at: Utc::now(),
+    })
+}
+
+/// Whether `error` is X refusing the session: rate limited or a stale token
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| matches!(status.as_u16(), 401 | 403 | 429))
+}
+
+fn features() -> Value {
+    json!({
+        "responsive_web_graphql_exclude_directive_enabled": true,
+        "verified_phone_label_enabled": false,
+        "responsive_web_graphql_timeline_navigation_enabled": true,
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
+        "tweetypie_unmention_optimization_enabled": true,
+        "longform_notetweets_consumption_enabled": true,
+        "view_counts_everywhere_api_enabled": true,
+    })
+}
+
+/// Scraper for x.com and twitter.com tweets, threads and profiles
+pub struct XScraper {
+    config: ScraperConfig,
+    endpoints: XEndpoints,
+    validator: UrlValidator,
+    auth: Arc<AuthStore>,
+    browser: Option<Arc<BrowserPool>>,
+    max_guest_tokens: u32,
+    timeline_pages: usize,
+    guest: Mutex<GuestBudget>,
+}
+
+impl XScraper {
+    pub fn new(config: ScraperConfig) -> Self {
+        Self {
+            config,
+            endpoints: XEndpoints::default(),
+            validator: UrlValidator::default(),
+            auth: Arc::new(AuthStore::in_memory()),
+            browser: None,
+            max_guest_tokens: 3,
+            timeline_pages: 3,
+            guest: Mutex::new(GuestBudget::default()),
+        }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: XEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Keep guest tokens and session headers in `auth`, e.g. a store loaded from disk
+    pub fn with_auth(mut self, auth: Arc<AuthStore>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Render pages in a browser once guest tokens are exhausted
+    pub fn with_browser(mut self, pool: Arc<BrowserPool>) -> Self {
+        self.browser = Some(pool);
+        self
+    }
+
+    /// Guest tokens to activate per rate-limit window before giving up on the API
+    pub fn with_max_guest_tokens(mut self, max: u32) -> Self {
+        self.max_guest_tokens = max;
+        self
+    }
+
+    /// Timeline pages (about 20 tweets each) fetched for a profile
+    pub fn with_timeline_pages(mut self, pages: usize) -> Self {
+        self.timeline_pages = pages.max(1);
+        self
+    }
+
+    fn client(&self) -> ApiClient {
+        ApiClient::new(Duration::from_secs(self.config.timeout_secs))
+            .with_validator(self.validator.clone())
+            .with_auth(self.auth.clone())
+    }
+
+    fn logged_in(&self) -> bool {
+        self.auth
+            .headers(PLATFORM)
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("cookie"))
+    }
+
+    fn has_guest_token(&self) -> bool {
+        self.auth
+            .headers(PLATFORM)
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case(GUEST_TOKEN_HEADER))
+    }
+
+    /// Count an activation against the window's budget
+    fn take_activation(&self) -> bool {
+        let mut budget = self.guest.lock().unwrap();
+        if budget
+            .window_start
+            .is_none_or(|start| start.elapsed() >= RATE_LIMIT_WINDOW)
+        {
+            *budget = GuestBudget {
+                window_start: Some(Instant::now()),
+                activations: 0,
+            };
+        }
+        if budget.activations >= self.max_guest_tokens {
+            return false;
+        }
+        budget.activations += 1;
+        true
+    }
+
+    async fn activate_guest_token(&self, client: &ApiClient) -> Result<()> {
+        if !self.take_activation() {
+            return Err(GuestTokensExhausted.into());
+        }
+        let request = ApiRequest::template(&self.endpoints.activate_url, json!({}))
+            .with_header("Authorization", &format!("Bearer {}", WEB_BEARER));
+        let response = match client.fetch_pages(PLATFORM, &request).await {
+            Ok(mut pages) => pages.remove(0),
+            Err(e) if is_rate_limited(&e) => return Err(GuestTokensExhausted.into()),
+            Err(e) => return Err(e.context("Failed to activate an X guest token")),
+        };
+        let token = response
+            .get("guest_token")
+            .and_then(|token| match token {
+                Value::String(token) => Some(token.clone()),
+                Value::Number(token) => Some(token.to_string()),
+                _ => None,
+            })
+            .context("X guest token response has no token")?;
+        self.auth.set(PLATFORM, GUEST_TOKEN_HEADER, &token);
+        self.auth.save()
+    }
+
+    /// Run a GraphQL operation, rotating guest tokens when X rate limits them
+    async fn query(&self, operation: &str, variables: Value) -> Result<Value> {
+        let request = ApiRequest::template(
+            &format!("{}/{}", self.endpoints.graphql_url, operation),
+            json!({ "variables": variables, "features": features() }),
+        )
+        .with_method(ApiMethod::Get)
+        .with_header("Authorization", &format!("Bearer {}", WEB_BEARER));
+        let client = self.client();
+
+        loop {
+            let logged_in = self.logged_in();
+            if !logged_in && !self.has_guest_token() {
+                self.activate_guest_token(&client).await?;
+            }
+            match client.fetch_pages(PLATFORM, &request).await {
+                Ok(mut pages) => return Ok(pages.remove(0)),
+                Err(e) if is_rate_limited(&e) && !logged_in => {
+                    self.auth.remove(PLATFORM, GUEST_TOKEN_HEADER);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn extract_tweet(&self, url: &str, id: &str) -> Result<ExtractedContent> {
+        let response = self
+            .query(
+                &self.endpoints.tweet_detail,
+                json!({
+                    "focalTweetId": id,
+                    "with_rux_injections": false,
+                    "includePromotedContent": false,
+                    "withCommunity": true,
+                    "withVoice": true,
+                }),
+            )
+            .await?;
+        let tweets = collect_tweets(&response);
+        let focal = tweets
+            .iter()
+            .find(|tweet| tweet.id == id)
+            .with_context(|| format!("Tweet {} is unavailable", id))?;
+        Ok(tweet_content(url, focal, &self_thread(&tweets, focal)))
+    }
+
+    async fn extract_profile(&self, url: &str, screen_name: &str) -> Result<ExtractedContent> {
+        let response = self
+            .query(
+                &self.endpoints.user_by_screen_name,
+                json!({ "screen_name": screen_name, "withSafetyModeUserFields": true }),
+            )
+            .await?;
+        let user = response
+            .pointer("/data/user/result")
+            .with_context(|| format!("X user @{} not found", screen_name))?;
+        let user_id = user
+            .get("rest_id")
+            .and_then(Value::as_str)
+            .context("X user response has no ID")?;
+
+        let mut tweets: Vec<Tweet> = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..self.timeline_pages {
+            let mut variables = json!({
+                "userId": user_id,
+                "count": 20,
+                "includePromotedContent": false,
+                "withVoice": true,
+                "withV2Timeline": true,
+            });
+            if let Some(cursor) = &cursor {
+                variables["cursor"] = Value::String(cursor.clone());
+            }
+            let page = self.query(&self.endpoints.user_tweets, variables).await?;
+            let before = tweets.len();
+            for tweet in collect_tweets(&page) {
+                // Skip retweeted and quoted tweets by other accounts
+                let own = tweet
+                    .screen_name
+                    .as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(screen_name));
+                if own && !tweets.iter().any(|seen| seen.id == tweet.id) {
+                    tweets.push(tweet);
+                }
+            }
+            let next = bottom_cursor(&page);
+            if tweets.len() == before || next.is_none() || next == cursor {
+                break;
+            }
+            cursor = next;
+        }
+        profile_content(url, user, &tweets)
+    }
+
+    async fn extract_via_api(&self, url: &str) -> Result<ExtractedContent> {
+        match parse_target(url) {
+            Some(XTarget::Tweet { id }) => self.extract_tweet(url, &id).await,
+            Some(XTarget::Profile { screen_name }) => self.extract_profile(url, &screen_name).await,
+            None => anyhow::bail!("Not a tweet or profile URL: {}", url),
+        }
+    }
+}
+
+impl PlatformScraper for XScraper {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        Box::pin(async move {
+            match self.extract_via_api(&url).await {
+                Err(e) if e.is::<GuestTokensExhausted>() => {
+                    let Some(pool) = &self.browser else {
+                        return Err(e.context("no browser configured to fall back to"));
+                    };
+                    let page = pool.get_browser().await?.scrape_page(&url).await?;
+                    let mut content = super::content_from_html(page.url, &page.html.into());
+                    content
+                        .metadata
+                        .insert("fetched_via".into(), "browser".to_string());
+                    Ok(content)
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        HOSTS.is_match(url)
+    }
+
+    fn platform_name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self, _url: &str) -> ScraperCapabilities {
+        // Guest tokens are activated with a POST
+        ScraperCapabilities {
+            browser: Need::Optional,
+            auth: Need::Optional,
+            post: true,
+            ..ScraperCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn tweet(id: &str, author: &str, text: &str, extra: Value) -> Value {
+        let mut legacy = json!({
+            "full_text": text,
+            "conversation_id_str": "100",
+            "created_at": "Wed Oct 10 20:19:24 +0000 2018",
+            "favorite_count": 12,
+            "retweet_count": 3,
+            "reply_count": 1,
+        });
+        if let (Value::Object(legacy), Value::Object(extra)) = (&mut legacy, extra) {
+            legacy.extend(extra);
+        }
+        json!({ "tweet_results": { "result": {
+            "__typename": "Tweet",
+            "rest_id": id,
+            "core": { "user_results": { "result": { "legacy": {
+                "screen_name": author, "name": author.to_uppercase()
+            } } } },
+            "views": { "count": "4500" },
+            "legacy": legacy,
+        } } })
+    }
+
+    fn tweet_detail() -> Value {
+        let video = json!({ "extended_entities": { "media": [{
+            "media_url_https": "https://pbs.twimg.com/thumb.jpg",
+            "video_info": { "variants": [
+                { "content_type": "video/mp4", "bitrate": 256000, "url": "https://video.twimg.com/low.mp4" },
+                { "content_type": "video/mp4", "bitrate": 2176000, "url": "https://video.twimg.com/high.mp4" },
+                { "content_type": "application/x-mpegURL", "url": "https://video.twimg.com/pl.m3u8" },
+            ] },
+        }] } });
+        json!({ "data": { "threaded_conversation_with_injections_v2": { "instructions": [{
+            "entries": [
+                { "entryId": "tweet-100", "content": { "itemContent": tweet("100", "ferris", "1/ Announcing", video) } },
+                { "entryId": "conversationthread-101", "content": { "items": [
+                    { "item": { "itemContent": tweet("101", "ferris", "2/ Details", json!({})) } },
+                    { "item": { "itemContent": tweet("102", "someone", "Congrats!", json!({})) } },
+                ] } },
+            ]
+        }] } } })
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(
+            parse_target("https://x.com/ferris/status/123?s=20"),
+            Some(XTarget::Tweet {
+                id: "123".to_string()
+            })
+        );
+        assert_eq!(
+            parse_target("https://twitter.com/ferris"),
+            Some(XTarget::Profile {
+                screen_name: "ferris".to_string()
+            })
+        );
+        assert_eq!(parse_target("https://x.com/explore"), None);
+
+        let scraper = XScraper::new(ScraperConfig::default());
+        assert!(scraper.can_handle("https://mobile.twitter.com/ferris"));
+        assert!(!scraper.can_handle("https://notx.com/ferris"));
+    }
+
+    #[test]
+    fn test_thread_metrics_and_media() {
+        let tweets = collect_tweets(&tweet_detail());
+        assert_eq!(tweets.len(), 3);
+        let focal = &tweets[0];
+        let thread = self_thread(&tweets, focal);
+        let content = tweet_content("https://x.com/ferris/status/100", focal, &thread);
+
+        assert_eq!(content.text.as_deref(), Some("1/ Announcing\n\n2/ Details"));
+        assert_eq!(content.title.as_deref(), Some("FERRIS on X"));
+        assert_eq!(content.metadata["x:thread_length"], "2");
+        assert_eq!(
+            content.metadata[POST_MEDIA_KEY],
+            "https://video.twimg.com/high.mp4"
+        );
+
+        let post = <Post as crate::schemas::Schema>::from_content(&content).unwrap();
+        assert_eq!(post.author.as_deref(), Some("@ferris"));
+        assert_eq!(post.metrics["likes"], 12);
+        assert_eq!(post.metrics["views"], 4500);
+        assert!(post.published_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_guest_tokens_rotate_until_exhausted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/1.1/guest/activate.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "guest_token": "g1" })))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/1.1/guest/activate.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "guest_token": "g2" })))
+            .mount(&server)
+            .await;
+        let detail = format!("/i/api/graphql/{}", XEndpoints::default().tweet_detail);
+        Mock::given(method("GET"))
+            .and(path(detail.as_str()))
+            .and(header(GUEST_TOKEN_HEADER, "g2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tweet_detail()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(detail.as_str()))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let scraper = |max_guest_tokens| {
+            XScraper::new(ScraperConfig::default())
+                .with_endpoints(XEndpoints::at(&server.uri()))
+                .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+                .with_max_guest_tokens(max_guest_tokens)
+        };
+
+        // With a single token per window the API gives up, and there's no browser
+        let error = scraper(1)
+            .extract("https://x.com/ferris/status/100")
+            .await
+            .unwrap_err();
+        assert!(error.is::<GuestTokensExhausted>());
+
+        // The next token is rate limited too, the one after works
+        let content = scraper(2)
+            .extract("https://x.com/ferris/status/100")
+            .await
+            .unwrap();
+        assert_eq!(content.metadata["author"], "@ferris");
+    }
+}
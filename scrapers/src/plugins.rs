@@ -0,0 +1,391 @@
+//! WASM plugin extractors
+//!
+//! Plugins are plain WebAssembly modules loaded from a plugin directory and
+//! registered into the [`ScraperRegistry`](crate::platforms::ScraperRegistry).
+//! A plugin must export:
+//! - `memory` - its linear memory
+//! - `alloc(len: i32) -> i32` - reserve `len` bytes for host input
+//! - `can_handle(url_ptr: i32, url_len: i32) -> i32` - non-zero if the plugin handles the URL
+//! - `extract(html_ptr: i32, html_len: i32, url_ptr: i32, url_len: i32) -> i64` - returns the
+//!   output location packed as `(ptr << 32) | len`
+//!
+//! The output is UTF-8 JSON of the form `{ "title": ..., "text": ..., "metadata": { ... } }`,
+//! with every field optional.
+//!
+//! Plugins are sandboxed: they may not import any host functions, every call runs
+//! in a fresh instance with a fuel budget, and linear memory is capped.
+
+use crate::{metadata::Metadata, ExtractedContent, PlatformScraper, ScraperConfig};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use swoop_core::text::SharedStr;
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// File extension of plugin modules
+pub const PLUGIN_EXTENSION: &str = "wasm";
+
+/// Resource limits applied to every plugin call
+#[derive(Debug, Clone)]
+pub struct PluginLimits {
+    /// Fuel (roughly, executed instructions) available to a single call
+    pub fuel: u64,
+    /// Maximum linear memory size in bytes
+    pub max_memory_bytes: usize,
+    /// Maximum size of the JSON a plugin may return
+    pub max_output_bytes: usize,
+}
+
+impl Default for PluginLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 50_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+            max_output_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Structured output returned by a plugin's `extract`
+#[derive(Debug, Default, Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    title: Option<SharedStr>,
+    #[serde(default)]
+    text: Option<SharedStr>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+/// A compiled WASM extractor
+pub struct WasmPlugin {
+    name: &'static str,
+    engine: Engine,
+    module: Module,
+    limits: PluginLimits,
+    config: ScraperConfig,
+}
+
+impl WasmPlugin {
+    /// Compile a plugin from WASM bytes
+    pub fn from_bytes(name: &str, wasm: &[u8], limits: PluginLimits) -> Result<Self> {
+        let mut engine_config = Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm)
+            .map_err(|e| anyhow::anyhow!("Failed to compile plugin {}: {}", name, e))?;
+
+        if let Some(import) = module.imports().next() {
+            anyhow::bail!(
+                "Plugin {} imports {}::{}, plugins may not import host functions",
+                name,
+                import.module(),
+                import.name()
+            );
+        }
+
+        Ok(Self {
+            // Platform names are `&'static str`; plugins are loaded once at startup
+            name: Box::leak(name.to_string().into_boxed_str()),
+            engine,
+            module,
+            limits,
+            config: ScraperConfig::default(),
+        })
+    }
+
+    /// Compile a plugin from a `.wasm` file, named after the file stem
+    pub fn from_file(path: impl AsRef<Path>, limits: PluginLimits) -> Result<Self> {
+        let path = path.as_ref();
+        let wasm =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("plugin");
+        Self::from_bytes(name, &wasm, limits)
+    }
+
+    /// Use `config` when fetching pages for this plugin
+    pub fn with_config(mut self, config: ScraperConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Ask the plugin whether it handles `url`
+    pub fn handles(&self, url: &str) -> Result<bool> {
+        let mut instance = self.instantiate()?;
+        let (url_ptr, url_len) = instance.write_input(url.as_bytes())?;
+        let can_handle = instance.func::<(i32, i32), i32>("can_handle")?;
+        let handled = can_handle
+            .call(&mut instance.store, (url_ptr, url_len))
+            .map_err(|e| anyhow::anyhow!("Plugin {} can_handle failed: {}", self.name, e))?;
+        Ok(handled != 0)
+    }
+
+    /// Run the plugin's extractor over already fetched HTML
+    pub fn extract_html(&self, html: &str, url: &str) -> Result<ExtractedContent> {
+        let mut instance = self.instantiate()?;
+        let (html_ptr, html_len) = instance.write_input(html.as_bytes())?;
+        let (url_ptr, url_len) = instance.write_input(url.as_bytes())?;
+        let extract = instance.func::<(i32, i32, i32, i32), i64>("extract")?;
+        let packed = extract
+            .call(&mut instance.store, (html_ptr, html_len, url_ptr, url_len))
+            .map_err(|e| anyhow::anyhow!("Plugin {} extract failed: {}", self.name, e))?;
+
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xFFFF_FFFF) as usize;
+        if len > self.limits.max_output_bytes {
+            anyhow::bail!(
+                "Plugin {} returned {} bytes, limit is {}",
+                self.name,
+                len,
+                self.limits.max_output_bytes
+            );
+        }
+        let output = instance
+            .memory
+            .data(&instance.store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Plugin {} returned an out of bounds result", self.name)
+            })?;
+        let output: PluginOutput = serde_json::from_slice(output)
+            .with_context(|| format!("Plugin {} returned invalid JSON", self.name))?;
+
+        let mut metadata = output.metadata;
+        metadata.insert("plugin".into(), self.name.to_string());
+        Ok(ExtractedContent {
+            url: url.to_string(),
+            title: output.title,
+            text: output.text,
+            metadata,
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        })
+    }
+
+    fn instantiate(&self) -> Result<PluginInstance> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(self.limits.fuel)
+            .map_err(|e| anyhow::anyhow!("Failed to set plugin fuel: {}", e))?;
+
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| anyhow::anyhow!("Failed to instantiate plugin {}: {}", self.name, e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} does not export memory", self.name))?;
+
+        Ok(PluginInstance {
+            name: self.name,
+            store,
+            instance,
+            memory,
+        })
+    }
+}
+
+/// A single-use plugin instance
+struct PluginInstance {
+    name: &'static str,
+    store: Store<StoreLimits>,
+    instance: wasmi::Instance,
+    memory: Memory,
+}
+
+impl PluginInstance {
+    fn func<Params, Results>(&self, name: &str) -> Result<wasmi::TypedFunc<Params, Results>>
+    where
+        Params: wasmi::WasmParams,
+        Results: wasmi::WasmResults,
+    {
+        self.instance
+            .get_typed_func(&self.store, name)
+            .map_err(|e| {
+                anyhow::anyhow!("Plugin {} has no valid `{}` export: {}", self.name, name, e)
+            })
+    }
+
+    /// Copy `bytes` into plugin memory through its `alloc` export
+    fn write_input(&mut self, bytes: &[u8]) -> Result<(i32, i32)> {
+        let len = i32::try_from(bytes.len()).context("Plugin input too large")?;
+        let alloc = self.func::<i32, i32>("alloc")?;
+        let ptr = alloc
+            .call(&mut self.store, len)
+            .map_err(|e| anyhow::anyhow!("Plugin {} alloc failed: {}", self.name, e))?;
+        self.memory
+            .write(&mut self.store, ptr as usize, bytes)
+            .map_err(|e| {
+                anyhow::anyhow!("Plugin {} alloc returned invalid memory: {}", self.name, e)
+            })?;
+        Ok((ptr, len))
+    }
+}
+
+impl PlatformScraper for WasmPlugin {
+    fn extract(
+        &self,
+        url: &str,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ExtractedContent>> + Send + '_>>
+    {
+        let url = url.to_string();
+        let timeout = self.config.timeout_secs;
+        Box::pin(async move {
+            let response = swoop_core::fetch_response(&url, Duration::from_secs(timeout)).await?;
+            let html = String::from_utf8_lossy(&response.body);
+            let mut content = self.extract_html(&html, &url)?;
+            response.meta.record_metadata(&mut content.metadata);
+            Ok(content)
+        })
+    }
+
+    fn extract_from_html(&self, url: &str, html: &SharedStr) -> Option<Result<ExtractedContent>> {
+        Some(self.extract_html(html, url))
+    }
+
+    fn can_handle(&self, url: &str) -> bool {
+        // A plugin that traps is treated as not handling the URL
+        self.handles(url).unwrap_or(false)
+    }
+
+    fn platform_name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Compile every `.wasm` file in `dir`, sorted by file name
+pub fn load_plugins(dir: impl AsRef<Path>, limits: &PluginLimits) -> Result<Vec<WasmPlugin>> {
+    let dir = dir.as_ref();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read plugin directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().and_then(|ext| ext.to_str()) == Some(PLUGIN_EXTENSION)
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| WasmPlugin::from_file(path, limits.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $heap (mut i32) (i32.const 1024))
+          (data (i32.const 0) "{\"title\":\"from plugin\",\"metadata\":{\"kind\":\"test\"}}")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+            (local.get $ptr))
+          ;; Handles URLs whose host starts with "e" (https://example.com)
+          (func (export "can_handle") (param $ptr i32) (param $len i32) (result i32)
+            (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 8))) (i32.const 101)))
+          (func (export "extract") (param i32 i32 i32 i32) (result i64)
+            (i64.const 50)))
+    "#;
+
+    fn compile(name: &str, wat_source: &str) -> Result<WasmPlugin> {
+        WasmPlugin::from_bytes(
+            name,
+            &wat::parse_str(wat_source).unwrap(),
+            PluginLimits::default(),
+        )
+    }
+
+    #[test]
+    fn test_plugin_extracts_structured_output() {
+        let plugin = compile("example", EXAMPLE_PLUGIN).unwrap();
+
+        assert!(plugin.can_handle("https://example.com/item"));
+        assert!(!plugin.can_handle("https://other.com/item"));
+
+        let content = plugin
+            .extract_html("<html></html>", "https://example.com/item")
+            .unwrap();
+        assert_eq!(content.title.as_deref(), Some("from plugin"));
+        assert_eq!(
+            content.metadata.get("kind").map(String::as_str),
+            Some("test")
+        );
+        assert_eq!(
+            content.metadata.get("plugin").map(String::as_str),
+            Some("example")
+        );
+        assert_eq!(plugin.platform_name(), "example");
+    }
+
+    #[test]
+    fn test_plugin_fuel_limit_stops_runaway_code() {
+        let plugin = compile(
+            "spin",
+            r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "alloc") (param i32) (result i32) (i32.const 0))
+              (func (export "can_handle") (param i32 i32) (result i32)
+                (loop $spin (br $spin))
+                (i32.const 1)))
+            "#,
+        )
+        .unwrap();
+
+        assert!(plugin.handles("https://example.com").is_err());
+        assert!(!plugin.can_handle("https://example.com"));
+    }
+
+    #[test]
+    fn test_plugin_host_imports_are_rejected() {
+        let result = compile(
+            "imports",
+            r#"(module (import "env" "read_file" (func (param i32))) (memory (export "memory") 1))"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_plugins_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("example.wasm"),
+            wat::parse_str(EXAMPLE_PLUGIN).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.txt"), "not a plugin").unwrap();
+
+        let plugins = load_plugins(dir.path(), &PluginLimits::default()).unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].platform_name(), "example");
+
+        let mut registry = crate::platforms::ScraperRegistry::default();
+        assert_eq!(
+            registry
+                .load_plugins(dir.path(), &PluginLimits::default())
+                .unwrap(),
+            1
+        );
+        let scraper = registry.find_scraper("https://example.com").unwrap();
+        assert_eq!(scraper.platform_name(), "example");
+        let scraper = registry.find_scraper("https://other.com").unwrap();
+        assert_eq!(scraper.platform_name(), "generic");
+    }
+}
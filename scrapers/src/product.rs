@@ -0,0 +1,467 @@
+//! Platform-agnostic product extraction
+//!
+//! [`ProductExtractor`] pulls name, price, currency, availability, SKU, brand
+//! and images from arbitrary shop pages so small shops don't each need a custom
+//! scraper. JSON-LD `Product` data is preferred, then schema.org microdata,
+//! then OpenGraph product tags; a price that none of them carry is taken from
+//! price-like text on the page. Pages without any product markup are not
+//! treated as products, however many prices they mention.
+
+use crate::{
+    extractors::{
+        decode_entities, extract_json_ld, find_json_ld_node, html_tags, json_ld_images,
+        json_ld_text,
+    },
+    metadata::Metadata,
+    schemas::{PRODUCT_IMAGES_KEY, PRODUCT_SKU_KEY},
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+static PRICE_ELEMENT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<[a-z][a-z0-9]*\b[^>]*(?:class|id)\s*=\s*["'][^"']*price[^"']*["'][^>]*>(.*?)</"#,
+    )
+    .unwrap()
+});
+static PRICE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:([$€£¥₹])\s?(\d{1,3}(?:[.,\s]\d{3})*(?:[.,]\d{1,2})?|\d+(?:[.,]\d{1,2})?))|(?:(\d{1,3}(?:[.,\s]\d{3})*(?:[.,]\d{1,2})?|\d+(?:[.,]\d{1,2})?)\s?([$€£¥₹]|(?:USD|EUR|GBP|JPY|INR|CAD|AUD|CHF)\b))",
+    )
+    .unwrap()
+});
+static STRIP_TAGS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+/// Where a product's fields came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductSource {
+    JsonLd,
+    Microdata,
+    OpenGraph,
+    /// Price-like text on the page
+    Heuristic,
+    /// A platform scraper's own parsing, e.g. eBay listing markup
+    Platform,
+}
+
+/// Product data found on a page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedProduct {
+    pub name: Option<String>,
+    pub price: Option<f64>,
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: Option<String>,
+    /// schema.org availability name, e.g. `InStock`
+    pub availability: Option<String>,
+    pub sku: Option<String>,
+    pub brand: Option<String>,
+    /// Absolute image URLs, best first
+    pub images: Vec<String>,
+    /// Sources that contributed at least one field, in order of precedence
+    pub sources: Vec<ProductSource>,
+}
+
+impl ExtractedProduct {
+    /// Fill fields that are still missing from `other`
+    pub(crate) fn merge(&mut self, other: ExtractedProduct, source: ProductSource) {
+        let before = self.clone();
+        self.name = self.name.take().or(other.name);
+        self.price = self.price.or(other.price);
+        self.currency = self.currency.take().or(other.currency);
+        self.availability = self.availability.take().or(other.availability);
+        self.sku = self.sku.take().or(other.sku);
+        self.brand = self.brand.take().or(other.brand);
+        for image in other.images {
+            if !self.images.contains(&image) {
+                self.images.push(image);
+            }
+        }
+        if *self != before {
+            self.sources.push(source);
+        }
+    }
+
+    /// Record the product as the `product:*` metadata the `product` schema reads
+    pub fn record(&self, metadata: &mut Metadata) {
+        let mut set = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                metadata.insert(key.into(), value);
+            }
+        };
+        set("product:name", self.name.clone());
+        set(
+            "product:price:amount",
+            self.price.map(|price| price.to_string()),
+        );
+        set("product:price:currency", self.currency.clone());
+        set("product:availability", self.availability.clone());
+        set("product:brand", self.brand.clone());
+        set(PRODUCT_SKU_KEY, self.sku.clone());
+        set(
+            PRODUCT_IMAGES_KEY,
+            (!self.images.is_empty()).then(|| self.images.join("\n")),
+        );
+    }
+}
+
+/// Extracts product data from arbitrary shop pages
+#[derive(Debug, Clone, Default)]
+pub struct ProductExtractor;
+
+impl ProductExtractor {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Product on the page at `url`, if it has product markup
+    pub fn extract(&self, html: &str, url: &str) -> Option<ExtractedProduct> {
+        let base = url::Url::parse(url).ok();
+        let mut product = ExtractedProduct::default();
+        let mut marked_up = false;
+
+        if let Some(json_ld) = json_ld_product(html) {
+            marked_up = true;
+            product.merge(json_ld, ProductSource::JsonLd);
+        }
+        if let Some(microdata) = microdata_product(html) {
+            marked_up = true;
+            product.merge(microdata, ProductSource::Microdata);
+        }
+        if let Some(open_graph) = open_graph_product(html) {
+            marked_up = true;
+            product.merge(open_graph, ProductSource::OpenGraph);
+        }
+        if !marked_up {
+            return None;
+        }
+        if product.price.is_none() {
+            if let Some((price, currency)) = heuristic_price(html) {
+                product.merge(
+                    ExtractedProduct {
+                        price: Some(price),
+                        currency,
+                        ..ExtractedProduct::default()
+                    },
+                    ProductSource::Heuristic,
+                );
+            }
+        }
+
+        let mut images = Vec::new();
+        for image in &product.images {
+            let image = match &base {
+                Some(base) => base.join(image).map(|image| image.to_string()).ok(),
+                None => Some(image.clone()),
+            };
+            if let Some(image) = image.filter(|image| !images.contains(image)) {
+                images.push(image);
+            }
+        }
+        product.images = images;
+        Some(product)
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = decode_entities(value.trim());
+    (!value.is_empty()).then_some(value)
+}
+
+/// Map OpenGraph and schema.org availability spellings to schema.org names
+pub(crate) fn normalize_availability(raw: &str) -> Option<String> {
+    let name = raw.trim().rsplit('/').next().unwrap_or_default();
+    let key: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let normalized = match key.as_str() {
+        "" => return None,
+        "instock" | "available" => "InStock",
+        "outofstock" | "oos" | "soldout" => "OutOfStock",
+        "preorder" => "PreOrder",
+        "backorder" => "BackOrder",
+        "discontinued" => "Discontinued",
+        "limitedavailability" => "LimitedAvailability",
+        "instoreonly" => "InStoreOnly",
+        "onlineonly" => "OnlineOnly",
+        _ => return Some(name.to_string()),
+    };
+    Some(normalized.to_string())
+}
+
+pub(crate) fn currency_code(symbol: &str) -> Option<String> {
+    let code = match symbol {
+        "$" => "USD",
+        "€" => "EUR",
+        "£" => "GBP",
+        "¥" => "JPY",
+        "₹" => "INR",
+        code if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => code,
+        _ => return None,
+    };
+    Some(code.to_ascii_uppercase())
+}
+
+/// Parse `1,299.00`, `1.299,00`, `1 299` or `24.99` as a number
+pub(crate) fn parse_price(raw: &str) -> Option<f64> {
+    let digits: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ','))
+        .collect();
+    // The last separator is a decimal point only when followed by one or two digits
+    let normalized = match digits.rfind(['.', ',']) {
+        Some(index) if (1..=2).contains(&(digits.len() - index - 1)) => {
+            let (whole, fraction) = digits.split_at(index);
+            format!("{}.{}", whole.replace(['.', ','], ""), &fraction[1..])
+        }
+        _ => digits.replace(['.', ','], ""),
+    };
+    normalized
+        .parse()
+        .ok()
+        .filter(|price: &f64| price.is_finite())
+}
+
+fn json_ld_product(html: &str) -> Option<ExtractedProduct> {
+    extract_json_ld(html).iter().find_map(|document| {
+        let node = find_json_ld_node(document, &["Product", "ProductGroup"])?;
+        let field = |key: &str| node.get(key).and_then(json_ld_text);
+
+        // `offers` may be an Offer, a list of them or an AggregateOffer
+        let offers = node.get("offers");
+        let offer = match offers {
+            Some(Value::Array(offers)) => offers.first(),
+            offer => offer,
+        };
+        let offer_field = |keys: &[&str]| {
+            keys.iter().find_map(|key| {
+                offer
+                    .and_then(|offer| offer.get(*key))
+                    .and_then(json_ld_text)
+            })
+        };
+
+        Some(ExtractedProduct {
+            name: field("name"),
+            price: offer_field(&["price", "lowPrice"]).and_then(|price| parse_price(&price)),
+            currency: offer_field(&["priceCurrency"]).map(|code| code.to_ascii_uppercase()),
+            availability: offer_field(&["availability"])
+                .and_then(|availability| normalize_availability(&availability)),
+            sku: field("sku")
+                .or_else(|| field("mpn"))
+                .or_else(|| field("productID")),
+            brand: field("brand"),
+            images: node.get("image").map(json_ld_images).unwrap_or_default(),
+            sources: Vec::new(),
+        })
+    })
+}
+
+fn microdata_product(html: &str) -> Option<ExtractedProduct> {
+    let start = html_tags(html).find_map(|tag| {
+        let item_type = tag.attributes.get("itemtype")?;
+        item_type
+            .trim_end_matches('/')
+            .ends_with("schema.org/Product")
+            .then_some(tag.start)
+    })?;
+
+    let mut props: HashMap<String, String> = HashMap::new();
+    let mut images = Vec::new();
+    let scope = &html[start..];
+    for tag in html_tags(scope) {
+        let attrs = &tag.attributes;
+        let Some(prop) = attrs.get("itemprop") else {
+            continue;
+        };
+        let value = attrs
+            .get("content")
+            .or_else(|| attrs.get("href"))
+            .or_else(|| attrs.get("src"))
+            .cloned()
+            .or_else(|| {
+                let text = scope[tag.end..].split('<').next().unwrap_or_default();
+                non_empty(text)
+            });
+        let Some(value) = value.and_then(|value| non_empty(&value)) else {
+            continue;
+        };
+        for prop in prop.split_whitespace() {
+            if prop == "image" {
+                images.push(value.clone());
+            } else {
+                props
+                    .entry(prop.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+
+    let get = |key: &str| props.get(key).cloned();
+    let product = ExtractedProduct {
+        name: get("name"),
+        price: get("price")
+            .or_else(|| get("lowPrice"))
+            .and_then(|price| parse_price(&price)),
+        currency: get("priceCurrency").map(|code| code.to_ascii_uppercase()),
+        availability: get("availability")
+            .and_then(|availability| normalize_availability(&availability)),
+        sku: get("sku")
+            .or_else(|| get("mpn"))
+            .or_else(|| get("productID")),
+        brand: get("brand"),
+        images,
+        sources: Vec::new(),
+    };
+    Some(product)
+}
+
+fn open_graph_product(html: &str) -> Option<ExtractedProduct> {
+    let mut properties: HashMap<String, String> = HashMap::new();
+    let mut images = Vec::new();
+    for tag in html_tags(html).filter(|tag| tag.name == "meta") {
+        let attrs = &tag.attributes;
+        let (Some(property), Some(content)) = (
+            attrs.get("property").or_else(|| attrs.get("name")),
+            attrs.get("content").and_then(|content| non_empty(content)),
+        ) else {
+            continue;
+        };
+        let property = property.to_ascii_lowercase();
+        if property == "og:image" {
+            images.push(content);
+        } else {
+            properties.entry(property).or_insert(content);
+        }
+    }
+
+    let get = |keys: &[&str]| keys.iter().find_map(|key| properties.get(*key).cloned());
+    let price = get(&["product:price:amount", "og:price:amount"]);
+    let is_product = properties
+        .get("og:type")
+        .is_some_and(|kind| kind.to_ascii_lowercase().contains("product"));
+    if !is_product && price.is_none() {
+        return None;
+    }
+
+    Some(ExtractedProduct {
+        name: get(&["og:title"]),
+        price: price.and_then(|price| parse_price(&price)),
+        currency: get(&["product:price:currency", "og:price:currency"])
+            .map(|code| code.to_ascii_uppercase()),
+        availability: get(&["product:availability", "og:availability"])
+            .and_then(|availability| normalize_availability(&availability)),
+        sku: get(&["product:retailer_item_id", "product:sku"]),
+        brand: get(&["product:brand", "og:brand"]),
+        images,
+        sources: Vec::new(),
+    })
+}
+
+/// First price in an element whose class or id mentions "price"
+fn heuristic_price(html: &str) -> Option<(f64, Option<String>)> {
+    PRICE_ELEMENT_REGEX.captures_iter(html).find_map(|element| {
+        let text = decode_entities(&STRIP_TAGS_REGEX.replace_all(&element[1], " "));
+        let captures = PRICE_REGEX.captures(&text)?;
+        let (symbol, amount) = match (captures.get(1), captures.get(2)) {
+            (Some(symbol), Some(amount)) => (symbol.as_str(), amount.as_str()),
+            _ => (captures.get(4)?.as_str(), captures.get(3)?.as_str()),
+        };
+        Some((parse_price(amount)?, currency_code(symbol)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_ld_product_wins_over_open_graph() {
+        let html = r#"<html><head>
+            <meta property="og:type" content="product">
+            <meta property="og:title" content="Kettle | Small Shop">
+            <meta property="og:image" content="/img/og.jpg">
+            <script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+              {"@type": "BreadcrumbList"},
+              {"@type": "Product", "name": "Steel Kettle", "sku": "KT-1",
+               "brand": {"@type": "Brand", "name": "Boil Co"},
+               "image": ["https://cdn.shop.example/kettle.jpg"],
+               "offers": [{"@type": "Offer", "price": "1299.00", "priceCurrency": "sek",
+                           "availability": "https://schema.org/InStock"}]}
+            ]}
+            </script></head><body></body></html>"#;
+
+        let product = ProductExtractor::new()
+            .extract(html, "https://shop.example/kettle")
+            .unwrap();
+        assert_eq!(product.name.as_deref(), Some("Steel Kettle"));
+        assert_eq!(product.price, Some(1299.0));
+        assert_eq!(product.currency.as_deref(), Some("SEK"));
+        assert_eq!(product.availability.as_deref(), Some("InStock"));
+        assert_eq!(product.sku.as_deref(), Some("KT-1"));
+        assert_eq!(product.brand.as_deref(), Some("Boil Co"));
+        assert_eq!(
+            product.images,
+            vec![
+                "https://cdn.shop.example/kettle.jpg".to_string(),
+                "https://shop.example/img/og.jpg".to_string()
+            ]
+        );
+        assert_eq!(
+            product.sources,
+            vec![ProductSource::JsonLd, ProductSource::OpenGraph]
+        );
+    }
+
+    #[test]
+    fn test_microdata_with_heuristic_price() {
+        let html = r#"<div itemscope itemtype="http://schema.org/Product">
+            <h1 itemprop="name">Wool Socks</h1>
+            <img itemprop="image" src="socks.jpg">
+            <span itemprop="sku">SOCK-9</span>
+            <link itemprop="availability" href="http://schema.org/OutOfStock">
+            <p class="product-price">Now only <b>12,50 €</b></p>
+        </div>"#;
+
+        let product = ProductExtractor::new()
+            .extract(html, "https://socks.example/p/9")
+            .unwrap();
+        assert_eq!(product.name.as_deref(), Some("Wool Socks"));
+        assert_eq!(product.sku.as_deref(), Some("SOCK-9"));
+        assert_eq!(product.availability.as_deref(), Some("OutOfStock"));
+        assert_eq!(product.price, Some(12.5));
+        assert_eq!(product.currency.as_deref(), Some("EUR"));
+        assert_eq!(
+            product.images,
+            vec!["https://socks.example/p/socks.jpg".to_string()]
+        );
+        assert_eq!(
+            product.sources,
+            vec![ProductSource::Microdata, ProductSource::Heuristic]
+        );
+
+        let mut metadata = HashMap::new();
+        product.record(&mut metadata);
+        assert_eq!(metadata["product:price:amount"], "12.5");
+        assert_eq!(metadata[PRODUCT_SKU_KEY], "SOCK-9");
+    }
+
+    #[test]
+    fn test_pages_without_product_markup_are_ignored() {
+        let html =
+            r#"<article><h1>Budget tips</h1><p class="price">Save $1,200 a year</p></article>"#;
+        assert!(ProductExtractor::new()
+            .extract(html, "https://blog.example/tips")
+            .is_none());
+
+        assert_eq!(parse_price("1.299,00"), Some(1299.0));
+        assert_eq!(parse_price("1,299"), Some(1299.0));
+        assert_eq!(parse_price("24.9"), Some(24.9));
+    }
+}
@@ -0,0 +1,256 @@
+//! Result quality scoring and escalation
+//!
+//! A page can come back `200 OK` and still be useless: a bot challenge, a
+//! login or consent wall, a soft 404 or a script shell without text.
+//! [`QualityScorer`] rates a fetched page from 0 to 1 on cheap signals, and a
+//! [`QualityGate`] decides which results are worth another try. Those are
+//! fetched again with an [`Escalation`]: rendered in a browser, or through the
+//! geo proxy of another country, until one scores well enough or the gate's
+//! retries run out.
+
+use crate::{
+    classifier::{ContentClassifier, PageClass, PageSignals},
+    policy::ResolvedPolicy,
+    utils::is_bot_protected,
+    walls::WallDetector,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static SCRIPT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<script[\s>]").unwrap());
+
+/// Words a page needs before it stops counting as thin
+const MIN_WORDS: usize = 50;
+
+/// Script tags past which a thin page is taken for an unrendered app shell
+const SHELL_SCRIPTS: usize = 5;
+
+/// How useful a fetched page is
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityScore {
+    /// From 0 (useless) to 1 (no problems found)
+    pub score: f64,
+    /// What cost the page points, e.g. `bot-protected` or `thin`
+    pub issues: Vec<&'static str>,
+}
+
+impl QualityScore {
+    /// Score of a fetch that returned no page
+    pub fn failed() -> Self {
+        Self {
+            score: 0.0,
+            issues: vec!["failed"],
+        }
+    }
+}
+
+/// Rates fetched pages
+#[derive(Debug, Clone, Default)]
+pub struct QualityScorer {
+    walls: WallDetector,
+}
+
+impl QualityScorer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Score `html`, fetched from `url` with `status_code`
+    pub fn score(&self, html: &str, url: &str, status_code: Option<u16>) -> QualityScore {
+        let mut score: f64 = 1.0;
+        let mut issues = Vec::new();
+        let mut penalize = |issue: &'static str, penalty: f64| {
+            score -= penalty;
+            issues.push(issue);
+        };
+
+        if status_code.is_some_and(|status| status >= 400) {
+            penalize("http-error", 0.6);
+        }
+        if is_bot_protected(html) {
+            penalize("bot-protected", 0.9);
+        }
+        if self.walls.detect(html, Some(url)).is_some() {
+            penalize("wall", 0.7);
+        }
+        let signals = PageSignals::from_html(html, status_code);
+        let classification = ContentClassifier::new().classify(&signals);
+        if classification
+            .classes
+            .iter()
+            .any(|(class, _)| *class == PageClass::ErrorPage)
+        {
+            penalize("error-page", 0.6);
+        }
+        if signals.word_count < MIN_WORDS {
+            let missing = 1.0 - signals.word_count as f64 / MIN_WORDS as f64;
+            if SCRIPT_REGEX.find_iter(html).count() >= SHELL_SCRIPTS {
+                penalize("script-shell", 0.3 + 0.4 * missing);
+            } else {
+                penalize("thin", 0.4 * missing);
+            }
+        }
+        if signals.title.is_empty() {
+            penalize("no-title", 0.1);
+        }
+
+        QualityScore {
+            score: score.clamp(0.0, 1.0),
+            issues,
+        }
+    }
+}
+
+/// Which results get fetched again, and how often
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityGate {
+    /// Results scoring below this are retried
+    pub min_score: f64,
+    /// Escalations tried per URL before keeping the best result
+    pub max_retries: u32,
+}
+
+impl QualityGate {
+    pub const DEFAULT_RETRIES: u32 = 2;
+
+    pub fn new(min_score: f64) -> Self {
+        Self {
+            min_score,
+            max_retries: Self::DEFAULT_RETRIES,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn accepts(&self, score: &QualityScore) -> bool {
+        score.score >= self.min_score
+    }
+}
+
+/// A stronger way to fetch a page than the one that gave a poor result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Escalation {
+    /// Render the page in a browser
+    Browser,
+    /// Fetch through the geo proxy of `country`
+    GeoProxy { country: String, proxy: String },
+}
+
+impl Escalation {
+    /// Escalations left for a URL fetched under `policy`, most promising first
+    ///
+    /// The browser comes first when there is one and the policy didn't use it
+    /// already; then the geo proxies other than the one in use, in country
+    /// order.
+    pub fn ladder(policy: &ResolvedPolicy, browser: bool) -> Vec<Self> {
+        let mut ladder = Vec::new();
+        if browser && !policy.browser_required {
+            ladder.push(Self::Browser);
+        }
+        ladder.extend(
+            policy
+                .geo_proxies
+                .iter()
+                .filter(|(_, proxy)| policy.proxy.as_ref() != Some(*proxy))
+                .map(|(country, proxy)| Self::GeoProxy {
+                    country: country.clone(),
+                    proxy: proxy.clone(),
+                }),
+        );
+        ladder
+    }
+
+    /// The policy to fetch with once escalated
+    pub fn apply(&self, policy: &ResolvedPolicy) -> ResolvedPolicy {
+        let mut policy = policy.clone();
+        match self {
+            Self::Browser => policy.browser_required = true,
+            Self::GeoProxy { proxy, .. } => {
+                policy.proxy = Some(proxy.clone());
+                policy.proxy_tier = None;
+            }
+        }
+        policy
+    }
+
+    /// Tag of results fetched this way, e.g. `escalated:geo-de`
+    pub fn as_tag(&self) -> String {
+        match self {
+            Self::Browser => "escalated:browser".to_string(),
+            Self::GeoProxy { country, .. } => format!("escalated:geo-{}", country),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, sync::Arc};
+
+    fn article() -> String {
+        let body =
+            "<p>The harbour reopened this week after repairs to the breakwater.</p>".repeat(20);
+        format!(
+            "<html><head><title>Harbour reopens</title></head><body><article>{}</article></body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_scores_pages() {
+        let scorer = QualityScorer::new();
+        let good = scorer.score(&article(), "https://example.com/news", Some(200));
+        assert_eq!(good.score, 1.0);
+        assert!(good.issues.is_empty());
+
+        let challenge = r#"<html><head><title>Just a moment...</title></head>
+            <body>Checking your browser before accessing the site. Performance &amp; security by Cloudflare</body></html>"#;
+        let blocked = scorer.score(challenge, "https://example.com/news", Some(200));
+        assert!(blocked.score < 0.2, "{:?}", blocked);
+        assert!(blocked.issues.contains(&"bot-protected"));
+
+        let shell = format!(
+            "<html><head><title>App</title>{}</head><body><div id=\"root\"></div></body></html>",
+            "<script src=\"/chunk.js\"></script>".repeat(6)
+        );
+        let shell = scorer.score(&shell, "https://example.com/app", Some(200));
+        assert!(shell.issues.contains(&"script-shell"));
+        assert!(shell.score < 0.5);
+
+        let gate = QualityGate::new(0.5);
+        assert!(gate.accepts(&good));
+        assert!(!gate.accepts(&shell));
+        assert!(!gate.accepts(&QualityScore::failed()));
+    }
+
+    #[test]
+    fn test_escalation_ladder() {
+        let policy = ResolvedPolicy {
+            proxy: Some("http://de.proxy:8080".to_string()),
+            geo_proxies: Arc::new(BTreeMap::from([
+                ("de".to_string(), "http://de.proxy:8080".to_string()),
+                ("us".to_string(), "http://us.proxy:8080".to_string()),
+            ])),
+            ..ResolvedPolicy::default()
+        };
+        let ladder = Escalation::ladder(&policy, true);
+        assert_eq!(
+            ladder,
+            vec![
+                Escalation::Browser,
+                Escalation::GeoProxy {
+                    country: "us".to_string(),
+                    proxy: "http://us.proxy:8080".to_string()
+                }
+            ]
+        );
+        assert!(ladder[0].apply(&policy).browser_required);
+        let escalated = ladder[1].apply(&policy);
+        assert_eq!(escalated.proxy.as_deref(), Some("http://us.proxy:8080"));
+        assert_eq!(ladder[1].as_tag(), "escalated:geo-us");
+        assert_eq!(Escalation::ladder(&policy, false).len(), 1);
+    }
+}
@@ -1,15 +1,18 @@
 use anyhow::Result;
 use governor::clock::{Clock, QuantaClock};
 use governor::{DefaultDirectRateLimiter, Quota};
-use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU32;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 
 pub struct DistributedRateLimiter {
     // Per-domain rate limiters
-    domain_limiters: Arc<RwLock<HashMap<String, DefaultDirectRateLimiter>>>,
+    domain_limiters: Arc<RwLock<HashMap<String, Arc<DefaultDirectRateLimiter>>>>,
     // Global rate limiter
     global_limiter: DefaultDirectRateLimiter,
     // Configuration
@@ -36,18 +39,37 @@ impl DistributedRateLimiter {
         // Check global rate limit first
         self.global_limiter.until_ready().await;
 
-        // Check domain-specific rate limit
-        {
+        // Check domain-specific rate limit; the map lock is released before
+        // waiting so one throttled domain doesn't hold up the others
+        let limiter = {
             let mut limiters = self.domain_limiters.write().await;
-            let limiter = limiters.entry(domain.to_string()).or_insert_with(|| {
-                DefaultDirectRateLimiter::direct(Quota::per_second(self.requests_per_domain))
-            });
-            limiter.until_ready().await;
-        }
+            limiters
+                .entry(domain.to_string())
+                .or_insert_with(|| {
+                    Arc::new(DefaultDirectRateLimiter::direct(Quota::per_second(
+                        self.requests_per_domain,
+                    )))
+                })
+                .clone()
+        };
+        limiter.until_ready().await;
 
         Ok(())
     }
 
+    /// Give `domain` its own per-second limit instead of the default one
+    pub async fn set_domain_limit(&self, domain: &str, requests_per_second: u32) -> Result<()> {
+        let quota = Quota::per_second(
+            NonZeroU32::new(requests_per_second)
+                .ok_or_else(|| anyhow::anyhow!("Domain rate limit must be > 0"))?,
+        );
+        self.domain_limiters.write().await.insert(
+            domain.to_string(),
+            Arc::new(DefaultDirectRateLimiter::direct(quota)),
+        );
+        Ok(())
+    }
+
     /// Get the current rate limit status for a domain
     pub async fn get_domain_status(&self, domain: &str) -> Option<Duration> {
         let limiters = self.domain_limiters.read().await;
@@ -86,6 +108,380 @@ pub struct RateLimiterStats {
     pub domain_rate_limit: u32,
 }
 
+/// Named bundle of pacing settings for a crawl
+///
+/// Profiles combine concurrency, per-host caps, delays between requests and
+/// how hard failed requests are retried, so a job can be made polite or fast
+/// with a single `--profile` flag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateProfile {
+    pub name: String,
+    /// Requests in flight across all hosts
+    pub concurrency: usize,
+    /// Requests in flight against a single host
+    pub per_host_concurrency: usize,
+    /// Requests per second against a single host
+    pub per_host_rps: u32,
+    /// Requests per second across all hosts
+    pub global_rps: u32,
+    /// Random delay before each request, in milliseconds
+    pub min_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Attempts per URL, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each further retry
+    pub retry_backoff_ms: u64,
+}
+
+impl RateProfile {
+    /// Names accepted by [`RateProfile::named`]
+    pub const NAMES: &'static [&'static str] = &["gentle", "normal", "aggressive"];
+
+    /// One request at a time per host with human-like pauses
+    pub fn gentle() -> Self {
+        Self {
+            name: "gentle".to_string(),
+            concurrency: 4,
+            per_host_concurrency: 1,
+            per_host_rps: 1,
+            global_rps: 10,
+            min_delay_ms: 1000,
+            max_delay_ms: 3000,
+            max_attempts: 2,
+            retry_backoff_ms: 5000,
+        }
+    }
+
+    pub fn normal() -> Self {
+        Self {
+            name: "normal".to_string(),
+            concurrency: 50,
+            per_host_concurrency: 4,
+            per_host_rps: 5,
+            global_rps: 100,
+            min_delay_ms: 100,
+            max_delay_ms: 500,
+            max_attempts: 3,
+            retry_backoff_ms: 1000,
+        }
+    }
+
+    /// As fast as the network allows; likely to trip rate limits
+    pub fn aggressive() -> Self {
+        Self {
+            name: "aggressive".to_string(),
+            concurrency: 300,
+            per_host_concurrency: 32,
+            per_host_rps: 50,
+            global_rps: 1000,
+            min_delay_ms: 0,
+            max_delay_ms: 0,
+            max_attempts: 5,
+            retry_backoff_ms: 200,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gentle" => Some(Self::gentle()),
+            "normal" => Some(Self::normal()),
+            "aggressive" => Some(Self::aggressive()),
+            _ => None,
+        }
+    }
+
+    pub fn is_aggressive(&self) -> bool {
+        self.name == "aggressive"
+    }
+
+    /// Wait before retry number `retry` (1 for the first retry)
+    pub fn retry_delay(&self, retry: u32) -> Duration {
+        let factor = 1u64 << retry.saturating_sub(1).min(10);
+        Duration::from_millis(self.retry_backoff_ms.saturating_mul(factor))
+    }
+
+    fn request_delay(&self) -> Duration {
+        if self.max_delay_ms <= self.min_delay_ms {
+            return Duration::from_millis(self.min_delay_ms);
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(self.min_delay_ms..=self.max_delay_ms))
+    }
+}
+
+/// Enforces a [`RateProfile`]'s per-host limits
+///
+/// Global concurrency is left to the caller, which usually already bounds it
+/// with its own semaphore.
+pub struct HostThrottle {
+    profile: RateProfile,
+    limiter: DistributedRateLimiter,
+    hosts: std::sync::Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl std::fmt::Debug for HostThrottle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostThrottle")
+            .field("profile", &self.profile)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HostThrottle {
+    pub fn new(profile: RateProfile) -> Result<Self> {
+        if profile.per_host_concurrency == 0 {
+            anyhow::bail!("Per-host concurrency must be > 0");
+        }
+        Ok(Self {
+            limiter: DistributedRateLimiter::new(profile.per_host_rps, profile.global_rps)?,
+            profile,
+            hosts: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn profile(&self) -> &RateProfile {
+        &self.profile
+    }
+
+    /// Wait until a request to `host` may start
+    ///
+    /// Hold the returned permit until the request has finished.
+    pub async fn acquire(&self, host: &str) -> OwnedSemaphorePermit {
+        self.acquire_with(host, &self.profile).await
+    }
+
+    /// Like [`Self::acquire`], with per-host limits from `profile`, e.g. a
+    /// domain policy; the limits of a host are fixed by its first request
+    pub async fn acquire_with(&self, host: &str, profile: &RateProfile) -> OwnedSemaphorePermit {
+        let (semaphore, first) = {
+            let mut hosts = self.hosts.lock().unwrap();
+            match hosts.get(host) {
+                Some(semaphore) => (semaphore.clone(), false),
+                None => {
+                    let semaphore = Arc::new(Semaphore::new(profile.per_host_concurrency.max(1)));
+                    hosts.insert(host.to_string(), semaphore.clone());
+                    (semaphore, true)
+                }
+            }
+        };
+        if first && profile.per_host_rps != self.profile.per_host_rps {
+            let _ = self.limiter.set_domain_limit(host, profile.per_host_rps).await;
+        }
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed");
+        let _ = self.limiter.check_rate_limit(host).await;
+        let delay = profile.request_delay();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        permit
+    }
+}
+
+/// Header names of a published rate limit: GitHub's and Reddit's
+/// `X-RateLimit-*`, X's `X-Rate-Limit-*` and the IETF draft's `RateLimit-*`
+const LIMIT_HEADERS: &[&str] = &["x-ratelimit-limit", "x-rate-limit-limit", "ratelimit-limit"];
+const REMAINING_HEADERS: &[&str] = &[
+    "x-ratelimit-remaining",
+    "x-rate-limit-remaining",
+    "ratelimit-remaining",
+];
+const RESET_HEADERS: &[&str] = &["x-ratelimit-reset", "x-rate-limit-reset", "ratelimit-reset"];
+
+/// Resets above this are Unix timestamps (GitHub, X), below it seconds from
+/// now (Reddit, the IETF draft)
+const EPOCH_RESET_THRESHOLD: u64 = 1_000_000_000;
+
+/// Limiter shared by API clients that aren't given their own
+static SHARED_API_LIMITS: Lazy<Arc<ApiRateLimiter>> =
+    Lazy::new(|| Arc::new(ApiRateLimiter::default()));
+
+/// Request budget an API published in a response's headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitWindow {
+    /// Requests allowed per window
+    pub limit: Option<u64>,
+    pub remaining: u64,
+    /// Time until the window resets
+    pub reset_in: Duration,
+}
+
+impl RateLimitWindow {
+    /// Window published by a response with `status` and `headers` (lowercased
+    /// names), received `now` seconds after the Unix epoch
+    ///
+    /// A 403 or 429 with `Retry-After` leaves no requests until it has passed,
+    /// as does a 429 with a reset time.
+    pub fn from_headers(headers: &BTreeMap<String, String>, status: u16, now: u64) -> Option<Self> {
+        let number = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.get(*name))
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|number| number.is_finite() && *number >= 0.0)
+                .map(|number| number as u64)
+        };
+        let limit = number(LIMIT_HEADERS);
+        let reset_in = number(RESET_HEADERS).map(|reset| {
+            if reset > EPOCH_RESET_THRESHOLD {
+                Duration::from_secs(reset.saturating_sub(now))
+            } else {
+                Duration::from_secs(reset)
+            }
+        });
+        let retry_after = headers
+            .get("retry-after")
+            .and_then(|value| parse_retry_after(value, now));
+
+        match (retry_after, number(REMAINING_HEADERS), reset_in) {
+            (Some(retry_after), ..) if matches!(status, 403 | 429) => Some(Self {
+                limit,
+                remaining: 0,
+                reset_in: retry_after,
+            }),
+            (_, Some(remaining), Some(reset_in)) => Some(Self {
+                limit,
+                remaining: if status == 429 { 0 } else { remaining },
+                reset_in,
+            }),
+            (_, None, Some(reset_in)) if status == 429 => Some(Self {
+                limit,
+                remaining: 0,
+                reset_in,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `Retry-After` as seconds or an HTTP date
+fn parse_retry_after(value: &str, now: u64) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .timestamp();
+    Some(Duration::from_secs((at.max(0) as u64).saturating_sub(now)))
+}
+
+#[derive(Debug)]
+struct ApiBudget {
+    /// Requests left in the window, less those already scheduled
+    remaining: u64,
+    reset_at: Instant,
+    /// Earliest start of the next request
+    next_slot: Instant,
+    /// Requests scheduled whose responses haven't published a budget yet
+    pending: u64,
+}
+
+/// Paces requests to APIs by the rate limits they publish
+///
+/// Each response's `X-RateLimit-*` headers (or their `X-Rate-Limit-*` and
+/// `RateLimit-*` variants) set the budget of its host, and the remaining
+/// requests are spread evenly over the time until the window resets instead
+/// of being spent in a burst that ends in 403s and 429s. Once a budget is
+/// used up, requests wait for the reset. Hosts that publish no limits are
+/// not slowed down.
+#[derive(Debug, Default)]
+pub struct ApiRateLimiter {
+    budgets: std::sync::Mutex<HashMap<String, ApiBudget>>,
+}
+
+impl ApiRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limiter shared by every caller, so one process keeps one budget per API
+    pub fn shared() -> Arc<Self> {
+        SHARED_API_LIMITS.clone()
+    }
+
+    /// Wait until a request to `url` fits its host's published budget
+    pub async fn acquire(&self, url: &str) {
+        let wait = self.reserve(&budget_key(url), Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take the budget of `url`'s host from a response's status and headers
+    /// (lowercased names); returns the window they published, if any
+    pub fn record(
+        &self,
+        url: &str,
+        headers: &BTreeMap<String, String>,
+        status: u16,
+    ) -> Option<RateLimitWindow> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let window = RateLimitWindow::from_headers(headers, status, now)?;
+        self.update(&budget_key(url), &window, Instant::now());
+        Some(window)
+    }
+
+    fn update(&self, key: &str, window: &RateLimitWindow, now: Instant) {
+        let mut budgets = self.budgets.lock().unwrap();
+        let (next_slot, pending) = budgets.get(key).map_or((now, 0), |budget| {
+            // This response settles one of the scheduled requests
+            (budget.next_slot.max(now), budget.pending.saturating_sub(1))
+        });
+        budgets.insert(
+            key.to_string(),
+            ApiBudget {
+                // The published count doesn't know of requests still to come
+                remaining: window.remaining.saturating_sub(pending),
+                reset_at: now + window.reset_in,
+                next_slot,
+                pending,
+            },
+        );
+    }
+
+    /// Schedule a request to the host of `key`, returning how long to wait
+    fn reserve(&self, key: &str, now: Instant) -> Duration {
+        let mut budgets = self.budgets.lock().unwrap();
+        let Some(budget) = budgets.get_mut(key) else {
+            return Duration::ZERO;
+        };
+        if now >= budget.reset_at {
+            // The next response publishes the new window
+            budgets.remove(key);
+            return Duration::ZERO;
+        }
+        if budget.remaining == 0 {
+            return budget.reset_at - now;
+        }
+        // What is left of the window after this request's slot is shared by
+        // the remaining requests
+        let slot = budget.next_slot.max(now);
+        let left = budget.reset_at.saturating_duration_since(slot).as_nanos();
+        let interval = Duration::from_nanos((left / budget.remaining as u128) as u64);
+        budget.next_slot = slot + interval;
+        budget.remaining -= 1;
+        budget.pending += 1;
+        slot - now
+    }
+}
+
+/// Budgets are kept per host and port
+fn budget_key(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(url) => format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default().to_ascii_lowercase(),
+            url.port_or_known_default().unwrap_or_default()
+        ),
+        Err(_) => url.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +542,167 @@ mod tests {
         limiter.check_rate_limit("example.com").await.unwrap();
         assert!(start.elapsed() < Duration::from_millis(100));
     }
+
+    #[test]
+    fn test_named_profiles() {
+        for name in RateProfile::NAMES {
+            assert_eq!(&RateProfile::named(name).unwrap().name, name);
+        }
+        assert!(RateProfile::named("Aggressive").unwrap().is_aggressive());
+        assert!(RateProfile::named("reckless").is_none());
+
+        let gentle = RateProfile::gentle();
+        assert!(gentle.concurrency < RateProfile::normal().concurrency);
+        assert_eq!(gentle.retry_delay(1), Duration::from_millis(5000));
+        assert_eq!(gentle.retry_delay(3), Duration::from_millis(20000));
+    }
+
+    #[tokio::test]
+    async fn test_host_throttle_caps_per_host() {
+        let mut profile = RateProfile::aggressive();
+        profile.per_host_concurrency = 1;
+        let throttle = HostThrottle::new(profile).unwrap();
+
+        let held = throttle.acquire("example.com").await;
+        // Other hosts are unaffected by a busy one
+        let other = tokio::time::timeout(
+            Duration::from_millis(100),
+            throttle.acquire("another.com"),
+        )
+        .await;
+        assert!(other.is_ok());
+        let same = tokio::time::timeout(
+            Duration::from_millis(100),
+            throttle.acquire("example.com"),
+        )
+        .await;
+        assert!(same.is_err());
+
+        drop(held);
+        assert!(tokio::time::timeout(
+            Duration::from_millis(100),
+            throttle.acquire("example.com")
+        )
+        .await
+        .is_ok());
+    }
+
+    #[test]
+    fn test_published_rate_limits() {
+        let headers = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        let now = 1_700_000_000;
+        let github = headers(&[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "4990"),
+            ("x-ratelimit-reset", "1700000600"),
+        ]);
+        assert_eq!(
+            RateLimitWindow::from_headers(&github, 200, now),
+            Some(RateLimitWindow {
+                limit: Some(5000),
+                remaining: 4990,
+                reset_in: Duration::from_secs(600),
+            })
+        );
+        // Reddit counts in floats and resets in seconds from now
+        let reddit = headers(&[
+            ("x-ratelimit-remaining", "95.0"),
+            ("x-ratelimit-reset", "240"),
+        ]);
+        let window = RateLimitWindow::from_headers(&reddit, 200, now).unwrap();
+        assert_eq!(
+            (window.remaining, window.reset_in),
+            (95, Duration::from_secs(240))
+        );
+
+        // GitHub's secondary limits are 403s with Retry-After
+        let secondary = headers(&[("x-ratelimit-remaining", "4000"), ("retry-after", "60")]);
+        let window = RateLimitWindow::from_headers(&secondary, 403, now).unwrap();
+        assert_eq!(
+            (window.remaining, window.reset_in),
+            (0, Duration::from_secs(60))
+        );
+        let x = headers(&[
+            ("x-rate-limit-remaining", "12"),
+            ("x-rate-limit-reset", "1700000900"),
+        ]);
+        assert_eq!(
+            RateLimitWindow::from_headers(&x, 429, now).map(|window| window.remaining),
+            Some(0)
+        );
+        assert_eq!(RateLimitWindow::from_headers(&headers(&[]), 429, now), None);
+        assert_eq!(
+            parse_retry_after("Tue, 14 Nov 2023 22:14:20 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_api_budget_is_spread_over_the_window() {
+        let limiter = ApiRateLimiter::new();
+        let start = Instant::now();
+        assert_eq!(limiter.reserve("api.test:443", start), Duration::ZERO);
+        let window = RateLimitWindow {
+            limit: Some(10),
+            remaining: 4,
+            reset_in: Duration::from_secs(8),
+        };
+        limiter.update("api.test:443", &window, start);
+        let waits: Vec<Duration> = (0..4)
+            .map(|_| limiter.reserve("api.test:443", start))
+            .collect();
+        assert_eq!(waits, [0, 2, 4, 6].map(Duration::from_secs).to_vec());
+        // Spent: wait for the reset, then go until a response says otherwise
+        assert_eq!(
+            limiter.reserve("api.test:443", start),
+            Duration::from_secs(8)
+        );
+        assert_eq!(
+            limiter.reserve("api.test:443", start + Duration::from_secs(9)),
+            Duration::ZERO
+        );
+        assert_eq!(limiter.reserve("other.test:443", start), Duration::ZERO);
+        assert_eq!(budget_key("https://API.test/v1?q=1"), "api.test:443");
+    }
+
+    #[test]
+    fn test_api_budget_counts_requests_already_scheduled() {
+        let limiter = ApiRateLimiter::new();
+        let start = Instant::now();
+        let window = |remaining| RateLimitWindow {
+            limit: Some(10),
+            remaining,
+            reset_in: Duration::from_secs(10),
+        };
+        limiter.update("api.test:443", &window(5), start);
+        for _ in 0..3 {
+            limiter.reserve("api.test:443", start);
+        }
+        // The first of the three answers with four left; two of those are
+        // already scheduled
+        limiter.update("api.test:443", &window(4), start);
+        let waits: Vec<Duration> = (0..3)
+            .map(|_| limiter.reserve("api.test:443", start))
+            .collect();
+        assert_eq!(waits, [6, 8, 10].map(Duration::from_secs).to_vec());
+    }
+
+    #[test]
+    fn test_api_budget_survives_huge_remaining_counts() {
+        let limiter = ApiRateLimiter::new();
+        let start = Instant::now();
+        let window = RateLimitWindow {
+            limit: None,
+            remaining: u64::from(u32::MAX) + 1,
+            reset_in: Duration::from_secs(60),
+        };
+        limiter.update("api.test:443", &window, start);
+        assert_eq!(limiter.reserve("api.test:443", start), Duration::ZERO);
+        assert!(limiter.reserve("api.test:443", start) < Duration::from_micros(1));
+    }
 }
@@ -0,0 +1,412 @@
+//! Interaction recipes
+//!
+//! A [`Recipe`] declares how to reach the pages worth scraping on a site
+//! that only shows them after some interaction, such as its internal search:
+//! the page to start on and the [`PageAction`]s to run there, read from TOML.
+//!
+//! ```toml
+//! name = "shop-search"
+//! url = "https://shop.example/search"
+//!
+//! [params]
+//! query = "espresso machine"
+//!
+//! [[steps]]
+//! action = "type"
+//! selector = "input[name=q]"
+//! text = "{query}"
+//!
+//! [[steps]]
+//! action = "select"
+//! selector = "select[name=sort]"
+//! value = "price-asc"
+//!
+//! [[steps]]
+//! action = "click"
+//! selector = "button[type=submit]"
+//!
+//! [[steps]]
+//! action = "wait_for"
+//! selector = ".results"
+//!
+//! [[steps]]
+//! action = "extract"
+//! name = "total"
+//! selector = ".result-count"
+//!
+//! [[steps]]
+//! action = "repeat"
+//! times = 5
+//! while_present = ".results"
+//! steps = [
+//!   { action = "capture" },
+//!   { action = "if_present", selector = "a.next", then = [{ action = "click", selector = "a.next" }] },
+//! ]
+//! ```
+//!
+//! `{name}` in the start URL and in the text typed or the option selected is
+//! filled with the recipe's parameter or a value an earlier `extract` step
+//! kept under that name. [`BrowserInstance::run_recipe`] runs a recipe and
+//! returns the pages its `capture` steps kept along with the extracted values.
+//!
+//! Every run keeps a [`RecipeTrace`] of when each step started, how long it
+//! took, the URL it left the browser on and the error it failed with.
+//! [`BrowserInstance::run_recipe_traced`] adds a screenshot and a DOM snapshot
+//! after each step, and [`RecipeTrace::write_to`] saves it all for debugging a
+//! recipe that broke when the site changed.
+//!
+//! [`BrowserInstance::run_recipe`]: crate::browser::BrowserInstance::run_recipe
+//! [`BrowserInstance::run_recipe_traced`]: crate::browser::BrowserInstance::run_recipe_traced
+
+use crate::browser::{PageAction, RecipeOutcome};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Steps run in a browser to reach the pages to scrape, see the
+/// [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    pub name: Option<String>,
+    /// Page the recipe starts on
+    pub url: String,
+    /// Parameters and their default values
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    pub steps: Vec<PageAction>,
+}
+
+impl Recipe {
+    /// Parse a TOML recipe, checking that every `{name}` it uses is filled
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let recipe: Recipe = toml::from_str(toml).context("Invalid recipe")?;
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recipe {}", path.display()))?;
+        Self::from_toml(&toml).with_context(|| format!("In {}", path.display()))
+    }
+
+    /// Set parameter `name`, e.g. the query of a search recipe
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Check that every `{name}` refers to a parameter or to a value an
+    /// earlier step extracts
+    pub fn validate(&self) -> Result<()> {
+        let mut known: HashSet<&str> = self.params.keys().map(String::as_str).collect();
+        check_placeholders(&self.url, &known)?;
+        check_steps(&self.steps, &mut known)
+    }
+}
+
+fn check_steps<'a>(steps: &'a [PageAction], known: &mut HashSet<&'a str>) -> Result<()> {
+    for step in steps {
+        match step {
+            PageAction::Type { text: template, .. }
+            | PageAction::Select {
+                value: template, ..
+            } => check_placeholders(template, known)?,
+            PageAction::Extract { name, .. } => {
+                known.insert(name);
+            }
+            PageAction::IfPresent {
+                then, otherwise, ..
+            } => {
+                // Values only one branch extracts may be missing afterwards
+                check_steps(then, &mut known.clone())?;
+                check_steps(otherwise, &mut known.clone())?;
+            }
+            PageAction::Repeat { steps, .. } => check_steps(steps, known)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_placeholders(template: &str, known: &HashSet<&str>) -> Result<()> {
+    for captures in placeholder_regex().captures_iter(template) {
+        if !known.contains(&captures[1]) {
+            bail!(
+                "`{{{}}}` in \"{}\" is neither a parameter nor extracted by an earlier step",
+                &captures[1],
+                template
+            );
+        }
+    }
+    Ok(())
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{([A-Za-z0-9_]+)\}").unwrap())
+}
+
+/// `template` with every `{name}` in `values` replaced by its value
+pub fn fill(template: &str, values: &BTreeMap<String, String>) -> String {
+    placeholder_regex()
+        .replace_all(template, |captures: &regex::Captures| {
+            values
+                .get(&captures[1])
+                .cloned()
+                .unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+/// What to keep after each recipe step besides its timing
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceOptions {
+    /// A PNG screenshot of the page
+    pub screenshots: bool,
+    /// The page's HTML as the browser has it
+    pub dom: bool,
+}
+
+impl TraceOptions {
+    /// Screenshots and DOM snapshots
+    pub fn full() -> Self {
+        Self {
+            screenshots: true,
+            dom: true,
+        }
+    }
+}
+
+/// The steps of a recipe run in the order they started; steps nested in
+/// `if_present` and `repeat` come right after the step containing them
+#[derive(Debug, Clone, Default)]
+pub struct RecipeTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+/// One step of a [`RecipeTrace`]
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    /// Position in the recipe: `0` opens the start page, `3` is the third
+    /// step, `3.1` the first step of its branch and `3[2].1` the first of
+    /// its second round
+    pub step: String,
+    pub action: String,
+    /// Milliseconds into the run the step started at
+    pub started_ms: u64,
+    /// Milliseconds the step took, including the steps nested in it
+    pub duration_ms: u64,
+    /// Page the browser was on once the step ended
+    pub url: Option<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub screenshot: Option<Vec<u8>>,
+    #[serde(skip)]
+    pub dom: Option<String>,
+}
+
+impl TraceStep {
+    pub fn new(step: &str, action: &str, started_ms: u64) -> Self {
+        Self {
+            step: step.to_string(),
+            action: action.to_string(),
+            started_ms,
+            duration_ms: 0,
+            url: None,
+            error: None,
+            screenshot: None,
+            dom: None,
+        }
+    }
+}
+
+impl RecipeTrace {
+    /// The step that failed, if one did
+    pub fn failed_step(&self) -> Option<&TraceStep> {
+        // Steps containing the failed one fail with it, but come before it
+        self.steps.iter().rev().find(|step| step.error.is_some())
+    }
+
+    /// Write the trace to `dir` as `trace.json`, next to each step's
+    /// screenshot and DOM snapshot (`003-click.png`, `003-click.html`), and
+    /// return the path of `trace.json`
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create trace directory {}", dir.display()))?;
+
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for (i, step) in self.steps.iter().enumerate() {
+            let mut entry = serde_json::to_value(step)?;
+            let stem = format!("{:03}-{}", i, step.action);
+            if let Some(screenshot) = &step.screenshot {
+                let file = format!("{}.png", stem);
+                fs::write(dir.join(&file), screenshot)?;
+                entry["screenshot"] = file.into();
+            }
+            if let Some(dom) = &step.dom {
+                let file = format!("{}.html", stem);
+                fs::write(dir.join(&file), dom)?;
+                entry["dom"] = file.into();
+            }
+            steps.push(entry);
+        }
+
+        let path = dir.join("trace.json");
+        let trace = serde_json::json!({ "steps": steps });
+        fs::write(&path, serde_json::to_string_pretty(&trace)?)?;
+        Ok(path)
+    }
+}
+
+/// A recipe step failed; `outcome` holds what the steps before it kept, and
+/// its trace ends with the failed step
+#[derive(Debug)]
+pub struct RecipeFailure {
+    pub error: anyhow::Error,
+    pub outcome: RecipeOutcome,
+}
+
+impl fmt::Display for RecipeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for RecipeFailure {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH: &str = r##"
+        url = "https://shop.example/search?lang={lang}"
+
+        [params]
+        lang = "en"
+        query = "kettle"
+
+        [[steps]]
+        action = "type"
+        selector = "input[name=q]"
+        text = "{query}"
+
+        [[steps]]
+        action = "check"
+        selector = "#in-stock"
+
+        [[steps]]
+        action = "wait_for"
+        selector = ".results"
+
+        [[steps]]
+        action = "extract"
+        name = "total"
+        selector = ".count"
+
+        [[steps]]
+        action = "repeat"
+        times = 3
+        while_present = "a.next"
+        steps = [
+            { action = "capture" },
+            { action = "if_present", selector = "a.next", then = [{ action = "click", selector = "a.next" }] },
+        ]
+    "##;
+
+    #[test]
+    fn test_parse_recipe() {
+        let recipe = Recipe::from_toml(SEARCH)
+            .unwrap()
+            .with_param("query", "teapot");
+        assert_eq!(recipe.params["query"], "teapot");
+        assert_eq!(
+            recipe.steps[1],
+            PageAction::Check {
+                selector: "#in-stock".to_string(),
+                checked: true,
+            }
+        );
+        assert_eq!(
+            recipe.steps[2],
+            PageAction::WaitFor {
+                selector: ".results".to_string(),
+                timeout_ms: 10_000,
+            }
+        );
+        let PageAction::Repeat { steps, .. } = &recipe.steps[4] else {
+            panic!("expected a repeat step, got {:?}", recipe.steps[4]);
+        };
+        assert_eq!(steps[0], PageAction::Capture);
+        assert!(
+            matches!(&steps[1], PageAction::IfPresent { then, otherwise, .. }
+            if then.len() == 1 && otherwise.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_placeholders_must_be_filled() {
+        let unknown = r##"
+            url = "https://shop.example/"
+            [[steps]]
+            action = "type"
+            selector = "#q"
+            text = "{query}"
+        "##;
+        let error = Recipe::from_toml(unknown).unwrap_err();
+        assert!(format!("{:#}", error).contains("`{query}`"));
+
+        // Values extracted in one branch only aren't there after it
+        let branch_only = r##"
+            url = "https://shop.example/"
+            [[steps]]
+            action = "if_present"
+            selector = "#promo"
+            then = [{ action = "extract", name = "code", selector = "#promo" }]
+            [[steps]]
+            action = "type"
+            selector = "#coupon"
+            text = "{code}"
+        "##;
+        assert!(Recipe::from_toml(branch_only).is_err());
+
+        let values = BTreeMap::from([("query".to_string(), "tea pot".to_string())]);
+        assert_eq!(fill("q={query}&p={page}", &values), "q=tea pot&p={page}");
+    }
+
+    #[test]
+    fn test_trace_written_with_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut open = TraceStep::new("0", "open", 0);
+        open.duration_ms = 2100;
+        open.url = Some("https://shop.example/search".to_string());
+        let mut click = TraceStep::new("1", "click", 2100);
+        click.error = Some("No element matches `#go`".to_string());
+        click.screenshot = Some(vec![0x89, b'P', b'N', b'G']);
+        click.dom = Some("<html></html>".to_string());
+        let trace = RecipeTrace {
+            steps: vec![open, click],
+        };
+        assert_eq!(trace.failed_step().unwrap().step, "1");
+
+        let path = trace.write_to(dir.path().join("trace")).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let steps = written["steps"].as_array().unwrap();
+        assert_eq!(steps[0]["duration_ms"], 2100);
+        assert!(steps[0].get("screenshot").is_none());
+        assert_eq!(steps[1]["screenshot"], "001-click.png");
+        assert_eq!(steps[1]["error"], "No element matches `#go`");
+        let dom = fs::read_to_string(dir.path().join("trace/001-click.html")).unwrap();
+        assert_eq!(dom, "<html></html>");
+    }
+}
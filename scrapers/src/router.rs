@@ -0,0 +1,239 @@
+//! URL pattern routing
+//!
+//! A [`UrlRouter`] maps URLs to targets (scrapers, extraction rule sets,
+//! policies) through patterns matched against the URL's lowercase host and
+//! its path, compiled into one [`RegexSet`]:
+//!
+//! - `instagram.com` — the host, with any path
+//! - `*.instagram.com` — subdomains of the host; `*` matches anything but `/`
+//! - `shop.example.com/products/*` — one path segment below `/products/`
+//! - `docs.example.com/**/api/*` — `**` matches anything, `/` included
+//! - `re:^([a-z]+\.)?ebay\.[a-z.]+/itm/` — a regular expression over the
+//!   same `host/path` string
+//!
+//! The query string and fragment are not part of the matched string. When
+//! several patterns match, the highest priority wins, then the pattern added
+//! first.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexSet};
+
+/// Prefix marking a pattern as a regular expression instead of a glob
+pub const REGEX_PREFIX: &str = "re:";
+
+/// `host/path` string of `url` that patterns are matched against
+fn subject(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    Some(format!("{}{}", host, parsed.path()))
+}
+
+/// Translate a glob pattern into an anchored regular expression
+fn glob_to_regex(glob: &str) -> String {
+    // Patterns without a path match the host with any path
+    let (host, path) = match glob.find('/') {
+        Some(slash) => (&glob[..slash], Some(&glob[slash..])),
+        None => (glob, None),
+    };
+    let mut regex = String::from("^");
+    let mut translate = |part: &str| {
+        let mut chars = part.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    regex.push_str(".*");
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+    };
+    translate(&host.to_ascii_lowercase());
+    match path {
+        Some(path) => translate(path),
+        None => regex.push_str("(/.*)?"),
+    }
+    regex.push('$');
+    regex
+}
+
+/// A compiled URL pattern
+#[derive(Debug, Clone)]
+pub struct UrlPattern {
+    source: String,
+    regex: Regex,
+}
+
+impl UrlPattern {
+    /// Compile a glob, or a regular expression prefixed with `re:`
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = match pattern.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => regex.to_string(),
+            None => glob_to_regex(pattern),
+        };
+        Ok(Self {
+            source: pattern.to_string(),
+            regex: Regex::new(&regex)
+                .with_context(|| format!("Invalid URL pattern '{}'", pattern))?,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, url: &str) -> bool {
+        subject(url).is_some_and(|subject| self.regex.is_match(&subject))
+    }
+}
+
+/// One entry of a [`UrlRouter`]
+#[derive(Debug, Clone)]
+pub struct Route<T> {
+    pub pattern: UrlPattern,
+    pub priority: i32,
+    pub target: T,
+}
+
+/// Maps URLs to targets by pattern, highest priority first
+#[derive(Debug, Clone)]
+pub struct UrlRouter<T> {
+    routes: Vec<Route<T>>,
+    set: RegexSet,
+}
+
+impl<T> Default for UrlRouter<T> {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            set: RegexSet::empty(),
+        }
+    }
+}
+
+impl<T> UrlRouter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Router sending every URL matching one of `patterns` to `target`
+    pub fn from_patterns(patterns: &[&str], target: T) -> Result<Self>
+    where
+        T: Clone,
+    {
+        let mut router = Self::new();
+        for pattern in patterns {
+            router.add(pattern, 0, target.clone())?;
+        }
+        Ok(router)
+    }
+
+    /// Route URLs matching `pattern` to `target`
+    pub fn add(&mut self, pattern: &str, priority: i32, target: T) -> Result<()> {
+        let pattern = UrlPattern::new(pattern)?;
+        let set = RegexSet::new(
+            self.routes
+                .iter()
+                .map(|route| route.pattern.regex.as_str())
+                .chain(std::iter::once(pattern.regex.as_str())),
+        )?;
+        self.routes.push(Route {
+            pattern,
+            priority,
+            target,
+        });
+        self.set = set;
+        Ok(())
+    }
+
+    /// Routes matching `url`, highest priority first, then in the order added
+    pub fn matches(&self, url: &str) -> Vec<&Route<T>> {
+        let Some(subject) = subject(url) else {
+            return Vec::new();
+        };
+        let mut matched: Vec<&Route<T>> = self
+            .set
+            .matches(&subject)
+            .into_iter()
+            .map(|index| &self.routes[index])
+            .collect();
+        // Stable, so equal priorities keep the order they were added in
+        matched.sort_by_key(|route| std::cmp::Reverse(route.priority));
+        matched
+    }
+
+    /// Target of the best route matching `url`
+    pub fn route(&self, url: &str) -> Option<&T> {
+        self.matches(url).first().map(|route| &route.target)
+    }
+
+    pub fn is_match(&self, url: &str) -> bool {
+        subject(url).is_some_and(|subject| self.set.is_match(&subject))
+    }
+
+    pub fn routes(&self) -> &[Route<T>] {
+        &self.routes
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_patterns() {
+        let host = UrlPattern::new("Instagram.com").unwrap();
+        assert!(host.is_match("https://instagram.com/"));
+        assert!(host.is_match("https://INSTAGRAM.com/p/abc?igshid=1"));
+        assert!(!host.is_match("https://www.instagram.com/"));
+        assert!(!host.is_match("https://instagram.com.evil.test/"));
+
+        let subdomains = UrlPattern::new("*.instagram.com").unwrap();
+        assert!(subdomains.is_match("https://www.instagram.com/reel/1"));
+        assert!(!subdomains.is_match("https://instagram.com/"));
+
+        let segment = UrlPattern::new("shop.test/products/*").unwrap();
+        assert!(segment.is_match("https://shop.test/products/blue-mug"));
+        assert!(!segment.is_match("https://shop.test/products/mugs/blue"));
+        let deep = UrlPattern::new("docs.test/**/api.html").unwrap();
+        assert!(deep.is_match("https://docs.test/v2/guide/api.html"));
+
+        let regex = UrlPattern::new(r"re:^(www\.)?ebay\.[a-z.]+/itm/\d+$").unwrap();
+        assert!(regex.is_match("https://www.ebay.co.uk/itm/1234"));
+        assert!(!regex.is_match("https://www.ebay.co.uk/sch/mugs"));
+        assert!(UrlPattern::new("re:(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_priority_then_insertion_order() {
+        let mut router = UrlRouter::new();
+        router.add("**", 0, "fallback").unwrap();
+        router.add("shop.test", 5, "shop").unwrap();
+        router.add("shop.test/products/*", 10, "product").unwrap();
+        router.add("shop.test/products/*", 10, "later").unwrap();
+
+        assert_eq!(
+            router.route("https://shop.test/products/mug"),
+            Some(&"product")
+        );
+        assert_eq!(router.route("https://shop.test/cart"), Some(&"shop"));
+        assert_eq!(router.route("https://other.test/"), Some(&"fallback"));
+        let all: Vec<&str> = router
+            .matches("https://shop.test/products/mug")
+            .iter()
+            .map(|route| route.target)
+            .collect();
+        assert_eq!(all, vec!["product", "later", "shop", "fallback"]);
+        assert_eq!(router.route("not a url"), None);
+    }
+}
@@ -0,0 +1,327 @@
+//! Safety filters for fetched responses
+//!
+//! Blind crawls follow links nobody has looked at, and some of them lead to
+//! installers, disk images and worse. [`SafetyFilter`] sniffs the magic bytes
+//! of each fetched body, whatever its `Content-Type` claims, and flags three
+//! [`Hazard`]s before the page is extracted, stored or exported:
+//! - executables: Windows PE, ELF, Mach-O, Java class files and WebAssembly
+//! - binaries, sniffed by magic bytes or NUL bytes, over a size limit
+//! - suspected malware: the EICAR test signature, or an executable served
+//!   as a document, page or image
+//!
+//! Each hazard has a [`SafetyAction`]: the response is rejected, quarantined
+//! for review, or allowed through. The [`SafetyVerdict`] records which
+//! hazard it was and why.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest binary body let through by default
+pub const DEFAULT_MAX_BINARY_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Bytes looked at for NUL bytes and the EICAR signature
+const SNIFF_BYTES: usize = 8192;
+
+const EICAR: &[u8] = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+/// Media types executables are expected to be served as
+const EXECUTABLE_TYPES: &[&str] = &[
+    "application/octet-stream",
+    "application/x-msdownload",
+    "application/x-msdos-program",
+    "application/x-dosexec",
+    "application/x-executable",
+    "application/x-elf",
+    "application/x-sharedlib",
+    "application/x-mach-binary",
+    "application/vnd.microsoft.portable-executable",
+    "application/java-vm",
+    "application/x-java-applet",
+    "application/wasm",
+    "binary/octet-stream",
+];
+
+/// What is wrong with a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hazard {
+    Executable,
+    OversizedBinary,
+    Malware,
+}
+
+impl Hazard {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Hazard::Executable => "executable",
+            Hazard::OversizedBinary => "oversized_binary",
+            Hazard::Malware => "malware",
+        }
+    }
+}
+
+/// What happens to a response with a hazard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyAction {
+    /// Let it through to extraction
+    Allow,
+    /// Drop it
+    #[default]
+    Reject,
+    /// Drop it from the crawl, but keep the body aside for review
+    Quarantine,
+}
+
+impl SafetyAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SafetyAction::Allow => "allow",
+            SafetyAction::Reject => "reject",
+            SafetyAction::Quarantine => "quarantine",
+        }
+    }
+}
+
+/// Why a response was stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetyVerdict {
+    pub hazard: Hazard,
+    pub action: SafetyAction,
+    /// What was found, e.g. `Windows executable served as text/html`
+    pub reason: String,
+}
+
+/// File format a body's magic bytes give away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Magic {
+    pub format: &'static str,
+    pub executable: bool,
+}
+
+impl Magic {
+    const fn executable(format: &'static str) -> Self {
+        Self {
+            format,
+            executable: true,
+        }
+    }
+
+    const fn binary(format: &'static str) -> Self {
+        Self {
+            format,
+            executable: false,
+        }
+    }
+}
+
+/// The format of `body` by its leading bytes, for formats worth telling apart
+pub fn sniff(body: &[u8]) -> Option<Magic> {
+    let magic = match body {
+        [b'M', b'Z', ..] if is_portable_executable(body) => Magic::executable("Windows executable"),
+        [0x7f, b'E', b'L', b'F', ..] => Magic::executable("ELF executable"),
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..] | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..] => {
+            Magic::executable("Mach-O executable")
+        }
+        // Fat Mach-O binaries and Java classes share their magic number
+        [0xca, 0xfe, 0xba, 0xbe, ..] => Magic::executable("Mach-O universal binary or Java class"),
+        [0x00, b'a', b's', b'm', ..] => Magic::executable("WebAssembly module"),
+        [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1, ..] => {
+            Magic::binary("OLE compound file (legacy Office document or MSI)")
+        }
+        [b'%', b'P', b'D', b'F', b'-', ..] => Magic::binary("PDF document"),
+        [b'P', b'K', 0x03, 0x04, ..] => Magic::binary("ZIP archive"),
+        [0x1f, 0x8b, ..] => Magic::binary("gzip archive"),
+        [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, ..] => Magic::binary("7z archive"),
+        [b'R', b'a', b'r', b'!', 0x1a, 0x07, ..] => Magic::binary("RAR archive"),
+        [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Magic::binary("xz archive"),
+        [0x89, b'P', b'N', b'G', ..] => Magic::binary("PNG image"),
+        [0xff, 0xd8, 0xff, ..] => Magic::binary("JPEG image"),
+        [b'G', b'I', b'F', b'8', ..] => Magic::binary("GIF image"),
+        [b'R', b'I', b'F', b'F', ..] => Magic::binary("RIFF media"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Magic::binary("MP4 media"),
+        _ => return None,
+    };
+    Some(magic)
+}
+
+/// Whether an `MZ` body has the PE header its DOS stub points to, which
+/// text that happens to start with `MZ` won't
+fn is_portable_executable(body: &[u8]) -> bool {
+    let Some(offset) = body.get(0x3c..0x40) else {
+        return false;
+    };
+    let offset = u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]]) as usize;
+    body.get(offset..offset + 4) == Some(b"PE\0\0".as_slice())
+}
+
+/// Sniffs fetched bodies for hazards; see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyFilter {
+    /// What happens to executables
+    pub executables: SafetyAction,
+    /// What happens to binaries over `max_binary_bytes`
+    pub oversized_binaries: SafetyAction,
+    /// What happens to suspected malware
+    pub malware: SafetyAction,
+    pub max_binary_bytes: u64,
+}
+
+impl Default for SafetyFilter {
+    fn default() -> Self {
+        Self {
+            executables: SafetyAction::Reject,
+            oversized_binaries: SafetyAction::Reject,
+            malware: SafetyAction::Quarantine,
+            max_binary_bytes: DEFAULT_MAX_BINARY_BYTES,
+        }
+    }
+}
+
+impl SafetyFilter {
+    /// A filter that lets everything through
+    pub fn disabled() -> Self {
+        Self {
+            executables: SafetyAction::Allow,
+            oversized_binaries: SafetyAction::Allow,
+            malware: SafetyAction::Allow,
+            max_binary_bytes: u64::MAX,
+        }
+    }
+
+    /// The verdict on `body`, served as `content_type`; `None` lets it through
+    ///
+    /// Suspected malware is checked first, then executables, then size, and
+    /// the first hazard whose action isn't [`SafetyAction::Allow`] decides.
+    pub fn check(&self, content_type: Option<&str>, body: &[u8]) -> Option<SafetyVerdict> {
+        let magic = sniff(body);
+        let head = &body[..body.len().min(SNIFF_BYTES)];
+        let media_type = content_type
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .filter(|value| !value.is_empty());
+
+        let mut hazards = Vec::new();
+        if contains(head, EICAR) {
+            hazards.push((
+                Hazard::Malware,
+                "EICAR antivirus test signature".to_string(),
+            ));
+        }
+        if let Some(magic) = magic.filter(|magic| magic.executable) {
+            match &media_type {
+                Some(declared) if !EXECUTABLE_TYPES.contains(&declared.as_str()) => hazards.push((
+                    Hazard::Malware,
+                    format!("{} served as {}", magic.format, declared),
+                )),
+                _ => hazards.push((Hazard::Executable, magic.format.to_string())),
+            }
+        }
+        let binary = magic.is_some() || head.contains(&0);
+        if binary && body.len() as u64 > self.max_binary_bytes {
+            let format = magic.map_or("binary body", |magic| magic.format);
+            hazards.push((
+                Hazard::OversizedBinary,
+                format!(
+                    "{} of {} bytes, over the {}-byte limit",
+                    format,
+                    body.len(),
+                    self.max_binary_bytes
+                ),
+            ));
+        }
+
+        hazards.into_iter().find_map(|(hazard, reason)| {
+            let action = self.action(hazard);
+            (action != SafetyAction::Allow).then_some(SafetyVerdict {
+                hazard,
+                action,
+                reason,
+            })
+        })
+    }
+
+    pub fn action(&self, hazard: Hazard) -> SafetyAction {
+        match hazard {
+            Hazard::Executable => self.executables,
+            Hazard::OversizedBinary => self.oversized_binaries,
+            Hazard::Malware => self.malware,
+        }
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smallest body with a DOS stub pointing at a PE header
+    fn pe_body() -> Vec<u8> {
+        let mut body = vec![0u8; 0x48];
+        body[..2].copy_from_slice(b"MZ");
+        body[0x3c] = 0x40;
+        body[0x40..0x44].copy_from_slice(b"PE\0\0");
+        body
+    }
+
+    #[test]
+    fn test_sniffs_executables_by_magic_bytes() {
+        assert_eq!(sniff(&pe_body()).unwrap().format, "Windows executable");
+        assert!(sniff(b"\x7fELF\x02\x01\x01").unwrap().executable);
+        assert!(sniff(b"\xcf\xfa\xed\xfe\x07\x00").unwrap().executable);
+        assert!(!sniff(b"%PDF-1.7\n").unwrap().executable);
+        // Text starting with MZ has no PE header behind it
+        assert_eq!(sniff(b"MZ is a postcode area in England"), None);
+        assert_eq!(sniff(b"<!doctype html><html></html>"), None);
+    }
+
+    #[test]
+    fn test_verdicts_follow_configured_actions() {
+        let filter = SafetyFilter::default();
+        let html = b"<html><body>Fixture Article</body></html>";
+        assert_eq!(filter.check(Some("text/html; charset=utf-8"), html), None);
+
+        let installer = filter
+            .check(Some("application/x-msdownload"), &pe_body())
+            .unwrap();
+        assert_eq!(installer.hazard, Hazard::Executable);
+        assert_eq!(installer.action, SafetyAction::Reject);
+
+        let disguised = filter.check(Some("image/jpeg"), &pe_body()).unwrap();
+        assert_eq!(disguised.hazard, Hazard::Malware);
+        assert_eq!(disguised.action, SafetyAction::Quarantine);
+        assert_eq!(disguised.reason, "Windows executable served as image/jpeg");
+
+        let mut eicar = b"<pre>".to_vec();
+        eicar.extend_from_slice(EICAR);
+        assert_eq!(
+            filter.check(Some("text/plain"), &eicar).unwrap().hazard,
+            Hazard::Malware
+        );
+
+        let filter: SafetyFilter = toml::from_str(
+            r#"
+            executables = "allow"
+            max_binary_bytes = 100
+            "#,
+        )
+        .unwrap();
+        assert_eq!(filter.check(None, &pe_body()), None);
+        let mut archive = b"PK\x03\x04".to_vec();
+        archive.resize(128, 0);
+        let oversized = filter.check(Some("application/zip"), &archive).unwrap();
+        assert_eq!(oversized.hazard, Hazard::OversizedBinary);
+        assert_eq!(
+            oversized.reason,
+            "ZIP archive of 128 bytes, over the 100-byte limit"
+        );
+        // Large text is not a binary
+        assert_eq!(filter.check(None, &[b'a'; 256]), None);
+        assert_eq!(SafetyFilter::disabled().check(None, &eicar), None);
+    }
+}
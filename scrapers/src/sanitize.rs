@@ -0,0 +1,398 @@
+//! Safe-to-serve HTML snapshots
+//!
+//! Stored pages are kept exactly as fetched, scripts and all. Opening one in a
+//! browser would run the site's third-party JavaScript, fire its tracking
+//! pixels and follow its meta refreshes. [`SnapshotSanitizer`] rewrites a page
+//! into a snapshot that only renders: scripts, inline event handlers,
+//! `javascript:` URLs, frames, embeds and tracking pixels are removed, while
+//! the markup, styles and images that make up the layout are kept as they
+//! are. `<noscript>` fallbacks are unwrapped, since nothing runs in the
+//! snapshot, and a [`SNAPSHOT_CSP`] meta tag is added to `<head>` in case
+//! anything slipped through.
+
+use anyhow::{anyhow, Result};
+use lol_html::{element, html_content::ContentType, rewrite_str, text, RewriteStrSettings};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+
+static CHAR_REF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)&#(x[0-9a-f]+|[0-9]+);?|&(colon|tab|newline);").unwrap());
+
+/// Content security policy snapshots are served with
+pub const SNAPSHOT_CSP: &str =
+    "script-src 'none'; object-src 'none'; frame-src 'none'; worker-src 'none'";
+
+/// Third-party beacons dropped from snapshots, as a host (matching its
+/// subdomains too) and an optional path prefix
+const TRACKERS: &[&str] = &[
+    "facebook.com/tr",
+    "google-analytics.com",
+    "googletagmanager.com",
+    "doubleclick.net",
+    "googleadservices.com",
+    "analytics.twitter.com",
+    "t.co/i/adsct",
+    "bat.bing.com",
+    "px.ads.linkedin.com",
+    "ct.pinterest.com",
+    "analytics.tiktok.com",
+    "scorecardresearch.com",
+    "quantserve.com",
+    "mc.yandex.ru",
+    "pixel.wp.com",
+];
+
+/// Attributes holding URLs a `javascript:` scheme would run from
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "xlink:href",
+    "data",
+    "poster",
+    "background",
+    "lowsrc",
+];
+
+/// What a snapshot lost to sanitization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizeReport {
+    /// `<script>` elements and script preloads
+    pub scripts: usize,
+    /// `on*` handlers, `javascript:` URLs, `srcdoc`, `ping` and meta refreshes
+    pub handlers: usize,
+    /// Tracking pixels and beacons
+    pub trackers: usize,
+    /// `<iframe>`, `<frame>`, `<object>` and `<embed>` elements
+    pub embeds: usize,
+}
+
+impl SanitizeReport {
+    pub fn total(&self) -> usize {
+        self.scripts + self.handlers + self.trackers + self.embeds
+    }
+
+    fn add(&mut self, other: &SanitizeReport) {
+        self.scripts += other.scripts;
+        self.handlers += other.handlers;
+        self.trackers += other.trackers;
+        self.embeds += other.embeds;
+    }
+}
+
+/// A page rewritten to render without running anything
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizedSnapshot {
+    pub html: String,
+    pub removed: SanitizeReport,
+}
+
+/// Rewrites stored pages into safe-to-serve snapshots
+#[derive(Debug, Clone)]
+pub struct SnapshotSanitizer {
+    trackers: Vec<String>,
+}
+
+impl Default for SnapshotSanitizer {
+    fn default() -> Self {
+        Self {
+            trackers: TRACKERS.iter().map(|tracker| tracker.to_string()).collect(),
+        }
+    }
+}
+
+impl SnapshotSanitizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also drop beacons to `trackers`, given as `host` or `host/path-prefix`
+    pub fn with_trackers(mut self, trackers: impl IntoIterator<Item = String>) -> Self {
+        self.trackers.extend(trackers);
+        self
+    }
+
+    /// Rewrite `html` into a snapshot
+    pub fn sanitize(&self, html: &str) -> Result<SanitizedSnapshot> {
+        let removed = RefCell::new(SanitizeReport::default());
+        let noscript = RefCell::new(String::new());
+        let fallback_removed = RefCell::new(SanitizeReport::default());
+
+        let handlers = vec![
+            element!("script", |element| {
+                element.remove();
+                removed.borrow_mut().scripts += 1;
+                Ok(())
+            }),
+            element!(
+                "iframe, frame, frameset, object, embed, applet",
+                |element| {
+                    element.remove();
+                    removed.borrow_mut().embeds += 1;
+                    Ok(())
+                }
+            ),
+            element!("link[rel]", |element| {
+                if is_script_link(element.get_attribute("rel"), element.get_attribute("as")) {
+                    element.remove();
+                    removed.borrow_mut().scripts += 1;
+                } else if element
+                    .get_attribute("href")
+                    .is_some_and(|href| self.is_tracker(&href))
+                {
+                    element.remove();
+                    removed.borrow_mut().trackers += 1;
+                }
+                Ok(())
+            }),
+            element!("meta[http-equiv]", |element| {
+                let refresh = element
+                    .get_attribute("http-equiv")
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"));
+                if refresh {
+                    element.remove();
+                    removed.borrow_mut().handlers += 1;
+                }
+                Ok(())
+            }),
+            element!("img", |element| {
+                let pixel = is_pixel(element.get_attribute("width"))
+                    && is_pixel(element.get_attribute("height"));
+                let tracker = element
+                    .get_attribute("src")
+                    .is_some_and(|src| self.is_tracker(&src));
+                if pixel || tracker {
+                    element.remove();
+                    removed.borrow_mut().trackers += 1;
+                }
+                Ok(())
+            }),
+            element!("*", |element| {
+                if element.removed() {
+                    return Ok(());
+                }
+                let unsafe_attributes: Vec<String> = element
+                    .attributes()
+                    .iter()
+                    .filter(|attribute| is_unsafe_attribute(&attribute.name(), &attribute.value()))
+                    .map(|attribute| attribute.name())
+                    .collect();
+                for name in &unsafe_attributes {
+                    element.remove_attribute(name);
+                }
+                removed.borrow_mut().handlers += unsafe_attributes.len();
+                Ok(())
+            }),
+            element!("noscript", |element| {
+                element.remove_and_keep_content();
+                Ok(())
+            }),
+            // Fallback markup is parsed as text, so it is sanitized on its own
+            text!("noscript", |chunk| {
+                noscript.borrow_mut().push_str(chunk.as_str());
+                if chunk.last_in_text_node() {
+                    match self.sanitize(&noscript.take()) {
+                        Ok(fallback) => {
+                            chunk.replace(&fallback.html, ContentType::Html);
+                            fallback_removed.borrow_mut().add(&fallback.removed);
+                        }
+                        Err(_) => chunk.remove(),
+                    }
+                } else {
+                    chunk.remove();
+                }
+                Ok(())
+            }),
+            element!("head", |element| {
+                element.prepend(
+                    &format!(
+                        r#"<meta http-equiv="Content-Security-Policy" content="{}">"#,
+                        SNAPSHOT_CSP
+                    ),
+                    ContentType::Html,
+                );
+                Ok(())
+            }),
+        ];
+
+        let html = rewrite_str(
+            html,
+            RewriteStrSettings {
+                element_content_handlers: handlers,
+                ..RewriteStrSettings::default()
+            },
+        )
+        .map_err(|e| anyhow!("Failed to sanitize snapshot: {}", e))?;
+        let mut removed = removed.into_inner();
+        removed.add(&fallback_removed.into_inner());
+        Ok(SanitizedSnapshot { html, removed })
+    }
+
+    /// Whether `url` points at one of the tracker endpoints
+    pub fn is_tracker(&self, url: &str) -> bool {
+        let url = url.trim();
+        let url = match url.strip_prefix("//") {
+            Some(rest) => format!("https://{}", rest),
+            None => url.to_string(),
+        };
+        let Ok(url) = url::Url::parse(&url) else {
+            return false;
+        };
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        self.trackers.iter().any(|tracker| {
+            let (tracker_host, path) = match tracker.split_once('/') {
+                Some((tracker_host, path)) => (tracker_host, Some(path)),
+                None => (tracker.as_str(), None),
+            };
+            let host_matches = host == tracker_host
+                || host
+                    .strip_suffix(tracker_host)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'));
+            let path_matches = path.map_or(true, |path| {
+                url.path().trim_start_matches('/').starts_with(path)
+            });
+            host_matches && path_matches
+        })
+    }
+}
+
+/// Whether a `width` or `height` is at most one pixel
+fn is_pixel(size: Option<String>) -> bool {
+    size.and_then(|size| size.trim().trim_end_matches("px").parse::<f64>().ok())
+        .is_some_and(|size| size <= 1.0)
+}
+
+/// Whether a `<link>` preloads or imports a script
+fn is_script_link(rel: Option<String>, kind: Option<String>) -> bool {
+    let rel = rel.unwrap_or_default().to_ascii_lowercase();
+    let kind = kind.unwrap_or_default().to_ascii_lowercase();
+    rel.split_whitespace().any(|rel| match rel {
+        "modulepreload" | "import" | "serviceworker" => true,
+        "preload" | "prefetch" => matches!(kind.trim(), "script" | "worker" | "sharedworker"),
+        _ => false,
+    })
+}
+
+fn is_unsafe_attribute(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("on") || matches!(name.as_str(), "srcdoc" | "ping") {
+        return true;
+    }
+    if name == "style" {
+        let style = value.to_ascii_lowercase();
+        return style.contains("expression(") || style.contains("javascript:");
+    }
+    URL_ATTRIBUTES.contains(&name.as_str()) && is_script_url(value)
+}
+
+/// Whether following `url` would run code, e.g. `javascript:` or an HTML
+/// `data:` URL
+fn is_script_url(url: &str) -> bool {
+    // Attribute values come undecoded, and character references can spell
+    // out the scheme
+    let url = CHAR_REF_REGEX.replace_all(url, |captures: &Captures| {
+        let decoded = match (captures.get(1), captures.get(2)) {
+            (Some(code), _) => match code.as_str().strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => code.as_str().parse().ok(),
+            }
+            .and_then(char::from_u32),
+            (_, Some(name)) => match name.as_str().to_ascii_lowercase().as_str() {
+                "colon" => Some(':'),
+                "tab" => Some('\t'),
+                _ => Some('\n'),
+            },
+            _ => None,
+        };
+        decoded.map(String::from).unwrap_or_default()
+    });
+    // Browsers skip control characters and whitespace anywhere in the scheme
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .take(32)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    url.starts_with("javascript:")
+        || url.starts_with("vbscript:")
+        || url.starts_with("data:text/html")
+        || url.starts_with("data:application/xhtml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_scripts_and_handlers() {
+        let html = r#"<!DOCTYPE html><html><head><title>Shop</title>
+            <link rel="stylesheet" href="/site.css">
+            <link rel="modulepreload" href="/app.js">
+            <meta http-equiv="refresh" content="0;url=https://example.com/next">
+            <script src="https://cdn.example/tag.js"></script>
+            <style>.hero { color: red }</style></head>
+            <body onload="track()"><div class="hero" onclick="buy()">Shoes</div>
+            <a href=" java&#x09;script&colon;alert(1)">Deal</a><a href="/cart" ping="/ping">Cart</a>
+            <iframe src="https://ads.example/frame"></iframe>
+            <img src="/shoe.jpg" width="400" height="300" alt="Shoe">
+            <img src="/spacer.gif" width="1" height="1">
+            <img src="https://www.facebook.com/tr?id=1&ev=PageView">
+            <svg><script>alert(1)</script></svg></body></html>"#;
+
+        let snapshot = SnapshotSanitizer::new().sanitize(html).unwrap();
+        let out = &snapshot.html;
+        assert!(!out.contains("<script"), "{}", out);
+        assert!(!out.contains("onload") && !out.contains("onclick"));
+        assert!(!out.contains("refresh") && !out.contains("modulepreload"));
+        assert!(!out.contains("<iframe") && !out.contains("ping="));
+        assert!(!out.contains("spacer.gif") && !out.contains("facebook.com"));
+        assert!(out.contains(r#"<link rel="stylesheet" href="/site.css">"#));
+        assert!(out.contains(".hero { color: red }"));
+        assert!(out.contains(r#"<div class="hero">Shoes</div>"#));
+        assert!(out.contains(r#"<img src="/shoe.jpg" width="400" height="300" alt="Shoe">"#));
+        assert!(out.contains(r#"<a href="/cart">Cart</a>"#));
+        let csp = format!(
+            r#"<head><meta http-equiv="Content-Security-Policy" content="{}">"#,
+            SNAPSHOT_CSP
+        );
+        assert!(out.contains(&csp));
+        assert_eq!(
+            snapshot.removed,
+            SanitizeReport {
+                scripts: 3,
+                handlers: 5,
+                trackers: 2,
+                embeds: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unwraps_noscript_fallbacks() {
+        let html = r#"<html><head></head><body><noscript><img src="/photo.jpg">
+            <img height="1" width="1" src="https://www.facebook.com/tr?id=1&noscript=1"/>
+            <iframe src="https://www.googletagmanager.com/ns.html"></iframe></noscript></body></html>"#;
+
+        let snapshot = SnapshotSanitizer::new().sanitize(html).unwrap();
+        assert!(!snapshot.html.contains("noscript>"), "{}", snapshot.html);
+        assert!(snapshot.html.contains(r#"<img src="/photo.jpg">"#));
+        assert!(!snapshot.html.contains("facebook.com") && !snapshot.html.contains("<iframe"));
+        assert_eq!(snapshot.removed.trackers, 1);
+        assert_eq!(snapshot.removed.embeds, 1);
+    }
+
+    #[test]
+    fn test_matches_trackers() {
+        let sanitizer = SnapshotSanitizer::new().with_trackers(["stats.example.com".to_string()]);
+        assert!(sanitizer.is_tracker("https://www.google-analytics.com/collect?v=1"));
+        assert!(sanitizer.is_tracker("//www.facebook.com/tr?id=1"));
+        assert!(sanitizer.is_tracker("https://stats.example.com/hit.gif"));
+        assert!(!sanitizer.is_tracker("https://www.facebook.com/photo.php"));
+        assert!(!sanitizer.is_tracker("https://notdoubleclick.net/ad.gif"));
+        assert!(!sanitizer.is_tracker("/images/logo.png"));
+    }
+}
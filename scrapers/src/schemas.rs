@@ -0,0 +1,716 @@
+//! Typed per-platform output schemas
+//!
+//! Scrapers return loosely typed [`ExtractedContent`]. A [`SchemaRegistry`]
+//! maps platforms to typed records such as [`Product`], [`Post`], [`Article`],
+//! [`JobPosting`] or [`VideoMeta`], builds them after extraction and validates
+//! them. Content that doesn't fit its schema fails with a [`SchemaViolation`]
+//! rather than a fetch error, so layout changes on a site show up as
+//! extraction drift.
+
+use crate::ExtractedContent;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+
+/// A typed record built from extracted content
+pub trait Schema: Serialize + DeserializeOwned {
+    /// Schema name recorded in content metadata
+    const NAME: &'static str;
+
+    /// Build and validate the record, returning every problem found
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>>;
+}
+
+/// First non-empty metadata value among `keys`
+fn meta<'a>(content: &'a ExtractedContent, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|key| content.metadata.get(*key))
+        .map(|value| value.trim())
+        .find(|value| !value.is_empty())
+}
+
+fn required(value: Option<String>, field: &str, issues: &mut Vec<String>) -> String {
+    value.unwrap_or_else(|| {
+        issues.push(format!("missing {}", field));
+        String::new()
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(
+    raw: Option<&str>,
+    field: &str,
+    issues: &mut Vec<String>,
+) -> Option<T> {
+    let raw = raw?;
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    match cleaned.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            issues.push(format!("invalid {}: '{}'", field, raw));
+            None
+        }
+    }
+}
+
+fn parse_timestamp(
+    raw: Option<&str>,
+    field: &str,
+    issues: &mut Vec<String>,
+) -> Option<DateTime<Utc>> {
+    let raw = raw?;
+    match DateTime::parse_from_rfc3339(raw) {
+        Ok(value) => Some(value.with_timezone(&Utc)),
+        Err(_) => {
+            issues.push(format!("invalid {}: '{}'", field, raw));
+            None
+        }
+    }
+}
+
+/// Parse seconds from either a plain number or an ISO 8601 duration like `PT1H2M3S`
+fn parse_duration_secs(raw: &str) -> Option<u64> {
+    if let Ok(secs) = raw.parse::<u64>() {
+        return Some(secs);
+    }
+
+    let time = raw.strip_prefix("PT")?;
+    let mut total = 0;
+    let mut number = String::new();
+    for c in time.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'H' => value * 3600,
+            'M' => value * 60,
+            'S' => value,
+            _ => return None,
+        };
+    }
+    number.is_empty().then_some(total)
+}
+
+fn finish<T>(record: T, issues: Vec<String>) -> Result<T, Vec<String>> {
+    if issues.is_empty() {
+        Ok(record)
+    } else {
+        Err(issues)
+    }
+}
+
+/// Metadata key holding a product's SKU
+pub const PRODUCT_SKU_KEY: &str = "product:sku";
+/// Metadata key holding a product's image URLs, one per line
+pub const PRODUCT_IMAGES_KEY: &str = "product:images";
+
+/// Product listing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub url: String,
+    pub name: String,
+    pub price: f64,
+    pub currency: Option<String>,
+    pub availability: Option<String>,
+    pub brand: Option<String>,
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+}
+
+impl Schema for Product {
+    const NAME: &'static str = "product";
+
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>> {
+        let mut issues = Vec::new();
+        let name = required(
+            meta(content, &["product:name", "og:title"])
+                .or(content.title.as_deref())
+                .map(str::to_string),
+            "name",
+            &mut issues,
+        );
+        let raw_price = meta(
+            content,
+            &["product:price:amount", "og:price:amount", "price"],
+        );
+        if raw_price.is_none() {
+            issues.push("missing price".to_string());
+        }
+        let price = parse_number(raw_price, "price", &mut issues);
+        let currency =
+            meta(content, &["product:price:currency", "og:price:currency"]).map(str::to_uppercase);
+        if let Some(currency) = &currency {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+                issues.push(format!("invalid currency: '{}'", currency));
+            }
+        }
+        let images: Vec<String> = meta(content, &[PRODUCT_IMAGES_KEY])
+            .map(|images| images.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        finish(
+            Self {
+                url: content.url.clone(),
+                name,
+                price: price.unwrap_or_default(),
+                currency,
+                availability: meta(content, &["product:availability", "og:availability"])
+                    .map(str::to_string),
+                brand: meta(content, &["product:brand", "og:brand"]).map(str::to_string),
+                image: meta(content, &["og:image"])
+                    .map(str::to_string)
+                    .or_else(|| images.first().cloned()),
+                sku: meta(content, &[PRODUCT_SKU_KEY]).map(str::to_string),
+                images,
+            },
+            issues,
+        )
+    }
+}
+
+/// Metadata key prefix for post engagement counts, e.g. `post:metric:likes`
+pub const POST_METRIC_PREFIX: &str = "post:metric:";
+/// Metadata key holding a post's media URLs, one per line
+pub const POST_MEDIA_KEY: &str = "post:media";
+
+/// Social media or blog post
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Post {
+    pub url: String,
+    pub author: Option<String>,
+    pub text: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub image: Option<String>,
+    /// Engagement counts such as `likes` or `reposts`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<String>,
+}
+
+impl Schema for Post {
+    const NAME: &'static str = "post";
+
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>> {
+        let mut issues = Vec::new();
+        let text = required(
+            content
+                .text
+                .as_deref()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .or_else(|| meta(content, &["og:description", "description"]))
+                .map(str::to_string),
+            "text",
+            &mut issues,
+        );
+        let published_at = parse_timestamp(
+            meta(content, &["article:published_time", "og:published_time"]),
+            "published_at",
+            &mut issues,
+        );
+        let mut metrics = BTreeMap::new();
+        for (key, value) in &content.metadata {
+            if let Some(name) = key.strip_prefix(POST_METRIC_PREFIX) {
+                if let Some(count) = parse_number(Some(value), key, &mut issues) {
+                    metrics.insert(name.to_string(), count);
+                }
+            }
+        }
+        let media: Vec<String> = meta(content, &[POST_MEDIA_KEY])
+            .map(|media| media.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        finish(
+            Self {
+                url: content.url.clone(),
+                author: meta(content, &["article:author", "author", "twitter:creator"])
+                    .map(str::to_string),
+                text,
+                published_at,
+                image: meta(content, &["og:image"])
+                    .map(str::to_string)
+                    .or_else(|| media.first().cloned()),
+                metrics,
+                media,
+            },
+            issues,
+        )
+    }
+}
+
+/// Video page metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoMeta {
+    pub url: String,
+    pub title: String,
+    pub duration_secs: Option<u64>,
+    pub channel: Option<String>,
+    pub views: Option<u64>,
+    pub thumbnail: Option<String>,
+}
+
+impl Schema for VideoMeta {
+    const NAME: &'static str = "video";
+
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>> {
+        let mut issues = Vec::new();
+        let title = required(
+            meta(content, &["og:title"])
+                .or(content.title.as_deref())
+                .map(str::to_string),
+            "title",
+            &mut issues,
+        );
+        let duration_secs = meta(
+            content,
+            &["og:video:duration", "video:duration", "duration"],
+        )
+        .and_then(|raw| {
+            let parsed = parse_duration_secs(raw);
+            if parsed.is_none() {
+                issues.push(format!("invalid duration: '{}'", raw));
+            }
+            parsed
+        });
+        let views = parse_number(
+            meta(content, &["interactioncount", "video:views"]),
+            "views",
+            &mut issues,
+        );
+
+        finish(
+            Self {
+                url: content.url.clone(),
+                title,
+                duration_secs,
+                channel: meta(content, &["og:site_name", "author", "video:channel"])
+                    .map(str::to_string),
+                views,
+                thumbnail: meta(content, &["og:image"]).map(str::to_string),
+            },
+            issues,
+        )
+    }
+}
+
+/// Metadata key holding an article's headline
+pub const ARTICLE_HEADLINE_KEY: &str = "article:headline";
+/// Metadata key holding an article's author names, one per line
+pub const ARTICLE_AUTHORS_KEY: &str = "article:authors";
+/// Metadata key holding the URL of an article's AMP variant
+pub const ARTICLE_AMP_URL_KEY: &str = "article:amp_url";
+
+/// News or blog article
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Article {
+    pub url: String,
+    pub headline: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub text: String,
+    pub word_count: usize,
+    pub canonical_url: Option<String>,
+    pub amp_url: Option<String>,
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl Schema for Article {
+    const NAME: &'static str = "article";
+
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>> {
+        let mut issues = Vec::new();
+        let headline = required(
+            meta(content, &[ARTICLE_HEADLINE_KEY, "og:title"])
+                .or(content.title.as_deref())
+                .map(str::to_string),
+            "headline",
+            &mut issues,
+        );
+        let text = required(
+            content
+                .text
+                .as_deref()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string),
+            "text",
+            &mut issues,
+        );
+        let authors = meta(content, &[ARTICLE_AUTHORS_KEY])
+            .map(|authors| authors.lines().map(str::to_string).collect())
+            .or_else(|| meta(content, &["author"]).map(|author| vec![author.to_string()]))
+            .unwrap_or_default();
+        let published_at = parse_timestamp(
+            meta(content, &["article:published_time", "og:published_time"]),
+            "published_at",
+            &mut issues,
+        );
+        let modified_at = parse_timestamp(
+            meta(content, &["article:modified_time", "og:updated_time"]),
+            "modified_at",
+            &mut issues,
+        );
+
+        finish(
+            Self {
+                url: content.url.clone(),
+                headline,
+                authors,
+                published_at,
+                modified_at,
+                word_count: text.split_whitespace().count(),
+                text,
+                canonical_url: meta(content, &[crate::platforms::CANONICAL_URL_KEY])
+                    .map(str::to_string),
+                amp_url: meta(content, &[ARTICLE_AMP_URL_KEY]).map(str::to_string),
+                image: meta(content, &["og:image"]).map(str::to_string),
+                site_name: meta(content, &["og:site_name"]).map(str::to_string),
+            },
+            issues,
+        )
+    }
+}
+
+/// Job posting, read from `job:*` metadata such as `job:title` or `job:company`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobPosting {
+    pub url: String,
+    pub title: String,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    /// e.g. `FULL_TIME` or `Contract`
+    pub employment_type: Option<String>,
+    /// e.g. `Mid-Senior level`
+    pub seniority: Option<String>,
+    pub posted_at: Option<DateTime<Utc>>,
+    /// Salary as advertised, e.g. `USD 120000-150000/YEAR`
+    pub salary: Option<String>,
+    pub applicants: Option<u64>,
+    pub description: String,
+}
+
+impl Schema for JobPosting {
+    const NAME: &'static str = "job_posting";
+
+    fn from_content(content: &ExtractedContent) -> Result<Self, Vec<String>> {
+        let mut issues = Vec::new();
+        let title = required(
+            meta(content, &["job:title", "og:title"])
+                .or(content.title.as_deref())
+                .map(str::to_string),
+            "title",
+            &mut issues,
+        );
+        let description = required(
+            content
+                .text
+                .as_deref()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string),
+            "description",
+            &mut issues,
+        );
+        let posted_at =
+            parse_timestamp(meta(content, &["job:posted_at"]), "posted_at", &mut issues);
+        let applicants = parse_number(
+            meta(content, &["job:applicants"]),
+            "applicants",
+            &mut issues,
+        );
+        let field = |key: &str| meta(content, &[key]).map(str::to_string);
+
+        finish(
+            Self {
+                url: content.url.clone(),
+                title,
+                company: field("job:company"),
+                location: field("job:location"),
+                employment_type: field("job:employment_type"),
+                seniority: field("job:seniority"),
+                posted_at,
+                salary: field("job:salary"),
+                applicants,
+                description,
+            },
+            issues,
+        )
+    }
+}
+
+/// Content that failed its platform schema
+#[derive(Debug, Clone)]
+pub struct SchemaViolation {
+    pub platform: String,
+    pub schema: &'static str,
+    pub issues: Vec<String>,
+    /// The extracted content, kept so nothing is lost
+    pub content: Box<ExtractedContent>,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} content from {} does not match the {} schema: {}",
+            self.platform,
+            self.content.url,
+            self.schema,
+            self.issues.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
+
+type Validator =
+    Arc<dyn Fn(&ExtractedContent) -> Result<serde_json::Value, Vec<String>> + Send + Sync>;
+
+/// Maps platform names to the schema their output must satisfy
+#[derive(Default, Clone)]
+pub struct SchemaRegistry {
+    schemas: HashMap<String, (&'static str, Validator)>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `platform` output to satisfy schema `S`
+    pub fn with_schema<S: Schema>(mut self, platform: &str) -> Self {
+        let validator: Validator = Arc::new(|content| {
+            let record = S::from_content(content)?;
+            serde_json::to_value(record).map_err(|e| vec![e.to_string()])
+        });
+        self.schemas
+            .insert(platform.to_string(), (S::NAME, validator));
+        self
+    }
+
+    /// Register every built-in schema under its own name, so domain policies
+    /// can select them as extraction rule sets
+    pub fn with_builtin_rule_sets(self) -> Self {
+        self.with_schema::<Product>(Product::NAME)
+            .with_schema::<Post>(Post::NAME)
+            .with_schema::<VideoMeta>(VideoMeta::NAME)
+            .with_schema::<Article>(Article::NAME)
+            .with_schema::<JobPosting>(JobPosting::NAME)
+    }
+
+    /// Schema name registered for `platform`
+    pub fn schema_for(&self, platform: &str) -> Option<&'static str> {
+        self.schemas.get(platform).map(|(name, _)| *name)
+    }
+
+    /// Validate `content`, recording the typed record as `schema`/`schema_record` metadata
+    ///
+    /// Platforms without a registered schema pass through unchanged.
+    pub fn apply(
+        &self,
+        platform: &str,
+        mut content: ExtractedContent,
+    ) -> Result<ExtractedContent, SchemaViolation> {
+        let Some((name, validator)) = self.schemas.get(platform) else {
+            return Ok(content);
+        };
+
+        match validator(&content) {
+            Ok(record) => {
+                content.metadata.insert("schema".into(), name.to_string());
+                content
+                    .metadata
+                    .insert("schema_record".into(), record.to_string());
+                Ok(content)
+            }
+            Err(issues) => Err(SchemaViolation {
+                platform: platform.to_string(),
+                schema: name,
+                issues,
+                content: Box::new(content),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swoop_core::text::SharedStr;
+
+    fn content(
+        title: Option<&str>,
+        text: Option<&str>,
+        metadata: &[(&str, &str)],
+    ) -> ExtractedContent {
+        ExtractedContent {
+            url: "https://shop.example.com/item/1".to_string(),
+            title: title.map(SharedStr::from),
+            text: text.map(SharedStr::from),
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| ((*k).into(), v.to_string()))
+                .collect(),
+            tags: Vec::new(),
+            extracted_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_product_schema() {
+        let valid = content(
+            Some("Kettle"),
+            None,
+            &[
+                ("product:price:amount", "$24.99"),
+                ("product:price:currency", "usd"),
+            ],
+        );
+        let product = Product::from_content(&valid).unwrap();
+        assert_eq!(product.name, "Kettle");
+        assert_eq!(product.price, 24.99);
+        assert_eq!(product.currency.as_deref(), Some("USD"));
+        assert!(product.sku.is_none());
+
+        let extracted = content(
+            Some("Kettle | Shop"),
+            None,
+            &[
+                ("product:name", "Kettle"),
+                ("product:price:amount", "24.99"),
+                (PRODUCT_SKU_KEY, "KT-1"),
+                (
+                    PRODUCT_IMAGES_KEY,
+                    "https://shop.example.com/a.jpg\nhttps://shop.example.com/b.jpg",
+                ),
+            ],
+        );
+        let product = Product::from_content(&extracted).unwrap();
+        assert_eq!(product.name, "Kettle");
+        assert_eq!(product.sku.as_deref(), Some("KT-1"));
+        assert_eq!(product.images.len(), 2);
+        assert_eq!(
+            product.image.as_deref(),
+            Some("https://shop.example.com/a.jpg")
+        );
+
+        let issues = Product::from_content(&content(None, None, &[])).unwrap_err();
+        assert!(issues.contains(&"missing name".to_string()));
+        assert!(issues.contains(&"missing price".to_string()));
+    }
+
+    #[test]
+    fn test_post_and_video_schemas() {
+        let post = content(
+            None,
+            Some("Shipping the new release today"),
+            &[("article:published_time", "2024-05-01T10:00:00Z")],
+        );
+        assert!(Post::from_content(&post).unwrap().published_at.is_some());
+
+        let social = content(
+            None,
+            Some("New logo"),
+            &[
+                ("post:metric:likes", "1200"),
+                (
+                    POST_MEDIA_KEY,
+                    "https://img.example/a.png\nhttps://img.example/b.png",
+                ),
+            ],
+        );
+        let social = Post::from_content(&social).unwrap();
+        assert_eq!(social.metrics["likes"], 1200);
+        assert_eq!(social.media.len(), 2);
+        assert_eq!(social.image.as_deref(), Some("https://img.example/a.png"));
+
+        let bad_date = content(
+            None,
+            Some("text"),
+            &[("article:published_time", "yesterday")],
+        );
+        assert_eq!(
+            Post::from_content(&bad_date).unwrap_err(),
+            vec!["invalid published_at: 'yesterday'".to_string()]
+        );
+
+        let article = content(
+            Some("Harbour reopens | Coast Times"),
+            Some("The harbour reopened on Tuesday."),
+            &[
+                (ARTICLE_HEADLINE_KEY, "Harbour reopens"),
+                (ARTICLE_AUTHORS_KEY, "Jane Doe\nJohn Roe"),
+                ("article:published_time", "2024-03-05T07:30:00+00:00"),
+            ],
+        );
+        let article = Article::from_content(&article).unwrap();
+        assert_eq!(article.headline, "Harbour reopens");
+        assert_eq!(article.authors, vec!["Jane Doe", "John Roe"]);
+        assert_eq!(article.word_count, 5);
+        assert!(Article::from_content(&content(Some("Empty"), None, &[])).is_err());
+
+        let job = content(
+            Some("Backend Engineer | Acme"),
+            Some("Build the crawler."),
+            &[
+                ("job:title", "Backend Engineer"),
+                ("job:posted_at", "2024-04-01T00:00:00Z"),
+                ("job:applicants", "over 200"),
+            ],
+        );
+        let job = JobPosting::from_content(&job).unwrap();
+        assert_eq!(job.title, "Backend Engineer");
+        assert_eq!(job.applicants, Some(200));
+
+        let video = content(Some("Talk"), None, &[("og:video:duration", "PT1M30S")]);
+        assert_eq!(
+            VideoMeta::from_content(&video).unwrap().duration_secs,
+            Some(90)
+        );
+    }
+
+    #[test]
+    fn test_registry_reports_violations_separately() {
+        let registry = SchemaRegistry::new().with_schema::<Product>("shop");
+
+        let ok = registry
+            .apply(
+                "shop",
+                content(Some("Kettle"), None, &[("og:price:amount", "10")]),
+            )
+            .unwrap();
+        assert_eq!(
+            ok.metadata.get("schema").map(String::as_str),
+            Some("product")
+        );
+        assert!(ok.metadata.contains_key("schema_record"));
+
+        let error = anyhow::Error::new(
+            registry
+                .apply("shop", content(Some("Kettle"), None, &[]))
+                .unwrap_err(),
+        );
+        let violation = error.downcast_ref::<SchemaViolation>().unwrap();
+        assert_eq!(violation.schema, "product");
+        assert_eq!(violation.issues, vec!["missing price".to_string()]);
+
+        // Platforms without a schema pass through untouched
+        assert!(registry.apply("generic", content(None, None, &[])).is_ok());
+    }
+}
@@ -0,0 +1,245 @@
+//! User scripting hooks
+//!
+//! Lets users customize a crawl with a small [Rhai](https://rhai.rs) script instead
+//! of recompiling. A script may define any of these functions:
+//! - `pre_request(request)` - receives `#{ url, headers }`; return the (modified)
+//!   map to continue or `false` to skip the URL
+//! - `post_extract(record)` - receives the extracted record as a map; return the
+//!   (modified) map to keep it or `false` to drop it
+//! - `should_follow_link(url, from)` - return `true` to follow a discovered link
+//!
+//! Missing functions fall back to the default behavior. Scripts run with
+//! operation and size limits so a buggy hook cannot stall the crawler.
+
+use anyhow::{Context, Result};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// Maximum number of operations a single hook invocation may execute
+const MAX_OPERATIONS: u64 = 100_000;
+/// Maximum length of any string built by a script
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+/// Outgoing request as seen by the `pre_request` hook
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookRequest {
+    /// URL to fetch
+    pub url: String,
+    /// Extra headers to send
+    pub headers: HashMap<String, String>,
+}
+
+impl HookRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: HashMap::new(),
+        }
+    }
+}
+
+/// Compiled user script with optional crawl hooks
+#[derive(Debug)]
+pub struct ScriptHooks {
+    engine: Engine,
+    ast: AST,
+    has_pre_request: bool,
+    has_post_extract: bool,
+    has_should_follow_link: bool,
+}
+
+impl ScriptHooks {
+    /// Compile hooks from script source
+    pub fn from_source(source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow::anyhow!("Failed to compile script: {}", e))?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+
+        Ok(Self {
+            has_pre_request: defines("pre_request"),
+            has_post_extract: defines("post_extract"),
+            has_should_follow_link: defines("should_follow_link"),
+            engine,
+            ast,
+        })
+    }
+
+    /// Compile hooks from a script file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script {}", path.display()))?;
+        Self::from_source(&source)
+    }
+
+    /// Run `pre_request`; `None` means the script asked to skip the URL
+    pub fn pre_request(&self, request: HookRequest) -> Result<Option<HookRequest>> {
+        if !self.has_pre_request {
+            return Ok(Some(request));
+        }
+        self.call_record_hook("pre_request", &request)
+    }
+
+    /// Run `post_extract` on any serializable record; `None` means the record was dropped
+    pub fn post_extract<T: Serialize + DeserializeOwned>(&self, record: T) -> Result<Option<T>> {
+        if !self.has_post_extract {
+            return Ok(Some(record));
+        }
+        self.call_record_hook("post_extract", &record)
+    }
+
+    /// Like [`Self::post_extract`], but rewrites `record` in place instead of
+    /// taking it by value, so callers keep the original without cloning it
+    ///
+    /// Returns `false` when the record was dropped; on error `record` is unchanged.
+    pub fn post_extract_in_place<T: Serialize + DeserializeOwned>(
+        &self,
+        record: &mut T,
+    ) -> Result<bool> {
+        if !self.has_post_extract {
+            return Ok(true);
+        }
+        match self.call_record_hook("post_extract", record)? {
+            Some(output) => {
+                *record = output;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Run `should_follow_link`; links are followed when the hook is not defined
+    pub fn should_follow_link(&self, url: &str, from: &str) -> Result<bool> {
+        if !self.has_should_follow_link {
+            return Ok(true);
+        }
+        self.engine
+            .call_fn::<bool>(
+                &mut Scope::new(),
+                &self.ast,
+                "should_follow_link",
+                (url.to_string(), from.to_string()),
+            )
+            .map_err(|e| anyhow::anyhow!("should_follow_link failed: {}", e))
+    }
+
+    fn call_record_hook<T: Serialize + DeserializeOwned>(
+        &self,
+        name: &str,
+        record: &T,
+    ) -> Result<Option<T>> {
+        let input = rhai::serde::to_dynamic(record)
+            .map_err(|e| anyhow::anyhow!("Failed to convert record for {}: {}", name, e))?;
+        let output: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, name, (input,))
+            .map_err(|e| anyhow::anyhow!("{} failed: {}", name, e))?;
+
+        if output.as_bool() == Ok(false) || output.is_unit() {
+            return Ok(None);
+        }
+
+        rhai::serde::from_dynamic(&output)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{} returned an invalid record: {}", name, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExtractedContent;
+
+    #[test]
+    fn test_missing_hooks_use_defaults() {
+        let hooks = ScriptHooks::from_source("let x = 1;").unwrap();
+        let request = HookRequest::new("https://example.com");
+
+        assert_eq!(hooks.pre_request(request.clone()).unwrap(), Some(request));
+        assert!(hooks
+            .should_follow_link("https://a.com", "https://b.com")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_pre_request_rewrites_and_skips() {
+        let hooks = ScriptHooks::from_source(
+            r#"
+            fn pre_request(req) {
+                if req.url.contains("/logout") { return false; }
+                req.headers["X-Trace"] = "1";
+                req.url.replace("http://", "https://");
+                req
+            }
+            "#,
+        )
+        .unwrap();
+
+        let rewritten = hooks
+            .pre_request(HookRequest::new("http://example.com/page"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(rewritten.url, "https://example.com/page");
+        assert_eq!(
+            rewritten.headers.get("X-Trace").map(String::as_str),
+            Some("1")
+        );
+
+        assert!(hooks
+            .pre_request(HookRequest::new("https://example.com/logout"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_post_extract_transforms_records() {
+        let hooks = ScriptHooks::from_source(
+            r#"
+            fn post_extract(record) {
+                if record.title == () { return false; }
+                record.title = record.title.to_upper();
+                record
+            }
+            "#,
+        )
+        .unwrap();
+
+        let content = ExtractedContent {
+            url: "https://example.com".to_string(),
+            title: Some("hello".into()),
+            text: None,
+            metadata: HashMap::new(),
+            tags: Vec::new(),
+            extracted_at: chrono::Utc::now(),
+        };
+        let transformed = hooks.post_extract(content.clone()).unwrap().unwrap();
+        assert_eq!(transformed.title.as_deref(), Some("HELLO"));
+
+        let untitled = ExtractedContent {
+            title: None,
+            ..content.clone()
+        };
+        assert!(hooks.post_extract(untitled.clone()).unwrap().is_none());
+
+        let mut record = content;
+        assert!(hooks.post_extract_in_place(&mut record).unwrap());
+        assert_eq!(record.title.as_deref(), Some("HELLO"));
+        let mut record = untitled;
+        assert!(!hooks.post_extract_in_place(&mut record).unwrap());
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let hooks =
+            ScriptHooks::from_source("fn should_follow_link(url, from) { loop {} }").unwrap();
+        assert!(hooks
+            .should_follow_link("https://a.com", "https://b.com")
+            .is_err());
+    }
+}
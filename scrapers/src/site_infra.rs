@@ -0,0 +1,223 @@
+//! DNS and TLS facts of a site
+//!
+//! [`resolve_dns`] records where a domain points: the CNAME chain it is
+//! aliased through and the addresses it finally resolves to. [`fetch_certificate`]
+//! completes a TLS handshake with the site and keeps the leaf certificate's
+//! subject, issuer, alternative names and validity. Both feed the optional
+//! infrastructure part of a [`SiteProfile`](crate::site_profile::SiteProfile),
+//! e.g. to spot sites moving between CDNs or certificates about to expire.
+//!
+//! The handshake records the certificate without judging it: expired,
+//! self-signed and mismatched certificates are exactly the ones worth
+//! reporting, so they are accepted here and no data is sent over the
+//! connection.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use hickory_resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    proto::rr::RData,
+    TokioAsyncResolver,
+};
+use serde::{Deserialize, Serialize};
+use std::{net::IpAddr, sync::Arc, time::Duration};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{self, ring, WebPkiSupportedAlgorithms},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, SignatureScheme,
+    },
+    TlsConnector,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, time::ASN1Time};
+
+/// Where a domain's name resolves to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteDns {
+    /// IPv4 and IPv6 addresses, in the order the resolver returned them
+    pub addresses: Vec<String>,
+    /// Names the domain is aliased to, in resolution order
+    pub cname_chain: Vec<String>,
+}
+
+/// The certificate a site presents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteCertificate {
+    /// Subject distinguished name, e.g. `CN=example.com`
+    pub subject: String,
+    /// Issuer distinguished name, e.g. `C=US, O=Let's Encrypt, CN=R11`
+    pub issuer: String,
+    /// DNS names and IP addresses the certificate is valid for
+    pub san: Vec<String>,
+    /// Serial number as colon-separated hex
+    pub serial: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+}
+
+/// Resolve `domain`'s addresses and the CNAME chain leading to them
+pub async fn resolve_dns(domain: &str, timeout: Duration) -> Result<SiteDns> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().unwrap_or_else(|_| {
+        TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())
+    });
+    let lookup = tokio::time::timeout(timeout, resolver.lookup_ip(domain))
+        .await
+        .with_context(|| format!("DNS lookup of {} timed out", domain))?
+        .with_context(|| format!("DNS lookup of {} failed", domain))?;
+
+    let mut dns = SiteDns::default();
+    for address in lookup.iter().map(|address| address.to_string()) {
+        if !dns.addresses.contains(&address) {
+            dns.addresses.push(address);
+        }
+    }
+    for record in lookup.as_lookup().records() {
+        if let Some(RData::CNAME(target)) = record.data() {
+            let target = target.0.to_utf8().trim_end_matches('.').to_string();
+            if !dns.cname_chain.contains(&target) {
+                dns.cname_chain.push(target);
+            }
+        }
+    }
+    Ok(dns)
+}
+
+/// The leaf certificate `domain` presents on `port`, see the [module docs](self)
+pub async fn fetch_certificate(
+    domain: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<SiteCertificate> {
+    let provider = Arc::new(ring::default_provider());
+    let verifier = RecordOnly(provider.signature_verification_algorithms);
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    let name = ServerName::try_from(domain.to_string())
+        .with_context(|| format!("Invalid server name: {}", domain))?;
+
+    let handshake = async {
+        let tcp = TcpStream::connect((domain, port)).await?;
+        TlsConnector::from(Arc::new(config))
+            .connect(name, tcp)
+            .await
+    };
+    let stream = tokio::time::timeout(timeout, handshake)
+        .await
+        .with_context(|| format!("TLS handshake with {}:{} timed out", domain, port))?
+        .with_context(|| format!("TLS handshake with {}:{} failed", domain, port))?;
+    let leaf = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|chain| chain.first())
+        .with_context(|| format!("{} presented no certificate", domain))?;
+    parse_certificate(leaf)
+}
+
+/// Subject, issuer, names and validity of a DER-encoded certificate
+pub fn parse_certificate(der: &[u8]) -> Result<SiteCertificate> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(der)
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    let validity = certificate.validity();
+    Ok(SiteCertificate {
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        san: alternative_names(&certificate),
+        serial: certificate.raw_serial_as_string(),
+        not_before: timestamp(validity.not_before)?,
+        not_after: timestamp(validity.not_after)?,
+    })
+}
+
+fn alternative_names(certificate: &X509Certificate) -> Vec<String> {
+    let Ok(Some(extension)) = certificate.subject_alternative_name() else {
+        return Vec::new();
+    };
+    extension
+        .value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(name) => Some(name.to_string()),
+            GeneralName::IPAddress(bytes) => ip_address(bytes).map(|address| address.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// An IPv4 or IPv6 address from its 4 or 16 bytes
+fn ip_address(bytes: &[u8]) -> Option<IpAddr> {
+    match <[u8; 4]>::try_from(bytes) {
+        Ok(v4) => Some(IpAddr::from(v4)),
+        Err(_) => <[u8; 16]>::try_from(bytes).ok().map(IpAddr::from),
+    }
+}
+
+fn timestamp(time: ASN1Time) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(time.timestamp(), 0)
+        .with_context(|| format!("Certificate date out of range: {}", time))
+}
+
+/// Accepts any certificate so it can be recorded; handshake signatures are
+/// still checked, so the peer does hold the certificate's key
+#[derive(Debug)]
+struct RecordOnly(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for RecordOnly {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_certificate() {
+        let names = vec!["example.com".to_string(), "127.0.0.1".to_string()];
+        let generated = rcgen::generate_simple_self_signed(names).unwrap();
+        let certificate = parse_certificate(generated.cert.der()).unwrap();
+
+        assert_eq!(certificate.san, vec!["example.com", "127.0.0.1"]);
+        assert_eq!(certificate.subject, certificate.issuer);
+        assert!(certificate.not_before < certificate.not_after);
+        assert!(!certificate.serial.is_empty());
+        assert!(parse_certificate(b"not a certificate").is_err());
+    }
+}
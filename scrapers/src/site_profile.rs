@@ -0,0 +1,437 @@
+//! Per-domain site profiles
+//!
+//! [`SiteProfiler`] visits a site's home page, favicon and robots.txt and
+//! summarizes what matters when planning a crawl of it: the site's name and
+//! favicon, the CMS or framework it runs on, what its robots.txt allows and how
+//! fast it usually answers. Profiles are meant to be collected once per domain
+//! and stored, e.g. as `storage::models::SiteProfile`.
+//!
+//! With [`SiteProfiler::with_infrastructure`] the profile also records the
+//! domain's DNS records and TLS certificate, see [`site_infra`](crate::site_infra).
+
+use crate::extractors::{
+    decode_entities, extract_json_ld, extract_metadata_secure, extract_title, find_json_ld_node,
+    html_tags, json_ld_text,
+};
+use crate::site_infra::{self, SiteCertificate, SiteDns};
+use crate::utils::parse_robots_txt;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use swoop_core::security::UrlValidator;
+
+/// What a site's robots.txt says
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SiteRobots {
+    /// Whether the site serves a robots.txt at all
+    pub found: bool,
+    pub crawl_delay_secs: Option<u64>,
+    pub disallow: Vec<String>,
+    pub sitemaps: Vec<String>,
+}
+
+/// Facts about one domain, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteProfile {
+    pub domain: String,
+    /// Home page after redirects
+    pub home_url: String,
+    pub site_name: Option<String>,
+    /// Absolute URL of the favicon
+    pub favicon: Option<String>,
+    /// Detected CMS and frameworks, most specific first
+    pub technologies: Vec<String>,
+    pub robots: SiteRobots,
+    /// Median total time of the home page fetches
+    pub response_ms: Option<u64>,
+    /// Median time to first byte of the home page fetches
+    pub ttfb_ms: Option<u64>,
+    /// Addresses and CNAME chain, when infrastructure was collected
+    pub dns: Option<SiteDns>,
+    /// Certificate of an HTTPS site, when infrastructure was collected
+    pub certificate: Option<SiteCertificate>,
+    pub profiled_at: DateTime<Utc>,
+}
+
+/// Builds [`SiteProfile`]s
+#[derive(Clone)]
+pub struct SiteProfiler {
+    validator: UrlValidator,
+    timeout: Duration,
+    /// Home page fetches the response-time baseline is taken from
+    samples: usize,
+    /// Also collect DNS records and the TLS certificate
+    infrastructure: bool,
+}
+
+impl Default for SiteProfiler {
+    fn default() -> Self {
+        Self {
+            validator: UrlValidator::default(),
+            timeout: Duration::from_secs(15),
+            samples: 3,
+            infrastructure: false,
+        }
+    }
+}
+
+impl SiteProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Fetch the home page this many times for the response-time baseline
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Also record the domain's DNS records and, for HTTPS sites, its certificate
+    pub fn with_infrastructure(mut self, enabled: bool) -> Self {
+        self.infrastructure = enabled;
+        self
+    }
+
+    /// Profile the site `target` belongs to; `target` is a URL or a bare domain
+    pub async fn profile(&self, target: &str) -> Result<SiteProfile> {
+        let url = if target.contains("://") {
+            url::Url::parse(target)
+        } else {
+            url::Url::parse(&format!("https://{}", target))
+        }
+        .with_context(|| format!("Invalid site: {}", target))?;
+        let domain = url
+            .host_str()
+            .with_context(|| format!("No host in {}", target))?
+            .to_ascii_lowercase();
+        let origin = url.origin().ascii_serialization();
+
+        let home =
+            swoop_core::fetch_response_with(&self.validator, &format!("{}/", origin), self.timeout)
+                .await?;
+        let mut total = vec![home.meta.timing.total_ms];
+        let mut ttfb: Vec<u64> = home.meta.timing.ttfb_ms.into_iter().collect();
+        for _ in 1..self.samples {
+            if let Ok(sample) =
+                swoop_core::fetch_response_with(&self.validator, &home.meta.final_url, self.timeout)
+                    .await
+            {
+                total.push(sample.meta.timing.total_ms);
+                ttfb.extend(sample.meta.timing.ttfb_ms);
+            }
+        }
+
+        let html = String::from_utf8_lossy(&home.body);
+        let favicon = match find_favicon(&html, &home.meta.final_url) {
+            Some(favicon) => Some(favicon),
+            None => self.default_favicon(&origin).await,
+        };
+        let (dns, certificate) = if self.infrastructure {
+            self.infrastructure(&url, &domain).await
+        } else {
+            (None, None)
+        };
+        Ok(SiteProfile {
+            domain,
+            site_name: find_site_name(&html),
+            favicon,
+            technologies: detect_technologies(&html, &home.meta.headers),
+            robots: self.robots(&origin).await,
+            response_ms: median(&mut total),
+            ttfb_ms: median(&mut ttfb),
+            dns,
+            certificate,
+            home_url: home.meta.final_url,
+            profiled_at: Utc::now(),
+        })
+    }
+
+    /// DNS records and certificate of `domain`; lookups that fail are left out
+    async fn infrastructure(
+        &self,
+        url: &url::Url,
+        domain: &str,
+    ) -> (Option<SiteDns>, Option<SiteCertificate>) {
+        let dns = site_infra::resolve_dns(domain, self.timeout)
+            .await
+            .map_err(|e| tracing::debug!("No DNS records for {}: {:#}", domain, e))
+            .ok();
+        let certificate = match (url.scheme(), url.port_or_known_default()) {
+            ("https", Some(port)) => site_infra::fetch_certificate(domain, port, self.timeout)
+                .await
+                .map_err(|e| tracing::debug!("No certificate for {}: {:#}", domain, e))
+                .ok(),
+            _ => None,
+        };
+        (dns, certificate)
+    }
+
+    /// `/favicon.ico`, when the page doesn't declare an icon but the server has one
+    async fn default_favicon(&self, origin: &str) -> Option<String> {
+        let url = format!("{}/favicon.ico", origin);
+        let response = swoop_core::fetch_response_with(&self.validator, &url, self.timeout)
+            .await
+            .ok()?;
+        (response.meta.status < 300 && !response.body.is_empty()).then_some(url)
+    }
+
+    async fn robots(&self, origin: &str) -> SiteRobots {
+        let url = format!("{}/robots.txt", origin);
+        match swoop_core::fetch_response_with(&self.validator, &url, self.timeout).await {
+            Ok(response) if response.meta.status < 300 => {
+                parse_site_robots(&String::from_utf8_lossy(&response.body))
+            }
+            _ => SiteRobots::default(),
+        }
+    }
+}
+
+/// Summarize a robots.txt body
+pub fn parse_site_robots(body: &str) -> SiteRobots {
+    let robots = parse_robots_txt(body);
+    let sitemaps = body
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .filter(|(directive, _)| directive.trim().eq_ignore_ascii_case("sitemap"))
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+    SiteRobots {
+        found: true,
+        crawl_delay_secs: robots.crawl_delay,
+        disallow: robots
+            .disallow
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .collect(),
+        sitemaps,
+    }
+}
+
+/// Absolute URL of the icon a page declares, preferring `icon` over Apple touch icons
+pub fn find_favicon(html: &str, base_url: &str) -> Option<String> {
+    let base = url::Url::parse(base_url).ok()?;
+    let mut icons: Vec<(u8, String)> = html_tags(html)
+        .filter(|tag| tag.name == "link")
+        .filter_map(|tag| {
+            let rel = tag.attributes.get("rel")?.to_ascii_lowercase();
+            let rank = rel.split_whitespace().find_map(|rel| match rel {
+                "icon" => Some(0),
+                "apple-touch-icon" | "apple-touch-icon-precomposed" => Some(1),
+                "mask-icon" => Some(2),
+                _ => None,
+            })?;
+            let href = decode_entities(tag.attributes.get("href")?.trim());
+            let icon = base.join(&href).ok()?;
+            matches!(icon.scheme(), "http" | "https").then(|| (rank, icon.to_string()))
+        })
+        .collect();
+    icons.sort_by_key(|(rank, _)| *rank);
+    icons.into_iter().next().map(|(_, icon)| icon)
+}
+
+/// The site's own name: `og:site_name`, `application-name`, a JSON-LD
+/// `WebSite`, or failing those the page title
+pub fn find_site_name(html: &str) -> Option<String> {
+    let metadata = extract_metadata_secure(html).unwrap_or_default();
+    [
+        "og:site_name",
+        "application-name",
+        "apple-mobile-web-app-title",
+    ]
+    .iter()
+    .find_map(|key| metadata.get(*key))
+    .map(|name| decode_entities(name))
+    .or_else(|| {
+        extract_json_ld(html).iter().find_map(|document| {
+            json_ld_text(find_json_ld_node(document, &["WebSite"])?.get("name")?)
+        })
+    })
+    .or_else(|| extract_title(html).ok().flatten())
+    .map(|name| name.trim().to_string())
+    .filter(|name| !name.is_empty())
+}
+
+/// Markup and header signatures: (technology, substrings of the page)
+const PAGE_SIGNATURES: &[(&str, &[&str])] = &[
+    ("WordPress", &["/wp-content/", "/wp-includes/"]),
+    ("Drupal", &["drupal-settings-json", "/sites/default/files/"]),
+    ("Joomla", &["/media/jui/", "/media/system/js/core.js"]),
+    ("Shopify", &["cdn.shopify.com", "Shopify.theme"]),
+    ("Magento", &["Magento_Theme", "mage/cookies"]),
+    ("Squarespace", &["static1.squarespace.com"]),
+    ("Wix", &["static.wixstatic.com"]),
+    ("Webflow", &["data-wf-site"]),
+    ("Ghost", &["ghost-portal", "/ghost/api/"]),
+    ("Next.js", &["/_next/static/", "__NEXT_DATA__"]),
+    ("Nuxt", &["/_nuxt/", "window.__NUXT__"]),
+    ("Gatsby", &["___gatsby"]),
+    ("Angular", &["ng-version="]),
+    ("React", &["data-reactroot"]),
+];
+
+/// (technology, response header, substring of its value; empty matches any value)
+const HEADER_SIGNATURES: &[(&str, &str, &str)] = &[
+    ("Drupal", "x-drupal-cache", ""),
+    ("Drupal", "x-generator", "drupal"),
+    ("Shopify", "x-shopid", ""),
+    ("Wix", "x-wix-request-id", ""),
+    ("Next.js", "x-powered-by", "next.js"),
+    ("Express", "x-powered-by", "express"),
+    ("PHP", "x-powered-by", "php"),
+    ("ASP.NET", "x-powered-by", "asp.net"),
+];
+
+/// CMS and frameworks a page was built with, from its `generator` meta tag,
+/// markup and response headers
+pub fn detect_technologies(html: &str, headers: &BTreeMap<String, String>) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut add = |name: &str| {
+        if !found.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+            found.push(name.to_string());
+        }
+    };
+
+    // "WordPress 6.4.2" or "Hugo 0.121.0": the product, without its version
+    let metadata = extract_metadata_secure(html).unwrap_or_default();
+    if let Some(generator) = metadata.get("generator") {
+        let product: Vec<&str> = generator
+            .split_whitespace()
+            .take_while(|word| {
+                !word
+                    .trim_start_matches('v')
+                    .starts_with(|c: char| c.is_ascii_digit())
+            })
+            .collect();
+        if !product.is_empty() {
+            add(&product.join(" "));
+        }
+    }
+    for (name, needles) in PAGE_SIGNATURES {
+        if needles.iter().any(|needle| html.contains(needle)) {
+            add(name);
+        }
+    }
+    for (name, header, needle) in HEADER_SIGNATURES {
+        if headers
+            .get(*header)
+            .is_some_and(|value| value.to_ascii_lowercase().contains(needle))
+        {
+            add(name);
+        }
+    }
+    found
+}
+
+fn median(values: &mut [u64]) -> Option<u64> {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_detect_technologies() {
+        let html = r#"<meta name="generator" content="WordPress 6.4.2">
+            <link rel="stylesheet" href="/wp-content/themes/x/style.css">
+            <script src="/_next/static/chunks/main.js"></script>"#;
+        let headers = BTreeMap::from([("x-powered-by".to_string(), "PHP/8.2".to_string())]);
+        assert_eq!(
+            detect_technologies(html, &headers),
+            vec!["WordPress", "Next.js", "PHP"]
+        );
+        assert!(detect_technologies("<p>plain</p>", &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_find_favicon_and_site_name() {
+        let html = r#"<head><title>Home | Example</title>
+            <link rel="apple-touch-icon" href="/touch.png">
+            <link rel="shortcut icon" href="/static/fav.ico?v=2&amp;x=1">
+            <meta property="og:site_name" content="Example News"></head>"#;
+        assert_eq!(
+            find_favicon(html, "https://example.com/a/").as_deref(),
+            Some("https://example.com/static/fav.ico?v=2&x=1")
+        );
+        assert_eq!(find_site_name(html).as_deref(), Some("Example News"));
+        assert_eq!(
+            find_site_name("<title> Just a title </title>").as_deref(),
+            Some("Just a title")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_collects_site_facts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/html")
+                    .insert_header("x-drupal-cache", "HIT")
+                    .set_body_string(
+                        r#"<html><head><meta name="application-name" content="Docs Portal"></head>
+                        <body><script type="application/json" data-drupal-selector="drupal-settings-json">{}</script></body></html>"#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/favicon.ico"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 16]))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/robots.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "User-agent: *\nDisallow: /admin\nCrawl-delay: 5\nSitemap: https://docs.example/sitemap.xml\n",
+            ))
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let profiler = SiteProfiler::new()
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()))
+            .with_samples(2)
+            .with_infrastructure(true);
+        let profile = profiler.profile(&server.uri()).await.unwrap();
+
+        assert_eq!(profile.domain, host.host_str().unwrap());
+        assert_eq!(profile.site_name.as_deref(), Some("Docs Portal"));
+        assert_eq!(
+            profile.favicon,
+            Some(format!("{}/favicon.ico", server.uri()))
+        );
+        assert_eq!(profile.technologies, vec!["Drupal"]);
+        assert_eq!(
+            profile.robots,
+            SiteRobots {
+                found: true,
+                crawl_delay_secs: Some(5),
+                disallow: vec!["/admin".to_string()],
+                sitemaps: vec!["https://docs.example/sitemap.xml".to_string()],
+            }
+        );
+        assert!(profile.response_ms.is_some());
+        // A plain-HTTP site on a literal address: no certificate, no CNAMEs
+        let dns = profile.dns.unwrap();
+        assert_eq!(dns.addresses, vec![host.host_str().unwrap()]);
+        assert!(dns.cname_chain.is_empty());
+        assert_eq!(profile.certificate, None);
+    }
+}
@@ -0,0 +1,347 @@
+//! Streaming extraction for very large pages
+//!
+//! The regular extractors work on the whole document as one string. For pages
+//! above [`STREAMING_THRESHOLD`], [`StreamingExtractor`] instead runs a
+//! streaming HTML tokenizer over the bytes as they are downloaded and keeps
+//! only what it extracts: the title, `<meta>` tags, the canonical link,
+//! outgoing links and a capped amount of body text. Memory use depends on the
+//! [`StreamingLimits`], not on the size of the page.
+
+use crate::extractors::{decode_entities, is_safe_content, is_safe_metadata_key};
+use crate::metadata::Metadata;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use lol_html::{element, html_content::TextType, text, HtmlRewriter, MemorySettings, Settings};
+use std::{cell::RefCell, rc::Rc};
+
+/// Pages larger than this are extracted while streaming instead of in memory
+pub const STREAMING_THRESHOLD: usize = 8 * 1024 * 1024;
+
+/// Caps on what a streamed page keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamingLimits {
+    /// Body text kept, in bytes
+    pub max_text_bytes: usize,
+    pub max_links: usize,
+    pub max_meta_tags: usize,
+    /// Memory the tokenizer may buffer, e.g. for a tag split across chunks
+    pub max_parser_memory: usize,
+}
+
+impl Default for StreamingLimits {
+    fn default() -> Self {
+        Self {
+            max_text_bytes: 1024 * 1024,
+            max_links: 10_000,
+            max_meta_tags: 512,
+            max_parser_memory: 1024 * 1024,
+        }
+    }
+}
+
+/// What the streaming pass kept of a page
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamedPage {
+    pub title: Option<String>,
+    /// `<meta>` tags by lowercased `name` or `property`
+    pub metadata: Metadata,
+    pub canonical_url: Option<String>,
+    pub language: Option<String>,
+    /// Absolute `http(s)` link targets, in document order
+    pub links: Vec<String>,
+    /// Body text with whitespace collapsed, cut at `max_text_bytes`
+    pub text: String,
+    /// Whether `text` was cut short
+    pub text_truncated: bool,
+    /// Size of the page as downloaded
+    pub bytes: usize,
+}
+
+#[derive(Default)]
+struct State {
+    page: StreamedPage,
+    title: String,
+    /// Whether the last text kept ended in whitespace
+    space_pending: bool,
+}
+
+impl State {
+    fn push_text(&mut self, chunk: &str, last_in_node: bool, limit: usize) {
+        if self.page.text_truncated {
+            return;
+        }
+        let text = decode_entities(chunk);
+        if text.starts_with(char::is_whitespace) {
+            self.space_pending = true;
+        }
+        for word in text.split_whitespace() {
+            let separator = usize::from(self.space_pending && !self.page.text.is_empty());
+            if self.page.text.len() + separator + word.len() > limit {
+                self.page.text_truncated = true;
+                return;
+            }
+            if separator == 1 {
+                self.page.text.push(' ');
+            }
+            self.page.text.push_str(word);
+            self.space_pending = true;
+        }
+        // A word split across chunks of one text node continues without a space
+        self.space_pending = last_in_node || text.ends_with(char::is_whitespace);
+    }
+}
+
+fn discard(_: &[u8]) {}
+
+/// Extracts a page chunk by chunk as it is written
+///
+/// The tokenizer isn't `Send`, so the extractor stays on the thread that
+/// created it; [`extract_response`] runs it on a thread of its own.
+pub struct StreamingExtractor {
+    rewriter: HtmlRewriter<'static, fn(&[u8])>,
+    state: Rc<RefCell<State>>,
+}
+
+impl StreamingExtractor {
+    /// Extractor for the page at `base_url`, which relative links resolve against
+    pub fn new(base_url: &str, limits: StreamingLimits) -> Result<Self> {
+        let base = url::Url::parse(base_url).context("Invalid page URL")?;
+        let state = Rc::new(RefCell::new(State::default()));
+
+        let title_state = state.clone();
+        let meta_state = state.clone();
+        let link_state = state.clone();
+        let anchor_state = state.clone();
+        let html_state = state.clone();
+        let text_state = state.clone();
+        let link_base = base.clone();
+        let handlers = vec![
+            text!("title", move |chunk| {
+                let mut state = title_state.borrow_mut();
+                if state.title.len() < 1024 {
+                    state.title.push_str(chunk.as_str());
+                }
+                Ok(())
+            }),
+            element!("meta[content]", move |element| {
+                let mut state = meta_state.borrow_mut();
+                if state.page.metadata.len() >= limits.max_meta_tags {
+                    return Ok(());
+                }
+                let key = element
+                    .get_attribute("name")
+                    .or_else(|| element.get_attribute("property"))
+                    .map(|key| key.to_lowercase());
+                if let (Some(key), Some(content)) = (key, element.get_attribute("content")) {
+                    let content = decode_entities(content.trim());
+                    if is_safe_metadata_key(&key) && is_safe_content(&content) {
+                        state.page.metadata.entry(key.into()).or_insert(content);
+                    }
+                }
+                Ok(())
+            }),
+            element!("link[rel][href]", move |element| {
+                let is_canonical = element.get_attribute("rel").is_some_and(|rel| {
+                    rel.split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("canonical"))
+                });
+                let mut state = link_state.borrow_mut();
+                if is_canonical && state.page.canonical_url.is_none() {
+                    state.page.canonical_url = element
+                        .get_attribute("href")
+                        .and_then(|href| link_base.join(href.trim()).ok())
+                        .filter(|url| matches!(url.scheme(), "http" | "https"))
+                        .map(String::from);
+                }
+                Ok(())
+            }),
+            element!("a[href]", move |element| {
+                let mut state = anchor_state.borrow_mut();
+                if state.page.links.len() >= limits.max_links {
+                    return Ok(());
+                }
+                if let Some(link) = element
+                    .get_attribute("href")
+                    .and_then(|href| base.join(decode_entities(href.trim()).as_str()).ok())
+                    .filter(|url| matches!(url.scheme(), "http" | "https"))
+                {
+                    state.page.links.push(link.into());
+                }
+                Ok(())
+            }),
+            element!("html[lang]", move |element| {
+                html_state.borrow_mut().page.language = element.get_attribute("lang");
+                Ok(())
+            }),
+            text!("body", move |chunk| {
+                // Script and style contents are not text
+                if chunk.text_type() == TextType::Data {
+                    text_state.borrow_mut().push_text(
+                        chunk.as_str(),
+                        chunk.last_in_text_node(),
+                        limits.max_text_bytes,
+                    );
+                }
+                Ok(())
+            }),
+        ];
+
+        let rewriter = HtmlRewriter::new(
+            Settings {
+                element_content_handlers: handlers,
+                memory_settings: MemorySettings {
+                    max_allowed_memory_usage: limits.max_parser_memory,
+                    ..MemorySettings::default()
+                },
+                ..Settings::default()
+            },
+            discard as fn(&[u8]),
+        );
+        Ok(Self { rewriter, state })
+    }
+
+    /// Tokenize the next chunk of the page
+    pub fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        self.state.borrow_mut().page.bytes += chunk.len();
+        self.rewriter.write(chunk)?;
+        Ok(())
+    }
+
+    /// Finish the page and return what was extracted
+    pub fn finish(self) -> Result<StreamedPage> {
+        self.rewriter.end()?;
+        let state = Rc::try_unwrap(self.state)
+            .ok()
+            .context("Streaming handlers still hold the page state")?
+            .into_inner();
+        let mut page = state.page;
+        let title = decode_entities(
+            state
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .as_str(),
+        );
+        page.title = (!title.is_empty()).then_some(title);
+        Ok(page)
+    }
+}
+
+/// Extract `response`, whose first bytes `head` were already read, without
+/// holding its body in memory
+pub async fn extract_response(
+    url: &str,
+    head: Vec<u8>,
+    mut response: reqwest::Response,
+    limits: StreamingLimits,
+) -> Result<StreamedPage> {
+    // A small bounded channel keeps at most a few chunks in flight
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Bytes>(4);
+    let (result_sender, result) = tokio::sync::oneshot::channel();
+    let base_url = url.to_string();
+    std::thread::spawn(move || {
+        let extracted = (|| {
+            let mut extractor = StreamingExtractor::new(&base_url, limits)?;
+            while let Some(chunk) = receiver.blocking_recv() {
+                extractor.write(&chunk)?;
+            }
+            extractor.finish()
+        })();
+        let _ = result_sender.send(extracted);
+    });
+
+    let mut next = Some(Bytes::from(head));
+    while let Some(chunk) = next {
+        // The extractor stopped early, e.g. on its memory limit; its error is reported below
+        if sender.send(chunk).await.is_err() {
+            break;
+        }
+        next = response.chunk().await?;
+    }
+    drop(sender);
+    result
+        .await
+        .context("Streaming extractor thread panicked")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_across_chunk_boundaries() {
+        let html = r#"<html lang="de"><head><title>Large &amp; slow</title>
+            <meta name="description" content="A very long page">
+            <meta property="og:type" content="website">
+            <link rel="canonical" href="/archive/all"></head>
+            <body><h1>Archive</h1><script>var skipped = 1;</script>
+            <p>First entry</p><a href="/entry/1">One</a><a href="mailto:x@example.com">Mail</a>
+            <p>Second entry</p><a href="https://other.example/2">Two</a></body></html>"#;
+        let mut extractor =
+            StreamingExtractor::new("https://example.com/archive", StreamingLimits::default())
+                .unwrap();
+        // Tiny chunks split tags, attributes and words
+        for chunk in html.as_bytes().chunks(7) {
+            extractor.write(chunk).unwrap();
+        }
+        let page = extractor.finish().unwrap();
+
+        assert_eq!(page.title.as_deref(), Some("Large & slow"));
+        assert_eq!(page.language.as_deref(), Some("de"));
+        assert_eq!(page.metadata["description"], "A very long page");
+        assert_eq!(page.metadata["og:type"], "website");
+        assert_eq!(
+            page.canonical_url.as_deref(),
+            Some("https://example.com/archive/all")
+        );
+        assert_eq!(
+            page.links,
+            vec!["https://example.com/entry/1", "https://other.example/2"]
+        );
+        assert_eq!(page.text, "Archive First entry One Mail Second entry Two");
+        assert_eq!(page.bytes, html.len());
+
+        let limits = StreamingLimits {
+            max_text_bytes: 16,
+            ..StreamingLimits::default()
+        };
+        let mut extractor = StreamingExtractor::new("https://example.com/", limits).unwrap();
+        extractor.write(html.as_bytes()).unwrap();
+        let page = extractor.finish().unwrap();
+        assert_eq!(page.text, "Archive First");
+        assert!(page.text_truncated);
+    }
+
+    #[tokio::test]
+    async fn test_extracts_response_while_downloading() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let rows = "<tr><td><a href=\"/row\">row</a></td></tr>".repeat(20_000);
+        let html = format!(
+            "<html><head><title>Export</title></head><body><table>{}</table></body></html>",
+            rows
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(html.clone()))
+            .mount(&server)
+            .await;
+
+        let mut response = reqwest::get(server.uri()).await.unwrap();
+        let head = response.chunk().await.unwrap().unwrap().to_vec();
+        let limits = StreamingLimits {
+            max_text_bytes: 1024,
+            max_links: 100,
+            ..StreamingLimits::default()
+        };
+        let page = extract_response(&server.uri(), head, response, limits)
+            .await
+            .unwrap();
+        assert_eq!(page.title.as_deref(), Some("Export"));
+        assert_eq!(page.bytes, html.len());
+        assert_eq!(page.links.len(), 100);
+        assert!(page.text.len() <= 1024);
+        assert!(page.text_truncated);
+    }
+}
@@ -0,0 +1,444 @@
+//! Honeypot and crawler-trap avoidance for followed links
+//!
+//! Sites bait crawlers with links no visitor can see (`display:none`,
+//! zero-size anchors, hidden containers) and ban whoever follows them, and
+//! calendars, faceted search and session IDs in paths produce endless URLs
+//! that all serve the same few pages. [`page_links`] collects a page's links
+//! with whether they are visible, and [`LinkGuard`] decides which of them are
+//! worth following: hidden links are skipped, links that look like bait are
+//! recorded as suspected honeypots, calendar pages far from today and
+//! repeating paths are dropped, and no URL template may produce more than a
+//! set number of links.
+
+use crate::extractors::{decode_entities, element_inner_html, html_tags, HtmlTag};
+use chrono::Datelike;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+/// Inline styles that keep an element from being seen
+static HIDDEN_STYLE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"display:none|visibility:hidden|(?:^|;)opacity:0(?:\.0+)?(?:;|$)|(?:^|;)(?:max-)?(?:width|height|font-size):0(?:px|em|rem|%)?(?:;|$)|(?:^|;)(?:left|top|text-indent):-\d{3,}",
+    )
+    .unwrap()
+});
+
+/// URL words that only show up in links planted for crawlers
+static TRAP_URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)honey-?pot|(?:bot|spider|crawler)-?trap|bad-?bots?\b|blackhole").unwrap()
+});
+
+/// URLs of calendar-like pages, which link to the next period forever
+static CALENDAR_URL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)calendar|/events?/|agenda|schedule|[?&](?:date|day|week|month|year|cal)=")
+        .unwrap()
+});
+
+static YEAR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b((?:19|20)\d{2})\b").unwrap());
+
+/// Classes sites use to hide elements
+const HIDDEN_CLASSES: &[&str] = &["hidden", "d-none", "invisible", "honeypot", "hp-link"];
+
+/// Elements whose inner HTML can be scoped by [`element_inner_html`]
+const CONTAINER_TAGS: &[&str] = &[
+    "div", "span", "p", "ul", "ol", "li", "section", "aside", "nav", "footer", "header", "table",
+    "tr", "td",
+];
+
+/// A link found on a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLink {
+    /// Absolute URL, fragment removed
+    pub url: String,
+    /// Lowercased `rel` tokens
+    pub rel: Vec<String>,
+    /// Whether the anchor, or an element around it, is hidden from visitors
+    pub hidden: bool,
+}
+
+impl PageLink {
+    pub fn is_nofollow(&self) -> bool {
+        self.rel.iter().any(|rel| rel == "nofollow")
+    }
+}
+
+/// Every `http(s)` link on a page, resolved against `base_url`
+pub fn page_links(html: &str, base_url: &str) -> Vec<PageLink> {
+    let Ok(base) = url::Url::parse(base_url) else {
+        return Vec::new();
+    };
+    let tags: Vec<HtmlTag> = html_tags(html).collect();
+    // Byte ranges of hidden elements; anchors opened inside them are hidden too
+    let hidden_ranges: Vec<(usize, usize)> = tags
+        .iter()
+        .filter(|tag| CONTAINER_TAGS.contains(&tag.name.as_str()) && is_hidden(tag))
+        .map(|tag| (tag.end, tag.end + element_inner_html(html, tag).len()))
+        .collect();
+
+    tags.iter()
+        .filter(|tag| tag.name == "a")
+        .filter_map(|tag| {
+            let href = decode_entities(tag.attributes.get("href")?.trim());
+            let mut url = base.join(&href).ok()?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return None;
+            }
+            url.set_fragment(None);
+            let inside_hidden = hidden_ranges
+                .iter()
+                .any(|(start, end)| (*start..*end).contains(&tag.start));
+            Some(PageLink {
+                url: url.to_string(),
+                rel: tag
+                    .attributes
+                    .get("rel")
+                    .map(|rel| {
+                        rel.split_whitespace()
+                            .map(str::to_ascii_lowercase)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                hidden: inside_hidden
+                    || is_hidden(tag)
+                    || element_inner_html(html, tag).trim().is_empty(),
+            })
+        })
+        .collect()
+}
+
+/// Whether a tag's own attributes hide it
+fn is_hidden(tag: &HtmlTag) -> bool {
+    let attribute = |name: &str| tag.attributes.get(name).map(|value| value.trim());
+    if tag.attributes.contains_key("hidden") {
+        return true;
+    }
+    // `aria-hidden` alone is common on icon links; out of the tab order as well it is bait
+    if attribute("aria-hidden") == Some("true") && attribute("tabindex") == Some("-1") {
+        return true;
+    }
+    if let Some(style) = attribute("style") {
+        let style: String = style
+            .to_ascii_lowercase()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        if HIDDEN_STYLE_REGEX.is_match(&style) {
+            return true;
+        }
+    }
+    attribute("class").is_some_and(|class| {
+        class
+            .split_whitespace()
+            .any(|class| HIDDEN_CLASSES.contains(&class.to_ascii_lowercase().as_str()))
+    })
+}
+
+/// Why a link was not followed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Invisible to visitors
+    Hidden,
+    /// Suspected bait planted for crawlers
+    Honeypot,
+    /// Marked `rel="nofollow"` while nofollow is respected
+    Nofollow,
+    /// Calendar page outside the allowed window around today
+    Calendar,
+    /// Path segments that repeat, as relative links on a misconfigured site do
+    RepeatingPath,
+    /// Its URL template already produced as many links as allowed
+    TemplateCap,
+    /// URL too long to be a page anyone links to on purpose
+    TooLong,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::Hidden => "hidden",
+            SkipReason::Honeypot => "honeypot",
+            SkipReason::Nofollow => "nofollow",
+            SkipReason::Calendar => "calendar",
+            SkipReason::RepeatingPath => "repeating_path",
+            SkipReason::TemplateCap => "template_cap",
+            SkipReason::TooLong => "too_long",
+        }
+    }
+}
+
+/// A link that looked like crawler bait
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Honeypot {
+    pub url: String,
+    /// Page the link was found on
+    pub found_on: String,
+    pub reason: &'static str,
+}
+
+const MAX_URL_LEN: usize = 2000;
+
+/// Decides which links a crawl follows, see the [module docs](self)
+///
+/// The per-template counts and the honeypots seen are kept for the guard's
+/// lifetime, so one guard should live as long as the crawl it protects.
+#[derive(Debug)]
+pub struct LinkGuard {
+    max_per_template: usize,
+    calendar_years: i32,
+    respect_nofollow: bool,
+    templates: Mutex<HashMap<String, usize>>,
+    honeypots: Mutex<Vec<Honeypot>>,
+}
+
+impl LinkGuard {
+    pub fn new() -> Self {
+        Self {
+            max_per_template: 500,
+            calendar_years: 1,
+            respect_nofollow: false,
+            templates: Mutex::new(HashMap::new()),
+            honeypots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Links one URL template may produce before the rest are skipped
+    pub fn with_max_per_template(mut self, max: usize) -> Self {
+        self.max_per_template = max;
+        self
+    }
+
+    /// Years either side of the current one a calendar link may point at
+    pub fn with_calendar_years(mut self, years: i32) -> Self {
+        self.calendar_years = years;
+        self
+    }
+
+    /// Skip every `rel="nofollow"` link, not only hidden ones
+    pub fn with_respect_nofollow(mut self, respect: bool) -> Self {
+        self.respect_nofollow = respect;
+        self
+    }
+
+    /// Check how a link found on `from` is presented, recording it if it
+    /// looks like a honeypot; [`Self::check_url`] checks where it leads
+    pub fn check_link(&self, from: &str, link: &PageLink) -> Result<(), SkipReason> {
+        let bait = if TRAP_URL_REGEX.is_match(&link.url) {
+            Some("trap_url")
+        } else if link.hidden && link.is_nofollow() {
+            Some("hidden_nofollow")
+        } else {
+            None
+        };
+        if let Some(reason) = bait {
+            self.honeypots.lock().unwrap().push(Honeypot {
+                url: link.url.clone(),
+                found_on: from.to_string(),
+                reason,
+            });
+            return Err(SkipReason::Honeypot);
+        }
+        if link.hidden {
+            return Err(SkipReason::Hidden);
+        }
+        if self.respect_nofollow && link.is_nofollow() {
+            return Err(SkipReason::Nofollow);
+        }
+        Ok(())
+    }
+
+    /// The checks that only need the URL; a URL that passes counts towards its template's cap
+    pub fn check_url(&self, link: &str) -> Result<(), SkipReason> {
+        if link.len() > MAX_URL_LEN {
+            return Err(SkipReason::TooLong);
+        }
+        if TRAP_URL_REGEX.is_match(link) {
+            return Err(SkipReason::Honeypot);
+        }
+        let Ok(url) = url::Url::parse(link) else {
+            return Ok(());
+        };
+        if has_repeating_segments(&url) {
+            return Err(SkipReason::RepeatingPath);
+        }
+        if self.outside_calendar_window(link) {
+            return Err(SkipReason::Calendar);
+        }
+
+        let mut templates = self.templates.lock().unwrap();
+        let count = templates.entry(url_template(&url)).or_insert(0);
+        if *count >= self.max_per_template {
+            return Err(SkipReason::TemplateCap);
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    /// Suspected honeypots seen so far
+    pub fn honeypots(&self) -> Vec<Honeypot> {
+        self.honeypots.lock().unwrap().clone()
+    }
+
+    fn outside_calendar_window(&self, link: &str) -> bool {
+        if !CALENDAR_URL_REGEX.is_match(link) {
+            return false;
+        }
+        let current = chrono::Utc::now().year();
+        YEAR_REGEX.captures_iter(link).any(|captures| {
+            captures[1]
+                .parse::<i32>()
+                .is_ok_and(|year| (year - current).abs() > self.calendar_years)
+        })
+    }
+}
+
+impl Default for LinkGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A path segment appearing three times, e.g. `/a/b/a/b/a/b`
+fn has_repeating_segments(url: &url::Url) -> bool {
+    let Some(segments) = url.path_segments() else {
+        return false;
+    };
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    segments
+        .filter(|segment| !segment.is_empty())
+        .any(|segment| {
+            let count = counts.entry(segment).or_insert(0);
+            *count += 1;
+            *count >= 3
+        })
+}
+
+/// Host and path with digit-bearing segments replaced by `{n}`, plus the
+/// sorted query parameter names
+///
+/// `/events/2031-04?view=month` and `/events/2031-05?view=week` share the
+/// template `example.com/events/{n}?view`.
+pub fn url_template(url: &url::Url) -> String {
+    let path: Vec<&str> = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .map(|segment| {
+                    if segment.chars().any(|c| c.is_ascii_digit()) {
+                        "{n}"
+                    } else {
+                        segment
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let keys: BTreeSet<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+    let mut template = format!("{}/{}", url.host_str().unwrap_or_default(), path.join("/"));
+    if !keys.is_empty() {
+        template.push('?');
+        template.push_str(&keys.into_iter().collect::<Vec<_>>().join("&"));
+    }
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_links_flags_hidden_anchors() {
+        let html = r#"
+            <a href="/about">About</a>
+            <a href="/trap-a" style="display: none">x</a>
+            <a href="/trap-b"></a>
+            <div class="footer hidden"><a href="/trap-c">Archive</a></div>
+            <a href="/icon" aria-hidden="true"><svg></svg></a>
+            <a href="mailto:hi@example.com">Mail</a>
+            <a href="/about#team" rel="nofollow">Team</a>
+        "#;
+        let links = page_links(html, "https://example.com/index.html");
+        let visible: Vec<&str> = links
+            .iter()
+            .filter(|link| !link.hidden)
+            .map(|link| link.url.as_str())
+            .collect();
+        assert_eq!(
+            visible,
+            vec![
+                "https://example.com/about",
+                "https://example.com/icon",
+                "https://example.com/about"
+            ]
+        );
+        assert_eq!(links.len(), 6);
+        assert!(links[5].is_nofollow());
+    }
+
+    #[test]
+    fn test_guard_marks_honeypots() {
+        let guard = LinkGuard::new();
+        let html = r#"
+            <a href="/products">Products</a>
+            <a href="/secret" rel="nofollow" style="position:absolute;left:-9999px">.</a>
+            <a href="/bot-trap/">Do not follow</a>
+            <span style="visibility:hidden"><a href="/hidden">Hidden</a></span>
+        "#;
+        let results: Vec<_> = page_links(html, "https://example.com/")
+            .iter()
+            .map(|link| guard.check_link("https://example.com/", link))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err(SkipReason::Honeypot),
+                Err(SkipReason::Honeypot),
+                Err(SkipReason::Hidden)
+            ]
+        );
+        let honeypots = guard.honeypots();
+        assert_eq!(honeypots.len(), 2);
+        assert_eq!(honeypots[0].url, "https://example.com/secret");
+        assert_eq!(honeypots[0].reason, "hidden_nofollow");
+        assert_eq!(honeypots[1].found_on, "https://example.com/");
+    }
+
+    #[test]
+    fn test_guard_skips_infinite_spaces() {
+        let guard = LinkGuard::new().with_max_per_template(2);
+        let year = chrono::Utc::now().year();
+
+        let this_month = format!("https://example.com/calendar?month={}-04", year);
+        let far_future = format!("https://example.com/calendar?month={}-04", year + 5);
+        assert_eq!(guard.check_url(&this_month), Ok(()));
+        assert_eq!(guard.check_url(&far_future), Err(SkipReason::Calendar));
+        // Dated blog archives are not calendars
+        assert_eq!(
+            guard.check_url("https://example.com/2009/05/launch"),
+            Ok(())
+        );
+
+        assert_eq!(
+            guard.check_url("https://example.com/a/b/a/b/a/b"),
+            Err(SkipReason::RepeatingPath)
+        );
+
+        assert_eq!(
+            guard.check_url("https://example.com/item/1?sort=asc"),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check_url("https://example.com/item/2?sort=desc"),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check_url("https://example.com/item/3?sort=asc"),
+            Err(SkipReason::TemplateCap)
+        );
+        assert_eq!(guard.check_url("https://example.com/item/3"), Ok(()));
+    }
+}
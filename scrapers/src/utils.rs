@@ -3,9 +3,16 @@
 //! This module provides common utilities for URL processing, rate limiting,
 //! and other scraping-related operations.
 
+use crate::metadata::Metadata;
 use anyhow::Result;
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::OnceCell, time::sleep};
 
 /// Rate limiter for controlling request frequency
 pub struct RateLimiter {
@@ -36,21 +43,41 @@ impl RateLimiter {
     }
 }
 
+/// Query parameters that only track where a visitor came from
+const TRACKING_PARAMS: &[&str] = &[
+    "gclid", "dclid", "fbclid", "msclkid", "yclid", "mc_cid", "mc_eid", "_ga", "igshid",
+];
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
 /// Normalize URLs by removing fragments and common tracking parameters
+///
+/// Scheme and host are lowercased and default ports dropped, so variants of
+/// the same page compare equal.
 pub fn normalize_url(url: &str) -> String {
-    let mut normalized = url.to_string();
-
-    // Remove fragment identifier
-    if let Some(fragment_pos) = normalized.find('#') {
-        normalized.truncate(fragment_pos);
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        // Not an absolute URL; only the fragment can be dropped safely
+        return url.split('#').next().unwrap_or_default().to_string();
+    };
+    parsed.set_fragment(None);
+
+    if parsed.query().is_some() {
+        let kept: Vec<(String, String)> = parsed
+            .query_pairs()
+            .filter(|(name, _)| !is_tracking_param(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(kept);
+        }
     }
 
-    // TODO: Add more normalization rules as needed
-    // - Remove common tracking parameters
-    // - Normalize case
-    // - Remove trailing slashes
-
-    normalized
+    parsed.to_string()
 }
 
 /// Check if a URL is valid and accessible
@@ -64,10 +91,13 @@ pub fn extract_domain(url: &str) -> Result<String> {
     Ok(url.host_str().unwrap_or("").to_string())
 }
 
-/// Generate a realistic user agent string
+/// Generate a realistic desktop user agent string, picked by weight from the
+/// built-in [`UserAgentPool`](crate::anti_bot::user_agents::UserAgentPool)
 pub fn generate_user_agent() -> String {
-    // In a real implementation, this would rotate between different user agents
-    "Mozilla/5.0 (X11; Linux x86_64; rv:91.0) Gecko/20100101 Firefox/91.0".to_string()
+    crate::anti_bot::user_agents::UserAgentPool::shared()
+        .pick(crate::anti_bot::vendors::FingerprintKind::Desktop, &[])
+        .user_agent
+        .clone()
 }
 
 /// Sleep for a random duration to avoid detection
@@ -79,16 +109,23 @@ pub async fn random_delay(min_ms: u64, max_ms: u64) {
     sleep(Duration::from_millis(delay_ms)).await;
 }
 
+// Case-insensitive, so pages aren't copied just to be lowercased
+static BOT_PROTECTION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)captcha|cloudflare|access denied|blocked").unwrap());
+static ROBOT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)robot").unwrap());
+static DETECTED_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)detected").unwrap());
+
 /// Detect if content is likely to be bot-protected
+///
+/// Signatures learned from a block page corpus and
+/// [installed](crate::anti_bot::block_pages::install) count too.
 pub fn is_bot_protected(html: &str) -> bool {
-    let html_lower = html.to_lowercase();
-
     // Common indicators of bot protection
-    html_lower.contains("captcha")
-        || html_lower.contains("cloudflare")
-        || html_lower.contains("access denied")
-        || html_lower.contains("blocked")
-        || html_lower.contains("robot") && html_lower.contains("detected")
+    BOT_PROTECTION_REGEX.is_match(html)
+        || ROBOT_REGEX.is_match(html) && DETECTED_REGEX.is_match(html)
+        || crate::anti_bot::block_pages::installed()
+            .classify(html)
+            .is_some()
 }
 
 /// Parse robots.txt content
@@ -167,6 +204,158 @@ impl Default for RobotsTxt {
     }
 }
 
+/// robots.txt files fetched once per origin and reused for every URL
+#[derive(Debug, Default)]
+pub struct RobotsCache {
+    client: reqwest::Client,
+    origins: std::sync::Mutex<HashMap<String, Arc<OnceCell<Option<RobotsTxt>>>>>,
+}
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the robots.txt of `url`'s origin allows fetching it
+    ///
+    /// A missing or unreachable robots.txt allows everything.
+    pub async fn is_allowed(&self, url: &str) -> bool {
+        let Ok(url) = url::Url::parse(url) else {
+            return true;
+        };
+        let origin = url.origin().ascii_serialization();
+        let cell = self
+            .origins
+            .lock()
+            .unwrap()
+            .entry(origin.clone())
+            .or_default()
+            .clone();
+        let robots = cell
+            .get_or_init(|| async {
+                let response = self
+                    .client
+                    .get(format!("{}/robots.txt", origin))
+                    .timeout(Duration::from_secs(10))
+                    .send()
+                    .await
+                    .ok()
+                    .filter(|response| response.status().is_success())?;
+                Some(parse_robots_txt(&response.text().await.ok()?))
+            })
+            .await;
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        robots.as_ref().is_none_or(|robots| robots.is_allowed(&path))
+    }
+}
+
+/// Page-level directives from `<meta name="robots">` tags and `X-Robots-Tag`
+/// headers
+///
+/// Only directives addressed to every crawler, or to `swoop` by name, apply;
+/// `googlebot: noindex` does not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RobotsDirectives {
+    /// Don't keep the page's content
+    pub noindex: bool,
+    /// Don't follow the page's links
+    pub nofollow: bool,
+    /// Don't keep a copy of the raw page
+    pub noarchive: bool,
+}
+
+/// Directives that carry a `name: value` argument, not a user agent prefix
+const VALUED_ROBOTS_DIRECTIVES: &[&str] = &[
+    "unavailable_after",
+    "max-snippet",
+    "max-image-preview",
+    "max-video-preview",
+];
+
+impl RobotsDirectives {
+    /// Directives of a page's meta tags and its `X-Robots-Tag` header, where
+    /// repeated headers were joined with `, `
+    pub fn from_page(html: &str, x_robots_tag: Option<&str>) -> Self {
+        let mut directives = Self::default();
+        for tag in crate::extractors::html_tags(html).filter(|tag| tag.name == "meta") {
+            let name = tag.attributes.get("name").map(|name| name.to_ascii_lowercase());
+            if let (Some("robots" | "swoop"), Some(content)) =
+                (name.as_deref(), tag.attributes.get("content"))
+            {
+                directives.add(content, false);
+            }
+        }
+        if let Some(header) = x_robots_tag {
+            directives.add(header, true);
+        }
+        directives
+    }
+
+    /// Directives of extracted metadata: the `robots`/`swoop` meta tags and
+    /// the `http:x-robots-tag` header recorded by the fetch
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let mut directives = Self::default();
+        for name in ["robots", "swoop"] {
+            if let Some(content) = metadata.get(name) {
+                directives.add(content, false);
+            }
+        }
+        if let Some(header) = metadata.get("http:x-robots-tag") {
+            directives.add(header, true);
+        }
+        directives
+    }
+
+    /// Add the comma-separated directives of `value`; header values may
+    /// prefix a run of directives with the user agent they address
+    fn add(&mut self, value: &str, agent_prefixes: bool) {
+        let mut applies = true;
+        for directive in value.split(',') {
+            let mut directive = directive.trim().to_ascii_lowercase();
+            if let Some((prefix, rest)) = directive.split_once(':') {
+                let prefix = prefix.trim();
+                if agent_prefixes && !VALUED_ROBOTS_DIRECTIVES.contains(&prefix) {
+                    applies = matches!(prefix, "*" | "swoop");
+                    directive = rest.trim().to_string();
+                }
+            }
+            if !applies {
+                continue;
+            }
+            match directive.as_str() {
+                "noindex" => self.noindex = true,
+                "nofollow" => self.nofollow = true,
+                "noarchive" => self.noarchive = true,
+                "none" => {
+                    self.noindex = true;
+                    self.nofollow = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Names of the directives set, e.g. `["noindex", "nofollow"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.noindex, "noindex"),
+            (self.nofollow, "nofollow"),
+            (self.noarchive, "noarchive"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,6 +372,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalize_url_drops_tracking() {
+        assert_eq!(
+            normalize_url("HTTPS://Example.COM:443/a?utm_source=x&id=7&fbclid=abc#top"),
+            "https://example.com/a?id=7"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/a?utm_medium=email"),
+            "https://example.com/a"
+        );
+        assert_eq!(normalize_url("/relative#frag"), "/relative");
+    }
+
     #[test]
     fn test_is_valid_url() {
         assert!(is_valid_url("https://example.com"));
@@ -203,6 +405,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_robots_directives() {
+        let html = r#"<meta name="robots" content="noindex, follow"><meta name="description" content="none">"#;
+        let directives = RobotsDirectives::from_page(html, None);
+        assert_eq!(directives.names(), vec!["noindex"]);
+
+        let header = "googlebot: nofollow, unavailable_after: 25 Jun 2010 15:00:00 PST, *: noarchive";
+        let directives = RobotsDirectives::from_page("<p>Hi</p>", Some(header));
+        assert_eq!(directives.names(), vec!["noarchive"]);
+
+        let directives = RobotsDirectives::from_page("", Some("none"));
+        assert!(directives.noindex && directives.nofollow);
+        assert!(RobotsDirectives::from_page("<p>Hi</p>", None).is_empty());
+    }
+
     #[test]
     fn test_is_bot_protected() {
         assert!(is_bot_protected("This page contains a CAPTCHA"));
@@ -252,4 +469,26 @@ mod tests {
         // Should have waited at least 100ms between requests
         assert!(elapsed >= Duration::from_millis(100));
     }
+
+    #[tokio::test]
+    async fn test_robots_cache() {
+        use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/robots.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "User-agent: *\nDisallow: /private\nDisallow: /search?q=",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let robots = RobotsCache::new();
+        let url = |path: &str| format!("{}{}", server.uri(), path);
+        assert!(robots.is_allowed(&url("/article")).await);
+        assert!(!robots.is_allowed(&url("/private/page")).await);
+        assert!(!robots.is_allowed(&url("/search?q=rust")).await);
+        // Unreachable robots.txt allows everything
+        assert!(robots.is_allowed("http://127.0.0.1:9/private").await);
+    }
 }
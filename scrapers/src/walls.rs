@@ -0,0 +1,322 @@
+//! Login, consent and paywall detection
+//!
+//! Many sites answer a plain HTTP fetch with a page that only asks the visitor
+//! to sign in, accept cookies or subscribe. Storing that HTML is useless, so
+//! [`WallDetector`] flags such responses and callers can retry them through the
+//! browser flow instead.
+
+use crate::classifier::PageSignals;
+use crate::metadata::Metadata;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static PAYWALL_MARKUP_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:class|id)\s*=\s*["'][^"']*(?:paywall|regwall|meteredcontent|tp-modal|piano-offer)"#)
+        .unwrap()
+});
+static NOT_FREE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)"isAccessibleForFree"\s*:\s*"?false"#).unwrap());
+static CONTENT_TIER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)<meta[^>]*(?:article:content_tier|content-tier)[^>]*content\s*=\s*["'](?:locked|metered)"#)
+        .unwrap()
+});
+
+/// Matches any of [`CONSENT_MARKERS`] without lowercasing the whole page
+static CONSENT_MARKER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    let markers: Vec<String> = CONSENT_MARKERS.iter().map(|m| regex::escape(m)).collect();
+    Regex::new(&format!("(?i){}", markers.join("|"))).unwrap()
+});
+
+/// Markers of common consent management platforms
+const CONSENT_MARKERS: &[&str] = &[
+    "onetrust-banner-sdk",
+    "cybotcookiebotdialog",
+    "qc-cmp2",
+    "didomi-popup",
+    "sp_message_container",
+    "truste-consent",
+    "cmpwrapper",
+    "usercentrics",
+];
+
+const CONSENT_PHRASES: &[&str] = &[
+    "before you continue",
+    "we value your privacy",
+    "accept all cookies",
+    "manage your cookie preferences",
+    "your privacy choices",
+];
+
+const CONSENT_HOST_PREFIXES: &[&str] = &["consent.", "guce.", "privacy-gate."];
+
+const LOGIN_PATH_MARKERS: &[&str] = &[
+    "/login",
+    "/signin",
+    "/sign-in",
+    "/sign_in",
+    "/authwall",
+    "/accounts/login",
+    "/servicelogin",
+    "/auth/",
+];
+
+const LOGIN_PHRASES: &[&str] = &[
+    "sign in to continue",
+    "log in to continue",
+    "sign in to view",
+    "log in to see",
+    "join to view",
+    "you must be logged in",
+    "login required",
+];
+
+const LOGIN_TITLE_PHRASES: &[&str] = &["sign in", "log in", "login", "sign up"];
+
+const PAYWALL_PHRASES: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribers only",
+    "this article is for subscribers",
+    "already a subscriber",
+    "to continue reading, subscribe",
+    "you have reached your limit of free articles",
+];
+
+/// Kind of wall in front of the real content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WallKind {
+    Login,
+    Consent,
+    Paywall,
+}
+
+impl WallKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WallKind::Login => "login",
+            WallKind::Consent => "consent",
+            WallKind::Paywall => "paywall",
+        }
+    }
+
+    /// Tag stored on results, e.g. `wall:paywall`
+    pub fn as_tag(&self) -> String {
+        format!("wall:{}", self.as_str())
+    }
+}
+
+/// A detected wall and the signals that triggered it
+#[derive(Debug, Clone, PartialEq)]
+pub struct WallDetection {
+    pub kind: WallKind,
+    pub signals: Vec<&'static str>,
+}
+
+impl WallDetection {
+    /// Record the detection as `wall` / `wall_signals` metadata
+    pub fn record(&self, metadata: &mut Metadata) {
+        metadata.insert("wall".into(), self.kind.as_str().to_string());
+        metadata.insert("wall_signals".into(), self.signals.join(","));
+    }
+}
+
+#[derive(Default)]
+struct Evidence {
+    score: u32,
+    signals: Vec<&'static str>,
+}
+
+impl Evidence {
+    fn add(&mut self, present: bool, weight: u32, signal: &'static str) {
+        if present {
+            self.score += weight;
+            self.signals.push(signal);
+        }
+    }
+}
+
+/// Heuristic wall detector
+#[derive(Debug, Clone)]
+pub struct WallDetector {
+    threshold: u32,
+    thin_page_words: usize,
+}
+
+impl Default for WallDetector {
+    fn default() -> Self {
+        Self {
+            threshold: 3,
+            thin_page_words: 250,
+        }
+    }
+}
+
+impl WallDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimum evidence score before a page counts as walled
+    pub fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Pages with fewer words than this count as thin, which backs up weaker signals
+    pub fn with_thin_page_words(mut self, words: usize) -> Self {
+        self.thin_page_words = words;
+        self
+    }
+
+    /// Inspect a response body; `final_url` is the URL after redirects, if known
+    pub fn detect(&self, html: &str, final_url: Option<&str>) -> Option<WallDetection> {
+        let signals = PageSignals::from_html(html, None);
+        let text = crate::extractors::extract_text_secure(html)
+            .unwrap_or_default()
+            .to_lowercase();
+        let thin = signals.word_count < self.thin_page_words;
+
+        let (host, path) = final_url
+            .and_then(|url| url::Url::parse(url).ok())
+            .map(|url| {
+                (
+                    url.host_str().unwrap_or_default().to_lowercase(),
+                    url.path().to_lowercase(),
+                )
+            })
+            .unwrap_or_default();
+
+        let mut login = Evidence::default();
+        login.add(signals.has_password_input, 2, "password-input");
+        login.add(
+            LOGIN_PATH_MARKERS
+                .iter()
+                .any(|marker| path.contains(marker)),
+            2,
+            "login-url",
+        );
+        login.add(
+            LOGIN_TITLE_PHRASES
+                .iter()
+                .any(|phrase| signals.title.contains(phrase)),
+            1,
+            "login-title",
+        );
+        login.add(
+            LOGIN_PHRASES.iter().any(|phrase| text.contains(phrase)),
+            2,
+            "login-prompt",
+        );
+
+        let mut consent = Evidence::default();
+        consent.add(
+            CONSENT_HOST_PREFIXES
+                .iter()
+                .any(|prefix| host.starts_with(prefix)),
+            3,
+            "consent-host",
+        );
+        consent.add(CONSENT_MARKER_REGEX.is_match(html), 1, "consent-platform");
+        consent.add(
+            CONSENT_PHRASES.iter().any(|phrase| text.contains(phrase)),
+            1,
+            "consent-prompt",
+        );
+
+        let mut paywall = Evidence::default();
+        paywall.add(NOT_FREE_REGEX.is_match(html), 3, "not-accessible-for-free");
+        paywall.add(CONTENT_TIER_REGEX.is_match(html), 3, "locked-content-tier");
+        paywall.add(PAYWALL_MARKUP_REGEX.is_match(html), 2, "paywall-markup");
+        paywall.add(
+            PAYWALL_PHRASES.iter().any(|phrase| text.contains(phrase)),
+            2,
+            "subscribe-prompt",
+        );
+
+        // Banners and sign-in links appear on plenty of real pages; thin content
+        // is what turns them into a wall
+        for evidence in [&mut login, &mut consent] {
+            if evidence.score > 0 {
+                evidence.add(thin, 1, "thin-content");
+            }
+        }
+
+        [
+            (WallKind::Login, login),
+            (WallKind::Consent, consent),
+            (WallKind::Paywall, paywall),
+        ]
+        .into_iter()
+        .filter(|(_, evidence)| evidence.score >= self.threshold)
+        .max_by_key(|(_, evidence)| evidence.score)
+        .map(|(kind, evidence)| WallDetection {
+            kind,
+            signals: evidence.signals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article() -> String {
+        let body =
+            "<p>The harbour reopened this week after repairs to the breakwater.</p>".repeat(40);
+        format!(
+            "<html><head><title>Harbour reopens</title></head><body><article>{}</article>\
+             <a href=\"/login\">Sign in</a></body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_detects_login_wall() {
+        let html = r#"<html><head><title>Sign in | Example</title></head><body>
+            <form action="/session"><input type="email" name="email">
+            <input type="password" name="password"><button>Log in</button></form>
+            </body></html>"#;
+
+        let detection = WallDetector::new()
+            .detect(html, Some("https://example.com/login?next=/feed"))
+            .unwrap();
+        assert_eq!(detection.kind, WallKind::Login);
+        assert!(detection.signals.contains(&"password-input"));
+        assert!(detection.signals.contains(&"login-url"));
+    }
+
+    #[test]
+    fn test_detects_paywall_and_consent_wall() {
+        let paywall = format!(
+            r#"<html><head><script type="application/ld+json">
+            {{"@type":"NewsArticle","isAccessibleForFree":"False"}}</script></head>
+            <body><p>First paragraph.</p><div class="article-paywall">Subscribe to continue reading</div>
+            {}</body></html>"#,
+            "<p>filler text for the teaser</p>".repeat(80)
+        );
+        assert_eq!(
+            WallDetector::new().detect(&paywall, None).unwrap().kind,
+            WallKind::Paywall
+        );
+
+        let consent = r#"<html><body><div id="onetrust-banner-sdk">
+            <p>Before you continue, we value your privacy.</p><button>Accept all</button>
+            </div></body></html>"#;
+        let detection = WallDetector::new()
+            .detect(consent, Some("https://consent.example.com/?continue=x"))
+            .unwrap();
+        assert_eq!(detection.kind, WallKind::Consent);
+
+        let mut metadata = Metadata::new();
+        detection.record(&mut metadata);
+        assert_eq!(metadata.get("wall").map(String::as_str), Some("consent"));
+    }
+
+    #[test]
+    fn test_ignores_regular_article() {
+        assert_eq!(
+            WallDetector::new().detect(&article(), Some("https://example.com/news/harbour")),
+            None
+        );
+    }
+}
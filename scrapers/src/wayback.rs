@@ -0,0 +1,270 @@
+//! Internet Archive Wayback Machine client
+//!
+//! [`WaybackClient`] lists the archived captures of a URL through the CDX
+//! API and fetches their original bytes (the `id_` form of a snapshot URL,
+//! without the archive's toolbar and rewritten links). Captures are
+//! collapsed by content digest, so consecutive identical captures come back
+//! once and every snapshot is a distinct version of the page.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, time::Duration};
+use swoop_core::security::UrlValidator;
+
+/// Public Wayback Machine endpoint
+pub const WAYBACK_URL: &str = "https://web.archive.org";
+
+/// Layout of capture timestamps in CDX results and snapshot URLs
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// One archived capture of a URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaybackSnapshot {
+    /// When the archive captured the page
+    pub captured_at: DateTime<Utc>,
+    /// URL as the archive recorded it
+    pub original: String,
+    pub status: Option<u16>,
+    /// Archive's digest of the captured body
+    pub digest: String,
+    pub mime_type: Option<String>,
+    pub length: Option<u64>,
+}
+
+impl WaybackSnapshot {
+    /// Capture timestamp in the archive's `YYYYMMDDhhmmss` form
+    pub fn timestamp(&self) -> String {
+        self.captured_at.format(TIMESTAMP_FORMAT).to_string()
+    }
+
+    /// Snapshot URL as browsed on the archive
+    pub fn snapshot_url(&self, base_url: &str) -> String {
+        format!("{}/web/{}/{}", base_url, self.timestamp(), self.original)
+    }
+
+    /// URL of the captured bytes as originally served
+    pub fn raw_url(&self, base_url: &str) -> String {
+        format!("{}/web/{}id_/{}", base_url, self.timestamp(), self.original)
+    }
+}
+
+/// Capture range and limits of a [`WaybackClient::snapshots`] query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotQuery {
+    /// Earliest capture, as a timestamp prefix such as `2019` or `20190601`
+    pub from: Option<String>,
+    /// Latest capture, as a timestamp prefix
+    pub to: Option<String>,
+    /// Maximum number of snapshots, oldest first
+    pub limit: Option<usize>,
+}
+
+/// Parse a date such as `2019`, `2019-06` or `2019-06-01` into a timestamp prefix
+pub fn timestamp_prefix(date: &str) -> Result<String> {
+    let digits: String = date.chars().filter(|c| *c != '-').collect();
+    if digits.len() < 4 || digits.len() > 14 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        bail!("Invalid date '{}', expected e.g. 2019 or 2019-06-01", date);
+    }
+    Ok(digits)
+}
+
+/// Client for the Wayback Machine's CDX API and snapshots
+pub struct WaybackClient {
+    base_url: String,
+    user_agent: String,
+    timeout: Duration,
+    validator: UrlValidator,
+}
+
+impl WaybackClient {
+    pub fn new(user_agent: &str, timeout: Duration) -> Self {
+        Self {
+            base_url: WAYBACK_URL.to_string(),
+            user_agent: user_agent.to_string(),
+            timeout,
+            validator: UrlValidator::default(),
+        }
+    }
+
+    /// Talk to another archive serving the same API, e.g. a local mirror
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_validator(mut self, validator: UrlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn get(&self, url: &str) -> Result<bytes::Bytes> {
+        let headers = HashMap::from([("User-Agent".to_string(), self.user_agent.clone())]);
+        swoop_core::send_request_with(
+            &self.validator,
+            reqwest::Method::GET,
+            url,
+            &headers,
+            None,
+            self.timeout,
+        )
+        .await
+    }
+
+    /// Successful captures of `url` with distinct content, oldest first
+    pub async fn snapshots(
+        &self,
+        url: &str,
+        query: &SnapshotQuery,
+    ) -> Result<Vec<WaybackSnapshot>> {
+        let mut cdx = url::Url::parse(&format!("{}/cdx/search/cdx", self.base_url))?;
+        {
+            let mut params = cdx.query_pairs_mut();
+            params
+                .append_pair("url", url)
+                .append_pair("output", "json")
+                .append_pair("fl", "timestamp,original,statuscode,digest,mimetype,length")
+                .append_pair("filter", "statuscode:200")
+                .append_pair("collapse", "digest");
+            if let Some(from) = &query.from {
+                params.append_pair("from", from);
+            }
+            if let Some(to) = &query.to {
+                params.append_pair("to", to);
+            }
+            if let Some(limit) = query.limit {
+                params.append_pair("limit", &limit.to_string());
+            }
+        }
+
+        let body = self
+            .get(cdx.as_str())
+            .await
+            .with_context(|| format!("CDX query for {} failed", url))?;
+        // An empty body rather than an empty array means no captures
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Vec::new());
+        }
+        parse_cdx(&serde_json::from_slice(&body).context("Invalid CDX response")?)
+    }
+
+    /// Original body of `snapshot`
+    pub async fn fetch(&self, snapshot: &WaybackSnapshot) -> Result<String> {
+        let body = self.get(&snapshot.raw_url(&self.base_url)).await?;
+        Ok(swoop_core::body_to_string(body))
+    }
+}
+
+/// Rows of a CDX JSON response: a header row naming the fields, then one row
+/// per capture
+fn parse_cdx(response: &Value) -> Result<Vec<WaybackSnapshot>> {
+    let Some(rows) = response.as_array() else {
+        bail!("CDX response is not an array");
+    };
+    let Some((header, rows)) = rows.split_first() else {
+        return Ok(Vec::new());
+    };
+    let columns: HashMap<&str, usize> = header
+        .as_array()
+        .context("CDX header row is not an array")?
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| Some((name.as_str()?, i)))
+        .collect();
+    let field = |row: &Value, name: &str| -> Option<String> {
+        let value = row.get(*columns.get(name)?)?.as_str()?;
+        // The CDX server writes "-" for unknown values
+        (value != "-").then(|| value.to_string())
+    };
+
+    let mut snapshots = Vec::new();
+    for row in rows {
+        let (Some(timestamp), Some(original)) = (field(row, "timestamp"), field(row, "original"))
+        else {
+            continue;
+        };
+        let Ok(captured_at) = NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT) else {
+            continue;
+        };
+        snapshots.push(WaybackSnapshot {
+            captured_at: captured_at.and_utc(),
+            original,
+            status: field(row, "statuscode").and_then(|status| status.parse().ok()),
+            digest: field(row, "digest").unwrap_or_default(),
+            mime_type: field(row, "mimetype"),
+            length: field(row, "length").and_then(|length| length.parse().ok()),
+        });
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.captured_at);
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const CDX_RESPONSE: &str = r#"[
+        ["timestamp", "original", "statuscode", "digest", "mimetype", "length"],
+        ["20200301120000", "https://example.com/pricing", "200", "BBB", "text/html", "-"],
+        ["20190105080000", "http://example.com:80/pricing", "200", "AAA", "text/html", "812"],
+        ["garbage", "https://example.com/pricing", "200", "CCC", "text/html", "1"]
+    ]"#;
+
+    #[test]
+    fn test_timestamp_prefix() {
+        assert_eq!(timestamp_prefix("2019").unwrap(), "2019");
+        assert_eq!(timestamp_prefix("2019-06-01").unwrap(), "20190601");
+        assert!(timestamp_prefix("19").is_err());
+        assert!(timestamp_prefix("June 2019").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_fetches_snapshots() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cdx/search/cdx"))
+            .and(query_param("url", "https://example.com/pricing"))
+            .and(query_param("collapse", "digest"))
+            .and(query_param("from", "2019"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(CDX_RESPONSE))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/web/20190105080000id_/http://example.com:80/pricing"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Plans from $5</h1>"))
+            .mount(&server)
+            .await;
+
+        let host = url::Url::parse(&server.uri()).unwrap();
+        let client = WaybackClient::new("swoop-test", Duration::from_secs(5))
+            .with_base_url(&server.uri())
+            .with_validator(UrlValidator::default().with_allowed_host(host.host_str().unwrap()));
+        let query = SnapshotQuery {
+            from: Some("2019".to_string()),
+            ..SnapshotQuery::default()
+        };
+        let snapshots = client
+            .snapshots("https://example.com/pricing", &query)
+            .await
+            .unwrap();
+
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].timestamp(), "20190105080000");
+        assert_eq!(snapshots[0].length, Some(812));
+        assert_eq!(snapshots[1].digest, "BBB");
+        assert_eq!(snapshots[1].length, None);
+        assert_eq!(
+            client.fetch(&snapshots[0]).await.unwrap(),
+            "<h1>Plans from $5</h1>"
+        );
+    }
+}
@@ -48,3 +48,63 @@ async fn test_fingerprint_profile_completeness() {
     assert!(profile.viewport_data.height > 0);
     assert!(profile.viewport_data.color_depth > 0);
 }
+
+#[tokio::test]
+async fn test_session_fingerprints_are_stable_per_seed() {
+    let manager = FingerprintManager::new().await.unwrap();
+
+    // A session keeps its fingerprint, and the script applying it
+    let session = manager.session_fingerprint(42, None);
+    assert_eq!(session, manager.session_fingerprint(42, None));
+    assert_eq!(
+        session.injection_script(),
+        manager.session_fingerprint(42, None).injection_script()
+    );
+
+    // Other sessions draw their own
+    let renderers: std::collections::HashSet<String> = (0..32)
+        .map(|seed| manager.session_fingerprint(seed, None).webgl_renderer)
+        .collect();
+    assert!(renderers.len() > 1);
+    assert_ne!(
+        session.injection_script(),
+        manager.session_fingerprint(43, None).injection_script()
+    );
+
+    let script = session.injection_script();
+    assert!(script.contains(&session.webgl_renderer));
+    assert!(script.contains("\"seed\":42"));
+    assert!(!script.contains("__FINGERPRINT__"));
+}
+
+#[tokio::test]
+async fn test_session_fingerprints_come_from_one_device() {
+    let manager = FingerprintManager::new().await.unwrap();
+    let mac = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 \
+               (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+    for seed in 0..64 {
+        let session = manager.session_fingerprint(seed, Some(mac));
+        let device = HardwareProfile::named(&session.hardware).unwrap();
+        // Every value is the device's own, on the user agent's system
+        assert_eq!(device.platform, "macOS");
+        assert_eq!(session.navigator_platform, "MacIntel");
+        assert_eq!(session.webgl_vendor, device.webgl_vendor);
+        assert_eq!(session.webgl_renderer, device.webgl_renderer);
+        assert!(device
+            .screens
+            .contains(&(session.screen.width, session.screen.height)));
+        assert_eq!(session.screen.color_depth, device.color_depth);
+        assert_eq!(session.hardware_concurrency, device.hardware_concurrency);
+        assert_eq!(session.device_memory, device.device_memory);
+        assert_eq!(session.audio_sample_rate, device.audio_sample_rate);
+    }
+
+    let laptop = HardwareProfile::named("windows-laptop-budget").unwrap();
+    let manager = manager.with_hardware_profiles(vec![laptop]);
+    let session = manager.session_fingerprint(7, None);
+    assert_eq!(session.hardware, "windows-laptop-budget");
+    assert_eq!((session.screen.width, session.screen.height), (1366, 768));
+    // A user agent no device matches still gets a coherent device
+    assert_eq!(manager.session_fingerprint(7, Some(mac)), session);
+}
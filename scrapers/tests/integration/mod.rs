@@ -0,0 +1,128 @@
+//! End-to-end pipeline tests against the local fixture server
+//!
+//! Pages are fetched from [`FixtureServer`] and run through bot protection
+//! detection, wall detection, classification and extraction, so every test is
+//! deterministic and works offline. Real pages are replayed from the cassettes
+//! in `tests/cassettes`.
+
+use scrapers::{
+    classifier::{ContentClassifier, PageClass},
+    platforms::ScraperRegistry,
+    utils::is_bot_protected,
+    walls::{WallDetector, WallKind},
+};
+use std::time::Duration;
+use swoop_core::cassette::Cassette;
+use swoop_core::test_support::{pages, FixtureServer, RATE_LIMITED_RESPONSES};
+use swoop_core::text::SharedStr;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn fetch_html(server: &FixtureServer, path: &str) -> String {
+    let body = server.fetch(path, TIMEOUT).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_article_extraction_pipeline() {
+    let server = FixtureServer::start().await;
+    let url = server.url("/article");
+    let html = fetch_html(&server, "/article").await;
+
+    assert!(!is_bot_protected(&html));
+    assert!(WallDetector::new().detect(&html, Some(&url)).is_none());
+
+    let content = ScraperRegistry::default()
+        .reprocess(&url, &SharedStr::from(html.as_str()))
+        .await
+        .unwrap();
+    assert_eq!(content.title.as_deref(), Some("Fixture Article"));
+    assert!(content.text.unwrap().contains("predictable output"));
+    assert!(!content.metadata.contains_key("wall"));
+}
+
+#[tokio::test]
+async fn test_redirects_and_gzip_yield_same_page() {
+    let server = FixtureServer::start().await;
+
+    assert_eq!(fetch_html(&server, "/redirect/1").await, pages::ARTICLE);
+    assert_eq!(fetch_html(&server, "/gzip").await, pages::ARTICLE);
+}
+
+#[tokio::test]
+async fn test_rate_limited_route_recovers() {
+    let server = FixtureServer::start().await;
+
+    for _ in 0..RATE_LIMITED_RESPONSES {
+        let html = fetch_html(&server, "/rate-limited").await;
+        assert!(html.contains("Too Many Requests"));
+    }
+    assert_eq!(fetch_html(&server, "/rate-limited").await, pages::ARTICLE);
+}
+
+#[tokio::test]
+async fn test_cloudflare_challenge_is_detected() {
+    let server = FixtureServer::start().await;
+    let html = fetch_html(&server, "/cloudflare").await;
+
+    assert!(is_bot_protected(&html));
+    let classification = ContentClassifier::new().classify_html(&html, Some(403));
+    assert!(classification.is_junk());
+}
+
+#[tokio::test]
+async fn test_login_wall_is_flagged() {
+    let server = FixtureServer::start().await;
+    let url = server.url("/login");
+    let html = fetch_html(&server, "/login").await;
+
+    let wall = WallDetector::new().detect(&html, Some(&url)).unwrap();
+    assert_eq!(wall.kind, WallKind::Login);
+    assert_eq!(
+        ContentClassifier::new()
+            .classify_html(&html, Some(200))
+            .primary(),
+        Some(PageClass::LoginWall)
+    );
+
+    let content = ScraperRegistry::default()
+        .reprocess(&url, &SharedStr::from(html.as_str()))
+        .await
+        .unwrap();
+    assert_eq!(
+        content.metadata.get("wall").map(String::as_str),
+        Some("login")
+    );
+}
+
+#[tokio::test]
+async fn test_missing_page_is_error_page() {
+    let server = FixtureServer::start().await;
+    let html = fetch_html(&server, "/not-found").await;
+
+    assert_eq!(
+        ContentClassifier::new()
+            .classify_html(&html, Some(404))
+            .primary(),
+        Some(PageClass::ErrorPage)
+    );
+}
+
+#[tokio::test]
+async fn test_replayed_cassette_extraction() {
+    let cassette = Cassette::open(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/cassettes/example_com.json"
+    ))
+    .unwrap();
+    let url = "https://example.com/";
+    let html = cassette.fetch(url, TIMEOUT).await.unwrap();
+    cassette.save().unwrap();
+
+    let content = ScraperRegistry::default()
+        .reprocess(url, &SharedStr::from_body(html))
+        .await
+        .unwrap();
+    assert_eq!(content.title.as_deref(), Some("Example Domain"));
+    assert!(content.text.unwrap().contains("illustrative examples"));
+}
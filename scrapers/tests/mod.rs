@@ -5,7 +5,7 @@
 //! and performance benchmarks.
 
 pub mod anti_bot;
+pub mod integration;
 // TODO: Implement remaining test modules
-// pub mod integration;
 // pub mod benchmarks;
 // pub mod utils;
@@ -0,0 +1,385 @@
+//! Binary artifact store
+//!
+//! Images and other media downloaded alongside pages are stored by the SHA-256
+//! digest of their bytes, so a file linked from many pages is kept once.
+//! Artifacts live in a local directory or an S3-compatible bucket:
+//! - `objects/<xx>/<digest>.<ext>` for the files themselves
+//! - `thumbnails/<xx>/<digest>.png` for image thumbnails (`thumbnails` feature)
+//!
+//! Pages reference their artifacts through [`StoredContent::images`](crate::models::StoredContent).
+
+use crate::{quota::DiskQuota, s3_store::S3Client, S3Config};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{path::PathBuf, sync::Arc};
+
+/// Largest artifact stored by default
+pub const DEFAULT_MAX_ARTIFACT_BYTES: u64 = 25 * 1024 * 1024;
+
+const OBJECTS_DIR: &str = "objects";
+#[cfg(feature = "thumbnails")]
+const THUMBNAILS_DIR: &str = "thumbnails";
+
+/// A stored artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactRef {
+    /// SHA-256 of the bytes, hex encoded
+    pub digest: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    /// URL the artifact was downloaded from
+    pub source_url: String,
+    /// Where it is stored: a file path or an `s3://` URL
+    pub location: String,
+    pub thumbnail: Option<String>,
+    /// Whether the same bytes were already stored
+    #[serde(default)]
+    pub deduplicated: bool,
+}
+
+/// Where artifact bytes are kept
+#[async_trait]
+pub trait ArtifactBackend: Send + Sync {
+    async fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()>;
+
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Location recorded for `key`
+    fn location(&self, key: &str) -> String;
+}
+
+/// Artifacts under a local directory
+#[derive(Debug, Clone)]
+pub struct LocalArtifacts {
+    root: PathBuf,
+}
+
+impl LocalArtifacts {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl ArtifactBackend for LocalArtifacts {
+    async fn put(&self, key: &str, body: Vec<u8>, _content_type: &str) -> Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, body).await?;
+        // Rename so a digest-named file is always complete
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(tokio::fs::try_exists(self.root.join(key)).await?)
+    }
+
+    fn location(&self, key: &str) -> String {
+        self.root.join(key).display().to_string()
+    }
+}
+
+#[async_trait]
+impl ArtifactBackend for S3Client {
+    async fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        self.put_object(key, body, content_type).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        self.head_object(key).await
+    }
+
+    fn location(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket(), key)
+    }
+}
+
+/// Stores artifacts by digest, within a size limit
+pub struct ArtifactStore {
+    backend: Box<dyn ArtifactBackend>,
+    max_bytes: u64,
+    quota: Option<Arc<DiskQuota>>,
+    #[cfg(feature = "thumbnails")]
+    thumbnail_size: Option<u32>,
+}
+
+impl std::fmt::Debug for ArtifactStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArtifactStore")
+            .field("max_bytes", &self.max_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArtifactStore {
+    pub fn new(backend: impl ArtifactBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            max_bytes: DEFAULT_MAX_ARTIFACT_BYTES,
+            quota: None,
+            #[cfg(feature = "thumbnails")]
+            thumbnail_size: None,
+        }
+    }
+
+    /// Store artifacts under the directory `root`
+    pub fn local(root: impl Into<PathBuf>) -> Self {
+        Self::new(LocalArtifacts::new(root))
+    }
+
+    /// Store artifacts in an S3-compatible bucket
+    pub fn s3(config: S3Config) -> Self {
+        Self::new(S3Client::new(config))
+    }
+
+    /// Refuse artifacts larger than `bytes`
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Charge newly stored bytes, thumbnails included, to `quota`
+    pub fn with_quota(mut self, quota: Arc<DiskQuota>) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Also store a PNG thumbnail, at most `size` pixels on each side, of every image
+    #[cfg(feature = "thumbnails")]
+    pub fn with_thumbnails(mut self, size: u32) -> Self {
+        self.thumbnail_size = Some(size);
+        self
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Store `body`, downloaded from `source_url`, unless the same bytes already are
+    pub async fn store(
+        &self,
+        source_url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<ArtifactRef> {
+        if body.len() as u64 > self.max_bytes {
+            anyhow::bail!(
+                "Artifact {} is {} bytes, over the {} byte limit",
+                source_url,
+                body.len(),
+                self.max_bytes
+            );
+        }
+        let digest = format!("{:x}", Sha256::digest(body));
+        let key = format!(
+            "{}/{}/{}.{}",
+            OBJECTS_DIR,
+            &digest[..2],
+            digest,
+            extension(content_type)
+        );
+        let deduplicated = self.backend.exists(&key).await?;
+        if !deduplicated {
+            self.reserve(body.len())?;
+            self.backend.put(&key, body.to_vec(), content_type).await?;
+        }
+
+        Ok(ArtifactRef {
+            thumbnail: self
+                .thumbnail(&digest, content_type, body, deduplicated)
+                .await,
+            location: self.backend.location(&key),
+            digest,
+            content_type: content_type.to_string(),
+            size_bytes: body.len() as u64,
+            source_url: source_url.to_string(),
+            deduplicated,
+        })
+    }
+
+    #[cfg(feature = "thumbnails")]
+    async fn thumbnail(
+        &self,
+        digest: &str,
+        content_type: &str,
+        body: &[u8],
+        deduplicated: bool,
+    ) -> Option<String> {
+        let size = self.thumbnail_size?;
+        if !content_type.starts_with("image/") || content_type == "image/svg+xml" {
+            return None;
+        }
+        let key = format!("{}/{}/{}.png", THUMBNAILS_DIR, &digest[..2], digest);
+        if deduplicated && self.backend.exists(&key).await.unwrap_or(false) {
+            return Some(self.backend.location(&key));
+        }
+        let png = image::load_from_memory(body).and_then(|image| {
+            let mut png = std::io::Cursor::new(Vec::new());
+            image
+                .thumbnail(size, size)
+                .write_to(&mut png, image::ImageFormat::Png)?;
+            Ok(png.into_inner())
+        });
+        let stored = match png {
+            Ok(png) => match self.reserve(png.len()) {
+                Ok(()) => self.backend.put(&key, png, "image/png").await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.into()),
+        };
+        match stored {
+            Ok(()) => Some(self.backend.location(&key)),
+            Err(e) => {
+                tracing::warn!("No thumbnail for artifact {}: {}", digest, e);
+                None
+            }
+        }
+    }
+
+    fn reserve(&self, bytes: usize) -> Result<()> {
+        if let Some(quota) = &self.quota {
+            quota.reserve(bytes as u64)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "thumbnails"))]
+    async fn thumbnail(&self, _: &str, _: &str, _: &[u8], _: bool) -> Option<String> {
+        None
+    }
+}
+
+/// File extension for a media type, `bin` when unknown
+fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "application/pdf" => "pdf",
+        "text/html" => "html",
+        "multipart/related" => "mhtml",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_artifacts_dedupe_by_digest() {
+        let root = std::env::temp_dir().join(format!("swoop-artifacts-{}", uuid::Uuid::new_v4()));
+        let store = ArtifactStore::local(&root).with_max_bytes(16);
+
+        let first = store
+            .store(
+                "https://a.example/logo.png",
+                "image/png",
+                b"not really a png",
+            )
+            .await
+            .unwrap();
+        assert!(!first.deduplicated);
+        assert_eq!(first.size_bytes, 16);
+        assert!(first.location.ends_with(&format!("{}.png", first.digest)));
+        assert_eq!(std::fs::read(&first.location).unwrap(), b"not really a png");
+
+        let again = store
+            .store(
+                "https://b.example/copy.png",
+                "image/png",
+                b"not really a png",
+            )
+            .await
+            .unwrap();
+        assert!(again.deduplicated);
+        assert_eq!(again.location, first.location);
+        assert_eq!(again.source_url, "https://b.example/copy.png");
+
+        let mut page = crate::models::StoredContent::new(
+            "https://a.example/".to_string(),
+            "a.example".to_string(),
+            "generic".to_string(),
+            None,
+            None,
+            None,
+            Default::default(),
+        );
+        page.add_artifact(&first);
+        page.add_artifact(&again);
+        assert_eq!(page.images, vec![first.location.clone()]);
+
+        let quota = Arc::new(DiskQuota::new(20));
+        let limited = ArtifactStore::local(&root).with_quota(quota.clone());
+        limited
+            .store(
+                "https://a.example/logo.png",
+                "image/png",
+                b"not really a png",
+            )
+            .await
+            .unwrap();
+        assert_eq!(quota.used(), 0, "deduplicated bytes are free");
+        let stored = limited
+            .store(
+                "https://a.example/new.gif",
+                "image/gif",
+                b"another 16 bytes",
+            )
+            .await
+            .unwrap();
+        assert!(!stored.deduplicated);
+        assert_eq!(quota.used(), 16);
+        assert!(limited
+            .store("https://a.example/more.gif", "image/gif", b"over quota")
+            .await
+            .is_err());
+
+        let too_large = store
+            .store(
+                "https://a.example/big.bin",
+                "application/octet-stream",
+                &[0; 17],
+            )
+            .await;
+        assert!(too_large.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(feature = "thumbnails")]
+    #[tokio::test]
+    async fn test_thumbnails_images() {
+        let root = std::env::temp_dir().join(format!("swoop-thumbs-{}", uuid::Uuid::new_v4()));
+        let store = ArtifactStore::local(&root).with_thumbnails(8);
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(64, 32)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let artifact = store
+            .store("https://a.example/wide.png", "image/png", png.get_ref())
+            .await
+            .unwrap();
+        let thumbnail = image::open(artifact.thumbnail.unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (8, 4));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
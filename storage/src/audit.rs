@@ -0,0 +1,168 @@
+//! Audit log of the daemon
+//!
+//! An authenticating daemon records who queued which job, who paused or
+//! resumed the engine and which requests it refused, and `swoop tokens`
+//! records every token it issues, rotates or revokes. Each event is one JSON
+//! line in `<state-dir>/audit.jsonl`; `swoop tokens audit` shows them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Audit log file, relative to the state directory
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditAction {
+    Submit,
+    Pause,
+    Resume,
+    /// A request without a valid credential, or outside its caller's role
+    Denied,
+    TokenIssued,
+    TokenRotated,
+    TokenRevoked,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Denied => "denied",
+            Self::TokenIssued => "token-issued",
+            Self::TokenRotated => "token-rotated",
+            Self::TokenRevoked => "token-revoked",
+        }
+    }
+}
+
+/// One audited event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub at: DateTime<Utc>,
+    /// Who acted, e.g. `token:ci (3f2a9c1e)` or `oidc:alice@example.com`
+    pub actor: String,
+    pub tenant: String,
+    pub action: AuditAction,
+    /// Job record ID, as shown by `swoop jobs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urls: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(actor: &str, tenant: &str, action: AuditAction) -> Self {
+        Self {
+            at: Utc::now(),
+            actor: actor.to_string(),
+            tenant: tenant.to_string(),
+            action,
+            job_id: None,
+            job_name: None,
+            urls: None,
+            detail: None,
+        }
+    }
+
+    /// Name the job the event is about
+    pub fn with_job(mut self, id: &str, name: &str, urls: usize) -> Self {
+        self.job_id = Some(id.to_string());
+        self.job_name = Some(name.to_string());
+        self.urls = Some(urls);
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Append-only audit log of a state directory
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    /// The log in the state directory `root`
+    pub fn open(root: &Path) -> Self {
+        Self {
+            path: root.join(AUDIT_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, event: &AuditEvent) -> Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        file.write_all(&line)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Every event, oldest first
+    pub fn load(&self) -> Result<Vec<AuditEvent>> {
+        let _lock = self.lock.lock().unwrap();
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", self.path.display())),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Invalid audit event in {}", self.path.display()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log_round_trip() {
+        let root = std::env::temp_dir().join(format!("swoop-audit-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let log = AuditLog::open(&root);
+        assert!(log.load().unwrap().is_empty());
+
+        let submit = AuditEvent::new("token:ci (3f2a9c1e)", "search", AuditAction::Submit)
+            .with_job("6d0c", "nightly", 2);
+        let denied = AuditEvent::new("token:ci (3f2a9c1e)", "search", AuditAction::Denied)
+            .with_detail("POST /pause needs the admin role");
+        log.append(&submit).unwrap();
+        log.append(&denied).unwrap();
+        assert_eq!(log.load().unwrap(), vec![submit, denied]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,578 @@
+//! Persistent browser profiles
+//!
+//! Sites that fingerprint returning visitors look for the state a real
+//! browser keeps between visits: cookies, `localStorage`, IndexedDB and
+//! service workers. A [`ProfileStore`] keeps a browser profile directory
+//! (Chrome's `--user-data-dir`, Firefox's `-profile`) per name, so a session
+//! started in a profile picks up where the last one left off, across runs.
+//! One session uses a profile at a time: [`ProfileStore::checkout`] waits
+//! until the previous session has [released](ProfileLease::release) it.
+//!
+//! Releasing a profile removes the lock files the browser left and, unless
+//! `clear_caches` is off, the caches the site rebuilds on its own (HTTP,
+//! code and GPU caches). A profile still over `max_bytes` is discarded, so
+//! the next session starts fresh. Profiles unused for `max_idle_days` are
+//! removed by [`ProfileStore::prune`], which also runs when the store opens.
+//!
+//! With an [`ExportKey`], profiles are encrypted at rest: a released profile
+//! is packed into `<name>.tar.enc` with [`sealing`](crate::sealing), and
+//! only unpacked under `.open/` while a session uses it. Profiles a run left
+//! open when it stopped are sealed when the store opens again.
+
+use crate::sealing::{self, ExportKey, ENCRYPTED_EXTENSION};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::OwnedMutexGuard;
+
+/// Directory of the store under a state directory
+pub const PROFILES_DIR: &str = "browser_profiles";
+
+/// Largest a profile may stay by default, once its caches are dropped
+pub const DEFAULT_MAX_PROFILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Where encrypted profiles are unpacked while in use
+const OPEN_DIR: &str = ".open";
+
+/// Touched in a plain profile whenever a session releases it
+const LAST_USED_FILE: &str = ".swoop-last-used";
+
+/// Caches Chrome and Firefox rebuild by themselves, wherever they are in a
+/// profile
+const CACHE_DIRS: &[&str] = &[
+    "Cache",
+    "Code Cache",
+    "GPUCache",
+    "GrShaderCache",
+    "GraphiteDawnCache",
+    "ShaderCache",
+    "DawnCache",
+    "Crashpad",
+    "BrowserMetrics",
+    "cache2",
+    "startupCache",
+    "crashes",
+    "minidumps",
+];
+
+/// Lock files at the top of a profile, which would make the next browser
+/// think the profile is still in use
+const LOCK_FILES: &[&str] = &[
+    "SingletonLock",
+    "SingletonSocket",
+    "SingletonCookie",
+    "lock",
+    ".parentlock",
+    "parent.lock",
+];
+
+/// How profiles are trimmed and when they are removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Largest a profile may stay once its caches are dropped
+    pub max_bytes: u64,
+    /// Days after its last session a profile is removed; unset keeps them
+    pub max_idle_days: Option<u64>,
+    /// Drop caches whenever a profile is released, not only when it is over
+    /// `max_bytes`
+    pub clear_caches: bool,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_PROFILE_BYTES,
+            max_idle_days: Some(30),
+            clear_caches: true,
+        }
+    }
+}
+
+/// A stored profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    /// Size on disk, of the archive for encrypted profiles
+    pub bytes: u64,
+    /// When a session last released it
+    pub last_used: Option<DateTime<Utc>>,
+    pub encrypted: bool,
+}
+
+/// Browser profiles under a directory; see the [module docs](self)
+#[derive(Debug)]
+pub struct ProfileStore {
+    root: PathBuf,
+    config: ProfileConfig,
+    key: Option<ExportKey>,
+    /// Held by the session using each profile
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl ProfileStore {
+    /// Open the profiles under `root`, sealing the ones a previous run left
+    /// open and removing idle ones; `key` encrypts them at rest
+    pub fn open(
+        root: impl Into<PathBuf>,
+        config: ProfileConfig,
+        key: Option<ExportKey>,
+    ) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create {}", root.display()))?;
+        let store = Self {
+            root,
+            config,
+            key,
+            locks: Mutex::default(),
+        };
+        store.recover()?;
+        let pruned = store.prune()?;
+        if !pruned.is_empty() {
+            tracing::info!("🗂️  Removed {} idle browser profile(s)", pruned.len());
+        }
+        Ok(store)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &ProfileConfig {
+        &self.config
+    }
+
+    /// Take profile `name` for a session, creating it on first use; waits
+    /// while another session has it
+    pub async fn checkout(self: &Arc<Self>, name: &str) -> Result<ProfileLease> {
+        validate_name(name)?;
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .clone();
+        let guard = lock.lock_owned().await;
+        let store = self.clone();
+        let owned_name = name.to_string();
+        let dir = tokio::task::spawn_blocking(move || store.unpack(&owned_name))
+            .await?
+            .with_context(|| format!("Failed to open browser profile {}", name))?;
+        Ok(ProfileLease {
+            store: self.clone(),
+            name: name.to_string(),
+            dir,
+            released: false,
+            _guard: guard,
+        })
+    }
+
+    /// Profiles in the store, by name
+    pub fn profiles(&self) -> Result<Vec<ProfileInfo>> {
+        let archive_suffix = format!(".tar.{}", ENCRYPTED_EXTENSION);
+        let mut profiles = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with('.') {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let profile = if metadata.is_dir() {
+                ProfileInfo {
+                    name: file_name,
+                    bytes: dir_size(&entry.path())?,
+                    last_used: modified(&entry.path().join(LAST_USED_FILE)),
+                    encrypted: false,
+                }
+            } else if let Some(name) = file_name.strip_suffix(archive_suffix.as_str()) {
+                ProfileInfo {
+                    name: name.to_string(),
+                    bytes: metadata.len(),
+                    last_used: modified(&entry.path()),
+                    encrypted: true,
+                }
+            } else {
+                continue;
+            };
+            profiles.push(profile);
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(profiles)
+    }
+
+    /// Remove profiles not used for `max_idle_days`, returning their names;
+    /// profiles in use are kept
+    pub fn prune(&self) -> Result<Vec<String>> {
+        let Some(days) = self.config.max_idle_days else {
+            return Ok(Vec::new());
+        };
+        let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+        let mut removed = Vec::new();
+        for profile in self.profiles()? {
+            let idle = profile.last_used.is_some_and(|used| used <= cutoff);
+            if idle && !self.in_use(&profile.name) {
+                self.remove(&profile.name)?;
+                removed.push(profile.name);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Delete profile `name`, so its next session starts fresh
+    pub fn remove(&self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        let plain = self.root.join(name);
+        if plain.is_dir() {
+            fs::remove_dir_all(&plain)?;
+        }
+        let archive = self.archive(name);
+        if archive.exists() {
+            fs::remove_file(&archive)?;
+        }
+        Ok(())
+    }
+
+    fn in_use(&self, name: &str) -> bool {
+        self.locks
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(|lock| lock.try_lock().is_err())
+    }
+
+    fn archive(&self, name: &str) -> PathBuf {
+        self.root
+            .join(format!("{}.tar.{}", name, ENCRYPTED_EXTENSION))
+    }
+
+    fn open_dir(&self) -> PathBuf {
+        self.root.join(OPEN_DIR)
+    }
+
+    /// The directory a session uses profile `name` in
+    fn unpack(&self, name: &str) -> Result<PathBuf> {
+        let plain = self.root.join(name);
+        let archive = self.archive(name);
+        let Some(key) = &self.key else {
+            if archive.exists() {
+                bail!("it is encrypted, and the store was opened without its key");
+            }
+            fs::create_dir_all(&plain)?;
+            return Ok(plain);
+        };
+
+        let dir = self.open_dir().join(name);
+        fs::create_dir_all(self.open_dir())?;
+        if archive.exists() {
+            let tarball = self.open_dir().join(format!("{}.tar", name));
+            let unpacked = sealing::decrypt_file(key, &archive, &tarball).and_then(|()| {
+                tar::Archive::new(BufReader::new(File::open(&tarball)?))
+                    .unpack(&dir)
+                    .context("Invalid profile archive")
+            });
+            let _ = fs::remove_file(&tarball);
+            unpacked?;
+        } else if plain.is_dir() {
+            // Kept before the store had a key; sealed from its next release on
+            fs::rename(&plain, &dir)?;
+        } else {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Trim profile `name` in `dir` after its session, and seal it when the
+    /// store has a key; returns the size it was kept at
+    fn seal(&self, name: &str, dir: &Path) -> Result<u64> {
+        let bytes = self.trim(dir)?;
+        if bytes > self.config.max_bytes {
+            tracing::warn!(
+                "🗂️  Browser profile {} is {} bytes, over the {}-byte limit; discarding it",
+                name,
+                bytes,
+                self.config.max_bytes
+            );
+            self.remove(name)?;
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+            return Ok(0);
+        }
+        let Some(key) = &self.key else {
+            fs::write(dir.join(LAST_USED_FILE), Utc::now().to_rfc3339())?;
+            return Ok(bytes);
+        };
+
+        let tarball = self.open_dir().join(format!("{}.tar", name));
+        pack(dir, &tarball)?;
+        let sealed = sealing::encrypt_file(key, &tarball)?;
+        fs::rename(&sealed, self.archive(name))?;
+        fs::remove_dir_all(dir)?;
+        Ok(bytes)
+    }
+
+    /// Drop lock files and caches from a profile, returning its size
+    fn trim(&self, dir: &Path) -> Result<u64> {
+        for name in LOCK_FILES {
+            let path = dir.join(name);
+            // Chrome's locks are symlinks to nowhere, so don't follow them
+            if path.symlink_metadata().is_ok() {
+                fs::remove_file(&path)?;
+            }
+        }
+        if self.config.clear_caches {
+            sweep(dir, CACHE_DIRS)?;
+        }
+        let mut bytes = dir_size(dir)?;
+        if bytes > self.config.max_bytes && !self.config.clear_caches {
+            sweep(dir, CACHE_DIRS)?;
+            bytes = dir_size(dir)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Seal profiles left unpacked by a run that stopped, and drop its
+    /// half-written archives
+    fn recover(&self) -> Result<()> {
+        let open = self.open_dir();
+        let Ok(entries) = fs::read_dir(&open) else {
+            return Ok(());
+        };
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                fs::remove_file(entry.path())?;
+                continue;
+            }
+            if self.key.is_none() {
+                bail!(
+                    "{} holds unpacked encrypted profiles; open the store with their key",
+                    open.display()
+                );
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            self.seal(&name, &entry.path())
+                .with_context(|| format!("Failed to seal browser profile {}", name))?;
+        }
+        Ok(())
+    }
+}
+
+/// A profile checked out for one session
+///
+/// Release it with [`ProfileLease::release`] once the browser using it has
+/// quit. A lease dropped without being released leaves the profile as the
+/// browser left it, and an encrypted one unpacked until the store opens again.
+#[derive(Debug)]
+pub struct ProfileLease {
+    store: Arc<ProfileStore>,
+    name: String,
+    dir: PathBuf,
+    released: bool,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl ProfileLease {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Directory the browser keeps the profile in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Trim and seal the profile for the next session, returning the size it
+    /// was kept at; 0 when it was discarded for being over `max_bytes`
+    pub async fn release(mut self) -> Result<u64> {
+        self.released = true;
+        let store = self.store.clone();
+        let name = self.name.clone();
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || store.seal(&name, &dir))
+            .await?
+            .with_context(|| format!("Failed to save browser profile {}", self.name))
+    }
+}
+
+impl Drop for ProfileLease {
+    fn drop(&mut self) {
+        if !self.released {
+            tracing::warn!("🗂️  Browser profile {} was not released", self.name);
+        }
+    }
+}
+
+/// Profile names become file names, so they are kept to a safe alphabet
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!(
+            "Invalid browser profile name '{}': use up to 64 letters, digits, '-', '_' and '.'",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn pack(dir: &Path, tarball: &Path) -> Result<()> {
+    let mut builder = tar::Builder::new(BufWriter::new(File::create(tarball)?));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    let mut writer = builder.into_inner()?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Remove the directories named in `names`, anywhere under `dir`
+fn sweep(dir: &Path, names: &[&str]) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let listed = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| names.contains(&name));
+        if listed {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            sweep(&entry.path(), names)?;
+        }
+    }
+    Ok(())
+}
+
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        bytes += if file_type.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            entry.metadata()?.len()
+        };
+    }
+    Ok(bytes)
+}
+
+fn modified(path: &Path) -> Option<DateTime<Utc>> {
+    fs::metadata(path).ok()?.modified().ok().map(DateTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("swoop-{}-{}", name, uuid::Uuid::new_v4()))
+    }
+
+    /// A profile as Chrome leaves it, with site state, a cache and a lock
+    fn browse(dir: &Path) {
+        let storage = dir.join("Default/Local Storage/leveldb");
+        fs::create_dir_all(&storage).unwrap();
+        fs::write(storage.join("000003.log"), b"returning-visitor=1").unwrap();
+        fs::create_dir_all(dir.join("Default/Cache/Cache_Data")).unwrap();
+        fs::write(dir.join("Default/Cache/Cache_Data/data_0"), [0u8; 4096]).unwrap();
+        fs::write(dir.join("SingletonLock"), b"host-1234").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_profiles_persist_trimmed() {
+        let root = root("profiles");
+        let store = Arc::new(ProfileStore::open(&root, ProfileConfig::default(), None).unwrap());
+
+        let lease = store.checkout("shop-de").await.unwrap();
+        assert_eq!(lease.dir(), root.join("shop-de"));
+        browse(lease.dir());
+        let bytes = lease.release().await.unwrap();
+        assert_eq!(bytes, "returning-visitor=1".len() as u64);
+
+        let lease = store.checkout("shop-de").await.unwrap();
+        let kept = lease.dir().join("Default/Local Storage/leveldb/000003.log");
+        assert_eq!(fs::read(kept).unwrap(), b"returning-visitor=1");
+        assert!(!lease.dir().join("Default/Cache").exists());
+        assert!(!lease.dir().join("SingletonLock").exists());
+        // Another session waits for this one
+        let waiting = store.checkout("shop-de");
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), waiting)
+                .await
+                .is_err()
+        );
+        lease.release().await.unwrap();
+
+        let profiles = store.profiles().unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert!(profiles[0].last_used.is_some() && !profiles[0].encrypted);
+        assert!(store.checkout("../etc").await.is_err());
+
+        // Over the limit even without its cache
+        let config = ProfileConfig {
+            max_bytes: 8,
+            ..ProfileConfig::default()
+        };
+        let small = Arc::new(ProfileStore::open(&root, config, None).unwrap());
+        let lease = small.checkout("shop-de").await.unwrap();
+        assert_eq!(lease.release().await.unwrap(), 0);
+        assert!(small.profiles().unwrap().is_empty());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_profiles() {
+        let root = root("sealed-profiles");
+        let key = ExportKey::generate();
+        let store = Arc::new(
+            ProfileStore::open(&root, ProfileConfig::default(), Some(key.clone())).unwrap(),
+        );
+
+        let lease = store.checkout("bank").await.unwrap();
+        assert_eq!(lease.dir(), root.join(".open/bank"));
+        browse(lease.dir());
+        lease.release().await.unwrap();
+        assert!(!root.join(".open/bank").exists());
+        let sealed = fs::read(root.join("bank.tar.enc")).unwrap();
+        assert!(!sealed
+            .windows(b"returning-visitor".len())
+            .any(|window| window == b"returning-visitor"));
+
+        let lease = store.checkout("bank").await.unwrap();
+        let kept = lease.dir().join("Default/Local Storage/leveldb/000003.log");
+        assert_eq!(fs::read(kept).unwrap(), b"returning-visitor=1");
+        // Left open, as by a run that was killed
+        std::mem::forget(lease);
+        drop(store);
+
+        assert!(ProfileStore::open(&root, ProfileConfig::default(), None).is_err());
+        let store = ProfileStore::open(&root, ProfileConfig::default(), Some(key)).unwrap();
+        assert!(!root.join(".open/bank").exists());
+        assert!(store.profiles().unwrap()[0].encrypted);
+
+        let config = ProfileConfig {
+            max_idle_days: Some(0),
+            ..ProfileConfig::default()
+        };
+        let store = ProfileStore::open(&root, config, None);
+        assert!(store.unwrap().profiles().unwrap().is_empty());
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,139 @@
+//! Checkpoints of interrupted jobs
+//!
+//! When a scrape is stopped by SIGINT or SIGTERM it finishes what it can, then
+//! records the URLs it never got to as a [`Checkpoint`] in
+//! `<state-dir>/checkpoints/<job-id>.json`. Resuming the job runs just those
+//! URLs with the job's configuration and drops the checkpoint once done.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Checkpoint directory, relative to the state directory
+pub const CHECKPOINTS_DIR: &str = "checkpoints";
+
+/// Where an interrupted job stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub job_id: String,
+    pub job_name: String,
+    /// URLs that were not finished, in the order they were queued
+    pub pending: Vec<String>,
+    /// URLs that were finished before the job stopped
+    pub completed: u64,
+    /// Exports written with the results gathered so far
+    #[serde(default)]
+    pub exports: Vec<PathBuf>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    pub fn new(job_id: &str, job_name: &str, pending: Vec<String>, completed: u64) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            job_name: job_name.to_string(),
+            pending,
+            completed,
+            exports: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Record the exports the interrupted run wrote
+    pub fn with_exports(mut self, exports: Vec<PathBuf>) -> Self {
+        self.exports = exports;
+        self
+    }
+}
+
+/// Checkpoints kept in a state directory, one file per job
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    /// The checkpoints of the state directory `root`
+    pub fn open(root: &Path) -> Self {
+        Self {
+            dir: root.join(CHECKPOINTS_DIR),
+        }
+    }
+
+    /// Write `checkpoint`, replacing an older one of the same job
+    pub fn save(&self, checkpoint: &Checkpoint) -> Result<PathBuf> {
+        let path = self.path(&checkpoint.job_id)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        // Through a temporary file, so a second signal can't leave half a checkpoint
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(checkpoint)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load(&self, job_id: &str) -> Result<Option<Checkpoint>> {
+        let path = self.path(job_id)?;
+        match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .with_context(|| format!("Invalid checkpoint {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context(format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Drop the checkpoint of `job_id`, returning whether there was one
+    pub fn remove(&self, job_id: &str) -> Result<bool> {
+        match fs::remove_file(self.path(job_id)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn path(&self, job_id: &str) -> Result<PathBuf> {
+        // Job IDs become file names
+        if job_id.is_empty()
+            || job_id.starts_with('.')
+            || !job_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid job ID for a checkpoint: {}", job_id);
+        }
+        Ok(self.dir.join(format!("{}.json", job_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let root = std::env::temp_dir().join(format!("swoop-checkpoints-{}", uuid::Uuid::new_v4()));
+        let store = CheckpointStore::open(&root);
+        assert!(store.load("job-1").unwrap().is_none());
+        assert!(store
+            .save(&Checkpoint::new("../job", "x", Vec::new(), 0))
+            .is_err());
+
+        let checkpoint =
+            Checkpoint::new("job-1", "nightly", vec!["https://a.test/2".to_string()], 3)
+                .with_exports(vec![PathBuf::from("output/scraped_data.json")]);
+        let path = store.save(&checkpoint).unwrap();
+        assert!(path.ends_with("checkpoints/job-1.json"));
+        assert_eq!(store.load("job-1").unwrap(), Some(checkpoint));
+
+        assert!(store.remove("job-1").unwrap());
+        assert!(!store.remove("job-1").unwrap());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+//! Per-job cost and resource reports
+//!
+//! While a job runs, its fetches, browser renders and writes are tallied in a
+//! [`ResourceUsage`]: bytes and requests through each proxy tier, captcha
+//! solves, browser time and bytes written to disk. At the end the usage is
+//! priced with [`CostRates`] into a [`CostReport`], kept in
+//! `<state-dir>/costs/<job-id>.json` so `swoop jobs cost` can show or export
+//! it later. Prices are estimates; set your providers' in `costs.toml`.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Cost report directory, relative to the state directory
+pub const COSTS_DIR: &str = "costs";
+
+/// Default cost rates file name
+pub const COST_RATES_FILE: &str = "costs.toml";
+
+/// Tier of fetches made without a proxy
+pub const DIRECT_TIER: &str = "direct";
+
+/// Providers bill bandwidth and storage in decimal gigabytes
+const BYTES_PER_GB: f64 = 1e9;
+
+/// Prices the usage of a job is charged at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CostRates {
+    pub currency: String,
+    /// Price per GB through each proxy tier; tiers not listed cost nothing
+    pub proxy_per_gb: BTreeMap<String, f64>,
+    pub captcha_per_solve: f64,
+    pub browser_per_minute: f64,
+    pub storage_per_gb: f64,
+}
+
+impl Default for CostRates {
+    fn default() -> Self {
+        // Typical list prices, as a starting point
+        Self {
+            currency: "USD".to_string(),
+            proxy_per_gb: BTreeMap::from([
+                ("datacenter".to_string(), 0.5),
+                ("residential".to_string(), 8.0),
+                ("mobile".to_string(), 20.0),
+            ]),
+            captcha_per_solve: 0.003,
+            browser_per_minute: 0.01,
+            storage_per_gb: 0.023,
+        }
+    }
+}
+
+/// Resources a job used
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Response bytes through each proxy tier, [`DIRECT_TIER`] without one
+    pub bytes_by_tier: BTreeMap<String, u64>,
+    pub requests_by_tier: BTreeMap<String, u64>,
+    /// Vendor challenges the browser got past
+    pub captcha_solves: u64,
+    pub browser_ms: u64,
+    /// Exports and artifacts written
+    pub storage_bytes: u64,
+}
+
+impl ResourceUsage {
+    /// Count a fetch of `bytes` through `tier`
+    pub fn record_fetch(&mut self, tier: &str, bytes: u64) {
+        *self.bytes_by_tier.entry(tier.to_string()).or_default() += bytes;
+        *self.requests_by_tier.entry(tier.to_string()).or_default() += 1;
+    }
+
+    pub fn record_browser(&mut self, elapsed: Duration) {
+        self.browser_ms += elapsed.as_millis() as u64;
+    }
+
+    pub fn record_captcha_solve(&mut self) {
+        self.captcha_solves += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One priced item of a report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostLine {
+    /// e.g. `proxy:residential`, `captcha`, `browser` or `storage`
+    pub item: String,
+    pub quantity: f64,
+    /// Unit of `quantity` and `rate`: `GB`, `solve` or `minute`
+    pub unit: String,
+    pub rate: f64,
+    pub cost: f64,
+}
+
+/// What a job used and what that is estimated to cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostReport {
+    pub job_id: String,
+    pub job_name: String,
+    pub created_at: DateTime<Utc>,
+    pub currency: String,
+    pub usage: ResourceUsage,
+    pub lines: Vec<CostLine>,
+    pub total: f64,
+}
+
+impl CostReport {
+    /// Price `usage` at `rates`
+    pub fn new(job_id: &str, job_name: &str, usage: ResourceUsage, rates: &CostRates) -> Self {
+        let line = |item: String, quantity: f64, unit: &str, rate: f64| CostLine {
+            item,
+            quantity,
+            unit: unit.to_string(),
+            rate,
+            cost: quantity * rate,
+        };
+        let mut lines: Vec<CostLine> = usage
+            .bytes_by_tier
+            .iter()
+            .map(|(tier, bytes)| {
+                let rate = rates.proxy_per_gb.get(tier).copied().unwrap_or_default();
+                line(
+                    format!("proxy:{}", tier),
+                    *bytes as f64 / BYTES_PER_GB,
+                    "GB",
+                    rate,
+                )
+            })
+            .collect();
+        if usage.captcha_solves > 0 {
+            lines.push(line(
+                "captcha".to_string(),
+                usage.captcha_solves as f64,
+                "solve",
+                rates.captcha_per_solve,
+            ));
+        }
+        if usage.browser_ms > 0 {
+            lines.push(line(
+                "browser".to_string(),
+                usage.browser_ms as f64 / 60_000.0,
+                "minute",
+                rates.browser_per_minute,
+            ));
+        }
+        if usage.storage_bytes > 0 {
+            lines.push(line(
+                "storage".to_string(),
+                usage.storage_bytes as f64 / BYTES_PER_GB,
+                "GB",
+                rates.storage_per_gb,
+            ));
+        }
+        Self {
+            job_id: job_id.to_string(),
+            job_name: job_name.to_string(),
+            created_at: Utc::now(),
+            currency: rates.currency.clone(),
+            total: lines.iter().map(|line| line.cost).sum(),
+            usage,
+            lines,
+        }
+    }
+}
+
+/// Cost reports kept in a state directory, one file per job
+#[derive(Debug, Clone)]
+pub struct CostStore {
+    dir: PathBuf,
+}
+
+impl CostStore {
+    /// The cost reports of the state directory `root`
+    pub fn open(root: &Path) -> Self {
+        Self {
+            dir: root.join(COSTS_DIR),
+        }
+    }
+
+    pub fn save(&self, report: &CostReport) -> Result<PathBuf> {
+        let path = self.path(&report.job_id)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        fs::write(&path, serde_json::to_vec_pretty(report)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load(&self, job_id: &str) -> Result<Option<CostReport>> {
+        let path = self.path(job_id)?;
+        match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .with_context(|| format!("Invalid cost report {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context(format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn path(&self, job_id: &str) -> Result<PathBuf> {
+        // Job IDs become file names
+        if job_id.is_empty()
+            || job_id.starts_with('.')
+            || !job_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid job ID for a cost report: {}", job_id);
+        }
+        Ok(self.dir.join(format!("{}.json", job_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_prices_usage() {
+        let mut usage = ResourceUsage::default();
+        assert!(usage.is_empty());
+        usage.record_fetch("residential", 2_000_000_000);
+        usage.record_fetch("residential", 500_000_000);
+        usage.record_fetch(DIRECT_TIER, 1_000_000);
+        usage.record_captcha_solve();
+        usage.record_browser(Duration::from_secs(90));
+        usage.storage_bytes = 1_000_000_000;
+
+        let report = CostReport::new("job-1", "nightly", usage, &CostRates::default());
+        let cost = |item: &str| {
+            report
+                .lines
+                .iter()
+                .find(|line| line.item == item)
+                .map(|line| line.cost)
+                .unwrap()
+        };
+        assert_eq!(report.usage.requests_by_tier["residential"], 2);
+        assert!((cost("proxy:residential") - 20.0).abs() < 1e-9);
+        assert_eq!(cost("proxy:direct"), 0.0);
+        assert!((cost("captcha") - 0.003).abs() < 1e-9);
+        assert!((cost("browser") - 0.015).abs() < 1e-9);
+        assert!((cost("storage") - 0.023).abs() < 1e-9);
+        assert!((report.total - 20.041).abs() < 1e-9);
+        assert_eq!(report.currency, "USD");
+    }
+
+    #[test]
+    fn test_cost_report_round_trip() {
+        let root = std::env::temp_dir().join(format!("swoop-costs-{}", uuid::Uuid::new_v4()));
+        let store = CostStore::open(&root);
+        assert!(store.load("job-1").unwrap().is_none());
+        assert!(store.load("../job").is_err());
+
+        let report = CostReport::new(
+            "job-1",
+            "nightly",
+            ResourceUsage::default(),
+            &CostRates::default(),
+        );
+        let path = store.save(&report).unwrap();
+        assert!(path.ends_with("costs/job-1.json"));
+        assert_eq!(store.load("job-1").unwrap(), Some(report));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
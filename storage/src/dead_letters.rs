@@ -0,0 +1,368 @@
+//! Dead-letter queue for URLs that failed every attempt
+//!
+//! Scrapes record each URL whose fetch still failed after its retries as a
+//! [`DeadLetter`]. A local run appends them to `dead_letters.jsonl` in its
+//! state directory through a [`DeadLetterLog`]; runs sharing a Redis queue
+//! keep them in the queue's dead-letter list instead. Errors are sorted into
+//! [`FailureClass`]es so a [`FailureReport`] can show what went wrong where,
+//! and the URLs of a class can be queued again once its cause is fixed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Dead-letter file, relative to the state directory
+pub const DEAD_LETTERS_FILE: &str = "dead_letters.jsonl";
+
+/// What kind of failure a dead letter's error was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureClass {
+    /// 403s and bot-protection challenges
+    Blocked,
+    /// 429s
+    RateLimited,
+    /// 404s and 410s
+    NotFound,
+    /// Other 4xx responses
+    ClientError,
+    /// 5xx responses
+    ServerError,
+    /// Requests, or queue leases, that ran out of time
+    Timeout,
+    Dns,
+    Connect,
+    Tls,
+    /// Login, consent and paywalls no browser got past
+    Wall,
+    /// Responses cut short or that couldn't be decoded
+    Body,
+    Other,
+}
+
+impl FailureClass {
+    pub const ALL: [FailureClass; 12] = [
+        Self::Blocked,
+        Self::RateLimited,
+        Self::NotFound,
+        Self::ClientError,
+        Self::ServerError,
+        Self::Timeout,
+        Self::Dns,
+        Self::Connect,
+        Self::Tls,
+        Self::Wall,
+        Self::Body,
+        Self::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blocked => "blocked",
+            Self::RateLimited => "rate-limited",
+            Self::NotFound => "not-found",
+            Self::ClientError => "client-error",
+            Self::ServerError => "server-error",
+            Self::Timeout => "timeout",
+            Self::Dns => "dns",
+            Self::Connect => "connect",
+            Self::Tls => "tls",
+            Self::Wall => "wall",
+            Self::Body => "body",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.as_str() == name)
+    }
+
+    /// Class of a fetch error message such as `HTTP 503` or a client error
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        if let Some(status) = error
+            .strip_prefix("http ")
+            .and_then(|rest| rest.get(..3))
+            .and_then(|status| status.parse::<u16>().ok())
+        {
+            return match status {
+                403 => Self::Blocked,
+                429 => Self::RateLimited,
+                404 | 410 => Self::NotFound,
+                400..=499 => Self::ClientError,
+                500..=599 => Self::ServerError,
+                _ => Self::Other,
+            };
+        }
+
+        let mentions = |words: &[&str]| words.iter().any(|word| error.contains(word));
+        if mentions(&["captcha", "challenge", "bot protection", "blocked"]) {
+            Self::Blocked
+        } else if mentions(&["rate limit", "too many requests"]) {
+            Self::RateLimited
+        } else if mentions(&["wall detected", "wall, browser"]) {
+            Self::Wall
+        } else if mentions(&["timed out", "timeout", "lease expired"]) {
+            Self::Timeout
+        } else if mentions(&["dns", "lookup", "resolve", "no such host"]) {
+            Self::Dns
+        } else if mentions(&["certificate", "tls", "ssl", "handshake"]) {
+            Self::Tls
+        } else if mentions(&["connect", "refused", "reset", "unreachable"]) {
+            Self::Connect
+        } else if mentions(&["body", "decode", "incomplete"]) {
+            Self::Body
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// A URL that failed every attempt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub attempts: u32,
+    /// Error of the last attempt
+    pub error: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub failed_at: DateTime<Utc>,
+    /// Job the URL failed in, for local runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+}
+
+impl DeadLetter {
+    pub fn class(&self) -> FailureClass {
+        FailureClass::classify(&self.error)
+    }
+
+    /// Lowercase host of the URL
+    pub fn domain(&self) -> String {
+        reqwest::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default()
+    }
+}
+
+/// Dead letters counted by class and by domain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureReport {
+    pub total: usize,
+    pub by_class: BTreeMap<FailureClass, usize>,
+    pub by_domain: BTreeMap<String, BTreeMap<FailureClass, usize>>,
+    /// Last error seen for each class
+    pub examples: BTreeMap<FailureClass, String>,
+}
+
+impl FailureReport {
+    pub fn new(letters: &[DeadLetter]) -> Self {
+        let mut report = Self::default();
+        for letter in letters {
+            let class = letter.class();
+            report.total += 1;
+            *report.by_class.entry(class).or_default() += 1;
+            *report
+                .by_domain
+                .entry(letter.domain())
+                .or_default()
+                .entry(class)
+                .or_default() += 1;
+            report.examples.insert(class, letter.error.clone());
+        }
+        report
+    }
+
+    /// Domains with the most dead letters first
+    pub fn top_domains(&self, limit: usize) -> Vec<(&str, usize)> {
+        let mut domains: Vec<(&str, usize)> = self
+            .by_domain
+            .iter()
+            .map(|(domain, classes)| (domain.as_str(), classes.values().sum()))
+            .collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        domains.truncate(limit);
+        domains
+    }
+}
+
+/// Dead letters of local runs, one JSON object per line
+#[derive(Debug)]
+pub struct DeadLetterLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl DeadLetterLog {
+    /// The log in the state directory `root`
+    pub fn open(root: &Path) -> Self {
+        Self {
+            path: root.join(DEAD_LETTERS_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, letter: &DeadLetter) -> Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut line = serde_json::to_vec(letter)?;
+        line.push(b'\n');
+        file.write_all(&line)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// The latest dead letter of every URL, oldest first
+    pub fn load(&self) -> Result<Vec<DeadLetter>> {
+        let _lock = self.lock.lock().unwrap();
+        self.read()
+    }
+
+    /// Drop the dead letters of `urls`, returning how many were dropped
+    pub fn remove(&self, urls: &HashSet<String>) -> Result<usize> {
+        let _lock = self.lock.lock().unwrap();
+        let letters = self.read()?;
+        let (removed, kept): (Vec<DeadLetter>, Vec<DeadLetter>) = letters
+            .into_iter()
+            .partition(|letter| urls.contains(&letter.url));
+        let mut contents = Vec::new();
+        for letter in &kept {
+            contents.extend(serde_json::to_vec(letter)?);
+            contents.push(b'\n');
+        }
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(removed.len())
+    }
+
+    fn read(&self) -> Result<Vec<DeadLetter>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", self.path.display())),
+        };
+        // A URL that failed in several runs keeps its latest entry
+        let mut latest: HashMap<String, usize> = HashMap::new();
+        let mut letters: Vec<Option<DeadLetter>> = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let letter: DeadLetter = serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Invalid entry on line {} of {}",
+                    number + 1,
+                    self.path.display()
+                )
+            })?;
+            if let Some(previous) = latest.insert(letter.url.clone(), letters.len()) {
+                letters[previous] = None;
+            }
+            letters.push(Some(letter));
+        }
+        Ok(letters.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(url: &str, error: &str) -> DeadLetter {
+        DeadLetter {
+            url: url.to_string(),
+            attempts: 2,
+            error: error.to_string(),
+            failed_at: Utc::now(),
+            job: None,
+        }
+    }
+
+    #[test]
+    fn test_failure_classes() {
+        let classes: Vec<&str> = [
+            "HTTP 403",
+            "HTTP 429",
+            "HTTP 404",
+            "HTTP 401",
+            "HTTP 502",
+            "error sending request: operation timed out",
+            "Lease expired",
+            "dns error: failed to lookup address information",
+            "invalid peer certificate: UnknownIssuer",
+            "tcp connect error: Connection refused",
+            "login wall detected (use --webdriver to render it)",
+            "error decoding response body",
+            "Unsupported scheme",
+        ]
+        .iter()
+        .map(|error| FailureClass::classify(error).as_str())
+        .collect();
+        assert_eq!(
+            classes,
+            [
+                "blocked",
+                "rate-limited",
+                "not-found",
+                "client-error",
+                "server-error",
+                "timeout",
+                "timeout",
+                "dns",
+                "tls",
+                "connect",
+                "wall",
+                "body",
+                "other"
+            ]
+        );
+        assert_eq!(
+            FailureClass::named("server-error"),
+            Some(FailureClass::ServerError)
+        );
+    }
+
+    #[test]
+    fn test_log_keeps_latest_letter_per_url() {
+        let root =
+            std::env::temp_dir().join(format!("swoop-dead-letters-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        let log = DeadLetterLog::open(&root);
+        assert!(log.load().unwrap().is_empty());
+
+        log.append(&letter("https://a.test/1", "HTTP 503")).unwrap();
+        log.append(&letter("https://b.test:8443/x", "HTTP 404"))
+            .unwrap();
+        log.append(&letter("https://a.test/1", "HTTP 403")).unwrap();
+        let letters = log.load().unwrap();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[1].error, "HTTP 403");
+
+        let report = FailureReport::new(&letters);
+        assert_eq!(report.by_class[&FailureClass::Blocked], 1);
+        assert_eq!(report.by_domain["b.test"][&FailureClass::NotFound], 1);
+        assert_eq!(report.top_domains(1), [("a.test", 1)]);
+
+        let urls = HashSet::from(["https://a.test/1".to_string()]);
+        assert_eq!(log.remove(&urls).unwrap(), 1);
+        assert_eq!(log.load().unwrap()[0].url, "https://b.test:8443/x");
+        fs::remove_dir_all(root).unwrap();
+    }
+}
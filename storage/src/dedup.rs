@@ -0,0 +1,241 @@
+//! Near-duplicate detection
+//!
+//! Documents are fingerprinted with a 64-bit simhash over word shingles.
+//! Candidates are found by bucketing on fingerprint bands and confirmed by
+//! Hamming distance, then merged into clusters with union-find.
+
+use crate::models::StoredContent;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// Words per shingle
+const SHINGLE_SIZE: usize = 3;
+/// Number of 16-bit bands used for candidate bucketing
+const BANDS: u32 = 4;
+
+/// 64-bit simhash of `text`
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return 0;
+    }
+
+    let mut weights = [0i64; 64];
+    let shingles: Vec<&[String]> = if words.len() < SHINGLE_SIZE {
+        vec![&words[..]]
+    } else {
+        words.windows(SHINGLE_SIZE).collect()
+    };
+    for shingle in shingles {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit))
+}
+
+/// Number of differing bits between two fingerprints
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// A group of near-identical documents
+#[derive(Debug, Clone)]
+pub struct DuplicateCluster {
+    /// Document kept when pruning (the most recently scraped one)
+    pub representative: StoredContent,
+    /// The other members of the cluster
+    pub duplicates: Vec<StoredContent>,
+}
+
+impl DuplicateCluster {
+    pub fn size(&self) -> usize {
+        self.duplicates.len() + 1
+    }
+
+    /// Bytes that would be freed by keeping only the representative
+    pub fn wasted_bytes(&self) -> u64 {
+        self.duplicates.iter().map(|doc| doc.size_bytes).sum()
+    }
+}
+
+/// Summary of near-duplicates across a set of documents
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateReport {
+    pub total_documents: usize,
+    /// Clusters with at least two members, largest first
+    pub clusters: Vec<DuplicateCluster>,
+}
+
+impl DuplicateReport {
+    pub fn duplicate_documents(&self) -> usize {
+        self.clusters.iter().map(|c| c.duplicates.len()).sum()
+    }
+
+    pub fn wasted_bytes(&self) -> u64 {
+        self.clusters
+            .iter()
+            .map(DuplicateCluster::wasted_bytes)
+            .sum()
+    }
+}
+
+fn find(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+fn document_text(doc: &StoredContent) -> String {
+    format!(
+        "{} {}",
+        doc.title.as_deref().unwrap_or_default(),
+        doc.text.as_deref().unwrap_or_default()
+    )
+}
+
+/// Cluster `documents` whose fingerprints differ by at most `max_distance` bits
+///
+/// Bucketing finds every pair within `BANDS - 1` bits; larger distances are only
+/// found when the pair also shares a band.
+pub fn find_duplicate_clusters(
+    documents: Vec<StoredContent>,
+    max_distance: u32,
+) -> DuplicateReport {
+    let total_documents = documents.len();
+    let fingerprints: Vec<u64> = documents
+        .iter()
+        .map(|doc| simhash(&document_text(doc)))
+        .collect();
+
+    let mut parents: Vec<usize> = (0..documents.len()).collect();
+    for band in 0..BANDS {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, fingerprint) in fingerprints.iter().enumerate() {
+            buckets
+                .entry((fingerprint >> (band * 16)) & 0xFFFF)
+                .or_default()
+                .push(index);
+        }
+
+        for members in buckets.values().filter(|members| members.len() > 1) {
+            for (i, &a) in members.iter().enumerate() {
+                for &b in &members[i + 1..] {
+                    if hamming_distance(fingerprints[a], fingerprints[b]) <= max_distance {
+                        let (root_a, root_b) = (find(&mut parents, a), find(&mut parents, b));
+                        if root_a != root_b {
+                            parents[root_b] = root_a;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<StoredContent>> = HashMap::new();
+    for (index, doc) in documents.into_iter().enumerate() {
+        let root = find(&mut parents, index);
+        groups.entry(root).or_default().push(doc);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|mut members| {
+            members.sort_by_key(|doc| std::cmp::Reverse(doc.scraped_at));
+            let representative = members.remove(0);
+            DuplicateCluster {
+                representative,
+                duplicates: members,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.size()
+            .cmp(&a.size())
+            .then(b.wasted_bytes().cmp(&a.wasted_bytes()))
+    });
+
+    DuplicateReport {
+        total_documents,
+        clusters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn doc(url: &str, text: &str) -> StoredContent {
+        StoredContent::new(
+            url.to_string(),
+            "example.com".to_string(),
+            "generic".to_string(),
+            None,
+            Some(text.to_string()),
+            None,
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_simhash_similarity() {
+        let base = "the quick brown fox jumps over the lazy dog near the quiet river bank today";
+        let similar =
+            "the quick brown fox jumps over the lazy dog near the quiet river bank tonight";
+        let different = "completely unrelated text about databases indexes and query planners";
+
+        assert_eq!(simhash(base), simhash(base));
+        assert!(
+            hamming_distance(simhash(base), simhash(similar))
+                < hamming_distance(simhash(base), simhash(different))
+        );
+    }
+
+    #[test]
+    fn test_clusters_group_duplicates_and_count_waste() {
+        let article = "breaking news the city council approved the new park budget on monday \
+                       after a long debate about maintenance costs and public access";
+        let documents = vec![
+            doc("https://example.com/a?utm=1", article),
+            doc("https://example.com/a?utm=2", article),
+            doc("https://example.com/a", article),
+            doc(
+                "https://example.com/b",
+                "recipe for sourdough bread with flour water salt and a starter",
+            ),
+        ];
+        let cluster_bytes: u64 = documents[..3].iter().map(|doc| doc.size_bytes).sum();
+
+        let report = find_duplicate_clusters(documents, 3);
+        assert_eq!(report.total_documents, 4);
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].size(), 3);
+        assert_eq!(report.duplicate_documents(), 2);
+        assert_eq!(
+            report.wasted_bytes(),
+            cluster_bytes - report.clusters[0].representative.size_bytes
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Body fingerprints of fetched pages
+//!
+//! Recurring crawls mostly fetch pages that haven't changed since the last
+//! run. Hashing a fetched body takes a fraction of the time extracting it
+//! does, so the hash is compared with the [`PageFingerprint`] of the URL's
+//! last fetch first: an unchanged page is only recorded as seen, without
+//! being extracted and stored again. [`record`] does both against a
+//! [`FingerprintStore`].
+//!
+//! Bodies are hashed with 64-bit FNV-1a.
+
+use crate::{models::PageFingerprint, FingerprintStore};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hash of a body
+pub fn body_hash(body: &[u8]) -> u64 {
+    body.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// How a fetched body compares with the URL's fingerprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sighting {
+    /// First fetch of the URL
+    New,
+    Changed,
+    Unchanged,
+}
+
+impl Sighting {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sighting::New => "new",
+            Sighting::Changed => "changed",
+            Sighting::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// Record a fetch of `url` that returned `body` at `at`
+///
+/// The fingerprint of a new or changed body keeps the previous `stored_id`
+/// until the caller stores the page again and sets it.
+pub fn observe(
+    previous: Option<PageFingerprint>,
+    url: &str,
+    body: &[u8],
+    at: DateTime<Utc>,
+) -> (PageFingerprint, Sighting) {
+    let (hash, size) = (body_hash(body), body.len() as u64);
+    let Some(mut record) = previous else {
+        let record = PageFingerprint {
+            url: url.to_string(),
+            hash,
+            size,
+            stored_id: None,
+            first_seen: at,
+            last_seen: at,
+            last_changed: at,
+            unchanged: 0,
+        };
+        return (record, Sighting::New);
+    };
+    record.last_seen = at;
+    if record.hash == hash && record.size == size {
+        record.unchanged += 1;
+        return (record, Sighting::Unchanged);
+    }
+    record.hash = hash;
+    record.size = size;
+    record.last_changed = at;
+    record.unchanged = 0;
+    (record, Sighting::Changed)
+}
+
+/// Record in `store` a fetch of `url` that returned `body` now
+///
+/// For callers that don't wait for the page to make it through before saving
+/// its fingerprint; the saved fingerprint keeps the previous `stored_id`.
+pub async fn record(
+    store: &dyn FingerprintStore,
+    url: &str,
+    body: &[u8],
+) -> Result<(PageFingerprint, Sighting)> {
+    let previous = store.get_fingerprint(url).await?;
+    let (fingerprint, sighting) = observe(previous, url, body, Utc::now());
+    store.save_fingerprint(&fingerprint).await?;
+    Ok((fingerprint, sighting))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_hash() {
+        // Reference values of 64-bit FNV-1a
+        assert_eq!(body_hash(b""), FNV_OFFSET);
+        assert_eq!(body_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(body_hash(b"a"), body_hash(b"b"));
+    }
+
+    #[test]
+    fn test_observe_counts_unchanged_fetches() {
+        let url = "https://example.com/a";
+        let first = Utc::now();
+        let later = |secs| first + chrono::Duration::seconds(secs);
+        let (record, sighting) = observe(None, url, b"v1", first);
+        assert_eq!(sighting, Sighting::New);
+
+        let record = PageFingerprint {
+            stored_id: Some("doc-1".to_string()),
+            ..record
+        };
+        let (record, sighting) = observe(Some(record), url, b"v1", later(1));
+        assert_eq!(sighting, Sighting::Unchanged);
+        let (record, sighting) = observe(Some(record), url, b"v1", later(2));
+        assert_eq!(sighting, Sighting::Unchanged);
+        assert_eq!(record.unchanged, 2);
+        assert_eq!((record.last_changed, record.last_seen), (first, later(2)));
+
+        let (record, sighting) = observe(Some(record), url, b"v2", later(3));
+        assert_eq!(sighting, Sighting::Changed);
+        assert_eq!(record.unchanged, 0);
+        assert_eq!(record.last_changed, later(3));
+        assert_eq!(record.stored_id.as_deref(), Some("doc-1"));
+    }
+}
@@ -0,0 +1,236 @@
+//! Re-crawl scheduling from per-URL change history
+//!
+//! Each fetch of a tracked URL is recorded with [`FreshnessPolicy::observe`],
+//! which counts how often the content differed from the previous fetch and
+//! sets the URL's next due time. The interval is half the mean time between
+//! observed changes, clamped between the policy's bounds: a page that changes
+//! daily is checked twice a day, while a page that never changes backs off
+//! toward the maximum. Pinned URLs keep the interval set by hand.
+//!
+//! [`FreshnessPolicy::score`] is the chance that a page has changed since its
+//! last fetch, assuming changes arrive at the observed rate; due URLs are
+//! fetched highest score first.
+
+use crate::models::UrlFreshness;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Bounds of the computed re-crawl intervals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreshnessPolicy {
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    /// Interval of URLs without enough history to go on
+    pub initial_interval: Duration,
+}
+
+impl Default for FreshnessPolicy {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(3600),
+            max_interval: Duration::from_secs(30 * 86400),
+            initial_interval: Duration::from_secs(86400),
+        }
+    }
+}
+
+impl FreshnessPolicy {
+    /// A URL to start tracking, due right away
+    pub fn track(&self, url: &str) -> UrlFreshness {
+        UrlFreshness::new(url.to_string(), self.initial_interval.as_secs())
+    }
+
+    /// Record a fetch of `url` that returned `content` at `at`
+    pub fn observe(
+        &self,
+        previous: Option<UrlFreshness>,
+        url: &str,
+        content: &str,
+        at: DateTime<Utc>,
+    ) -> UrlFreshness {
+        let hash = format!("{:x}", md5::compute(content.as_bytes()));
+        self.record(previous, url, hash, at)
+    }
+
+    /// Record a fetch of `url` at `at` whose body was the same as at the
+    /// previous fetch, without looking at its content again
+    pub fn observe_unchanged(
+        &self,
+        previous: Option<UrlFreshness>,
+        url: &str,
+        at: DateTime<Utc>,
+    ) -> UrlFreshness {
+        let hash = previous
+            .as_ref()
+            .map(|record| record.content_hash.clone())
+            .unwrap_or_default();
+        self.record(previous, url, hash, at)
+    }
+
+    fn record(
+        &self,
+        previous: Option<UrlFreshness>,
+        url: &str,
+        hash: String,
+        at: DateTime<Utc>,
+    ) -> UrlFreshness {
+        let mut record = previous.unwrap_or_else(|| self.track(url));
+        if record.checks > 0 && record.content_hash != hash {
+            record.changes += 1;
+            record.last_changed = Some(at);
+        }
+        record.checks += 1;
+        record.content_hash = hash;
+        record.first_checked.get_or_insert(at);
+        record.last_checked = Some(at);
+        record.interval_secs = self.interval_secs(&record);
+        record.next_due = at + chrono::Duration::seconds(record.effective_interval_secs() as i64);
+        record
+    }
+
+    /// Pin `record` to `interval`, or go back to the computed one with `None`
+    pub fn pin(&self, record: &mut UrlFreshness, interval: Option<Duration>) {
+        record.pinned_secs = interval.map(|interval| interval.as_secs());
+        if let Some(last) = record.last_checked {
+            record.next_due =
+                last + chrono::Duration::seconds(record.effective_interval_secs() as i64);
+        }
+    }
+
+    /// Mean time between changes as far as the history shows, in seconds
+    fn mean_change_secs(&self, record: &UrlFreshness) -> f64 {
+        let span = match (record.first_checked, record.last_checked) {
+            (Some(first), Some(last)) => (last - first).num_seconds().max(0) as f64,
+            _ => 0.0,
+        };
+        let observed = span.max(self.initial_interval.as_secs_f64());
+        if record.changes == 0 {
+            // Unchanged so far: assume a change is at least twice as far off
+            observed * 2.0
+        } else {
+            observed / record.changes as f64
+        }
+    }
+
+    /// Re-crawl interval computed from the change history, in seconds
+    pub fn interval_secs(&self, record: &UrlFreshness) -> u64 {
+        if record.checks < 2 {
+            return self.initial_interval.as_secs();
+        }
+        let interval = (self.mean_change_secs(record) / 2.0) as u64;
+        interval.clamp(self.min_interval.as_secs(), self.max_interval.as_secs())
+    }
+
+    /// Chance between 0 and 1 that the page changed since its last fetch
+    pub fn score(&self, record: &UrlFreshness, now: DateTime<Utc>) -> f64 {
+        let Some(last) = record.last_checked else {
+            return 1.0;
+        };
+        let elapsed = (now - last).num_seconds().max(0) as f64;
+        1.0 - (-elapsed / self.mean_change_secs(record)).exp()
+    }
+
+    /// URLs due at `now`, most likely changed first
+    pub fn due<'a>(
+        &self,
+        records: &'a [UrlFreshness],
+        now: DateTime<Utc>,
+    ) -> Vec<&'a UrlFreshness> {
+        let mut due: Vec<&UrlFreshness> = records
+            .iter()
+            .filter(|record| record.next_due <= now)
+            .collect();
+        due.sort_by(|a, b| self.score(b, now).total_cmp(&self.score(a, now)));
+        due
+    }
+}
+
+/// Parse an interval such as `90` (seconds), `30m`, `6h`, `2d` or `1w`
+pub fn parse_interval(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (number, unit) = raw.split_at(split);
+    let Ok(value) = number.parse::<u64>() else {
+        bail!("Invalid interval '{}', expected e.g. 30m, 6h or 2d", raw);
+    };
+    let unit_secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => bail!("Invalid interval unit in '{}', use s, m, h, d or w", raw),
+    };
+    if value == 0 {
+        bail!("Interval must be positive");
+    }
+    Ok(Duration::from_secs(value * unit_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intervals_follow_change_rate() {
+        let policy = FreshnessPolicy::default();
+        let start = Utc::now();
+        let day = chrono::Duration::days(1);
+
+        // Changes on every daily fetch: the interval shrinks below a day
+        let mut busy = None;
+        let mut quiet = None;
+        for i in 0..5 {
+            let at = start + day * i;
+            busy = Some(policy.observe(busy, "https://a.test/", &format!("v{}", i), at));
+            quiet = Some(policy.observe(quiet, "https://b.test/", "same", at));
+        }
+        let (busy, quiet) = (busy.unwrap(), quiet.unwrap());
+        assert_eq!((busy.checks, busy.changes), (5, 4));
+        assert_eq!(busy.interval_secs, 43200);
+        assert_eq!(quiet.changes, 0);
+        assert_eq!(quiet.interval_secs, 4 * 86400);
+        assert_eq!(quiet.next_due, start + day * 8);
+
+        let now = start + day * 5;
+        assert!(policy.score(&busy, now) > policy.score(&quiet, now));
+        let records = vec![quiet.clone(), busy.clone()];
+        let due: Vec<&str> = policy
+            .due(&records, start + day * 9)
+            .iter()
+            .map(|record| record.url.as_str())
+            .collect();
+        assert_eq!(due, vec!["https://a.test/", "https://b.test/"]);
+
+        // Pinning overrides the computed interval until unpinned
+        let mut pinned = quiet;
+        policy.pin(&mut pinned, Some(Duration::from_secs(3600)));
+        assert_eq!(
+            pinned.next_due,
+            start + day * 4 + chrono::Duration::hours(1)
+        );
+        let pinned = policy.observe(Some(pinned), "https://b.test/", "same", now);
+        assert_eq!(pinned.next_due, now + chrono::Duration::hours(1));
+
+        // A body known to be unchanged counts as a check without a change
+        let later = now + day;
+        let same = policy.observe_unchanged(Some(pinned.clone()), "https://b.test/", later);
+        assert_eq!((same.checks, same.changes), (pinned.checks + 1, 0));
+        assert_eq!(same.content_hash, pinned.content_hash);
+        assert_eq!(same.last_checked, Some(later));
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("6h").unwrap(), Duration::from_secs(6 * 3600));
+        assert_eq!(
+            parse_interval("2d").unwrap(),
+            Duration::from_secs(2 * 86400)
+        );
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("3y").is_err());
+        assert!(parse_interval("h").is_err());
+    }
+}
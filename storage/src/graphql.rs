@@ -0,0 +1,577 @@
+//! GraphQL query API over stored content
+//!
+//! Read-only schema for exploring scraped datasets: content with filtering and
+//! pagination, revisions of a URL, per-domain aggregates and crawl jobs. Works
+//! with any backend implementing [`StorageBackend`], [`ContentCatalog`] and [`JobStore`].
+
+use crate::{models, query::ContentQuery, ContentCatalog, JobStore, StorageBackend};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, InputObject, Object, Schema, SimpleObject,
+};
+use chrono::{DateTime, Utc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+/// Largest page a single query may request
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Upper bound on jobs scanned per query
+const MAX_JOB_SCAN: usize = 10_000;
+
+/// Backend the schema reads from
+pub trait DatasetSource: StorageBackend + ContentCatalog + JobStore {}
+
+impl<T: StorageBackend + ContentCatalog + JobStore> DatasetSource for T {}
+
+/// Executable schema type
+pub type SwoopSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Build the schema over `source`
+pub fn build_schema(source: Arc<dyn DatasetSource>) -> SwoopSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(source)
+        .finish()
+}
+
+fn source<'a>(ctx: &'a Context<'_>) -> &'a Arc<dyn DatasetSource> {
+    ctx.data_unchecked::<Arc<dyn DatasetSource>>()
+}
+
+fn page_size(limit: i32) -> usize {
+    (limit.max(0) as usize).min(MAX_PAGE_SIZE)
+}
+
+/// Key/value metadata entry
+#[derive(SimpleObject)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: String,
+}
+
+fn entries(map: &HashMap<String, String>, keys: Option<Vec<String>>) -> Vec<MetadataEntry> {
+    let mut entries: Vec<MetadataEntry> = map
+        .iter()
+        .filter(|(key, _)| keys.as_ref().is_none_or(|keys| keys.contains(key)))
+        .map(|(key, value)| MetadataEntry {
+            key: key.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+/// A stored content document
+pub struct Content(models::StoredContent);
+
+#[Object]
+impl Content {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn url(&self) -> &str {
+        &self.0.url
+    }
+
+    /// Canonical URL the page declared, or its own URL
+    async fn canonical_url(&self) -> &str {
+        self.0.canonical_url()
+    }
+
+    async fn domain(&self) -> &str {
+        &self.0.domain
+    }
+
+    async fn platform(&self) -> &str {
+        &self.0.platform
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.0.title.as_deref()
+    }
+
+    async fn text(&self) -> Option<&str> {
+        self.0.text.as_deref()
+    }
+
+    async fn html(&self) -> Option<&str> {
+        self.0.html.as_deref()
+    }
+
+    /// Metadata entries, optionally restricted to `keys`
+    async fn metadata(&self, keys: Option<Vec<String>>) -> Vec<MetadataEntry> {
+        entries(&self.0.metadata, keys)
+    }
+
+    async fn links(&self) -> &[String] {
+        &self.0.links
+    }
+
+    async fn images(&self) -> &[String] {
+        &self.0.images
+    }
+
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+
+    async fn scraped_at(&self) -> DateTime<Utc> {
+        self.0.scraped_at
+    }
+
+    async fn stored_at(&self) -> DateTime<Utc> {
+        self.0.stored_at
+    }
+
+    async fn content_hash(&self) -> &str {
+        &self.0.content_hash
+    }
+
+    async fn size_bytes(&self) -> u64 {
+        self.0.size_bytes
+    }
+
+    /// All stored versions of this URL, newest first
+    async fn revisions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Content>> {
+        revisions_of(ctx, &self.0.url).await
+    }
+}
+
+/// Filter for content queries; all set fields must match
+#[derive(InputObject, Default)]
+pub struct ContentFilter {
+    pub domain: Option<String>,
+    pub platform: Option<String>,
+    pub url_contains: Option<String>,
+    pub canonical_url: Option<String>,
+    /// Case-insensitive match against title and text
+    pub search: Option<String>,
+    pub tag: Option<String>,
+    pub scraped_after: Option<DateTime<Utc>>,
+    pub scraped_before: Option<DateTime<Utc>>,
+}
+
+impl ContentFilter {
+    fn matches(&self, content: &models::StoredContent) -> bool {
+        ContentQuery {
+            domain: self.domain.clone(),
+            platform: self.platform.clone(),
+            url_contains: self.url_contains.clone(),
+            canonical: self.canonical_url.clone(),
+            search: self.search.clone(),
+            tag: self.tag.clone(),
+            scraped_after: self.scraped_after,
+            scraped_before: self.scraped_before,
+        }
+        .matches(content)
+    }
+}
+
+/// One page of content results
+#[derive(SimpleObject)]
+pub struct ContentPage {
+    /// Number of documents matching the filter
+    pub total_count: usize,
+    pub offset: usize,
+    pub has_more: bool,
+    pub items: Vec<Content>,
+}
+
+/// Aggregates for a single domain
+#[derive(SimpleObject)]
+pub struct Domain {
+    pub name: String,
+    pub documents: u64,
+    pub unique_urls: u64,
+    pub total_bytes: u64,
+    pub platforms: Vec<String>,
+    pub last_scraped_at: DateTime<Utc>,
+}
+
+/// URL variants stored for one canonical page
+#[derive(SimpleObject)]
+pub struct CanonicalGroup {
+    pub canonical_url: String,
+    pub documents: u64,
+    /// Distinct URLs the page was stored under
+    pub urls: Vec<String>,
+    pub last_scraped_at: DateTime<Utc>,
+}
+
+/// Lifecycle state of a crawl job
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Interrupted,
+}
+
+impl From<models::JobStatus> for JobStatus {
+    fn from(status: models::JobStatus) -> Self {
+        match status {
+            models::JobStatus::Running => JobStatus::Running,
+            models::JobStatus::Completed => JobStatus::Completed,
+            models::JobStatus::Failed => JobStatus::Failed,
+            models::JobStatus::Interrupted => JobStatus::Interrupted,
+        }
+    }
+}
+
+/// A recorded crawl job
+pub struct Job(models::Job);
+
+#[Object]
+impl Job {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+
+    async fn seeds(&self) -> &[String] {
+        &self.0.seeds
+    }
+
+    async fn config(&self) -> Vec<MetadataEntry> {
+        entries(&self.0.config, None)
+    }
+
+    async fn status(&self) -> JobStatus {
+        self.0.status.into()
+    }
+
+    async fn started_at(&self) -> DateTime<Utc> {
+        self.0.started_at
+    }
+
+    async fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.0.finished_at
+    }
+
+    async fn duration_secs(&self) -> i64 {
+        self.0.duration().num_seconds()
+    }
+
+    async fn total(&self) -> u64 {
+        self.0.counts.total
+    }
+
+    async fn successful(&self) -> u64 {
+        self.0.counts.successful
+    }
+
+    async fn failed(&self) -> u64 {
+        self.0.counts.failed
+    }
+
+    async fn bytes(&self) -> u64 {
+        self.0.counts.bytes
+    }
+
+    async fn success_rate(&self) -> f64 {
+        self.0.counts.success_rate()
+    }
+
+    async fn rerun_of(&self) -> Option<&str> {
+        self.0.rerun_of.as_deref()
+    }
+}
+
+async fn revisions_of(ctx: &Context<'_>, url: &str) -> async_graphql::Result<Vec<Content>> {
+    let revisions = source(ctx).get_content_by_url(url).await?;
+    Ok(revisions.into_iter().map(Content).collect())
+}
+
+/// Root query type
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Look up a single document by ID
+    async fn content(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Option<Content>> {
+        Ok(source(ctx).get_content(&id).await?.map(Content))
+    }
+
+    /// Filtered, paginated content, newest first
+    async fn contents(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ContentFilter>,
+        #[graphql(default = 0)] offset: i32,
+        #[graphql(default = 20)] limit: i32,
+    ) -> async_graphql::Result<ContentPage> {
+        let filter = filter.unwrap_or_default();
+        let mut matches: Vec<models::StoredContent> = source(ctx)
+            .list_content()
+            .await?
+            .into_iter()
+            .filter(|content| filter.matches(content))
+            .collect();
+        matches.sort_by_key(|content| Reverse(content.scraped_at));
+
+        let total_count = matches.len();
+        let offset = offset.max(0) as usize;
+        let items: Vec<Content> = matches
+            .into_iter()
+            .skip(offset)
+            .take(page_size(limit))
+            .map(Content)
+            .collect();
+
+        Ok(ContentPage {
+            total_count,
+            offset,
+            has_more: offset + items.len() < total_count,
+            items,
+        })
+    }
+
+    /// All stored versions of `url`, newest first
+    async fn revisions(
+        &self,
+        ctx: &Context<'_>,
+        url: String,
+    ) -> async_graphql::Result<Vec<Content>> {
+        revisions_of(ctx, &url).await
+    }
+
+    /// Per-domain aggregates, largest first
+    async fn domains(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 50)] limit: i32,
+    ) -> async_graphql::Result<Vec<Domain>> {
+        struct Aggregate {
+            documents: u64,
+            urls: BTreeSet<String>,
+            total_bytes: u64,
+            platforms: BTreeSet<String>,
+            last_scraped_at: DateTime<Utc>,
+        }
+
+        let mut aggregates: HashMap<String, Aggregate> = HashMap::new();
+        for content in source(ctx).list_content().await? {
+            let aggregate = aggregates
+                .entry(content.domain.clone())
+                .or_insert_with(|| Aggregate {
+                    documents: 0,
+                    urls: BTreeSet::new(),
+                    total_bytes: 0,
+                    platforms: BTreeSet::new(),
+                    last_scraped_at: content.scraped_at,
+                });
+            aggregate.documents += 1;
+            aggregate.urls.insert(content.url);
+            aggregate.total_bytes += content.size_bytes;
+            aggregate.platforms.insert(content.platform);
+            aggregate.last_scraped_at = aggregate.last_scraped_at.max(content.scraped_at);
+        }
+
+        let mut domains: Vec<Domain> = aggregates
+            .into_iter()
+            .map(|(name, aggregate)| Domain {
+                name,
+                documents: aggregate.documents,
+                unique_urls: aggregate.urls.len() as u64,
+                total_bytes: aggregate.total_bytes,
+                platforms: aggregate.platforms.into_iter().collect(),
+                last_scraped_at: aggregate.last_scraped_at,
+            })
+            .collect();
+        domains.sort_by(|a, b| b.documents.cmp(&a.documents).then(a.name.cmp(&b.name)));
+        domains.truncate(page_size(limit));
+        Ok(domains)
+    }
+
+    /// Canonical pages stored under more than one URL, most variants first
+    async fn canonical_groups(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 50)] limit: i32,
+    ) -> async_graphql::Result<Vec<CanonicalGroup>> {
+        let mut groups: HashMap<String, CanonicalGroup> = HashMap::new();
+        for content in source(ctx).list_content().await? {
+            let group = groups
+                .entry(content.canonical_url().to_string())
+                .or_insert_with(|| CanonicalGroup {
+                    canonical_url: content.canonical_url().to_string(),
+                    documents: 0,
+                    urls: Vec::new(),
+                    last_scraped_at: content.scraped_at,
+                });
+            group.documents += 1;
+            if !group.urls.contains(&content.url) {
+                group.urls.push(content.url);
+            }
+            group.last_scraped_at = group.last_scraped_at.max(content.scraped_at);
+        }
+
+        let mut groups: Vec<CanonicalGroup> = groups
+            .into_values()
+            .filter(|group| group.urls.len() > 1)
+            .map(|mut group| {
+                group.urls.sort();
+                group
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            b.urls
+                .len()
+                .cmp(&a.urls.len())
+                .then_with(|| a.canonical_url.cmp(&b.canonical_url))
+        });
+        groups.truncate(page_size(limit));
+        Ok(groups)
+    }
+
+    /// Look up a job by ID
+    async fn job(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<Job>> {
+        Ok(source(ctx).get_job(&id).await?.map(Job))
+    }
+
+    /// Recent jobs, newest first
+    async fn jobs(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<JobStatus>,
+        tag: Option<String>,
+        #[graphql(default = 20)] limit: i32,
+    ) -> async_graphql::Result<Vec<Job>> {
+        Ok(source(ctx)
+            .list_jobs(MAX_JOB_SCAN)
+            .await?
+            .into_iter()
+            .filter(|job| status.is_none_or(|status| JobStatus::from(job.status) == status))
+            .filter(|job| tag.as_ref().is_none_or(|tag| job.tags.contains(tag)))
+            .take(page_size(limit))
+            .map(Job)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_store::LocalStore;
+
+    async fn seeded_schema() -> (SwoopSchema, std::path::PathBuf) {
+        let root = std::env::temp_dir().join(format!("swoop-graphql-{}", uuid::Uuid::new_v4()));
+        let store = LocalStore::new(&root).await.unwrap();
+
+        for (url, domain, title, canonical) in [
+            ("https://a.com/1", "a.com", "Rust news", None),
+            ("https://a.com/1", "a.com", "Rust news (updated)", None),
+            ("https://a.com/2", "a.com", "Other", None),
+            (
+                "https://b.com/1",
+                "b.com",
+                "Rust tips",
+                Some("https://a.com/1"),
+            ),
+        ] {
+            let mut metadata = HashMap::from([("lang".to_string(), "en".to_string())]);
+            if let Some(canonical) = canonical {
+                metadata.insert(models::CANONICAL_URL_KEY.to_string(), canonical.to_string());
+            }
+            let content = models::StoredContent::new(
+                url.to_string(),
+                domain.to_string(),
+                "generic".to_string(),
+                Some(title.to_string()),
+                None,
+                None,
+                metadata,
+            );
+            store.store_content(&content).await.unwrap();
+        }
+        let job = models::Job::new("nightly".to_string(), Vec::new(), HashMap::new())
+            .with_tags(vec!["nightly".to_string()]);
+        store.save_job(&job).await.unwrap();
+
+        (build_schema(Arc::new(store)), root)
+    }
+
+    #[tokio::test]
+    async fn test_contents_filter_and_pagination() {
+        let (schema, root) = seeded_schema().await;
+
+        let response = schema
+            .execute(
+                r#"{ contents(filter: { search: "rust" }, limit: 2) {
+                    totalCount hasMore items { url metadata(keys: ["lang"]) { key value } }
+                } }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["contents"]["totalCount"], 3);
+        assert_eq!(data["contents"]["hasMore"], true);
+        assert_eq!(data["contents"]["items"].as_array().unwrap().len(), 2);
+        assert_eq!(data["contents"]["items"][0]["metadata"][0]["value"], "en");
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_domains_revisions_and_jobs() {
+        let (schema, root) = seeded_schema().await;
+
+        let response = schema
+            .execute(
+                r#"{
+                    domains { name documents uniqueUrls }
+                    revisions(url: "https://a.com/1") { title }
+                    jobs(tag: "nightly", status: RUNNING) { name status }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["domains"][0]["name"], "a.com");
+        assert_eq!(data["domains"][0]["documents"], 3);
+        assert_eq!(data["domains"][0]["uniqueUrls"], 2);
+        assert_eq!(data["revisions"].as_array().unwrap().len(), 2);
+        assert_eq!(data["jobs"][0]["name"], "nightly");
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_canonical_groups() {
+        let (schema, root) = seeded_schema().await;
+
+        let response = schema
+            .execute(
+                r#"{
+                    canonicalGroups { canonicalUrl documents urls }
+                    contents(filter: { canonicalUrl: "https://a.com/1" }) { totalCount }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        let groups = data["canonicalGroups"].as_array().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0]["canonicalUrl"], "https://a.com/1");
+        assert_eq!(groups[0]["documents"], 3);
+        assert_eq!(groups[0]["urls"][1], "https://b.com/1");
+        assert_eq!(data["contents"]["totalCount"], 3);
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -1,16 +1,45 @@
 //! Storage crate - High-performance data persistence layer
 //!
 //! This crate provides data storage and persistence capabilities using ScyllaDB
-//! for time-series data and S3-compatible storage for data archival.
+//! for time-series data, S3-compatible storage for data archival, and a local
+//! filesystem store for single-machine use. Downloaded images and media are
+//! kept separately by digest in an [`artifacts::ArtifactStore`]. Exports can be
+//! encrypted and listed in a signed checksum manifest with [`sealing`]. With
+//! the `redis` feature, several processes can share one crawl's URL queue
+//! through a `redis_queue::RedisQueue`. Jobs stopped by a signal leave a
+//! [`checkpoint::Checkpoint`] to resume from. API [`tokens`] of the daemon are
+//! kept hashed, and what their holders did in an [`audit`] log. Each job's
+//! resource use is priced into a [`costs::CostReport`]. Body [`fingerprints`]
+//! let recurring crawls skip pages that haven't changed since their last fetch,
+//! and a [`QuarantineStore`] keeps responses the safety filters stopped.
+//! [`browser_profiles`] keep browser state between the sessions of a site.
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+pub mod artifacts;
+pub mod audit;
+pub mod browser_profiles;
+pub mod checkpoint;
 pub mod config;
+pub mod costs;
+pub mod dead_letters;
+pub mod dedup;
+pub mod fingerprints;
+pub mod freshness;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod local_store;
 pub mod models;
+pub mod query;
+pub mod quota;
+#[cfg(feature = "redis")]
+pub mod redis_queue;
 pub mod s3_store;
+pub mod sealing;
 pub mod scylla_store;
+pub mod tokens;
 
 /// Configuration for storage systems
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -91,10 +120,81 @@ pub trait StorageBackend: Send + Sync {
     async fn get_stats(&self) -> Result<models::StorageStats>;
 }
 
+/// Trait for backends that can enumerate all stored content
+#[async_trait]
+pub trait ContentCatalog: Send + Sync {
+    /// Load every stored content document
+    async fn list_content(&self) -> Result<Vec<models::StoredContent>>;
+}
+
+/// Trait for persisting crawl job records
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Insert or update a job record
+    async fn save_job(&self, job: &models::Job) -> Result<()>;
+
+    /// Retrieve a job by ID
+    async fn get_job(&self, id: &str) -> Result<Option<models::Job>>;
+
+    /// List the most recently started jobs
+    async fn list_jobs(&self, limit: usize) -> Result<Vec<models::Job>>;
+}
+
+/// Trait for persisting per-domain site profiles
+#[async_trait]
+pub trait SiteProfileStore: Send + Sync {
+    /// Insert or replace the profile of `profile.domain`
+    async fn save_site_profile(&self, profile: &models::SiteProfile) -> Result<()>;
+
+    /// Retrieve the profile of a domain
+    async fn get_site_profile(&self, domain: &str) -> Result<Option<models::SiteProfile>>;
+
+    /// List every profiled domain
+    async fn list_site_profiles(&self) -> Result<Vec<models::SiteProfile>>;
+}
+
+/// Trait for persisting per-URL change histories for re-crawl scheduling
+#[async_trait]
+pub trait FreshnessStore: Send + Sync {
+    /// Insert or replace the history of `freshness.url`
+    async fn save_freshness(&self, freshness: &models::UrlFreshness) -> Result<()>;
+
+    /// Retrieve the history of a URL
+    async fn get_freshness(&self, url: &str) -> Result<Option<models::UrlFreshness>>;
+
+    /// List every tracked URL
+    async fn list_freshness(&self) -> Result<Vec<models::UrlFreshness>>;
+
+    /// Stop tracking a URL, returning whether it was tracked
+    async fn delete_freshness(&self, url: &str) -> Result<bool>;
+}
+
+/// Trait for persisting the body fingerprint of each fetched URL
+#[async_trait]
+pub trait FingerprintStore: Send + Sync {
+    /// Insert or replace the fingerprint of `fingerprint.url`
+    async fn save_fingerprint(&self, fingerprint: &models::PageFingerprint) -> Result<()>;
+
+    /// Retrieve the fingerprint of a URL
+    async fn get_fingerprint(&self, url: &str) -> Result<Option<models::PageFingerprint>>;
+}
+
+/// Trait for keeping the bodies of quarantined responses for review
+#[async_trait]
+pub trait QuarantineStore: Send + Sync {
+    /// Keep `body` with the record of why it was quarantined, returning where
+    async fn quarantine(&self, record: &models::QuarantinedResponse, body: &[u8])
+        -> Result<String>;
+
+    /// List every quarantined response
+    async fn list_quarantined(&self) -> Result<Vec<models::QuarantinedResponse>>;
+}
+
 /// Storage manager that coordinates multiple storage backends
 pub struct StorageManager {
     scylla_store: Option<scylla_store::ScyllaStore>,
     s3_store: Option<s3_store::S3Store>,
+    local_store: Option<local_store::LocalStore>,
 }
 
 impl StorageManager {
@@ -102,6 +202,7 @@ impl StorageManager {
         Self {
             scylla_store: None,
             s3_store: None,
+            local_store: None,
         }
     }
 
@@ -115,13 +216,20 @@ impl StorageManager {
         Ok(self)
     }
 
-    /// Store content in primary storage (ScyllaDB) and optionally archive to S3
+    pub async fn with_local(mut self, root: impl Into<std::path::PathBuf>) -> Result<Self> {
+        self.local_store = Some(local_store::LocalStore::new(root).await?);
+        Ok(self)
+    }
+
+    /// Store content in primary storage (ScyllaDB, or the local store) and optionally archive to S3
     pub async fn store_content(&self, content: &models::StoredContent) -> Result<String> {
         let mut content_id = None;
 
         // Store in ScyllaDB (primary storage)
         if let Some(scylla) = &self.scylla_store {
             content_id = Some(scylla.store_content(content).await?);
+        } else if let Some(local) = &self.local_store {
+            content_id = Some(local.store_content(content).await?);
         }
 
         // Archive to S3 (secondary storage)
@@ -138,6 +246,10 @@ impl StorageManager {
             return scylla.get_content(id).await;
         }
 
+        if let Some(local) = &self.local_store {
+            return local.get_content(id).await;
+        }
+
         if let Some(s3) = &self.s3_store {
             return s3.get_content(id).await;
         }
@@ -155,6 +267,12 @@ impl StorageManager {
             stats.total_size_bytes += scylla_stats.total_size_bytes;
         }
 
+        if let Some(local) = &self.local_store {
+            let local_stats = local.get_stats().await?;
+            stats.total_documents += local_stats.total_documents;
+            stats.total_size_bytes += local_stats.total_size_bytes;
+        }
+
         if let Some(s3) = &self.s3_store {
             let s3_stats = s3.get_stats().await?;
             stats.archived_documents = s3_stats.total_documents;
@@ -163,6 +281,34 @@ impl StorageManager {
 
         Ok(stats)
     }
+
+    /// Backend used for job records: ScyllaDB if configured, otherwise the local store
+    fn job_store(&self) -> Result<&dyn JobStore> {
+        if let Some(scylla) = &self.scylla_store {
+            return Ok(scylla);
+        }
+
+        if let Some(local) = &self.local_store {
+            return Ok(local);
+        }
+
+        Err(anyhow::anyhow!("No job storage configured"))
+    }
+
+    /// Insert or update a job record
+    pub async fn save_job(&self, job: &models::Job) -> Result<()> {
+        self.job_store()?.save_job(job).await
+    }
+
+    /// Retrieve a job by ID
+    pub async fn get_job(&self, id: &str) -> Result<Option<models::Job>> {
+        self.job_store()?.get_job(id).await
+    }
+
+    /// List the most recently started jobs
+    pub async fn list_jobs(&self, limit: usize) -> Result<Vec<models::Job>> {
+        self.job_store()?.list_jobs(limit).await
+    }
 }
 
 impl Default for StorageManager {
@@ -195,5 +341,19 @@ mod tests {
         let manager = StorageManager::new();
         assert!(manager.scylla_store.is_none());
         assert!(manager.s3_store.is_none());
+        assert!(manager.local_store.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_storage_manager_local_jobs() {
+        let root = std::env::temp_dir().join(format!("swoop-manager-{}", uuid::Uuid::new_v4()));
+        let manager = StorageManager::new().with_local(&root).await.unwrap();
+
+        let job = models::Job::new("run".to_string(), Vec::new(), Default::default());
+        manager.save_job(&job).await.unwrap();
+        assert_eq!(manager.get_job(&job.id).await.unwrap().unwrap().name, "run");
+        assert_eq!(manager.list_jobs(10).await.unwrap().len(), 1);
+
+        std::fs::remove_dir_all(root).unwrap();
     }
 }
@@ -0,0 +1,458 @@
+//! Local filesystem storage backend
+//!
+//! Stores content and job records as JSON documents under a root directory:
+//! - `content/<id>.json` for scraped content
+//! - `jobs/<id>.json` for crawl job records
+//! - `sites/<domain>.json` for site profiles
+//! - `freshness/<md5 of url>.json` for per-URL change histories
+//! - `fingerprints/<md5 of url>.json` for the body hash of each fetched URL
+//! - `quarantine/<sha256>.json` and `.bin` for responses the safety filters
+//!   quarantined, with the body kept under a name nothing will run
+//!
+//! Intended for single-machine CLI/TUI usage where running ScyllaDB is overkill.
+
+use crate::{
+    models, ContentCatalog, FingerprintStore, FreshnessStore, JobStore, QuarantineStore,
+    SiteProfileStore, StorageBackend,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Default root directory for local state
+pub const DEFAULT_LOCAL_ROOT: &str = ".swoop";
+
+const CONTENT_DIR: &str = "content";
+const JOBS_DIR: &str = "jobs";
+const SITES_DIR: &str = "sites";
+const FRESHNESS_DIR: &str = "freshness";
+const FINGERPRINTS_DIR: &str = "fingerprints";
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// Filesystem-backed storage
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    /// Open (and create if needed) a local store rooted at `root`
+    pub async fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        for dir in [
+            CONTENT_DIR,
+            JOBS_DIR,
+            SITES_DIR,
+            FRESHNESS_DIR,
+            FINGERPRINTS_DIR,
+            QUARANTINE_DIR,
+        ] {
+            tokio::fs::create_dir_all(root.join(dir))
+                .await
+                .with_context(|| format!("Failed to create {}", root.join(dir).display()))?;
+        }
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Load every stored content document
+    pub async fn list_content(&self) -> Result<Vec<models::StoredContent>> {
+        self.read_all(CONTENT_DIR).await
+    }
+
+    fn document_path(&self, dir: &str, id: &str) -> Result<PathBuf> {
+        // IDs become file names, so reject anything that could escape the directory;
+        // dots are allowed for domains, but not leading ones
+        if id.is_empty()
+            || id.starts_with('.')
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        {
+            anyhow::bail!("Invalid document id: {}", id);
+        }
+        Ok(self.root.join(dir).join(format!("{}.json", id)))
+    }
+
+    async fn write_document<T: Serialize>(&self, dir: &str, id: &str, value: &T) -> Result<()> {
+        let path = self.document_path(dir, id)?;
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(value)?).await?;
+        // Rename so readers never observe a partially written document
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn read_document<T: DeserializeOwned>(&self, dir: &str, id: &str) -> Result<Option<T>> {
+        let path = self.document_path(dir, id)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => {
+                Ok(Some(serde_json::from_slice(&bytes).with_context(|| {
+                    format!("Failed to parse {}", path.display())
+                })?))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_all<T: DeserializeOwned>(&self, dir: &str) -> Result<Vec<T>> {
+        let mut entries = tokio::fs::read_dir(self.root.join(dir)).await?;
+        let mut documents = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let bytes = tokio::fs::read(&path).await?;
+            match serde_json::from_slice(&bytes) {
+                Ok(document) => documents.push(document),
+                Err(e) => tracing::warn!("Skipping unreadable document {}: {}", path.display(), e),
+            }
+        }
+        Ok(documents)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStore {
+    async fn store_content(&self, content: &models::StoredContent) -> Result<String> {
+        self.write_document(CONTENT_DIR, &content.id, content)
+            .await?;
+        Ok(content.id.clone())
+    }
+
+    async fn get_content(&self, id: &str) -> Result<Option<models::StoredContent>> {
+        self.read_document(CONTENT_DIR, id).await
+    }
+
+    async fn get_content_by_url(&self, url: &str) -> Result<Vec<models::StoredContent>> {
+        let mut matches: Vec<models::StoredContent> = self
+            .list_content()
+            .await?
+            .into_iter()
+            .filter(|content| content.url == url)
+            .collect();
+        matches.sort_by_key(|content| Reverse(content.scraped_at));
+        Ok(matches)
+    }
+
+    async fn delete_content(&self, id: &str) -> Result<bool> {
+        let path = self.document_path(CONTENT_DIR, id)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_stats(&self) -> Result<models::StorageStats> {
+        let content = self.list_content().await?;
+        let domains: HashSet<&str> = content.iter().map(|c| c.domain.as_str()).collect();
+        let platforms: HashSet<&str> = content.iter().map(|c| c.platform.as_str()).collect();
+
+        let mut stats = models::StorageStats {
+            total_documents: content.len() as u64,
+            total_size_bytes: content.iter().map(|c| c.size_bytes).sum(),
+            unique_domains: domains.len() as u64,
+            unique_platforms: platforms.len() as u64,
+            ..Default::default()
+        };
+        stats.calculate_derived();
+        Ok(stats)
+    }
+}
+
+#[async_trait]
+impl ContentCatalog for LocalStore {
+    async fn list_content(&self) -> Result<Vec<models::StoredContent>> {
+        LocalStore::list_content(self).await
+    }
+}
+
+#[async_trait]
+impl JobStore for LocalStore {
+    async fn save_job(&self, job: &models::Job) -> Result<()> {
+        self.write_document(JOBS_DIR, &job.id, job).await
+    }
+
+    async fn get_job(&self, id: &str) -> Result<Option<models::Job>> {
+        self.read_document(JOBS_DIR, id).await
+    }
+
+    async fn list_jobs(&self, limit: usize) -> Result<Vec<models::Job>> {
+        let mut jobs: Vec<models::Job> = self.read_all(JOBS_DIR).await?;
+        jobs.sort_by_key(|job| Reverse(job.started_at));
+        jobs.truncate(limit);
+        Ok(jobs)
+    }
+}
+
+#[async_trait]
+impl SiteProfileStore for LocalStore {
+    async fn save_site_profile(&self, profile: &models::SiteProfile) -> Result<()> {
+        self.write_document(SITES_DIR, &profile.domain, profile)
+            .await
+    }
+
+    async fn get_site_profile(&self, domain: &str) -> Result<Option<models::SiteProfile>> {
+        self.read_document(SITES_DIR, &domain.to_ascii_lowercase())
+            .await
+    }
+
+    async fn list_site_profiles(&self) -> Result<Vec<models::SiteProfile>> {
+        let mut profiles: Vec<models::SiteProfile> = self.read_all(SITES_DIR).await?;
+        profiles.sort_by(|a, b| a.domain.cmp(&b.domain));
+        Ok(profiles)
+    }
+}
+
+/// Document ID of a per-URL record; URLs can't be file names
+fn url_id(url: &str) -> String {
+    format!("{:x}", md5::compute(url.as_bytes()))
+}
+
+#[async_trait]
+impl FreshnessStore for LocalStore {
+    async fn save_freshness(&self, freshness: &models::UrlFreshness) -> Result<()> {
+        self.write_document(FRESHNESS_DIR, &url_id(&freshness.url), freshness)
+            .await
+    }
+
+    async fn get_freshness(&self, url: &str) -> Result<Option<models::UrlFreshness>> {
+        self.read_document(FRESHNESS_DIR, &url_id(url)).await
+    }
+
+    async fn list_freshness(&self) -> Result<Vec<models::UrlFreshness>> {
+        let mut records: Vec<models::UrlFreshness> = self.read_all(FRESHNESS_DIR).await?;
+        records.sort_by_key(|record| record.next_due);
+        Ok(records)
+    }
+
+    async fn delete_freshness(&self, url: &str) -> Result<bool> {
+        let path = self.document_path(FRESHNESS_DIR, &url_id(url))?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl FingerprintStore for LocalStore {
+    async fn save_fingerprint(&self, fingerprint: &models::PageFingerprint) -> Result<()> {
+        self.write_document(FINGERPRINTS_DIR, &url_id(&fingerprint.url), fingerprint)
+            .await
+    }
+
+    async fn get_fingerprint(&self, url: &str) -> Result<Option<models::PageFingerprint>> {
+        self.read_document(FINGERPRINTS_DIR, &url_id(url)).await
+    }
+}
+
+#[async_trait]
+impl QuarantineStore for LocalStore {
+    async fn quarantine(
+        &self,
+        record: &models::QuarantinedResponse,
+        body: &[u8],
+    ) -> Result<String> {
+        let path = self
+            .document_path(QUARANTINE_DIR, &record.sha256)?
+            .with_extension("bin");
+        let tmp_path = path.with_extension("bin.tmp");
+        tokio::fs::write(&tmp_path, body).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        self.write_document(QUARANTINE_DIR, &record.sha256, record)
+            .await?;
+        Ok(path.display().to_string())
+    }
+
+    async fn list_quarantined(&self) -> Result<Vec<models::QuarantinedResponse>> {
+        let mut records: Vec<models::QuarantinedResponse> = self.read_all(QUARANTINE_DIR).await?;
+        records.sort_by_key(|record| Reverse(record.quarantined_at));
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn temp_root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "swoop-local-store-{}-{}",
+            name,
+            uuid::Uuid::new_v4()
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_content_roundtrip() {
+        let root = temp_root("content");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let content = models::StoredContent::new(
+            "https://example.com/a".to_string(),
+            "example.com".to_string(),
+            "generic".to_string(),
+            Some("Title".to_string()),
+            Some("Body".to_string()),
+            None,
+            HashMap::new(),
+        );
+        let id = store.store_content(&content).await.unwrap();
+
+        let loaded = store.get_content(&id).await.unwrap().unwrap();
+        assert_eq!(loaded.url, content.url);
+        assert_eq!(
+            store.get_content_by_url(&content.url).await.unwrap().len(),
+            1
+        );
+
+        let stats = store.get_stats().await.unwrap();
+        assert_eq!(stats.total_documents, 1);
+        assert_eq!(stats.unique_domains, 1);
+
+        assert!(store.delete_content(&id).await.unwrap());
+        assert!(store.get_content(&id).await.unwrap().is_none());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_jobs_listed_newest_first() {
+        let root = temp_root("jobs");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let mut older = models::Job::new("older".to_string(), Vec::new(), HashMap::new());
+        older.started_at -= chrono::Duration::hours(1);
+        let newer = models::Job::new("newer".to_string(), Vec::new(), HashMap::new());
+        store.save_job(&older).await.unwrap();
+        store.save_job(&newer).await.unwrap();
+
+        let jobs = store.list_jobs(10).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].name, "newer");
+        assert_eq!(store.list_jobs(1).await.unwrap().len(), 1);
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_site_profiles_keyed_by_domain() {
+        let root = temp_root("sites");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let profile = models::SiteProfile {
+            domain: "docs.example.com".to_string(),
+            home_url: "https://docs.example.com/".to_string(),
+            site_name: Some("Docs".to_string()),
+            favicon: None,
+            technologies: vec!["WordPress".to_string()],
+            robots: models::SiteRobots::default(),
+            response_ms: Some(120),
+            ttfb_ms: Some(80),
+            dns: Some(models::SiteDns {
+                addresses: vec!["203.0.113.7".to_string()],
+                cname_chain: vec!["docs.example.cdn.net".to_string()],
+            }),
+            certificate: None,
+            profiled_at: chrono::Utc::now(),
+        };
+        store.save_site_profile(&profile).await.unwrap();
+        store.save_site_profile(&profile).await.unwrap();
+
+        assert_eq!(
+            store.get_site_profile("Docs.Example.com").await.unwrap(),
+            Some(profile)
+        );
+        assert_eq!(store.list_site_profiles().await.unwrap().len(), 1);
+        assert!(store
+            .get_site_profile("other.example")
+            .await
+            .unwrap()
+            .is_none());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_freshness_keyed_by_url() {
+        let root = temp_root("freshness");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let url = "https://example.com/news?page=1";
+        let record = models::UrlFreshness::new(url.to_string(), 3600);
+        store.save_freshness(&record).await.unwrap();
+        assert_eq!(store.get_freshness(url).await.unwrap(), Some(record));
+        assert_eq!(store.list_freshness().await.unwrap().len(), 1);
+        assert!(store.delete_freshness(url).await.unwrap());
+        assert!(store.get_freshness(url).await.unwrap().is_none());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fingerprints_keyed_by_url() {
+        let root = temp_root("fingerprints");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let url = "https://example.com/news?page=1";
+        let (record, _) =
+            crate::fingerprints::observe(None, url, b"<html></html>", chrono::Utc::now());
+        store.save_fingerprint(&record).await.unwrap();
+        assert_eq!(store.get_fingerprint(url).await.unwrap(), Some(record));
+        assert!(store
+            .get_fingerprint("https://example.com/news")
+            .await
+            .unwrap()
+            .is_none());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_keeps_body_apart() {
+        let root = temp_root("quarantine");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        let body = b"MZ\x90\x00";
+        let record = models::QuarantinedResponse::new(
+            "https://example.com/invoice.pdf".to_string(),
+            Some("application/pdf".to_string()),
+            "malware".to_string(),
+            "Windows executable served as application/pdf".to_string(),
+            body,
+        );
+        let location = store.quarantine(&record, body).await.unwrap();
+        assert!(location.ends_with(".bin"));
+        assert_eq!(tokio::fs::read(&location).await.unwrap(), body);
+        assert_eq!(store.list_quarantined().await.unwrap(), vec![record]);
+        // Quarantined responses are not content
+        assert!(store.list_content().await.unwrap().is_empty());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_like_ids() {
+        let root = temp_root("ids");
+        let store = LocalStore::new(&root).await.unwrap();
+
+        assert!(store.get_job("../escape").await.is_err());
+        assert!(store.get_site_profile("..").await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
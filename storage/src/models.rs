@@ -6,6 +6,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Metadata key holding the canonical URL of a page
+pub const CANONICAL_URL_KEY: &str = "canonical_url";
+
 /// Content data structure for storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredContent {
@@ -101,12 +104,26 @@ impl StoredContent {
         self
     }
 
+    /// Reference a stored artifact, such as a downloaded image, in `images`
+    pub fn add_artifact(&mut self, artifact: &crate::artifacts::ArtifactRef) {
+        if !self.images.contains(&artifact.location) {
+            self.images.push(artifact.location.clone());
+        }
+    }
+
     /// Set tags for categorization
     pub fn with_tags(mut self, tags: Vec<String>) -> Self {
         self.tags = tags;
         self
     }
 
+    /// URL the page declared as canonical, falling back to its own URL
+    pub fn canonical_url(&self) -> &str {
+        self.metadata
+            .get(CANONICAL_URL_KEY)
+            .map_or(self.url.as_str(), String::as_str)
+    }
+
     /// Update the stored timestamp
     pub fn mark_stored(&mut self) {
         self.stored_at = chrono::Utc::now();
@@ -248,6 +265,295 @@ impl Default for BatchResult {
     }
 }
 
+/// Lifecycle state of a crawl job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Job is currently running
+    Running,
+    /// Job finished processing all seeds
+    Completed,
+    /// Job aborted before finishing
+    Failed,
+    /// Job stopped by a shutdown signal; its checkpoint holds the URLs left
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+/// Result counters for a crawl job
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobCounts {
+    /// Number of URLs processed
+    pub total: u64,
+    /// Number of successful fetches
+    pub successful: u64,
+    /// Number of failed fetches
+    pub failed: u64,
+    /// Bytes downloaded
+    pub bytes: u64,
+}
+
+impl JobCounts {
+    /// Success rate as a percentage
+    pub fn success_rate(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.successful as f64 / self.total as f64 * 100.0
+    }
+}
+
+/// A tracked crawl run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    /// Unique identifier for the job
+    pub id: String,
+    /// Human readable name
+    pub name: String,
+    /// Tags for grouping runs
+    pub tags: Vec<String>,
+    /// Seed URLs the job started from
+    pub seeds: Vec<String>,
+    /// Snapshot of the configuration the job ran with
+    pub config: HashMap<String, String>,
+    /// Current lifecycle state
+    pub status: JobStatus,
+    /// Timestamp when the job started
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Timestamp when the job finished
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Result counters
+    pub counts: JobCounts,
+    /// ID of the job this run was re-run from
+    pub rerun_of: Option<String>,
+}
+
+impl Job {
+    pub fn new(name: String, seeds: Vec<String>, config: HashMap<String, String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            tags: Vec::new(),
+            seeds,
+            config,
+            status: JobStatus::Running,
+            started_at: chrono::Utc::now(),
+            finished_at: None,
+            counts: JobCounts::default(),
+            rerun_of: None,
+        }
+    }
+
+    /// Set tags for grouping
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Mark this job as a re-run of another job
+    pub fn with_rerun_of(mut self, job_id: String) -> Self {
+        self.rerun_of = Some(job_id);
+        self
+    }
+
+    /// Record final counts and mark the job finished
+    pub fn finish(&mut self, status: JobStatus, counts: JobCounts) {
+        self.status = status;
+        self.counts = counts;
+        self.finished_at = Some(chrono::Utc::now());
+    }
+
+    /// Wall-clock duration of the job, up to now if it is still running
+    pub fn duration(&self) -> chrono::Duration {
+        self.finished_at.unwrap_or_else(chrono::Utc::now) - self.started_at
+    }
+}
+
+/// What a site's robots.txt allows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteRobots {
+    /// Whether the site serves a robots.txt
+    pub found: bool,
+    /// Requested delay between requests, in seconds
+    pub crawl_delay_secs: Option<u64>,
+    /// Disallowed path prefixes
+    pub disallow: Vec<String>,
+    /// Sitemaps the robots.txt lists
+    pub sitemaps: Vec<String>,
+}
+
+/// Where a site's domain resolves to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteDns {
+    /// IPv4 and IPv6 addresses the domain resolves to
+    pub addresses: Vec<String>,
+    /// Names the domain is aliased to, in resolution order
+    pub cname_chain: Vec<String>,
+}
+
+/// The TLS certificate a site presents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteCertificate {
+    /// Subject distinguished name
+    pub subject: String,
+    /// Issuer distinguished name
+    pub issuer: String,
+    /// DNS names and IP addresses the certificate is valid for
+    pub san: Vec<String>,
+    pub serial: String,
+    pub not_before: chrono::DateTime<chrono::Utc>,
+    pub not_after: chrono::DateTime<chrono::Utc>,
+}
+
+impl SiteCertificate {
+    /// Whole days until the certificate expires; negative once it has
+    pub fn days_left(&self, now: chrono::DateTime<chrono::Utc>) -> i64 {
+        (self.not_after - now).num_days()
+    }
+}
+
+/// Facts collected about one domain, used to plan and prioritize crawls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteProfile {
+    /// Lowercased host name, also the profile's key
+    pub domain: String,
+    /// Home page after redirects
+    pub home_url: String,
+    /// Name the site gives itself
+    pub site_name: Option<String>,
+    /// Absolute URL of the favicon
+    pub favicon: Option<String>,
+    /// Detected CMS and frameworks
+    pub technologies: Vec<String>,
+    /// Summary of the robots.txt
+    pub robots: SiteRobots,
+    /// Typical total response time of the home page, in milliseconds
+    pub response_ms: Option<u64>,
+    /// Typical time to first byte of the home page, in milliseconds
+    pub ttfb_ms: Option<u64>,
+    /// DNS records, when infrastructure was collected
+    #[serde(default)]
+    pub dns: Option<SiteDns>,
+    /// TLS certificate, when infrastructure was collected for an HTTPS site
+    #[serde(default)]
+    pub certificate: Option<SiteCertificate>,
+    /// Timestamp when the profile was collected
+    pub profiled_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Change history of one URL, from which its re-crawl interval is computed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlFreshness {
+    pub url: String,
+    /// Times the URL was fetched
+    pub checks: u32,
+    /// Fetches that found the content different from the previous one
+    pub changes: u32,
+    /// MD5 of the content at the last fetch; empty until the first one
+    pub content_hash: String,
+    pub first_checked: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_checked: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_changed: Option<chrono::DateTime<chrono::Utc>>,
+    /// Re-crawl interval computed from the change history, in seconds
+    pub interval_secs: u64,
+    /// Interval set by hand, used instead of the computed one
+    pub pinned_secs: Option<u64>,
+    /// When the URL should be fetched next
+    pub next_due: chrono::DateTime<chrono::Utc>,
+}
+
+impl UrlFreshness {
+    /// A URL not fetched yet, due right away
+    pub fn new(url: String, interval_secs: u64) -> Self {
+        Self {
+            url,
+            checks: 0,
+            changes: 0,
+            content_hash: String::new(),
+            first_checked: None,
+            last_checked: None,
+            last_changed: None,
+            interval_secs,
+            pinned_secs: None,
+            next_due: chrono::Utc::now(),
+        }
+    }
+
+    /// Interval in effect: the pinned one if set, else the computed one
+    pub fn effective_interval_secs(&self) -> u64 {
+        self.pinned_secs.unwrap_or(self.interval_secs)
+    }
+}
+
+/// Hash of the body a URL returned at its last fetch, checked before
+/// extracting the page again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageFingerprint {
+    pub url: String,
+    /// 64-bit hash of the body; see [`crate::fingerprints::body_hash`]
+    pub hash: u64,
+    /// Length of the body, in bytes
+    pub size: u64,
+    /// ID the page was last stored under
+    pub stored_id: Option<String>,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    /// Last fetch that found a different body
+    pub last_changed: chrono::DateTime<chrono::Utc>,
+    /// Fetches since the last change that found the body unchanged
+    pub unchanged: u32,
+}
+
+/// A response the safety filters kept aside for review instead of letting
+/// it through to extraction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantinedResponse {
+    /// SHA-256 of the body, hex encoded; a payload is kept once however many
+    /// URLs serve it
+    pub sha256: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    /// Length of the body, in bytes
+    pub size: u64,
+    /// Hazard the filters found, e.g. `malware`
+    pub hazard: String,
+    /// What was found, e.g. `Windows executable served as image/jpeg`
+    pub reason: String,
+    pub quarantined_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl QuarantinedResponse {
+    pub fn new(
+        url: String,
+        content_type: Option<String>,
+        hazard: String,
+        reason: String,
+        body: &[u8],
+    ) -> Self {
+        use sha2::{Digest, Sha256};
+
+        Self {
+            sha256: format!("{:x}", Sha256::digest(body)),
+            url,
+            content_type,
+            size: body.len() as u64,
+            hazard,
+            reason,
+            quarantined_at: chrono::Utc::now(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -309,4 +615,57 @@ mod tests {
         assert_eq!(query.offset, Some(0));
         assert_eq!(query.sort_by, Some("newest_first".to_string()));
     }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let mut job = Job::new(
+            "nightly".to_string(),
+            vec!["https://example.com".to_string()],
+            HashMap::from([("concurrency".to_string(), "10".to_string())]),
+        )
+        .with_tags(vec!["news".to_string()]);
+
+        assert_eq!(job.status, JobStatus::Running);
+        assert!(job.finished_at.is_none());
+
+        job.finish(
+            JobStatus::Completed,
+            JobCounts {
+                total: 4,
+                successful: 3,
+                failed: 1,
+                bytes: 1024,
+            },
+        );
+
+        assert_eq!(job.status, JobStatus::Completed);
+        assert!(job.finished_at.is_some());
+        assert_eq!(job.counts.success_rate(), 75.0);
+        assert!(job.duration() >= chrono::Duration::zero());
+    }
+
+    #[test]
+    fn test_site_profiles_without_infrastructure_still_load() {
+        let profile: SiteProfile = serde_json::from_str(
+            r#"{"domain": "example.com", "home_url": "https://example.com/",
+                "site_name": null, "favicon": null, "technologies": [],
+                "robots": {"found": false, "crawl_delay_secs": null, "disallow": [], "sitemaps": []},
+                "response_ms": 90, "ttfb_ms": null, "profiled_at": "2026-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!((profile.dns, profile.certificate), (None, None));
+
+        let not_after = chrono::Utc::now();
+        let certificate = SiteCertificate {
+            subject: "CN=example.com".to_string(),
+            issuer: "C=US, O=Let's Encrypt, CN=R11".to_string(),
+            san: vec!["example.com".to_string()],
+            serial: "03:a1".to_string(),
+            not_before: not_after - chrono::Duration::days(90),
+            not_after,
+        };
+        let days = chrono::Duration::days;
+        assert_eq!(certificate.days_left(not_after - days(12)), 12);
+        assert_eq!(certificate.days_left(not_after + days(3)), -3);
+    }
 }
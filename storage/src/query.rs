@@ -0,0 +1,149 @@
+//! Content queries
+//!
+//! A [`ContentQuery`] selects stored documents by domain, platform, URL, tag,
+//! scrape time and text. It can be built directly or parsed from a compact
+//! `key:value` string such as `domain:example.com tag:news since:2024-01-01 rust`.
+
+use crate::models::StoredContent;
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Filter over stored content; all set fields must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentQuery {
+    pub domain: Option<String>,
+    pub platform: Option<String>,
+    pub url_contains: Option<String>,
+    /// Exact canonical URL, matching every URL variant of one page
+    pub canonical: Option<String>,
+    /// Case-insensitive match against title and text
+    pub search: Option<String>,
+    pub tag: Option<String>,
+    pub scraped_after: Option<DateTime<Utc>>,
+    pub scraped_before: Option<DateTime<Utc>>,
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+        Err(_) => bail!("Invalid date '{}', expected YYYY-MM-DD or RFC 3339", value),
+    }
+}
+
+impl ContentQuery {
+    /// Parse `key:value` terms; other words become the text search
+    ///
+    /// Keys: `domain`, `platform`, `url`, `canonical`, `tag`, `since`/`after`,
+    /// `until`/`before`.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut words = Vec::new();
+
+        for term in query.split_whitespace() {
+            let Some((key, value)) = term.split_once(':') else {
+                words.push(term);
+                continue;
+            };
+            match key {
+                "domain" => parsed.domain = Some(value.to_string()),
+                "platform" => parsed.platform = Some(value.to_string()),
+                "url" => parsed.url_contains = Some(value.to_string()),
+                "canonical" => parsed.canonical = Some(value.to_string()),
+                "tag" => parsed.tag = Some(value.to_string()),
+                "since" | "after" => parsed.scraped_after = Some(parse_time(value)?),
+                "until" | "before" => parsed.scraped_before = Some(parse_time(value)?),
+                _ => words.push(term),
+            }
+        }
+
+        if !words.is_empty() {
+            parsed.search = Some(words.join(" "));
+        }
+        Ok(parsed)
+    }
+
+    pub fn matches(&self, content: &StoredContent) -> bool {
+        let search = self.search.as_ref().map(|search| search.to_lowercase());
+        self.domain
+            .as_ref()
+            .is_none_or(|domain| &content.domain == domain)
+            && self
+                .platform
+                .as_ref()
+                .is_none_or(|platform| &content.platform == platform)
+            && self
+                .url_contains
+                .as_ref()
+                .is_none_or(|needle| content.url.contains(needle.as_str()))
+            && self
+                .canonical
+                .as_ref()
+                .is_none_or(|canonical| content.canonical_url() == canonical)
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|tag| content.tags.contains(tag))
+            && self
+                .scraped_after
+                .is_none_or(|after| content.scraped_at >= after)
+            && self
+                .scraped_before
+                .is_none_or(|before| content.scraped_at < before)
+            && search.is_none_or(|search| {
+                [&content.title, &content.text]
+                    .into_iter()
+                    .flatten()
+                    .any(|field| field.to_lowercase().contains(&search))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_query() {
+        let query =
+            ContentQuery::parse("domain:example.com tag:news since:2024-01-01 rust async").unwrap();
+        assert_eq!(query.domain.as_deref(), Some("example.com"));
+        assert_eq!(query.tag.as_deref(), Some("news"));
+        assert_eq!(query.search.as_deref(), Some("rust async"));
+        assert_eq!(
+            query.scraped_after.unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+
+        assert!(ContentQuery::parse("since:last-week").is_err());
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let content = StoredContent::new(
+            "https://example.com/blog/rust".to_string(),
+            "example.com".to_string(),
+            "generic".to_string(),
+            Some("Async Rust".to_string()),
+            None,
+            None,
+            HashMap::new(),
+        );
+
+        assert!(ContentQuery::parse("url:/blog rust")
+            .unwrap()
+            .matches(&content));
+        assert!(!ContentQuery::parse("platform:linkedin")
+            .unwrap()
+            .matches(&content));
+        assert!(ContentQuery::default().matches(&content));
+        assert!(
+            ContentQuery::parse("canonical:https://example.com/blog/rust")
+                .unwrap()
+                .matches(&content)
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Per-job disk quotas
+//!
+//! A [`DiskQuota`] is shared by everything one job writes: exports and
+//! artifacts reserve bytes before writing them, and once the limit is reached
+//! every further reservation fails, so a job left running unattended stops
+//! instead of filling the disk.
+
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// A write that would take a job over its quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub limit: u64,
+    pub used: u64,
+    pub requested: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Disk quota exceeded: {} of {} bytes used, {} more requested",
+            self.used, self.limit, self.requested
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Disk space used by a job, optionally capped
+#[derive(Debug, Default)]
+pub struct DiskQuota {
+    limit: Option<u64>,
+    used: AtomicU64,
+    exceeded: AtomicBool,
+}
+
+impl DiskQuota {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Accounting only, without a limit
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// Bytes reserved so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Whether a reservation has been refused
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Account for `bytes` about to be written, unless that would go over the limit
+    pub fn reserve(&self, bytes: u64) -> Result<(), QuotaExceeded> {
+        let Some(limit) = self.limit else {
+            self.used.fetch_add(bytes, Ordering::Relaxed);
+            return Ok(());
+        };
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|total| *total <= limit)
+            })
+            .map(|_| ())
+            .map_err(|used| {
+                self.exceeded.store(true, Ordering::Relaxed);
+                QuotaExceeded {
+                    limit,
+                    used,
+                    requested: bytes,
+                }
+            })
+    }
+
+    /// Give back `bytes` that were freed, e.g. by compressing or deleting a file
+    pub fn release(&self, bytes: u64) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+}
+
+/// Writer that reserves quota for every byte written through it
+#[derive(Debug)]
+pub struct QuotaWriter<W> {
+    inner: W,
+    quota: Arc<DiskQuota>,
+}
+
+impl<W: Write> QuotaWriter<W> {
+    pub fn new(inner: W, quota: Arc<DiskQuota>) -> Self {
+        Self { inner, quota }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for QuotaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.quota
+            .reserve(buf.len() as u64)
+            .map_err(io::Error::other)?;
+        let written = self.inner.write(buf)?;
+        // Short writes are retried by the caller and reserve again
+        self.quota.release((buf.len() - written) as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_refuses_writes_past_limit() {
+        let quota = Arc::new(DiskQuota::new(10));
+        quota.reserve(6).unwrap();
+        assert_eq!(
+            quota.reserve(5),
+            Err(QuotaExceeded {
+                limit: 10,
+                used: 6,
+                requested: 5
+            })
+        );
+        assert!(quota.is_exceeded());
+        quota.release(2);
+        quota.reserve(5).unwrap();
+        assert_eq!(quota.used(), 9);
+
+        let mut writer = QuotaWriter::new(Vec::new(), Arc::new(DiskQuota::new(4)));
+        writer.write_all(b"abc").unwrap();
+        assert!(writer.write_all(b"de").is_err());
+        assert_eq!(writer.into_inner(), b"abc");
+
+        let unlimited = DiskQuota::unlimited();
+        unlimited.reserve(u64::MAX / 2).unwrap();
+        assert!(!unlimited.is_exceeded());
+    }
+}
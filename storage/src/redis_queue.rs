@@ -0,0 +1,487 @@
+//! Shared URL queue in Redis
+//!
+//! A [`RedisQueue`] lets several swoop processes, on one machine or many,
+//! work through the same crawl. Every URL is enqueued once per queue. A
+//! worker claims a URL with a lease and acks it when done; a failed URL goes
+//! back to the queue until it has been claimed `max_attempts` times, then
+//! moves to the dead-letter list with its last error. URLs whose lease runs
+//! out, because their worker died or stalled, are handed out again.
+//!
+//! Each operation is a Lua script, so it is atomic however many workers
+//! share the queue. The keys of a queue named `crawl` are:
+//! - `swoop:{crawl}:pending` URLs waiting to be claimed, oldest last
+//! - `swoop:{crawl}:leases` claimed URLs scored by lease deadline (ms)
+//! - `swoop:{crawl}:attempts` claims per URL
+//! - `swoop:{crawl}:seen` every URL ever enqueued
+//! - `swoop:{crawl}:done` count of acked URLs
+//! - `swoop:{crawl}:dead` [`DeadLetter`]s as JSON, newest first
+
+use crate::dead_letters::DeadLetter;
+use anyhow::{Context, Result};
+use redis::{aio::ConnectionManager, AsyncCommands, Script};
+use std::{fmt, time::Duration};
+
+/// How long a claimed URL stays with its worker before it is handed out again
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(10 * 60);
+
+/// Claims per URL before it is dead-lettered
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// URLs sent to Redis per enqueue call
+const ENQUEUE_BATCH: usize = 1000;
+
+const ENQUEUE: &str = r"
+local added = 0
+for _, url in ipairs(ARGV) do
+  if redis.call('SADD', KEYS[2], url) == 1 then
+    redis.call('LPUSH', KEYS[1], url)
+    added = added + 1
+  end
+end
+return added
+";
+
+const CLAIM: &str = r"
+local time = redis.call('TIME')
+local now = time[1] * 1000 + math.floor(time[2] / 1000)
+while true do
+  local url = redis.call('ZRANGEBYSCORE', KEYS[2], '-inf', now, 'LIMIT', 0, 1)[1]
+  if url then
+    local attempts = tonumber(redis.call('HGET', KEYS[3], url) or '0')
+    if attempts >= tonumber(ARGV[2]) then
+      redis.call('ZREM', KEYS[2], url)
+      redis.call('HDEL', KEYS[3], url)
+      redis.call('LPUSH', KEYS[4], cjson.encode({
+        url = url, attempts = attempts, error = 'Lease expired', failed_at = now
+      }))
+      url = nil
+    end
+  else
+    url = redis.call('RPOP', KEYS[1])
+    if not url then
+      return false
+    end
+  end
+  if url then
+    redis.call('ZADD', KEYS[2], now + tonumber(ARGV[1]), url)
+    return {url, redis.call('HINCRBY', KEYS[3], url, 1), redis.call('LLEN', KEYS[1])}
+  end
+end
+";
+
+const ACK: &str = r"
+if redis.call('ZREM', KEYS[1], ARGV[1]) == 0 then
+  return 0
+end
+redis.call('HDEL', KEYS[2], ARGV[1])
+redis.call('INCR', KEYS[3])
+return 1
+";
+
+const NACK: &str = r"
+if redis.call('ZREM', KEYS[2], ARGV[1]) == 0 then
+  return -1
+end
+local attempts = tonumber(redis.call('HGET', KEYS[3], ARGV[1]) or '0')
+if attempts < tonumber(ARGV[3]) then
+  redis.call('LPUSH', KEYS[1], ARGV[1])
+  return 0
+end
+local time = redis.call('TIME')
+redis.call('HDEL', KEYS[3], ARGV[1])
+redis.call('LPUSH', KEYS[4], cjson.encode({
+  url = ARGV[1], attempts = attempts, error = ARGV[2],
+  failed_at = time[1] * 1000 + math.floor(time[2] / 1000)
+}))
+return 1
+";
+
+const REQUEUE: &str = r"
+local requeued = 0
+for i = 1, #ARGV, 2 do
+  if redis.call('LREM', KEYS[2], 1, ARGV[i]) == 1 then
+    redis.call('LPUSH', KEYS[1], ARGV[i + 1])
+    requeued = requeued + 1
+  end
+end
+return requeued
+";
+
+const RELEASE: &str = r"
+if redis.call('ZREM', KEYS[2], ARGV[1]) == 0 then
+  return 0
+end
+redis.call('HINCRBY', KEYS[3], ARGV[1], -1)
+redis.call('RPUSH', KEYS[1], ARGV[1])
+return 1
+";
+
+/// A URL claimed from the queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimedUrl {
+    pub url: String,
+    /// 1 for the first claim of the URL
+    pub attempt: u32,
+    /// URLs still waiting in the queue
+    pub backlog: u64,
+}
+
+/// What became of a URL handed back with [`RedisQueue::nack`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nack {
+    /// Back in the queue for another attempt
+    Requeued,
+    /// Out of attempts and moved to the dead-letter list
+    DeadLettered,
+    /// The lease ran out and the URL was already handed out again
+    LeaseLost,
+}
+
+/// Queue sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub pending: u64,
+    /// Claimed and not yet acked
+    pub in_flight: u64,
+    pub done: u64,
+    pub dead: u64,
+    /// Distinct URLs ever enqueued
+    pub seen: u64,
+}
+
+impl QueueStats {
+    /// Whether nothing is left to claim now or later
+    pub fn is_drained(&self) -> bool {
+        self.pending == 0 && self.in_flight == 0
+    }
+}
+
+/// Keys of one queue; the `{name}` hash tag keeps them in one Redis Cluster
+/// slot, as scripts touching several keys require
+#[derive(Debug, Clone)]
+struct QueueKeys {
+    pending: String,
+    leases: String,
+    attempts: String,
+    seen: String,
+    done: String,
+    dead: String,
+}
+
+impl QueueKeys {
+    fn new(name: &str) -> Self {
+        let key = |suffix: &str| format!("swoop:{{{}}}:{}", name, suffix);
+        Self {
+            pending: key("pending"),
+            leases: key("leases"),
+            attempts: key("attempts"),
+            seen: key("seen"),
+            done: key("done"),
+            dead: key("dead"),
+        }
+    }
+}
+
+/// URL queue shared through Redis
+#[derive(Clone)]
+pub struct RedisQueue {
+    connection: ConnectionManager,
+    name: String,
+    keys: QueueKeys,
+    lease: Duration,
+    max_attempts: u32,
+}
+
+impl fmt::Debug for RedisQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisQueue")
+            .field("name", &self.name)
+            .field("lease", &self.lease)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisQueue {
+    /// Connect to the queue `name` on the Redis server at `url`
+    /// (`redis://[:password@]host[:port][/db]`)
+    pub async fn connect(url: &str, name: &str) -> Result<Self> {
+        if name.is_empty() || name.contains(['{', '}']) {
+            anyhow::bail!("Invalid queue name: {:?}", name);
+        }
+        let client = redis::Client::open(url).context("Invalid Redis URL")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(Self {
+            connection,
+            name: name.to_string(),
+            keys: QueueKeys::new(name),
+            lease: DEFAULT_LEASE,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        })
+    }
+
+    /// Hand claimed URLs out again once they have been held for `lease`
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Dead-letter URLs after `max_attempts` claims
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add `urls` that were never enqueued before, returning how many were new
+    pub async fn enqueue(&self, urls: &[String]) -> Result<usize> {
+        let script = Script::new(ENQUEUE);
+        let mut added = 0;
+        for batch in urls.chunks(ENQUEUE_BATCH) {
+            let count: usize = script
+                .key(&self.keys.pending)
+                .key(&self.keys.seen)
+                .arg(batch)
+                .invoke_async(&mut self.connection.clone())
+                .await
+                .context("Failed to enqueue URLs")?;
+            added += count;
+        }
+        Ok(added)
+    }
+
+    /// Claim the next URL, reclaiming expired leases first; `None` when
+    /// nothing is waiting
+    pub async fn claim(&self) -> Result<Option<ClaimedUrl>> {
+        let claimed: Option<(String, u32, u64)> = Script::new(CLAIM)
+            .key(&self.keys.pending)
+            .key(&self.keys.leases)
+            .key(&self.keys.attempts)
+            .key(&self.keys.dead)
+            .arg(self.lease.as_millis() as u64)
+            .arg(self.max_attempts)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .context("Failed to claim a URL")?;
+        Ok(claimed.map(|(url, attempt, backlog)| ClaimedUrl {
+            url,
+            attempt,
+            backlog,
+        }))
+    }
+
+    /// Mark a claimed URL done; false if it was already acked, by the worker
+    /// its expired lease went to
+    pub async fn ack(&self, url: &str) -> Result<bool> {
+        let acked: i64 = Script::new(ACK)
+            .key(&self.keys.leases)
+            .key(&self.keys.attempts)
+            .key(&self.keys.done)
+            .arg(url)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .context("Failed to ack a URL")?;
+        Ok(acked == 1)
+    }
+
+    /// Hand a claimed URL back after it failed with `error`
+    pub async fn nack(&self, url: &str, error: &str) -> Result<Nack> {
+        let outcome: i64 = Script::new(NACK)
+            .key(&self.keys.pending)
+            .key(&self.keys.leases)
+            .key(&self.keys.attempts)
+            .key(&self.keys.dead)
+            .arg(url)
+            .arg(error)
+            .arg(self.max_attempts)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .context("Failed to nack a URL")?;
+        Ok(match outcome {
+            0 => Nack::Requeued,
+            1 => Nack::DeadLettered,
+            _ => Nack::LeaseLost,
+        })
+    }
+
+    /// Put a claimed URL back at the front of the queue without counting the
+    /// attempt, for a worker that can't take it after all
+    pub async fn release(&self, url: &str) -> Result<bool> {
+        let released: i64 = Script::new(RELEASE)
+            .key(&self.keys.pending)
+            .key(&self.keys.leases)
+            .key(&self.keys.attempts)
+            .arg(url)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .context("Failed to release a URL")?;
+        Ok(released == 1)
+    }
+
+    pub async fn stats(&self) -> Result<QueueStats> {
+        let (pending, in_flight, done, dead, seen): (u64, u64, Option<u64>, u64, u64) =
+            redis::pipe()
+                .llen(&self.keys.pending)
+                .zcard(&self.keys.leases)
+                .get(&self.keys.done)
+                .llen(&self.keys.dead)
+                .scard(&self.keys.seen)
+                .query_async(&mut self.connection.clone())
+                .await
+                .context("Failed to read queue stats")?;
+        Ok(QueueStats {
+            pending,
+            in_flight,
+            done: done.unwrap_or_default(),
+            dead,
+            seen,
+        })
+    }
+
+    /// Dead-lettered URLs, newest first
+    pub async fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        Ok(self
+            .raw_dead_letters()
+            .await?
+            .into_iter()
+            .map(|(_, letter)| letter)
+            .collect())
+    }
+
+    /// Move the dead letters `select` picks back into the queue for another
+    /// round of attempts, returning them
+    pub async fn requeue(&self, select: impl Fn(&DeadLetter) -> bool) -> Result<Vec<DeadLetter>> {
+        let selected: Vec<(String, DeadLetter)> = self
+            .raw_dead_letters()
+            .await?
+            .into_iter()
+            .filter(|(_, letter)| select(letter))
+            .collect();
+        let script = Script::new(REQUEUE);
+        for batch in selected.chunks(ENQUEUE_BATCH) {
+            let mut invocation = script.key(&self.keys.pending);
+            invocation.key(&self.keys.dead);
+            for (entry, letter) in batch {
+                invocation.arg(entry).arg(&letter.url);
+            }
+            let _: usize = invocation
+                .invoke_async(&mut self.connection.clone())
+                .await
+                .context("Failed to requeue dead letters")?;
+        }
+        Ok(selected.into_iter().map(|(_, letter)| letter).collect())
+    }
+
+    /// Dead-letter entries as stored, which removing them needs, and parsed
+    async fn raw_dead_letters(&self) -> Result<Vec<(String, DeadLetter)>> {
+        let entries: Vec<String> = self
+            .connection
+            .clone()
+            .lrange(&self.keys.dead, 0, -1)
+            .await
+            .context("Failed to read the dead-letter list")?;
+        entries
+            .into_iter()
+            .map(|entry| {
+                let letter = serde_json::from_str(&entry).context("Invalid dead-letter entry")?;
+                Ok((entry, letter))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letters_from_scripts() {
+        // As cjson encodes them
+        let letter: DeadLetter = serde_json::from_str(
+            r#"{"failed_at":1760000000000,"url":"https://example.com/a","error":"HTTP 503","attempts":3}"#,
+        )
+        .unwrap();
+        assert_eq!(letter.attempts, 3);
+        assert_eq!(letter.error, "HTTP 503");
+        assert_eq!(letter.failed_at.timestamp(), 1_760_000_000);
+
+        let keys = QueueKeys::new("crawl");
+        assert_eq!(keys.pending, "swoop:{crawl}:pending");
+        assert_eq!(keys.dead, "swoop:{crawl}:dead");
+    }
+
+    /// Needs a Redis server at `REDIS_URL` (default `redis://127.0.0.1/`)
+    #[tokio::test]
+    #[ignore]
+    async fn test_claim_ack_and_dead_letter() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let name = format!("test-{}", uuid::Uuid::new_v4());
+        let queue = RedisQueue::connect(&url, &name)
+            .await
+            .unwrap()
+            .with_max_attempts(2);
+        let urls = vec![
+            "https://example.com/a".to_string(),
+            "https://example.com/b".to_string(),
+        ];
+        assert_eq!(queue.enqueue(&urls).await.unwrap(), 2);
+        assert_eq!(queue.enqueue(&urls).await.unwrap(), 0);
+
+        let first = queue.claim().await.unwrap().unwrap();
+        assert_eq!(
+            (first.url.as_str(), first.attempt, first.backlog),
+            (urls[0].as_str(), 1, 1)
+        );
+        assert!(queue.ack(&first.url).await.unwrap());
+        assert!(!queue.ack(&first.url).await.unwrap());
+
+        let second = queue.claim().await.unwrap().unwrap();
+        assert_eq!(
+            queue.nack(&second.url, "HTTP 503").await.unwrap(),
+            Nack::Requeued
+        );
+        let retry = queue.claim().await.unwrap().unwrap();
+        assert_eq!((retry.url.as_str(), retry.attempt), (urls[1].as_str(), 2));
+        assert_eq!(
+            queue.nack(&retry.url, "HTTP 503").await.unwrap(),
+            Nack::DeadLettered
+        );
+        assert!(queue.claim().await.unwrap().is_none());
+
+        let stats = queue.stats().await.unwrap();
+        assert_eq!((stats.done, stats.dead, stats.seen), (1, 1, 2));
+        assert!(stats.is_drained());
+        let dead = queue.dead_letters().await.unwrap();
+        assert_eq!(
+            (dead[0].url.as_str(), dead[0].attempts),
+            (urls[1].as_str(), 2)
+        );
+
+        // Dead letters go back for a fresh round of attempts
+        let requeued = queue
+            .requeue(|letter| letter.error == "HTTP 503")
+            .await
+            .unwrap();
+        assert_eq!(requeued.len(), 1);
+        let again = queue.claim().await.unwrap().unwrap();
+        assert_eq!((again.url.as_str(), again.attempt), (urls[1].as_str(), 1));
+        queue.ack(&again.url).await.unwrap();
+        assert_eq!(queue.stats().await.unwrap().dead, 0);
+
+        // Expired leases are handed out again
+        let queue = queue.with_lease(Duration::ZERO);
+        queue
+            .enqueue(&["https://example.com/c".to_string()])
+            .await
+            .unwrap();
+        let stalled = queue.claim().await.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let reclaimed = queue.claim().await.unwrap().unwrap();
+        assert_eq!((reclaimed.url, reclaimed.attempt), (stalled.url, 2));
+    }
+}
@@ -3,8 +3,10 @@
 //! This module provides object storage using S3-compatible APIs for data archival.
 
 use crate::{models, S3Config, StorageBackend};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
 
 pub struct S3Store {
     _config: S3Config,
@@ -45,3 +47,196 @@ impl StorageBackend for S3Store {
         Ok(models::StorageStats::default())
     }
 }
+
+/// Minimal S3 client for single objects, signing requests with AWS Signature V4
+///
+/// Objects are addressed path-style (`<endpoint>/<bucket>/<key>`), which works
+/// with AWS as well as MinIO and other S3-compatible servers.
+#[derive(Debug, Clone)]
+pub struct S3Client {
+    config: S3Config,
+    http: reqwest::Client,
+}
+
+impl S3Client {
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.config.bucket
+    }
+
+    fn object_url(&self, key: &str) -> Result<reqwest::Url> {
+        let url = format!(
+            "{}/{}/{}",
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.bucket,
+            key.trim_start_matches('/')
+        );
+        reqwest::Url::parse(&url).with_context(|| format!("Invalid S3 object URL: {}", url))
+    }
+
+    /// Upload `body` as `key`
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        let url = self.object_url(key)?;
+        let payload_hash = format!("{:x}", Sha256::digest(&body));
+        let headers = self.sign("PUT", &url, &payload_hash, Some(content_type), chrono::Utc::now());
+        let mut request = self.http.put(url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("S3 PUT {} failed: HTTP {}", key, response.status());
+        }
+        Ok(())
+    }
+
+    /// Whether `key` exists in the bucket
+    pub async fn head_object(&self, key: &str) -> Result<bool> {
+        let url = self.object_url(key)?;
+        let payload_hash = format!("{:x}", Sha256::digest(b""));
+        let headers = self.sign("HEAD", &url, &payload_hash, None, chrono::Utc::now());
+        let mut request = self.http.head(url);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => anyhow::bail!("S3 HEAD {} failed: HTTP {}", key, status),
+        }
+    }
+
+    /// Headers, including `Authorization`, for a request without query parameters
+    fn sign(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        payload_hash: &str,
+        content_type: Option<&str>,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<(String, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // Sorted by name, as the canonical request requires
+        let mut headers = Vec::new();
+        if let Some(content_type) = content_type {
+            headers.push(("content-type".to_string(), content_type.to_string()));
+        }
+        headers.push(("host".to_string(), host));
+        headers.push(("x-amz-content-sha256".to_string(), payload_hash.to_string()));
+        headers.push(("x-amz-date".to_string(), amz_date.clone()));
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method,
+            url.path(),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date,
+            scope,
+            Sha256::digest(canonical_request.as_bytes())
+        );
+        let key = signing_key(
+            &self.config.secret_access_key,
+            &date,
+            &self.config.region,
+            "s3",
+        );
+        let signature = format!("{:x}", hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        headers.retain(|(name, _)| name != "host");
+        headers.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.config.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        headers
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> sha2::digest::Output<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes()
+}
+
+/// Signature V4 signing key for one day, region and service
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request").to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // Example from the AWS Signature V4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        let hex: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            hex,
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_sign_path_style_request() {
+        let client = S3Client::new(S3Config {
+            endpoint: "http://localhost:9000/".to_string(),
+            access_key_id: "AKID".to_string(),
+            secret_access_key: "secret".to_string(),
+            bucket: "media".to_string(),
+            region: "us-east-1".to_string(),
+        });
+        let url = client.object_url("/objects/ab/abc.png").unwrap();
+        assert_eq!(url.as_str(), "http://localhost:9000/media/objects/ab/abc.png");
+
+        let now = chrono::DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let headers = client.sign("PUT", &url, "UNSIGNED", Some("image/png"), now);
+        let authorization = &headers.iter().find(|(name, _)| name == "authorization").unwrap().1;
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKID/20240501/us-east-1/s3/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-content-sha256;x-amz-date, Signature="
+        ));
+        assert!(headers.iter().all(|(name, _)| name != "host"));
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This module provides high-performance time-series data storage using ScyllaDB.
 
-use crate::{models, ScyllaConfig, StorageBackend};
+use crate::{models, ContentCatalog, JobStore, ScyllaConfig, StorageBackend};
 use anyhow::Result;
 use async_trait::async_trait;
 use scylla::{Session, SessionBuilder};
@@ -100,6 +100,27 @@ impl ScyllaStore {
 
         self.session.query_unpaged(create_stats_table, &[]).await?;
 
+        // Crawl job records
+        let create_jobs_table = "
+            CREATE TABLE IF NOT EXISTS jobs (
+                id uuid PRIMARY KEY,
+                name text,
+                tags list<text>,
+                seeds list<text>,
+                config map<text, text>,
+                status text,
+                started_at timestamp,
+                finished_at timestamp,
+                total bigint,
+                successful bigint,
+                failed bigint,
+                bytes bigint,
+                rerun_of text
+            )
+        ";
+
+        self.session.query_unpaged(create_jobs_table, &[]).await?;
+
         Ok(())
     }
 }
@@ -156,6 +177,52 @@ impl StorageBackend for ScyllaStore {
     }
 }
 
+#[async_trait]
+impl ContentCatalog for ScyllaStore {
+    async fn list_content(&self) -> Result<Vec<models::StoredContent>> {
+        // TODO: Implement paged full-table scan
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl JobStore for ScyllaStore {
+    async fn save_job(&self, job: &models::Job) -> Result<()> {
+        let prepared = self.session.prepare("INSERT INTO jobs (id, name, tags, seeds, config, status, started_at, finished_at, total, successful, failed, bytes, rerun_of) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)").await?;
+        self.session
+            .execute_unpaged(
+                &prepared,
+                (
+                    uuid::Uuid::parse_str(&job.id)?,
+                    &job.name,
+                    &job.tags,
+                    &job.seeds,
+                    &job.config,
+                    job.status.as_str(),
+                    job.started_at,
+                    job.finished_at,
+                    job.counts.total as i64,
+                    job.counts.successful as i64,
+                    job.counts.failed as i64,
+                    job.counts.bytes as i64,
+                    &job.rerun_of,
+                ),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_job(&self, _id: &str) -> Result<Option<models::Job>> {
+        // TODO: Implement job lookup
+        Ok(None)
+    }
+
+    async fn list_jobs(&self, _limit: usize) -> Result<Vec<models::Job>> {
+        // TODO: Implement job listing
+        Ok(Vec::new())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
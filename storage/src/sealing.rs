@@ -0,0 +1,604 @@
+//! Encrypted and signed exports
+//!
+//! [`encrypt_file`] seals an export with AES-256-GCM under an [`ExportKey`].
+//! Files are encrypted in 64 KiB chunks (the STREAM construction), so exports
+//! of any size are sealed without being held in memory, and truncating or
+//! reordering chunks fails decryption. A [`Manifest`] lists the SHA-256 of
+//! every file of an export along with its [`Provenance`] (the job, its
+//! configuration, seeds and result counts); signed with an Ed25519 key it gets a detached
+//! `.sig` next to it, so the receiving end can check with
+//! [`verify_manifest`] that nothing was altered or swapped in transit.
+
+use aes_gcm::{
+    aead::{
+        rand_core::RngCore,
+        stream::{DecryptorBE32, EncryptorBE32},
+        KeyInit, OsRng,
+    },
+    Aes256Gcm, Key,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::Job;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub use ed25519_dalek::SigningKey;
+
+/// Extension appended to encrypted files
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+/// Extension appended to a manifest for its detached signature
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Leading bytes of an encrypted file, naming the format version
+const MAGIC: &[u8; 8] = b"SWOOPEN1";
+
+/// Plaintext bytes per encrypted chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Authentication tag added to every chunk
+const TAG_SIZE: usize = 16;
+
+/// Nonce prefix of the STREAM construction: the 12-byte AES-GCM nonce minus
+/// the 4-byte counter and 1-byte last-chunk flag
+const NONCE_PREFIX_SIZE: usize = 7;
+
+/// 256-bit key for encrypting exports, kept as hex in key files
+#[derive(Clone)]
+pub struct ExportKey(Key<Aes256Gcm>);
+
+impl ExportKey {
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = decode_hex(hex.trim()).context("Export key is not valid hex")?;
+        if bytes.len() != 32 {
+            bail!(
+                "Export key must be 32 bytes (64 hex digits), got {}",
+                bytes.len()
+            );
+        }
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+
+    /// Read a key file holding the key as hex
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let hex = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read export key {}", path.display()))?;
+        Self::from_hex(&hex).with_context(|| format!("Invalid export key in {}", path.display()))
+    }
+
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+}
+
+impl std::fmt::Debug for ExportKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ExportKey(..)")
+    }
+}
+
+/// Encrypt `path` into `<path>.enc` and remove the plaintext
+pub fn encrypt_file(key: &ExportKey, path: &Path) -> Result<PathBuf> {
+    let mut encrypted_name = path.as_os_str().to_owned();
+    encrypted_name.push(".");
+    encrypted_name.push(ENCRYPTED_EXTENSION);
+    let encrypted_path = PathBuf::from(encrypted_name);
+
+    let mut prefix = [0u8; NONCE_PREFIX_SIZE];
+    OsRng.fill_bytes(&mut prefix);
+    let mut encryptor = EncryptorBE32::from_aead(Aes256Gcm::new(&key.0), (&prefix).into());
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut writer = BufWriter::new(File::create(&encrypted_path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&prefix)?;
+
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE];
+    let mut len = read_full(&mut reader, &mut chunk)?;
+    loop {
+        // The last chunk is sealed differently, so look one chunk ahead
+        let next_len = read_full(&mut reader, &mut next)?;
+        if next_len == 0 {
+            let sealed = encryptor
+                .encrypt_last(&chunk[..len])
+                .map_err(|_| anyhow!("Failed to encrypt {}", path.display()))?;
+            writer.write_all(&sealed)?;
+            break;
+        }
+        let sealed = encryptor
+            .encrypt_next(&chunk[..len])
+            .map_err(|_| anyhow!("Failed to encrypt {}", path.display()))?;
+        writer.write_all(&sealed)?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    fs::remove_file(path)?;
+    Ok(encrypted_path)
+}
+
+/// Decrypt a file written by [`encrypt_file`] into `output`
+///
+/// Fails if the file was altered, truncated or encrypted under another key;
+/// `output` may then hold a partial plaintext and should be discarded.
+pub fn decrypt_file(key: &ExportKey, path: &Path, output: &Path) -> Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; MAGIC.len() + NONCE_PREFIX_SIZE];
+    reader
+        .read_exact(&mut header)
+        .with_context(|| format!("{} is not an encrypted export", path.display()))?;
+    if &header[..MAGIC.len()] != MAGIC {
+        bail!("{} is not an encrypted export", path.display());
+    }
+    let prefix: [u8; NONCE_PREFIX_SIZE] = header[MAGIC.len()..].try_into()?;
+    let mut decryptor = DecryptorBE32::from_aead(Aes256Gcm::new(&key.0), (&prefix).into());
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    let failed = || {
+        anyhow!(
+            "Failed to decrypt {}: wrong key or corrupted file",
+            path.display()
+        )
+    };
+    let mut chunk = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut len = read_full(&mut reader, &mut chunk)?;
+    loop {
+        let next_len = read_full(&mut reader, &mut next)?;
+        if next_len == 0 {
+            let plain = decryptor
+                .decrypt_last(&chunk[..len])
+                .map_err(|_| failed())?;
+            writer.write_all(&plain)?;
+            break;
+        }
+        let plain = decryptor
+            .decrypt_next(&chunk[..len])
+            .map_err(|_| failed())?;
+        writer.write_all(&plain)?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Fill `buf` as far as the reader allows, returning how much was read
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Ed25519 key for signing manifests, kept as a hex seed in key files
+pub fn generate_signing_key() -> SigningKey {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    SigningKey::from_bytes(&seed)
+}
+
+/// Read a signing key file holding the 32-byte seed as hex
+pub fn load_signing_key(path: impl AsRef<Path>) -> Result<SigningKey> {
+    let path = path.as_ref();
+    let hex = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signing key {}", path.display()))?;
+    let seed: [u8; 32] = decode_hex(hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid signing key in {}: expected 64 hex digits",
+                path.display()
+            )
+        })?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+pub fn signing_key_hex(key: &SigningKey) -> String {
+    encode_hex(key.as_bytes())
+}
+
+/// Public half of a signing key, as given to `verify`
+pub fn public_key_hex(key: &SigningKey) -> String {
+    encode_hex(key.verifying_key().as_bytes())
+}
+
+/// One file of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// File name, relative to the manifest
+    pub name: String,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// Where an export came from: the job, its configuration and what it fetched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub job_id: String,
+    pub job_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerun_of: Option<String>,
+    /// Version of swoop that ran the job
+    pub swoop_version: String,
+    /// Configuration snapshot the job ran with
+    pub config: BTreeMap<String, String>,
+    /// SHA-256 of the files the configuration names (extraction script,
+    /// policies, dictionaries), so a replay can tell whether its rules changed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+    pub seeds: usize,
+    /// SHA-256 of the seed URLs, one per line in job order
+    pub seeds_sha256: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Results per HTTP status; `ok` or `failed` for results without one,
+    /// such as browser fetches and connection errors
+    pub status_counts: BTreeMap<String, u64>,
+    pub domain_counts: BTreeMap<String, u64>,
+}
+
+impl Provenance {
+    /// Provenance of `job`, finishing now; results are added with [`Self::record`]
+    pub fn from_job(job: &Job, swoop_version: &str) -> Self {
+        Self {
+            job_id: job.id.clone(),
+            job_name: job.name.clone(),
+            rerun_of: job.rerun_of.clone(),
+            swoop_version: swoop_version.to_string(),
+            config: job.config.clone().into_iter().collect(),
+            inputs: BTreeMap::new(),
+            seeds: job.seeds.len(),
+            seeds_sha256: seeds_digest(&job.seeds),
+            started_at: job.started_at,
+            finished_at: job.finished_at.unwrap_or_else(Utc::now),
+            status_counts: BTreeMap::new(),
+            domain_counts: BTreeMap::new(),
+        }
+    }
+
+    /// Record the digest of an input file under `name`
+    pub fn record_input(&mut self, name: &str, path: &Path) -> Result<()> {
+        let (sha256, _) =
+            sha256_file(path).with_context(|| format!("Failed to hash {}", path.display()))?;
+        self.inputs.insert(name.to_string(), sha256);
+        Ok(())
+    }
+
+    /// Count one result
+    pub fn record(&mut self, status: Option<u16>, success: bool, domain: &str) {
+        let status = match status {
+            Some(code) => code.to_string(),
+            None if success => "ok".to_string(),
+            None => "failed".to_string(),
+        };
+        *self.status_counts.entry(status).or_default() += 1;
+        *self.domain_counts.entry(domain.to_string()).or_default() += 1;
+    }
+}
+
+/// Digest identifying a seed list, so reruns can be matched to their seeds
+pub fn seeds_digest(seeds: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for seed in seeds {
+        hasher.update(seed.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Checksums of every file of an export, and where the export came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub created_at: DateTime<Utc>,
+    /// Whether the listed files are encrypted exports
+    pub encrypted: bool,
+    pub files: Vec<ManifestEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Hex Ed25519 public key of the signature, when the manifest is signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl Manifest {
+    /// Hash `files`, which must share a directory with the manifest
+    pub fn build(files: &[PathBuf], encrypted: bool) -> Result<Self> {
+        let files = files
+            .iter()
+            .map(|path| {
+                let (sha256, bytes) = sha256_file(path)?;
+                let name = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("{} is not a file", path.display()))?
+                    .to_string_lossy()
+                    .into_owned();
+                Ok(ManifestEntry {
+                    name,
+                    sha256,
+                    bytes,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            created_at: Utc::now(),
+            encrypted,
+            files,
+            provenance: None,
+            public_key: None,
+        })
+    }
+
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Write the manifest to `path`, plus `<path>.sig` when `signer` is given
+    pub fn write(mut self, path: &Path, signer: Option<&SigningKey>) -> Result<()> {
+        self.public_key = signer.map(public_key_hex);
+        let json = serde_json::to_vec_pretty(&self)?;
+        fs::write(path, &json)?;
+        if let Some(signer) = signer {
+            let signature = signer.sign(&json);
+            fs::write(signature_path(path), encode_hex(&signature.to_bytes()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Detached signature file of the manifest at `path`
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// How a manifest's signature checked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// No `.sig` next to the manifest
+    Unsigned,
+    /// Valid for the public key the caller expected
+    Trusted,
+    /// Valid for the key named in the manifest itself, which proves the files
+    /// match what was signed but not who signed them
+    SelfSigned,
+}
+
+/// Outcome of [`verify_manifest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub manifest: Manifest,
+    pub signature: SignatureCheck,
+    /// Files whose checksum or size differs
+    pub mismatched: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_intact(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Check the signature of the manifest at `path` and the checksum of every
+/// file it lists
+///
+/// A signature that doesn't verify, or is missing while `public_key` is
+/// given, is an error; changed or missing files are reported in the result.
+pub fn verify_manifest(path: &Path, public_key: Option<&str>) -> Result<Verification> {
+    let json =
+        fs::read(path).with_context(|| format!("Failed to read manifest {}", path.display()))?;
+    let manifest: Manifest = serde_json::from_slice(&json)
+        .with_context(|| format!("{} is not an export manifest", path.display()))?;
+
+    let signature = match fs::read_to_string(signature_path(path)) {
+        Ok(hex) => {
+            let signature = decode_hex(hex.trim())
+                .ok()
+                .and_then(|bytes| Signature::from_slice(&bytes).ok())
+                .ok_or_else(|| anyhow!("Malformed signature for {}", path.display()))?;
+            let key_hex = public_key
+                .or(manifest.public_key.as_deref())
+                .ok_or_else(|| anyhow!("{} is signed but names no public key", path.display()))?;
+            let key = decode_hex(key_hex.trim())
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+                .ok_or_else(|| anyhow!("Invalid public key {}", key_hex))?;
+            key.verify(&json, &signature)
+                .map_err(|_| anyhow!("Signature of {} does not match", path.display()))?;
+            if public_key.is_some() {
+                SignatureCheck::Trusted
+            } else {
+                SignatureCheck::SelfSigned
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if public_key.is_some() {
+                bail!("{} is not signed", path.display());
+            }
+            SignatureCheck::Unsigned
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut mismatched = Vec::new();
+    let mut missing = Vec::new();
+    for entry in &manifest.files {
+        // Names are plain file names; anything else could point outside the export
+        if Path::new(&entry.name).file_name() != Some(entry.name.as_ref()) {
+            bail!("Manifest entry '{}' is not a file name", entry.name);
+        }
+        match sha256_file(&dir.join(&entry.name)) {
+            Ok((sha256, bytes)) if sha256 == entry.sha256 && bytes == entry.bytes => {}
+            Ok(_) => mismatched.push(entry.name.clone()),
+            Err(_) => missing.push(entry.name.clone()),
+        }
+    }
+    Ok(Verification {
+        manifest,
+        signature,
+        mismatched,
+        missing,
+    })
+}
+
+fn sha256_file(path: &Path) -> Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok((format!("{:x}", hasher.finalize()), bytes))
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[(byte >> 4) as usize] as char);
+        hex.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    hex
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        bail!("odd length or non-ASCII hex");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let dir = std::env::temp_dir().join(format!("swoop-sealing-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("export.jsonl");
+        // More than two chunks, ending mid-chunk
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        fs::write(&path, &data).unwrap();
+
+        let key = ExportKey::generate();
+        let encrypted = encrypt_file(&key, &path).unwrap();
+        assert_eq!(encrypted, dir.join("export.jsonl.enc"));
+        assert!(!path.exists());
+
+        let restored = dir.join("restored.jsonl");
+        decrypt_file(&key, &encrypted, &restored).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), data);
+
+        let other = ExportKey::from_hex(&"ab".repeat(32)).unwrap();
+        assert!(decrypt_file(&other, &encrypted, &restored).is_err());
+
+        // Dropping the last chunk is caught
+        let sealed = fs::read(&encrypted).unwrap();
+        let truncated = dir.join("truncated.enc");
+        fs::write(&truncated, &sealed[..sealed.len() - 1016]).unwrap();
+        assert!(decrypt_file(&key, &truncated, &restored).is_err());
+
+        // Empty files still round-trip
+        let empty = dir.join("empty.csv");
+        fs::write(&empty, b"").unwrap();
+        let encrypted = encrypt_file(&key, &empty).unwrap();
+        decrypt_file(&key, &encrypted, &restored).unwrap();
+        assert!(fs::read(&restored).unwrap().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_signed_manifest_verification() {
+        let dir = std::env::temp_dir().join(format!("swoop-manifest-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let files: Vec<PathBuf> = ["a.jsonl", "a.1.jsonl"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, name.as_bytes()).unwrap();
+                path
+            })
+            .collect();
+        let signer = generate_signing_key();
+        let manifest_path = dir.join("a.manifest.json");
+        let seeds = vec!["https://a.test/".to_string(), "https://b.test/".to_string()];
+        let mut provenance = Provenance::from_job(
+            &Job::new("nightly".to_string(), seeds.clone(), HashMap::new()),
+            "0.1.0",
+        );
+        provenance.record(Some(200), true, "a.test");
+        provenance.record(None, false, "b.test");
+        provenance.record_input("script", &files[0]).unwrap();
+        Manifest::build(&files, false)
+            .unwrap()
+            .with_provenance(provenance)
+            .write(&manifest_path, Some(&signer))
+            .unwrap();
+
+        let public = public_key_hex(&signer);
+        let verified = verify_manifest(&manifest_path, Some(&public)).unwrap();
+        assert_eq!(verified.signature, SignatureCheck::Trusted);
+        assert!(verified.is_intact());
+        assert_eq!(verified.manifest.files[1].sha256.len(), 64);
+        let provenance = verified.manifest.provenance.unwrap();
+        assert_eq!(provenance.seeds_sha256, seeds_digest(&seeds));
+        assert_eq!(provenance.status_counts["failed"], 1);
+        assert_eq!(provenance.domain_counts["a.test"], 1);
+        assert_eq!(
+            provenance.inputs["script"],
+            verified.manifest.files[0].sha256
+        );
+        let verified = verify_manifest(&manifest_path, None).unwrap();
+        assert_eq!(verified.signature, SignatureCheck::SelfSigned);
+
+        // Another key's signature is rejected
+        let stranger = public_key_hex(&generate_signing_key());
+        assert!(verify_manifest(&manifest_path, Some(&stranger)).is_err());
+
+        fs::write(&files[1], b"tampered").unwrap();
+        fs::remove_file(&files[0]).unwrap();
+        let verified = verify_manifest(&manifest_path, Some(&public)).unwrap();
+        assert_eq!(verified.mismatched, vec!["a.1.jsonl"]);
+        assert_eq!(verified.missing, vec!["a.jsonl"]);
+
+        // Editing the manifest breaks the signature
+        let edited = fs::read_to_string(&manifest_path)
+            .unwrap()
+            .replace("\"encrypted\": false", "\"encrypted\": true");
+        fs::write(&manifest_path, edited).unwrap();
+        assert!(verify_manifest(&manifest_path, None).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,316 @@
+//! API tokens of the daemon
+//!
+//! `swoop tokens issue` creates a token for one tenant and [`Role`] and prints
+//! its secret once; only the secret's SHA-256 digest is kept, in
+//! `<state-dir>/tokens.json`. Rotating a token gives it a new secret and can
+//! keep the old one valid for a grace period, so clients are switched over
+//! without downtime. A daemon checking tokens reads the file again whenever it
+//! changes, so issued, rotated and revoked tokens take effect at once.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::sealing::encode_hex;
+
+/// Token file, relative to the state directory
+pub const TOKENS_FILE: &str = "tokens.json";
+
+/// Start of every issued secret, so leaked ones are easy to search for
+pub const SECRET_PREFIX: &str = "swoop_";
+
+/// What a caller may do on the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// Queue jobs, nothing else
+    Submit,
+    /// Read status, logs, results, the schedule and stored data
+    Read,
+    /// Everything, including pausing and resuming the engine
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Self::Submit, Self::Read, Self::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Read => "read",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|role| role.as_str() == name)
+    }
+
+    /// Whether this role covers what needs `needed`
+    pub fn allows(self, needed: Role) -> bool {
+        self == Self::Admin || self == needed
+    }
+}
+
+/// An issued token, without its secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    /// Who or what the token was issued to, as recorded in the audit log
+    pub name: String,
+    pub tenant: String,
+    pub role: Role,
+    /// SHA-256 of the secret, as hex
+    pub digest: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub rotated_at: Option<DateTime<Utc>>,
+    /// Digest of the secret before the last rotation, valid until `previous_expires_at`
+    #[serde(default)]
+    pub previous_digest: Option<String>,
+    #[serde(default)]
+    pub previous_expires_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether `secret` is this token's, or its previous one still in its grace period
+    pub fn verifies(&self, secret: &str, now: DateTime<Utc>) -> bool {
+        if self.is_expired(now) {
+            return false;
+        }
+        let digest = digest(secret);
+        digest == self.digest
+            || (self.previous_digest.as_deref() == Some(digest.as_str())
+                && self
+                    .previous_expires_at
+                    .is_some_and(|expires_at| expires_at > now))
+    }
+}
+
+/// SHA-256 of a secret, as kept in the token file
+pub fn digest(secret: &str) -> String {
+    encode_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn new_secret() -> String {
+    // Two v4 UUIDs carry 244 random bits
+    format!(
+        "{}{}{}",
+        SECRET_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Issued tokens of a state directory
+#[derive(Debug)]
+pub struct TokenStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl TokenStore {
+    /// The tokens of the state directory `root`
+    pub fn open(root: &Path) -> Self {
+        Self {
+            path: root.join(TOKENS_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<Vec<ApiToken>> {
+        let _lock = self.lock.lock().unwrap();
+        self.read()
+    }
+
+    /// Create a token, returning it with its secret
+    pub fn issue(
+        &self,
+        name: &str,
+        tenant: &str,
+        role: Role,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(ApiToken, String)> {
+        if name.trim().is_empty() {
+            bail!("A token needs a name");
+        }
+        let _lock = self.lock.lock().unwrap();
+        let mut tokens = self.read()?;
+        let id = loop {
+            let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+            if tokens.iter().all(|token| token.id != id) {
+                break id;
+            }
+        };
+        let secret = new_secret();
+        let token = ApiToken {
+            id,
+            name: name.trim().to_string(),
+            tenant: tenant.to_string(),
+            role,
+            digest: digest(&secret),
+            created_at: Utc::now(),
+            expires_at,
+            rotated_at: None,
+            previous_digest: None,
+            previous_expires_at: None,
+        };
+        tokens.push(token.clone());
+        self.write(&tokens)?;
+        Ok((token, secret))
+    }
+
+    /// Give token `id` a new secret, accepting the old one for `grace` longer
+    pub fn rotate(&self, id: &str, grace: chrono::Duration) -> Result<Option<(ApiToken, String)>> {
+        let _lock = self.lock.lock().unwrap();
+        let mut tokens = self.read()?;
+        let Some(token) = tokens.iter_mut().find(|token| token.id == id) else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        let secret = new_secret();
+        let old_digest = std::mem::replace(&mut token.digest, digest(&secret));
+        if grace > chrono::Duration::zero() {
+            token.previous_digest = Some(old_digest);
+            token.previous_expires_at = Some(now + grace);
+        } else {
+            token.previous_digest = None;
+            token.previous_expires_at = None;
+        }
+        token.rotated_at = Some(now);
+        let token = token.clone();
+        self.write(&tokens)?;
+        Ok(Some((token, secret)))
+    }
+
+    /// Drop token `id`, returning it if there was one
+    pub fn revoke(&self, id: &str) -> Result<Option<ApiToken>> {
+        let _lock = self.lock.lock().unwrap();
+        let mut tokens = self.read()?;
+        let Some(index) = tokens.iter().position(|token| token.id == id) else {
+            return Ok(None);
+        };
+        let token = tokens.remove(index);
+        self.write(&tokens)?;
+        Ok(Some(token))
+    }
+
+    fn read(&self) -> Result<Vec<ApiToken>> {
+        match fs::read(&self.path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .with_context(|| format!("Invalid token file {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).context(format!("Failed to read {}", self.path.display())),
+        }
+    }
+
+    fn write(&self, tokens: &[ApiToken]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_vec_pretty(tokens)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        // Left over from a failed write, it would keep its own permissions
+        let _ = fs::remove_file(&tmp_path);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // Digests only, but still nobody else's business, even while written
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&tmp_path)
+            .and_then(|mut file| file.write_all(&json))
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_rotate_revoke() {
+        let root = std::env::temp_dir().join(format!("swoop-tokens-{}", uuid::Uuid::new_v4()));
+        let store = TokenStore::open(&root);
+        assert!(store.load().unwrap().is_empty());
+        assert!(store.issue(" ", "default", Role::Read, None).is_err());
+
+        let (token, secret) = store.issue("ci", "default", Role::Submit, None).unwrap();
+        assert!(secret.starts_with(SECRET_PREFIX));
+        assert_ne!(token.digest, secret);
+        let now = Utc::now();
+        assert!(token.verifies(&secret, now));
+        assert!(!token.verifies("swoop_wrong", now));
+        assert_eq!(store.load().unwrap(), vec![token.clone()]);
+
+        // The old secret survives the grace period only
+        let (rotated, new_secret) = store
+            .rotate(&token.id, chrono::Duration::hours(1))
+            .unwrap()
+            .unwrap();
+        assert!(rotated.verifies(&new_secret, now));
+        assert!(rotated.verifies(&secret, now));
+        assert!(!rotated.verifies(&secret, now + chrono::Duration::hours(2)));
+        let (rotated, _) = store
+            .rotate(&token.id, chrono::Duration::zero())
+            .unwrap()
+            .unwrap();
+        assert!(!rotated.verifies(&new_secret, now));
+        assert!(store
+            .rotate("missing", chrono::Duration::zero())
+            .unwrap()
+            .is_none());
+
+        let (expiring, expiring_secret) = store
+            .issue(
+                "nightly",
+                "search",
+                Role::Read,
+                Some(now + chrono::Duration::days(1)),
+            )
+            .unwrap();
+        assert!(expiring.verifies(&expiring_secret, now));
+        assert!(!expiring.verifies(&expiring_secret, now + chrono::Duration::days(2)));
+
+        assert_eq!(
+            store.revoke(&token.id).unwrap().map(|token| token.name),
+            Some("ci".to_string())
+        );
+        assert!(store.revoke(&token.id).unwrap().is_none());
+        assert_eq!(store.load().unwrap().len(), 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_role_permissions() {
+        assert!(Role::Admin.allows(Role::Submit));
+        assert!(Role::Admin.allows(Role::Read));
+        assert!(Role::Read.allows(Role::Read));
+        assert!(!Role::Read.allows(Role::Submit));
+        assert!(!Role::Submit.allows(Role::Read));
+        assert!(!Role::Submit.allows(Role::Admin));
+        assert_eq!(Role::named("read"), Some(Role::Read));
+        assert_eq!(Role::named("owner"), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! Crawl analysis commands
+//!
+//! `swoop analyze duplicates` groups stored documents into near-duplicate
+//! clusters and can prune everything but the newest copy of each.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use storage::{
+    dedup::{find_duplicate_clusters, DuplicateReport},
+    local_store::LocalStore,
+    StorageBackend,
+};
+
+/// `swoop analyze` command definition
+pub fn command() -> Command {
+    Command::new("analyze")
+        .about("Analyze stored crawl data")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("duplicates")
+                .about("Report clusters of near-duplicate documents")
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .short('n')
+                        .value_name("NUM")
+                        .help("Number of clusters to show")
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::new("distance")
+                        .long("distance")
+                        .value_name("BITS")
+                        .help("Maximum simhash distance between duplicates (0-64)")
+                        .default_value("3"),
+                )
+                .arg(
+                    Arg::new("prune")
+                        .long("prune")
+                        .action(ArgAction::SetTrue)
+                        .help("Delete duplicates, keeping the newest document of each cluster"),
+                ),
+        )
+}
+
+/// Dispatch a `swoop analyze` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+
+    match matches.subcommand() {
+        Some(("duplicates", sub)) => {
+            let top: usize = sub.get_one::<String>("top").unwrap().parse()?;
+            let distance: u32 = sub.get_one::<String>("distance").unwrap().parse()?;
+            if distance > 64 {
+                return Err("--distance must be between 0 and 64".into());
+            }
+
+            let report = find_duplicate_clusters(store.list_content().await?, distance);
+            print_report(&report, top);
+
+            if sub.get_flag("prune") {
+                prune(&store, &report).await?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn prune(
+    store: &LocalStore,
+    report: &DuplicateReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut removed = 0;
+    for cluster in &report.clusters {
+        for doc in &cluster.duplicates {
+            if store.delete_content(&doc.id).await? {
+                removed += 1;
+            }
+        }
+    }
+    println!(
+        "\n🧹 Pruned {} duplicate documents, freed {} KB",
+        removed,
+        report.wasted_bytes() / 1024
+    );
+    Ok(())
+}
+
+fn print_report(report: &DuplicateReport, top: usize) {
+    println!("🔍 Duplicate content report");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("📄 Documents: {}", report.total_documents);
+    println!(
+        "👯 Duplicates: {} in {} clusters",
+        report.duplicate_documents(),
+        report.clusters.len()
+    );
+    println!("🗑️  Wasted: {} KB", report.wasted_bytes() / 1024);
+
+    if report.clusters.is_empty() {
+        return;
+    }
+
+    println!("\n{:>5} {:>10}  KEPT URL", "SIZE", "WASTED");
+    for cluster in report.clusters.iter().take(top) {
+        println!(
+            "{:>5} {:>7} KB  {}",
+            cluster.size(),
+            cluster.wasted_bytes() / 1024,
+            cluster.representative.url
+        );
+        for doc in cluster.duplicates.iter().take(3) {
+            println!("{:>19}{}", "↳ ", doc.url);
+        }
+        if cluster.duplicates.len() > 3 {
+            println!("{:>19}… and {} more", "", cluster.duplicates.len() - 3);
+        }
+    }
+    if report.clusters.len() > top {
+        println!("… and {} more clusters", report.clusters.len() - top);
+    }
+}
@@ -0,0 +1,245 @@
+//! Authentication of daemon control requests
+//!
+//! A daemon started with `--auth`, `--tenants` or `--oidc-issuer` wants
+//! `Authorization: Bearer <credential>` on every control request but the
+//! health and metrics endpoints. The credential is one of:
+//! - a tenant's `api_key` from the tenants file, an admin of that tenant
+//! - a token from `swoop tokens issue`, with the tenant and role it was issued for
+//! - with `--oidc-issuer`, a JWT of that issuer (see [`crate::oidc`])
+//!
+//! Each route needs a [`Role`]: `submit` to queue jobs, `admin` to pause and
+//! resume, and `read` for everything else. Who did what, and every refused
+//! request, goes to the audit log.
+
+use chrono::Utc;
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use storage::{
+    audit::{AuditEvent, AuditLog},
+    tokens::{ApiToken, Role, TokenStore},
+};
+use tracing::warn;
+
+use crate::oidc::OidcVerifier;
+use crate::tenants::{Tenants, DEFAULT_TENANT};
+
+/// Who a control request comes from and what they may do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// As recorded in the audit log, e.g. `token:ci (3f2a9c1e)`
+    pub actor: String,
+    pub tenant: String,
+    pub roles: Vec<Role>,
+}
+
+impl Principal {
+    /// Caller of a daemon that doesn't authenticate
+    pub fn anonymous() -> Self {
+        Self {
+            actor: "anonymous".to_string(),
+            tenant: DEFAULT_TENANT.to_string(),
+            roles: vec![Role::Admin],
+        }
+    }
+
+    pub fn allows(&self, needed: Role) -> bool {
+        self.roles.iter().any(|role| role.allows(needed))
+    }
+}
+
+/// Issued tokens, read again whenever the token file changes
+#[derive(Debug)]
+struct TokenCache {
+    store: TokenStore,
+    loaded: Mutex<(Option<SystemTime>, Vec<ApiToken>)>,
+}
+
+impl TokenCache {
+    fn find(&self, secret: &str) -> Option<ApiToken> {
+        let modified = fs::metadata(self.store.path())
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut loaded = self.loaded.lock().unwrap();
+        if loaded.0 != modified {
+            match self.store.load() {
+                Ok(tokens) => *loaded = (modified, tokens),
+                Err(e) => warn!("⚠️  Keeping the tokens loaded before: {:#}", e),
+            }
+        }
+        let now = Utc::now();
+        loaded
+            .1
+            .iter()
+            .find(|token| token.verifies(secret, now))
+            .cloned()
+    }
+}
+
+/// Checks the credentials of control requests
+#[derive(Debug)]
+pub struct Authenticator {
+    tenants: Option<Arc<Tenants>>,
+    tokens: TokenCache,
+    oidc: Option<OidcVerifier>,
+    audit: AuditLog,
+}
+
+impl Authenticator {
+    /// Accept the keys of `tenants` and the tokens issued in `state_dir`,
+    /// auditing to `state_dir` too
+    pub fn new(tenants: Option<Arc<Tenants>>, state_dir: &Path) -> Self {
+        Self {
+            tenants,
+            tokens: TokenCache {
+                store: TokenStore::open(state_dir),
+                loaded: Mutex::new((None, Vec::new())),
+            },
+            oidc: None,
+            audit: AuditLog::open(state_dir),
+        }
+    }
+
+    /// Accept the JWTs `verifier` vouches for too
+    pub fn with_oidc(mut self, verifier: OidcVerifier) -> Self {
+        self.oidc = Some(verifier);
+        self
+    }
+
+    /// Who `credential` belongs to
+    pub async fn authenticate(&self, credential: &str) -> Result<Principal, String> {
+        if let Some(tenant) = self
+            .tenants
+            .as_ref()
+            .and_then(|tenants| tenants.authenticate(credential))
+        {
+            return Ok(Principal {
+                actor: format!("tenant-key:{}", tenant),
+                tenant: tenant.to_string(),
+                roles: vec![Role::Admin],
+            });
+        }
+
+        let principal = if let Some(token) = self.tokens.find(credential) {
+            Principal {
+                actor: format!("token:{} ({})", token.name, token.id),
+                tenant: token.tenant,
+                roles: vec![token.role],
+            }
+        } else if let Some(oidc) = self.oidc.as_ref().filter(|_| credential.contains('.')) {
+            let identity = oidc.verify(credential).await?;
+            let tenant = match (identity.tenant, &self.tenants) {
+                (Some(tenant), _) => tenant,
+                (None, None) => DEFAULT_TENANT.to_string(),
+                (None, Some(_)) => return Err("OIDC token names no tenant".to_string()),
+            };
+            Principal {
+                actor: format!("oidc:{}", identity.subject),
+                tenant,
+                roles: identity.roles,
+            }
+        } else {
+            return Err("Missing or unknown API key".to_string());
+        };
+
+        let known = match &self.tenants {
+            Some(tenants) => tenants.get(&principal.tenant).is_some(),
+            None => principal.tenant == DEFAULT_TENANT,
+        };
+        if known {
+            Ok(principal)
+        } else {
+            Err(format!("Unknown tenant {}", principal.tenant))
+        }
+    }
+
+    pub fn audit(&self, event: AuditEvent) {
+        if let Err(e) = self.audit.append(&event) {
+            warn!("⚠️  Failed to write the audit log: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oidc::tests::{config, TestIssuer};
+    use serde_json::json;
+
+    fn tenants() -> Arc<Tenants> {
+        Arc::new(
+            Tenants::parse(
+                r#"
+                [tenants.search]
+                api_key = "search-key"
+
+                [tenants.ads]
+                api_key = "ads-key"
+                "#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn tenant_keys_administer_their_own_tenant() {
+        let dir = tempfile::tempdir().unwrap();
+        let auth = Authenticator::new(Some(tenants()), dir.path());
+
+        let search = auth.authenticate("search-key").await.unwrap();
+        assert_eq!(search.tenant, "search");
+        assert_eq!(search.roles, vec![Role::Admin]);
+        assert_eq!(auth.authenticate("ads-key").await.unwrap().tenant, "ads");
+        assert!(auth.authenticate("search-key ").await.is_err());
+        assert!(auth.authenticate("").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_credentials_for_unknown_tenants() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, secret) = TokenStore::open(dir.path())
+            .issue("ci", "ghost", Role::Submit, None)
+            .unwrap();
+        let issuer = TestIssuer::generate();
+        let auth =
+            Authenticator::new(Some(tenants()), dir.path()).with_oidc(issuer.verifier(config()));
+
+        assert_eq!(
+            auth.authenticate(&secret).await.unwrap_err(),
+            "Unknown tenant ghost"
+        );
+
+        let claims = |tenant: Option<&str>| {
+            let mut claims = json!({
+                "iss": "https://id.example.com",
+                "aud": "swoop",
+                "sub": "ci",
+                "exp": Utc::now().timestamp() + 3600,
+                "swoop_role": "read",
+            });
+            if let Some(tenant) = tenant {
+                claims["swoop_tenant"] = json!(tenant);
+            }
+            claims
+        };
+        let ghost = issuer.sign(&claims(Some("ghost")));
+        assert_eq!(
+            auth.authenticate(&ghost).await.unwrap_err(),
+            "Unknown tenant ghost"
+        );
+        let none = issuer.sign(&claims(None));
+        assert_eq!(
+            auth.authenticate(&none).await.unwrap_err(),
+            "OIDC token names no tenant"
+        );
+        let ads = auth
+            .authenticate(&issuer.sign(&claims(Some("ads"))))
+            .await
+            .unwrap();
+        assert_eq!(ads.tenant, "ads");
+        assert_eq!(ads.roles, vec![Role::Read]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Backfill history from the Wayback Machine
+//!
+//! `swoop backfill` looks up the Internet Archive's captures of each URL and
+//! saves every distinct one as a revision in the local store, dated when it
+//! was captured, so revision history and change analysis reach back before
+//! swoop first crawled the page. Captures already backfilled are skipped.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{collections::HashSet, fs, time::Duration};
+use tracing::warn;
+
+use scrapers::{
+    extractors::{extract_images, extract_links},
+    metadata,
+    platforms::ScraperRegistry,
+    utils::extract_domain,
+    wayback::{timestamp_prefix, SnapshotQuery, WaybackClient},
+    ScraperConfig,
+};
+use storage::{local_store::LocalStore, models::StoredContent, StorageBackend};
+use swoop_core::text::SharedStr;
+
+/// Metadata key holding the capture timestamp a revision was backfilled from
+const WAYBACK_TIMESTAMP_KEY: &str = "wayback:timestamp";
+
+/// `swoop backfill` command definition
+pub fn command() -> Command {
+    Command::new("backfill")
+        .about("Import archived snapshots from the Wayback Machine as past revisions")
+        .arg(
+            Arg::new("url")
+                .action(ArgAction::Append)
+                .help("URLs to backfill"),
+        )
+        .arg(
+            Arg::new("file")
+                .long("file")
+                .short('f')
+                .value_name("FILE")
+                .help("File of URLs to backfill, one per line"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .value_name("DATE")
+                .help("Earliest capture to import, e.g. 2019 or 2019-06-01"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .value_name("DATE")
+                .help("Latest capture to import"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_name("NUM")
+                .help("Maximum number of snapshots per URL, oldest first"),
+        )
+        .arg(
+            Arg::new("delay")
+                .long("delay")
+                .value_name("MS")
+                .help("Pause between archive requests in milliseconds")
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::new("archive")
+                .long("archive")
+                .value_name("URL")
+                .help("Wayback-compatible archive to query")
+                .default_value(scrapers::wayback::WAYBACK_URL),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("List the snapshots that would be imported without fetching them"),
+        )
+}
+
+/// Run `swoop backfill`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut urls: Vec<String> = matches
+        .get_many::<String>("url")
+        .map(|urls| urls.cloned().collect())
+        .unwrap_or_default();
+    if let Some(path) = matches.get_one::<String>("file") {
+        urls.extend(
+            fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+    if urls.is_empty() {
+        return Err("Give URLs to backfill or --file".into());
+    }
+    let query = SnapshotQuery {
+        from: matches
+            .get_one::<String>("from")
+            .map(|date| timestamp_prefix(date))
+            .transpose()?,
+        to: matches
+            .get_one::<String>("to")
+            .map(|date| timestamp_prefix(date))
+            .transpose()?,
+        limit: matches
+            .get_one::<String>("limit")
+            .map(|limit| limit.parse::<usize>())
+            .transpose()?,
+    };
+    let delay = Duration::from_millis(matches.get_one::<String>("delay").unwrap().parse()?);
+    let dry_run = matches.get_flag("dry-run");
+
+    let config = ScraperConfig::default();
+    let client = WaybackClient::new(&config.user_agent, Duration::from_secs(config.timeout_secs))
+        .with_base_url(matches.get_one::<String>("archive").unwrap());
+    let registry = ScraperRegistry::default();
+    let store = LocalStore::new(state_dir).await?;
+
+    println!("🏛️  Backfilling from {}", client.base_url());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    let (mut imported, mut existing, mut failed) = (0, 0, 0);
+    for url in &urls {
+        let domain = extract_domain(url)?;
+        let snapshots = match client.snapshots(url, &query).await {
+            Ok(snapshots) => snapshots,
+            Err(e) => {
+                warn!("⚠️  {}: {}", url, e);
+                failed += 1;
+                continue;
+            }
+        };
+        let known: HashSet<String> = store
+            .get_content_by_url(url)
+            .await?
+            .into_iter()
+            .filter_map(|doc| doc.metadata.get(WAYBACK_TIMESTAMP_KEY).cloned())
+            .collect();
+        println!("\n🔗 {} — {} snapshot(s)", url, snapshots.len());
+
+        for snapshot in snapshots {
+            let timestamp = snapshot.timestamp();
+            if known.contains(&timestamp) {
+                existing += 1;
+                continue;
+            }
+            let captured = snapshot.captured_at.format("%Y-%m-%d %H:%M");
+            if dry_run {
+                println!(
+                    "  📅 {} {}",
+                    captured,
+                    snapshot.snapshot_url(client.base_url())
+                );
+                imported += 1;
+                continue;
+            }
+
+            tokio::time::sleep(delay).await;
+            let html = match client.fetch(&snapshot).await {
+                Ok(html) => SharedStr::from(html),
+                Err(e) => {
+                    warn!(
+                        "⚠️  Failed to fetch snapshot {} of {}: {}",
+                        timestamp, url, e
+                    );
+                    failed += 1;
+                    continue;
+                }
+            };
+            let extracted = match registry.reprocess(url, &html).await {
+                Ok(extracted) => extracted,
+                Err(e) => {
+                    warn!(
+                        "⚠️  Failed to extract snapshot {} of {}: {}",
+                        timestamp, url, e
+                    );
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            let mut metadata = extracted.metadata;
+            metadata.insert(WAYBACK_TIMESTAMP_KEY.into(), timestamp);
+            metadata.insert("wayback:digest".into(), snapshot.digest.clone());
+            metadata.insert(
+                "wayback:snapshot_url".into(),
+                snapshot.snapshot_url(client.base_url()),
+            );
+            let mut revision = StoredContent::new(
+                url.clone(),
+                domain.clone(),
+                "wayback".to_string(),
+                extracted.title.map(String::from),
+                extracted.text.map(String::from),
+                Some(html.to_string()),
+                metadata::into_owned(metadata),
+            );
+            // Dated at capture, so it sorts before the revisions swoop crawled
+            revision.scraped_at = snapshot.captured_at;
+            revision.stored_at = snapshot.captured_at;
+            revision.links = extract_links(&html).unwrap_or_default();
+            revision.images = extract_images(&html).unwrap_or_default();
+            revision.tags = extracted.tags;
+            store.store_content(&revision).await?;
+
+            println!(
+                "  ✅ {}{}",
+                captured,
+                revision
+                    .title
+                    .as_deref()
+                    .map(|title| format!(" — {}", title))
+                    .unwrap_or_default()
+            );
+            imported += 1;
+        }
+    }
+
+    println!(
+        "\n📄 {} {}, ⏭️  {} already imported, ❌ {} failed",
+        imported,
+        if dry_run {
+            "would be imported"
+        } else {
+            "revisions imported"
+        },
+        existing,
+        failed
+    );
+    Ok(())
+}
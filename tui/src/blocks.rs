@@ -0,0 +1,295 @@
+//! Block page corpus commands
+//!
+//! `swoop blocks` manages the [`BlockCorpus`] kept in
+//! `<state-dir>/block_corpus`: pages added by hand or sampled by scrapes run
+//! with `--sample-blocks`, each labeled block, captcha or clean. `train`
+//! rebuilds the signature set in `<state-dir>/block_signatures.json` from it,
+//! and every command loads that set on start so block detection picks up
+//! site-specific block pages without code changes.
+
+use chrono::Utc;
+use clap::{builder::PossibleValuesParser, Arg, ArgAction, ArgMatches, Command};
+use scrapers::anti_bot::{
+    block_pages::{self, BlockCorpus, BlockLabel, BlockSample, BlockSignatures},
+    vendors::BotVendor,
+};
+use scrapers::utils::is_bot_protected;
+use std::{fs, path::Path};
+use tracing::warn;
+
+/// Corpus directory, relative to the state directory
+pub(crate) const CORPUS_DIR: &str = "block_corpus";
+
+const SIGNATURES_FILE: &str = "block_signatures.json";
+
+/// `swoop blocks` command definition
+pub fn command() -> Command {
+    let label = || {
+        Arg::new("label")
+            .value_parser(PossibleValuesParser::new(BlockLabel::NAMES.iter().copied()))
+            .help("What the page is")
+    };
+    Command::new("blocks")
+        .about("Collect block and captcha pages and train block detection on them")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add saved pages to the corpus")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .help("HTML files to add"),
+                )
+                .arg(label().long("label").required(true))
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .value_name("URL")
+                        .help("URL the pages were served at"),
+                )
+                .arg(
+                    Arg::new("vendor")
+                        .long("vendor")
+                        .value_name("VENDOR")
+                        .value_parser(PossibleValuesParser::new(
+                            BotVendor::ALL.iter().map(BotVendor::as_str),
+                        ))
+                        .help("Bot-protection vendor that served the pages"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the corpus")
+                .arg(label().long("label"))
+                .arg(
+                    Arg::new("unreviewed")
+                        .long("unreviewed")
+                        .help("Only list sampled pages nobody has labeled yet")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("label")
+                .about("Label a page of the corpus")
+                .arg(Arg::new("id").required(true).help("Sample id or its start"))
+                .arg(label().required(true)),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a page from the corpus")
+                .arg(Arg::new("id").required(true).help("Sample id or its start")),
+        )
+        .subcommand(Command::new("train").about("Rebuild the block signatures from the corpus"))
+        .subcommand(
+            Command::new("check")
+                .about("Check whether saved pages would be detected as blocked")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .help("HTML files to check"),
+                ),
+        )
+}
+
+/// Load the trained signature set of `state_dir`, if there is one, into the
+/// built-in block checks
+pub(crate) fn install_signatures(state_dir: &str) {
+    let path = Path::new(state_dir).join(SIGNATURES_FILE);
+    if !path.exists() {
+        return;
+    }
+    match BlockSignatures::load(&path) {
+        Ok(signatures) => block_pages::install(signatures),
+        Err(e) => warn!("⚠️  Ignoring block signatures: {:#}", e),
+    }
+}
+
+/// Dispatch a `swoop blocks` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let corpus = BlockCorpus::open(Path::new(state_dir).join(CORPUS_DIR))?;
+
+    match matches.subcommand() {
+        Some(("add", sub)) => {
+            let label = parse_label(sub.get_one::<String>("label").unwrap());
+            let vendor = sub.get_one::<String>("vendor").and_then(|name| {
+                BotVendor::ALL
+                    .into_iter()
+                    .find(|vendor| vendor.as_str() == name)
+            });
+            for file in sub.get_many::<String>("file").unwrap() {
+                let html = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+                let sample = BlockSample {
+                    id: String::new(),
+                    label,
+                    url: sub.get_one::<String>("url").cloned(),
+                    status: None,
+                    vendor,
+                    reviewed: true,
+                    captured_at: Utc::now(),
+                };
+                let (sample, new) = corpus.add(&html, sample)?;
+                if new {
+                    println!("➕ {} {} ({})", sample.id, sample.label.as_str(), file);
+                } else {
+                    println!(
+                        "⏭️  {} already in the corpus as {}",
+                        file,
+                        sample.label.as_str()
+                    );
+                }
+            }
+            Ok(())
+        }
+        Some(("list", sub)) => {
+            let label = sub.get_one::<String>("label").map(|name| parse_label(name));
+            let samples: Vec<BlockSample> = corpus
+                .samples()?
+                .into_iter()
+                .filter(|sample| label.is_none_or(|label| sample.label == label))
+                .filter(|sample| !sub.get_flag("unreviewed") || !sample.reviewed)
+                .collect();
+            print_samples(&samples);
+            Ok(())
+        }
+        Some(("label", sub)) => {
+            let label = parse_label(sub.get_one::<String>("label").unwrap());
+            let sample = corpus.relabel(sub.get_one::<String>("id").unwrap(), label)?;
+            println!("🏷️  {} is now {}", sample.id, sample.label.as_str());
+            Ok(())
+        }
+        Some(("remove", sub)) => {
+            let sample = corpus.remove(sub.get_one::<String>("id").unwrap())?;
+            println!("🗑️  Removed {}", sample.id);
+            Ok(())
+        }
+        Some(("train", _)) => train(&corpus, state_dir),
+        Some(("check", sub)) => {
+            let signatures = block_pages::installed();
+            for file in sub.get_many::<String>("file").unwrap() {
+                let html = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+                match signatures.classify(&html) {
+                    Some(found) => println!(
+                        "🧱 {}: {}{} (learned: {})",
+                        file,
+                        found.label.as_str(),
+                        found
+                            .vendor
+                            .map(|vendor| format!(" by {}", vendor.as_str()))
+                            .unwrap_or_default(),
+                        found.features.join(", ")
+                    ),
+                    None if is_bot_protected(&html) => println!("🧱 {}: blocked (built-in)", file),
+                    None => println!("✅ {}: not blocked", file),
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Train a signature set on the corpus, save it and report how it does on
+/// the corpus itself
+fn train(corpus: &BlockCorpus, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let pages = corpus.load()?;
+    if !pages
+        .iter()
+        .any(|(sample, _)| sample.label != BlockLabel::Clean)
+    {
+        return Err("The corpus has no block or captcha pages to train on".into());
+    }
+    if !pages
+        .iter()
+        .any(|(sample, _)| sample.label == BlockLabel::Clean)
+    {
+        warn!("⚠️  No clean pages in the corpus; signatures may match ordinary pages");
+    }
+    let unreviewed = pages.iter().filter(|(sample, _)| !sample.reviewed).count();
+    if unreviewed > 0 {
+        warn!(
+            "⚠️  Training on {} sampled pages nobody has labeled yet",
+            unreviewed
+        );
+    }
+
+    let signatures = BlockSignatures::train(&pages);
+    let path = Path::new(state_dir).join(SIGNATURES_FILE);
+    signatures.save(&path)?;
+
+    println!(
+        "🧠 Trained {} signatures on {} pages",
+        signatures.signatures.len(),
+        pages.len()
+    );
+    for (label, count) in &signatures.samples {
+        let learned = signatures
+            .signatures
+            .iter()
+            .filter(|signature| signature.label == *label)
+            .count();
+        println!(
+            "  {:<8} {:>4} pages {:>4} signatures",
+            label.as_str(),
+            count,
+            learned
+        );
+    }
+    for (sample, html) in &pages {
+        let found = signatures.classify(html);
+        match (sample.label, found) {
+            (BlockLabel::Clean, Some(found)) => {
+                println!(
+                    "  ⚠️  {} is clean but matches {}",
+                    sample.id,
+                    found.label.as_str()
+                )
+            }
+            (BlockLabel::Block | BlockLabel::Captcha, None) => {
+                println!(
+                    "  ⚠️  {} ({}) is not recognized",
+                    sample.id,
+                    sample.label.as_str()
+                )
+            }
+            _ => {}
+        }
+    }
+    println!("💾 Saved to {}", path.display());
+    Ok(())
+}
+
+fn parse_label(name: &str) -> BlockLabel {
+    BlockLabel::named(name).expect("labels are validated by clap")
+}
+
+fn print_samples(samples: &[BlockSample]) {
+    if samples.is_empty() {
+        println!("No pages in the corpus");
+        return;
+    }
+
+    println!(
+        "{:<12} {:<8} {:<10} {:<6} {:<16} URL",
+        "ID", "LABEL", "VENDOR", "STATUS", "CAPTURED"
+    );
+    for sample in samples {
+        let label = if sample.reviewed {
+            sample.label.as_str().to_string()
+        } else {
+            format!("{}?", sample.label.as_str())
+        };
+        println!(
+            "{:<12} {:<8} {:<10} {:<6} {:<16} {}",
+            sample.id,
+            label,
+            sample.vendor.map_or("-", |vendor| vendor.as_str()),
+            sample
+                .status
+                .map_or_else(|| "-".to_string(), |status| status.to_string()),
+            sample.captured_at.format("%Y-%m-%d %H:%M"),
+            sample.url.as_deref().unwrap_or("-")
+        );
+    }
+}
@@ -1,61 +1,179 @@
-use clap::{Arg, Command};
+use clap::{builder::PossibleValuesParser, parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs,
-    path::PathBuf,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tokio::sync::Semaphore;
-use tracing::{error, info, warn};
+use futures::StreamExt;
+use tokio::sync::{mpsc, OwnedSemaphorePermit};
+use tracing::{debug, error, info, warn};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// HTTP fetch function with retry logic and connection pooling
-async fn fetch_url_simple(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-    info!("Fetching URL: {}", url);
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .pool_max_idle_per_host(10)
-        .pool_idle_timeout(Duration::from_secs(30))
-        .tcp_keepalive(Duration::from_secs(60))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .build()?;
-    
-    // Retry logic - 2 attempts with short delay
-    for attempt in 1..=2 {
-        match client.get(url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.bytes().await {
-                        Ok(bytes) => {
-                            info!("Finished fetching URL: {} (attempt {})", url, attempt);
-                            return Ok(bytes.to_vec());
-                        }
-                        Err(e) => {
-                            if attempt == 2 {
-                                return Err(format!("Failed to read response body: {}", e).into());
-                            }
-                            tokio::time::sleep(Duration::from_millis(200)).await;
-                        }
-                    }
-                } else {
-                    if attempt == 2 {
-                        return Err(format!("HTTP {}", response.status()).into());
-                    }
-                    tokio::time::sleep(Duration::from_millis(200)).await;
-                }
-            }
-            Err(e) => {
-                if attempt == 2 {
-                    return Err(e.into());
-                }
-                tokio::time::sleep(Duration::from_millis(200)).await;
-            }
+use scrapers::{
+    anti_bot::{
+        behavior_engine::{DiurnalCurve, PacingConfig, PacingPlanner},
+        block_pages::BlockCorpus,
+        fingerprint_manager::{FingerprintManager, HardwareProfile},
+        referrers::{EntryReferrer, ReferrerChain, ReferrerConfig},
+        user_agents::UserAgentPool,
+        vendors::detect_vendor,
+    },
+    browser::{select_pool, BrowserConfig, BrowserEngine, BrowserPool},
+    classifier::ContentClassifier,
+    extractors::{decode_entities, extract_canonical, extract_images, summarize_page},
+    frontier::{Claim, Frontier, HostBudget},
+    locale::{served_language, LocaleProfile},
+    offline::{PageArchiver, SnapshotFormat},
+    pii::{PiiAuditLog, PiiKind, PiiScrubber, RedactionMode},
+    policy::{PolicyResolver, ResolvedPolicy, POLICY_FILE},
+    quality::{Escalation, QualityGate, QualityScore, QualityScorer},
+    rate_limiter::{ApiRateLimiter, HostThrottle, RateProfile},
+    safety::SafetyFilter,
+    scripting::{HookRequest, ScriptHooks},
+    utils::{extract_domain, is_bot_protected, normalize_url, RobotsCache, RobotsDirectives},
+    walls::WallDetector,
+};
+use storage::{
+    artifacts::{ArtifactRef, ArtifactStore, DEFAULT_MAX_ARTIFACT_BYTES},
+    browser_profiles::{ProfileConfig, ProfileStore, PROFILES_DIR},
+    checkpoint::{Checkpoint, CheckpointStore},
+    costs::{CostRates, CostReport, CostStore, ResourceUsage, COST_RATES_FILE, DIRECT_TIER},
+    dead_letters::{DeadLetter, DeadLetterLog},
+    fingerprints::Sighting,
+    freshness::parse_interval,
+    local_store::{LocalStore, DEFAULT_LOCAL_ROOT},
+    models::{Job, JobCounts, JobStatus},
+    quota::DiskQuota,
+    redis_queue::{Nack, RedisQueue},
+    sealing::{self, ExportKey, Manifest, Provenance, SigningKey},
+    JobStore, S3Config,
+};
+use swoop_core::{
+    metrics::FetchHistograms,
+    throttle::Throttle,
+    trace::FetchTrace,
+};
+use swoop_orchestrator::fetch::{blocked_vendor, fetch_past_bot_protection};
+
+use crate::guardrails::{parse_cpu_percent, ResourceGuard, ResourceLimits};
+use crate::output::{JsonArrayWriter, Rotation, RotatingWriter};
+use crate::progress::{ProgressMode, ProgressReporter};
+use crate::quarantine::{stop_unsafe, UNSAFE_TAG};
+use crate::rate_history::RateLimitHistory;
+use crate::shutdown::{Shutdown, GRACE_PERIOD};
+use crate::{
+    analyze, backfill, blocks, daemon, discover, download, exports, failures, jobs, probe,
+    recipe, reprocess, rerun, schedule, serp, sites, snapshot, tokens, workflow,
+};
+
+/// Tier a fetch is billed under in the cost report; proxies outside the
+/// policy's tiers, such as geo proxies, count as `other`
+pub(crate) fn usage_tier(trace: &FetchTrace) -> &str {
+    match (&trace.proxy_tier, &trace.proxy) {
+        (Some(tier), _) => tier,
+        (None, Some(_)) => "other",
+        (None, None) => DIRECT_TIER,
+    }
+}
+
+/// Response headers of the final attempt, minus cookies, for the export
+fn exported_headers(trace: &FetchTrace) -> HashMap<String, String> {
+    trace
+        .response_headers
+        .iter()
+        .filter(|(name, _)| name.as_str() != "set-cookie")
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Tag of results still scoring below `--min-quality` after their retries
+const LOW_QUALITY_TAG: &str = "low-quality";
+
+/// Tag of pages whose body is the same as at their last fetch, with
+/// `--skip-unchanged`; they are neither extracted nor exported
+const UNCHANGED_TAG: &str = "unchanged";
+
+/// Whether `body` is what `url` served at its last fetch, recording this
+/// fetch in `body_fingerprints` either way
+pub(crate) async fn is_unchanged(
+    body_fingerprints: Option<&LocalStore>,
+    url: &str,
+    body: &[u8],
+) -> bool {
+    let Some(store) = body_fingerprints else {
+        return false;
+    };
+    match storage::fingerprints::record(store, url, body).await {
+        Ok((fingerprint, Sighting::Unchanged)) => {
+            debug!(
+                "⏭️  {} unchanged since {}, skipping extraction",
+                url, fingerprint.last_changed
+            );
+            true
         }
+        Ok(_) => false,
+        Err(e) => {
+            warn!("⚠️  Failed to check the fingerprint of {}: {:#}", url, e);
+            false
+        }
+    }
+}
+
+/// Whether results tagged `tag` aren't worth fetching again to raise their
+/// quality score
+fn is_settled_tag(tag: &str) -> bool {
+    tag == UNCHANGED_TAG || tag.split(':').next() == Some(UNSAFE_TAG)
+}
+
+fn is_junk_tag(tag: &str) -> bool {
+    matches!(tag, "login-wall" | "error-page")
+}
+
+/// Load the policy file at `path`, or `policies.toml` in the working directory if it exists
+pub(crate) fn load_policies(
+    path: Option<&Path>,
+) -> Result<Option<Arc<PolicyResolver>>, Box<dyn std::error::Error + Send + Sync>> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(POLICY_FILE).exists() => Path::new(POLICY_FILE),
+        None => return Ok(None),
+    };
+    let resolver = PolicyResolver::from_file(path).map_err(|e| format!("{:#}", e))?;
+    info!("📜 Using domain policies from {}", path.display());
+    Ok(Some(Arc::new(resolver)))
+}
+
+/// Files a job configuration reads its extraction and filtering rules from
+pub(crate) fn input_files(config: &HashMap<String, String>) -> Vec<(&'static str, PathBuf)> {
+    let mut files: Vec<(&'static str, PathBuf)> = ["script", "policies", "pii_names"]
+        .into_iter()
+        .filter_map(|name| config.get(name).map(|path| (name, PathBuf::from(path))))
+        .collect();
+    // Without --policies the policy file in the working directory applies
+    if !config.contains_key("policies") && Path::new(POLICY_FILE).exists() {
+        files.push(("policies", PathBuf::from(POLICY_FILE)));
+    }
+    files
+}
+
+/// Effective policy for `url`; without a policy file only the job's rate profile applies
+pub(crate) fn resolve_policy(
+    policies: Option<&PolicyResolver>,
+    url: &str,
+    profile: Option<&RateProfile>,
+) -> ResolvedPolicy {
+    match policies {
+        Some(policies) => policies.resolve(url, profile),
+        None => ResolvedPolicy {
+            rate: profile.cloned(),
+            user_agents: UserAgentPool::shared(),
+            ..ResolvedPolicy::default()
+        },
     }
-    
-    Err("All retry attempts failed".into())
 }
 
 /// Scraped data entry
@@ -70,239 +188,3380 @@ struct ScrapedData {
     content_length: usize,
     content_type: Option<String>,
     title: Option<String>,
+    /// Start of the page text
+    #[serde(default)]
+    excerpt: Option<String>,
     success: bool,
     error: Option<String>,
+    /// Page classes such as `article` or `login-wall`
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Redirect target or `<link rel="canonical">`, when it differs from `url`
+    #[serde(default)]
+    canonical_url: Option<String>,
+    /// Stored copies of the page's images, with `--download-images`
+    #[serde(default)]
+    images: Vec<String>,
+    /// Stored offline copy of the page, with `--snapshot`
+    #[serde(default)]
+    snapshot: Option<String>,
+    /// How the page was fetched; exported separately with `--trace`
+    #[serde(skip)]
+    trace: Option<FetchTrace>,
+}
+
+impl ScrapedData {
+    /// Result for a page that is the same as at its last fetch, which isn't
+    /// extracted again
+    fn unchanged(url: &str, status_code: Option<u16>, trace: FetchTrace) -> Self {
+        Self {
+            status_code,
+            success: true,
+            error: None,
+            tags: vec![UNCHANGED_TAG.to_string()],
+            trace: Some(trace),
+            ..Self::failed(url, "")
+        }
+    }
+
+    /// Result for a URL that was never fetched
+    fn failed(url: &str, error: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            timestamp: Utc::now(),
+            content: String::new(),
+            status_code: None,
+            headers: HashMap::new(),
+            response_time: 0,
+            content_length: 0,
+            content_type: None,
+            title: None,
+            excerpt: None,
+            success: false,
+            error: Some(error.to_string()),
+            tags: Vec::new(),
+            canonical_url: None,
+            images: Vec::new(),
+            snapshot: None,
+            trace: None,
+        }
+    }
+}
+
+/// How often an empty shared queue is checked for URLs coming back
+const QUEUE_POLL: Duration = Duration::from_secs(1);
+
+/// URL claimed from a shared queue, holding its concurrency slot until it is
+/// settled
+struct QueueLease {
+    queue: Arc<RedisQueue>,
+    url: String,
+    attempt: u32,
+    /// URLs left in the queue when this one was claimed
+    backlog: u64,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl QueueLease {
+    /// Mark the URL done
+    async fn ack(self) {
+        if let Err(e) = self.queue.ack(&self.url).await {
+            warn!(
+                "⚠️  Failed to ack {} on queue {}: {:#}",
+                self.url,
+                self.queue.name(),
+                e
+            );
+        }
+    }
+
+    /// Hand the URL back for another process without counting the attempt
+    async fn release(self) {
+        if let Err(e) = self.queue.release(&self.url).await {
+            warn!(
+                "⚠️  Failed to release {} on queue {}: {:#}",
+                self.url,
+                self.queue.name(),
+                e
+            );
+        }
+    }
+
+    /// Hand a failed URL back; true if it will be tried again, false once it
+    /// is out of attempts and dead-lettered
+    async fn retry(self, error: &str) -> bool {
+        match self.queue.nack(&self.url, error).await {
+            Ok(Nack::Requeued) => {
+                debug!(
+                    "🔁 {} failed on attempt {}, requeued: {}",
+                    self.url, self.attempt, error
+                );
+                true
+            }
+            Ok(Nack::LeaseLost) => true,
+            Ok(Nack::DeadLettered) => {
+                debug!(
+                    "☠️  {} dead-lettered after {} attempts: {}",
+                    self.url, self.attempt, error
+                );
+                false
+            }
+            Err(e) => {
+                warn!(
+                    "⚠️  Failed to requeue {} on queue {}: {:#}",
+                    self.url,
+                    self.queue.name(),
+                    e
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Results waiting to be exported at most; scraping tasks wait for room
+/// beyond it, so a slow disk holds the scrape back instead of filling memory
+const RESULT_BUFFER: usize = 256;
+
+/// What a run's results add up to, kept instead of the results themselves
+#[derive(Debug)]
+struct ResultTally {
+    total: u64,
+    successful: u64,
+    bytes: u64,
+    /// Results per HTTP status, success and domain, for the provenance
+    outcomes: BTreeMap<(Option<u16>, bool, String), u64>,
+    /// Domains that answered with 429 Too Many Requests
+    rate_limited: BTreeSet<String>,
+    /// Timings of the successful results
+    histograms: FetchHistograms,
+}
+
+impl ResultTally {
+    fn new() -> Self {
+        Self {
+            total: 0,
+            successful: 0,
+            bytes: 0,
+            outcomes: BTreeMap::new(),
+            rate_limited: BTreeSet::new(),
+            histograms: FetchHistograms::new(),
+        }
+    }
+
+    fn record(&mut self, result: &ScrapedData) {
+        self.total += 1;
+        self.bytes += result.content_length as u64;
+        let domain = extract_domain(&result.url).unwrap_or_else(|_| "unknown".to_string());
+        if result.status_code == Some(429) {
+            self.rate_limited.insert(domain.clone());
+        }
+        *self.outcomes.entry((result.status_code, result.success, domain)).or_default() += 1;
+        if !result.success {
+            return;
+        }
+        self.successful += 1;
+        match &result.trace {
+            Some(trace) => self.histograms.record_trace(trace, result.content_length),
+            None => self.histograms.record(
+                Duration::from_millis(result.response_time),
+                None,
+                result.content_length,
+            ),
+        }
+    }
+}
+
+/// Export file results are written to as they arrive
+#[derive(Debug)]
+enum ResultExport {
+    Json(JsonArrayWriter),
+    /// `jsonl` or `csv`, rotated
+    Rotating { format: String, writer: RotatingWriter },
+}
+
+/// Where results end up: the export, the traces file and the tally
+#[derive(Debug)]
+struct ResultSink {
+    export: Option<ResultExport>,
+    /// Fetch traces, with `--trace`
+    traces: Option<JsonArrayWriter>,
+    tally: ResultTally,
+}
+
+impl ResultSink {
+    fn new() -> Self {
+        Self {
+            export: None,
+            traces: None,
+            tally: ResultTally::new(),
+        }
+    }
+
+    fn write(&mut self, result: ScrapedData) {
+        self.tally.record(&result);
+        let exported = match &mut self.export {
+            Some(ResultExport::Json(writer)) => writer.write_element(&result),
+            Some(ResultExport::Rotating { format, writer }) => {
+                encode_record(format, &result).and_then(|record| writer.write_record(&record))
+            }
+            None => Ok(()),
+        };
+        if let Err(e) = exported {
+            warn!("⚠️  Failed to export {}: {}", result.url, e);
+        }
+        if let (Some(traces), Some(trace)) = (&mut self.traces, &result.trace) {
+            if let Err(e) = traces.write_element(trace) {
+                warn!("⚠️  Failed to write the fetch trace of {}: {}", result.url, e);
+            }
+        }
+    }
+}
+
+/// Browser a URL can be rendered in, with the persistent profiles of the
+/// domains that name one
+#[derive(Clone)]
+struct BrowserSessions {
+    pool: Arc<BrowserPool>,
+    profiles: Option<Arc<ProfileStore>>,
 }
 
 /// CLI scraper state
 #[derive(Debug)]
 struct CliScraper {
     concurrency: usize,
+    /// Concurrency slots, which the resource guard can cut down
+    slots: Arc<Throttle>,
     output_dir: PathBuf,
-    scraped_data: Arc<Mutex<Vec<ScrapedData>>>,
+    progress_mode: ProgressMode,
+    hooks: Option<Arc<ScriptHooks>>,
+    skip_junk: bool,
+    /// Body fingerprints of earlier runs, with `--skip-unchanged`
+    body_fingerprints: Option<Arc<LocalStore>>,
+    /// Where bodies the safety filters quarantine are kept
+    quarantine: Option<Arc<LocalStore>>,
+    browser: Option<Arc<BrowserPool>>,
+    /// Pools of further engines, for domains whose `browser_engine` the
+    /// `browser` pool doesn't run
+    engines: Vec<Arc<BrowserPool>>,
+    /// Profiles kept between runs for domains with a `browser_profile`
+    browser_profiles: Option<Arc<ProfileStore>>,
+    trace: bool,
+    profile: Option<RateProfile>,
+    policies: Option<Arc<PolicyResolver>>,
+    throttle: Arc<HostThrottle>,
+    robots: Arc<RobotsCache>,
+    frontier: Arc<Mutex<Frontier>>,
+    /// Per-host limits for hosts their policy sets none for
+    budget: HostBudget,
+    /// Market emulated for URLs whose policy sets no locale
+    locale: Option<LocaleProfile>,
+    /// Warm sessions up for URLs whose policy doesn't say
+    warm_up: bool,
+    /// Referrer chain for jobs whose policy file sets up none
+    referrers: Option<Arc<ReferrerChain>>,
+    pacing: Option<Arc<PacingPlanner>>,
+    /// Corpus a share of the bot-protected pages is archived to
+    block_samples: Option<(Arc<BlockCorpus>, f64)>,
+    /// Shared queue URLs are claimed from instead of the list given
+    queue: Option<Arc<RedisQueue>>,
+    /// Log URLs that failed every attempt go to, with the job they failed in
+    dead_letters: Option<(Arc<DeadLetterLog>, String)>,
+    /// Stops intake and winds the scrape down on SIGINT or SIGTERM
+    shutdown: Shutdown,
+    artifacts: Option<Arc<ArtifactStore>>,
+    /// Where pages are packed into single-file snapshots, and how
+    snapshots: Option<(Arc<ArtifactStore>, SnapshotFormat)>,
+    /// Disk used by this run's exports and artifacts
+    quota: Arc<DiskQuota>,
+    scrubber: Option<Arc<PiiScrubber>>,
+    /// Bandwidth, browser time and solves, for the cost report
+    usage: Arc<Mutex<ResourceUsage>>,
+    /// Results fetched again, escalated, while they score below the gate
+    quality: Option<QualityGate>,
+    /// Export and tally of the results, written by the sink task as they arrive
+    sink: Arc<Mutex<ResultSink>>,
 }
 
 impl CliScraper {
-    fn new(concurrency: usize, output_dir: PathBuf) -> Self {
+    fn new(concurrency: usize, output_dir: PathBuf, progress_mode: ProgressMode) -> Self {
         fs::create_dir_all(&output_dir).expect("Failed to create output directory");
         Self {
             concurrency,
+            slots: Arc::new(Throttle::new(concurrency)),
             output_dir,
-            scraped_data: Arc::new(Mutex::new(Vec::new())),
+            progress_mode,
+            hooks: None,
+            skip_junk: false,
+            body_fingerprints: None,
+            quarantine: None,
+            browser: None,
+            engines: Vec::new(),
+            browser_profiles: None,
+            trace: false,
+            profile: None,
+            policies: None,
+            throttle: Arc::new(
+                HostThrottle::new(RateProfile::aggressive()).expect("preset profile is valid"),
+            ),
+            robots: Arc::new(RobotsCache::new()),
+            frontier: Arc::new(Mutex::new(Frontier::new())),
+            budget: HostBudget::default(),
+            locale: None,
+            warm_up: false,
+            referrers: None,
+            pacing: None,
+            block_samples: None,
+            queue: None,
+            dead_letters: None,
+            shutdown: Shutdown::new(),
+            artifacts: None,
+            snapshots: None,
+            quota: Arc::new(DiskQuota::unlimited()),
+            scrubber: None,
+            usage: Arc::new(Mutex::new(ResourceUsage::default())),
+            quality: None,
+            sink: Arc::new(Mutex::new(ResultSink::new())),
         }
     }
 
+    /// Drop login walls and error pages instead of exporting them
+    fn with_skip_junk(mut self, skip_junk: bool) -> Self {
+        self.skip_junk = skip_junk;
+        self
+    }
 
-    async fn scrape_urls(&self, urls: Vec<String>) {
-        let semaphore = Arc::new(Semaphore::new(self.concurrency));
-        let mut handles = Vec::new();
+    /// Skip pages whose body is the same as at their last fetch in `store`
+    fn with_body_fingerprints(mut self, store: Option<Arc<LocalStore>>) -> Self {
+        self.body_fingerprints = store;
+        self
+    }
 
-        info!("🚀 Starting to scrape {} URLs with concurrency {}", urls.len(), self.concurrency);
+    /// Keep bodies the safety filters quarantine in `store`
+    fn with_quarantine(mut self, store: Option<Arc<LocalStore>>) -> Self {
+        self.quarantine = store;
+        self
+    }
 
-        for url in urls {
-            let semaphore = semaphore.clone();
-            let scraped_data = self.scraped_data.clone();
-            let url_clone = url.clone();
+    /// Render pages behind login, consent or paywalls through a WebDriver browser
+    fn with_browser(mut self, browser: Option<Arc<BrowserPool>>) -> Self {
+        self.browser = browser;
+        self
+    }
 
-            let handle = tokio::spawn(async move {
-                let _permit = semaphore.acquire().await.unwrap();
-                let result = Self::scrape_url_static(&url_clone).await;
-                scraped_data.lock().unwrap().push(result);
-            });
+    /// Run further engines' browsers next to `--webdriver`'s, for the domains
+    /// whose `browser_engine` they run
+    fn with_engines(mut self, engines: Vec<Arc<BrowserPool>>) -> Self {
+        self.engines = engines;
+        self
+    }
 
-            handles.push(handle);
-        }
+    /// Render the pages of domains with a `browser_profile` in their persistent profile
+    fn with_browser_profiles(mut self, profiles: Option<Arc<ProfileStore>>) -> Self {
+        self.browser_profiles = profiles;
+        self
+    }
+
+    /// Write a fetch trace for every URL next to the export
+    fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
 
-        // Wait for all tasks to complete
-        for handle in handles {
-            handle.await.unwrap();
+    /// Apply a rate profile's per-host caps, delays and retries to every URL
+    fn with_profile(
+        mut self,
+        profile: Option<RateProfile>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(profile) = &profile {
+            self.throttle = Arc::new(HostThrottle::new(profile.clone())?);
         }
+        self.profile = profile;
+        Ok(self)
+    }
 
-        info!("✅ Completed scraping all URLs");
+    /// Stop fetching from a host once it has used up `budget`
+    fn with_budget(mut self, budget: HostBudget) -> Self {
+        self.budget = budget;
+        self
     }
 
-    async fn scrape_url_static(url: &str) -> ScrapedData {
-        let start_time = Instant::now();
-        match fetch_url_simple(url).await {
-            Ok(data) => {
-                let duration = start_time.elapsed();
-                let content = String::from_utf8_lossy(&data).to_string();
-                info!("✅ Successfully scraped: {}", url);
-                ScrapedData {
-                    url: url.to_string(),
-                    timestamp: Utc::now(),
-                    content,
-                    status_code: Some(200),
-                    headers: HashMap::new(),
-                    response_time: duration.as_millis() as u64,
-                    content_length: data.len(),
-                    content_type: Some("text/html".to_string()),
-                    title: None,
-                    success: true,
-                    error: None,
-                }
-            }
-            Err(e) => {
-                error!("❌ Failed to scrape {}: {}", url, e);
-                ScrapedData {
-                    url: url.to_string(),
-                    timestamp: Utc::now(),
-                    content: String::new(),
-                    status_code: None,
-                    headers: HashMap::new(),
-                    response_time: start_time.elapsed().as_millis() as u64,
-                    content_length: 0,
-                    content_type: None,
-                    title: None,
-                    success: false,
-                    error: Some(e.to_string()),
-                }
-            }
-        }
+    /// Emulate `locale` for URLs whose policy doesn't pick one
+    fn with_locale(mut self, locale: Option<LocaleProfile>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Warm sessions up for URLs whose policy doesn't set `warm_up`
+    fn with_warm_up(mut self, warm_up: bool) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Send referrers along the crawl's navigation graph, with hosts entered
+    /// from `entry`, unless the policy file configures its own
+    fn with_referrers(mut self, entry: Option<EntryReferrer>) -> Self {
+        self.referrers = entry.map(|entry| {
+            Arc::new(ReferrerChain::new(ReferrerConfig {
+                entry,
+                ..ReferrerConfig::default()
+            }))
+        });
+        self
+    }
+
+    /// Spread each host's requests over the day and break them into sessions
+    fn with_pacing(mut self, pacing: Option<PacingConfig>) -> Result<Self, Box<dyn std::error::Error>> {
+        self.pacing = pacing
+            .map(|config| PacingPlanner::new(config).map_err(|e| e.to_string()))
+            .transpose()?
+            .map(Arc::new);
+        Ok(self)
+    }
+
+    /// Fetch results scoring below `gate` again with an escalated strategy
+    fn with_quality(mut self, gate: Option<QualityGate>) -> Self {
+        self.quality = gate;
+        self
+    }
+
+    /// Archive a fraction `rate` of the bot-protected pages into `corpus`
+    fn with_block_samples(mut self, block_samples: Option<(Arc<BlockCorpus>, f64)>) -> Self {
+        self.block_samples = block_samples;
+        self
+    }
+
+    /// Work through `queue` alongside other processes
+    fn with_queue(mut self, queue: Option<Arc<RedisQueue>>) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Record URLs that failed every attempt of job `job` in `log`
+    fn with_dead_letters(mut self, dead_letters: Option<(Arc<DeadLetterLog>, String)>) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    /// Stop taking URLs once `shutdown` is requested
+    fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Look up per-domain overrides for every URL
+    fn with_policies(mut self, policies: Option<Arc<PolicyResolver>>) -> Self {
+        self.policies = policies;
+        self
     }
 
-    fn export_results(&self, format: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let data = self.scraped_data.lock().unwrap();
+    /// Charge exports and artifacts to `quota`, and stream the export, and
+    /// with `--trace` the fetch traces, to disk as results arrive; `jsonl`
+    /// and `csv` exports are rotated per `rotation`
+    fn with_output(
+        mut self,
+        format: &str,
+        rotation: Rotation,
+        quota: Arc<DiskQuota>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-        
-        match format {
-            "json" => {
-                let file_path = self.output_dir.join(format!("scraped_data_{}.json", timestamp));
-                let json_data = serde_json::to_string_pretty(&*data)?;
-                fs::write(&file_path, json_data)?;
-                info!("📄 Exported {} entries to {}", data.len(), file_path.display());
-            }
-            "csv" => {
-                let file_path = self.output_dir.join(format!("scraped_data_{}.csv", timestamp));
-                let mut csv_content = "URL,Timestamp,Status Code,Success,Response Time,Content Length,Title,Error\n".to_string();
-                for item in data.iter() {
-                    csv_content.push_str(&format!(
-                        "{},{},{},{},{},{},{},{}\n",
-                        item.url,
-                        item.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                        item.status_code.unwrap_or(0),
-                        item.success,
-                        item.response_time,
-                        item.content_length,
-                        item.title.as_deref().unwrap_or(""),
-                        item.error.as_deref().unwrap_or("")
-                    ));
+        let path = self.output_dir.join(format!("scraped_data_{}.{}", timestamp, format));
+        let export = match format {
+            "json" => ResultExport::Json(JsonArrayWriter::create(path.clone(), quota.clone())?),
+            "jsonl" | "csv" => {
+                let header = if format == "csv" { csv_line(CSV_HEADER)? } else { Vec::new() };
+                ResultExport::Rotating {
+                    format: format.to_string(),
+                    writer: RotatingWriter::create(path.clone(), header, rotation, quota.clone())?,
                 }
-                fs::write(&file_path, csv_content)?;
-                info!("📄 Exported {} entries to {}", data.len(), file_path.display());
-            }
-            _ => {
-                return Err("Unsupported format. Use 'json' or 'csv'".into());
             }
+            _ => return Err("Unsupported format. Use 'json', 'jsonl' or 'csv'".into()),
+        };
+        let mut sink = self.sink.lock().unwrap();
+        sink.export = Some(export);
+        if self.trace {
+            // Traces aren't part of the export, and don't count against its quota
+            let traces = path.with_extension("traces.json");
+            sink.traces = Some(JsonArrayWriter::create(traces, Arc::new(DiskQuota::unlimited()))?);
         }
+        drop(sink);
+        self.quota = quota;
+        Ok(self)
+    }
 
-        Ok(())
+    /// Download the images of every page into `artifacts`
+    fn with_artifacts(mut self, artifacts: Option<Arc<ArtifactStore>>) -> Self {
+        self.artifacts = artifacts;
+        self
     }
 
-    fn print_summary(&self) {
-        let data = self.scraped_data.lock().unwrap();
-        let total = data.len();
-        let successful = data.iter().filter(|d| d.success).count();
-        let failed = total - successful;
-        let avg_response_time = if !data.is_empty() {
-            data.iter().map(|d| d.response_time).sum::<u64>() / data.len() as u64
-        } else {
-            0
-        };
+    /// Pack every page with its resources into a snapshot in `store`
+    fn with_snapshots(mut self, snapshots: Option<(Arc<ArtifactStore>, SnapshotFormat)>) -> Self {
+        self.snapshots = snapshots;
+        self
+    }
 
-        println!("\n📊 Scraping Summary:");
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("📈 Total URLs: {}", total);
-        println!("✅ Successful: {}", successful);
-        println!("❌ Failed: {}", failed);
-        println!("⏱️  Average Response Time: {}ms", avg_response_time);
-        println!("🎯 Success Rate: {:.1}%", if total > 0 { (successful as f64 / total as f64) * 100.0 } else { 0.0 });
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    /// Scrub personal data from every result before it is exported
+    fn with_scrubber(mut self, scrubber: Option<Arc<PiiScrubber>>) -> Self {
+        self.scrubber = scrubber;
+        self
     }
-}
 
-pub async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = Command::new("swoop")
-        .version("1.0")
-        .about("High-performance web scraper")
-        .arg(
-            Arg::new("file")
-                .long("file")
-                .short('f')
-                .value_name("FILE")
-                .help("File containing URLs to scrape (one per line)")
-                .conflicts_with("url")
-        )
-        .arg(
-            Arg::new("url")
-                .long("url")
-                .short('u')
-                .value_name("URL")
-                .help("Single URL to scrape")
-                .conflicts_with("file")
-        )
-        .arg(
-            Arg::new("dir")
-                .long("dir")
-                .short('d')
-                .value_name("DIR")
-                .help("Output directory for results")
-                .default_value("./output")
-        )
-        .arg(
-            Arg::new("concurrency")
-                .long("concurrency")
-                .short('c')
-                .value_name("NUM")
-                .help("Number of concurrent requests")
-                .default_value("300")
-        )
-        .arg(
-            Arg::new("format")
-                .long("format")
-                .value_name("FORMAT")
-                .help("Output format (json, csv)")
-                .default_value("json")
-        )
-        .get_matches();
+    fn with_hooks(mut self, hooks: Option<Arc<ScriptHooks>>) -> Self {
+        self.hooks = hooks;
+        self
+    }
 
-    let output_dir = PathBuf::from(matches.get_one::<String>("dir").unwrap());
-    let concurrency: usize = matches.get_one::<String>("concurrency").unwrap().parse()?;
-    let format = matches.get_one::<String>("format").unwrap();
+    /// Run the `pre_request` hook over every URL, dropping the ones the script skips
+    fn prepare_requests(&self, urls: Vec<String>) -> Vec<HookRequest> {
+        let Some(hooks) = &self.hooks else {
+            return urls.into_iter().map(HookRequest::new).collect();
+        };
 
-    let scraper = CliScraper::new(concurrency, output_dir);
+        urls.into_iter()
+            .filter_map(|url| match hooks.pre_request(HookRequest::new(url.clone())) {
+                Ok(Some(request)) => Some(request),
+                Ok(None) => {
+                    debug!("⏭️  Skipped by script: {}", url);
+                    None
+                }
+                Err(e) => {
+                    warn!("⚠️  pre_request hook failed for {}: {}", url, e);
+                    Some(HookRequest::new(url))
+                }
+            })
+            .collect()
+    }
 
-    let urls = if let Some(file_path) = matches.get_one::<String>("file") {
-        info!("📂 Loading URLs from file: {}", file_path);
-        let contents = fs::read_to_string(file_path)?;
-        let urls: Vec<String> = contents
-            .lines()
-            .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
-            .map(|line| line.trim().to_string())
-            .collect();
-        info!("📋 Loaded {} URLs from file", urls.len());
-        urls
-    } else if let Some(url) = matches.get_one::<String>("url") {
-        info!("🎯 Single URL mode: {}", url);
-        vec![url.clone()]
-    } else {
-        warn!("⚠️  No URL or file specified. Use --help for usage information.");
-        return Ok(());
-    };
 
-    if urls.is_empty() {
-        warn!("⚠️  No URLs to scrape");
-        return Ok(());
-    }
+    /// Scrape `urls`, or the shared queue, returning the URLs left unfinished
+    /// if shutdown was requested before the end
+    async fn scrape_urls(&self, urls: Vec<String>) -> Option<Vec<String>> {
+        let mut requests = match &self.queue {
+            // The shared queue hands out the URLs, seeds included
+            Some(_) => Vec::new(),
+            None => {
+                let requested = urls.len();
+                let requests = self.prepare_requests(urls);
+                if requests.len() < requested {
+                    info!(
+                        "⏭️  Script skipped {} of {} URLs",
+                        requested - requests.len(),
+                        requested
+                    );
+                }
+                requests
+            }
+        }
+        .into_iter();
 
-    // Perform scraping
-    scraper.scrape_urls(urls).await;
+        let progress = Arc::new(ProgressReporter::new(self.progress_mode, requests.len() as u64));
+        let mut handles = Vec::new();
 
-    // Print summary
-    scraper.print_summary();
+        match &self.queue {
+            Some(queue) => info!(
+                "🚀 Starting to scrape from queue {} with concurrency {}",
+                queue.name(),
+                self.concurrency
+            ),
+            None => info!(
+                "🚀 Starting to scrape {} URLs with concurrency {}",
+                requests.len(),
+                self.concurrency
+            ),
+        }
 
-    // Export results
-    scraper.export_results(format)?;
+        let junk = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        // Results refetched for their quality, and those still poor after it
+        let escalated = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let low_quality = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let duplicates = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let unchanged = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let over_quota = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let over_budget = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let dead = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (results, mut received) = mpsc::channel::<ScrapedData>(RESULT_BUFFER);
+        let sink = self.sink.clone();
+        let sink_task = tokio::spawn(async move {
+            while let Some(result) = received.recv().await {
+                sink.lock().unwrap().write(result);
+            }
+        });
+        let mut claimed = 0;
+        loop {
+            if self.shutdown.is_requested() {
+                break;
+            }
+            let (request, lease) = match &self.queue {
+                Some(queue) => match self.claim_request(queue).await {
+                    Some((request, lease)) => {
+                        claimed += 1;
+                        progress.set_total(claimed + lease.backlog);
+                        (request, Some(lease))
+                    }
+                    None => break,
+                },
+                None => match requests.next() {
+                    Some(request) => (request, None),
+                    None => break,
+                },
+            };
+            let slots = self.slots.clone();
+            let results = results.clone();
+            let progress = progress.clone();
+            let hooks = self.hooks.clone();
+            let skip_junk = self.skip_junk;
+            let junk = junk.clone();
+            let duplicates = duplicates.clone();
+            let unchanged = unchanged.clone();
+            let body_fingerprints = self.body_fingerprints.clone();
+            let quarantine = self.quarantine.clone();
+            let frontier = self.frontier.clone();
+            let throttle = self.throttle.clone();
+            let pacing = self.pacing.clone();
+            let block_samples = self.block_samples.clone();
+            let robots = self.robots.clone();
+            let artifacts = self.artifacts.clone();
+            let snapshots = self.snapshots.clone();
+            let quota = self.quota.clone();
+            let over_quota = over_quota.clone();
+            let over_budget = over_budget.clone();
+            let dead_letters = self.dead_letters.clone();
+            let dead = dead.clone();
+            let shutdown = self.shutdown.clone();
+            let scrubber = self.scrubber.clone();
+            let usage = self.usage.clone();
+            let quality = self.quality;
+            let escalated = escalated.clone();
+            let low_quality = low_quality.clone();
+            let mut policy = resolve_policy(
+                self.policies.as_deref(),
+                &request.url,
+                self.profile.as_ref(),
+            );
+            if let (None, Some(locale)) = (&policy.locale, &self.locale) {
+                policy.localize(locale.clone());
+            }
+            policy.warm_up.get_or_insert(self.warm_up);
+            if policy.referrers.is_none() {
+                policy.referrers = self.referrers.clone();
+            }
+            let budget = policy.budget.or(&self.budget);
+            let pools = self.browser.iter().chain(&self.engines);
+            let browser = select_pool(pools, policy.browser_engine).map(|pool| BrowserSessions {
+                pool: pool.clone(),
+                profiles: self.browser_profiles.clone(),
+            });
+            // Counted in the browser pool's demand until the URL gets its slot
+            let queued_page = match &browser {
+                Some(browser) if policy.browser_required => Some(browser.pool.queue_page()),
+                _ => None,
+            };
+            // Claims from a shared queue go back to it if the process stops
+            let checkpointed = lease.is_none().then(|| request.url.clone());
+
+            // Resolves to false for a URL left unfetched because of a shutdown
+            let handle = tokio::spawn(async move {
+                // Wait for the host's slot before taking a concurrency slot,
+                // so a host on a break doesn't hold up the others
+                let leased = lease.is_some();
+                let started = async {
+                    if let Some(pacing) = &pacing {
+                        pacing
+                            .pace(&extract_domain(&request.url).unwrap_or_default())
+                            .await;
+                    }
+                    // Claims from a shared queue already hold their slot
+                    if leased {
+                        None
+                    } else {
+                        Some(slots.acquire().await.unwrap())
+                    }
+                };
+                let _permit = tokio::select! {
+                    permit = started => permit,
+                    _ = shutdown.requested() => {
+                        if let Some(lease) = lease {
+                            lease.release().await;
+                        }
+                        return false;
+                    }
+                };
+                drop(queued_page);
+                let claim = frontier.lock().unwrap().claim(&request.url, &budget);
+                match claim {
+                    Claim::New => {}
+                    // Another attempt at a URL that failed here before
+                    Claim::Duplicate if lease.as_ref().is_some_and(|lease| lease.attempt > 1) => {}
+                    Claim::Duplicate => {
+                        debug!("⏭️  Already crawled: {}", request.url);
+                        duplicates.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        progress.record(true, None);
+                        if let Some(lease) = lease {
+                            lease.ack().await;
+                        }
+                        return true;
+                    }
+                    Claim::OverBudget(reason) => {
+                        // Exported as failed so the run shows what the budget left out
+                        let reason = reason.to_string();
+                        debug!("🪣 {}: {}", reason, request.url);
+                        over_budget.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        progress.record(false, Some(&reason));
+                        let result = ScrapedData::failed(&request.url, &reason);
+                        Self::keep_result(&results, result).await;
+                        if let Some(lease) = lease {
+                            lease.ack().await;
+                        }
+                        return true;
+                    }
+                }
+                // Once the quota is used up, nothing more could be saved
+                if quota.is_exceeded() {
+                    over_quota.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    progress.record(false, Some("Disk quota exceeded"));
+                    if let Some(lease) = lease {
+                        lease.release().await;
+                    }
+                    return true;
+                }
+                let _host_permit = match &policy.rate {
+                    Some(rate) => {
+                        let host = extract_domain(&request.url).unwrap_or_default();
+                        Some(throttle.acquire_with(&host, rate).await)
+                    }
+                    None => None,
+                };
+                // Headers set by the pre_request hook win over the policy's
+                let mut headers = policy.headers.clone();
+                headers.extend(request.headers.clone());
+                let disallowed = policy.respect_robots && !robots.is_allowed(&request.url).await;
+                let result = if disallowed {
+                    debug!("🤖 Disallowed by robots.txt: {}", request.url);
+                    ScrapedData::failed(&request.url, "Disallowed by robots.txt")
+                } else {
+                    // Hosts that publish rate limits get their budget spread over the window
+                    ApiRateLimiter::shared().acquire(&request.url).await;
+                    let result = Self::scrape_url_static(
+                        &request.url,
+                        &headers,
+                        browser.as_ref(),
+                        &policy,
+                        body_fingerprints.as_deref(),
+                        quarantine.as_deref(),
+                        &usage,
+                    )
+                    .await;
+                    match quality {
+                        Some(gate) if !result.tags.iter().any(|tag| is_settled_tag(tag)) => {
+                            let (result, retries) = Self::escalate_low_quality(
+                                result,
+                                gate,
+                                &headers,
+                                browser.as_ref(),
+                                &policy,
+                                quarantine.as_deref(),
+                                &usage,
+                            )
+                            .await;
+                            if retries > 0 {
+                                escalated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            if result.tags.iter().any(|tag| tag == LOW_QUALITY_TAG) {
+                                low_quality.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            result
+                        }
+                        _ => result,
+                    }
+                };
+                if let (Some(trace), Some(status)) = (&result.trace, result.status_code) {
+                    ApiRateLimiter::shared().record(&request.url, &trace.response_headers, status);
+                }
+                if result.tags.iter().any(|tag| tag == UNCHANGED_TAG) {
+                    unchanged.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    progress.record(true, None);
+                    if let Some(lease) = lease {
+                        lease.ack().await;
+                    }
+                    return true;
+                }
+                if let Some((corpus, rate)) = &block_samples {
+                    Self::sample_block_page(corpus, *rate, &result);
+                }
+                // Failed fetches go back to the shared queue until they run out
+                // of attempts; the queue keeps their dead letters, local runs log them
+                let lease = match lease {
+                    Some(lease) if !disallowed && !result.success => {
+                        let error = result.error.as_deref().unwrap_or_default();
+                        if lease.retry(error).await {
+                            progress.record(false, Some(error));
+                            return true;
+                        }
+                        dead.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        None
+                    }
+                    None if !disallowed && !result.success => {
+                        dead.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        if let Some((log, job)) = &dead_letters {
+                            Self::dead_letter(log, job, &result);
+                        }
+                        None
+                    }
+                    lease => lease,
+                };
+                let mut result = Self::record_canonical(&frontier, result);
+                frontier.lock().unwrap().charge(&request.url, result.content_length as u64);
+                if let Some(locale) = &policy.locale {
+                    Self::check_locale(&mut result, locale);
+                }
+                Self::apply_robots_directives(&mut result, policy.respect_robots_meta);
+                if let (Some(artifacts), true) = (&artifacts, result.success) {
+                    let base = result.trace.as_ref().and_then(|trace| trace.final_url.clone());
+                    let base = base.as_deref().unwrap_or(&result.url);
+                    result.images =
+                        download_images(artifacts, &policy.safety, base, &result.content).await;
+                }
+                if let (Some((store, format)), true) = (&snapshots, result.success) {
+                    let base = result.trace.as_ref().and_then(|trace| trace.final_url.clone());
+                    let base = base.as_deref().unwrap_or(&result.url);
+                    result.snapshot =
+                        save_snapshot(store, *format, &policy.safety, base, &result.content).await;
+                }
+                progress.record(result.success, result.error.as_deref());
+
+                let result = match &hooks {
+                    Some(hooks) => {
+                        // The trace isn't visible to scripts, keep it across the hook
+                        let mut result = result;
+                        let trace = result.trace.take();
+                        let keep = hooks.post_extract_in_place(&mut result).unwrap_or_else(|e| {
+                            warn!("⚠️  post_extract hook failed for {}: {}", request.url, e);
+                            true
+                        });
+                        result.trace = trace;
+                        keep.then_some(result)
+                    }
+                    None => Some(result),
+                };
+                if let Some(mut result) = result {
+                    if let Some(scrubber) = &scrubber {
+                        scrub_pii(scrubber, &mut result);
+                    }
+                    if skip_junk && result.tags.iter().any(|tag| is_junk_tag(tag)) {
+                        debug!("🗑️  Dropped junk page {} ({})", result.url, result.tags.join(", "));
+                        junk.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    } else {
+                        Self::keep_result(&results, result).await;
+                    }
+                }
+                if let Some(lease) = lease {
+                    lease.ack().await;
+                }
+                true
+            });
+
+            handles.push((checkpointed, handle));
+        }
+
+        // Wait for all tasks to complete, or after a shutdown request for at
+        // most the grace period
+        let mut unfinished: Vec<String> = requests.map(|request| request.url).collect();
+        let mut deadline = None;
+        for (url, mut handle) in handles {
+            let fetched = loop {
+                tokio::select! {
+                    fetched = &mut handle => break fetched.unwrap(),
+                    _ = self.shutdown.requested(), if deadline.is_none() => {
+                        deadline = Some(tokio::time::Instant::now() + GRACE_PERIOD);
+                    }
+                    _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                        handle.abort();
+                        break false;
+                    }
+                }
+            };
+            if !fetched {
+                unfinished.extend(url);
+            }
+        }
+        // Every task is done or aborted, so the sink has all the results once
+        // the channel drains
+        drop(results);
+        if let Err(e) = sink_task.await {
+            warn!("⚠️  Results stopped being exported: {}", e);
+        }
+        progress.finish();
+
+        let junk = junk.load(std::sync::atomic::Ordering::Relaxed);
+        if junk > 0 {
+            info!("🗑️  Dropped {} login-wall/error pages", junk);
+        }
+        let escalated = escalated.load(std::sync::atomic::Ordering::Relaxed);
+        if escalated > 0 {
+            info!(
+                "🔁 Refetched {} low-quality results with an escalated strategy, {} still low",
+                escalated,
+                low_quality.load(std::sync::atomic::Ordering::Relaxed)
+            );
+        }
+        let duplicates = duplicates.load(std::sync::atomic::Ordering::Relaxed);
+        if duplicates > 0 {
+            info!("⏭️  Skipped {} already crawled URLs", duplicates);
+        }
+        let unchanged = unchanged.load(std::sync::atomic::Ordering::Relaxed);
+        if unchanged > 0 {
+            info!("⏭️  Skipped {} unchanged pages", unchanged);
+        }
+        if over_budget.load(std::sync::atomic::Ordering::Relaxed) > 0 {
+            for host in self.frontier.lock().unwrap().exhausted_hosts() {
+                warn!(
+                    "🪣 {}: budget exhausted ({}) after {} pages and {} KB; skipped {} URLs",
+                    host.host,
+                    host.reason.as_str(),
+                    host.pages,
+                    host.bytes / 1024,
+                    host.refused
+                );
+            }
+        }
+        let dead = dead.load(std::sync::atomic::Ordering::Relaxed);
+        if dead > 0 {
+            warn!("☠️  {} URLs failed every attempt; see `swoop failures report`", dead);
+        }
+        if self.quota.is_exceeded() {
+            warn!(
+                "💾 Disk quota of {} MB reached; skipped {} URLs",
+                self.quota.limit().unwrap_or_default() / (1024 * 1024),
+                over_quota.load(std::sync::atomic::Ordering::Relaxed)
+            );
+        }
+
+        if let Some(scrubber) = self.scrubber.as_ref().filter(|scrubber| scrubber.redacted() > 0) {
+            info!("🕵️  Redacted {} pieces of personal data", scrubber.redacted());
+        }
+
+        if self.shutdown.is_requested() {
+            warn!("🛑 Stopped early, {} URLs left", unfinished.len());
+            return Some(unfinished);
+        }
+        info!("✅ Completed scraping all URLs");
+        None
+    }
+
+    /// Claim the next URL of `queue` once a concurrency slot is free, running
+    /// it through the `pre_request` hook
+    ///
+    /// While other claims are in flight, here or in other processes, an empty
+    /// queue is polled: failed URLs come back and expired leases are handed
+    /// out again. Returns `None` once the queue is drained, or the disk quota
+    /// is used up.
+    async fn claim_request(&self, queue: &Arc<RedisQueue>) -> Option<(HookRequest, QueueLease)> {
+        loop {
+            let permit = tokio::select! {
+                permit = self.slots.acquire() => permit.unwrap(),
+                _ = self.shutdown.requested() => return None,
+            };
+            if self.quota.is_exceeded() || self.shutdown.is_requested() {
+                return None;
+            }
+            let claimed = match queue.claim().await {
+                Ok(Some(claimed)) => claimed,
+                Ok(None) => {
+                    drop(permit);
+                    match queue.stats().await {
+                        Ok(stats) if !stats.is_drained() => tokio::select! {
+                            _ = tokio::time::sleep(QUEUE_POLL) => {}
+                            _ = self.shutdown.requested() => return None,
+                        },
+                        Ok(_) => return None,
+                        Err(e) => {
+                            warn!("⚠️  Failed to read queue {}: {:#}", queue.name(), e);
+                            return None;
+                        }
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    warn!("⚠️  Failed to claim from queue {}: {:#}", queue.name(), e);
+                    return None;
+                }
+            };
+            let lease = QueueLease {
+                queue: queue.clone(),
+                url: claimed.url.clone(),
+                attempt: claimed.attempt,
+                backlog: claimed.backlog,
+                _permit: permit,
+            };
+            match self.prepare_requests(vec![claimed.url]).pop() {
+                Some(request) => return Some((request, lease)),
+                None => lease.ack().await,
+            }
+        }
+    }
+
+    /// Archive bot-protected pages into the block corpus, `rate` of them
+    fn sample_block_page(corpus: &BlockCorpus, rate: f64, result: &ScrapedData) {
+        let flagged = result
+            .trace
+            .as_ref()
+            .is_some_and(|trace| trace.classification.iter().any(|tag| tag == "bot-protected"));
+        if !result.success || !flagged {
+            return;
+        }
+        let vendor = detect_vendor(&BTreeMap::new(), &result.content);
+        match corpus.sample(rate, &result.content, &result.url, result.status_code, vendor) {
+            Ok(Some(sample)) => debug!("🧱 Sampled {} as {} page {}", result.url, sample.label.as_str(), sample.id),
+            Ok(None) => {}
+            Err(e) => warn!("⚠️  Failed to sample block page {}: {:#}", result.url, e),
+        }
+    }
+
+    /// Append the failed `result` to the dead-letter log
+    fn dead_letter(log: &DeadLetterLog, job: &str, result: &ScrapedData) {
+        let letter = DeadLetter {
+            url: result.url.clone(),
+            attempts: result.trace.as_ref().map_or(1, |trace| trace.attempts.max(1)),
+            // The trace has the error's causes, which tell a refused connection from a DNS failure
+            error: result
+                .trace
+                .as_ref()
+                .and_then(|trace| trace.error.clone())
+                .or_else(|| result.error.clone())
+                .unwrap_or_default(),
+            failed_at: Utc::now(),
+            job: Some(job.to_string()),
+        };
+        if let Err(e) = log.append(&letter) {
+            warn!("⚠️  Failed to record dead letter {}: {:#}", result.url, e);
+        }
+    }
+
+    /// Hand `result` to the sink task, waiting while it is behind
+    async fn keep_result(results: &mpsc::Sender<ScrapedData>, result: ScrapedData) {
+        // The sink only stops once every task is done
+        let _ = results.send(result).await;
+    }
+
+    /// Tag pages served in another language than `locale`'s as `locale-mismatch`,
+    /// going by `<html lang>` or else the `Content-Language` header
+    fn check_locale(result: &mut ScrapedData, locale: &LocaleProfile) {
+        if !result.success {
+            return;
+        }
+        let served = served_language(&result.content).or_else(|| {
+            result
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-language"))
+                .map(|(_, language)| language.clone())
+        });
+        if let Some(served) = served.filter(|served| !locale.matches_language(served)) {
+            debug!("🌐 {} served in {} instead of {}", result.url, served, locale.tag);
+            result.tags.push("locale-mismatch".to_string());
+        }
+    }
+
+    /// Record the redirect target and canonical link of a fetched page
+    ///
+    /// Pages whose canonical URL was already crawled under another URL are
+    /// tagged `duplicate`.
+    fn record_canonical(frontier: &Mutex<Frontier>, mut result: ScrapedData) -> ScrapedData {
+        if !result.success {
+            return result;
+        }
+        let final_url = result.trace.as_ref().and_then(|trace| trace.final_url.clone());
+        let base = final_url.as_deref().unwrap_or(&result.url);
+        let canonical = extract_canonical(&result.content, base);
+
+        let mut frontier = frontier.lock().unwrap();
+        if let Some(first) =
+            frontier.record(&result.url, final_url.as_deref(), canonical.as_deref())
+        {
+            debug!("♻️  {} is a duplicate of {}", result.url, first);
+            result.tags.push("duplicate".to_string());
+        }
+        let canonical = frontier.canonical_of(&result.url);
+        if canonical != normalize_url(&result.url) {
+            result.canonical_url = Some(canonical);
+        }
+        result
+    }
+
+    /// Tag the page with its robots meta/`X-Robots-Tag` directives and, when
+    /// the policy respects them, drop what they forbid keeping: the page's
+    /// content for `noindex`, the raw HTML for `noarchive`
+    fn apply_robots_directives(result: &mut ScrapedData, respect: bool) {
+        if !result.success {
+            return;
+        }
+        let header = result.headers.get("x-robots-tag").map(String::as_str);
+        let directives = RobotsDirectives::from_page(&result.content, header);
+        result.tags.extend(directives.names().into_iter().map(|name| format!("robots:{}", name)));
+        if !respect {
+            return;
+        }
+        if directives.noindex {
+            debug!("🤖 Not keeping noindex page: {}", result.url);
+            result.title = None;
+            result.excerpt = None;
+        }
+        if directives.noindex || directives.noarchive {
+            result.content.clear();
+        }
+    }
+
+    /// Fetch and extract `url`; with `body_fingerprints`, a page whose body
+    /// is the same as at its last fetch comes back tagged `unchanged` instead
+    async fn scrape_url_static(
+        url: &str,
+        headers: &HashMap<String, String>,
+        browser: Option<&BrowserSessions>,
+        policy: &ResolvedPolicy,
+        body_fingerprints: Option<&LocalStore>,
+        quarantine: Option<&LocalStore>,
+        usage: &Mutex<ResourceUsage>,
+    ) -> ScrapedData {
+        let start_time = Instant::now();
+        if policy.browser_required {
+            return Self::scrape_in_browser(
+                url,
+                browser,
+                policy,
+                body_fingerprints,
+                start_time,
+                usage,
+            )
+            .await;
+        }
+        let (result, mut trace, vendor) = fetch_past_bot_protection(url, headers, policy).await;
+        usage.lock().unwrap().record_fetch(usage_tier(&trace), trace.bytes_received);
+        let vendor_tag = vendor.map(|vendor| format!("bot:{}", vendor.as_str()));
+        if let (Some(vendor), Some(_)) = (vendor, browser) {
+            if policy.strategy(vendor).browser && blocked_vendor(&result, &trace).is_some() {
+                debug!("🛡️  Still blocked by {}, rendering in browser: {}", vendor.as_str(), url);
+                let mut data = Self::scrape_in_browser(
+                    url,
+                    browser,
+                    policy,
+                    body_fingerprints,
+                    start_time,
+                    usage,
+                )
+                .await;
+                // The browser got past the vendor's challenge
+                if data.success && !is_bot_protected(&data.content) {
+                    usage.lock().unwrap().record_captcha_solve();
+                }
+                data.tags.extend(vendor_tag);
+                return data;
+            }
+        }
+        let status_code = trace.status;
+        let response_headers = exported_headers(&trace);
+        let mut content_type = trace.response_headers.get("content-type").cloned();
+        match result {
+            Ok(data) => {
+                if let Some(verdict) = policy.safety.check(content_type.as_deref(), &data) {
+                    let error =
+                        stop_unsafe(quarantine, url, content_type.clone(), &verdict, &data).await;
+                    let mut tags: Vec<String> = vendor_tag.into_iter().collect();
+                    tags.push(format!("{}:{}", UNSAFE_TAG, verdict.hazard.as_str()));
+                    trace.classification.extend(tags.iter().cloned());
+                    return ScrapedData {
+                        status_code,
+                        headers: response_headers,
+                        response_time: start_time.elapsed().as_millis() as u64,
+                        content_type,
+                        tags,
+                        trace: Some(trace),
+                        ..ScrapedData::failed(url, &error)
+                    };
+                }
+                let mut content = swoop_core::body_to_string(data);
+                let mut tags: Vec<String> = vendor_tag.into_iter().collect();
+                let mut error = None;
+
+                // Walled pages are useless as-is; render them in a browser or report them
+                if let Some(wall) = WallDetector::new().detect(&content, Some(url)) {
+                    let kind = wall.kind.as_str();
+                    tags.push(wall.kind.as_tag());
+                    match browser {
+                        Some(browser) => match Self::render_in_browser(browser, policy, url, usage)
+                            .await
+                        {
+                            Ok(html) => {
+                                debug!("🌐 Rendered {} wall in browser: {}", kind, url);
+                                tags.push("fetched-via:browser".to_string());
+                                content = html;
+                                content_type = Some("text/html".to_string());
+                            }
+                            Err(e) => {
+                                error = Some(format!("{} wall, browser fetch failed: {}", kind, e))
+                            }
+                        },
+                        None => {
+                            error = Some(format!(
+                                "{} wall detected (use --webdriver to render it)",
+                                kind
+                            ))
+                        }
+                    }
+                }
+
+                let bot_protected = is_bot_protected(&content);
+                if bot_protected {
+                    trace.classification.push("bot-protected".to_string());
+                }
+
+                let duration = start_time.elapsed();
+                if let Some(error) = error {
+                    debug!("🧱 {}: {}", url, error);
+                    trace.classification.extend(tags.iter().cloned());
+                    return ScrapedData {
+                        url: url.to_string(),
+                        timestamp: Utc::now(),
+                        content: String::new(),
+                        status_code,
+                        headers: response_headers,
+                        response_time: duration.as_millis() as u64,
+                        content_length: 0,
+                        content_type,
+                        title: None,
+                        excerpt: None,
+                        success: false,
+                        error: Some(error),
+                        tags,
+                        canonical_url: None,
+                        images: Vec::new(),
+                        snapshot: None,
+                        trace: Some(trace),
+                    };
+                }
+                // A challenge served again says nothing about the page behind it
+                if !bot_protected
+                    && is_unchanged(body_fingerprints, url, content.as_bytes()).await
+                {
+                    return ScrapedData::unchanged(url, status_code, trace);
+                }
+
+                tags.extend(ContentClassifier::new().classify_html(&content, status_code).tags());
+                trace.classification.extend(tags.iter().cloned());
+                let summary = summarize_page(&content, url, content_type.as_deref());
+                info!("✅ Successfully scraped: {}", url);
+                ScrapedData {
+                    url: url.to_string(),
+                    timestamp: Utc::now(),
+                    content_length: content.len(),
+                    content,
+                    status_code,
+                    headers: response_headers,
+                    response_time: duration.as_millis() as u64,
+                    content_type: Some(summary.content_type),
+                    title: summary.title,
+                    excerpt: summary.excerpt,
+                    success: true,
+                    error: None,
+                    tags,
+                    canonical_url: None,
+                    images: Vec::new(),
+                    snapshot: None,
+                    trace: Some(trace),
+                }
+            }
+            Err(e) => {
+                error!("❌ Failed to scrape {}: {}", url, e);
+                let error = match vendor {
+                    Some(vendor) => format!("{} (blocked by {})", e, vendor.as_str()),
+                    None => e.to_string(),
+                };
+                let tags: Vec<String> = vendor_tag.into_iter().collect();
+                trace.classification.extend(tags.iter().cloned());
+                ScrapedData {
+                    url: url.to_string(),
+                    timestamp: Utc::now(),
+                    content: String::new(),
+                    status_code,
+                    headers: response_headers,
+                    response_time: start_time.elapsed().as_millis() as u64,
+                    content_length: 0,
+                    content_type,
+                    title: None,
+                    excerpt: None,
+                    success: false,
+                    error: Some(error),
+                    tags,
+                    canonical_url: None,
+                    images: Vec::new(),
+                    snapshot: None,
+                    trace: Some(trace),
+                }
+            }
+        }
+    }
+
+    /// Fetch `result`'s URL again up the escalation ladder while it scores
+    /// below `gate`, returning the best result and the retries made
+    ///
+    /// Results fetched escalated are tagged with how; one still below the
+    /// gate at the end is tagged `low-quality`.
+    async fn escalate_low_quality(
+        result: ScrapedData,
+        gate: QualityGate,
+        headers: &HashMap<String, String>,
+        browser: Option<&BrowserSessions>,
+        policy: &ResolvedPolicy,
+        quarantine: Option<&LocalStore>,
+        usage: &Mutex<ResourceUsage>,
+    ) -> (ScrapedData, u32) {
+        let scorer = QualityScorer::new();
+        let score = |data: &ScrapedData| match data.success {
+            true => scorer.score(&data.content, &data.url, data.status_code),
+            false => QualityScore::failed(),
+        };
+        let mut best_score = score(&result);
+        let mut best = result;
+        let mut retries = 0;
+        for escalation in Escalation::ladder(policy, browser.is_some())
+            .into_iter()
+            .take(gate.max_retries as usize)
+        {
+            if gate.accepts(&best_score) {
+                break;
+            }
+            debug!(
+                "🔁 {} scored {:.2} ({}), refetching with {}",
+                best.url,
+                best_score.score,
+                best_score.issues.join(", "),
+                escalation.as_tag()
+            );
+            retries += 1;
+            let escalated_policy = escalation.apply(policy);
+            // The first fetch already recorded the body's fingerprint
+            let mut retry = Self::scrape_url_static(
+                &best.url,
+                headers,
+                browser,
+                &escalated_policy,
+                None,
+                quarantine,
+                usage,
+            )
+            .await;
+            retry.tags.push(escalation.as_tag());
+            let retry_score = score(&retry);
+            if retry_score.score > best_score.score {
+                best = retry;
+                best_score = retry_score;
+            }
+        }
+        if !gate.accepts(&best_score) {
+            best.tags.push(LOW_QUALITY_TAG.to_string());
+        }
+        (best, retries)
+    }
+
+    /// Render a page the domain policy marks as browser-only
+    async fn scrape_in_browser(
+        url: &str,
+        browser: Option<&BrowserSessions>,
+        policy: &ResolvedPolicy,
+        body_fingerprints: Option<&LocalStore>,
+        start_time: Instant,
+        usage: &Mutex<ResourceUsage>,
+    ) -> ScrapedData {
+        let Some(browser) = browser else {
+            return ScrapedData::failed(url, "Policy requires a browser (use --webdriver)");
+        };
+        match Self::render_in_browser(browser, policy, url, usage).await {
+            Ok(content) => {
+                if is_unchanged(body_fingerprints, url, content.as_bytes()).await {
+                    return ScrapedData::unchanged(url, None, FetchTrace::new(url));
+                }
+                let mut tags = vec!["fetched-via:browser".to_string()];
+                tags.extend(
+                    ContentClassifier::new()
+                        .classify_html(&content, None)
+                        .tags(),
+                );
+                let summary = summarize_page(&content, url, Some("text/html"));
+                info!("✅ Successfully scraped: {}", url);
+                ScrapedData {
+                    url: url.to_string(),
+                    timestamp: Utc::now(),
+                    content_length: content.len(),
+                    content,
+                    status_code: None,
+                    headers: HashMap::new(),
+                    response_time: start_time.elapsed().as_millis() as u64,
+                    content_type: Some(summary.content_type),
+                    title: summary.title,
+                    excerpt: summary.excerpt,
+                    success: true,
+                    error: None,
+                    tags,
+                    canonical_url: None,
+                    images: Vec::new(),
+                    snapshot: None,
+                    trace: None,
+                }
+            }
+            Err(e) => ScrapedData::failed(url, &format!("Browser fetch failed: {}", e)),
+        }
+    }
+
+    /// Render `url` in a pooled session, or in the persistent profile its
+    /// policy names, which is sealed again once the session is closed
+    async fn render_in_browser(
+        browser: &BrowserSessions,
+        policy: &ResolvedPolicy,
+        url: &str,
+        usage: &Mutex<ResourceUsage>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let started = Instant::now();
+        let profiles = browser.profiles.as_ref();
+        let Some((store, name)) = profiles.zip(policy.browser_profile.as_deref()) else {
+            let page = browser.pool.get_browser().await?.scrape_page(url).await;
+            usage.lock().unwrap().record_browser(started.elapsed());
+            return Ok(page?.html);
+        };
+        let lease = store.checkout(name).await?;
+        let instance = browser.pool.get_browser_in(lease.dir()).await?;
+        let page = instance.scrape_page(url).await;
+        usage.lock().unwrap().record_browser(started.elapsed());
+        // The browser must be gone before its profile is trimmed and sealed
+        if let Err(e) = instance.close().await {
+            warn!("⚠️  Failed to close the session of profile {}: {}", name, e);
+        }
+        match lease.release().await {
+            Ok(bytes) => debug!("🗂️  Browser profile {} kept ({} bytes)", name, bytes),
+            Err(e) => warn!("⚠️  Failed to keep browser profile {}: {}", name, e),
+        }
+        Ok(page?.html)
+    }
+
+    /// Finish the export, returning every file written
+    fn export_results(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut sink = self.sink.lock().unwrap();
+        let total = sink.tally.total;
+        let mut files = match &mut sink.export {
+            Some(ResultExport::Json(writer)) => {
+                let file_path = writer.finish()?;
+                info!("📄 Exported {} entries to {}", total, file_path.display());
+                vec![file_path]
+            }
+            // Only the last part is left to flush
+            Some(ResultExport::Rotating { writer, .. }) => {
+                let files = writer.finish()?;
+                info!(
+                    "📄 Exported {} entries to {} file(s) starting at {}",
+                    total,
+                    files.len(),
+                    files[0].display()
+                );
+                files
+            }
+            None => {
+                return Err("Unsupported format. Use 'json', 'jsonl' or 'csv'".into());
+            }
+        };
+
+        if let Some(traces) = &mut sink.traces {
+            let trace_path = traces.finish()?;
+            info!("🔍 Wrote {} fetch traces to {}", traces.elements(), trace_path.display());
+            files.push(trace_path);
+        }
+
+        Ok(files)
+    }
+
+    /// Provenance of this run's exports, counting its results
+    fn provenance(&self, job: &Job) -> Provenance {
+        let mut provenance = Provenance::from_job(job, env!("CARGO_PKG_VERSION"));
+        for ((status, success, domain), count) in &self.sink.lock().unwrap().tally.outcomes {
+            for _ in 0..*count {
+                provenance.record(*status, *success, domain);
+            }
+        }
+        for (name, path) in input_files(&job.config) {
+            if let Err(e) = provenance.record_input(name, &path) {
+                warn!("⚠️  {:#}", e);
+            }
+        }
+        provenance
+    }
+
+    /// Domains that answered with 429 Too Many Requests during this run
+    fn rate_limited_domains(&self) -> Vec<String> {
+        self.sink.lock().unwrap().tally.rate_limited.iter().cloned().collect()
+    }
+
+    fn job_counts(&self) -> JobCounts {
+        let sink = self.sink.lock().unwrap();
+        let tally = &sink.tally;
+        JobCounts {
+            total: tally.total,
+            successful: tally.successful,
+            failed: tally.total - tally.successful,
+            bytes: tally.bytes,
+        }
+    }
+
+    /// Price what the scrape used, with the disk its exports and artifacts took
+    fn cost_report(&self, job: &Job, rates: &CostRates) -> CostReport {
+        let mut usage = self.usage.lock().unwrap().clone();
+        usage.storage_bytes = self.quota.used();
+        CostReport::new(&job.id, &job.name, usage, rates)
+    }
+
+    fn print_summary(&self) {
+        let sink = self.sink.lock().unwrap();
+        let tally = &sink.tally;
+        let (total, successful) = (tally.total, tally.successful);
+        let failed = total - successful;
+        let (latency, ttfb) = (tally.histograms.latency(), tally.histograms.ttfb());
+
+        println!("\n📊 Scraping Summary:");
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("📈 Total URLs: {}", total);
+        println!("✅ Successful: {}", successful);
+        println!("❌ Failed: {}", failed);
+        println!(
+            "⏱️  Response Time: p50 {}ms, p90 {}ms, p99 {}ms (TTFB p50 {}ms, p99 {}ms)",
+            latency.p50, latency.p90, latency.p99, ttfb.p50, ttfb.p99
+        );
+        println!("🎯 Success Rate: {:.1}%", if total > 0 { (successful as f64 / total as f64) * 100.0 } else { 0.0 });
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
+}
+
+/// Build the command line interface definition
+pub fn command() -> Command {
+    Command::new("swoop")
+        .version("1.0")
+        .about("High-performance web scraper")
+        .args_conflicts_with_subcommands(true)
+        .subcommand(daemon::command())
+        .subcommand(download::command())
+        .subcommand(probe::command())
+        .subcommand(jobs::command())
+        .subcommand(analyze::command())
+        .subcommand(reprocess::command())
+        .subcommand(serp::command())
+        .subcommand(sites::command())
+        .subcommand(blocks::command())
+        .subcommand(failures::command())
+        .subcommand(tokens::command())
+        .subcommand(exports::command())
+        .subcommand(rerun::command())
+        .subcommand(schedule::command())
+        .subcommand(backfill::command())
+        .subcommand(discover::command())
+        .subcommand(snapshot::command())
+        .subcommand(workflow::command())
+        .subcommand(recipe::command())
+        .arg(
+            Arg::new("state-dir")
+                .long("state-dir")
+                .value_name("DIR")
+                .help("Directory for local state such as job history")
+                .default_value(DEFAULT_LOCAL_ROOT)
+                .global(true)
+        )
+        .arg(
+            Arg::new("file")
+                .long("file")
+                .short('f')
+                .value_name("FILE")
+                .help("File containing URLs to scrape (one per line)")
+                .conflicts_with("url")
+        )
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .short('u')
+                .value_name("URL")
+                .help("Single URL to scrape")
+                .conflicts_with("file")
+        )
+        .arg(
+            Arg::new("dir")
+                .long("dir")
+                .short('d')
+                .value_name("DIR")
+                .help("Output directory for results")
+                .default_value("./output")
+        )
+        .arg(
+            Arg::new("concurrency")
+                .long("concurrency")
+                .short('c')
+                .value_name("NUM")
+                .help("Number of concurrent requests (overrides the profile's)")
+                .default_value("300")
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("PROFILE")
+                .help("Rate-limit profile: gentle, normal or aggressive")
+                .value_parser(PossibleValuesParser::new(RateProfile::NAMES.iter().copied()))
+        )
+        .arg(
+            Arg::new("policies")
+                .long("policies")
+                .value_name("FILE")
+                .help("Per-domain policy file (default: ./policies.toml if present)")
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Run the aggressive profile without asking, even against domains that returned 429s")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format (json, jsonl, csv)")
+                .value_parser(PossibleValuesParser::new(["json", "jsonl", "csv"]))
+                .default_value("json")
+        )
+        .arg(
+            Arg::new("rotate-mb")
+                .long("rotate-mb")
+                .value_name("MB")
+                .help("Start a new output file once the current one reaches this size (jsonl, csv)")
+        )
+        .arg(
+            Arg::new("compress-rotated")
+                .long("compress-rotated")
+                .help("Gzip output files as they are rotated out")
+                .action(ArgAction::SetTrue)
+                .requires("rotate-mb")
+        )
+        .arg(
+            Arg::new("quota-mb")
+                .long("quota-mb")
+                .value_name("MB")
+                .help("Stop the job once its exports and downloaded images take this much disk")
+        )
+        .arg(
+            Arg::new("max-rss-mb")
+                .long("max-rss-mb")
+                .value_name("MB")
+                .help("Cut concurrency and browser instances while the process uses more memory than this")
+        )
+        .arg(
+            Arg::new("max-cpu")
+                .long("max-cpu")
+                .value_name("PERCENT")
+                .help("Cut concurrency and browser instances while the process uses more of the host's CPU than this")
+                .value_parser(parse_cpu_percent)
+        )
+        .arg(
+            Arg::new("host-max-pages")
+                .long("host-max-pages")
+                .value_name("NUM")
+                .help("Pages fetched from one host before its remaining URLs are skipped")
+        )
+        .arg(
+            Arg::new("host-max-mb")
+                .long("host-max-mb")
+                .value_name("MB")
+                .help("Response data fetched from one host before its remaining URLs are skipped")
+        )
+        .arg(
+            Arg::new("host-max-duration")
+                .long("host-max-duration")
+                .value_name("INTERVAL")
+                .help("Time spent on one host, e.g. 30m, before its remaining URLs are skipped")
+        )
+        .arg(
+            Arg::new("locale")
+                .long("locale")
+                .value_name("TAG")
+                .help("Market to emulate for domains without a policy locale, e.g. de-DE: Accept-Language and geo proxy")
+        )
+        .arg(
+            Arg::new("warm-up")
+                .long("warm-up")
+                .action(ArgAction::SetTrue)
+                .help("Browse each site's home page and a couple of category pages before its first URL, for cookies and a referrer")
+        )
+        .arg(
+            Arg::new("referrers")
+                .long("referrers")
+                .value_name("ENTRY")
+                .help("Send Referer headers along the crawl's navigation graph, entering each site from: mixed, search, social or direct")
+                .value_parser(PossibleValuesParser::new(EntryReferrer::NAMES.iter().copied()))
+                .num_args(0..=1)
+                .default_missing_value("mixed")
+        )
+        .arg(
+            Arg::new("pacing")
+                .long("pacing")
+                .value_name("CURVE")
+                .help("Pace each host over the day with session breaks, for long stealth crawls: human, office, evening, flat or 24 hourly weights")
+        )
+        .arg(
+            Arg::new("pacing-utc-offset")
+                .long("pacing-utc-offset")
+                .value_name("OFFSET")
+                .help("Target market's local time for --pacing, e.g. -5 or +5:30")
+                .requires("pacing")
+                .allow_hyphen_values(true)
+                .default_value("0")
+        )
+        .arg(
+            Arg::new("sample-blocks")
+                .long("sample-blocks")
+                .value_name("RATE")
+                .help("Archive this fraction (0-1) of bot-protected pages into the block corpus, for `blocks train`")
+                .value_parser(parse_sample_rate)
+        )
+        .arg(
+            Arg::new("min-quality")
+                .long("min-quality")
+                .value_name("SCORE")
+                .help("Fetch results scoring below this (0-1) again, rendered in a browser or through another country's geo proxy")
+                .value_parser(parse_quality_score)
+        )
+        .arg(
+            Arg::new("quality-retries")
+                .long("quality-retries")
+                .value_name("NUM")
+                .help("Escalated refetches per low-quality result")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("2")
+        )
+        .arg(
+            Arg::new("queue")
+                .long("queue")
+                .value_name("REDIS_URL")
+                .help("Share the URL queue with other swoop processes through Redis, e.g. redis://host:6379/0; --url/--file seed it and are optional")
+        )
+        .arg(
+            Arg::new("queue-name")
+                .long("queue-name")
+                .value_name("NAME")
+                .help("Queue to work on, so several crawls can share one Redis")
+                .requires("queue")
+                .default_value("swoop")
+        )
+        .arg(
+            Arg::new("queue-attempts")
+                .long("queue-attempts")
+                .value_name("N")
+                .help("Attempts at a failing URL before it moves to the queue's dead-letter list")
+                .requires("queue")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .default_value("3")
+        )
+        .arg(
+            Arg::new("queue-lease")
+                .long("queue-lease")
+                .value_name("INTERVAL")
+                .help("How long a claimed URL may take before another process gets it, e.g. 10m")
+                .requires("queue")
+                .default_value("10m")
+        )
+        .arg(
+            Arg::new("scrub-pii")
+                .long("scrub-pii")
+                .value_name("KINDS")
+                .help("Scrub personal data before export: email, phone, national-id, name (comma-separated)")
+                .num_args(0..=1)
+                .default_missing_value("email,phone,national-id")
+        )
+        .arg(
+            Arg::new("pii-names")
+                .long("pii-names")
+                .value_name("FILE")
+                .help("Dictionary of names to scrub, one per line")
+                .requires("scrub-pii")
+        )
+        .arg(
+            Arg::new("pii-mode")
+                .long("pii-mode")
+                .value_name("MODE")
+                .help("Replace scrubbed data with its kind (mask) or remove it (drop)")
+                .value_parser(PossibleValuesParser::new(["mask", "drop"]))
+                .default_value("mask")
+        )
+        .arg(
+            Arg::new("encrypt-key")
+                .long("encrypt-key")
+                .value_name("FILE")
+                .help("Encrypt export files with the AES-256 key in FILE (see `swoop exports keygen`)")
+        )
+        .arg(
+            Arg::new("sign-key")
+                .long("sign-key")
+                .value_name("FILE")
+                .help("Sign the export manifest with the Ed25519 key in FILE")
+        )
+        .arg(
+            Arg::new("cost-rates")
+                .long("cost-rates")
+                .value_name("FILE")
+                .help("Prices the cost report is made with (default: ./costs.toml if present)")
+        )
+        .arg(
+            Arg::new("cost-report")
+                .long("cost-report")
+                .value_name("FILE")
+                .help("Also write the job's cost report to FILE as JSON")
+        )
+        .arg(
+            Arg::new("script")
+                .long("script")
+                .value_name("FILE")
+                .help("Rhai script defining pre_request/post_extract/should_follow_link hooks")
+        )
+        .arg(
+            Arg::new("skip-junk")
+                .long("skip-junk")
+                .help("Drop pages classified as login walls or error pages from the results")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("skip-unchanged")
+                .long("skip-unchanged")
+                .help("Skip pages unchanged since their last fetch (needs the state directory)")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("webdriver")
+                .long("webdriver")
+                .value_name("URL")
+                .help("WebDriver server for rendering login, consent and paywall pages")
+        )
+        .arg(
+            Arg::new("firefox-webdriver")
+                .long("firefox-webdriver")
+                .value_name("URL")
+                .help("geckodriver server rendering the pages of domains with browser_engine = \"firefox\"")
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("browsers")
+                .long("browsers")
+                .value_name("MIN-MAX")
+                .help("Browser sessions the pool scales between, e.g. 1-8 (default: 0 to the concurrency, at most 4)")
+                .value_parser(parse_browser_range)
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("browser-standby")
+                .long("browser-standby")
+                .value_name("NUM")
+                .help("Idle browser sessions kept ready on top of the pages waiting for one")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("0")
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("browser-idle")
+                .long("browser-idle")
+                .value_name("INTERVAL")
+                .help("Time an unneeded browser session stays open, e.g. 30s or 5m")
+                .default_value("60s")
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("profile-key")
+                .long("profile-key")
+                .value_name("FILE")
+                .help("Encrypt persistent browser profiles at rest with the AES-256 key in FILE")
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("spoof-fingerprints")
+                .long("spoof-fingerprints")
+                .help("Give each Chromium browser session its own canvas, WebGL, audio and screen fingerprint")
+                .action(ArgAction::SetTrue)
+                .requires("webdriver")
+        )
+        .arg(
+            Arg::new("hardware-profiles")
+                .long("hardware-profiles")
+                .value_name("LIST")
+                .help("Comma-separated devices spoofed sessions are drawn from, e.g. windows-laptop,macbook-air-m1 (default: all)")
+                .requires("spoof-fingerprints")
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .help("Write a per-URL fetch trace (IPs, redirects, headers, timings) next to the export")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("download-images")
+                .long("download-images")
+                .value_name("DIR|s3://BUCKET")
+                .help("Download every page's images into a directory or S3 bucket, deduplicated by content")
+        )
+        .arg(
+            Arg::new("max-image-mb")
+                .long("max-image-mb")
+                .value_name("MB")
+                .help("Skip images larger than this")
+                .default_value("25")
+        )
+        .arg(
+            Arg::new("thumbnails")
+                .long("thumbnails")
+                .value_name("PIXELS")
+                .help("Also store a PNG thumbnail of each downloaded image, at most this many pixels per side")
+                .requires("download-images")
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .value_name("FORMAT")
+                .help("Save every page with its stylesheets and images as one file that opens offline")
+                .value_parser(["html", "mhtml"])
+        )
+        .arg(
+            Arg::new("snapshot-dir")
+                .long("snapshot-dir")
+                .value_name("DIR|s3://BUCKET")
+                .help("Where --snapshot files are stored (default: <dir>/snapshots)")
+                .requires("snapshot")
+        )
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .value_name("NAME")
+                .help("Name recorded for this run in the job history")
+        )
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .value_name("TAG")
+                .help("Tag recorded for this run (repeatable)")
+                .action(ArgAction::Append)
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .help("Suppress progress and summary output")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("json-progress")
+                .global(true)
+        )
+        .arg(
+            Arg::new("json-progress")
+                .long("json-progress")
+                .help("Emit machine-readable JSON status lines instead of a progress bar")
+                .action(ArgAction::SetTrue)
+                .global(true)
+        )
+}
+
+/// Resolve the progress reporting mode from parsed arguments
+pub fn progress_mode(matches: &ArgMatches) -> ProgressMode {
+    ProgressMode::from_flags(matches.get_flag("quiet"), matches.get_flag("json-progress"))
+}
+
+/// Options for a single scrape run
+#[derive(Debug, Clone)]
+pub(crate) struct ScrapeOptions {
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub concurrency: usize,
+    pub profile: Option<RateProfile>,
+    pub policies: Option<PathBuf>,
+    /// Skip the confirmation before running the aggressive profile
+    pub force: bool,
+    pub output_dir: PathBuf,
+    pub format: String,
+    pub progress_mode: ProgressMode,
+    pub script: Option<PathBuf>,
+    pub skip_junk: bool,
+    /// Skip pages whose body is the same as at their last fetch
+    pub skip_unchanged: bool,
+    pub webdriver: Option<String>,
+    /// geckodriver server for domains rendered with Firefox
+    pub firefox_webdriver: Option<String>,
+    /// How the `webdriver` browser pool scales
+    pub browsers: BrowserScaling,
+    /// Key file persistent browser profiles are encrypted with
+    pub profile_key: Option<PathBuf>,
+    /// Spoof a fingerprint per Chromium browser session
+    pub spoof_fingerprints: bool,
+    /// Names of the devices spoofed fingerprints are drawn from; empty for
+    /// all built-in ones
+    pub hardware_profiles: Vec<String>,
+    pub trace: bool,
+    /// Directory or `s3://bucket` the pages' images are downloaded to
+    pub download_images: Option<String>,
+    pub max_image_bytes: u64,
+    /// Longest side of image thumbnails, when they are made
+    pub thumbnail_size: Option<u32>,
+    /// Single-file offline copies of every page
+    pub snapshot: Option<SnapshotFormat>,
+    /// Directory or `s3://bucket` the snapshots go to, `<output dir>/snapshots` by default
+    pub snapshot_dir: Option<String>,
+    /// Size at which `jsonl` and `csv` exports move on to a new file
+    pub rotate_bytes: Option<u64>,
+    pub compress_rotated: bool,
+    /// Disk the job's exports and artifacts may use
+    pub quota_bytes: Option<u64>,
+    /// Memory and CPU use past which concurrency is cut back
+    pub resource_limits: ResourceLimits,
+    /// Per-host limits for hosts without their own in the policy file
+    pub host_budget: HostBudget,
+    /// Market emulated for URLs without a locale in the policy file
+    pub locale: Option<LocaleProfile>,
+    /// Warm sessions up for URLs without `warm_up` in the policy file
+    pub warm_up: bool,
+    /// Where hosts are entered from, when referrers follow the navigation graph
+    pub referrers: Option<EntryReferrer>,
+    /// Diurnal pacing and session breaks for each host
+    pub pacing: Option<PacingConfig>,
+    /// Fraction of bot-protected pages archived into the block corpus
+    pub sample_blocks: Option<f64>,
+    /// Low-quality results fetched again with an escalated strategy
+    pub quality: Option<QualityGate>,
+    /// Personal data scrubbed from results; empty leaves them as fetched
+    pub scrub_pii: Vec<PiiKind>,
+    pub pii_names: Option<PathBuf>,
+    pub pii_mode: RedactionMode,
+    /// Key file export files are encrypted with
+    pub encrypt_key: Option<PathBuf>,
+    /// Key file the export manifest is signed with
+    pub sign_key: Option<PathBuf>,
+    /// Prices of the cost report, instead of `costs.toml` or the defaults
+    pub cost_rates: Option<PathBuf>,
+    /// File the cost report is exported to, besides the state directory
+    pub cost_report: Option<PathBuf>,
+    /// Shared Redis queue the URLs are taken from; not part of the snapshot,
+    /// reruns fetch their seeds by themselves
+    pub queue: Option<QueueOptions>,
+    pub rerun_of: Option<String>,
+}
+
+/// Redis queue a scrape shares with other processes, as given to `--queue`
+#[derive(Debug, Clone)]
+pub(crate) struct QueueOptions {
+    pub redis_url: String,
+    pub name: String,
+    pub max_attempts: u32,
+    pub lease: Duration,
+}
+
+impl ScrapeOptions {
+    /// Configuration snapshot recorded on the job
+    pub fn snapshot(&self) -> HashMap<String, String> {
+        let mut config = HashMap::from([
+            ("concurrency".to_string(), self.concurrency.to_string()),
+            ("output_dir".to_string(), self.output_dir.display().to_string()),
+            ("format".to_string(), self.format.clone()),
+        ]);
+        if let Some(profile) = &self.profile {
+            config.insert("profile".to_string(), profile.name.clone());
+        }
+        if let Some(policies) = &self.policies {
+            config.insert("policies".to_string(), policies.display().to_string());
+        }
+        if let Some(script) = &self.script {
+            config.insert("script".to_string(), script.display().to_string());
+        }
+        if self.skip_junk {
+            config.insert("skip_junk".to_string(), "true".to_string());
+        }
+        if self.skip_unchanged {
+            config.insert("skip_unchanged".to_string(), "true".to_string());
+        }
+        if let Some(webdriver) = &self.webdriver {
+            config.insert("webdriver".to_string(), webdriver.clone());
+            config.extend(self.browsers.snapshot());
+            if let Some(firefox) = &self.firefox_webdriver {
+                config.insert("firefox_webdriver".to_string(), firefox.clone());
+            }
+            if let Some(key) = &self.profile_key {
+                config.insert("profile_key".to_string(), key.display().to_string());
+            }
+            if self.spoof_fingerprints {
+                config.insert("spoof_fingerprints".to_string(), "true".to_string());
+            }
+            if !self.hardware_profiles.is_empty() {
+                config.insert(
+                    "hardware_profiles".to_string(),
+                    self.hardware_profiles.join(","),
+                );
+            }
+        }
+        if self.trace {
+            config.insert("trace".to_string(), "true".to_string());
+        }
+        if let Some(destination) = &self.download_images {
+            config.insert("download_images".to_string(), destination.clone());
+            config.insert("max_image_bytes".to_string(), self.max_image_bytes.to_string());
+        }
+        if let Some(size) = self.thumbnail_size {
+            config.insert("thumbnail_size".to_string(), size.to_string());
+        }
+        if let Some(format) = self.snapshot {
+            config.insert("snapshot".to_string(), format.as_str().to_string());
+        }
+        if let Some(destination) = &self.snapshot_dir {
+            config.insert("snapshot_dir".to_string(), destination.clone());
+        }
+        if let Some(bytes) = self.rotate_bytes {
+            config.insert("rotate_bytes".to_string(), bytes.to_string());
+        }
+        if self.compress_rotated {
+            config.insert("compress_rotated".to_string(), "true".to_string());
+        }
+        if let Some(bytes) = self.quota_bytes {
+            config.insert("quota_bytes".to_string(), bytes.to_string());
+        }
+        if let Some(bytes) = self.resource_limits.max_rss_bytes {
+            config.insert("max_rss_bytes".to_string(), bytes.to_string());
+        }
+        if let Some(percent) = self.resource_limits.max_cpu_percent {
+            config.insert("max_cpu_percent".to_string(), percent.to_string());
+        }
+        if let Some(pages) = self.host_budget.max_pages {
+            config.insert("host_max_pages".to_string(), pages.to_string());
+        }
+        if let Some(bytes) = self.host_budget.max_bytes {
+            config.insert("host_max_bytes".to_string(), bytes.to_string());
+        }
+        if let Some(duration) = self.host_budget.max_duration {
+            config.insert("host_max_duration_secs".to_string(), duration.as_secs().to_string());
+        }
+        if let Some(locale) = &self.locale {
+            config.insert("locale".to_string(), locale.tag.clone());
+        }
+        if self.warm_up {
+            config.insert("warm_up".to_string(), "true".to_string());
+        }
+        if let Some(entry) = self.referrers {
+            config.insert("referrers".to_string(), entry.as_str().to_string());
+        }
+        if let Some(pacing) = &self.pacing {
+            config.insert("pacing".to_string(), pacing.curve.to_string());
+            config.insert("pacing_utc_offset_minutes".to_string(), pacing.utc_offset_minutes.to_string());
+        }
+        if let Some(rate) = self.sample_blocks {
+            config.insert("sample_blocks".to_string(), rate.to_string());
+        }
+        if let Some(gate) = &self.quality {
+            config.insert("min_quality".to_string(), gate.min_score.to_string());
+            config.insert("quality_retries".to_string(), gate.max_retries.to_string());
+        }
+        if !self.scrub_pii.is_empty() {
+            let kinds: Vec<&str> = self.scrub_pii.iter().map(PiiKind::as_str).collect();
+            config.insert("scrub_pii".to_string(), kinds.join(","));
+            let mode = match self.pii_mode {
+                RedactionMode::Mask => "mask",
+                RedactionMode::Drop => "drop",
+            };
+            config.insert("pii_mode".to_string(), mode.to_string());
+        }
+        if let Some(names) = &self.pii_names {
+            config.insert("pii_names".to_string(), names.display().to_string());
+        }
+        if let Some(key) = &self.encrypt_key {
+            config.insert("encrypt_key".to_string(), key.display().to_string());
+        }
+        if let Some(key) = &self.sign_key {
+            config.insert("sign_key".to_string(), key.display().to_string());
+        }
+        if let Some(rates) = &self.cost_rates {
+            config.insert("cost_rates".to_string(), rates.display().to_string());
+        }
+        config
+    }
+
+    /// Restore options from a job's configuration snapshot
+    pub fn from_snapshot(
+        config: &HashMap<String, String>,
+        progress_mode: ProgressMode,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            name: None,
+            tags: Vec::new(),
+            concurrency: config
+                .get("concurrency")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(300),
+            profile: config.get("profile").map(|name| parse_profile(name)).transpose()?,
+            policies: config.get("policies").map(PathBuf::from),
+            force: false,
+            output_dir: PathBuf::from(config.get("output_dir").map_or("./output", String::as_str)),
+            format: config.get("format").cloned().unwrap_or_else(|| "json".to_string()),
+            progress_mode,
+            script: config.get("script").map(PathBuf::from),
+            skip_junk: config.get("skip_junk").is_some_and(|value| value == "true"),
+            skip_unchanged: config
+                .get("skip_unchanged")
+                .is_some_and(|value| value == "true"),
+            webdriver: config.get("webdriver").cloned(),
+            firefox_webdriver: config.get("firefox_webdriver").cloned(),
+            browsers: BrowserScaling::from_snapshot(config)?,
+            profile_key: config.get("profile_key").map(PathBuf::from),
+            spoof_fingerprints: config
+                .get("spoof_fingerprints")
+                .is_some_and(|value| value == "true"),
+            hardware_profiles: config
+                .get("hardware_profiles")
+                .map(|list| split_list(list))
+                .unwrap_or_default(),
+            trace: config.get("trace").is_some_and(|value| value == "true"),
+            download_images: config.get("download_images").cloned(),
+            max_image_bytes: config
+                .get("max_image_bytes")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or(DEFAULT_MAX_ARTIFACT_BYTES),
+            thumbnail_size: config.get("thumbnail_size").map(|value| value.parse()).transpose()?,
+            snapshot: config
+                .get("snapshot")
+                .map(|format| format.parse())
+                .transpose()?,
+            snapshot_dir: config.get("snapshot_dir").cloned(),
+            rotate_bytes: config.get("rotate_bytes").map(|value| value.parse()).transpose()?,
+            compress_rotated: config.get("compress_rotated").is_some_and(|value| value == "true"),
+            quota_bytes: config.get("quota_bytes").map(|value| value.parse()).transpose()?,
+            resource_limits: ResourceLimits {
+                max_rss_bytes: config.get("max_rss_bytes").map(|value| value.parse()).transpose()?,
+                max_cpu_percent: config
+                    .get("max_cpu_percent")
+                    .map(|value| parse_cpu_percent(value))
+                    .transpose()?,
+            },
+            host_budget: HostBudget {
+                max_pages: config.get("host_max_pages").map(|value| value.parse()).transpose()?,
+                max_bytes: config.get("host_max_bytes").map(|value| value.parse()).transpose()?,
+                max_duration: config
+                    .get("host_max_duration_secs")
+                    .map(|value| value.parse().map(Duration::from_secs))
+                    .transpose()?,
+            },
+            locale: config.get("locale").map(|tag| LocaleProfile::named(tag)).transpose()?,
+            warm_up: config.get("warm_up").is_some_and(|value| value == "true"),
+            referrers: config.get("referrers").and_then(|entry| EntryReferrer::named(entry)),
+            pacing: config
+                .get("pacing")
+                .map(|curve| {
+                    pacing_config(
+                        curve,
+                        config
+                            .get("pacing_utc_offset_minutes")
+                            .map(|minutes| minutes.parse())
+                            .transpose()?
+                            .unwrap_or(0),
+                    )
+                })
+                .transpose()?,
+            sample_blocks: config.get("sample_blocks").map(|rate| parse_sample_rate(rate)).transpose()?,
+            quality: config
+                .get("min_quality")
+                .map(|score| -> Result<QualityGate, Box<dyn std::error::Error>> {
+                    let retries = config.get("quality_retries").map(|retries| retries.parse()).transpose()?;
+                    Ok(QualityGate::new(parse_quality_score(score)?)
+                        .with_max_retries(retries.unwrap_or(QualityGate::DEFAULT_RETRIES)))
+                })
+                .transpose()?,
+            scrub_pii: config
+                .get("scrub_pii")
+                .map(|kinds| parse_pii_kinds(kinds))
+                .transpose()?
+                .unwrap_or_default(),
+            pii_names: config.get("pii_names").map(PathBuf::from),
+            pii_mode: parse_pii_mode(config.get("pii_mode").map_or("mask", String::as_str)),
+            encrypt_key: config.get("encrypt_key").map(PathBuf::from),
+            sign_key: config.get("sign_key").map(PathBuf::from),
+            cost_rates: config.get("cost_rates").map(PathBuf::from),
+            cost_report: None,
+            queue: None,
+            rerun_of: None,
+        })
+    }
+}
+
+/// A size flag given in megabytes, in bytes
+fn megabytes_arg(matches: &ArgMatches, name: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    Ok(matches
+        .get_one::<String>(name)
+        .map(|mb| mb.parse::<u64>())
+        .transpose()?
+        .map(|mb| mb * 1024 * 1024))
+}
+
+/// Fraction of pages to sample, as given to `--sample-blocks`
+fn parse_sample_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(format!("Invalid sample rate '{}', expected a fraction from 0 to 1", value)),
+    }
+}
+
+/// Size of the `--webdriver` browser pool and how it scales
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BrowserScaling {
+    pub min: usize,
+    /// The concurrency, up to 4, when not given
+    pub max: Option<usize>,
+    /// Idle sessions kept ready on top of the demand
+    pub standby: usize,
+    /// Time an idle session above the demand stays open
+    pub idle_timeout: Duration,
+}
+
+impl Default for BrowserScaling {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: None,
+            standby: 0,
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl BrowserScaling {
+    /// Pool config for `engine` behind `webdriver_url`, at most `concurrency`
+    /// sessions (up to 4) unless a maximum was given
+    pub fn config(
+        &self,
+        engine: BrowserEngine,
+        webdriver_url: &str,
+        concurrency: usize,
+    ) -> BrowserConfig {
+        let max = self.max.unwrap_or_else(|| concurrency.clamp(1, 4));
+        BrowserConfig {
+            max_instances: max,
+            min_instances: self.min.min(max),
+            warm_standby: self.standby,
+            idle_timeout_secs: self.idle_timeout.as_secs(),
+            webdriver_url: webdriver_url.to_string(),
+            ..BrowserConfig::for_engine(engine)
+        }
+    }
+
+    pub fn from_args(matches: &ArgMatches) -> Result<Self, String> {
+        let (min, max) = match matches.get_one::<(usize, usize)>("browsers") {
+            Some((min, max)) => (*min, Some(*max)),
+            None => (0, None),
+        };
+        Ok(Self {
+            min,
+            max,
+            standby: *matches.get_one::<usize>("browser-standby").unwrap(),
+            idle_timeout: parse_interval(matches.get_one::<String>("browser-idle").unwrap())
+                .map_err(|e| e.to_string())?,
+        })
+    }
+
+    fn snapshot(&self) -> Vec<(String, String)> {
+        let mut config = vec![
+            ("browser_standby".to_string(), self.standby.to_string()),
+            ("browser_idle_secs".to_string(), self.idle_timeout.as_secs().to_string()),
+        ];
+        if let Some(max) = self.max {
+            config.push(("browsers".to_string(), format!("{}-{}", self.min, max)));
+        }
+        config
+    }
+
+    fn from_snapshot(config: &HashMap<String, String>) -> Result<Self, Box<dyn std::error::Error>> {
+        let defaults = Self::default();
+        let (min, max) = match config.get("browsers") {
+            Some(range) => {
+                let (min, max) = parse_browser_range(range)?;
+                (min, Some(max))
+            }
+            None => (defaults.min, defaults.max),
+        };
+        Ok(Self {
+            min,
+            max,
+            standby: config
+                .get("browser_standby")
+                .map(|standby| standby.parse())
+                .transpose()?
+                .unwrap_or(defaults.standby),
+            idle_timeout: config
+                .get("browser_idle_secs")
+                .map(|secs| secs.parse().map(Duration::from_secs))
+                .transpose()?
+                .unwrap_or(defaults.idle_timeout),
+        })
+    }
+}
+
+/// Browser sessions given to `--browsers`, `MIN-MAX` or just the maximum
+pub(crate) fn parse_browser_range(value: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("Invalid browser range '{}', expected e.g. 1-8 or 8", value);
+    let parse = |number: &str| number.trim().parse::<usize>().map_err(|_| invalid());
+    let (min, max) = match value.split_once('-') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => (0, parse(value)?),
+    };
+    if max == 0 || min > max {
+        return Err(invalid());
+    }
+    Ok((min, max))
+}
+
+pub(crate) fn parse_quality_score(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(score) if (0.0..=1.0).contains(&score) => Ok(score),
+        _ => Err(format!("Invalid quality score '{}', expected a score from 0 to 1", value)),
+    }
+}
+
+/// Pacing along `curve`, as given to `--pacing`, in a market `utc_offset_minutes`
+/// from UTC
+fn pacing_config(curve: &str, utc_offset_minutes: i32) -> Result<PacingConfig, Box<dyn std::error::Error>> {
+    Ok(PacingConfig {
+        curve: DiurnalCurve::parse(curve).map_err(|e| e.to_string())?,
+        utc_offset_minutes,
+        ..PacingConfig::default()
+    })
+}
+
+/// A UTC offset such as `-5` or `+5:30`, in minutes
+fn parse_utc_offset(offset: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    let invalid = || format!("Invalid UTC offset '{}', expected hours such as -5 or +5:30", offset);
+    let (sign, rest) = match offset.trim().strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, offset.trim().trim_start_matches('+')),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid().into());
+    }
+    Ok(sign * (hours * 60 + minutes))
+}
+
+/// Non-empty items of a comma-separated list
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Comma-separated personal data kinds, as given to `--scrub-pii`
+fn parse_pii_kinds(list: &str) -> Result<Vec<PiiKind>, Box<dyn std::error::Error>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .map(|kind| {
+            PiiKind::named(kind).ok_or_else(|| {
+                format!(
+                    "Unknown personal data kind '{}', expected email, phone, national-id or name",
+                    kind
+                )
+                .into()
+            })
+        })
+        .collect()
+}
+
+fn parse_pii_mode(mode: &str) -> RedactionMode {
+    match mode {
+        "drop" => RedactionMode::Drop,
+        _ => RedactionMode::Mask,
+    }
+}
+
+/// Look up a rate profile by name
+pub(crate) fn parse_profile(name: &str) -> Result<RateProfile, Box<dyn std::error::Error>> {
+    RateProfile::named(name).ok_or_else(|| {
+        format!(
+            "Unknown rate profile '{}', expected one of: {}",
+            name,
+            RateProfile::NAMES.join(", ")
+        )
+        .into()
+    })
+}
+
+/// Refuse to run the aggressive profile against domains that returned 429s
+/// before, unless forced or confirmed at the terminal
+fn confirm_aggressive(
+    urls: &[String],
+    options: &ScrapeOptions,
+    store: Option<&LocalStore>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(profile), Some(store)) = (&options.profile, store) else {
+        return Ok(());
+    };
+    if !profile.is_aggressive() {
+        return Ok(());
+    }
+    let history = RateLimitHistory::load(store.root());
+    let flagged = history.flagged(urls);
+    if flagged.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("⚠️  These domains answered with 429 Too Many Requests before:");
+    for (domain, record) in &flagged {
+        eprintln!(
+            "   {} ({} times, last {})",
+            domain,
+            record.count,
+            record.last_seen.format("%Y-%m-%d %H:%M")
+        );
+    }
+    if options.force {
+        warn!("⚠️  Running the aggressive profile anyway (--force)");
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("Refusing to run the aggressive profile against rate-limited domains; \
+                    use --force or a gentler --profile"
+            .into());
+    }
+
+    eprint!("Run the aggressive profile against them anyway? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim(), "y" | "Y" | "yes") {
+        Ok(())
+    } else {
+        Err("Aborted".into())
+    }
+}
+
+/// Remember domains that rate limited this run for the aggressive-profile check
+fn record_rate_limited(domains: &[String], store: Option<&LocalStore>) {
+    let Some(store) = store else {
+        return;
+    };
+    if domains.is_empty() {
+        return;
+    }
+    let mut history = RateLimitHistory::load(store.root());
+    for domain in domains {
+        history.record(domain);
+    }
+    if let Err(e) = history.save() {
+        warn!("⚠️  Failed to save rate limit history: {}", e);
+    }
+    warn!("🐢 Rate limited by: {}", domains.join(", "));
+}
+
+/// Open the local store used for job tracking, warning instead of failing if it is unavailable
+pub(crate) async fn open_state_store(state_dir: &str) -> Option<LocalStore> {
+    match LocalStore::new(state_dir).await {
+        Ok(store) => Some(store),
+        Err(e) => {
+            warn!("⚠️  Job tracking disabled, cannot open {}: {}", state_dir, e);
+            None
+        }
+    }
+}
+
+/// Scrape `urls` as a tracked job and export the results
+///
+/// Returns the finished job and the manifest of its exports.
+pub(crate) async fn run_scrape(
+    urls: Vec<String>,
+    options: ScrapeOptions,
+    store: Option<&LocalStore>,
+) -> Result<(Job, PathBuf), Box<dyn std::error::Error>> {
+    let name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("scrape-{}", Utc::now().format("%Y%m%d_%H%M%S")));
+    let mut job = Job::new(name, urls.clone(), options.snapshot()).with_tags(options.tags.clone());
+    if let Some(rerun_of) = &options.rerun_of {
+        job = job.with_rerun_of(rerun_of.clone());
+    }
+    confirm_aggressive(&urls, &options, store)?;
+    let hooks = match &options.script {
+        Some(path) => Some(Arc::new(ScriptHooks::from_file(path)?)),
+        None => None,
+    };
+    if let Some(profile) = &options.profile {
+        info!("🚦 Using the {} rate profile", profile.name);
+    }
+    let policies = load_policies(options.policies.as_deref()).map_err(|e| e.to_string())?;
+    let fingerprints = if options.spoof_fingerprints && options.webdriver.is_some() {
+        let devices = options
+            .hardware_profiles
+            .iter()
+            .map(|name| {
+                HardwareProfile::named(name).ok_or_else(|| {
+                    let known: Vec<String> = HardwareProfile::builtin()
+                        .into_iter()
+                        .map(|device| device.name)
+                        .collect();
+                    format!(
+                        "Unknown hardware profile '{}', expected one of {}",
+                        name,
+                        known.join(", ")
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let manager = FingerprintManager::new()
+            .await
+            .map_err(|e| e.to_string())?
+            .with_hardware_profiles(devices);
+        Some(Arc::new(manager))
+    } else {
+        None
+    };
+    let start_pool = |engine, webdriver_url: &str| {
+        let mut pool = BrowserPool::new(options.browsers.config(
+            engine,
+            webdriver_url,
+            options.concurrency,
+        ));
+        if let (Some(manager), BrowserEngine::Chromium) = (&fingerprints, engine) {
+            pool = pool.with_fingerprints(manager.clone());
+        }
+        let pool = Arc::new(pool);
+        pool.start_autoscaling();
+        pool
+    };
+    let browser = options
+        .webdriver
+        .as_deref()
+        .map(|webdriver_url| start_pool(BrowserEngine::Chromium, webdriver_url));
+    let engines: Vec<Arc<BrowserPool>> = options
+        .firefox_webdriver
+        .as_deref()
+        .map(|webdriver_url| start_pool(BrowserEngine::Firefox, webdriver_url))
+        .into_iter()
+        .collect();
+    let quota = Arc::new(match options.quota_bytes {
+        Some(bytes) => DiskQuota::new(bytes),
+        None => DiskQuota::unlimited(),
+    });
+    let artifacts = options
+        .download_images
+        .as_deref()
+        .map(|destination| {
+            open_artifact_store(destination, options.max_image_bytes, options.thumbnail_size)
+                .map(|store| store.with_quota(quota.clone()))
+        })
+        .transpose()?
+        .map(Arc::new);
+    let snapshots = match options.snapshot {
+        Some(format) => {
+            let destination = options
+                .snapshot_dir
+                .clone()
+                .unwrap_or_else(|| options.output_dir.join("snapshots").display().to_string());
+            let store = open_artifact_store(&destination, DEFAULT_MAX_ARTIFACT_BYTES, None)?;
+            Some((Arc::new(store.with_quota(quota.clone())), format))
+        }
+        None => None,
+    };
+    let rotation = Rotation {
+        max_bytes: options.rotate_bytes,
+        compress: options.compress_rotated,
+    };
+    let scrubber = pii_scrubber(&options)?.map(Arc::new);
+    let block_samples = match (options.sample_blocks, store) {
+        (Some(rate), Some(store)) => {
+            Some((Arc::new(BlockCorpus::open(store.root().join(blocks::CORPUS_DIR))?), rate))
+        }
+        _ => None,
+    };
+    let queue = match &options.queue {
+        Some(queue) => Some(Arc::new(open_queue(queue, &urls).await?)),
+        None => None,
+    };
+    let dead_letters = store.map(|store| (Arc::new(DeadLetterLog::open(store.root())), job.id.clone()));
+    let body_fingerprints = match (options.skip_unchanged, store) {
+        (true, Some(store)) => Some(Arc::new(store.clone())),
+        (true, None) => {
+            warn!("⚠️  --skip-unchanged needs the state directory, which is unavailable");
+            None
+        }
+        (false, _) => None,
+    };
+    // Load keys up front so a bad key file fails before the scrape, not after
+    let export_key = options.encrypt_key.as_deref().map(ExportKey::load).transpose()?;
+    let profile_key = options.profile_key.as_deref().map(ExportKey::load).transpose()?;
+    // Profiles live in the state store, so they outlast the run
+    let browser_profiles = match (&browser, store) {
+        (Some(_), Some(store)) => Some(Arc::new(ProfileStore::open(
+            store.root().join(PROFILES_DIR),
+            ProfileConfig::default(),
+            profile_key,
+        )?)),
+        _ => None,
+    };
+    let sign_key = options.sign_key.as_deref().map(sealing::load_signing_key).transpose()?;
+    let rates = load_cost_rates(options.cost_rates.as_deref()).map_err(|e| e.to_string())?;
+    save_job(store, &job).await;
+
+    let scraper = CliScraper::new(
+        options.concurrency,
+        options.output_dir.clone(),
+        options.progress_mode,
+    )
+    .with_hooks(hooks)
+    .with_skip_junk(options.skip_junk)
+    .with_body_fingerprints(body_fingerprints)
+    .with_quarantine(store.map(|store| Arc::new(store.clone())))
+    .with_browser(browser)
+    .with_engines(engines)
+    .with_browser_profiles(browser_profiles)
+    .with_trace(options.trace)
+    .with_profile(options.profile.clone())?
+    .with_policies(policies)
+    .with_budget(options.host_budget)
+    .with_locale(options.locale.clone())
+    .with_warm_up(options.warm_up)
+    .with_referrers(options.referrers)
+    .with_pacing(options.pacing.clone())?
+    .with_block_samples(block_samples)
+    .with_quality(options.quality)
+    .with_queue(queue)
+    .with_dead_letters(dead_letters)
+    .with_shutdown(Shutdown::on_signal())
+    .with_artifacts(artifacts)
+    .with_snapshots(snapshots)
+    .with_scrubber(scrubber)
+    .with_output(&options.format, rotation, quota.clone())?;
+
+    let guard = options.resource_limits.is_set().then(|| {
+        let mut guard = ResourceGuard::new(options.resource_limits)
+            .with_throttle("concurrency", scraper.slots.clone());
+        if let Some(browser) = &scraper.browser {
+            guard = guard.with_throttle("browsers", browser.throttle());
+        }
+        for engine in &scraper.engines {
+            guard = guard.with_throttle("firefox browsers", engine.throttle());
+        }
+        tokio::spawn(guard.watch(|_| {}))
+    });
+
+    // Perform scraping
+    let unfinished = scraper.scrape_urls(urls).await;
+    if let Some(guard) = guard {
+        guard.abort();
+    }
+    for browser in scraper.browser.iter().chain(&scraper.engines) {
+        browser.close_idle().await;
+    }
+    record_rate_limited(&scraper.rate_limited_domains(), store);
+
+    // Print summary
+    if options.progress_mode.is_interactive() {
+        scraper.print_summary();
+    }
+
+    // Export results
+    let export_result = scraper.export_results().and_then(|files| {
+        seal_exports(files, scraper.provenance(&job), export_key.as_ref(), sign_key.as_ref())
+    });
+    let status = if unfinished.is_some() {
+        JobStatus::Interrupted
+    } else if export_result.is_ok() && !quota.is_exceeded() {
+        JobStatus::Completed
+    } else {
+        JobStatus::Failed
+    };
+    job.finish(status, scraper.job_counts());
+    save_job(store, &job).await;
+    let costs = scraper.cost_report(&job, &rates);
+    save_cost_report(store, &costs, &options);
+
+    let (export_paths, manifest) = export_result?;
+    if options.progress_mode.is_interactive() {
+        print_cost_report(&costs);
+        println!("📄 Results written to {}", export_paths[0].display());
+        println!("🧾 Manifest written to {}", manifest.display());
+        println!("🗂️  Job {} ({})", job.name, job.id);
+    }
+    if let Some(unfinished) = unfinished {
+        let mut exports = export_paths;
+        exports.push(manifest.clone());
+        save_checkpoint(store, &job, unfinished, exports, &options);
+    }
+
+    Ok((job, manifest))
+}
+
+/// Load the cost rates at `path`, or `costs.toml` in the working directory if
+/// it exists, or else the default list prices
+pub(crate) fn load_cost_rates(
+    path: Option<&Path>,
+) -> Result<CostRates, Box<dyn std::error::Error + Send + Sync>> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(COST_RATES_FILE).exists() => Path::new(COST_RATES_FILE),
+        None => return Ok(CostRates::default()),
+    };
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&contents).map_err(|e| format!("Invalid cost rates {}: {}", path.display(), e).into())
+}
+
+/// Keep a job's cost report in the state directory, and write it to
+/// `--cost-report` if given
+fn save_cost_report(store: Option<&LocalStore>, report: &CostReport, options: &ScrapeOptions) {
+    if let Some(store) = store {
+        if let Err(e) = CostStore::open(store.root()).save(report) {
+            warn!("⚠️  Failed to save the cost report of job {}: {:#}", report.job_id, e);
+        }
+    }
+    if let Some(path) = &options.cost_report {
+        let written = serde_json::to_vec_pretty(report)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => info!("💰 Cost report written to {}", path.display()),
+            Err(e) => warn!("⚠️  Failed to write the cost report to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Print what a job used and what it is estimated to have cost
+pub(crate) fn print_cost_report(report: &CostReport) {
+    println!("💰 Estimated cost: {:.4} {}", report.total, report.currency);
+    for line in &report.lines {
+        println!(
+            "   {:<20} {:>14.6} {:<6} × {:<8} = {:.4}",
+            line.item, line.quantity, line.unit, line.rate, line.cost
+        );
+    }
+}
+
+/// Record the URLs an interrupted job left and say how to pick them up
+fn save_checkpoint(
+    store: Option<&LocalStore>,
+    job: &Job,
+    unfinished: Vec<String>,
+    exports: Vec<PathBuf>,
+    options: &ScrapeOptions,
+) {
+    // Quiet and JSON progress keep stdout clean, so the way back goes to the log
+    let tell = |message: String| {
+        if options.progress_mode.is_interactive() {
+            println!("{}", message);
+        } else {
+            warn!("{}", message);
+        }
+    };
+    if let Some(queue) = &options.queue {
+        tell(format!(
+            "▶️  URLs left stay on queue {}; run with --queue again to pick them up",
+            queue.name
+        ));
+        return;
+    }
+    if unfinished.is_empty() {
+        return;
+    }
+    let left = unfinished.len();
+    match store {
+        Some(store) => {
+            let checkpoint = Checkpoint::new(&job.id, &job.name, unfinished, job.counts.total)
+                .with_exports(exports);
+            match CheckpointStore::open(store.root()).save(&checkpoint) {
+                Ok(path) => {
+                    tell(format!(
+                        "💾 Checkpoint of {} URLs left written to {}",
+                        left,
+                        path.display()
+                    ));
+                    tell(format!(
+                        "▶️  Resume with: swoop jobs resume {}",
+                        &job.id[..8.min(job.id.len())]
+                    ));
+                }
+                Err(e) => warn!(
+                    "⚠️  Failed to write the checkpoint of job {}: {:#}",
+                    job.id, e
+                ),
+            }
+        }
+        // Without a state directory the URLs left go next to the exports
+        None => {
+            let path = options
+                .output_dir
+                .join(format!("unfinished_{}.txt", &job.id[..8.min(job.id.len())]));
+            match fs::write(&path, unfinished.join("\n") + "\n") {
+                Ok(()) => {
+                    tell(format!(
+                        "💾 {} URLs left written to {}",
+                        left,
+                        path.display()
+                    ));
+                    tell(format!("▶️  Resume with: swoop --file {}", path.display()));
+                }
+                Err(e) => warn!(
+                    "⚠️  Failed to write the URLs left to {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+}
+
+async fn open_queue(
+    options: &QueueOptions,
+    seeds: &[String],
+) -> Result<RedisQueue, Box<dyn std::error::Error>> {
+    let queue = RedisQueue::connect(&options.redis_url, &options.name)
+        .await
+        .map_err(|e| format!("{:#}", e))?
+        .with_max_attempts(options.max_attempts)
+        .with_lease(options.lease);
+    if !seeds.is_empty() {
+        let added = queue.enqueue(seeds).await.map_err(|e| format!("{:#}", e))?;
+        info!(
+            "📥 Queued {} of {} URLs on queue {}",
+            added,
+            seeds.len(),
+            queue.name()
+        );
+    }
+    Ok(queue)
+}
+
+/// Encrypt the export files if a key is given and write their manifest
+///
+/// Returns the files as they are left on disk and the manifest. The manifest
+/// is named after the first file, lists the encrypted files when they are
+/// encrypted and is signed when `signer` is given.
+fn seal_exports(
+    files: Vec<PathBuf>,
+    provenance: Provenance,
+    key: Option<&ExportKey>,
+    signer: Option<&SigningKey>,
+) -> Result<(Vec<PathBuf>, PathBuf), Box<dyn std::error::Error>> {
+    let files = match key {
+        Some(key) => {
+            let encrypted = files
+                .iter()
+                .map(|path| sealing::encrypt_file(key, path))
+                .collect::<Result<Vec<_>, _>>()?;
+            info!("🔒 Encrypted {} export file(s)", encrypted.len());
+            encrypted
+        }
+        None => files,
+    };
+    let first = files[0].file_name().unwrap_or_default().to_string_lossy();
+    let stem = first.split('.').next().unwrap_or("export");
+    let manifest_path = files[0].with_file_name(format!("{}.manifest.json", stem));
+    Manifest::build(&files, key.is_some())?
+        .with_provenance(provenance)
+        .write(&manifest_path, signer)?;
+    Ok((files, manifest_path))
+}
+
+/// Audit log of scrubbed personal data, in the output directory
+const PII_AUDIT_LOG: &str = "pii_audit.jsonl";
+
+/// Scrubber for `--scrub-pii`, logging what it removes next to the exports
+fn pii_scrubber(options: &ScrapeOptions) -> Result<Option<PiiScrubber>, Box<dyn std::error::Error>> {
+    if options.scrub_pii.is_empty() {
+        return Ok(None);
+    }
+    if options.scrub_pii.contains(&PiiKind::Name) && options.pii_names.is_none() {
+        return Err("Scrubbing names needs a dictionary (--pii-names)".into());
+    }
+    let mut scrubber = PiiScrubber::new(options.scrub_pii.iter().copied()).with_mode(options.pii_mode);
+    if let Some(path) = &options.pii_names {
+        scrubber = scrubber.with_names_file(path)?;
+    }
+    let log = options.output_dir.join(PII_AUDIT_LOG);
+    let scrubber = scrubber.with_audit_log(PiiAuditLog::open(&log)?);
+    let kinds: Vec<&str> = scrubber.kinds().iter().map(PiiKind::as_str).collect();
+    info!("🕵️  Scrubbing {} from results, audit log: {}", kinds.join(", "), log.display());
+    Ok(Some(scrubber))
+}
+
+/// Scrub the stored fields of a result and log what was removed
+fn scrub_pii(scrubber: &PiiScrubber, result: &mut ScrapedData) {
+    let mut redactions = scrubber.scrub_text(&result.url, "content", &mut result.content);
+    for (field, value) in [("title", &mut result.title), ("excerpt", &mut result.excerpt)] {
+        if let Some(value) = value {
+            redactions.extend(scrubber.scrub_text(&result.url, field, value));
+        }
+    }
+    if redactions.is_empty() {
+        return;
+    }
+    result.content_length = result.content.len();
+    result.tags.push("pii:redacted".to_string());
+    if let Err(e) = scrubber.audit(&redactions) {
+        warn!("⚠️  Failed to write the PII audit log: {}", e);
+    }
+}
+
+/// Most images downloaded from one page
+const MAX_IMAGES_PER_PAGE: usize = 100;
+
+/// Artifact store for `--download-images`: a directory, or an S3 bucket with
+/// credentials from the usual `AWS_*` environment variables
+fn open_artifact_store(
+    destination: &str,
+    max_bytes: u64,
+    thumbnail_size: Option<u32>,
+) -> Result<ArtifactStore, Box<dyn std::error::Error>> {
+    let store = match destination.strip_prefix("s3://") {
+        Some(bucket) => {
+            let bucket = bucket.trim_end_matches('/');
+            if bucket.is_empty() || bucket.contains('/') {
+                return Err(format!("Expected s3://BUCKET, got {}", destination).into());
+            }
+            let defaults = S3Config::default();
+            let env = |name: &str| std::env::var(name).ok();
+            ArtifactStore::s3(S3Config {
+                endpoint: env("AWS_ENDPOINT_URL").unwrap_or(defaults.endpoint),
+                access_key_id: env("AWS_ACCESS_KEY_ID").ok_or("AWS_ACCESS_KEY_ID is not set")?,
+                secret_access_key: env("AWS_SECRET_ACCESS_KEY")
+                    .ok_or("AWS_SECRET_ACCESS_KEY is not set")?,
+                bucket: bucket.to_string(),
+                region: env("AWS_REGION").unwrap_or(defaults.region),
+            })
+        }
+        None => ArtifactStore::local(destination),
+    };
+    let store = store.with_max_bytes(max_bytes);
+    Ok(match thumbnail_size {
+        Some(size) => store.with_thumbnails(size),
+        None => store,
+    })
+}
+
+/// Download the images `html` references into `store`, returning where they were stored
+async fn download_images(
+    store: &ArtifactStore,
+    safety: &SafetyFilter,
+    page_url: &str,
+    html: &str,
+) -> Vec<String> {
+    let Ok(base) = reqwest::Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let mut seen = HashSet::new();
+    let urls: Vec<String> = extract_images(html)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|src| base.join(decode_entities(src.trim()).as_str()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(String::from)
+        .filter(|url| seen.insert(url.clone()))
+        .take(MAX_IMAGES_PER_PAGE)
+        .collect();
+
+    let mut locations: Vec<String> = futures::stream::iter(urls)
+        .map(|url| async move {
+            match download_artifact(store, safety, &url).await {
+                Ok(artifact) => Some(artifact.location),
+                Err(e) => {
+                    debug!("🖼️  Skipped image {}: {}", url, e);
+                    None
+                }
+            }
+        })
+        .buffered(4)
+        .filter_map(|location| async move { location })
+        .collect()
+        .await;
+    // Pages often use the same image twice under different URLs
+    locations.dedup();
+    locations
+}
+
+/// Pack `html` with its stylesheets and images into one `format` file in
+/// `store`, returning where it was stored
+async fn save_snapshot(
+    store: &ArtifactStore,
+    format: SnapshotFormat,
+    safety: &SafetyFilter,
+    page_url: &str,
+    html: &str,
+) -> Option<String> {
+    let archiver = PageArchiver::new().with_safety(safety.clone());
+    let snapshot = match archiver.archive(html, page_url, format).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            warn!("⚠️  No snapshot of {}: {}", page_url, e);
+            return None;
+        }
+    };
+    if !snapshot.missing.is_empty() {
+        debug!(
+            "📦 Snapshot of {} packs {} resources, {} left out",
+            page_url,
+            snapshot.resources,
+            snapshot.missing.len()
+        );
+    }
+    match store.store(page_url, format.content_type(), &snapshot.body).await {
+        Ok(artifact) => Some(artifact.location),
+        Err(e) => {
+            warn!("⚠️  Failed to store the snapshot of {}: {}", page_url, e);
+            None
+        }
+    }
+}
+
+async fn download_artifact(
+    store: &ArtifactStore,
+    safety: &SafetyFilter,
+    url: &str,
+) -> Result<ArtifactRef, Box<dyn std::error::Error + Send + Sync>> {
+    let response = swoop_core::fetch_response(url, Duration::from_secs(30)).await?;
+    if response.meta.status >= 400 {
+        return Err(format!("HTTP {}", response.meta.status).into());
+    }
+    let content_type = response
+        .meta
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_ascii_lowercase();
+    if !["image/", "video/", "audio/"].iter().any(|kind| content_type.starts_with(kind)) {
+        return Err(format!("not an image ({})", content_type).into());
+    }
+    if let Some(verdict) = safety.check(Some(&content_type), &response.body) {
+        return Err(format!("{} ({})", verdict.reason, verdict.hazard.as_str()).into());
+    }
+    Ok(store.store(url, &content_type, &response.body).await?)
+}
+
+const CSV_HEADER: [&str; 12] = [
+    "URL",
+    "Timestamp",
+    "Status Code",
+    "Success",
+    "Response Time",
+    "Content Length",
+    "Content Type",
+    "Title",
+    "Excerpt",
+    "Tags",
+    "Error",
+    "Canonical URL",
+];
+
+/// One CSV line; titles and excerpts contain commas and quotes, so fields are quoted as needed
+fn csv_line<I, T>(fields: I) -> Result<Vec<u8>, csv::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    writer.write_record(fields)?;
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// A result as one `jsonl` or `csv` record
+fn encode_record(format: &str, item: &ScrapedData) -> std::io::Result<Vec<u8>> {
+    if format == "jsonl" {
+        let mut line = serde_json::to_vec(item)?;
+        line.push(b'\n');
+        return Ok(line);
+    }
+    csv_line([
+        item.url.clone(),
+        item.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        item.status_code.unwrap_or(0).to_string(),
+        item.success.to_string(),
+        item.response_time.to_string(),
+        item.content_length.to_string(),
+        item.content_type.clone().unwrap_or_default(),
+        item.title.clone().unwrap_or_default(),
+        item.excerpt.clone().unwrap_or_default(),
+        item.tags.join(";"),
+        item.error.clone().unwrap_or_default(),
+        item.canonical_url.clone().unwrap_or_default(),
+    ])
+    .map_err(std::io::Error::other)
+}
+
+async fn save_job(store: Option<&LocalStore>, job: &Job) {
+    if let Some(store) = store {
+        if let Err(e) = store.save_job(job).await {
+            warn!("⚠️  Failed to record job {}: {}", job.id, e);
+        }
+    }
+}
+
+pub async fn run_cli(matches: ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let state_dir = matches.get_one::<String>("state-dir").unwrap();
+    blocks::install_signatures(state_dir);
+    match matches.subcommand() {
+        Some(("daemon", sub)) => {
+            return daemon::run(sub, state_dir)
+                .await
+                .map_err(|e| e as Box<dyn std::error::Error>);
+        }
+        Some(("download", sub)) => return download::run(sub).await,
+        Some(("probe", sub)) => return probe::run(sub).await,
+        Some(("jobs", sub)) => return jobs::run(sub, state_dir).await,
+        Some(("analyze", sub)) => return analyze::run(sub, state_dir).await,
+        Some(("reprocess", sub)) => return reprocess::run(sub, state_dir).await,
+        Some(("serp", sub)) => return serp::run(sub, state_dir).await,
+        Some(("sites", sub)) => return sites::run(sub, state_dir).await,
+        Some(("blocks", sub)) => return blocks::run(sub, state_dir).await,
+        Some(("failures", sub)) => return failures::run(sub, state_dir).await,
+        Some(("tokens", sub)) => return tokens::run(sub, state_dir).await,
+        Some(("exports", sub)) => return exports::run(sub),
+        Some(("rerun", sub)) => return rerun::run(sub, state_dir).await,
+        Some(("schedule", sub)) => return schedule::run(sub, state_dir).await,
+        Some(("backfill", sub)) => return backfill::run(sub, state_dir).await,
+        Some(("discover", sub)) => return discover::run(sub, state_dir).await,
+        Some(("snapshot", sub)) => return snapshot::run(sub, state_dir).await,
+        Some(("workflow", sub)) => return workflow::run(sub, state_dir).await,
+        Some(("recipe", sub)) => return recipe::run(sub, state_dir).await,
+        _ => {}
+    }
+
+    let profile = matches
+        .get_one::<String>("profile")
+        .map(|name| parse_profile(name))
+        .transpose()?;
+    // The profile sets concurrency unless -c was given explicitly
+    let concurrency = match (&profile, matches.value_source("concurrency")) {
+        (Some(profile), Some(source)) if source != ValueSource::CommandLine => profile.concurrency,
+        _ => matches.get_one::<String>("concurrency").unwrap().parse()?,
+    };
+    let options = ScrapeOptions {
+        name: matches.get_one::<String>("name").cloned(),
+        tags: matches
+            .get_many::<String>("tag")
+            .map(|tags| tags.cloned().collect())
+            .unwrap_or_default(),
+        concurrency,
+        profile,
+        policies: matches.get_one::<String>("policies").map(PathBuf::from),
+        force: matches.get_flag("force"),
+        output_dir: PathBuf::from(matches.get_one::<String>("dir").unwrap()),
+        format: matches.get_one::<String>("format").unwrap().clone(),
+        progress_mode: progress_mode(&matches),
+        script: matches.get_one::<String>("script").map(PathBuf::from),
+        skip_junk: matches.get_flag("skip-junk"),
+        skip_unchanged: matches.get_flag("skip-unchanged"),
+        webdriver: matches.get_one::<String>("webdriver").cloned(),
+        firefox_webdriver: matches.get_one::<String>("firefox-webdriver").cloned(),
+        browsers: BrowserScaling::from_args(&matches)?,
+        profile_key: matches.get_one::<String>("profile-key").map(PathBuf::from),
+        spoof_fingerprints: matches.get_flag("spoof-fingerprints"),
+        hardware_profiles: matches
+            .get_one::<String>("hardware-profiles")
+            .map(|list| split_list(list))
+            .unwrap_or_default(),
+        trace: matches.get_flag("trace"),
+        download_images: matches.get_one::<String>("download-images").cloned(),
+        max_image_bytes: matches.get_one::<String>("max-image-mb").unwrap().parse::<u64>()? * 1024 * 1024,
+        thumbnail_size: matches
+            .get_one::<String>("thumbnails")
+            .map(|size| size.parse())
+            .transpose()?,
+        snapshot: matches
+            .get_one::<String>("snapshot")
+            .map(|format| format.parse())
+            .transpose()?,
+        snapshot_dir: matches.get_one::<String>("snapshot-dir").cloned(),
+        rotate_bytes: megabytes_arg(&matches, "rotate-mb")?,
+        compress_rotated: matches.get_flag("compress-rotated"),
+        quota_bytes: megabytes_arg(&matches, "quota-mb")?,
+        resource_limits: ResourceLimits {
+            max_rss_bytes: megabytes_arg(&matches, "max-rss-mb")?,
+            max_cpu_percent: matches.get_one::<f32>("max-cpu").copied(),
+        },
+        host_budget: HostBudget {
+            max_pages: matches
+                .get_one::<String>("host-max-pages")
+                .map(|pages| pages.parse())
+                .transpose()?,
+            max_bytes: megabytes_arg(&matches, "host-max-mb")?,
+            max_duration: matches
+                .get_one::<String>("host-max-duration")
+                .map(|duration| parse_interval(duration))
+                .transpose()?,
+        },
+        locale: matches
+            .get_one::<String>("locale")
+            .map(|tag| LocaleProfile::named(tag))
+            .transpose()?,
+        warm_up: matches.get_flag("warm-up"),
+        referrers: matches
+            .get_one::<String>("referrers")
+            .and_then(|entry| EntryReferrer::named(entry)),
+        pacing: matches
+            .get_one::<String>("pacing")
+            .map(|curve| pacing_config(curve, parse_utc_offset(matches.get_one::<String>("pacing-utc-offset").unwrap())?))
+            .transpose()?,
+        sample_blocks: matches.get_one::<f64>("sample-blocks").copied(),
+        quality: matches.get_one::<f64>("min-quality").map(|score| {
+            QualityGate::new(*score).with_max_retries(*matches.get_one::<u32>("quality-retries").unwrap())
+        }),
+        scrub_pii: matches
+            .get_one::<String>("scrub-pii")
+            .map(|kinds| parse_pii_kinds(kinds))
+            .transpose()?
+            .unwrap_or_default(),
+        pii_names: matches.get_one::<String>("pii-names").map(PathBuf::from),
+        pii_mode: parse_pii_mode(matches.get_one::<String>("pii-mode").unwrap()),
+        encrypt_key: matches.get_one::<String>("encrypt-key").map(PathBuf::from),
+        sign_key: matches.get_one::<String>("sign-key").map(PathBuf::from),
+        cost_rates: matches.get_one::<String>("cost-rates").map(PathBuf::from),
+        cost_report: matches.get_one::<String>("cost-report").map(PathBuf::from),
+        queue: matches
+            .get_one::<String>("queue")
+            .map(
+                |redis_url| -> Result<QueueOptions, Box<dyn std::error::Error>> {
+                    Ok(QueueOptions {
+                        redis_url: redis_url.clone(),
+                        name: matches.get_one::<String>("queue-name").unwrap().clone(),
+                        max_attempts: *matches.get_one::<u32>("queue-attempts").unwrap(),
+                        lease: parse_interval(matches.get_one::<String>("queue-lease").unwrap())?,
+                    })
+                },
+            )
+            .transpose()?,
+        rerun_of: None,
+    };
+
+    let urls = if let Some(file_path) = matches.get_one::<String>("file") {
+        info!("📂 Loading URLs from file: {}", file_path);
+        let contents = fs::read_to_string(file_path)?;
+        let urls: Vec<String> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.trim().starts_with('#'))
+            .map(|line| line.trim().to_string())
+            .collect();
+        info!("📋 Loaded {} URLs from file", urls.len());
+        urls
+    } else if let Some(url) = matches.get_one::<String>("url") {
+        info!("🎯 Single URL mode: {}", url);
+        vec![url.clone()]
+    } else if options.queue.is_some() {
+        // Work on what other processes queued
+        Vec::new()
+    } else {
+        warn!("⚠️  No URL or file specified. Use --help for usage information.");
+        return Ok(());
+    };
+
+    if urls.is_empty() && options.queue.is_none() {
+        warn!("⚠️  No URLs to scrape");
+        return Ok(());
+    }
+
+    let store = open_state_store(state_dir).await;
+    run_scrape(urls, options, store.as_ref()).await?;
 
     Ok(())
 }
@@ -6,16 +6,47 @@ use tracing_subscriber::{
     EnvFilter,
 };
 
+mod analyze;
+mod auth;
+mod backfill;
+mod blocks;
 mod cli;
+mod control;
+mod daemon;
+mod discover;
+mod download;
+mod exports;
+mod failures;
+mod guardrails;
+mod jobs;
+mod oidc;
+mod output;
+mod probe;
+mod progress;
+mod quarantine;
+mod rate_history;
+mod recipe;
+mod reprocess;
+mod rerun;
+mod schedule;
+mod serp;
+mod shutdown;
+mod sites;
+mod snapshot;
+mod tenants;
+mod tokens;
+mod workflow;
 
-fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
+fn setup_logging(default_level: LevelFilter) -> Result<(), Box<dyn std::error::Error>> {
     let filter = EnvFilter::builder()
-        .with_default_directive(LevelFilter::INFO.into())
+        .with_default_directive(default_level.into())
         .from_env_lossy();
 
+    // Logs go to stderr so they never mix with `--json-progress` lines on stdout,
+    // printed above the progress bar while one is shown
     tracing_subscriber::registry()
         .with(filter)
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(progress::LogWriter))
         .init();
     
     Ok(())
@@ -23,12 +54,21 @@ fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    setup_logging()?;
+    let matches = cli::command().get_matches();
+    let progress_mode = cli::progress_mode(&matches);
+    let default_level = if matches.subcommand_name() == Some("daemon") {
+        LevelFilter::INFO
+    } else {
+        progress_mode.default_log_level()
+    };
+    setup_logging(default_level)?;
     
-    println!("🕸️  Swoop CLI - High-Performance Web Scraper");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    if progress_mode.is_interactive() && matches.subcommand().is_none() {
+        println!("🕸️  Swoop CLI - High-Performance Web Scraper");
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    }
     
-    cli::run_cli().await?;
+    cli::run_cli(matches).await?;
     
     Ok(())
 }
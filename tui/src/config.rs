@@ -0,0 +1,61 @@
+//! Config file of the dashboard
+//!
+//! `swoop-tui` reads `--config FILE`, or `<state-dir>/tui.toml` when it
+//! exists. See [`crate::theme`] for `theme` and `colors`, and
+//! [`crate::keymap`] for the `[keys]` table.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{
+    keymap::Keymap,
+    theme::{ColorSupport, Theme},
+};
+
+/// Config file read from the state directory when `--config` isn't given
+pub const DEFAULT_CONFIG_FILE: &str = "tui.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    theme: Option<String>,
+    colors: Option<String>,
+    #[serde(default)]
+    keys: BTreeMap<String, Vec<String>>,
+}
+
+/// Settings of the dashboard
+#[derive(Debug, Clone, Default)]
+pub struct TuiConfig {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Colors the terminal shows, when not detected
+    pub colors: Option<ColorSupport>,
+}
+
+impl TuiConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let theme = match &file.theme {
+            Some(name) => Theme::named(name).ok_or_else(|| {
+                format!(
+                    "unknown theme '{}', expected one of {}",
+                    name,
+                    Theme::NAMES.join(", ")
+                )
+            })?,
+            None => Theme::default(),
+        };
+        Ok(Self {
+            keymap: Keymap::with_overrides(&file.keys)?,
+            theme,
+            colors: file.colors.as_deref().map(str::parse).transpose()?,
+        })
+    }
+}
@@ -0,0 +1,291 @@
+//! Daemon control protocol
+//!
+//! Shared message types for the local HTTP control interface exposed by
+//! `swoop daemon start`, plus a small client used by the CLI subcommands and by
+//! the TUI when it attaches to a running daemon.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Default address the daemon listens on
+pub const DEFAULT_DAEMON_ADDR: &str = "127.0.0.1:7878";
+
+/// Environment variable holding the API key for a daemon with tenants
+pub const API_KEY_ENV: &str = "SWOOP_API_KEY";
+
+type ControlResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Request to queue a new job on the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRequest {
+    pub name: Option<String>,
+    pub urls: Vec<String>,
+}
+
+/// Acknowledgement for a submitted job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobAccepted {
+    pub id: u64,
+    pub queued: usize,
+}
+
+/// Lifecycle state of a daemon job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+}
+
+/// Progress of a single job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub tenant: String,
+    pub state: JobState,
+    pub total: usize,
+    pub completed: usize,
+    pub successful: usize,
+    pub failed: usize,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Snapshot of the daemon engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    /// Tenant the status is scoped to
+    #[serde(default)]
+    pub tenant: String,
+    pub paused: bool,
+    pub uptime_secs: u64,
+    pub concurrency: usize,
+    /// Rate profile applied to every job, if any
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub queued: usize,
+    pub in_flight: usize,
+    pub completed: u64,
+    pub successful: u64,
+    pub failed: u64,
+    pub bytes_processed: u64,
+    pub jobs: Vec<JobProgress>,
+}
+
+/// Severity of a daemon log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlLogLevel {
+    Info,
+    Warning,
+    Error,
+    Success,
+}
+
+/// Log line from the daemon's in-memory log ring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub level: ControlLogLevel,
+    pub message: String,
+    /// Tenant the line is about; `None` for engine-wide lines
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+/// Outcome of a single fetched URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultRecord {
+    pub seq: u64,
+    pub job_id: u64,
+    #[serde(default)]
+    pub tenant: String,
+    pub url: String,
+    pub timestamp: DateTime<Utc>,
+    pub success: bool,
+    pub status_code: Option<u16>,
+    pub response_time: u64,
+    pub content_length: usize,
+    pub error: Option<String>,
+    /// Redirect target or canonical link, when it differs from `url`
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+}
+
+/// Outcome of one health check, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    /// Degraded, but the daemon can still take work
+    Warn,
+    Fail,
+}
+
+/// One component checked by `/healthz` or `/readyz`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Answer of `/healthz` and `/readyz`; a failed check makes it a 503
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Worst status of the checks
+    pub status: CheckStatus,
+    pub uptime_secs: u64,
+    pub queued: usize,
+    pub in_flight: usize,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    pub fn is_ok(&self) -> bool {
+        self.status != CheckStatus::Fail
+    }
+}
+
+/// HTTP client for the daemon control interface
+#[derive(Debug, Clone)]
+pub struct ControlClient {
+    base_url: String,
+    http: reqwest::Client,
+    api_key: Option<String>,
+}
+
+impl ControlClient {
+    /// Create a client for a daemon at `addr` (`host:port` or a full `http://` URL),
+    /// authenticated with the key in `SWOOP_API_KEY` if set
+    pub fn new(addr: &str) -> ControlResult<Self> {
+        let base_url = if addr.starts_with("http://") || addr.starts_with("https://") {
+            addr.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", addr.trim_end_matches('/'))
+        };
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()?;
+
+        let api_key = std::env::var(API_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty());
+
+        Ok(Self {
+            base_url,
+            http,
+            api_key,
+        })
+    }
+
+    /// Authenticate as the tenant `api_key` belongs to
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn submit(&self, request: &JobRequest) -> ControlResult<JobAccepted> {
+        let response = self
+            .request(reqwest::Method::POST, format!("{}/jobs", self.base_url))
+            .json(request)
+            .send()
+            .await?;
+        let response = check(response).await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn pause(&self) -> ControlResult<DaemonStatus> {
+        self.post_status("pause").await
+    }
+
+    pub async fn resume(&self) -> ControlResult<DaemonStatus> {
+        self.post_status("resume").await
+    }
+
+    pub async fn status(&self) -> ControlResult<DaemonStatus> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/status", self.base_url))
+            .send()
+            .await?;
+        let response = check(response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Fetch log lines with a sequence number greater than `since`
+    pub async fn logs(&self, since: u64) -> ControlResult<Vec<LogLine>> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/logs", self.base_url))
+            .query(&[("since", since)])
+            .send()
+            .await?;
+        let response = check(response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Fetch URL results with a sequence number greater than `since`
+    pub async fn results(&self, since: u64) -> ControlResult<Vec<ResultRecord>> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/results", self.base_url))
+            .query(&[("since", since)])
+            .send()
+            .await?;
+        let response = check(response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Ask whether the daemon is ready for work; a daemon that isn't still answers
+    pub async fn readiness(&self) -> ControlResult<HealthReport> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/readyz", self.base_url))
+            .send()
+            .await?;
+        // Not ready is a 503 with the report as its body
+        if response.status() != reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            response.error_for_status_ref()?;
+        }
+        Ok(response.json().await?)
+    }
+
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, url);
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    async fn post_status(&self, action: &str) -> ControlResult<DaemonStatus> {
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                format!("{}/{}", self.base_url, action),
+            )
+            .send()
+            .await?;
+        let response = check(response).await?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Turn an error status into an error carrying the daemon's explanation
+async fn check(response: reqwest::Response) -> ControlResult<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.text().await.unwrap_or_default();
+    if message.is_empty() {
+        Err(format!("Daemon answered {}", status).into())
+    } else {
+        Err(format!("Daemon answered {}: {}", status, message.trim()).into())
+    }
+}
@@ -0,0 +1,2502 @@
+//! Background daemon mode
+//!
+//! `swoop daemon start` runs the scraping engine as a long-lived process and
+//! exposes a local HTTP control interface:
+//! - `POST /jobs` queue a job (list of seed URLs)
+//! - `POST /pause`, `POST /resume` toggle the engine
+//! - `GET /status` engine and per-job progress
+//! - `GET /logs?since=N`, `GET /results?since=N` tail the log and result rings
+//! - `GET /metrics` counters, latency/size histograms and HTTP client pool stats in the Prometheus text format
+//! - `GET /schedule` re-crawl schedule of the tracked URLs, soonest due first
+//! - `POST /graphql` query stored content and job history (`GET` serves a playground)
+//! - `GET /snapshots/{id}` a stored page as a sanitized snapshot that runs no
+//!   scripts (see [`scrapers::sanitize`])
+//! - `GET /healthz` liveness: the engine is running
+//! - `GET /readyz` readiness: state directory, proxy pool, browser pool and
+//!   queue depth; `503` with the failing checks while not ready to take work
+//!
+//! With `--webdriver`, URLs whose policy requires a browser are rendered
+//! through that WebDriver server; otherwise they fail. With
+//! `--firefox-webdriver`, domains with `browser_engine = "firefox"` are
+//! rendered through that geckodriver server instead.
+//!
+//! With `--recrawl`, every page the daemon fetches is tracked for re-crawling:
+//! its change history sets how often it is fetched again (see
+//! [`storage::freshness`]) and due URLs are queued as `recrawl` jobs.
+//!
+//! On SIGINT or SIGTERM the daemon stops taking URLs, gives the fetches in
+//! flight a grace period and checkpoints every unfinished job, so
+//! `swoop jobs resume` can finish it.
+//!
+//! With `--auth`, `--tenants` or `--oidc-issuer`, control requests need a
+//! bearer token whose role allows them, and are audited; see [`crate::auth`].
+//!
+//! Each finished job leaves a cost report in the state directory of its
+//! tenant, priced with `--cost-rates` or `./costs.toml`; `swoop jobs cost`
+//! shows it.
+//!
+//! The remaining `swoop daemon` subcommands are thin clients for that interface.
+
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+use axum::{
+    extract::{FromRef, FromRequestParts, Path as RoutePath, Query, State},
+    http::request::Parts,
+    http::{header, Method, StatusCode},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+    Json, Router,
+};
+use bytes::Bytes;
+use chrono::Utc;
+use clap::{
+    builder::PossibleValuesParser, parser::ValueSource, Arg, ArgAction, ArgMatches, Command,
+};
+use scrapers::{
+    browser::{select_pool, BrowserEngine, BrowserPool},
+    extractors::{extract_canonical, extract_text_secure},
+    frontier::Frontier,
+    policy::PolicyResolver,
+    quality::{Escalation, QualityGate, QualityScore, QualityScorer},
+    rate_limiter::{HostThrottle, RateProfile},
+    sanitize::{SnapshotSanitizer, SNAPSHOT_CSP},
+    utils::{extract_domain, normalize_url, RobotsCache},
+};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use storage::{
+    audit::{AuditAction, AuditEvent},
+    checkpoint::{Checkpoint, CheckpointStore},
+    costs::{CostRates, CostReport, CostStore, ResourceUsage},
+    freshness::FreshnessPolicy,
+    graphql::{self, SwoopSchema},
+    local_store::LocalStore,
+    models::{Job, JobStatus},
+    tokens::Role,
+    FreshnessStore, JobStore, StorageBackend,
+};
+use swoop_core::{metrics::FetchHistograms, throttle::Throttle, trace::FetchTrace};
+use swoop_orchestrator::fetch::fetch_past_bot_protection;
+use tracing::{info, warn};
+
+use crate::auth::{Authenticator, Principal};
+use crate::cli::{
+    is_unchanged, load_cost_rates, load_policies, open_state_store, parse_browser_range,
+    parse_profile, parse_quality_score, resolve_policy, usage_tier, BrowserScaling,
+};
+use crate::control::{
+    CheckStatus, ControlClient, ControlLogLevel, DaemonStatus, HealthCheck, HealthReport,
+    JobAccepted, JobProgress, JobRequest, JobState, LogLine, ResultRecord, DEFAULT_DAEMON_ADDR,
+};
+use crate::guardrails::{parse_cpu_percent, GuardAction, ResourceGuard, ResourceLimits};
+use crate::oidc::{OidcConfig, OidcVerifier};
+use crate::quarantine::{stop_unsafe, UNSAFE_TAG};
+use crate::rate_history::RateLimitHistory;
+use crate::schedule::ScheduleEntry;
+use crate::shutdown::{Shutdown, GRACE_PERIOD};
+use crate::tenants::{Tenants, DEFAULT_TENANT, TENANTS_DIR};
+
+/// Number of log lines and results kept in memory for tailing
+const RING_CAPACITY: usize = 1000;
+
+/// How often `--recrawl` looks for due URLs
+const RECRAWL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Fetches in a row a proxy has to fail before `/readyz` counts it as down
+const PROXY_FAILURE_LIMIT: u32 = 5;
+
+/// URL waiting to be fetched
+#[derive(Debug, Clone)]
+struct QueuedUrl {
+    job_id: u64,
+    url: String,
+    tenant: String,
+    /// Rungs of the escalation ladder climbed after low-quality results
+    escalation: u32,
+}
+
+/// Queue, quota use and counters of one tenant
+#[derive(Debug, Default)]
+struct TenantState {
+    paused: bool,
+    queue: VecDeque<QueuedUrl>,
+    in_flight: usize,
+    /// When the tenant's rate quota lets its next fetch start
+    next_start: Option<Instant>,
+    completed: u64,
+    successful: u64,
+    failed: u64,
+    bytes_processed: u64,
+}
+
+/// What a tenant may use of the engine
+#[derive(Debug, Clone, Copy)]
+struct Quota {
+    concurrency: usize,
+    /// Time between fetch starts
+    interval: Option<Duration>,
+}
+
+/// Outcomes of the fetches sent through one proxy
+#[derive(Debug, Default)]
+struct ProxyHealth {
+    requests: u64,
+    /// Fetches that got no HTTP answer at all
+    failures: u64,
+    /// Failures since the last answer
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+impl ProxyHealth {
+    fn record(&mut self, trace: &FetchTrace) {
+        self.requests += 1;
+        if trace.status.is_some() {
+            self.consecutive_failures = 0;
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+            self.last_error = trace.error.clone();
+        }
+    }
+
+    fn is_down(&self) -> bool {
+        self.consecutive_failures >= PROXY_FAILURE_LIMIT
+    }
+}
+
+/// Mutable engine state shared between the workers and the control interface
+#[derive(Debug)]
+struct EngineState {
+    /// Queues by tenant, served in turn
+    tenants: BTreeMap<String, TenantState>,
+    /// Tenant the last URL was taken from
+    last_tenant: Option<String>,
+    in_flight: usize,
+    /// URLs being fetched, checkpointed if a shutdown cuts them off
+    running: Vec<QueuedUrl>,
+    jobs: Vec<JobProgress>,
+    next_job_id: u64,
+    completed: u64,
+    successful: u64,
+    failed: u64,
+    bytes_processed: u64,
+    /// Latency, TTFB and body size distributions of successful fetches
+    histograms: FetchHistograms,
+    logs: VecDeque<LogLine>,
+    next_log_seq: u64,
+    results: VecDeque<ResultRecord>,
+    next_result_seq: u64,
+    /// Persistent job records, keyed by daemon job ID
+    records: HashMap<u64, Job>,
+    /// URLs each unfinished job has claimed, so aliases are crawled once per job
+    frontiers: HashMap<u64, Frontier>,
+    /// Fetch outcomes by proxy, password masked
+    proxies: HashMap<String, ProxyHealth>,
+    /// Bandwidth and browser time of each unfinished job, for its cost report
+    usage: HashMap<u64, ResourceUsage>,
+}
+
+impl EngineState {
+    fn new() -> Self {
+        Self {
+            tenants: BTreeMap::from([(DEFAULT_TENANT.to_string(), TenantState::default())]),
+            last_tenant: None,
+            in_flight: 0,
+            running: Vec::new(),
+            jobs: Vec::new(),
+            next_job_id: 1,
+            completed: 0,
+            successful: 0,
+            failed: 0,
+            bytes_processed: 0,
+            histograms: FetchHistograms::new(),
+            logs: VecDeque::with_capacity(RING_CAPACITY),
+            next_log_seq: 1,
+            results: VecDeque::with_capacity(RING_CAPACITY),
+            next_result_seq: 1,
+            records: HashMap::new(),
+            frontiers: HashMap::new(),
+            proxies: HashMap::new(),
+            usage: HashMap::new(),
+        }
+    }
+
+    fn log(&mut self, level: ControlLogLevel, message: String) {
+        self.push_log(None, level, message);
+    }
+
+    /// Log a line only `tenant` and the engine-wide readers see
+    fn log_for(&mut self, tenant: &str, level: ControlLogLevel, message: String) {
+        self.push_log(Some(tenant.to_string()), level, message);
+    }
+
+    fn push_log(&mut self, tenant: Option<String>, level: ControlLogLevel, message: String) {
+        if self.logs.len() >= RING_CAPACITY {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(LogLine {
+            seq: self.next_log_seq,
+            timestamp: Utc::now(),
+            level,
+            message,
+            tenant,
+        });
+        self.next_log_seq += 1;
+    }
+
+    fn tenant_mut(&mut self, tenant: &str) -> &mut TenantState {
+        self.tenants.entry(tenant.to_string()).or_default()
+    }
+
+    /// URLs waiting across all tenants
+    fn queued(&self) -> usize {
+        self.tenants.values().map(|tenant| tenant.queue.len()).sum()
+    }
+
+    /// Take the next URL, serving the tenants with work in turn and skipping
+    /// those that are paused or at the limit of their quota
+    fn next(&mut self, quota: impl Fn(&str) -> Quota) -> Option<QueuedUrl> {
+        let now = Instant::now();
+        let names: Vec<String> = self.tenants.keys().cloned().collect();
+        // Start after the tenant served last, so a busy tenant can't starve the others
+        let start = self
+            .last_tenant
+            .as_ref()
+            .and_then(|last| names.iter().position(|name| name > last))
+            .unwrap_or(0);
+        for name in names[start..].iter().chain(&names[..start]) {
+            let quota = quota(name);
+            let tenant = self
+                .tenants
+                .get_mut(name)
+                .expect("name was taken from the map");
+            if tenant.paused
+                || tenant.queue.is_empty()
+                || tenant.in_flight >= quota.concurrency
+                || tenant.next_start.is_some_and(|next_start| next_start > now)
+            {
+                continue;
+            }
+            let item = tenant.queue.pop_front()?;
+            tenant.in_flight += 1;
+            if let Some(interval) = quota.interval {
+                tenant.next_start = Some(tenant.next_start.unwrap_or(now).max(now) + interval);
+            }
+            self.last_tenant = Some(name.clone());
+            return Some(item);
+        }
+        None
+    }
+
+    /// Earliest time a rate quota lets a waiting URL start
+    fn next_start(&self) -> Option<Instant> {
+        self.tenants
+            .values()
+            .filter(|tenant| !tenant.paused && !tenant.queue.is_empty())
+            .filter_map(|tenant| tenant.next_start)
+            .min()
+    }
+
+    /// Log lines after `since` that `tenant` may read: its own and the engine-wide ones
+    fn logs_since(&self, tenant: &str, since: u64) -> Vec<LogLine> {
+        self.logs
+            .iter()
+            .filter(|line| line.seq > since)
+            .filter(|line| {
+                line.tenant
+                    .as_deref()
+                    .map_or(true, |line_tenant| line_tenant == tenant)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Results after `since` of `tenant`'s jobs
+    fn results_since(&self, tenant: &str, since: u64) -> Vec<ResultRecord> {
+        self.results
+            .iter()
+            .filter(|record| record.seq > since && record.tenant == tenant)
+            .cloned()
+            .collect()
+    }
+
+    fn job_mut(&mut self, id: u64) -> Option<&mut JobProgress> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+}
+
+/// Scraping engine driven by the daemon
+#[derive(Debug, Clone)]
+struct Engine {
+    state: Arc<Mutex<EngineState>>,
+    concurrency: usize,
+    /// Worker slots, which the resource guard can cut down
+    slots: Arc<Throttle>,
+    /// Rate profile applied to every job
+    profile: Option<RateProfile>,
+    policies: Option<Arc<PolicyResolver>>,
+    throttle: Arc<HostThrottle>,
+    robots: Arc<RobotsCache>,
+    /// Domains that answered with 429s, shared with CLI runs through the state dir
+    rate_history: Option<Arc<Mutex<RateLimitHistory>>>,
+    started: Instant,
+    store: Option<LocalStore>,
+    /// Key and quotas of each tenant, with `--tenants`
+    tenants: Option<Arc<Tenants>>,
+    /// Job records and GraphQL data of each tenant
+    stores: HashMap<String, LocalStore>,
+    /// Re-crawl scheduling, with `--recrawl`
+    freshness: Option<FreshnessPolicy>,
+    /// Stops the workers taking URLs
+    shutdown: Shutdown,
+    /// Renders browser-only pages, with `--webdriver`
+    browser: Option<Arc<BrowserPool>>,
+    /// Pools of further engines, for domains whose `browser_engine` the
+    /// `browser` pool doesn't run, with `--firefox-webdriver`
+    engines: Vec<Arc<BrowserPool>>,
+    /// Queued URLs past which `/readyz` turns new work away
+    max_queue: Option<usize>,
+    /// Checks control requests, with `--auth`, `--tenants` or `--oidc-issuer`
+    auth: Option<Arc<Authenticator>>,
+    /// Prices of the jobs' cost reports
+    cost_rates: Arc<CostRates>,
+    /// Results requeued with an escalated strategy while they score below
+    /// the gate, with `--min-quality`
+    quality: Option<QualityGate>,
+}
+
+impl Engine {
+    fn new(
+        concurrency: usize,
+        profile: Option<RateProfile>,
+        policies: Option<Arc<PolicyResolver>>,
+        store: Option<LocalStore>,
+    ) -> Self {
+        let throttle = HostThrottle::new(profile.clone().unwrap_or_else(RateProfile::aggressive))
+            .expect("preset profiles are valid");
+        let rate_history = store
+            .as_ref()
+            .map(|store| Arc::new(Mutex::new(RateLimitHistory::load(store.root()))));
+        Self {
+            state: Arc::new(Mutex::new(EngineState::new())),
+            concurrency,
+            slots: Arc::new(Throttle::new(concurrency)),
+            profile,
+            policies,
+            throttle: Arc::new(throttle),
+            robots: Arc::new(RobotsCache::new()),
+            rate_history,
+            started: Instant::now(),
+            stores: store
+                .iter()
+                .map(|store| (DEFAULT_TENANT.to_string(), store.clone()))
+                .collect(),
+            store,
+            tenants: None,
+            freshness: None,
+            shutdown: Shutdown::new(),
+            browser: None,
+            engines: Vec::new(),
+            max_queue: None,
+            auth: None,
+            cost_rates: Arc::new(CostRates::default()),
+            quality: None,
+        }
+    }
+
+    /// Stop taking URLs once `shutdown` is requested
+    fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Track fetched pages and re-crawl them as they come due
+    fn with_recrawl(mut self, policy: FreshnessPolicy) -> Self {
+        self.freshness = Some(policy);
+        self
+    }
+
+    /// Render browser-only pages through `browser`
+    fn with_browser(mut self, browser: BrowserPool) -> Self {
+        self.browser = Some(Arc::new(browser));
+        self
+    }
+
+    /// Render the pages of domains whose `browser_engine` is `pool`'s in it
+    fn with_engine(mut self, pool: BrowserPool) -> Self {
+        self.engines.push(Arc::new(pool));
+        self
+    }
+
+    /// Every browser pool, `--webdriver`'s first
+    fn browser_pools(&self) -> impl Iterator<Item = &Arc<BrowserPool>> {
+        self.browser.iter().chain(&self.engines)
+    }
+
+    /// Report not ready while more than `max_queue` URLs wait
+    fn with_max_queue(mut self, max_queue: usize) -> Self {
+        self.max_queue = Some(max_queue);
+        self
+    }
+
+    /// Serve `tenants` instead of the default tenant, each keeping its job
+    /// records in its own store
+    fn with_tenants(mut self, tenants: Tenants, stores: HashMap<String, LocalStore>) -> Self {
+        self.state.lock().unwrap().tenants = tenants
+            .names()
+            .map(|name| (name.to_string(), TenantState::default()))
+            .collect();
+        self.tenants = Some(Arc::new(tenants));
+        self.stores = stores;
+        self
+    }
+
+    /// Requeue results scoring below `gate` with an escalated strategy
+    fn with_quality(mut self, gate: QualityGate) -> Self {
+        self.quality = Some(gate);
+        self
+    }
+
+    /// Price the jobs' cost reports at `rates`
+    fn with_cost_rates(mut self, rates: CostRates) -> Self {
+        self.cost_rates = Arc::new(rates);
+        self
+    }
+
+    /// Want a credential on control requests, checked by `auth`
+    fn with_auth(mut self, auth: Authenticator) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    fn quota(&self, tenant: &str) -> Quota {
+        let config = self
+            .tenants
+            .as_ref()
+            .and_then(|tenants| tenants.get(tenant));
+        Quota {
+            concurrency: config
+                .and_then(|config| config.concurrency)
+                .map_or(self.concurrency, |concurrency| {
+                    concurrency.min(self.concurrency)
+                }),
+            interval: config
+                .and_then(|config| config.requests_per_second)
+                .map(|rps| Duration::from_secs(1) / rps),
+        }
+    }
+
+    fn profile(&self) -> Option<&RateProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Record `event` in the audit log of an authenticating daemon
+    fn audit(&self, event: AuditEvent) {
+        if let Some(auth) = &self.auth {
+            auth.audit(event);
+        }
+    }
+
+    /// Persist a job record in the background, in its tenant's store
+    /// Keep the cost report of a finished job with the tenant's job records
+    fn save_cost_report(&self, tenant: &str, report: &CostReport) {
+        let Some(store) = self.stores.get(tenant) else {
+            return;
+        };
+        if let Err(e) = CostStore::open(store.root()).save(report) {
+            warn!(
+                "Failed to save the cost report of job {}: {:#}",
+                report.job_id, e
+            );
+        }
+    }
+
+    fn persist(&self, tenant: &str, job: Job) {
+        if let Some(store) = self.stores.get(tenant).cloned() {
+            tokio::spawn(async move {
+                if let Err(e) = store.save_job(&job).await {
+                    warn!("Failed to record job {}: {}", job.id, e);
+                }
+            });
+        }
+    }
+
+    /// Queue a job for `tenant`, refusing it if it would exceed the tenant's queue quota
+    ///
+    /// Jobs of an authenticated `submitted_by` are audited and record who that was.
+    fn submit(
+        &self,
+        tenant: &str,
+        submitted_by: Option<&str>,
+        request: JobRequest,
+    ) -> Result<JobAccepted, String> {
+        let mut frontier = Frontier::new();
+        let urls: Vec<String> = request
+            .urls
+            .iter()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty() && frontier.insert(url))
+            .collect();
+        let duplicates = request.urls.len() - urls.len();
+
+        let mut state = self.state.lock().unwrap();
+        let max_queued = self
+            .tenants
+            .as_ref()
+            .and_then(|tenants| tenants.get(tenant))
+            .and_then(|config| config.max_queued);
+        if let Some(max_queued) = max_queued {
+            let queued = state.tenant_mut(tenant).queue.len();
+            if queued + urls.len() > max_queued {
+                return Err(format!(
+                    "Tenant {} has {} URLs queued; {} more would exceed its quota of {}",
+                    tenant,
+                    queued,
+                    urls.len(),
+                    max_queued
+                ));
+            }
+        }
+        let id = state.next_job_id;
+        state.next_job_id += 1;
+        let name = request.name.unwrap_or_else(|| format!("job-{}", id));
+
+        state.jobs.push(JobProgress {
+            id,
+            name: name.clone(),
+            tenant: tenant.to_string(),
+            state: if urls.is_empty() {
+                JobState::Finished
+            } else {
+                JobState::Queued
+            },
+            total: urls.len(),
+            completed: 0,
+            successful: 0,
+            failed: 0,
+            submitted_at: Utc::now(),
+        });
+        let queued = urls.len();
+
+        let mut config = HashMap::from([
+            ("concurrency".to_string(), self.concurrency.to_string()),
+            ("source".to_string(), "daemon".to_string()),
+        ]);
+        if let Some(profile) = self.profile() {
+            config.insert("profile".to_string(), profile.name.clone());
+        }
+        if self.tenants.is_some() {
+            config.insert("tenant".to_string(), tenant.to_string());
+        }
+        if let Some(actor) = submitted_by {
+            config.insert("submitted_by".to_string(), actor.to_string());
+        }
+        let seeds = urls.clone();
+        let mut record = Job::new(name.clone(), seeds.clone(), config);
+        if urls.is_empty() {
+            record.finish(JobStatus::Completed, Default::default());
+        }
+        if let Some(actor) = submitted_by {
+            self.audit(
+                AuditEvent::new(actor, tenant, AuditAction::Submit)
+                    .with_job(&record.id, &name, queued),
+            );
+        }
+        state.records.insert(id, record.clone());
+        self.persist(tenant, record);
+
+        if !urls.is_empty() {
+            state.frontiers.insert(id, frontier);
+            state.usage.insert(id, ResourceUsage::default());
+        }
+        state
+            .tenant_mut(tenant)
+            .queue
+            .extend(urls.into_iter().map(|url| QueuedUrl {
+                job_id: id,
+                url,
+                tenant: tenant.to_string(),
+                escalation: 0,
+            }));
+        state.log_for(
+            tenant,
+            ControlLogLevel::Info,
+            format!("Job {} '{}' queued with {} URLs", id, name, queued),
+        );
+        if duplicates > 0 {
+            state.log_for(
+                tenant,
+                ControlLogLevel::Info,
+                format!("Job {} skipped {} duplicate or empty URLs", id, duplicates),
+            );
+        }
+        // The daemon can't prompt, so the aggressive-profile interlock is a warning here
+        if let (Some(true), Some(history)) = (
+            self.profile().map(RateProfile::is_aggressive),
+            &self.rate_history,
+        ) {
+            let flagged: Vec<String> = history
+                .lock()
+                .unwrap()
+                .flagged(&seeds)
+                .into_iter()
+                .map(|(domain, _)| domain.to_string())
+                .collect();
+            if !flagged.is_empty() {
+                state.log_for(
+                    tenant,
+                    ControlLogLevel::Warning,
+                    format!(
+                        "Job {} runs the aggressive profile against domains that returned 429s before: {}",
+                        id,
+                        flagged.join(", ")
+                    ),
+                );
+            }
+        }
+
+        Ok(JobAccepted { id, queued })
+    }
+
+    /// Pause or resume the fetches of `tenant`; without tenants, of the whole engine
+    fn set_paused(&self, tenant: &str, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        let tenant_state = state.tenant_mut(tenant);
+        if tenant_state.paused != paused {
+            tenant_state.paused = paused;
+            let action = if paused { "paused" } else { "resumed" };
+            if self.tenants.is_some() {
+                state.log_for(
+                    tenant,
+                    ControlLogLevel::Info,
+                    format!("Tenant {} {}", tenant, action),
+                );
+            } else {
+                state.log(ControlLogLevel::Info, format!("Engine {}", action));
+            }
+        }
+    }
+
+    /// Engine and job status as `tenant` sees it
+    fn status(&self, tenant: &str) -> DaemonStatus {
+        let mut state = self.state.lock().unwrap();
+        let jobs = state
+            .jobs
+            .iter()
+            .filter(|job| job.tenant == tenant)
+            .cloned()
+            .collect();
+        let tenant_state = state.tenant_mut(tenant);
+        DaemonStatus {
+            tenant: tenant.to_string(),
+            paused: tenant_state.paused,
+            uptime_secs: self.started.elapsed().as_secs(),
+            concurrency: self.quota(tenant).concurrency,
+            profile: self.profile().map(|profile| profile.name.clone()),
+            queued: tenant_state.queue.len(),
+            in_flight: tenant_state.in_flight,
+            completed: tenant_state.completed,
+            successful: tenant_state.successful,
+            failed: tenant_state.failed,
+            bytes_processed: tenant_state.bytes_processed,
+            jobs,
+        }
+    }
+
+    /// Prometheus text exposition of the engine counters and histograms
+    fn prometheus(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP swoop_requests_total Completed fetches by result"
+        );
+        let _ = writeln!(out, "# TYPE swoop_requests_total counter");
+        let _ = writeln!(
+            out,
+            "swoop_requests_total{{result=\"success\"}} {}",
+            state.successful
+        );
+        let _ = writeln!(
+            out,
+            "swoop_requests_total{{result=\"failure\"}} {}",
+            state.failed
+        );
+        let _ = writeln!(
+            out,
+            "# HELP swoop_bytes_processed_total Response bytes fetched"
+        );
+        let _ = writeln!(out, "# TYPE swoop_bytes_processed_total counter");
+        let _ = writeln!(out, "swoop_bytes_processed_total {}", state.bytes_processed);
+        let _ = writeln!(out, "# HELP swoop_queued_urls URLs waiting to be fetched");
+        let _ = writeln!(out, "# TYPE swoop_queued_urls gauge");
+        let _ = writeln!(out, "swoop_queued_urls {}", state.queued());
+        let _ = writeln!(out, "# HELP swoop_in_flight_requests Fetches in progress");
+        let _ = writeln!(out, "# TYPE swoop_in_flight_requests gauge");
+        let _ = writeln!(out, "swoop_in_flight_requests {}", state.in_flight);
+        if self.tenants.is_some() {
+            let _ = writeln!(
+                out,
+                "# HELP swoop_tenant_requests_total Completed fetches by tenant and result"
+            );
+            let _ = writeln!(out, "# TYPE swoop_tenant_requests_total counter");
+            for (name, tenant) in &state.tenants {
+                let _ = writeln!(
+                    out,
+                    "swoop_tenant_requests_total{{tenant=\"{}\",result=\"success\"}} {}",
+                    name, tenant.successful
+                );
+                let _ = writeln!(
+                    out,
+                    "swoop_tenant_requests_total{{tenant=\"{}\",result=\"failure\"}} {}",
+                    name, tenant.failed
+                );
+            }
+            let _ = writeln!(
+                out,
+                "# HELP swoop_tenant_bytes_processed_total Response bytes fetched by tenant"
+            );
+            let _ = writeln!(out, "# TYPE swoop_tenant_bytes_processed_total counter");
+            for (name, tenant) in &state.tenants {
+                let _ = writeln!(
+                    out,
+                    "swoop_tenant_bytes_processed_total{{tenant=\"{}\"}} {}",
+                    name, tenant.bytes_processed
+                );
+            }
+            let _ = writeln!(
+                out,
+                "# HELP swoop_tenant_queued_urls URLs waiting to be fetched by tenant"
+            );
+            let _ = writeln!(out, "# TYPE swoop_tenant_queued_urls gauge");
+            for (name, tenant) in &state.tenants {
+                let _ = writeln!(
+                    out,
+                    "swoop_tenant_queued_urls{{tenant=\"{}\"}} {}",
+                    name,
+                    tenant.queue.len()
+                );
+            }
+            let _ = writeln!(
+                out,
+                "# HELP swoop_tenant_in_flight_requests Fetches in progress by tenant"
+            );
+            let _ = writeln!(out, "# TYPE swoop_tenant_in_flight_requests gauge");
+            for (name, tenant) in &state.tenants {
+                let _ = writeln!(
+                    out,
+                    "swoop_tenant_in_flight_requests{{tenant=\"{}\"}} {}",
+                    name, tenant.in_flight
+                );
+            }
+        }
+        out.push_str(&state.histograms.render_prometheus("swoop"));
+        out.push_str(
+            &swoop_core::client::pool()
+                .stats()
+                .render_prometheus("swoop"),
+        );
+        out
+    }
+
+    /// Liveness: answering at all means the engine's state is usable
+    fn health(&self) -> HealthReport {
+        let (queued, in_flight) = match self.state.lock() {
+            Ok(state) => (state.queued(), state.in_flight),
+            Err(_) => {
+                let check = HealthCheck {
+                    name: "engine".to_string(),
+                    status: CheckStatus::Fail,
+                    detail: "engine state poisoned by a panicked worker".to_string(),
+                };
+                return health_report(self.started.elapsed().as_secs(), 0, 0, vec![check]);
+            }
+        };
+        let check = HealthCheck {
+            name: "engine".to_string(),
+            status: CheckStatus::Pass,
+            detail: if self.shutdown.is_requested() {
+                "shutting down".to_string()
+            } else {
+                "running".to_string()
+            },
+        };
+        health_report(
+            self.started.elapsed().as_secs(),
+            queued,
+            in_flight,
+            vec![check],
+        )
+    }
+
+    /// Readiness: whether the daemon should be sent more work
+    async fn readiness(&self) -> HealthReport {
+        let check = |name: &str, status: CheckStatus, detail: String| HealthCheck {
+            name: name.to_string(),
+            status,
+            detail,
+        };
+        let mut checks = Vec::new();
+
+        if self.shutdown.is_requested() {
+            checks.push(check(
+                "shutdown",
+                CheckStatus::Fail,
+                "shutting down".to_string(),
+            ));
+        }
+
+        checks.push(match &self.store {
+            Some(store) => {
+                // Job records and checkpoints are written here, so it has to take a write
+                let probe = store.root().join(".readyz");
+                let written = match tokio::fs::write(&probe, b"ok").await {
+                    Ok(()) => tokio::fs::remove_file(&probe).await,
+                    Err(e) => Err(e),
+                };
+                match written {
+                    Ok(()) => check(
+                        "storage",
+                        CheckStatus::Pass,
+                        store.root().display().to_string(),
+                    ),
+                    Err(e) => check(
+                        "storage",
+                        CheckStatus::Fail,
+                        format!("can't write to {}: {}", store.root().display(), e),
+                    ),
+                }
+            }
+            None => check(
+                "storage",
+                CheckStatus::Warn,
+                "no state directory; job history and checkpoints aren't kept".to_string(),
+            ),
+        });
+
+        let (queued, in_flight, paused, used, down) = {
+            let state = self.state.lock().unwrap();
+            let mut down: Vec<String> = state
+                .proxies
+                .iter()
+                .filter(|(_, health)| health.is_down())
+                .map(|(proxy, health)| match &health.last_error {
+                    Some(error) => format!("{} ({})", proxy, error),
+                    None => proxy.clone(),
+                })
+                .collect();
+            down.sort();
+            let used = state.proxies.len();
+            // Paused tenants don't make the daemon unready unless every tenant is
+            let paused = state.tenants.values().all(|tenant| tenant.paused);
+            (state.queued(), state.in_flight, paused, used, down)
+        };
+
+        checks.push(if used == 0 {
+            check(
+                "proxies",
+                CheckStatus::Pass,
+                "no fetches through a proxy yet".to_string(),
+            )
+        } else if down.is_empty() {
+            check(
+                "proxies",
+                CheckStatus::Pass,
+                format!("{} in use, all answering", used),
+            )
+        } else {
+            // Every proxy down means every proxied fetch fails; some is a degraded pool
+            let status = if down.len() == used {
+                CheckStatus::Fail
+            } else {
+                CheckStatus::Warn
+            };
+            check(
+                "proxies",
+                status,
+                format!(
+                    "{} of {} failed {} fetches in a row: {}",
+                    down.len(),
+                    used,
+                    PROXY_FAILURE_LIMIT,
+                    down.join(", ")
+                ),
+            )
+        });
+
+        for (index, browser) in self.browser_pools().enumerate() {
+            // Only browser-only pages need it, so a missing browser degrades rather than fails
+            let name = match index {
+                0 => "browser".to_string(),
+                _ => format!("{} browser", browser.engine().as_str()),
+            };
+            let free = format!(
+                "{} of {} browsers free",
+                browser.available(),
+                browser.max_instances()
+            );
+            checks.push(match browser.status().await {
+                Ok(status) if status.ready => check(&name, CheckStatus::Pass, free),
+                Ok(status) => check(
+                    &name,
+                    CheckStatus::Warn,
+                    format!("WebDriver not ready: {}; {}", status.message, free),
+                ),
+                Err(e) => check(
+                    &name,
+                    CheckStatus::Warn,
+                    format!("WebDriver unreachable: {:#}", e),
+                ),
+            });
+        }
+
+        let depth = format!("{} queued, {} in flight", queued, in_flight);
+        checks.push(match self.max_queue {
+            Some(max_queue) if queued > max_queue => check(
+                "queue",
+                CheckStatus::Fail,
+                format!("{}, over the limit of {}", depth, max_queue),
+            ),
+            _ if paused => check(
+                "queue",
+                CheckStatus::Warn,
+                format!("{}, engine paused", depth),
+            ),
+            _ => check("queue", CheckStatus::Pass, depth),
+        });
+
+        health_report(self.started.elapsed().as_secs(), queued, in_flight, checks)
+    }
+
+    /// Record a fetch of a tracked page in the background; `None` stands for
+    /// a body that is the same as at the previous fetch
+    fn observe(&self, url: String, body: Option<String>) {
+        let (Some(store), Some(policy)) = (self.store.clone(), self.freshness.clone()) else {
+            return;
+        };
+        tokio::spawn(async move {
+            let previous = store.get_freshness(&url).await.ok().flatten();
+            let record = match body {
+                Some(body) => {
+                    // Compare the text, so markup churn (nonces, build IDs) isn't a change
+                    let text = extract_text_secure(&body).unwrap_or(body);
+                    policy.observe(previous, &url, &text, Utc::now())
+                }
+                None => policy.observe_unchanged(previous, &url, Utc::now()),
+            };
+            if let Err(e) = store.save_freshness(&record).await {
+                warn!("Failed to record freshness of {}: {}", url, e);
+            }
+        });
+    }
+
+    /// Re-crawl schedule of every tracked URL, soonest due first
+    async fn schedule(&self) -> Vec<ScheduleEntry> {
+        let (Some(store), Some(policy)) = (&self.store, &self.freshness) else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        match store.list_freshness().await {
+            Ok(records) => records
+                .iter()
+                .map(|record| ScheduleEntry::new(policy, record, now))
+                .collect(),
+            Err(e) => {
+                warn!("Failed to read the re-crawl schedule: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Scheduler loop: queues due URLs as `recrawl` jobs, most likely changed first
+    async fn recrawl(self) {
+        let (Some(store), Some(policy)) = (self.store.clone(), self.freshness.clone()) else {
+            return;
+        };
+        loop {
+            let now = Utc::now();
+            let records = match store.list_freshness().await {
+                Ok(records) => records,
+                Err(e) => {
+                    warn!("Failed to read the re-crawl schedule: {}", e);
+                    Vec::new()
+                }
+            };
+            let mut urls = Vec::new();
+            for record in policy.due(&records, now) {
+                // Push the due time out so a failed fetch waits a full interval
+                // instead of being queued again on the next check
+                let mut record = record.clone();
+                record.next_due =
+                    now + chrono::Duration::seconds(record.effective_interval_secs() as i64);
+                if let Err(e) = store.save_freshness(&record).await {
+                    warn!("Failed to update the schedule of {}: {}", record.url, e);
+                    continue;
+                }
+                urls.push(record.url);
+            }
+            if !urls.is_empty() {
+                let request = JobRequest {
+                    name: Some("recrawl".to_string()),
+                    urls,
+                };
+                if let Err(e) = self.submit(DEFAULT_TENANT, None, request) {
+                    warn!("Failed to queue the due URLs: {}", e);
+                }
+            }
+            tokio::time::sleep(RECRAWL_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Worker loop: pulls queued URLs of the tenants that aren't paused, bounded
+    /// by the concurrency limit and the tenants' quotas
+    async fn run(self) {
+        loop {
+            let permit = tokio::select! {
+                permit = self.slots.acquire() => permit.unwrap(),
+                _ = self.shutdown.requested() => return,
+            };
+
+            let (next, next_start) = {
+                let mut state = self.state.lock().unwrap();
+                let next = state.next(|tenant| self.quota(tenant));
+                if let Some(item) = &next {
+                    state.in_flight += 1;
+                    state.running.push(item.clone());
+                    if let Some(job) = state.job_mut(item.job_id) {
+                        job.state = JobState::Running;
+                    }
+                }
+                (next, state.next_start())
+            };
+
+            let Some(item) = next else {
+                drop(permit);
+                // Wake up for a rate quota's next start rather than a whole poll later
+                let poll = Duration::from_millis(250);
+                let wait = next_start.map_or(poll, |next_start| {
+                    next_start
+                        .saturating_duration_since(Instant::now())
+                        .min(poll)
+                });
+                tokio::time::sleep(wait.max(Duration::from_millis(1))).await;
+                continue;
+            };
+
+            let engine = self.clone();
+            tokio::spawn(async move {
+                let _permit = permit;
+                engine.process(item).await;
+            });
+        }
+    }
+
+    /// Wait up to the grace period for the fetches in flight, then record
+    /// every unfinished job as interrupted with a checkpoint of its URLs left
+    async fn wind_down(&self) {
+        let deadline = Instant::now() + GRACE_PERIOD;
+        while self.state.lock().unwrap().in_flight > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        let interrupted: Vec<(Job, Vec<String>)> = {
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+            let mut records: Vec<(u64, Job)> = state.records.drain().collect();
+            records.sort_by_key(|(id, _)| *id);
+            records
+                .into_iter()
+                .map(|(id, mut record)| {
+                    let pending = state
+                        .running
+                        .iter()
+                        .chain(
+                            state
+                                .tenants
+                                .values()
+                                .flat_map(|tenant| tenant.queue.iter()),
+                        )
+                        .filter(|item| item.job_id == id)
+                        .map(|item| item.url.clone())
+                        .collect();
+                    let counts = record.counts.clone();
+                    record.finish(JobStatus::Interrupted, counts);
+                    (record, pending)
+                })
+                .collect()
+        };
+        if self.store.is_none() {
+            let left: usize = interrupted.iter().map(|(_, pending)| pending.len()).sum();
+            if left > 0 {
+                warn!(
+                    "⚠️  No state directory to checkpoint to; {} queued URLs are dropped",
+                    left
+                );
+            }
+            return;
+        }
+        for (record, pending) in interrupted {
+            let tenant = record
+                .config
+                .get("tenant")
+                .map_or(DEFAULT_TENANT, String::as_str);
+            let Some(store) = self.stores.get(tenant) else {
+                continue;
+            };
+            let checkpoints = CheckpointStore::open(store.root());
+            if let Err(e) = store.save_job(&record).await {
+                warn!("Failed to record job {}: {}", record.id, e);
+            }
+            let left = pending.len();
+            let checkpoint =
+                Checkpoint::new(&record.id, &record.name, pending, record.counts.total);
+            match checkpoints.save(&checkpoint) {
+                // A tenant's jobs are in its own state directory
+                Ok(_) if self.tenants.is_some() => info!(
+                    "💾 Job '{}' of tenant {} interrupted with {} URLs left; resume with: swoop jobs resume {} --state-dir {}",
+                    record.name,
+                    tenant,
+                    left,
+                    &record.id[..8.min(record.id.len())],
+                    store.root().display()
+                ),
+                Ok(_) => info!(
+                    "💾 Job '{}' interrupted with {} URLs left; resume with: swoop jobs resume {}",
+                    record.name,
+                    left,
+                    &record.id[..8.min(record.id.len())]
+                ),
+                Err(e) => warn!("Failed to checkpoint job {}: {:#}", record.id, e),
+            }
+        }
+    }
+
+    async fn process(&self, item: QueuedUrl) {
+        let start_time = Instant::now();
+        let domain = extract_domain(&item.url).unwrap_or_default();
+        let base_policy = resolve_policy(self.policies.as_deref(), &item.url, self.profile());
+        let ladder = match self.quality {
+            Some(gate) => {
+                let mut ladder = Escalation::ladder(&base_policy, self.browser.is_some());
+                ladder.truncate(gate.max_retries as usize);
+                ladder
+            }
+            None => Vec::new(),
+        };
+        let escalation = (item.escalation as usize)
+            .checked_sub(1)
+            .and_then(|rung| ladder.get(rung));
+        let policy = match escalation {
+            Some(escalation) => escalation.apply(&base_policy),
+            None => base_policy,
+        };
+        let _host_permit = match &policy.rate {
+            Some(rate) => Some(self.throttle.acquire_with(&domain, rate).await),
+            None => None,
+        };
+        let mut browser_time = Duration::ZERO;
+        let mut fetched = false;
+        let mut disallowed = false;
+        let (result, mut trace, vendor) = if policy.browser_required {
+            let result = match select_pool(self.browser_pools(), policy.browser_engine) {
+                Some(browser) => {
+                    let started = Instant::now();
+                    let result = render_in_browser(browser, &item.url).await;
+                    browser_time = started.elapsed();
+                    result
+                }
+                None if self.browser.is_none() => {
+                    Err("Policy requires a browser (start the daemon with --webdriver)".into())
+                }
+                None => Err(format!(
+                    "Policy requires a {} browser, which the daemon doesn't run",
+                    policy.browser_engine.unwrap_or_default().as_str()
+                )
+                .into()),
+            };
+            (result, FetchTrace::new(&item.url), None)
+        } else if policy.respect_robots && !self.robots.is_allowed(&item.url).await {
+            disallowed = true;
+            (
+                Err("Disallowed by robots.txt".into()),
+                FetchTrace::new(&item.url),
+                None,
+            )
+        } else {
+            fetched = true;
+            fetch_past_bot_protection(&item.url, &policy.headers, &policy).await
+        };
+        if let Some(vendor) = vendor {
+            trace
+                .classification
+                .push(format!("bot:{}", vendor.as_str()));
+        }
+        if let Some(escalation) = escalation {
+            trace.classification.push(escalation.as_tag());
+        }
+        // Bodies the safety filters stop fail the fetch, without a retry
+        let mut stopped = false;
+        let result = match result {
+            Ok(data) => {
+                let content_type = trace.response_headers.get("content-type").cloned();
+                match policy.safety.check(content_type.as_deref(), &data) {
+                    Some(verdict) => {
+                        stopped = true;
+                        let tag = format!("{}:{}", UNSAFE_TAG, verdict.hazard.as_str());
+                        trace.classification.push(tag);
+                        let quarantine = self.stores.get(&item.tenant);
+                        let error =
+                            stop_unsafe(quarantine, &item.url, content_type, &verdict, &data).await;
+                        Err(error.into())
+                    }
+                    None => Ok(data),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        let quality = match (self.quality, &result) {
+            (Some(_), _) if disallowed || stopped => None,
+            (Some(_), Ok(data)) => Some(QualityScorer::new().score(
+                &String::from_utf8_lossy(data),
+                &item.url,
+                trace.status,
+            )),
+            (Some(_), Err(_)) => Some(QualityScore::failed()),
+            (None, _) => None,
+        };
+        let low_quality = quality
+            .as_ref()
+            .zip(self.quality)
+            .filter(|(score, gate)| !gate.accepts(score))
+            .map(|(score, _)| score);
+        // Re-crawled pages whose body hasn't changed aren't extracted again
+        let unchanged = match (&result, &self.store) {
+            (Ok(data), Some(store)) if self.freshness.is_some() && low_quality.is_none() => {
+                is_unchanged(Some(store), &item.url, data).await
+            }
+            _ => false,
+        };
+        let response_time = start_time.elapsed().as_millis() as u64;
+        if let (Some(429), Some(history)) = (trace.status, &self.rate_history) {
+            let mut history = history.lock().unwrap();
+            history.record(&domain);
+            if let Err(e) = history.save() {
+                warn!("Failed to save rate limit history: {}", e);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(proxy) = &trace.proxy {
+            state
+                .proxies
+                .entry(proxy.clone())
+                .or_default()
+                .record(&trace);
+        }
+        if let Some(usage) = state.usage.get_mut(&item.job_id) {
+            if fetched {
+                usage.record_fetch(usage_tier(&trace), trace.bytes_received);
+            }
+            usage.record_browser(browser_time);
+        }
+        state.in_flight = state.in_flight.saturating_sub(1);
+        if let Some(index) = state
+            .running
+            .iter()
+            .position(|running| running.job_id == item.job_id && running.url == item.url)
+        {
+            state.running.swap_remove(index);
+        }
+        if let Some(score) = low_quality {
+            // Fetched again further up the ladder, until it runs out
+            if let Some(next) = ladder.get(item.escalation as usize) {
+                state.log_for(
+                    &item.tenant,
+                    ControlLogLevel::Info,
+                    format!(
+                        "Requeued {} scoring {:.2} ({}) with {}",
+                        item.url,
+                        score.score,
+                        score.issues.join(", "),
+                        next.as_tag()
+                    ),
+                );
+                let tenant = state.tenant_mut(&item.tenant);
+                tenant.in_flight = tenant.in_flight.saturating_sub(1);
+                tenant.queue.push_back(QueuedUrl {
+                    escalation: item.escalation + 1,
+                    ..item
+                });
+                return;
+            }
+            state.log_for(
+                &item.tenant,
+                ControlLogLevel::Warning,
+                format!(
+                    "{} still scores {:.2} ({}) after {} escalations",
+                    item.url,
+                    score.score,
+                    score.issues.join(", "),
+                    item.escalation
+                ),
+            );
+        }
+        state.completed += 1;
+        let mut canonical_url = None;
+
+        let (success, content_length, error) = match result {
+            Ok(data) => {
+                state.successful += 1;
+                state.bytes_processed += data.len() as u64;
+                state.histograms.record_trace(&trace, data.len());
+                state.log_for(
+                    &item.tenant,
+                    ControlLogLevel::Success,
+                    format!(
+                        "Fetched {} ({} bytes{})",
+                        item.url,
+                        data.len(),
+                        if unchanged { ", unchanged" } else { "" }
+                    ),
+                );
+                let base = trace.final_url.as_deref().unwrap_or(&item.url);
+                let canonical = extract_canonical(&String::from_utf8_lossy(&data), base);
+                if let Some(frontier) = state.frontiers.get_mut(&item.job_id) {
+                    let duplicate_of = frontier.record(
+                        &item.url,
+                        trace.final_url.as_deref(),
+                        canonical.as_deref(),
+                    );
+                    let canonical = frontier.canonical_of(&item.url);
+                    if canonical != normalize_url(&item.url) {
+                        canonical_url = Some(canonical);
+                    }
+                    if let Some(first) = duplicate_of {
+                        state.log_for(
+                            &item.tenant,
+                            ControlLogLevel::Info,
+                            format!("{} is a duplicate of {}", item.url, first),
+                        );
+                    }
+                }
+                if self.freshness.is_some() {
+                    let body = (!unchanged).then(|| String::from_utf8_lossy(&data).into_owned());
+                    self.observe(item.url.clone(), body);
+                }
+                (true, data.len(), None)
+            }
+            Err(e) => {
+                state.failed += 1;
+                state.log_for(
+                    &item.tenant,
+                    ControlLogLevel::Error,
+                    format!("Failed to fetch {}: {}", item.url, e),
+                );
+                (false, 0, Some(e.to_string()))
+            }
+        };
+
+        let tenant = state.tenant_mut(&item.tenant);
+        tenant.in_flight = tenant.in_flight.saturating_sub(1);
+        tenant.completed += 1;
+        if success {
+            tenant.successful += 1;
+            tenant.bytes_processed += content_length as u64;
+        } else {
+            tenant.failed += 1;
+        }
+
+        let seq = state.next_result_seq;
+        state.next_result_seq += 1;
+        if state.results.len() >= RING_CAPACITY {
+            state.results.pop_front();
+        }
+        state.results.push_back(ResultRecord {
+            seq,
+            job_id: item.job_id,
+            tenant: item.tenant.clone(),
+            url: item.url,
+            timestamp: Utc::now(),
+            success,
+            status_code: success.then_some(200),
+            response_time,
+            content_length,
+            error,
+            canonical_url,
+        });
+
+        if let Some(record) = state.records.get_mut(&item.job_id) {
+            record.counts.total += 1;
+            record.counts.bytes += content_length as u64;
+            if success {
+                record.counts.successful += 1;
+            } else {
+                record.counts.failed += 1;
+            }
+        }
+
+        let mut finished = None;
+        if let Some(job) = state.job_mut(item.job_id) {
+            job.completed += 1;
+            if success {
+                job.successful += 1;
+            } else {
+                job.failed += 1;
+            }
+            if job.completed >= job.total {
+                job.state = JobState::Finished;
+                finished = Some(format!(
+                    "Job {} '{}' finished: {}/{} successful",
+                    job.id, job.name, job.successful, job.total
+                ));
+            }
+        }
+        if let Some(message) = finished {
+            state.log_for(&item.tenant, ControlLogLevel::Success, message);
+            state.frontiers.remove(&item.job_id);
+            let usage = state.usage.remove(&item.job_id).unwrap_or_default();
+            if let Some(mut record) = state.records.remove(&item.job_id) {
+                let counts = record.counts.clone();
+                record.finish(JobStatus::Completed, counts);
+                let report = CostReport::new(&record.id, &record.name, usage, &self.cost_rates);
+                state.log_for(
+                    &item.tenant,
+                    ControlLogLevel::Info,
+                    format!(
+                        "Job {} '{}' cost an estimated {:.4} {}",
+                        item.job_id, record.name, report.total, report.currency
+                    ),
+                );
+                self.save_cost_report(&item.tenant, &report);
+                self.persist(&item.tenant, record);
+            }
+        }
+    }
+}
+
+async fn render_in_browser(
+    browser: &BrowserPool,
+    url: &str,
+) -> Result<Bytes, Box<dyn std::error::Error + Send + Sync>> {
+    let page = browser.get_browser().await?.scrape_page(url).await?;
+    Ok(Bytes::from(page.html))
+}
+
+/// Who a control request comes from, from the credential it carries
+///
+/// Requests outside the caller's role are refused here, so handlers only see
+/// callers allowed to reach them.
+#[derive(Debug, Clone)]
+struct Caller(Principal);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for Caller
+where
+    Option<Arc<Authenticator>>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(auth) = Option::<Arc<Authenticator>>::from_ref(state) else {
+            return Ok(Caller(Principal::anonymous()));
+        };
+        let request = format!("{} {}", parts.method, parts.uri.path());
+        let credential = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .unwrap_or_default();
+        let principal = match auth.authenticate(credential).await {
+            Ok(principal) => principal,
+            Err(reason) => {
+                auth.audit(
+                    AuditEvent::new("unauthenticated", "-", AuditAction::Denied)
+                        .with_detail(format!("{}: {}", request, reason)),
+                );
+                return Err((StatusCode::UNAUTHORIZED, reason));
+            }
+        };
+        let needed = required_role(&parts.method, parts.uri.path());
+        if !principal.allows(needed) {
+            let message = format!("{} needs the {} role", request, needed.as_str());
+            auth.audit(
+                AuditEvent::new(&principal.actor, &principal.tenant, AuditAction::Denied)
+                    .with_detail(message.clone()),
+            );
+            return Err((StatusCode::FORBIDDEN, message));
+        }
+        Ok(Caller(principal))
+    }
+}
+
+/// Role a control route needs
+fn required_role(method: &Method, path: &str) -> Role {
+    match (method, path) {
+        (&Method::POST, "/jobs") => Role::Submit,
+        (&Method::POST, "/pause" | "/resume") => Role::Admin,
+        _ => Role::Read,
+    }
+}
+
+impl FromRef<Engine> for Option<Arc<Authenticator>> {
+    fn from_ref(engine: &Engine) -> Self {
+        engine.auth.clone()
+    }
+}
+
+/// Each tenant's store, and the GraphQL schemas over them
+#[derive(Clone)]
+struct QueryState {
+    auth: Option<Arc<Authenticator>>,
+    stores: Arc<HashMap<String, LocalStore>>,
+    schemas: Arc<HashMap<String, SwoopSchema>>,
+}
+
+impl FromRef<QueryState> for Option<Arc<Authenticator>> {
+    fn from_ref(state: &QueryState) -> Self {
+        state.auth.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SinceQuery {
+    #[serde(default)]
+    since: u64,
+}
+
+async fn submit_job(
+    State(engine): State<Engine>,
+    Caller(caller): Caller,
+    Json(request): Json<JobRequest>,
+) -> Result<(StatusCode, Json<JobAccepted>), (StatusCode, String)> {
+    let submitted_by = engine.auth.as_ref().map(|_| caller.actor.as_str());
+    match engine.submit(&caller.tenant, submitted_by, request) {
+        Ok(accepted) => Ok((StatusCode::ACCEPTED, Json(accepted))),
+        Err(message) => Err((StatusCode::TOO_MANY_REQUESTS, message)),
+    }
+}
+
+async fn pause(State(engine): State<Engine>, Caller(caller): Caller) -> Json<DaemonStatus> {
+    engine.set_paused(&caller.tenant, true);
+    engine.audit(AuditEvent::new(
+        &caller.actor,
+        &caller.tenant,
+        AuditAction::Pause,
+    ));
+    Json(engine.status(&caller.tenant))
+}
+
+async fn resume(State(engine): State<Engine>, Caller(caller): Caller) -> Json<DaemonStatus> {
+    engine.set_paused(&caller.tenant, false);
+    engine.audit(AuditEvent::new(
+        &caller.actor,
+        &caller.tenant,
+        AuditAction::Resume,
+    ));
+    Json(engine.status(&caller.tenant))
+}
+
+async fn status(State(engine): State<Engine>, Caller(caller): Caller) -> Json<DaemonStatus> {
+    Json(engine.status(&caller.tenant))
+}
+
+async fn schedule(State(engine): State<Engine>, _caller: Caller) -> Json<Vec<ScheduleEntry>> {
+    Json(engine.schedule().await)
+}
+
+async fn metrics(State(engine): State<Engine>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        engine.prometheus(),
+    )
+}
+
+async fn healthz(State(engine): State<Engine>) -> (StatusCode, Json<HealthReport>) {
+    health_response(engine.health())
+}
+
+async fn readyz(State(engine): State<Engine>) -> (StatusCode, Json<HealthReport>) {
+    health_response(engine.readiness().await)
+}
+
+fn health_report(
+    uptime_secs: u64,
+    queued: usize,
+    in_flight: usize,
+    checks: Vec<HealthCheck>,
+) -> HealthReport {
+    let status = checks
+        .iter()
+        .map(|check| check.status)
+        .max()
+        .unwrap_or(CheckStatus::Pass);
+    HealthReport {
+        status,
+        uptime_secs,
+        queued,
+        in_flight,
+        checks,
+    }
+}
+
+fn health_response(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = if report.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+async fn logs(
+    State(engine): State<Engine>,
+    Caller(Principal { tenant, .. }): Caller,
+    Query(query): Query<SinceQuery>,
+) -> Json<Vec<LogLine>> {
+    Json(
+        engine
+            .state
+            .lock()
+            .unwrap()
+            .logs_since(&tenant, query.since),
+    )
+}
+
+async fn results(
+    State(engine): State<Engine>,
+    Caller(Principal { tenant, .. }): Caller,
+    Query(query): Query<SinceQuery>,
+) -> Json<Vec<ResultRecord>> {
+    Json(
+        engine
+            .state
+            .lock()
+            .unwrap()
+            .results_since(&tenant, query.since),
+    )
+}
+
+async fn graphql_query(
+    State(state): State<QueryState>,
+    Caller(Principal { tenant, .. }): Caller,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, (StatusCode, &'static str)> {
+    let schema = state
+        .schemas
+        .get(&tenant)
+        .ok_or((StatusCode::NOT_FOUND, "No store for this tenant"))?;
+    Ok(Json(schema.execute(request).await))
+}
+
+async fn graphql_playground() -> Html<String> {
+    Html(playground_source(GraphQLPlaygroundConfig::new("/graphql")))
+}
+
+async fn snapshot(
+    State(state): State<QueryState>,
+    Caller(Principal { tenant, .. }): Caller,
+    RoutePath(id): RoutePath<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let store = state.stores.get(&tenant).ok_or((
+        StatusCode::NOT_FOUND,
+        "No store for this tenant".to_string(),
+    ))?;
+    let page = store
+        .get_content(&id)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("No stored content '{}'", id)))?;
+    let html = page.html.ok_or((
+        StatusCode::NOT_FOUND,
+        format!("No HTML stored for {}", page.url),
+    ))?;
+    let snapshot = SnapshotSanitizer::new()
+        .sanitize(&html)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // The header also sandboxes the page, which a meta tag can't
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                format!("{}; sandbox", SNAPSHOT_CSP),
+            ),
+        ],
+        snapshot.html,
+    ))
+}
+
+fn router(engine: Engine) -> Router {
+    let query = QueryState {
+        auth: engine.auth.clone(),
+        stores: Arc::new(engine.stores.clone()),
+        schemas: Arc::new(
+            engine
+                .stores
+                .iter()
+                .map(|(tenant, store)| {
+                    (
+                        tenant.clone(),
+                        graphql::build_schema(Arc::new(store.clone())),
+                    )
+                })
+                .collect(),
+        ),
+    };
+    let router = Router::new()
+        .route("/jobs", post(submit_job))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/status", get(status))
+        .route("/logs", get(logs))
+        .route("/results", get(results))
+        .route("/metrics", get(metrics))
+        .route("/schedule", get(schedule))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(engine);
+
+    // The query API needs the local store; without it only the control routes are served
+    if query.stores.is_empty() {
+        return router;
+    }
+    router.merge(
+        Router::new()
+            .route("/graphql", get(graphql_playground).post(graphql_query))
+            .route("/snapshots/:id", get(snapshot))
+            .with_state(query),
+    )
+}
+
+/// Optional engine features of `swoop daemon start`
+#[derive(Debug, Default)]
+struct ServeOptions {
+    recrawl: bool,
+    tenants: Option<Tenants>,
+    /// Want a token on control requests even without tenants or OIDC
+    auth: bool,
+    oidc: Option<OidcConfig>,
+    webdriver: Option<String>,
+    /// geckodriver server for domains rendered with Firefox
+    firefox_webdriver: Option<String>,
+    browsers: BrowserScaling,
+    max_queue: Option<usize>,
+    cost_rates: CostRates,
+    quality: Option<QualityGate>,
+    resource_limits: ResourceLimits,
+}
+
+/// Run the daemon until interrupted
+async fn serve(
+    addr: &str,
+    concurrency: usize,
+    profile: Option<RateProfile>,
+    policies: Option<Arc<PolicyResolver>>,
+    options: ServeOptions,
+    state_dir: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let recrawl = options.recrawl;
+    let store = open_state_store(state_dir).await;
+    if recrawl && store.is_none() {
+        return Err(
+            "--recrawl keeps page histories in the state directory, which is unavailable".into(),
+        );
+    }
+    if recrawl && options.tenants.is_some() {
+        return Err("--recrawl can't tell which tenant a tracked page belongs to; it doesn't go with --tenants".into());
+    }
+    let shutdown = Shutdown::on_signal();
+    let mut engine =
+        Engine::new(concurrency, profile, policies, store).with_shutdown(shutdown.clone());
+    if recrawl {
+        engine = engine.with_recrawl(FreshnessPolicy::default());
+    }
+    if let Some(webdriver_url) = options.webdriver {
+        engine = engine.with_browser(BrowserPool::new(options.browsers.config(
+            BrowserEngine::Chromium,
+            &webdriver_url,
+            concurrency,
+        )));
+    }
+    if let Some(webdriver_url) = options.firefox_webdriver {
+        engine = engine.with_engine(BrowserPool::new(options.browsers.config(
+            BrowserEngine::Firefox,
+            &webdriver_url,
+            concurrency,
+        )));
+    }
+    if let Some(max_queue) = options.max_queue {
+        engine = engine.with_max_queue(max_queue);
+    }
+    engine = engine.with_cost_rates(options.cost_rates);
+    if let Some(gate) = options.quality {
+        engine = engine.with_quality(gate);
+    }
+    if let Some(tenants) = options.tenants {
+        let mut stores = HashMap::new();
+        if engine.store.is_some() {
+            for name in tenants.names() {
+                let dir = Path::new(state_dir).join(TENANTS_DIR).join(name);
+                if let Some(store) = open_state_store(&dir.to_string_lossy()).await {
+                    stores.insert(name.to_string(), store);
+                }
+            }
+        }
+        info!("👥 Serving {} tenants", tenants.names().count());
+        engine = engine.with_tenants(tenants, stores);
+    }
+    if options.auth || options.oidc.is_some() || engine.tenants.is_some() {
+        let mut auth = Authenticator::new(engine.tenants.clone(), Path::new(state_dir));
+        if let Some(config) = options.oidc {
+            let verifier = OidcVerifier::discover(config).await?;
+            info!("🔐 Accepting OIDC tokens of {}", verifier.issuer());
+            auth = auth.with_oidc(verifier);
+        }
+        info!("🔐 Control requests need a token; issue them with `swoop tokens issue`");
+        engine = engine.with_auth(auth);
+    }
+    let profile = engine
+        .profile()
+        .map(|profile| format!(" and the {} rate profile", profile.name))
+        .unwrap_or_default();
+    engine.state.lock().unwrap().log(
+        ControlLogLevel::Info,
+        format!("Daemon started with concurrency {}{}", concurrency, profile),
+    );
+    if options.resource_limits.is_set() {
+        let mut guard = ResourceGuard::new(options.resource_limits)
+            .with_throttle("concurrency", engine.slots.clone());
+        if let Some(browser) = &engine.browser {
+            guard = guard.with_throttle("browsers", browser.throttle());
+        }
+        for pool in &engine.engines {
+            guard = guard.with_throttle("firefox browsers", pool.throttle());
+        }
+        let state = engine.state.clone();
+        tokio::spawn(guard.watch(move |action| {
+            let level = match action {
+                GuardAction::Throttled { .. } => ControlLogLevel::Warning,
+                GuardAction::Recovered { .. } => ControlLogLevel::Info,
+            };
+            state.lock().unwrap().log(level, action.to_string());
+        }));
+    }
+    for browser in engine.browser_pools() {
+        browser.start_autoscaling();
+    }
+    tokio::spawn(engine.clone().run());
+    if recrawl {
+        tokio::spawn(engine.clone().recrawl());
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(
+        "🛰️  Swoop daemon listening on http://{}",
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, router(engine.clone()))
+        .with_graceful_shutdown(async move {
+            shutdown.requested().await;
+            info!("Shutdown signal received, stopping daemon");
+        })
+        .await?;
+    engine.wind_down().await;
+
+    Ok(())
+}
+
+/// `swoop daemon` command definition
+pub fn command() -> Command {
+    Command::new("daemon")
+        .about("Run the scraping engine in the background and control it")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("addr")
+                .long("addr")
+                .value_name("ADDR")
+                .help("Control interface address")
+                .default_value(DEFAULT_DAEMON_ADDR)
+                .global(true),
+        )
+        .arg(
+            Arg::new("api-key")
+                .long("api-key")
+                .value_name("KEY")
+                .help("API token or tenant key, for a daemon that checks them (default: $SWOOP_API_KEY)")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("start")
+                .about("Start the daemon in the foreground")
+                .arg(
+                    Arg::new("concurrency")
+                        .long("concurrency")
+                        .short('c')
+                        .value_name("NUM")
+                        .help("Number of concurrent requests (overrides the profile's)")
+                        .default_value("50"),
+                )
+                .arg(
+                    Arg::new("profile")
+                        .long("profile")
+                        .value_name("PROFILE")
+                        .help("Rate-limit profile applied to every job: gentle, normal or aggressive")
+                        .value_parser(PossibleValuesParser::new(
+                            RateProfile::NAMES.iter().copied(),
+                        )),
+                )
+                .arg(
+                    Arg::new("policies")
+                        .long("policies")
+                        .value_name("FILE")
+                        .help("Per-domain policy file (default: ./policies.toml if present)"),
+                )
+                .arg(
+                    Arg::new("recrawl")
+                        .long("recrawl")
+                        .help("Track fetched pages and re-crawl them at intervals learned from how often they change")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("webdriver")
+                        .long("webdriver")
+                        .value_name("URL")
+                        .help("WebDriver server for pages that need a browser (e.g. http://localhost:4444)"),
+                )
+                .arg(
+                    Arg::new("firefox-webdriver")
+                        .long("firefox-webdriver")
+                        .value_name("URL")
+                        .help("geckodriver server rendering the pages of domains with browser_engine = \"firefox\"")
+                        .requires("webdriver"),
+                )
+                .arg(
+                    Arg::new("browsers")
+                        .long("browsers")
+                        .value_name("MIN-MAX")
+                        .help("Browser sessions the pool scales between, e.g. 1-8 (default: 0 to the concurrency, at most 4)")
+                        .value_parser(parse_browser_range)
+                        .requires("webdriver"),
+                )
+                .arg(
+                    Arg::new("browser-standby")
+                        .long("browser-standby")
+                        .value_name("NUM")
+                        .help("Idle browser sessions kept ready on top of the pages waiting for one")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("0")
+                        .requires("webdriver"),
+                )
+                .arg(
+                    Arg::new("browser-idle")
+                        .long("browser-idle")
+                        .value_name("INTERVAL")
+                        .help("Time an unneeded browser session stays open, e.g. 30s or 5m")
+                        .default_value("60s")
+                        .requires("webdriver"),
+                )
+                .arg(
+                    Arg::new("tenants")
+                        .long("tenants")
+                        .value_name("FILE")
+                        .help("Serve the tenants in FILE, each with its own API key, queue, quotas and storage")
+                        .conflicts_with("recrawl"),
+                )
+                .arg(
+                    Arg::new("auth")
+                        .long("auth")
+                        .help("Want a token from `swoop tokens issue` on control requests (implied by --tenants and --oidc-issuer)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("oidc-issuer")
+                        .long("oidc-issuer")
+                        .value_name("URL")
+                        .help("Also accept JWTs signed by this OpenID Connect issuer")
+                        .requires("oidc-audience"),
+                )
+                .arg(
+                    Arg::new("oidc-audience")
+                        .long("oidc-audience")
+                        .value_name("AUD")
+                        .help("Audience the issuer's tokens have to be meant for")
+                        .requires("oidc-issuer"),
+                )
+                .arg(
+                    Arg::new("oidc-role-claim")
+                        .long("oidc-role-claim")
+                        .value_name("CLAIM")
+                        .help("Claim holding the caller's roles: submit, read or admin")
+                        .default_value("swoop_role"),
+                )
+                .arg(
+                    Arg::new("oidc-tenant-claim")
+                        .long("oidc-tenant-claim")
+                        .value_name("CLAIM")
+                        .help("Claim naming the caller's tenant")
+                        .default_value("swoop_tenant"),
+                )
+                .arg(
+                    Arg::new("max-queue")
+                        .long("max-queue")
+                        .value_name("NUM")
+                        .help("Report not ready on /readyz while more URLs than this are queued")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("cost-rates")
+                        .long("cost-rates")
+                        .value_name("FILE")
+                        .help("Prices the jobs' cost reports are made with (default: ./costs.toml if present)"),
+                )
+                .arg(
+                    Arg::new("min-quality")
+                        .long("min-quality")
+                        .value_name("SCORE")
+                        .help("Requeue results scoring below this (0-1), rendered in a browser or through another country's geo proxy")
+                        .value_parser(parse_quality_score),
+                )
+                .arg(
+                    Arg::new("quality-retries")
+                        .long("quality-retries")
+                        .value_name("NUM")
+                        .help("Escalated refetches per low-quality result")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::new("max-rss-mb")
+                        .long("max-rss-mb")
+                        .value_name("MB")
+                        .help("Cut concurrency and browser instances while the daemon uses more memory than this")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("max-cpu")
+                        .long("max-cpu")
+                        .value_name("PERCENT")
+                        .help("Cut concurrency and browser instances while the daemon uses more of the host's CPU than this")
+                        .value_parser(parse_cpu_percent),
+                ),
+        )
+        .subcommand(
+            Command::new("submit")
+                .about("Queue a job on the running daemon")
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .short('f')
+                        .value_name("FILE")
+                        .help("File containing URLs to scrape (one per line)")
+                        .conflicts_with("url"),
+                )
+                .arg(
+                    Arg::new("url")
+                        .long("url")
+                        .short('u')
+                        .value_name("URL")
+                        .help("Single URL to scrape"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Job name"),
+                ),
+        )
+        .subcommand(Command::new("pause").about("Pause the engine"))
+        .subcommand(Command::new("resume").about("Resume the engine"))
+        .subcommand(Command::new("status").about("Show engine and job status"))
+        .subcommand(Command::new("health").about("Show the readiness checks; exits non-zero when not ready"))
+        .subcommand(Command::new("results").about("Show recent per-URL results"))
+        .subcommand(
+            Command::new("logs").about("Tail daemon logs").arg(
+                Arg::new("follow")
+                    .long("follow")
+                    .short('F')
+                    .help("Keep polling for new log lines")
+                    .action(ArgAction::SetTrue),
+            ),
+        )
+}
+
+/// Dispatch a `swoop daemon` subcommand
+pub async fn run(
+    matches: &ArgMatches,
+    state_dir: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr = matches.get_one::<String>("addr").unwrap();
+    let connect = || -> Result<ControlClient, Box<dyn std::error::Error + Send + Sync>> {
+        let client = ControlClient::new(addr)?;
+        Ok(match matches.get_one::<String>("api-key") {
+            Some(api_key) => client.with_api_key(api_key),
+            None => client,
+        })
+    };
+
+    match matches.subcommand() {
+        Some(("start", sub)) => {
+            let profile = sub
+                .get_one::<String>("profile")
+                .map(|name| parse_profile(name).map_err(|e| e.to_string()))
+                .transpose()?;
+            let concurrency = match (&profile, sub.value_source("concurrency")) {
+                (Some(profile), Some(source)) if source != ValueSource::CommandLine => {
+                    profile.concurrency
+                }
+                _ => sub.get_one::<String>("concurrency").unwrap().parse()?,
+            };
+            let policies = load_policies(sub.get_one::<String>("policies").map(Path::new))?;
+            let tenants = sub
+                .get_one::<String>("tenants")
+                .map(|path| Tenants::load(Path::new(path)))
+                .transpose()?;
+            let options = ServeOptions {
+                recrawl: sub.get_flag("recrawl"),
+                tenants,
+                auth: sub.get_flag("auth"),
+                oidc: sub
+                    .get_one::<String>("oidc-issuer")
+                    .map(|issuer| OidcConfig {
+                        issuer: issuer.clone(),
+                        audience: sub.get_one::<String>("oidc-audience").unwrap().clone(),
+                        role_claim: sub.get_one::<String>("oidc-role-claim").unwrap().clone(),
+                        tenant_claim: sub.get_one::<String>("oidc-tenant-claim").unwrap().clone(),
+                    }),
+                webdriver: sub.get_one::<String>("webdriver").cloned(),
+                firefox_webdriver: sub.get_one::<String>("firefox-webdriver").cloned(),
+                browsers: BrowserScaling::from_args(sub)?,
+                max_queue: sub.get_one::<usize>("max-queue").copied(),
+                cost_rates: load_cost_rates(sub.get_one::<String>("cost-rates").map(Path::new))?,
+                quality: sub.get_one::<f64>("min-quality").map(|score| {
+                    QualityGate::new(*score)
+                        .with_max_retries(*sub.get_one::<u32>("quality-retries").unwrap())
+                }),
+                resource_limits: ResourceLimits {
+                    max_rss_bytes: sub.get_one::<u64>("max-rss-mb").map(|mb| mb * 1024 * 1024),
+                    max_cpu_percent: sub.get_one::<f32>("max-cpu").copied(),
+                },
+            };
+            serve(addr, concurrency, profile, policies, options, state_dir).await
+        }
+        Some(("submit", sub)) => {
+            let urls = if let Some(file_path) = sub.get_one::<String>("file") {
+                fs::read_to_string(file_path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string)
+                    .collect()
+            } else if let Some(url) = sub.get_one::<String>("url") {
+                vec![url.clone()]
+            } else {
+                return Err("Specify --url or --file".into());
+            };
+
+            let client = connect()?;
+            let accepted = client
+                .submit(&JobRequest {
+                    name: sub.get_one::<String>("name").cloned(),
+                    urls,
+                })
+                .await?;
+            println!(
+                "📥 Job {} queued with {} URLs",
+                accepted.id, accepted.queued
+            );
+            Ok(())
+        }
+        Some(("pause", _)) => {
+            connect()?.pause().await?;
+            println!("⏸️  Engine paused");
+            Ok(())
+        }
+        Some(("resume", _)) => {
+            connect()?.resume().await?;
+            println!("▶️  Engine resumed");
+            Ok(())
+        }
+        Some(("status", _)) => {
+            let client = connect()?;
+            let status = client.status().await?;
+            print_status(client.base_url(), &status);
+            Ok(())
+        }
+        Some(("health", _)) => {
+            let client = connect()?;
+            let report = client.readiness().await?;
+            print_health(client.base_url(), &report);
+            if report.is_ok() {
+                Ok(())
+            } else {
+                Err("Daemon is not ready".into())
+            }
+        }
+        Some(("results", _)) => {
+            let records = connect()?.results(0).await?;
+            for record in records {
+                let outcome = if record.success { "✅" } else { "❌" };
+                println!(
+                    "{} {} job #{} {} {}ms {}",
+                    record.timestamp.format("%H:%M:%S"),
+                    outcome,
+                    record.job_id,
+                    record.url,
+                    record.response_time,
+                    record.error.as_deref().unwrap_or("")
+                );
+            }
+            Ok(())
+        }
+        Some(("logs", sub)) => {
+            let client = connect()?;
+            let follow = sub.get_flag("follow");
+            let mut since = 0;
+            loop {
+                match client.logs(since).await {
+                    Ok(lines) => {
+                        for line in lines {
+                            println!(
+                                "{} [{:?}] {}",
+                                line.timestamp.format("%H:%M:%S"),
+                                line.level,
+                                line.message
+                            );
+                            since = line.seq;
+                        }
+                    }
+                    Err(e) if follow => warn!("Failed to poll daemon logs: {}", e),
+                    Err(e) => return Err(e),
+                }
+                if !follow {
+                    return Ok(());
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+fn print_status(base_url: &str, status: &DaemonStatus) {
+    let state = if status.paused {
+        "🔴 PAUSED"
+    } else {
+        "🟢 RUNNING"
+    };
+    println!("🛰️  Daemon Status: {} ({})", state, base_url);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    if !status.tenant.is_empty() && status.tenant != DEFAULT_TENANT {
+        println!("👥 Tenant: {}", status.tenant);
+    }
+    println!("⏱️  Uptime: {}s", status.uptime_secs);
+    println!("⚙️  Concurrency: {}", status.concurrency);
+    if let Some(profile) = &status.profile {
+        println!("🚦 Rate profile: {}", profile);
+    }
+    println!(
+        "📋 Queued: {} | In flight: {}",
+        status.queued, status.in_flight
+    );
+    println!(
+        "📈 Completed: {} (✅ {} / ❌ {})",
+        status.completed, status.successful, status.failed
+    );
+    println!("💾 Data Processed: {} KB", status.bytes_processed / 1024);
+    if !status.jobs.is_empty() {
+        println!("\n🗂️  Jobs:");
+        for job in &status.jobs {
+            println!(
+                "  #{:<4} {:<24} {:<9} {}/{} done, {} failed",
+                job.id,
+                job.name,
+                format!("{:?}", job.state),
+                job.completed,
+                job.total,
+                job.failed
+            );
+        }
+    }
+}
+
+fn print_health(base_url: &str, report: &HealthReport) {
+    let state = if report.is_ok() {
+        "🟢 READY"
+    } else {
+        "🔴 NOT READY"
+    };
+    println!("🩺 Daemon Health: {} ({})", state, base_url);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for check in &report.checks {
+        let mark = match check.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        };
+        println!("{} {:<9} {}", mark, check.name, check.detail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNLIMITED: Quota = Quota {
+        concurrency: usize::MAX,
+        interval: None,
+    };
+
+    fn enqueue(state: &mut EngineState, tenant: &str, urls: usize) {
+        for n in 0..urls {
+            state.tenant_mut(tenant).queue.push_back(QueuedUrl {
+                job_id: 1,
+                url: format!("https://{}.example.com/{}", tenant, n),
+                tenant: tenant.to_string(),
+                escalation: 0,
+            });
+        }
+    }
+
+    fn take(state: &mut EngineState, quota: impl Fn(&str) -> Quota + Copy) -> Option<String> {
+        state.next(quota).map(|item| item.tenant)
+    }
+
+    #[test]
+    fn serves_tenants_in_turn() {
+        let mut state = EngineState::new();
+        enqueue(&mut state, "ads", 10);
+        enqueue(&mut state, "search", 2);
+        let quota = |_: &str| UNLIMITED;
+
+        let order: Vec<_> = std::iter::from_fn(|| take(&mut state, quota)).collect();
+        assert_eq!(
+            order[..5],
+            ["ads", "search", "ads", "search", "ads"].map(String::from)
+        );
+        assert_eq!(order.len(), 12);
+
+        // A tenant queueing later is served before the busy one's next URL
+        let mut state = EngineState::new();
+        enqueue(&mut state, "ads", 10);
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+        enqueue(&mut state, "search", 1);
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+    }
+
+    #[test]
+    fn skips_paused_tenants() {
+        let mut state = EngineState::new();
+        enqueue(&mut state, "ads", 2);
+        enqueue(&mut state, "search", 2);
+        state.tenant_mut("ads").paused = true;
+        let quota = |_: &str| UNLIMITED;
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        assert_eq!(take(&mut state, quota), None);
+    }
+
+    #[test]
+    fn holds_tenants_to_their_concurrency() {
+        let mut state = EngineState::new();
+        enqueue(&mut state, "ads", 5);
+        enqueue(&mut state, "search", 5);
+        let quota = |tenant: &str| Quota {
+            concurrency: if tenant == "ads" { 1 } else { usize::MAX },
+            interval: None,
+        };
+
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        assert_eq!(state.tenants["ads"].in_flight, 1);
+
+        state.tenant_mut("ads").in_flight -= 1;
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+    }
+
+    #[test]
+    fn holds_tenants_to_their_rate() {
+        let mut state = EngineState::new();
+        enqueue(&mut state, "ads", 5);
+        enqueue(&mut state, "search", 1);
+        let interval = Duration::from_secs(3600);
+        let quota = |tenant: &str| Quota {
+            concurrency: usize::MAX,
+            interval: (tenant == "ads").then_some(interval),
+        };
+
+        let before = Instant::now();
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+        assert_eq!(take(&mut state, quota).as_deref(), Some("search"));
+        // The next start of "ads" is an interval away, nothing else is waiting
+        assert_eq!(take(&mut state, quota), None);
+        let next_start = state.next_start().unwrap();
+        assert!(next_start >= before + interval);
+
+        state.tenant_mut("ads").next_start = Some(Instant::now());
+        assert_eq!(take(&mut state, quota).as_deref(), Some("ads"));
+    }
+
+    fn result(seq: u64, tenant: &str) -> ResultRecord {
+        ResultRecord {
+            seq,
+            job_id: 1,
+            tenant: tenant.to_string(),
+            url: format!("https://{}.example.com/", tenant),
+            timestamp: Utc::now(),
+            success: true,
+            status_code: Some(200),
+            response_time: 10,
+            content_length: 100,
+            error: None,
+            canonical_url: None,
+        }
+    }
+
+    #[test]
+    fn reports_only_the_callers_results_and_logs() {
+        let mut state = EngineState::new();
+        state
+            .results
+            .extend([result(1, "ads"), result(2, "search"), result(3, "ads")]);
+        state.log_for("ads", ControlLogLevel::Info, "ads job started".to_string());
+        state.log_for(
+            "search",
+            ControlLogLevel::Info,
+            "search job started".to_string(),
+        );
+        state.log(
+            ControlLogLevel::Warning,
+            "Running low on memory".to_string(),
+        );
+
+        let seqs: Vec<_> = state
+            .results_since("ads", 0)
+            .iter()
+            .map(|record| record.seq)
+            .collect();
+        assert_eq!(seqs, [1, 3]);
+        assert_eq!(state.results_since("ads", 1).len(), 1);
+        assert!(state.results_since("shop", 0).is_empty());
+
+        let messages: Vec<_> = state
+            .logs_since("search", 0)
+            .into_iter()
+            .map(|line| line.message)
+            .collect();
+        assert_eq!(messages, ["search job started", "Running low on memory"]);
+        assert_eq!(state.logs_since("ads", 1).len(), 1);
+    }
+}
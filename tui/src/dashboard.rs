@@ -7,7 +7,7 @@ use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     symbols,
     text::{Line, Text},
     widgets::{
@@ -23,6 +23,8 @@ use std::{
 };
 use tokio::time::sleep;
 
+use crate::theme::Theme;
+
 /// Maximum number of data points to keep in memory for charts
 const MAX_DATA_POINTS: usize = 100;
 
@@ -165,6 +167,7 @@ impl Default for PerformanceMetrics {
 pub struct Dashboard {
     state: Arc<RwLock<DashboardState>>,
     should_quit: bool,
+    theme: Theme,
 }
 
 impl Dashboard {
@@ -172,9 +175,16 @@ impl Dashboard {
         Self {
             state: Arc::new(RwLock::new(DashboardState::default())),
             should_quit: false,
+            theme: Theme::default(),
         }
     }
 
+    /// Draw in `theme`'s colors instead of the default theme's
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
     /// Run the dashboard with simulated data
     pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> anyhow::Result<()> {
         // Start background data simulation
@@ -252,10 +262,10 @@ impl Dashboard {
                     .title("Swoop Advanced Web Scraper Dashboard")
                     .title_alignment(Alignment::Center),
             )
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(self.theme.text))
             .highlight_style(
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.highlight)
                     .add_modifier(Modifier::BOLD),
             )
             .select(state.active_tab);
@@ -301,7 +311,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Total Requests"),
             )
-            .gauge_style(Style::default().fg(Color::Blue))
+            .gauge_style(Style::default().fg(self.theme.running))
             .percent(((state.scraping_stats.total_requests % 1000) * 100 / 1000) as u16)
             .label(format!("{}", state.scraping_stats.total_requests));
         f.render_widget(total_requests, chunks[0]);
@@ -309,7 +319,7 @@ impl Dashboard {
         // Success Rate
         let success_rate = Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("Success Rate"))
-            .gauge_style(Style::default().fg(Color::Green))
+            .gauge_style(Style::default().fg(self.theme.success))
             .percent((state.scraping_stats.success_rate * 100.0) as u16)
             .label(format!("{:.1}%", state.scraping_stats.success_rate * 100.0));
         f.render_widget(success_rate, chunks[1]);
@@ -321,7 +331,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Evasion Rate"),
             )
-            .gauge_style(Style::default().fg(Color::Yellow))
+            .gauge_style(Style::default().fg(self.theme.highlight))
             .percent((state.anti_bot_metrics.evasion_success_rate * 100.0) as u16)
             .label(format!(
                 "{:.1}%",
@@ -336,7 +346,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("System Health"),
             )
-            .gauge_style(Style::default().fg(Color::Cyan))
+            .gauge_style(Style::default().fg(self.theme.accent))
             .percent(85) // Simulated health score
             .label("85%");
         f.render_widget(system_health, chunks[3]);
@@ -360,7 +370,7 @@ impl Dashboard {
             let dataset = Dataset::default()
                 .name("Requests/min")
                 .marker(symbols::Marker::Braille)
-                .style(Style::default().fg(Color::Green))
+                .style(Style::default().fg(self.theme.success))
                 .data(&requests_data);
 
             let chart = Chart::new(vec![dataset])
@@ -372,13 +382,13 @@ impl Dashboard {
                 .x_axis(
                     Axis::default()
                         .title("Time")
-                        .style(Style::default().fg(Color::Gray))
+                        .style(Style::default().fg(self.theme.muted))
                         .bounds([0.0, 60.0]),
                 )
                 .y_axis(
                     Axis::default()
                         .title("Requests")
-                        .style(Style::default().fg(Color::Gray))
+                        .style(Style::default().fg(self.theme.muted))
                         .bounds([0.0, 100.0]),
                 );
 
@@ -397,7 +407,7 @@ impl Dashboard {
             let dataset = Dataset::default()
                 .name("Response Time")
                 .marker(symbols::Marker::Braille)
-                .style(Style::default().fg(Color::Yellow))
+                .style(Style::default().fg(self.theme.highlight))
                 .data(&response_data);
 
             let chart = Chart::new(vec![dataset])
@@ -409,13 +419,13 @@ impl Dashboard {
                 .x_axis(
                     Axis::default()
                         .title("Time")
-                        .style(Style::default().fg(Color::Gray))
+                        .style(Style::default().fg(self.theme.muted))
                         .bounds([0.0, 60.0]),
                 )
                 .y_axis(
                     Axis::default()
                         .title("Milliseconds")
-                        .style(Style::default().fg(Color::Gray))
+                        .style(Style::default().fg(self.theme.muted))
                         .bounds([0.0, 2000.0]),
                 );
 
@@ -449,7 +459,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Scraper Status"),
             )
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(self.theme.text));
 
         f.render_widget(scraper_list, chunks[0]);
 
@@ -479,7 +489,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Anti-Bot Status"),
             )
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(self.theme.text));
 
         f.render_widget(proxy_list, chunks[1]);
     }
@@ -543,7 +553,7 @@ impl Dashboard {
         // CPU Usage
         let cpu_gauge = Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("CPU Usage"))
-            .gauge_style(Style::default().fg(Color::Red))
+            .gauge_style(Style::default().fg(self.theme.error))
             .percent((state.performance_metrics.cpu_usage * 100.0) as u16)
             .label(format!("{:.1}%", state.performance_metrics.cpu_usage * 100.0));
         f.render_widget(cpu_gauge, system_chunks[0]);
@@ -551,7 +561,7 @@ impl Dashboard {
         // Memory Usage
         let memory_gauge = Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("Memory Usage"))
-            .gauge_style(Style::default().fg(Color::Blue))
+            .gauge_style(Style::default().fg(self.theme.running))
             .percent((state.performance_metrics.memory_usage * 100.0) as u16)
             .label(format!(
                 "{:.1}%",
@@ -566,7 +576,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Network Throughput"),
             )
-            .gauge_style(Style::default().fg(Color::Green))
+            .gauge_style(Style::default().fg(self.theme.success))
             .percent((state.performance_metrics.network_throughput * 10.0) as u16)
             .label(format!(
                 "{:.1} MB/s",
@@ -599,7 +609,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Performance Details"),
             )
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(self.theme.text));
 
         f.render_widget(perf_list, chunks[1]);
     }
@@ -612,9 +622,9 @@ impl Dashboard {
             .take(20)
             .map(|log| {
                 let style = match log.level {
-                    LogLevel::Success => Style::default().fg(Color::Green),
-                    LogLevel::Info => Style::default().fg(Color::Blue),
-                    LogLevel::Debug => Style::default().fg(Color::Gray),
+                    LogLevel::Success => Style::default().fg(self.theme.success),
+                    LogLevel::Info => Style::default().fg(self.theme.accent),
+                    LogLevel::Debug => Style::default().fg(self.theme.muted),
                 };
 
                 let timestamp = log
@@ -638,7 +648,7 @@ impl Dashboard {
                     .borders(Borders::ALL)
                     .title("Recent Logs (Press 'r' to reset)"),
             )
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(self.theme.text));
 
         f.render_widget(logs_list, area);
     }
@@ -3,7 +3,7 @@
 //! This module provides the main entry point for running the dashboard
 //! as a standalone application or integrated with the TUI.
 
-use crate::dashboard::Dashboard;
+use crate::{dashboard::Dashboard, theme::Theme};
 use anyhow::Result;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -14,7 +14,7 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 
 /// Run the dashboard in full-screen mode
-pub async fn run_dashboard() -> Result<()> {
+pub async fn run_dashboard(theme: Theme) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -23,7 +23,7 @@ pub async fn run_dashboard() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create and run dashboard
-    let mut dashboard = Dashboard::new();
+    let mut dashboard = Dashboard::new().with_theme(theme);
     let result = dashboard.run(&mut terminal).await;
 
     // Restore terminal
@@ -0,0 +1,243 @@
+//! Seed discovery from search results
+//!
+//! `swoop discover "query"` builds a seed URL list for a topic from Google
+//! results or a search API, optionally drops URLs the local store already
+//! knows, and writes the list to a file or queues it on a running daemon.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{collections::HashSet, fs};
+
+use scrapers::{
+    discovery::{self, SearchApi, Seed, BRAVE_SEARCH_URL},
+    platforms::serp::{SerpQuery, SerpScraper},
+    utils::normalize_url,
+    ScraperConfig,
+};
+use storage::{local_store::LocalStore, FreshnessStore};
+
+use crate::control::{ControlClient, JobRequest, DEFAULT_DAEMON_ADDR};
+
+/// Organic results on one Google result page
+const SERP_PAGE_SIZE: usize = 10;
+
+/// Most Google result pages fetched for one query
+const MAX_SERP_PAGES: usize = 50;
+
+/// Environment variable holding the search API key
+const API_KEY_ENV: &str = "SWOOP_SEARCH_API_KEY";
+
+/// `swoop discover` command definition
+pub fn command() -> Command {
+    Command::new("discover")
+        .about("Build a seed URL list for a topic from search results")
+        .arg(
+            Arg::new("query")
+                .value_name("QUERY")
+                .help("Search query describing the topic")
+                .required(true),
+        )
+        .arg(
+            Arg::new("source")
+                .long("source")
+                .value_name("SOURCE")
+                .help("Where results come from: serp (Google result pages) or api (a JSON search API)")
+                .value_parser(["serp", "api"])
+                .default_value("serp"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_name("NUM")
+                .help("Maximum number of seed URLs")
+                .default_value("100"),
+        )
+        .arg(
+            Arg::new("lang")
+                .long("lang")
+                .value_name("CODE")
+                .help("Interface language for --source serp, e.g. 'en'"),
+        )
+        .arg(
+            Arg::new("country")
+                .long("country")
+                .value_name("CODE")
+                .help("Country to search from for --source serp, e.g. 'us'"),
+        )
+        .arg(
+            Arg::new("api-url")
+                .long("api-url")
+                .value_name("TEMPLATE")
+                .help("Search API URL with {query}, {count} and {page} placeholders (default: Brave Search)"),
+        )
+        .arg(
+            Arg::new("api-key")
+                .long("api-key")
+                .value_name("KEY")
+                .help("Search API key (default: $SWOOP_SEARCH_API_KEY)"),
+        )
+        .arg(
+            Arg::new("api-key-header")
+                .long("api-key-header")
+                .value_name("HEADER")
+                .help("Header the API key is sent in")
+                .default_value("X-Subscription-Token"),
+        )
+        .arg(
+            Arg::new("results-path")
+                .long("results-path")
+                .value_name("POINTER")
+                .help("JSON pointer to the API's result array; each result needs a 'url' field")
+                .default_value("/web/results"),
+        )
+        .arg(
+            Arg::new("dedupe")
+                .long("dedupe")
+                .action(ArgAction::SetTrue)
+                .help("Skip URLs already in the local store or the re-crawl schedule"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FILE")
+                .help("Write the seed URLs to FILE, one per line"),
+        )
+        .arg(
+            Arg::new("submit")
+                .long("submit")
+                .action(ArgAction::SetTrue)
+                .help("Queue the seeds as a job on the running daemon"),
+        )
+        .arg(
+            Arg::new("addr")
+                .long("addr")
+                .value_name("ADDR")
+                .help("Daemon control address for --submit")
+                .default_value(DEFAULT_DAEMON_ADDR),
+        )
+}
+
+/// Run `swoop discover`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let query = matches.get_one::<String>("query").unwrap();
+    let limit: usize = matches.get_one::<String>("limit").unwrap().parse()?;
+    let source = matches.get_one::<String>("source").unwrap();
+
+    println!("🧭 Discovering seeds for \"{}\" via {}", query, source);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    let mut seeds = match source.as_str() {
+        "api" => {
+            let key = matches
+                .get_one::<String>("api-key")
+                .cloned()
+                .or_else(|| std::env::var(API_KEY_ENV).ok())
+                .ok_or_else(|| format!("--source api needs --api-key or ${}", API_KEY_ENV))?;
+            let mut api = SearchApi::new(
+                matches
+                    .get_one::<String>("api-url")
+                    .map(String::as_str)
+                    .unwrap_or(BRAVE_SEARCH_URL),
+                matches.get_one::<String>("results-path").unwrap(),
+            )
+            .with_auth(matches.get_one::<String>("api-key-header").unwrap(), &key);
+            api.timeout = std::time::Duration::from_secs(ScraperConfig::default().timeout_secs);
+            api.search(query, limit).await.map_err(|e| e.to_string())?
+        }
+        _ => {
+            let mut serp_query = SerpQuery::new(query);
+            if let Some(language) = matches.get_one::<String>("lang") {
+                serp_query = serp_query.with_language(language);
+            }
+            if let Some(country) = matches.get_one::<String>("country") {
+                serp_query = serp_query.with_country(country);
+            }
+            let pages = limit.div_ceil(SERP_PAGE_SIZE).clamp(1, MAX_SERP_PAGES);
+            let results = SerpScraper::new(ScraperConfig::default())
+                .search(&serp_query, pages as u32)
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Some(block) = results.blocked {
+                tracing::warn!(
+                    "🚫 {} after {} page(s); keeping the results fetched so far",
+                    block,
+                    results.pages.len()
+                );
+            }
+            discovery::from_serp(&results)
+        }
+    };
+    seeds.truncate(limit);
+    let found = seeds.len();
+
+    let known = if matches.get_flag("dedupe") {
+        known_urls(state_dir).await?
+    } else {
+        HashSet::new()
+    };
+    let (seeds, dropped) = discovery::dedupe(seeds, &known);
+    print_seeds(&seeds);
+    println!(
+        "\n🌱 {} seed(s) from {} result(s), {} duplicate or already known",
+        seeds.len(),
+        found,
+        dropped
+    );
+
+    let urls: Vec<String> = seeds.into_iter().map(|seed| seed.url).collect();
+    if let Some(path) = matches.get_one::<String>("output") {
+        let mut contents = urls.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)?;
+        println!("💾 Seeds saved to: {}", path);
+    }
+    if matches.get_flag("submit") {
+        if urls.is_empty() {
+            println!("📭 Nothing to queue");
+            return Ok(());
+        }
+        let client = ControlClient::new(matches.get_one::<String>("addr").unwrap())
+            .map_err(|e| e.to_string())?;
+        let accepted = client
+            .submit(&JobRequest {
+                name: Some(format!("discover: {}", query)),
+                urls,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "📥 Job {} queued with {} URLs",
+            accepted.id, accepted.queued
+        );
+    }
+    Ok(())
+}
+
+/// Normalized URLs of stored documents and tracked re-crawl URLs
+async fn known_urls(state_dir: &str) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+    let stored = store.list_content().await?.into_iter().map(|doc| doc.url);
+    let tracked = store
+        .list_freshness()
+        .await?
+        .into_iter()
+        .map(|record| record.url);
+    Ok(stored
+        .chain(tracked)
+        .map(|url| normalize_url(&url))
+        .collect())
+}
+
+fn print_seeds(seeds: &[Seed]) {
+    for seed in seeds {
+        println!(
+            "  {:>3}. {}{}",
+            seed.rank,
+            seed.url,
+            seed.title
+                .as_deref()
+                .map(|title| format!(" — {}", title))
+                .unwrap_or_default()
+        );
+    }
+}
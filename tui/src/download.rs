@@ -0,0 +1,107 @@
+//! Large file downloads
+//!
+//! `swoop download` fetches videos, archives and other large files with
+//! [`Downloader`]: parallel range segments, resume after an interruption,
+//! checksum verification and an optional bandwidth cap.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::path::PathBuf;
+use swoop_core::download::{file_name, DownloadOptions, Downloader};
+
+/// `swoop download` command definition
+pub fn command() -> Command {
+    Command::new("download")
+        .about("Download large files in parallel segments, resuming interrupted downloads")
+        .arg(
+            Arg::new("url")
+                .required(true)
+                .action(ArgAction::Append)
+                .help("URLs to download"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("DIR")
+                .help("Directory the files are saved to")
+                .default_value("."),
+        )
+        .arg(
+            Arg::new("segments")
+                .long("segments")
+                .value_name("NUM")
+                .help("Parallel segments per file, when the server accepts ranges")
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("max-rate")
+                .long("max-rate")
+                .value_name("KIB_PER_SEC")
+                .help("Bandwidth cap per file, in KiB/s"),
+        )
+        .arg(
+            Arg::new("sha256")
+                .long("sha256")
+                .value_name("HEX")
+                .help("Expected SHA-256 of the file; only with a single URL"),
+        )
+}
+
+/// Run `swoop download`
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let urls: Vec<&String> = matches.get_many::<String>("url").unwrap().collect();
+    let sha256 = matches.get_one::<String>("sha256").cloned();
+    if sha256.is_some() && urls.len() > 1 {
+        return Err("--sha256 needs a single URL".into());
+    }
+    let output = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    std::fs::create_dir_all(&output)?;
+    let downloader = Downloader::new(DownloadOptions {
+        segments: matches.get_one::<String>("segments").unwrap().parse()?,
+        max_bytes_per_sec: matches
+            .get_one::<String>("max-rate")
+            .map(|rate| rate.parse::<u64>().map(|kib| kib * 1024))
+            .transpose()?,
+        sha256,
+        ..DownloadOptions::default()
+    })?;
+
+    let mut failed = 0;
+    for url in urls {
+        let dest = output.join(file_name(url));
+        match downloader.download(url, &dest).await {
+            Ok(report) => {
+                let resumed = if report.resumed_bytes > 0 {
+                    format!(", resumed at {:.1} MB", megabytes(report.resumed_bytes))
+                } else {
+                    String::new()
+                };
+                println!(
+                    "✅ {} ({:.1} MB in {:.1}s, {} segment(s){})",
+                    report.path.display(),
+                    megabytes(report.bytes),
+                    report.elapsed.as_secs_f64(),
+                    report.segments,
+                    resumed
+                );
+                println!("   sha256 {}", report.sha256);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ {}: {:#}", url, e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} download(s) failed; run the same command again to resume",
+            failed
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
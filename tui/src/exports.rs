@@ -0,0 +1,202 @@
+//! Encrypted and signed exports
+//!
+//! `swoop exports keygen` creates the keys `--encrypt-key` and `--sign-key`
+//! take, `verify` checks an export against its manifest and signature, and
+//! `decrypt` restores an encrypted export file.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use storage::sealing::{self, ExportKey, SignatureCheck, ENCRYPTED_EXTENSION};
+
+/// AES-256 key file written by `keygen`
+const EXPORT_KEY_FILE: &str = "export.key";
+
+/// Ed25519 signing key file written by `keygen`
+const SIGN_KEY_FILE: &str = "sign.key";
+
+/// Public half of the signing key, to hand to whoever verifies the exports
+const PUBLIC_KEY_FILE: &str = "sign.pub";
+
+/// `swoop exports` command definition
+pub fn command() -> Command {
+    Command::new("exports")
+        .about("Manage export encryption and signing keys, verify and decrypt exports")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("keygen")
+                .about("Create an encryption key and a signing key pair")
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .short('d')
+                        .value_name("DIR")
+                        .help("Directory the key files are written to")
+                        .default_value("."),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check an export's files against its manifest and signature")
+                .arg(Arg::new("manifest").required(true).help("Manifest file of the export"))
+                .arg(
+                    Arg::new("public-key")
+                        .long("public-key")
+                        .value_name("HEX|FILE")
+                        .help("Public key the manifest must be signed with"),
+                ),
+        )
+        .subcommand(
+            Command::new("decrypt")
+                .about("Decrypt encrypted export files")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .help("Encrypted files (.enc)"),
+                )
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("Key file the files were encrypted with"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("DIR")
+                        .help("Directory the decrypted files are written to (default: next to each file)"),
+                ),
+        )
+}
+
+/// Dispatch a `swoop exports` subcommand
+pub fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("keygen", sub)) => keygen(Path::new(sub.get_one::<String>("dir").unwrap())),
+        Some(("verify", sub)) => {
+            let manifest = Path::new(sub.get_one::<String>("manifest").unwrap());
+            let public_key = sub.get_one::<String>("public-key").map(|key| {
+                // Either the key itself or the sign.pub file keygen wrote
+                fs::read_to_string(key).unwrap_or_else(|_| key.clone())
+            });
+            verify(manifest, public_key.as_deref())
+        }
+        Some(("decrypt", sub)) => {
+            let key = ExportKey::load(sub.get_one::<String>("key").unwrap())?;
+            let output = sub.get_one::<String>("output").map(PathBuf::from);
+            if let Some(dir) = &output {
+                fs::create_dir_all(dir)?;
+            }
+            for file in sub.get_many::<String>("file").unwrap() {
+                let file = Path::new(file);
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let name = name
+                    .strip_suffix(&format!(".{}", ENCRYPTED_EXTENSION))
+                    .ok_or_else(|| {
+                        format!("{} is not an encrypted export (.enc)", file.display())
+                    })?;
+                let target = match &output {
+                    Some(dir) => dir.join(name),
+                    None => file.with_file_name(name),
+                };
+                if let Err(e) = sealing::decrypt_file(&key, file, &target) {
+                    let _ = fs::remove_file(&target);
+                    return Err(e.into());
+                }
+                println!("🔓 {} -> {}", file.display(), target.display());
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn keygen(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let paths = [EXPORT_KEY_FILE, SIGN_KEY_FILE, PUBLIC_KEY_FILE].map(|name| dir.join(name));
+    if let Some(existing) = paths.iter().find(|path| path.exists()) {
+        return Err(format!(
+            "{} already exists; not overwriting keys",
+            existing.display()
+        )
+        .into());
+    }
+
+    let signing_key = sealing::generate_signing_key();
+    let public_key = sealing::public_key_hex(&signing_key);
+    write_secret(&paths[0], &ExportKey::generate().to_hex())?;
+    write_secret(&paths[1], &sealing::signing_key_hex(&signing_key))?;
+    fs::write(&paths[2], format!("{}\n", public_key))?;
+
+    println!("🔑 Encryption key: {} (--encrypt-key)", paths[0].display());
+    println!("🔏 Signing key: {} (--sign-key)", paths[1].display());
+    println!("📢 Public key: {}", public_key);
+    Ok(())
+}
+
+/// Write a key file readable only by its owner
+fn write_secret(path: &Path, hex: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, format!("{}\n", hex).as_bytes())
+}
+
+fn verify(manifest: &Path, public_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let verification = sealing::verify_manifest(manifest, public_key)?;
+    match verification.signature {
+        SignatureCheck::Trusted => println!("🔏 Signature valid for the given public key"),
+        SignatureCheck::SelfSigned => println!(
+            "⚠️  Signature valid for the key named in the manifest ({}); pass --public-key to check who signed it",
+            verification.manifest.public_key.as_deref().unwrap_or("-")
+        ),
+        SignatureCheck::Unsigned => println!("⚠️  Manifest is not signed"),
+    }
+    if let Some(provenance) = &verification.manifest.provenance {
+        println!(
+            "🗂️  Job {} ({}), swoop {}, {} seed(s) {}",
+            provenance.job_name,
+            provenance.job_id,
+            provenance.swoop_version,
+            provenance.seeds,
+            &provenance.seeds_sha256[..12.min(provenance.seeds_sha256.len())]
+        );
+        println!(
+            "🕐 {} to {}",
+            provenance.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            provenance.finished_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        let statuses: Vec<String> = provenance
+            .status_counts
+            .iter()
+            .map(|(status, count)| format!("{} {}", status, count))
+            .collect();
+        println!(
+            "📊 {} across {} domain(s)",
+            statuses.join(", "),
+            provenance.domain_counts.len()
+        );
+    }
+    for name in &verification.mismatched {
+        println!("❌ {}: checksum mismatch", name);
+    }
+    for name in &verification.missing {
+        println!("❌ {}: missing", name);
+    }
+    if !verification.is_intact() {
+        return Err(format!(
+            "{} of {} file(s) failed verification",
+            verification.mismatched.len() + verification.missing.len(),
+            verification.manifest.files.len()
+        )
+        .into());
+    }
+    println!("✅ {} file(s) intact", verification.manifest.files.len());
+    Ok(())
+}
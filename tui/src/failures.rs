@@ -0,0 +1,250 @@
+//! Failure triage commands
+//!
+//! `swoop failures` works on the dead-letter queue: URLs whose fetch failed
+//! every attempt, kept in `<state-dir>/dead_letters.jsonl` by local runs and
+//! in the Redis queue's dead-letter list by `--queue` runs. `report` counts
+//! them by failure class and by domain; `requeue` hands the URLs of chosen
+//! classes or domains back for another try once the cause is fixed.
+
+use clap::{builder::PossibleValuesParser, Arg, ArgAction, ArgMatches, Command};
+use std::{collections::HashSet, fs, path::Path};
+use storage::{
+    dead_letters::{DeadLetter, DeadLetterLog, FailureClass, FailureReport},
+    redis_queue::RedisQueue,
+};
+
+use crate::control::{ControlClient, JobRequest, DEFAULT_DAEMON_ADDR};
+
+/// `swoop failures` command definition
+pub fn command() -> Command {
+    let queue_args = |command: Command| {
+        command
+            .arg(
+                Arg::new("queue")
+                    .long("queue")
+                    .value_name("REDIS_URL")
+                    .help("Use the dead letters of a shared Redis queue instead of the local log"),
+            )
+            .arg(
+                Arg::new("queue-name")
+                    .long("queue-name")
+                    .value_name("NAME")
+                    .help("Queue the dead letters belong to")
+                    .requires("queue")
+                    .default_value("swoop"),
+            )
+    };
+    Command::new("failures")
+        .about("Triage URLs that failed every attempt")
+        .subcommand_required(true)
+        .subcommand(queue_args(
+            Command::new("report")
+                .about("Count dead letters by failure class and by domain")
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .value_name("N")
+                        .help("Domains to list")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("20"),
+                ),
+        ))
+        .subcommand(queue_args(
+            Command::new("requeue")
+                .about("Hand dead letters back for another try")
+                .arg(
+                    Arg::new("class")
+                        .long("class")
+                        .value_name("CLASS")
+                        .help("Only requeue this failure class; repeatable")
+                        .action(ArgAction::Append)
+                        .value_parser(PossibleValuesParser::new(
+                            FailureClass::ALL.iter().map(FailureClass::as_str),
+                        )),
+                )
+                .arg(
+                    Arg::new("domain")
+                        .long("domain")
+                        .value_name("DOMAIN")
+                        .help("Only requeue URLs of this domain; repeatable")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Write the URLs to FILE, one per line, for `--file`"),
+                )
+                .arg(
+                    Arg::new("submit")
+                        .long("submit")
+                        .action(ArgAction::SetTrue)
+                        .help("Queue the URLs as a job on the running daemon"),
+                )
+                .arg(
+                    Arg::new("addr")
+                        .long("addr")
+                        .value_name("ADDR")
+                        .help("Daemon control address for --submit")
+                        .default_value(DEFAULT_DAEMON_ADDR),
+                ),
+        ))
+}
+
+/// Dispatch a `swoop failures` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("report", sub)) => {
+            let (letters, source) = match open_queue(sub).await? {
+                Some(queue) => (
+                    queue.dead_letters().await.map_err(|e| format!("{:#}", e))?,
+                    format!("queue {}", queue.name()),
+                ),
+                None => {
+                    let log = DeadLetterLog::open(Path::new(state_dir));
+                    let source = log.path().display().to_string();
+                    (log.load().map_err(|e| format!("{:#}", e))?, source)
+                }
+            };
+            print_report(
+                &FailureReport::new(&letters),
+                &source,
+                *sub.get_one::<usize>("top").unwrap(),
+            );
+            Ok(())
+        }
+        Some(("requeue", sub)) => requeue(sub, state_dir).await,
+        _ => Ok(()),
+    }
+}
+
+async fn open_queue(
+    matches: &ArgMatches,
+) -> Result<Option<RedisQueue>, Box<dyn std::error::Error>> {
+    let Some(redis_url) = matches.get_one::<String>("queue") else {
+        return Ok(None);
+    };
+    let queue = RedisQueue::connect(redis_url, matches.get_one::<String>("queue-name").unwrap())
+        .await
+        .map_err(|e| format!("{:#}", e))?;
+    Ok(Some(queue))
+}
+
+async fn requeue(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let classes: Vec<FailureClass> = matches
+        .get_many::<String>("class")
+        .into_iter()
+        .flatten()
+        .filter_map(|name| FailureClass::named(name))
+        .collect();
+    let domains: Vec<String> = matches
+        .get_many::<String>("domain")
+        .into_iter()
+        .flatten()
+        .map(|domain| domain.to_ascii_lowercase())
+        .collect();
+    let selected = |letter: &DeadLetter| {
+        (classes.is_empty() || classes.contains(&letter.class()))
+            && (domains.is_empty() || domains.contains(&letter.domain()))
+    };
+
+    // Dead letters of a shared queue go straight back into it
+    if let Some(queue) = open_queue(matches).await? {
+        let requeued = queue
+            .requeue(selected)
+            .await
+            .map_err(|e| format!("{:#}", e))?;
+        println!(
+            "🔁 Requeued {} URLs on queue {}",
+            requeued.len(),
+            queue.name()
+        );
+        return Ok(());
+    }
+
+    let output = matches.get_one::<String>("output");
+    if output.is_none() && !matches.get_flag("submit") {
+        return Err("Give --output or --submit to requeue local dead letters, or --queue for a shared queue".into());
+    }
+    let log = DeadLetterLog::open(Path::new(state_dir));
+    let urls: Vec<String> = log
+        .load()
+        .map_err(|e| format!("{:#}", e))?
+        .into_iter()
+        .filter(selected)
+        .map(|letter| letter.url)
+        .collect();
+    if urls.is_empty() {
+        println!("📭 No dead letters match");
+        return Ok(());
+    }
+
+    if let Some(path) = output {
+        let mut contents = urls.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)?;
+        println!("💾 {} URLs saved to: {}", urls.len(), path);
+    }
+    if matches.get_flag("submit") {
+        let client = ControlClient::new(matches.get_one::<String>("addr").unwrap())
+            .map_err(|e| e.to_string())?;
+        let accepted = client
+            .submit(&JobRequest {
+                name: Some("requeued failures".to_string()),
+                urls: urls.clone(),
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "📥 Job {} queued with {} URLs",
+            accepted.id, accepted.queued
+        );
+    }
+    // They are handed off; failing again puts them back in the log
+    let removed = log
+        .remove(&urls.into_iter().collect::<HashSet<String>>())
+        .map_err(|e| format!("{:#}", e))?;
+    println!("🗑️  Removed {} dead letters", removed);
+    Ok(())
+}
+
+fn print_report(report: &FailureReport, source: &str, top: usize) {
+    if report.total == 0 {
+        println!("✅ No dead letters in {}", source);
+        return;
+    }
+
+    println!("☠️  {} dead letters in {}", report.total, source);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("By class:");
+    let mut classes: Vec<(&FailureClass, &usize)> = report.by_class.iter().collect();
+    classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (class, count) in classes {
+        println!(
+            "  {:<14} {:>6}  {}",
+            class.as_str(),
+            count,
+            report.examples.get(class).map_or("", String::as_str)
+        );
+    }
+
+    println!("\nBy domain:");
+    for (domain, count) in report.top_domains(top) {
+        let mut classes: Vec<(&FailureClass, &usize)> = report.by_domain[domain].iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let classes: Vec<String> = classes
+            .into_iter()
+            .map(|(class, count)| format!("{} {}", class.as_str(), count))
+            .collect();
+        println!(
+            "  {:<30} {:>6}  {}",
+            if domain.is_empty() { "-" } else { domain },
+            count,
+            classes.join(", ")
+        );
+    }
+    if report.by_domain.len() > top {
+        println!("  … and {} more domains", report.by_domain.len() - top);
+    }
+}
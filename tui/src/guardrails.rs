@@ -0,0 +1,229 @@
+//! Resource guardrails
+//!
+//! A [`ResourceGuard`] watches the memory (RSS) and CPU use of the process
+//! and sheds load when either goes over its limit: every throttle it guards,
+//! such as the scrape concurrency and the browser pool, is halved, down to a
+//! single slot. Once use stays below 80% of the limits for a few samples in a
+//! row, the throttles are raised again a quarter of their maximum at a time.
+//! `--max-rss-mb` and `--max-cpu` set the limits of `swoop scrape`,
+//! `swoop daemon start` and `swoop-tui`.
+
+use std::{fmt, sync::Arc, thread, time::Duration};
+use swoop_core::throttle::Throttle;
+use sysinfo::System;
+use tracing::{info, warn};
+
+/// Time between two samples of the process
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Share of a limit use has to stay below before throttles are raised
+const RECOVERY_SHARE: f64 = 0.8;
+
+/// Samples in a row below the recovery share before each raise
+const RECOVERY_SAMPLES: u32 = 3;
+
+/// Samples to wait after lowering before lowering again, so that permits in
+/// use have a chance to come back and show their effect
+const SETTLE_SAMPLES: u32 = 2;
+
+const MB: u64 = 1024 * 1024;
+
+/// Ceilings on the process's resource use
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Resident memory, in bytes
+    pub max_rss_bytes: Option<u64>,
+    /// CPU, in percent of the whole host
+    pub max_cpu_percent: Option<f32>,
+}
+
+impl ResourceLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_rss_bytes.is_some() || self.max_cpu_percent.is_some()
+    }
+}
+
+/// Resource use of the process at one point
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResourceSample {
+    pub rss_bytes: u64,
+    /// CPU, in percent of the whole host
+    pub cpu_percent: f32,
+}
+
+impl ResourceSample {
+    /// A sample from what sysinfo reports for a process, whose CPU use is in
+    /// percent of a single core
+    pub fn from_process(memory: u64, cpu_usage: f32) -> Self {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        Self {
+            rss_bytes: memory,
+            cpu_percent: cpu_usage / cores as f32,
+        }
+    }
+}
+
+/// Something the guard did to its throttles
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardAction {
+    /// Limits lowered, with the resource over its limit and the new limits
+    Throttled { reason: String, limits: String },
+    /// Limits raised again
+    Recovered { limits: String },
+}
+
+impl fmt::Display for GuardAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardAction::Throttled { reason, limits } => {
+                write!(f, "{}, throttled to {}", reason, limits)
+            }
+            GuardAction::Recovered { limits } => {
+                write!(f, "Resource use is back down, raised to {}", limits)
+            }
+        }
+    }
+}
+
+/// Lowers and raises throttles as the process's resource use crosses limits
+#[derive(Debug)]
+pub struct ResourceGuard {
+    limits: ResourceLimits,
+    throttles: Vec<(&'static str, Arc<Throttle>)>,
+    calm_samples: u32,
+    settling: u32,
+}
+
+impl ResourceGuard {
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self {
+            limits,
+            throttles: Vec::new(),
+            calm_samples: 0,
+            settling: 0,
+        }
+    }
+
+    /// Guard `throttle` too, called `name` in the log
+    pub fn with_throttle(mut self, name: &'static str, throttle: Arc<Throttle>) -> Self {
+        self.throttles.push((name, throttle));
+        self
+    }
+
+    /// Take in a sample, lowering or raising the throttles if it calls for it
+    pub fn observe(&mut self, sample: ResourceSample) -> Option<GuardAction> {
+        for (_, throttle) in &self.throttles {
+            throttle.settle();
+        }
+        self.settling = self.settling.saturating_sub(1);
+
+        if let Some(reason) = self.pressure(&sample) {
+            self.calm_samples = 0;
+            if self.settling > 0 || self.throttles.iter().all(|(_, t)| t.limit() == 1) {
+                return None;
+            }
+            self.settling = SETTLE_SAMPLES;
+            for (_, throttle) in &self.throttles {
+                throttle.set_limit(throttle.limit() / 2);
+            }
+            return Some(GuardAction::Throttled {
+                reason,
+                limits: self.describe_limits(),
+            });
+        }
+
+        if !self.is_calm(&sample) {
+            self.calm_samples = 0;
+            return None;
+        }
+        self.calm_samples += 1;
+        let throttled = self.throttles.iter().any(|(_, t)| t.limit() < t.max());
+        if self.calm_samples < RECOVERY_SAMPLES || !throttled {
+            return None;
+        }
+        self.calm_samples = 0;
+        for (_, throttle) in &self.throttles {
+            let step = throttle.max().div_ceil(4);
+            throttle.set_limit(throttle.limit() + step);
+        }
+        Some(GuardAction::Recovered {
+            limits: self.describe_limits(),
+        })
+    }
+
+    /// Sample the process every [`SAMPLE_INTERVAL`] for good, logging what
+    /// the guard does and passing it to `report`
+    pub async fn watch(mut self, mut report: impl FnMut(&GuardAction) + Send) {
+        let Ok(pid) = sysinfo::get_current_pid() else {
+            warn!("⚠️  Can't tell the process ID, resource limits are not enforced");
+            return;
+        };
+        let mut system = System::new();
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            system.refresh_process(pid);
+            let Some(process) = system.process(pid) else {
+                continue;
+            };
+            let sample = ResourceSample::from_process(process.memory(), process.cpu_usage());
+            let Some(action) = self.observe(sample) else {
+                continue;
+            };
+            match &action {
+                GuardAction::Throttled { .. } => warn!("🧯 {}", action),
+                GuardAction::Recovered { .. } => info!("🧯 {}", action),
+            }
+            report(&action);
+        }
+    }
+
+    fn pressure(&self, sample: &ResourceSample) -> Option<String> {
+        if let Some(max) = self.limits.max_rss_bytes {
+            if sample.rss_bytes > max {
+                return Some(format!(
+                    "Memory at {} MB is over the {} MB limit",
+                    sample.rss_bytes / MB,
+                    max / MB
+                ));
+            }
+        }
+        match self.limits.max_cpu_percent {
+            Some(max) if sample.cpu_percent > max => Some(format!(
+                "CPU at {:.0}% is over the {:.0}% limit",
+                sample.cpu_percent, max
+            )),
+            _ => None,
+        }
+    }
+
+    fn is_calm(&self, sample: &ResourceSample) -> bool {
+        let rss_calm = self.limits.max_rss_bytes.map_or(true, |max| {
+            (sample.rss_bytes as f64) < max as f64 * RECOVERY_SHARE
+        });
+        let cpu_calm = self.limits.max_cpu_percent.map_or(true, |max| {
+            (sample.cpu_percent as f64) < max as f64 * RECOVERY_SHARE
+        });
+        rss_calm && cpu_calm
+    }
+
+    /// E.g. `concurrency 5/10, browsers 2/4`
+    fn describe_limits(&self) -> String {
+        self.throttles
+            .iter()
+            .map(|(name, throttle)| format!("{} {}/{}", name, throttle.limit(), throttle.max()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// CPU limit given to `--max-cpu`, in percent of the whole host
+pub fn parse_cpu_percent(value: &str) -> Result<f32, String> {
+    match value.trim_end_matches('%').parse::<f32>() {
+        Ok(percent) if (1.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!(
+            "Invalid CPU limit '{}', expected a percentage from 1 to 100",
+            value
+        )),
+    }
+}
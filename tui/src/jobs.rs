@@ -0,0 +1,299 @@
+//! Job history commands
+//!
+//! Every CLI scrape and daemon job is recorded as a [`Job`] in the local store.
+//! `swoop jobs` lists, inspects and re-runs those records, resumes jobs
+//! stopped by a signal from their checkpoint and shows what a job cost.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use storage::{
+    checkpoint::CheckpointStore, costs::CostStore, local_store::LocalStore, models::Job, JobStore,
+};
+
+use crate::cli::{self, ScrapeOptions};
+
+/// Upper bound on jobs scanned when resolving an ID prefix
+const MAX_JOB_SCAN: usize = 10_000;
+
+/// `swoop jobs` command definition
+pub fn command() -> Command {
+    Command::new("jobs")
+        .about("Inspect and re-run recorded crawl jobs")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("list")
+                .about("List recent jobs")
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .short('n')
+                        .value_name("NUM")
+                        .help("Maximum number of jobs to show")
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .value_name("TAG")
+                        .help("Only show jobs with this tag"),
+                ),
+        )
+        .subcommand(
+            Command::new("show").about("Show details for a job").arg(
+                Arg::new("id")
+                    .required(true)
+                    .help("Job ID or unique prefix"),
+            ),
+        )
+        .subcommand(
+            Command::new("rerun")
+                .about("Run a job again with the same seeds and configuration")
+                .arg(
+                    Arg::new("id")
+                        .required(true)
+                        .help("Job ID or unique prefix"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Name for the new run"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Skip the confirmation for aggressive jobs against rate-limited domains")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("resume")
+                .about("Finish an interrupted job: fetch the URLs its checkpoint lists")
+                .arg(
+                    Arg::new("id")
+                        .required(true)
+                        .help("Job ID or unique prefix"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Skip the confirmation for aggressive jobs against rate-limited domains")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("cost")
+                .about("Show the resources a job used and their estimated cost")
+                .arg(
+                    Arg::new("id")
+                        .required(true)
+                        .help("Job ID or unique prefix"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the report as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+}
+
+/// Dispatch a `swoop jobs` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+
+    match matches.subcommand() {
+        Some(("list", sub)) => {
+            let limit: usize = sub.get_one::<String>("limit").unwrap().parse()?;
+            let tag = sub.get_one::<String>("tag");
+            let jobs: Vec<Job> = store
+                .list_jobs(MAX_JOB_SCAN)
+                .await?
+                .into_iter()
+                .filter(|job| tag.is_none_or(|tag| job.tags.contains(tag)))
+                .take(limit)
+                .collect();
+            print_job_table(&jobs);
+            Ok(())
+        }
+        Some(("show", sub)) => {
+            let job = find_job(&store, sub.get_one::<String>("id").unwrap()).await?;
+            print_job_details(&job);
+            if let Some(checkpoint) = CheckpointStore::open(store.root()).load(&job.id)? {
+                println!(
+                    "\n💾 Checkpoint: {} URLs left, resume with `swoop jobs resume {}`",
+                    checkpoint.pending.len(),
+                    &job.id[..8.min(job.id.len())]
+                );
+            }
+            Ok(())
+        }
+        Some(("rerun", sub)) => {
+            let job = find_job(&store, sub.get_one::<String>("id").unwrap()).await?;
+            let mut options = ScrapeOptions::from_snapshot(&job.config, cli::progress_mode(sub))?;
+            options.name = Some(
+                sub.get_one::<String>("name")
+                    .cloned()
+                    .unwrap_or_else(|| format!("{} (rerun)", job.name)),
+            );
+            options.tags = job.tags.clone();
+            options.rerun_of = Some(job.id.clone());
+            options.force = sub.get_flag("force");
+
+            cli::run_scrape(job.seeds.clone(), options, Some(&store)).await?;
+            Ok(())
+        }
+        Some(("resume", sub)) => {
+            let job = find_job(&store, sub.get_one::<String>("id").unwrap()).await?;
+            let checkpoints = CheckpointStore::open(store.root());
+            let checkpoint = checkpoints.load(&job.id)?.ok_or_else(|| {
+                format!(
+                    "Job {} has no checkpoint; only interrupted jobs can be resumed",
+                    job.id
+                )
+            })?;
+            let mut options = ScrapeOptions::from_snapshot(&job.config, cli::progress_mode(sub))?;
+            options.name = Some(format!("{} (resumed)", job.name));
+            options.tags = job.tags.clone();
+            options.rerun_of = Some(job.id.clone());
+            options.force = sub.get_flag("force");
+            println!(
+                "▶️  Resuming {} with {} URLs left ({} done before it stopped)",
+                job.name,
+                checkpoint.pending.len(),
+                checkpoint.completed
+            );
+
+            // Interrupted again, the new job keeps its own checkpoint
+            cli::run_scrape(checkpoint.pending, options, Some(&store)).await?;
+            checkpoints.remove(&job.id)?;
+            Ok(())
+        }
+        Some(("cost", sub)) => {
+            let job = find_job(&store, sub.get_one::<String>("id").unwrap()).await?;
+            let report = CostStore::open(store.root())
+                .load(&job.id)
+                .map_err(|e| format!("{:#}", e))?
+                .ok_or_else(|| format!("Job {} has no cost report", job.id))?;
+            if sub.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("🗂️  Job {} ({})", job.name, job.id);
+                cli::print_cost_report(&report);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolve a full job ID or a unique ID prefix
+async fn find_job(store: &LocalStore, id: &str) -> Result<Job, Box<dyn std::error::Error>> {
+    if let Ok(Some(job)) = store.get_job(id).await {
+        return Ok(job);
+    }
+
+    let mut matches: Vec<Job> = store
+        .list_jobs(MAX_JOB_SCAN)
+        .await?
+        .into_iter()
+        .filter(|job| job.id.starts_with(id))
+        .collect();
+
+    match matches.len() {
+        0 => Err(format!("No job matching '{}'", id).into()),
+        1 => Ok(matches.remove(0)),
+        n => Err(format!("'{}' is ambiguous, it matches {} jobs", id, n).into()),
+    }
+}
+
+fn print_job_table(jobs: &[Job]) {
+    if jobs.is_empty() {
+        println!("No jobs recorded yet");
+        return;
+    }
+
+    println!(
+        "{:<10} {:<28} {:<11} {:<17} {:>8} {:>6} {:>7} {:>7}",
+        "ID", "NAME", "STATUS", "STARTED", "DURATION", "SEEDS", "OK", "FAILED"
+    );
+    for job in jobs {
+        println!(
+            "{:<10} {:<28} {:<11} {:<17} {:>8} {:>6} {:>7} {:>7}",
+            &job.id[..8.min(job.id.len())],
+            truncate(&job.name, 28),
+            job.status.as_str(),
+            job.started_at.format("%Y-%m-%d %H:%M"),
+            format_duration(job),
+            job.seeds.len(),
+            job.counts.successful,
+            job.counts.failed
+        );
+    }
+}
+
+fn print_job_details(job: &Job) {
+    println!("🗂️  Job {}", job.id);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("📛 Name: {}", job.name);
+    println!("🚦 Status: {}", job.status.as_str());
+    if !job.tags.is_empty() {
+        println!("🏷️  Tags: {}", job.tags.join(", "));
+    }
+    if let Some(rerun_of) = &job.rerun_of {
+        println!("🔁 Rerun of: {}", rerun_of);
+    }
+    println!(
+        "🕐 Started: {}",
+        job.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if let Some(finished_at) = job.finished_at {
+        println!(
+            "🏁 Finished: {}",
+            finished_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+    println!("⏱️  Duration: {}", format_duration(job));
+    println!(
+        "📈 Results: {} total, ✅ {} / ❌ {} ({:.1}% success), {} KB",
+        job.counts.total,
+        job.counts.successful,
+        job.counts.failed,
+        job.counts.success_rate(),
+        job.counts.bytes / 1024
+    );
+
+    let mut config: Vec<_> = job.config.iter().collect();
+    config.sort();
+    println!("\n⚙️  Config:");
+    for (key, value) in config {
+        println!("  {} = {}", key, value);
+    }
+
+    println!("\n🌱 Seeds ({}):", job.seeds.len());
+    for seed in job.seeds.iter().take(20) {
+        println!("  {}", seed);
+    }
+    if job.seeds.len() > 20 {
+        println!("  … and {} more", job.seeds.len() - 20);
+    }
+}
+
+fn format_duration(job: &Job) -> String {
+    let secs = job.duration().num_seconds().max(0);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+pub(crate) fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        value.to_string()
+    } else {
+        let truncated: String = value.chars().take(width - 1).collect();
+        format!("{}…", truncated)
+    }
+}
@@ -0,0 +1,526 @@
+//! Key bindings of the dashboard
+//!
+//! Every action has default keys, including vim-style `hjkl` navigation,
+//! `g`/`G` for the top and bottom of tables and logs and `ctrl-u`/`ctrl-d`
+//! for paging. The `[keys]` table of the config file replaces the keys of any
+//! action, or unbinds it with an empty list:
+//!
+//! ```toml
+//! [keys]
+//! quit = ["q", "ctrl-q"]
+//! load_urls = ["o"]
+//! dashboard = []
+//! ```
+//!
+//! Keys are a character, a name (`Enter`, `Esc`, `Tab`, `BackTab`, `Space`,
+//! `Backspace`, `Up`, `Down`, `Left`, `Right`, `PageUp`, `PageDown`, `Home`,
+//! `End`, `Insert`, `Delete`, `F1` to `F12`) or either with `ctrl-`, `alt-` or
+//! `shift-` in front. `?` shows the active bindings.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashMap};
+
+/// Something a key press does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    AddUrls,
+    LoadUrls,
+    NextTab,
+    PrevTab,
+    /// Switch to the tab at this index
+    Tab(usize),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    PrevPane,
+    NextPane,
+    Select,
+    TogglePause,
+    RateUp,
+    RateDown,
+    ExportTab,
+    ExportFormat,
+    Dashboard,
+    LogInfo,
+    LogWarnings,
+    LogErrors,
+    LogFollow,
+    LogFilter,
+    LogExport,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them
+    pub const ALL: [Action; 35] = [
+        Action::Quit,
+        Action::Help,
+        Action::AddUrls,
+        Action::LoadUrls,
+        Action::NextTab,
+        Action::PrevTab,
+        Action::Tab(0),
+        Action::Tab(1),
+        Action::Tab(2),
+        Action::Tab(3),
+        Action::Tab(4),
+        Action::Tab(5),
+        Action::Tab(6),
+        Action::Tab(7),
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::PrevPane,
+        Action::NextPane,
+        Action::Select,
+        Action::TogglePause,
+        Action::RateUp,
+        Action::RateDown,
+        Action::ExportTab,
+        Action::ExportFormat,
+        Action::Dashboard,
+        Action::LogInfo,
+        Action::LogWarnings,
+        Action::LogErrors,
+        Action::LogFollow,
+        Action::LogFilter,
+        Action::LogExport,
+    ];
+
+    /// Name of the action in the `[keys]` table
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::AddUrls => "add_urls",
+            Action::LoadUrls => "load_urls",
+            Action::NextTab => "next_tab",
+            Action::PrevTab => "prev_tab",
+            Action::Tab(0) => "tab_1",
+            Action::Tab(1) => "tab_2",
+            Action::Tab(2) => "tab_3",
+            Action::Tab(3) => "tab_4",
+            Action::Tab(4) => "tab_5",
+            Action::Tab(5) => "tab_6",
+            Action::Tab(6) => "tab_7",
+            Action::Tab(_) => "tab_8",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::PrevPane => "prev_pane",
+            Action::NextPane => "next_pane",
+            Action::Select => "select",
+            Action::TogglePause => "toggle_pause",
+            Action::RateUp => "rate_up",
+            Action::RateDown => "rate_down",
+            Action::ExportTab => "export_tab",
+            Action::ExportFormat => "export_format",
+            Action::Dashboard => "dashboard",
+            Action::LogInfo => "log_info",
+            Action::LogWarnings => "log_warnings",
+            Action::LogErrors => "log_errors",
+            Action::LogFollow => "log_follow",
+            Action::LogFilter => "log_filter",
+            Action::LogExport => "log_export",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide this help",
+            Action::AddUrls => "Type in URLs to scrape",
+            Action::LoadUrls => "Load URLs from the URL file",
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::Tab(0) => "Overview tab",
+            Action::Tab(1) => "Metrics tab",
+            Action::Tab(2) => "Proxies tab",
+            Action::Tab(3) => "Logs tab",
+            Action::Tab(4) => "Targets tab",
+            Action::Tab(5) => "Export tab",
+            Action::Tab(6) => "Settings tab",
+            Action::Tab(_) => "Jobs tab",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Go to the top",
+            Action::Bottom => "Go to the bottom",
+            Action::PrevPane => "Previous overview pane",
+            Action::NextPane => "Next overview pane",
+            Action::Select => "Inspect the target's trace / start the export",
+            Action::TogglePause => "Pause or resume scraping",
+            Action::RateUp => "Raise the rate limit",
+            Action::RateDown => "Lower the rate limit",
+            Action::ExportTab => "Go to the export tab",
+            Action::ExportFormat => "Switch the export format",
+            Action::Dashboard => "Open the monitoring dashboard",
+            Action::LogInfo => "Logs: show or hide info",
+            Action::LogWarnings => "Logs: show or hide warnings",
+            Action::LogErrors => "Logs: show or hide errors",
+            Action::LogFollow => "Logs: follow new entries or pause",
+            Action::LogFilter => "Logs: filter by text",
+            Action::LogExport => "Logs: save the shown entries to a file",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::Help => &["?", "F1"],
+            Action::AddUrls => &["i"],
+            Action::LoadUrls => &["L"],
+            Action::NextTab => &["Tab"],
+            Action::PrevTab => &["BackTab"],
+            Action::Tab(0) => &["1"],
+            Action::Tab(1) => &["2"],
+            Action::Tab(2) => &["3"],
+            Action::Tab(3) => &["4"],
+            Action::Tab(4) => &["5"],
+            Action::Tab(5) => &["6"],
+            Action::Tab(6) => &["7"],
+            Action::Tab(_) => &["8"],
+            Action::Up => &["k", "Up"],
+            Action::Down => &["j", "Down"],
+            Action::PageUp => &["PageUp", "ctrl-u", "ctrl-b"],
+            Action::PageDown => &["PageDown", "ctrl-d", "ctrl-f"],
+            Action::Top => &["g", "Home"],
+            Action::Bottom => &["G", "End"],
+            Action::PrevPane => &["h", "Left"],
+            Action::NextPane => &["l", "Right"],
+            Action::Select => &["Enter"],
+            Action::TogglePause => &["Space"],
+            Action::RateUp => &["+"],
+            Action::RateDown => &["-"],
+            Action::ExportTab => &["e"],
+            Action::ExportFormat => &["f"],
+            Action::Dashboard => &["d"],
+            Action::LogInfo => &["I"],
+            Action::LogWarnings => &["W"],
+            Action::LogErrors => &["E"],
+            Action::LogFollow => &["p"],
+            Action::LogFilter => &["/"],
+            Action::LogExport => &["s"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A key with the modifiers held down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Terminals disagree on whether Shift is reported with the capital
+        // letter or back tab it produces, so the key itself says it all
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers.difference(KeyModifiers::SHIFT),
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parse a key such as `j`, `G`, `PageDown` or `ctrl-d`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = spec;
+        while let Some((modifier, rest)) = key.split_once('-') {
+            if rest.is_empty() {
+                break;
+            }
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "insert" => KeyCode::Insert,
+                "delete" | "del" => KeyCode::Delete,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", spec)),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// The key the way the config file spells it
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("alt-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("shift-");
+        }
+        label.push_str(&key);
+        label
+    }
+}
+
+/// Which action each key triggers
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .flat_map(|action| {
+                action.default_keys().iter().map(move |spec| {
+                    let key = KeyBinding::parse(spec).expect("default keys parse");
+                    (key, action)
+                })
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default bindings, with the keys of the actions in `keys` replaced
+    pub fn with_overrides(keys: &BTreeMap<String, Vec<String>>) -> Result<Self, String> {
+        let mut keymap = Self::default();
+        let mut overrides = HashMap::new();
+        for (name, specs) in keys {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("unknown action '{}' in [keys]", name))?;
+            keymap.bindings.retain(|_, bound| *bound != action);
+            for spec in specs {
+                let key = KeyBinding::parse(spec)?;
+                if let Some(other) = overrides.insert(key, action) {
+                    return Err(format!(
+                        "'{}' is bound to both {} and {}",
+                        spec,
+                        other.name(),
+                        action.name()
+                    ));
+                }
+            }
+        }
+        // Configured keys win over the defaults of other actions
+        keymap.bindings.extend(overrides);
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyBinding::from_event(key)).copied()
+    }
+
+    /// Keys bound to `action`, e.g. `j, Down`
+    pub fn keys(&self, action: Action) -> String {
+        let mut keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| key.label())
+            .collect();
+        // Single characters first, as the defaults list them
+        keys.sort_by_key(|key| (key.chars().count() > 1, key.clone()));
+        keys.join(", ")
+    }
+
+    /// Key and description of every bound action, for the help overlay
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        Action::ALL
+            .into_iter()
+            .map(|action| (self.keys(action), action.description()))
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn parsed(spec: &str) -> (KeyCode, KeyModifiers) {
+        let binding = KeyBinding::parse(spec).unwrap();
+        (binding.code, binding.modifiers)
+    }
+
+    fn overrides(keys: &[(&str, &[&str])]) -> Result<Keymap, String> {
+        let keys = keys
+            .iter()
+            .map(|(name, specs)| {
+                let specs = specs.iter().map(|spec| spec.to_string()).collect();
+                (name.to_string(), specs)
+            })
+            .collect();
+        Keymap::with_overrides(&keys)
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(parsed("j"), (KeyCode::Char('j'), KeyModifiers::NONE));
+        assert_eq!(
+            parsed("ctrl-d"),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parsed("Control-Alt-x"),
+            (
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        // Shift is folded into the character and back tab it produces
+        assert_eq!(parsed("shift-g"), (KeyCode::Char('G'), KeyModifiers::NONE));
+        assert_eq!(parsed("shift-Tab"), (KeyCode::BackTab, KeyModifiers::NONE));
+        assert_eq!(parsed("-"), (KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(
+            parsed("ctrl--"),
+            (KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        for (spec, code) in [
+            ("Enter", KeyCode::Enter),
+            ("esc", KeyCode::Esc),
+            ("Space", KeyCode::Char(' ')),
+            ("PageDown", KeyCode::PageDown),
+            ("del", KeyCode::Delete),
+            ("F1", KeyCode::F(1)),
+            ("f12", KeyCode::F(12)),
+        ] {
+            assert_eq!(parsed(spec), (code, KeyModifiers::NONE), "{}", spec);
+        }
+        assert_eq!(parsed("shift-F5"), (KeyCode::F(5), KeyModifiers::SHIFT));
+        assert_eq!(
+            KeyBinding::parse("ctrl-PageUp").unwrap().label(),
+            "ctrl-PageUp"
+        );
+        assert_eq!(KeyBinding::parse("Space").unwrap().label(), "Space");
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        for spec in ["", "F13", "F0", "nope", "hyper-x", "ctrl-"] {
+            assert!(KeyBinding::parse(spec).is_err(), "{}", spec);
+        }
+        assert_eq!(KeyBinding::parse("nope").unwrap_err(), "unknown key 'nope'");
+    }
+
+    #[test]
+    fn defaults_bind_every_action() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::PageDown)
+        );
+        // Shift reported with the capital letter still matches
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(keymap.keys(Action::Down), "j, Down");
+        assert_eq!(keymap.help().len(), Action::ALL.len());
+    }
+
+    #[test]
+    fn overrides_replace_the_default_keys() {
+        let keymap = overrides(&[("quit", &["x", "ctrl-q"]), ("dashboard", &[])]).unwrap();
+        let quit = |code, modifiers| keymap.action(&key(code, modifiers)) == Some(Action::Quit);
+        assert!(quit(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(quit(KeyCode::Char('q'), KeyModifiers::CONTROL));
+        assert!(!quit(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(keymap.keys(Action::Dashboard), "");
+        assert!(keymap
+            .help()
+            .iter()
+            .all(|(_, description)| *description != Action::Dashboard.description()));
+        // Actions left out keep their defaults
+        assert_eq!(keymap.keys(Action::Help), "?, F1");
+    }
+
+    #[test]
+    fn configured_keys_take_over_other_defaults() {
+        let keymap = overrides(&[("quit", &["j"])]).unwrap();
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.keys(Action::Down), "Down");
+    }
+
+    #[test]
+    fn rejects_conflicting_and_unknown_bindings() {
+        let conflict = overrides(&[("help", &["x"]), ("quit", &["x"])]).unwrap_err();
+        assert_eq!(conflict, "'x' is bound to both help and quit");
+        assert!(overrides(&[("up", &["k", "K"]), ("down", &["shift-k"])]).is_err());
+        assert_eq!(
+            overrides(&[("fly", &["f"])]).unwrap_err(),
+            "unknown action 'fly' in [keys]"
+        );
+        assert!(overrides(&[("quit", &["hyper-q"])]).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Library log events for the Logs tab
+//!
+//! [`LogForwarder`] is a `tracing` layer passing the info, warning and error
+//! events of the scrapers, storage and core crates to the dashboard, which
+//! otherwise only shows its own messages. The dashboard's own tracing output
+//! (one event per frame and key press) stays in the log file.
+
+use std::{
+    fmt::{self, Write},
+    sync::mpsc::{self, Receiver, Sender},
+};
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// Crates whose events are forwarded
+const FORWARDED_CRATES: [&str; 3] = ["scrapers", "storage", "swoop_core"];
+
+/// A library event, ready for the Logs tab
+#[derive(Debug, Clone)]
+pub struct ForwardedLog {
+    pub level: Level,
+    /// Crate the event came from
+    pub source: String,
+    /// The message, followed by the event's other fields as `key=value`
+    pub message: String,
+}
+
+/// Layer sending library events down a channel
+pub struct LogForwarder {
+    sender: Sender<ForwardedLog>,
+}
+
+impl LogForwarder {
+    /// The layer, and the receiving end the dashboard drains
+    pub fn new() -> (Self, Receiver<ForwardedLog>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self { sender }, receiver)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogForwarder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::INFO {
+            return;
+        }
+        let source = metadata.target().split("::").next().unwrap_or_default();
+        if !FORWARDED_CRATES.contains(&source) {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message;
+        if !visitor.fields.is_empty() {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&visitor.fields);
+        }
+        // The dashboard may be gone while libraries still log on shutdown
+        let _ = self.sender.send(ForwardedLog {
+            level: *metadata.level(),
+            source: source.to_string(),
+            message,
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={}", field.name(), value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={:?}", field.name(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn forwards_library_events_at_info_and_above() {
+        let (forwarder, receiver) = LogForwarder::new();
+        let subscriber = tracing_subscriber::registry().with(forwarder);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "scrapers::platforms", "Fetched page");
+            tracing::warn!(target: "storage", "Slow write");
+            tracing::error!(target: "swoop_core::client", status = 503, url = "https://a.test/", "Request failed");
+            // Too verbose, or not from a forwarded crate
+            tracing::debug!(target: "scrapers", "Parsed links");
+            tracing::trace!(target: "storage::local_store", "Opened file");
+            tracing::info!(target: "swoop_tui", "Frame drawn");
+            tracing::error!(target: "scrapers_extra", "Lookalike crate");
+        });
+
+        let forwarded: Vec<(Level, String, String)> = receiver
+            .try_iter()
+            .map(|log| (log.level, log.source, log.message))
+            .collect();
+        assert_eq!(
+            forwarded,
+            [
+                (
+                    Level::INFO,
+                    "scrapers".to_string(),
+                    "Fetched page".to_string()
+                ),
+                (Level::WARN, "storage".to_string(), "Slow write".to_string()),
+                (
+                    Level::ERROR,
+                    "swoop_core".to_string(),
+                    "Request failed status=503 url=https://a.test/".to_string()
+                ),
+            ]
+        );
+    }
+}
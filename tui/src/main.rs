@@ -1,34 +1,73 @@
+mod config;
+mod control;
 mod dashboard;
 mod dashboard_main;
+mod guardrails;
+mod keymap;
+mod log_layer;
+mod quarantine;
+mod shutdown;
+mod spill;
+mod theme;
+
+use bytes::Bytes;
+use clap::{Arg, Command};
 
 use crossterm::{
-    event::{Event, KeyCode, KeyEventKind, EventStream},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, EventStream},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{
-        Axis, Block, Borders, Cell, Chart, Dataset, List, ListItem, ListState, Paragraph, Row,
-        Table, Tabs, Wrap,
+        Axis, Block, Borders, Cell, Chart, Clear, Dataset, List, ListItem, ListState, Paragraph,
+        Row, Table, Tabs, Wrap,
     },
     Frame, Terminal,
 };
 use futures::StreamExt;
 use std::{
     collections::{HashMap, VecDeque},
+    fmt::Write,
     fs,
     io::{self, stdout},
     panic,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tokio::sync::Semaphore;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use config::TuiConfig;
+use guardrails::{parse_cpu_percent, GuardAction, ResourceGuard, ResourceLimits};
+use keymap::{Action, Keymap};
+use log_layer::{ForwardedLog, LogForwarder};
+use quarantine::{stop_unsafe, UNSAFE_TAG};
+use spill::{BufferLimits, ResultBuffer};
+use theme::{ColorSupport, Theme};
+use scrapers::{
+    classifier::ContentClassifier,
+    extractors::summarize_page,
+    policy::{PolicyResolver, ResolvedPolicy, POLICY_FILE},
+    utils::is_bot_protected,
+    walls::WallDetector,
+};
+use shutdown::{Shutdown, GRACE_PERIOD};
+use storage::{
+    checkpoint::{Checkpoint, CheckpointStore},
+    local_store::LocalStore,
+    models::{Job, JobCounts, JobStatus},
+    JobStore,
+};
+use swoop_core::{
+    metrics::{FetchHistograms, Quantiles},
+    throttle::Throttle,
+    trace::{FetchTrace, FetchTracer, TracedFetch},
+};
 use sysinfo::System;
 use tracing::{error, info, instrument};
 use tracing::level_filters::LevelFilter;
@@ -39,7 +78,7 @@ use tracing_subscriber::{
     EnvFilter,
 };
 
-fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
+fn setup_logging(forwarder: LogForwarder) -> Result<(), Box<dyn std::error::Error>> {
     let log_dir = PathBuf::from("logs");
     fs::create_dir_all(&log_dir)?;
     let log_file = fs::File::create(log_dir.join("swoop-tui.log"))?;
@@ -51,23 +90,54 @@ fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::registry()
         .with(filter)
         .with(fmt::layer().with_writer(log_file).json())
+        .with(forwarder)
         .init();
     
     Ok(())
 }
 
 
-/// Simple HTTP fetch function to avoid dependency issues
-#[instrument]
-async fn fetch_url_simple(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+/// Fetch a URL with its policy's headers, recording a trace for the result
+/// inspector; bodies the policy's safety filters stop fail the fetch
+#[instrument(skip(policy, quarantine))]
+async fn fetch_url_traced(
+    url: &str,
+    policy: &ResolvedPolicy,
+    quarantine: Option<&LocalStore>,
+) -> (Result<Bytes, anyhow::Error>, FetchTrace) {
     info!("Fetching URL: {}", url);
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await?;
-    let bytes = response.bytes().await?;
+    let TracedFetch { body, mut trace } = FetchTracer::new().fetch(url, &policy.headers).await;
     info!("Finished fetching URL: {}", url);
-    Ok(bytes.to_vec())
+
+    if let Ok(data) = &body {
+        let content_type = trace.response_headers.get("content-type").cloned();
+        if let Some(verdict) = policy.safety.check(content_type.as_deref(), data) {
+            trace
+                .classification
+                .push(format!("{}:{}", UNSAFE_TAG, verdict.hazard.as_str()));
+            let error = stop_unsafe(quarantine, url, content_type, &verdict, data).await;
+            return (Err(anyhow::anyhow!(error)), trace);
+        }
+    }
+
+    if let Ok(bytes) = &body {
+        let html = String::from_utf8_lossy(bytes);
+        if is_bot_protected(&html) {
+            trace.classification.push("bot-protected".to_string());
+        }
+        if let Some(wall) = WallDetector::new().detect(&html, Some(url)) {
+            trace.classification.push(wall.kind.as_tag());
+        }
+        trace
+            .classification
+            .extend(ContentClassifier::new().classify_html(&html, trace.status).tags());
+    }
+    (body, trace)
 }
 
+/// Rows the page up and down keys move by
+const PAGE_ROWS: i32 = 10;
+
 /// Application state for the TUI dashboard
 #[derive(Debug, Clone)]
 struct AppState {
@@ -85,8 +155,8 @@ struct AppState {
     controls: ControlState,
     /// URL queue
     targets: VecDeque<Target>,
-    /// Scraped data storage
-    scraped_data: VecDeque<ScrapedData>,
+    /// Scraped data storage, the oldest results spilled to disk
+    scraped_data: ResultBuffer<ScrapedData>,
     /// Export state
     export_state: ExportState,
     /// Whether the app should quit
@@ -103,6 +173,22 @@ struct AppState {
     input_mode: bool,
     /// Buffer for the input box
     input_buffer: String,
+    /// Is the input box editing the Logs tab filter
+    log_filter_mode: bool,
+    /// Control address of the daemon this dashboard is attached to, if any
+    attached_to: Option<String>,
+    /// Recorded crawl jobs, newest first
+    jobs: Vec<storage::models::Job>,
+    /// Jobs table scroll position
+    jobs_scroll: usize,
+    /// Show the fetch trace of the selected target
+    inspecting_target: bool,
+    /// Key bindings
+    keymap: Keymap,
+    /// Colors, fitted to the terminal
+    theme: Theme,
+    /// Show the key bindings over the current tab
+    show_help: bool,
 }
 
 /// System information
@@ -128,6 +214,8 @@ struct Target {
     status: TargetStatus,
     response_time: Option<u64>,
     status_code: Option<u16>,
+    /// Fetch trace, only available for targets scraped by the local engine
+    trace: Option<FetchTrace>,
 }
 
 /// Metrics data for monitoring
@@ -136,7 +224,8 @@ struct Metrics {
     requests_per_second: VecDeque<f64>,
     request_timestamps: VecDeque<Instant>,
     success_rate: VecDeque<f64>,
-    response_time: VecDeque<f64>,
+    /// Latency, TTFB and body size distributions of successful fetches
+    histograms: FetchHistograms,
     total_requests: u64,
     total_successful: u64,
     total_failed: u64,
@@ -162,18 +251,31 @@ struct LogBuffer {
     entries: VecDeque<LogEntry>,
     max_size: usize,
     scroll_position: usize,
+    /// Sequence number of the next entry
+    next_seq: u64,
+    /// Levels the Logs tab shows; success messages count as info
+    show_info: bool,
+    show_warnings: bool,
+    show_errors: bool,
+    /// Only show entries containing this, ignoring case
+    filter: String,
+    /// While paused, the Logs tab leaves out entries from this sequence
+    /// number on, so the view holds still
+    paused_at: Option<u64>,
 }
 
 /// Individual log entry
 #[derive(Debug, Clone)]
 struct LogEntry {
+    seq: u64,
     timestamp: Instant,
+    logged_at: DateTime<Utc>,
     level: LogLevel,
     message: String,
 }
 
 /// Log levels
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum LogLevel {
     Info,
     Warning,
@@ -205,6 +307,12 @@ struct ScrapedData {
     content_length: usize,
     content_type: Option<String>,
     title: Option<String>,
+    /// Start of the page text
+    #[serde(default)]
+    excerpt: Option<String>,
+    /// `<link rel="canonical">`, when the page declares one
+    #[serde(default)]
+    canonical_url: Option<String>,
     success: bool,
     error: Option<String>,
 }
@@ -254,7 +362,7 @@ impl Default for Metrics {
             requests_per_second: VecDeque::with_capacity(60),
             request_timestamps: VecDeque::with_capacity(1000),
             success_rate: VecDeque::with_capacity(60),
-            response_time: VecDeque::with_capacity(60),
+            histograms: FetchHistograms::new(),
             total_requests: 0,
             total_successful: 0,
             total_failed: 0,
@@ -284,6 +392,12 @@ impl Default for LogBuffer {
             entries: VecDeque::new(),
             max_size: 1000,
             scroll_position: 0,
+            next_seq: 0,
+            show_info: true,
+            show_warnings: true,
+            show_errors: true,
+            filter: String::new(),
+            paused_at: None,
         }
     }
 }
@@ -294,10 +408,85 @@ impl LogBuffer {
             self.entries.pop_front();
         }
         self.entries.push_back(LogEntry {
+            seq: self.next_seq,
             timestamp: Instant::now(),
+            logged_at: Utc::now(),
             level,
             message,
         });
+        self.next_seq += 1;
+        // Following, the newest entry stays selected
+        if self.paused_at.is_none() {
+            self.scroll_position = 0;
+        }
+    }
+
+    /// Entries the Logs tab shows, newest first
+    fn visible(&self) -> impl Iterator<Item = &LogEntry> {
+        let filter = self.filter.to_lowercase();
+        self.entries.iter().rev().filter(move |entry| {
+            let level_shown = match entry.level {
+                LogLevel::Info | LogLevel::Success => self.show_info,
+                LogLevel::Warning => self.show_warnings,
+                LogLevel::Error => self.show_errors,
+            };
+            level_shown
+                && self.paused_at.map_or(true, |paused_at| entry.seq < paused_at)
+                && (filter.is_empty() || entry.message.to_lowercase().contains(&filter))
+        })
+    }
+
+    /// Stop showing new entries, keeping the view where it is
+    fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(self.next_seq);
+        }
+    }
+
+    fn toggle_follow(&mut self) {
+        if self.paused_at.take().is_none() {
+            self.pause();
+        }
+        self.scroll_position = 0;
+    }
+
+    /// Entries logged since the view was paused
+    fn new_while_paused(&self) -> usize {
+        self.paused_at.map_or(0, |paused_at| {
+            self.entries.iter().filter(|entry| entry.seq >= paused_at).count()
+        })
+    }
+
+    /// Write the entries the Logs tab shows, oldest first, to a new file
+    /// in `dir`
+    fn export_visible(&self, dir: &Path) -> io::Result<(PathBuf, usize)> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("logs_{}.log", Utc::now().format("%Y%m%d_%H%M%S")));
+        let mut entries: Vec<&LogEntry> = self.visible().collect();
+        entries.reverse();
+        let mut lines = String::new();
+        for entry in &entries {
+            let _ = writeln!(
+                lines,
+                "{} {:<7} {}",
+                entry.logged_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                entry.level.as_str(),
+                entry.message
+            );
+        }
+        fs::write(&path, lines)?;
+        Ok((path, entries.len()))
+    }
+}
+
+impl LogLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Warning => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Success => "SUCCESS",
+        }
     }
 }
 
@@ -340,7 +529,7 @@ impl ExportFormat {
 }
 
 impl AppState {
-    fn new() -> Self {
+    fn new(result_limits: BufferLimits, keymap: Keymap, theme: Theme) -> Self {
         let mut logs = LogBuffer::default();
         logs.add_entry(
             LogLevel::Info,
@@ -356,7 +545,7 @@ impl AppState {
             logs,
             controls: ControlState::default(),
             targets: VecDeque::new(),
-            scraped_data: VecDeque::with_capacity(10000),
+            scraped_data: ResultBuffer::new(result_limits),
             export_state: ExportState::default(),
             should_quit: false,
             system_info: SystemInfo::default(),
@@ -365,15 +554,34 @@ impl AppState {
             focused_pane: FocusedPane::default(),
             input_mode: false,
             input_buffer: String::new(),
+            log_filter_mode: false,
+            attached_to: None,
+            jobs: Vec::new(),
+            jobs_scroll: 0,
+            inspecting_target: false,
+            keymap,
+            theme,
+            show_help: false,
+        }
+    }
+
+    /// Keep a result for export, spilling older results to disk as needed
+    fn record_result(&mut self, entry: ScrapedData) {
+        let body_bytes = entry.content.len();
+        if let Err(e) = self.scraped_data.push(entry, body_bytes) {
+            self.logs.add_entry(
+                LogLevel::Warning,
+                format!("Failed to spill results to disk, dropping the oldest: {}", e),
+            );
         }
     }
 
     #[instrument(skip(self))]
-    fn handle_key_event(&mut self, key: KeyCode) {
+    fn handle_key_event(&mut self, key: KeyEvent) {
         info!(?key, "Handling key event");
 
         if self.input_mode {
-            match key {
+            match key.code {
                 KeyCode::Esc => {
                     self.input_mode = false;
                     self.input_buffer.clear();
@@ -389,6 +597,7 @@ impl AppState {
                                 status: TargetStatus::Pending,
                                 response_time: None,
                                 status_code: None,
+                                trace: None,
                             });
                         }
                     }
@@ -404,112 +613,161 @@ impl AppState {
                 }
                 _ => {}
             }
-        } else {
-            match key {
-                KeyCode::Char('q') => {
-                    self.should_quit = true;
-                    info!("Quit key pressed, should_quit set to true");
-                }
-                KeyCode::Char('i') => {
-                    self.input_mode = true;
-                }
-                KeyCode::Tab => {
-                    self.current_tab = (self.current_tab + 1) % 7;
-                }
-                KeyCode::BackTab => {
-                    self.current_tab = (self.current_tab + 6) % 7;
-                }
-                KeyCode::Up => {
-                    self.scroll(-1);
-                }
-                KeyCode::Down => {
-                    self.scroll(1);
-                }
-                KeyCode::Char('1') => self.current_tab = 0,
-                KeyCode::Char('2') => self.current_tab = 1,
-                KeyCode::Char('3') => self.current_tab = 2,
-                KeyCode::Char('4') => self.current_tab = 3,
-                KeyCode::Char('5') => self.current_tab = 4,
-                KeyCode::Char('6') => self.current_tab = 5,
-                KeyCode::Char('7') => self.current_tab = 6,
-                KeyCode::Char(' ') => {
-                    self.controls.is_paused = !self.controls.is_paused;
-                    let state = if self.controls.is_paused {
-                        "paused"
-                    } else {
-                        "resumed"
-                    };
-                    self.logs
-                        .add_entry(LogLevel::Info, format!("Scraping {}", state));
-                }
-                KeyCode::Char('+') => {
-                    self.controls.rate_limit = (self.controls.rate_limit + 10.0).min(500.0);
-                }
-                KeyCode::Char('-') => {
-                    self.controls.rate_limit = (self.controls.rate_limit - 10.0).max(10.0);
-                }
-                KeyCode::Left => self.navigate_panes(-1),
-                KeyCode::Right => self.navigate_panes(1),
-                KeyCode::Char('l') => {
-                    self.load_urls_from_file();
-                }
-                KeyCode::Char('e') => {
-                    self.current_tab = 5; // Export tab
-                }
-                KeyCode::Char('d') => {
-                    // Launch advanced dashboard
-                    tokio::spawn(async {
-                        if let Err(e) = dashboard_main::run_dashboard().await {
-                            eprintln!("Dashboard error: {}", e);
-                        }
-                    });
-                }
-                KeyCode::Char('f') => {
-                    if self.current_tab == 5 {
-                        self.export_state.format = match self.export_state.format {
-                            ExportFormat::Json => ExportFormat::Csv,
-                            ExportFormat::Csv => ExportFormat::Json,
-                        };
-                        self.export_state.file_path = match self.export_state.format {
-                            ExportFormat::Json => "export.json".to_string(),
-                            ExportFormat::Csv => "export.csv".to_string(),
-                        };
-                    }
+            return;
+        }
+        if self.log_filter_mode {
+            // The filter applies as it is typed
+            match key.code {
+                KeyCode::Esc => {
+                    self.logs.filter.clear();
+                    self.log_filter_mode = false;
                 }
-                KeyCode::Enter => {
-                    if self.current_tab == 5 {
-                        self.export_requested = true;
-                    }
+                KeyCode::Enter => self.log_filter_mode = false,
+                KeyCode::Char(c) => self.logs.filter.push(c),
+                KeyCode::Backspace => {
+                    self.logs.filter.pop();
                 }
                 _ => {}
             }
+            self.logs.scroll_position = 0;
+            return;
+        }
+        if self.show_help {
+            // Any key closes the help
+            self.show_help = false;
+            return;
         }
-    }
 
-    fn scroll(&mut self, direction: i32) {
-        match self.current_tab {
-            3 => { // Logs
-                let len = self.logs.entries.len();
-                if len > 0 {
-                    let new_pos = self.logs.scroll_position as i32 + direction;
-                    self.logs.scroll_position = new_pos.max(0).min((len - 1) as i32) as usize;
+        let Some(action) = self.keymap.action(&key) else {
+            return;
+        };
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
+                info!("Quit key pressed, should_quit set to true");
+            }
+            Action::Help => self.show_help = true,
+            Action::AddUrls => {
+                self.input_mode = true;
+            }
+            Action::LoadUrls => {
+                self.load_urls_from_file();
+            }
+            Action::NextTab => {
+                self.current_tab = (self.current_tab + 1) % 8;
+            }
+            Action::PrevTab => {
+                self.current_tab = (self.current_tab + 7) % 8;
+            }
+            Action::Tab(tab) => self.current_tab = tab,
+            Action::Up => self.scroll(-1),
+            Action::Down => self.scroll(1),
+            Action::PageUp => self.scroll(-PAGE_ROWS),
+            Action::PageDown => self.scroll(PAGE_ROWS),
+            Action::Top => self.scroll(i32::MIN),
+            Action::Bottom => self.scroll(i32::MAX),
+            Action::PrevPane => self.navigate_panes(-1),
+            Action::NextPane => self.navigate_panes(1),
+            Action::TogglePause => {
+                self.controls.is_paused = !self.controls.is_paused;
+                let state = if self.controls.is_paused {
+                    "paused"
+                } else {
+                    "resumed"
+                };
+                self.logs
+                    .add_entry(LogLevel::Info, format!("Scraping {}", state));
+            }
+            Action::RateUp => {
+                self.controls.rate_limit = (self.controls.rate_limit + 10.0).min(500.0);
+            }
+            Action::RateDown => {
+                self.controls.rate_limit = (self.controls.rate_limit - 10.0).max(10.0);
+            }
+            Action::ExportTab => {
+                self.current_tab = 5; // Export tab
+            }
+            Action::Dashboard => {
+                // Launch advanced dashboard
+                let theme = self.theme;
+                tokio::spawn(async move {
+                    if let Err(e) = dashboard_main::run_dashboard(theme).await {
+                        eprintln!("Dashboard error: {}", e);
+                    }
+                });
+            }
+            Action::ExportFormat => {
+                if self.current_tab == 5 {
+                    self.export_state.format = match self.export_state.format {
+                        ExportFormat::Json => ExportFormat::Csv,
+                        ExportFormat::Csv => ExportFormat::Json,
+                    };
+                    self.export_state.file_path = match self.export_state.format {
+                        ExportFormat::Json => "export.json".to_string(),
+                        ExportFormat::Csv => "export.csv".to_string(),
+                    };
                 }
             }
-            4 => { // Targets
-                let len = self.targets.len();
-                if len > 0 {
-                    let new_pos = self.target_scroll as i32 + direction;
-                    self.target_scroll = new_pos.max(0).min((len - 1) as i32) as usize;
+            Action::LogInfo | Action::LogWarnings | Action::LogErrors if self.current_tab == 3 => {
+                let shown = match action {
+                    Action::LogInfo => &mut self.logs.show_info,
+                    Action::LogWarnings => &mut self.logs.show_warnings,
+                    _ => &mut self.logs.show_errors,
+                };
+                *shown = !*shown;
+                self.logs.scroll_position = 0;
+            }
+            Action::LogFollow if self.current_tab == 3 => self.logs.toggle_follow(),
+            Action::LogFilter if self.current_tab == 3 => self.log_filter_mode = true,
+            Action::LogExport if self.current_tab == 3 => {
+                match self.logs.export_visible(&self.controls.export_dir) {
+                    Ok((path, count)) => self.logs.add_entry(
+                        LogLevel::Success,
+                        format!("Exported {} log entries to {}", count, path.display()),
+                    ),
+                    Err(e) => self
+                        .logs
+                        .add_entry(LogLevel::Error, format!("Log export failed: {}", e)),
                 }
             }
-            5 => { // Export
-                let len = self.export_state.recent_exports.len();
-                if len > 0 {
-                    let new_pos = self.export_state.scroll_position as i32 + direction;
-                    self.export_state.scroll_position = new_pos.max(0).min((len - 1) as i32) as usize;
+            Action::LogInfo
+            | Action::LogWarnings
+            | Action::LogErrors
+            | Action::LogFollow
+            | Action::LogFilter
+            | Action::LogExport => {}
+            Action::Select => {
+                if self.current_tab == 4 {
+                    self.inspecting_target = !self.inspecting_target;
+                }
+                if self.current_tab == 5 {
+                    self.export_requested = true;
                 }
             }
-            _ => {}
+        }
+    }
+
+    /// Move the selection of the current tab's list by `rows`, clamped to
+    /// its first and last row
+    fn scroll(&mut self, rows: i32) {
+        let (position, len) = match self.current_tab {
+            // Logs; scrolling stops following new entries, so the view holds still
+            3 => {
+                self.logs.pause();
+                let len = self.logs.visible().count();
+                (&mut self.logs.scroll_position, len)
+            }
+            // Targets
+            4 => (&mut self.target_scroll, self.targets.len()),
+            // Export
+            5 => (&mut self.export_state.scroll_position, self.export_state.recent_exports.len()),
+            // Jobs
+            7 => (&mut self.jobs_scroll, self.jobs.len()),
+            _ => return,
+        };
+        if len > 0 {
+            let new_pos = (*position as i64 + rows as i64).clamp(0, len as i64 - 1);
+            *position = new_pos as usize;
         }
     }
 
@@ -535,6 +793,7 @@ impl AppState {
                         status: TargetStatus::Pending,
                         response_time: None,
                         status_code: None,
+                        trace: None,
                     });
                 }
             }
@@ -551,13 +810,21 @@ impl AppState {
     }
 }
 
-async fn scraping_engine(app: Arc<Mutex<AppState>>) {
+async fn scraping_engine(
+    app: Arc<Mutex<AppState>>,
+    shutdown: Shutdown,
+    slots: Arc<Throttle>,
+    policies: Option<Arc<PolicyResolver>>,
+    quarantine: Option<LocalStore>,
+) {
     info!("Scraping engine started");
     loop {
-        let (concurrency, is_paused) = {
-            let app_guard = app.lock().unwrap();
-            (app_guard.controls.concurrency, app_guard.controls.is_paused)
-        };
+        // On quit or a signal, leave the pending targets for the checkpoint
+        if shutdown.is_requested() {
+            info!("Scraping engine stopped");
+            return;
+        }
+        let is_paused = app.lock().unwrap().controls.is_paused;
 
         if is_paused {
             tokio::time::sleep(Duration::from_millis(500)).await;
@@ -570,36 +837,56 @@ async fn scraping_engine(app: Arc<Mutex<AppState>>) {
         };
 
         if let Some(index) = url_to_process_index {
+            // Targets show as in progress once they have a slot
+            let permit = tokio::select! {
+                permit = slots.acquire() => permit.unwrap(),
+                _ = shutdown.requested() => continue,
+            };
             let url = {
                 let mut app_guard = app.lock().unwrap();
                 app_guard.targets[index].status = TargetStatus::InProgress;
                 app_guard.targets[index].url.clone()
             };
-            
-            let semaphore = Arc::new(Semaphore::new(concurrency));
-            let permit_fut = semaphore.clone().acquire_owned();
+
             let app_clone = Arc::clone(&app);
+            let policy = match &policies {
+                Some(policies) => policies.resolve(&url, None),
+                None => ResolvedPolicy::default(),
+            };
+            let quarantine = quarantine.clone();
 
             tokio::spawn(async move {
-                let _permit = permit_fut.await.unwrap();
+                let _permit = permit;
                 let start_time = Instant::now();
-                match fetch_url_simple(&url).await {
+                let (result, trace) = fetch_url_traced(&url, &policy, quarantine.as_ref()).await;
+                let status_code = trace.status;
+                // Cookies stay out of exports
+                let headers: HashMap<String, String> = trace
+                    .response_headers
+                    .iter()
+                    .filter(|(name, _)| name.as_str() != "set-cookie")
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                let content_type = headers.get("content-type").cloned();
+                match result {
                     Ok(data) => {
                         let duration = start_time.elapsed();
+                        let content_length = data.len();
+                        let content = swoop_core::body_to_string(data);
+                        let base_url = trace.final_url.as_deref().unwrap_or(&url);
+                        let summary = summarize_page(&content, base_url, content_type.as_deref());
                         let mut app_guard = app_clone.lock().unwrap();
+                        app_guard.metrics.histograms.record_trace(&trace, content_length);
                         if let Some(target) = app_guard.targets.get_mut(index) {
                             target.status = TargetStatus::Completed;
                             target.response_time = Some(duration.as_millis() as u64);
-                            target.status_code = Some(200);
+                            target.status_code = status_code;
+                            target.trace = Some(trace);
                         }
                         app_guard.metrics.total_requests += 1;
                         app_guard.metrics.total_successful += 1;
                         app_guard.metrics.request_timestamps.push_back(Instant::now());
-                        app_guard.metrics.data_processed += data.len() as u64;
-                        app_guard.metrics.response_time.push_back(duration.as_millis() as f64);
-                        if app_guard.metrics.response_time.len() > 60 {
-                            app_guard.metrics.response_time.pop_front();
-                        }
+                        app_guard.metrics.data_processed += content_length as u64;
                         app_guard.metrics.success_rate.push_back(1.0);
                         if app_guard.metrics.success_rate.len() > 60 {
                             app_guard.metrics.success_rate.pop_front();
@@ -608,20 +895,19 @@ async fn scraping_engine(app: Arc<Mutex<AppState>>) {
                         let scraped_entry = ScrapedData {
                             url: url.clone(),
                             timestamp: Utc::now(),
-                            content: String::from_utf8_lossy(&data).to_string(),
-                            status_code: Some(200),
-                            headers: HashMap::new(),
+                            content_length,
+                            content,
+                            status_code,
+                            headers,
                             response_time: duration.as_millis() as u64,
-                            content_length: data.len(),
-                            content_type: Some("text/html".to_string()),
-                            title: None,
+                            content_type: Some(summary.content_type),
+                            title: summary.title,
+                            excerpt: summary.excerpt,
+                            canonical_url: summary.canonical_url,
                             success: true,
                             error: None,
                         };
-                        app_guard.scraped_data.push_back(scraped_entry);
-                        if app_guard.scraped_data.len() > 10000 {
-                            app_guard.scraped_data.pop_front();
-                        }
+                        app_guard.record_result(scraped_entry);
 
                         app_guard.logs.add_entry(
                             LogLevel::Success,
@@ -634,6 +920,7 @@ async fn scraping_engine(app: Arc<Mutex<AppState>>) {
                             target.status = TargetStatus::Failed;
                             target.response_time = None;
                             target.status_code = None;
+                            target.trace = Some(trace);
                         }
                         app_guard.metrics.total_requests += 1;
                         app_guard.metrics.total_failed += 1;
@@ -653,13 +940,12 @@ async fn scraping_engine(app: Arc<Mutex<AppState>>) {
                             content_length: 0,
                             content_type: None,
                             title: None,
+                            excerpt: None,
+                            canonical_url: None,
                             success: false,
                             error: Some(e.to_string()),
                         };
-                        app_guard.scraped_data.push_back(scraped_entry);
-                        if app_guard.scraped_data.len() > 10000 {
-                            app_guard.scraped_data.pop_front();
-                        }
+                        app_guard.record_result(scraped_entry);
 
                         app_guard.logs.add_entry(
                             LogLevel::Error,
@@ -674,18 +960,217 @@ async fn scraping_engine(app: Arc<Mutex<AppState>>) {
     }
 }
 
+/// Frontend-only engine: forwards targets and pause state to a running daemon
+/// and mirrors its results and logs into the dashboard
+async fn attach_engine(app: Arc<Mutex<AppState>>, client: control::ControlClient) {
+    info!("Attaching to daemon at {}", client.base_url());
+    let mut log_seq = 0;
+    let mut result_seq = 0;
+    let mut daemon_paused = None;
+    let mut connected = false;
+    let mut ready = true;
+    let mut polls: u32 = 0;
+
+    loop {
+        match client.status().await {
+            Ok(status) => {
+                let mut app_guard = app.lock().unwrap();
+                if !connected {
+                    connected = true;
+                    app_guard.controls.is_paused = status.paused;
+                    app_guard.logs.add_entry(
+                        LogLevel::Success,
+                        format!("Attached to daemon at {}", client.base_url()),
+                    );
+                }
+                daemon_paused = Some(status.paused);
+                app_guard.metrics.active_connections = status.in_flight as u32;
+            }
+            Err(e) => {
+                if connected || daemon_paused.is_none() {
+                    app.lock().unwrap().logs.add_entry(
+                        LogLevel::Error,
+                        format!("Daemon unreachable at {}: {}", client.base_url(), e),
+                    );
+                }
+                connected = false;
+                daemon_paused = Some(false);
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                continue;
+            }
+        }
+
+        // Sync pause state
+        let wants_paused = app.lock().unwrap().controls.is_paused;
+        if daemon_paused != Some(wants_paused) {
+            let result = if wants_paused {
+                client.pause().await
+            } else {
+                client.resume().await
+            };
+            if let Err(e) = result {
+                app.lock().unwrap().logs.add_entry(
+                    LogLevel::Error,
+                    format!("Failed to update daemon pause state: {}", e),
+                );
+            }
+        }
+
+        // Forward newly added targets as a job
+        let pending: Vec<String> = {
+            let mut app_guard = app.lock().unwrap();
+            app_guard
+                .targets
+                .iter_mut()
+                .filter(|t| t.status == TargetStatus::Pending)
+                .map(|t| {
+                    t.status = TargetStatus::InProgress;
+                    t.url.clone()
+                })
+                .collect()
+        };
+        if !pending.is_empty() {
+            let request = control::JobRequest {
+                name: None,
+                urls: pending.clone(),
+            };
+            if let Err(e) = client.submit(&request).await {
+                let mut app_guard = app.lock().unwrap();
+                for target in app_guard.targets.iter_mut() {
+                    if target.status == TargetStatus::InProgress && pending.contains(&target.url) {
+                        target.status = TargetStatus::Pending;
+                    }
+                }
+                app_guard
+                    .logs
+                    .add_entry(LogLevel::Error, format!("Failed to submit job: {}", e));
+            }
+        }
+
+        // Mirror results
+        if let Ok(records) = client.results(result_seq).await {
+            let mut app_guard = app.lock().unwrap();
+            for record in records {
+                result_seq = record.seq;
+                let status = if record.success {
+                    TargetStatus::Completed
+                } else {
+                    TargetStatus::Failed
+                };
+                match app_guard
+                    .targets
+                    .iter_mut()
+                    .find(|t| t.status == TargetStatus::InProgress && t.url == record.url)
+                {
+                    Some(target) => {
+                        target.status = status;
+                        target.response_time = Some(record.response_time);
+                        target.status_code = record.status_code;
+                    }
+                    None => app_guard.targets.push_back(Target {
+                        url: record.url.clone(),
+                        status,
+                        response_time: Some(record.response_time),
+                        status_code: record.status_code,
+                        trace: None,
+                    }),
+                }
+
+                let metrics = &mut app_guard.metrics;
+                metrics.total_requests += 1;
+                if record.success {
+                    metrics.total_successful += 1;
+                } else {
+                    metrics.total_failed += 1;
+                }
+                metrics.request_timestamps.push_back(Instant::now());
+                metrics.data_processed += record.content_length as u64;
+                if record.success {
+                    // The daemon doesn't report time to first byte
+                    metrics.histograms.record(
+                        Duration::from_millis(record.response_time),
+                        None,
+                        record.content_length,
+                    );
+                }
+                metrics.success_rate.push_back(if record.success { 1.0 } else { 0.0 });
+                if metrics.success_rate.len() > 60 {
+                    metrics.success_rate.pop_front();
+                }
+
+                app_guard.record_result(ScrapedData {
+                    url: record.url,
+                    timestamp: record.timestamp,
+                    content: String::new(),
+                    status_code: record.status_code,
+                    headers: HashMap::new(),
+                    response_time: record.response_time,
+                    content_length: record.content_length,
+                    content_type: None,
+                    title: None,
+                    excerpt: None,
+                    canonical_url: None,
+                    success: record.success,
+                    error: record.error,
+                });
+            }
+        }
+
+        // Report readiness changes, about every 10 seconds
+        polls = polls.wrapping_add(1);
+        if polls % 20 == 1 {
+            if let Ok(report) = client.readiness().await {
+                if report.is_ok() != ready {
+                    ready = report.is_ok();
+                    let (level, message) = if ready {
+                        (LogLevel::Success, "Daemon is ready again".to_string())
+                    } else {
+                        let failing: Vec<String> = report
+                            .checks
+                            .iter()
+                            .filter(|check| check.status == control::CheckStatus::Fail)
+                            .map(|check| format!("{}: {}", check.name, check.detail))
+                            .collect();
+                        (LogLevel::Warning, format!("Daemon not ready ({})", failing.join("; ")))
+                    };
+                    app.lock().unwrap().logs.add_entry(level, message);
+                }
+            }
+        }
+
+        // Mirror daemon logs
+        if let Ok(lines) = client.logs(log_seq).await {
+            let mut app_guard = app.lock().unwrap();
+            for line in lines {
+                log_seq = line.seq;
+                let level = match line.level {
+                    control::ControlLogLevel::Info => LogLevel::Info,
+                    control::ControlLogLevel::Warning => LogLevel::Warning,
+                    control::ControlLogLevel::Error => LogLevel::Error,
+                    control::ControlLogLevel::Success => LogLevel::Success,
+                };
+                app_guard
+                    .logs
+                    .add_entry(level, format!("[daemon] {}", line.message));
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
 #[instrument(skip(f, app))]
 fn render_dashboard(f: &mut Frame, app: &AppState) {
     if app.show_banner {
         let banner = Paragraph::new("🕸️  Unstoppable Scraper v0.9.3")
-            .style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))
+            .style(Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(banner, f.area());
         return;
     }
 
     info!("Rendering dashboard");
-    let constraints = if app.input_mode {
+    let constraints = if app.input_mode || app.log_filter_mode {
         vec![Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)]
     } else {
         vec![Constraint::Length(3), Constraint::Min(0)]
@@ -696,16 +1181,16 @@ fn render_dashboard(f: &mut Frame, app: &AppState) {
         .constraints(constraints)
         .split(f.area());
 
-    let tabs = Tabs::new(vec!["Overview", "Metrics", "Proxies", "Logs", "Targets", "Export", "Settings"])
+    let tabs = Tabs::new(vec!["Overview", "Metrics", "Proxies", "Logs", "Targets", "Export", "Settings", "Jobs"])
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .title("Swoop Dashboard"),
         )
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(app.theme.text))
         .highlight_style(
             Style::default()
-                .fg(Color::Yellow)
+                .fg(app.theme.highlight)
                 .add_modifier(Modifier::BOLD),
         )
         .select(app.current_tab);
@@ -718,20 +1203,64 @@ fn render_dashboard(f: &mut Frame, app: &AppState) {
         3 => render_logs(f, chunks[1], app),
         4 => render_targets(f, chunks[1], app),
         5 => render_export(f, chunks[1], app),
+        7 => render_jobs(f, chunks[1], app),
         _ => {}
     }
 
-    if app.input_mode {
+    if app.input_mode || app.log_filter_mode {
         render_input_box(f, chunks[2], app);
     }
+    if app.show_help {
+        render_help(f, app);
+    }
+}
+
+/// Active key bindings, over the middle of the screen
+fn render_help(f: &mut Frame, app: &AppState) {
+    let help = app.keymap.help();
+    let key_width = help.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    let description_width = help
+        .iter()
+        .map(|(_, description)| description.chars().count())
+        .max()
+        .unwrap_or(0);
+    // Two columns two spaces apart, inside the borders
+    let screen = f.area();
+    let width = ((key_width + description_width + 4) as u16).max(26).min(screen.width);
+    let height = (help.len() as u16 + 2).min(screen.height);
+    let area = Rect::new(
+        screen.x + (screen.width - width) / 2,
+        screen.y + (screen.height - height) / 2,
+        width,
+        height,
+    );
+
+    let text = help
+        .iter()
+        .map(|(keys, description)| format!("{:<width$}  {}", keys, description, width = key_width))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let overlay = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Keys (any key to close)")
+            .border_style(Style::default().fg(app.theme.accent)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(overlay, area);
 }
 
 fn render_input_box(f: &mut Frame, area: Rect, app: &AppState) {
-    let input = Paragraph::new(app.input_buffer.as_str())
-        .style(Style::default().fg(Color::Yellow))
-        .block(Block::default().borders(Borders::ALL).title("Input URLs (Press Esc to cancel, Enter to submit)"));
+    let (text, title) = if app.log_filter_mode {
+        (&app.logs.filter, "Filter logs (Press Esc to clear, Enter to keep)")
+    } else {
+        (&app.input_buffer, "Input URLs (Press Esc to cancel, Enter to submit)")
+    };
+    let input = Paragraph::new(text.as_str())
+        .style(Style::default().fg(app.theme.highlight))
+        .block(Block::default().borders(Borders::ALL).title(title));
     f.render_widget(input, area);
-    f.set_cursor_position((area.x + app.input_buffer.len() as u16 + 1, area.y + 1));
+    f.set_cursor_position((area.x + text.chars().count() as u16 + 1, area.y + 1));
 }
 
 #[instrument(skip(f, app))]
@@ -758,8 +1287,12 @@ fn render_overview(f: &mut Frame, area: Rect, app: &AppState) {
     } else {
         "🟢 RUNNING"
     };
+    let engine_text = match &app.attached_to {
+        Some(addr) => format!("Daemon ({})", addr),
+        None => "Local".to_string(),
+    };
 
-    let active_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let active_style = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD);
 
     let system_status_block = if app.focused_pane == FocusedPane::SystemStatus {
         Block::default().title("▶ System Status").borders(Borders::ALL).border_style(active_style)
@@ -767,9 +1300,23 @@ fn render_overview(f: &mut Frame, area: Rect, app: &AppState) {
         Block::default().title("System Status").borders(Borders::ALL)
     };
 
+    let keys = &app.keymap;
     let system_status = Paragraph::new(format!(
-        "System Status: {}\n\nControls:\n• Press 'q' to quit\n• Press 'i' to input URLs\n• Press 'Space' to pause/resume\n• Press '+/-' to adjust RPS\n• Press 'l' to load URLs from file\n• Press 'Tab'/'Shift+Tab' to switch tabs\n• Press '←/→' to navigate panes",
-        status_text
+        "System Status: {}\nEngine: {}\n\nControls:\n• Press '{}' to quit\n• Press '{}' to input URLs\n• Press '{}' to pause/resume\n• Press '{}'/'{}' to adjust RPS\n• Press '{}' to load URLs from file\n• Press '{}'/'{}' to switch tabs\n• Press '{}' on Targets to inspect a fetch trace\n• Press '{}'/'{}' to navigate panes\n• Press '{}' for all keys",
+        status_text,
+        engine_text,
+        keys.keys(Action::Quit),
+        keys.keys(Action::AddUrls),
+        keys.keys(Action::TogglePause),
+        keys.keys(Action::RateUp),
+        keys.keys(Action::RateDown),
+        keys.keys(Action::LoadUrls),
+        keys.keys(Action::NextTab),
+        keys.keys(Action::PrevTab),
+        keys.keys(Action::Select),
+        keys.keys(Action::PrevPane),
+        keys.keys(Action::NextPane),
+        keys.keys(Action::Help)
     ))
     .block(system_status_block)
     .wrap(Wrap { trim: true });
@@ -802,11 +1349,17 @@ fn render_overview(f: &mut Frame, area: Rect, app: &AppState) {
 
     let proxy_status = &app.proxy_status;
     let db_status = "🟢 Healthy";
+    let pool = swoop_core::client::pool().stats();
     let proxy_text = format!(
-        "Proxy Pool:\n- Total: {}\n- Active: {}\n- Failed: {}\n\nDB Status: {}",
+        "Proxy Pool:\n- Total: {}\n- Active: {}\n- Failed: {}\n\nHTTP Clients: {} ({} idle)\n- In Flight: {}\n- DNS Cache: {} hits / {} misses\n\nDB Status: {}",
         proxy_status.total_proxies,
         proxy_status.active_proxies,
         proxy_status.failed_proxies,
+        pool.clients,
+        pool.idle_clients,
+        pool.in_flight,
+        pool.dns_cache_hits,
+        pool.dns_cache_misses,
         db_status
     );
 
@@ -830,10 +1383,10 @@ fn render_overview(f: &mut Frame, area: Rect, app: &AppState) {
         .take(10)
         .map(|entry| {
             let style = match entry.level {
-                LogLevel::Info => Style::default().fg(Color::Cyan),
-                LogLevel::Warning => Style::default().fg(Color::Yellow),
-                LogLevel::Error => Style::default().fg(Color::LightRed),
-                LogLevel::Success => Style::default().fg(Color::LightGreen),
+                LogLevel::Info => Style::default().fg(app.theme.accent),
+                LogLevel::Warning => Style::default().fg(app.theme.warning),
+                LogLevel::Error => Style::default().fg(app.theme.error),
+                LogLevel::Success => Style::default().fg(app.theme.success),
             };
             ListItem::new(entry.message.clone()).style(style)
         })
@@ -865,7 +1418,7 @@ fn render_metrics(f: &mut Frame, area: Rect, app: &AppState) {
     let metrics = &app.metrics;
     if metrics.total_requests == 0 {
         let msg = Paragraph::new("📊 0 metrics yet — waiting for first scrape…")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(app.theme.muted))
             .block(Block::default().borders(Borders::ALL).title("Metrics"));
         f.render_widget(msg, area);
         return;
@@ -881,7 +1434,7 @@ fn render_metrics(f: &mut Frame, area: Rect, app: &AppState) {
 
         let dataset = Dataset::default()
             .marker(ratatui::symbols::Marker::Braille)
-            .style(Style::default().fg(Color::Cyan))
+            .style(Style::default().fg(app.theme.accent))
             .data(&data);
 
         let chart = Chart::new(vec![dataset])
@@ -894,13 +1447,13 @@ fn render_metrics(f: &mut Frame, area: Rect, app: &AppState) {
                 Axis::default()
                     .title("Time (seconds)")
                     .bounds([0.0, 60.0])
-                    .style(Style::default().fg(Color::Gray)),
+                    .style(Style::default().fg(app.theme.muted)),
             )
             .y_axis(
                 Axis::default()
                     .title("Requests/sec")
                     .bounds([0.0, 10.0])
-                    .style(Style::default().fg(Color::Gray)),
+                    .style(Style::default().fg(app.theme.muted)),
             );
         f.render_widget(chart, top_chunks[0]);
     }
@@ -915,7 +1468,7 @@ fn render_metrics(f: &mut Frame, area: Rect, app: &AppState) {
 
             let dataset = Dataset::default()
                 .marker(ratatui::symbols::Marker::Braille)
-                .style(Style::default().fg(Color::Green))
+                .style(Style::default().fg(app.theme.success))
                 .data(&data);
 
             let chart = Chart::new(vec![dataset])
@@ -928,49 +1481,79 @@ fn render_metrics(f: &mut Frame, area: Rect, app: &AppState) {
                     Axis::default()
                         .title("Time (seconds)")
                         .bounds([0.0, 60.0])
-                        .style(Style::default().fg(Color::Gray)),
+                        .style(Style::default().fg(app.theme.muted)),
                 )
                 .y_axis(
                     Axis::default()
                         .title("Success %")
                         .bounds([80.0, 100.0])
-                        .style(Style::default().fg(Color::Gray)),
+                        .style(Style::default().fg(app.theme.muted)),
                 );
             f.render_widget(chart, top_chunks[1]);
     }
 
-    if !metrics.response_time.is_empty() {
-        let data: Vec<(f64, f64)> = metrics
-            .response_time
-                .iter()
-                .enumerate()
-                .map(|(i, &value)| (i as f64, value))
-                .collect();
+    let header = Row::new(
+        ["", "p50", "p90", "p99", "max", "count"]
+            .iter()
+            .map(|h| Cell::from(*h).style(Style::default().fg(app.theme.highlight))),
+    )
+    .bottom_margin(1);
+    let ms = |value: u64| format!("{}ms", value);
+    let rows = [
+        ("Total latency", metrics.histograms.latency(), &ms as &dyn Fn(u64) -> String),
+        ("Time to first byte", metrics.histograms.ttfb(), &ms),
+        ("Body size", metrics.histograms.body_size(), &format_bytes),
+    ]
+    .into_iter()
+    .map(|(name, quantiles, format)| quantile_row(name, quantiles, format, &app.theme));
 
-            let dataset = Dataset::default()
-                .marker(ratatui::symbols::Marker::Braille)
-                .style(Style::default().fg(Color::Yellow))
-                .data(&data);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(20),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(10),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title("Latency & Size Percentiles")
+            .borders(Borders::ALL),
+    );
+    f.render_widget(table, chunks[1]);
+}
 
-            let chart = Chart::new(vec![dataset])
-                .block(
-                    Block::default()
-                        .title("Response Time (ms)")
-                        .borders(Borders::ALL),
-                )
-                .x_axis(
-                    Axis::default()
-                        .title("Time (seconds)")
-                        .bounds([0.0, 60.0])
-                        .style(Style::default().fg(Color::Gray)),
-                )
-                .y_axis(
-                    Axis::default()
-                        .title("Response Time (ms)")
-                        .bounds([0.0, 600.0])
-                        .style(Style::default().fg(Color::Gray)),
-                );
-            f.render_widget(chart, chunks[1]);
+fn quantile_row<'a>(
+    name: &'a str,
+    quantiles: Quantiles,
+    format: &dyn Fn(u64) -> String,
+    theme: &Theme,
+) -> Row<'a> {
+    if quantiles.count == 0 {
+        return Row::new(vec![
+            Cell::from(name),
+            Cell::from("N/A").style(Style::default().fg(theme.muted)),
+        ]);
+    }
+    Row::new(vec![
+        Cell::from(name),
+        Cell::from(format(quantiles.p50)),
+        Cell::from(format(quantiles.p90)),
+        Cell::from(format(quantiles.p99)),
+        Cell::from(format(quantiles.max)),
+        Cell::from(quantiles.count.to_string()),
+    ])
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{}B", bytes),
+        1024..=1_048_575 => format!("{:.1}KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1}MB", bytes as f64 / 1_048_576.0),
     }
 }
 
@@ -983,11 +1566,11 @@ fn render_proxies(f: &mut Frame, area: Rect, app: &AppState) {
     let proxy_status = &app.proxy_status;
 
     let failure_color = if proxy_status.failed_proxies >= 50 {
-        Color::Red
+        app.theme.error
     } else if proxy_status.failed_proxies > 0 {
-        Color::Rgb(255, 165, 0) // Orange
+        app.theme.warning
     } else {
-        Color::Green
+        app.theme.success
     };
 
     let failure_text = format!(
@@ -1046,15 +1629,13 @@ fn render_proxies(f: &mut Frame, area: Rect, app: &AppState) {
 fn render_logs(f: &mut Frame, area: Rect, app: &AppState) {
     let logs = &app.logs;
     let log_items: Vec<ListItem> = logs
-        .entries
-        .iter()
-        .rev()
+        .visible()
         .map(|entry| {
             let style = match entry.level {
-                LogLevel::Info => Style::default().fg(Color::Cyan),
-                LogLevel::Warning => Style::default().fg(Color::Yellow),
-                LogLevel::Error => Style::default().fg(Color::LightRed),
-                LogLevel::Success => Style::default().fg(Color::LightGreen),
+                LogLevel::Info => Style::default().fg(app.theme.accent),
+                LogLevel::Warning => Style::default().fg(app.theme.warning),
+                LogLevel::Error => Style::default().fg(app.theme.error),
+                LogLevel::Success => Style::default().fg(app.theme.success),
             };
 
             let elapsed = entry.timestamp.elapsed();
@@ -1071,26 +1652,56 @@ fn render_logs(f: &mut Frame, area: Rect, app: &AppState) {
     let mut list_state = ListState::default();
     list_state.select(Some(logs.scroll_position));
 
+    let check = |shown: bool| if shown { "✓" } else { "✗" };
+    let mut title = format!(
+        "System Logs [info {} warn {} error {}]",
+        check(logs.show_info),
+        check(logs.show_warnings),
+        check(logs.show_errors)
+    );
+    if !logs.filter.is_empty() {
+        title.push_str(&format!(" [/{}]", logs.filter));
+    }
+    match logs.paused_at {
+        Some(_) => title.push_str(&format!(
+            " — paused, {} new ('{}' to follow)",
+            logs.new_while_paused(),
+            app.keymap.keys(Action::LogFollow)
+        )),
+        None => title.push_str(" — following"),
+    }
+
     let logs_widget = List::new(log_items)
-        .block(Block::default().title("System Logs").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White))
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(app.theme.text))
         .highlight_style(Style::default().add_modifier(Modifier::BOLD))
         .highlight_symbol(">> ");
     f.render_stateful_widget(logs_widget, area, &mut list_state);
 }
 
 fn render_targets(f: &mut Frame, area: Rect, app: &AppState) {
+    let area = if app.inspecting_target {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        render_target_inspector(f, chunks[1], app);
+        chunks[0]
+    } else {
+        area
+    };
+
     let header_cells = ["URL", "Status", "Response Time", "Status Code"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+        .map(|h| Cell::from(*h).style(Style::default().fg(app.theme.highlight)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
     let rows = app.targets.iter().map(|target| {
         let status_style = match target.status {
-            TargetStatus::Pending => Style::default().fg(Color::DarkGray),
-            TargetStatus::InProgress => Style::default().fg(Color::Blue),
-            TargetStatus::Completed => Style::default().fg(Color::Green),
-            TargetStatus::Failed => Style::default().fg(Color::Red),
+            TargetStatus::Pending => Style::default().fg(app.theme.muted),
+            TargetStatus::InProgress => Style::default().fg(app.theme.running),
+            TargetStatus::Completed => Style::default().fg(app.theme.success),
+            TargetStatus::Failed => Style::default().fg(app.theme.error),
         };
         let status_text = format!("{:?}", target.status);
         let response_time_text = target.response_time.map_or("N/A".to_string(), |t| format!("{}ms", t));
@@ -1124,6 +1735,72 @@ fn render_targets(f: &mut Frame, area: Rect, app: &AppState) {
     f.render_stateful_widget(table, area, &mut table_state);
 }
 
+/// Fetch trace of the selected target
+fn render_target_inspector(f: &mut Frame, area: Rect, app: &AppState) {
+    let target = app.targets.get(app.target_scroll);
+    let text = match target.and_then(|target| target.trace.as_ref()) {
+        Some(trace) => trace_lines(trace).join("\n"),
+        None if target.is_some() => "No fetch trace recorded for this target yet".to_string(),
+        None => "No target selected".to_string(),
+    };
+
+    let inspector = Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Inspector (Enter to close)"),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(inspector, area);
+}
+
+fn trace_lines(trace: &FetchTrace) -> Vec<String> {
+    let or_na = |value: Option<String>| value.unwrap_or_else(|| "N/A".to_string());
+    let ms = |value: Option<u64>| or_na(value.map(|ms| format!("{}ms", ms)));
+
+    let mut lines = vec![
+        format!("URL:            {}", trace.url),
+        format!("Final URL:      {}", or_na(trace.final_url.clone())),
+        format!(
+            "Status:         {} ({} attempt(s))",
+            or_na(trace.status.map(|status| status.to_string())),
+            trace.attempts
+        ),
+        format!("Resolved IPs:   {}", trace.resolved_ips.join(", ")),
+        format!("Remote address: {}", or_na(trace.remote_addr.clone())),
+        format!("Proxy:          {}", or_na(trace.proxy.clone())),
+        format!("TLS profile:    {}", or_na(trace.tls_profile.clone())),
+        format!(
+            "Timing:         dns {} · ttfb {} · download {} · total {}ms",
+            ms(trace.timing.dns_ms),
+            ms(trace.timing.ttfb_ms),
+            ms(trace.timing.download_ms),
+            trace.timing.total_ms
+        ),
+        format!("Classification: {}", trace.classification.join(", ")),
+    ];
+    if let Some(error) = &trace.error {
+        lines.push(format!("Error:          {}", error));
+    }
+    if !trace.redirects.is_empty() {
+        lines.push("Redirects:".to_string());
+        lines.extend(
+            trace
+                .redirects
+                .iter()
+                .map(|hop| format!("  {} {} → {}", hop.status, hop.url, hop.location)),
+        );
+    }
+    lines.push("Response headers:".to_string());
+    lines.extend(
+        trace
+            .response_headers
+            .iter()
+            .map(|(name, value)| format!("  {}: {}", name, value)),
+    );
+    lines
+}
+
 fn render_export(f: &mut Frame, area: Rect, app: &AppState) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -1136,10 +1813,12 @@ fn render_export(f: &mut Frame, area: Rect, app: &AppState) {
 
     let export_state = &app.export_state;
     let controls_text = format!(
-        "Export Controls:\n\n• Format: {}\n• File: {}\n• Status: {}\n\nPress 'Enter' to export data\nPress 'f' to toggle format (JSON/CSV)",
+        "Export Controls:\n\n• Format: {}\n• File: {}\n• Status: {}\n\nPress '{}' to export data\nPress '{}' to toggle format (JSON/CSV)",
         export_state.format.as_str(),
         export_state.file_path,
-        export_state.status
+        export_state.status,
+        app.keymap.keys(Action::Select),
+        app.keymap.keys(Action::ExportFormat)
     );
 
     let controls = Paragraph::new(controls_text)
@@ -1169,14 +1848,16 @@ fn render_export(f: &mut Frame, area: Rect, app: &AppState) {
 
     let recent_list = List::new(recent_items)
         .block(Block::default().title("Recent Exports").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White))
+        .style(Style::default().fg(app.theme.text))
         .highlight_style(Style::default().add_modifier(Modifier::BOLD))
         .highlight_symbol(">> ");
     f.render_stateful_widget(recent_list, chunks[2], &mut list_state);
 
     let summary_text = format!(
-        "Scraped Data Summary:\n\n• Total Entries: {}\n• Ready for Export",
-        app.scraped_data.len()
+        "Scraped Data Summary:\n\n• Total Entries: {}\n• Spilled to Disk: {}\n• Bodies in Memory: {:.1} MB\n• Ready for Export",
+        app.scraped_data.len(),
+        app.scraped_data.spilled(),
+        app.scraped_data.body_bytes() as f64 / (1024.0 * 1024.0)
     );
 
     let _summary = Paragraph::new(summary_text)
@@ -1184,10 +1865,245 @@ fn render_export(f: &mut Frame, area: Rect, app: &AppState) {
         .wrap(Wrap { trim: true });
 }
 
+fn render_jobs(f: &mut Frame, area: Rect, app: &AppState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(9)])
+        .split(area);
+
+    let header_cells = ["Name", "Status", "Started", "Duration", "Seeds", "OK", "Failed", "Success"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(app.theme.highlight)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = app.jobs.iter().map(|job| {
+        let status_style = match job.status {
+            storage::models::JobStatus::Running => Style::default().fg(app.theme.running),
+            storage::models::JobStatus::Completed => Style::default().fg(app.theme.success),
+            storage::models::JobStatus::Failed => Style::default().fg(app.theme.error),
+            storage::models::JobStatus::Interrupted => Style::default().fg(app.theme.warning),
+        };
+        Row::new(vec![
+            Cell::from(job.name.clone()),
+            Cell::from(job.status.as_str()).style(status_style),
+            Cell::from(job.started_at.format("%Y-%m-%d %H:%M").to_string()),
+            Cell::from(format!("{}s", job.duration().num_seconds().max(0))),
+            Cell::from(job.seeds.len().to_string()),
+            Cell::from(job.counts.successful.to_string()),
+            Cell::from(job.counts.failed.to_string()),
+            Cell::from(format!("{:.1}%", job.counts.success_rate())),
+        ])
+    });
+
+    let mut table_state = ratatui::widgets::TableState::default();
+    table_state.select(Some(app.jobs_scroll));
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Length(10),
+            Constraint::Length(17),
+            Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title("Job History"))
+    .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+    .highlight_symbol(">> ");
+    f.render_stateful_widget(table, chunks[0], &mut table_state);
+
+    let details_text = match app.jobs.get(app.jobs_scroll) {
+        Some(job) => {
+            let mut config: Vec<String> = job
+                .config
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            config.sort();
+            format!(
+                "ID: {}\nTags: {}\nRerun of: {}\nFirst seed: {}\nData: {} KB\nConfig: {}",
+                job.id,
+                if job.tags.is_empty() { "-".to_string() } else { job.tags.join(", ") },
+                job.rerun_of.as_deref().unwrap_or("-"),
+                job.seeds.first().map_or("-", String::as_str),
+                job.counts.bytes / 1024,
+                config.join(" ")
+            )
+        }
+        None => "No jobs recorded yet — runs from swoop-cli and the daemon appear here".to_string(),
+    };
+
+    let details = Paragraph::new(details_text)
+        .block(Block::default().title("Job Details").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(details, chunks[1]);
+}
+
+/// Periodically reload the job history from the local store
+async fn refresh_jobs(app: Arc<Mutex<AppState>>, state_dir: String) {
+    let store = match LocalStore::new(&state_dir).await {
+        Ok(store) => store,
+        Err(e) => {
+            app.lock().unwrap().logs.add_entry(
+                LogLevel::Warning,
+                format!("Job history unavailable ({}): {}", state_dir, e),
+            );
+            return;
+        }
+    };
+
+    loop {
+        match store.list_jobs(200).await {
+            Ok(jobs) => {
+                let mut app_guard = app.lock().unwrap();
+                app_guard.jobs_scroll = app_guard.jobs_scroll.min(jobs.len().saturating_sub(1));
+                app_guard.jobs = jobs;
+            }
+            Err(e) => error!("Failed to load job history: {}", e),
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    setup_logging().expect("Failed to set up logging.");
+    let matches = Command::new("swoop-tui")
+        .about("Swoop terminal dashboard")
+        .arg(
+            Arg::new("attach")
+                .long("attach")
+                .value_name("ADDR")
+                .num_args(0..=1)
+                .default_missing_value(control::DEFAULT_DAEMON_ADDR)
+                .help("Attach to a running `swoop daemon` instead of running a local engine"),
+        )
+        .arg(
+            Arg::new("api-key")
+                .long("api-key")
+                .value_name("KEY")
+                .requires("attach")
+                .help("API key of a tenant of the daemon (default: $SWOOP_API_KEY)"),
+        )
+        .arg(
+            Arg::new("state-dir")
+                .long("state-dir")
+                .value_name("DIR")
+                .default_value(storage::local_store::DEFAULT_LOCAL_ROOT)
+                .help("Directory holding local state such as job history"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Dashboard config file, e.g. key bindings (default: <state-dir>/tui.toml if it exists)"),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .value_name("THEME")
+                .value_parser(Theme::NAMES)
+                .help("Color theme, instead of the config file's (default: dark)"),
+        )
+        .arg(
+            Arg::new("max-results")
+                .long("max-results")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10000")
+                .help("Results kept in memory before older ones spill to disk"),
+        )
+        .arg(
+            Arg::new("max-body-mb")
+                .long("max-body-mb")
+                .value_name("MB")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("256")
+                .help("Page bodies kept in memory, in megabytes, before older results spill to disk"),
+        )
+        .arg(
+            Arg::new("spill-dir")
+                .long("spill-dir")
+                .value_name("DIR")
+                .help("Directory for spilled results (default: the system temp directory)"),
+        )
+        .arg(
+            Arg::new("policies")
+                .long("policies")
+                .value_name("FILE")
+                .conflicts_with("attach")
+                .help("Domain policy file, e.g. for safety filters (default: policies.toml)"),
+        )
+        .arg(
+            Arg::new("max-rss-mb")
+                .long("max-rss-mb")
+                .value_name("MB")
+                .value_parser(clap::value_parser!(u64))
+                .conflicts_with("attach")
+                .help("Cut concurrency while the dashboard uses more memory than this"),
+        )
+        .arg(
+            Arg::new("max-cpu")
+                .long("max-cpu")
+                .value_name("PERCENT")
+                .value_parser(parse_cpu_percent)
+                .conflicts_with("attach")
+                .help("Cut concurrency while the dashboard uses more of the host's CPU than this"),
+        )
+        .get_matches();
+    let attach_addr = matches.get_one::<String>("attach").cloned();
+    let api_key = matches.get_one::<String>("api-key").cloned();
+    let state_dir = matches.get_one::<String>("state-dir").cloned().unwrap_or_default();
+    let result_limits = BufferLimits {
+        max_entries: matches.get_one::<usize>("max-results").copied().unwrap_or_default().max(1),
+        max_body_bytes: matches.get_one::<usize>("max-body-mb").copied().unwrap_or_default()
+            * 1024
+            * 1024,
+        spill_dir: matches.get_one::<String>("spill-dir").map(PathBuf::from),
+    };
+    let resource_limits = ResourceLimits {
+        max_rss_bytes: matches.get_one::<u64>("max-rss-mb").map(|mb| mb * 1024 * 1024),
+        max_cpu_percent: matches.get_one::<f32>("max-cpu").copied(),
+    };
+
+    // A bad config file is reported before the terminal switches to raw mode
+    let config = match matches.get_one::<String>("config") {
+        Some(path) => TuiConfig::load(Path::new(path)),
+        None => {
+            let path = Path::new(&state_dir).join(config::DEFAULT_CONFIG_FILE);
+            if path.exists() {
+                TuiConfig::load(&path)
+            } else {
+                Ok(TuiConfig::default())
+            }
+        }
+    }
+    .map_err(io::Error::other)?;
+    let theme = match matches.get_one::<String>("theme") {
+        Some(name) => Theme::named_or_default(name),
+        None => config.theme,
+    };
+    let theme = theme.for_terminal(config.colors.unwrap_or_else(ColorSupport::detect));
+    let policies = match matches.get_one::<String>("policies").map(PathBuf::from) {
+        Some(path) => Some(path),
+        None if attach_addr.is_none() && Path::new(POLICY_FILE).exists() => {
+            Some(PathBuf::from(POLICY_FILE))
+        }
+        None => None,
+    }
+    .map(|path| {
+        PolicyResolver::from_file(path)
+            .map(Arc::new)
+            .map_err(|e| io::Error::other(format!("{:#}", e)))
+    })
+    .transpose()?;
+
+    let (forwarder, forwarded_logs) = LogForwarder::new();
+    setup_logging(forwarder).expect("Failed to set up logging.");
     info!("Swoop TUI starting up");
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
@@ -1201,14 +2117,54 @@ async fn main() -> io::Result<()> {
     enable_raw_mode()?;
     execute!(terminal.backend_mut(), EnterAlternateScreen)?;
 
-    let app = Arc::new(Mutex::new(AppState::new()));
+    let app = Arc::new(Mutex::new(AppState::new(result_limits, config.keymap, theme)));
     let app_clone = Arc::clone(&app);
 
-    tokio::spawn(async move {
-        scraping_engine(app_clone).await;
-    });
+    tokio::spawn(refresh_jobs(Arc::clone(&app), state_dir.clone()));
+
+    let shutdown = Shutdown::on_signal();
+    let local_engine = attach_addr.is_none();
+    match attach_addr {
+        Some(addr) => {
+            let mut client = control::ControlClient::new(&addr)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            if let Some(api_key) = &api_key {
+                client = client.with_api_key(api_key);
+            }
+            app.lock().unwrap().attached_to = Some(client.base_url().to_string());
+            tokio::spawn(attach_engine(app_clone, client));
+        }
+        None => {
+            let slots = Arc::new(Throttle::new(app.lock().unwrap().controls.concurrency));
+            if resource_limits.is_set() {
+                let guard = ResourceGuard::new(resource_limits)
+                    .with_throttle("concurrency", slots.clone());
+                let app = Arc::clone(&app);
+                tokio::spawn(guard.watch(move |action| {
+                    let level = match action {
+                        GuardAction::Throttled { .. } => LogLevel::Warning,
+                        GuardAction::Recovered { .. } => LogLevel::Info,
+                    };
+                    app.lock().unwrap().logs.add_entry(level, action.to_string());
+                }));
+            }
+            // Stopped bodies are quarantined with the job history
+            let quarantine = match LocalStore::new(&state_dir).await {
+                Ok(store) => Some(store),
+                Err(e) => {
+                    error!("Quarantine store unavailable ({}): {}", state_dir, e);
+                    None
+                }
+            };
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                scraping_engine(app_clone, shutdown, slots, policies, quarantine).await;
+            });
+        }
+    }
 
-    let res = run_app(&mut terminal, app).await;
+    let res = run_app(&mut terminal, Arc::clone(&app), &shutdown, forwarded_logs).await;
+    shutdown.request();
 
     info!("Swoop TUI shutting down");
     disable_raw_mode()?;
@@ -1218,14 +2174,124 @@ async fn main() -> io::Result<()> {
     if let Err(err) = res {
         println!("Error: {:?}", err);
     }
+    // An attached dashboard leaves the work to the daemon
+    if local_engine {
+        wind_down(&app, &state_dir).await;
+    }
 
     Ok(())
 }
 
-#[instrument(skip(terminal, app))]
+/// Give the local engine's requests in flight the grace period, then write
+/// out the results and checkpoint the targets left as an interrupted job
+async fn wind_down(app: &Arc<Mutex<AppState>>, state_dir: &str) {
+    let in_flight = || {
+        app.lock()
+            .unwrap()
+            .targets
+            .iter()
+            .filter(|target| target.status == TargetStatus::InProgress)
+            .count()
+    };
+    if in_flight() > 0 {
+        println!(
+            "🛑 Waiting up to {}s for {} requests in flight",
+            GRACE_PERIOD.as_secs(),
+            in_flight()
+        );
+    }
+    let deadline = Instant::now() + GRACE_PERIOD;
+    while in_flight() > 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    let app = app.lock().unwrap().clone();
+    let mut exports = Vec::new();
+    if !app.scraped_data.is_empty() {
+        let path = &app.export_state.file_path;
+        match write_export(&app.scraped_data, app.export_state.format, path) {
+            Ok(()) => {
+                println!("📄 {} results written to {}", app.scraped_data.len(), path);
+                exports.push(PathBuf::from(path));
+            }
+            Err(e) => eprintln!("⚠️  Failed to write results to {}: {}", path, e),
+        }
+    }
+
+    let left: Vec<String> = app
+        .targets
+        .iter()
+        .filter(|target| {
+            matches!(
+                target.status,
+                TargetStatus::Pending | TargetStatus::InProgress
+            )
+        })
+        .map(|target| target.url.clone())
+        .collect();
+    if left.is_empty() {
+        return;
+    }
+    let store = match LocalStore::new(state_dir).await {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("⚠️  Can't checkpoint the {} URLs left: {:#}", left.len(), e);
+            return;
+        }
+    };
+    let config = HashMap::from([
+        ("source".to_string(), "tui".to_string()),
+        (
+            "concurrency".to_string(),
+            app.controls.concurrency.to_string(),
+        ),
+    ]);
+    let seeds = app
+        .targets
+        .iter()
+        .map(|target| target.url.clone())
+        .collect();
+    let mut job = Job::new(
+        format!("tui-{}", Utc::now().format("%Y%m%d_%H%M%S")),
+        seeds,
+        config,
+    );
+    job.finish(
+        JobStatus::Interrupted,
+        JobCounts {
+            total: app.metrics.total_requests,
+            successful: app.metrics.total_successful,
+            failed: app.metrics.total_failed,
+            bytes: app.metrics.data_processed,
+        },
+    );
+    if let Err(e) = store.save_job(&job).await {
+        eprintln!("⚠️  Failed to record job {}: {:#}", job.id, e);
+    }
+    let checkpoint =
+        Checkpoint::new(&job.id, &job.name, left, job.counts.total).with_exports(exports);
+    match CheckpointStore::open(store.root()).save(&checkpoint) {
+        Ok(path) => {
+            println!(
+                "💾 Checkpoint of {} URLs left written to {}",
+                checkpoint.pending.len(),
+                path.display()
+            );
+            println!(
+                "▶️  Resume with: swoop jobs resume {}",
+                &job.id[..8.min(job.id.len())]
+            );
+        }
+        Err(e) => eprintln!("⚠️  Failed to write the checkpoint: {:#}", e),
+    }
+}
+
+#[instrument(skip(terminal, app, forwarded_logs))]
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: Arc<Mutex<AppState>>,
+    shutdown: &Shutdown,
+    forwarded_logs: Receiver<ForwardedLog>,
 ) -> io::Result<()> {
     info!("Entering main application loop");
     let mut event_stream = EventStream::new();
@@ -1251,6 +2317,16 @@ async fn run_app<B: ratatui::backend::Backend>(
             app_guard.metrics.requests_per_second.pop_front();
         }
 
+        // Events of the scrapers, storage and core crates
+        for log in forwarded_logs.try_iter() {
+            let level = match log.level {
+                tracing::Level::ERROR => LogLevel::Error,
+                tracing::Level::WARN => LogLevel::Warning,
+                _ => LogLevel::Info,
+            };
+            app_guard.logs.add_entry(level, format!("[{}] {}", log.source, log.message));
+        }
+
         // Handle export requests
         if app_guard.export_requested {
             app_guard.export_requested = false;
@@ -1284,10 +2360,16 @@ async fn run_app<B: ratatui::backend::Backend>(
 
         tokio::select! {
             _ = interval.tick() => {}
+            _ = shutdown.requested() => {
+                app.lock().unwrap().should_quit = true;
+            }
             maybe_event = event_stream.next() => {
                 if let Some(Ok(Event::Key(key))) = maybe_event {
-                    if key.kind == KeyEventKind::Press {
-                        app.lock().unwrap().handle_key_event(key.code);
+                    // Raw mode turns Ctrl-C into a key press instead of SIGINT
+                    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                        app.lock().unwrap().should_quit = true;
+                    } else if key.kind == KeyEventKind::Press {
+                        app.lock().unwrap().handle_key_event(key);
                     }
                 } else {
                     app.lock().unwrap().should_quit = true;
@@ -1325,32 +2407,7 @@ async fn export_data(mut app_state: AppState) {
         tokio::time::sleep(Duration::from_millis(20)).await;
     }
 
-    let export_result = match export_format {
-        ExportFormat::Json => {
-            let json_data = serde_json::to_string_pretty(&data_clone);
-            match json_data {
-                Ok(json) => fs::write(&file_path, json).map_err(|e| e.to_string()),
-                Err(e) => Err(e.to_string()),
-            }
-        }
-        ExportFormat::Csv => {
-            let mut csv_content = "URL,Timestamp,Status Code,Success,Response Time,Content Length,Title,Error\n".to_string();
-            for item in data_clone.iter() {
-                csv_content.push_str(&format!(
-                    "{},{},{},{},{},{},{},{}\n",
-                    item.url,
-                    item.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                    item.status_code.unwrap_or(0),
-                    item.success,
-                    item.response_time,
-                    item.content_length,
-                    item.title.as_deref().unwrap_or(""),
-                    item.error.as_deref().unwrap_or("")
-                ));
-            }
-            fs::write(&file_path, csv_content).map_err(|e| e.to_string())
-        }
-    };
+    let export_result = write_export(&data_clone, export_format, &file_path);
 
     app_state.export_state.is_exporting = false;
     match export_result {
@@ -1377,3 +2434,47 @@ async fn export_data(mut app_state: AppState) {
         }
     }
 }
+
+/// Write `data` to `file_path`; spilled results are read back from disk and
+/// written out one at a time
+fn write_export(
+    data: &ResultBuffer<ScrapedData>,
+    format: ExportFormat,
+    file_path: &str,
+) -> io::Result<()> {
+    fs::File::create(file_path).and_then(|file| match format {
+        ExportFormat::Json => spill::write_json(data, file),
+        ExportFormat::Csv => {
+            // Titles and excerpts contain commas and quotes, so fields are quoted as needed
+            let mut writer = csv::Writer::from_writer(file);
+            writer.write_record([
+                "URL",
+                "Timestamp",
+                "Status Code",
+                "Success",
+                "Response Time",
+                "Content Length",
+                "Content Type",
+                "Title",
+                "Excerpt",
+                "Error",
+            ])?;
+            data.try_for_each(|item| {
+                writer.write_record([
+                    item.url.clone(),
+                    item.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    item.status_code.unwrap_or(0).to_string(),
+                    item.success.to_string(),
+                    item.response_time.to_string(),
+                    item.content_length.to_string(),
+                    item.content_type.clone().unwrap_or_default(),
+                    item.title.clone().unwrap_or_default(),
+                    item.excerpt.clone().unwrap_or_default(),
+                    item.error.clone().unwrap_or_default(),
+                ])?;
+                Ok(())
+            })?;
+            writer.flush()
+        }
+    })
+}
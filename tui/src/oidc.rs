@@ -0,0 +1,614 @@
+//! OpenID Connect bearer tokens
+//!
+//! With `--oidc-issuer`, the daemon also accepts JWTs signed by that issuer,
+//! such as the access tokens an identity provider hands out to people and CI
+//! jobs. The issuer's signing keys are found through its discovery document
+//! and fetched again when a token names a key not seen yet. A token has to be
+//! signed with RS256 or ES256, unexpired, issued by the issuer and meant for
+//! `--oidc-audience`. Its roles come from the `swoop_role` claim and its
+//! tenant from `swoop_tenant`; either claim can be renamed, and may be a
+//! dotted path such as `realm_access.roles`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::Utc;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+use storage::tokens::Role;
+use tokio::sync::Mutex;
+
+/// Clock skew allowed on `exp` and `nbf`, in seconds
+const LEEWAY_SECS: i64 = 60;
+
+/// Least time between two fetches of the issuer's keys
+const KEYS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Issuer and claims to check tokens against
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub role_claim: String,
+    pub tenant_claim: String,
+}
+
+/// Caller named by a verified token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcIdentity {
+    /// Email, preferred user name or subject
+    pub subject: String,
+    pub roles: Vec<Role>,
+    pub tenant: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeySet {
+    keys: Vec<Jwk>,
+}
+
+/// Public key of the issuer, in the JWK format
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default, rename = "use")]
+    usage: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+struct CachedKeys {
+    keys: Vec<Jwk>,
+    fetched: Instant,
+}
+
+/// Checks JWTs against the keys of one issuer
+pub struct OidcVerifier {
+    config: OidcConfig,
+    client: reqwest::Client,
+    jwks_uri: String,
+    keys: Mutex<CachedKeys>,
+}
+
+impl std::fmt::Debug for OidcVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcVerifier")
+            .field("issuer", &self.config.issuer)
+            .field("jwks_uri", &self.jwks_uri)
+            .finish()
+    }
+}
+
+impl OidcVerifier {
+    /// Find the issuer's keys through its discovery document
+    pub async fn discover(config: OidcConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer.trim_end_matches('/')
+        );
+        let discovery: Discovery = fetch_json(&client, &url).await?;
+        if discovery.issuer != config.issuer {
+            return Err(format!(
+                "{} names issuer {}, not {}",
+                url, discovery.issuer, config.issuer
+            ));
+        }
+        let keys: KeySet = fetch_json(&client, &discovery.jwks_uri).await?;
+        Ok(Self {
+            config,
+            client,
+            jwks_uri: discovery.jwks_uri,
+            keys: Mutex::new(CachedKeys {
+                keys: keys.keys,
+                fetched: Instant::now(),
+            }),
+        })
+    }
+
+    /// Verifier trusting the keys of `key_set`, a JWKS document
+    #[cfg(test)]
+    pub(crate) fn with_key_set(config: OidcConfig, key_set: Value) -> Self {
+        let keys: KeySet = serde_json::from_value(key_set).expect("valid key set");
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            jwks_uri: "http://127.0.0.1:9/jwks".to_string(),
+            keys: Mutex::new(CachedKeys {
+                keys: keys.keys,
+                fetched: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.config.issuer
+    }
+
+    /// Check `token`'s signature and claims, returning who it names
+    pub async fn verify(&self, token: &str) -> Result<OidcIdentity, String> {
+        let Some((signed, signature)) = token.rsplit_once('.') else {
+            return Err("not a JWT".to_string());
+        };
+        let Some((header, payload)) = signed.split_once('.') else {
+            return Err("not a JWT".to_string());
+        };
+        let header: JwtHeader = serde_json::from_slice(&decode(header)?)
+            .map_err(|e| format!("invalid JWT header: {}", e))?;
+        let key = self.key(header.kid.as_deref(), &header.alg).await?;
+        verify_signature(&key, &header.alg, signed.as_bytes(), &decode(signature)?)?;
+
+        let claims: Map<String, Value> = serde_json::from_slice(&decode(payload)?)
+            .map_err(|e| format!("invalid JWT claims: {}", e))?;
+        check_claims(&self.config, &claims, Utc::now().timestamp())
+    }
+
+    /// The issuer key for a token header, fetching the key set again for unseen keys
+    async fn key(&self, kid: Option<&str>, alg: &str) -> Result<Jwk, String> {
+        let kty = match alg {
+            "RS256" => "RSA",
+            "ES256" => "EC",
+            _ => return Err(format!("unsupported JWT algorithm {}", alg)),
+        };
+        let find = |keys: &[Jwk]| {
+            keys.iter()
+                .filter(|key| key.kty == kty && key.usage.as_deref().unwrap_or("sig") == "sig")
+                .find(|key| kid.is_none() || key.kid.as_deref() == kid)
+                .cloned()
+        };
+        let mut cached = self.keys.lock().await;
+        if let Some(key) = find(&cached.keys) {
+            return Ok(key);
+        }
+        // Issuers publish new keys ahead of signing with them; one may just have rotated
+        if cached.fetched.elapsed() >= KEYS_REFRESH_INTERVAL {
+            let keys: KeySet = fetch_json(&self.client, &self.jwks_uri).await?;
+            *cached = CachedKeys {
+                keys: keys.keys,
+                fetched: Instant::now(),
+            };
+            if let Some(key) = find(&cached.keys) {
+                return Ok(key);
+            }
+        }
+        Err(format!("unknown signing key {}", kid.unwrap_or("(no kid)")))
+    }
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, String> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", url, e))
+}
+
+fn decode(part: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(part.trim_end_matches('='))
+        .map_err(|e| format!("invalid base64 in JWT: {}", e))
+}
+
+fn verify_signature(key: &Jwk, alg: &str, signed: &[u8], signature: &[u8]) -> Result<(), String> {
+    let field = |value: &Option<String>, name: &str| {
+        value
+            .as_deref()
+            .ok_or_else(|| format!("signing key lacks '{}'", name))
+            .and_then(decode)
+    };
+    let verified = match alg {
+        "RS256" => {
+            let n = field(&key.n, "n")?;
+            let e = field(&key.e, "e")?;
+            RsaPublicKeyComponents { n: &n, e: &e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, signed, signature)
+                .is_ok()
+        }
+        "ES256" => {
+            if key.crv.as_deref() != Some("P-256") {
+                return Err("ES256 signing key is not on P-256".to_string());
+            }
+            // Uncompressed point: 0x04, then x and y
+            let mut point = vec![0x04];
+            point.extend(field(&key.x, "x")?);
+            point.extend(field(&key.y, "y")?);
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                .verify(signed, signature)
+                .is_ok()
+        }
+        _ => false,
+    };
+    if verified {
+        Ok(())
+    } else {
+        Err("bad JWT signature".to_string())
+    }
+}
+
+fn check_claims(
+    config: &OidcConfig,
+    claims: &Map<String, Value>,
+    now: i64,
+) -> Result<OidcIdentity, String> {
+    if claims.get("iss").and_then(Value::as_str) != Some(config.issuer.as_str()) {
+        return Err("token is from another issuer".to_string());
+    }
+    let audience_matches = match claims.get("aud") {
+        Some(Value::String(audience)) => *audience == config.audience,
+        Some(Value::Array(audiences)) => audiences
+            .iter()
+            .any(|audience| audience.as_str() == Some(config.audience.as_str())),
+        _ => false,
+    };
+    if !audience_matches {
+        return Err(format!("token is not meant for {}", config.audience));
+    }
+    match claims.get("exp").and_then(Value::as_i64) {
+        Some(exp) if exp + LEEWAY_SECS > now => {}
+        Some(_) => return Err("token expired".to_string()),
+        None => return Err("token has no expiry".to_string()),
+    }
+    if claims
+        .get("nbf")
+        .and_then(Value::as_i64)
+        .is_some_and(|nbf| nbf - LEEWAY_SECS > now)
+    {
+        return Err("token not valid yet".to_string());
+    }
+
+    let mut roles: Vec<Role> = match claim(claims, &config.role_claim) {
+        Some(Value::String(names)) => names.split_whitespace().filter_map(Role::named).collect(),
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .filter_map(Role::named)
+            .collect(),
+        _ => Vec::new(),
+    };
+    roles.sort();
+    roles.dedup();
+    if roles.is_empty() {
+        return Err(format!("token grants no role in '{}'", config.role_claim));
+    }
+    let subject = ["email", "preferred_username", "sub"]
+        .iter()
+        .find_map(|name| claims.get(*name).and_then(Value::as_str))
+        .ok_or("token has no subject")?;
+    Ok(OidcIdentity {
+        subject: subject.to_string(),
+        roles,
+        tenant: claim(claims, &config.tenant_claim)
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+/// Claim at a dotted path, e.g. `realm_access.roles`
+fn claim<'a>(claims: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = claims.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, RsaKeyPair},
+    };
+    use serde_json::json;
+
+    pub(crate) fn config() -> OidcConfig {
+        OidcConfig {
+            issuer: "https://id.example.com".to_string(),
+            audience: "swoop".to_string(),
+            role_claim: "swoop_role".to_string(),
+            tenant_claim: "swoop_tenant".to_string(),
+        }
+    }
+
+    /// Claims `check_claims` accepts at time `now`
+    fn valid_claims(now: i64) -> Map<String, Value> {
+        let Value::Object(claims) = json!({
+            "iss": "https://id.example.com",
+            "aud": "swoop",
+            "sub": "ci",
+            "exp": now + 3600,
+            "nbf": now - 10,
+            "swoop_role": "submit",
+        }) else {
+            unreachable!()
+        };
+        claims
+    }
+
+    /// An ES256 issuer key pair, made up for the test
+    pub(crate) struct TestIssuer {
+        key_pair: EcdsaKeyPair,
+        rng: SystemRandom,
+    }
+
+    impl TestIssuer {
+        pub(crate) fn generate() -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                    .unwrap();
+            let key_pair = EcdsaKeyPair::from_pkcs8(
+                &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                pkcs8.as_ref(),
+                &rng,
+            )
+            .unwrap();
+            Self { key_pair, rng }
+        }
+
+        pub(crate) fn verifier(&self, config: OidcConfig) -> OidcVerifier {
+            // Uncompressed point: 0x04, then x and y
+            let point = &self.key_pair.public_key().as_ref()[1..];
+            let (x, y) = point.split_at(32);
+            OidcVerifier::with_key_set(
+                config,
+                json!({"keys": [{
+                    "kty": "EC",
+                    "kid": "ec",
+                    "crv": "P-256",
+                    "x": URL_SAFE_NO_PAD.encode(x),
+                    "y": URL_SAFE_NO_PAD.encode(y),
+                }]}),
+            )
+        }
+
+        pub(crate) fn sign(&self, claims: &Value) -> String {
+            let signed = unsigned(&json!({"alg": "ES256", "kid": "ec"}), claims);
+            let signature = self.key_pair.sign(&self.rng, signed.as_bytes()).unwrap();
+            format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+        }
+    }
+
+    fn unsigned(header: &Value, claims: &Value) -> String {
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    /// Swap the first signature byte for another
+    fn tamper(token: &str) -> String {
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let mut signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+        signature[0] ^= 0x01;
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    const TEST_RSA_KEY: &str = "
+MIIEvQIBADANBgkqhkiG9w0BAQEFAASCBKcwggSjAgEAAoIBAQCTVZmCb3CARMnx
+Q8hcBjV3zFTD5PyJDvEbXw6eD9skeTlDR3vBSa6WqYPFt5vJBSQJ4SUIlb4HCdty
+lci3FlMqWinAIxMSdoK3Kteuu3RnqrmHlOSWH8stcs9kLphO0LEL6mMrvj7z8VVG
+sehgwZiD0ax2NFlJoeE9VhxT8uFNaiX7Phq/Zyb+reNh6RvYik4AssgdfAxZT/6n
+NL99iwgy7zaQI7EgQEY4VzY85zwlsL/KWfELJaobgSi2RwggsCwenVKNK30YkrUl
+lwZdzqeQzrKHDiSiIlurtluFm1s1yz4JpcwkJn8/6fF0ZPB/rKwNcNO/PR0RQbGV
+1CCpjHqhAgMBAAECggEASJ11Ueyz0XxV3YPvYa+rtZYvJveaocCMA9YHYc7lzqWy
+m97E92Bl3JrCt/WFIf/XIclmE8Ody2FH18NMEDTAG40hq7F9ymk/hqBmeheoyRlD
+ZLf1K6qS3J0L60/twFaqE/nbE+mppFiLE5NGuT1heoKiUgX+BcTzuuPQhW2dLqZF
+Gz7d/CKD88Stirdm4POG37MPTB4py3FPhnOPuSVqWD+2pmkSUTyz8DLD2ztrlwNZ
+o9WHjv57u4Y0JaEx9szIc3PcCvl7xld7Qzhtklt2RGa1lCgV0LhM+l8khu7xmF0L
+Ho8TDv2N1qmv1TogTTBAjPxIRAdMu8oxn7ibzFU88wKBgQDP/oVQGS3oTIJV48/0
+M7ZftLBoC0Eu3uoxJucBs3BefV7hh/ygQPcBY1wb/g5H7BFps9ZkMTZsjg/bpC6D
+fFRgH5EgS+LT3BUTi15cv9texpkL2i3F3B+oiOj54FIgnUM7k36PS3CuWrL/YG8U
+ikMk4HekQIwpjQxatlSfti9ohwKBgQC1VvNkNxhdbt14sVds4wVE2Ko7JQGFNc0N
+rCe6mw+RM7oBcJkX4kcnwwEJ0NijbaiCSJABjyT/cJH6EkmIeV1F4sJBNDbfxgd0
+xxozyADO722iLrxVNXeLf7Bk+k3NPYNNTUqbzGCLzW4GQNHIImCmRRCCLQyi228P
+3lXKkFdVlwKBgDex8ubA508V9aykJRJCKqq0CqzBnsG7RUDGJwOIliVnMtn3fQk4
+fCvt/EMuo4pUx1DjrBwGJYmp/MsWyrHAwCNBMK5SQ7nxa99g6XKymT5mtaK72Jfr
+2rpZdvE6RSOgmNlCJJigXdsCRH4nx3bbkyEzzjW4Zw/VSiKcQkrGESnPAoGBALSF
+/QK0/mJ1LbErCpdxYRtqff0nF95e8uU7jslr+BFa5z7rstxd/OwOawg0ipYXYntg
+dUu4DuLkU84ZSm68MyiRV0TGECKw51dPpjwKTRO0svWThRVmhr2uvhGoRK+fHwlj
+enSi/zHtriw3dx+/aWvcsVSGOGAgBu5LLijbjLW9AoGAM2/DnqdJiN+Hhe50x5gD
+jQqfAG54izhbA54ONgLZoOkrKQI3G44iKn68XT0xvH3QJgjFdDjF1jr7pcmJ4G2s
+N5186/s/UBgR4YBeWM3cx2u6kGrJtNR5oClX3cuhl1ft3gGgQpCJcYap6HsVvSqN
+I22iQ5bDvPpxb6HcPyXB+IU=
+";
+
+    #[test]
+    fn checks_issuer_audience_and_lifetime() {
+        let now = Utc::now().timestamp();
+        let config = config();
+        let identity = check_claims(&config, &valid_claims(now), now).unwrap();
+        assert_eq!(identity.subject, "ci");
+        assert_eq!(identity.roles, vec![Role::Submit]);
+        assert_eq!(identity.tenant, None);
+
+        let with = |name: &str, value: Value| {
+            let mut claims = valid_claims(now);
+            claims.insert(name.to_string(), value);
+            check_claims(&config, &claims, now)
+        };
+        assert_eq!(
+            with("iss", json!("https://evil.example.com")).unwrap_err(),
+            "token is from another issuer"
+        );
+        assert_eq!(
+            with("aud", json!("other")).unwrap_err(),
+            "token is not meant for swoop"
+        );
+        assert!(with("aud", json!(["other", "swoop"])).is_ok());
+        assert_eq!(
+            with("exp", json!(now - LEEWAY_SECS - 1)).unwrap_err(),
+            "token expired"
+        );
+        // Within the allowed clock skew
+        assert!(with("exp", json!(now - 1)).is_ok());
+        assert_eq!(
+            with("nbf", json!(now + LEEWAY_SECS + 1)).unwrap_err(),
+            "token not valid yet"
+        );
+
+        let mut claims = valid_claims(now);
+        claims.remove("exp");
+        assert_eq!(
+            check_claims(&config, &claims, now).unwrap_err(),
+            "token has no expiry"
+        );
+    }
+
+    #[test]
+    fn needs_a_role() {
+        let now = Utc::now().timestamp();
+        let config = config();
+        let mut claims = valid_claims(now);
+        claims.remove("swoop_role");
+        assert_eq!(
+            check_claims(&config, &claims, now).unwrap_err(),
+            "token grants no role in 'swoop_role'"
+        );
+        // Names that aren't roles grant nothing
+        claims.insert("swoop_role".to_string(), json!(["owner"]));
+        assert!(check_claims(&config, &claims, now).is_err());
+    }
+
+    #[test]
+    fn reads_roles_and_tenant_at_dotted_paths() {
+        let now = Utc::now().timestamp();
+        let config = OidcConfig {
+            role_claim: "realm_access.roles".to_string(),
+            tenant_claim: "org.name".to_string(),
+            ..config()
+        };
+        let mut claims = valid_claims(now);
+        claims.insert(
+            "realm_access".to_string(),
+            json!({"roles": ["read", "offline_access", "admin", "read"]}),
+        );
+        claims.insert("org".to_string(), json!({"name": "search"}));
+        let identity = check_claims(&config, &claims, now).unwrap();
+        assert_eq!(identity.roles, vec![Role::Read, Role::Admin]);
+        assert_eq!(identity.tenant.as_deref(), Some("search"));
+
+        assert_eq!(claim(&claims, "realm_access.missing"), None);
+        assert_eq!(claim(&claims, "sub.name"), None);
+    }
+
+    #[tokio::test]
+    async fn verifies_es256_signatures() {
+        let issuer = TestIssuer::generate();
+        let verifier = issuer.verifier(config());
+        let claims = Value::Object(valid_claims(Utc::now().timestamp()));
+        let token = issuer.sign(&claims);
+        assert_eq!(verifier.verify(&token).await.unwrap().subject, "ci");
+
+        assert_eq!(
+            verifier.verify(&tamper(&token)).await.unwrap_err(),
+            "bad JWT signature"
+        );
+        // Signed by another key pair
+        let other = TestIssuer::generate().sign(&claims);
+        assert_eq!(
+            verifier.verify(&other).await.unwrap_err(),
+            "bad JWT signature"
+        );
+    }
+
+    #[tokio::test]
+    async fn verifies_rs256_signatures() {
+        let pkcs8 = base64::engine::general_purpose::STANDARD
+            .decode(TEST_RSA_KEY.replace('\n', ""))
+            .unwrap();
+        let key_pair = RsaKeyPair::from_pkcs8(&pkcs8).unwrap();
+        let public = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+        let verifier = OidcVerifier::with_key_set(
+            config(),
+            json!({"keys": [{
+                "kty": "RSA",
+                "kid": "rsa",
+                "use": "sig",
+                "n": URL_SAFE_NO_PAD.encode(&public.n),
+                "e": URL_SAFE_NO_PAD.encode(&public.e),
+            }]}),
+        );
+
+        let signed = unsigned(
+            &json!({"alg": "RS256", "kid": "rsa"}),
+            &Value::Object(valid_claims(Utc::now().timestamp())),
+        );
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &signature::RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                signed.as_bytes(),
+                &mut signature,
+            )
+            .unwrap();
+        let token = format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature));
+        assert_eq!(verifier.verify(&token).await.unwrap().subject, "ci");
+        assert_eq!(
+            verifier.verify(&tamper(&token)).await.unwrap_err(),
+            "bad JWT signature"
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_unsigned_and_hmac_tokens() {
+        let verifier = TestIssuer::generate().verifier(config());
+        let claims = Value::Object(valid_claims(Utc::now().timestamp()));
+
+        let none = format!("{}.", unsigned(&json!({"alg": "none"}), &claims));
+        assert_eq!(
+            verifier.verify(&none).await.unwrap_err(),
+            "unsupported JWT algorithm none"
+        );
+        // HS256 with the public key as the secret is the classic confusion attack
+        let hmac = format!(
+            "{}.{}",
+            unsigned(&json!({"alg": "HS256", "kid": "ec"}), &claims),
+            URL_SAFE_NO_PAD.encode([0u8; 32])
+        );
+        assert_eq!(
+            verifier.verify(&hmac).await.unwrap_err(),
+            "unsupported JWT algorithm HS256"
+        );
+        assert_eq!(
+            verifier.verify("not-a-token").await.unwrap_err(),
+            "not a JWT"
+        );
+    }
+}
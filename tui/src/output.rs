@@ -0,0 +1,201 @@
+//! Rotating export files
+//!
+//! Streaming exports (`jsonl`, `csv`) are appended to as results arrive.
+//! [`RotatingWriter`] moves on to a new numbered file once the current one
+//! reaches a size limit, gzips the finished one if asked to, and charges every
+//! byte it writes to the job's [`DiskQuota`]. `json` exports are one array,
+//! which [`JsonArrayWriter`] writes an element at a time instead.
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use storage::quota::{DiskQuota, QuotaWriter};
+
+/// When export files are rotated
+#[derive(Debug, Clone, Default)]
+pub struct Rotation {
+    /// Size a file may reach before the next one is started
+    pub max_bytes: Option<u64>,
+    /// Gzip files once they are rotated out
+    pub compress: bool,
+}
+
+/// Export file split into size-limited parts, see the [module docs](self)
+#[derive(Debug)]
+pub struct RotatingWriter {
+    base: PathBuf,
+    /// Written at the top of every part, e.g. a CSV header
+    header: Vec<u8>,
+    rotation: Rotation,
+    quota: Arc<DiskQuota>,
+    writer: BufWriter<File>,
+    current: PathBuf,
+    written: u64,
+    part: usize,
+    /// Parts rotated out, after compression
+    finished: Vec<PathBuf>,
+}
+
+impl RotatingWriter {
+    /// Start writing to `base`; later parts are named `<stem>.<n>.<ext>` next to it
+    pub fn create(
+        base: PathBuf,
+        header: Vec<u8>,
+        rotation: Rotation,
+        quota: Arc<DiskQuota>,
+    ) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(&base)?);
+        let mut output = Self {
+            current: base.clone(),
+            base,
+            header,
+            rotation,
+            quota,
+            writer,
+            written: 0,
+            part: 0,
+            finished: Vec::new(),
+        };
+        output.write_header()?;
+        Ok(output)
+    }
+
+    /// Append one record, rotating first if it would take the file past its size limit
+    ///
+    /// Fails without writing anything when the quota has no room for the record.
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let len = record.len() as u64;
+        let has_records = self.written > self.header.len() as u64;
+        if has_records
+            && self
+                .rotation
+                .max_bytes
+                .is_some_and(|max| self.written + len > max)
+        {
+            self.rotate()?;
+        }
+        self.quota.reserve(len).map_err(io::Error::other)?;
+        self.writer.write_all(record)?;
+        self.written += len;
+        Ok(())
+    }
+
+    /// Flush the current part and return every part written, oldest first
+    pub fn finish(&mut self) -> io::Result<Vec<PathBuf>> {
+        self.writer.flush()?;
+        let mut files = self.finished.clone();
+        files.push(self.current.clone());
+        Ok(files)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if self.header.is_empty() {
+            return Ok(());
+        }
+        self.quota
+            .reserve(self.header.len() as u64)
+            .map_err(io::Error::other)?;
+        self.writer.write_all(&self.header)?;
+        self.written = self.header.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.part += 1;
+        let next = part_path(&self.base, self.part);
+        let previous = std::mem::replace(&mut self.writer, BufWriter::new(File::create(&next)?));
+        previous
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        let rotated = std::mem::replace(&mut self.current, next);
+        let rotated = if self.rotation.compress {
+            self.compress(&rotated)?
+        } else {
+            rotated
+        };
+        self.finished.push(rotated);
+        self.write_header()
+    }
+
+    /// Replace `path` with `<path>.gz`, giving the space saved back to the quota
+    fn compress(&self, path: &Path) -> io::Result<PathBuf> {
+        let mut gz_name = path.as_os_str().to_owned();
+        gz_name.push(".gz");
+        let gz_path = PathBuf::from(gz_name);
+
+        let original = fs::metadata(path)?.len();
+        let mut encoder = GzEncoder::new(
+            BufWriter::new(File::create(&gz_path)?),
+            Compression::default(),
+        );
+        io::copy(&mut BufReader::new(File::open(path)?), &mut encoder)?;
+        encoder.finish()?.flush()?;
+        let compressed = fs::metadata(&gz_path)?.len();
+        fs::remove_file(path)?;
+
+        self.quota.release(original);
+        // The gzip file already exists; if artifacts took the freed space in
+        // the meantime, the refusal still marks the quota as exceeded
+        let _ = self.quota.reserve(compressed);
+        Ok(gz_path)
+    }
+}
+
+/// JSON array written to a file an element at a time, charged to a quota
+#[derive(Debug)]
+pub struct JsonArrayWriter {
+    path: PathBuf,
+    writer: BufWriter<QuotaWriter<File>>,
+    elements: usize,
+}
+
+impl JsonArrayWriter {
+    pub fn create(path: PathBuf, quota: Arc<DiskQuota>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(QuotaWriter::new(File::create(&path)?, quota));
+        writer.write_all(b"[")?;
+        Ok(Self {
+            path,
+            writer,
+            elements: 0,
+        })
+    }
+
+    pub fn write_element<T: Serialize>(&mut self, element: &T) -> io::Result<()> {
+        // Serialized up front, so a failure doesn't leave half an element
+        let element = serde_json::to_vec_pretty(element)?;
+        let separator: &[u8] = if self.elements == 0 { b"\n" } else { b",\n" };
+        self.writer.write_all(separator)?;
+        self.writer.write_all(&element)?;
+        self.elements += 1;
+        Ok(())
+    }
+
+    /// Elements written so far
+    pub fn elements(&self) -> usize {
+        self.elements
+    }
+
+    /// Close the array and flush it, returning the file's path
+    pub fn finish(&mut self) -> io::Result<PathBuf> {
+        let end: &[u8] = if self.elements == 0 { b"]\n" } else { b"\n]\n" };
+        self.writer.write_all(end)?;
+        self.writer.flush()?;
+        Ok(self.path.clone())
+    }
+}
+
+/// `<stem>.<part>.<ext>` next to `base`
+fn part_path(base: &Path, part: usize) -> PathBuf {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, part, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, part),
+    };
+    base.with_file_name(name)
+}
@@ -0,0 +1,200 @@
+//! Metadata-only URL scans
+//!
+//! `swoop probe` checks URL lists with [`Prober`]: `HEAD` requests, or a
+//! one-byte ranged `GET` where `HEAD` is refused, report each URL's status,
+//! content type and size without downloading a body. Results print as a
+//! table, JSON lines or CSV, in the order URLs answer.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use futures::{stream, StreamExt};
+use std::{collections::BTreeMap, fs, io::Write, time::Duration};
+use swoop_core::probe::{ProbeOptions, ProbeReport, Prober};
+
+/// `swoop probe` command definition
+pub fn command() -> Command {
+    Command::new("probe")
+        .about("Check URLs' status, content type and size without downloading them")
+        .arg(
+            Arg::new("url")
+                .action(ArgAction::Append)
+                .help("URLs to probe"),
+        )
+        .arg(
+            Arg::new("file")
+                .long("file")
+                .short('f')
+                .value_name("FILE")
+                .help("File of URLs to probe, one per line"),
+        )
+        .arg(
+            Arg::new("concurrency")
+                .long("concurrency")
+                .short('c')
+                .value_name("NUM")
+                .help("Requests in flight at once")
+                .default_value("200"),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECS")
+                .help("Time each URL gets, redirects included")
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("no-range-fallback")
+                .long("no-range-fallback")
+                .action(ArgAction::SetTrue)
+                .help("Don't retry servers that refuse HEAD with a ranged GET"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format: text, jsonl or csv")
+                .value_parser(["text", "jsonl", "csv"])
+                .default_value("text"),
+        )
+}
+
+/// Run `swoop probe`
+pub async fn run(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let mut urls: Vec<String> = matches
+        .get_many::<String>("url")
+        .map(|urls| urls.cloned().collect())
+        .unwrap_or_default();
+    if let Some(path) = matches.get_one::<String>("file") {
+        urls.extend(
+            fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+    if urls.is_empty() {
+        return Err("Give URLs to probe or --file".into());
+    }
+    let concurrency: usize = matches.get_one::<String>("concurrency").unwrap().parse()?;
+    let prober = Prober::new(ProbeOptions {
+        timeout: Duration::from_secs(matches.get_one::<String>("timeout").unwrap().parse()?),
+        ranged_fallback: !matches.get_flag("no-range-fallback"),
+        user_agent: Some(scrapers::ScraperConfig::default().user_agent),
+    })?;
+    let format = matches.get_one::<String>("format").unwrap().as_str();
+
+    let total = urls.len();
+    let started = std::time::Instant::now();
+    let mut reports = stream::iter(urls)
+        .map(|url| {
+            let prober = &prober;
+            async move { prober.probe(&url).await }
+        })
+        .buffer_unordered(concurrency.max(1));
+    let mut csv = (format == "csv").then(|| csv::Writer::from_writer(std::io::stdout()));
+    if let Some(writer) = &mut csv {
+        writer.write_record([
+            "url",
+            "status",
+            "method",
+            "content_type",
+            "content_length",
+            "final_url",
+            "last_modified",
+            "etag",
+            "elapsed_ms",
+            "error",
+        ])?;
+    }
+    // Status classes (2xx, 3xx, ...) and failed requests
+    let mut by_class: BTreeMap<String, usize> = BTreeMap::new();
+    while let Some(report) = reports.next().await {
+        let class = report
+            .status
+            .map_or("error".to_string(), |status| format!("{}xx", status / 100));
+        *by_class.entry(class).or_default() += 1;
+        match (format, &mut csv) {
+            ("jsonl", _) => {
+                let mut stdout = std::io::stdout().lock();
+                serde_json::to_writer(&mut stdout, &report)?;
+                writeln!(stdout)?;
+            }
+            (_, Some(writer)) => writer.write_record(csv_record(&report))?,
+            _ => print_report(&report),
+        }
+    }
+    if let Some(writer) = &mut csv {
+        writer.flush()?;
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+    let summary: Vec<String> = by_class
+        .iter()
+        .map(|(class, count)| format!("{} {}", count, class))
+        .collect();
+    eprintln!(
+        "\n📋 {} URL(s) probed in {:.1}s ({:.0}/s): {}",
+        total,
+        elapsed,
+        total as f64 / elapsed.max(0.001),
+        summary.join(", ")
+    );
+    Ok(())
+}
+
+fn print_report(report: &ProbeReport) {
+    let Some(status) = report.status else {
+        println!(
+            "❌ {}  {}",
+            report.url,
+            report.error.as_deref().unwrap_or("no answer")
+        );
+        return;
+    };
+    let icon = match status {
+        200..=299 => "✅",
+        300..=399 => "↪️ ",
+        _ => "⚠️ ",
+    };
+    let size = report.content_length.map_or("-".to_string(), |bytes| {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    });
+    let moved = match &report.final_url {
+        Some(final_url) if final_url != &report.url => format!("  → {}", final_url),
+        _ => String::new(),
+    };
+    println!(
+        "{} {}  {:<24} {:>10}  {}{}",
+        icon,
+        status,
+        report.content_type.as_deref().unwrap_or("-"),
+        size,
+        report.url,
+        moved
+    );
+}
+
+fn csv_record(report: &ProbeReport) -> [String; 10] {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    [
+        report.url.clone(),
+        report
+            .status
+            .map(|status| status.to_string())
+            .unwrap_or_default(),
+        report
+            .method
+            .map(|method| method.as_str().to_string())
+            .unwrap_or_default(),
+        text(&report.content_type),
+        report
+            .content_length
+            .map(|bytes| bytes.to_string())
+            .unwrap_or_default(),
+        text(&report.final_url),
+        text(&report.last_modified),
+        text(&report.etag),
+        report.elapsed_ms.to_string(),
+        text(&report.error),
+    ]
+}
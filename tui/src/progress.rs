@@ -0,0 +1,359 @@
+//! Live progress reporting for the CLI scraper
+//!
+//! Tracks completed requests while a scrape is running and renders them as:
+//! - An interactive progress bar on stderr (default)
+//! - Machine-readable JSON status lines on stdout (`--json-progress`)
+//! - Nothing at all (`--quiet`)
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Minimum interval between two JSON status lines
+const JSON_EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bar on screen, which log lines are written around
+static ACTIVE_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// How progress is reported while scraping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Interactive progress bar
+    Bar,
+    /// One JSON status object per line on stdout
+    Json,
+    /// No progress output
+    Quiet,
+}
+
+impl ProgressMode {
+    pub fn from_flags(quiet: bool, json_progress: bool) -> Self {
+        if quiet {
+            ProgressMode::Quiet
+        } else if json_progress {
+            ProgressMode::Json
+        } else {
+            ProgressMode::Bar
+        }
+    }
+
+    /// Whether human-readable output (banner, summary) should be printed
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, ProgressMode::Bar)
+    }
+
+    /// Default log level, chosen so log lines don't tear through the progress output
+    pub fn default_log_level(&self) -> LevelFilter {
+        match self {
+            ProgressMode::Bar | ProgressMode::Json => LevelFilter::WARN,
+            ProgressMode::Quiet => LevelFilter::ERROR,
+        }
+    }
+}
+
+/// Coarse failure category used for the live error counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCategory {
+    Timeout,
+    Connect,
+    Http4xx,
+    Http5xx,
+    Body,
+    Other,
+}
+
+impl ErrorCategory {
+    /// Classify a fetch error from its message
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if let Some(status) = message.strip_prefix("http ") {
+            return match status.chars().next() {
+                Some('4') => ErrorCategory::Http4xx,
+                Some('5') => ErrorCategory::Http5xx,
+                _ => ErrorCategory::Other,
+            };
+        }
+
+        if message.contains("timed out") || message.contains("timeout") {
+            ErrorCategory::Timeout
+        } else if message.contains("connect") || message.contains("dns") {
+            ErrorCategory::Connect
+        } else if message.contains("body") {
+            ErrorCategory::Body
+        } else {
+            ErrorCategory::Other
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Timeout => "timeout",
+            ErrorCategory::Connect => "connect",
+            ErrorCategory::Http4xx => "4xx",
+            ErrorCategory::Http5xx => "5xx",
+            ErrorCategory::Body => "body",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+/// Point-in-time progress snapshot, also the shape of `--json-progress` lines
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressStatus {
+    pub event: &'static str,
+    pub completed: u64,
+    pub total: u64,
+    pub successful: u64,
+    pub rps: f64,
+    pub success_rate: f64,
+    pub eta_secs: Option<u64>,
+    pub elapsed_secs: f64,
+    pub errors: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Debug)]
+struct ProgressState {
+    completed: u64,
+    successful: u64,
+    errors: BTreeMap<ErrorCategory, u64>,
+    last_emit: Option<Instant>,
+}
+
+/// Collects per-request outcomes and renders them according to the progress mode
+#[derive(Debug)]
+pub struct ProgressReporter {
+    mode: ProgressMode,
+    total: AtomicU64,
+    started: Instant,
+    bar: Option<ProgressBar>,
+    state: Mutex<ProgressState>,
+}
+
+impl ProgressReporter {
+    pub fn new(mode: ProgressMode, total: u64) -> Self {
+        let bar = (mode == ProgressMode::Bar).then(|| {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ETA {eta} {msg}",
+                )
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("█▉▊▋▌▍▎▏ "),
+            );
+            bar.enable_steady_tick(Duration::from_millis(120));
+            *ACTIVE_BAR.lock().unwrap() = Some(bar.clone());
+            bar
+        });
+
+        Self {
+            mode,
+            total: AtomicU64::new(total),
+            started: Instant::now(),
+            bar,
+            state: Mutex::new(ProgressState {
+                completed: 0,
+                successful: 0,
+                errors: BTreeMap::new(),
+                last_emit: None,
+            }),
+        }
+    }
+
+    /// Change the number of requests expected, for runs that only learn it
+    /// as they go
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        if let Some(bar) = &self.bar {
+            bar.set_length(total);
+        }
+    }
+
+    /// Record the outcome of a single request
+    pub fn record(&self, success: bool, error: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        state.completed += 1;
+        if success {
+            state.successful += 1;
+        } else {
+            let category = ErrorCategory::classify(error.unwrap_or_default());
+            *state.errors.entry(category).or_insert(0) += 1;
+        }
+
+        match self.mode {
+            ProgressMode::Bar => {
+                if let Some(bar) = &self.bar {
+                    let status = self.snapshot(&state, "progress");
+                    bar.set_position(status.completed);
+                    bar.set_message(format_status_message(&status));
+                }
+            }
+            ProgressMode::Json => {
+                let due = state
+                    .last_emit
+                    .is_none_or(|last| last.elapsed() >= JSON_EMIT_INTERVAL);
+                if due {
+                    state.last_emit = Some(Instant::now());
+                    emit_json(&self.snapshot(&state, "progress"));
+                }
+            }
+            ProgressMode::Quiet => {}
+        }
+    }
+
+    /// Finish the display and return the final status
+    pub fn finish(&self) -> ProgressStatus {
+        let state = self.state.lock().unwrap();
+        let status = self.snapshot(&state, "done");
+
+        match self.mode {
+            ProgressMode::Bar => {
+                if let Some(bar) = &self.bar {
+                    bar.finish_with_message(format_status_message(&status));
+                    ACTIVE_BAR.lock().unwrap().take();
+                }
+            }
+            ProgressMode::Json => emit_json(&status),
+            ProgressMode::Quiet => {}
+        }
+
+        status
+    }
+
+    fn snapshot(&self, state: &ProgressState, event: &'static str) -> ProgressStatus {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rps = if elapsed > 0.0 {
+            state.completed as f64 / elapsed
+        } else {
+            0.0
+        };
+        let success_rate = if state.completed > 0 {
+            state.successful as f64 / state.completed as f64 * 100.0
+        } else {
+            0.0
+        };
+        let total = self.total.load(Ordering::Relaxed);
+        let remaining = total.saturating_sub(state.completed);
+        let eta_secs = (rps > 0.0).then(|| (remaining as f64 / rps).ceil() as u64);
+
+        ProgressStatus {
+            event,
+            completed: state.completed,
+            total,
+            successful: state.successful,
+            rps,
+            success_rate,
+            eta_secs,
+            elapsed_secs: elapsed,
+            errors: state
+                .errors
+                .iter()
+                .map(|(category, count)| (category.as_str(), *count))
+                .collect(),
+        }
+    }
+}
+
+/// Compact one-line status shown next to the progress bar
+fn format_status_message(status: &ProgressStatus) -> String {
+    let mut message = format!("{:.1} req/s | {:.1}% ok", status.rps, status.success_rate);
+    if !status.errors.is_empty() {
+        let errors: Vec<String> = status
+            .errors
+            .iter()
+            .map(|(category, count)| format!("{}:{}", category, count))
+            .collect();
+        message.push_str(" | ");
+        message.push_str(&errors.join(" "));
+    }
+    message
+}
+
+fn emit_json(status: &ProgressStatus) {
+    if let Ok(line) = serde_json::to_string(status) {
+        println!("{}", line);
+    }
+}
+
+/// Log writer for stderr that hides the progress bar while a line is
+/// written, so log lines print above the bar instead of tearing through it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogWriter;
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogLine(Vec::new())
+    }
+}
+
+/// One log event, written out in one go when dropped
+pub struct LogLine(Vec<u8>);
+
+impl Write for LogLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogLine {
+    fn drop(&mut self) {
+        if self.0.is_empty() {
+            return;
+        }
+        let write = || {
+            let _ = io::stderr().write_all(&self.0);
+        };
+        match ACTIVE_BAR.lock().unwrap().as_ref() {
+            Some(bar) => bar.suspend(write),
+            None => write(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors() {
+        for (message, category) in [
+            ("HTTP 404", ErrorCategory::Http4xx),
+            ("http 503 Service Unavailable", ErrorCategory::Http5xx),
+            ("HTTP 302", ErrorCategory::Other),
+            ("operation timed out", ErrorCategory::Timeout),
+            ("Request Timeout after 30s", ErrorCategory::Timeout),
+            ("error trying to connect: refused", ErrorCategory::Connect),
+            ("DNS lookup failed", ErrorCategory::Connect),
+            ("error decoding response body", ErrorCategory::Body),
+            ("something else", ErrorCategory::Other),
+            ("", ErrorCategory::Other),
+        ] {
+            assert_eq!(ErrorCategory::classify(message), category, "{}", message);
+        }
+    }
+
+    #[test]
+    fn picks_the_mode_from_flags() {
+        assert_eq!(ProgressMode::from_flags(false, false), ProgressMode::Bar);
+        assert_eq!(ProgressMode::from_flags(false, true), ProgressMode::Json);
+        // Quiet wins over JSON progress
+        assert_eq!(ProgressMode::from_flags(true, true), ProgressMode::Quiet);
+        assert_eq!(ProgressMode::from_flags(true, false), ProgressMode::Quiet);
+    }
+}
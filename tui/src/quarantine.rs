@@ -0,0 +1,58 @@
+//! Bodies the `[safety]` filters stop
+//!
+//! `swoop scrape`, the daemon and the dashboard check every fetched body
+//! against the safety filters of its policy before extracting anything. A
+//! stopped body fails its URL; with the `quarantine` action it is also kept in
+//! the store for a later look instead of being dropped.
+
+use scrapers::safety::{SafetyAction, SafetyVerdict};
+use storage::{local_store::LocalStore, models::QuarantinedResponse, QuarantineStore};
+use tracing::warn;
+
+/// Tag of pages the `[safety]` filters stopped, followed by the hazard
+pub(crate) const UNSAFE_TAG: &str = "unsafe";
+
+/// Keep a body the safety filters stopped from going any further, in
+/// `quarantine` when the verdict says so, returning the error to report
+pub(crate) async fn stop_unsafe(
+    quarantine: Option<&LocalStore>,
+    url: &str,
+    content_type: Option<String>,
+    verdict: &SafetyVerdict,
+    body: &[u8],
+) -> String {
+    warn!(
+        "☣️  {} stopped as {}: {}",
+        url,
+        verdict.hazard.as_str(),
+        verdict.reason
+    );
+    let mut message = format!(
+        "{}: {} ({})",
+        verdict.action.as_str(),
+        verdict.reason,
+        verdict.hazard.as_str()
+    );
+    if verdict.action == SafetyAction::Quarantine {
+        match quarantine {
+            Some(store) => {
+                let record = QuarantinedResponse::new(
+                    url.to_string(),
+                    content_type,
+                    verdict.hazard.as_str().to_string(),
+                    verdict.reason.clone(),
+                    body,
+                );
+                match store.quarantine(&record, body).await {
+                    Ok(location) => message.push_str(&format!(", kept at {}", location)),
+                    Err(e) => {
+                        warn!("⚠️  Failed to quarantine {}: {:#}", url, e);
+                        message.push_str(", dropped as it couldn't be kept");
+                    }
+                }
+            }
+            None => message.push_str(", dropped without a quarantine store"),
+        }
+    }
+    message
+}
@@ -0,0 +1,78 @@
+//! Domains that have answered with 429 Too Many Requests
+//!
+//! Scrape runs record every domain that rate limited them in
+//! `<state-dir>/rate_limited.json`, so the aggressive profile can warn before
+//! hitting the same sites at full speed again.
+
+use chrono::{DateTime, Utc};
+use scrapers::utils::extract_domain;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+const HISTORY_FILE: &str = "rate_limited.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RateLimitRecord {
+    /// Number of 429 responses seen across all runs
+    pub count: u64,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RateLimitHistory {
+    path: PathBuf,
+    domains: BTreeMap<String, RateLimitRecord>,
+}
+
+impl RateLimitHistory {
+    /// Load the history kept in `state_dir`, starting empty if there is none
+    pub fn load(state_dir: &Path) -> Self {
+        let path = state_dir.join(HISTORY_FILE);
+        let domains = match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+                warn!("⚠️  Ignoring unreadable {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self { path, domains }
+    }
+
+    /// Note a 429 response from `domain`
+    pub fn record(&mut self, domain: &str) {
+        let now = Utc::now();
+        self.domains
+            .entry(domain.to_string())
+            .and_modify(|record| {
+                record.count += 1;
+                record.last_seen = now;
+            })
+            .or_insert(RateLimitRecord {
+                count: 1,
+                last_seen: now,
+            });
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.domains)?;
+        fs::write(&self.path, json)
+    }
+
+    /// Domains of `urls` that have rate limited us before
+    pub fn flagged(&self, urls: &[String]) -> Vec<(&str, &RateLimitRecord)> {
+        let mut flagged: Vec<(&str, &RateLimitRecord)> = urls
+            .iter()
+            .filter_map(|url| extract_domain(url).ok())
+            .filter_map(|domain| self.domains.get_key_value(&domain))
+            .map(|(domain, record)| (domain.as_str(), record))
+            .collect();
+        flagged.sort_by_key(|(domain, _)| *domain);
+        flagged.dedup_by_key(|(domain, _)| *domain);
+        flagged
+    }
+}
@@ -0,0 +1,177 @@
+//! Interaction recipes from the command line
+//!
+//! `swoop recipe FILE --webdriver URL` runs a recipe (see
+//! [`scrapers::recipes`]) in a browser, e.g. to search a site through its
+//! own search form, then extracts the result pages it captured and keeps
+//! them in the local store. `--trace DIR` saves a screenshot and the DOM
+//! after every step, with the step's timing, whether the recipe succeeds or not.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use tracing::warn;
+
+use scrapers::{
+    browser::{BrowserConfig, BrowserPool},
+    extractors::{extract_images, extract_links},
+    metadata,
+    platforms::ScraperRegistry,
+    recipes::{Recipe, RecipeTrace, TraceOptions},
+    utils::extract_domain,
+};
+use storage::{local_store::LocalStore, models::StoredContent, StorageBackend};
+use swoop_core::text::SharedStr;
+
+/// `swoop recipe` command definition
+pub fn command() -> Command {
+    Command::new("recipe")
+        .about("Run an interaction recipe in a browser and scrape the pages it reaches")
+        .arg(
+            Arg::new("file")
+                .required(true)
+                .value_name("FILE")
+                .help("Recipe file (TOML)"),
+        )
+        .arg(
+            Arg::new("webdriver")
+                .long("webdriver")
+                .value_name("URL")
+                .help("WebDriver server to run the recipe in")
+                .default_value("http://localhost:4444"),
+        )
+        .arg(
+            Arg::new("param")
+                .long("param")
+                .short('p')
+                .value_name("NAME=VALUE")
+                .help("Set a recipe parameter, e.g. query=kettle (repeatable)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .value_name("DIR")
+                .help("Save a screenshot, the DOM and timing of every step to DIR"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .help("Only check the recipe, without running it")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Run `swoop recipe`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut recipe = Recipe::from_file(matches.get_one::<String>("file").unwrap())
+        .map_err(|e| format!("{:#}", e))?;
+    for param in matches.get_many::<String>("param").unwrap_or_default() {
+        let (name, value) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Expected NAME=VALUE, got '{}'", param))?;
+        recipe = recipe.with_param(name, value);
+    }
+    if matches.get_flag("check") {
+        println!("✅ {} step(s), ready to run", recipe.steps.len());
+        return Ok(());
+    }
+
+    let pool = BrowserPool::new(BrowserConfig {
+        max_instances: 1,
+        webdriver_url: matches.get_one::<String>("webdriver").unwrap().clone(),
+        ..BrowserConfig::default()
+    });
+    println!(
+        "🧭 Running recipe {}",
+        recipe.name.as_deref().unwrap_or(&recipe.url)
+    );
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    let trace_dir = matches.get_one::<String>("trace");
+    let options = match trace_dir {
+        Some(_) => TraceOptions::full(),
+        None => TraceOptions::default(),
+    };
+    let browser = pool.get_browser().await.map_err(|e| format!("{:#}", e))?;
+    let outcome = match browser.run_recipe_traced(&recipe, options).await {
+        Ok(outcome) => outcome,
+        Err(failure) => {
+            if let Some(dir) = trace_dir {
+                save_trace(&failure.outcome.trace, dir)?;
+            }
+            return Err(format!("Recipe failed: {}", failure).into());
+        }
+    };
+    if let Some(dir) = trace_dir {
+        save_trace(&outcome.trace, dir)?;
+    }
+    for (name, value) in outcome
+        .values
+        .iter()
+        .filter(|(name, _)| !recipe.params.contains_key(*name))
+    {
+        println!("  🔖 {} = {}", name, value);
+    }
+
+    let registry = ScraperRegistry::default();
+    let store = LocalStore::new(state_dir).await?;
+    let mut stored = 0;
+    for page in &outcome.pages {
+        let html = SharedStr::from(page.html.as_str());
+        let extracted = match registry.reprocess(&page.url, &html).await {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                warn!("⚠️  Failed to extract {}: {}", page.url, e);
+                continue;
+            }
+        };
+        let platform = registry
+            .find_scraper(&page.url)
+            .map_or("generic", |scraper| scraper.platform_name());
+        let mut content = StoredContent::new(
+            page.url.clone(),
+            extract_domain(&page.url).unwrap_or_default(),
+            platform.to_string(),
+            extracted.title.map(String::from),
+            extracted.text.map(String::from),
+            Some(page.html.clone()),
+            metadata::into_owned(extracted.metadata),
+        );
+        content.scraped_at = page.timestamp;
+        content.links = extract_links(&page.html).unwrap_or_default();
+        content.images = extract_images(&page.html).unwrap_or_default();
+        content.tags = extracted.tags;
+        println!(
+            "  📄 {}{}",
+            page.url,
+            content
+                .title
+                .as_deref()
+                .map(|title| format!(" — {}", title))
+                .unwrap_or_default()
+        );
+        store.store_content(&content).await?;
+        stored += 1;
+    }
+    println!(
+        "\n✅ {} of {} captured page(s) stored",
+        stored,
+        outcome.pages.len()
+    );
+    Ok(())
+}
+
+fn save_trace(trace: &RecipeTrace, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = trace.write_to(dir).map_err(|e| format!("{:#}", e))?;
+    for step in &trace.steps {
+        println!(
+            "  ⏱️  {:<8} {:<10} {:>6}ms{}",
+            step.step,
+            step.action,
+            step.duration_ms,
+            step.error
+                .as_deref()
+                .map(|error| format!("  ❌ {}", error))
+                .unwrap_or_default()
+        );
+    }
+    println!("🧾 Trace saved to {}", path.display());
+    Ok(())
+}
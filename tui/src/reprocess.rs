@@ -0,0 +1,181 @@
+//! Replay extraction over stored HTML
+//!
+//! `swoop reprocess` runs the current extractors over the HTML kept in the
+//! local store and saves the results as new revisions, so improved extraction
+//! rules can be applied to past crawls without fetching anything again.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::warn;
+
+use scrapers::{
+    metadata, platforms::ScraperRegistry, schemas::SchemaRegistry, scripting::ScriptHooks,
+};
+use storage::{
+    local_store::LocalStore, models::StoredContent, query::ContentQuery, StorageBackend,
+};
+use swoop_core::text::SharedStr;
+
+/// `swoop reprocess` command definition
+pub fn command() -> Command {
+    Command::new("reprocess")
+        .about("Re-run extraction over stored HTML and save the results as new revisions")
+        .arg(
+            Arg::new("query")
+                .long("query")
+                .value_name("QUERY")
+                .help("Documents to reprocess, e.g. 'domain:example.com tag:news since:2024-01-01'")
+                .default_value(""),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_name("NUM")
+                .help("Maximum number of documents to reprocess"),
+        )
+        .arg(
+            Arg::new("script")
+                .long("script")
+                .value_name("FILE")
+                .help("Rhai script whose post_extract hook runs on every result"),
+        )
+        .arg(
+            Arg::new("policies")
+                .long("policies")
+                .value_name("FILE")
+                .help("Per-domain policy file selecting extraction rule sets (default: ./policies.toml if present)"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Show what would change without writing revisions"),
+        )
+}
+
+/// Run `swoop reprocess`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let query = ContentQuery::parse(matches.get_one::<String>("query").unwrap())?;
+    let limit = matches
+        .get_one::<String>("limit")
+        .map(|limit| limit.parse::<usize>())
+        .transpose()?
+        .unwrap_or(usize::MAX);
+    let dry_run = matches.get_flag("dry-run");
+
+    let mut registry = ScraperRegistry::default();
+    if let Some(script) = matches.get_one::<String>("script") {
+        registry = registry.with_hooks(Arc::new(ScriptHooks::from_file(PathBuf::from(script))?));
+    }
+    let policies = crate::cli::load_policies(matches.get_one::<String>("policies").map(Path::new))
+        .map_err(|e| e.to_string())?;
+    if let Some(policies) = policies {
+        registry = registry
+            .with_schemas(Arc::new(SchemaRegistry::new().with_builtin_rule_sets()))
+            .with_policies(policies);
+    }
+
+    let store = LocalStore::new(state_dir).await?;
+    let documents = latest_revisions(store.list_content().await?, &query);
+    let skipped = documents.iter().filter(|doc| doc.html.is_none()).count();
+
+    println!("♻️  Reprocessing stored HTML");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    let (mut written, mut failed) = (0, 0);
+    for doc in documents
+        .into_iter()
+        .filter(|doc| doc.html.is_some())
+        .take(limit)
+    {
+        let html = SharedStr::from(doc.html.as_deref().unwrap_or_default());
+        let extracted = match registry.reprocess(&doc.url, &html).await {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                warn!("⚠️  Failed to reprocess {}: {}", doc.url, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let mut metadata = extracted.metadata;
+        metadata.insert("reprocessed_from".into(), doc.id.clone());
+        // HTTP facts describe the original fetch, which isn't repeated
+        for (key, value) in doc
+            .metadata
+            .iter()
+            .filter(|(key, _)| key.starts_with("http:"))
+        {
+            metadata
+                .entry(key.as_str().into())
+                .or_insert_with(|| value.clone());
+        }
+        let mut revision = StoredContent::new(
+            doc.url.clone(),
+            doc.domain.clone(),
+            doc.platform.clone(),
+            extracted.title.map(String::from),
+            extracted.text.map(String::from),
+            doc.html.clone(),
+            metadata::into_owned(metadata),
+        );
+        // The page itself is unchanged, only the extraction is new
+        revision.scraped_at = doc.scraped_at;
+        revision.links = doc.links.clone();
+        revision.images = doc.images.clone();
+        revision.tags = extracted.tags;
+
+        println!(
+            "  {} {}{}",
+            if revision.title != doc.title {
+                "✏️ "
+            } else {
+                "✅"
+            },
+            doc.url,
+            revision
+                .title
+                .as_deref()
+                .map(|title| format!(" — {}", title))
+                .unwrap_or_default()
+        );
+        if !dry_run {
+            store.store_content(&revision).await?;
+        }
+        written += 1;
+    }
+
+    println!(
+        "\n📄 {} {}, ❌ {} failed, ⏭️  {} without stored HTML",
+        written,
+        if dry_run {
+            "would be revised"
+        } else {
+            "revisions written"
+        },
+        failed,
+        skipped
+    );
+    Ok(())
+}
+
+/// Newest stored revision of each URL matching `query`
+fn latest_revisions(documents: Vec<StoredContent>, query: &ContentQuery) -> Vec<StoredContent> {
+    let mut latest: HashMap<String, StoredContent> = HashMap::new();
+    for doc in documents.into_iter().filter(|doc| query.matches(doc)) {
+        match latest.get(&doc.url) {
+            Some(existing) if existing.stored_at >= doc.stored_at => {}
+            _ => {
+                latest.insert(doc.url.clone(), doc);
+            }
+        }
+    }
+
+    let mut documents: Vec<StoredContent> = latest.into_values().collect();
+    documents.sort_by(|a, b| a.url.cmp(&b.url));
+    documents
+}
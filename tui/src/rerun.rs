@@ -0,0 +1,503 @@
+//! Job replay from an export manifest
+//!
+//! `swoop rerun <manifest>` runs the job an export came from again: the same
+//! seeds (checked against the manifest's seed hash) and the same configuration
+//! snapshot, warning when the swoop version or an extraction rules file has
+//! changed since. `--compare` then diffs the new results against the original
+//! export by URL.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+use storage::{
+    models::JobStatus,
+    sealing::{self, ExportKey, Manifest, Provenance, ENCRYPTED_EXTENSION},
+    JobStore,
+};
+use tracing::warn;
+
+use crate::cli::{self, ScrapeOptions};
+
+/// `swoop rerun` command definition
+pub fn command() -> Command {
+    Command::new("rerun")
+        .about("Replay the job behind an export manifest and optionally compare the results")
+        .arg(
+            Arg::new("manifest")
+                .required(true)
+                .help("Manifest of the export to replay"),
+        )
+        .arg(
+            Arg::new("seeds")
+                .long("seeds")
+                .value_name("FILE")
+                .help("Seed URLs, when the job is not in the local job history"),
+        )
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .value_name("NAME")
+                .help("Name for the new run"),
+        )
+        .arg(
+            Arg::new("output-dir")
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Directory for the new exports (default: the original job's)"),
+        )
+        .arg(
+            Arg::new("compare")
+                .long("compare")
+                .help("Report URLs added, removed or changed since the original run")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("key")
+                .long("key")
+                .value_name("FILE")
+                .help("Key to read encrypted exports with (default: the job's --encrypt-key)"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Skip the confirmation for aggressive jobs against rate-limited domains")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Run `swoop rerun`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest_path = PathBuf::from(matches.get_one::<String>("manifest").unwrap());
+    let manifest = load_manifest(&manifest_path)?;
+    let provenance = manifest.provenance.clone().ok_or_else(|| {
+        format!(
+            "{} has no provenance; only manifests written with the job's details can be replayed",
+            manifest_path.display()
+        )
+    })?;
+    let config: HashMap<String, String> = provenance.config.clone().into_iter().collect();
+    let store = cli::open_state_store(state_dir).await;
+    let job = match &store {
+        Some(store) => store.get_job(&provenance.job_id).await.ok().flatten(),
+        None => None,
+    };
+
+    let seeds = match (matches.get_one::<String>("seeds"), &job) {
+        (Some(path), _) => fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        (None, Some(job)) => job.seeds.clone(),
+        (None, None) => {
+            return Err(format!(
+                "Job {} is not in the job history at {}; pass its seed list with --seeds",
+                provenance.job_id, state_dir
+            )
+            .into())
+        }
+    };
+    if sealing::seeds_digest(&seeds) != provenance.seeds_sha256 {
+        return Err(format!(
+            "The seed list does not match the manifest ({} seeds, SHA-256 {})",
+            provenance.seeds, provenance.seeds_sha256
+        )
+        .into());
+    }
+    warn_on_drift(&provenance, &config);
+
+    let mut options = ScrapeOptions::from_snapshot(&config, cli::progress_mode(matches))?;
+    options.name = Some(
+        matches
+            .get_one::<String>("name")
+            .cloned()
+            .unwrap_or_else(|| format!("{} (rerun)", provenance.job_name)),
+    );
+    options.tags = job.map(|job| job.tags).unwrap_or_default();
+    options.rerun_of = Some(provenance.job_id.clone());
+    options.force = matches.get_flag("force");
+    if let Some(dir) = matches.get_one::<String>("output-dir") {
+        options.output_dir = PathBuf::from(dir);
+    }
+    let key_path = matches
+        .get_one::<String>("key")
+        .map(PathBuf::from)
+        .or_else(|| options.encrypt_key.clone());
+    fs::create_dir_all(&options.output_dir)?;
+
+    let (rerun, rerun_manifest) = cli::run_scrape(seeds, options, store.as_ref()).await?;
+    if !matches.get_flag("compare") {
+        return Ok(());
+    }
+    if rerun.status != JobStatus::Completed {
+        return Err("The rerun did not complete; not comparing its results".into());
+    }
+
+    let key = key_path.map(ExportKey::load).transpose()?;
+    let before = load_results(&manifest_path, &manifest, key.as_ref())?;
+    let after = load_results(
+        &rerun_manifest,
+        &load_manifest(&rerun_manifest)?,
+        key.as_ref(),
+    )?;
+    let diff = RunDiff {
+        original_job: provenance.job_id,
+        rerun_job: rerun.id,
+        ..RunDiff::between(&before, &after)
+    };
+    let stem = rerun_manifest
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .trim_end_matches(".manifest.json")
+        .to_string();
+    let diff_path = rerun_manifest.with_file_name(format!("{}.diff.json", stem));
+    fs::write(&diff_path, serde_json::to_string_pretty(&diff)?)?;
+    diff.print(before.len(), after.len());
+    println!("🧾 Full comparison written to {}", diff_path.display());
+    Ok(())
+}
+
+/// Read a manifest, refusing one whose files were altered since it was written
+fn load_manifest(path: &Path) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let verification = sealing::verify_manifest(path, None)?;
+    if !verification.missing.is_empty() || !verification.mismatched.is_empty() {
+        let mut names = verification.missing;
+        names.extend(verification.mismatched);
+        return Err(format!(
+            "Export files of {} are missing or changed: {}",
+            path.display(),
+            names.join(", ")
+        )
+        .into());
+    }
+    Ok(verification.manifest)
+}
+
+/// Tell the user what may make the replay differ from the original run
+fn warn_on_drift(provenance: &Provenance, config: &HashMap<String, String>) {
+    if provenance.swoop_version != env!("CARGO_PKG_VERSION") {
+        warn!(
+            "⚠️  The original run used swoop {}, this is {}",
+            provenance.swoop_version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    let mut current = Provenance {
+        inputs: BTreeMap::new(),
+        ..provenance.clone()
+    };
+    for (name, path) in cli::input_files(config) {
+        if current.record_input(name, &path).is_err() {
+            warn!("⚠️  {} ({}) no longer exists", name, path.display());
+        }
+    }
+    for (name, digest) in &current.inputs {
+        match provenance.inputs.get(name) {
+            Some(original) if original == digest => {}
+            Some(_) => warn!("⚠️  {} has changed since the original run", name),
+            None => warn!("⚠️  {} was not recorded by the original run", name),
+        }
+    }
+}
+
+/// What the diff looks at in one result
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ResultSummary {
+    status: Option<u16>,
+    success: bool,
+    /// Hash of the stored content (the title, excerpt and length for CSV)
+    digest: u64,
+}
+
+/// Fields of an exported result needed to summarize it
+#[derive(Debug, Deserialize)]
+struct ExportedResult {
+    url: String,
+    status_code: Option<u16>,
+    success: bool,
+    #[serde(default)]
+    content: String,
+}
+
+impl ExportedResult {
+    fn summary(&self) -> ResultSummary {
+        ResultSummary {
+            status: self.status_code,
+            success: self.success,
+            digest: digest(&self.content),
+        }
+    }
+}
+
+fn digest(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Results of an export by URL, decrypting and decompressing parts as needed
+fn load_results(
+    manifest_path: &Path,
+    manifest: &Manifest,
+    key: Option<&ExportKey>,
+) -> Result<BTreeMap<String, ResultSummary>, Box<dyn std::error::Error>> {
+    let dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut results = BTreeMap::new();
+    for entry in &manifest.files {
+        let mut name = entry.name.as_str();
+        if name.contains(".traces.json") {
+            continue;
+        }
+        let path = dir.join(name);
+        // Kept until the file is read; dropping it removes the plaintext
+        let mut decrypted = None;
+        if let Some(stripped) = name.strip_suffix(&format!(".{}", ENCRYPTED_EXTENSION)) {
+            let key = key.ok_or("The export is encrypted; pass its key with --key")?;
+            let file = tempfile::NamedTempFile::new()?;
+            sealing::decrypt_file(key, &path, file.path())?;
+            decrypted = Some(file);
+            name = stripped;
+        }
+        let file = File::open(
+            decrypted
+                .as_ref()
+                .map_or(path.as_path(), |file| file.path()),
+        )?;
+        let mut reader: Box<dyn Read> = Box::new(BufReader::new(file));
+        if let Some(stripped) = name.strip_suffix(".gz") {
+            reader = Box::new(GzDecoder::new(reader));
+            name = stripped;
+        }
+
+        match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let items: Vec<ExportedResult> = serde_json::from_reader(reader)?;
+                results.extend(
+                    items
+                        .into_iter()
+                        .map(|item| (item.url.clone(), item.summary())),
+                );
+            }
+            Some("jsonl") => {
+                for line in BufReader::new(reader).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let item: ExportedResult = serde_json::from_str(&line)?;
+                    results.insert(item.url.clone(), item.summary());
+                }
+            }
+            Some("csv") => {
+                let mut csv = csv::Reader::from_reader(reader);
+                let headers = csv.headers()?.clone();
+                let column = |name: &str| headers.iter().position(|header| header == name);
+                let (url, status, success) =
+                    (column("URL"), column("Status Code"), column("Success"));
+                let compared: Vec<usize> = ["Content Length", "Title", "Excerpt"]
+                    .into_iter()
+                    .filter_map(column)
+                    .collect();
+                for record in csv.records() {
+                    let record = record?;
+                    let field =
+                        |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or("");
+                    let values: Vec<&str> = compared.iter().map(|&i| field(Some(i))).collect();
+                    results.insert(
+                        field(url).to_string(),
+                        ResultSummary {
+                            status: field(status).parse().ok().filter(|&code| code != 0),
+                            success: field(success) == "true",
+                            digest: digest(values),
+                        },
+                    );
+                }
+            }
+            _ => warn!(
+                "⚠️  Skipping {}, not a json, jsonl or csv export",
+                entry.name
+            ),
+        }
+    }
+    Ok(results)
+}
+
+/// A URL whose result differs between two runs
+#[derive(Debug, Serialize)]
+struct ChangedResult {
+    url: String,
+    status_before: Option<u16>,
+    status_after: Option<u16>,
+    success_before: bool,
+    success_after: bool,
+    content_changed: bool,
+}
+
+/// Differences between an original run and its replay
+#[derive(Debug, Default, Serialize)]
+struct RunDiff {
+    original_job: String,
+    rerun_job: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<ChangedResult>,
+    unchanged: usize,
+}
+
+impl RunDiff {
+    fn between(
+        before: &BTreeMap<String, ResultSummary>,
+        after: &BTreeMap<String, ResultSummary>,
+    ) -> Self {
+        let mut diff = Self {
+            added: after
+                .keys()
+                .filter(|url| !before.contains_key(*url))
+                .cloned()
+                .collect(),
+            ..Self::default()
+        };
+        for (url, old) in before {
+            match after.get(url) {
+                None => diff.removed.push(url.clone()),
+                Some(new) if new == old => diff.unchanged += 1,
+                Some(new) => diff.changed.push(ChangedResult {
+                    url: url.clone(),
+                    status_before: old.status,
+                    status_after: new.status,
+                    success_before: old.success,
+                    success_after: new.success,
+                    content_changed: old.digest != new.digest,
+                }),
+            }
+        }
+        diff
+    }
+
+    fn print(&self, before: usize, after: usize) {
+        println!(
+            "\n🔁 Rerun comparison: {} results before, {} after",
+            before, after
+        );
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("➕ Added: {}", self.added.len());
+        for url in self.added.iter().take(10) {
+            println!("  {}", url);
+        }
+        println!("➖ Removed: {}", self.removed.len());
+        for url in self.removed.iter().take(10) {
+            println!("  {}", url);
+        }
+        println!("✏️  Changed: {}", self.changed.len());
+        for change in self.changed.iter().take(10) {
+            let status =
+                |code: Option<u16>| code.map_or_else(|| "-".to_string(), |code| code.to_string());
+            let mut what = Vec::new();
+            if change.status_before != change.status_after {
+                what.push(format!(
+                    "status {} → {}",
+                    status(change.status_before),
+                    status(change.status_after)
+                ));
+            }
+            if change.success_before != change.success_after {
+                what.push(
+                    if change.success_after {
+                        "now succeeds"
+                    } else {
+                        "now fails"
+                    }
+                    .to_string(),
+                );
+            }
+            if change.content_changed {
+                what.push("content".to_string());
+            }
+            println!("  {} ({})", change.url, what.join(", "));
+        }
+        println!("🟰 Unchanged: {}", self.unchanged);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(status: u16, success: bool, content: &str) -> ResultSummary {
+        ResultSummary {
+            status: Some(status),
+            success,
+            digest: digest(content),
+        }
+    }
+
+    fn results(entries: &[(&str, ResultSummary)]) -> BTreeMap<String, ResultSummary> {
+        entries
+            .iter()
+            .map(|(url, summary)| (url.to_string(), summary.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_added_removed_and_changed_urls() {
+        let before = results(&[
+            ("https://a.test/", summary(200, true, "a")),
+            ("https://gone.test/", summary(200, true, "gone")),
+            ("https://status.test/", summary(200, true, "s")),
+            ("https://content.test/", summary(200, true, "old")),
+            ("https://fails.test/", summary(200, true, "f")),
+        ]);
+        let after = results(&[
+            ("https://a.test/", summary(200, true, "a")),
+            ("https://new.test/", summary(200, true, "new")),
+            ("https://status.test/", summary(304, true, "s")),
+            ("https://content.test/", summary(200, true, "new")),
+            ("https://fails.test/", summary(200, false, "f")),
+        ]);
+        let diff = RunDiff::between(&before, &after);
+
+        assert_eq!(diff.added, ["https://new.test/"]);
+        assert_eq!(diff.removed, ["https://gone.test/"]);
+        assert_eq!(diff.unchanged, 1);
+        let changed: Vec<(&str, Option<u16>, bool, bool)> = diff
+            .changed
+            .iter()
+            .map(|change| {
+                (
+                    change.url.as_str(),
+                    change.status_after,
+                    change.success_after,
+                    change.content_changed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                ("https://content.test/", Some(200), true, true),
+                ("https://fails.test/", Some(200), false, false),
+                ("https://status.test/", Some(304), true, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_runs_have_no_differences() {
+        let run = results(&[
+            ("https://a.test/", summary(200, true, "a")),
+            ("https://b.test/", summary(404, false, "")),
+        ]);
+        let diff = RunDiff::between(&run, &run);
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty());
+        assert_eq!(diff.unchanged, 2);
+    }
+}
@@ -0,0 +1,238 @@
+//! Re-crawl schedule commands
+//!
+//! `swoop daemon start --recrawl` re-fetches tracked URLs at intervals learned
+//! from how often each one changed. `swoop schedule` shows that schedule from
+//! the local store, adds URLs to it and pins or unpins their intervals; the
+//! daemon picks up changes on its next check.
+
+use chrono::{DateTime, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use storage::{
+    freshness::{parse_interval, FreshnessPolicy},
+    local_store::LocalStore,
+    models::UrlFreshness,
+    FreshnessStore,
+};
+
+use crate::jobs::truncate;
+
+/// Re-crawl schedule of one tracked URL, as listed here and by the daemon's `/schedule`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub url: String,
+    pub checks: u32,
+    pub changes: u32,
+    /// Interval in effect, in seconds
+    pub interval_secs: u64,
+    /// Whether the interval was pinned by hand
+    pub pinned: bool,
+    pub last_changed: Option<DateTime<Utc>>,
+    pub next_due: DateTime<Utc>,
+    /// Chance that the page changed since its last fetch
+    pub score: f64,
+}
+
+impl ScheduleEntry {
+    pub fn new(policy: &FreshnessPolicy, record: &UrlFreshness, now: DateTime<Utc>) -> Self {
+        Self {
+            url: record.url.clone(),
+            checks: record.checks,
+            changes: record.changes,
+            interval_secs: record.effective_interval_secs(),
+            pinned: record.pinned_secs.is_some(),
+            last_changed: record.last_changed,
+            next_due: record.next_due,
+            score: policy.score(record, now),
+        }
+    }
+}
+
+/// `swoop schedule` command definition
+pub fn command() -> Command {
+    Command::new("schedule")
+        .about("Inspect and adjust the daemon's re-crawl schedule")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("list")
+                .about("List tracked URLs, soonest due first")
+                .arg(
+                    Arg::new("due")
+                        .long("due")
+                        .help("Only URLs that are due now, most likely changed first")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the schedule as JSON")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("track")
+                .about("Start tracking URLs; they are due right away")
+                .arg(
+                    Arg::new("url")
+                        .action(ArgAction::Append)
+                        .help("URLs to track"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .short('f')
+                        .value_name("FILE")
+                        .help("File of URLs to track, one per line"),
+                ),
+        )
+        .subcommand(
+            Command::new("untrack")
+                .about("Stop tracking a URL")
+                .arg(Arg::new("url").required(true).help("Tracked URL")),
+        )
+        .subcommand(
+            Command::new("pin")
+                .about("Re-crawl a URL at a fixed interval instead of the computed one")
+                .arg(Arg::new("url").required(true).help("Tracked URL"))
+                .arg(
+                    Arg::new("interval")
+                        .required(true)
+                        .help("Interval such as 30m, 6h or 2d"),
+                ),
+        )
+        .subcommand(
+            Command::new("unpin")
+                .about("Go back to the computed interval")
+                .arg(Arg::new("url").required(true).help("Tracked URL")),
+        )
+}
+
+/// Dispatch a `swoop schedule` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+    let policy = FreshnessPolicy::default();
+
+    match matches.subcommand() {
+        Some(("list", sub)) => {
+            let now = Utc::now();
+            let records = store.list_freshness().await?;
+            let entries: Vec<ScheduleEntry> = if sub.get_flag("due") {
+                policy
+                    .due(&records, now)
+                    .into_iter()
+                    .map(|record| ScheduleEntry::new(&policy, record, now))
+                    .collect()
+            } else {
+                records
+                    .iter()
+                    .map(|record| ScheduleEntry::new(&policy, record, now))
+                    .collect()
+            };
+            if sub.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                print_schedule(&entries);
+            }
+            Ok(())
+        }
+        Some(("track", sub)) => {
+            let mut urls: Vec<String> = sub
+                .get_many::<String>("url")
+                .map(|urls| urls.cloned().collect())
+                .unwrap_or_default();
+            if let Some(path) = sub.get_one::<String>("file") {
+                urls.extend(
+                    fs::read_to_string(path)?
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(str::to_string),
+                );
+            }
+            if urls.is_empty() {
+                return Err("Give URLs to track or --file".into());
+            }
+            let mut added = 0;
+            for url in &urls {
+                if store.get_freshness(url).await?.is_none() {
+                    store.save_freshness(&policy.track(url)).await?;
+                    added += 1;
+                }
+            }
+            println!(
+                "📌 Tracking {} new URL(s), {} already tracked",
+                added,
+                urls.len() - added
+            );
+            Ok(())
+        }
+        Some(("untrack", sub)) => {
+            let url = sub.get_one::<String>("url").unwrap();
+            if !store.delete_freshness(url).await? {
+                return Err(format!("{} is not tracked", url).into());
+            }
+            println!("🗑️  Stopped tracking {}", url);
+            Ok(())
+        }
+        Some((action @ ("pin" | "unpin"), sub)) => {
+            let url = sub.get_one::<String>("url").unwrap();
+            let interval = match action {
+                "pin" => Some(parse_interval(sub.get_one::<String>("interval").unwrap())?),
+                _ => None,
+            };
+            let mut record = store
+                .get_freshness(url)
+                .await?
+                .ok_or_else(|| format!("{} is not tracked; add it with `schedule track`", url))?;
+            policy.pin(&mut record, interval);
+            store.save_freshness(&record).await?;
+            println!(
+                "{} {} every {}, next due {}",
+                if action == "pin" {
+                    "📌 Pinned"
+                } else {
+                    "🔓 Unpinned"
+                },
+                url,
+                format_interval(record.effective_interval_secs()),
+                record.next_due.format("%Y-%m-%d %H:%M UTC")
+            );
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn print_schedule(entries: &[ScheduleEntry]) {
+    if entries.is_empty() {
+        println!("No URLs scheduled");
+        return;
+    }
+
+    println!(
+        "{:<48} {:>7} {:>9} {:>6} {:<17} PINNED",
+        "URL", "CHANGES", "INTERVAL", "SCORE", "NEXT DUE"
+    );
+    for entry in entries {
+        println!(
+            "{:<48} {:>7} {:>9} {:>6.2} {:<17} {}",
+            truncate(&entry.url, 48),
+            format!("{}/{}", entry.changes, entry.checks.saturating_sub(1)),
+            format_interval(entry.interval_secs),
+            entry.score,
+            entry.next_due.format("%Y-%m-%d %H:%M"),
+            if entry.pinned { "yes" } else { "" }
+        );
+    }
+}
+
+/// Largest whole unit of an interval, e.g. `90m` or `2d`
+fn format_interval(secs: u64) -> String {
+    match secs {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
@@ -0,0 +1,95 @@
+//! Graceful shutdown on SIGINT and SIGTERM
+//!
+//! The first signal asks the running scrape, daemon or dashboard to stop
+//! taking new URLs and to give the requests in flight up to [`GRACE_PERIOD`]
+//! to finish. Each then writes out what it has, records the URLs it never got
+//! to as a checkpoint and says how to resume. A second signal quits at once.
+
+use std::{sync::Arc, time::Duration};
+use tokio::sync::watch;
+use tracing::warn;
+
+/// How long requests in flight get to finish once shutdown is requested
+pub const GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Exit code after a second signal, as for a process killed by SIGINT
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Shutdown request shared by everything that has to wind down
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// A shutdown that is only ever requested through [`Shutdown::request`]
+    pub fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// A shutdown requested by the first SIGINT or SIGTERM
+    pub fn on_signal() -> Self {
+        let shutdown = Self::new();
+        let requested = shutdown.clone();
+        tokio::spawn(async move {
+            let name = signal().await;
+            warn!(
+                "🛑 {} received, finishing the requests in flight (up to {}s); send it again to quit now",
+                name,
+                GRACE_PERIOD.as_secs()
+            );
+            requested.request();
+            let name = signal().await;
+            warn!("🛑 {} received again, quitting", name);
+            std::process::exit(FORCED_EXIT_CODE);
+        });
+        shutdown
+    }
+
+    pub fn request(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Wait until shutdown is requested
+    pub async fn requested(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so this only returns once requested
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+}
+
+/// Wait for SIGINT or SIGTERM, returning its name
+async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!("⚠️  Can't listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
@@ -0,0 +1,343 @@
+//! Site profile commands
+//!
+//! `swoop sites profile` collects a [`SiteProfile`] per domain with
+//! [`SiteProfiler`] (favicon, site name, CMS/framework, robots.txt summary and
+//! response-time baseline) and keeps it in the local store; `list` and `show`
+//! read them back. With `--infra` the profile also records DNS records and
+//! the TLS certificate, and `certs` reports certificates about to expire.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use scrapers::{
+    notify::{Alert, NotificationHub, Severity, WebhookNotifier},
+    site_profile::{self, SiteProfiler},
+};
+use storage::{
+    local_store::LocalStore,
+    models::{SiteCertificate, SiteDns, SiteProfile, SiteRobots},
+    SiteProfileStore,
+};
+
+use crate::jobs::truncate;
+
+/// Certificates this close to expiry raise critical rather than warning alerts
+const CRITICAL_DAYS: i64 = 7;
+
+/// `swoop sites` command definition
+pub fn command() -> Command {
+    Command::new("sites")
+        .about("Collect and inspect per-domain site profiles")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("profile")
+                .about("Profile the sites of the given domains or URLs")
+                .arg(
+                    Arg::new("target")
+                        .required(true)
+                        .action(ArgAction::Append)
+                        .help("Domains or URLs to profile"),
+                )
+                .arg(
+                    Arg::new("samples")
+                        .long("samples")
+                        .value_name("NUM")
+                        .help("Home page fetches the response-time baseline is taken from")
+                        .default_value("3"),
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .help("Profile domains again even if they already have a profile")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("infra")
+                        .long("infra")
+                        .help("Also record DNS records and the TLS certificate")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("list").about("List profiled domains"))
+        .subcommand(
+            Command::new("certs")
+                .about("Report profiled sites whose TLS certificates expire soon")
+                .arg(
+                    Arg::new("within")
+                        .long("within")
+                        .value_name("DAYS")
+                        .help("Report certificates expiring within this many days")
+                        .default_value("30"),
+                )
+                .arg(
+                    Arg::new("webhook")
+                        .long("webhook")
+                        .value_name("URL")
+                        .help("Also POST an alert per expiring certificate to this URL"),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Show the profile of a domain")
+                .arg(Arg::new("domain").required(true).help("Domain to show")),
+        )
+}
+
+/// Dispatch a `swoop sites` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+
+    match matches.subcommand() {
+        Some(("profile", sub)) => {
+            let profiler = SiteProfiler::new()
+                .with_samples(sub.get_one::<String>("samples").unwrap().parse()?)
+                .with_infrastructure(sub.get_flag("infra"));
+            let mut failed = 0;
+            for target in sub.get_many::<String>("target").unwrap() {
+                if !sub.get_flag("refresh") {
+                    if let Some(profile) = store.get_site_profile(&domain_of(target)).await? {
+                        println!(
+                            "⏭️  {} already profiled ({})",
+                            profile.domain,
+                            profile.profiled_at.format("%Y-%m-%d")
+                        );
+                        continue;
+                    }
+                }
+                match profiler.profile(target).await {
+                    Ok(profile) => {
+                        let profile = stored_profile(profile);
+                        store.save_site_profile(&profile).await?;
+                        print_profile(&profile);
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("❌ {}: {:#}", target, e);
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} site(s) could not be profiled", failed).into());
+            }
+            Ok(())
+        }
+        Some(("list", _)) => {
+            print_profile_table(&store.list_site_profiles().await?);
+            Ok(())
+        }
+        Some(("certs", sub)) => {
+            let within: i64 = sub.get_one::<String>("within").unwrap().parse()?;
+            let mut notifications = NotificationHub::new();
+            if let Some(url) = sub.get_one::<String>("webhook") {
+                notifications = notifications.with_notifier(WebhookNotifier::new(url.as_str()));
+            }
+            let now = chrono::Utc::now();
+            let profiles = store.list_site_profiles().await?;
+            let mut expiring: Vec<(&SiteProfile, &SiteCertificate)> = Vec::new();
+            for profile in &profiles {
+                if let Some(certificate) = &profile.certificate {
+                    if certificate.days_left(now) <= within {
+                        expiring.push((profile, certificate));
+                    }
+                }
+            }
+            expiring.sort_by_key(|(_, certificate)| certificate.not_after);
+
+            if expiring.is_empty() {
+                println!("✅ No certificates expire within {} days", within);
+                return Ok(());
+            }
+            for (profile, certificate) in &expiring {
+                let days_left = certificate.days_left(now);
+                let icon = if days_left <= CRITICAL_DAYS {
+                    "🔴"
+                } else {
+                    "🟡"
+                };
+                println!(
+                    "{} {:<32} {:>5} days  {}  {}",
+                    icon,
+                    truncate(&profile.domain, 32),
+                    days_left,
+                    certificate.not_after.format("%Y-%m-%d"),
+                    certificate.issuer
+                );
+                notifications
+                    .send(&expiry_alert(&profile.domain, certificate, days_left))
+                    .await?;
+            }
+            Err(format!(
+                "{} certificate(s) expire within {} days",
+                expiring.len(),
+                within
+            )
+            .into())
+        }
+        Some(("show", sub)) => {
+            let domain = sub.get_one::<String>("domain").unwrap();
+            match store.get_site_profile(&domain_of(domain)).await? {
+                Some(profile) => print_profile(&profile),
+                None => return Err(format!("No profile for '{}'", domain).into()),
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Host of a URL, or the argument itself when it is already a domain
+fn domain_of(target: &str) -> String {
+    reqwest::Url::parse(target)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| target.trim_end_matches('/').to_string())
+        .to_ascii_lowercase()
+}
+
+/// The storage layer's copy of a collected profile
+fn stored_profile(profile: site_profile::SiteProfile) -> SiteProfile {
+    SiteProfile {
+        domain: profile.domain,
+        home_url: profile.home_url,
+        site_name: profile.site_name,
+        favicon: profile.favicon,
+        technologies: profile.technologies,
+        robots: SiteRobots {
+            found: profile.robots.found,
+            crawl_delay_secs: profile.robots.crawl_delay_secs,
+            disallow: profile.robots.disallow,
+            sitemaps: profile.robots.sitemaps,
+        },
+        response_ms: profile.response_ms,
+        ttfb_ms: profile.ttfb_ms,
+        dns: profile.dns.map(|dns| SiteDns {
+            addresses: dns.addresses,
+            cname_chain: dns.cname_chain,
+        }),
+        certificate: profile.certificate.map(|certificate| SiteCertificate {
+            subject: certificate.subject,
+            issuer: certificate.issuer,
+            san: certificate.san,
+            serial: certificate.serial,
+            not_before: certificate.not_before,
+            not_after: certificate.not_after,
+        }),
+        profiled_at: profile.profiled_at,
+    }
+}
+
+fn expiry_alert(domain: &str, certificate: &SiteCertificate, days_left: i64) -> Alert {
+    let severity = if days_left <= CRITICAL_DAYS {
+        Severity::Critical
+    } else {
+        Severity::Warning
+    };
+    let message = if days_left < 0 {
+        format!(
+            "TLS certificate of {} expired {} days ago",
+            domain, -days_left
+        )
+    } else {
+        format!(
+            "TLS certificate of {} expires in {} days",
+            domain, days_left
+        )
+    };
+    Alert::new("certificate_expiring", severity, message)
+        .with_detail("domain", domain)
+        .with_detail("issuer", &certificate.issuer)
+        .with_detail("not_after", certificate.not_after.to_rfc3339())
+        .with_detail("days_left", days_left)
+}
+
+fn print_profile_table(profiles: &[SiteProfile]) {
+    if profiles.is_empty() {
+        println!("No sites profiled yet");
+        return;
+    }
+
+    println!(
+        "{:<32} {:<24} {:<24} {:>8} {:<8}",
+        "DOMAIN", "NAME", "TECHNOLOGIES", "RESPONSE", "ROBOTS"
+    );
+    for profile in profiles {
+        println!(
+            "{:<32} {:<24} {:<24} {:>8} {:<8}",
+            truncate(&profile.domain, 32),
+            truncate(profile.site_name.as_deref().unwrap_or("-"), 24),
+            truncate(&profile.technologies.join(", "), 24),
+            profile
+                .response_ms
+                .map_or_else(|| "-".to_string(), |ms| format!("{}ms", ms)),
+            robots_summary(&profile.robots)
+        );
+    }
+}
+
+fn print_profile(profile: &SiteProfile) {
+    println!("🌐 {}", profile.domain);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("🏠 Home: {}", profile.home_url);
+    if let Some(name) = &profile.site_name {
+        println!("📛 Name: {}", name);
+    }
+    if let Some(favicon) = &profile.favicon {
+        println!("🖼️  Favicon: {}", favicon);
+    }
+    if !profile.technologies.is_empty() {
+        println!("🧱 Built with: {}", profile.technologies.join(", "));
+    }
+    if let Some(response_ms) = profile.response_ms {
+        match profile.ttfb_ms {
+            Some(ttfb_ms) => println!(
+                "⏱️  Response: {}ms ({}ms to first byte)",
+                response_ms, ttfb_ms
+            ),
+            None => println!("⏱️  Response: {}ms", response_ms),
+        }
+    }
+    println!("🤖 robots.txt: {}", robots_summary(&profile.robots));
+    if let Some(delay) = profile.robots.crawl_delay_secs {
+        println!("  Crawl-delay: {}s", delay);
+    }
+    for path in profile.robots.disallow.iter().take(10) {
+        println!("  Disallow: {}", path);
+    }
+    if profile.robots.disallow.len() > 10 {
+        println!("  … and {} more", profile.robots.disallow.len() - 10);
+    }
+    for sitemap in &profile.robots.sitemaps {
+        println!("  Sitemap: {}", sitemap);
+    }
+    if let Some(dns) = &profile.dns {
+        println!("🧭 DNS: {}", dns.addresses.join(", "));
+        if !dns.cname_chain.is_empty() {
+            println!("  CNAME: {}", dns.cname_chain.join(" → "));
+        }
+    }
+    if let Some(certificate) = &profile.certificate {
+        println!("🔒 Certificate: {}", certificate.subject);
+        println!("  Issuer: {}", certificate.issuer);
+        println!(
+            "  Valid: {} to {} ({} days left)",
+            certificate.not_before.format("%Y-%m-%d"),
+            certificate.not_after.format("%Y-%m-%d"),
+            certificate.days_left(chrono::Utc::now())
+        );
+        if !certificate.san.is_empty() {
+            println!("  Names: {}", certificate.san.join(", "));
+        }
+    }
+    println!(
+        "🕐 Profiled: {}\n",
+        profile.profiled_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+}
+
+fn robots_summary(robots: &SiteRobots) -> String {
+    if !robots.found {
+        "none".to_string()
+    } else if robots.disallow.iter().any(|path| path == "/") {
+        "blocks all".to_string()
+    } else {
+        format!("{} rules", robots.disallow.len())
+    }
+}
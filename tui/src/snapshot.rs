@@ -0,0 +1,84 @@
+//! Safe previews of stored pages
+//!
+//! `swoop snapshot` writes the HTML kept for a page in the local store as a
+//! sanitized snapshot (see [`scrapers::sanitize`]): it renders as captured in
+//! any browser, without running the site's scripts or firing its trackers.
+
+use clap::{Arg, ArgMatches, Command};
+use std::fs;
+
+use scrapers::sanitize::SnapshotSanitizer;
+use storage::{local_store::LocalStore, models::StoredContent, StorageBackend};
+
+/// `swoop snapshot` command definition
+pub fn command() -> Command {
+    Command::new("snapshot")
+        .about("Write a stored page as a sanitized, safe-to-open HTML snapshot")
+        .arg(
+            Arg::new("page")
+                .required(true)
+                .help("Stored content ID, unique ID prefix or URL (its newest revision)"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FILE")
+                .help("File to write the snapshot to (default: stdout)"),
+        )
+}
+
+/// Run `swoop snapshot`
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let store = LocalStore::new(state_dir).await?;
+    let page = find_page(&store, matches.get_one::<String>("page").unwrap()).await?;
+    let html = page
+        .html
+        .as_deref()
+        .ok_or_else(|| format!("No HTML stored for {} ({})", page.url, page.id))?;
+    let snapshot = SnapshotSanitizer::new().sanitize(html)?;
+
+    match matches.get_one::<String>("output") {
+        Some(path) => {
+            fs::write(path, &snapshot.html)?;
+            println!("📸 {} → {}", page.url, path);
+        }
+        None => print!("{}", snapshot.html),
+    }
+    let removed = snapshot.removed;
+    eprintln!(
+        "🧹 Removed {} scripts, {} handlers, {} trackers and {} embeds",
+        removed.scripts, removed.handlers, removed.trackers, removed.embeds
+    );
+    Ok(())
+}
+
+/// Resolve a content ID, a unique ID prefix or a URL's newest revision
+async fn find_page(
+    store: &LocalStore,
+    page: &str,
+) -> Result<StoredContent, Box<dyn std::error::Error>> {
+    if page.starts_with("http://") || page.starts_with("https://") {
+        return store
+            .get_content_by_url(page)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No stored revision of {}", page).into());
+    }
+    if let Ok(Some(content)) = store.get_content(page).await {
+        return Ok(content);
+    }
+
+    let mut matches: Vec<StoredContent> = store
+        .list_content()
+        .await?
+        .into_iter()
+        .filter(|content| content.id.starts_with(page))
+        .collect();
+    match matches.len() {
+        0 => Err(format!("No stored content matching '{}'", page).into()),
+        1 => Ok(matches.remove(0)),
+        n => Err(format!("'{}' is ambiguous, it matches {} documents", page, n).into()),
+    }
+}
@@ -0,0 +1,263 @@
+//! Bounded result buffer with disk spill
+//!
+//! The dashboard keeps every result for export. Holding full page bodies for a
+//! long crawl in memory eventually runs the process out of RAM, so
+//! [`ResultBuffer`] keeps only the most recent results in memory, within an
+//! entry count and a body byte budget, and appends older ones to a temporary
+//! JSONL file. Exports read the spilled results back before the in-memory ones.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tempfile::NamedTempFile;
+
+/// How much of the buffer stays in memory
+#[derive(Debug, Clone)]
+pub struct BufferLimits {
+    pub max_entries: usize,
+    /// Body bytes held across all in-memory results
+    pub max_body_bytes: usize,
+    /// Where the spill file is created; the system temp dir when unset
+    pub spill_dir: Option<PathBuf>,
+}
+
+/// Spilled results, one JSON object per line; the file is removed on drop
+#[derive(Debug)]
+struct SpillFile {
+    writer: Mutex<BufWriter<NamedTempFile>>,
+}
+
+/// Results in arrival order, the oldest of them on disk
+#[derive(Debug, Clone)]
+pub struct ResultBuffer<T> {
+    recent: VecDeque<(T, usize)>,
+    body_bytes: usize,
+    limits: BufferLimits,
+    /// Shared by clones so a snapshot taken for export sees the same file
+    spill: Option<Arc<SpillFile>>,
+    spilled: usize,
+}
+
+impl<T: Serialize + DeserializeOwned> ResultBuffer<T> {
+    pub fn new(limits: BufferLimits) -> Self {
+        Self {
+            recent: VecDeque::new(),
+            body_bytes: 0,
+            limits,
+            spill: None,
+            spilled: 0,
+        }
+    }
+
+    /// Results held, in memory and on disk
+    pub fn len(&self) -> usize {
+        self.spilled + self.recent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Results written to the spill file so far
+    pub fn spilled(&self) -> usize {
+        self.spilled
+    }
+
+    /// Body bytes held in memory
+    pub fn body_bytes(&self) -> usize {
+        self.body_bytes
+    }
+
+    /// Add a result whose body is `body_bytes` long, spilling the oldest
+    /// results to disk once a limit is exceeded
+    ///
+    /// If the spill file can't be written the oldest results are dropped
+    /// instead, as before spilling existed, and the error is returned.
+    pub fn push(&mut self, item: T, body_bytes: usize) -> io::Result<()> {
+        self.body_bytes += body_bytes;
+        self.recent.push_back((item, body_bytes));
+
+        let mut result = Ok(());
+        while self.recent.len() > self.limits.max_entries
+            || (self.body_bytes > self.limits.max_body_bytes && self.recent.len() > 1)
+        {
+            let Some((oldest, size)) = self.recent.pop_front() else {
+                break;
+            };
+            self.body_bytes -= size;
+            if result.is_ok() {
+                result = self.spill(&oldest);
+            }
+        }
+        result
+    }
+
+    fn spill(&mut self, item: &T) -> io::Result<()> {
+        let spill = match &self.spill {
+            Some(spill) => spill,
+            None => {
+                let file = tempfile::Builder::new()
+                    .prefix("swoop-results-")
+                    .suffix(".jsonl")
+                    .tempfile_in(
+                        self.limits
+                            .spill_dir
+                            .clone()
+                            .unwrap_or_else(std::env::temp_dir),
+                    )?;
+                self.spill.insert(Arc::new(SpillFile {
+                    writer: Mutex::new(BufWriter::new(file)),
+                }))
+            }
+        };
+        let mut writer = spill.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, item)?;
+        writer.write_all(b"\n")?;
+        self.spilled += 1;
+        Ok(())
+    }
+
+    /// Results still in memory, oldest first
+    pub fn recent(&self) -> impl Iterator<Item = &T> {
+        self.recent.iter().map(|(item, _)| item)
+    }
+
+    /// Call `f` with every result, oldest first, reading spilled ones back from disk
+    pub fn try_for_each(&self, mut f: impl FnMut(&T) -> io::Result<()>) -> io::Result<()> {
+        if let Some(spill) = &self.spill {
+            let mut writer = spill.writer.lock().unwrap();
+            writer.flush()?;
+            let file = writer.get_ref().reopen()?;
+            drop(writer);
+            // Only the lines spilled when this snapshot was taken
+            for line in BufReader::new(file).lines().take(self.spilled) {
+                f(&serde_json::from_str(&line?)?)?;
+            }
+        }
+        self.recent().try_for_each(f)
+    }
+}
+
+/// Write every result as a pretty-printed JSON array without collecting them first
+pub fn write_json<T: Serialize + DeserializeOwned>(
+    buffer: &ResultBuffer<T>,
+    file: File,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    writer.write_all(b"[")?;
+    let mut first = true;
+    buffer.try_for_each(|item| {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        first = false;
+        serde_json::to_writer_pretty(&mut writer, item)?;
+        Ok(())
+    })?;
+    writer.write_all(b"\n]\n")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(
+        dir: &tempfile::TempDir,
+        max_entries: usize,
+        max_body_bytes: usize,
+    ) -> ResultBuffer<String> {
+        ResultBuffer::new(BufferLimits {
+            max_entries,
+            max_body_bytes,
+            spill_dir: Some(dir.path().to_path_buf()),
+        })
+    }
+
+    fn all(buffer: &ResultBuffer<String>) -> Vec<String> {
+        let mut items = Vec::new();
+        buffer
+            .try_for_each(|item| {
+                items.push(item.clone());
+                Ok(())
+            })
+            .unwrap();
+        items
+    }
+
+    fn spill_files(dir: &tempfile::TempDir) -> usize {
+        std::fs::read_dir(dir.path()).unwrap().count()
+    }
+
+    #[test]
+    fn keeps_results_in_memory_within_the_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = buffer(&dir, 3, 100);
+        for item in ["a", "b", "c"] {
+            buffer.push(item.to_string(), 10).unwrap();
+        }
+        assert_eq!(buffer.spilled(), 0);
+        assert_eq!(buffer.body_bytes(), 30);
+        assert_eq!(all(&buffer), ["a", "b", "c"]);
+        assert_eq!(spill_files(&dir), 0);
+    }
+
+    #[test]
+    fn spills_past_the_entry_limit_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = buffer(&dir, 2, 1_000);
+        for item in ["a", "b", "c", "d", "e"] {
+            buffer.push(item.to_string(), 1).unwrap();
+        }
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(buffer.spilled(), 3);
+        assert_eq!(buffer.recent().collect::<Vec<_>>(), ["d", "e"]);
+        assert_eq!(all(&buffer), ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn spills_past_the_body_budget_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = buffer(&dir, 100, 50);
+        buffer.push("a".to_string(), 30).unwrap();
+        buffer.push("b".to_string(), 30).unwrap();
+        assert_eq!(buffer.spilled(), 1);
+        assert_eq!(buffer.body_bytes(), 30);
+        // One result over the budget on its own stays in memory
+        buffer.push("c".to_string(), 80).unwrap();
+        assert_eq!(buffer.spilled(), 2);
+        assert_eq!(buffer.recent().collect::<Vec<_>>(), ["c"]);
+        assert_eq!(all(&buffer), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn snapshots_see_only_what_was_spilled_before_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = buffer(&dir, 1, 1_000);
+        buffer.push("a".to_string(), 1).unwrap();
+        buffer.push("b".to_string(), 1).unwrap();
+        let snapshot = buffer.clone();
+        buffer.push("c".to_string(), 1).unwrap();
+        assert_eq!(all(&snapshot), ["a", "b"]);
+        assert_eq!(all(&buffer), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn removes_the_spill_file_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = buffer(&dir, 1, 1_000);
+        buffer.push("a".to_string(), 1).unwrap();
+        buffer.push("b".to_string(), 1).unwrap();
+        assert_eq!(spill_files(&dir), 1);
+
+        // Clones share the file, which goes with the last of them
+        let snapshot = buffer.clone();
+        drop(buffer);
+        assert_eq!(spill_files(&dir), 1);
+        drop(snapshot);
+        assert_eq!(spill_files(&dir), 0);
+    }
+}
@@ -0,0 +1,188 @@
+//! Tenants of a shared daemon
+//!
+//! `swoop daemon start --tenants tenants.toml` lets one daemon serve several
+//! teams. Every control request then has to carry a tenant's API key as
+//! `Authorization: Bearer <key>`, and acts on that tenant only: its jobs are
+//! queued, paused and reported apart from the others', fetched within its
+//! quotas and stored under `<state-dir>/tenants/<name>`.
+//!
+//! ```toml
+//! [tenants.search]
+//! api_key = "..."
+//! concurrency = 20             # fetches in flight
+//! requests_per_second = 10     # fetch starts
+//! max_queued = 100000          # URLs waiting; larger submissions are refused
+//! ```
+//!
+//! Without a tenants file every request acts for [`DEFAULT_TENANT`].
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Tenant of a daemon started without `--tenants`
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Subdirectory of the state directory holding one directory per tenant
+pub const TENANTS_DIR: &str = "tenants";
+
+/// Key and quotas of one tenant
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub api_key: String,
+    /// Fetches in flight, within the daemon's own concurrency
+    pub concurrency: Option<usize>,
+    pub requests_per_second: Option<u32>,
+    /// URLs waiting to be fetched
+    pub max_queued: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TenantsFile {
+    tenants: BTreeMap<String, TenantConfig>,
+}
+
+/// Tenants of a daemon, by name
+#[derive(Debug, Clone)]
+pub struct Tenants {
+    tenants: BTreeMap<String, TenantConfig>,
+}
+
+impl Tenants {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+            .map_err(|e| format!("Invalid tenants file {}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let file: TenantsFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        if file.tenants.is_empty() {
+            return Err("no tenants".to_string());
+        }
+        for (name, tenant) in &file.tenants {
+            // Names become directory names
+            if name.starts_with('.')
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!("invalid tenant name '{}'", name));
+            }
+            if tenant.api_key.trim().is_empty() {
+                return Err(format!("tenant '{}' has no API key", name));
+            }
+            if tenant.concurrency == Some(0) || tenant.requests_per_second == Some(0) {
+                return Err(format!("tenant '{}' has a zero quota", name));
+            }
+            if let Some((other, _)) = file.tenants.iter().find(|(other, other_tenant)| {
+                *other < name && other_tenant.api_key == tenant.api_key
+            }) {
+                return Err(format!(
+                    "tenants '{}' and '{}' share an API key",
+                    other, name
+                ));
+            }
+        }
+        Ok(Self {
+            tenants: file.tenants,
+        })
+    }
+
+    /// Name of the tenant `api_key` belongs to
+    pub fn authenticate(&self, api_key: &str) -> Option<&str> {
+        self.tenants
+            .iter()
+            .find(|(_, tenant)| constant_time_eq(tenant.api_key.as_bytes(), api_key.as_bytes()))
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TenantConfig> {
+        self.tenants.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tenants.keys().map(String::as_str)
+    }
+}
+
+/// Compare keys without returning early, so response times don't give them away
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(name: &str, api_key: &str) -> String {
+        format!("[tenants.{}]\napi_key = \"{}\"\n", name, api_key)
+    }
+
+    #[test]
+    fn parses_tenants() {
+        let tenants = Tenants::parse(&format!(
+            "{}concurrency = 4\nrequests_per_second = 2\n{}",
+            tenant("search", "search-key"),
+            tenant("ads", "ads-key")
+        ))
+        .unwrap();
+        assert_eq!(tenants.names().collect::<Vec<_>>(), ["ads", "search"]);
+        let search = tenants.get("search").unwrap();
+        assert_eq!(search.concurrency, Some(4));
+        assert_eq!(search.requests_per_second, Some(2));
+        assert_eq!(tenants.get("ads").unwrap().concurrency, None);
+    }
+
+    #[test]
+    fn rejects_invalid_tenants() {
+        for name in ["\".hidden\"", "\"a/b\"", "\"with space\"", "\"..\""] {
+            let err = Tenants::parse(&tenant(name, "key")).unwrap_err();
+            assert!(err.starts_with("invalid tenant name"), "{}: {}", name, err);
+        }
+        assert_eq!(
+            Tenants::parse(&tenant("search", "  ")).unwrap_err(),
+            "tenant 'search' has no API key"
+        );
+        assert_eq!(
+            Tenants::parse(&format!(
+                "{}{}",
+                tenant("search", "shared"),
+                tenant("ads", "shared")
+            ))
+            .unwrap_err(),
+            "tenants 'ads' and 'search' share an API key"
+        );
+        assert_eq!(
+            Tenants::parse(&format!("{}concurrency = 0\n", tenant("search", "key"))).unwrap_err(),
+            "tenant 'search' has a zero quota"
+        );
+        assert_eq!(Tenants::parse("tenants = {}").unwrap_err(), "no tenants");
+        assert!(Tenants::parse(&format!("{}quota = 1\n", tenant("search", "key"))).is_err());
+    }
+
+    #[test]
+    fn authenticates_by_key() {
+        let tenants = Tenants::parse(&format!(
+            "{}{}",
+            tenant("search", "search-key"),
+            tenant("ads", "ads-key")
+        ))
+        .unwrap();
+        assert_eq!(tenants.authenticate("search-key"), Some("search"));
+        assert_eq!(tenants.authenticate("ads-key"), Some("ads"));
+        assert_eq!(tenants.authenticate("search-ke"), None);
+        assert_eq!(tenants.authenticate("search-key2"), None);
+        assert_eq!(tenants.authenticate(""), None);
+    }
+
+    #[test]
+    fn compares_keys_exactly() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
@@ -0,0 +1,343 @@
+//! Colors of the dashboard
+//!
+//! Render functions take every color from a [`Theme`] instead of naming
+//! colors themselves. `dark` (the default), `light` and `high-contrast` are
+//! truecolor palettes, `ansi16` sticks to the 16 colors every terminal has and
+//! so follows the terminal's own palette. Picked with `--theme` or
+//! `theme = "..."` in the config file.
+//!
+//! Terminals without truecolor get the palette mapped to the nearest of the
+//! 256 or 16 colors they have: `COLORTERM=truecolor` (or `24bit`) means
+//! truecolor, a `TERM` ending in `256color` means 256 colors, anything else
+//! 16. `colors = "truecolor" | "256" | "16"` in the config file overrides the
+//! detection, e.g. under tmux or screen.
+
+use ratatui::style::Color;
+use std::{env, str::FromStr};
+
+/// Colors a terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// What the terminal announces in `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Self::TrueColor;
+        }
+        if env::var("TERM").map_or(false, |term| term.ends_with("256color")) {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+impl FromStr for ColorSupport {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(Self::TrueColor),
+            "256" => Ok(Self::Ansi256),
+            "16" => Ok(Self::Ansi16),
+            _ => Err(format!(
+                "unknown color support '{}', expected truecolor, 256 or 16",
+                value
+            )),
+        }
+    }
+}
+
+/// Colors every part of the dashboard is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Body text
+    pub text: Color,
+    /// Placeholders, axes and pending work
+    pub muted: Color,
+    /// Focused borders, chart lines and info messages
+    pub accent: Color,
+    /// Selected tab, table headers and the input box
+    pub highlight: Color,
+    /// Work in progress
+    pub running: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// Names `--theme` accepts
+    pub const NAMES: [&'static str; 4] = ["dark", "light", "high-contrast", "ansi16"];
+
+    pub fn dark() -> Self {
+        Self {
+            text: Color::Rgb(220, 223, 228),
+            muted: Color::Rgb(127, 132, 142),
+            accent: Color::Rgb(86, 182, 194),
+            highlight: Color::Rgb(229, 192, 123),
+            running: Color::Rgb(97, 175, 239),
+            success: Color::Rgb(152, 195, 121),
+            warning: Color::Rgb(209, 154, 102),
+            error: Color::Rgb(224, 108, 117),
+        }
+    }
+
+    /// For terminals with a light background
+    pub fn light() -> Self {
+        Self {
+            text: Color::Rgb(36, 41, 47),
+            muted: Color::Rgb(110, 119, 129),
+            accent: Color::Rgb(9, 105, 218),
+            highlight: Color::Rgb(154, 103, 0),
+            running: Color::Rgb(130, 80, 223),
+            success: Color::Rgb(26, 127, 55),
+            warning: Color::Rgb(188, 76, 0),
+            error: Color::Rgb(207, 34, 46),
+        }
+    }
+
+    /// Saturated colors on black, readable with low vision or in glare
+    pub fn high_contrast() -> Self {
+        Self {
+            text: Color::Rgb(255, 255, 255),
+            muted: Color::Rgb(200, 200, 200),
+            accent: Color::Rgb(0, 255, 255),
+            highlight: Color::Rgb(255, 255, 0),
+            running: Color::Rgb(96, 160, 255),
+            success: Color::Rgb(0, 255, 0),
+            warning: Color::Rgb(255, 170, 0),
+            error: Color::Rgb(255, 80, 80),
+        }
+    }
+
+    /// The 16 standard colors, in whatever shades the terminal gives them
+    pub fn ansi16() -> Self {
+        Self {
+            text: Color::Reset,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            highlight: Color::Yellow,
+            running: Color::Blue,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::LightRed,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" | "high_contrast" => Some(Self::high_contrast()),
+            "ansi16" | "16" => Some(Self::ansi16()),
+            _ => None,
+        }
+    }
+
+    /// Like [`Self::named`], with the default theme for a name it doesn't know
+    pub fn named_or_default(name: &str) -> Self {
+        Self::named(name).unwrap_or_default()
+    }
+
+    /// The theme with every color the terminal can't show replaced by the
+    /// nearest one it can
+    pub fn for_terminal(self, support: ColorSupport) -> Self {
+        let fit = |color| fit_color(color, support);
+        Self {
+            text: fit(self.text),
+            muted: fit(self.muted),
+            accent: fit(self.accent),
+            highlight: fit(self.highlight),
+            running: fit(self.running),
+            success: fit(self.success),
+            warning: fit(self.warning),
+            error: fit(self.error),
+        }
+    }
+}
+
+/// Channel values of the 6x6x6 cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn fit_color(color: Color, support: ColorSupport) -> Color {
+    let rgb = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) if index >= 16 => indexed_rgb(index),
+        // Named colors and the first 16 indexes are there everywhere
+        _ => return color,
+    };
+    match support {
+        ColorSupport::TrueColor => color,
+        ColorSupport::Ansi256 => Color::Indexed(nearest_indexed(rgb)),
+        ColorSupport::Ansi16 => nearest_ansi16(rgb),
+    }
+}
+
+/// Standard color of the same hue, so a palette's reds, greens and yellows
+/// stay apart instead of all landing on the nearest gray
+fn nearest_ansi16((r, g, b): (u8, u8, u8)) -> Color {
+    let max = r.max(g).max(b) as i32;
+    let min = r.min(g).min(b) as i32;
+    if max - min < 40 {
+        return match (max + min) / 2 {
+            0..=63 => Color::Black,
+            64..=159 => Color::DarkGray,
+            160..=223 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let chroma = max - min;
+    let hue = if max == r {
+        (60 * (g - b) / chroma).rem_euclid(360)
+    } else if max == g {
+        60 * (b - r) / chroma + 120
+    } else {
+        60 * (r - g) / chroma + 240
+    };
+    let bright = max >= 200;
+    match (hue, bright) {
+        (20..=74, false) => Color::Yellow,
+        (20..=74, true) => Color::LightYellow,
+        (75..=149, false) => Color::Green,
+        (75..=149, true) => Color::LightGreen,
+        (150..=209, false) => Color::Cyan,
+        (150..=209, true) => Color::LightCyan,
+        (210..=269, false) => Color::Blue,
+        (210..=269, true) => Color::LightBlue,
+        (270..=329, false) => Color::Magenta,
+        (270..=329, true) => Color::LightMagenta,
+        (_, false) => Color::Red,
+        (_, true) => Color::LightRed,
+    }
+}
+
+/// Nearest color of the cube or the gray ramp of the 256-color palette
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = 16 + 36 * r + 6 * g + b;
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, indexed_rgb(gray)) < distance(rgb, indexed_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Color of a 256-color palette index beyond the standard 16
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    if index >= 232 {
+        let value = 8 + 10 * (index - 232);
+        return (value, value, value);
+    }
+    let i = index.saturating_sub(16);
+    let level = |n: u8| CUBE_LEVELS[n as usize];
+    (level(i / 36), level(i / 6 % 6), level(i % 6))
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TuiConfig;
+
+    #[test]
+    fn looks_up_themes_by_name() {
+        for name in Theme::NAMES {
+            assert!(Theme::named(name).is_some(), "{}", name);
+        }
+        assert_eq!(Theme::named("High_Contrast"), Some(Theme::high_contrast()));
+        assert_eq!(Theme::named("16"), Some(Theme::ansi16()));
+        assert_eq!(Theme::named("neon"), None);
+    }
+
+    #[test]
+    fn falls_back_to_the_default_theme() {
+        assert_eq!(Theme::default(), Theme::dark());
+        assert_eq!(Theme::named_or_default("neon"), Theme::dark());
+        assert_eq!(Theme::named_or_default(""), Theme::dark());
+        assert_eq!(Theme::named_or_default("light"), Theme::light());
+    }
+
+    #[test]
+    fn loads_the_theme_and_colors_from_the_config() {
+        let config = TuiConfig::parse("theme = \"light\"\ncolors = \"256\"\n").unwrap();
+        assert_eq!(config.theme, Theme::light());
+        assert_eq!(config.colors, Some(ColorSupport::Ansi256));
+
+        let config = TuiConfig::parse("").unwrap();
+        assert_eq!(config.theme, Theme::dark());
+        assert_eq!(config.colors, None);
+
+        let error = TuiConfig::parse("theme = \"neon\"").unwrap_err();
+        assert!(error.contains("unknown theme 'neon'"), "{}", error);
+        assert!(TuiConfig::parse("colors = \"8\"").is_err());
+    }
+
+    #[test]
+    fn parses_color_support() {
+        for (value, support) in [
+            ("truecolor", ColorSupport::TrueColor),
+            ("24BIT", ColorSupport::TrueColor),
+            ("256", ColorSupport::Ansi256),
+            ("16", ColorSupport::Ansi16),
+        ] {
+            assert_eq!(value.parse::<ColorSupport>(), Ok(support), "{}", value);
+        }
+        assert!("88".parse::<ColorSupport>().is_err());
+    }
+
+    #[test]
+    fn fits_colors_to_the_terminal() {
+        let red = Color::Rgb(255, 0, 0);
+        assert_eq!(fit_color(red, ColorSupport::TrueColor), red);
+        assert_eq!(fit_color(red, ColorSupport::Ansi256), Color::Indexed(196));
+        assert_eq!(fit_color(red, ColorSupport::Ansi16), Color::LightRed);
+        // Grays go to the gray ramp and the gray standard colors
+        let gray = Color::Rgb(128, 128, 128);
+        assert_eq!(fit_color(gray, ColorSupport::Ansi256), Color::Indexed(244));
+        assert_eq!(fit_color(gray, ColorSupport::Ansi16), Color::DarkGray);
+        // Named colors are left to the terminal
+        assert_eq!(fit_color(Color::Cyan, ColorSupport::Ansi16), Color::Cyan);
+        // Indexes past the first 16 are fitted like RGB colors
+        assert_eq!(
+            fit_color(Color::Indexed(19), ColorSupport::Ansi16),
+            Color::Blue
+        );
+
+        let themed = Theme::dark().for_terminal(ColorSupport::Ansi16);
+        assert!(!matches!(themed.error, Color::Rgb(..)));
+        assert_eq!(
+            Theme::ansi16().for_terminal(ColorSupport::Ansi16),
+            Theme::ansi16()
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! API token commands
+//!
+//! `swoop tokens` manages the tokens a daemon started with `--auth`,
+//! `--tenants` or `--oidc-issuer` accepts, kept in `<state-dir>/tokens.json`:
+//! `issue` creates one for a tenant and role and prints its secret once,
+//! `rotate` swaps a token's secret, `revoke` drops it and `list` shows them.
+//! The daemon picks changes up at once. `audit` shows who queued which job,
+//! who paused or resumed the engine, which requests were refused and every
+//! token change.
+
+use chrono::{DateTime, Utc};
+use clap::{builder::PossibleValuesParser, Arg, ArgMatches, Command};
+use std::path::Path;
+use storage::{
+    audit::{AuditAction, AuditEvent, AuditLog},
+    freshness::parse_interval,
+    tokens::{ApiToken, Role, TokenStore},
+};
+
+use crate::tenants::DEFAULT_TENANT;
+
+/// `swoop tokens` command definition
+pub fn command() -> Command {
+    Command::new("tokens")
+        .about("Issue, rotate and revoke API tokens of the daemon")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("issue")
+                .about("Create a token and print its secret once")
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Who or what the token is for, as recorded in the audit log")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("role")
+                        .long("role")
+                        .value_name("ROLE")
+                        .help("submit: queue jobs only; read: status, logs and results; admin: everything")
+                        .value_parser(PossibleValuesParser::new(Role::ALL.iter().map(Role::as_str)))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .help("Tenant the token acts for")
+                        .default_value(DEFAULT_TENANT),
+                )
+                .arg(
+                    Arg::new("expires-in")
+                        .long("expires-in")
+                        .value_name("INTERVAL")
+                        .help("Stop accepting the token after this long, e.g. 12h or 90d"),
+                ),
+        )
+        .subcommand(Command::new("list").about("List the tokens, without their secrets"))
+        .subcommand(
+            Command::new("rotate")
+                .about("Give a token a new secret")
+                .arg(Arg::new("id").value_name("ID").required(true))
+                .arg(
+                    Arg::new("grace")
+                        .long("grace")
+                        .value_name("INTERVAL")
+                        .help("Keep accepting the old secret this long, e.g. 1h"),
+                ),
+        )
+        .subcommand(
+            Command::new("revoke")
+                .about("Stop accepting a token")
+                .arg(Arg::new("id").value_name("ID").required(true)),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Show who did what on the daemon")
+                .arg(
+                    Arg::new("tenant")
+                        .long("tenant")
+                        .value_name("TENANT")
+                        .help("Only show events of this tenant"),
+                )
+                .arg(
+                    Arg::new("actor")
+                        .long("actor")
+                        .value_name("TEXT")
+                        .help("Only show events of actors containing TEXT"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .help("Latest events to show")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("50"),
+                ),
+        )
+}
+
+/// Dispatch a `swoop tokens` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let root = Path::new(state_dir);
+    let store = TokenStore::open(root);
+    let audit = AuditLog::open(root);
+    match matches.subcommand() {
+        Some(("issue", sub)) => {
+            let role = Role::named(sub.get_one::<String>("role").unwrap()).unwrap();
+            let expires_at = sub
+                .get_one::<String>("expires-in")
+                .map(|raw| interval(raw).map(|interval| Utc::now() + interval))
+                .transpose()?;
+            let (token, secret) = store
+                .issue(
+                    sub.get_one::<String>("name").unwrap(),
+                    sub.get_one::<String>("tenant").unwrap(),
+                    role,
+                    expires_at,
+                )
+                .map_err(|e| format!("{:#}", e))?;
+            record(&audit, &token, AuditAction::TokenIssued);
+            println!(
+                "🔑 Token {} issued to {} ({} on tenant {})",
+                token.id,
+                token.name,
+                token.role.as_str(),
+                token.tenant
+            );
+            print_secret(&secret);
+        }
+        Some(("list", _)) => {
+            let tokens = store.load().map_err(|e| format!("{:#}", e))?;
+            print_tokens(&tokens);
+        }
+        Some(("rotate", sub)) => {
+            let id = sub.get_one::<String>("id").unwrap();
+            let grace = sub
+                .get_one::<String>("grace")
+                .map(|raw| interval(raw))
+                .transpose()?
+                .unwrap_or_else(chrono::Duration::zero);
+            let (token, secret) = store
+                .rotate(id, grace)
+                .map_err(|e| format!("{:#}", e))?
+                .ok_or_else(|| format!("No token {}", id))?;
+            record(&audit, &token, AuditAction::TokenRotated);
+            println!("🔄 Token {} of {} rotated", token.id, token.name);
+            if let Some(until) = token.previous_expires_at {
+                println!("   The old secret works until {}", format_time(until));
+            }
+            print_secret(&secret);
+        }
+        Some(("revoke", sub)) => {
+            let id = sub.get_one::<String>("id").unwrap();
+            let token = store
+                .revoke(id)
+                .map_err(|e| format!("{:#}", e))?
+                .ok_or_else(|| format!("No token {}", id))?;
+            record(&audit, &token, AuditAction::TokenRevoked);
+            println!("🗑️  Token {} of {} revoked", token.id, token.name);
+        }
+        Some(("audit", sub)) => {
+            let tenant = sub.get_one::<String>("tenant");
+            let actor = sub.get_one::<String>("actor");
+            let events: Vec<AuditEvent> = audit
+                .load()
+                .map_err(|e| format!("{:#}", e))?
+                .into_iter()
+                .filter(|event| tenant.map_or(true, |tenant| event.tenant == *tenant))
+                .filter(|event| actor.map_or(true, |actor| event.actor.contains(actor.as_str())))
+                .collect();
+            let limit = *sub.get_one::<usize>("limit").unwrap();
+            print_audit(&events[events.len().saturating_sub(limit)..], audit.path());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interval(raw: &str) -> Result<chrono::Duration, String> {
+    let interval = parse_interval(raw).map_err(|e| e.to_string())?;
+    chrono::Duration::from_std(interval).map_err(|e| e.to_string())
+}
+
+/// Audit a token change, made by whoever runs the command
+fn record(audit: &AuditLog, token: &ApiToken, action: AuditAction) {
+    let user = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+    let event =
+        AuditEvent::new(&format!("cli:{}", user), &token.tenant, action).with_detail(format!(
+            "token {} ({}, {})",
+            token.id,
+            token.name,
+            token.role.as_str()
+        ));
+    if let Err(e) = audit.append(&event) {
+        eprintln!("⚠️  Failed to write the audit log: {:#}", e);
+    }
+}
+
+fn print_secret(secret: &str) {
+    println!("   {}", secret);
+    println!("   Shown only this once; pass it as --api-key or $SWOOP_API_KEY");
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn print_tokens(tokens: &[ApiToken]) {
+    if tokens.is_empty() {
+        println!("📭 No tokens issued; create one with `swoop tokens issue`");
+        return;
+    }
+    let now = Utc::now();
+    println!(
+        "{:<10} {:<20} {:<12} {:<7} {:<17} EXPIRES",
+        "ID", "NAME", "TENANT", "ROLE", "CREATED"
+    );
+    for token in tokens {
+        let expires = match token.expires_at {
+            Some(at) if token.is_expired(now) => format!("{} (expired)", format_time(at)),
+            Some(at) => format_time(at),
+            None => "never".to_string(),
+        };
+        println!(
+            "{:<10} {:<20} {:<12} {:<7} {:<17} {}",
+            token.id,
+            token.name,
+            token.tenant,
+            token.role.as_str(),
+            format_time(token.created_at),
+            expires
+        );
+    }
+}
+
+fn print_audit(events: &[AuditEvent], path: &Path) {
+    if events.is_empty() {
+        println!("📭 No audit events in {}", path.display());
+        return;
+    }
+    for event in events {
+        let subject = match (&event.job_id, &event.job_name, event.urls) {
+            (Some(id), Some(name), Some(urls)) => {
+                format!("job {} '{}' with {} URLs", id, name, urls)
+            }
+            _ => event.detail.clone().unwrap_or_default(),
+        };
+        println!(
+            "{}  {:<13} {:<12} {:<30} {}",
+            event.at.format("%Y-%m-%d %H:%M:%S"),
+            event.action.as_str(),
+            event.tenant,
+            event.actor,
+            subject
+        );
+    }
+}
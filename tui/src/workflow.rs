@@ -0,0 +1,140 @@
+//! Workflow commands
+//!
+//! `swoop workflow run` runs a multi-step YAML workflow (see
+//! [`swoop_orchestrator::workflow`]) with the local store as its storage
+//! backend and quarantine, and reports which fetch tier served each host;
+//! with `--skip-unchanged`, it keeps body fingerprints there too, so pages
+//! unchanged since the last run aren't passed on again. `check` only
+//! validates the file.
+
+use clap::{Arg, ArgMatches, Command};
+use std::{path::Path, sync::Arc};
+
+use storage::local_store::LocalStore;
+use swoop_orchestrator::{workflow::Workflow, Swoop};
+
+/// `swoop workflow` command definition
+pub fn command() -> Command {
+    let file = Arg::new("file")
+        .required(true)
+        .value_name("FILE")
+        .help("Workflow file (YAML)");
+    Command::new("workflow")
+        .about("Run multi-step scraping workflows declared in YAML")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("run")
+                .about("Run the steps of a workflow in dependency order")
+                .arg(file.clone())
+                .arg(
+                    Arg::new("policies")
+                        .long("policies")
+                        .value_name("FILE")
+                        .help("Per-domain policy file (default: ./policies.toml if present)"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .long("concurrency")
+                        .short('c')
+                        .value_name("NUM")
+                        .help("Pages crawled at once")
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::new("skip-unchanged")
+                        .long("skip-unchanged")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Don't pass on pages whose body is the same as at their last fetch"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check that a workflow's steps fit together without running it")
+                .arg(file),
+        )
+}
+
+/// Dispatch a `swoop workflow` subcommand
+pub async fn run(matches: &ArgMatches, state_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("run", sub)) => {
+            let workflow = Workflow::from_file(sub.get_one::<String>("file").unwrap())?;
+            let policies =
+                crate::cli::load_policies(sub.get_one::<String>("policies").map(Path::new))
+                    .map_err(|e| e.to_string())?;
+            let store = Arc::new(LocalStore::new(state_dir).await?);
+            let mut builder = Swoop::builder()
+                .with_storage(store.clone())
+                .with_quarantine(store.clone())
+                .with_concurrency(sub.get_one::<String>("concurrency").unwrap().parse()?);
+            if let Some(policies) = policies {
+                builder = builder.with_policies(policies);
+            }
+            if sub.get_flag("skip-unchanged") {
+                builder = builder.with_fingerprints(store.clone());
+            }
+
+            println!(
+                "🧩 Running workflow {}",
+                workflow.name.as_deref().unwrap_or("(unnamed)")
+            );
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            let swoop = builder.build();
+            let report = swoop.run_workflow(&workflow).await?;
+            for step in &report.steps {
+                println!(
+                    "  {:<20} {:<9} {:>6} passed on, {} failed",
+                    step.id,
+                    step.action,
+                    step.output,
+                    step.failures.len()
+                );
+                for failure in &step.failures {
+                    println!("      ❌ {}", failure);
+                }
+            }
+            println!(
+                "\n✅ {} step(s) done, {} failure(s)",
+                report.steps.len(),
+                report.failures()
+            );
+            let tier_stats = swoop.tier_stats();
+            if !tier_stats.is_empty() {
+                println!("\n📶 Fetch tiers by host:");
+            }
+            for (host, tiers) in tier_stats {
+                let tiers: Vec<String> = tiers
+                    .iter()
+                    .map(|(tier, stats)| {
+                        format!(
+                            "{} {} served, {} escalated, {} failed, {} over budget",
+                            tier.as_str(),
+                            stats.served,
+                            stats.escalated,
+                            stats.failed,
+                            stats.over_budget
+                        )
+                    })
+                    .collect();
+                println!("  {:<30} {}", host, tiers.join(" | "));
+            }
+        }
+        Some(("check", sub)) => {
+            let workflow = Workflow::from_file(sub.get_one::<String>("file").unwrap())?;
+            println!("✅ {} step(s), ready to run:", workflow.steps.len());
+            for step in &workflow.steps {
+                match step.needs.as_slice() {
+                    [] => println!("  {} ({})", step.id, step.action.as_str()),
+                    needs => println!(
+                        "  {} ({}) after {}",
+                        step.id,
+                        step.action.as_str(),
+                        needs.join(", ")
+                    ),
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}